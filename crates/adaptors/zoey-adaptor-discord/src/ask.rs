@@ -0,0 +1,157 @@
+//! `/ask`: chatting without the message content intent
+//!
+//! Servers that deny the bot the `MESSAGE_CONTENT` intent deliver messages
+//! with empty content, so the bot cannot hear anything said in the channel.
+//! `/ask question:<text>` carries the question in the interaction instead.
+//! The handler defers the response, sends the question to `/chat/stream` in
+//! the channel's room (the same room text chat uses) and edits the deferred
+//! response with the reply, continuing in follow-ups past Discord's
+//! [`INTERACTION_MESSAGE_LIMIT`].
+//!
+//! With `ephemeral:true` only the asker sees the question being answered.
+//! Invocations go through the adapter's rate limiter under their own key
+//! ([`rate_limit_key`]); over the limit the user is told to slow down,
+//! ephemerally, rather than getting no answer.
+
+use serenity::all::{CommandDataOption, CommandDataOptionValue};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::CommandOptionType;
+
+/// Name of the slash command
+pub const ASK_COMMAND: &str = "ask";
+
+/// Most characters in one interaction response or follow-up
+pub const INTERACTION_MESSAGE_LIMIT: usize = 2000;
+
+/// Answer to invocations over the rate limit
+pub const SLOW_DOWN_REPLY: &str =
+    "You're asking faster than I can answer. Slow down and try again in a minute.";
+
+/// Answer when the backend failed or timed out
+pub const FAILED_REPLY: &str = "I couldn't answer that right now. Please try again.";
+
+/// Answer when the channel's filters exclude the bot or the user
+pub const FILTERED_REPLY: &str = "I'm not answering here.";
+
+/// Answer when the backend replied with nothing
+pub const EMPTY_REPLY: &str = "I don't have an answer for that.";
+
+/// Reply for an invocation without a question
+const USAGE: &str = "Use `/ask question:<your question>`.";
+
+/// `/ask` slash command definition
+pub fn ask_command() -> CreateCommand {
+    CreateCommand::new(ASK_COMMAND)
+        .description("Ask me something without mentioning me")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "question", "What to ask")
+                .required(true)
+                .max_length(2000),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "ephemeral",
+            "Only you see the answer",
+        ))
+}
+
+/// Parsed `/ask` invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AskCommand {
+    pub question: String,
+    /// Answer so only the asker can see it
+    pub ephemeral: bool,
+}
+
+impl AskCommand {
+    /// Parse the options of an `/ask` interaction
+    pub fn from_options(options: &[CommandDataOption]) -> std::result::Result<Self, String> {
+        let mut question = None;
+        let mut ephemeral = false;
+        for option in options {
+            match (option.name.as_str(), &option.value) {
+                ("question", CommandDataOptionValue::String(text)) => question = Some(text.trim()),
+                ("ephemeral", CommandDataOptionValue::Boolean(value)) => ephemeral = *value,
+                _ => {}
+            }
+        }
+        match question.filter(|q| !q.is_empty()) {
+            Some(question) => Ok(Self {
+                question: question.to_string(),
+                ephemeral,
+            }),
+            None => Err(USAGE.to_string()),
+        }
+    }
+}
+
+/// Rate limiter key of an `/ask` invocation, separate from the user's messages in the channel
+pub fn rate_limit_key(guild_id: u64, channel_id: u64, user_id: u64) -> String {
+    format!("{}:{}:{}:{}", ASK_COMMAND, guild_id, channel_id, user_id)
+}
+
+/// Split `text` into messages of at most `limit` characters
+///
+/// Breaks at the last newline, else the last whitespace, within the limit;
+/// a run without either is cut at the limit.
+pub fn split_reply(text: &str, limit: usize) -> Vec<String> {
+    let limit = limit.max(1);
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+    while rest.chars().count() > limit {
+        let window_end = rest
+            .char_indices()
+            .nth(limit)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let window = &rest[..window_end];
+        let cut = window
+            .rfind('\n')
+            .or_else(|| window.rfind(char::is_whitespace))
+            .filter(|&i| i > 0)
+            .unwrap_or(window_end);
+        parts.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_reply_prefers_line_then_word_breaks() {
+        assert_eq!(split_reply("  short answer \n", 2000), vec!["short answer"]);
+        assert!(split_reply("   ", 2000).is_empty());
+
+        let text = "First line here\nsecond line is longer";
+        assert_eq!(
+            split_reply(text, 20),
+            vec!["First line here", "second line is", "longer"]
+        );
+
+        let parts = split_reply(&"é".repeat(45), 20);
+        assert_eq!(
+            parts.iter().map(|p| p.chars().count()).collect::<Vec<_>>(),
+            vec![20, 20, 5]
+        );
+
+        let long = "word ".repeat(900);
+        let parts = split_reply(&long, INTERACTION_MESSAGE_LIMIT);
+        assert_eq!(parts.len(), 3);
+        assert!(parts
+            .iter()
+            .all(|p| p.chars().count() <= INTERACTION_MESSAGE_LIMIT));
+        assert_eq!(parts.join(" "), long.trim());
+    }
+
+    #[test]
+    fn test_rate_limit_key_is_separate_from_messages() {
+        assert_eq!(rate_limit_key(1, 2, 3), "ask:1:2:3");
+        assert_ne!(rate_limit_key(1, 2, 3), format!("{}:{}:{}", 1, 2, 3));
+    }
+}
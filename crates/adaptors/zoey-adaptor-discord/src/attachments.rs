@@ -0,0 +1,366 @@
+//! Image attachments forwarded to the agent
+//!
+//! A screenshot posted with "what's wrong with this error?" means nothing
+//! to the model as text alone, so image attachments (by content type, or by
+//! extension when Discord sent none) go to `/chat/stream` in the body's
+//! `images` array: downloaded and base64-encoded, or as their CDN URLs when
+//! [`AttachmentConfig::prefer_urls`] is set. An image that fails to download
+//! is sent by URL instead. The memory's content records every attachment as
+//! [`Media`]; other files are listed by filename in the text so the model at
+//! least knows they exist ([`MessageAttachments::annotate`]).
+//!
+//! More than [`MAX_IMAGES_PER_MESSAGE`] images, or one over
+//! [`AttachmentConfig::max_image_bytes`], gets a polite reply asking the
+//! user to resend rather than an answer that silently ignores some of them.
+
+use base64::Engine;
+use serde::Serialize;
+use serenity::model::channel::Attachment;
+use std::time::Duration;
+use zoey_core::{ContentType, Media, Result, ZoeyError};
+
+/// Default largest image forwarded to the agent
+pub const DEFAULT_MAX_IMAGE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Most images the agent is shown from one message
+pub const MAX_IMAGES_PER_MESSAGE: usize = 4;
+
+/// Timeout for downloading a single image
+const IMAGE_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Extensions treated as images when Discord sent no content type
+const IMAGE_EXTENSIONS: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+/// How image attachments are forwarded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentConfig {
+    /// Largest image forwarded; bigger ones are refused with a reply
+    pub max_image_bytes: u64,
+    /// Send CDN URLs instead of downloading and base64-encoding
+    pub prefer_urls: bool,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            prefer_urls: false,
+        }
+    }
+}
+
+/// What the handler keeps of one attachment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentInfo {
+    pub filename: String,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub size: u64,
+}
+
+impl From<&Attachment> for AttachmentInfo {
+    fn from(attachment: &Attachment) -> Self {
+        Self {
+            filename: attachment.filename.clone(),
+            url: attachment.url.clone(),
+            content_type: attachment.content_type.clone(),
+            size: attachment.size.into(),
+        }
+    }
+}
+
+impl AttachmentInfo {
+    /// MIME type without parameters, guessed from the extension if missing
+    pub fn mime_type(&self) -> Option<String> {
+        if let Some(content_type) = &self.content_type {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            return Some(mime.to_lowercase());
+        }
+        let extension = self.filename.rsplit_once('.')?.1.to_lowercase();
+        IMAGE_EXTENSIONS
+            .iter()
+            .find(|(ext, _)| *ext == extension)
+            .map(|(_, mime)| mime.to_string())
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type()
+            .is_some_and(|mime| mime.starts_with("image/"))
+    }
+
+    /// The attachment as media on the memory's content
+    pub fn media(&self) -> Media {
+        let mime = self.mime_type().unwrap_or_default();
+        let content_type = match mime.split('/').next().unwrap_or_default() {
+            "image" => ContentType::Image,
+            "audio" => ContentType::Audio,
+            "video" => ContentType::Video,
+            "text" => ContentType::Text,
+            "application" => ContentType::Document,
+            _ => ContentType::Unknown,
+        };
+        Media {
+            url: self.url.clone(),
+            content_type,
+            title: Some(self.filename.clone()),
+            description: None,
+            text: None,
+        }
+    }
+}
+
+/// Image in the `images` array of a chat request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatImage {
+    pub filename: String,
+    pub mime_type: String,
+    /// Base64 of the image bytes, when downloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// CDN URL, when not downloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl ChatImage {
+    fn by_url(info: &AttachmentInfo, mime_type: String) -> Self {
+        Self {
+            filename: info.filename.clone(),
+            mime_type,
+            data: None,
+            url: Some(info.url.clone()),
+        }
+    }
+}
+
+/// A message's attachments, split into images and other files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageAttachments {
+    pub images: Vec<AttachmentInfo>,
+    pub files: Vec<AttachmentInfo>,
+}
+
+impl MessageAttachments {
+    /// Split `attachments`, or the reply refusing them if the agent can't take the images
+    pub fn sort(
+        attachments: Vec<AttachmentInfo>,
+        config: &AttachmentConfig,
+    ) -> std::result::Result<Self, String> {
+        let (images, files): (Vec<_>, Vec<_>) =
+            attachments.into_iter().partition(AttachmentInfo::is_image);
+        if images.len() > MAX_IMAGES_PER_MESSAGE {
+            return Err(format!(
+                "I can look at up to {} images per message and you sent {}. \
+                 Could you send them in smaller batches?",
+                MAX_IMAGES_PER_MESSAGE,
+                images.len()
+            ));
+        }
+        let oversized: Vec<String> = images
+            .iter()
+            .filter(|image| image.size > config.max_image_bytes)
+            .map(|image| format!("`{}`", image.filename))
+            .collect();
+        if !oversized.is_empty() {
+            return Err(format!(
+                "{} {} too large for me to look at (the limit is {}). \
+                 Could you send a smaller or cropped version?",
+                oversized.join(", "),
+                if oversized.len() == 1 { "is" } else { "are" },
+                megabytes(config.max_image_bytes)
+            ));
+        }
+        Ok(Self { images, files })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty() && self.files.is_empty()
+    }
+
+    /// Message text with the non-image files listed by filename
+    pub fn annotate(&self, text: &str) -> String {
+        if self.files.is_empty() {
+            return text.to_string();
+        }
+        let names: Vec<&str> = self.files.iter().map(|f| f.filename.as_str()).collect();
+        let listing = format!("[Attached files: {}]", names.join(", "));
+        if text.trim().is_empty() {
+            listing
+        } else {
+            format!("{}\n\n{}", text.trim_end(), listing)
+        }
+    }
+
+    /// Every attachment as media, images first
+    pub fn media(&self) -> Vec<Media> {
+        self.images
+            .iter()
+            .chain(&self.files)
+            .map(AttachmentInfo::media)
+            .collect()
+    }
+
+    /// The images for the chat request, downloaded unless `prefer_urls` is set
+    pub async fn chat_images(
+        &self,
+        client: &reqwest::Client,
+        config: &AttachmentConfig,
+    ) -> Vec<ChatImage> {
+        let mut images = Vec::with_capacity(self.images.len());
+        for info in &self.images {
+            let mime_type = info.mime_type().unwrap_or_default();
+            if config.prefer_urls {
+                images.push(ChatImage::by_url(info, mime_type));
+                continue;
+            }
+            match download(client, &info.url, config.max_image_bytes).await {
+                Ok(bytes) => images.push(ChatImage {
+                    filename: info.filename.clone(),
+                    mime_type,
+                    data: Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+                    url: None,
+                }),
+                Err(e) => {
+                    tracing::warn!(file = %info.filename, error = %e, "Image download failed, sending its URL");
+                    images.push(ChatImage::by_url(info, mime_type));
+                }
+            }
+        }
+        images
+    }
+}
+
+/// Body of `url`, refused past `max_bytes`
+async fn download(client: &reqwest::Client, url: &str, max_bytes: u64) -> Result<Vec<u8>> {
+    let fetch = async {
+        let mut resp = client.get(url).send().await?.error_for_status()?;
+        if resp.content_length().is_some_and(|len| len > max_bytes) {
+            return Err(ZoeyError::validation("Image is too large"));
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > max_bytes {
+                return Err(ZoeyError::validation("Image is too large"));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    };
+    tokio::time::timeout(IMAGE_FETCH_TIMEOUT, fetch)
+        .await
+        .map_err(|_| ZoeyError::validation("Image download timed out"))?
+}
+
+/// `bytes` as megabytes for a reply, e.g. "8 MB" or "2.5 MB"
+fn megabytes(bytes: u64) -> String {
+    let mb = format!("{:.1}", bytes as f64 / (1024.0 * 1024.0));
+    format!("{} MB", mb.trim_end_matches(".0"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: &str, content_type: Option<&str>, size: u64) -> AttachmentInfo {
+        AttachmentInfo {
+            filename: filename.to_string(),
+            url: format!("https://cdn.discordapp.com/attachments/1/2/{}", filename),
+            content_type: content_type.map(str::to_string),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_sort_splits_images_from_files() {
+        let sorted = MessageAttachments::sort(
+            vec![
+                attachment("error.png", Some("image/png"), 1000),
+                attachment("log.txt", Some("text/plain; charset=utf-8"), 200),
+                attachment("photo.JPG", None, 5000),
+                attachment("notes", None, 10),
+            ],
+            &AttachmentConfig::default(),
+        )
+        .unwrap();
+        let names =
+            |list: &[AttachmentInfo]| list.iter().map(|a| a.filename.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&sorted.images), vec!["error.png", "photo.JPG"]);
+        assert_eq!(names(&sorted.files), vec!["log.txt", "notes"]);
+        assert_eq!(sorted.images[1].mime_type().as_deref(), Some("image/jpeg"));
+
+        let media = sorted.media();
+        assert_eq!(media.len(), 4);
+        assert_eq!(media[0].content_type, ContentType::Image);
+        assert_eq!(media[2].content_type, ContentType::Text);
+        assert_eq!(media[3].content_type, ContentType::Unknown);
+        assert_eq!(media[2].title.as_deref(), Some("log.txt"));
+
+        assert_eq!(
+            sorted.annotate("what's wrong here?"),
+            "what's wrong here?\n\n[Attached files: log.txt, notes]"
+        );
+        assert_eq!(sorted.annotate("  "), "[Attached files: log.txt, notes]");
+        assert_eq!(MessageAttachments::default().annotate("hi"), "hi");
+    }
+
+    #[test]
+    fn test_sort_refuses_too_many_or_oversized_images() {
+        let config = AttachmentConfig {
+            max_image_bytes: 2 * 1024 * 1024,
+            prefer_urls: false,
+        };
+        let five = (0..5)
+            .map(|i| attachment(&format!("{}.png", i), Some("image/png"), 10))
+            .collect();
+        let reply = MessageAttachments::sort(five, &config).unwrap_err();
+        assert!(reply.contains("up to 4 images"), "{}", reply);
+
+        // Files other than images don't count towards the limit or the size cap
+        let mut four: Vec<_> = (0..4)
+            .map(|i| attachment(&format!("{}.png", i), Some("image/png"), 10))
+            .collect();
+        four.push(attachment("dump.zip", Some("application/zip"), u64::MAX));
+        assert!(MessageAttachments::sort(four, &config).is_ok());
+
+        let big = vec![
+            attachment("small.png", Some("image/png"), 1024),
+            attachment("huge.png", Some("image/png"), 3 * 1024 * 1024),
+        ];
+        assert_eq!(
+            MessageAttachments::sort(big, &config).unwrap_err(),
+            "`huge.png` is too large for me to look at (the limit is 2 MB). \
+             Could you send a smaller or cropped version?"
+        );
+        assert_eq!(megabytes(DEFAULT_MAX_IMAGE_BYTES), "8 MB");
+        assert_eq!(megabytes(512 * 1024), "0.5 MB");
+    }
+
+    #[tokio::test]
+    async fn test_prefer_urls_skips_downloads() {
+        let sorted = MessageAttachments::sort(
+            vec![attachment("error.png", Some("image/png"), 1000)],
+            &AttachmentConfig::default(),
+        )
+        .unwrap();
+        let config = AttachmentConfig {
+            prefer_urls: true,
+            ..Default::default()
+        };
+        let images = sorted.chat_images(&reqwest::Client::new(), &config).await;
+        assert_eq!(
+            serde_json::to_value(&images).unwrap(),
+            serde_json::json!([{
+                "filename": "error.png",
+                "mimeType": "image/png",
+                "url": "https://cdn.discordapp.com/attachments/1/2/error.png",
+            }])
+        );
+    }
+}
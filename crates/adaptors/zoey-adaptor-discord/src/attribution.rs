@@ -0,0 +1,271 @@
+//! Spoken attribution of voice answers
+//!
+//! When several people talk to the bot in one voice channel, answers queued
+//! behind each other give no hint of whose question they address. Each answer
+//! is therefore preceded by a short clip ("Answering Marcus:") as long as
+//! more than one distinct asker is waiting in the guild's playback queue or
+//! was answered within the last [`DEFAULT_ATTRIBUTION_WINDOW`]
+//! ([`AskerTracker`]). A single person talking to the bot hears no
+//! attribution.
+//!
+//! Display names are turned into something a TTS engine can pronounce
+//! ([`speakable_name`]) and the phrase's audio is cached by its text
+//! ([`ClipCache`]), so a regular's name is synthesized once.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long an answered asker still counts towards a multi-asker channel
+pub const DEFAULT_ATTRIBUTION_WINDOW: Duration = Duration::from_secs(120);
+
+/// Longest name spoken in an attribution
+pub const MAX_SPOKEN_NAME_CHARS: usize = 24;
+
+/// Most synthesized clips kept for reuse
+pub const MAX_CACHED_CLIPS: usize = 256;
+
+/// Display name cleaned up for speech, or `None` if nothing pronounceable is left
+///
+/// Bracketed clan tags and decorations are dropped, as are emoji and other
+/// symbols; underscores separate words. Names longer than
+/// [`MAX_SPOKEN_NAME_CHARS`] keep their leading whole words.
+pub fn speakable_name(display_name: &str) -> Option<String> {
+    let mut cleaned = String::new();
+    let mut depth = 0usize;
+    for c in display_name.chars() {
+        match c {
+            '[' | '(' | '{' | '<' => depth += 1,
+            ']' | ')' | '}' | '>' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            '_' => cleaned.push(' '),
+            c if c.is_alphanumeric() || c.is_whitespace() || matches!(c, '\'' | '-' | '.') => {
+                cleaned.push(c)
+            }
+            _ => cleaned.push(' '),
+        }
+    }
+    let words: Vec<&str> = cleaned
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .collect();
+    let first = words.first()?;
+    if first.chars().count() > MAX_SPOKEN_NAME_CHARS {
+        return Some(first.chars().take(MAX_SPOKEN_NAME_CHARS).collect());
+    }
+    let mut name = first.to_string();
+    for word in &words[1..] {
+        if name.chars().count() + 1 + word.chars().count() > MAX_SPOKEN_NAME_CHARS {
+            break;
+        }
+        name.push(' ');
+        name.push_str(word);
+    }
+    Some(name)
+}
+
+/// Phrase spoken before an answer to `name`
+pub fn attribution_phrase(name: &str) -> String {
+    format!("Answering {}:", name)
+}
+
+#[derive(Default)]
+struct GuildAskers {
+    /// Askers with an answer waiting to be spoken, one entry per answer
+    queued: Vec<u64>,
+    /// Askers answered recently, oldest first
+    served: VecDeque<(u64, Instant)>,
+}
+
+/// Who is waiting for, or recently got, a spoken answer in each guild
+pub struct AskerTracker {
+    window: Duration,
+    guilds: Mutex<HashMap<u64, GuildAskers>>,
+}
+
+impl Default for AskerTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_ATTRIBUTION_WINDOW)
+    }
+}
+
+impl AskerTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            guilds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// An answer for `user_id` joined the guild's playback queue
+    pub fn enqueue(&self, guild_id: u64, user_id: u64) {
+        let mut guilds = self.guilds.lock().unwrap();
+        guilds.entry(guild_id).or_default().queued.push(user_id);
+    }
+
+    /// Whether the answer for `user_id` should name them
+    ///
+    /// True when someone else is queued or was answered within the window.
+    pub fn should_attribute(&self, guild_id: u64, user_id: u64, now: Instant) -> bool {
+        let guilds = self.guilds.lock().unwrap();
+        let Some(askers) = guilds.get(&guild_id) else {
+            return false;
+        };
+        let recent = askers
+            .served
+            .iter()
+            .filter(|(_, at)| now.saturating_duration_since(*at) < self.window)
+            .map(|(id, _)| id);
+        askers.queued.iter().chain(recent).any(|&id| id != user_id)
+    }
+
+    /// The answer for `user_id` was spoken (or dropped)
+    pub fn finish(&self, guild_id: u64, user_id: u64, now: Instant) {
+        let mut guilds = self.guilds.lock().unwrap();
+        let askers = guilds.entry(guild_id).or_default();
+        if let Some(i) = askers.queued.iter().position(|&id| id == user_id) {
+            askers.queued.remove(i);
+        }
+        askers
+            .served
+            .retain(|(id, at)| *id != user_id && now.saturating_duration_since(*at) < self.window);
+        askers.served.push_back((user_id, now));
+    }
+}
+
+/// Synthesized clips by the text they speak
+///
+/// Keyed by the exact phrase, so differently decorated nicknames that clean
+/// up to the same name share a clip. Once full, new phrases are synthesized
+/// every time rather than evicting the common ones.
+pub struct ClipCache<T> {
+    clips: Mutex<HashMap<String, T>>,
+    max: usize,
+}
+
+impl<T: Clone> Default for ClipCache<T> {
+    fn default() -> Self {
+        Self::new(MAX_CACHED_CLIPS)
+    }
+}
+
+impl<T: Clone> ClipCache<T> {
+    pub fn new(max: usize) -> Self {
+        Self {
+            clips: Mutex::new(HashMap::new()),
+            max,
+        }
+    }
+
+    pub fn get(&self, text: &str) -> Option<T> {
+        self.clips.lock().unwrap().get(text).cloned()
+    }
+
+    /// Keep `clip` for `text`; returns false when the cache is full
+    pub fn insert(&self, text: String, clip: T) -> bool {
+        let mut clips = self.clips.lock().unwrap();
+        if clips.len() >= self.max && !clips.contains_key(&text) {
+            return false;
+        }
+        clips.insert(text, clip);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: u64 = 1;
+    const ALICE: u64 = 10;
+    const BOB: u64 = 20;
+
+    #[test]
+    fn test_single_asker_is_not_attributed() {
+        let askers = AskerTracker::new(Duration::from_secs(120));
+        let now = Instant::now();
+        assert!(!askers.should_attribute(GUILD, ALICE, now));
+
+        askers.enqueue(GUILD, ALICE);
+        assert!(!askers.should_attribute(GUILD, ALICE, now));
+        askers.finish(GUILD, ALICE, now);
+        // Two answers in a row for the same person
+        askers.enqueue(GUILD, ALICE);
+        askers.enqueue(GUILD, ALICE);
+        assert!(!askers.should_attribute(GUILD, ALICE, now));
+        // Other guilds do not count
+        askers.enqueue(2, BOB);
+        assert!(!askers.should_attribute(GUILD, ALICE, now));
+    }
+
+    #[test]
+    fn test_multiple_askers_within_window() {
+        let askers = AskerTracker::new(Duration::from_secs(120));
+        let start = Instant::now();
+
+        // Both waiting in the queue
+        askers.enqueue(GUILD, ALICE);
+        askers.enqueue(GUILD, BOB);
+        assert!(askers.should_attribute(GUILD, ALICE, start));
+        askers.finish(GUILD, ALICE, start);
+        // Alice was answered a moment ago
+        assert!(askers.should_attribute(GUILD, BOB, start + Duration::from_secs(5)));
+        askers.finish(GUILD, BOB, start + Duration::from_secs(5));
+
+        // Within the window Alice still hears her name, since Bob was answered
+        askers.enqueue(GUILD, ALICE);
+        assert!(askers.should_attribute(GUILD, ALICE, start + Duration::from_secs(60)));
+        // After it, only Alice remains
+        assert!(!askers.should_attribute(GUILD, ALICE, start + Duration::from_secs(200)));
+    }
+
+    #[test]
+    fn test_speakable_names() {
+        assert_eq!(speakable_name("Marcus").as_deref(), Some("Marcus"));
+        assert_eq!(speakable_name("[ABC] Marcus 🎮").as_deref(), Some("Marcus"));
+        assert_eq!(
+            speakable_name("xX_dark_lord_Xx").as_deref(),
+            Some("xX dark lord Xx")
+        );
+        assert_eq!(speakable_name("★ Zoë ★").as_deref(), Some("Zoë"));
+        assert_eq!(speakable_name("O'Brien (away)").as_deref(), Some("O'Brien"));
+        assert_eq!(
+            speakable_name("--Anne-Marie--").as_deref(),
+            Some("Anne-Marie")
+        );
+        assert_eq!(speakable_name("🔥🔥🔥"), None);
+        assert_eq!(speakable_name("  "), None);
+
+        // Long names keep whole leading words
+        assert_eq!(
+            speakable_name("Bartholomew the Magnificent of Nowhere").as_deref(),
+            Some("Bartholomew the")
+        );
+        let long = "a".repeat(40);
+        assert_eq!(
+            speakable_name(&long).unwrap().chars().count(),
+            MAX_SPOKEN_NAME_CHARS
+        );
+    }
+
+    #[test]
+    fn test_attribution_clips_reuse_cache_key() {
+        let cache: ClipCache<usize> = ClipCache::new(2);
+        let phrase = |raw: &str| attribution_phrase(&speakable_name(raw).unwrap());
+        assert_eq!(phrase("Marcus"), "Answering Marcus:");
+
+        assert!(cache.insert(phrase("Marcus"), 1));
+        // Decorations do not make a new clip
+        assert_eq!(cache.get(&phrase("[TAG] Marcus ✨")), Some(1));
+        assert_eq!(cache.get(&phrase("Marcus_")), Some(1));
+        assert_eq!(cache.get(&phrase("Marcy")), None);
+
+        // A full cache keeps what it has
+        assert!(cache.insert(phrase("Marcy"), 2));
+        assert!(!cache.insert(phrase("Bob"), 3));
+        assert_eq!(cache.get(&phrase("Bob")), None);
+        assert!(cache.insert(phrase("Marcus"), 4));
+        assert_eq!(cache.get(&phrase("Marcus")), Some(4));
+    }
+}
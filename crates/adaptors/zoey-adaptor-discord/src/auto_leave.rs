@@ -0,0 +1,180 @@
+//! Leaving voice channels nobody is using
+//!
+//! A voice session keeps the STT/TTS pipeline running, so the bot leaves
+//! once nothing has been transcribed or spoken for the idle timeout
+//! (`idle_timeout_seconds`, 10 minutes by default), or once it has been the
+//! only member of the channel for the alone timeout (`alone_timeout_seconds`,
+//! 30 seconds). Membership comes from the adapter's `voice_state_update`
+//! tracking ([`crate::VoiceStates`]).
+//!
+//! [`spawn_auto_leave`] checks the sessions every
+//! [`AUTO_LEAVE_CHECK_INTERVAL`], leaves, and says goodbye in the text
+//! channel the join was asked from.
+
+use std::time::Duration;
+
+use crate::voice::DiscordVoiceSettings;
+
+/// Default time without transcriptions or speech before leaving
+pub const DEFAULT_VOICE_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Default time the bot stays alone in a channel before leaving
+pub const DEFAULT_ALONE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often voice sessions are checked
+pub const AUTO_LEAVE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Why a voice channel was left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaveReason {
+    /// Nothing transcribed or spoken for the idle timeout
+    Idle,
+    /// Nobody else in the channel for the alone timeout
+    Alone,
+}
+
+impl LeaveReason {
+    /// Goodbye posted in the text channel the join was asked from
+    pub fn goodbye(&self) -> &'static str {
+        match self {
+            Self::Idle => {
+                "👋 It's been quiet a while, so I left voice - ask me to join again anytime!"
+            }
+            Self::Alone => "👋 Everyone left the voice channel, so I did too.",
+        }
+    }
+}
+
+/// When a voice session is left on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoLeave {
+    /// `None` stays however long the channel is quiet
+    idle_timeout: Option<Duration>,
+    /// `None` stays in an empty channel
+    alone_timeout: Option<Duration>,
+}
+
+impl Default for AutoLeave {
+    fn default() -> Self {
+        Self::new(
+            Some(DEFAULT_VOICE_IDLE_TIMEOUT),
+            Some(DEFAULT_ALONE_TIMEOUT),
+        )
+    }
+}
+
+impl AutoLeave {
+    pub fn new(idle_timeout: Option<Duration>, alone_timeout: Option<Duration>) -> Self {
+        Self {
+            idle_timeout,
+            alone_timeout,
+        }
+    }
+
+    /// Policy from the character's Discord voice settings
+    ///
+    /// An idle timeout of 0 never leaves a quiet channel; `leave_when_alone`
+    /// off never leaves an empty one.
+    pub fn from_settings(settings: &DiscordVoiceSettings) -> Self {
+        Self::new(
+            (settings.idle_timeout_seconds > 0)
+                .then(|| Duration::from_secs(settings.idle_timeout_seconds)),
+            settings
+                .leave_when_alone
+                .then(|| Duration::from_secs(settings.alone_timeout_seconds)),
+        )
+    }
+
+    /// Whether to leave a session quiet for `idle_for` and alone for `alone_for`
+    pub fn reason(&self, idle_for: Duration, alone_for: Option<Duration>) -> Option<LeaveReason> {
+        if let (Some(limit), Some(alone_for)) = (self.alone_timeout, alone_for) {
+            if alone_for >= limit {
+                return Some(LeaveReason::Alone);
+            }
+        }
+        match self.idle_timeout {
+            Some(limit) if idle_for >= limit => Some(LeaveReason::Idle),
+            _ => None,
+        }
+    }
+}
+
+/// A voice channel left by [`crate::VoiceManager::check_and_cleanup`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Departure {
+    pub guild_id: u64,
+    /// Text channel the join was asked from
+    pub text_channel_id: Option<u64>,
+    pub reason: LeaveReason,
+}
+
+/// Check the voice sessions every [`AUTO_LEAVE_CHECK_INTERVAL`], posting a
+/// goodbye for each channel left
+#[cfg(feature = "voice")]
+pub fn spawn_auto_leave(
+    voice_manager: std::sync::Arc<crate::VoiceManager>,
+    http: std::sync::Arc<serenity::http::Http>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(AUTO_LEAVE_CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            for departure in voice_manager.check_and_cleanup().await {
+                let Some(channel_id) = departure.text_channel_id else {
+                    continue;
+                };
+                let channel = serenity::model::id::ChannelId::new(channel_id);
+                if let Err(e) = channel.say(&http, departure.reason.goodbye()).await {
+                    tracing::warn!(
+                        guild_id = %departure.guild_id,
+                        channel_id = %channel_id,
+                        error = %e,
+                        "Failed to post voice goodbye"
+                    );
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_idle_and_alone_thresholds() {
+        let policy = AutoLeave::default();
+        assert_eq!(policy.reason(9 * 60 * SECOND, None), None);
+        assert_eq!(
+            policy.reason(10 * 60 * SECOND, None),
+            Some(LeaveReason::Idle)
+        );
+        assert_eq!(policy.reason(SECOND, Some(29 * SECOND)), None);
+        assert_eq!(
+            policy.reason(SECOND, Some(30 * SECOND)),
+            Some(LeaveReason::Alone)
+        );
+        // Alone wins when both apply
+        assert_eq!(
+            policy.reason(20 * 60 * SECOND, Some(40 * SECOND)),
+            Some(LeaveReason::Alone)
+        );
+    }
+
+    #[test]
+    fn test_policy_from_settings() {
+        let mut settings = DiscordVoiceSettings::default();
+        assert_eq!(AutoLeave::from_settings(&settings), AutoLeave::default());
+
+        settings.idle_timeout_seconds = 0;
+        settings.leave_when_alone = false;
+        let policy = AutoLeave::from_settings(&settings);
+        assert_eq!(
+            policy.reason(24 * 60 * 60 * SECOND, Some(60 * 60 * SECOND)),
+            None
+        );
+    }
+}
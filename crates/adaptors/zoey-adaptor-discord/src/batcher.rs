@@ -0,0 +1,530 @@
+//! Batched persistence for non-critical memory writes
+//!
+//! Busy guilds already cost one backend write per message through
+//! `/chat/stream`. Side writes such as reaction feedback, telemetry and voice
+//! transcript lines go through a [`MemoryBatcher`] instead of their own
+//! request each:
+//!
+//! - writes are queued and flushed every `flush_interval` or as soon as
+//!   `max_batch` are waiting, whichever comes first
+//! - a flush is one `POST /memories/batch`; when the backend answers 404/405
+//!   the batcher switches to one `POST /memory` per item for the rest of its life
+//! - the queue holds at most `max_queue` writes; when full, the oldest
+//!   droppable write ([`WriteClass::droppable`]) is evicted
+//! - [`MemoryBatcher::shutdown`] (also run as a [`ShutdownHook`]) flushes what is left
+//!
+//! Queue depth, flush size and flush latency are logged at debug level under
+//! this module's target. Chat requests themselves never go through here.
+//!
+//! The batcher is generic over the item and [`BatchSink`] so it can move to
+//! `zoey_core` once other adapters need it.
+
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use zoey_core::agent_api::types::MemoryCreateRequest;
+use zoey_core::infrastructure::ShutdownHook;
+use zoey_core::{Result, ZoeyError};
+
+/// Default time between flushes
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default number of queued writes that triggers an early flush
+pub const DEFAULT_MAX_BATCH: usize = 50;
+
+/// Default queue bound
+pub const DEFAULT_MAX_QUEUE: usize = 1_000;
+
+const BULK_UNKNOWN: u8 = 0;
+const BULK_SUPPORTED: u8 = 1;
+const BULK_UNSUPPORTED: u8 = 2;
+
+/// Kind of queued write, deciding what may be dropped under pressure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteClass {
+    /// Explicit user feedback such as reactions on a reply
+    Feedback,
+    /// Voice transcript lines
+    Transcript,
+    /// Adapter metrics and diagnostics
+    Telemetry,
+}
+
+impl WriteClass {
+    /// Whether a queued write of this class may be evicted when the queue is full
+    pub fn droppable(self) -> bool {
+        !matches!(self, Self::Feedback)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Feedback => "feedback",
+            Self::Transcript => "transcript",
+            Self::Telemetry => "telemetry",
+        }
+    }
+}
+
+/// Result of [`MemoryBatcher::push`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// Queued after evicting the oldest droppable write
+    QueuedDroppedOldest,
+    /// Not queued: the queue is full of undroppable writes, or the batcher is shut down
+    Rejected,
+}
+
+/// Flush triggers and queue bound
+#[derive(Debug, Clone)]
+pub struct BatcherConfig {
+    pub flush_interval: Duration,
+    pub max_batch: usize,
+    pub max_queue: usize,
+}
+
+impl Default for BatcherConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            max_batch: DEFAULT_MAX_BATCH,
+            max_queue: DEFAULT_MAX_QUEUE,
+        }
+    }
+}
+
+/// Destination of flushed batches
+#[async_trait]
+pub trait BatchSink<T>: Send + Sync {
+    /// Write `items` in one call; `Ok(false)` when the backend has no bulk endpoint
+    async fn write_batch(&self, items: &[T]) -> Result<bool>;
+
+    /// Write a single item, used once bulk writes turn out to be unsupported
+    async fn write_one(&self, item: &T) -> Result<()>;
+}
+
+/// Counters for monitoring the batcher
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatcherStats {
+    pub queue_depth: usize,
+    pub flushed: u64,
+    pub dropped: u64,
+    pub failed: u64,
+    /// Duration of the most recent flush
+    pub last_flush: Option<Duration>,
+    /// `None` until the first flush has probed the backend
+    pub bulk_supported: Option<bool>,
+}
+
+struct Shared<T> {
+    config: BatcherConfig,
+    queue: Mutex<VecDeque<(WriteClass, T)>>,
+    /// Wakes the flush task when a full batch is waiting
+    batch_ready: Notify,
+    closed: AtomicBool,
+    bulk: AtomicU8,
+    flushed: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+    /// Microseconds taken by the last flush, `u64::MAX` before the first one
+    last_flush_us: AtomicU64,
+}
+
+struct Control {
+    shutdown_tx: watch::Sender<bool>,
+    task: tokio::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Queue of non-critical writes flushed in batches by a background task
+pub struct MemoryBatcher<T> {
+    shared: Arc<Shared<T>>,
+    control: Arc<Control>,
+}
+
+impl<T> Clone for MemoryBatcher<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            control: self.control.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> MemoryBatcher<T> {
+    /// Start the flush task writing to `sink`
+    pub fn start(config: BatcherConfig, sink: Arc<dyn BatchSink<T>>) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(VecDeque::new()),
+            batch_ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            bulk: AtomicU8::new(BULK_UNKNOWN),
+            flushed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            last_flush_us: AtomicU64::new(u64::MAX),
+            config,
+        });
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let task = tokio::spawn(run(shared.clone(), sink, shutdown_rx));
+        Self {
+            shared,
+            control: Arc::new(Control {
+                shutdown_tx,
+                task: tokio::sync::Mutex::new(Some(task)),
+            }),
+        }
+    }
+
+    /// Queue a write, evicting the oldest droppable write if the queue is full
+    pub fn push(&self, class: WriteClass, item: T) -> PushOutcome {
+        if self.shared.closed.load(Ordering::SeqCst) {
+            return PushOutcome::Rejected;
+        }
+        let config = &self.shared.config;
+        let mut queue = self.lock_queue();
+        let mut outcome = PushOutcome::Queued;
+        if queue.len() >= config.max_queue {
+            let Some(oldest) = queue.iter().position(|(c, _)| c.droppable()) else {
+                self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    class = class.as_str(),
+                    queue_depth = queue.len(),
+                    "Memory batch queue full of undroppable writes, rejecting write"
+                );
+                return PushOutcome::Rejected;
+            };
+            if let Some((dropped_class, _)) = queue.remove(oldest) {
+                self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                debug!(
+                    class = dropped_class.as_str(),
+                    queue_depth = queue.len(),
+                    "Memory batch queue full, dropped oldest write"
+                );
+            }
+            outcome = PushOutcome::QueuedDroppedOldest;
+        }
+        queue.push_back((class, item));
+        if queue.len() >= config.max_batch {
+            self.shared.batch_ready.notify_one();
+        }
+        outcome
+    }
+
+    /// Number of writes waiting to be flushed
+    pub fn depth(&self) -> usize {
+        self.lock_queue().len()
+    }
+
+    /// Counters since the batcher started
+    pub fn stats(&self) -> BatcherStats {
+        let last_flush_us = self.shared.last_flush_us.load(Ordering::Relaxed);
+        BatcherStats {
+            queue_depth: self.depth(),
+            flushed: self.shared.flushed.load(Ordering::Relaxed),
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            failed: self.shared.failed.load(Ordering::Relaxed),
+            last_flush: (last_flush_us != u64::MAX).then(|| Duration::from_micros(last_flush_us)),
+            bulk_supported: match self.shared.bulk.load(Ordering::Relaxed) {
+                BULK_SUPPORTED => Some(true),
+                BULK_UNSUPPORTED => Some(false),
+                _ => None,
+            },
+        }
+    }
+
+    /// Stop accepting writes, flush everything queued and wait for the flush task
+    pub async fn shutdown(&self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+        let _ = self.control.shutdown_tx.send(true);
+        if let Some(task) = self.control.task.lock().await.take() {
+            let _ = task.await;
+        }
+    }
+
+    fn lock_queue(&self) -> std::sync::MutexGuard<'_, VecDeque<(WriteClass, T)>> {
+        // The queue stays consistent even if a holder panicked
+        self.shared.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + 'static> ShutdownHook for MemoryBatcher<T> {
+    fn name(&self) -> &str {
+        "discord-memory-batcher"
+    }
+
+    async fn on_shutdown(&self) -> Result<()> {
+        self.shutdown().await;
+        Ok(())
+    }
+}
+
+async fn run<T: Send + Sync + 'static>(
+    shared: Arc<Shared<T>>,
+    sink: Arc<dyn BatchSink<T>>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let mut ticker = tokio::time::interval(shared.config.flush_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => flush(&shared, sink.as_ref(), false).await,
+            _ = shared.batch_ready.notified() => flush(&shared, sink.as_ref(), true).await,
+            _ = shutdown_rx.changed() => {
+                flush(&shared, sink.as_ref(), false).await;
+                break;
+            }
+        }
+    }
+}
+
+/// Write queued items in batches of at most `max_batch`; with `full_only`,
+/// stop once less than a full batch is left
+async fn flush<T>(shared: &Shared<T>, sink: &dyn BatchSink<T>, full_only: bool) {
+    let max_batch = shared.config.max_batch.max(1);
+    loop {
+        let batch: Vec<T> = {
+            let mut queue = shared.queue.lock().unwrap_or_else(PoisonError::into_inner);
+            if queue.is_empty() || (full_only && queue.len() < max_batch) {
+                return;
+            }
+            let n = queue.len().min(max_batch);
+            queue.drain(..n).map(|(_, item)| item).collect()
+        };
+        write_batch(shared, sink, batch).await;
+    }
+}
+
+async fn write_batch<T>(shared: &Shared<T>, sink: &dyn BatchSink<T>, batch: Vec<T>) {
+    let started = Instant::now();
+    let mut bulk = shared.bulk.load(Ordering::Relaxed) != BULK_UNSUPPORTED;
+    let mut failed = 0u64;
+    if bulk {
+        match sink.write_batch(&batch).await {
+            Ok(true) => shared.bulk.store(BULK_SUPPORTED, Ordering::Relaxed),
+            Ok(false) => {
+                info!("Backend has no bulk memory endpoint, writing memories one at a time");
+                shared.bulk.store(BULK_UNSUPPORTED, Ordering::Relaxed);
+                bulk = false;
+            }
+            Err(e) => {
+                warn!(error = %e, items = batch.len(), "Memory batch write failed");
+                failed = batch.len() as u64;
+            }
+        }
+    }
+    if !bulk {
+        for item in &batch {
+            if let Err(e) = sink.write_one(item).await {
+                warn!(error = %e, "Memory write failed");
+                failed += 1;
+            }
+        }
+    }
+
+    let elapsed = started.elapsed();
+    shared
+        .last_flush_us
+        .store(elapsed.as_micros().min(u64::MAX as u128 - 1) as u64, Ordering::Relaxed);
+    shared.failed.fetch_add(failed, Ordering::Relaxed);
+    shared
+        .flushed
+        .fetch_add(batch.len() as u64 - failed, Ordering::Relaxed);
+    debug!(
+        items = batch.len(),
+        failed,
+        bulk,
+        flush_ms = elapsed.as_millis() as u64,
+        queue_depth = shared.queue.lock().unwrap_or_else(PoisonError::into_inner).len(),
+        "Flushed memory batch"
+    );
+}
+
+/// [`BatchSink`] writing memories to the Agent API
+pub struct AgentApiMemorySink {
+    client: reqwest::Client,
+    api_base: String,
+}
+
+impl AgentApiMemorySink {
+    /// `api_base` is the Agent API root, e.g. `http://127.0.0.1:9090/agent`
+    pub fn new(client: reqwest::Client, api_base: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_base: api_base.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl BatchSink<MemoryCreateRequest> for AgentApiMemorySink {
+    async fn write_batch(&self, items: &[MemoryCreateRequest]) -> Result<bool> {
+        let resp = self
+            .client
+            .post(format!("{}/memories/batch", self.api_base))
+            .json(&serde_json::json!({ "memories": items }))
+            .send()
+            .await
+            .map_err(|e| ZoeyError::other(format!("memory batch request failed: {}", e)))?;
+        match resp.status() {
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::METHOD_NOT_ALLOWED => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(ZoeyError::other(format!("memory batch rejected: {}", status))),
+        }
+    }
+
+    async fn write_one(&self, item: &MemoryCreateRequest) -> Result<()> {
+        let resp = self
+            .client
+            .post(format!("{}/memory", self.api_base))
+            .json(item)
+            .send()
+            .await
+            .map_err(|e| ZoeyError::other(format!("memory request failed: {}", e)))?;
+        if !resp.status().is_success() {
+            return Err(ZoeyError::other(format!("memory rejected: {}", resp.status())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records batch and single writes; `bulk` decides whether batches are accepted
+    #[derive(Default)]
+    struct RecordingSink {
+        bulk: bool,
+        batch_calls: Mutex<Vec<Vec<u32>>>,
+        singles: Mutex<Vec<u32>>,
+    }
+
+    #[async_trait]
+    impl BatchSink<u32> for RecordingSink {
+        async fn write_batch(&self, items: &[u32]) -> Result<bool> {
+            self.batch_calls.lock().unwrap().push(items.to_vec());
+            Ok(self.bulk)
+        }
+
+        async fn write_one(&self, item: &u32) -> Result<()> {
+            self.singles.lock().unwrap().push(*item);
+            Ok(())
+        }
+    }
+
+    fn config(flush_interval: Duration, max_batch: usize, max_queue: usize) -> BatcherConfig {
+        BatcherConfig {
+            flush_interval,
+            max_batch,
+            max_queue,
+        }
+    }
+
+    async fn wait_until(mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !done() {
+            assert!(Instant::now() < deadline, "condition not reached in time");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flushes_when_batch_is_full() {
+        let sink = Arc::new(RecordingSink {
+            bulk: true,
+            ..Default::default()
+        });
+        let batcher = MemoryBatcher::start(config(Duration::from_secs(3600), 3, 100), sink.clone());
+        batcher.push(WriteClass::Telemetry, 1);
+        batcher.push(WriteClass::Telemetry, 2);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(sink.batch_calls.lock().unwrap().is_empty());
+
+        batcher.push(WriteClass::Feedback, 3);
+        wait_until(|| !sink.batch_calls.lock().unwrap().is_empty()).await;
+        assert_eq!(*sink.batch_calls.lock().unwrap(), vec![vec![1, 2, 3]]);
+        assert_eq!(batcher.stats().bulk_supported, Some(true));
+        assert!(batcher.stats().last_flush.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_flushes_on_interval() {
+        let sink = Arc::new(RecordingSink {
+            bulk: true,
+            ..Default::default()
+        });
+        let batcher = MemoryBatcher::start(config(Duration::from_millis(50), 100, 100), sink.clone());
+        batcher.push(WriteClass::Transcript, 7);
+        batcher.push(WriteClass::Transcript, 8);
+        wait_until(|| !sink.batch_calls.lock().unwrap().is_empty()).await;
+        assert_eq!(*sink.batch_calls.lock().unwrap(), vec![vec![7, 8]]);
+        assert_eq!(batcher.depth(), 0);
+        assert_eq!(batcher.stats().flushed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_single_writes_once() {
+        let sink = Arc::new(RecordingSink::default());
+        let batcher = MemoryBatcher::start(config(Duration::from_secs(3600), 2, 100), sink.clone());
+        for i in 0..6 {
+            batcher.push(WriteClass::Telemetry, i);
+        }
+        wait_until(|| sink.singles.lock().unwrap().len() == 6).await;
+        // Only the first flush probes the bulk endpoint
+        assert_eq!(*sink.batch_calls.lock().unwrap(), vec![vec![0, 1]]);
+        assert_eq!(*sink.singles.lock().unwrap(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(batcher.stats().bulk_supported, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_droppable_when_full() {
+        let sink = Arc::new(RecordingSink {
+            bulk: true,
+            ..Default::default()
+        });
+        let batcher = MemoryBatcher::start(config(Duration::from_secs(3600), 10, 3), sink.clone());
+        assert_eq!(batcher.push(WriteClass::Telemetry, 1), PushOutcome::Queued);
+        assert_eq!(batcher.push(WriteClass::Feedback, 2), PushOutcome::Queued);
+        assert_eq!(batcher.push(WriteClass::Transcript, 3), PushOutcome::Queued);
+        // Telemetry 1 is the oldest droppable write
+        assert_eq!(batcher.push(WriteClass::Feedback, 4), PushOutcome::QueuedDroppedOldest);
+        // Transcript 3 is next; feedback is never evicted
+        assert_eq!(batcher.push(WriteClass::Telemetry, 5), PushOutcome::QueuedDroppedOldest);
+        assert_eq!(batcher.push(WriteClass::Feedback, 6), PushOutcome::QueuedDroppedOldest);
+        assert_eq!(batcher.push(WriteClass::Telemetry, 7), PushOutcome::Rejected);
+        assert_eq!(batcher.depth(), 3);
+        assert_eq!(batcher.stats().dropped, 4);
+
+        batcher.shutdown().await;
+        assert_eq!(*sink.batch_calls.lock().unwrap(), vec![vec![2, 4, 6]]);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_hook_flushes_remaining_writes() {
+        let sink = Arc::new(RecordingSink {
+            bulk: true,
+            ..Default::default()
+        });
+        let batcher = MemoryBatcher::start(config(Duration::from_secs(3600), 2, 100), sink.clone());
+        for i in 0..5 {
+            batcher.push(WriteClass::Transcript, i);
+        }
+        let manager = zoey_core::infrastructure::ShutdownManager::new(Duration::from_secs(1));
+        manager.register_hook(batcher.clone());
+        manager.shutdown().await.unwrap();
+
+        let flushed: Vec<u32> = sink.batch_calls.lock().unwrap().concat();
+        assert_eq!(flushed, vec![0, 1, 2, 3, 4]);
+        assert!(sink.batch_calls.lock().unwrap().iter().all(|b| b.len() <= 2));
+        assert_eq!(batcher.depth(), 0);
+        assert_eq!(batcher.push(WriteClass::Feedback, 9), PushOutcome::Rejected);
+    }
+}
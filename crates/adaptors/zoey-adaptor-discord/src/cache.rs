@@ -0,0 +1,354 @@
+//! Bounded serenity cache and cache-independent lookups
+//!
+//! In large guilds an unbounded cache (every user, every message) grows
+//! without limit, so [`CacheTuning`] keeps it small by default: no user
+//! cache, no member chunks and a short per-channel message history.
+//!
+//! Nothing in the voice join flow needs the user cache. Voice channels are
+//! looked up in the adapter's own voice-state tracker first and then in the
+//! guild's cached voice states ([`voice_channel_of`]), both of which are kept
+//! with users off. Display names fall back to the REST API when the member
+//! or user is not cached ([`DisplayNames`]).
+//!
+//! [`spawn_cache_gauge`] logs the cache sizes periodically so the effect of
+//! the settings is visible in production.
+
+use async_trait::async_trait;
+use serenity::cache::{Cache, Settings as CacheSettings};
+use serenity::http::Http;
+use serenity::model::gateway::GatewayIntents;
+use serenity::model::id::{GuildId, UserId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Default number of messages kept per channel
+pub const DEFAULT_MAX_MESSAGES: usize = 200;
+
+/// Default interval between cache size reports
+pub const DEFAULT_CACHE_GAUGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a display name resolved over REST is reused
+const DISPLAY_NAME_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Most display names kept before expired entries are dropped
+const MAX_DISPLAY_NAMES: usize = 4096;
+
+/// What the serenity cache keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTuning {
+    /// Cache every user seen in events (grows with guild size)
+    pub cache_users: bool,
+    /// Messages kept per channel; 0 disables the message cache
+    pub max_messages: usize,
+    /// Receive and cache guild members (adds the privileged `GUILD_MEMBERS` intent)
+    pub cache_members: bool,
+}
+
+impl Default for CacheTuning {
+    fn default() -> Self {
+        Self {
+            cache_users: false,
+            max_messages: DEFAULT_MAX_MESSAGES,
+            cache_members: false,
+        }
+    }
+}
+
+impl CacheTuning {
+    /// Serenity cache settings for this tuning
+    ///
+    /// Guilds and channels are always cached: guild voice states and owner
+    /// checks depend on them.
+    pub fn to_settings(&self) -> CacheSettings {
+        let mut settings = CacheSettings::default();
+        settings.cache_guilds = true;
+        settings.cache_channels = true;
+        settings.cache_users = self.cache_users;
+        settings.max_messages = self.max_messages;
+        settings
+    }
+
+    /// Gateway intents adjusted for member caching
+    ///
+    /// Serenity has no switch for the member cache; members only arrive (and
+    /// are cached) through the `GUILD_MEMBERS` and `GUILD_PRESENCES` intents.
+    pub fn apply_intents(&self, intents: GatewayIntents) -> GatewayIntents {
+        if self.cache_members {
+            intents | GatewayIntents::GUILD_MEMBERS
+        } else {
+            intents - GatewayIntents::GUILD_MEMBERS - GatewayIntents::GUILD_PRESENCES
+        }
+    }
+}
+
+/// Number of entries held by the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheSizes {
+    pub guilds: usize,
+    pub users: usize,
+    pub members: usize,
+    pub messages: usize,
+}
+
+impl CacheSizes {
+    /// Count the entries currently in `cache`
+    pub fn of(cache: &Cache) -> Self {
+        let mut sizes = Self {
+            guilds: cache.guild_count(),
+            users: cache.user_count(),
+            ..Self::default()
+        };
+        for guild_id in cache.guilds() {
+            // Collect first so the guild entry is not held across message lookups
+            let channels: Vec<_> = match cache.guild(guild_id) {
+                Some(guild) => {
+                    sizes.members += guild.members.len();
+                    guild.channels.keys().copied().collect()
+                }
+                None => continue,
+            };
+            sizes.messages += channels
+                .into_iter()
+                .filter_map(|c| cache.channel_messages(c).map(|m| m.len()))
+                .sum::<usize>();
+        }
+        sizes
+    }
+}
+
+/// Log the cache sizes every `interval` until the returned task is aborted
+pub fn spawn_cache_gauge(cache: Arc<Cache>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let sizes = CacheSizes::of(&cache);
+            info!(
+                guilds = sizes.guilds,
+                users = sizes.users,
+                members = sizes.members,
+                messages = sizes.messages,
+                "Discord cache sizes"
+            );
+        }
+    })
+}
+
+/// Voice channel `user_id` is in: the voice-state tracker's answer
+/// (`tracked`), else the guild cache
+///
+/// Neither source depends on the user cache.
+pub fn voice_channel_of(
+    tracked: Option<u64>,
+    cache: &Cache,
+    guild_id: u64,
+    user_id: u64,
+) -> Option<u64> {
+    if tracked.is_some() {
+        return tracked;
+    }
+    let guild = cache.guild(GuildId::new(guild_id))?;
+    guild
+        .voice_states
+        .get(&UserId::new(user_id))
+        .and_then(|vs| vs.channel_id)
+        .map(|c| c.get())
+}
+
+/// Where display names come from
+#[async_trait]
+pub trait NameSource: Send + Sync {
+    /// Name from the local cache, if present
+    fn cached(&self, guild_id: u64, user_id: u64) -> Option<String>;
+
+    /// Name fetched from Discord
+    async fn fetch(&self, guild_id: u64, user_id: u64) -> Option<String>;
+}
+
+/// [`NameSource`] backed by serenity's cache and REST client
+pub struct SerenityNames {
+    cache: Arc<Cache>,
+    http: Arc<Http>,
+}
+
+impl SerenityNames {
+    pub fn new(cache: Arc<Cache>, http: Arc<Http>) -> Self {
+        Self { cache, http }
+    }
+}
+
+#[async_trait]
+impl NameSource for SerenityNames {
+    fn cached(&self, guild_id: u64, user_id: u64) -> Option<String> {
+        let (guild_id, user_id) = (GuildId::new(guild_id), UserId::new(user_id));
+        if let Some(member) = self.cache.member(guild_id, user_id) {
+            return Some(member.display_name().to_string());
+        }
+        self.cache
+            .user(user_id)
+            .map(|u| u.display_name().to_string())
+    }
+
+    async fn fetch(&self, guild_id: u64, user_id: u64) -> Option<String> {
+        let (guild_id, user_id) = (GuildId::new(guild_id), UserId::new(user_id));
+        match self.http.get_member(guild_id, user_id).await {
+            Ok(member) => Some(member.display_name().to_string()),
+            Err(_) => self
+                .http
+                .get_user(user_id)
+                .await
+                .ok()
+                .map(|u| u.display_name().to_string()),
+        }
+    }
+}
+
+/// Display names resolved from the cache, falling back to REST
+///
+/// REST results are remembered for a while so a busy voice channel does not
+/// cost one request per utterance.
+pub struct DisplayNames {
+    ttl: Duration,
+    resolved: RwLock<HashMap<(u64, u64), (String, Instant)>>,
+}
+
+impl Default for DisplayNames {
+    fn default() -> Self {
+        Self::new(DISPLAY_NAME_TTL)
+    }
+}
+
+impl DisplayNames {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            resolved: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Display name of `user_id` in `guild_id`, or `None` if Discord does not know it
+    pub async fn resolve(
+        &self,
+        source: &dyn NameSource,
+        guild_id: u64,
+        user_id: u64,
+    ) -> Option<String> {
+        if let Some(name) = source.cached(guild_id, user_id) {
+            return Some(name);
+        }
+        if let Some((name, at)) = self.resolved.read().unwrap().get(&(guild_id, user_id)) {
+            if at.elapsed() < self.ttl {
+                return Some(name.clone());
+            }
+        }
+        let name = source.fetch(guild_id, user_id).await?;
+        let mut resolved = self.resolved.write().unwrap();
+        if resolved.len() >= MAX_DISPLAY_NAMES {
+            let ttl = self.ttl;
+            resolved.retain(|_, (_, at)| at.elapsed() < ttl);
+            if resolved.len() >= MAX_DISPLAY_NAMES {
+                resolved.clear();
+            }
+        }
+        resolved.insert((guild_id, user_id), (name.clone(), Instant::now()));
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_settings_mapping() {
+        let settings = CacheTuning::default().to_settings();
+        assert!(!settings.cache_users);
+        assert_eq!(settings.max_messages, DEFAULT_MAX_MESSAGES);
+        assert!(settings.cache_guilds && settings.cache_channels);
+
+        let tuning = CacheTuning {
+            cache_users: true,
+            max_messages: 0,
+            cache_members: true,
+        };
+        let settings = tuning.to_settings();
+        assert!(settings.cache_users);
+        assert_eq!(settings.max_messages, 0);
+
+        let base = GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES;
+        assert!(tuning
+            .apply_intents(base)
+            .contains(GatewayIntents::GUILD_MEMBERS));
+        let stripped = CacheTuning::default()
+            .apply_intents(base | GatewayIntents::GUILD_MEMBERS | GatewayIntents::GUILD_PRESENCES);
+        assert_eq!(stripped, base);
+    }
+
+    struct FakeNames {
+        cached: Option<&'static str>,
+        fetched: Option<&'static str>,
+        fetches: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl NameSource for FakeNames {
+        fn cached(&self, _guild_id: u64, _user_id: u64) -> Option<String> {
+            self.cached.map(str::to_string)
+        }
+
+        async fn fetch(&self, _guild_id: u64, _user_id: u64) -> Option<String> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.fetched.map(str::to_string)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_display_name_rest_fallback() {
+        let names = DisplayNames::default();
+        let rest = FakeNames {
+            cached: None,
+            fetched: Some("Ada"),
+            fetches: AtomicUsize::new(0),
+        };
+        assert_eq!(names.resolve(&rest, 1, 2).await.as_deref(), Some("Ada"));
+        assert_eq!(names.resolve(&rest, 1, 2).await.as_deref(), Some("Ada"));
+        // The second lookup is served from the resolved names
+        assert_eq!(rest.fetches.load(Ordering::SeqCst), 1);
+
+        let cached = FakeNames {
+            cached: Some("Grace"),
+            fetched: Some("ignored"),
+            fetches: AtomicUsize::new(0),
+        };
+        assert_eq!(names.resolve(&cached, 1, 3).await.as_deref(), Some("Grace"));
+        assert_eq!(cached.fetches.load(Ordering::SeqCst), 0);
+
+        let unknown = FakeNames {
+            cached: None,
+            fetched: None,
+            fetches: AtomicUsize::new(0),
+        };
+        assert_eq!(names.resolve(&unknown, 1, 4).await, None);
+
+        // Expired names are fetched again
+        let short = DisplayNames::new(Duration::ZERO);
+        short.resolve(&rest, 5, 6).await;
+        short.resolve(&rest, 5, 6).await;
+        assert_eq!(rest.fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_voice_lookup_without_user_cache() {
+        let cache = Cache::new_with_settings(CacheTuning::default().to_settings());
+
+        assert_eq!(voice_channel_of(Some(30), &cache, 10, 20), Some(30));
+        // Untracked user and uncached guild: no channel, no panic
+        assert_eq!(voice_channel_of(None, &cache, 10, 21), None);
+        assert_eq!(voice_channel_of(None, &cache, 11, 20), None);
+        assert_eq!(CacheSizes::of(&cache), CacheSizes::default());
+    }
+}
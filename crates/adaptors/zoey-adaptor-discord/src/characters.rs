@@ -0,0 +1,316 @@
+//! Per-channel character routing
+//!
+//! A single bot process can answer as different characters in different
+//! channels of one guild (e.g. `#support` as "Zoey Support" and `#lounge` as
+//! "Zoey Casual"). Mappings come from [`DiscordConfig::channel_characters`]
+//! and can be changed at runtime with `/character here <name>`; runtime
+//! changes are persisted per guild as a component on the guild's world.
+//!
+//! [`DiscordConfig::channel_characters`]: crate::DiscordConfig::channel_characters
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+use zoey_core::types::Component;
+use zoey_core::{IDatabaseAdapter, Result};
+
+/// Component type holding a guild's channel -> character overrides
+pub const CHANNEL_CHARACTERS_COMPONENT_TYPE: &str = "discord_channel_characters";
+
+/// Argument to `/character here` that clears a channel's mapping
+const RESET_ARGS: [&str; 3] = ["default", "reset", "none"];
+
+/// World ID for a guild (DMs use guild 0)
+pub fn guild_world_id(guild_id: u64) -> Uuid {
+    zoey_core::string_to_uuid(&format!("discord-guild-{}", guild_id))
+}
+
+/// Deterministic room ID for a channel, separated per mapped character
+///
+/// Channels answered by the runtime default character keep the original
+/// `discord-room-{guild}-{channel}` ID so existing history is preserved.
+pub fn room_uuid(guild_id: u64, channel_id: u64, character: Option<&str>) -> Uuid {
+    room_uuid_at(guild_id, channel_id, character, 0)
+}
+
+/// Room ID for a channel's context epoch (see [`crate::epochs`])
+///
+/// Epoch 0 is [`room_uuid`]; later epochs append `-epoch-{n}` to its seed.
+pub fn room_uuid_at(guild_id: u64, channel_id: u64, character: Option<&str>, epoch: u64) -> Uuid {
+    let seed = match character.map(character_key).filter(|c| !c.is_empty()) {
+        Some(character) => format!("discord-room-{}-{}-{}", guild_id, channel_id, character),
+        None => format!("discord-room-{}-{}", guild_id, channel_id),
+    };
+    if epoch == 0 {
+        zoey_core::string_to_uuid(&seed)
+    } else {
+        zoey_core::string_to_uuid(&format!("{}-epoch-{}", seed, epoch))
+    }
+}
+
+/// Normalized form of a character name or file used for IDs and comparisons
+pub fn character_key(character: &str) -> String {
+    let file = character.rsplit(['/', '\\']).next().unwrap_or(character);
+    file.trim_end_matches(".xml").trim().to_lowercase()
+}
+
+/// Human-facing name for a mapped character (file names become "zoey support")
+pub fn display_name(character: &str) -> String {
+    let file = character.rsplit(['/', '\\']).next().unwrap_or(character);
+    file.trim_end_matches(".xml")
+        .split(['-', '_'])
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parsed `/character here` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum CharacterCommand {
+    /// Show the current mapping for this channel
+    Show,
+    /// Map this channel to a character
+    Set(String),
+    /// Clear this channel's mapping and fall back to the runtime default
+    Reset,
+}
+
+impl CharacterCommand {
+    /// Parse a message as a `/character here` command
+    pub fn parse(text: &str) -> Option<Self> {
+        let rest = text.trim().strip_prefix("/character")?;
+        let rest = rest.trim_start().strip_prefix("here")?;
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let arg = rest.trim();
+        if arg.is_empty() {
+            Some(Self::Show)
+        } else if RESET_ARGS.iter().any(|r| arg.eq_ignore_ascii_case(r)) {
+            Some(Self::Reset)
+        } else {
+            Some(Self::Set(arg.to_string()))
+        }
+    }
+}
+
+/// Channel -> character mapping with per-guild runtime overrides
+pub struct ChannelCharacters {
+    /// Mappings from configuration
+    configured: HashMap<u64, String>,
+    /// guild_id -> (channel_id -> character); an empty name means "runtime default"
+    overrides: RwLock<HashMap<u64, HashMap<u64, String>>>,
+    adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
+}
+
+impl ChannelCharacters {
+    pub fn new(
+        configured: HashMap<u64, String>,
+        adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
+    ) -> Self {
+        Self {
+            configured,
+            overrides: RwLock::new(HashMap::new()),
+            adapter,
+        }
+    }
+
+    /// Character mapped to a channel, or `None` for the runtime default
+    pub fn resolve(&self, guild_id: u64, channel_id: u64) -> Option<String> {
+        let overridden = self
+            .overrides
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .and_then(|m| m.get(&channel_id))
+            .cloned();
+        match overridden {
+            Some(name) if name.is_empty() => None,
+            Some(name) => Some(name),
+            None => self.configured.get(&channel_id).cloned(),
+        }
+    }
+
+    /// Name used for addressing and wake-word matching in a channel
+    pub fn name_for(&self, guild_id: u64, channel_id: u64, default_name: &str) -> String {
+        self.resolve(guild_id, channel_id)
+            .map(|c| display_name(&c))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| default_name.to_string())
+    }
+
+    /// Load persisted overrides for a guild
+    pub async fn load_guild(&self, guild_id: u64) -> Result<()> {
+        let Some(component) = self.find(guild_id).await? else {
+            return Ok(());
+        };
+        let stored: HashMap<String, String> = serde_json::from_value(component.data)?;
+        let parsed = stored
+            .into_iter()
+            .filter_map(|(cid, name)| cid.parse::<u64>().ok().map(|cid| (cid, name)))
+            .collect();
+        self.overrides.write().unwrap().insert(guild_id, parsed);
+        Ok(())
+    }
+
+    /// Map a channel to a character (`None` resets to the runtime default) and persist it
+    pub async fn set(&self, guild_id: u64, channel_id: u64, character: Option<String>) -> Result<()> {
+        let guild_map = {
+            let mut overrides = self.overrides.write().unwrap();
+            let guild_map = overrides.entry(guild_id).or_default();
+            guild_map.insert(channel_id, character.unwrap_or_default());
+            guild_map.clone()
+        };
+        self.persist(guild_id, guild_map).await
+    }
+
+    async fn find(&self, guild_id: u64) -> Result<Option<Component>> {
+        match &self.adapter {
+            Some(adapter) => {
+                let component_type = CHANNEL_CHARACTERS_COMPONENT_TYPE;
+                find_guild_component(adapter.as_ref(), guild_id, component_type).await
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn persist(&self, guild_id: u64, guild_map: HashMap<u64, String>) -> Result<()> {
+        let Some(adapter) = &self.adapter else {
+            return Ok(());
+        };
+        let data = serde_json::to_value(
+            guild_map
+                .into_iter()
+                .map(|(cid, name)| (cid.to_string(), name))
+                .collect::<HashMap<_, _>>(),
+        )?;
+        let component_type = CHANNEL_CHARACTERS_COMPONENT_TYPE;
+        save_guild_component(adapter.as_ref(), guild_id, component_type, data).await
+    }
+}
+
+/// A guild's component of `component_type`, stored on the guild's world
+pub(crate) async fn find_guild_component(
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    guild_id: u64,
+    component_type: &str,
+) -> Result<Option<Component>> {
+    let world_id = guild_world_id(guild_id);
+    adapter
+        .get_component(world_id, component_type, Some(world_id), None)
+        .await
+}
+
+/// Store `data` as the guild's component of `component_type`, creating it if needed
+pub(crate) async fn save_guild_component(
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    guild_id: u64,
+    component_type: &str,
+    data: serde_json::Value,
+) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    match find_guild_component(adapter, guild_id, component_type).await? {
+        Some(mut existing) => {
+            existing.data = data;
+            existing.updated_at = Some(now);
+            adapter.update_component(&existing).await
+        }
+        None => {
+            let world_id = guild_world_id(guild_id);
+            let component = Component {
+                id: Uuid::new_v4(),
+                entity_id: world_id,
+                world_id,
+                source_entity_id: None,
+                component_type: component_type.to_string(),
+                data,
+                created_at: Some(now),
+                updated_at: Some(now),
+            };
+            adapter.create_component(&component).await.map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping() -> ChannelCharacters {
+        ChannelCharacters::new(
+            HashMap::from([
+                (10, "Zoey Support".to_string()),
+                (20, "characters/zoey-casual.xml".to_string()),
+            ]),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_mapping_resolution() {
+        let map = mapping();
+        assert_eq!(map.resolve(1, 10).as_deref(), Some("Zoey Support"));
+        assert_eq!(map.resolve(1, 30), None);
+
+        // Runtime override wins over configuration, per guild
+        map.set(1, 10, Some("Zoey Casual".to_string())).await.unwrap();
+        assert_eq!(map.resolve(1, 10).as_deref(), Some("Zoey Casual"));
+        assert_eq!(map.resolve(2, 10).as_deref(), Some("Zoey Support"));
+
+        // Reset falls back to the runtime default even if configured
+        map.set(1, 10, None).await.unwrap();
+        assert_eq!(map.resolve(1, 10), None);
+    }
+
+    #[test]
+    fn test_room_uuid_includes_character() {
+        let default_room = room_uuid(1, 10, None);
+        assert_eq!(default_room, zoey_core::string_to_uuid("discord-room-1-10"));
+        let support = room_uuid(1, 10, Some("Zoey Support"));
+        let casual = room_uuid(1, 10, Some("Zoey Casual"));
+        assert_ne!(support, default_room);
+        assert_ne!(support, casual);
+        // Name and file forms are normalized consistently
+        assert_eq!(room_uuid(1, 10, Some("zoey support")), support);
+        assert_eq!(
+            room_uuid(1, 10, Some("characters/Zoey-Casual.xml")),
+            room_uuid(1, 10, Some("zoey-casual"))
+        );
+    }
+
+    #[test]
+    fn test_room_uuid_epochs() {
+        // Epoch 0 keeps the rooms that existed before epochs
+        assert_eq!(room_uuid_at(1, 10, None, 0), room_uuid(1, 10, None));
+        assert_eq!(
+            room_uuid_at(1, 10, Some("Zoey Support"), 0),
+            room_uuid(1, 10, Some("Zoey Support"))
+        );
+        assert_eq!(
+            room_uuid_at(1, 10, None, 2),
+            zoey_core::string_to_uuid("discord-room-1-10-epoch-2")
+        );
+        assert_ne!(room_uuid_at(1, 10, None, 1), room_uuid_at(1, 10, None, 2));
+        assert_ne!(room_uuid_at(1, 10, Some("Zoey Support"), 1), room_uuid_at(1, 10, None, 1));
+    }
+
+    #[test]
+    fn test_wake_word_name_selection() {
+        let map = mapping();
+        assert_eq!(map.name_for(1, 10, "Zoey"), "Zoey Support");
+        assert_eq!(map.name_for(1, 20, "Zoey"), "zoey casual");
+        assert_eq!(map.name_for(1, 30, "Zoey"), "Zoey");
+    }
+
+    #[test]
+    fn test_command_parsing() {
+        assert_eq!(CharacterCommand::parse("/character here"), Some(CharacterCommand::Show));
+        assert_eq!(
+            CharacterCommand::parse("/character here Zoey Support"),
+            Some(CharacterCommand::Set("Zoey Support".to_string()))
+        );
+        assert_eq!(CharacterCommand::parse("/character here reset"), Some(CharacterCommand::Reset));
+        assert_eq!(CharacterCommand::parse("/character hereafter"), None);
+        assert_eq!(CharacterCommand::parse("hello /character here"), None);
+    }
+}
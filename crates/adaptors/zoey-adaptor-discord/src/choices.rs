@@ -0,0 +1,462 @@
+//! Buttons for clarifying questions
+//!
+//! When the agent needs the user to pick between options ("did you mean X or
+//! Y?"), its reply carries a block such as
+//!
+//! ```xml
+//! <choices><c id="a">Label A</c><c id="b">Label B</c></choices>
+//! ```
+//!
+//! [`split_choices`] removes the block from the displayed text and up to
+//! [`MAX_CHOICES`] buttons are added under the reply. A button's custom ID
+//! encodes the room and choice ID ([`custom_id`]); the full label, the asker
+//! and the character are kept in [`PendingChoices`] for the prompt's TTL.
+//!
+//! Clicking a button acknowledges the interaction by disabling the row, then
+//! submits the choice's label as a message from the clicking user in that
+//! room. Only the asker may answer unless `DiscordConfig::choices_open_to_all`
+//! is set; clicks after the TTL are told the prompt has expired.
+
+use serenity::all::ButtonStyle;
+use serenity::builder::{CreateActionRow, CreateButton, EditMessage};
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, MessageId};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Buttons shown per prompt (one Discord action row)
+pub const MAX_CHOICES: usize = 5;
+
+/// Default time a prompt's buttons can be answered
+pub const DEFAULT_CHOICE_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Reply to clicks on a prompt that is no longer pending
+pub const EXPIRED_REPLY: &str = "This prompt has expired.";
+
+/// Prefix of the custom IDs of choice buttons
+const CUSTOM_ID_PREFIX: &str = "choice";
+
+/// Discord's limit on a component custom ID
+const MAX_CUSTOM_ID_LEN: usize = 100;
+
+/// Discord's limit on a button label
+const MAX_LABEL_LEN: usize = 80;
+
+/// One option offered by the agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice {
+    pub id: String,
+    /// Full label, submitted as the user's message when chosen
+    pub label: String,
+}
+
+/// `text` without its `<choices>` block, and the choices in it
+///
+/// Choices without a label, with a duplicate ID or an ID too long for a
+/// custom ID are dropped, and at most [`MAX_CHOICES`] are kept. A block
+/// still being streamed (no closing tag yet) is cut off as well.
+pub fn split_choices(text: &str) -> (String, Vec<Choice>) {
+    let Some(start) = text.find("<choices") else {
+        return (text.to_string(), Vec::new());
+    };
+    let (block, rest) = match text[start..].find("</choices>") {
+        Some(end) => (
+            &text[start..start + end],
+            &text[start + end + "</choices>".len()..],
+        ),
+        None => (&text[start..], ""),
+    };
+    let display = format!("{}{}", text[..start].trim_end(), rest)
+        .trim()
+        .to_string();
+
+    static CHOICE_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = CHOICE_RE.get_or_init(|| {
+        regex::Regex::new(r#"(?s)<c\s+id\s*=\s*["']([^"']+)["']\s*>(.*?)</c>"#).unwrap()
+    });
+    let mut choices: Vec<Choice> = Vec::new();
+    for caps in re.captures_iter(block) {
+        let id = caps[1].trim().to_string();
+        let label = unescape(caps[2].trim());
+        if id.is_empty()
+            || label.is_empty()
+            || custom_id(Uuid::nil(), &id).is_none()
+            || choices.iter().any(|c| c.id == id)
+        {
+            continue;
+        }
+        choices.push(Choice { id, label });
+        if choices.len() == MAX_CHOICES {
+            break;
+        }
+    }
+    (display, choices)
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Custom ID of the button for `choice_id` in `room_id`, `None` when too long
+pub fn custom_id(room_id: Uuid, choice_id: &str) -> Option<String> {
+    let id = format!("{}:{}:{}", CUSTOM_ID_PREFIX, room_id.simple(), choice_id);
+    (id.len() <= MAX_CUSTOM_ID_LEN).then_some(id)
+}
+
+/// Room and choice ID of a choice button's custom ID
+pub fn parse_custom_id(custom_id: &str) -> Option<(Uuid, &str)> {
+    let rest = custom_id
+        .strip_prefix(CUSTOM_ID_PREFIX)?
+        .strip_prefix(':')?;
+    let (room, choice_id) = rest.split_once(':')?;
+    Some((Uuid::parse_str(room).ok()?, choice_id))
+}
+
+/// Action row with a button per choice; `chosen` is highlighted
+pub fn buttons(
+    room_id: Uuid,
+    choices: &[Choice],
+    chosen: Option<&str>,
+    disabled: bool,
+) -> Vec<CreateActionRow> {
+    let buttons: Vec<CreateButton> = choices
+        .iter()
+        .filter_map(|choice| {
+            let style = if chosen == Some(choice.id.as_str()) {
+                ButtonStyle::Success
+            } else if chosen.is_some() {
+                ButtonStyle::Secondary
+            } else {
+                ButtonStyle::Primary
+            };
+            Some(
+                CreateButton::new(custom_id(room_id, &choice.id)?)
+                    .label(button_label(&choice.label))
+                    .style(style)
+                    .disabled(disabled),
+            )
+        })
+        .collect();
+    if buttons.is_empty() {
+        return Vec::new();
+    }
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+fn button_label(label: &str) -> String {
+    if label.chars().count() <= MAX_LABEL_LEN {
+        return label.to_string();
+    }
+    let mut short: String = label.chars().take(MAX_LABEL_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
+/// A reply waiting for one of its buttons to be clicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChoicePrompt {
+    pub room_id: Uuid,
+    /// `{room}` of the response template
+    pub room_name: String,
+    /// User whose message the reply answered
+    pub asker: u64,
+    /// Character requested from the Agent API
+    pub character: String,
+    /// `{character}` of the response template
+    pub character_name: String,
+    pub choices: Vec<Choice>,
+}
+
+/// Result of a click on a choice button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChoiceClick {
+    /// The click is accepted; the prompt is closed
+    Chosen {
+        prompt: ChoicePrompt,
+        choice: Choice,
+    },
+    /// Someone other than the asker clicked
+    NotAllowed { asker: u64 },
+    /// The prompt timed out, was already answered or is unknown
+    Expired,
+}
+
+/// Open prompts by the ID of the message carrying their buttons
+pub struct PendingChoices {
+    prompts: Mutex<HashMap<u64, (ChoicePrompt, Instant)>>,
+    ttl: Duration,
+    /// Anyone in the channel may answer, not just the asker
+    open_to_all: bool,
+}
+
+impl Default for PendingChoices {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHOICE_TTL, false)
+    }
+}
+
+impl PendingChoices {
+    pub fn new(ttl: Duration, open_to_all: bool) -> Self {
+        Self {
+            prompts: Mutex::new(HashMap::new()),
+            ttl,
+            open_to_all,
+        }
+    }
+
+    /// Remember the prompt shown on `message_id`
+    pub fn register(&self, message_id: u64, prompt: ChoicePrompt) {
+        let mut prompts = self.prompts.lock().unwrap();
+        prompts.retain(|_, (_, at)| at.elapsed() < self.ttl);
+        prompts.insert(message_id, (prompt, Instant::now()));
+    }
+
+    /// Prompt on `message_id`, if still pending
+    pub fn get(&self, message_id: u64) -> Option<ChoicePrompt> {
+        let prompts = self.prompts.lock().unwrap();
+        prompts
+            .get(&message_id)
+            .filter(|(_, at)| at.elapsed() < self.ttl)
+            .map(|(prompt, _)| prompt.clone())
+    }
+
+    /// Handle a click by `user_id` on button `custom_id` of `message_id`
+    pub fn click(&self, message_id: u64, user_id: u64, custom_id: &str) -> ChoiceClick {
+        let Some((room_id, choice_id)) = parse_custom_id(custom_id) else {
+            return ChoiceClick::Expired;
+        };
+        let mut prompts = self.prompts.lock().unwrap();
+        let Some((prompt, at)) = prompts.get(&message_id) else {
+            return ChoiceClick::Expired;
+        };
+        if at.elapsed() >= self.ttl {
+            prompts.remove(&message_id);
+            return ChoiceClick::Expired;
+        }
+        if prompt.room_id != room_id {
+            return ChoiceClick::Expired;
+        }
+        if !self.open_to_all && prompt.asker != user_id {
+            return ChoiceClick::NotAllowed {
+                asker: prompt.asker,
+            };
+        }
+        let Some(choice) = prompt.choices.iter().find(|c| c.id == choice_id).cloned() else {
+            return ChoiceClick::Expired;
+        };
+        let (prompt, _) = prompts.remove(&message_id).expect("prompt is pending");
+        ChoiceClick::Chosen { prompt, choice }
+    }
+}
+
+/// Add the buttons of `prompt` under `message_id` and keep it open for answers
+///
+/// Nothing happens when the reply was not posted or offers no choices.
+pub async fn offer(
+    http: &Http,
+    channel: ChannelId,
+    message_id: Option<u64>,
+    pending: &PendingChoices,
+    prompt: ChoicePrompt,
+) {
+    let Some(message_id) = message_id else {
+        return;
+    };
+    let rows = buttons(prompt.room_id, &prompt.choices, None, false);
+    if rows.is_empty() {
+        return;
+    }
+    match channel
+        .edit_message(
+            http,
+            MessageId::new(message_id),
+            EditMessage::new().components(rows),
+        )
+        .await
+    {
+        Ok(_) => pending.register(message_id, prompt),
+        Err(e) => warn!(error = %e, "Failed to add choice buttons"),
+    }
+}
+
+/// Reply text assembled from a `/chat/stream` SSE response
+pub async fn collect_reply(mut resp: reqwest::Response) -> String {
+    let mut buffer = String::new();
+    let mut assembled = String::new();
+    while let Ok(Some(chunk)) = resp.chunk().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        let mut lines: Vec<&str> = buffer.split('\n').collect();
+        let tail = lines.pop().unwrap_or("").to_string();
+        for line in lines {
+            let Some(payload) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let Ok(json) = serde_json::from_str::<serde_json::Value>(payload.trim()) else {
+                continue;
+            };
+            if json.get("error").is_some() {
+                continue;
+            }
+            if let Some(text) = json.get("text").and_then(|v| v.as_str()) {
+                assembled.push_str(text);
+            }
+            if json.get("final").and_then(|v| v.as_bool()).unwrap_or(false) {
+                return assembled;
+            }
+        }
+        buffer = tail;
+    }
+    assembled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(asker: u64) -> ChoicePrompt {
+        ChoicePrompt {
+            room_id: Uuid::from_u128(7),
+            room_name: "general".to_string(),
+            asker,
+            character: "Zoey".to_string(),
+            character_name: "Zoey".to_string(),
+            choices: vec![
+                Choice {
+                    id: "a".to_string(),
+                    label: "The 2023 lease".to_string(),
+                },
+                Choice {
+                    id: "b".to_string(),
+                    label: "The 2024 renewal".to_string(),
+                },
+            ],
+        }
+    }
+
+    fn button(choice_id: &str) -> String {
+        custom_id(Uuid::from_u128(7), choice_id).unwrap()
+    }
+
+    #[test]
+    fn test_choices_block_is_parsed_and_stripped() {
+        let reply = "<response><text>Which lease do you mean?\n<choices>\
+            <c id=\"a\">The 2023 lease</c><c id='b'>Renewal &amp; addendum</c>\
+            </choices></text></response>";
+        let (display, choices) = split_choices(reply);
+        assert_eq!(
+            display,
+            "<response><text>Which lease do you mean?</text></response>"
+        );
+        assert_eq!(
+            choices,
+            vec![
+                Choice {
+                    id: "a".to_string(),
+                    label: "The 2023 lease".to_string()
+                },
+                Choice {
+                    id: "b".to_string(),
+                    label: "Renewal & addendum".to_string()
+                },
+            ]
+        );
+
+        // No block: unchanged
+        assert_eq!(split_choices("Hello").0, "Hello");
+        assert!(split_choices("Hello").1.is_empty());
+
+        // Mid-stream the partial block is hidden
+        let (display, choices) = split_choices("Which one?<choices><c id=\"a\">Fi");
+        assert_eq!(display, "Which one?");
+        assert!(choices.is_empty());
+
+        // At most five, no duplicates, no empty labels
+        let many: String = (0..8)
+            .map(|i| format!("<c id=\"{}\">Option {}</c>", i % 7, i))
+            .collect();
+        let (_, choices) = split_choices(&format!("<choices>{}<c id=\"x\"> </c></choices>", many));
+        let ids: Vec<&str> = choices.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["0", "1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_custom_id_fits_discord_limit() {
+        let room = Uuid::new_v4();
+        let id = custom_id(room, "a").unwrap();
+        assert_eq!(parse_custom_id(&id), Some((room, "a")));
+        // Choice IDs may contain the separator
+        let id = custom_id(room, "x:y").unwrap();
+        assert_eq!(parse_custom_id(&id), Some((room, "x:y")));
+
+        let longest = "c".repeat(MAX_CUSTOM_ID_LEN - "choice::".len() - 32);
+        assert_eq!(custom_id(room, &longest).unwrap().len(), MAX_CUSTOM_ID_LEN);
+        assert_eq!(custom_id(room, &format!("{}c", longest)), None);
+        let (_, choices) = split_choices(&format!(
+            "<choices><c id=\"{}c\">Too long</c><c id=\"ok\">Fine</c></choices>",
+            longest
+        ));
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].id, "ok");
+
+        assert_eq!(parse_custom_id("stage:invite"), None);
+        assert_eq!(parse_custom_id("choice:not-a-uuid:a"), None);
+        assert_eq!(
+            button_label(&"x".repeat(200)).chars().count(),
+            MAX_LABEL_LEN
+        );
+    }
+
+    #[test]
+    fn test_only_the_asker_may_answer_unless_open() {
+        let pending = PendingChoices::new(DEFAULT_CHOICE_TTL, false);
+        pending.register(1, prompt(42));
+        assert_eq!(
+            pending.click(1, 99, &button("a")),
+            ChoiceClick::NotAllowed { asker: 42 }
+        );
+        // A refused click leaves the prompt open
+        assert_eq!(
+            pending.click(1, 42, &button("b")),
+            ChoiceClick::Chosen {
+                prompt: prompt(42),
+                choice: prompt(42).choices[1].clone(),
+            }
+        );
+
+        let open = PendingChoices::new(DEFAULT_CHOICE_TTL, true);
+        open.register(1, prompt(42));
+        assert!(matches!(
+            open.click(1, 99, &button("a")),
+            ChoiceClick::Chosen { .. }
+        ));
+    }
+
+    #[test]
+    fn test_expired_prompts() {
+        let pending = PendingChoices::new(Duration::ZERO, false);
+        pending.register(1, prompt(42));
+        assert!(pending.get(1).is_none());
+        assert_eq!(pending.click(1, 42, &button("a")), ChoiceClick::Expired);
+
+        let pending = PendingChoices::default();
+        pending.register(1, prompt(42));
+        // Unknown message, other room, unknown choice
+        assert_eq!(pending.click(2, 42, &button("a")), ChoiceClick::Expired);
+        let other_room = custom_id(Uuid::from_u128(8), "a").unwrap();
+        assert_eq!(pending.click(1, 42, &other_room), ChoiceClick::Expired);
+        assert_eq!(pending.click(1, 42, &button("z")), ChoiceClick::Expired);
+        // Answered once, then closed
+        assert!(matches!(
+            pending.click(1, 42, &button("a")),
+            ChoiceClick::Chosen { .. }
+        ));
+        assert_eq!(pending.click(1, 42, &button("a")), ChoiceClick::Expired);
+    }
+}
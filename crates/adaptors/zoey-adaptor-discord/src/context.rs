@@ -0,0 +1,135 @@
+//! `/context` slash command showing what the bot holds for a channel
+//!
+//! The command asks the agent API's `/state` endpoint for the channel's room
+//! (the same deterministic room ID chat messages use), polls the resulting
+//! task and replies ephemerally with the room's turn count, ingested document
+//! count and the persona answering there.
+
+use serenity::builder::CreateCommand;
+use std::time::Duration;
+use uuid::Uuid;
+use zoey_core::{Result, ZoeyError};
+
+/// Interval between task polls
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Total time to wait for the state task before giving up
+const POLL_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// `/context` slash command definition
+pub fn context_command() -> CreateCommand {
+    CreateCommand::new("context").description("Show what I remember for this channel")
+}
+
+/// Room summary reported by `/context`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomContext {
+    pub turn_count: u64,
+    pub document_count: u64,
+    pub persona: String,
+}
+
+impl RoomContext {
+    /// Read `state.data.roomStats` from a completed state task result
+    pub fn from_task_result(result: &serde_json::Value) -> Option<Self> {
+        let stats = result.get("state")?.get("data")?.get("roomStats")?;
+        Some(Self {
+            turn_count: stats.get("turnCount")?.as_u64()?,
+            document_count: stats.get("documentCount")?.as_u64()?,
+            persona: stats
+                .get("persona")
+                .and_then(|p| p.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    /// Ephemeral reply text
+    pub fn to_reply(&self) -> String {
+        let persona = if self.persona.is_empty() {
+            "default"
+        } else {
+            self.persona.as_str()
+        };
+        format!(
+            "**Channel context**\n• Turns: {}\n• Documents: {}\n• Persona: {}",
+            self.turn_count, self.document_count, persona
+        )
+    }
+}
+
+/// Fetch the room summary for `room_id` from the agent API
+pub async fn fetch_room_context(
+    client: &reqwest::Client,
+    api_base: &str,
+    room_id: Uuid,
+) -> Result<RoomContext> {
+    let submitted: serde_json::Value = client
+        .post(format!("{}/state", api_base))
+        .json(&serde_json::json!({ "roomId": room_id }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| ZoeyError::other(format!("state request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| ZoeyError::other(format!("invalid state response: {}", e)))?;
+    let task_id = submitted
+        .get("taskId")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| ZoeyError::other("state response has no task id"))?;
+
+    let deadline = tokio::time::Instant::now() + POLL_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let task: serde_json::Value = client
+            .get(format!("{}/task/{}", api_base, task_id))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ZoeyError::other(format!("task poll failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| ZoeyError::other(format!("invalid task response: {}", e)))?;
+        match task.get("status").and_then(|s| s.as_str()) {
+            Some("completed") => {
+                return task
+                    .get("result")
+                    .and_then(RoomContext::from_task_result)
+                    .ok_or_else(|| ZoeyError::other("state result has no room stats"));
+            }
+            Some("failed") => {
+                let error = task.get("error").and_then(|e| e.as_str()).unwrap_or("unknown error");
+                return Err(ZoeyError::other(format!("state task failed: {}", error)));
+            }
+            _ => {}
+        }
+    }
+    Err(ZoeyError::other("state task timed out"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_room_context_from_task_result() {
+        let result = serde_json::json!({
+            "success": true,
+            "state": {
+                "values": {},
+                "data": { "roomStats": { "turnCount": 12, "documentCount": 3, "persona": "Zoey" } }
+            }
+        });
+        let ctx = RoomContext::from_task_result(&result).unwrap();
+        assert_eq!(
+            ctx,
+            RoomContext { turn_count: 12, document_count: 3, persona: "Zoey".to_string() }
+        );
+        assert!(ctx.to_reply().contains("Turns: 12"));
+        assert!(ctx.to_reply().contains("Persona: Zoey"));
+
+        let old_backend = serde_json::json!({ "success": true, "state": { "values": {}, "data": {} } });
+        assert!(RoomContext::from_task_result(&old_backend).is_none());
+    }
+}
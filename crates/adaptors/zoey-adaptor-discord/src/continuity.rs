@@ -0,0 +1,678 @@
+//! Carrying a conversation's context into another channel
+//!
+//! Room IDs are derived per channel, so a conversation started in DMs has no
+//! history when the user moves to a server channel. `/continue` links the
+//! current room (the target) to another room (the source):
+//!
+//! - `/continue from` in a server channel continues the user's DM with the bot
+//! - `/continue from channel:#other` continues another channel of the server
+//! - `/continue dm` in a server channel continues that channel in the DM
+//! - `/continue stop` removes the link for the current room
+//!
+//! While a link is active, chat requests from the user who made it in the
+//! target room carry a `context_rooms` metadata array with the source room so
+//! the backend can draw on the memories of both. Other users in the room are
+//! unaffected. A link expires after [`DEFAULT_CONTINUITY_IDLE`] (or the
+//! configured period) without a message from that user in the target room.
+//!
+//! Links are persisted as a component on the user's entity. Linking a DM into
+//! a server channel first asks the user, ephemerally, to confirm that DM
+//! context may show up in replies others can read ([`PendingConfirmations`]).
+
+use crate::prefs::user_entity_id;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ButtonStyle, CommandDataOption, CommandDataOptionValue};
+use serenity::builder::{CreateActionRow, CreateButton, CreateCommand, CreateCommandOption};
+use serenity::model::application::CommandOptionType;
+use serenity::model::channel::ChannelType;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+use uuid::Uuid;
+use zoey_core::types::Component;
+use zoey_core::{IDatabaseAdapter, Result};
+
+/// Component type holding a user's continuity links
+pub const CONTINUITY_COMPONENT_TYPE: &str = "discord_continuity_links";
+
+/// Chat request metadata key the linked source rooms are sent under
+pub const CONTEXT_ROOMS_METADATA_KEY: &str = "context_rooms";
+
+/// Default inactivity after which a link expires
+pub const DEFAULT_CONTINUITY_IDLE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long the DM confirmation buttons can be answered
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Reply to clicks on a confirmation that is no longer pending
+pub const EXPIRED_REPLY: &str = "This confirmation has expired. Run `/continue from` again.";
+
+/// Asked before DM context is linked into a server channel
+pub const CONFIRMATION_PROMPT: &str = "Continue our DM conversation here? Things from our DM \
+may come up in my replies in this channel, where others can read them.";
+
+/// Prefix of the custom IDs of confirmation buttons
+const CUSTOM_ID_PREFIX: &str = "continue";
+
+/// Reply for a `/continue` invocation that doesn't match any subcommand
+const USAGE: &str = "Use `/continue from`, `/continue dm` or `/continue stop`.";
+
+/// A target room drawing on a source room's context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContinuityLink {
+    pub source_room: Uuid,
+    pub target_room: Uuid,
+    /// Whether the source is the user's DM with the bot
+    pub from_dm: bool,
+    /// Last message from the user in the target room (epoch ms)
+    pub last_active: i64,
+}
+
+impl ContinuityLink {
+    fn is_expired(&self, idle: Duration, now_ms: i64) -> bool {
+        now_ms.saturating_sub(self.last_active) >= idle.as_millis() as i64
+    }
+}
+
+/// A user's links as stored in the component
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StoredLinks {
+    #[serde(default)]
+    links: Vec<ContinuityLink>,
+}
+
+/// Whether linking `source` into `target` needs the user's confirmation
+///
+/// Only DM context surfacing in a server channel does: the other direction
+/// only shows the user what they could already read.
+pub fn needs_confirmation(source_is_dm: bool, target_is_dm: bool) -> bool {
+    source_is_dm && !target_is_dm
+}
+
+/// Parsed `/continue` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContinueCommand {
+    /// Continue a channel of this server here, or the DM without one
+    From {
+        channel: Option<u64>,
+    },
+    /// Continue this channel in the user's DM
+    Dm,
+    Stop,
+}
+
+impl ContinueCommand {
+    /// Parse the options of a `/continue` interaction
+    pub fn from_options(options: &[CommandDataOption]) -> std::result::Result<Self, String> {
+        let Some(option) = options.first() else {
+            return Err(USAGE.to_string());
+        };
+        let CommandDataOptionValue::SubCommand(inner) = &option.value else {
+            return Err(USAGE.to_string());
+        };
+        match option.name.as_str() {
+            "from" => {
+                let channel = inner.iter().find_map(|o| match o.value {
+                    CommandDataOptionValue::Channel(id) if o.name == "channel" => Some(id.get()),
+                    _ => None,
+                });
+                Ok(Self::From { channel })
+            }
+            "dm" => Ok(Self::Dm),
+            "stop" => Ok(Self::Stop),
+            _ => Err(USAGE.to_string()),
+        }
+    }
+}
+
+/// `/continue` slash command definition
+pub fn continue_command() -> CreateCommand {
+    CreateCommand::new("continue")
+        .description("Carry a conversation over from another channel")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "from",
+                "Continue our DM here, or another channel of this server",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "channel",
+                    "Channel to continue (leave empty for our DM)",
+                )
+                .channel_types(vec![ChannelType::Text, ChannelType::PublicThread]),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "dm",
+            "Continue this channel's conversation in our DM",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "stop",
+            "Stop using another channel's context here",
+        ))
+}
+
+/// Where links are persisted
+#[async_trait]
+pub trait ContinuityBackend: Send + Sync {
+    async fn load(&self, user_id: u64) -> Result<Vec<ContinuityLink>>;
+    /// Replace the user's links; an empty list removes them
+    async fn save(&self, user_id: u64, links: &[ContinuityLink]) -> Result<()>;
+}
+
+/// Persists links as a component on the user's entity
+pub struct ComponentContinuity {
+    adapter: Arc<dyn IDatabaseAdapter + Send + Sync>,
+}
+
+impl ComponentContinuity {
+    pub fn new(adapter: Arc<dyn IDatabaseAdapter + Send + Sync>) -> Self {
+        Self { adapter }
+    }
+
+    async fn find(&self, user_id: u64) -> Result<Option<Component>> {
+        let entity_id = user_entity_id(user_id);
+        self.adapter
+            .get_component(entity_id, CONTINUITY_COMPONENT_TYPE, Some(entity_id), None)
+            .await
+    }
+}
+
+#[async_trait]
+impl ContinuityBackend for ComponentContinuity {
+    async fn load(&self, user_id: u64) -> Result<Vec<ContinuityLink>> {
+        match self.find(user_id).await? {
+            Some(component) => Ok(serde_json::from_value::<StoredLinks>(component.data)?.links),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn save(&self, user_id: u64, links: &[ContinuityLink]) -> Result<()> {
+        let existing = self.find(user_id).await?;
+        if links.is_empty() {
+            return match existing {
+                Some(existing) => self.adapter.delete_component(existing.id).await,
+                None => Ok(()),
+            };
+        }
+        let data = serde_json::to_value(StoredLinks {
+            links: links.to_vec(),
+        })?;
+        let now = chrono::Utc::now().timestamp();
+        match existing {
+            Some(mut existing) => {
+                existing.data = data;
+                existing.updated_at = Some(now);
+                self.adapter.update_component(&existing).await
+            }
+            None => {
+                let entity_id = user_entity_id(user_id);
+                let component = Component {
+                    id: Uuid::new_v4(),
+                    entity_id,
+                    world_id: entity_id,
+                    source_entity_id: None,
+                    component_type: CONTINUITY_COMPONENT_TYPE.to_string(),
+                    data,
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                };
+                self.adapter.create_component(&component).await.map(|_| ())
+            }
+        }
+    }
+}
+
+/// Continuity links by Discord user ID, cached in front of the backend
+pub struct ContinuityLinks {
+    idle: Duration,
+    cache: RwLock<HashMap<u64, Vec<ContinuityLink>>>,
+    backend: Option<Arc<dyn ContinuityBackend>>,
+}
+
+impl ContinuityLinks {
+    pub fn new(idle: Duration, backend: Option<Arc<dyn ContinuityBackend>>) -> Self {
+        Self {
+            idle,
+            cache: RwLock::new(HashMap::new()),
+            backend,
+        }
+    }
+
+    /// Links backed by the runtime's database adapter, or memory only without one
+    pub fn from_adapter(
+        idle: Duration,
+        adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
+    ) -> Self {
+        Self::new(
+            idle,
+            adapter.map(|a| Arc::new(ComponentContinuity::new(a)) as Arc<dyn ContinuityBackend>),
+        )
+    }
+
+    /// A user's links, loaded from the backend on first use
+    ///
+    /// Load failures are logged and treated as no links so chat is never blocked.
+    async fn links(&self, user_id: u64) -> Vec<ContinuityLink> {
+        if let Some(cached) = self.cache.read().unwrap().get(&user_id) {
+            return cached.clone();
+        }
+        let loaded = match &self.backend {
+            Some(backend) => match backend.load(user_id).await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    warn!(user_id = %user_id, error = %e, "Failed to load continuity links");
+                    return Vec::new();
+                }
+            },
+            None => Vec::new(),
+        };
+        self.cache.write().unwrap().insert(user_id, loaded.clone());
+        loaded
+    }
+
+    /// Cache and persist a user's links, dropping expired ones
+    async fn store(&self, user_id: u64, mut links: Vec<ContinuityLink>, now_ms: i64) -> Result<()> {
+        links.retain(|l| !l.is_expired(self.idle, now_ms));
+        self.cache.write().unwrap().insert(user_id, links.clone());
+        match &self.backend {
+            Some(backend) => backend.save(user_id, &links).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Link `target_room` to `source_room` for `user_id`, replacing any link of that target
+    pub async fn link(
+        &self,
+        user_id: u64,
+        source_room: Uuid,
+        target_room: Uuid,
+        from_dm: bool,
+        now_ms: i64,
+    ) -> String {
+        if source_room == target_room {
+            return "This channel already has its own context.".to_string();
+        }
+        let mut links = self.links(user_id).await;
+        links.retain(|l| l.target_room != target_room);
+        links.push(ContinuityLink {
+            source_room,
+            target_room,
+            from_dm,
+            last_active: now_ms,
+        });
+        let reply = if from_dm {
+            "Picking up where we left off in our DM. Use `/continue stop` to end it.".to_string()
+        } else {
+            "Picking up that conversation here. Use `/continue stop` to end it.".to_string()
+        };
+        self.reply_after_store(user_id, links, now_ms, reply).await
+    }
+
+    /// Remove the user's link for `target_room`
+    pub async fn stop(&self, user_id: u64, target_room: Uuid, now_ms: i64) -> String {
+        let mut links = self.links(user_id).await;
+        let before = links.len();
+        links.retain(|l| l.target_room != target_room);
+        if links.len() == before {
+            return "Nothing is linked here.".to_string();
+        }
+        self.reply_after_store(
+            user_id,
+            links,
+            now_ms,
+            "Stopped - this channel has its own context again.".to_string(),
+        )
+        .await
+    }
+
+    async fn reply_after_store(
+        &self,
+        user_id: u64,
+        links: Vec<ContinuityLink>,
+        now_ms: i64,
+        reply: String,
+    ) -> String {
+        match self.store(user_id, links, now_ms).await {
+            Ok(()) => reply,
+            Err(e) => {
+                warn!(user_id = %user_id, error = %e, "Failed to persist continuity links");
+                format!("{} (not persisted: {})", reply, e)
+            }
+        }
+    }
+
+    /// Source room linked into `target_room` for `user_id`, if the link is active
+    ///
+    /// An active link's inactivity timer restarts; an expired one is removed.
+    pub async fn active_source(
+        &self,
+        user_id: u64,
+        target_room: Uuid,
+        now_ms: i64,
+    ) -> Option<Uuid> {
+        let mut links = self.links(user_id).await;
+        let index = links.iter().position(|l| l.target_room == target_room)?;
+        let source = if links[index].is_expired(self.idle, now_ms) {
+            None
+        } else {
+            links[index].last_active = now_ms;
+            Some(links[index].source_room)
+        };
+        if let Err(e) = self.store(user_id, links, now_ms).await {
+            warn!(user_id = %user_id, error = %e, "Failed to persist continuity links");
+        }
+        source
+    }
+
+    /// Add the linked source room of `target_room` to chat request metadata
+    ///
+    /// Nothing is added unless `user_id` linked the room themselves.
+    pub async fn annotate(
+        &self,
+        user_id: u64,
+        target_room: Uuid,
+        metadata: &mut serde_json::Map<String, serde_json::Value>,
+    ) {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        if let Some(source) = self.active_source(user_id, target_room, now_ms).await {
+            insert_context_rooms(metadata, &[source]);
+        }
+    }
+}
+
+/// Set the `context_rooms` metadata array
+pub fn insert_context_rooms(
+    metadata: &mut serde_json::Map<String, serde_json::Value>,
+    rooms: &[Uuid],
+) {
+    if rooms.is_empty() {
+        return;
+    }
+    let rooms: Vec<String> = rooms.iter().map(Uuid::to_string).collect();
+    metadata.insert(
+        CONTEXT_ROOMS_METADATA_KEY.to_string(),
+        serde_json::json!(rooms),
+    );
+}
+
+/// Custom ID of a confirmation button
+fn custom_id(nonce: u64, confirm: bool) -> String {
+    let answer = if confirm { "yes" } else { "no" };
+    format!("{}:{}:{}", CUSTOM_ID_PREFIX, nonce, answer)
+}
+
+/// Nonce and answer of a confirmation button's custom ID
+pub fn parse_custom_id(custom_id: &str) -> Option<(u64, bool)> {
+    let rest = custom_id
+        .strip_prefix(CUSTOM_ID_PREFIX)?
+        .strip_prefix(':')?;
+    let (nonce, answer) = rest.split_once(':')?;
+    let confirm = match answer {
+        "yes" => true,
+        "no" => false,
+        _ => return None,
+    };
+    Some((nonce.parse().ok()?, confirm))
+}
+
+/// Confirm and cancel buttons for the pending link `nonce`
+pub fn confirmation_buttons(nonce: u64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(custom_id(nonce, true))
+            .label("Continue here")
+            .style(ButtonStyle::Primary),
+        CreateButton::new(custom_id(nonce, false))
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ])]
+}
+
+/// Outcome of a confirmation button click
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Create the link
+    Confirmed {
+        source_room: Uuid,
+        target_room: Uuid,
+    },
+    Cancelled,
+    /// Clicked by someone other than the user who ran `/continue`
+    NotAllowed,
+    Expired,
+}
+
+struct PendingLink {
+    user_id: u64,
+    source_room: Uuid,
+    target_room: Uuid,
+    asked_at: Instant,
+}
+
+/// DM links waiting for the user's confirmation, by nonce
+pub struct PendingConfirmations {
+    ttl: Duration,
+    pending: Mutex<HashMap<u64, PendingLink>>,
+}
+
+impl PendingConfirmations {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hold a DM link for confirmation under `nonce` (the command interaction's ID)
+    pub fn request(&self, nonce: u64, user_id: u64, source_room: Uuid, target_room: Uuid) {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| now.duration_since(p.asked_at) < self.ttl);
+        pending.insert(
+            nonce,
+            PendingLink {
+                user_id,
+                source_room,
+                target_room,
+                asked_at: now,
+            },
+        );
+    }
+
+    /// Resolve a click on a confirmation button; `None` for other components
+    pub fn resolve(&self, custom_id: &str, user_id: u64) -> Option<Confirmation> {
+        let (nonce, confirm) = parse_custom_id(custom_id)?;
+        let mut pending = self.pending.lock().unwrap();
+        let Some(link) = pending.get(&nonce) else {
+            return Some(Confirmation::Expired);
+        };
+        if link.user_id != user_id {
+            return Some(Confirmation::NotAllowed);
+        }
+        let link = pending.remove(&nonce)?;
+        if link.asked_at.elapsed() >= self.ttl {
+            return Some(Confirmation::Expired);
+        }
+        Some(if confirm {
+            Confirmation::Confirmed {
+                source_room: link.source_room,
+                target_room: link.target_room,
+            }
+        } else {
+            Confirmation::Cancelled
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+
+    /// Backend keeping the serialized component data, like the database would
+    #[derive(Default)]
+    struct MemoryBackend {
+        rows: Mutex<HashMap<u64, serde_json::Value>>,
+    }
+
+    #[async_trait]
+    impl ContinuityBackend for MemoryBackend {
+        async fn load(&self, user_id: u64) -> Result<Vec<ContinuityLink>> {
+            match self.rows.lock().unwrap().get(&user_id) {
+                Some(data) => Ok(serde_json::from_value::<StoredLinks>(data.clone())?.links),
+                None => Ok(Vec::new()),
+            }
+        }
+
+        async fn save(&self, user_id: u64, links: &[ContinuityLink]) -> Result<()> {
+            let mut rows = self.rows.lock().unwrap();
+            if links.is_empty() {
+                rows.remove(&user_id);
+            } else {
+                let stored = StoredLinks {
+                    links: links.to_vec(),
+                };
+                rows.insert(user_id, serde_json::to_value(stored)?);
+            }
+            Ok(())
+        }
+    }
+
+    fn rooms() -> (Uuid, Uuid) {
+        (Uuid::new_v4(), Uuid::new_v4())
+    }
+
+    #[tokio::test]
+    async fn test_link_persists_and_expires_after_inactivity() {
+        let backend = Arc::new(MemoryBackend::default());
+        let idle = Duration::from_secs(2 * 60 * 60);
+        let store = ContinuityLinks::new(idle, Some(backend.clone()));
+        let (dm, channel) = rooms();
+        store.link(7, dm, channel, true, 0).await;
+
+        // A fresh store (e.g. after a restart) reads the link back
+        let restarted = ContinuityLinks::new(idle, Some(backend.clone()));
+        assert_eq!(restarted.active_source(7, channel, HOUR_MS).await, Some(dm));
+        // Activity restarts the timer
+        assert_eq!(
+            restarted.active_source(7, channel, 2 * HOUR_MS + 1).await,
+            Some(dm)
+        );
+        assert_eq!(
+            restarted.active_source(7, channel, 4 * HOUR_MS + 2).await,
+            None
+        );
+        assert!(backend.rows.lock().unwrap().is_empty());
+
+        store.link(7, dm, channel, true, 0).await;
+        assert_eq!(
+            store.stop(7, channel, 0).await,
+            "Stopped - this channel has its own context again."
+        );
+        assert_eq!(store.active_source(7, channel, 0).await, None);
+        assert_eq!(store.stop(7, channel, 0).await, "Nothing is linked here.");
+    }
+
+    #[tokio::test]
+    async fn test_context_rooms_metadata() {
+        let store = ContinuityLinks::new(DEFAULT_CONTINUITY_IDLE, None);
+        let (dm, channel) = rooms();
+        let mut before = serde_json::Map::new();
+        store.annotate(7, channel, &mut before).await;
+        assert!(before.is_empty());
+
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        store.link(7, dm, channel, true, now_ms).await;
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("ingested_urls".into(), serde_json::json!([]));
+        store.annotate(7, channel, &mut metadata).await;
+        assert_eq!(
+            metadata[CONTEXT_ROOMS_METADATA_KEY],
+            serde_json::json!([dm.to_string()])
+        );
+        assert!(metadata.contains_key("ingested_urls"));
+
+        // Linking a room to itself adds nothing
+        assert_eq!(
+            store.link(7, channel, channel, false, now_ms).await,
+            "This channel already has its own context."
+        );
+        assert_eq!(store.active_source(7, channel, now_ms).await, Some(dm));
+    }
+
+    #[tokio::test]
+    async fn test_link_is_scoped_to_its_user() {
+        let store = ContinuityLinks::new(DEFAULT_CONTINUITY_IDLE, None);
+        let (dm, channel) = rooms();
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        store.link(7, dm, channel, true, now_ms).await;
+
+        let mut other = serde_json::Map::new();
+        store.annotate(8, channel, &mut other).await;
+        assert!(other.is_empty());
+        assert_eq!(
+            store.stop(8, channel, now_ms).await,
+            "Nothing is linked here."
+        );
+        assert_eq!(store.active_source(7, channel, now_ms).await, Some(dm));
+
+        // A second target of the same user keeps the first link
+        let (other_source, other_target) = rooms();
+        store
+            .link(7, other_source, other_target, false, now_ms)
+            .await;
+        assert_eq!(store.active_source(7, channel, now_ms).await, Some(dm));
+        assert_eq!(
+            store.active_source(7, other_target, now_ms).await,
+            Some(other_source)
+        );
+    }
+
+    #[test]
+    fn test_dm_into_channel_needs_confirmation() {
+        assert!(needs_confirmation(true, false));
+        assert!(!needs_confirmation(false, true));
+        assert!(!needs_confirmation(false, false));
+
+        let pending = PendingConfirmations::new(CONFIRMATION_TTL);
+        let (dm, channel) = rooms();
+        pending.request(42, 7, dm, channel);
+        assert_eq!(pending.resolve("choice:abc:a", 7), None);
+        assert_eq!(
+            pending.resolve(&custom_id(42, true), 8),
+            Some(Confirmation::NotAllowed)
+        );
+        assert_eq!(
+            pending.resolve(&custom_id(42, true), 7),
+            Some(Confirmation::Confirmed {
+                source_room: dm,
+                target_room: channel
+            })
+        );
+        // Answered once only
+        assert_eq!(
+            pending.resolve(&custom_id(42, true), 7),
+            Some(Confirmation::Expired)
+        );
+
+        pending.request(43, 7, dm, channel);
+        assert_eq!(
+            pending.resolve(&custom_id(43, false), 7),
+            Some(Confirmation::Cancelled)
+        );
+
+        let expired = PendingConfirmations::new(Duration::ZERO);
+        expired.request(44, 7, dm, channel);
+        assert_eq!(
+            expired.resolve(&custom_id(44, true), 7),
+            Some(Confirmation::Expired)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! `/reset` starting a fresh conversation in a channel
+//!
+//! Room IDs are derived from the guild and channel, so without this a
+//! channel's history only ever grows. `/reset` (or `!reset`) moves the
+//! channel, or thread, to its next context epoch: epochs after 0 are appended
+//! to the room ID seed (see [`room_uuid_at`](crate::characters::room_uuid_at)),
+//! so later messages go to a new, empty room. Earlier rooms are left as they
+//! are for admins to query.
+//!
+//! Resetting takes Manage Messages in a server; anyone can reset a DM.
+//! Epochs are persisted per guild as a component on the guild's world, like
+//! channel characters.
+
+use crate::characters::{find_guild_component, save_guild_component};
+use serenity::builder::{CreateCommand, CreateEmbed, CreateEmbedFooter};
+use serenity::model::Permissions;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+use zoey_core::{IDatabaseAdapter, Result};
+
+/// Component type holding a guild's channel -> epoch counters
+pub const CONTEXT_EPOCHS_COMPONENT_TYPE: &str = "discord_context_epochs";
+
+/// Name of the `/reset` slash command
+pub const RESET_COMMAND: &str = "reset";
+
+/// Text alias for `/reset`
+pub const RESET_TEXT_COMMAND: &str = "!reset";
+
+/// Embed colour of the reset confirmation
+const RESET_COLOUR: u32 = 0x5865F2;
+
+/// `/reset` slash command definition
+pub fn reset_command() -> CreateCommand {
+    CreateCommand::new(RESET_COMMAND)
+        .description("Start a fresh conversation in this channel")
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .dm_permission(true)
+}
+
+/// Whether a message is the `!reset` text command
+pub fn is_reset_text(text: &str) -> bool {
+    text.trim().eq_ignore_ascii_case(RESET_TEXT_COMMAND)
+}
+
+/// Whether someone with `permissions` in the channel may reset it (DMs use guild 0)
+pub fn can_reset(guild_id: u64, permissions: Option<Permissions>) -> bool {
+    guild_id == 0 || permissions.is_some_and(|p| p.manage_messages())
+}
+
+/// Epochs before and after a reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochChange {
+    pub previous: u64,
+    pub current: u64,
+}
+
+impl EpochChange {
+    /// Confirmation embed; `previous_room` is where the earlier conversation stays
+    pub fn embed(&self, previous_room: Uuid) -> CreateEmbed {
+        CreateEmbed::new()
+            .title("Conversation reset")
+            .description(
+                "I'll start fresh from here. Earlier messages in this channel won't be brought up.",
+            )
+            .field("Previous epoch", self.previous.to_string(), true)
+            .field("New epoch", self.current.to_string(), true)
+            .footer(CreateEmbedFooter::new(format!(
+                "Previous room: {}",
+                previous_room
+            )))
+            .colour(RESET_COLOUR)
+    }
+}
+
+/// Context epoch of every channel, with per-guild persistence
+pub struct ContextEpochs {
+    /// guild_id -> (channel_id -> epoch); channels never reset are absent
+    epochs: RwLock<HashMap<u64, HashMap<u64, u64>>>,
+    adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
+}
+
+impl ContextEpochs {
+    pub fn new(adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>) -> Self {
+        Self {
+            epochs: RwLock::new(HashMap::new()),
+            adapter,
+        }
+    }
+
+    /// Current epoch of a channel, 0 until it is first reset
+    pub fn current(&self, guild_id: u64, channel_id: u64) -> u64 {
+        self.epochs
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .and_then(|m| m.get(&channel_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Load persisted epochs for a guild
+    pub async fn load_guild(&self, guild_id: u64) -> Result<()> {
+        let Some(adapter) = &self.adapter else {
+            return Ok(());
+        };
+        let component_type = CONTEXT_EPOCHS_COMPONENT_TYPE;
+        if let Some(component) =
+            find_guild_component(adapter.as_ref(), guild_id, component_type).await?
+        {
+            self.restore(guild_id, component.data)?;
+        }
+        Ok(())
+    }
+
+    /// Move a channel to its next epoch and persist it
+    ///
+    /// The new epoch applies right away, even when persisting fails.
+    pub async fn reset(&self, guild_id: u64, channel_id: u64) -> Result<EpochChange> {
+        let change = {
+            let mut epochs = self.epochs.write().unwrap();
+            let epoch = epochs
+                .entry(guild_id)
+                .or_default()
+                .entry(channel_id)
+                .or_default();
+            let change = EpochChange {
+                previous: *epoch,
+                current: *epoch + 1,
+            };
+            *epoch = change.current;
+            change
+        };
+        if let Some(adapter) = &self.adapter {
+            let data = self.snapshot(guild_id)?;
+            save_guild_component(
+                adapter.as_ref(),
+                guild_id,
+                CONTEXT_EPOCHS_COMPONENT_TYPE,
+                data,
+            )
+            .await?;
+        }
+        Ok(change)
+    }
+
+    /// Replace a guild's epochs with persisted component data
+    fn restore(&self, guild_id: u64, data: serde_json::Value) -> Result<()> {
+        let stored: HashMap<String, u64> = serde_json::from_value(data)?;
+        let parsed = stored
+            .into_iter()
+            .filter_map(|(cid, epoch)| cid.parse::<u64>().ok().map(|cid| (cid, epoch)))
+            .collect();
+        self.epochs.write().unwrap().insert(guild_id, parsed);
+        Ok(())
+    }
+
+    /// Component data for a guild's epochs
+    fn snapshot(&self, guild_id: u64) -> Result<serde_json::Value> {
+        let stored: HashMap<String, u64> = self
+            .epochs
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .into_iter()
+            .flatten()
+            .map(|(cid, epoch)| (cid.to_string(), *epoch))
+            .collect();
+        Ok(serde_json::to_value(stored)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::characters::room_uuid_at;
+
+    #[tokio::test]
+    async fn test_reset_advances_only_that_channel() {
+        let epochs = ContextEpochs::new(None);
+        assert_eq!(epochs.current(1, 10), 0);
+
+        let change = epochs.reset(1, 10).await.unwrap();
+        assert_eq!(
+            change,
+            EpochChange {
+                previous: 0,
+                current: 1
+            }
+        );
+        assert_eq!(epochs.reset(1, 10).await.unwrap().current, 2);
+        assert_eq!(epochs.current(1, 10), 2);
+        assert_eq!(epochs.current(1, 20), 0);
+        assert_eq!(epochs.current(2, 10), 0);
+    }
+
+    #[tokio::test]
+    async fn test_epochs_survive_a_restart() {
+        let epochs = ContextEpochs::new(None);
+        epochs.reset(1, 10).await.unwrap();
+        epochs.reset(1, 10).await.unwrap();
+        epochs.reset(1, 20).await.unwrap();
+        let data = epochs.snapshot(1).unwrap();
+        assert_eq!(data, serde_json::json!({ "10": 2, "20": 1 }));
+
+        let restarted = ContextEpochs::new(None);
+        restarted.restore(1, data).unwrap();
+        assert_eq!(restarted.current(1, 10), 2);
+        assert_eq!(restarted.current(1, 20), 1);
+        assert_eq!(restarted.current(1, 30), 0);
+        assert!(restarted.restore(1, serde_json::json!("garbage")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_room_stable_until_reset() {
+        let epochs = ContextEpochs::new(None);
+        let room = |e: &ContextEpochs| room_uuid_at(1, 10, None, e.current(1, 10));
+        let before = room(&epochs);
+        assert_eq!(before, zoey_core::string_to_uuid("discord-room-1-10"));
+        assert_eq!(room(&epochs), before);
+
+        epochs.reset(1, 10).await.unwrap();
+        let after = room(&epochs);
+        assert_ne!(after, before);
+        assert_eq!(room(&epochs), after);
+        // Other channels keep their room
+        assert_eq!(
+            room_uuid_at(1, 20, None, epochs.current(1, 20)),
+            room_uuid_at(1, 20, None, 0)
+        );
+    }
+
+    #[test]
+    fn test_reset_command_and_permissions() {
+        assert!(is_reset_text("!reset"));
+        assert!(is_reset_text("  !RESET "));
+        assert!(!is_reset_text("!reset now"));
+        assert!(!is_reset_text("please !reset"));
+
+        assert!(can_reset(0, None));
+        assert!(can_reset(
+            1,
+            Some(Permissions::MANAGE_MESSAGES | Permissions::SEND_MESSAGES)
+        ));
+        assert!(!can_reset(1, Some(Permissions::SEND_MESSAGES)));
+        assert!(!can_reset(1, None));
+    }
+
+    #[test]
+    fn test_reset_embed_shows_both_epochs() {
+        let room = room_uuid_at(1, 10, None, 0);
+        let embed = EpochChange {
+            previous: 0,
+            current: 1,
+        }
+        .embed(room);
+        let json = serde_json::to_value(&embed).unwrap();
+        assert_eq!(json["fields"][0]["value"], "0");
+        assert_eq!(json["fields"][1]["value"], "1");
+        assert!(json["footer"]["text"]
+            .as_str()
+            .unwrap()
+            .contains(&room.to_string()));
+    }
+}
@@ -0,0 +1,599 @@
+//! Where a reply goes when the bot can't post in the triggering channel
+//!
+//! Read-only channels (announcements, rules) still deliver mentions, but every
+//! send there fails with Missing Access or Missing Permissions. When posting
+//! the placeholder fails that way, the reply is routed instead to:
+//!
+//! 1. the guild's configured fallback channel, after a note mentioning the user;
+//! 2. the user's DMs, after a note naming the original channel;
+//! 3. nowhere: a [`BLOCKED_REACTION`] on the user's message is the only signal.
+//!
+//! The channel is then skipped for [`DEFAULT_BLOCKED_CHANNEL_TTL`], so later
+//! messages go straight to the fallback instead of failing again.
+
+use async_trait::async_trait;
+use serenity::http::{Http, HttpError};
+use serenity::model::channel::ReactionType;
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use serenity::model::ModelError;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::placeholder::DEFAULT_PLACEHOLDER;
+
+/// Discord JSON error code: the bot cannot see the channel
+pub const MISSING_ACCESS: isize = 50001;
+
+/// Discord JSON error code: the bot lacks a permission (e.g. Send Messages)
+pub const MISSING_PERMISSIONS: isize = 50013;
+
+/// Reaction left on the user's message when the reply could not be posted anywhere
+pub const BLOCKED_REACTION: &str = "🚫";
+
+/// How long a channel that refused a message is skipped
+pub const DEFAULT_BLOCKED_CHANNEL_TTL: Duration = Duration::from_secs(600);
+
+/// Whether a Discord JSON error code means the bot may not post there
+pub fn is_permission_code(code: isize) -> bool {
+    matches!(code, MISSING_ACCESS | MISSING_PERMISSIONS)
+}
+
+/// Whether a serenity error is a permission failure rather than a transient one
+pub fn is_permission_error(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            is_permission_code(response.error.code) || response.status_code.as_u16() == 403
+        }
+        serenity::Error::Model(ModelError::InvalidPermissions { .. }) => true,
+        _ => false,
+    }
+}
+
+/// A failed send, with whether the bot is not allowed to post there
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendError {
+    pub message: String,
+    pub forbidden: bool,
+}
+
+impl SendError {
+    /// A failure other than a missing permission
+    pub fn other(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            forbidden: false,
+        }
+    }
+
+    /// A missing-permission failure
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            forbidden: true,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<serenity::Error> for SendError {
+    fn from(error: serenity::Error) -> Self {
+        Self {
+            forbidden: is_permission_error(&error),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The Discord calls a fallback needs
+#[async_trait]
+pub trait ReplyRoutes: Send + Sync {
+    /// Post a message in a channel, returning its ID
+    async fn send(&self, channel_id: u64, content: &str) -> Result<u64, SendError>;
+    /// Open (or reuse) the DM channel with a user
+    async fn dm_channel(&self, user_id: u64) -> Result<u64, SendError>;
+    async fn react(&self, channel_id: u64, message_id: u64, emoji: &str) -> Result<(), SendError>;
+}
+
+/// [`ReplyRoutes`] over the Discord REST API
+pub struct DiscordRoutes<'a> {
+    pub http: &'a Http,
+}
+
+#[async_trait]
+impl ReplyRoutes for DiscordRoutes<'_> {
+    async fn send(&self, channel_id: u64, content: &str) -> Result<u64, SendError> {
+        Ok(ChannelId::new(channel_id)
+            .say(self.http, content)
+            .await?
+            .id
+            .get())
+    }
+
+    async fn dm_channel(&self, user_id: u64) -> Result<u64, SendError> {
+        Ok(UserId::new(user_id)
+            .create_dm_channel(self.http)
+            .await?
+            .id
+            .get())
+    }
+
+    async fn react(&self, channel_id: u64, message_id: u64, emoji: &str) -> Result<(), SendError> {
+        ChannelId::new(channel_id)
+            .create_reaction(
+                self.http,
+                MessageId::new(message_id),
+                ReactionType::Unicode(emoji.to_string()),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// The message a reply answers
+#[derive(Debug, Clone, Copy)]
+pub struct Origin {
+    /// 0 for DMs
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub message_id: u64,
+}
+
+/// Where the reply ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// The channel the message was sent in
+    Origin,
+    /// The guild's configured fallback channel
+    GuildChannel,
+    /// The user's DMs
+    Dm,
+    /// Only a reaction on the user's message
+    Reaction,
+    /// Not even the reaction went through
+    Unreachable,
+}
+
+/// Channel and placeholder the streamed reply goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub route: Route,
+    /// `None` when the reply can't be posted anywhere
+    pub channel_id: Option<u64>,
+    /// `None` when no placeholder was posted; the reply is then sent fresh
+    pub placeholder: Option<u64>,
+}
+
+impl Placement {
+    /// Reply in the origin channel without a placeholder
+    pub fn origin(channel_id: u64) -> Self {
+        Self {
+            route: Route::Origin,
+            channel_id: Some(channel_id),
+            placeholder: None,
+        }
+    }
+}
+
+/// Per-guild fallback channels and channels recently found read-only
+pub struct ReplyFallback {
+    /// Guild ID -> channel ID
+    channels: HashMap<u64, u64>,
+    ttl: Duration,
+    blocked: Mutex<HashMap<u64, Instant>>,
+}
+
+impl ReplyFallback {
+    pub fn new(channels: HashMap<u64, u64>, ttl: Duration) -> Self {
+        Self {
+            channels,
+            ttl,
+            blocked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether posting in `channel_id` failed for lack of permission within the TTL
+    pub fn is_blocked(&self, channel_id: u64, now: Instant) -> bool {
+        self.blocked
+            .lock()
+            .unwrap()
+            .get(&channel_id)
+            .is_some_and(|at| now.saturating_duration_since(*at) < self.ttl)
+    }
+
+    fn block(&self, channel_id: u64, now: Instant) {
+        let mut blocked = self.blocked.lock().unwrap();
+        blocked.retain(|_, at| now.saturating_duration_since(*at) < self.ttl);
+        blocked.insert(channel_id, now);
+    }
+
+    /// Post the placeholder in the origin channel, or wherever the fallback leads
+    pub async fn place(
+        &self,
+        routes: &dyn ReplyRoutes,
+        origin: &Origin,
+        placeholder: &str,
+    ) -> Placement {
+        let placement = self.try_place(routes, origin, placeholder).await;
+        if placement.route != Route::Origin {
+            info!(
+                channel_id = %origin.channel_id,
+                route = ?placement.route,
+                reply_channel = ?placement.channel_id,
+                "Bot can't post in the channel, reply rerouted"
+            );
+        }
+        placement
+    }
+
+    async fn try_place(
+        &self,
+        routes: &dyn ReplyRoutes,
+        origin: &Origin,
+        placeholder: &str,
+    ) -> Placement {
+        if !self.is_blocked(origin.channel_id, Instant::now()) {
+            match post_placeholder(routes, origin.channel_id, placeholder).await {
+                Ok(id) => {
+                    return Placement {
+                        placeholder: Some(id),
+                        ..Placement::origin(origin.channel_id)
+                    }
+                }
+                Err(e) if e.forbidden => {
+                    warn!(channel_id = %origin.channel_id, error = %e, "Missing permission to post, trying the fallbacks");
+                    self.block(origin.channel_id, Instant::now());
+                }
+                Err(e) => {
+                    warn!(error = %e, "Could not post placeholder, the reply will be sent fresh");
+                    return Placement::origin(origin.channel_id);
+                }
+            }
+        }
+
+        // In a DM there is no other place to answer
+        if origin.guild_id != 0 {
+            let note = format!(
+                "<@{}> I can't post in <#{}>, so I'm answering here.",
+                origin.user_id, origin.channel_id
+            );
+            let fallback = self
+                .channels
+                .get(&origin.guild_id)
+                .copied()
+                .filter(|&id| id != origin.channel_id);
+            if let Some(channel_id) = fallback {
+                if let Some(placement) = self
+                    .place_after_note(routes, channel_id, &note, placeholder, Route::GuildChannel)
+                    .await
+                {
+                    return placement;
+                }
+            }
+
+            let note = format!(
+                "I can't post in <#{}>, so I'm answering here.",
+                origin.channel_id
+            );
+            match routes.dm_channel(origin.user_id).await {
+                Ok(channel_id) => {
+                    if let Some(placement) = self
+                        .place_after_note(routes, channel_id, &note, placeholder, Route::Dm)
+                        .await
+                    {
+                        return placement;
+                    }
+                }
+                Err(e) => {
+                    warn!(user_id = %origin.user_id, error = %e, "Could not open a DM for the reply")
+                }
+            }
+        }
+
+        let route = match routes
+            .react(origin.channel_id, origin.message_id, BLOCKED_REACTION)
+            .await
+        {
+            Ok(()) => Route::Reaction,
+            Err(e) => {
+                warn!(channel_id = %origin.channel_id, error = %e, "Could not react to the message either");
+                Route::Unreachable
+            }
+        };
+        Placement {
+            route,
+            channel_id: None,
+            placeholder: None,
+        }
+    }
+
+    /// Post `note` then the placeholder in `channel_id`; `None` if the note can't be posted
+    async fn place_after_note(
+        &self,
+        routes: &dyn ReplyRoutes,
+        channel_id: u64,
+        note: &str,
+        placeholder: &str,
+        route: Route,
+    ) -> Option<Placement> {
+        if self.is_blocked(channel_id, Instant::now()) {
+            return None;
+        }
+        if let Err(e) = routes.send(channel_id, note).await {
+            warn!(channel_id = %channel_id, route = ?route, error = %e, "Fallback channel refused the reply");
+            if e.forbidden {
+                self.block(channel_id, Instant::now());
+            }
+            return None;
+        }
+        let placeholder = post_placeholder(routes, channel_id, placeholder).await.ok();
+        Some(Placement {
+            route,
+            channel_id: Some(channel_id),
+            placeholder,
+        })
+    }
+}
+
+/// Post the placeholder, retrying with [`DEFAULT_PLACEHOLDER`] unless the channel is forbidden
+async fn post_placeholder(
+    routes: &dyn ReplyRoutes,
+    channel_id: u64,
+    content: &str,
+) -> Result<u64, SendError> {
+    let content = content.trim();
+    if !content.is_empty() && content != DEFAULT_PLACEHOLDER {
+        match routes.send(channel_id, content).await {
+            Err(e) if !e.forbidden => warn!(error = %e, "Placeholder rejected, using the default"),
+            result => return result,
+        }
+    }
+    routes.send(channel_id, DEFAULT_PLACEHOLDER).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: u64 = 1;
+    const ANNOUNCEMENTS: u64 = 10;
+    const GENERAL: u64 = 11;
+    const DM: u64 = 99;
+    const USER: u64 = 5;
+    const MESSAGE: u64 = 42;
+
+    /// Records calls; sends to `forbidden` channels fail with a permission error
+    #[derive(Default)]
+    struct FakeRoutes {
+        forbidden: Vec<u64>,
+        fail_sends: bool,
+        no_dm: bool,
+        no_reactions: bool,
+        calls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ReplyRoutes for FakeRoutes {
+        async fn send(&self, channel_id: u64, content: &str) -> Result<u64, SendError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("send {} {}", channel_id, content));
+            if self.forbidden.contains(&channel_id) {
+                Err(SendError::forbidden("Missing Permissions"))
+            } else if self.fail_sends {
+                Err(SendError::other("timed out"))
+            } else {
+                Ok(channel_id * 100)
+            }
+        }
+
+        async fn dm_channel(&self, user_id: u64) -> Result<u64, SendError> {
+            self.calls.lock().unwrap().push(format!("dm {}", user_id));
+            if self.no_dm {
+                Err(SendError::other("Cannot send messages to this user"))
+            } else {
+                Ok(DM)
+            }
+        }
+
+        async fn react(
+            &self,
+            channel_id: u64,
+            message_id: u64,
+            emoji: &str,
+        ) -> Result<(), SendError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("react {} {} {}", channel_id, message_id, emoji));
+            if self.no_reactions {
+                Err(SendError::forbidden("Missing Permissions"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn calls(routes: &FakeRoutes) -> Vec<String> {
+        routes.calls.lock().unwrap().clone()
+    }
+
+    fn origin(guild_id: u64) -> Origin {
+        Origin {
+            guild_id,
+            channel_id: ANNOUNCEMENTS,
+            user_id: USER,
+            message_id: MESSAGE,
+        }
+    }
+
+    fn fallback(channels: &[(u64, u64)]) -> ReplyFallback {
+        ReplyFallback::new(
+            channels.iter().copied().collect(),
+            DEFAULT_BLOCKED_CHANNEL_TTL,
+        )
+    }
+
+    #[test]
+    fn test_permission_error_codes() {
+        assert!(is_permission_code(MISSING_ACCESS));
+        assert!(is_permission_code(MISSING_PERMISSIONS));
+        // Unknown Message, Cannot send an empty message, Cannot send messages to this user
+        assert!(!is_permission_code(10008));
+        assert!(!is_permission_code(50006));
+        assert!(!is_permission_code(50007));
+
+        let missing = serenity::Error::Model(ModelError::InvalidPermissions {
+            required: serenity::model::Permissions::SEND_MESSAGES,
+            present: serenity::model::Permissions::empty(),
+        });
+        assert!(is_permission_error(&missing));
+        assert!(SendError::from(missing).forbidden);
+        assert!(!is_permission_error(&serenity::Error::Model(
+            ModelError::MessageTooLong(2500)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_origin_channel_is_used_when_allowed() {
+        let routes = FakeRoutes::default();
+        let placement = fallback(&[(GUILD, GENERAL)])
+            .place(&routes, &origin(GUILD), "Thinking")
+            .await;
+        assert_eq!(placement.route, Route::Origin);
+        assert_eq!(placement.channel_id, Some(ANNOUNCEMENTS));
+        assert_eq!(placement.placeholder, Some(ANNOUNCEMENTS * 100));
+        assert_eq!(calls(&routes), ["send 10 Thinking"]);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_order() {
+        // Guild fallback channel first, with a note for the user
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS],
+            ..Default::default()
+        };
+        let placement = fallback(&[(GUILD, GENERAL)])
+            .place(&routes, &origin(GUILD), "Thinking")
+            .await;
+        assert_eq!(placement.route, Route::GuildChannel);
+        assert_eq!(placement.channel_id, Some(GENERAL));
+        assert_eq!(placement.placeholder, Some(GENERAL * 100));
+        assert_eq!(
+            calls(&routes),
+            [
+                "send 10 Thinking",
+                "send 11 <@5> I can't post in <#10>, so I'm answering here.",
+                "send 11 Thinking",
+            ]
+        );
+
+        // Fallback channel read-only too, or none configured: DM the user
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS, GENERAL],
+            ..Default::default()
+        };
+        let placement = fallback(&[(GUILD, GENERAL)])
+            .place(&routes, &origin(GUILD), "Thinking")
+            .await;
+        assert_eq!(placement.route, Route::Dm);
+        assert_eq!(placement.channel_id, Some(DM));
+        assert_eq!(
+            calls(&routes)[2..],
+            [
+                "dm 5",
+                "send 99 I can't post in <#10>, so I'm answering here.",
+                "send 99 Thinking",
+            ]
+        );
+
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS],
+            ..Default::default()
+        };
+        let placement = fallback(&[]).place(&routes, &origin(GUILD), "").await;
+        assert_eq!(placement.route, Route::Dm);
+        assert_eq!(placement.placeholder, Some(DM * 100));
+    }
+
+    #[tokio::test]
+    async fn test_reaction_is_the_last_resort() {
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS, GENERAL],
+            no_dm: true,
+            ..Default::default()
+        };
+        let placement = fallback(&[(GUILD, GENERAL)])
+            .place(&routes, &origin(GUILD), "")
+            .await;
+        assert_eq!(placement.route, Route::Reaction);
+        assert_eq!(placement.channel_id, None);
+        assert_eq!(calls(&routes).last().unwrap(), "react 10 42 🚫");
+
+        // A DM that can't be answered goes straight to the reaction
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS],
+            ..Default::default()
+        };
+        let placement = fallback(&[]).place(&routes, &origin(0), "").await;
+        assert_eq!(placement.route, Route::Reaction);
+        assert_eq!(calls(&routes), ["send 10 …", "react 10 42 🚫"]);
+
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS],
+            no_reactions: true,
+            ..Default::default()
+        };
+        let placement = fallback(&[]).place(&routes, &origin(0), "").await;
+        assert_eq!(placement.route, Route::Unreachable);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_channel_is_skipped_for_ttl() {
+        let fallback = fallback(&[(GUILD, GENERAL)]);
+        let routes = FakeRoutes {
+            forbidden: vec![ANNOUNCEMENTS],
+            ..Default::default()
+        };
+        // A rejected custom placeholder is not retried with the default when forbidden
+        fallback.place(&routes, &origin(GUILD), "Thinking").await;
+        assert_eq!(calls(&routes)[0], "send 10 Thinking");
+        assert_ne!(calls(&routes)[1], "send 10 …");
+        let now = Instant::now();
+        assert!(fallback.is_blocked(ANNOUNCEMENTS, now));
+        assert!(!fallback.is_blocked(GENERAL, now));
+
+        // The next message skips the read-only channel
+        let routes = FakeRoutes::default();
+        let placement = fallback.place(&routes, &origin(GUILD), "Thinking").await;
+        assert_eq!(placement.route, Route::GuildChannel);
+        assert!(calls(&routes).iter().all(|c| !c.starts_with("send 10 ")));
+
+        // After the TTL the channel is tried again
+        assert!(!fallback.is_blocked(ANNOUNCEMENTS, now + DEFAULT_BLOCKED_CHANNEL_TTL));
+    }
+
+    #[tokio::test]
+    async fn test_other_failures_reply_fresh_in_origin() {
+        let fallback = fallback(&[(GUILD, GENERAL)]);
+        let routes = FakeRoutes {
+            fail_sends: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fallback.place(&routes, &origin(GUILD), "").await,
+            Placement::origin(ANNOUNCEMENTS)
+        );
+        assert_eq!(calls(&routes), ["send 10 …"]);
+        assert!(!fallback.is_blocked(ANNOUNCEMENTS, Instant::now()));
+    }
+}
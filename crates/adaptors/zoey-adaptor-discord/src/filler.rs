@@ -0,0 +1,172 @@
+//! Thinking filler while a voice reply is generated
+//!
+//! A long silence after the user stops speaking sounds like the bot stopped
+//! listening. When the reply has not arrived after a configured delay, a short
+//! filler ("let me think...") is played. The filler is skipped if the reply
+//! arrives first and cut off when it arrives mid-filler, so it never delays
+//! the actual answer.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Default filler phrases, spoken in rotation
+pub fn default_filler_phrases() -> Vec<String> {
+    vec![
+        "Let me think...".to_string(),
+        "Hmm, one moment.".to_string(),
+        "Good question, give me a second.".to_string(),
+    ]
+}
+
+/// Rotates through the configured filler phrases
+#[derive(Debug, Default)]
+pub struct FillerPhrases {
+    phrases: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl FillerPhrases {
+    pub fn new(phrases: Vec<String>) -> Self {
+        Self {
+            phrases: phrases
+                .into_iter()
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Next phrase, or `None` when none are configured
+    pub fn pick(&self) -> Option<&str> {
+        if self.phrases.is_empty() {
+            return None;
+        }
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.phrases.len();
+        Some(&self.phrases[i])
+    }
+}
+
+/// Handle to a pending or playing thinking filler
+///
+/// [`ThinkingFiller::cancel`] (or dropping the handle) skips a filler that has
+/// not started and stops one that is playing.
+pub struct ThinkingFiller {
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+impl ThinkingFiller {
+    /// Call `play` after `delay` unless cancelled first
+    ///
+    /// `play` starts the filler and returns a function stopping it, or `None`
+    /// when nothing was played (e.g. the bot is already speaking). Cancelling
+    /// while `play` is still preparing the clip abandons it. A zero delay
+    /// disables the filler.
+    pub fn start<P, Fut, S>(delay: Duration, play: P) -> Self
+    where
+        P: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Option<S>> + Send,
+        S: FnOnce() + Send + 'static,
+    {
+        if delay.is_zero() {
+            return Self { cancel: None };
+        }
+        let (tx, mut rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = &mut rx => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+            let stop = tokio::select! {
+                _ = &mut rx => return,
+                stop = play() => stop,
+            };
+            if let Some(stop) = stop {
+                // Resolves on cancel or when the handle is dropped
+                let _ = rx.await;
+                stop();
+            }
+        });
+        Self { cancel: Some(tx) }
+    }
+
+    /// Skip the filler, or stop it if it is playing
+    pub fn cancel(mut self) {
+        if let Some(tx) = self.cancel.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn tracked(
+        played: &Arc<AtomicBool>,
+        stopped: &Arc<AtomicBool>,
+    ) -> impl FnOnce() -> std::future::Ready<Option<Box<dyn FnOnce() + Send>>> + Send + 'static
+    {
+        let (played, stopped) = (played.clone(), stopped.clone());
+        move || {
+            played.store(true, Ordering::SeqCst);
+            let stop: Box<dyn FnOnce() + Send> =
+                Box::new(move || stopped.store(true, Ordering::SeqCst));
+            std::future::ready(Some(stop))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skipped_when_reply_arrives_first() {
+        let (played, stopped) = (
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicBool::new(false)),
+        );
+        let filler = ThinkingFiller::start(Duration::from_millis(80), tracked(&played, &stopped));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        filler.cancel();
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert!(!played.load(Ordering::SeqCst));
+        assert!(!stopped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_plays_after_delay_and_stops_on_reply() {
+        let (played, stopped) = (
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicBool::new(false)),
+        );
+        let filler = ThinkingFiller::start(Duration::from_millis(20), tracked(&played, &stopped));
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(played.load(Ordering::SeqCst));
+        assert!(!stopped.load(Ordering::SeqCst));
+        filler.cancel();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_zero_delay_disables() {
+        let (played, stopped) = (
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicBool::new(false)),
+        );
+        let _filler = ThinkingFiller::start(Duration::ZERO, tracked(&played, &stopped));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(!played.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_phrases_rotate() {
+        let phrases = FillerPhrases::new(vec!["a".into(), "  ".into(), "b".into()]);
+        let picked: Vec<_> = (0..3)
+            .map(|_| phrases.pick().unwrap().to_string())
+            .collect();
+        assert_eq!(picked, ["a", "b", "a"]);
+        assert_eq!(FillerPhrases::new(Vec::new()).pick(), None);
+    }
+}
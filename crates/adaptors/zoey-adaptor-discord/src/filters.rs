@@ -0,0 +1,451 @@
+//! Guild, channel and user allow-lists, adjustable at runtime
+//!
+//! [`DiscordConfig::allowed_guilds`], `allowed_channels` and `allowed_users`
+//! are the starting point. Server managers then allow or deny channels of
+//! their own guild without restarting the bot (which would drop voice
+//! sessions):
+//!
+//! - `/zoey allow-channel [channel]` answers every message in the channel
+//! - `/zoey deny-channel [channel]` stops answering in the channel
+//! - `/zoey list-filters` shows what applies to the guild
+//!
+//! The commands need the Manage Server permission. Changes apply from the next
+//! message and are kept per guild in the adapter's [`StateStore`], so they
+//! survive restarts when the store is persistent (e.g. `MongoStateStore`).
+//!
+//! [`DiscordConfig::allowed_guilds`]: crate::DiscordConfig::allowed_guilds
+
+use serde::{Deserialize, Serialize};
+use serenity::all::{CommandDataOption, CommandDataOptionValue};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::CommandOptionType;
+use serenity::model::channel::ChannelType;
+use serenity::model::Permissions;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+use zoey_core::{Result, StateStore};
+
+/// State store namespace; one entry per guild, keyed by guild ID
+pub const FILTERS_NAMESPACE: &str = "discord:filters";
+
+/// Reply for a `/zoey` invocation that doesn't match any subcommand
+const USAGE: &str = "Use `/zoey allow-channel`, `/zoey deny-channel` or `/zoey list-filters`.";
+
+/// Runtime channel overrides of one guild
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuildFilters {
+    /// Answered in full, even when not in the configured channel list
+    #[serde(default)]
+    pub allowed_channels: BTreeSet<u64>,
+    /// Never answered
+    #[serde(default)]
+    pub denied_channels: BTreeSet<u64>,
+}
+
+impl GuildFilters {
+    fn is_empty(&self) -> bool {
+        self.allowed_channels.is_empty() && self.denied_channels.is_empty()
+    }
+}
+
+/// Parsed `/zoey` command; a missing channel means the one it was used in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterCommand {
+    AllowChannel(Option<u64>),
+    DenyChannel(Option<u64>),
+    List,
+}
+
+impl FilterCommand {
+    /// Parse the options of a `/zoey` interaction
+    pub fn from_options(options: &[CommandDataOption]) -> std::result::Result<Self, String> {
+        let Some(option) = options.first() else {
+            return Err(USAGE.to_string());
+        };
+        let CommandDataOptionValue::SubCommand(inner) = &option.value else {
+            return Err(USAGE.to_string());
+        };
+        let channel = inner.iter().find_map(|o| match o.value {
+            CommandDataOptionValue::Channel(id) if o.name == "channel" => Some(id.get()),
+            _ => None,
+        });
+        match option.name.as_str() {
+            "allow-channel" => Ok(Self::AllowChannel(channel)),
+            "deny-channel" => Ok(Self::DenyChannel(channel)),
+            "list-filters" => Ok(Self::List),
+            _ => Err(USAGE.to_string()),
+        }
+    }
+}
+
+/// `/zoey` slash command definition, shown to server managers only
+pub fn zoey_command() -> CreateCommand {
+    let channel = || {
+        CreateCommandOption::new(
+            CommandOptionType::Channel,
+            "channel",
+            "Channel to change (defaults to this one)",
+        )
+        .channel_types(vec![ChannelType::Text, ChannelType::PublicThread])
+    };
+    CreateCommand::new("zoey")
+        .description("Choose where I answer in this server")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "allow-channel",
+                "Answer every message in a channel",
+            )
+            .add_sub_option(channel()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "deny-channel",
+                "Stop answering in a channel",
+            )
+            .add_sub_option(channel()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list-filters",
+            "Show where I answer in this server",
+        ))
+}
+
+/// Whether an interaction member's resolved permissions allow changing filters
+pub fn can_manage_guild(permissions: Option<Permissions>) -> bool {
+    permissions.is_some_and(|p| {
+        p.contains(Permissions::MANAGE_GUILD) || p.contains(Permissions::ADMINISTRATOR)
+    })
+}
+
+/// Configured allow-lists plus per-guild channel overrides
+///
+/// Shared between the adapter service and its event handler; every message
+/// is checked against the current state.
+pub struct DiscordFilters {
+    allowed_guilds: Option<HashSet<u64>>,
+    allowed_channels: Option<HashSet<u64>>,
+    allowed_users: Option<HashSet<u64>>,
+    guilds: RwLock<HashMap<u64, GuildFilters>>,
+    store: Arc<dyn StateStore>,
+}
+
+impl DiscordFilters {
+    pub fn new(
+        allowed_guilds: Option<&[u64]>,
+        allowed_channels: Option<&[u64]>,
+        allowed_users: Option<&[u64]>,
+        store: Arc<dyn StateStore>,
+    ) -> Self {
+        let set = |ids: Option<&[u64]>| ids.map(|ids| ids.iter().copied().collect());
+        Self {
+            allowed_guilds: set(allowed_guilds),
+            allowed_channels: set(allowed_channels),
+            allowed_users: set(allowed_users),
+            guilds: RwLock::new(HashMap::new()),
+            store,
+        }
+    }
+
+    /// Filters from the adapter configuration, persisted in its state store
+    pub fn from_config(config: &crate::DiscordConfig) -> Self {
+        Self::new(
+            config.allowed_guilds.as_deref(),
+            config.allowed_channels.as_deref(),
+            config.allowed_users.as_deref(),
+            config.state_store.clone(),
+        )
+    }
+
+    /// Load the persisted overrides of every guild, returning how many guilds have some
+    pub async fn load(&self) -> Result<usize> {
+        let entries = self.store.list(FILTERS_NAMESPACE).await?;
+        let mut guilds = self.guilds.write().unwrap();
+        for (key, value) in entries {
+            let Ok(guild_id) = key.parse::<u64>() else {
+                continue;
+            };
+            match serde_json::from_value::<GuildFilters>(value) {
+                Ok(filters) => {
+                    guilds.insert(guild_id, filters);
+                }
+                Err(e) => {
+                    warn!(guild_id = %guild_id, error = %e, "Ignoring unreadable guild filters")
+                }
+            }
+        }
+        Ok(guilds.len())
+    }
+
+    /// Why a message is not handled, or `None` when it passes every filter
+    ///
+    /// DMs (guild 0) skip the guild and channel lists.
+    pub fn rejection(&self, guild_id: u64, channel_id: u64, user_id: u64) -> Option<&'static str> {
+        if guild_id != 0 {
+            if self
+                .allowed_guilds
+                .as_ref()
+                .is_some_and(|set| !set.contains(&guild_id))
+            {
+                return Some("guild not in allowed list");
+            }
+            let guilds = self.guilds.read().unwrap();
+            let overrides = guilds.get(&guild_id);
+            if overrides.is_some_and(|g| g.denied_channels.contains(&channel_id)) {
+                return Some("channel denied");
+            }
+            let allowed_here = overrides.is_some_and(|g| g.allowed_channels.contains(&channel_id));
+            if !allowed_here
+                && self
+                    .allowed_channels
+                    .as_ref()
+                    .is_some_and(|set| !set.contains(&channel_id))
+            {
+                return Some("channel not in allowed list");
+            }
+        }
+        if self
+            .allowed_users
+            .as_ref()
+            .is_some_and(|set| !set.contains(&user_id))
+        {
+            return Some("user not in allowed list");
+        }
+        None
+    }
+
+    /// Whether a channel is explicitly allowed, so every message in it is answered
+    pub fn channel_listed(&self, guild_id: u64, channel_id: u64) -> bool {
+        let guilds = self.guilds.read().unwrap();
+        let overrides = guilds.get(&guild_id);
+        if overrides.is_some_and(|g| g.denied_channels.contains(&channel_id)) {
+            return false;
+        }
+        overrides.is_some_and(|g| g.allowed_channels.contains(&channel_id))
+            || self
+                .allowed_channels
+                .as_ref()
+                .is_some_and(|set| set.contains(&channel_id))
+    }
+
+    /// Whether messages from the runtime may be sent to a channel of any guild
+    pub fn send_allowed(&self, channel_id: u64) -> bool {
+        let guilds = self.guilds.read().unwrap();
+        if guilds
+            .values()
+            .any(|g| g.denied_channels.contains(&channel_id))
+        {
+            return false;
+        }
+        match &self.allowed_channels {
+            None => true,
+            Some(set) => {
+                set.contains(&channel_id)
+                    || guilds
+                        .values()
+                        .any(|g| g.allowed_channels.contains(&channel_id))
+            }
+        }
+    }
+
+    /// The overrides of one guild
+    pub fn guild(&self, guild_id: u64) -> GuildFilters {
+        self.guilds
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Allow a channel of a guild and persist the change
+    pub async fn allow_channel(&self, guild_id: u64, channel_id: u64) -> Result<()> {
+        self.update(guild_id, |g| {
+            g.denied_channels.remove(&channel_id);
+            g.allowed_channels.insert(channel_id);
+        })
+        .await
+    }
+
+    /// Deny a channel of a guild and persist the change
+    pub async fn deny_channel(&self, guild_id: u64, channel_id: u64) -> Result<()> {
+        self.update(guild_id, |g| {
+            g.allowed_channels.remove(&channel_id);
+            g.denied_channels.insert(channel_id);
+        })
+        .await
+    }
+
+    async fn update(&self, guild_id: u64, f: impl FnOnce(&mut GuildFilters)) -> Result<()> {
+        let updated = {
+            let mut guilds = self.guilds.write().unwrap();
+            let filters = guilds.entry(guild_id).or_default();
+            f(filters);
+            filters.clone()
+        };
+        let key = guild_id.to_string();
+        if updated.is_empty() {
+            self.store.delete(FILTERS_NAMESPACE, &key).await.map(|_| ())
+        } else {
+            self.store
+                .set(
+                    FILTERS_NAMESPACE,
+                    &key,
+                    serde_json::to_value(&updated)?,
+                    None,
+                )
+                .await
+        }
+    }
+
+    /// What applies to a guild, for `/zoey list-filters`
+    pub fn describe(&self, guild_id: u64) -> String {
+        let channels = |ids: &mut dyn Iterator<Item = u64>| {
+            let list: Vec<String> = ids.map(|id| format!("<#{}>", id)).collect();
+            if list.is_empty() {
+                "none".to_string()
+            } else {
+                list.join(", ")
+            }
+        };
+        let overrides = self.guild(guild_id);
+        let configured = match &self.allowed_channels {
+            None => "all channels".to_string(),
+            Some(set) => {
+                let mut ids: Vec<u64> = set.iter().copied().collect();
+                ids.sort_unstable();
+                channels(&mut ids.into_iter())
+            }
+        };
+        let mut lines = vec![
+            format!("**Configured channels:** {}", configured),
+            format!(
+                "**Allowed here:** {}",
+                channels(&mut overrides.allowed_channels.iter().copied())
+            ),
+            format!(
+                "**Denied here:** {}",
+                channels(&mut overrides.denied_channels.iter().copied())
+            ),
+        ];
+        if let Some(users) = &self.allowed_users {
+            lines.push(format!("**Allowed users:** {}", users.len()));
+        }
+        lines.join("\n")
+    }
+
+    /// Apply a `/zoey` command and build the (ephemeral) reply
+    ///
+    /// `can_manage` comes from [`can_manage_guild`]; without it nothing changes.
+    pub async fn handle(
+        &self,
+        command: FilterCommand,
+        guild_id: u64,
+        channel_id: u64,
+        can_manage: bool,
+    ) -> String {
+        if guild_id == 0 {
+            return "Filters can only be changed in a server.".to_string();
+        }
+        if !can_manage {
+            return "You need the Manage Server permission to change where I answer.".to_string();
+        }
+        let (result, reply) = match command {
+            FilterCommand::List => return self.describe(guild_id),
+            FilterCommand::AllowChannel(channel) => {
+                let channel = channel.unwrap_or(channel_id);
+                (
+                    self.allow_channel(guild_id, channel).await,
+                    format!("I'll answer every message in <#{}>.", channel),
+                )
+            }
+            FilterCommand::DenyChannel(channel) => {
+                let channel = channel.unwrap_or(channel_id);
+                (
+                    self.deny_channel(guild_id, channel).await,
+                    format!("I'll stay quiet in <#{}>.", channel),
+                )
+            }
+        };
+        match result {
+            Ok(()) => reply,
+            Err(e) => {
+                warn!(guild_id = %guild_id, error = %e, "Failed to persist guild filters");
+                format!("{} (not persisted: {})", reply, e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::MemoryStateStore;
+
+    fn filters(store: Arc<dyn StateStore>) -> DiscordFilters {
+        DiscordFilters::new(None, Some(&[10]), None, store)
+    }
+
+    #[tokio::test]
+    async fn test_changes_apply_to_next_message_and_survive_restart() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let filters = filters(store.clone());
+        assert_eq!(filters.rejection(1, 10, 7), None);
+        assert_eq!(
+            filters.rejection(1, 20, 7),
+            Some("channel not in allowed list")
+        );
+        // DMs skip the channel list
+        assert_eq!(filters.rejection(0, 20, 7), None);
+
+        let reply = filters
+            .handle(FilterCommand::AllowChannel(Some(20)), 1, 10, true)
+            .await;
+        assert_eq!(reply, "I'll answer every message in <#20>.");
+        assert_eq!(filters.rejection(1, 20, 7), None);
+        assert!(filters.channel_listed(1, 20));
+        // Overrides are per guild
+        assert!(filters.rejection(2, 20, 7).is_some());
+
+        filters
+            .handle(FilterCommand::DenyChannel(None), 1, 10, true)
+            .await;
+        assert_eq!(filters.rejection(1, 10, 7), Some("channel denied"));
+        assert!(!filters.channel_listed(1, 10));
+        assert!(!filters.send_allowed(10));
+
+        let restarted = self::filters(store);
+        assert_eq!(restarted.load().await.unwrap(), 1);
+        assert_eq!(restarted.guild(1), filters.guild(1));
+        assert_eq!(restarted.rejection(1, 20, 7), None);
+        assert_eq!(restarted.rejection(1, 10, 7), Some("channel denied"));
+    }
+
+    #[tokio::test]
+    async fn test_commands_need_manage_server() {
+        let filters = filters(Arc::new(MemoryStateStore::new()));
+        for command in [
+            FilterCommand::AllowChannel(Some(20)),
+            FilterCommand::DenyChannel(None),
+            FilterCommand::List,
+        ] {
+            let reply = filters.handle(command, 1, 10, false).await;
+            assert_eq!(
+                reply,
+                "You need the Manage Server permission to change where I answer."
+            );
+        }
+        assert_eq!(filters.guild(1), GuildFilters::default());
+        assert_eq!(filters.rejection(1, 10, 7), None);
+
+        assert!(!can_manage_guild(None));
+        assert!(!can_manage_guild(Some(Permissions::SEND_MESSAGES)));
+        assert!(can_manage_guild(Some(Permissions::MANAGE_GUILD)));
+        assert!(can_manage_guild(Some(Permissions::ADMINISTRATOR)));
+    }
+}
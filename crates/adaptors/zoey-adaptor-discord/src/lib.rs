@@ -1,19 +1,23 @@
 use async_trait::async_trait;
+use zoey_core::agent_api::types::MemoryCreateRequest;
 use zoey_core::{
-    types::{service::Service, ChannelType, Content, Memory, Room},
-    validate_input, AgentRuntime, RateLimiter, Result,
+    types::{service::Service, Content, Memory, Room},
+    validate_input, AgentRuntime, MemoryStateStore, RateLimiter, Result, SnapshotContributor,
+    StateStore,
 };
 use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
 use serenity::all::Interaction;
 use serenity::async_trait as serenity_async_trait;
 use serenity::builder::{
-    CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage, EditMessage,
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
+    EditInteractionResponse, EditMessage,
 };
 use serenity::http::Http;
-use serenity::model::application::Command;
+use serenity::model::application::{Command, CommandOptionType, ComponentInteraction};
 use serenity::model::channel::Message as DiscordMessage;
 use serenity::model::gateway::GatewayIntents;
-use serenity::cache::Settings as CacheSettings;
 use serenity::model::id::ChannelId;
 use serenity::model::id::MessageId;
 use serenity::prelude::*;
@@ -23,73 +27,100 @@ use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+pub mod ask;
+pub mod attachments;
+pub mod attribution;
+pub mod auto_leave;
+pub mod batcher;
+pub mod cache;
+pub mod characters;
+pub mod choices;
+pub mod context;
+pub mod continuity;
+pub mod epochs;
+pub mod fallback;
+pub mod filler;
+pub mod filters;
+pub mod links;
+pub mod listen;
+pub mod placeholder;
+pub mod prefs;
+pub mod push_to_talk;
+#[cfg(feature = "voice-speaker-id")]
+pub mod speaker_gate;
+pub mod stage;
+pub mod threads;
+pub mod typing;
 pub mod voice;
-pub use voice::{VoiceConfig, VoiceManager, VoiceSession};
+pub mod voice_states;
+pub use ask::AskCommand;
+pub use attachments::{AttachmentConfig, AttachmentInfo, MessageAttachments};
+pub use auto_leave::{AutoLeave, Departure, LeaveReason};
+pub use batcher::{
+    AgentApiMemorySink, BatchSink, BatcherConfig, BatcherStats, MemoryBatcher, PushOutcome,
+    WriteClass,
+};
+pub use cache::{CacheSizes, CacheTuning, DisplayNames};
+pub use characters::{CharacterCommand, ChannelCharacters};
+pub use choices::{Choice, ChoiceClick, ChoicePrompt, PendingChoices};
+pub use context::RoomContext;
+pub use continuity::{ContinueCommand, ContinuityLink, ContinuityLinks, PendingConfirmations};
+pub use epochs::{ContextEpochs, EpochChange};
+pub use fallback::{Origin, Placement, ReplyFallback, Route};
+pub use filler::ThinkingFiller;
+pub use filters::{DiscordFilters, FilterCommand, GuildFilters};
+pub use links::{LinkFetcher, PendingLinks, UrlIngestion};
+pub use listen::{ListenMode, ListenModes};
+pub use placeholder::{ReplyChannel, DEFAULT_PLACEHOLDER};
+pub use prefs::{PrefsCommand, UserPreferenceStore, UserPreferences, Verbosity};
+pub use push_to_talk::{PushToTalk, Toggle, VoiceConversation, VoiceConversations};
+pub use stage::{Floor, StageChange, StageRole, StageSessions};
+pub use threads::{MessageChannel, ThreadTracker};
+use placeholder::{deliver_final, send_placeholder, DiscordReplyChannel};
+pub use typing::TypingRefresh;
+pub use voice::{VoiceConfig, VoiceManager, VoiceSession, WakeWordMatcher};
+pub use voice_states::VoiceStates;
 
 #[cfg(feature = "voice")]
 use songbird::serenity::{SerenityInit, SongbirdKey};
 
-/// Extract text content from XML response format
-/// Handles both complete and partial XML responses
-fn extract_text_from_xml(content: &str) -> String {
-    // Try to extract content from <text>...</text> tags
-    if let Some(start) = content.find("<text>") {
-        let after_tag = &content[start + 6..];
-        if let Some(end) = after_tag.find("</text>") {
-            return after_tag[..end].trim().to_string();
-        }
-        // Partial XML - text tag opened but not closed yet
-        // Return content after <text> tag
-        return after_tag.trim().to_string();
-    }
-
-    // Check for self-closing or empty text tag
-    if content.contains("<text/>") || content.contains("<text />") {
-        return String::new();
-    }
-
-    // If no XML structure detected, check if we're inside a response block
-    // This handles streaming where <text> came in an earlier chunk
-    if content.contains("</text>") {
-        // We have the closing tag - extract everything before it
-        if let Some(end) = content.find("</text>") {
-            return content[..end].trim().to_string();
-        }
-    }
-
-    // No XML tags found - might be plain text or partial content
-    // Return as-is if it doesn't look like XML metadata
-    let trimmed = content.trim();
-    if trimmed.starts_with("<response>")
-        || trimmed.starts_with("<thought>")
-        || trimmed.starts_with("<actions>")
-        || trimmed.contains("</response>")
-        || trimmed.contains("</thought>")
-        || trimmed.contains("</actions>")
-    {
-        // This is XML structure but not the text content - return empty
-        return String::new();
-    }
-
-    // Plain text or content not wrapped in XML
-    trimmed.to_string()
-}
-
 /// Extract text content from a fully assembled XML response
 fn extract_final_text_from_xml(content: &str) -> String {
-    // Try to extract content from <text>...</text> tags
-    if let Some(start) = content.find("<text>") {
-        let after_tag = &content[start + 6..];
-        if let Some(end) = after_tag.find("</text>") {
-            return after_tag[..end].trim().to_string();
-        }
-        // Text tag opened but not closed - return what's after it
-        return after_tag.trim().to_string();
-    }
+    zoey_core::extract_response(content).text
+}
+
+/// Final message for a fully assembled response, wrapped in the configured
+/// `response_template`; falls back to the raw response when no text is extracted
+fn render_final_text(
+    assembled: &str,
+    template: Option<&str>,
+    room: &str,
+    character: &str,
+) -> String {
+    let display_text = extract_final_text_from_xml(assembled);
+    let text = if display_text.is_empty() {
+        assembled
+    } else {
+        display_text.as_str()
+    };
+    zoey_core::render_response_template(
+        template,
+        zoey_core::ResponseTemplateVars {
+            text,
+            room,
+            character,
+        },
+    )
+}
 
-    // No text tag found - return the original content trimmed
-    // This handles cases where the LLM didn't follow XML format
-    content.trim().to_string()
+/// `{room}` for a send-handler target: its `room_name` metadata, else the room ID
+fn target_room_label(target: &zoey_core::types::messaging::TargetInfo) -> String {
+    target
+        .metadata
+        .get("room_name")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| target.room_id.to_string())
 }
 
 #[derive(Clone)]
@@ -103,6 +134,47 @@ pub struct DiscordConfig {
     pub allowed_users: Option<Vec<u64>>,
     /// Voice configuration from character XML
     pub voice: VoiceConfig,
+    /// Channel ID -> character name/file answering in that channel
+    pub channel_characters: HashMap<u64, String>,
+    /// Guild ID -> channel answering for channels where the bot can't post
+    pub fallback_channels: HashMap<u64, u64>,
+    /// Users allowed to change channel characters with `/character here`
+    pub admin_users: Vec<u64>,
+    /// How often the typing indicator is re-broadcast while waiting for the first chunk
+    pub typing_refresh_interval: Duration,
+    /// Maximum time to keep the typing indicator alive for one message
+    pub typing_max_duration: Duration,
+    /// Voice inactivity after which `/listen always` falls back to wake-word mode
+    pub listen_auto_off: Duration,
+    /// Listening window opened with the 🎙️ reaction on the voice-join message; zero disables it
+    pub push_to_talk_window: Duration,
+    /// Whether links in messages addressed to the bot are fetched into the knowledge store
+    pub url_ingestion: UrlIngestion,
+    /// How long the author has to confirm fetching links in `Ask` mode
+    pub url_ask_timeout: Duration,
+    /// How long the buttons of a clarifying question can be answered
+    pub choice_ttl: Duration,
+    /// Let anyone in the channel answer a clarifying question, not only the asker
+    pub choices_open_to_all: bool,
+    /// Wraps the final reply; placeholders `{text}`, `{room}`, `{character}` (default `{text}`)
+    pub response_template: Option<String>,
+    /// Queueing and flush policy for non-critical memory writes
+    pub memory_batch: BatcherConfig,
+    /// What the serenity cache keeps (bounded by default)
+    pub cache: CacheTuning,
+    /// Message shown while a reply streams in; blank uses the neutral "…"
+    pub placeholder: String,
+    /// Inactivity after which a `/continue` link expires
+    pub continuity_idle: Duration,
+    /// Move long replies in busy guild channels into a thread started from the user's message
+    pub auto_thread: bool,
+    /// Reply length (in characters) above which `auto_thread` applies
+    pub auto_thread_min_chars: usize,
+    /// Size cap for image attachments and whether they are sent as CDN URLs
+    pub attachments: AttachmentConfig,
+    /// Where message dedup keys and voice states are kept; share one store
+    /// (e.g. `MongoStateStore`) between processes serving the same bot
+    pub state_store: Arc<dyn StateStore>,
 }
 
 impl Default for DiscordConfig {
@@ -114,12 +186,34 @@ impl Default for DiscordConfig {
                 | GatewayIntents::GUILD_MESSAGES
                 | GatewayIntents::DIRECT_MESSAGES
                 | GatewayIntents::MESSAGE_CONTENT
-                | GatewayIntents::GUILD_VOICE_STATES, // Added for voice support
+                | GatewayIntents::GUILD_VOICE_STATES // Added for voice support
+                | GatewayIntents::GUILD_MESSAGE_REACTIONS
+                | GatewayIntents::DIRECT_MESSAGE_REACTIONS, // ✅ confirmations for link ingestion
             application_id: None,
             allowed_guilds: None,
             allowed_channels: None,
             allowed_users: None,
             voice: VoiceConfig::default(),
+            channel_characters: HashMap::new(),
+            fallback_channels: HashMap::new(),
+            admin_users: Vec::new(),
+            typing_refresh_interval: typing::DEFAULT_TYPING_REFRESH_INTERVAL,
+            typing_max_duration: typing::DEFAULT_TYPING_MAX_DURATION,
+            listen_auto_off: listen::DEFAULT_LISTEN_AUTO_OFF,
+            push_to_talk_window: push_to_talk::DEFAULT_PUSH_TO_TALK_WINDOW,
+            url_ingestion: UrlIngestion::default(),
+            url_ask_timeout: links::DEFAULT_URL_ASK_TIMEOUT,
+            choice_ttl: choices::DEFAULT_CHOICE_TTL,
+            choices_open_to_all: false,
+            response_template: None,
+            memory_batch: BatcherConfig::default(),
+            cache: CacheTuning::default(),
+            placeholder: DEFAULT_PLACEHOLDER.to_string(),
+            continuity_idle: continuity::DEFAULT_CONTINUITY_IDLE,
+            auto_thread: false,
+            auto_thread_min_chars: threads::DEFAULT_AUTO_THREAD_MIN_CHARS,
+            attachments: AttachmentConfig::default(),
+            state_store: Arc::new(MemoryStateStore::new()),
         }
     }
 }
@@ -149,37 +243,882 @@ pub struct DiscordAdapterService {
     runtime: Arc<RwLock<AgentRuntime>>,
     running: bool,
     limiter: Arc<RateLimiter>,
+    memory_batcher: Option<MemoryBatcher<MemoryCreateRequest>>,
+    filters: Arc<DiscordFilters>,
+    #[cfg(feature = "voice")]
+    voice_latency: Option<Arc<voice::LatencyTracker>>,
 }
 
 impl DiscordAdapterService {
     pub fn new(config: DiscordConfig, runtime: Arc<RwLock<AgentRuntime>>) -> Self {
         let limiter = Arc::new(RateLimiter::new(Duration::from_secs(60), 30));
+        let filters = Arc::new(DiscordFilters::from_config(&config));
         Self {
             config,
             runtime,
             running: false,
             limiter,
+            memory_batcher: None,
+            filters,
+            #[cfg(feature = "voice")]
+            voice_latency: None,
+        }
+    }
+
+    /// Queue for non-critical memory writes, available once the service is started
+    pub fn memory_batcher(
+        &self,
+    ) -> Option<MemoryBatcher<MemoryCreateRequest>> {
+        self.memory_batcher.clone()
+    }
+
+    /// Guild/channel/user filters, changeable at runtime with `/zoey`
+    pub fn filters(&self) -> Arc<DiscordFilters> {
+        self.filters.clone()
+    }
+
+    /// Share filters with another component (e.g. the plugin's send handler)
+    pub fn with_filters(mut self, filters: Arc<DiscordFilters>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Voice turn latency percentiles, available once the service is started
+    #[cfg(feature = "voice")]
+    pub fn voice_latency_summary(&self) -> Option<voice::LatencySummary> {
+        self.voice_latency.as_ref().map(|t| t.latency_summary())
+    }
+}
+
+/// Active voice conversations per user
+/// Once the bot's name is detected, the conversation stays active for a window
+/// (`voice.discord.conversation_timeout_secs`); the 🎙️ reaction opens a manual
+/// window instead (see [`push_to_talk`])
+type VoiceConversationMap = Arc<VoiceConversations>;
+
+/// Snapshot section key for the Discord adapter
+const SNAPSHOT_KEY: &str = "discord";
+
+/// State store namespace for messages already picked up
+const DEDUP_NAMESPACE: &str = "discord:dedup";
+
+/// How long a handled message ID is remembered
+const DEDUP_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Serialized Discord adapter state for warm restarts
+///
+/// Only wake-word conversation windows are captured; manual push-to-talk
+/// windows are too short to be worth restoring. Voice-channel membership and
+/// message dedup keys live in the state store, rate limiter windows are
+/// intentionally not restored, and voice calls themselves must be re-joined by
+/// a trigger.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DiscordStateSection {
+    /// user_id -> unix timestamp (ms) of the last voice interaction
+    voice_conversations: HashMap<u64, i64>,
+}
+
+/// Snapshot contributor exposing the handler's ephemeral maps to the runtime
+struct DiscordSnapshotContributor {
+    voice_conversations: VoiceConversationMap,
+    /// Conversations older than this are neither saved nor restored
+    conversation_timeout_secs: u64,
+}
+
+impl SnapshotContributor for DiscordSnapshotContributor {
+    fn key(&self) -> &str {
+        SNAPSHOT_KEY
+    }
+
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let section = DiscordStateSection {
+            voice_conversations: self
+                .voice_conversations
+                .wake_word_conversations()
+                .into_iter()
+                .filter(|(_, at)| at.elapsed().as_secs() < self.conversation_timeout_secs)
+                .map(|(uid, at)| (uid, now_ms - at.elapsed().as_millis() as i64))
+                .collect(),
+        };
+        if section.voice_conversations.is_empty() {
+            return None;
+        }
+        serde_json::to_value(section).ok()
+    }
+
+    fn restore(&self, section: serde_json::Value) -> Result<()> {
+        let section: DiscordStateSection = serde_json::from_value(section)?;
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let timeout = Duration::from_secs(self.conversation_timeout_secs);
+        for (uid, at_ms) in section.voice_conversations {
+            let age = Duration::from_millis(now_ms.saturating_sub(at_ms).max(0) as u64);
+            if age.as_secs() >= self.conversation_timeout_secs {
+                continue;
+            }
+            if let Some(at) = Instant::now().checked_sub(age) {
+                self.voice_conversations.restore(uid, at, timeout);
+            }
+        }
+        info!(
+            voice_conversations = %self.voice_conversations.len(),
+            "Discord state restored from snapshot"
+        );
+        Ok(())
+    }
+}
+
+/// `/listen [mode]` slash command definition
+fn listen_command() -> CreateCommand {
+    CreateCommand::new("listen")
+        .description("Choose when I respond in voice (no option toggles always-on)")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "mode", "Listen mode")
+                .required(false)
+                .add_string_choice("Always on", ListenMode::AlwaysOn.as_str())
+                .add_string_choice("Wake word", ListenMode::WakeWord.as_str())
+                .add_string_choice("Off", ListenMode::Off.as_str()),
+        )
+}
+
+/// Apply a `/listen` command and build the reply
+fn handle_listen_command(listen_modes: &ListenModes, guild_id: u64, requested: Option<&str>) -> String {
+    if guild_id == 0 {
+        return "Listen mode can only be changed in a server.".to_string();
+    }
+    let mode = match requested {
+        Some(value) => match ListenMode::parse(value) {
+            Some(mode) => {
+                listen_modes.set(guild_id, mode);
+                mode
+            }
+            None => return format!("Unknown listen mode `{}`. Use always, wake or off.", value),
+        },
+        None => listen_modes.toggle(guild_id),
+    };
+    info!(guild_id = %guild_id, mode = ?mode, "Voice listen mode changed");
+    match mode {
+        ListenMode::AlwaysOn => "🎤 Always-on listening enabled - I'll respond to everything said in voice. \
+            It switches off automatically after a while without conversation."
+            .to_string(),
+        ListenMode::WakeWord => "🎤 Wake-word mode - say my name to start talking to me.".to_string(),
+        ListenMode::Off => "🔇 Voice responses are off. Use /listen to turn them back on.".to_string(),
+    }
+}
+
+/// Permissions of a message's author in `channel_id` of its guild, from the cache
+fn author_permissions(ctx: &Context, msg: &DiscordMessage, channel_id: u64) -> Option<serenity::model::Permissions> {
+    let guild = ctx.cache.guild(msg.guild_id?)?;
+    let channel = guild.channels.get(&ChannelId::new(channel_id))?;
+    let member = msg.member.as_ref()?;
+    Some(guild.partial_member_permissions_in(channel, msg.author.id, member))
+}
+
+/// Apply a `/character here` command and build the reply
+async fn handle_character_command(
+    channel_characters: &ChannelCharacters,
+    command: CharacterCommand,
+    guild_id: u64,
+    channel_id: u64,
+    is_admin: bool,
+) -> String {
+    if guild_id == 0 {
+        return "Channel characters can only be set in a server channel.".to_string();
+    }
+    let update = match command {
+        CharacterCommand::Show => {
+            return match channel_characters.resolve(guild_id, channel_id) {
+                Some(character) => format!("This channel is answered by **{}**.", character),
+                None => "This channel uses the default character.".to_string(),
+            };
+        }
+        CharacterCommand::Set(character) => Some(character),
+        CharacterCommand::Reset => None,
+    };
+    if !is_admin {
+        return "Only server admins can change the channel character.".to_string();
+    }
+    let reply = match &update {
+        Some(character) => format!("This channel will now be answered by **{}**.", character),
+        None => "This channel now uses the default character.".to_string(),
+    };
+    match channel_characters.set(guild_id, channel_id, update).await {
+        Ok(()) => reply,
+        Err(e) => {
+            warn!(guild_id = %guild_id, channel_id = %channel_id, error = %e, "Failed to persist channel character");
+            format!("{} (not persisted: {})", reply, e)
         }
     }
 }
 
-/// Custom voice state tracker - maps (guild_id, user_id) -> channel_id
-/// More reliable than serenity's cache for voice state tracking
-type VoiceStateMap = Arc<RwLock<HashMap<(u64, u64), u64>>>;
+/// Reply to `/reset` from someone without Manage Messages
+const RESET_DENIED_REPLY: &str = "Only members who can manage messages can reset this channel's conversation.";
+
+/// Move a channel or thread to its next context epoch and build the confirmation
+async fn reset_context(
+    context_epochs: &ContextEpochs,
+    channel_characters: &ChannelCharacters,
+    guild_id: u64,
+    conversation: &MessageChannel,
+) -> std::result::Result<CreateEmbed, String> {
+    let channel_id = conversation.channel_id;
+    let mapped_character = channel_characters.resolve(guild_id, conversation.settings_channel());
+    let previous_room = conversation.room_uuid_at(
+        guild_id,
+        mapped_character.as_deref(),
+        context_epochs.current(guild_id, channel_id),
+    );
+    match context_epochs.reset(guild_id, channel_id).await {
+        Ok(change) => {
+            info!(guild_id = %guild_id, channel_id = %channel_id, epoch = change.current, previous_room = %previous_room, "Conversation context reset");
+            Ok(change.embed(previous_room))
+        }
+        Err(e) => {
+            warn!(guild_id = %guild_id, channel_id = %channel_id, error = %e, "Failed to persist context epoch");
+            Err(format!("I'll start fresh from here (not persisted: {})", e))
+        }
+    }
+}
 
 struct Handler {
     runtime: Arc<RwLock<AgentRuntime>>,
     token: String,
     limiter: Arc<RateLimiter>,
     application_id: Option<u64>,
-    allowed_guilds: Option<HashSet<u64>>,
-    allowed_channels: Option<HashSet<u64>>,
-    allowed_users: Option<HashSet<u64>>,
-    recent_sends: Arc<RwLock<HashMap<u64, (String, Instant)>>>,
+    /// Guild/channel/user filters, shared with the service
+    filters: Arc<DiscordFilters>,
+    /// Shared dedup keys (and the store behind `voice_states`)
+    state_store: Arc<dyn StateStore>,
     /// Voice manager for handling voice channels
     voice_manager: Arc<VoiceManager>,
     /// Custom voice state tracker - more reliable than cache
-    voice_states: VoiceStateMap,
+    voice_states: VoiceStates,
+    /// Active voice conversations, shared across voice joins
+    voice_conversations: VoiceConversationMap,
+    /// Voice-join messages accepting the 🎙️ reaction
+    push_to_talk: Arc<PushToTalk>,
+    /// Channel -> character routing
+    channel_characters: Arc<ChannelCharacters>,
+    /// Context epoch per channel, advanced with `/reset`
+    context_epochs: Arc<ContextEpochs>,
+    /// Users allowed to change channel characters (guild owners always can)
+    admin_users: HashSet<u64>,
+    /// Typing indicator refresh interval and cap
+    typing_refresh_interval: Duration,
+    typing_max_duration: Duration,
+    /// Per-guild voice listen mode set with `/listen`
+    listen_modes: Arc<ListenModes>,
+    /// Link ingestion mode and Ask-mode prompts awaiting a reaction
+    url_ingestion: UrlIngestion,
+    pending_links: Arc<PendingLinks>,
+    /// Clarifying questions whose buttons can still be answered
+    pending_choices: Arc<PendingChoices>,
+    /// Template wrapping the final reply
+    response_template: Option<String>,
+    /// Content of the message edited while a reply streams in
+    placeholder: String,
+    /// Where replies go when the bot can't post in the channel
+    reply_fallback: Arc<ReplyFallback>,
+    /// Per-user preferences set with `/prefs`, sent with every chat request
+    user_prefs: Arc<UserPreferenceStore>,
+    /// Rooms continued from another channel with `/continue`, per user
+    continuity: Arc<ContinuityLinks>,
+    /// `/continue from` DM links awaiting the user's confirmation
+    pending_continuations: Arc<PendingConfirmations>,
+    /// Which channels are threads, and channel activity for `auto_thread`
+    threads: Arc<ThreadTracker>,
+    /// How image attachments are forwarded to the agent
+    attachments: AttachmentConfig,
+    /// Display names resolved over REST when the member is not cached
+    #[cfg(feature = "voice")]
+    display_names: Arc<DisplayNames>,
+}
+
+/// Speak a push-to-talk acknowledgement when voice replies are spoken
+async fn speak_ack(voice_manager: &VoiceManager, guild_id: u64, text: &str) {
+    if !voice_manager.config.discord.speak_responses {
+        return;
+    }
+    if let Err(e) = voice_manager.speak(guild_id, text).await {
+        debug!(guild_id = %guild_id, error = %e, "Push-to-talk acknowledgement not spoken");
+    }
+}
+
+/// Wrap a voice callback so stage answers wait for the floor, and are posted
+/// in the stage session's text channel when it is not granted in time
+#[cfg(feature = "voice")]
+fn stage_text_fallback(
+    callback: voice::TranscriptionCallback,
+    stage: Arc<StageSessions>,
+    guild_id: u64,
+    http: Arc<Http>,
+) -> voice::TranscriptionCallback {
+    let callback = Arc::new(callback);
+    Box::new(move |user_id, text, turn| {
+        let callback = callback.clone();
+        let stage = stage.clone();
+        let http = http.clone();
+        Box::pin(async move {
+            let answer = (callback)(user_id, text, turn).await?;
+            if stage.await_floor(guild_id).await {
+                return Some(answer);
+            }
+            let (_, text_channel) = stage.channels(guild_id)?;
+            info!(guild_id = %guild_id, channel_id = %text_channel, "Not a stage speaker, answering in text");
+            if let Err(e) = ChannelId::new(text_channel).say(&http, &answer).await {
+                warn!(guild_id = %guild_id, error = %format!("{:?}", e), "Failed to post stage answer");
+            }
+            None
+        })
+    })
+}
+
+/// `/stage invite` slash command definition
+fn stage_command() -> CreateCommand {
+    CreateCommand::new("stage")
+        .description("Stage channel controls")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "invite",
+            "Make me a speaker on the stage I joined",
+        ))
+}
+
+/// `/voice enroll` slash command definition
+#[cfg(feature = "voice-speaker-id")]
+fn voice_command() -> CreateCommand {
+    CreateCommand::new("voice")
+        .description("Voice identity")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "enroll",
+            "Teach me your voice so I accept your privileged voice commands",
+        ))
+}
+
+impl Handler {
+    /// Apply `/voice enroll`: record the caller's next utterances as their voice
+    #[cfg(feature = "voice-speaker-id")]
+    async fn handle_voice_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let reply = match (
+            &self.voice_manager.speaker_gate,
+            cmd.data.options.first().map(|o| o.name.as_str()),
+        ) {
+            (None, _) => "Speaker verification is not configured.".to_string(),
+            (Some(gate), Some("enroll")) => {
+                let needed = gate.start_enrollment(cmd.user.id.get());
+                format!(
+                    "🎙️ Say {} short sentences in voice and I'll learn your voice. I won't answer them.",
+                    needed
+                )
+            }
+            (Some(_), _) => return,
+        };
+        let response = CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(reply).ephemeral(true),
+        );
+        if let Err(e) = cmd.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer /voice");
+        }
+    }
+
+    /// Apply `/stage invite`: promote the bot on its stage where permissions allow
+    async fn handle_stage_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let reply = match (cmd.guild_id, cmd.data.options.first().map(|o| o.name.as_str())) {
+            (None, _) => "Stage commands only work in a server.".to_string(),
+            (Some(_), Some("invite")) if !self.is_stage_admin(cmd) => {
+                "Only stage moderators can invite me to speak.".to_string()
+            }
+            (Some(guild_id), Some("invite")) => match self.voice_manager.stage.channels(guild_id.get()) {
+                None => "I'm not on a stage in this server. Ask me to join voice from a Stage channel first."
+                    .to_string(),
+                Some((channel_id, _)) => match stage::stage_channel(&ctx.http, channel_id).await {
+                    None => "The stage I joined is no longer available.".to_string(),
+                    Some(channel) => match stage::promote(&ctx.http, &channel).await {
+                        Ok(()) => "🎤 I'm a speaker now - answers will be spoken on the stage.".to_string(),
+                        Err(e) => {
+                            warn!(guild_id = %guild_id.get(), error = %format!("{:?}", e), "Stage promotion failed");
+                            "I don't have permission to make myself a speaker here. \
+                            A stage moderator can accept my request to speak instead."
+                                .to_string()
+                        }
+                    },
+                },
+            },
+            (Some(_), _) => return,
+        };
+        let response = CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(reply).ephemeral(true),
+        );
+        if let Err(e) = cmd.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer /stage");
+        }
+    }
+
+    /// Configured admins and members who can moderate stages
+    fn is_stage_admin(&self, cmd: &serenity::model::application::CommandInteraction) -> bool {
+        self.admin_users.contains(&cmd.user.id.get())
+            || cmd
+                .member
+                .as_ref()
+                .and_then(|m| m.permissions)
+                .is_some_and(|p| p.administrator() || p.mute_members())
+    }
+
+    /// Apply a 🎙️ reaction change on a voice-join message
+    async fn toggle_push_to_talk(&self, ctx: &Context, message_id: u64, user_id: u64, emoji: &str, added: bool) {
+        // The bot's own reaction marks the message, it does not toggle anything
+        if user_id == ctx.cache.current_user().id.get() {
+            return;
+        }
+        let toggle = if added {
+            let Some((guild_id, _)) = self.push_to_talk.prompt_channel(message_id) else {
+                return;
+            };
+            let tracked = self.voice_states.channel_of(guild_id, user_id).await.unwrap_or_else(|e| {
+                warn!(guild_id = %guild_id, user_id = %user_id, error = %e, "Voice state lookup failed");
+                None
+            });
+            let user_channel = cache::voice_channel_of(tracked, &ctx.cache, guild_id, user_id);
+            self.push_to_talk
+                .reaction_added(&self.voice_conversations, message_id, user_id, emoji, user_channel)
+        } else {
+            self.push_to_talk
+                .reaction_removed(&self.voice_conversations, message_id, user_id, emoji)
+        };
+
+        let vm = self.voice_manager.clone();
+        match toggle {
+            Toggle::Opened { guild_id, since } => {
+                let window = self.push_to_talk.window();
+                info!(guild_id = %guild_id, user_id = %user_id, window_secs = window.as_secs(), "Push-to-talk window opened");
+                let convs = self.voice_conversations.clone();
+                tokio::spawn(async move {
+                    speak_ack(&vm, guild_id, push_to_talk::OPEN_ACK).await;
+                    tokio::time::sleep(window.saturating_sub(since.elapsed())).await;
+                    if convs.expire_manual(user_id, since) {
+                        info!(guild_id = %guild_id, user_id = %user_id, "Push-to-talk window expired");
+                        speak_ack(&vm, guild_id, push_to_talk::CLOSE_ACK).await;
+                    }
+                });
+            }
+            Toggle::Closed { guild_id } => {
+                info!(guild_id = %guild_id, user_id = %user_id, "Push-to-talk window closed");
+                tokio::spawn(async move {
+                    speak_ack(&vm, guild_id, push_to_talk::CLOSE_ACK).await;
+                });
+            }
+            Toggle::Ignored => {}
+        }
+    }
+
+    /// Answer `/context` ephemerally with the channel room's stats from the agent API
+    async fn handle_context_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        // The state task can outlive Discord's 3s response window
+        let defer = CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new().ephemeral(true));
+        if let Err(e) = cmd.create_response(&ctx.http, defer).await {
+            warn!(error = %format!("{:?}", e), "Failed to defer /context");
+            return;
+        }
+        let guild_id = cmd.guild_id.map(|g| g.get()).unwrap_or(0);
+        let conversation = threads::MessageChannel::from_interaction(cmd.channel_id.get(), cmd.channel.as_ref());
+        let room_id = self.channel_room(guild_id, conversation);
+        let api_base = std::env::var("AGENT_API_URL")
+            .unwrap_or_else(|_| "http://127.0.0.1:9090/agent".to_string());
+
+        let reply = match context::fetch_room_context(&HttpClient::new(), &api_base, room_id).await {
+            Ok(mut room_context) => {
+                room_context.persona = self
+                    .channel_characters
+                    .name_for(guild_id, conversation.settings_channel(), &room_context.persona);
+                room_context.to_reply()
+            }
+            Err(e) => {
+                warn!(room_id = %room_id, error = %e, "Failed to fetch room context");
+                "Couldn't load this channel's context right now.".to_string()
+            }
+        };
+        if let Err(e) = cmd
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(reply))
+            .await
+        {
+            warn!(error = %format!("{:?}", e), "Failed to answer /context");
+        }
+    }
+
+    /// Apply `/prefs` for the invoking user and confirm ephemerally
+    async fn handle_prefs_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let reply = match PrefsCommand::from_options(&cmd.data.options) {
+            Ok(command) => self.user_prefs.handle(cmd.user.id.get(), command).await,
+            Err(reason) => reason,
+        };
+        let response = CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(reply).ephemeral(true),
+        );
+        if let Err(e) = cmd.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer /prefs");
+        }
+    }
+
+    /// Apply `/zoey` filter changes for a server manager and answer ephemerally
+    async fn handle_zoey_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let reply = match FilterCommand::from_options(&cmd.data.options) {
+            Ok(command) => {
+                let can_manage = filters::can_manage_guild(cmd.member.as_ref().and_then(|m| m.permissions));
+                self.filters
+                    .handle(
+                        command,
+                        cmd.guild_id.map(|g| g.get()).unwrap_or(0),
+                        cmd.channel_id.get(),
+                        can_manage,
+                    )
+                    .await
+            }
+            Err(reason) => reason,
+        };
+        let response = CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(reply).ephemeral(true),
+        );
+        if let Err(e) = cmd.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer /zoey");
+        }
+    }
+
+    /// Start a fresh conversation in the channel with `/reset` and confirm with an embed
+    async fn handle_reset_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let guild_id = cmd.guild_id.map(|g| g.get()).unwrap_or(0);
+        let permissions = cmd.member.as_ref().and_then(|m| m.permissions);
+        let message = if self.admin_users.contains(&cmd.user.id.get()) || epochs::can_reset(guild_id, permissions) {
+            let conversation = threads::MessageChannel::from_interaction(cmd.channel_id.get(), cmd.channel.as_ref());
+            match reset_context(&self.context_epochs, &self.channel_characters, guild_id, &conversation).await {
+                Ok(embed) => CreateInteractionResponseMessage::new().embed(embed),
+                Err(reply) => CreateInteractionResponseMessage::new().content(reply),
+            }
+        } else {
+            CreateInteractionResponseMessage::new()
+                .content(RESET_DENIED_REPLY)
+                .ephemeral(true)
+        };
+        if let Err(e) = cmd
+            .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+            .await
+        {
+            warn!(error = %format!("{:?}", e), "Failed to answer /reset");
+        }
+    }
+
+    /// Answer `/ask` with a reply from the channel's room
+    ///
+    /// Works without the message content intent. The deferred response is
+    /// edited with the reply, and follow-ups carry what does not fit.
+    async fn handle_ask_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let reply_now = |content: String| {
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(content).ephemeral(true),
+            )
+        };
+        let ask = match AskCommand::from_options(&cmd.data.options) {
+            Ok(ask) => ask,
+            Err(reason) => {
+                let _ = cmd.create_response(&ctx.http, reply_now(reason)).await;
+                return;
+            }
+        };
+        let user_id = cmd.user.id.get();
+        let guild_id = cmd.guild_id.map(|g| g.get()).unwrap_or(0);
+        let conversation = threads::MessageChannel::from_interaction(cmd.channel_id.get(), cmd.channel.as_ref());
+        let settings_channel = conversation.settings_channel();
+        if !self.limiter.check(&ask::rate_limit_key(guild_id, conversation.channel_id, user_id)) {
+            debug!(guild_id = %guild_id, user_id = %user_id, "/ask rate limited");
+            let _ = cmd.create_response(&ctx.http, reply_now(ask::SLOW_DOWN_REPLY.to_string())).await;
+            return;
+        }
+        if let Some(reason) = self.filters.rejection(guild_id, settings_channel, user_id) {
+            debug!(guild_id = %guild_id, channel_id = %settings_channel, user_id = %user_id, "/ask filtered out - {}", reason);
+            let _ = cmd.create_response(&ctx.http, reply_now(ask::FILTERED_REPLY.to_string())).await;
+            return;
+        }
+        // The reply takes longer than Discord's 3s response window
+        let defer = CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(ask.ephemeral),
+        );
+        if let Err(e) = cmd.create_response(&ctx.http, defer).await {
+            warn!(error = %format!("{:?}", e), "Failed to defer /ask");
+            return;
+        }
+
+        let mapped_character = self.channel_characters.resolve(guild_id, settings_channel);
+        let char_name = {
+            let runtime_name = self.runtime.read().unwrap().character.name.clone();
+            self.channel_characters.name_for(guild_id, settings_channel, &runtime_name)
+        };
+        let room_id = self.channel_room(guild_id, conversation);
+        let room_name = format!("discord-{}-{}", guild_id, conversation.channel_id);
+        let api_base = std::env::var("AGENT_API_URL")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "http://127.0.0.1:9090/agent".to_string());
+        let entity_id = zoey_core::string_to_uuid(&format!("discord-user-{}", user_id));
+        let mut metadata = serde_json::Map::new();
+        self.user_prefs.annotate(user_id, &mut metadata).await;
+        self.continuity.annotate(user_id, room_id, &mut metadata).await;
+        let body = serde_json::json!({
+            "text": ask.question,
+            "roomId": room_id,
+            "entityId": entity_id,
+            "character": mapped_character.unwrap_or_else(|| char_name.clone()),
+            "stream": true,
+            "metadata": metadata,
+        });
+        let resp = tokio::time::timeout(
+            Duration::from_secs(
+                std::env::var("DISCORD_STREAM_REQUEST_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or(20),
+            ),
+            HttpClient::new()
+                .post(format!("{}/chat/stream", api_base))
+                .header("accept", "text/event-stream")
+                .json(&body)
+                .send(),
+        )
+        .await;
+        let final_content = match resp {
+            Ok(Ok(r)) if r.status().is_success() => {
+                let assembled = choices::collect_reply(r).await;
+                let (reply, _) = choices::split_choices(&assembled);
+                render_final_text(&reply, self.response_template.as_deref(), &room_name, &char_name)
+            }
+            _ => {
+                error!(room_id = %room_id, error = %"stream send timeout or error", "Streaming request for /ask failed");
+                ask::FAILED_REPLY.to_string()
+            }
+        };
+        let mut parts = ask::split_reply(&final_content, ask::INTERACTION_MESSAGE_LIMIT).into_iter();
+        let first = parts.next().unwrap_or_else(|| ask::EMPTY_REPLY.to_string());
+        if let Err(e) = cmd
+            .edit_response(&ctx.http, EditInteractionResponse::new().content(first))
+            .await
+        {
+            warn!(error = %format!("{:?}", e), "Failed to answer /ask");
+            return;
+        }
+        for part in parts {
+            let followup = CreateInteractionResponseFollowup::new()
+                .content(part)
+                .ephemeral(ask.ephemeral);
+            if let Err(e) = cmd.create_followup(&ctx.http, followup).await {
+                warn!(error = %format!("{:?}", e), "Failed to send /ask follow-up");
+                return;
+            }
+        }
+    }
+
+    /// Room of a channel or thread, as text chat derives it
+    fn channel_room(&self, guild_id: u64, conversation: threads::MessageChannel) -> uuid::Uuid {
+        conversation.current_room(guild_id, &self.channel_characters, &self.context_epochs)
+    }
+
+    /// Apply `/continue` for the invoking user and answer ephemerally
+    ///
+    /// Linking the DM into a server channel is only held until the user
+    /// confirms it with the buttons of the reply.
+    async fn handle_continue_command(&self, ctx: &Context, cmd: &serenity::model::application::CommandInteraction) {
+        let user_id = cmd.user.id.get();
+        let guild_id = cmd.guild_id.map(|g| g.get()).unwrap_or(0);
+        let here = self.channel_room(
+            guild_id,
+            threads::MessageChannel::from_interaction(cmd.channel_id.get(), cmd.channel.as_ref()),
+        );
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut buttons = None;
+        let reply = match ContinueCommand::from_options(&cmd.data.options) {
+            Err(reason) => reason,
+            Ok(ContinueCommand::Stop) => self.continuity.stop(user_id, here, now_ms).await,
+            Ok(ContinueCommand::Dm) if guild_id == 0 => "We're already in our DM.".to_string(),
+            Ok(ContinueCommand::Dm) => match cmd.user.create_dm_channel(&ctx.http).await {
+                Ok(dm) => {
+                    let dm_room = self.channel_room(0, threads::MessageChannel::channel(dm.id.get()));
+                    self.continuity.link(user_id, here, dm_room, false, now_ms).await
+                }
+                Err(e) => {
+                    warn!(user_id = %user_id, error = %format!("{:?}", e), "Failed to open DM for /continue");
+                    "I couldn't open a DM with you.".to_string()
+                }
+            },
+            Ok(ContinueCommand::From { channel: Some(channel) }) => {
+                // Only channels the user can read may lend their context
+                let resolved = cmd.data.resolved.channels.get(&ChannelId::new(channel));
+                let visible = resolved
+                    .and_then(|c| c.permissions)
+                    .is_some_and(|p| p.contains(serenity::model::Permissions::VIEW_CHANNEL));
+                if !visible {
+                    "You can't continue a channel you can't read.".to_string()
+                } else {
+                    let source = self.channel_room(
+                        guild_id,
+                        threads::MessageChannel::from_interaction(channel, resolved),
+                    );
+                    self.continuity.link(user_id, source, here, false, now_ms).await
+                }
+            }
+            Ok(ContinueCommand::From { channel: None }) if guild_id == 0 => {
+                "We're already in our DM.".to_string()
+            }
+            Ok(ContinueCommand::From { channel: None }) => match cmd.user.create_dm_channel(&ctx.http).await {
+                Ok(dm) => {
+                    let dm_room = self.channel_room(0, threads::MessageChannel::channel(dm.id.get()));
+                    if continuity::needs_confirmation(true, guild_id == 0) {
+                        let nonce = cmd.id.get();
+                        self.pending_continuations.request(nonce, user_id, dm_room, here);
+                        buttons = Some(continuity::confirmation_buttons(nonce));
+                        continuity::CONFIRMATION_PROMPT.to_string()
+                    } else {
+                        self.continuity.link(user_id, dm_room, here, true, now_ms).await
+                    }
+                }
+                Err(e) => {
+                    warn!(user_id = %user_id, error = %format!("{:?}", e), "Failed to open DM for /continue");
+                    "I couldn't open a DM with you.".to_string()
+                }
+            },
+        };
+        let mut message = CreateInteractionResponseMessage::new().content(reply).ephemeral(true);
+        if let Some(rows) = buttons {
+            message = message.components(rows);
+        }
+        if let Err(e) = cmd.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer /continue");
+        }
+    }
+
+    /// A button of a `/continue from` DM confirmation was clicked
+    async fn handle_continue_confirmation(&self, ctx: &Context, component: &ComponentInteraction) {
+        let user_id = component.user.id.get();
+        let Some(confirmation) = self
+            .pending_continuations
+            .resolve(&component.data.custom_id, user_id)
+        else {
+            return;
+        };
+        let reply = match confirmation {
+            continuity::Confirmation::Confirmed { source_room, target_room } => {
+                let now_ms = chrono::Utc::now().timestamp_millis();
+                self.continuity.link(user_id, source_room, target_room, true, now_ms).await
+            }
+            continuity::Confirmation::Cancelled => "Okay, this channel keeps its own context.".to_string(),
+            continuity::Confirmation::NotAllowed => {
+                self.answer_ephemeral(ctx, component, "This confirmation isn't yours.".to_string())
+                    .await;
+                return;
+            }
+            continuity::Confirmation::Expired => continuity::EXPIRED_REPLY.to_string(),
+        };
+        // Replace the prompt so the buttons can't be clicked again
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().content(reply).components(Vec::new()),
+        );
+        if let Err(e) = component.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer /continue confirmation");
+        }
+    }
+
+    /// A button of a clarifying question was clicked
+    ///
+    /// The buttons are disabled with the chosen one highlighted, then the
+    /// choice's label is sent as the clicking user's message in the prompt's room.
+    async fn handle_choice_click(&self, ctx: &Context, component: &ComponentInteraction) {
+        let user_id = component.user.id.get();
+        let click = self.pending_choices.click(
+            component.message.id.get(),
+            user_id,
+            &component.data.custom_id,
+        );
+        let (prompt, choice) = match click {
+            ChoiceClick::Chosen { prompt, choice } => (prompt, choice),
+            ChoiceClick::NotAllowed { asker } => {
+                let reply = format!("Only <@{}> can answer this question.", asker);
+                self.answer_ephemeral(ctx, component, reply).await;
+                return;
+            }
+            ChoiceClick::Expired => {
+                self.answer_ephemeral(ctx, component, choices::EXPIRED_REPLY.to_string())
+                    .await;
+                return;
+            }
+        };
+        let rows = choices::buttons(prompt.room_id, &prompt.choices, Some(&choice.id), true);
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().components(rows),
+        );
+        if let Err(e) = component.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to acknowledge choice");
+        }
+        info!(room_id = %prompt.room_id, user_id = %user_id, choice = %choice.id, "Choice selected");
+
+        let api_base = std::env::var("AGENT_API_URL")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "http://127.0.0.1:9090/agent".to_string());
+        let entity_id = zoey_core::string_to_uuid(&format!("discord-user-{}", user_id));
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("choice_id".into(), serde_json::json!(choice.id));
+        self.user_prefs.annotate(user_id, &mut metadata).await;
+        self.continuity.annotate(user_id, prompt.room_id, &mut metadata).await;
+        let body = serde_json::json!({
+            "text": choice.label,
+            "roomId": prompt.room_id,
+            "entityId": entity_id,
+            "character": prompt.character,
+            "stream": true,
+            "metadata": metadata,
+        });
+
+        let replies = DiscordReplyChannel { http: &ctx.http, channel: component.channel_id };
+        let placeholder_id = send_placeholder(&replies, &self.placeholder).await;
+        let resp = tokio::time::timeout(
+            Duration::from_secs(
+                std::env::var("DISCORD_STREAM_REQUEST_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or(20),
+            ),
+            HttpClient::new()
+                .post(format!("{}/chat/stream", api_base))
+                .header("accept", "text/event-stream")
+                .json(&body)
+                .send(),
+        )
+        .await;
+        let assembled = match resp {
+            Ok(Ok(r)) => choices::collect_reply(r).await,
+            _ => {
+                error!(error = %"stream send timeout or error", "Streaming request for choice failed");
+                "Error".to_string()
+            }
+        };
+        let (reply, offered) = choices::split_choices(&assembled);
+        let final_content = render_final_text(
+            &reply,
+            self.response_template.as_deref(),
+            &prompt.room_name,
+            &prompt.character_name,
+        );
+        let reply_id = deliver_final(&replies, placeholder_id, &final_content).await;
+        let follow_up = ChoicePrompt { asker: user_id, choices: offered, ..prompt };
+        choices::offer(&ctx.http, component.channel_id, reply_id, &self.pending_choices, follow_up)
+            .await;
+    }
+
+    async fn answer_ephemeral(&self, ctx: &Context, component: &ComponentInteraction, reply: String) {
+        let response = CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(reply).ephemeral(true),
+        );
+        if let Err(e) = component.create_response(&ctx.http, response).await {
+            warn!(error = %format!("{:?}", e), "Failed to answer choice click");
+        }
+    }
 }
 
 #[serenity_async_trait]
@@ -248,54 +1187,20 @@ impl serenity::prelude::EventHandler for Handler {
                     let gid = guild_id.get();
                     let uid = msg.author.id.get();
                     
-                    // First check our custom voice state tracker (most reliable)
-                    let user_voice_channel: Option<u64> = {
-                        let states = self.voice_states.read().unwrap();
-                        let custom_channel = states.get(&(gid, uid)).copied();
-                        
-                        if let Some(cid) = custom_channel {
-                            info!(
-                                guild_id = %gid,
-                                user_id = %uid,
-                                channel_id = %cid,
-                                "Found user's voice channel in custom tracker"
-                            );
-                            Some(cid)
-                        } else {
-                            // Fallback to serenity cache
-                            info!(
-                                guild_id = %gid,
-                                user_id = %uid,
-                                tracked_states = %states.len(),
-                                "User not in custom tracker, checking serenity cache"
-                            );
-                            drop(states); // Release lock before cache access
-                            
-                            if let Some(guild) = ctx.cache.guild(guild_id) {
-                                info!(
-                                    guild_name = %guild.name,
-                                    voice_states_count = %guild.voice_states.len(),
-                                    "Checking guild cache for voice states"
-                                );
-                                
-                                // Log all voice states for debugging
-                                for (uid, vs) in guild.voice_states.iter() {
-                                    info!(
-                                        user_id = %uid.get(),
-                                        channel_id = ?vs.channel_id.map(|c| c.get()),
-                                        "Voice state in serenity cache"
-                                    );
-                                }
-                                
-                                guild.voice_states.get(&msg.author.id)
-                                    .and_then(|vs| vs.channel_id)
-                                    .map(|c| c.get())
-                            } else {
-                                info!("Guild not found in serenity cache");
-                                None
-                            }
-                        }
-                    };
+                    // Custom voice state tracker first (most reliable), then the guild's
+                    // cached voice states; neither needs the user cache
+                    let tracked = self.voice_states.channel_of(gid, uid).await.unwrap_or_else(|e| {
+                        warn!(guild_id = %gid, user_id = %uid, error = %e, "Voice state lookup failed");
+                        None
+                    });
+                    let user_voice_channel = cache::voice_channel_of(tracked, &ctx.cache, gid, uid);
+                    info!(
+                        guild_id = %gid,
+                        user_id = %uid,
+                        channel_id = ?user_voice_channel,
+                        tracked = %tracked.is_some(),
+                        "Looked up user's voice channel"
+                    );
                     
                     let vm = voice_manager.clone();
                     let http = ctx.http.clone();
@@ -303,132 +1208,98 @@ impl serenity::prelude::EventHandler for Handler {
                     let channel_id_for_voice = msg.channel_id.get();
                     let guild_id_for_voice = gid;
                     
-                    // Get character name for wake word detection
+                    // Get the character mapped to the triggering text channel for wake word detection
+                    let mapped_character = self.channel_characters.resolve(gid, channel_id_for_voice);
                     let char_name_for_voice = {
                         let rt_guard = self.runtime.read().unwrap();
-                        rt_guard.character.name.clone()
+                        self.channel_characters.name_for(gid, channel_id_for_voice, &rt_guard.character.name)
                     };
+                    let request_character = mapped_character.clone().unwrap_or_else(|| char_name_for_voice.clone());
                     
+                    // Track active conversations per user (persistent mode: once name is detected, keep conversation active)
+                    let active_conversations = self.voice_conversations.clone();
+                    let persistent_conversation = vm.config.discord.persistent_conversation;
+                    let latency = vm.latency.clone();
+                    let conversation_timeout_secs = vm.config.discord.conversation_timeout_secs;
+                    let listen_modes = self.listen_modes.clone();
+                    let push_to_talk = self.push_to_talk.clone();
+                    let display_names = self.display_names.clone();
+                    let user_prefs = self.user_prefs.clone();
+                    let continuity = self.continuity.clone();
+                    let context_epochs = self.context_epochs.clone();
+                    let name_source = Arc::new(cache::SerenityNames::new(ctx.cache.clone(), ctx.http.clone()));
+                    let voice_states = self.voice_states.clone();
+                    let bot_id = ctx.cache.current_user().id.get();
+
                     if let Some(cid) = user_voice_channel {
                         // User found in voice channel - spawn task to join
                         tokio::spawn(async move {
                             info!(channel_id = %cid, "Joining voice channel");
                             
+                            let join_mode = listen_modes.mode(gid);
+
                             // Create transcription callback that routes to agent when name is mentioned
                             let char_name = char_name_for_voice.clone();
+                            let wake_word = WakeWordMatcher::new(&char_name);
                             let api_base = std::env::var("AGENT_API_URL")
                                 .ok()
                                 .filter(|s| !s.trim().is_empty())
                                 .unwrap_or_else(|| "http://127.0.0.1:9090/agent".to_string());
                             
-                            // Track active conversations per user (persistent mode: once name is detected, keep conversation active)
-                            // Maps user_id -> last interaction timestamp
-                            let active_conversations: std::sync::Arc<std::sync::RwLock<std::collections::HashMap<u64, std::time::Instant>>> = 
-                                std::sync::Arc::new(std::sync::RwLock::new(std::collections::HashMap::new()));
-                            const CONVERSATION_TIMEOUT_SECS: u64 = 45; // Conversation stays active for 45 seconds after last interaction
-                            
-                            let callback: voice::TranscriptionCallback = Box::new(move |user_id, text| {
+                            let callback: voice::TranscriptionCallback = Box::new(move |user_id, text, turn| {
                                 let char_name = char_name.clone();
+                                let wake_word = wake_word.clone();
+                                let mapped_character = mapped_character.clone();
+                                let request_character = request_character.clone();
                                 let api_base = api_base.clone();
                                 let channel_id = channel_id_for_voice;
                                 let guild_id = guild_id_for_voice;
                                 let active_conversations = active_conversations.clone();
+                                let listen_modes = listen_modes.clone();
+                                let latency = latency.clone();
+                                let display_names = display_names.clone();
+                                let user_prefs = user_prefs.clone();
+                                let continuity = continuity.clone();
+                                let context_epochs = context_epochs.clone();
+                                let name_source = name_source.clone();
                                 
                                 Box::pin(async move {
-                                    // Check if user is in an active conversation (within timeout window)
-                                    let is_in_active_conversation = {
-                                        let convs = active_conversations.read().unwrap();
-                                        if let Some(last_interaction) = convs.get(&user_id) {
-                                            last_interaction.elapsed().as_secs() < CONVERSATION_TIMEOUT_SECS
-                                        } else {
-                                            false
-                                        }
-                                    };
+                                    // Check if user is in an active conversation (within timeout window);
+                                    // with persistent mode off every turn needs the wake word
+                                    // (a 🎙️ push-to-talk window counts either way)
+                                    let is_in_active_conversation =
+                                        active_conversations.is_active(user_id, persistent_conversation);
                                     
-                                    // Check if the transcribed text mentions the bot's name
-                                    // Handle STT variations: "Zoey" might be transcribed as "Zoe", "Zowie", etc.
-                                    let text_lower = text.to_lowercase();
-                                    let char_name_lower = char_name.to_lowercase();
+                                    // Check if the transcribed text mentions the channel's character
+                                    let mentioned = wake_word.matches(&text);
                                     
-                                    // Generate comprehensive name variants for fuzzy matching
-                                    let name_variants: Vec<String> = {
-                                        let mut variants = vec![char_name_lower.clone()];
-                                        
-                                        // For names ending in 'ey' (like Zoey)
-                                        if char_name_lower.ends_with("ey") {
-                                            let base = &char_name_lower[..char_name_lower.len()-2];
-                                            variants.push(format!("{}e", base));      // zoey -> zoe
-                                            variants.push(format!("{}ie", base));     // zoey -> zoie
-                                            variants.push(format!("{}owie", base));   // zoey -> zowie
-                                            variants.push(format!("{}oy", base));     // zoey -> zoy
-                                            variants.push(format!("{}oi", base));     // zoey -> zoi
-                                            variants.push(format!("{}o e", base));    // zoey -> zo e (space)
-                                            variants.push(format!("{}o-i", base));    // zoey -> zo-i (hyphen)
-                                            // Also check the base itself (zo)
-                                            if base.len() >= 2 {
-                                                variants.push(base.to_string());
-                                            }
-                                        } else if char_name_lower.ends_with("y") {
-                                            // For names ending in 'y' (like Joey, Amy)
-                                            let base = &char_name_lower[..char_name_lower.len()-1];
-                                            variants.push(base.to_string());          // joey -> joe
-                                            variants.push(format!("{}ie", base));     // joey -> joie
-                                            variants.push(format!("{}i", base));      // joey -> joi
+                                    // In wake-word mode process if name is mentioned OR user is in active conversation;
+                                    // the guild's /listen mode can answer everything or nothing instead
+                                    if !listen_modes.should_respond(guild_id, mentioned, is_in_active_conversation) {
+                                        // Not a conversation turn, so no latency report
+                                        if let Some(id) = turn {
+                                            latency.discard(id);
                                         }
-                                        
-                                        // Remove duplicates
-                                        variants.sort();
-                                        variants.dedup();
-                                        
-                                        variants
-                                    };
-                                    
-                                    // More flexible matching: check for variants OR partial matches
-                                    let mentioned = {
-                                        // First try exact variant matches (case-insensitive substring match)
-                                        let exact_match = name_variants.iter().any(|variant| {
-                                            // Check if variant appears anywhere in the text (as substring)
-                                            text_lower.contains(variant)
-                                        });
-                                        
-                                        if exact_match {
-                                            true
-                                        } else {
-                                            // Fallback: check for partial phonetic matches
-                                            // If name is 4+ chars, check if first 3-4 chars match anywhere
-                                            if char_name_lower.len() >= 4 {
-                                                let prefix = &char_name_lower[..char_name_lower.len().min(4)];
-                                                // Check if prefix appears and is likely the name (not just a common word)
-                                                let words: Vec<&str> = text_lower.split_whitespace().collect();
-                                                words.iter().any(|word| {
-                                                    word.starts_with(prefix) && word.len() <= prefix.len() + 2
-                                                })
-                                            } else {
-                                                false
-                                            }
-                                        }
-                                    };
-                                    
-                                    // Process message if name is mentioned OR user is in active conversation
-                                    if !mentioned && !is_in_active_conversation {
-                                        // Only log debug when not in active conversation to reduce noise
                                         return None;
                                     }
                                     
                                     // Update conversation timestamp (user is actively engaged)
-                                    {
-                                        let mut convs = active_conversations.write().unwrap();
-                                        convs.insert(user_id, std::time::Instant::now());
-                                    }
+                                    active_conversations.record_turn(
+                                        user_id,
+                                        Duration::from_secs(conversation_timeout_secs),
+                                    );
                                     
                                     if mentioned {
                                         eprintln!("[{}][voice] Processing: '{}' - name detected! (conversation activated)", char_name, text);
+                                    } else if !is_in_active_conversation {
+                                        eprintln!("[{}][voice] Processing: '{}' - always-on listening", char_name, text);
                                     } else {
                                         eprintln!("[{}][voice] Processing: '{}' - continuing active conversation", char_name, text);
                                     }
                                     
                                     // Build room ID consistent with text chat
-                                    let room_id = zoey_core::string_to_uuid(&format!("discord-room-{}-{}", guild_id, channel_id));
+                                    let epoch = context_epochs.current(guild_id, channel_id);
+                                    let room_id = characters::room_uuid_at(guild_id, channel_id, mapped_character.as_deref(), epoch);
                                     let entity_id = zoey_core::string_to_uuid(&format!("discord-voice-user-{}", user_id));
                                     
                                     // Use streaming endpoint (like text chat) - much faster and more reliable than task polling
@@ -436,12 +1307,31 @@ impl serenity::prelude::EventHandler for Handler {
                                         .timeout(std::time::Duration::from_secs(30)) // Reduced timeout for faster failure detection
                                         .build()
                                         .unwrap_or_else(|_| reqwest::Client::new());
+                                    let mut metadata = serde_json::Map::new();
+                                    // Users are not cached by default, so this may cost a REST call
+                                    if let Some(name) = display_names.resolve(name_source.as_ref(), guild_id, user_id).await {
+                                        metadata.insert("userName".into(), serde_json::json!(name));
+                                    }
+                                    // Lets backend logs be correlated with the turn's latency report
+                                    if let Some(id) = turn {
+                                        metadata.insert("voiceTurnId".into(), serde_json::json!(id.get()));
+                                    }
+                                    user_prefs.annotate(user_id, &mut metadata).await;
+                                    continuity.annotate(user_id, room_id, &mut metadata).await;
                                     let body = serde_json::json!({
                                         "text": text,
                                         "roomId": room_id,
                                         "entityId": entity_id,
-                                        "stream": true
+                                        "character": request_character,
+                                        "stream": true,
+                                        "metadata": metadata
                                     });
+                                    let mark = |m: voice::TurnMark| {
+                                        if let Some(id) = turn {
+                                            latency.mark(id, m);
+                                        }
+                                    };
+                                    mark(voice::TurnMark::LlmStart);
                                     
                                     // Call streaming endpoint with timeout (reduced from 60s to 30s for faster responses)
                                     let mut stream_resp = match tokio::time::timeout(
@@ -497,9 +1387,11 @@ impl serenity::prelude::EventHandler for Handler {
                                                 let is_final = json.get("final").and_then(|v| v.as_bool()).unwrap_or(false);
                                                 let text_chunk = json.get("text").and_then(|v| v.as_str()).unwrap_or("");
                                                 if !text_chunk.is_empty() {
+                                                    mark(voice::TurnMark::LlmFirstToken);
                                                     assembled.push_str(text_chunk);
                                                 }
                                                 if is_final {
+                                                    mark(voice::TurnMark::LlmEnd);
                                                     let clean_text = extract_final_text_from_xml(&assembled);
                                                     if !clean_text.is_empty() {
                                                         eprintln!("[{}][voice] Response: '{}'", char_name, clean_text);
@@ -513,6 +1405,7 @@ impl serenity::prelude::EventHandler for Handler {
                                     }
                                     
                                     // If we got here, try to extract final text from what we assembled
+                                    mark(voice::TurnMark::LlmEnd);
                                     if !assembled.is_empty() {
                                         let clean_text = extract_final_text_from_xml(&assembled);
                                         if !clean_text.is_empty() {
@@ -526,18 +1419,86 @@ impl serenity::prelude::EventHandler for Handler {
                                 })
                             });
                             
+                            // On a Stage channel answers wait for the floor, falling back to text here
+                            let stage_channel = stage::stage_channel(&http, cid).await;
+                            let callback = match &stage_channel {
+                                Some(_) => {
+                                    vm.stage.join(gid, cid, channel_id_for_voice);
+                                    stage_text_fallback(callback, vm.stage.clone(), gid, http.clone())
+                                }
+                                None => {
+                                    vm.stage.leave(gid);
+                                    callback
+                                }
+                            };
+
                             let vm_clone = vm.clone();
                             match vm_clone.join_channel_with_callback(gid, cid, Some(callback)).await {
                                 Ok(_) => {
                                     info!("Successfully joined voice channel with transcription callback");
-                                    let listen_msg = if vm.config.discord.listen_enabled {
-                                        "🎤 Joining voice channel! Say my name to start chatting - I'll remember our conversation for 45 seconds!"
+                                    // Who else is there, so an emptied channel is noticed
+                                    let mut members = voice_states.members_of(gid, cid).await.unwrap_or_else(|e| {
+                                        warn!(guild_id = %gid, error = %e, "Voice channel members lookup failed");
+                                        Vec::new()
+                                    });
+                                    members.retain(|&member| member != bot_id);
+                                    members.push(uid);
+                                    vm.note_join(gid, channel_id_for_voice, members).await;
+                                    let offer_push_to_talk = vm.config.discord.listen_enabled
+                                        && push_to_talk.is_enabled()
+                                        && join_mode == ListenMode::WakeWord;
+                                    let mut listen_msg = if !vm.config.discord.listen_enabled {
+                                        "🎤 Joining voice channel!".to_string()
+                                    } else if join_mode == ListenMode::AlwaysOn {
+                                        "🎤 Joining voice channel! I'm listening to everything - use /listen to go back to wake-word mode.".to_string()
+                                    } else if persistent_conversation {
+                                        format!(
+                                            "🎤 Joining voice channel! Say my name to start chatting - I'll remember our conversation for {} seconds!",
+                                            conversation_timeout_secs
+                                        )
                                     } else {
-                                        "🎤 Joining voice channel!"
+                                        "🎤 Joining voice channel! Say my name whenever you want me to answer.".to_string()
                                     };
-                                    let _ = reply_channel.say(&http, listen_msg).await;
+                                    if let Some(channel) = &stage_channel {
+                                        let note = match stage::take_the_floor(&http, channel).await {
+                                            Ok(stage::FloorRequest::Promoted) => {
+                                                " I'm on the stage as a speaker.".to_string()
+                                            }
+                                            Ok(stage::FloorRequest::Requested) => {
+                                                vm.stage.request_sent(gid);
+                                                format!(
+                                                    " I've asked to speak on the stage - until a moderator invites me (or someone uses /stage invite), \
+                                                    I'll answer here after {} seconds.",
+                                                    vm.stage.speak_timeout().as_secs()
+                                                )
+                                            }
+                                            Err(e) => {
+                                                warn!(guild_id = %gid, channel_id = %cid, error = %format!("{:?}", e), "Stage request to speak failed");
+                                                " I couldn't ask to speak on the stage, so I'll answer here in text.".to_string()
+                                            }
+                                        };
+                                        listen_msg.push_str(&note);
+                                    }
+                                    if offer_push_to_talk {
+                                        listen_msg.push_str(&format!(
+                                            " React with {} to talk without saying my name for {} seconds.",
+                                            push_to_talk::LISTEN_REACTION,
+                                            push_to_talk.window().as_secs()
+                                        ));
+                                    }
+                                    let sent = reply_channel.say(&http, listen_msg).await;
+                                    if let (true, Ok(sent)) = (offer_push_to_talk, sent) {
+                                        push_to_talk.register_prompt(sent.id.get(), gid, cid);
+                                        let reaction = serenity::model::channel::ReactionType::Unicode(
+                                            push_to_talk::LISTEN_REACTION.to_string(),
+                                        );
+                                        if let Err(e) = sent.react(&http, reaction).await {
+                                            warn!(error = %format!("{:?}", e), "Failed to add listening reaction");
+                                        }
+                                    }
                                 }
                                 Err(e) => {
+                                    vm.stage.leave(gid);
                                     warn!(error = %e, "Failed to join voice channel");
                                     let _ = reply_channel
                                         .say(&http, format!("Could not join voice: {}", e))
@@ -564,6 +1525,10 @@ impl serenity::prelude::EventHandler for Handler {
             }
         }
 
+        // Threads scope their own room but follow their parent channel's settings
+        self.threads.note_message(msg.channel_id.get(), Instant::now());
+        let conversation = self.threads.resolve(&ctx, msg.guild_id, msg.channel_id).await;
+
         // Capture minimal data needed for the worker thread
         let msg_content = msg.content.clone();
         let msg_id = msg.id.get();
@@ -572,6 +1537,7 @@ impl serenity::prelude::EventHandler for Handler {
         let author_id = msg.author.id.get();
         let mentions: Vec<u64> = msg.mentions.iter().map(|u| u.id.get()).collect();
         let mention_roles: Vec<u64> = msg.mention_roles.iter().map(|r| r.get()).collect();
+        let msg_attachments: Vec<AttachmentInfo> = msg.attachments.iter().map(AttachmentInfo::from).collect();
 
         // Log voice trigger check result before spawning worker thread
         let voice_enabled_check = voice_manager.is_enabled();
@@ -585,10 +1551,30 @@ impl serenity::prelude::EventHandler for Handler {
         let token = self.token.clone();
         let limiter = self.limiter.clone();
         let application_id = self.application_id;
-        let allowed_guilds = self.allowed_guilds.clone();
-        let allowed_channels = self.allowed_channels.clone();
-        let allowed_users = self.allowed_users.clone();
+        let filters = self.filters.clone();
+        let state_store = self.state_store.clone();
         let voice_mgr = voice_manager.clone();
+        let channel_characters = self.channel_characters.clone();
+        let context_epochs = self.context_epochs.clone();
+        let typing_refresh_interval = self.typing_refresh_interval;
+        let typing_max_duration = self.typing_max_duration;
+        let url_ingestion = self.url_ingestion;
+        let pending_links = self.pending_links.clone();
+        let pending_choices = self.pending_choices.clone();
+        let response_template = self.response_template.clone();
+        let placeholder = self.placeholder.clone();
+        let reply_fallback = self.reply_fallback.clone();
+        let user_prefs = self.user_prefs.clone();
+        let continuity = self.continuity.clone();
+        let threads = self.threads.clone();
+        let attachment_config = self.attachments;
+        let is_character_admin = self.admin_users.contains(&author_id)
+            || msg
+                .guild_id
+                .and_then(|g| ctx.cache.guild(g).map(|guild| guild.owner_id == msg.author.id))
+                .unwrap_or(false);
+        let can_reset_context = self.admin_users.contains(&author_id)
+            || epochs::can_reset(guild_id_raw, author_permissions(&ctx, &msg, conversation.settings_channel()));
 
         // Spawn worker thread with large stack - all heavy work happens here
         std::thread::Builder::new()
@@ -608,18 +1594,15 @@ impl serenity::prelude::EventHandler for Handler {
                         return;
                     }
                     
-                    // Dedup check
-                    static ACTIVE: OnceLock<Arc<RwLock<std::collections::HashSet<String>>>> = OnceLock::new();
-                    let active_store = ACTIVE
-                        .get_or_init(|| Arc::new(RwLock::new(std::collections::HashSet::new())))
-                        .clone();
-                    let active_key = format!("{}:{}:{}", channel_id_raw, author_id, msg_id);
+                    // Dedup check, shared with other processes through the state store
+                    let dedup_key = format!("{}:{}:{}", channel_id_raw, author_id, msg_id);
+                    match state_store
+                        .set_if_absent(DEDUP_NAMESPACE, &dedup_key, serde_json::Value::Bool(true), Some(DEDUP_TTL))
+                        .await
                     {
-                        let mut guard = active_store.write().unwrap();
-                        if guard.contains(&active_key) {
-                            return;
-                        }
-                        guard.insert(active_key.clone());
+                        Ok(true) => {}
+                        Ok(false) => return,
+                        Err(e) => warn!(key = %dedup_key, error = %e, "Dedup check failed, handling message anyway"),
                     }
                     
                     // Rate limiting
@@ -628,35 +1611,52 @@ impl serenity::prelude::EventHandler for Handler {
                         return;
                     }
                     
-                    // Guild/channel/user filters
-                    if let Some(ref set) = allowed_guilds {
-                        debug!(guild_id = %guild_id_raw, allowed = ?set, "Checking guild filter");
-                        if guild_id_raw != 0 && !set.contains(&guild_id_raw) {
-                            debug!(guild_id = %guild_id_raw, "Filtered out - guild not in allowed list");
-                            return;
-                        }
+                    // Guild/channel/user filters (a thread's parent decides)
+                    let settings_channel = conversation.settings_channel();
+                    if let Some(reason) = filters.rejection(guild_id_raw, settings_channel, author_id) {
+                        debug!(guild_id = %guild_id_raw, channel_id = %channel_id_raw, author_id = %author_id, "Filtered out - {}", reason);
+                        return;
                     }
-                    if let Some(ref set) = allowed_channels {
-                        debug!(channel_id = %channel_id_raw, allowed = ?set, "Checking channel filter");
-                        if guild_id_raw != 0 && !set.contains(&channel_id_raw) {
-                            debug!(channel_id = %channel_id_raw, "Filtered out - channel not in allowed list");
-                            return;
-                        }
+                    
+                    // Channel character admin command
+                    if let Some(command) = CharacterCommand::parse(&msg_content) {
+                        let http = Http::new(&token);
+                        let reply = handle_character_command(
+                            &channel_characters,
+                            command,
+                            guild_id_raw,
+                            settings_channel,
+                            is_character_admin,
+                        )
+                        .await;
+                        let _ = ChannelId::new(channel_id_raw).say(&http, reply).await;
+                        return;
                     }
-                    if let Some(ref set) = allowed_users {
-                        debug!(author_id = %author_id, allowed = ?set, "Checking user filter");
-                        if !set.contains(&author_id) {
-                            debug!(author_id = %author_id, "Filtered out - user not in allowed list");
-                            return;
-                        }
+                    
+                    // `!reset` starts a fresh conversation, like `/reset`
+                    if epochs::is_reset_text(&msg_content) {
+                        let http = Http::new(&token);
+                        let message = if !can_reset_context {
+                            CreateMessage::new().content(RESET_DENIED_REPLY)
+                        } else {
+                            match reset_context(&context_epochs, &channel_characters, guild_id_raw, &conversation).await {
+                                Ok(embed) => CreateMessage::new().embed(embed),
+                                Err(reply) => CreateMessage::new().content(reply),
+                            }
+                        };
+                        let _ = ChannelId::new(channel_id_raw).send_message(&http, message).await;
+                        return;
                     }
                     
-                    // Get agent info
+                    // Get agent info, using the character mapped to this channel if any
+                    let mapped_character = channel_characters.resolve(guild_id_raw, settings_channel);
                     let (agent_id, world_id, char_name) = {
                         let rt_guard = runtime.read().unwrap();
-                        let world_id = zoey_core::string_to_uuid(&format!("discord-guild-{}", guild_id_raw));
-                        (rt_guard.agent_id, world_id, rt_guard.character.name.clone())
+                        let world_id = characters::guild_world_id(guild_id_raw);
+                        let char_name = channel_characters.name_for(guild_id_raw, settings_channel, &rt_guard.character.name);
+                        (rt_guard.agent_id, world_id, char_name)
                     };
+                    let request_character = mapped_character.clone().unwrap_or_else(|| char_name.clone());
                     
                     // Safe UTF-8 truncation helper - finds valid char boundary
                     fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
@@ -712,10 +1712,7 @@ impl serenity::prelude::EventHandler for Handler {
                         false // Don't respond to arbitrary role mentions without configured role
                     };
                     
-                    let in_allowed_channel = allowed_channels
-                        .as_ref()
-                        .map(|set| set.contains(&channel_id_raw))
-                        .unwrap_or(false);
+                    let in_allowed_channel = filters.channel_listed(guild_id_raw, settings_channel);
                     
                     // Determine if addressed to bot
                     let addressed_to_me = is_dm || mentioned_struct || mentioned_inline || mentioned_by_name || has_role_mention || in_allowed_channel;
@@ -728,23 +1725,49 @@ impl serenity::prelude::EventHandler for Handler {
                         is_dm, mentioned_struct, mentioned_inline, mentioned_by_name, has_role_mention, in_allowed_channel, addressed_to_me
                     );
                     
-                    // Only show typing indicator if we're going to respond
+                    // Only show typing indicator if we're going to respond, and keep it
+                    // alive until the first streamed chunk arrives
+                    let mut typing_refresh = None;
                     if addressed_to_me {
                         let _ = ChannelId::new(channel_id_raw).broadcast_typing(&http).await;
+                        let typing_http = Arc::new(Http::new(&token));
+                        typing_refresh = Some(TypingRefresh::start(
+                            typing_refresh_interval,
+                            typing_max_duration,
+                            move || {
+                                let http = typing_http.clone();
+                                async move {
+                                    let _ = ChannelId::new(channel_id_raw).broadcast_typing(&http).await;
+                                }
+                            },
+                        ));
                     } else {
                         eprintln!("[{}][discord] Ignoring - not addressed to bot", char_name);
                         return;
                     }
                     
+                    // Too many or too large images get a reply asking to resend instead of an answer
+                    let attachments = match MessageAttachments::sort(msg_attachments, &attachment_config) {
+                        Ok(attachments) => attachments,
+                        Err(reply) => {
+                            if let Some(refresh) = typing_refresh.take() {
+                                refresh.stop();
+                            }
+                            let _ = ChannelId::new(channel_id_raw).say(&http, reply).await;
+                            return;
+                        }
+                    };
+                    let request_text = attachments.annotate(&msg_content);
+                    
                     // Build room and memory
-                    // Use deterministic room ID based on channel for consistent conversation history
-                    let room_id = zoey_core::string_to_uuid(&format!("discord-room-{}-{}", guild_id_raw, channel_id_raw));
+                    // Use deterministic room ID based on channel or thread (and mapped character) for consistent conversation history
+                    let room_id = conversation.current_room(guild_id_raw, &channel_characters, &context_epochs);
                     let room = Room {
                         id: room_id,
                         agent_id: Some(agent_id),
                         name: format!("discord-{}-{}", guild_id_raw, channel_id_raw),
                         source: "discord".to_string(),
-                        channel_type: if is_dm { ChannelType::Dm } else { ChannelType::GuildText },
+                        channel_type: conversation.channel_type(is_dm),
                         channel_id: Some(channel_id_raw.to_string()),
                         server_id: if guild_id_raw != 0 { Some(guild_id_raw.to_string()) } else { None },
                         world_id,
@@ -756,12 +1779,17 @@ impl serenity::prelude::EventHandler for Handler {
                     let entity_id = zoey_core::string_to_uuid(&format!("discord-user-{}", author_id));
                     
                     let mut content = Content {
-                        text: msg_content.clone(),
+                        text: request_text.clone(),
                         source: Some("discord".to_string()),
-                        channel_type: Some(if is_dm { "DM".to_string() } else { "GUILD_TEXT".to_string() }),
+                        channel_type: Some(conversation.channel_type_label(is_dm).to_string()),
                         ..Default::default()
                     };
                     content.metadata.insert("addressed_to_me".to_string(), serde_json::Value::Bool(addressed_to_me));
+                    content.metadata.insert("character".to_string(), serde_json::Value::String(request_character.clone()));
+                    if !attachments.is_empty() {
+                        content.attachments = Some(attachments.media());
+                        content.metadata.insert("image_count".to_string(), serde_json::json!(attachments.images.len()));
+                    }
                     
                     let memory = Memory {
                         id: uuid::Uuid::new_v4(),
@@ -776,17 +1804,6 @@ impl serenity::prelude::EventHandler for Handler {
                         similarity: None,
                     };
                     
-                    // Send placeholder message
-                    let ch = ChannelId::new(channel_id_raw);
-                    let placeholder_id: Option<u64> = if addressed_to_me || is_dm {
-                        match ch.say(&http, "").await {
-                            Ok(m) => Some(m.id.get()),
-                            Err(_) => None,
-                        }
-                    } else {
-                        None
-                    };
-                    
                     // Setup HTTP client and API base
                     let api_base = std::env::var("AGENT_API_URL")
                         .ok()
@@ -802,14 +1819,109 @@ impl serenity::prelude::EventHandler for Handler {
                             .unwrap_or_else(|_| HttpClient::new())
                     }).clone();
                     
+                    // Fetch linked pages into the channel's knowledge before answering
+                    let urls = if url_ingestion == UrlIngestion::Off {
+                        Vec::new()
+                    } else {
+                        links::extract_urls(&msg_content)
+                    };
+                    let approved_urls = match url_ingestion {
+                        _ if urls.is_empty() => None,
+                        UrlIngestion::Auto => Some(urls),
+                        UrlIngestion::Ask => {
+                            let prompt = links::ask_prompt(&urls, pending_links.timeout());
+                            match ChannelId::new(channel_id_raw).say(&http, prompt).await {
+                                Ok(prompt_msg) => {
+                                    let prompt_id = prompt_msg.id.get();
+                                    let confirmed = pending_links.ask(prompt_id, author_id, urls);
+                                    let _ = prompt_msg
+                                        .react(&http, serenity::model::channel::ReactionType::Unicode(links::ASK_REACTION.to_string()))
+                                        .await;
+                                    pending_links.wait(prompt_id, confirmed).await
+                                }
+                                Err(_) => None,
+                            }
+                        }
+                        UrlIngestion::Off => None,
+                    };
+                    let ingested_urls = match approved_urls {
+                        Some(urls) => {
+                            links::ingest_links(&LinkFetcher::default(), &client, &api_base, room.id, entity_id, &urls).await
+                        }
+                        None => Vec::new(),
+                    };
+                    
+                    // Send placeholder message, in a fallback channel if this one is read-only
+                    let placement = if addressed_to_me || is_dm {
+                        let origin = Origin {
+                            guild_id: guild_id_raw,
+                            channel_id: channel_id_raw,
+                            user_id: author_id,
+                            message_id: msg_id,
+                        };
+                        reply_fallback.place(&fallback::DiscordRoutes { http: &http }, &origin, &placeholder).await
+                    } else {
+                        Placement::origin(channel_id_raw)
+                    };
+                    let Some(reply_channel_id) = placement.channel_id else {
+                        // Nowhere to answer; the 🚫 reaction (if any) is all the user gets
+                        if let Some(refresh) = typing_refresh.take() {
+                            refresh.stop();
+                        }
+                        return;
+                    };
+                    let ch = ChannelId::new(reply_channel_id);
+                    let placeholder_id = placement.placeholder;
+                    // Where the final reply goes: here, or a thread started from the user's message
+                    let reply_target = |final_content: &str| {
+                        let open = threads.should_open_thread(
+                            guild_id_raw,
+                            conversation,
+                            reply_channel_id,
+                            final_content.chars().count(),
+                            Instant::now(),
+                        );
+                        let name = threads::thread_name(&msg_content, &char_name);
+                        let (http, threads) = (&http, &threads);
+                        async move {
+                            if open {
+                                if let Some(thread) = threads.open_thread(http, ch, msg_id, placeholder_id, name).await {
+                                    return (thread, None);
+                                }
+                            }
+                            (ch, placeholder_id)
+                        }
+                    };
+                    
                     // Memory persistence is handled by Agent API's /chat/stream endpoint
                     let _ = &runtime; // Keep runtime in scope
-            let body = serde_json::json!({
-                "text": msg.content.clone(),
+                    let choice_prompt = |choices: Vec<Choice>| ChoicePrompt {
+                        room_id: room.id,
+                        room_name: room.name.clone(),
+                        asker: author_id,
+                        character: request_character.clone(),
+                        character_name: char_name.clone(),
+                        choices,
+                    };
+            let mut body = serde_json::json!({
+                "text": request_text,
                 "roomId": room.id,
                 "entityId": memory.entity_id,
+                "character": request_character,
                 "stream": true
             });
+            if !attachments.images.is_empty() {
+                body["images"] = serde_json::json!(attachments.chat_images(&client, &attachment_config).await);
+            }
+            let mut metadata = serde_json::Map::new();
+            if !ingested_urls.is_empty() {
+                metadata.insert("ingested_urls".into(), serde_json::json!(ingested_urls));
+            }
+            user_prefs.annotate(author_id, &mut metadata).await;
+            continuity.annotate(author_id, room.id, &mut metadata).await;
+            if !metadata.is_empty() {
+                body["metadata"] = serde_json::Value::Object(metadata);
+            }
             let resp = tokio::time::timeout(
                 std::time::Duration::from_secs(
                     std::env::var("DISCORD_STREAM_REQUEST_TIMEOUT_SECS")
@@ -871,6 +1983,10 @@ impl serenity::prelude::EventHandler for Handler {
                                 let text = json.get("text").and_then(|v| v.as_str()).unwrap_or("");
                                 if !text.is_empty() {
                                     assembled.push_str(text);
+                                    // First chunk arrived: the placeholder takes over from typing
+                                    if let Some(refresh) = typing_refresh.take() {
+                                        refresh.stop();
+                                    }
                                 }
                                 let now = std::time::Instant::now();
                                 if now.duration_since(last_edit) >= edit_interval {
@@ -879,7 +1995,7 @@ impl serenity::prelude::EventHandler for Handler {
                                             replaced_ack = true;
                                         }
                                         // Extract text content from XML format for display
-                                        let display_text = extract_final_text_from_xml(&assembled);
+                                        let display_text = extract_final_text_from_xml(&choices::split_choices(&assembled).0);
                                         if !display_text.is_empty() {
                                             let _ = ch
                                                 .edit_message(
@@ -895,28 +2011,22 @@ impl serenity::prelude::EventHandler for Handler {
                                 if is_final && !finalized {
                                     finalized = true;
                                     // Extract text content from XML format for final display
-                                    let display_text = extract_final_text_from_xml(&assembled);
-                                    let final_content = if display_text.is_empty() { assembled.clone() } else { display_text.clone() };
+                                    let (reply, offered) = choices::split_choices(&assembled);
+                                    let final_content = render_final_text(&reply, response_template.as_deref(), &room.name, &char_name);
                                     
-                                    // Send final message to Discord
-                                    if let Some(pid) = placeholder_id {
-                                        let _ = ch
-                                            .edit_message(
-                                                &http,
-                                                MessageId::new(pid),
-                                                EditMessage::new().content(final_content.clone()),
-                                            )
-                                            .await;
-                                    } else if !final_content.is_empty() {
-                                        let _ = ch.say(&http, final_content.clone()).await;
-                                    }
+                                    // Send final message to Discord (in a new thread if it is long and the channel busy), with buttons for any choices offered
+                                    let (target, target_placeholder) = reply_target(&final_content).await;
+                                    let replies = DiscordReplyChannel { http: &http, channel: target };
+                                    let reply_id = deliver_final(&replies, target_placeholder, &final_content).await;
+                                    choices::offer(&http, target, reply_id, &pending_choices, choice_prompt(offered)).await;
                                     
-                                    // Speak in voice channel if enabled and in voice
+                                    // Speak in voice channel if enabled and in voice (the bare reply, without the response template)
+                                    let spoken_text = extract_final_text_from_xml(&reply);
                                     if voice_mgr.is_enabled() && 
                                        voice_mgr.config.discord.speak_responses &&
                                        guild_id_raw != 0 &&
-                                       !final_content.is_empty() {
-                                        info!(guild_id = %guild_id_raw, text_len = %final_content.len(), "Attempting TTS speak");
+                                       !spoken_text.is_empty() {
+                                        info!(guild_id = %guild_id_raw, text_len = %spoken_text.len(), "Attempting TTS speak");
                                         // Check if we're in a voice channel for this guild
                                         let sessions = voice_mgr.sessions.read().await;
                                         let in_voice = sessions.contains_key(&guild_id_raw);
@@ -924,7 +2034,7 @@ impl serenity::prelude::EventHandler for Handler {
                                         
                                         if in_voice {
                                             info!(guild_id = %guild_id_raw, "In voice channel, calling speak()");
-                                            match voice_mgr.speak(guild_id_raw, &final_content).await {
+                                            match voice_mgr.speak_answer(guild_id_raw, author_id, &spoken_text, None).await {
                                                 Ok(_) => info!(guild_id = %guild_id_raw, "TTS speak completed successfully"),
                                                 Err(e) => warn!(error = %e, guild_id = %guild_id_raw, "Failed to speak in voice channel"),
                                             }
@@ -941,45 +2051,30 @@ impl serenity::prelude::EventHandler for Handler {
                         if !finalized && last_chunk_at.elapsed() >= inactivity_limit {
                             finalized = true;
                             // Extract text content from XML format
-                            let display_text = extract_final_text_from_xml(&assembled);
-                            if let Some(pid) = placeholder_id {
-                                let final_content = if display_text.is_empty() { assembled.clone() } else { display_text.clone() };
-                                let _ = ch
-                                    .edit_message(
-                                        &http,
-                                        MessageId::new(pid),
-                                        EditMessage::new().content(final_content),
-                                    )
-                                    .await;
-                            } else if !display_text.is_empty() {
-                                let _ = ch.say(&http, display_text).await;
-                            } else if !assembled.is_empty() {
-                                let _ = ch.say(&http, assembled.clone()).await;
-                            }
+                            let (reply, offered) = choices::split_choices(&assembled);
+                            let final_content = render_final_text(&reply, response_template.as_deref(), &room.name, &char_name);
+                            let (target, target_placeholder) = reply_target(&final_content).await;
+                            let replies = DiscordReplyChannel { http: &http, channel: target };
+                            let reply_id = deliver_final(&replies, target_placeholder, &final_content).await;
+                            choices::offer(&http, target, reply_id, &pending_choices, choice_prompt(offered)).await;
                             break;
                         }
                     }
                     // Ensure finalization after stream ends without explicit final
                     if !finalized {
                         // Extract text content from XML format
-                        let display_text = extract_final_text_from_xml(&assembled);
-                        if let Some(pid) = placeholder_id {
-                            let final_content = if display_text.is_empty() { assembled.clone() } else { display_text.clone() };
-                            let _ = ch
-                                .edit_message(
-                                    &http,
-                                    MessageId::new(pid),
-                                    EditMessage::new().content(final_content),
-                                )
-                                .await;
-                        } else if !display_text.is_empty() {
-                            let _ = ch.say(&http, display_text).await;
-                        } else if !assembled.is_empty() {
-                            let _ = ch.say(&http, assembled.clone()).await;
-                        }
+                        let (reply, offered) = choices::split_choices(&assembled);
+                        let final_content = render_final_text(&reply, response_template.as_deref(), &room.name, &char_name);
+                        let (target, target_placeholder) = reply_target(&final_content).await;
+                        let replies = DiscordReplyChannel { http: &http, channel: target };
+                        let reply_id = deliver_final(&replies, target_placeholder, &final_content).await;
+                        choices::offer(&http, target, reply_id, &pending_choices, choice_prompt(offered)).await;
                     }
                 }
                 _ => {
+                    if let Some(refresh) = typing_refresh.take() {
+                        refresh.stop();
+                    }
                     error!(error = %"stream send timeout or error", "Streaming request failed");
                     if let Some(pid) = placeholder_id {
                         let _ = ch
@@ -991,10 +2086,6 @@ impl serenity::prelude::EventHandler for Handler {
                             .await;
                     }
                 }
-            }
-            {
-                let mut guard = active_store.write().unwrap();
-                guard.remove(&active_key);
             }
                 });
             })
@@ -1011,8 +2102,21 @@ impl serenity::prelude::EventHandler for Handler {
             "Guild create: populating initial voice states"
         );
         
-        // Populate our custom voice state tracker with initial voice states
-        let mut states = self.voice_states.write().unwrap();
+        // Load persisted channel -> character overrides and context epochs for this guild
+        let channel_characters = self.channel_characters.clone();
+        let context_epochs = self.context_epochs.clone();
+        tokio::spawn(async move {
+            if let Err(e) = channel_characters.load_guild(guild_id).await {
+                warn!(guild_id = %guild_id, error = %e, "Failed to load channel characters");
+            }
+            if let Err(e) = context_epochs.load_guild(guild_id).await {
+                warn!(guild_id = %guild_id, error = %e, "Failed to load context epochs");
+            }
+        });
+        
+        // Populate our custom voice state tracker with initial voice states.
+        // Guild data is authoritative, so entries from an earlier session are dropped.
+        let mut states = Vec::new();
         for (user_id, voice_state) in guild.voice_states.iter() {
             if let Some(channel_id) = voice_state.channel_id {
                 let uid = user_id.get();
@@ -1023,14 +2127,17 @@ impl serenity::prelude::EventHandler for Handler {
                     channel_id = %cid,
                     "Initial voice state: user already in voice channel"
                 );
-                states.insert((guild_id, uid), cid);
+                states.push((uid, cid));
             }
         }
-        info!(
-            guild_id = %guild_id,
-            tracked_users = %states.len(),
-            "Voice states initialized from guild_create"
-        );
+        match self.voice_states.replace_guild(guild_id, states).await {
+            Ok(tracked) => info!(
+                guild_id = %guild_id,
+                tracked_users = %tracked,
+                "Voice states initialized from guild_create"
+            ),
+            Err(e) => warn!(guild_id = %guild_id, error = %e, "Failed to store initial voice states"),
+        }
     }
 
     async fn ready(&self, ctx: Context, data_about_bot: serenity::model::gateway::Ready) {
@@ -1044,12 +2151,55 @@ impl serenity::prelude::EventHandler for Handler {
         for guild in &data_about_bot.guilds {
             info!(guild_id = %guild.id.get(), "Bot is in guild");
         }
+        #[cfg(feature = "voice")]
+        self.voice_manager.set_name_source(Arc::new(cache::SerenityNames::new(
+            ctx.cache.clone(),
+            ctx.http.clone(),
+        )));
+        // DMs have no guild_create, their epochs are stored under guild 0
+        let context_epochs = self.context_epochs.clone();
+        tokio::spawn(async move {
+            if let Err(e) = context_epochs.load_guild(0).await {
+                warn!(error = %e, "Failed to load DM context epochs");
+            }
+        });
         let http = ctx.http.clone();
+        let voice_enabled = self.voice_manager.is_enabled();
         tokio::spawn(async move {
             let builder = CreateCommand::new("ping").description("A simple ping command");
             if let Err(e) = Command::create_global_command(&http, builder).await {
                 warn!(error = %format!("{:?}", e), "Register global ping failed");
             }
+            if let Err(e) = Command::create_global_command(&http, context::context_command()).await {
+                warn!(error = %format!("{:?}", e), "Register global context failed");
+            }
+            if let Err(e) = Command::create_global_command(&http, prefs::prefs_command()).await {
+                warn!(error = %format!("{:?}", e), "Register global prefs failed");
+            }
+            if let Err(e) = Command::create_global_command(&http, continuity::continue_command()).await {
+                warn!(error = %format!("{:?}", e), "Register global continue failed");
+            }
+            if let Err(e) = Command::create_global_command(&http, filters::zoey_command()).await {
+                warn!(error = %format!("{:?}", e), "Register global zoey failed");
+            }
+            if let Err(e) = Command::create_global_command(&http, ask::ask_command()).await {
+                warn!(error = %format!("{:?}", e), "Register global ask failed");
+            }
+            if let Err(e) = Command::create_global_command(&http, epochs::reset_command()).await {
+                warn!(error = %format!("{:?}", e), "Register global reset failed");
+            }
+            if voice_enabled {
+                if let Err(e) = Command::create_global_command(&http, listen_command()).await {
+                    warn!(error = %format!("{:?}", e), "Register global listen failed");
+                }
+                if let Err(e) = Command::create_global_command(&http, stage_command()).await {
+                    warn!(error = %format!("{:?}", e), "Register global stage failed");
+                }
+                #[cfg(feature = "voice-speaker-id")]
+                if let Err(e) = Command::create_global_command(&http, voice_command()).await {
+                    warn!(error = %format!("{:?}", e), "Register global voice failed");
+                }
+            }
         });
 
         if let Ok(cid_str) = std::env::var("DISCORD_TEST_CHANNEL_ID") {
@@ -1070,28 +2220,147 @@ impl serenity::prelude::EventHandler for Handler {
         let _ = std::env::var("DISCORD_GUILD_ID");
     }
 
+    /// Confirm an Ask-mode link prompt when its author reacts with ✅, or open
+    /// a push-to-talk window on 🎙️
+    async fn reaction_add(&self, ctx: Context, reaction: serenity::model::channel::Reaction) {
+        let (Some(user_id), serenity::model::channel::ReactionType::Unicode(emoji)) =
+            (reaction.user_id, &reaction.emoji)
+        else {
+            return;
+        };
+        if push_to_talk::is_listen_reaction(emoji) {
+            self.toggle_push_to_talk(&ctx, reaction.message_id.get(), user_id.get(), emoji, true)
+                .await;
+            return;
+        }
+        if self.url_ingestion != UrlIngestion::Ask {
+            return;
+        }
+        if self.pending_links.confirm(reaction.message_id.get(), user_id.get(), emoji) {
+            debug!(message_id = %reaction.message_id.get(), "Link ingestion confirmed");
+        }
+    }
+
+    /// Close a push-to-talk window when its 🎙️ reaction is removed
+    async fn reaction_remove(&self, ctx: Context, reaction: serenity::model::channel::Reaction) {
+        let (Some(user_id), serenity::model::channel::ReactionType::Unicode(emoji)) =
+            (reaction.user_id, &reaction.emoji)
+        else {
+            return;
+        };
+        if push_to_talk::is_listen_reaction(emoji) {
+            self.toggle_push_to_talk(&ctx, reaction.message_id.get(), user_id.get(), emoji, false)
+                .await;
+        }
+    }
+
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Component(component) = &interaction {
+            if choices::parse_custom_id(&component.data.custom_id).is_some() {
+                self.handle_choice_click(&ctx, component).await;
+            } else if continuity::parse_custom_id(&component.data.custom_id).is_some() {
+                self.handle_continue_confirmation(&ctx, component).await;
+            }
+            return;
+        }
         if let Interaction::Command(cmd) = interaction {
-            if cmd.data.name == "ping" {
-                let _ = cmd
-                    .create_response(
-                        &ctx.http,
-                        CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::default().content("Pong!"),
-                        ),
-                    )
-                    .await;
+            if cmd.data.name == "context" {
+                self.handle_context_command(&ctx, &cmd).await;
+                return;
+            }
+            if cmd.data.name == "prefs" {
+                self.handle_prefs_command(&ctx, &cmd).await;
+                return;
+            }
+            if cmd.data.name == "continue" {
+                self.handle_continue_command(&ctx, &cmd).await;
+                return;
             }
+            if cmd.data.name == "zoey" {
+                self.handle_zoey_command(&ctx, &cmd).await;
+                return;
+            }
+            if cmd.data.name == ask::ASK_COMMAND {
+                self.handle_ask_command(&ctx, &cmd).await;
+                return;
+            }
+            if cmd.data.name == epochs::RESET_COMMAND {
+                self.handle_reset_command(&ctx, &cmd).await;
+                return;
+            }
+            if cmd.data.name == "stage" {
+                self.handle_stage_command(&ctx, &cmd).await;
+                return;
+            }
+            #[cfg(feature = "voice-speaker-id")]
+            if cmd.data.name == "voice" {
+                self.handle_voice_command(&ctx, &cmd).await;
+                return;
+            }
+            let reply = match cmd.data.name.as_str() {
+                "ping" => "Pong!".to_string(),
+                "listen" => {
+                    let requested = cmd
+                        .data
+                        .options
+                        .iter()
+                        .find(|o| o.name == "mode")
+                        .and_then(|o| o.value.as_str());
+                    handle_listen_command(
+                        &self.listen_modes,
+                        cmd.guild_id.map(|g| g.get()).unwrap_or(0),
+                        requested,
+                    )
+                }
+                _ => return,
+            };
+            let _ = cmd
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::default().content(reply),
+                    ),
+                )
+                .await;
         }
     }
 
     /// Track voice state changes - this is more reliable than serenity's cache
-    async fn voice_state_update(&self, _ctx: Context, old: Option<serenity::model::voice::VoiceState>, new: serenity::model::voice::VoiceState) {
+    async fn voice_state_update(&self, ctx: Context, old: Option<serenity::model::voice::VoiceState>, new: serenity::model::voice::VoiceState) {
         let user_id = new.user_id.get();
         let guild_id = match new.guild_id {
             Some(g) => g.get(),
             None => return, // Ignore DM voice states
         };
+
+        // The bot's own state carries its stage role
+        if new.user_id == ctx.cache.current_user().id {
+            // Disconnected by someone else: stop listening as if it had left
+            #[cfg(feature = "voice")]
+            if new.channel_id.is_none() && self.voice_manager.has_session(guild_id).await {
+                info!(guild_id = %guild_id, "Disconnected from voice, ending the session");
+                let _ = self.voice_manager.leave_channel(guild_id).await;
+            }
+            let stage = &self.voice_manager.stage;
+            let on_stage = new.channel_id.map(|c| c.get()) == stage.channels(guild_id).map(|(c, _)| c);
+            if !on_stage {
+                if stage.leave(guild_id) {
+                    info!(guild_id = %guild_id, "Left the stage");
+                }
+            } else {
+                match stage.observe(guild_id, new.suppress, new.request_to_speak_timestamp.is_some()) {
+                    StageChange::Promoted => info!(guild_id = %guild_id, "Promoted to stage speaker"),
+                    StageChange::Demoted => info!(guild_id = %guild_id, "Moved to the stage audience"),
+                    StageChange::Requested => info!(guild_id = %guild_id, "Requested to speak on the stage"),
+                    StageChange::Unchanged => {}
+                }
+            }
+        } else {
+            // Members of the bot's channel decide when it is left alone
+            self.voice_manager
+                .observe_member(guild_id, user_id, new.channel_id.map(|c| c.get()))
+                .await;
+        }
         
         if let Some(channel_id) = new.channel_id {
             // User joined or moved to a voice channel
@@ -1102,8 +2371,9 @@ impl serenity::prelude::EventHandler for Handler {
                 channel_id = %cid,
                 "Voice state update: user joined/moved to voice channel"
             );
-            let mut states = self.voice_states.write().unwrap();
-            states.insert((guild_id, user_id), cid);
+            if let Err(e) = self.voice_states.set(guild_id, user_id, cid).await {
+                warn!(guild_id = %guild_id, user_id = %user_id, error = %e, "Failed to store voice state");
+            }
         } else {
             // User left voice channel
             info!(
@@ -1112,8 +2382,13 @@ impl serenity::prelude::EventHandler for Handler {
                 old_channel = ?old.as_ref().and_then(|o| o.channel_id).map(|c| c.get()),
                 "Voice state update: user left voice channel"
             );
-            let mut states = self.voice_states.write().unwrap();
-            states.remove(&(guild_id, user_id));
+            if let Err(e) = self.voice_states.remove(guild_id, user_id).await {
+                warn!(guild_id = %guild_id, user_id = %user_id, error = %e, "Failed to clear voice state");
+            }
+            // A push-to-talk window only makes sense while the user is in voice
+            if self.voice_conversations.close_manual(user_id) {
+                debug!(guild_id = %guild_id, user_id = %user_id, "Push-to-talk window closed on leave");
+            }
         }
     }
 }
@@ -1138,8 +2413,13 @@ impl Service for DiscordAdapterService {
         if !self.config.enabled || self.running {
             return Ok(());
         }
+        match self.filters.load().await {
+            Ok(guilds) => debug!(guilds, "Loaded guild filters"),
+            Err(e) => warn!(error = %e, "Failed to load guild filters, using configured lists"),
+        }
         let token = self.config.token.clone();
-        let intents = self.config.intents;
+        let cache_tuning = self.config.cache;
+        let intents = cache_tuning.apply_intents(self.config.intents);
         let voice_config = self.config.voice.clone();
 
         // Create Songbird voice client if voice feature is enabled
@@ -1169,46 +2449,102 @@ impl Service for DiscordAdapterService {
                 engine = %vm.config.engine,
                 "Voice manager initialized"
             );
+            self.voice_latency = Some(vm.latency.clone());
             vm
         };
 
         #[cfg(not(feature = "voice"))]
         let voice_manager = Arc::new(VoiceManager::new(voice_config));
 
+        let voice_conversations: VoiceConversationMap = Arc::new(VoiceConversations::new());
+        self.runtime
+            .read()
+            .unwrap()
+            .register_snapshot_contributor(Arc::new(DiscordSnapshotContributor {
+                voice_conversations: voice_conversations.clone(),
+                conversation_timeout_secs: voice_manager.config.discord.conversation_timeout_secs,
+            }));
+
+        // Attributions of spoken answers share the handler's resolved names
+        #[cfg(feature = "voice")]
+        let display_names = voice_manager.display_names.clone();
+        #[cfg(feature = "voice")]
+        let auto_leave_voice = voice_manager.clone();
+        let listen_modes = Arc::new(ListenModes::new(self.config.listen_auto_off));
+        #[cfg(feature = "voice")]
+        voice_manager.set_listen_modes(listen_modes.clone());
+
         let handler = Handler {
             runtime: self.runtime.clone(),
             token: token.clone(),
             limiter: self.limiter.clone(),
             application_id: self.config.application_id,
-            allowed_guilds: self
-                .config
-                .allowed_guilds
-                .as_ref()
-                .map(|v| v.iter().cloned().collect()),
-            allowed_channels: self
-                .config
-                .allowed_channels
-                .as_ref()
-                .map(|v| v.iter().cloned().collect()),
-            allowed_users: self
-                .config
-                .allowed_users
-                .as_ref()
-                .map(|v| v.iter().cloned().collect()),
-            recent_sends: Arc::new(RwLock::new(HashMap::new())),
+            filters: self.filters.clone(),
+            state_store: self.config.state_store.clone(),
             voice_manager,
-            voice_states: Arc::new(RwLock::new(HashMap::new())),
+            voice_states: VoiceStates::new(self.config.state_store.clone()),
+            voice_conversations,
+            push_to_talk: Arc::new(PushToTalk::new(
+                self.config.push_to_talk_window,
+                push_to_talk::DEFAULT_PROMPT_TTL,
+            )),
+            channel_characters: Arc::new(ChannelCharacters::new(
+                self.config.channel_characters.clone(),
+                self.runtime.read().unwrap().get_adapter(),
+            )),
+            context_epochs: Arc::new(ContextEpochs::new(self.runtime.read().unwrap().get_adapter())),
+            admin_users: self.config.admin_users.iter().cloned().collect(),
+            typing_refresh_interval: self.config.typing_refresh_interval,
+            typing_max_duration: self.config.typing_max_duration,
+            listen_modes,
+            url_ingestion: self.config.url_ingestion,
+            pending_links: Arc::new(PendingLinks::new(self.config.url_ask_timeout)),
+            pending_choices: Arc::new(PendingChoices::new(
+                self.config.choice_ttl,
+                self.config.choices_open_to_all,
+            )),
+            response_template: self.config.response_template.clone(),
+            placeholder: self.config.placeholder.clone(),
+            reply_fallback: Arc::new(ReplyFallback::new(
+                self.config.fallback_channels.clone(),
+                fallback::DEFAULT_BLOCKED_CHANNEL_TTL,
+            )),
+            user_prefs: Arc::new(UserPreferenceStore::from_adapter(
+                self.runtime.read().unwrap().get_adapter(),
+            )),
+            continuity: Arc::new(ContinuityLinks::from_adapter(
+                self.config.continuity_idle,
+                self.runtime.read().unwrap().get_adapter(),
+            )),
+            pending_continuations: Arc::new(PendingConfirmations::new(continuity::CONFIRMATION_TTL)),
+            threads: Arc::new(ThreadTracker::new(
+                self.config.auto_thread,
+                self.config.auto_thread_min_chars,
+            )),
+            attachments: self.config.attachments,
+            #[cfg(feature = "voice")]
+            display_names,
         };
 
+        let api_base = std::env::var("AGENT_API_URL")
+            .unwrap_or_else(|_| "http://127.0.0.1:9090/agent".to_string());
+        self.memory_batcher = Some(MemoryBatcher::start(
+            self.config.memory_batch.clone(),
+            Arc::new(AgentApiMemorySink::new(HttpClient::new(), api_base)),
+        ));
+
         #[cfg(feature = "voice")]
         let songbird_for_client = songbird.clone();
 
         tokio::spawn(async move {
-            // Configure cache to store voice states (required for voice channel detection)
-            let mut cache_settings = CacheSettings::default();
-            cache_settings.cache_guilds = true;
-            cache_settings.cache_channels = true;
-            cache_settings.cache_users = true;
+            // Guild cache holds voice states; users and messages are bounded by `cache_tuning`
+            let cache_settings = cache_tuning.to_settings();
+            info!(
+                cache_users = cache_tuning.cache_users,
+                max_messages = cache_tuning.max_messages,
+                cache_members = cache_tuning.cache_members,
+                "Discord cache configured"
+            );
             
             #[cfg(feature = "voice")]
             let client_result = Client::builder(&token, intents)
@@ -1225,9 +2561,18 @@ impl Service for DiscordAdapterService {
 
             match client_result {
                 Ok(mut client) => {
+                    let gauge = cache::spawn_cache_gauge(
+                        client.cache.clone(),
+                        cache::DEFAULT_CACHE_GAUGE_INTERVAL,
+                    );
+                    #[cfg(feature = "voice")]
+                    let auto_leave = auto_leave::spawn_auto_leave(auto_leave_voice, client.http.clone());
                     if let Err(why) = client.start().await {
                         error!(error = %format!("{:?}", why), "Discord client error");
                     }
+                    gauge.abort();
+                    #[cfg(feature = "voice")]
+                    auto_leave.abort();
                 }
                 Err(why) => {
                     error!(error = %format!("{:?}", why), "Err creating Discord client");
@@ -1242,6 +2587,9 @@ impl Service for DiscordAdapterService {
 
     async fn stop(&mut self) -> Result<()> {
         self.running = false;
+        if let Some(batcher) = self.memory_batcher.take() {
+            batcher.shutdown().await;
+        }
         Ok(())
     }
     fn is_running(&self) -> bool {
@@ -1255,16 +2603,24 @@ impl Service for DiscordAdapterService {
 pub struct DiscordPlugin {
     config: DiscordConfig,
     runtime: Arc<RwLock<AgentRuntime>>,
+    filters: Arc<DiscordFilters>,
 }
 
 impl DiscordPlugin {
     pub fn new(config: DiscordConfig, runtime: Arc<RwLock<AgentRuntime>>) -> Self {
-        Self { config, runtime }
+        let filters = Arc::new(DiscordFilters::from_config(&config));
+        Self {
+            config,
+            runtime,
+            filters,
+        }
     }
 }
 
 #[async_trait]
 impl zoey_core::types::Plugin for DiscordPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "discord"
     }
@@ -1281,16 +2637,16 @@ impl zoey_core::types::Plugin for DiscordPlugin {
             let mut rt = self.runtime.write().unwrap();
             let token = self.config.token.clone();
             let app_id = self.config.application_id;
-            let allowed_channels = self.config.allowed_channels.as_ref().map(|v| {
-                v.iter()
-                    .cloned()
-                    .collect::<std::collections::HashSet<u64>>()
-            });
+            let filters = self.filters.clone();
+            let response_template = self.config.response_template.clone();
+            let character = rt.character.name.clone();
             let handler: zoey_core::types::messaging::SendHandlerFunction =
                 Arc::new(move |params| {
                     let token = token.clone();
                     let app_id = app_id.clone();
-                    let allowed_channels = allowed_channels.clone();
+                    let filters = filters.clone();
+                    let response_template = response_template.clone();
+                    let character = character.clone();
                     Box::pin(async move {
                         let http = Http::new(&token);
                         if let Some(id) = app_id {
@@ -1303,19 +2659,17 @@ impl zoey_core::types::Plugin for DiscordPlugin {
                             .and_then(|v| v.as_str())
                             .and_then(|s| s.parse::<u64>().ok())
                         {
-                            if let Some(ref set) = allowed_channels {
-                                if !set.contains(&cid) {
-                                    return Ok(());
-                                }
+                            if !filters.send_allowed(cid) {
+                                return Ok(());
                             }
                             let ch = ChannelId::new(cid);
                             // Extract text content from XML format for display
-                            let display_text = extract_final_text_from_xml(&params.content.text);
-                            let content = if display_text.is_empty() {
-                                params.content.text.clone()
-                            } else {
-                                display_text
-                            };
+                            let content = render_final_text(
+                                &params.content.text,
+                                response_template.as_deref(),
+                                &target_room_label(&params.target),
+                                &character,
+                            );
                             ch.say(&http, content).await.map_err(|e| {
                                 zoey_core::ZoeyError::other(format!(
                                     "discord send error: {:?}",
@@ -1339,10 +2693,10 @@ impl zoey_core::types::Plugin for DiscordPlugin {
         if !self.config.enabled {
             return Vec::new();
         }
-        vec![Arc::new(DiscordAdapterService::new(
-            self.config.clone(),
-            self.runtime.clone(),
-        ))]
+        vec![Arc::new(
+            DiscordAdapterService::new(self.config.clone(), self.runtime.clone())
+                .with_filters(self.filters.clone()),
+        )]
     }
 }
 
@@ -1350,10 +2704,14 @@ pub async fn register_discord_send(
     runtime: Arc<RwLock<AgentRuntime>>,
     token: String,
     application_id: Option<u64>,
+    response_template: Option<String>,
 ) {
+    let character = runtime.read().unwrap().character.name.clone();
     let handler: zoey_core::types::messaging::SendHandlerFunction = Arc::new(move |params| {
         let token = token.clone();
         let app_id = application_id.clone();
+        let response_template = response_template.clone();
+        let character = character.clone();
         Box::pin(async move {
             let http = Http::new(&token);
             if let Some(id) = app_id {
@@ -1368,12 +2726,12 @@ pub async fn register_discord_send(
             {
                 let ch = ChannelId::new(cid);
                 // Extract text content from XML format for display
-                let display_text = extract_final_text_from_xml(&params.content.text);
-                let content = if display_text.is_empty() {
-                    params.content.text.clone()
-                } else {
-                    display_text
-                };
+                let content = render_final_text(
+                    &params.content.text,
+                    response_template.as_deref(),
+                    &target_room_label(&params.target),
+                    &character,
+                );
                 ch.say(&http, content).await.map_err(|e| {
                     zoey_core::ZoeyError::other(format!("discord send error: {:?}", e))
                 })?;
@@ -1396,7 +2754,13 @@ pub async fn start_discord(
     if !config.enabled {
         return Ok(());
     }
-    register_discord_send(runtime.clone(), config.token.clone(), config.application_id).await;
+    register_discord_send(
+        runtime.clone(),
+        config.token.clone(),
+        config.application_id,
+        config.response_template.clone(),
+    )
+    .await;
     let mut svc = DiscordAdapterService::new(config.clone(), runtime.clone());
     svc.initialize(Arc::new(())).await?;
     svc.start().await?;
@@ -0,0 +1,184 @@
+//! Per-guild voice listen mode
+//!
+//! By default the bot only responds in voice after hearing its name and then
+//! keeps a short conversation window open. `/listen` switches a guild between:
+//!
+//! - [`ListenMode::WakeWord`]: respond when the name is heard or during an active conversation
+//! - [`ListenMode::AlwaysOn`]: respond to every transcription, no name needed
+//! - [`ListenMode::Off`]: ignore voice entirely
+//!
+//! Always-on mode falls back to wake-word mode after a period without voice
+//! activity so a forgotten toggle does not leave the bot answering everything.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Default inactivity period after which always-on mode switches itself off
+pub const DEFAULT_LISTEN_AUTO_OFF: Duration = Duration::from_secs(10 * 60);
+
+/// How the bot decides which voice transcriptions to answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListenMode {
+    /// Respond after hearing the character name (default)
+    #[default]
+    WakeWord,
+    /// Respond to everything said in the voice channel
+    AlwaysOn,
+    /// Do not respond to voice
+    Off,
+}
+
+impl ListenMode {
+    /// Parse a `/listen` option value
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "wake" | "wakeword" | "wake_word" | "name" => Some(Self::WakeWord),
+            "always" | "always_on" | "on" => Some(Self::AlwaysOn),
+            "off" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    /// Option value used for this mode in the `/listen` command
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WakeWord => "wake",
+            Self::AlwaysOn => "always",
+            Self::Off => "off",
+        }
+    }
+
+    /// Whether a transcription should be answered in this mode
+    pub fn should_respond(&self, mentioned: bool, in_active_conversation: bool) -> bool {
+        match self {
+            Self::WakeWord => mentioned || in_active_conversation,
+            Self::AlwaysOn => true,
+            Self::Off => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GuildListen {
+    mode: ListenMode,
+    last_activity: Instant,
+}
+
+/// Listen mode per guild with always-on auto-off
+pub struct ListenModes {
+    guilds: RwLock<HashMap<u64, GuildListen>>,
+    auto_off_after: Duration,
+}
+
+impl ListenModes {
+    pub fn new(auto_off_after: Duration) -> Self {
+        Self {
+            guilds: RwLock::new(HashMap::new()),
+            auto_off_after,
+        }
+    }
+
+    /// Current mode for a guild, expiring an idle always-on mode
+    pub fn mode(&self, guild_id: u64) -> ListenMode {
+        let current = self.guilds.read().unwrap().get(&guild_id).copied();
+        match current {
+            Some(state)
+                if state.mode == ListenMode::AlwaysOn
+                    && state.last_activity.elapsed() >= self.auto_off_after =>
+            {
+                tracing::info!(guild_id = %guild_id, "Always-on listening timed out, back to wake word");
+                self.set(guild_id, ListenMode::WakeWord);
+                ListenMode::WakeWord
+            }
+            Some(state) => state.mode,
+            None => ListenMode::default(),
+        }
+    }
+
+    /// Set a guild's mode
+    pub fn set(&self, guild_id: u64, mode: ListenMode) {
+        let mut guilds = self.guilds.write().unwrap();
+        if mode == ListenMode::default() {
+            guilds.remove(&guild_id);
+        } else {
+            guilds.insert(
+                guild_id,
+                GuildListen {
+                    mode,
+                    last_activity: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Toggle between always-on and wake-word mode, returning the new mode
+    pub fn toggle(&self, guild_id: u64) -> ListenMode {
+        let next = match self.mode(guild_id) {
+            ListenMode::AlwaysOn => ListenMode::WakeWord,
+            _ => ListenMode::AlwaysOn,
+        };
+        self.set(guild_id, next);
+        next
+    }
+
+    /// Decide whether to answer a transcription, recording activity when answering
+    pub fn should_respond(&self, guild_id: u64, mentioned: bool, in_active_conversation: bool) -> bool {
+        let respond = self.mode(guild_id).should_respond(mentioned, in_active_conversation);
+        if respond {
+            if let Some(state) = self.guilds.write().unwrap().get_mut(&guild_id) {
+                state.last_activity = Instant::now();
+            }
+        }
+        respond
+    }
+}
+
+impl Default for ListenModes {
+    fn default() -> Self {
+        Self::new(DEFAULT_LISTEN_AUTO_OFF)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_decisions() {
+        assert!(ListenMode::WakeWord.should_respond(true, false));
+        assert!(ListenMode::WakeWord.should_respond(false, true));
+        assert!(!ListenMode::WakeWord.should_respond(false, false));
+        assert!(ListenMode::AlwaysOn.should_respond(false, false));
+        assert!(!ListenMode::Off.should_respond(true, true));
+    }
+
+    #[test]
+    fn test_parse_and_toggle() {
+        assert_eq!(ListenMode::parse("Always"), Some(ListenMode::AlwaysOn));
+        assert_eq!(ListenMode::parse("off"), Some(ListenMode::Off));
+        assert_eq!(ListenMode::parse(ListenMode::WakeWord.as_str()), Some(ListenMode::WakeWord));
+        assert_eq!(ListenMode::parse("loud"), None);
+
+        let modes = ListenModes::default();
+        assert_eq!(modes.mode(1), ListenMode::WakeWord);
+        assert_eq!(modes.toggle(1), ListenMode::AlwaysOn);
+        assert_eq!(modes.mode(2), ListenMode::WakeWord);
+        assert_eq!(modes.toggle(1), ListenMode::WakeWord);
+        modes.set(1, ListenMode::Off);
+        assert_eq!(modes.toggle(1), ListenMode::AlwaysOn);
+    }
+
+    #[test]
+    fn test_always_on_auto_off() {
+        let modes = ListenModes::new(Duration::from_millis(30));
+        modes.set(1, ListenMode::AlwaysOn);
+        modes.set(2, ListenMode::Off);
+        assert!(modes.should_respond(1, false, false));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(modes.mode(1), ListenMode::WakeWord);
+        assert!(!modes.should_respond(1, false, false));
+        // Off is an explicit choice and does not expire
+        assert_eq!(modes.mode(2), ListenMode::Off);
+    }
+}
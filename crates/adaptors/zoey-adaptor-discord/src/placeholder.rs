@@ -0,0 +1,205 @@
+//! Placeholder message edited while a reply streams in
+//!
+//! Before streaming, the worker posts a placeholder and edits it as chunks
+//! arrive. Discord rejects empty content, so the placeholder is never empty:
+//! a blank or rejected configured placeholder falls back to
+//! [`DEFAULT_PLACEHOLDER`]. When no placeholder could be posted, or editing
+//! it fails, the final reply is posted as a fresh message so it is not lost.
+
+use async_trait::async_trait;
+use serenity::builder::EditMessage;
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, MessageId};
+use tracing::warn;
+
+/// Neutral placeholder used when none is configured or Discord rejects it
+pub const DEFAULT_PLACEHOLDER: &str = "…";
+
+/// Messages the streaming worker posts, edits and deletes in one channel
+#[async_trait]
+pub trait ReplyChannel: Send + Sync {
+    /// Post a message, returning its ID
+    async fn send(&self, content: &str) -> Result<u64, String>;
+    async fn edit(&self, message_id: u64, content: &str) -> Result<(), String>;
+    async fn delete(&self, message_id: u64) -> Result<(), String>;
+}
+
+/// [`ReplyChannel`] for a Discord text channel
+pub struct DiscordReplyChannel<'a> {
+    pub http: &'a Http,
+    pub channel: ChannelId,
+}
+
+#[async_trait]
+impl ReplyChannel for DiscordReplyChannel<'_> {
+    async fn send(&self, content: &str) -> Result<u64, String> {
+        self.channel
+            .say(self.http, content)
+            .await
+            .map(|m| m.id.get())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn edit(&self, message_id: u64, content: &str) -> Result<(), String> {
+        self.channel
+            .edit_message(
+                self.http,
+                MessageId::new(message_id),
+                EditMessage::new().content(content),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn delete(&self, message_id: u64) -> Result<(), String> {
+        self.channel
+            .delete_message(self.http, MessageId::new(message_id))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Post the placeholder, retrying with [`DEFAULT_PLACEHOLDER`] if `content` is blank or rejected
+pub async fn send_placeholder(channel: &dyn ReplyChannel, content: &str) -> Option<u64> {
+    let content = content.trim();
+    if !content.is_empty() && content != DEFAULT_PLACEHOLDER {
+        match channel.send(content).await {
+            Ok(id) => return Some(id),
+            Err(e) => warn!(error = %e, "Placeholder rejected, using the default"),
+        }
+    }
+    match channel.send(DEFAULT_PLACEHOLDER).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!(error = %e, "Could not post placeholder, the reply will be sent fresh");
+            None
+        }
+    }
+}
+
+/// Put the final reply in the placeholder, or post it fresh when there is none or the edit fails
+///
+/// An empty reply removes the placeholder instead of leaving it behind.
+/// Returns the ID of the message holding the reply, if any.
+pub async fn deliver_final(
+    channel: &dyn ReplyChannel,
+    placeholder: Option<u64>,
+    content: &str,
+) -> Option<u64> {
+    if content.is_empty() {
+        if let Some(id) = placeholder {
+            let _ = channel.delete(id).await;
+        }
+        return None;
+    }
+    if let Some(id) = placeholder {
+        match channel.edit(id, content).await {
+            Ok(()) => return Some(id),
+            Err(e) => warn!(error = %e, "Editing placeholder failed, posting the reply fresh"),
+        }
+    }
+    match channel.send(content).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!(error = %e, "Failed to post reply");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records calls; sends of `rejected` content and edits fail
+    #[derive(Default)]
+    struct FakeChannel {
+        rejected: Vec<&'static str>,
+        fail_edits: bool,
+        calls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ReplyChannel for FakeChannel {
+        async fn send(&self, content: &str) -> Result<u64, String> {
+            self.calls.lock().unwrap().push(format!("send {}", content));
+            if self.rejected.contains(&content) {
+                Err("Cannot send an empty message".into())
+            } else {
+                Ok(7)
+            }
+        }
+
+        async fn edit(&self, message_id: u64, content: &str) -> Result<(), String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("edit {} {}", message_id, content));
+            if self.fail_edits {
+                Err("Unknown Message".into())
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn delete(&self, message_id: u64) -> Result<(), String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("delete {}", message_id));
+            Ok(())
+        }
+    }
+
+    fn calls(channel: &FakeChannel) -> Vec<String> {
+        channel.calls.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_falls_back_to_default() {
+        let channel = FakeChannel::default();
+        assert_eq!(send_placeholder(&channel, "  ").await, Some(7));
+        assert_eq!(calls(&channel), ["send …"]);
+
+        let channel = FakeChannel {
+            rejected: vec!["Thinking"],
+            ..Default::default()
+        };
+        assert_eq!(send_placeholder(&channel, "Thinking").await, Some(7));
+        assert_eq!(calls(&channel), ["send Thinking", "send …"]);
+
+        let channel = FakeChannel {
+            rejected: vec!["…"],
+            ..Default::default()
+        };
+        assert_eq!(send_placeholder(&channel, "").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_final_reply_is_never_lost() {
+        let channel = FakeChannel::default();
+        assert_eq!(deliver_final(&channel, Some(3), "Hello").await, Some(3));
+        assert_eq!(calls(&channel), ["edit 3 Hello"]);
+
+        // No placeholder was created: post fresh
+        let channel = FakeChannel::default();
+        assert_eq!(deliver_final(&channel, None, "Hello").await, Some(7));
+        assert_eq!(calls(&channel), ["send Hello"]);
+
+        // Placeholder deleted in the meantime: post fresh
+        let channel = FakeChannel {
+            fail_edits: true,
+            ..Default::default()
+        };
+        deliver_final(&channel, Some(3), "Hello").await;
+        assert_eq!(calls(&channel), ["edit 3 Hello", "send Hello"]);
+
+        // Nothing to say: remove the placeholder
+        let channel = FakeChannel::default();
+        assert_eq!(deliver_final(&channel, Some(3), "").await, None);
+        deliver_final(&channel, None, "").await;
+        assert_eq!(calls(&channel), ["delete 3"]);
+    }
+}
@@ -0,0 +1,524 @@
+//! Per-user conversation preferences
+//!
+//! Users state how they want to be answered with `/prefs`:
+//!
+//! - `/prefs set verbosity short|normal|long`
+//! - `/prefs set nickname <name>`
+//! - `/prefs show`
+//! - `/prefs clear`
+//!
+//! Preferences belong to the Discord user rather than a room, so they follow
+//! them across guilds and DMs. They are persisted as a component on the
+//! user's entity and sent as a `user_preferences` metadata object with every
+//! chat request from that user, text and voice alike.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serenity::all::{CommandDataOption, CommandDataOptionValue};
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::CommandOptionType;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+use uuid::Uuid;
+use zoey_core::types::Component;
+use zoey_core::{IDatabaseAdapter, Result};
+
+/// Component type holding a user's preferences
+pub const USER_PREFERENCES_COMPONENT_TYPE: &str = "discord_user_preferences";
+
+/// Chat request metadata key the preferences are sent under
+pub const USER_PREFERENCES_METADATA_KEY: &str = "user_preferences";
+
+/// Longest nickname accepted, in characters
+pub const MAX_NICKNAME_CHARS: usize = 32;
+
+/// Reply for a `/prefs` invocation that doesn't match any subcommand
+const USAGE: &str =
+    "Use `/prefs set verbosity`, `/prefs set nickname`, `/prefs show` or `/prefs clear`.";
+
+/// Entity ID for a Discord user, the same one text chat messages are attributed to
+pub fn user_entity_id(user_id: u64) -> Uuid {
+    zoey_core::string_to_uuid(&format!("discord-user-{}", user_id))
+}
+
+/// How long answers should be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Short,
+    Normal,
+    Long,
+}
+
+impl Verbosity {
+    pub const ALL: [Verbosity; 3] = [Self::Short, Self::Normal, Self::Long];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "short" | "brief" => Some(Self::Short),
+            "normal" | "default" => Some(Self::Normal),
+            "long" | "detailed" => Some(Self::Long),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Normal => "normal",
+            Self::Long => "long",
+        }
+    }
+}
+
+/// A user's stored preferences; unset fields are left to the character
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserPreferences {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<Verbosity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+}
+
+impl UserPreferences {
+    pub fn is_empty(&self) -> bool {
+        self.verbosity.is_none() && self.nickname.is_none()
+    }
+
+    /// Add the preferences to chat request metadata; nothing is added when none are set
+    pub fn insert_into(&self, metadata: &mut serde_json::Map<String, serde_json::Value>) {
+        if self.is_empty() {
+            return;
+        }
+        if let Ok(value) = serde_json::to_value(self) {
+            metadata.insert(USER_PREFERENCES_METADATA_KEY.to_string(), value);
+        }
+    }
+
+    /// Reply for `/prefs show`
+    pub fn describe(&self) -> String {
+        if self.is_empty() {
+            return "You haven't set any preferences.".to_string();
+        }
+        let mut lines = Vec::new();
+        if let Some(verbosity) = self.verbosity {
+            lines.push(format!("Verbosity: **{}**", verbosity.as_str()));
+        }
+        if let Some(nickname) = &self.nickname {
+            lines.push(format!("Nickname: **{}**", nickname));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Validate a nickname, returning it trimmed or a reply explaining the problem
+pub fn validate_nickname(name: &str) -> std::result::Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Nickname can't be empty.".to_string());
+    }
+    if name.chars().count() > MAX_NICKNAME_CHARS {
+        return Err(format!(
+            "Nickname must be at most {} characters.",
+            MAX_NICKNAME_CHARS
+        ));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Nickname can't contain line breaks or control characters.".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Parsed `/prefs` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefsCommand {
+    SetVerbosity(Verbosity),
+    SetNickname(String),
+    Show,
+    Clear,
+}
+
+impl PrefsCommand {
+    /// Parse a subcommand path (e.g. `["set", "verbosity"]`) and its value
+    ///
+    /// Invalid values are rejected with a reply for the user.
+    pub fn parse(path: &[&str], value: Option<&str>) -> std::result::Result<Self, String> {
+        match (path, value) {
+            (["show"], _) => Ok(Self::Show),
+            (["clear"], _) => Ok(Self::Clear),
+            (["set", "verbosity"], Some(value)) => Verbosity::parse(value)
+                .map(Self::SetVerbosity)
+                .ok_or_else(|| {
+                    format!("Unknown verbosity `{}`. Use short, normal or long.", value)
+                }),
+            (["set", "nickname"], Some(value)) => validate_nickname(value).map(Self::SetNickname),
+            _ => Err(USAGE.to_string()),
+        }
+    }
+
+    /// Parse the options of a `/prefs` interaction
+    pub fn from_options(options: &[CommandDataOption]) -> std::result::Result<Self, String> {
+        let mut path = Vec::new();
+        let mut value = None;
+        let mut options = options;
+        while let Some(option) = options.first() {
+            match &option.value {
+                CommandDataOptionValue::SubCommandGroup(inner)
+                | CommandDataOptionValue::SubCommand(inner) => {
+                    path.push(option.name.as_str());
+                    options = inner;
+                }
+                other => {
+                    value = other.as_str();
+                    break;
+                }
+            }
+        }
+        Self::parse(&path, value)
+    }
+}
+
+/// `/prefs` slash command definition
+pub fn prefs_command() -> CreateCommand {
+    let mut verbosity =
+        CreateCommandOption::new(CommandOptionType::String, "value", "Answer length")
+            .required(true);
+    for v in Verbosity::ALL {
+        verbosity = verbosity.add_string_choice(v.as_str(), v.as_str());
+    }
+    CreateCommand::new("prefs")
+        .description("How I should talk to you, in every server and DM")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommandGroup,
+                "set",
+                "Change a preference",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "verbosity",
+                    "How long my answers should be",
+                )
+                .add_sub_option(verbosity),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "nickname",
+                    "What I should call you",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "name", "Your nickname")
+                        .required(true)
+                        .max_length(MAX_NICKNAME_CHARS as u16),
+                ),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "show",
+            "Show your preferences",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "clear",
+            "Forget your preferences",
+        ))
+}
+
+/// Where preferences are persisted
+#[async_trait]
+pub trait PreferenceBackend: Send + Sync {
+    async fn load(&self, user_id: u64) -> Result<Option<UserPreferences>>;
+    async fn save(&self, user_id: u64, preferences: &UserPreferences) -> Result<()>;
+    async fn delete(&self, user_id: u64) -> Result<()>;
+}
+
+/// Persists preferences as a component on the user's entity
+pub struct ComponentPreferences {
+    adapter: Arc<dyn IDatabaseAdapter + Send + Sync>,
+}
+
+impl ComponentPreferences {
+    pub fn new(adapter: Arc<dyn IDatabaseAdapter + Send + Sync>) -> Self {
+        Self { adapter }
+    }
+
+    async fn find(&self, user_id: u64) -> Result<Option<Component>> {
+        let entity_id = user_entity_id(user_id);
+        self.adapter
+            .get_component(
+                entity_id,
+                USER_PREFERENCES_COMPONENT_TYPE,
+                Some(entity_id),
+                None,
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl PreferenceBackend for ComponentPreferences {
+    async fn load(&self, user_id: u64) -> Result<Option<UserPreferences>> {
+        match self.find(user_id).await? {
+            Some(component) => Ok(Some(serde_json::from_value(component.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, user_id: u64, preferences: &UserPreferences) -> Result<()> {
+        let data = serde_json::to_value(preferences)?;
+        let now = chrono::Utc::now().timestamp();
+        match self.find(user_id).await? {
+            Some(mut existing) => {
+                existing.data = data;
+                existing.updated_at = Some(now);
+                self.adapter.update_component(&existing).await
+            }
+            None => {
+                let entity_id = user_entity_id(user_id);
+                let component = Component {
+                    id: Uuid::new_v4(),
+                    entity_id,
+                    world_id: entity_id,
+                    source_entity_id: None,
+                    component_type: USER_PREFERENCES_COMPONENT_TYPE.to_string(),
+                    data,
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                };
+                self.adapter.create_component(&component).await.map(|_| ())
+            }
+        }
+    }
+
+    async fn delete(&self, user_id: u64) -> Result<()> {
+        match self.find(user_id).await? {
+            Some(existing) => self.adapter.delete_component(existing.id).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Preferences by Discord user ID, cached in front of the backend
+pub struct UserPreferenceStore {
+    cache: RwLock<HashMap<u64, UserPreferences>>,
+    backend: Option<Arc<dyn PreferenceBackend>>,
+}
+
+impl UserPreferenceStore {
+    pub fn new(backend: Option<Arc<dyn PreferenceBackend>>) -> Self {
+        Self {
+            cache: RwLock::new(HashMap::new()),
+            backend,
+        }
+    }
+
+    /// Store backed by the runtime's database adapter, or memory only without one
+    pub fn from_adapter(adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>) -> Self {
+        Self::new(
+            adapter.map(|a| Arc::new(ComponentPreferences::new(a)) as Arc<dyn PreferenceBackend>),
+        )
+    }
+
+    /// A user's preferences, loaded from the backend on first use
+    ///
+    /// Load failures are logged and treated as no preferences so chat is never blocked.
+    pub async fn get(&self, user_id: u64) -> UserPreferences {
+        if let Some(cached) = self.cache.read().unwrap().get(&user_id) {
+            return cached.clone();
+        }
+        let loaded = match &self.backend {
+            Some(backend) => match backend.load(user_id).await {
+                Ok(loaded) => loaded.unwrap_or_default(),
+                Err(e) => {
+                    warn!(user_id = %user_id, error = %e, "Failed to load user preferences");
+                    return UserPreferences::default();
+                }
+            },
+            None => UserPreferences::default(),
+        };
+        self.cache.write().unwrap().insert(user_id, loaded.clone());
+        loaded
+    }
+
+    /// Add a user's preferences to chat request metadata
+    pub async fn annotate(
+        &self,
+        user_id: u64,
+        metadata: &mut serde_json::Map<String, serde_json::Value>,
+    ) {
+        self.get(user_id).await.insert_into(metadata);
+    }
+
+    /// Apply a `/prefs` command and build the (ephemeral) reply
+    pub async fn handle(&self, user_id: u64, command: PrefsCommand) -> String {
+        let mut preferences = self.get(user_id).await;
+        let reply = match command {
+            PrefsCommand::Show => return preferences.describe(),
+            PrefsCommand::Clear => {
+                self.cache
+                    .write()
+                    .unwrap()
+                    .insert(user_id, UserPreferences::default());
+                let reply = "Preferences cleared.".to_string();
+                return match &self.backend {
+                    Some(backend) => match backend.delete(user_id).await {
+                        Ok(()) => reply,
+                        Err(e) => {
+                            warn!(user_id = %user_id, error = %e, "Failed to delete user preferences");
+                            format!("{} (not persisted: {})", reply, e)
+                        }
+                    },
+                    None => reply,
+                };
+            }
+            PrefsCommand::SetVerbosity(verbosity) => {
+                preferences.verbosity = Some(verbosity);
+                format!("Got it - I'll keep my answers **{}**.", verbosity.as_str())
+            }
+            PrefsCommand::SetNickname(nickname) => {
+                let reply = format!("Got it - I'll call you **{}**.", nickname);
+                preferences.nickname = Some(nickname);
+                reply
+            }
+        };
+        self.cache
+            .write()
+            .unwrap()
+            .insert(user_id, preferences.clone());
+        match &self.backend {
+            Some(backend) => match backend.save(user_id, &preferences).await {
+                Ok(()) => reply,
+                Err(e) => {
+                    warn!(user_id = %user_id, error = %e, "Failed to persist user preferences");
+                    format!("{} (not persisted: {})", reply, e)
+                }
+            },
+            None => reply,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Backend keeping the serialized component data, like the database would
+    #[derive(Default)]
+    struct MemoryBackend {
+        rows: Mutex<HashMap<u64, serde_json::Value>>,
+    }
+
+    #[async_trait]
+    impl PreferenceBackend for MemoryBackend {
+        async fn load(&self, user_id: u64) -> Result<Option<UserPreferences>> {
+            match self.rows.lock().unwrap().get(&user_id) {
+                Some(data) => Ok(Some(serde_json::from_value(data.clone())?)),
+                None => Ok(None),
+            }
+        }
+
+        async fn save(&self, user_id: u64, preferences: &UserPreferences) -> Result<()> {
+            self.rows
+                .lock()
+                .unwrap()
+                .insert(user_id, serde_json::to_value(preferences)?);
+            Ok(())
+        }
+
+        async fn delete(&self, user_id: u64) -> Result<()> {
+            self.rows.lock().unwrap().remove(&user_id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preferences_round_trip_through_backend() {
+        let backend = Arc::new(MemoryBackend::default());
+        let store = UserPreferenceStore::new(Some(backend.clone()));
+        store
+            .handle(7, PrefsCommand::SetVerbosity(Verbosity::Short))
+            .await;
+        store
+            .handle(7, PrefsCommand::SetNickname("Sam".into()))
+            .await;
+
+        // A fresh store (e.g. after a restart) reads them back
+        let restarted = UserPreferenceStore::new(Some(backend));
+        let loaded = restarted.get(7).await;
+        assert_eq!(loaded.verbosity, Some(Verbosity::Short));
+        assert_eq!(loaded.nickname.as_deref(), Some("Sam"));
+        assert!(restarted.get(8).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_injected_on_later_messages() {
+        let store = UserPreferenceStore::new(None);
+        let mut before = serde_json::Map::new();
+        store.annotate(7, &mut before).await;
+        assert!(before.is_empty());
+
+        store
+            .handle(7, PrefsCommand::SetVerbosity(Verbosity::Long))
+            .await;
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("ingested_urls".into(), serde_json::json!([]));
+        store.annotate(7, &mut metadata).await;
+        assert_eq!(
+            metadata[USER_PREFERENCES_METADATA_KEY],
+            serde_json::json!({ "verbosity": "long" })
+        );
+        assert!(metadata.contains_key("ingested_urls"));
+
+        // Other users are unaffected
+        let mut other = serde_json::Map::new();
+        store.annotate(8, &mut other).await;
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_values_rejected() {
+        assert_eq!(
+            PrefsCommand::parse(&["set", "verbosity"], Some("Brief")),
+            Ok(PrefsCommand::SetVerbosity(Verbosity::Short))
+        );
+        assert!(PrefsCommand::parse(&["set", "verbosity"], Some("verbose")).is_err());
+        assert_eq!(
+            PrefsCommand::parse(&["set", "nickname"], Some("  Sam  ")),
+            Ok(PrefsCommand::SetNickname("Sam".into()))
+        );
+        assert!(PrefsCommand::parse(&["set", "nickname"], Some("   ")).is_err());
+        assert!(PrefsCommand::parse(&["set", "nickname"], Some("Sam\nignore previous")).is_err());
+        let long = "x".repeat(MAX_NICKNAME_CHARS + 1);
+        assert!(PrefsCommand::parse(&["set", "nickname"], Some(&long)).is_err());
+        assert!(PrefsCommand::parse(&["set"], None).is_err());
+        assert_eq!(PrefsCommand::parse(&["show"], None), Ok(PrefsCommand::Show));
+    }
+
+    #[tokio::test]
+    async fn test_clear_forgets_preferences() {
+        let backend = Arc::new(MemoryBackend::default());
+        let store = UserPreferenceStore::new(Some(backend.clone()));
+        store
+            .handle(7, PrefsCommand::SetNickname("Sam".into()))
+            .await;
+        assert_eq!(
+            store.handle(7, PrefsCommand::Clear).await,
+            "Preferences cleared."
+        );
+
+        assert!(store.get(7).await.is_empty());
+        assert!(backend.rows.lock().unwrap().is_empty());
+        let mut metadata = serde_json::Map::new();
+        store.annotate(7, &mut metadata).await;
+        assert!(metadata.is_empty());
+        assert_eq!(
+            store.handle(7, PrefsCommand::Show).await,
+            "You haven't set any preferences."
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Push-to-talk listening windows opened with a 🎙️ reaction
+//!
+//! The "joining voice" confirmation carries a [`LISTEN_REACTION`]. A user who
+//! is in that voice channel and adds the reaction opens a listening window in
+//! which everything they say is answered without the wake word; removing the
+//! reaction closes it early. Windows live in the same per-user map as
+//! wake-word conversations ([`VoiceConversations`]), flagged `manual`:
+//!
+//! - a manual window lasts a fixed time from the reaction; turns inside it
+//!   neither extend nor replace it, and it counts even with
+//!   `persistent_conversation` off
+//! - outside a manual window, an answered turn starts or extends a wake-word
+//!   conversation as before
+//! - removing the reaction only closes a manual window, never a wake-word
+//!   conversation
+//!
+//! Confirmation messages stay eligible for [`DEFAULT_PROMPT_TTL`]. Joining the
+//! same channel again refreshes its older prompts, so their reactions keep
+//! working after the bot re-joins.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Reaction that toggles a listening window
+pub const LISTEN_REACTION: &str = "🎙️";
+
+/// Default length of a listening window
+pub const DEFAULT_PUSH_TO_TALK_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long a confirmation message accepts the reaction after the last join
+pub const DEFAULT_PROMPT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Spoken when a window opens
+pub const OPEN_ACK: &str = "Listening";
+
+/// Spoken when a window closes
+pub const CLOSE_ACK: &str = "Okay";
+
+/// Whether `emoji` is the listening reaction, with or without the variation selector
+pub fn is_listen_reaction(emoji: &str) -> bool {
+    emoji.trim_end_matches('\u{fe0f}') == LISTEN_REACTION.trim_end_matches('\u{fe0f}')
+}
+
+/// One user's voice conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceConversation {
+    /// Last answered turn, or when the manual window opened
+    pub since: Instant,
+    /// Opened with the listening reaction rather than the wake word
+    pub manual: bool,
+    /// How long the conversation stays open after `since`
+    pub duration: Duration,
+}
+
+impl VoiceConversation {
+    fn at(since: Instant, manual: bool, duration: Duration) -> Self {
+        Self {
+            since,
+            manual,
+            duration,
+        }
+    }
+
+    /// Whether the conversation has not yet timed out
+    pub fn is_open(&self) -> bool {
+        self.since.elapsed() < self.duration
+    }
+
+    /// Whether the user's next turn is answered without the wake word
+    pub fn is_active(&self, persistent_conversation: bool) -> bool {
+        self.is_open() && (self.manual || persistent_conversation)
+    }
+}
+
+/// Voice conversations per user, shared across voice joins
+#[derive(Debug, Default)]
+pub struct VoiceConversations {
+    users: RwLock<HashMap<u64, VoiceConversation>>,
+}
+
+impl VoiceConversations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `user_id`'s next turn skips the wake word
+    pub fn is_active(&self, user_id: u64, persistent_conversation: bool) -> bool {
+        self.users
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .is_some_and(|c| c.is_active(persistent_conversation))
+    }
+
+    /// Record an answered turn; an open manual window keeps its own end
+    pub fn record_turn(&self, user_id: u64, timeout: Duration) {
+        let mut users = self.users.write().unwrap();
+        if users.get(&user_id).is_some_and(|c| c.manual && c.is_open()) {
+            return;
+        }
+        users.insert(
+            user_id,
+            VoiceConversation::at(Instant::now(), false, timeout),
+        );
+    }
+
+    /// Open a manual window, returning its start to pass to [`Self::expire_manual`]
+    pub fn open_manual(&self, user_id: u64, window: Duration) -> Instant {
+        let since = Instant::now();
+        self.users
+            .write()
+            .unwrap()
+            .insert(user_id, VoiceConversation::at(since, true, window));
+        since
+    }
+
+    /// Close the user's manual window, returning whether one was open
+    pub fn close_manual(&self, user_id: u64) -> bool {
+        let mut users = self.users.write().unwrap();
+        if !users.get(&user_id).is_some_and(|c| c.manual) {
+            return false;
+        }
+        users.remove(&user_id).is_some_and(|c| c.is_open())
+    }
+
+    /// Drop the manual window that opened at `since` once it has run out
+    ///
+    /// Returns `false` if it was closed or replaced in the meantime.
+    pub fn expire_manual(&self, user_id: u64, since: Instant) -> bool {
+        let mut users = self.users.write().unwrap();
+        let expired = users
+            .get(&user_id)
+            .is_some_and(|c| c.manual && c.since == since && !c.is_open());
+        if expired {
+            users.remove(&user_id);
+        }
+        expired
+    }
+
+    /// Open wake-word conversations as (user_id, last turn)
+    pub fn wake_word_conversations(&self) -> Vec<(u64, Instant)> {
+        self.users
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, c)| !c.manual && c.is_open())
+            .map(|(&uid, c)| (uid, c.since))
+            .collect()
+    }
+
+    /// Restore a wake-word conversation unless the user already has one
+    pub fn restore(&self, user_id: u64, since: Instant, timeout: Duration) {
+        self.users
+            .write()
+            .unwrap()
+            .entry(user_id)
+            .or_insert(VoiceConversation::at(since, false, timeout));
+    }
+
+    /// Number of tracked users
+    pub fn len(&self) -> usize {
+        self.users.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// What a listening reaction did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toggle {
+    /// A window opened at `since`; acknowledge with [`OPEN_ACK`]
+    Opened { guild_id: u64, since: Instant },
+    /// An open window closed early; acknowledge with [`CLOSE_ACK`]
+    Closed { guild_id: u64 },
+    /// Not a listening reaction on a live prompt, or the user is not eligible
+    Ignored,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Prompt {
+    guild_id: u64,
+    channel_id: u64,
+    posted_at: Instant,
+}
+
+/// Confirmation messages accepting the listening reaction
+pub struct PushToTalk {
+    prompts: Mutex<HashMap<u64, Prompt>>,
+    prompt_ttl: Duration,
+    window: Duration,
+}
+
+impl Default for PushToTalk {
+    fn default() -> Self {
+        Self::new(DEFAULT_PUSH_TO_TALK_WINDOW, DEFAULT_PROMPT_TTL)
+    }
+}
+
+impl PushToTalk {
+    /// `window` of zero disables push-to-talk
+    pub fn new(window: Duration, prompt_ttl: Duration) -> Self {
+        Self {
+            prompts: Mutex::new(HashMap::new()),
+            prompt_ttl,
+            window,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    /// Length of a listening window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Accept the reaction on `message_id`, posted after joining `channel_id`
+    ///
+    /// Earlier prompts for the same channel are refreshed rather than dropped.
+    pub fn register_prompt(&self, message_id: u64, guild_id: u64, channel_id: u64) {
+        let now = Instant::now();
+        let mut prompts = self.prompts.lock().unwrap();
+        prompts.retain(|_, p| now.duration_since(p.posted_at) < self.prompt_ttl);
+        for prompt in prompts.values_mut() {
+            if prompt.guild_id == guild_id && prompt.channel_id == channel_id {
+                prompt.posted_at = now;
+            }
+        }
+        prompts.insert(
+            message_id,
+            Prompt {
+                guild_id,
+                channel_id,
+                posted_at: now,
+            },
+        );
+    }
+
+    /// (guild, voice channel) of a live prompt
+    pub fn prompt_channel(&self, message_id: u64) -> Option<(u64, u64)> {
+        self.prompts
+            .lock()
+            .unwrap()
+            .get(&message_id)
+            .filter(|p| p.posted_at.elapsed() < self.prompt_ttl)
+            .map(|p| (p.guild_id, p.channel_id))
+    }
+
+    /// Handle the reaction being added by a user currently in `user_channel`
+    pub fn reaction_added(
+        &self,
+        conversations: &VoiceConversations,
+        message_id: u64,
+        user_id: u64,
+        emoji: &str,
+        user_channel: Option<u64>,
+    ) -> Toggle {
+        if !self.is_enabled() || !is_listen_reaction(emoji) {
+            return Toggle::Ignored;
+        }
+        match self.prompt_channel(message_id) {
+            Some((guild_id, channel_id)) if user_channel == Some(channel_id) => Toggle::Opened {
+                guild_id,
+                since: conversations.open_manual(user_id, self.window),
+            },
+            _ => Toggle::Ignored,
+        }
+    }
+
+    /// Handle the reaction being removed
+    pub fn reaction_removed(
+        &self,
+        conversations: &VoiceConversations,
+        message_id: u64,
+        user_id: u64,
+        emoji: &str,
+    ) -> Toggle {
+        if !is_listen_reaction(emoji) {
+            return Toggle::Ignored;
+        }
+        match self.prompt_channel(message_id) {
+            Some((guild_id, _)) if conversations.close_manual(user_id) => {
+                Toggle::Closed { guild_id }
+            }
+            _ => Toggle::Ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: u64 = 1;
+    const VOICE: u64 = 10;
+    const PROMPT: u64 = 100;
+    const USER: u64 = 7;
+
+    fn push_to_talk(window: Duration) -> PushToTalk {
+        let ptt = PushToTalk::new(window, DEFAULT_PROMPT_TTL);
+        ptt.register_prompt(PROMPT, GUILD, VOICE);
+        ptt
+    }
+
+    #[test]
+    fn test_reaction_state_machine() {
+        let ptt = push_to_talk(DEFAULT_PUSH_TO_TALK_WINDOW);
+        let convs = VoiceConversations::new();
+
+        // Removing before adding, or the wrong emoji, does nothing
+        assert_eq!(
+            ptt.reaction_removed(&convs, PROMPT, USER, LISTEN_REACTION),
+            Toggle::Ignored
+        );
+        assert_eq!(
+            ptt.reaction_added(&convs, PROMPT, USER, "✅", Some(VOICE)),
+            Toggle::Ignored
+        );
+
+        let opened = ptt.reaction_added(&convs, PROMPT, USER, "🎙", Some(VOICE));
+        assert!(matches!(
+            opened,
+            Toggle::Opened {
+                guild_id: GUILD,
+                ..
+            }
+        ));
+        assert!(convs.is_active(USER, false));
+
+        assert_eq!(
+            ptt.reaction_removed(&convs, PROMPT, USER, LISTEN_REACTION),
+            Toggle::Closed { guild_id: GUILD }
+        );
+        assert!(!convs.is_active(USER, false));
+        assert_eq!(
+            ptt.reaction_removed(&convs, PROMPT, USER, LISTEN_REACTION),
+            Toggle::Ignored
+        );
+
+        // Unknown messages and a disabled window are ignored
+        assert_eq!(
+            ptt.reaction_added(&convs, 999, USER, LISTEN_REACTION, Some(VOICE)),
+            Toggle::Ignored
+        );
+        let disabled = push_to_talk(Duration::ZERO);
+        assert_eq!(
+            disabled.reaction_added(&convs, PROMPT, USER, LISTEN_REACTION, Some(VOICE)),
+            Toggle::Ignored
+        );
+    }
+
+    #[test]
+    fn test_only_users_in_the_voice_channel_are_eligible() {
+        let ptt = push_to_talk(DEFAULT_PUSH_TO_TALK_WINDOW);
+        let convs = VoiceConversations::new();
+        assert_eq!(
+            ptt.reaction_added(&convs, PROMPT, USER, LISTEN_REACTION, None),
+            Toggle::Ignored
+        );
+        assert_eq!(
+            ptt.reaction_added(&convs, PROMPT, USER, LISTEN_REACTION, Some(11)),
+            Toggle::Ignored
+        );
+        assert!(!convs.is_active(USER, true));
+        assert!(matches!(
+            ptt.reaction_added(&convs, PROMPT, USER, LISTEN_REACTION, Some(VOICE)),
+            Toggle::Opened { .. }
+        ));
+    }
+
+    #[test]
+    fn test_prompts_expire_and_survive_rejoin() {
+        let ptt = PushToTalk::new(DEFAULT_PUSH_TO_TALK_WINDOW, Duration::from_millis(40));
+        ptt.register_prompt(PROMPT, GUILD, VOICE);
+        std::thread::sleep(Duration::from_millis(25));
+        // Re-joining the same channel refreshes the earlier prompt
+        ptt.register_prompt(PROMPT + 1, GUILD, VOICE);
+        ptt.register_prompt(PROMPT + 2, GUILD, VOICE + 1);
+        std::thread::sleep(Duration::from_millis(25));
+        assert_eq!(ptt.prompt_channel(PROMPT), Some((GUILD, VOICE)));
+        assert_eq!(ptt.prompt_channel(PROMPT + 1), Some((GUILD, VOICE)));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(ptt.prompt_channel(PROMPT), None);
+        ptt.register_prompt(PROMPT + 3, GUILD, VOICE + 2);
+        assert_eq!(ptt.prompts.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_window_expiry_vs_manual_close() {
+        let ptt = push_to_talk(Duration::from_millis(30));
+        let convs = VoiceConversations::new();
+        let Toggle::Opened { since, .. } =
+            ptt.reaction_added(&convs, PROMPT, USER, LISTEN_REACTION, Some(VOICE))
+        else {
+            panic!("window should open");
+        };
+        assert!(!convs.expire_manual(USER, since), "still open");
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(!convs.is_active(USER, true));
+        assert!(convs.expire_manual(USER, since));
+        // Already gone, so a late removal is not acknowledged again
+        assert_eq!(
+            ptt.reaction_removed(&convs, PROMPT, USER, LISTEN_REACTION),
+            Toggle::Ignored
+        );
+
+        // A window closed by hand is not expired by its timer later
+        let Toggle::Opened { since, .. } =
+            ptt.reaction_added(&convs, PROMPT, USER, LISTEN_REACTION, Some(VOICE))
+        else {
+            panic!("window should open");
+        };
+        assert!(matches!(
+            ptt.reaction_removed(&convs, PROMPT, USER, LISTEN_REACTION),
+            Toggle::Closed { .. }
+        ));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(!convs.expire_manual(USER, since));
+    }
+
+    #[test]
+    fn test_manual_and_wake_word_interplay() {
+        let convs = VoiceConversations::new();
+        let timeout = Duration::from_secs(60);
+
+        // Wake-word conversations only count in persistent mode
+        convs.record_turn(USER, timeout);
+        assert!(convs.is_active(USER, true));
+        assert!(!convs.is_active(USER, false));
+        assert_eq!(convs.wake_word_conversations().len(), 1);
+
+        // A manual window replaces it and is not downgraded by turns inside it
+        let since = convs.open_manual(USER, Duration::from_millis(30));
+        convs.record_turn(USER, timeout);
+        assert!(convs.is_active(USER, false));
+        assert!(convs.wake_word_conversations().is_empty());
+
+        // Once the window runs out, the next turn starts a wake-word conversation
+        std::thread::sleep(Duration::from_millis(40));
+        convs.record_turn(USER, timeout);
+        assert!(!convs.expire_manual(USER, since));
+        assert!(!convs.is_active(USER, false));
+        assert!(convs.is_active(USER, true));
+
+        // Removing the reaction never ends a wake-word conversation
+        assert!(!convs.close_manual(USER));
+        assert!(convs.is_active(USER, true));
+    }
+}
@@ -0,0 +1,322 @@
+//! Speaker verification for privileged voice commands
+//!
+//! Anyone in a voice channel can say "Zoey, delete the case notes". Transcripts
+//! matching one of the configured privileged-command patterns (case-insensitive
+//! regexes) only reach the agent when the utterance's audio verifies against
+//! the speaker's enrolled voice; everything else passes untouched.
+//!
+//! - Buffered STT hands each utterance's audio to the gate
+//!   ([`SpeakerGate::record_utterance`]) before sending its transcript; a
+//!   privileged transcript without audio (streaming STT) is rejected.
+//! - `/voice enroll` starts an enrollment: the user's next few utterances are
+//!   recorded instead of answered, then averaged into their voice embedding.
+//!
+//! Embeddings and persistence come from the voice provider's
+//! [`SpeakerRegistry`].
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use zoey_provider_voice::{AudioData, SpeakerRegistry};
+
+/// How long an utterance's audio waits for its transcript
+const UTTERANCE_TTL: Duration = Duration::from_secs(30);
+
+/// What to do with a transcript
+#[derive(Debug, Clone, PartialEq)]
+pub enum Screening {
+    /// Route it to the agent
+    Pass,
+    /// Recorded as enrollment utterance `recorded` of `needed`
+    Recorded { recorded: usize, needed: usize },
+    /// The last enrollment utterance was recorded and the voice saved
+    Enrolled,
+    /// Enrollment could not be completed
+    EnrollmentFailed,
+    /// A privileged command from a voice that did not verify (`None`: no
+    /// audio or enrollment to check against)
+    Rejected { score: Option<f32> },
+}
+
+impl Screening {
+    /// Line spoken instead of answering, `None` for [`Screening::Pass`]
+    pub fn reply(&self) -> Option<String> {
+        match self {
+            Self::Pass => None,
+            Self::Recorded { recorded, needed } => Some(format!(
+                "Got it, {} of {}. Please say another sentence.",
+                recorded, needed
+            )),
+            Self::Enrolled => Some("Thanks, I'll recognize your voice from now on.".to_string()),
+            Self::EnrollmentFailed => Some(
+                "Sorry, I couldn't learn your voice. Please try /voice enroll again.".to_string(),
+            ),
+            Self::Rejected { .. } => {
+                Some("Sorry, only a verified speaker can ask me to do that.".to_string())
+            }
+        }
+    }
+}
+
+/// Privileged-command patterns, recent utterance audio and pending enrollments
+pub struct SpeakerGate {
+    registry: Arc<SpeakerRegistry>,
+    privileged: Vec<Regex>,
+    enroll_utterances: usize,
+    utterances: Mutex<HashMap<u64, (AudioData, Instant)>>,
+    enrolling: Mutex<HashMap<u64, Vec<AudioData>>>,
+}
+
+impl SpeakerGate {
+    /// Create a gate; patterns that are not valid regexes are skipped with a warning
+    pub fn new(
+        registry: Arc<SpeakerRegistry>,
+        patterns: &[String],
+        enroll_utterances: usize,
+    ) -> Self {
+        let privileged = patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(&format!("(?i){}", pattern)) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    warn!(pattern = %pattern, error = %e, "Ignoring invalid privileged voice command pattern");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            registry,
+            privileged,
+            enroll_utterances: enroll_utterances.max(1),
+            utterances: Mutex::new(HashMap::new()),
+            enrolling: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a transcript needs a verified speaker
+    pub fn is_privileged(&self, text: &str) -> bool {
+        self.privileged.iter().any(|re| re.is_match(text))
+    }
+
+    /// Keep the audio of a user's utterance for screening its transcript
+    pub fn record_utterance(&self, user_id: u64, audio: AudioData) {
+        let now = Instant::now();
+        let mut utterances = self.utterances.lock().unwrap();
+        utterances.retain(|_, (_, at)| now.saturating_duration_since(*at) < UTTERANCE_TTL);
+        utterances.insert(user_id, (audio, now));
+    }
+
+    /// Record the user's next utterances for enrollment; returns how many
+    pub fn start_enrollment(&self, user_id: u64) -> usize {
+        self.enrolling.lock().unwrap().insert(user_id, Vec::new());
+        info!(user_id = %user_id, utterances = self.enroll_utterances, "Voice enrollment started");
+        self.enroll_utterances
+    }
+
+    /// Decide what happens to a user's transcript
+    pub async fn screen(&self, user_id: u64, text: &str) -> Screening {
+        let audio = self
+            .utterances
+            .lock()
+            .unwrap()
+            .remove(&user_id)
+            .filter(|(_, at)| at.elapsed() < UTTERANCE_TTL)
+            .map(|(audio, _)| audio);
+
+        if let Some(screening) = self.enroll(user_id, audio.as_ref()).await {
+            return screening;
+        }
+        if !self.is_privileged(text) {
+            return Screening::Pass;
+        }
+        let Some(audio) = audio else {
+            warn!(user_id = %user_id, "Privileged voice command without audio to verify");
+            return Screening::Rejected { score: None };
+        };
+        match self.registry.verify(user_id, &audio).await {
+            Ok(result) if result.accepted => {
+                info!(user_id = %user_id, score = %result.score, "Privileged voice command verified");
+                Screening::Pass
+            }
+            Ok(result) => {
+                warn!(user_id = %user_id, score = %result.score, "Privileged voice command from unverified speaker");
+                let enrolled = self.registry.is_enrolled(user_id).await.unwrap_or(false);
+                Screening::Rejected {
+                    score: enrolled.then_some(result.score),
+                }
+            }
+            Err(e) => {
+                warn!(user_id = %user_id, error = %e, "Speaker verification failed");
+                Screening::Rejected { score: None }
+            }
+        }
+    }
+
+    /// Add an utterance to a pending enrollment, `None` when there is none
+    async fn enroll(&self, user_id: u64, audio: Option<&AudioData>) -> Option<Screening> {
+        let samples = {
+            let mut enrolling = self.enrolling.lock().unwrap();
+            let samples = enrolling.get_mut(&user_id)?;
+            let Some(audio) = audio else {
+                enrolling.remove(&user_id);
+                warn!(user_id = %user_id, "Voice enrollment needs buffered STT audio");
+                return Some(Screening::EnrollmentFailed);
+            };
+            samples.push(audio.clone());
+            if samples.len() < self.enroll_utterances {
+                return Some(Screening::Recorded {
+                    recorded: samples.len(),
+                    needed: self.enroll_utterances,
+                });
+            }
+            enrolling.remove(&user_id)?
+        };
+        match self.registry.enroll(user_id, samples).await {
+            Ok(()) => Some(Screening::Enrolled),
+            Err(e) => {
+                warn!(user_id = %user_id, error = %e, "Voice enrollment failed");
+                Some(Screening::EnrollmentFailed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use zoey_core::Result;
+    use zoey_provider_voice::speaker::{
+        MemoryEnrollmentStore, SpeakerEmbeddingModel, SPEAKER_SAMPLE_RATE,
+    };
+    use zoey_provider_voice::AudioFormat;
+
+    const ALICE: u64 = 1;
+    const BOB: u64 = 2;
+    /// First sample of each fixture voice
+    const ALICE_VOICE: i16 = 100;
+    const BOB_VOICE: i16 = 200;
+
+    /// Fixture embeddings picked by the clip's first sample
+    struct FixtureModel;
+
+    impl SpeakerEmbeddingModel for FixtureModel {
+        fn embed(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+            Ok(match (samples[0] * 32768.0).round() as i16 {
+                ALICE_VOICE => vec![1.0, 0.1],
+                _ => vec![0.1, 1.0],
+            })
+        }
+    }
+
+    fn clip(voice: i16) -> AudioData {
+        let samples = vec![voice; 1600];
+        AudioData::new(
+            Bytes::from(zoey_provider_voice::audio::samples_to_pcm16(&samples)),
+            AudioFormat::Pcm,
+            SPEAKER_SAMPLE_RATE,
+        )
+    }
+
+    fn gate() -> SpeakerGate {
+        let registry = SpeakerRegistry::with_model(
+            Box::new(FixtureModel),
+            Arc::new(MemoryEnrollmentStore::default()),
+            0.9,
+        );
+        SpeakerGate::new(
+            Arc::new(registry),
+            &[
+                r"\bdelete\b".to_string(),
+                "kick (him|her|them)".to_string(),
+                "([".to_string(),
+            ],
+            2,
+        )
+    }
+
+    async fn enroll(gate: &SpeakerGate, user_id: u64, voice: i16) {
+        gate.start_enrollment(user_id);
+        gate.record_utterance(user_id, clip(voice));
+        gate.screen(user_id, "the quick brown fox").await;
+        gate.record_utterance(user_id, clip(voice));
+        assert_eq!(
+            gate.screen(user_id, "jumps over").await,
+            Screening::Enrolled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_privileged_patterns_gate_commands() {
+        let gate = gate();
+        // The invalid pattern is skipped; matching is case-insensitive
+        assert!(gate.is_privileged("Zoey, DELETE the case notes"));
+        assert!(gate.is_privileged("kick them out"));
+        assert!(!gate.is_privileged("what did the deleted scene show"));
+
+        // Ordinary speech passes without audio or enrollment
+        assert_eq!(
+            gate.screen(BOB, "what's the weather").await,
+            Screening::Pass
+        );
+        // Privileged speech from someone not enrolled is rejected
+        gate.record_utterance(BOB, clip(BOB_VOICE));
+        assert_eq!(
+            gate.screen(BOB, "delete the notes").await,
+            Screening::Rejected { score: None }
+        );
+
+        enroll(&gate, ALICE, ALICE_VOICE).await;
+        gate.record_utterance(ALICE, clip(ALICE_VOICE));
+        assert_eq!(
+            gate.screen(ALICE, "delete the notes").await,
+            Screening::Pass
+        );
+
+        // Bob claiming Alice's turn with his own voice
+        gate.record_utterance(ALICE, clip(BOB_VOICE));
+        let Screening::Rejected { score: Some(score) } =
+            gate.screen(ALICE, "delete the notes").await
+        else {
+            panic!("expected a scored rejection");
+        };
+        assert!(score < 0.9);
+
+        // No audio for the utterance (streaming STT): rejected
+        assert_eq!(
+            gate.screen(ALICE, "delete the notes").await,
+            Screening::Rejected { score: None }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enrollment_records_next_utterances() {
+        let gate = gate();
+        assert_eq!(gate.start_enrollment(ALICE), 2);
+        gate.record_utterance(ALICE, clip(ALICE_VOICE));
+        let first = gate.screen(ALICE, "delete everything").await;
+        // Enrollment utterances are never answered, privileged or not
+        assert_eq!(
+            first,
+            Screening::Recorded {
+                recorded: 1,
+                needed: 2
+            }
+        );
+        assert!(first.reply().unwrap().contains("1 of 2"));
+
+        // Other users are unaffected
+        assert_eq!(gate.screen(BOB, "hello").await, Screening::Pass);
+
+        gate.record_utterance(ALICE, clip(ALICE_VOICE));
+        assert_eq!(gate.screen(ALICE, "hello again").await, Screening::Enrolled);
+        assert_eq!(gate.screen(ALICE, "hello").await, Screening::Pass);
+
+        // Enrollment without audio gives up
+        gate.start_enrollment(BOB);
+        assert_eq!(gate.screen(BOB, "hello").await, Screening::EnrollmentFailed);
+        assert_eq!(gate.screen(BOB, "hello").await, Screening::Pass);
+    }
+}
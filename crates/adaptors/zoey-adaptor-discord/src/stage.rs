@@ -0,0 +1,498 @@
+//! Speaking in Stage channels
+//!
+//! In a Stage channel the bot joins as audience (suppressed). After joining
+//! it tries to unsuppress itself, which works when it has stage moderator
+//! permissions, and otherwise raises its hand. Its role then follows its own
+//! voice state updates:
+//!
+//! ```text
+//! Audience --request--> Requested --unsuppressed--> Speaker
+//!                                                    |   ^
+//!                                           suppressed   unsuppressed
+//!                                                    v   |
+//!                                                 Suppressed
+//! ```
+//!
+//! Receiving and transcribing audio does not depend on the role, so questions
+//! are heard while suppressed. Answers are only spoken by a speaker. An
+//! answer ready while the bot is waiting for the floor (hand raised, or
+//! demoted) waits up to the speak timeout, counted from the request or
+//! demotion, and is then posted in the text channel the bot was invited from.
+//! Later answers go straight to text until the bot is promoted.
+//!
+//! Being demoted mid-answer pauses playback, and re-promotion resumes it. If
+//! the bot is not re-promoted within the timeout, the rest of the answer is
+//! dropped. [`Playback`] keeps the pause bookkeeping.
+
+use serenity::builder::EditVoiceState;
+use serenity::http::Http;
+use serenity::model::channel::{Channel, ChannelType, GuildChannel};
+use serenity::model::id::ChannelId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::futures::Notified;
+use tokio::sync::Notify;
+
+/// How long an answer waits for the floor before it is posted as text
+pub const DEFAULT_STAGE_SPEAK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The bot's role on a stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageRole {
+    /// Listening, no request to speak
+    Audience,
+    /// Hand raised at `since`
+    Requested { since: Instant },
+    /// Unsuppressed; answers are spoken
+    Speaker,
+    /// Moved back to the audience at `since` after speaking
+    Suppressed { since: Instant },
+}
+
+/// What a voice state update of the bot changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageChange {
+    /// The bot became a speaker
+    Promoted,
+    /// The bot was moved back to the audience
+    Demoted,
+    /// The bot's hand went up
+    Requested,
+    /// Nothing that matters for speaking, or not a stage session
+    Unchanged,
+}
+
+/// Where an answer goes right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Floor {
+    /// Speak it
+    Speak,
+    /// Wait up to this long for the floor
+    Wait(Duration),
+    /// Post it as text
+    Text,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StageSession {
+    channel_id: u64,
+    text_channel_id: u64,
+    role: StageRole,
+}
+
+/// The bot's stage role per guild
+///
+/// Guilds without a stage session (regular voice channels) always hold the
+/// floor.
+pub struct StageSessions {
+    sessions: Mutex<HashMap<u64, StageSession>>,
+    speak_timeout: Duration,
+    changed: Notify,
+}
+
+impl Default for StageSessions {
+    fn default() -> Self {
+        Self::new(DEFAULT_STAGE_SPEAK_TIMEOUT)
+    }
+}
+
+impl StageSessions {
+    pub fn new(speak_timeout: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            speak_timeout,
+            changed: Notify::new(),
+        }
+    }
+
+    /// How long answers wait for the floor
+    pub fn speak_timeout(&self) -> Duration {
+        self.speak_timeout
+    }
+
+    /// Start a session in Stage channel `channel_id`, answering in `text_channel_id` as text
+    pub fn join(&self, guild_id: u64, channel_id: u64, text_channel_id: u64) {
+        let session = StageSession {
+            channel_id,
+            text_channel_id,
+            role: StageRole::Audience,
+        };
+        self.sessions.lock().unwrap().insert(guild_id, session);
+        self.changed.notify_waiters();
+    }
+
+    /// End the guild's session, returning whether there was one
+    pub fn leave(&self, guild_id: u64) -> bool {
+        let removed = self.sessions.lock().unwrap().remove(&guild_id).is_some();
+        if removed {
+            self.changed.notify_waiters();
+        }
+        removed
+    }
+
+    /// (Stage channel, text channel) of the guild's session
+    pub fn channels(&self, guild_id: u64) -> Option<(u64, u64)> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&guild_id)
+            .map(|s| (s.channel_id, s.text_channel_id))
+    }
+
+    /// The bot's role, or `None` outside a Stage channel
+    pub fn role(&self, guild_id: u64) -> Option<StageRole> {
+        self.sessions.lock().unwrap().get(&guild_id).map(|s| s.role)
+    }
+
+    /// Whether the bot may speak (always outside a Stage channel)
+    pub fn holds_floor(&self, guild_id: u64) -> bool {
+        self.role(guild_id)
+            .is_none_or(|role| role == StageRole::Speaker)
+    }
+
+    /// Record that the bot raised its hand
+    pub fn request_sent(&self, guild_id: u64) -> StageChange {
+        self.request_sent_at(guild_id, Instant::now())
+    }
+
+    fn request_sent_at(&self, guild_id: u64, now: Instant) -> StageChange {
+        self.update(guild_id, |role| match role {
+            StageRole::Audience => Some(StageRole::Requested { since: now }),
+            _ => None,
+        })
+    }
+
+    /// Apply the bot's own voice state (`suppress`, whether its hand is up)
+    pub fn observe(&self, guild_id: u64, suppressed: bool, hand_raised: bool) -> StageChange {
+        self.observe_at(guild_id, suppressed, hand_raised, Instant::now())
+    }
+
+    fn observe_at(
+        &self,
+        guild_id: u64,
+        suppressed: bool,
+        hand_raised: bool,
+        now: Instant,
+    ) -> StageChange {
+        self.update(guild_id, |role| match (role, suppressed) {
+            (StageRole::Speaker, false) => None,
+            (_, false) => Some(StageRole::Speaker),
+            (StageRole::Speaker, true) => Some(StageRole::Suppressed { since: now }),
+            (StageRole::Audience, true) if hand_raised => Some(StageRole::Requested { since: now }),
+            _ => None,
+        })
+    }
+
+    fn update(
+        &self,
+        guild_id: u64,
+        next: impl FnOnce(StageRole) -> Option<StageRole>,
+    ) -> StageChange {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(session) = sessions.get_mut(&guild_id) else {
+            return StageChange::Unchanged;
+        };
+        let Some(role) = next(session.role) else {
+            return StageChange::Unchanged;
+        };
+        session.role = role;
+        drop(sessions);
+        self.changed.notify_waiters();
+        match role {
+            StageRole::Speaker => StageChange::Promoted,
+            StageRole::Suppressed { .. } => StageChange::Demoted,
+            StageRole::Requested { .. } => StageChange::Requested,
+            StageRole::Audience => StageChange::Unchanged,
+        }
+    }
+
+    /// Where an answer ready now should go
+    pub fn floor(&self, guild_id: u64) -> Floor {
+        self.floor_at(guild_id, Instant::now())
+    }
+
+    fn floor_at(&self, guild_id: u64, now: Instant) -> Floor {
+        match self.role(guild_id) {
+            None | Some(StageRole::Speaker) => Floor::Speak,
+            Some(StageRole::Audience) => Floor::Text,
+            Some(StageRole::Requested { since }) | Some(StageRole::Suppressed { since }) => {
+                match self
+                    .speak_timeout
+                    .checked_sub(now.saturating_duration_since(since))
+                {
+                    Some(left) if !left.is_zero() => Floor::Wait(left),
+                    _ => Floor::Text,
+                }
+            }
+        }
+    }
+
+    /// Wait until an answer can be spoken (`true`) or should be posted as text
+    pub async fn await_floor(&self, guild_id: u64) -> bool {
+        loop {
+            let changed = self.changed();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            match self.floor(guild_id) {
+                Floor::Speak => return true,
+                Floor::Text => return false,
+                Floor::Wait(left) => {
+                    tokio::select! {
+                        _ = changed => {}
+                        _ = tokio::time::sleep(left) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resolves on the next role change or session start/end
+    pub fn changed(&self) -> Notified<'_> {
+        self.changed.notified()
+    }
+}
+
+/// Progress of one spoken answer that may be paused
+#[derive(Debug, Clone, Copy)]
+pub struct Playback {
+    length: Duration,
+    started: Instant,
+    paused_at: Option<Instant>,
+    paused_for: Duration,
+}
+
+impl Playback {
+    /// Audio of `length` that started playing at `started`
+    pub fn new(length: Duration, started: Instant) -> Self {
+        Self {
+            length,
+            started,
+            paused_at: None,
+            paused_for: Duration::ZERO,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Pause at `now`, returning `false` if already paused
+    pub fn pause(&mut self, now: Instant) -> bool {
+        if self.is_paused() {
+            return false;
+        }
+        self.paused_at = Some(now);
+        true
+    }
+
+    /// Resume at `now`, returning `false` if not paused
+    pub fn resume(&mut self, now: Instant) -> bool {
+        let Some(at) = self.paused_at.take() else {
+            return false;
+        };
+        self.paused_for += now.saturating_duration_since(at);
+        true
+    }
+
+    /// Audio played so far
+    pub fn played(&self, now: Instant) -> Duration {
+        let until = self.paused_at.unwrap_or(now);
+        until
+            .saturating_duration_since(self.started)
+            .saturating_sub(self.paused_for)
+            .min(self.length)
+    }
+
+    /// Audio left to play
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.length - self.played(now)
+    }
+
+    /// How long the current pause has lasted
+    pub fn paused_for(&self, now: Instant) -> Option<Duration> {
+        self.paused_at.map(|at| now.saturating_duration_since(at))
+    }
+}
+
+/// How the bot asked for the floor after joining a stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloorRequest {
+    /// It unsuppressed itself (stage moderator permissions)
+    Promoted,
+    /// It raised its hand for a moderator to accept
+    Requested,
+}
+
+/// `channel_id` if it is a Stage channel
+pub async fn stage_channel(http: &Http, channel_id: u64) -> Option<GuildChannel> {
+    match ChannelId::new(channel_id).to_channel(http).await {
+        Ok(Channel::Guild(channel)) if channel.kind == ChannelType::Stage => Some(channel),
+        _ => None,
+    }
+}
+
+/// Become a speaker where permitted
+pub async fn promote(http: &Http, channel: &GuildChannel) -> serenity::Result<()> {
+    channel
+        .edit_own_voice_state(http, EditVoiceState::new().suppress(false))
+        .await
+}
+
+/// Become a speaker where permitted, otherwise request to speak
+pub async fn take_the_floor(http: &Http, channel: &GuildChannel) -> serenity::Result<FloorRequest> {
+    if promote(http, channel).await.is_ok() {
+        return Ok(FloorRequest::Promoted);
+    }
+    channel
+        .edit_own_voice_state(http, EditVoiceState::new().request_to_speak(true))
+        .await?;
+    Ok(FloorRequest::Requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: u64 = 1;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn test_role_follows_voice_state() {
+        let stage = StageSessions::new(secs(30));
+        let t0 = Instant::now();
+        // Outside a stage nothing is tracked and the floor is always held
+        assert_eq!(
+            stage.observe_at(GUILD, true, false, t0),
+            StageChange::Unchanged
+        );
+        assert!(stage.holds_floor(GUILD));
+
+        stage.join(GUILD, 10, 20);
+        assert_eq!(stage.channels(GUILD), Some((10, 20)));
+        assert_eq!(stage.role(GUILD), Some(StageRole::Audience));
+        // The suppressed state right after joining changes nothing
+        assert_eq!(
+            stage.observe_at(GUILD, true, false, t0),
+            StageChange::Unchanged
+        );
+
+        assert_eq!(stage.request_sent_at(GUILD, t0), StageChange::Requested);
+        assert_eq!(stage.role(GUILD), Some(StageRole::Requested { since: t0 }));
+        // The echo of our own request keeps the original request time
+        assert_eq!(
+            stage.observe_at(GUILD, true, true, t0 + secs(1)),
+            StageChange::Unchanged
+        );
+
+        assert_eq!(
+            stage.observe_at(GUILD, false, false, t0 + secs(2)),
+            StageChange::Promoted
+        );
+        assert!(stage.holds_floor(GUILD));
+        assert_eq!(
+            stage.observe_at(GUILD, false, false, t0 + secs(3)),
+            StageChange::Unchanged
+        );
+
+        assert_eq!(
+            stage.observe_at(GUILD, true, false, t0 + secs(4)),
+            StageChange::Demoted
+        );
+        assert_eq!(
+            stage.role(GUILD),
+            Some(StageRole::Suppressed {
+                since: t0 + secs(4)
+            })
+        );
+        assert!(!stage.holds_floor(GUILD));
+        assert_eq!(
+            stage.request_sent_at(GUILD, t0 + secs(5)),
+            StageChange::Unchanged
+        );
+
+        assert_eq!(
+            stage.observe_at(GUILD, false, false, t0 + secs(6)),
+            StageChange::Promoted
+        );
+        assert!(stage.leave(GUILD));
+        assert!(!stage.leave(GUILD));
+        assert_eq!(stage.role(GUILD), None);
+    }
+
+    #[test]
+    fn test_answers_fall_back_to_text_after_timeout() {
+        let stage = StageSessions::new(secs(30));
+        let t0 = Instant::now();
+        assert_eq!(stage.floor_at(GUILD, t0), Floor::Speak);
+
+        stage.join(GUILD, 10, 20);
+        // No hand raised (the request failed): straight to text
+        assert_eq!(stage.floor_at(GUILD, t0), Floor::Text);
+
+        stage.request_sent_at(GUILD, t0);
+        assert_eq!(stage.floor_at(GUILD, t0 + secs(10)), Floor::Wait(secs(20)));
+        assert_eq!(stage.floor_at(GUILD, t0 + secs(30)), Floor::Text);
+        assert_eq!(stage.floor_at(GUILD, t0 + secs(90)), Floor::Text);
+
+        // Promotion after the fallback started brings speech back
+        stage.observe_at(GUILD, false, false, t0 + secs(91));
+        assert_eq!(stage.floor_at(GUILD, t0 + secs(91)), Floor::Speak);
+
+        // After a demotion the timeout counts from the demotion
+        stage.observe_at(GUILD, true, false, t0 + secs(100));
+        assert_eq!(stage.floor_at(GUILD, t0 + secs(105)), Floor::Wait(secs(25)));
+        assert_eq!(stage.floor_at(GUILD, t0 + secs(130)), Floor::Text);
+    }
+
+    #[tokio::test]
+    async fn test_await_floor_wakes_on_promotion() {
+        let stage = std::sync::Arc::new(StageSessions::new(secs(30)));
+        stage.join(GUILD, 10, 20);
+        stage.request_sent(GUILD);
+        let waiting = tokio::spawn({
+            let stage = stage.clone();
+            async move { stage.await_floor(GUILD).await }
+        });
+        tokio::task::yield_now().await;
+        stage.observe(GUILD, false, false);
+        assert!(tokio::time::timeout(secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap());
+
+        let quick = StageSessions::new(Duration::from_millis(20));
+        quick.join(GUILD, 10, 20);
+        quick.request_sent(GUILD);
+        assert!(!quick.await_floor(GUILD).await);
+    }
+
+    #[test]
+    fn test_playback_pause_resume_bookkeeping() {
+        let t0 = Instant::now();
+        let mut playback = Playback::new(secs(10), t0);
+        assert_eq!(playback.remaining(t0 + secs(3)), secs(7));
+
+        // Demoted 3s in
+        assert!(playback.pause(t0 + secs(3)));
+        assert!(!playback.pause(t0 + secs(4)));
+        assert!(playback.is_paused());
+        assert_eq!(playback.played(t0 + secs(8)), secs(3));
+        assert_eq!(playback.paused_for(t0 + secs(8)), Some(secs(5)));
+
+        // Re-promoted 5s later; the pause does not count as played audio
+        assert!(playback.resume(t0 + secs(8)));
+        assert!(!playback.resume(t0 + secs(9)));
+        assert_eq!(playback.paused_for(t0 + secs(9)), None);
+        assert_eq!(playback.remaining(t0 + secs(10)), secs(5));
+
+        // A second pause adds up
+        playback.pause(t0 + secs(11));
+        playback.resume(t0 + secs(13));
+        assert_eq!(playback.remaining(t0 + secs(13)), secs(4));
+        assert_eq!(playback.remaining(t0 + secs(60)), Duration::ZERO);
+    }
+}
@@ -0,0 +1,442 @@
+//! Conversations inside Discord threads
+//!
+//! A message posted in a thread arrives with the thread's ID as its channel
+//! ID. [`MessageChannel`] keeps both the thread and its parent: the thread
+//! scopes the room (so its history stays separate from the parent channel's)
+//! and is where the placeholder and streamed edits go, while the parent's
+//! filters and character mapping still apply.
+//!
+//! With [`DiscordConfig::auto_thread`], a long reply to a message in a busy
+//! guild channel is moved into a thread started from the user's message; the
+//! placeholder in the channel is edited into a link to the thread.
+//!
+//! [`DiscordConfig::auto_thread`]: crate::DiscordConfig::auto_thread
+
+use serenity::builder::{CreateThread, EditMessage};
+use serenity::http::{CacheHttp, Http};
+use serenity::model::channel::{AutoArchiveDuration, Channel, PartialChannel};
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use uuid::Uuid;
+use zoey_core::types::ChannelType;
+
+use crate::characters::{self, ChannelCharacters};
+use crate::epochs::ContextEpochs;
+
+/// Reply length (in characters) above which a reply may be moved into a thread
+pub const DEFAULT_AUTO_THREAD_MIN_CHARS: usize = 800;
+
+/// Messages within [`BUSY_WINDOW`] that make a channel busy
+pub const BUSY_MESSAGES: usize = 5;
+
+/// Window over which channel activity is counted
+pub const BUSY_WINDOW: Duration = Duration::from_secs(120);
+
+/// Longest thread name taken from the user's message (Discord allows 100)
+const THREAD_NAME_MAX_CHARS: usize = 80;
+
+/// Whether a Discord channel kind is a thread
+pub fn is_thread_kind(kind: serenity::model::channel::ChannelType) -> bool {
+    use serenity::model::channel::ChannelType as Kind;
+    matches!(
+        kind,
+        Kind::PublicThread | Kind::PrivateThread | Kind::NewsThread
+    )
+}
+
+/// Channel a message arrived in, with the parent channel when it is a thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageChannel {
+    /// Where the message was posted and where the reply goes
+    pub channel_id: u64,
+    /// Parent channel when `channel_id` is a thread
+    pub parent_id: Option<u64>,
+}
+
+impl MessageChannel {
+    /// A regular channel or DM
+    pub fn channel(channel_id: u64) -> Self {
+        Self {
+            channel_id,
+            parent_id: None,
+        }
+    }
+
+    /// A thread of `parent_id`
+    pub fn thread(thread_id: u64, parent_id: u64) -> Self {
+        Self {
+            channel_id: thread_id,
+            parent_id: Some(parent_id),
+        }
+    }
+
+    /// Channel of an interaction, from the partial channel Discord sends with it
+    pub fn from_interaction(channel_id: u64, channel: Option<&PartialChannel>) -> Self {
+        match channel.and_then(|c| c.parent_id.filter(|_| is_thread_kind(c.kind))) {
+            Some(parent) => Self::thread(channel_id, parent.get()),
+            None => Self::channel(channel_id),
+        }
+    }
+
+    pub fn is_thread(&self) -> bool {
+        self.parent_id.is_some()
+    }
+
+    /// Channel whose filters and character mapping apply: the parent of a thread
+    pub fn settings_channel(&self) -> u64 {
+        self.parent_id.unwrap_or(self.channel_id)
+    }
+
+    /// Deterministic room ID; each thread gets its own room
+    pub fn room_uuid(&self, guild_id: u64, character: Option<&str>) -> Uuid {
+        characters::room_uuid(guild_id, self.channel_id, character)
+    }
+
+    /// Room ID in a context epoch of this channel or thread
+    pub fn room_uuid_at(&self, guild_id: u64, character: Option<&str>, epoch: u64) -> Uuid {
+        characters::room_uuid_at(guild_id, self.channel_id, character, epoch)
+    }
+
+    /// Room the conversation is in now: the parent's character, this channel's epoch
+    ///
+    /// Messages and the slash commands that act on a conversation all go
+    /// through here so a thread under a mapped channel lands in one room.
+    pub fn current_room(
+        &self,
+        guild_id: u64,
+        channel_characters: &ChannelCharacters,
+        context_epochs: &ContextEpochs,
+    ) -> Uuid {
+        let character = channel_characters.resolve(guild_id, self.settings_channel());
+        let epoch = context_epochs.current(guild_id, self.channel_id);
+        self.room_uuid_at(guild_id, character.as_deref(), epoch)
+    }
+
+    /// Channel type of the room
+    pub fn channel_type(&self, is_dm: bool) -> ChannelType {
+        if is_dm {
+            ChannelType::Dm
+        } else if self.is_thread() {
+            ChannelType::Thread
+        } else {
+            ChannelType::GuildText
+        }
+    }
+
+    /// `channel_type` sent with the message content
+    pub fn channel_type_label(&self, is_dm: bool) -> &'static str {
+        match self.channel_type(is_dm) {
+            ChannelType::Dm => "DM",
+            ChannelType::Thread => "THREAD",
+            _ => "GUILD_TEXT",
+        }
+    }
+}
+
+/// Thread name for a reply to `text`: its first line, shortened
+pub fn thread_name(text: &str, fallback: &str) -> String {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty());
+    let Some(line) = line else {
+        return fallback.to_string();
+    };
+    if line.chars().count() <= THREAD_NAME_MAX_CHARS {
+        return line.to_string();
+    }
+    let mut name: String = line.chars().take(THREAD_NAME_MAX_CHARS - 1).collect();
+    name.push('…');
+    name
+}
+
+/// Knows which channels are threads and how busy channels are
+pub struct ThreadTracker {
+    auto_thread: bool,
+    min_chars: usize,
+    /// Channel ID -> parent ID (`None` for channels that are not threads)
+    known: Mutex<HashMap<u64, Option<u64>>>,
+    /// Recent message times per channel
+    activity: Mutex<HashMap<u64, VecDeque<Instant>>>,
+}
+
+impl ThreadTracker {
+    /// Tracker moving replies longer than `min_chars` into threads when `auto_thread` is set
+    pub fn new(auto_thread: bool, min_chars: usize) -> Self {
+        Self {
+            auto_thread,
+            min_chars,
+            known: Mutex::new(HashMap::new()),
+            activity: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Previously resolved channel, if any
+    pub fn cached(&self, channel_id: u64) -> Option<MessageChannel> {
+        self.known
+            .lock()
+            .unwrap()
+            .get(&channel_id)
+            .map(|parent| MessageChannel {
+                channel_id,
+                parent_id: *parent,
+            })
+    }
+
+    /// Record what a channel is
+    pub fn remember(&self, channel: MessageChannel) {
+        self.known
+            .lock()
+            .unwrap()
+            .insert(channel.channel_id, channel.parent_id);
+    }
+
+    /// Work out whether `channel_id` is a thread, from the cache or over REST
+    ///
+    /// DMs are never threads. Lookups that fail are treated as regular
+    /// channels and retried on the next message.
+    pub async fn resolve(
+        &self,
+        cache_http: impl CacheHttp,
+        guild_id: Option<GuildId>,
+        channel_id: ChannelId,
+    ) -> MessageChannel {
+        let id = channel_id.get();
+        let Some(guild_id) = guild_id else {
+            return MessageChannel::channel(id);
+        };
+        if let Some(known) = self.cached(id) {
+            return known;
+        }
+        let cached = cache_http.cache().and_then(|cache| {
+            let guild = cache.guild(guild_id)?;
+            if guild.channels.contains_key(&channel_id) {
+                return Some(MessageChannel::channel(id));
+            }
+            guild
+                .threads
+                .iter()
+                .find(|t| t.id == channel_id)
+                .and_then(|t| t.parent_id)
+                .map(|parent| MessageChannel::thread(id, parent.get()))
+        });
+        let resolved = match cached {
+            Some(channel) => channel,
+            None => match channel_id.to_channel(&cache_http).await {
+                Ok(Channel::Guild(channel)) => match channel.parent_id {
+                    Some(parent) if is_thread_kind(channel.kind) => {
+                        MessageChannel::thread(id, parent.get())
+                    }
+                    _ => MessageChannel::channel(id),
+                },
+                Ok(_) => MessageChannel::channel(id),
+                Err(e) => {
+                    debug!(channel_id = %id, error = %e, "Could not look up channel kind");
+                    return MessageChannel::channel(id);
+                }
+            },
+        };
+        self.remember(resolved);
+        resolved
+    }
+
+    /// Count a message posted in `channel_id`
+    pub fn note_message(&self, channel_id: u64, now: Instant) {
+        let mut activity = self.activity.lock().unwrap();
+        activity.retain(|_, times| {
+            while times
+                .front()
+                .is_some_and(|t| now.duration_since(*t) > BUSY_WINDOW)
+            {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        activity.entry(channel_id).or_default().push_back(now);
+    }
+
+    /// Whether at least [`BUSY_MESSAGES`] were posted in `channel_id` within [`BUSY_WINDOW`]
+    pub fn is_busy(&self, channel_id: u64, now: Instant) -> bool {
+        self.activity
+            .lock()
+            .unwrap()
+            .get(&channel_id)
+            .map(|times| {
+                times
+                    .iter()
+                    .filter(|t| now.duration_since(**t) <= BUSY_WINDOW)
+                    .count()
+            })
+            .unwrap_or(0)
+            >= BUSY_MESSAGES
+    }
+
+    /// Whether a reply of `reply_chars` characters to a message in `channel`
+    /// should move into a new thread
+    ///
+    /// Only replies posted in the message's own guild channel move; DMs,
+    /// threads and fallback channels keep the reply where it is.
+    pub fn should_open_thread(
+        &self,
+        guild_id: u64,
+        channel: MessageChannel,
+        reply_channel_id: u64,
+        reply_chars: usize,
+        now: Instant,
+    ) -> bool {
+        self.auto_thread
+            && guild_id != 0
+            && !channel.is_thread()
+            && reply_channel_id == channel.channel_id
+            && reply_chars > self.min_chars
+            && self.is_busy(channel.channel_id, now)
+    }
+
+    /// Start a thread from the user's message and point the placeholder at it
+    ///
+    /// Returns the thread to send the reply to, or `None` if it could not be
+    /// created (the reply then stays in the channel).
+    pub async fn open_thread(
+        &self,
+        http: &Http,
+        channel: ChannelId,
+        message_id: u64,
+        placeholder_id: Option<u64>,
+        name: String,
+    ) -> Option<ChannelId> {
+        let builder = CreateThread::new(name).auto_archive_duration(AutoArchiveDuration::OneDay);
+        let thread = match channel
+            .create_thread_from_message(http, MessageId::new(message_id), builder)
+            .await
+        {
+            Ok(thread) => thread,
+            Err(e) => {
+                warn!(channel_id = %channel.get(), error = %e, "Could not start a thread for a long reply");
+                return None;
+            }
+        };
+        self.remember(MessageChannel::thread(thread.id.get(), channel.get()));
+        if let Some(pid) = placeholder_id {
+            let pointer = format!("🧵 Continued in <#{}>", thread.id.get());
+            let _ = channel
+                .edit_message(
+                    http,
+                    MessageId::new(pid),
+                    EditMessage::new().content(pointer),
+                )
+                .await;
+        }
+        Some(thread.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_rooms_are_stable_and_separate_from_parent() {
+        let parent = MessageChannel::channel(10);
+        let thread = MessageChannel::thread(20, 10);
+        let again = MessageChannel::thread(20, 10);
+
+        assert_eq!(thread.room_uuid(1, None), again.room_uuid(1, None));
+        assert_eq!(
+            thread.room_uuid(1, Some("Zoey Support")),
+            again.room_uuid(1, Some("zoey support"))
+        );
+        assert_ne!(thread.room_uuid(1, None), parent.room_uuid(1, None));
+        assert_ne!(
+            thread.room_uuid(1, None),
+            MessageChannel::thread(21, 10).room_uuid(1, None)
+        );
+        assert_eq!(
+            parent.room_uuid(1, None),
+            characters::room_uuid(1, 10, None)
+        );
+
+        assert_eq!(thread.settings_channel(), 10);
+        assert_eq!(parent.settings_channel(), 10);
+        assert_eq!(thread.channel_type(false), ChannelType::Thread);
+        assert_eq!(thread.channel_type_label(false), "THREAD");
+        assert_eq!(parent.channel_type(false), ChannelType::GuildText);
+        assert_eq!(parent.channel_type(true), ChannelType::Dm);
+    }
+
+    #[test]
+    fn test_thread_under_mapped_channel_shares_one_room() {
+        let characters = ChannelCharacters::new(HashMap::from([(10, "Support".to_string())]), None);
+        let epochs = ContextEpochs::new(None);
+        let thread = MessageChannel::thread(20, 10);
+
+        // What a message in the thread is stored under
+        let message_room = thread.room_uuid_at(
+            1,
+            characters.resolve(1, thread.settings_channel()).as_deref(),
+            epochs.current(1, thread.channel_id),
+        );
+        // What /context and /continue look up for an interaction in the thread
+        assert_eq!(thread.current_room(1, &characters, &epochs), message_room);
+        assert_eq!(
+            message_room,
+            characters::room_uuid_at(1, 20, Some("Support"), 0)
+        );
+        assert_ne!(
+            message_room,
+            MessageChannel::channel(20).current_room(1, &characters, &epochs),
+            "the thread's own ID has no mapping"
+        );
+        assert_eq!(
+            MessageChannel::channel(10).current_room(1, &characters, &epochs),
+            characters::room_uuid_at(1, 10, Some("Support"), 0)
+        );
+    }
+
+    #[test]
+    fn test_long_replies_in_busy_channels_open_threads() {
+        let tracker = ThreadTracker::new(true, 100);
+        let channel = MessageChannel::channel(10);
+        let start = Instant::now();
+        for i in 0..BUSY_MESSAGES as u64 - 1 {
+            tracker.note_message(10, start + Duration::from_secs(i));
+        }
+        let now = start + Duration::from_secs(10);
+        assert!(
+            !tracker.should_open_thread(1, channel, 10, 500, now),
+            "not busy yet"
+        );
+
+        tracker.note_message(10, now);
+        assert!(tracker.should_open_thread(1, channel, 10, 500, now));
+        assert!(
+            !tracker.should_open_thread(1, channel, 10, 100, now),
+            "short reply"
+        );
+        assert!(!tracker.should_open_thread(0, channel, 10, 500, now), "DM");
+        assert!(
+            !tracker.should_open_thread(1, channel, 99, 500, now),
+            "fallback channel"
+        );
+        assert!(!tracker.should_open_thread(1, MessageChannel::thread(10, 5), 10, 500, now));
+        let later = now + BUSY_WINDOW + Duration::from_secs(1);
+        assert!(
+            !tracker.should_open_thread(1, channel, 10, 500, later),
+            "quiet again"
+        );
+        assert!(!ThreadTracker::new(false, 100).should_open_thread(1, channel, 10, 500, now));
+    }
+
+    #[test]
+    fn test_thread_name_uses_first_line() {
+        assert_eq!(
+            thread_name("\n  How do I reset?\nmore", "Zoey"),
+            "How do I reset?"
+        );
+        assert_eq!(thread_name("   ", "Zoey"), "Zoey");
+        let long = "x".repeat(200);
+        assert_eq!(
+            thread_name(&long, "Zoey").chars().count(),
+            THREAD_NAME_MAX_CHARS
+        );
+    }
+}
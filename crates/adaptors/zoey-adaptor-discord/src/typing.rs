@@ -0,0 +1,108 @@
+//! Typing indicator refresh during long generations
+//!
+//! Discord's typing indicator expires after ~10 seconds. Slow local models can
+//! take longer than that to produce the first streamed chunk, so the indicator
+//! is re-broadcast on an interval until the refresh is stopped (first chunk or
+//! placeholder edit) or a maximum duration is reached.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// Default interval between typing broadcasts
+pub const DEFAULT_TYPING_REFRESH_INTERVAL: Duration = Duration::from_secs(8);
+
+/// Default cap on how long typing is refreshed for a single message
+pub const DEFAULT_TYPING_MAX_DURATION: Duration = Duration::from_secs(120);
+
+/// Handle to a running typing refresh task
+///
+/// The task stops when [`TypingRefresh::stop`] is called or the handle is dropped.
+pub struct TypingRefresh {
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl TypingRefresh {
+    /// Spawn a task calling `broadcast` every `interval` until stopped or `max_duration` elapses
+    ///
+    /// The initial broadcast is left to the caller; the first refresh happens
+    /// after one interval. A zero interval disables refreshing.
+    pub fn start<F, Fut>(interval: Duration, max_duration: Duration, broadcast: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        if interval.is_zero() {
+            return Self { stop: None };
+        }
+        let (tx, mut rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            loop {
+                tokio::select! {
+                    _ = &mut rx => break,
+                    _ = tokio::time::sleep(interval) => {
+                        if started.elapsed() >= max_duration {
+                            break;
+                        }
+                        broadcast().await;
+                    }
+                }
+            }
+        });
+        Self { stop: Some(tx) }
+    }
+
+    /// Stop refreshing the typing indicator
+    pub fn stop(mut self) {
+        if let Some(tx) = self.stop.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn counting(count: &Arc<AtomicUsize>) -> impl Fn() -> std::future::Ready<()> + Send + 'static {
+        let count = count.clone();
+        move || {
+            count.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_until_stopped() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let refresh = TypingRefresh::start(
+            Duration::from_millis(20),
+            Duration::from_secs(5),
+            counting(&count),
+        );
+        tokio::time::sleep(Duration::from_millis(110)).await;
+        refresh.stop();
+        let seen = count.load(Ordering::SeqCst);
+        assert!(seen >= 2, "expected repeated broadcasts, got {}", seen);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(count.load(Ordering::SeqCst), seen);
+    }
+
+    #[tokio::test]
+    async fn test_stops_at_max_duration() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let _refresh = TypingRefresh::start(
+            Duration::from_millis(20),
+            Duration::from_millis(50),
+            counting(&count),
+        );
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let seen = count.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(count.load(Ordering::SeqCst), seen);
+        assert!(seen <= 3);
+    }
+}
@@ -15,6 +15,8 @@ use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
+pub use zoey_core::nlp::WakeWordMatcher;
+
 #[cfg(feature = "voice")]
 use songbird::{
     input::{File as SongbirdFile, Input, RawAdapter, YoutubeDl},
@@ -28,12 +30,35 @@ use std::future::Future;
 use std::pin::Pin;
 use std::process::Child;
 
+#[cfg(feature = "voice")]
+pub use zoey_provider_voice::latency::{LatencySummary, LatencyTracker, TurnId, TurnMark};
+#[cfg(feature = "voice")]
+use zoey_provider_voice::streaming::{PcmReader, StreamPlayback, DEFAULT_CHUNK_MS};
+
+#[cfg(feature = "voice")]
+use crate::attribution::{AskerTracker, ClipCache};
+#[cfg(feature = "voice")]
+use crate::cache::{DisplayNames, NameSource};
+#[cfg(feature = "voice")]
+use crate::filler::{FillerPhrases, ThinkingFiller};
+use crate::auto_leave::{AutoLeave, Departure, LeaveReason};
+#[cfg(all(feature = "voice-wakeword", any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk")))]
+use crate::listen::ListenMode;
+#[cfg(feature = "voice")]
+use crate::listen::ListenModes;
+use crate::stage::StageSessions;
+
 /// Callback type for handling voice transcriptions
-/// Takes (user_id, transcribed_text) and returns Option<response_text>
+/// Takes (user_id, transcribed_text, latency turn) and returns Option<response_text>
+#[cfg(feature = "voice")]
 pub type TranscriptionCallback = Box<
-    dyn Fn(u64, String) -> Pin<Box<dyn Future<Output = Option<String>> + Send>> + Send + Sync
+    dyn Fn(u64, String, Option<TurnId>) -> Pin<Box<dyn Future<Output = Option<String>> + Send>> + Send + Sync
 >;
 
+/// Sender for (user_id, transcribed_text, latency turn) from a voice receiver
+#[cfg(feature = "voice")]
+pub type TranscriptionSender = tokio::sync::mpsc::Sender<(u64, String, Option<TurnId>)>;
+
 /// Voice configuration from character XML
 #[derive(Debug, Clone)]
 pub struct VoiceConfig {
@@ -65,6 +90,10 @@ pub struct VoiceConfig {
     pub similarity_boost: Option<f32>,
     /// Local TTS endpoint
     pub local_endpoint: Option<String>,
+    /// Endpoint each voice turn's latency report is POSTed to
+    pub latency_telemetry_endpoint: Option<String>,
+    /// Effect entries under `voice.effects`, parsed by the voice provider
+    pub effects: Vec<serde_json::Value>,
     /// Trigger phrases that initiate voice mode
     pub triggers: Vec<String>,
     /// Discord-specific settings
@@ -78,12 +107,46 @@ pub struct DiscordVoiceSettings {
     pub auto_join_voice: bool,
     /// Leave voice channel when alone
     pub leave_when_alone: bool,
-    /// Idle timeout in seconds before leaving
+    /// Seconds alone in the channel before leaving
+    pub alone_timeout_seconds: u64,
+    /// Seconds without transcriptions or speech before leaving (0 = stay)
     pub idle_timeout_seconds: u64,
     /// Speak text responses in voice channel
     pub speak_responses: bool,
     /// Enable speech-to-text listening
     pub listen_enabled: bool,
+    /// Keep a voice conversation active after a reply so follow-ups need no wake word
+    pub persistent_conversation: bool,
+    /// Seconds a voice conversation stays active after the last exchange
+    pub conversation_timeout_secs: u64,
+    /// Play a thinking filler when no reply has arrived this long after the utterance (0 = off)
+    pub thinking_filler_ms: u64,
+    /// Phrases spoken as thinking fillers, in rotation
+    pub thinking_filler_phrases: Vec<String>,
+    /// Audio file played as the filler instead of a synthesized phrase
+    pub thinking_filler_clip: Option<String>,
+    /// Directory with wake-word ONNX models (`voice-wakeword` feature)
+    pub wakeword_model_dir: Option<String>,
+    /// Wake-word model name (file stem in the model directory)
+    pub wakeword_model: String,
+    /// Wake-word detection threshold (0.0-1.0)
+    pub wakeword_threshold: f32,
+    /// Seconds an answer waits for the floor on a Stage channel before it is posted as text
+    pub stage_speak_timeout_secs: u64,
+    /// Say whose question an answer addresses ("Answering Marcus:") when several people are asking
+    pub announce_askers: bool,
+    /// Seconds an answered asker still counts as one of several askers
+    pub asker_window_secs: u64,
+    /// ONNX speaker-embedding model; enables speaker verification (`voice-speaker-id` feature)
+    pub speaker_model_path: Option<String>,
+    /// Similarity (0.0-1.0) at or above which a speaker counts as verified
+    pub speaker_threshold: f32,
+    /// Directory enrolled voices are kept in
+    pub speaker_enrollment_dir: Option<String>,
+    /// Utterances recorded by `/voice enroll`
+    pub enroll_utterances: usize,
+    /// Regexes (case-insensitive) for transcripts that need a verified speaker
+    pub privileged_commands: Vec<String>,
 }
 
 impl Default for DiscordVoiceSettings {
@@ -91,9 +154,26 @@ impl Default for DiscordVoiceSettings {
         Self {
             auto_join_voice: true,
             leave_when_alone: true,
-            idle_timeout_seconds: 300,
+            alone_timeout_seconds: crate::auto_leave::DEFAULT_ALONE_TIMEOUT.as_secs(),
+            idle_timeout_seconds: crate::auto_leave::DEFAULT_VOICE_IDLE_TIMEOUT.as_secs(),
             speak_responses: true,
             listen_enabled: false,
+            persistent_conversation: true,
+            conversation_timeout_secs: 45,
+            thinking_filler_ms: 0,
+            thinking_filler_phrases: crate::filler::default_filler_phrases(),
+            thinking_filler_clip: None,
+            wakeword_model_dir: None,
+            wakeword_model: "zoey".to_string(),
+            wakeword_threshold: 0.5,
+            stage_speak_timeout_secs: crate::stage::DEFAULT_STAGE_SPEAK_TIMEOUT.as_secs(),
+            announce_askers: true,
+            asker_window_secs: crate::attribution::DEFAULT_ATTRIBUTION_WINDOW.as_secs(),
+            speaker_model_path: None,
+            speaker_threshold: 0.7,
+            speaker_enrollment_dir: None,
+            enroll_utterances: 3,
+            privileged_commands: Vec::new(),
         }
     }
 }
@@ -115,6 +195,8 @@ impl Default for VoiceConfig {
             stability: Some(0.5),
             similarity_boost: Some(0.75),
             local_endpoint: None,
+            latency_telemetry_endpoint: None,
+            effects: Vec::new(),
             triggers: default_triggers(),
             discord: DiscordVoiceSettings::default(),
         }
@@ -271,6 +353,18 @@ impl VoiceConfig {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let latency_telemetry_endpoint = voice
+            .get("latency_telemetry_endpoint")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.to_string());
+
+        let effects = voice
+            .get("effects")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+
         // Parse triggers
         let triggers = voice
             .get("triggers")
@@ -316,6 +410,16 @@ impl VoiceConfig {
                         .map(|s| s == "true")
                 })
                 .unwrap_or(true),
+            alone_timeout_seconds: discord_settings
+                .get("alone_timeout_seconds")
+                .and_then(|v| v.as_u64())
+                .or_else(|| {
+                    discord_settings
+                        .get("alone_timeout_seconds")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(crate::auto_leave::DEFAULT_ALONE_TIMEOUT.as_secs()),
             idle_timeout_seconds: discord_settings
                 .get("idle_timeout_seconds")
                 .and_then(|v| v.as_u64())
@@ -325,7 +429,7 @@ impl VoiceConfig {
                         .and_then(|v| v.as_str())
                         .and_then(|s| s.parse().ok())
                 })
-                .unwrap_or(300),
+                .unwrap_or(crate::auto_leave::DEFAULT_VOICE_IDLE_TIMEOUT.as_secs()),
             speak_responses: discord_settings
                 .get("speak_responses")
                 .and_then(|v| v.as_bool())
@@ -346,6 +450,146 @@ impl VoiceConfig {
                         .map(|s| s == "true")
                 })
                 .unwrap_or(false),
+            persistent_conversation: discord_settings
+                .get("persistent_conversation")
+                .and_then(|v| v.as_bool())
+                .or_else(|| {
+                    discord_settings
+                        .get("persistent_conversation")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s == "true")
+                })
+                .unwrap_or(true),
+            conversation_timeout_secs: discord_settings
+                .get("conversation_timeout_secs")
+                .and_then(|v| v.as_u64())
+                .or_else(|| {
+                    discord_settings
+                        .get("conversation_timeout_secs")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(45),
+            thinking_filler_ms: discord_settings
+                .get("thinking_filler_ms")
+                .and_then(|v| v.as_u64())
+                .or_else(|| {
+                    discord_settings
+                        .get("thinking_filler_ms")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(0),
+            thinking_filler_phrases: discord_settings
+                .get("thinking_fillers")
+                .and_then(|t| t.get("filler"))
+                .and_then(|f| match f {
+                    serde_json::Value::Array(a) => Some(
+                        a.iter()
+                            .filter_map(|v| v.as_str().map(str::to_string))
+                            .collect(),
+                    ),
+                    serde_json::Value::String(s) => Some(vec![s.clone()]),
+                    _ => None,
+                })
+                .unwrap_or_else(crate::filler::default_filler_phrases),
+            thinking_filler_clip: discord_settings
+                .get("thinking_filler_clip")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| s.to_string()),
+            wakeword_model_dir: discord_settings
+                .get("wakeword_model_dir")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            wakeword_model: discord_settings
+                .get("wakeword_model")
+                .and_then(|v| v.as_str())
+                .unwrap_or("zoey")
+                .to_string(),
+            wakeword_threshold: discord_settings
+                .get("wakeword_threshold")
+                .and_then(|v| v.as_f64())
+                .or_else(|| {
+                    discord_settings
+                        .get("wakeword_threshold")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(0.5) as f32,
+            stage_speak_timeout_secs: discord_settings
+                .get("stage_speak_timeout_secs")
+                .and_then(|v| v.as_u64())
+                .or_else(|| {
+                    discord_settings
+                        .get("stage_speak_timeout_secs")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(crate::stage::DEFAULT_STAGE_SPEAK_TIMEOUT.as_secs()),
+            announce_askers: discord_settings
+                .get("announce_askers")
+                .and_then(|v| v.as_bool())
+                .or_else(|| {
+                    discord_settings
+                        .get("announce_askers")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s == "true")
+                })
+                .unwrap_or(true),
+            asker_window_secs: discord_settings
+                .get("asker_window_secs")
+                .and_then(|v| v.as_u64())
+                .or_else(|| {
+                    discord_settings
+                        .get("asker_window_secs")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(crate::attribution::DEFAULT_ATTRIBUTION_WINDOW.as_secs()),
+            speaker_model_path: discord_settings
+                .get("speaker_model_path")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| s.to_string()),
+            speaker_threshold: discord_settings
+                .get("speaker_threshold")
+                .and_then(|v| v.as_f64())
+                .or_else(|| {
+                    discord_settings
+                        .get("speaker_threshold")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(0.7) as f32,
+            speaker_enrollment_dir: discord_settings
+                .get("speaker_enrollment_dir")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| s.to_string()),
+            enroll_utterances: discord_settings
+                .get("enroll_utterances")
+                .and_then(|v| v.as_u64())
+                .or_else(|| {
+                    discord_settings
+                        .get("enroll_utterances")
+                        .and_then(|v| v.as_str())
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(3) as usize,
+            privileged_commands: discord_settings
+                .get("privileged_commands")
+                .and_then(|t| t.get("pattern"))
+                .and_then(|p| match p {
+                    serde_json::Value::Array(a) => Some(
+                        a.iter()
+                            .filter_map(|v| v.as_str().map(str::to_string))
+                            .collect(),
+                    ),
+                    serde_json::Value::String(s) => Some(vec![s.clone()]),
+                    _ => None,
+                })
+                .unwrap_or_default(),
         };
 
         Self {
@@ -363,11 +607,47 @@ impl VoiceConfig {
             stability,
             similarity_boost,
             local_endpoint,
+            latency_telemetry_endpoint,
+            effects,
             triggers,
             discord,
         }
     }
 
+    /// Wake-word settings for the voice plugin's detector
+    #[cfg(feature = "voice-wakeword")]
+    pub fn wakeword_config(&self) -> zoey_provider_voice::wakeword::WakeWordConfig {
+        let defaults = zoey_provider_voice::wakeword::WakeWordConfig::default();
+        zoey_provider_voice::wakeword::WakeWordConfig {
+            model_dir: self
+                .discord
+                .wakeword_model_dir
+                .as_ref()
+                .map(std::path::PathBuf::from)
+                .unwrap_or(defaults.model_dir),
+            model: self.discord.wakeword_model.clone(),
+            threshold: self.discord.wakeword_threshold,
+            window: WAKEWORD_WINDOW,
+        }
+    }
+
+    /// Speaker verification settings for the voice plugin's registry
+    #[cfg(feature = "voice-speaker-id")]
+    pub fn speaker_config(&self) -> Option<zoey_provider_voice::speaker::SpeakerConfig> {
+        let defaults = zoey_provider_voice::speaker::SpeakerConfig::default();
+        let model_path = self.discord.speaker_model_path.as_ref()?;
+        Some(zoey_provider_voice::speaker::SpeakerConfig {
+            model_path: std::path::PathBuf::from(model_path),
+            threshold: self.discord.speaker_threshold,
+            enrollment_dir: self
+                .discord
+                .speaker_enrollment_dir
+                .as_ref()
+                .map(std::path::PathBuf::from)
+                .unwrap_or(defaults.enrollment_dir),
+        })
+    }
+
     /// Check if a message contains a voice trigger phrase
     pub fn is_voice_trigger(&self, message: &str) -> bool {
         if !self.enabled {
@@ -408,6 +688,12 @@ pub struct VoiceSession {
     pub is_speaking: bool,
     /// Users in the voice channel (for leave_when_alone detection)
     pub users_in_channel: HashSet<u64>,
+    /// Text channel the join was asked from, where the goodbye goes
+    pub text_channel_id: Option<u64>,
+    /// Since when nobody else has been in the channel
+    pub alone_since: Option<Instant>,
+    /// Receive and transcription tasks, stopped when the session ends
+    tasks: Vec<tokio::task::JoinHandle<()>>,
 }
 
 impl VoiceSession {
@@ -420,6 +706,9 @@ impl VoiceSession {
             last_activity: now,
             is_speaking: false,
             users_in_channel: HashSet::new(),
+            text_channel_id: None,
+            alone_since: None,
+            tasks: Vec::new(),
         }
     }
 
@@ -437,6 +726,64 @@ impl VoiceSession {
     pub fn is_alone(&self) -> bool {
         self.users_in_channel.is_empty()
     }
+
+    /// Replace the channel's members (the bot excluded)
+    pub fn set_members(&mut self, members: impl IntoIterator<Item = u64>) {
+        self.set_members_at(members, Instant::now());
+    }
+
+    fn set_members_at(&mut self, members: impl IntoIterator<Item = u64>, now: Instant) {
+        self.users_in_channel = members.into_iter().collect();
+        self.note_alone(now);
+    }
+
+    /// Apply a member's voice state: in `channel_id`, or out of voice when `None`
+    pub fn observe_member(&mut self, user_id: u64, channel_id: Option<u64>) {
+        self.observe_member_at(user_id, channel_id, Instant::now());
+    }
+
+    fn observe_member_at(&mut self, user_id: u64, channel_id: Option<u64>, now: Instant) {
+        if channel_id == Some(self.channel_id) {
+            self.users_in_channel.insert(user_id);
+        } else {
+            self.users_in_channel.remove(&user_id);
+        }
+        self.note_alone(now);
+    }
+
+    fn note_alone(&mut self, now: Instant) {
+        if !self.is_alone() {
+            self.alone_since = None;
+        } else if self.alone_since.is_none() {
+            self.alone_since = Some(now);
+        }
+    }
+
+    /// Why `policy` would leave this session at `now`
+    pub fn leave_reason(&self, policy: &AutoLeave, now: Instant) -> Option<LeaveReason> {
+        policy.reason(
+            now.saturating_duration_since(self.last_activity),
+            self.alone_since.map(|since| now.saturating_duration_since(since)),
+        )
+    }
+}
+
+impl Drop for VoiceSession {
+    fn drop(&mut self) {
+        // A replaced or ended session must not keep routing transcriptions to the agent
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Synthesized audio kept for replay
+#[cfg(feature = "voice")]
+#[derive(Clone, Copy)]
+struct Clip {
+    bytes: &'static [u8],
+    /// Estimated playback length
+    duration: Duration,
 }
 
 /// Voice manager for handling Discord voice connections
@@ -445,12 +792,35 @@ pub struct VoiceManager {
     pub config: VoiceConfig,
     /// Active voice sessions by guild ID
     pub sessions: Arc<RwLock<std::collections::HashMap<u64, VoiceSession>>>,
+    /// The bot's speaker role in guilds where it joined a Stage channel
+    pub stage: Arc<StageSessions>,
     /// Songbird voice client (when voice feature is enabled)
     #[cfg(feature = "voice")]
     pub songbird: Option<Arc<Songbird>>,
+    /// End-to-end latency traces of voice conversation turns
+    #[cfg(feature = "voice")]
+    pub latency: Arc<LatencyTracker>,
     /// Lock to prevent overlapping TTS - one speak at a time per guild
     #[cfg(feature = "voice")]
     speaking_locks: Arc<RwLock<std::collections::HashMap<u64, Arc<tokio::sync::Mutex<()>>>>>,
+    /// Thinking filler phrases, spoken in rotation
+    #[cfg(feature = "voice")]
+    filler_phrases: Arc<FillerPhrases>,
+    /// Short synthesized clips (thinking fillers, asker attributions) by text, kept after first use
+    #[cfg(feature = "voice")]
+    clips: Arc<ClipCache<Clip>>,
+    /// Who is waiting for or recently got a spoken answer, per guild
+    #[cfg(feature = "voice")]
+    askers: Arc<AskerTracker>,
+    /// Display names for attributions, resolved from the cache with a REST fallback
+    #[cfg(feature = "voice")]
+    pub display_names: Arc<DisplayNames>,
+    /// Set once the gateway is ready ([`set_name_source`](Self::set_name_source))
+    #[cfg(feature = "voice")]
+    name_source: std::sync::RwLock<Option<Arc<dyn NameSource>>>,
+    /// Per-guild `/listen` modes ([`set_listen_modes`](Self::set_listen_modes))
+    #[cfg(feature = "voice")]
+    listen_modes: std::sync::RwLock<Option<Arc<ListenModes>>>,
     /// Piper server process (auto-started when engine is "piper")
     #[cfg(feature = "voice")]
     piper_server: Arc<RwLock<Option<Child>>>,
@@ -463,12 +833,28 @@ pub struct VoiceManager {
     /// Moshi streaming client for real-time full-duplex voice (STT + TTS)
     #[cfg(all(feature = "voice", feature = "voice-moshi"))]
     moshi_client: Arc<RwLock<Option<zoey_provider_voice::MoshiStreamingClient>>>,
+    /// Speaker verification for privileged voice commands and `/voice enroll`
+    #[cfg(feature = "voice-speaker-id")]
+    pub speaker_gate: Option<Arc<crate::speaker_gate::SpeakerGate>>,
 }
 
 impl VoiceManager {
     /// Create a new voice manager
     pub fn new(config: VoiceConfig) -> Self {
         Self {
+            #[cfg(feature = "voice")]
+            latency: Arc::new(Self::latency_tracker(&config)),
+            #[cfg(feature = "voice")]
+            filler_phrases: Arc::new(FillerPhrases::new(config.discord.thinking_filler_phrases.clone())),
+            #[cfg(feature = "voice")]
+            askers: Arc::new(AskerTracker::new(Duration::from_secs(
+                config.discord.asker_window_secs,
+            ))),
+            stage: Arc::new(StageSessions::new(Duration::from_secs(
+                config.discord.stage_speak_timeout_secs,
+            ))),
+            #[cfg(feature = "voice-speaker-id")]
+            speaker_gate: Self::speaker_gate(&config),
             config,
             sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
             #[cfg(feature = "voice")]
@@ -476,6 +862,14 @@ impl VoiceManager {
             #[cfg(feature = "voice")]
             speaking_locks: Arc::new(RwLock::new(std::collections::HashMap::new())),
             #[cfg(feature = "voice")]
+            clips: Arc::new(ClipCache::default()),
+            #[cfg(feature = "voice")]
+            display_names: Arc::new(DisplayNames::default()),
+            #[cfg(feature = "voice")]
+            name_source: std::sync::RwLock::new(None),
+            #[cfg(feature = "voice")]
+            listen_modes: std::sync::RwLock::new(None),
+            #[cfg(feature = "voice")]
             piper_server: Arc::new(RwLock::new(None)),
             #[cfg(all(feature = "voice", feature = "voice-unmute"))]
             unmute_manager: Arc::new(RwLock::new(None)),
@@ -490,10 +884,24 @@ impl VoiceManager {
     #[cfg(feature = "voice")]
     pub fn with_songbird(config: VoiceConfig, songbird: Arc<Songbird>) -> Self {
         Self {
+            latency: Arc::new(Self::latency_tracker(&config)),
+            filler_phrases: Arc::new(FillerPhrases::new(config.discord.thinking_filler_phrases.clone())),
+            askers: Arc::new(AskerTracker::new(Duration::from_secs(
+                config.discord.asker_window_secs,
+            ))),
+            stage: Arc::new(StageSessions::new(Duration::from_secs(
+                config.discord.stage_speak_timeout_secs,
+            ))),
+            #[cfg(feature = "voice-speaker-id")]
+            speaker_gate: Self::speaker_gate(&config),
             config,
             sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
             songbird: Some(songbird),
             speaking_locks: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            clips: Arc::new(ClipCache::default()),
+            display_names: Arc::new(DisplayNames::default()),
+            name_source: std::sync::RwLock::new(None),
+            listen_modes: std::sync::RwLock::new(None),
             piper_server: Arc::new(RwLock::new(None)),
             #[cfg(all(feature = "voice", feature = "voice-unmute"))]
             unmute_manager: Arc::new(RwLock::new(None)),
@@ -504,6 +912,53 @@ impl VoiceManager {
         }
     }
 
+    /// Load the speaker-embedding model when verification is configured
+    #[cfg(feature = "voice-speaker-id")]
+    fn speaker_gate(config: &VoiceConfig) -> Option<Arc<crate::speaker_gate::SpeakerGate>> {
+        use zoey_provider_voice::speaker::DirectoryEnrollmentStore;
+        use zoey_provider_voice::SpeakerRegistry;
+
+        let speaker = config.speaker_config()?;
+        let store = Arc::new(DirectoryEnrollmentStore::new(speaker.enrollment_dir.clone()));
+        match SpeakerRegistry::load(&speaker, store) {
+            Ok(registry) => {
+                info!(model = %speaker.model_path.display(), "Speaker verification enabled for voice commands");
+                Some(Arc::new(crate::speaker_gate::SpeakerGate::new(
+                    Arc::new(registry),
+                    &config.discord.privileged_commands,
+                    config.discord.enroll_utterances,
+                )))
+            }
+            Err(e) => {
+                warn!(error = %e, "Speaker verification unavailable");
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "voice")]
+    fn latency_tracker(config: &VoiceConfig) -> LatencyTracker {
+        let tracker = LatencyTracker::default();
+        match &config.latency_telemetry_endpoint {
+            Some(endpoint) => tracker.with_telemetry_endpoint(endpoint.clone()),
+            None => tracker,
+        }
+    }
+
+    /// Latency percentiles over recent voice conversation turns
+    #[cfg(feature = "voice")]
+    pub fn latency_summary(&self) -> LatencySummary {
+        self.latency.latency_summary()
+    }
+
+    /// Record `mark` for `turn` when the speech is part of a traced turn
+    #[cfg(feature = "voice")]
+    fn mark_turn(&self, turn: Option<TurnId>, mark: TurnMark) {
+        if let Some(id) = turn {
+            self.latency.mark(id, mark);
+        }
+    }
+
     /// Initialize voice manager - starts Piper server or Unmute dockerless if needed
     /// 
     /// Call this after creating the VoiceManager to auto-start the TTS/STT
@@ -1024,12 +1479,16 @@ impl VoiceManager {
             Ok(call_lock) => {
                 info!(guild_id = %guild_id, channel_id = %channel_id, "Joined voice channel");
 
+                // Receive and transcription tasks, owned by the session
+                #[allow(unused_mut)]
+                let mut tasks = Vec::new();
+
                 // Register voice receiver for STT if listen_enabled
                 #[cfg(any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk", feature = "voice-moshi"))]
                 if self.config.discord.listen_enabled {
                     use songbird::events::Event;
                     
-                    let (tx, mut rx) = tokio::sync::mpsc::channel::<(u64, String)>(32);
+                    let (tx, mut rx) = tokio::sync::mpsc::channel::<(u64, String, Option<TurnId>)>(32);
                     let engine = self.config.engine.as_str();
                     let stt_engine = self.config.stt_engine.as_str();
                     
@@ -1096,7 +1555,17 @@ impl VoiceManager {
                         
                         if !skip_buffered {
                             let stt_engine = self.config.stt_engine.clone();
-                            let receiver = std::sync::Arc::new(VoiceReceiver::new(guild_id, tx.clone(), stt_engine));
+                            let receiver = VoiceReceiver::new(guild_id, tx.clone(), stt_engine)
+                                .with_latency(self.latency.clone());
+                            // Prefer on-device wake-word gating over transcribing everything
+                            #[cfg(feature = "voice-wakeword")]
+                            let receiver = receiver.with_wakeword(
+                                self.config.wakeword_config(),
+                                self.listen_modes.read().unwrap().clone(),
+                            );
+                            #[cfg(feature = "voice-speaker-id")]
+                            let receiver = receiver.with_speaker_gate(self.speaker_gate.clone());
+                            let receiver = std::sync::Arc::new(receiver);
                             let handler = VoiceReceiverHandler { receiver: receiver.clone() };
                             
                             call.add_global_event(Event::Core(songbird::CoreEvent::VoiceTick), handler);
@@ -1109,12 +1578,12 @@ impl VoiceManager {
                             
                             // Spawn task to periodically check for completed utterances
                             let receiver_clone = receiver.clone();
-                            tokio::spawn(async move {
+                            tasks.push(tokio::spawn(async move {
                                 loop {
                                     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
                                     receiver_clone.check_and_transcribe().await;
                                 }
-                            });
+                            }));
                         }
                     }
                     
@@ -1123,32 +1592,61 @@ impl VoiceManager {
                     // Spawn task to handle transcriptions and route to agent
                     if let Some(callback) = transcription_callback {
                         let voice_mgr = Arc::clone(&self);
-                        tokio::spawn(async move {
-                            while let Some((user_id, text)) = rx.recv().await {
+                        tasks.push(tokio::spawn(async move {
+                            while let Some((user_id, text, turn)) = rx.recv().await {
                                 info!(user_id = %user_id, text = %text, "Received transcription from voice - routing to agent");
+                                voice_mgr.record_activity(guild_id).await;
+
+                                // Enrollment utterances and unverified privileged commands stop here
+                                #[cfg(feature = "voice-speaker-id")]
+                                if let Some(gate) = &voice_mgr.speaker_gate {
+                                    if let Some(reply) = gate.screen(user_id, &text).await.reply() {
+                                        if let Some(id) = turn {
+                                            voice_mgr.latency.discard(id);
+                                        }
+                                        if let Err(e) = voice_mgr.speak(guild_id, &reply).await {
+                                            warn!(error = %e, "Failed to speak speaker verification reply");
+                                        }
+                                        continue;
+                                    }
+                                }
                                 
-                                // Call the transcription callback to process and respond
-                                if let Some(response) = (callback)(user_id, text.clone()).await {
+                                // Call the transcription callback to process and respond,
+                                // covering a slow reply with the thinking filler
+                                let filler = voice_mgr.thinking_filler(guild_id);
+                                let response = (callback)(user_id, text.clone(), turn).await;
+                                filler.cancel();
+                                if let Some(response) = response {
                                     // Speak the response
-                                    if let Err(e) = voice_mgr.speak(guild_id, &response).await {
+                                    if let Err(e) = voice_mgr.speak_answer(guild_id, user_id, &response, turn).await {
                                         warn!(error = %e, "Failed to speak response");
                                     }
                                 }
+                                // Unaddressed utterances were discarded by the callback
+                                if let Some(id) = turn {
+                                    voice_mgr.latency.finish(id);
+                                }
                             }
-                        });
+                        }));
                     } else {
                         // No callback - just log transcriptions
-                        tokio::spawn(async move {
-                            while let Some((user_id, text)) = rx.recv().await {
+                        let voice_mgr_latency = self.latency.clone();
+                        tasks.push(tokio::spawn(async move {
+                            while let Some((user_id, text, turn)) = rx.recv().await {
                                 info!(user_id = %user_id, text = %text, "Received transcription from voice (no callback configured)");
+                                if let Some(id) = turn {
+                                    voice_mgr_latency.discard(id);
+                                }
                             }
-                        });
+                        }));
                     }
                 }
 
-                // Create session
+                // Create session; a session replaced on re-join stops its tasks
+                let mut session = VoiceSession::new(guild_id, channel_id);
+                session.tasks = tasks;
                 let mut sessions = self.sessions.write().await;
-                sessions.insert(guild_id, VoiceSession::new(guild_id, channel_id));
+                sessions.insert(guild_id, session);
 
                 Ok(())
             }
@@ -1177,6 +1675,7 @@ impl VoiceManager {
         // Remove session
         let mut sessions = self.sessions.write().await;
         sessions.remove(&guild_id);
+        self.stage.leave(guild_id);
 
         info!(guild_id = %guild_id, "Left voice channel");
         Ok(())
@@ -1186,14 +1685,53 @@ impl VoiceManager {
     /// Uses a per-guild lock to ensure responses are played sequentially
     #[cfg(feature = "voice")]
     pub async fn speak(&self, guild_id: u64, text: &str) -> Result<(), String> {
-        use zoey_provider_voice::{AudioFormat, Voice, VoiceConfig as TTSConfig, VoicePlugin};
+        self.speak_traced(guild_id, text, None).await
+    }
+
+    /// [`speak`](Self::speak) recording TTS and playback marks for a voice turn
+    #[cfg(feature = "voice")]
+    pub async fn speak_traced(&self, guild_id: u64, text: &str, turn: Option<TurnId>) -> Result<(), String> {
+        self.speak_queued(guild_id, None, text, turn).await
+    }
+
+    /// Speak the answer to `asker`'s question, naming them first when several
+    /// people have been asking in the guild (see [`crate::attribution`])
+    #[cfg(feature = "voice")]
+    pub async fn speak_answer(
+        &self,
+        guild_id: u64,
+        asker: u64,
+        text: &str,
+        turn: Option<TurnId>,
+    ) -> Result<(), String> {
+        self.askers.enqueue(guild_id, asker);
+        let spoken = self.speak_queued(guild_id, Some(asker), text, turn).await;
+        self.askers.finish(guild_id, asker, Instant::now());
+        spoken
+    }
+
+    #[cfg(feature = "voice")]
+    async fn speak_queued(
+        &self,
+        guild_id: u64,
+        asker: Option<u64>,
+        text: &str,
+        turn: Option<TurnId>,
+    ) -> Result<(), String> {
+        use zoey_provider_voice::{Voice, VoiceConfig as TTSConfig};
 
         // Acquire speaking lock for this guild - ensures sequential playback
         let speaking_lock = self.get_speaking_lock(guild_id).await;
         let _guard = speaking_lock.lock().await;
+        self.mark_turn(turn, TurnMark::TtsStart);
         
         info!(guild_id = %guild_id, "Acquired speaking lock, starting TTS");
 
+        // On a stage, only a speaker is heard
+        if !self.stage.await_floor(guild_id).await {
+            return Err("Not a speaker on this stage".to_string());
+        }
+
         let songbird = self
             .songbird
             .as_ref()
@@ -1215,71 +1753,45 @@ impl VoiceManager {
             }
         }
 
-        // Create TTS plugin based on config
-        let tts = match self.config.engine.as_str() {
-            "elevenlabs" => VoicePlugin::with_elevenlabs(None),
-            "piper" => {
-                // Piper TTS - ultra low latency (~50ms)
-                let endpoint = self
-                    .config
-                    .local_endpoint
-                    .clone()
-                    .unwrap_or_else(|| "http://localhost:5500".to_string());
-                VoicePlugin::with_piper(&endpoint)
-            }
-            #[cfg(feature = "voice-unmute")]
-            "unmute" => {
-                // Unmute TTS - GPU-accelerated, streaming WebSocket protocol
-                // Use endpoint from dockerless manager if running, otherwise from config
-                let endpoint = self.get_unmute_endpoint().await;
-                VoicePlugin::with_unmute(&endpoint)
-            }
-            "supertonic" => {
-                // Supertonic TTS - Ultra-fast on-device TTS (~10-50ms latency)
-                // Uses ONNX models, runs via HTTP server on port 5080
-                let endpoint = self
-                    .config
-                    .local_endpoint
-                    .clone()
-                    .unwrap_or_else(|| "http://127.0.0.1:5080".to_string());
-                info!(guild_id = %guild_id, endpoint = %endpoint, "Using Supertonic TTS");
-                VoicePlugin::with_supertonic(&endpoint)
-            }
-            "pocket_tts" | "pocket-tts" | "pockettts" => {
-                // Pocket TTS - Lightweight CPU-based TTS by Kyutai Labs (~200ms latency)
-                // 100M parameters, ~6x real-time synthesis, no GPU required
-                // Run: pip install pocket-tts && pocket-tts serve
-                let endpoint = self
-                    .config
-                    .local_endpoint
-                    .clone()
-                    .unwrap_or_else(|| "http://localhost:8000".to_string());
-                info!(guild_id = %guild_id, endpoint = %endpoint, "Using Pocket TTS");
-                VoicePlugin::with_pocket_tts(&endpoint)
+        // Name the asker while the answer is being synthesized
+        let mut attribution = None;
+        if let Some(asker) = asker {
+            if let Some(clip) = self.attribution_clip(guild_id, asker).await {
+                let track = call_lock.lock().await.play_input(clip.bytes.into());
+                attribution = Some((track, clip.duration, Instant::now()));
             }
-            #[cfg(feature = "voice-moshi")]
-            "moshi" => {
-                // Moshi TTS - Full-duplex real-time voice model (~200ms latency)
-                // Uses Kyutai's Moshi model with Mimi audio codec
-                let endpoint = self
-                    .config
-                    .local_endpoint
-                    .clone()
-                    .or_else(|| self.config.stt_endpoint.clone())
-                    .unwrap_or_else(|| "localhost:8998".to_string());
-                info!(guild_id = %guild_id, endpoint = %endpoint, "Using Moshi TTS");
-                VoicePlugin::with_moshi(&endpoint)
+        }
+
+        let tts = self.tts_plugin(guild_id).await;
+
+        // Engines that stream start playing after the first chunk
+        if self.config.engine != "unmute" && tts.supports_streaming().await {
+            let streamed = async {
+                let stream = tts.synthesize_stream_chunks(text, DEFAULT_CHUNK_MS).await?;
+                zoey_provider_voice::streaming::playback(stream).await
             }
-            "local" => {
-                let endpoint = self
-                    .config
-                    .local_endpoint
-                    .clone()
-                    .unwrap_or_else(|| "http://localhost:5000".to_string());
-                VoicePlugin::with_local(endpoint)
+            .await;
+            match streamed {
+                Ok(playback) => {
+                    self.mark_turn(turn, TurnMark::TtsFirstByte);
+                    info!(
+                        guild_id = %guild_id,
+                        time_to_first_chunk_ms = %playback.time_to_first_chunk.as_millis(),
+                        sample_rate = %playback.sample_rate,
+                        "First TTS chunk, playing while synthesis continues"
+                    );
+                    if let Some((track, length, started)) = attribution {
+                        self.wait_for_playback(guild_id, &track, length.saturating_sub(started.elapsed()))
+                            .await?;
+                    }
+                    let mut call = call_lock.lock().await;
+                    return self.play_stream(guild_id, &mut call, playback, turn).await;
+                }
+                Err(e) => {
+                    warn!(guild_id = %guild_id, error = %e, "Streaming TTS failed, synthesizing the whole reply");
+                }
             }
-            _ => VoicePlugin::with_openai(None), // Default to OpenAI
-        };
+        }
 
         // For Unmute, use native WebSocket streaming TTS for realtime playback
         // Unmute streams audio chunks as they're generated - play them immediately!
@@ -1323,6 +1835,7 @@ impl VoiceManager {
                                     Ok(data) => {
                                         if first_chunk_time.is_none() {
                                             first_chunk_time = Some(std::time::Instant::now());
+                                            self.mark_turn(turn, TurnMark::TtsFirstByte);
                                             let latency_ms = tts_start.elapsed().as_millis();
                                             info!(
                                                 guild_id = %guild_id,
@@ -1373,6 +1886,7 @@ impl VoiceManager {
                         Ok(chunk) => {
                             if first_chunk_time.is_none() && !chunk.data.is_empty() {
                                 first_chunk_time = Some(std::time::Instant::now());
+                                self.mark_turn(turn, TurnMark::TtsFirstByte);
                                 let latency_ms = chunk.timestamp_ms.unwrap_or(0);
                                 info!(
                                     guild_id = %guild_id,
@@ -1422,28 +1936,36 @@ impl VoiceManager {
                 data: Bytes::from(audio_data),
                 format,
                 sample_rate: self.config.sample_rate,
+                channels: 1,
                 duration_ms: None,
                 character_count: text.len(),
+                engine: None,
             }
         } else {
-            // Non-Unmute engines
+            // Non-Unmute engines; long replies are synthesized in chunks and joined
             tts
-                .synthesize(text)
+                .synthesize_long(text)
                 .await
                 .map_err(|e| format!("TTS synthesis failed: {}", e))?
+                .audio
         };
         
         #[cfg(not(feature = "voice-unmute"))]
         let audio = if self.config.engine == "unmute" {
             return Err("Unmute feature not enabled".to_string());
         } else {
-            // Synthesize speech (non-Unmute engines: OpenAI, ElevenLabs, Piper, etc.)
+            // Synthesize speech (non-Unmute engines: OpenAI, ElevenLabs, Piper, etc.),
+            // chunked when the reply is over the engine's input limit
             tts
-                .synthesize(text)
+                .synthesize_long(text)
                 .await
                 .map_err(|e| format!("TTS synthesis failed: {}", e))?
+                .audio
         };
 
+        // Non-streaming engines deliver all audio at once (streamed chunks marked earlier)
+        self.mark_turn(turn, TurnMark::TtsFirstByte);
+
         info!(
             guild_id = %guild_id,
             text_len = %text.len(),
@@ -1452,54 +1974,30 @@ impl VoiceManager {
             "Synthesized speech"
         );
 
+        if let Some((track, length, started)) = attribution {
+            self.wait_for_playback(guild_id, &track, length.saturating_sub(started.elapsed()))
+                .await?;
+        }
+
         // Play audio in voice channel
         let mut call = call_lock.lock().await;
 
         info!(audio_size = %audio.data.len(), format = ?audio.format, "Creating audio input from bytes");
 
-        // Handle different audio formats
-        let input: Input = match audio.format {
-            AudioFormat::Pcm => {
-                // Raw PCM audio - wrap in WAV header for symphonia to decode
-                // Piper/Unmute outputs 16-bit signed PCM at the configured sample rate (mono)
-                let pcm_data = audio.data.to_vec();
-                let sample_rate = audio.sample_rate;
-                let wav_data = wrap_pcm_in_wav(&pcm_data, sample_rate, 1, 16);
-                
-                info!(
-                    pcm_size = %pcm_data.len(),
-                    wav_size = %wav_data.len(),
-                    sample_rate = %sample_rate,
-                    "Wrapped PCM in WAV header"
-                );
-                
-                let audio_bytes: &'static [u8] = Box::leak(wav_data.into_boxed_slice());
-                audio_bytes.into()
-            }
-            _ => {
-                // For encoded formats (MP3, WAV, etc), let symphonia auto-detect
-                let audio_bytes: &'static [u8] = Box::leak(audio.data.to_vec().into_boxed_slice());
-                audio_bytes.into()
-            }
-        };
+        let input: Input = Self::leak_playable(&audio).into();
 
-        let _track_handle = call.play_input(input);
+        let track = call.play_input(input);
+        self.mark_turn(turn, TurnMark::PlaybackStart);
 
         info!(guild_id = %guild_id, "Started playing audio in voice channel");
 
         // Estimate playback duration and wait for it to complete
         // This keeps the lock held until audio finishes, preventing overlaps
         let audio_size = audio.data.len();
-        let bytes_per_second = match audio.format {
-            // PCM 16-bit mono at sample_rate
-            AudioFormat::Pcm => (audio.sample_rate * 2) as f64,  // 24000Hz * 2 bytes = 48000 bytes/sec
-            // MP3/encoded formats are ~3-4KB/s for speech
-            _ => 4000.0,
-        };
-        let duration_secs = (audio_size as f64 / bytes_per_second).max(1.0).ceil() as u64;
+        let duration = Self::playback_estimate(&audio);
         
-        info!(guild_id = %guild_id, duration_secs = %duration_secs, audio_bytes = %audio_size, "Waiting for audio playback to complete");
-        tokio::time::sleep(Duration::from_secs(duration_secs)).await;
+        info!(guild_id = %guild_id, duration_secs = %duration.as_secs(), audio_bytes = %audio_size, "Waiting for audio playback to complete");
+        let played = self.wait_for_playback(guild_id, &track, duration).await;
 
         // Mark as not speaking
         {
@@ -1511,7 +2009,331 @@ impl VoiceManager {
         
         info!(guild_id = %guild_id, "Releasing speaking lock");
 
-        Ok(())
+        played
+    }
+
+    /// Play a TTS stream as its chunks arrive and wait until it has been heard
+    #[cfg(feature = "voice")]
+    async fn play_stream(
+        &self,
+        guild_id: u64,
+        call: &mut Call,
+        playback: StreamPlayback,
+        turn: Option<TurnId>,
+    ) -> Result<(), String> {
+        let StreamPlayback {
+            reader,
+            sample_rate,
+            channels,
+            length,
+            ..
+        } = playback;
+        let input: Input = RawAdapter::new(TtsStreamSource(reader), sample_rate, channels as u32).into();
+        let track = call.play_input(input);
+        let started = Instant::now();
+        self.mark_turn(turn, TurnMark::PlaybackStart);
+
+        // The length is known once synthesis is done; wait out what is left of it
+        let length = length.wait().await;
+        info!(
+            guild_id = %guild_id,
+            duration_ms = %length.as_millis(),
+            "TTS stream complete, waiting for playback to finish"
+        );
+        let played = self
+            .wait_for_playback(guild_id, &track, length.saturating_sub(started.elapsed()))
+            .await;
+
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(session) = sessions.get_mut(&guild_id) {
+                session.is_speaking = false;
+            }
+        }
+        info!(guild_id = %guild_id, "Releasing speaking lock");
+
+        played
+    }
+
+    /// Wait out `length` of playback, pausing the track while demoted on a stage
+    ///
+    /// Stops the track if the bot is not re-promoted within the stage speak timeout.
+    #[cfg(feature = "voice")]
+    async fn wait_for_playback(
+        &self,
+        guild_id: u64,
+        track: &songbird::tracks::TrackHandle,
+        length: Duration,
+    ) -> Result<(), String> {
+        let mut playback = crate::stage::Playback::new(length, Instant::now());
+        loop {
+            let changed = self.stage.changed();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            let now = Instant::now();
+            if self.stage.holds_floor(guild_id) {
+                if playback.resume(now) {
+                    info!(guild_id = %guild_id, "Promoted on stage again, resuming answer");
+                    let _ = track.play();
+                }
+            } else if playback.pause(now) {
+                info!(guild_id = %guild_id, "Moved to the stage audience mid-answer, pausing");
+                let _ = track.pause();
+            }
+            let wait = match playback.paused_for(now) {
+                Some(paused) => match self.stage.speak_timeout().checked_sub(paused) {
+                    Some(left) if !left.is_zero() => left,
+                    _ => {
+                        let _ = track.stop();
+                        return Err("Not promoted again on stage; answer dropped".to_string());
+                    }
+                },
+                None if playback.remaining(now).is_zero() => return Ok(()),
+                None => playback.remaining(now),
+            };
+            tokio::select! {
+                _ = changed => {}
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+    }
+
+    /// Start the thinking filler for a reply being generated in `guild_id`
+    ///
+    /// Plays after `thinking_filler_ms` unless the returned handle is
+    /// cancelled first; cancelling mid-filler stops it so the reply is not
+    /// delayed. Nothing is played while a previous reply is still speaking.
+    #[cfg(feature = "voice")]
+    pub fn thinking_filler(self: &Arc<Self>, guild_id: u64) -> ThinkingFiller {
+        let vm = Arc::clone(self);
+        ThinkingFiller::start(
+            Duration::from_millis(self.config.discord.thinking_filler_ms),
+            move || async move {
+                let speaking_lock = vm.get_speaking_lock(guild_id).await;
+                let _guard = speaking_lock.try_lock().ok()?;
+                let input = vm.filler_input(guild_id).await?;
+                let call_lock = vm.songbird.as_ref()?.get(GuildId::new(guild_id))?;
+                let track = call_lock.lock().await.play_input(input);
+                debug!(guild_id = %guild_id, "Playing thinking filler");
+                Some(move || {
+                    let _ = track.stop();
+                })
+            },
+        )
+    }
+
+    /// Filler clip from `thinking_filler_clip`, else the next phrase synthesized once and reused
+    #[cfg(feature = "voice")]
+    async fn filler_input(&self, guild_id: u64) -> Option<Input> {
+        if let Some(path) = &self.config.discord.thinking_filler_clip {
+            return Some(SongbirdFile::new(path.clone()).into());
+        }
+        let phrase = self.filler_phrases.pick()?.to_string();
+        Some(self.clip(guild_id, &phrase).await?.bytes.into())
+    }
+
+    /// "Answering <name>:" for `asker`, unless disabled or they are the only one asking lately
+    #[cfg(feature = "voice")]
+    async fn attribution_clip(&self, guild_id: u64, asker: u64) -> Option<Clip> {
+        use crate::attribution::{attribution_phrase, speakable_name};
+
+        if !self.config.discord.announce_askers
+            || !self.askers.should_attribute(guild_id, asker, Instant::now())
+        {
+            return None;
+        }
+        let source = self.name_source.read().unwrap().clone()?;
+        let name = self
+            .display_names
+            .resolve(source.as_ref(), guild_id, asker)
+            .await?;
+        let phrase = attribution_phrase(&speakable_name(&name)?);
+        debug!(guild_id = %guild_id, asker = %asker, phrase = %phrase, "Announcing asker");
+        self.clip(guild_id, &phrase).await
+    }
+
+    /// `text` synthesized once and reused from the clip cache
+    #[cfg(feature = "voice")]
+    async fn clip(&self, guild_id: u64, text: &str) -> Option<Clip> {
+        if let Some(clip) = self.clips.get(text) {
+            return Some(clip);
+        }
+        let audio = match self.tts_plugin(guild_id).await.synthesize(text).await {
+            Ok(audio) => audio,
+            Err(e) => {
+                warn!(error = %e, "Failed to synthesize voice clip");
+                return None;
+            }
+        };
+        let clip = Clip {
+            bytes: Self::leak_playable(&audio),
+            duration: Self::playback_estimate(&audio),
+        };
+        self.clips.insert(text.to_string(), clip);
+        Some(clip)
+    }
+
+    /// Where display names for asker attributions come from
+    #[cfg(feature = "voice")]
+    pub fn set_name_source(&self, source: Arc<dyn NameSource>) {
+        *self.name_source.write().unwrap() = Some(source);
+    }
+
+    /// Listen modes deciding whether receivers gate STT on the wake word
+    #[cfg(feature = "voice")]
+    pub fn set_listen_modes(&self, modes: Arc<ListenModes>) {
+        *self.listen_modes.write().unwrap() = Some(modes);
+    }
+
+    /// TTS engine selected by the voice config
+    #[cfg(feature = "voice")]
+    async fn tts_plugin(&self, guild_id: u64) -> zoey_provider_voice::VoicePlugin {
+        use zoey_provider_voice::length_limit::DEFAULT_MAX_TTS_CHARS;
+        use zoey_provider_voice::{EffectConfig, LengthPolicy, VoicePlugin};
+
+        let mut plugin = match self.config.engine.as_str() {
+            "elevenlabs" => VoicePlugin::with_elevenlabs(None),
+            "piper" => {
+                // Piper TTS - ultra low latency (~50ms)
+                let endpoint = self
+                    .config
+                    .local_endpoint
+                    .clone()
+                    .unwrap_or_else(|| "http://localhost:5500".to_string());
+                VoicePlugin::with_piper(&endpoint)
+            }
+            #[cfg(feature = "voice-unmute")]
+            "unmute" => {
+                // Unmute TTS - GPU-accelerated, streaming WebSocket protocol
+                // Use endpoint from dockerless manager if running, otherwise from config
+                let endpoint = self.get_unmute_endpoint().await;
+                VoicePlugin::with_unmute(&endpoint)
+            }
+            "supertonic" => {
+                // Supertonic TTS - Ultra-fast on-device TTS (~10-50ms latency)
+                // Uses ONNX models, runs via HTTP server on port 5080
+                let endpoint = self
+                    .config
+                    .local_endpoint
+                    .clone()
+                    .unwrap_or_else(|| "http://127.0.0.1:5080".to_string());
+                info!(guild_id = %guild_id, endpoint = %endpoint, "Using Supertonic TTS");
+                VoicePlugin::with_supertonic(&endpoint)
+            }
+            "pocket_tts" | "pocket-tts" | "pockettts" => {
+                // Pocket TTS - Lightweight CPU-based TTS by Kyutai Labs (~200ms latency)
+                // 100M parameters, ~6x real-time synthesis, no GPU required
+                // Run: pip install pocket-tts && pocket-tts serve
+                let endpoint = self
+                    .config
+                    .local_endpoint
+                    .clone()
+                    .unwrap_or_else(|| "http://localhost:8000".to_string());
+                info!(guild_id = %guild_id, endpoint = %endpoint, "Using Pocket TTS");
+                VoicePlugin::with_pocket_tts(&endpoint)
+            }
+            #[cfg(feature = "voice-moshi")]
+            "moshi" => {
+                // Moshi TTS - Full-duplex real-time voice model (~200ms latency)
+                // Uses Kyutai's Moshi model with Mimi audio codec
+                let endpoint = self
+                    .config
+                    .local_endpoint
+                    .clone()
+                    .or_else(|| self.config.stt_endpoint.clone())
+                    .unwrap_or_else(|| "localhost:8998".to_string());
+                info!(guild_id = %guild_id, endpoint = %endpoint, "Using Moshi TTS");
+                VoicePlugin::with_moshi(&endpoint)
+            }
+            "local" => {
+                let endpoint = self
+                    .config
+                    .local_endpoint
+                    .clone()
+                    .unwrap_or_else(|| "http://localhost:5000".to_string());
+                VoicePlugin::with_local(endpoint)
+            }
+            _ => VoicePlugin::with_openai(None), // Default to OpenAI
+        };
+        plugin.set_effects(EffectConfig::parse_list(&self.config.effects));
+        // A runaway reply is cut short rather than spoken for minutes
+        plugin.set_length_limit(DEFAULT_MAX_TTS_CHARS, LengthPolicy::TruncateWithNotice);
+        plugin
+    }
+
+    /// Rough playback length of synthesized audio, at least a second
+    #[cfg(feature = "voice")]
+    fn playback_estimate(audio: &zoey_provider_voice::AudioData) -> Duration {
+        use zoey_provider_voice::AudioFormat;
+
+        let bytes_per_second = match audio.format {
+            // PCM 16-bit mono at sample_rate
+            AudioFormat::Pcm => (audio.sample_rate * 2) as f64, // 24000Hz * 2 bytes = 48000 bytes/sec
+            // MP3/encoded formats are ~3-4KB/s for speech
+            _ => 4000.0,
+        };
+        Duration::from_secs((audio.data.len() as f64 / bytes_per_second).max(1.0).ceil() as u64)
+    }
+
+    /// Synthesized audio as bytes songbird can decode, leaked for the `'static` input
+    #[cfg(feature = "voice")]
+    fn leak_playable(audio: &zoey_provider_voice::AudioData) -> &'static [u8] {
+        use zoey_provider_voice::AudioFormat;
+
+        match audio.format {
+            AudioFormat::Pcm => {
+                // Raw PCM audio - wrap in WAV header for symphonia to decode
+                // Piper/Unmute outputs 16-bit signed PCM at the configured sample rate (mono)
+                let pcm_data = audio.data.to_vec();
+                let sample_rate = audio.sample_rate;
+                let wav_data = wrap_pcm_in_wav(&pcm_data, sample_rate, 1, 16);
+                
+                info!(
+                    pcm_size = %pcm_data.len(),
+                    wav_size = %wav_data.len(),
+                    sample_rate = %sample_rate,
+                    "Wrapped PCM in WAV header"
+                );
+                
+                Box::leak(wav_data.into_boxed_slice())
+            }
+            _ => {
+                // For encoded formats (MP3, WAV, etc), let symphonia auto-detect
+                Box::leak(audio.data.to_vec().into_boxed_slice())
+            }
+        }
+    }
+
+    /// Record the text channel a join was asked from and who is in the voice channel
+    pub async fn note_join(
+        &self,
+        guild_id: u64,
+        text_channel_id: u64,
+        members: impl IntoIterator<Item = u64>,
+    ) {
+        if let Some(session) = self.sessions.write().await.get_mut(&guild_id) {
+            session.text_channel_id = Some(text_channel_id);
+            session.set_members(members);
+        }
+    }
+
+    /// Apply another member's voice state to the guild's session, if any
+    pub async fn observe_member(&self, guild_id: u64, user_id: u64, channel_id: Option<u64>) {
+        if let Some(session) = self.sessions.write().await.get_mut(&guild_id) {
+            session.observe_member(user_id, channel_id);
+        }
+    }
+
+    /// Note a transcription or spoken reply, postponing the idle leave
+    pub async fn record_activity(&self, guild_id: u64) {
+        if let Some(session) = self.sessions.write().await.get_mut(&guild_id) {
+            session.touch();
+        }
+    }
+
+    /// Whether the bot is in a voice channel of the guild
+    pub async fn has_session(&self, guild_id: u64) -> bool {
+        self.sessions.read().await.contains_key(&guild_id)
     }
 
     /// Update user presence in voice channel
@@ -1527,36 +2349,34 @@ impl VoiceManager {
         }
     }
 
-    /// Check for idle/alone sessions and leave if needed
-    pub async fn check_and_cleanup(&self) {
+    /// Check for idle/alone sessions and leave if needed, returning the channels left
+    pub async fn check_and_cleanup(&self) -> Vec<Departure> {
         if !self.config.enabled {
-            return;
+            return Vec::new();
         }
 
-        let sessions_to_leave: Vec<u64> = {
+        let policy = AutoLeave::from_settings(&self.config.discord);
+        let now = Instant::now();
+        let departures: Vec<Departure> = {
             let sessions = self.sessions.read().await;
             sessions
                 .iter()
-                .filter(|(_, session)| {
-                    // Leave if alone and configured to do so
-                    if self.config.discord.leave_when_alone && session.is_alone() {
-                        return true;
-                    }
-                    // Leave if idle too long
-                    if session.is_idle(self.config.discord.idle_timeout_seconds) {
-                        return true;
-                    }
-                    false
+                .filter_map(|(guild_id, session)| {
+                    Some(Departure {
+                        guild_id: *guild_id,
+                        text_channel_id: session.text_channel_id,
+                        reason: session.leave_reason(&policy, now)?,
+                    })
                 })
-                .map(|(guild_id, _)| *guild_id)
                 .collect()
         };
 
         #[cfg(feature = "voice")]
-        for guild_id in sessions_to_leave {
-            info!(guild_id = %guild_id, "Leaving voice channel due to idle/alone");
-            let _ = self.leave_channel(guild_id).await;
+        for departure in &departures {
+            info!(guild_id = %departure.guild_id, reason = ?departure.reason, "Leaving voice channel due to idle/alone");
+            let _ = self.leave_channel(departure.guild_id).await;
         }
+        departures
     }
 
     /// Check if listening (STT) is available
@@ -1576,6 +2396,30 @@ impl VoiceManager {
 // Voice Receiver for STT (Speech-to-Text)
 // ============================================================================
 
+/// Discord voice receive sample rate (Hz)
+#[cfg(any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk", feature = "voice-moshi"))]
+const DISCORD_SAMPLE_RATE: u32 = 48000;
+
+/// Discord voice receive channel count (interleaved stereo)
+#[cfg(any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk", feature = "voice-moshi"))]
+const DISCORD_CHANNELS: u16 = 2;
+
+/// Wrap received Discord PCM as voice-plugin audio, keeping its native spec
+#[cfg(any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk"))]
+fn discord_pcm_audio(samples: &[i16]) -> zoey_provider_voice::AudioData {
+    use zoey_provider_voice::{audio::samples_to_pcm16, AudioData, AudioFormat};
+
+    let frames = samples.len() as u64 / DISCORD_CHANNELS as u64;
+    let mut audio = AudioData::new(
+        bytes::Bytes::from(samples_to_pcm16(samples)),
+        AudioFormat::Pcm,
+        DISCORD_SAMPLE_RATE,
+    )
+    .with_channels(DISCORD_CHANNELS);
+    audio.duration_ms = Some(frames * 1000 / DISCORD_SAMPLE_RATE as u64);
+    audio
+}
+
 /// Audio buffer for accumulating voice data from a user
 #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
 #[derive(Debug)]
@@ -1632,28 +2476,14 @@ impl UserAudioBuffer {
     /// Discord audio is 48kHz stereo (2 channels)
     pub fn duration_ms(&self) -> u64 {
         // samples / (sample_rate * channels) * 1000
-        (self.samples.len() as u64 * 1000) / (48000 * 2)
+        (self.samples.len() as u64 * 1000) / (DISCORD_SAMPLE_RATE as u64 * DISCORD_CHANNELS as u64)
     }
 
-    /// Convert stereo 48kHz to mono 16kHz for Whisper
-    pub fn to_mono_16khz(&self) -> Vec<i16> {
-        // First convert stereo to mono by averaging channels
-        let mono: Vec<i16> = self.samples
-            .chunks(2)
-            .map(|chunk| {
-                if chunk.len() == 2 {
-                    ((chunk[0] as i32 + chunk[1] as i32) / 2) as i16
-                } else {
-                    chunk[0]
-                }
-            })
-            .collect();
-
-        // Then downsample from 48kHz to 16kHz (factor of 3)
-        mono.iter()
-            .step_by(3)
-            .copied()
-            .collect()
+    /// Buffered audio in Discord's native layout (48kHz stereo PCM)
+    ///
+    /// The voice plugin converts it to whatever the STT engine expects.
+    pub fn to_audio_data(&self) -> zoey_provider_voice::AudioData {
+        discord_pcm_audio(&self.samples)
     }
 
     /// Clear the buffer
@@ -1668,6 +2498,142 @@ impl UserAudioBuffer {
     }
 }
 
+/// How long buffered STT stays enabled after a wake-word detection,
+/// matching the text handler's voice conversation window
+#[cfg(feature = "voice-wakeword")]
+const WAKEWORD_WINDOW: Duration = Duration::from_secs(45);
+
+/// VoiceTick batches (20ms each) queued for the wake-word worker before audio is dropped
+#[cfg(feature = "voice-wakeword")]
+const WAKEWORD_QUEUE_TICKS: usize = 250;
+
+/// A speaker's detector state as seen by the STT loop
+#[cfg(all(feature = "voice-wakeword", any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk")))]
+#[derive(Debug, Clone, Copy, Default)]
+struct WakeWindow {
+    /// The worker has a detector for this speaker; until then everything is transcribed
+    ready: bool,
+    /// STT stays open until this instant
+    open_until: Option<Instant>,
+}
+
+/// Per-user wake-word gates in front of buffered STT
+///
+/// Audio is scored on a dedicated worker thread, so loading detectors and
+/// running the ONNX models never blocks the VoiceTick path; completed
+/// utterances are only transcribed while the speaker's window is open. The
+/// gate only applies in [`ListenMode::WakeWord`]: always-on and off guilds
+/// skip detection and leave the decision to the transcription callback.
+#[cfg(all(feature = "voice-wakeword", any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk")))]
+pub struct WakeWordGate {
+    guild_id: u64,
+    window: Duration,
+    listen_modes: Option<Arc<ListenModes>>,
+    audio_tx: std::sync::mpsc::SyncSender<(u64, Vec<i16>)>,
+    windows: Arc<parking_lot::Mutex<std::collections::HashMap<u64, WakeWindow>>>,
+}
+
+#[cfg(all(feature = "voice-wakeword", any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk")))]
+impl WakeWordGate {
+    /// Create a gate and start its detector worker, failing if the configured models are missing
+    pub fn new(
+        guild_id: u64,
+        config: zoey_provider_voice::wakeword::WakeWordConfig,
+        listen_modes: Option<Arc<ListenModes>>,
+    ) -> Result<Self, String> {
+        zoey_provider_voice::wakeword::check_model_files(&config.model_path()).map_err(|e| e.to_string())?;
+        let (audio_tx, audio_rx) = std::sync::mpsc::sync_channel(WAKEWORD_QUEUE_TICKS);
+        let windows = Arc::new(parking_lot::Mutex::new(std::collections::HashMap::new()));
+        let window = config.window;
+        let worker_windows = windows.clone();
+        // Exits once the gate, and with it the sending half, is dropped
+        std::thread::Builder::new()
+            .name(format!("wakeword-{}", guild_id))
+            .spawn(move || Self::run_detectors(config, audio_rx, worker_windows))
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            guild_id,
+            window,
+            listen_modes,
+            audio_tx,
+            windows,
+        })
+    }
+
+    /// Whether the guild's listen mode uses the wake word at all
+    fn is_gating(&self) -> bool {
+        self.listen_modes
+            .as_ref()
+            .map(|modes| modes.mode(self.guild_id) == ListenMode::WakeWord)
+            .unwrap_or(true)
+    }
+
+    /// Queue received Discord audio (48kHz stereo) for scoring
+    fn push_audio(&self, user_id: u64, audio: &[i16]) {
+        if !self.is_gating() {
+            return;
+        }
+        // A backed-up worker loses audio rather than stalling voice receive
+        let _ = self.audio_tx.try_send((user_id, audio.to_vec()));
+    }
+
+    /// Worker loop: load each speaker's detector on first audio and score what arrives
+    fn run_detectors(
+        config: zoey_provider_voice::wakeword::WakeWordConfig,
+        audio_rx: std::sync::mpsc::Receiver<(u64, Vec<i16>)>,
+        windows: Arc<parking_lot::Mutex<std::collections::HashMap<u64, WakeWindow>>>,
+    ) {
+        use zoey_provider_voice::audio::{downmix_to_mono, resample_linear};
+        use zoey_provider_voice::wakeword::{WakeWordDetector, WAKEWORD_SAMPLE_RATE};
+
+        // `None` marks speakers whose detector failed to load, so it isn't retried every tick
+        let mut detectors: std::collections::HashMap<u64, Option<WakeWordDetector>> =
+            std::collections::HashMap::new();
+        while let Ok((user_id, audio)) = audio_rx.recv() {
+            let detector = detectors.entry(user_id).or_insert_with(|| {
+                match WakeWordDetector::new(config.model_path(), config.threshold) {
+                    Ok(detector) => {
+                        windows.lock().entry(user_id).or_default().ready = true;
+                        Some(detector)
+                    }
+                    Err(e) => {
+                        warn!(user_id = %user_id, error = %e, "Wake-word detector failed to load");
+                        None
+                    }
+                }
+            });
+            let Some(detector) = detector else {
+                continue;
+            };
+            let mono = downmix_to_mono(&audio, DISCORD_CHANNELS);
+            let samples = resample_linear(&mono, 1, DISCORD_SAMPLE_RATE, WAKEWORD_SAMPLE_RATE);
+            if let Some(detection) = detector.process(&samples) {
+                info!(user_id = %user_id, score = %detection.score, "Wake word detected");
+                windows.lock().entry(user_id).or_default().open_until = Some(Instant::now() + config.window);
+            }
+        }
+    }
+
+    /// Whether a user's completed utterance should go to STT
+    ///
+    /// Users without a loaded detector fall back to transcribing everything.
+    fn should_transcribe(&self, user_id: u64) -> bool {
+        if !self.is_gating() {
+            return true;
+        }
+        self.windows
+            .lock()
+            .get(&user_id)
+            .map(|w| !w.ready || w.open_until.is_some_and(|until| Instant::now() < until))
+            .unwrap_or(true)
+    }
+
+    /// Keep a user's window open while they keep talking to the bot
+    fn keep_open(&self, user_id: u64) {
+        self.windows.lock().entry(user_id).or_default().open_until = Some(Instant::now() + self.window);
+    }
+}
+
 /// Voice receiver event handler for capturing audio from users
 #[cfg(any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk"))]
 pub struct VoiceReceiver {
@@ -1676,9 +2642,17 @@ pub struct VoiceReceiver {
     /// Audio buffers per user
     pub buffers: Arc<parking_lot::RwLock<std::collections::HashMap<u64, UserAudioBuffer>>>,
     /// Channel to send transcribed text
-    pub transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+    pub transcription_tx: TranscriptionSender,
     /// STT engine to use (whisper, vosk)
     pub stt_engine: String,
+    /// Wake-word gate; `None` transcribes every utterance
+    #[cfg(feature = "voice-wakeword")]
+    pub wake_gate: Option<WakeWordGate>,
+    /// Receives each transcribed utterance's audio for speaker verification
+    #[cfg(feature = "voice-speaker-id")]
+    pub speaker_gate: Option<Arc<crate::speaker_gate::SpeakerGate>>,
+    /// Tracker for voice turns started by this receiver's utterances
+    pub latency: Option<Arc<LatencyTracker>>,
 }
 
 // ============================================================================
@@ -1698,7 +2672,7 @@ pub struct RealtimeVoiceReceiver {
     /// Persistent Unmute conversation (per-user)
     pub conversations: Arc<parking_lot::RwLock<std::collections::HashMap<u64, RealtimeUserState>>>,
     /// Channel to send transcribed text
-    pub transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+    pub transcription_tx: TranscriptionSender,
     /// Unmute endpoint
     pub endpoint: String,
 }
@@ -1720,7 +2694,7 @@ impl RealtimeVoiceReceiver {
     pub fn new(
         guild_id: u64,
         endpoint: &str,
-        transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+        transcription_tx: TranscriptionSender,
     ) -> Self {
         Self {
             guild_id,
@@ -1795,7 +2769,7 @@ impl RealtimeVoiceReceiver {
         guild_id: u64,
         endpoint: String,
         mut audio_rx: tokio::sync::mpsc::Receiver<Vec<i16>>,
-        transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+        transcription_tx: TranscriptionSender,
     ) {
         use zoey_provider_voice::UnmuteRealtime;
         
@@ -1840,7 +2814,7 @@ impl RealtimeVoiceReceiver {
                     if let Some(text) = text {
                         if !text.trim().is_empty() {
                             info!(user_id = %user_id, text = %text, "Realtime transcription");
-                            let _ = transcription_tx.send((user_id, text)).await;
+                            let _ = transcription_tx.send((user_id, text, None)).await;
                         }
                     }
                 }
@@ -1852,20 +2826,9 @@ impl RealtimeVoiceReceiver {
     
     /// Convert 48kHz stereo to 16kHz mono
     fn convert_48k_stereo_to_16k_mono(stereo_48k: &[i16]) -> Vec<i16> {
-        // Average stereo channels
-        let mono: Vec<i16> = stereo_48k
-            .chunks(2)
-            .map(|chunk| {
-                if chunk.len() == 2 {
-                    ((chunk[0] as i32 + chunk[1] as i32) / 2) as i16
-                } else {
-                    chunk[0]
-                }
-            })
-            .collect();
-        
-        // Downsample 48kHz -> 16kHz (factor of 3)
-        mono.iter().step_by(3).copied().collect()
+        use zoey_provider_voice::audio::{downmix_to_mono, resample_linear};
+        let mono = downmix_to_mono(stereo_48k, DISCORD_CHANNELS);
+        resample_linear(&mono, 1, DISCORD_SAMPLE_RATE, 16000)
     }
     
     /// Calculate RMS for VAD
@@ -1882,7 +2845,7 @@ impl RealtimeVoiceReceiver {
 impl VoiceReceiver {
     pub fn new(
         guild_id: u64,
-        transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+        transcription_tx: TranscriptionSender,
         stt_engine: String,
     ) -> Self {
         Self {
@@ -1890,9 +2853,49 @@ impl VoiceReceiver {
             buffers: Arc::new(parking_lot::RwLock::new(std::collections::HashMap::new())),
             transcription_tx,
             stt_engine,
+            #[cfg(feature = "voice-wakeword")]
+            wake_gate: None,
+            #[cfg(feature = "voice-speaker-id")]
+            speaker_gate: None,
+            latency: None,
         }
     }
 
+    /// Hand each transcribed utterance's audio to the speaker gate
+    #[cfg(feature = "voice-speaker-id")]
+    pub fn with_speaker_gate(mut self, gate: Option<Arc<crate::speaker_gate::SpeakerGate>>) -> Self {
+        self.speaker_gate = gate;
+        self
+    }
+
+    /// Start a latency trace for every utterance when its capture ends
+    pub fn with_latency(mut self, tracker: Arc<LatencyTracker>) -> Self {
+        self.latency = Some(tracker);
+        self
+    }
+
+    /// Only transcribe speech following a wake-word detection while the
+    /// guild listens for the wake word (`listen_modes`, when set)
+    ///
+    /// Falls back to transcribing everything if the models cannot be found.
+    #[cfg(feature = "voice-wakeword")]
+    pub fn with_wakeword(
+        mut self,
+        config: zoey_provider_voice::wakeword::WakeWordConfig,
+        listen_modes: Option<Arc<ListenModes>>,
+    ) -> Self {
+        match WakeWordGate::new(self.guild_id, config, listen_modes) {
+            Ok(gate) => {
+                info!(guild_id = %self.guild_id, "Wake-word gating enabled for voice STT");
+                self.wake_gate = Some(gate);
+            }
+            Err(e) => {
+                warn!(error = %e, "Wake-word gating unavailable, transcribing all speech");
+            }
+        }
+        self
+    }
+
     /// Get or create buffer for user
     fn get_or_create_buffer(&self, user_id: u64) -> parking_lot::RwLockWriteGuard<'_, std::collections::HashMap<u64, UserAudioBuffer>> {
         let mut buffers = self.buffers.write();
@@ -1907,19 +2910,26 @@ impl VoiceReceiver {
         let mut buffers = self.buffers.write();
         let buffer = buffers.entry(user_id).or_insert_with(|| UserAudioBuffer::new(user_id));
         buffer.push_samples(audio);
+        drop(buffers);
+
+        #[cfg(feature = "voice-wakeword")]
+        if let Some(gate) = &self.wake_gate {
+            gate.push_audio(user_id, audio);
+        }
     }
 
     /// Check for completed utterances and trigger transcription
     pub async fn check_and_transcribe(&self) {
-        let users_to_transcribe: Vec<(u64, Vec<i16>)> = {
+        let users_to_transcribe: Vec<(u64, zoey_provider_voice::AudioData, Option<TurnId>)> = {
             let mut buffers = self.buffers.write();
             let mut to_transcribe = Vec::new();
             
             for (user_id, buffer) in buffers.iter_mut() {
                 // If user has stopped speaking and we have enough audio
                 if buffer.has_silence() && buffer.has_enough_audio() {
-                    let mono_16k = buffer.to_mono_16khz();
-                    to_transcribe.push((*user_id, mono_16k));
+                    // Capture ended with the last audio packet, before the silence window
+                    let turn = self.latency.as_ref().map(|t| t.begin_at(buffer.last_audio));
+                    to_transcribe.push((*user_id, buffer.to_audio_data(), turn));
                     buffer.clear();
                 }
             }
@@ -1928,19 +2938,51 @@ impl VoiceReceiver {
         };
 
         // Transcribe each completed utterance
-        for (user_id, audio_samples) in users_to_transcribe {
-            if let Some(text) = self.transcribe_audio(&audio_samples).await {
-                if !text.trim().is_empty() {
+        for (user_id, audio, turn) in users_to_transcribe {
+            #[cfg(feature = "voice-wakeword")]
+            if let Some(gate) = &self.wake_gate {
+                if !gate.should_transcribe(user_id) {
+                    debug!(user_id = %user_id, "Skipping STT: no wake word detected");
+                    self.discard_turn(turn);
+                    continue;
+                }
+            }
+            self.mark_turn(turn, TurnMark::SttStart);
+            let transcript = self.transcribe_audio(&audio).await;
+            self.mark_turn(turn, TurnMark::SttEnd);
+            match transcript {
+                Some(text) if !text.trim().is_empty() => {
                     info!(user_id = %user_id, text = %text, "Transcribed user speech");
-                    let _ = self.transcription_tx.send((user_id, text)).await;
+                    #[cfg(feature = "voice-wakeword")]
+                    if let Some(gate) = &self.wake_gate {
+                        gate.keep_open(user_id);
+                    }
+                    #[cfg(feature = "voice-speaker-id")]
+                    if let Some(gate) = &self.speaker_gate {
+                        gate.record_utterance(user_id, audio);
+                    }
+                    let _ = self.transcription_tx.send((user_id, text, turn)).await;
                 }
+                _ => self.discard_turn(turn),
             }
         }
     }
 
+    fn mark_turn(&self, turn: Option<TurnId>, mark: TurnMark) {
+        if let (Some(tracker), Some(id)) = (&self.latency, turn) {
+            tracker.mark(id, mark);
+        }
+    }
+
+    fn discard_turn(&self, turn: Option<TurnId>) {
+        if let (Some(tracker), Some(id)) = (&self.latency, turn) {
+            tracker.discard(id);
+        }
+    }
+
     /// Transcribe audio samples using configured STT engine
     #[cfg(any(feature = "voice-whisper", feature = "voice-vosk"))]
-    async fn transcribe_audio(&self, samples: &[i16]) -> Option<String> {
+    async fn transcribe_audio(&self, audio: &zoey_provider_voice::AudioData) -> Option<String> {
         use std::time::Instant;
         
         let start = Instant::now();
@@ -1949,7 +2991,7 @@ impl VoiceReceiver {
         #[cfg(feature = "voice-vosk")]
         if self.stt_engine == "vosk" {
             // Use fast Vosk transcription directly on samples
-            return match Self::transcribe_with_vosk(samples).await {
+            return match Self::transcribe_with_vosk(audio).await {
                 Ok(text) => {
                     let elapsed = start.elapsed().as_millis();
                     if !text.trim().is_empty() {
@@ -1967,24 +3009,10 @@ impl VoiceReceiver {
         // Fall back to Whisper
         #[cfg(feature = "voice-whisper")]
         {
-            use zoey_provider_voice::{AudioData, AudioFormat, VoicePlugin, WhisperModel};
-            use bytes::Bytes;
-            
-            let pcm_bytes: Vec<u8> = samples
-                .iter()
-                .flat_map(|&s| s.to_le_bytes())
-                .collect();
-
-            let audio = AudioData {
-                data: Bytes::from(pcm_bytes),
-                format: AudioFormat::Pcm,
-                sample_rate: 16000,
-                duration_ms: Some((samples.len() as u64 * 1000) / 16000),
-                character_count: 0,
-            };
+            use zoey_provider_voice::{VoicePlugin, WhisperModel};
             
             let plugin = VoicePlugin::with_whisper(WhisperModel::Tiny);
-            return match plugin.transcribe(&audio).await {
+            return match plugin.transcribe(audio).await {
                 Ok(result) => {
                     let elapsed = start.elapsed().as_millis();
                     info!(latency_ms = %elapsed, text = %result.text, "Whisper STT complete");
@@ -2006,7 +3034,7 @@ impl VoiceReceiver {
 
     /// Fast Vosk transcription - uses cached model
     #[cfg(feature = "voice-vosk")]
-    async fn transcribe_with_vosk(samples: &[i16]) -> Result<String, String> {
+    async fn transcribe_with_vosk(audio: &zoey_provider_voice::AudioData) -> Result<String, String> {
         use once_cell::sync::Lazy;
         use std::sync::Mutex;
         use vosk::{Model, Recognizer};
@@ -2034,7 +3062,14 @@ impl VoiceReceiver {
             Mutex::new(None)
         });
         
-        let samples = samples.to_vec();
+        // Vosk is driven directly rather than through the voice plugin, so
+        // convert to its 16kHz mono input here
+        let normalized = zoey_provider_voice::audio::normalize(
+            audio,
+            &zoey_provider_voice::AudioSpec::pcm_mono(16000),
+        )
+        .map_err(|e| e.to_string())?;
+        let samples = zoey_provider_voice::audio::pcm16_to_samples(&normalized.data);
         
         // Run blocking transcription in thread pool
         tokio::task::spawn_blocking(move || {
@@ -2061,27 +3096,13 @@ impl VoiceReceiver {
 
     /// Transcribe audio (stub when using unmute only)
     #[cfg(all(feature = "voice-unmute", not(any(feature = "voice-whisper", feature = "voice-vosk"))))]
-    async fn transcribe_audio(&self, samples: &[i16]) -> Option<String> {
-        use zoey_provider_voice::{AudioData, AudioFormat, VoicePlugin};
-        use bytes::Bytes;
-
-        let pcm_bytes: Vec<u8> = samples
-            .iter()
-            .flat_map(|&s| s.to_le_bytes())
-            .collect();
-
-        let audio = AudioData {
-            data: Bytes::from(pcm_bytes),
-            format: AudioFormat::Pcm,
-            sample_rate: 16000,
-            duration_ms: Some((samples.len() as u64 * 1000) / 16000),
-            character_count: 0,
-        };
+    async fn transcribe_audio(&self, audio: &zoey_provider_voice::AudioData) -> Option<String> {
+        use zoey_provider_voice::VoicePlugin;
 
         // Use Unmute for transcription
         let plugin = VoicePlugin::with_unmute("ws://localhost:8000");
         
-        match plugin.transcribe(&audio).await {
+        match plugin.transcribe(audio).await {
             Ok(result) => Some(result.text),
             Err(e) => {
                 warn!(error = %e, "Transcription failed");
@@ -2201,7 +3222,7 @@ pub struct MoshiVoiceReceiver {
     /// Per-user state for Moshi streaming
     pub user_states: Arc<parking_lot::RwLock<std::collections::HashMap<u64, MoshiUserState>>>,
     /// Channel to send transcribed text
-    pub transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+    pub transcription_tx: TranscriptionSender,
     /// Moshi endpoint
     pub endpoint: String,
 }
@@ -2225,7 +3246,7 @@ impl MoshiVoiceReceiver {
     pub fn new(
         guild_id: u64,
         endpoint: &str,
-        transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+        transcription_tx: TranscriptionSender,
     ) -> Self {
         Self {
             guild_id,
@@ -2299,7 +3320,7 @@ impl MoshiVoiceReceiver {
         guild_id: u64,
         endpoint: String,
         mut audio_rx: tokio::sync::mpsc::Receiver<Vec<f32>>,
-        transcription_tx: tokio::sync::mpsc::Sender<(u64, String)>,
+        transcription_tx: TranscriptionSender,
     ) {
         use zoey_provider_voice::{MoshiConfig, MoshiStreamingClient, MoshiEvent, MoshiControl};
         
@@ -2378,7 +3399,7 @@ impl MoshiVoiceReceiver {
                                 );
                                 if is_final {
                                     // Send final transcription to callback
-                                    let _ = transcription_tx.send((user_id, text)).await;
+                                    let _ = transcription_tx.send((user_id, text, None)).await;
                                 }
                             }
                         }
@@ -2427,22 +3448,11 @@ impl MoshiVoiceReceiver {
     /// Discord: 48kHz stereo i16 PCM
     /// Moshi: 24kHz mono f32 PCM (normalized to -1.0 to 1.0)
     fn convert_discord_to_moshi(stereo_48k: &[i16]) -> Vec<f32> {
-        // First: stereo to mono by averaging channels
-        let mono_48k: Vec<i16> = stereo_48k
-            .chunks(2)
-            .map(|chunk| {
-                if chunk.len() == 2 {
-                    ((chunk[0] as i32 + chunk[1] as i32) / 2) as i16
-                } else {
-                    chunk[0]
-                }
-            })
-            .collect();
-        
-        // Second: downsample 48kHz -> 24kHz (factor of 2)
-        let mono_24k: Vec<i16> = mono_48k.iter().step_by(2).copied().collect();
+        use zoey_provider_voice::audio::{downmix_to_mono, resample_linear};
+        let mono_48k = downmix_to_mono(stereo_48k, DISCORD_CHANNELS);
+        let mono_24k = resample_linear(&mono_48k, 1, DISCORD_SAMPLE_RATE, 24000);
         
-        // Third: convert i16 to f32 normalized
+        // Convert i16 to f32 normalized
         mono_24k
             .iter()
             .map(|&s| s as f32 / 32768.0)
@@ -2505,6 +3515,40 @@ impl VoiceManager {
     }
 }
 
+/// A TTS stream's `f32` samples as a source for songbird's `RawAdapter`
+///
+/// Reads block until synthesis catches up; the stream can't seek.
+#[cfg(feature = "voice")]
+struct TtsStreamSource(PcmReader);
+
+#[cfg(feature = "voice")]
+impl std::io::Read for TtsStreamSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::io::Read::read(&mut self.0, buf)
+    }
+}
+
+#[cfg(feature = "voice")]
+impl std::io::Seek for TtsStreamSource {
+    fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "TTS streams can't seek",
+        ))
+    }
+}
+
+#[cfg(feature = "voice")]
+impl symphonia::core::io::MediaSource for TtsStreamSource {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
 /// Wrap raw PCM audio data in a WAV header for symphonia to decode
 /// 
 /// Creates a minimal WAV file header for the given PCM parameters
@@ -2574,6 +3618,16 @@ mod tests {
         assert!(!config.is_tts_request("Hello, how are you?"));
     }
 
+    #[cfg(any(feature = "voice-whisper", feature = "voice-unmute", feature = "voice-vosk"))]
+    #[test]
+    fn test_discord_pcm_audio_keeps_native_spec() {
+        // One second of 48kHz stereo
+        let audio = discord_pcm_audio(&[0; 96000]);
+        assert_eq!(audio.sample_rate, 48000);
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.duration_ms, Some(1000));
+    }
+
     #[test]
     fn test_voice_config_parsing() {
         let settings = serde_json::json!({
@@ -2584,9 +3638,14 @@ mod tests {
                 "voice_id": "21m00Tcm4TlvDq8ikWAM",
                 "voice_name": "Rachel",
                 "speed": "1.0",
+                "latency_telemetry_endpoint": "http://127.0.0.1:4318/voice-latency",
                 "discord": {
                     "auto_join_voice": "true",
-                    "idle_timeout_seconds": "600"
+                    "idle_timeout_seconds": "600",
+                    "persistent_conversation": false,
+                    "conversation_timeout_secs": "90",
+                    "thinking_filler_ms": "1200",
+                    "thinking_fillers": { "filler": ["One sec...", "Let me check."] }
                 },
                 "triggers": {
                     "trigger": ["hello voice", "start talking"]
@@ -2599,8 +3658,19 @@ mod tests {
         assert_eq!(config.engine, "elevenlabs");
         assert_eq!(config.model, "eleven_turbo_v2_5");
         assert_eq!(config.voice_id, "21m00Tcm4TlvDq8ikWAM");
+        assert_eq!(
+            config.latency_telemetry_endpoint.as_deref(),
+            Some("http://127.0.0.1:4318/voice-latency")
+        );
         assert!(config.discord.auto_join_voice);
         assert_eq!(config.discord.idle_timeout_seconds, 600);
+        assert!(!config.discord.persistent_conversation);
+        assert_eq!(config.discord.conversation_timeout_secs, 90);
+        assert_eq!(config.discord.thinking_filler_ms, 1200);
+        assert_eq!(config.discord.thinking_filler_phrases, ["One sec...", "Let me check."]);
+        assert_eq!(config.discord.thinking_filler_clip, None);
+        // Fillers are opt-in
+        assert_eq!(DiscordVoiceSettings::default().thinking_filler_ms, 0);
     }
 
     #[test]
@@ -2609,4 +3679,40 @@ mod tests {
         // Fresh session should not be idle
         assert!(!session.is_idle(300));
     }
+
+    #[test]
+    fn test_session_leaves_when_idle_or_alone() {
+        let policy = AutoLeave::default();
+        let mut session = VoiceSession::new(123, 456);
+        let start = session.last_activity;
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        // Members only count while in the session's channel
+        session.set_members_at([1, 2], start);
+        session.observe_member_at(1, Some(789), at(10));
+        assert_eq!(session.leave_reason(&policy, at(100)), None);
+        session.observe_member_at(2, None, at(100));
+        assert_eq!(session.alone_since, Some(at(100)));
+        assert_eq!(session.leave_reason(&policy, at(120)), None);
+        // Someone coming back resets the countdown
+        session.observe_member_at(1, Some(456), at(125));
+        assert_eq!(session.leave_reason(&policy, at(135)), None);
+        session.observe_member_at(1, None, at(140));
+        assert_eq!(session.leave_reason(&policy, at(170)), Some(LeaveReason::Alone));
+
+        // Activity postpones the idle leave
+        session.set_members_at([1], at(170));
+        session.last_activity = at(300);
+        assert_eq!(session.leave_reason(&policy, at(899)), None);
+        assert_eq!(session.leave_reason(&policy, at(900)), Some(LeaveReason::Idle));
+    }
+
+    #[test]
+    fn test_auto_leave_settings_parsing() {
+        let config = VoiceConfig::from_character_settings(&serde_json::json!({
+            "voice": { "discord": { "alone_timeout_seconds": "45" } }
+        }));
+        assert_eq!(config.discord.alone_timeout_seconds, 45);
+        assert_eq!(config.discord.idle_timeout_seconds, 600);
+    }
 }
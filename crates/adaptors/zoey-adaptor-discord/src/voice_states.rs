@@ -0,0 +1,121 @@
+//! Voice-channel membership tracker
+//!
+//! Maps (guild, user) to the voice channel the user is in. It is fed by
+//! `guild_create` and `voice_state_update` and is more reliable than
+//! serenity's cache, which is kept small (see [`crate::cache`]). Entries live
+//! in the adapter's [`StateStore`] under one namespace per guild, so every
+//! process serving the bot sees the same membership.
+
+use std::sync::Arc;
+use zoey_core::{Result, StateStore};
+
+/// Namespace prefix; the guild ID is appended
+const NAMESPACE_PREFIX: &str = "discord:voice_states";
+
+/// (guild, user) -> voice channel, backed by a [`StateStore`]
+#[derive(Clone)]
+pub struct VoiceStates {
+    store: Arc<dyn StateStore>,
+}
+
+impl VoiceStates {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        Self { store }
+    }
+
+    fn namespace(guild_id: u64) -> String {
+        format!("{}:{}", NAMESPACE_PREFIX, guild_id)
+    }
+
+    /// Voice channel the user is in, if tracked
+    pub async fn channel_of(&self, guild_id: u64, user_id: u64) -> Result<Option<u64>> {
+        let value = self
+            .store
+            .get(&Self::namespace(guild_id), &user_id.to_string())
+            .await?;
+        Ok(value.and_then(|v| v.as_u64()))
+    }
+
+    /// Record that the user joined or moved to `channel_id`
+    pub async fn set(&self, guild_id: u64, user_id: u64, channel_id: u64) -> Result<()> {
+        self.store
+            .set(
+                &Self::namespace(guild_id),
+                &user_id.to_string(),
+                channel_id.into(),
+                None,
+            )
+            .await
+    }
+
+    /// Record that the user left voice
+    pub async fn remove(&self, guild_id: u64, user_id: u64) -> Result<()> {
+        self.store
+            .delete(&Self::namespace(guild_id), &user_id.to_string())
+            .await?;
+        Ok(())
+    }
+
+    /// Users tracked in `channel_id`
+    pub async fn members_of(&self, guild_id: u64, channel_id: u64) -> Result<Vec<u64>> {
+        let members = self
+            .store
+            .list(&Self::namespace(guild_id))
+            .await?
+            .into_iter()
+            .filter(|(_, channel)| channel.as_u64() == Some(channel_id))
+            .filter_map(|(user, _)| user.parse().ok())
+            .collect();
+        Ok(members)
+    }
+
+    /// Replace everything tracked for a guild with `(user_id, channel_id)` pairs
+    ///
+    /// Guild data from the gateway is authoritative, so entries left over from
+    /// an earlier session are dropped. Returns the number of tracked users.
+    pub async fn replace_guild(
+        &self,
+        guild_id: u64,
+        states: impl IntoIterator<Item = (u64, u64)>,
+    ) -> Result<usize> {
+        let namespace = Self::namespace(guild_id);
+        let states: Vec<(u64, u64)> = states.into_iter().collect();
+        for (key, _) in self.store.list(&namespace).await? {
+            let current = key.parse::<u64>().ok();
+            if !states.iter().any(|&(uid, _)| Some(uid) == current) {
+                self.store.delete(&namespace, &key).await?;
+            }
+        }
+        for &(user_id, channel_id) in &states {
+            self.set(guild_id, user_id, channel_id).await?;
+        }
+        Ok(states.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::MemoryStateStore;
+
+    #[tokio::test]
+    async fn test_replace_guild_drops_stale_entries() {
+        let states = VoiceStates::new(Arc::new(MemoryStateStore::new()));
+        states.set(1, 10, 100).await.unwrap();
+        states.set(1, 11, 100).await.unwrap();
+        states.set(2, 10, 200).await.unwrap();
+
+        assert_eq!(states.replace_guild(1, [(11, 101)]).await.unwrap(), 1);
+        assert_eq!(states.channel_of(1, 10).await.unwrap(), None);
+        assert_eq!(states.channel_of(1, 11).await.unwrap(), Some(101));
+        assert_eq!(states.channel_of(2, 10).await.unwrap(), Some(200));
+
+        states.set(1, 12, 101).await.unwrap();
+        let mut members = states.members_of(1, 101).await.unwrap();
+        members.sort();
+        assert_eq!(members, vec![11, 12]);
+
+        states.remove(2, 10).await.unwrap();
+        assert_eq!(states.channel_of(2, 10).await.unwrap(), None);
+    }
+}
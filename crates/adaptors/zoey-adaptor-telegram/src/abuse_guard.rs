@@ -0,0 +1,561 @@
+//! Temporary mutes for users flooding the bot
+//!
+//! Every message meant for the bot feeds per-user rolling statistics over the
+//! last `window`: message count, average length and how many messages repeat
+//! earlier content (compared by a hash of the whitespace-normalized text).
+//! A user crossing any threshold is muted locally: their messages are ignored
+//! until the mute ends, and they are told why exactly once.
+//!
+//! - Each offense within `offense_memory` doubles the next mute, starting at
+//!   `base_mute` and capped at `max_mute`.
+//! - Length and repetition only count once a user has sent `min_messages`
+//!   messages in the window, so one long paste or a repeated "thanks" is fine.
+//! - Admins are never muted and lift mutes with `/unmute <user_id>`.
+//! - At most `max_tracked_users` users are tracked; idle ones are dropped first.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Default period the rolling statistics cover
+pub const DEFAULT_ABUSE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Default length of a first mute
+pub const DEFAULT_BASE_MUTE: Duration = Duration::from_secs(10 * 60);
+
+/// Default longest mute
+pub const DEFAULT_MAX_MUTE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default time an offense counts towards longer mutes
+pub const DEFAULT_OFFENSE_MEMORY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Anti-spam thresholds
+#[derive(Debug, Clone)]
+pub struct AbuseGuardConfig {
+    /// Period the rolling statistics cover
+    pub window: Duration,
+    /// Most messages allowed per `window`
+    pub max_messages: usize,
+    /// Highest average message length (characters) allowed over `window`
+    pub max_average_chars: usize,
+    /// Highest share (0.0-1.0) of messages repeating earlier ones in `window`
+    pub max_repeat_ratio: f32,
+    /// Messages needed in `window` before length and repetition are judged
+    pub min_messages: usize,
+    /// First mute; each further offense within `offense_memory` doubles it
+    pub base_mute: Duration,
+    /// Longest mute
+    pub max_mute: Duration,
+    /// How long an offense counts towards longer mutes
+    pub offense_memory: Duration,
+    /// Most users tracked at once
+    pub max_tracked_users: usize,
+}
+
+impl Default for AbuseGuardConfig {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_ABUSE_WINDOW,
+            max_messages: 20,
+            max_average_chars: 1500,
+            max_repeat_ratio: 0.5,
+            min_messages: 4,
+            base_mute: DEFAULT_BASE_MUTE,
+            max_mute: DEFAULT_MAX_MUTE,
+            offense_memory: DEFAULT_OFFENSE_MEMORY,
+            max_tracked_users: 10_000,
+        }
+    }
+}
+
+impl AbuseGuardConfig {
+    /// Mute for the `offense`th offense (1-based) within `offense_memory`
+    pub fn mute_for(&self, offense: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(offense.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.base_mute.saturating_mul(factor).min(self.max_mute)
+    }
+}
+
+/// Threshold a user crossed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbuseSignal {
+    /// Too many messages in the window
+    MessageRate { messages: usize },
+    /// Messages too long on average
+    MessageLength { average_chars: usize },
+    /// Too many messages repeating earlier ones
+    RepeatedContent { ratio: f32 },
+}
+
+impl fmt::Display for AbuseSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MessageRate { messages } => write!(f, "{} messages in a short time", messages),
+            Self::MessageLength { average_chars } => {
+                write!(
+                    f,
+                    "very long messages (about {} characters each)",
+                    average_chars
+                )
+            }
+            Self::RepeatedContent { ratio } => {
+                write!(f, "repeated messages ({:.0}% duplicates)", ratio * 100.0)
+            }
+        }
+    }
+}
+
+/// A mute that just started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mute {
+    pub user_id: u64,
+    pub signal: AbuseSignal,
+    pub duration: Duration,
+    /// Offenses within `offense_memory`, this one included
+    pub offense: u32,
+}
+
+impl Mute {
+    /// Explanation sent to the muted user
+    pub fn user_notice(&self) -> String {
+        format!(
+            "You've sent {}, so I'll ignore your messages for {}.",
+            self.signal,
+            format_duration(self.duration)
+        )
+    }
+
+    /// Notice sent to admins
+    pub fn admin_notice(&self, chat_id: i64) -> String {
+        format!(
+            "Muted user {} in chat {} for {} (offense {} in 24h): {}. Use /unmute {} to lift it.",
+            self.user_id,
+            chat_id,
+            format_duration(self.duration),
+            self.offense,
+            self.signal,
+            self.user_id
+        )
+    }
+}
+
+/// What to do with a message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Handle it
+    Allow,
+    /// The sender was just muted; tell them and the admins, then drop it
+    Muted(Mute),
+    /// The sender is muted; drop it silently
+    Ignore,
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    at: Instant,
+    chars: usize,
+    hash: u64,
+}
+
+#[derive(Default)]
+struct UserStats {
+    recent: VecDeque<Sample>,
+    muted_until: Option<Instant>,
+    offenses: VecDeque<Instant>,
+    last_seen: Option<Instant>,
+}
+
+impl UserStats {
+    fn is_muted(&self, now: Instant) -> bool {
+        self.muted_until.is_some_and(|until| now < until)
+    }
+
+    fn is_idle(&self, now: Instant, config: &AbuseGuardConfig) -> bool {
+        !self.is_muted(now)
+            && self
+                .recent
+                .back()
+                .is_none_or(|s| now.saturating_duration_since(s.at) >= config.window)
+            && self
+                .offenses
+                .back()
+                .is_none_or(|&at| now.saturating_duration_since(at) >= config.offense_memory)
+    }
+
+    /// First threshold the current window crosses
+    fn signal(&self, config: &AbuseGuardConfig) -> Option<AbuseSignal> {
+        let messages = self.recent.len();
+        if messages > config.max_messages {
+            return Some(AbuseSignal::MessageRate { messages });
+        }
+        if messages < config.min_messages.max(1) {
+            return None;
+        }
+        let average_chars = self.recent.iter().map(|s| s.chars).sum::<usize>() / messages;
+        if average_chars > config.max_average_chars {
+            return Some(AbuseSignal::MessageLength { average_chars });
+        }
+        let mut seen = HashSet::new();
+        let repeats = self.recent.iter().filter(|s| !seen.insert(s.hash)).count();
+        let ratio = repeats as f32 / messages as f32;
+        if ratio > config.max_repeat_ratio {
+            return Some(AbuseSignal::RepeatedContent { ratio });
+        }
+        None
+    }
+}
+
+/// Hash of `text` with case kept and whitespace runs collapsed
+pub fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for word in text.split_whitespace() {
+        word.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Per-user message statistics and mutes
+pub struct AbuseGuard {
+    config: AbuseGuardConfig,
+    admins: HashSet<u64>,
+    users: Mutex<HashMap<u64, UserStats>>,
+}
+
+impl AbuseGuard {
+    pub fn new(config: AbuseGuardConfig, admins: HashSet<u64>) -> Self {
+        Self {
+            config,
+            admins,
+            users: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a message from `user_id` and decide whether to handle it
+    pub fn check(&self, user_id: u64, text: &str, now: Instant) -> Verdict {
+        if self.admins.contains(&user_id) {
+            return Verdict::Allow;
+        }
+        let config = &self.config;
+        let mut users = self.users.lock().unwrap();
+        if !users.contains_key(&user_id) && users.len() >= config.max_tracked_users {
+            Self::make_room(&mut users, now, config);
+        }
+        let stats = users.entry(user_id).or_default();
+        stats.last_seen = Some(now);
+        if stats.is_muted(now) {
+            return Verdict::Ignore;
+        }
+
+        while stats
+            .recent
+            .front()
+            .is_some_and(|s| now.saturating_duration_since(s.at) >= config.window)
+        {
+            stats.recent.pop_front();
+        }
+        stats.recent.push_back(Sample {
+            at: now,
+            chars: text.chars().count(),
+            hash: content_hash(text),
+        });
+        let Some(signal) = stats.signal(config) else {
+            return Verdict::Allow;
+        };
+
+        while stats
+            .offenses
+            .front()
+            .is_some_and(|&at| now.saturating_duration_since(at) >= config.offense_memory)
+        {
+            stats.offenses.pop_front();
+        }
+        stats.offenses.push_back(now);
+        let offense = stats.offenses.len() as u32;
+        let duration = config.mute_for(offense);
+        stats.muted_until = Some(now + duration);
+        stats.recent.clear();
+        Verdict::Muted(Mute {
+            user_id,
+            signal,
+            duration,
+            offense,
+        })
+    }
+
+    /// Whether `user_id` is muted at `now`
+    pub fn is_muted(&self, user_id: u64, now: Instant) -> bool {
+        self.users
+            .lock()
+            .unwrap()
+            .get(&user_id)
+            .is_some_and(|stats| stats.is_muted(now))
+    }
+
+    /// Lift a mute; past offenses still count towards the next one
+    pub fn unmute(&self, user_id: u64) -> bool {
+        let mut users = self.users.lock().unwrap();
+        let Some(stats) = users.get_mut(&user_id) else {
+            return false;
+        };
+        stats.recent.clear();
+        stats.muted_until.take().is_some()
+    }
+
+    /// Reply to `/unmute <user_id>`
+    pub fn handle_command(&self, caller_id: u64, text: &str, now: Instant) -> String {
+        if !self.admins.contains(&caller_id) {
+            return "Only admins can lift mutes.".to_string();
+        }
+        let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+        let [user] = args.as_slice() else {
+            return "Usage: /unmute <user_id>".to_string();
+        };
+        let Ok(user_id) = user.trim_start_matches('@').parse::<u64>() else {
+            return format!("Invalid user id: {}", user);
+        };
+        let was_muted = self.is_muted(user_id, now);
+        self.unmute(user_id);
+        if was_muted {
+            info!(user_id = %user_id, admin = %caller_id, "Telegram abuse mute lifted");
+            format!("User {} is no longer muted.", user_id)
+        } else {
+            format!("User {} is not muted.", user_id)
+        }
+    }
+
+    /// Drop idle users, or the longest-unseen one when none are idle
+    fn make_room(users: &mut HashMap<u64, UserStats>, now: Instant, config: &AbuseGuardConfig) {
+        users.retain(|_, stats| !stats.is_idle(now, config));
+        if users.len() < config.max_tracked_users {
+            return;
+        }
+        let oldest = users
+            .iter()
+            .filter(|(_, stats)| !stats.is_muted(now))
+            .min_by_key(|(_, stats)| stats.last_seen)
+            .or_else(|| users.iter().min_by_key(|(_, stats)| stats.last_seen))
+            .map(|(&id, _)| id);
+        if let Some(id) = oldest {
+            users.remove(&id);
+        }
+    }
+}
+
+/// "10 minutes", "2 hours", "1 day"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (n, unit) = if secs >= 86_400 && secs.is_multiple_of(86_400) {
+        (secs / 86_400, "day")
+    } else if secs >= 3600 && secs.is_multiple_of(3600) {
+        (secs / 3600, "hour")
+    } else if secs >= 60 {
+        (secs.div_ceil(60), "minute")
+    } else {
+        (secs.max(1), "second")
+    };
+    format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN: u64 = 1;
+    const USER: u64 = 42;
+
+    fn guard() -> AbuseGuard {
+        AbuseGuard::new(AbuseGuardConfig::default(), HashSet::from([ADMIN]))
+    }
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    /// Sends `texts` one every `gap`, returning the first mute
+    fn send(guard: &AbuseGuard, start: Instant, gap: Duration, texts: &[&str]) -> Option<Mute> {
+        texts.iter().enumerate().find_map(|(i, text)| {
+            match guard.check(USER, text, start + gap * i as u32) {
+                Verdict::Muted(mute) => Some(mute),
+                _ => None,
+            }
+        })
+    }
+
+    #[test]
+    fn test_each_signal_triggers() {
+        let start = Instant::now();
+
+        // Rate: 21 distinct short messages within a minute
+        let g = guard();
+        let texts: Vec<String> = (0..21).map(|i| format!("question {}", i)).collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let mute = send(&g, start, secs(2), &texts).unwrap();
+        assert_eq!(mute.signal, AbuseSignal::MessageRate { messages: 21 });
+        assert_eq!(mute.duration, DEFAULT_BASE_MUTE);
+
+        // Length: four prompt-sized pastes in a minute
+        let g = guard();
+        let long = "word ".repeat(400);
+        let mute = send(
+            &g,
+            start,
+            secs(5),
+            &[&long, &long[..1995], &long[..1990], &long],
+        )
+        .unwrap();
+        assert!(matches!(
+            mute.signal,
+            AbuseSignal::MessageLength { average_chars } if average_chars > 1500
+        ));
+
+        // Repetition: the same text with different spacing is the same content
+        let g = guard();
+        let mute = send(
+            &g,
+            start,
+            secs(5),
+            &[
+                "buy cheap tokens now",
+                "buy  cheap tokens\nnow",
+                " buy cheap tokens now ",
+                "buy cheap\ttokens now",
+            ],
+        )
+        .unwrap();
+        assert_eq!(mute.signal, AbuseSignal::RepeatedContent { ratio: 0.75 });
+        assert!(mute.user_notice().contains("10 minutes"));
+
+        // Muted: later messages are ignored, then allowed once it ends
+        assert_eq!(g.check(USER, "hello?", start + secs(60)), Verdict::Ignore);
+        assert!(g.is_muted(USER, start + secs(60)));
+        assert_eq!(
+            g.check(USER, "hello?", start + secs(15 + 600)),
+            Verdict::Allow
+        );
+    }
+
+    #[test]
+    fn test_normal_conversation_never_triggers() {
+        let g = guard();
+        let start = Instant::now();
+        let chat = [
+            "hi!",
+            "can you help me plan a trip to Lisbon?",
+            "thanks",
+            "what about food?",
+            "thanks",
+            "ok",
+            "and the weather in May?",
+            "ok",
+            "great, thanks",
+        ];
+        for round in 0..20u32 {
+            let base = start + secs(90) * round;
+            assert_eq!(send(&g, base, secs(8), &chat), None, "round {}", round);
+        }
+
+        // One long paste among short messages
+        let paste = "fn main() {}\n".repeat(300);
+        assert_eq!(
+            send(
+                &g,
+                start + secs(3600),
+                secs(5),
+                &[&paste, "what does this do?", "thanks", "ok"]
+            ),
+            None
+        );
+
+        // Quick bursts just under the rate limit
+        let burst: Vec<String> = (0..20).map(|i| format!("line {}", i)).collect();
+        let burst: Vec<&str> = burst.iter().map(String::as_str).collect();
+        assert_eq!(send(&g, start + secs(7200), secs(1), &burst), None);
+
+        // Admins are exempt
+        for i in 0..50 {
+            assert_eq!(g.check(ADMIN, "spam", start + secs(i)), Verdict::Allow);
+        }
+    }
+
+    #[test]
+    fn test_repeat_offenses_mute_exponentially_longer() {
+        let config = AbuseGuardConfig::default();
+        assert_eq!(config.mute_for(1), secs(600));
+        assert_eq!(config.mute_for(2), secs(1200));
+        assert_eq!(config.mute_for(3), secs(2400));
+        assert_eq!(config.mute_for(9), DEFAULT_MAX_MUTE);
+        assert_eq!(config.mute_for(100), DEFAULT_MAX_MUTE);
+
+        let g = guard();
+        let start = Instant::now();
+        let spam = ["spam spam", "spam spam", "spam spam", "spam spam"];
+        let first = send(&g, start, secs(1), &spam).unwrap();
+        assert_eq!((first.offense, first.duration), (1, secs(600)));
+
+        let t = start + secs(3600);
+        let second = send(&g, t, secs(1), &spam).unwrap();
+        assert_eq!((second.offense, second.duration), (2, secs(1200)));
+
+        // Offenses older than a day are forgotten
+        let t = start + secs(3600 + 25 * 3600);
+        let third = send(&g, t, secs(1), &spam).unwrap();
+        assert_eq!((third.offense, third.duration), (1, secs(600)));
+    }
+
+    #[test]
+    fn test_admin_unmute() {
+        let g = guard();
+        let start = Instant::now();
+        let spam = ["spam spam", "spam spam", "spam spam", "spam spam"];
+        let mute = send(&g, start, secs(1), &spam).unwrap();
+        assert!(mute.admin_notice(-100).contains("/unmute 42"));
+        let now = start + secs(10);
+
+        assert_eq!(
+            g.handle_command(USER, "/unmute 42", now),
+            "Only admins can lift mutes."
+        );
+        assert!(g.is_muted(USER, now));
+        assert_eq!(
+            g.handle_command(ADMIN, "/unmute", now),
+            "Usage: /unmute <user_id>"
+        );
+        assert_eq!(
+            g.handle_command(ADMIN, "/unmute bob", now),
+            "Invalid user id: bob"
+        );
+        assert_eq!(
+            g.handle_command(ADMIN, "/unmute 42", now),
+            "User 42 is no longer muted."
+        );
+        assert_eq!(g.check(USER, "sorry", now), Verdict::Allow);
+        assert_eq!(
+            g.handle_command(ADMIN, "/unmute 42", now),
+            "User 42 is not muted."
+        );
+
+        // The lifted offense still counts
+        let again = send(&g, now + secs(60), secs(1), &spam).unwrap();
+        assert_eq!(again.offense, 2);
+    }
+
+    #[test]
+    fn test_tracked_users_are_bounded() {
+        let config = AbuseGuardConfig {
+            max_tracked_users: 3,
+            ..Default::default()
+        };
+        let g = AbuseGuard::new(config, HashSet::new());
+        let start = Instant::now();
+        for user in 0..10 {
+            g.check(user, "hello", start + secs(user));
+        }
+        assert!(g.users.lock().unwrap().len() <= 3);
+    }
+}
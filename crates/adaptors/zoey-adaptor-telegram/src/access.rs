@@ -0,0 +1,628 @@
+//! Access requests from users outside `allowed_users`
+//!
+//! With `allowed_users` set, what happens to a message from anyone else
+//! depends on [`UnknownUserPolicy`]:
+//!
+//! - `Reject` drops it without a reply, as before.
+//! - `Queue` records an [`AccessRequest`] with the user's name and first
+//!   message, tells them once that their request was sent, and posts it to
+//!   the admin chat with Approve/Deny buttons. Approving lets the user chat
+//!   right away; denying sends them the rejection message.
+//! - `Notify` sends the rejection message once and tells the admin chat who
+//!   tried, without buttons.
+//!
+//! Either way a user is only reported once: later messages from a pending,
+//! denied or reported user are dropped silently, and unknown users go through
+//! the rate limiter like everyone else. Requests and decisions are kept in an
+//! [`AccessStore`] ([`StateAccessStore`] by default) and reloaded on start,
+//! so approvals survive a restart.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use teloxide::prelude::*;
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup};
+use tracing::{info, warn};
+use zoey_core::{Result, StateStore, ZoeyError};
+
+/// State store namespace holding access requests, keyed by user ID
+const ACCESS_NAMESPACE: &str = "telegram:access";
+
+/// Prefix of callback data produced by the Approve/Deny buttons
+const CALLBACK_PREFIX: &str = "acc:";
+
+/// Longest first message quoted to the admins
+const MAX_QUOTED_CHARS: usize = 500;
+
+/// What to do with messages from users outside `allowed_users`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownUserPolicy {
+    /// Drop them silently
+    #[default]
+    Reject,
+    /// Queue an access request for the admins to approve or deny
+    Queue,
+    /// Reply with the rejection message and tell the admins who tried
+    Notify,
+}
+
+impl UnknownUserPolicy {
+    /// Parse `reject`, `queue` or `notify`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "reject" => Some(UnknownUserPolicy::Reject),
+            "queue" => Some(UnknownUserPolicy::Queue),
+            "notify" => Some(UnknownUserPolicy::Notify),
+            _ => None,
+        }
+    }
+}
+
+/// Where access requests go and what users are told
+#[derive(Debug, Clone)]
+pub struct AccessRequestConfig {
+    /// Chat receiving requests; when `None` each of `admin_users` gets them directly
+    pub admin_chat: Option<i64>,
+    /// Sent once to a user whose request was queued
+    pub pending_reply: String,
+    /// Sent to a denied user, and under `Notify` to every unknown user
+    pub rejection_message: String,
+    /// Sent to a user once approved
+    pub approval_message: String,
+}
+
+impl Default for AccessRequestConfig {
+    fn default() -> Self {
+        Self {
+            admin_chat: None,
+            pending_reply: "Your access request has been sent. You'll get a message here once an admin has looked at it.".to_string(),
+            rejection_message: "Sorry, this bot is private and you don't have access.".to_string(),
+            approval_message: "Your access request was approved. Go ahead!".to_string(),
+        }
+    }
+}
+
+/// Where an access request stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessStatus {
+    /// Waiting for an admin
+    Pending,
+    Approved,
+    Denied,
+    /// Reported to the admins under `Notify`; nothing to decide
+    Reported,
+}
+
+/// A user outside `allowed_users` who messaged the bot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessRequest {
+    pub user_id: u64,
+    pub name: String,
+    /// Chat the user wrote from, where they are told the outcome
+    pub chat_id: i64,
+    pub first_message: String,
+    /// Unix timestamp (seconds) of the first message
+    pub requested_at: i64,
+    pub status: AccessStatus,
+}
+
+/// Persistence for access requests and decisions
+#[async_trait]
+pub trait AccessStore: Send + Sync {
+    /// Save a request, replacing the one for the same user
+    async fn save(&self, request: &AccessRequest) -> Result<()>;
+
+    /// Every saved request
+    async fn load(&self) -> Result<Vec<AccessRequest>>;
+}
+
+/// In-memory store (decisions are lost on restart)
+#[derive(Default)]
+pub struct MemoryAccessStore {
+    requests: RwLock<HashMap<u64, AccessRequest>>,
+}
+
+#[async_trait]
+impl AccessStore for MemoryAccessStore {
+    async fn save(&self, request: &AccessRequest) -> Result<()> {
+        self.requests
+            .write()
+            .unwrap()
+            .insert(request.user_id, request.clone());
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Vec<AccessRequest>> {
+        Ok(self.requests.read().unwrap().values().cloned().collect())
+    }
+}
+
+/// Store keeping access requests in a [`StateStore`] under `telegram:access`
+pub struct StateAccessStore {
+    store: Arc<dyn StateStore>,
+}
+
+impl StateAccessStore {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AccessStore for StateAccessStore {
+    async fn save(&self, request: &AccessRequest) -> Result<()> {
+        let value = serde_json::to_value(request)
+            .map_err(|e| ZoeyError::other(format!("access request not serializable: {}", e)))?;
+        self.store
+            .set(ACCESS_NAMESPACE, &request.user_id.to_string(), value, None)
+            .await
+    }
+
+    async fn load(&self) -> Result<Vec<AccessRequest>> {
+        let entries = self.store.list(ACCESS_NAMESPACE).await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(key, value)| match serde_json::from_value(value) {
+                Ok(request) => Some(request),
+                Err(e) => {
+                    warn!(key = %key, error = %e, "Skipping unreadable access request");
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// An admin's answer to a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessDecision {
+    Approve,
+    Deny,
+}
+
+impl AccessDecision {
+    fn as_str(self) -> &'static str {
+        match self {
+            AccessDecision::Approve => "approve",
+            AccessDecision::Deny => "deny",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "approve" => Some(AccessDecision::Approve),
+            "deny" => Some(AccessDecision::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Callback data for an Approve/Deny button
+pub fn callback_data(decision: AccessDecision, user_id: u64) -> String {
+    format!("{}{}:{}", CALLBACK_PREFIX, decision.as_str(), user_id)
+}
+
+/// The decision and user an Approve/Deny button is for, or `None` for other callback data
+pub fn parse_callback(data: &str) -> Option<(AccessDecision, u64)> {
+    let (decision, user_id) = data.strip_prefix(CALLBACK_PREFIX)?.split_once(':')?;
+    Some((AccessDecision::parse(decision)?, user_id.parse().ok()?))
+}
+
+/// Buttons posted under a queued request
+pub fn keyboard(user_id: u64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            "✅ Approve",
+            callback_data(AccessDecision::Approve, user_id),
+        ),
+        InlineKeyboardButton::callback("🚫 Deny", callback_data(AccessDecision::Deny, user_id)),
+    ]])
+}
+
+/// Message telling the admins about a request
+pub fn admin_notice(request: &AccessRequest) -> String {
+    let quoted: String = request
+        .first_message
+        .chars()
+        .take(MAX_QUOTED_CHARS)
+        .collect();
+    let ellipsis = if request.first_message.chars().count() > MAX_QUOTED_CHARS {
+        "…"
+    } else {
+        ""
+    };
+    let heading = match request.status {
+        AccessStatus::Reported => "Unknown user tried the bot",
+        _ => "Access request",
+    };
+    format!(
+        "{} from {} (ID {}):\n\n{}{}",
+        heading, request.name, request.user_id, quoted, ellipsis
+    )
+}
+
+/// What to do with a message from a user who isn't allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnknownUser {
+    /// Drop it without a reply
+    Ignore,
+    /// Reply with `reply` and report `request` to the admins
+    Report {
+        reply: String,
+        request: AccessRequest,
+    },
+}
+
+/// Result of pressing an Approve/Deny button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessTap {
+    /// Not an access button
+    NotAccess,
+    /// The caller is not an admin
+    Unauthorized,
+    /// No request from that user
+    Unknown,
+    /// Another admin already decided
+    AlreadyDecided(AccessRequest),
+    /// The request now carries the decision
+    Decided(AccessRequest),
+}
+
+/// Allow list with runtime approvals, and the requests waiting for them
+pub struct AccessRequests {
+    policy: UnknownUserPolicy,
+    config: AccessRequestConfig,
+    /// Configured `allowed_users`; everyone is allowed when `None`
+    allowed: Option<HashSet<u64>>,
+    admins: HashSet<u64>,
+    store: Arc<dyn AccessStore>,
+    /// Requests by user ID, mirrored to the store
+    requests: RwLock<HashMap<u64, AccessRequest>>,
+}
+
+impl AccessRequests {
+    pub fn new(
+        policy: UnknownUserPolicy,
+        config: AccessRequestConfig,
+        allowed: Option<HashSet<u64>>,
+        admins: HashSet<u64>,
+        store: Arc<dyn AccessStore>,
+    ) -> Self {
+        Self {
+            policy,
+            config,
+            allowed,
+            admins,
+            store,
+            requests: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Reload saved requests and decisions, returning how many were loaded
+    pub async fn load(&self) -> Result<usize> {
+        let saved = self.store.load().await?;
+        let count = saved.len();
+        let mut requests = self.requests.write().unwrap();
+        for request in saved {
+            requests.insert(request.user_id, request);
+        }
+        Ok(count)
+    }
+
+    /// Whether a user may talk to the bot: listed in `allowed_users` or approved
+    pub fn is_allowed(&self, user_id: u64) -> bool {
+        let Some(ref allowed) = self.allowed else {
+            return true;
+        };
+        allowed.contains(&user_id)
+            || self
+                .requests
+                .read()
+                .unwrap()
+                .get(&user_id)
+                .is_some_and(|r| r.status == AccessStatus::Approved)
+    }
+
+    /// Requests waiting for an admin, oldest first
+    pub fn pending(&self) -> Vec<AccessRequest> {
+        let mut pending: Vec<AccessRequest> = self
+            .requests
+            .read()
+            .unwrap()
+            .values()
+            .filter(|r| r.status == AccessStatus::Pending)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|r| r.requested_at);
+        pending
+    }
+
+    /// Decide what to do with a message from a user who isn't allowed
+    ///
+    /// Only a user's first message is reported; the request is saved before
+    /// returning.
+    pub async fn observe(&self, user_id: u64, name: &str, chat_id: i64, text: &str) -> UnknownUser {
+        let (status, reply) = match self.policy {
+            UnknownUserPolicy::Reject => return UnknownUser::Ignore,
+            UnknownUserPolicy::Queue => (AccessStatus::Pending, &self.config.pending_reply),
+            UnknownUserPolicy::Notify => (AccessStatus::Reported, &self.config.rejection_message),
+        };
+        let request = {
+            let mut requests = self.requests.write().unwrap();
+            if requests.contains_key(&user_id) {
+                return UnknownUser::Ignore;
+            }
+            let request = AccessRequest {
+                user_id,
+                name: name.to_string(),
+                chat_id,
+                first_message: text.trim().to_string(),
+                requested_at: chrono::Utc::now().timestamp(),
+                status,
+            };
+            requests.insert(user_id, request.clone());
+            request
+        };
+        if let Err(e) = self.store.save(&request).await {
+            warn!(user_id = %user_id, error = %e, "Failed to persist access request");
+        }
+        UnknownUser::Report {
+            reply: reply.clone(),
+            request,
+        }
+    }
+
+    /// Apply an Approve/Deny button pressed by `caller_id` in `chat_id`
+    ///
+    /// Configured admins may press it anywhere, anyone else only in the admin chat.
+    pub async fn handle_callback(&self, data: &str, chat_id: i64, caller_id: u64) -> AccessTap {
+        let Some((decision, user_id)) = parse_callback(data) else {
+            return AccessTap::NotAccess;
+        };
+        if !self.admins.contains(&caller_id) && self.config.admin_chat != Some(chat_id) {
+            return AccessTap::Unauthorized;
+        }
+        let request = {
+            let mut requests = self.requests.write().unwrap();
+            let Some(request) = requests.get_mut(&user_id) else {
+                return AccessTap::Unknown;
+            };
+            if request.status != AccessStatus::Pending {
+                return AccessTap::AlreadyDecided(request.clone());
+            }
+            request.status = match decision {
+                AccessDecision::Approve => AccessStatus::Approved,
+                AccessDecision::Deny => AccessStatus::Denied,
+            };
+            request.clone()
+        };
+        if let Err(e) = self.store.save(&request).await {
+            warn!(user_id = %user_id, error = %e, "Failed to persist access decision");
+        }
+        info!(user_id = %user_id, by = %caller_id, status = ?request.status, "Telegram access request decided");
+        AccessTap::Decided(request)
+    }
+
+    /// Message for the user once their request was decided
+    pub fn decision_message(&self, request: &AccessRequest) -> &str {
+        match request.status {
+            AccessStatus::Approved => &self.config.approval_message,
+            _ => &self.config.rejection_message,
+        }
+    }
+
+    /// Handle a message from a user who isn't allowed: reply once and tell the admins
+    pub async fn report(&self, bot: &Bot, user_id: u64, name: &str, chat_id: i64, text: &str) {
+        let UnknownUser::Report { reply, request } =
+            self.observe(user_id, name, chat_id, text).await
+        else {
+            return;
+        };
+        if let Err(e) = bot.send_message(ChatId(chat_id), reply).await {
+            warn!(chat_id = %chat_id, error = %e, "Failed to answer unknown user");
+        }
+        let notice = admin_notice(&request);
+        for admin_chat in self.admin_chats() {
+            let send = bot.send_message(ChatId(admin_chat), notice.clone());
+            let sent = if request.status == AccessStatus::Pending {
+                send.reply_markup(keyboard(user_id)).await
+            } else {
+                send.await
+            };
+            if let Err(e) = sent {
+                warn!(admin_chat = %admin_chat, error = %e, "Failed to post access request");
+            }
+        }
+        info!(user_id = %user_id, status = ?request.status, "Telegram access request from unknown user");
+    }
+
+    /// Chats access requests are posted to
+    fn admin_chats(&self) -> Vec<i64> {
+        match self.config.admin_chat {
+            Some(chat) => vec![chat],
+            None => self.admins.iter().map(|&admin| admin as i64).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::MemoryStateStore;
+
+    const ADMIN: u64 = 1;
+    const ADMIN_CHAT: i64 = -500;
+    const FRIEND: u64 = 10;
+    const STRANGER: u64 = 20;
+
+    fn access(policy: UnknownUserPolicy, store: Arc<dyn AccessStore>) -> AccessRequests {
+        AccessRequests::new(
+            policy,
+            AccessRequestConfig {
+                admin_chat: Some(ADMIN_CHAT),
+                ..Default::default()
+            },
+            Some(HashSet::from([FRIEND])),
+            HashSet::from([ADMIN]),
+            store,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_queue_reports_first_message_only() {
+        let access = access(
+            UnknownUserPolicy::Queue,
+            Arc::new(MemoryAccessStore::default()),
+        );
+        assert!(access.is_allowed(FRIEND));
+        assert!(!access.is_allowed(STRANGER));
+
+        let UnknownUser::Report { reply, request } = access
+            .observe(STRANGER, "Sam", 20, "hi, can I use this?")
+            .await
+        else {
+            panic!("first message should be reported");
+        };
+        assert_eq!(reply, AccessRequestConfig::default().pending_reply);
+        assert_eq!(request.status, AccessStatus::Pending);
+        assert!(admin_notice(&request).contains("hi, can I use this?"));
+        assert_eq!(access.pending(), vec![request]);
+
+        assert_eq!(
+            access.observe(STRANGER, "Sam", 20, "hello??").await,
+            UnknownUser::Ignore
+        );
+        assert!(!access.is_allowed(STRANGER));
+    }
+
+    #[tokio::test]
+    async fn test_approval_allows_immediately_and_survives_restart() {
+        let store: Arc<dyn AccessStore> =
+            Arc::new(StateAccessStore::new(Arc::new(MemoryStateStore::new())));
+        let first = access(UnknownUserPolicy::Queue, store.clone());
+        first.observe(STRANGER, "Sam", 20, "hi").await;
+
+        let data = callback_data(AccessDecision::Approve, STRANGER);
+        let AccessTap::Decided(request) = first.handle_callback(&data, ADMIN_CHAT, 99).await else {
+            panic!("admin chat members may decide");
+        };
+        assert_eq!(request.status, AccessStatus::Approved);
+        assert!(first.is_allowed(STRANGER));
+        assert_eq!(
+            first.decision_message(&request),
+            AccessRequestConfig::default().approval_message
+        );
+        assert!(matches!(
+            first.handle_callback(&data, ADMIN_CHAT, ADMIN).await,
+            AccessTap::AlreadyDecided(_)
+        ));
+
+        let restarted = access(UnknownUserPolicy::Queue, store);
+        assert!(!restarted.is_allowed(STRANGER));
+        assert_eq!(restarted.load().await.unwrap(), 1);
+        assert!(restarted.is_allowed(STRANGER));
+    }
+
+    #[tokio::test]
+    async fn test_denial_is_remembered() {
+        let access = access(
+            UnknownUserPolicy::Queue,
+            Arc::new(MemoryAccessStore::default()),
+        );
+        access.observe(STRANGER, "Sam", 20, "hi").await;
+
+        let data = callback_data(AccessDecision::Deny, STRANGER);
+        assert_eq!(
+            access.handle_callback(&data, 12345, 99).await,
+            AccessTap::Unauthorized
+        );
+        let AccessTap::Decided(request) = access.handle_callback(&data, 12345, ADMIN).await else {
+            panic!("configured admins may decide anywhere");
+        };
+        assert_eq!(request.status, AccessStatus::Denied);
+        assert_eq!(
+            access.decision_message(&request),
+            AccessRequestConfig::default().rejection_message
+        );
+        assert!(!access.is_allowed(STRANGER));
+        assert_eq!(
+            access.observe(STRANGER, "Sam", 20, "please?").await,
+            UnknownUser::Ignore
+        );
+        assert_eq!(
+            access
+                .handle_callback(
+                    &callback_data(AccessDecision::Approve, 30),
+                    ADMIN_CHAT,
+                    ADMIN
+                )
+                .await,
+            AccessTap::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_and_notify_policies() {
+        let reject = access(
+            UnknownUserPolicy::Reject,
+            Arc::new(MemoryAccessStore::default()),
+        );
+        assert_eq!(
+            reject.observe(STRANGER, "Sam", 20, "hi").await,
+            UnknownUser::Ignore
+        );
+
+        let notify = access(
+            UnknownUserPolicy::Notify,
+            Arc::new(MemoryAccessStore::default()),
+        );
+        let UnknownUser::Report { reply, request } =
+            notify.observe(STRANGER, "Sam", 20, "hi").await
+        else {
+            panic!("notify reports the first message");
+        };
+        assert_eq!(reply, AccessRequestConfig::default().rejection_message);
+        assert_eq!(request.status, AccessStatus::Reported);
+        assert!(notify.pending().is_empty());
+        assert_eq!(
+            notify
+                .handle_callback(
+                    &callback_data(AccessDecision::Approve, STRANGER),
+                    ADMIN_CHAT,
+                    ADMIN
+                )
+                .await,
+            AccessTap::AlreadyDecided(request)
+        );
+    }
+
+    #[test]
+    fn test_callback_data_and_policy_parsing() {
+        let data = callback_data(AccessDecision::Deny, 42);
+        assert_eq!(parse_callback(&data), Some((AccessDecision::Deny, 42)));
+        assert_eq!(parse_callback("acc:maybe:42"), None);
+        assert_eq!(parse_callback("ob:quiet"), None);
+        assert!(data.len() <= 64);
+
+        assert_eq!(
+            UnknownUserPolicy::parse(" Queue "),
+            Some(UnknownUserPolicy::Queue)
+        );
+        assert_eq!(
+            UnknownUserPolicy::parse("notify"),
+            Some(UnknownUserPolicy::Notify)
+        );
+        assert_eq!(UnknownUserPolicy::parse("drop"), None);
+
+        let everyone = AccessRequests::new(
+            UnknownUserPolicy::Queue,
+            AccessRequestConfig::default(),
+            None,
+            HashSet::new(),
+            Arc::new(MemoryAccessStore::default()),
+        );
+        assert!(everyone.is_allowed(STRANGER));
+    }
+}
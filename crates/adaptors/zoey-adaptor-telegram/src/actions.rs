@@ -0,0 +1,309 @@
+//! Tappable suggested actions parsed from the agent's `<actions>` block
+//!
+//! Besides its internal action names (`REPLY`, `ASK_CLARIFY`, …) the model
+//! may list short follow-up actions for the user, either as
+//! `<actions><action>Show pricing</action></actions>` or comma-separated.
+//! Up to [`MAX_ACTIONS`] of them are offered as inline keyboard buttons under
+//! the reply; tapping one marks it as chosen on the reply and answers it as
+//! if the user had typed it, in the same room.
+//!
+//! Callback data only carries `ac:<offer>:<index>`; the labels stay in an
+//! [`ActionStore`] until one is tapped or the offer expires. Labels longer
+//! than Telegram's 64-byte callback data limit are dropped. When a reply
+//! offers actions, follow-up questions are not offered under it.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageId};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::offers::{
+    self, truncate_label, OfferStore, OfferTap, MAX_CALLBACK_DATA, MAX_LABEL_CHARS,
+};
+
+/// Most actions offered under one reply
+pub const MAX_ACTIONS: usize = 4;
+
+/// Default time the action buttons stay under a reply
+pub const DEFAULT_ACTION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Prefix of callback data produced by action buttons
+const CALLBACK_PREFIX: &str = "ac:";
+
+/// Callback data of the inert button marking the chosen action
+const CHOSEN_CALLBACK: &str = "ac:chosen";
+
+/// Suggested action settings
+#[derive(Debug, Clone)]
+pub struct ActionConfig {
+    /// How long the buttons stay before the keyboard is removed
+    pub ttl: Duration,
+}
+
+impl Default for ActionConfig {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_ACTION_TTL,
+        }
+    }
+}
+
+/// Whether an entry is one of the runtime's action names (`REPLY`, `ASK_CLARIFY`)
+fn is_action_name(entry: &str) -> bool {
+    entry
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Up to [`MAX_ACTIONS`] user-facing action labels from a response's `<actions>` block
+///
+/// Runtime action names, duplicates and labels over [`MAX_CALLBACK_DATA`]
+/// bytes are skipped.
+pub fn parse_actions(raw: &str) -> Vec<String> {
+    let Some(start) = raw.find("<actions>") else {
+        return Vec::new();
+    };
+    let inner_start = start + "<actions>".len();
+    let Some(inner_len) = raw[inner_start..].find("</actions>") else {
+        return Vec::new();
+    };
+    let inner = &raw[inner_start..inner_start + inner_len];
+
+    let mut entries = Vec::new();
+    if inner.contains("<action>") {
+        let mut remaining = inner;
+        while let Some(open) = remaining.find("<action>") {
+            let after = &remaining[open + "<action>".len()..];
+            let Some(close) = after.find("</action>") else {
+                break;
+            };
+            entries.push(&after[..close]);
+            remaining = &after[close + "</action>".len()..];
+        }
+    } else {
+        entries.extend(inner.split(','));
+    }
+
+    let mut labels: Vec<String> = Vec::new();
+    for entry in entries.into_iter().map(str::trim) {
+        if entry.is_empty()
+            || is_action_name(entry)
+            || entry.len() > MAX_CALLBACK_DATA
+            || labels.iter().any(|l| l == entry)
+        {
+            continue;
+        }
+        labels.push(entry.to_string());
+        if labels.len() == MAX_ACTIONS {
+            break;
+        }
+    }
+    labels
+}
+
+/// Callback data referencing action `index` of `offer_id`
+pub fn callback_data(offer_id: u64, index: usize) -> String {
+    offers::callback_data(CALLBACK_PREFIX, offer_id, index)
+}
+
+/// Offer ID and action index from action callback data
+pub fn parse_callback_data(data: &str) -> Option<(u64, usize)> {
+    offers::parse_callback_data(CALLBACK_PREFIX, data)
+}
+
+/// Keyboard left on a reply once `label` was chosen
+pub fn chosen_keyboard(label: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        format!("✅ {}", truncate_label(label, MAX_LABEL_CHARS)),
+        CHOSEN_CALLBACK,
+    )]])
+}
+
+/// Result of a tapped action button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionTap {
+    /// The callback was not produced by an action button
+    NotAction,
+    /// The button marking an already chosen action
+    AlreadyChosen,
+    /// The offer expired, was already used, or belongs to another message
+    Expired,
+    /// The action the user chose
+    Chosen(String),
+}
+
+/// Offered actions, keyed by offer ID until tapped or expired
+pub struct ActionStore {
+    offers: Arc<OfferStore<String>>,
+}
+
+impl ActionStore {
+    pub fn new(config: ActionConfig) -> Self {
+        Self {
+            offers: Arc::new(OfferStore::new(CALLBACK_PREFIX, config.ttl)),
+        }
+    }
+
+    /// Store up to [`MAX_ACTIONS`] labels and build their keyboard, two buttons per row
+    ///
+    /// Returns `None` when there is nothing to offer. The offer only accepts
+    /// taps once [`ActionStore::attach`] names the message carrying it.
+    pub fn offer(
+        &self,
+        chat_id: i64,
+        labels: Vec<String>,
+        now: Instant,
+    ) -> Option<(u64, InlineKeyboardMarkup)> {
+        let labels = labels.into_iter().take(MAX_ACTIONS).collect();
+        let (offer_id, buttons) = self.offers.offer(chat_id, labels, now, Clone::clone)?;
+        let rows = buttons.chunks(2).map(<[_]>::to_vec).collect::<Vec<_>>();
+        Some((offer_id, InlineKeyboardMarkup::new(rows)))
+    }
+
+    /// Record the message the keyboard was posted with
+    pub fn attach(&self, offer_id: u64, message_id: i32) {
+        self.offers.attach(offer_id, message_id);
+    }
+
+    /// Drop an offer whose keyboard could not be posted
+    pub fn discard(&self, offer_id: u64) {
+        self.offers.discard(offer_id);
+    }
+
+    /// Resolve a tap on `message_id` in `chat_id`, consuming the whole offer
+    pub fn tap(&self, data: &str, chat_id: i64, message_id: i32, now: Instant) -> ActionTap {
+        if data == CHOSEN_CALLBACK {
+            return ActionTap::AlreadyChosen;
+        }
+        match self.offers.tap(data, chat_id, message_id, now) {
+            OfferTap::NotOffer => ActionTap::NotAction,
+            OfferTap::Expired => ActionTap::Expired,
+            OfferTap::Chosen(label) => ActionTap::Chosen(label),
+        }
+    }
+
+    /// Remove expired offers, returning the messages whose keyboard should be cleared
+    pub fn expire(&self, now: Instant) -> Vec<(i64, i32)> {
+        self.offers.expire(now)
+    }
+
+    pub fn len(&self) -> usize {
+        self.offers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offers.is_empty()
+    }
+}
+
+/// Replace the action buttons under a reply with the chosen one
+pub async fn mark_chosen(bot: &Bot, chat_id: i64, message_id: i32, label: &str) {
+    if let Err(e) = bot
+        .edit_message_reply_markup(ChatId(chat_id), MessageId(message_id))
+        .reply_markup(chosen_keyboard(label))
+        .await
+    {
+        debug!(chat_id = %chat_id, error = %e, "Failed to mark the chosen action");
+    }
+}
+
+/// Periodically remove expired offers and their keyboards until the task is aborted
+pub fn spawn_action_sweeper(store: Arc<ActionStore>, bot: Bot) -> JoinHandle<()> {
+    offers::spawn_offer_sweeper(store.offers.clone(), bot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    fn labels(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("Action {}", i)).collect()
+    }
+
+    #[test]
+    fn test_actions_are_parsed_from_either_form() {
+        let raw = "<response><thought>t</thought><actions>REPLY, Show pricing ,Book a demo,\
+                   Show pricing,ASK_CLARIFY</actions><text>Sure.</text></response>";
+        assert_eq!(parse_actions(raw), ["Show pricing", "Book a demo"]);
+
+        let raw = "<actions><action>One</action><action> </action><action>Two</action>\
+                   <action>Three</action><action>Four</action><action>Five</action></actions>";
+        assert_eq!(parse_actions(raw), ["One", "Two", "Three", "Four"]);
+
+        let long = "x".repeat(MAX_CALLBACK_DATA + 1);
+        let raw = format!(
+            "<actions><action>{}</action><action>Ok</action></actions>",
+            long
+        );
+        assert_eq!(parse_actions(&raw), ["Ok"]);
+
+        assert!(parse_actions("<actions>REPLY</actions><text>Hi</text>").is_empty());
+        assert!(parse_actions("<actions>Half a li").is_empty());
+        assert!(parse_actions("Plain answer").is_empty());
+    }
+
+    #[test]
+    fn test_tap_round_trip_marks_choice() {
+        let store = ActionStore::new(ActionConfig::default());
+        let now = Instant::now();
+        let (offer_id, markup) = store.offer(-100, labels(3), now).unwrap();
+        assert_eq!(markup.inline_keyboard.len(), 2, "two buttons per row");
+        match &markup.inline_keyboard[1][0].kind {
+            InlineKeyboardButtonKind::CallbackData(data) => {
+                assert!(data.len() <= MAX_CALLBACK_DATA);
+                assert_eq!(parse_callback_data(data), Some((offer_id, 2)));
+            }
+            other => panic!("unexpected button kind {:?}", other),
+        }
+        assert!(store.offer(-100, Vec::new(), now).is_none());
+
+        let data = callback_data(offer_id, 1);
+        // Not attached to a message yet
+        assert_eq!(store.tap(&data, -100, 42, now), ActionTap::Expired);
+        store.attach(offer_id, 42);
+        assert_eq!(store.tap(&data, -100, 41, now), ActionTap::Expired);
+        assert_eq!(
+            store.tap(&data, -100, 42, now),
+            ActionTap::Chosen("Action 1".to_string())
+        );
+        assert_eq!(store.tap(&data, -100, 42, now), ActionTap::Expired);
+        assert_eq!(
+            store.tap(CHOSEN_CALLBACK, -100, 42, now),
+            ActionTap::AlreadyChosen
+        );
+        assert_eq!(store.tap("fu:1:0", -100, 42, now), ActionTap::NotAction);
+
+        let chosen = chosen_keyboard("Action 1");
+        assert_eq!(chosen.inline_keyboard[0][0].text, "✅ Action 1");
+    }
+
+    #[test]
+    fn test_offers_expire_after_ttl() {
+        let store = ActionStore::new(ActionConfig {
+            ttl: Duration::from_secs(60),
+        });
+        let now = Instant::now();
+        let (posted, _) = store.offer(1, labels(2), now).unwrap();
+        store.attach(posted, 10);
+        // Keyboard never posted
+        store.offer(2, labels(1), now).unwrap();
+
+        assert!(store.expire(now + Duration::from_secs(30)).is_empty());
+        assert_eq!(store.len(), 2);
+
+        let late = now + Duration::from_secs(61);
+        assert_eq!(store.expire(late), vec![(1, 10)]);
+        assert!(store.is_empty());
+
+        // Taps after the TTL are refused even before a sweep
+        let (offer_id, _) = store.offer(3, labels(1), now).unwrap();
+        store.attach(offer_id, 11);
+        assert_eq!(
+            store.tap(&callback_data(offer_id, 0), 3, 11, late),
+            ActionTap::Expired
+        );
+    }
+}
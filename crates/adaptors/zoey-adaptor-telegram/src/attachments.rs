@@ -0,0 +1,408 @@
+//! Documents and photos sent to the bot, added to the chat's knowledge
+//!
+//! A PDF, spreadsheet or text file sent to the bot is downloaded through the
+//! Bot API and posted to the agent API's `/knowledge/ingest` under the chat's
+//! room and the sender's entity, as the web UI's file drop does, so later
+//! questions in the chat can draw on it. Binary formats are base64-encoded.
+//! The bot answers with the number of chunks stored, or why the file was
+//! refused (unsupported type, too large). A caption is then answered as an
+//! ordinary message in the same room.
+//!
+//! Which file types are accepted follows the agent API
+//! ([`KnowledgeDocumentType`]); photos are refused until it reads images.
+
+use reqwest::Client as HttpClient;
+use std::sync::OnceLock;
+use std::time::Duration;
+use teloxide::net::Download;
+use teloxide::prelude::*;
+use teloxide::types::Message;
+use uuid::Uuid;
+use zoey_core::agent_api::types::{KnowledgeDocumentType, KnowledgeIngestResponse};
+
+/// Default largest file downloaded for ingestion
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// How long the agent API may take to chunk a document
+const INGEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// What kind of Telegram attachment a file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Document,
+    Photo,
+}
+
+/// A document or photo attached to a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    /// Bot API file ID, used to download the file
+    pub file_id: String,
+    /// Name sent by the client, or one made up from the file's unique ID
+    pub filename: String,
+    pub mime_type: Option<String>,
+    /// Size reported by Telegram, in bytes
+    pub size: u64,
+    pub caption: Option<String>,
+}
+
+impl Attachment {
+    /// The document or (largest) photo of a message, if it has one
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let caption = msg
+            .caption()
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string);
+        if let Some(document) = msg.document() {
+            return Some(Self {
+                kind: AttachmentKind::Document,
+                file_id: document.file.id.clone(),
+                filename: document
+                    .file_name
+                    .clone()
+                    .unwrap_or_else(|| format!("document_{}", document.file.unique_id)),
+                mime_type: document.mime_type.as_ref().map(|m| m.to_string()),
+                size: document.file.size as u64,
+                caption,
+            });
+        }
+        let photo = msg.photo()?.iter().max_by_key(|p| p.width * p.height)?;
+        Some(Self {
+            kind: AttachmentKind::Photo,
+            file_id: photo.file.id.clone(),
+            filename: format!("photo_{}.jpg", photo.file.unique_id),
+            mime_type: Some("image/jpeg".to_string()),
+            size: photo.file.size as u64,
+            caption,
+        })
+    }
+
+    /// Document type the agent API reads the file as, or why it won't be downloaded
+    pub fn check(&self, max_bytes: u64) -> Result<KnowledgeDocumentType, AttachmentError> {
+        let doc_type = KnowledgeDocumentType::from_filename(&self.filename)
+            .filter(|_| self.kind == AttachmentKind::Document)
+            .ok_or_else(|| AttachmentError::Unsupported {
+                filename: self.filename.clone(),
+                photo: self.kind == AttachmentKind::Photo,
+            })?;
+        self.check_size(self.size, max_bytes)?;
+        Ok(doc_type)
+    }
+
+    fn check_size(&self, size: u64, max_bytes: u64) -> Result<(), AttachmentError> {
+        if size > max_bytes {
+            return Err(AttachmentError::TooLarge {
+                filename: self.filename.clone(),
+                size,
+                max: max_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why an attachment was not added to the chat's knowledge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentError {
+    /// Not a format the agent API reads
+    Unsupported { filename: String, photo: bool },
+    /// Over the configured size limit
+    TooLarge {
+        filename: String,
+        size: u64,
+        max: u64,
+    },
+    /// The Bot API did not hand out the file
+    Download(String),
+    /// The agent API refused or failed the document
+    Ingest { filename: String, reason: String },
+}
+
+impl AttachmentError {
+    /// Reply for the sender
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::Unsupported { photo: true, .. } => {
+                "I can't read photos yet. Send the document as a PDF or text file instead."
+                    .to_string()
+            }
+            Self::Unsupported { filename, .. } => format!(
+                "I can't read {}. Send a PDF, Excel, CSV, JSON, Markdown or text file.",
+                filename
+            ),
+            Self::TooLarge {
+                filename,
+                size,
+                max,
+            } => format!(
+                "{} is {}; I can take files up to {}.",
+                filename,
+                megabytes(*size),
+                megabytes(*max)
+            ),
+            Self::Download(_) => {
+                "I couldn't download that file from Telegram. Please try again.".to_string()
+            }
+            Self::Ingest { filename, reason } => {
+                format!("I couldn't add {}: {}", filename, reason)
+            }
+        }
+    }
+
+    /// Short class for telemetry
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::Unsupported { .. } => "unsupported_type",
+            Self::TooLarge { .. } => "too_large",
+            Self::Download(_) => "download",
+            Self::Ingest { .. } => "ingest",
+        }
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Body for `/knowledge/ingest`, in the shape the web UI's file drop sends
+pub fn ingest_request(
+    attachment: &Attachment,
+    doc_type: KnowledgeDocumentType,
+    bytes: &[u8],
+    room_id: Uuid,
+    entity_id: Uuid,
+) -> serde_json::Value {
+    use base64::Engine;
+    let text = std::str::from_utf8(bytes)
+        .ok()
+        .filter(|_| !doc_type.requires_base64());
+    let (content, base64_encoded) = match text {
+        Some(text) => (text.to_string(), false),
+        None => (
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+            true,
+        ),
+    };
+    let mime_type = attachment.mime_type.clone().unwrap_or_else(|| {
+        if base64_encoded {
+            "application/octet-stream".to_string()
+        } else {
+            "text/plain".to_string()
+        }
+    });
+    serde_json::json!({
+        "room_id": room_id,
+        "entity_id": entity_id,
+        "filename": attachment.filename,
+        "content": content,
+        "base64_encoded": base64_encoded,
+        "mime_type": mime_type,
+        "metadata": {
+            "original_size": bytes.len(),
+            "source": "telegram",
+            "telegram_file_id": attachment.file_id,
+        },
+    })
+}
+
+/// Reply confirming a stored document
+pub fn acknowledgment(filename: &str, chunks: usize) -> String {
+    format!(
+        "Added {} to this chat's knowledge ({} chunk{}).",
+        filename,
+        chunks,
+        if chunks == 1 { "" } else { "s" }
+    )
+}
+
+fn client() -> HttpClient {
+    static CLIENT: OnceLock<HttpClient> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            HttpClient::builder()
+                .timeout(INGEST_TIMEOUT)
+                .build()
+                .unwrap_or_else(|_| HttpClient::new())
+        })
+        .clone()
+}
+
+/// Download an attachment and ingest it into `room_id`, returning the chunks created
+pub async fn ingest(
+    bot: &Bot,
+    api_base: &str,
+    attachment: &Attachment,
+    max_bytes: u64,
+    room_id: Uuid,
+    entity_id: Uuid,
+) -> Result<usize, AttachmentError> {
+    let doc_type = attachment.check(max_bytes)?;
+    let file = bot
+        .get_file(attachment.file_id.clone())
+        .await
+        .map_err(|e| AttachmentError::Download(e.to_string()))?;
+    attachment.check_size(file.size as u64, max_bytes)?;
+    let mut bytes = Vec::with_capacity(file.size as usize);
+    bot.download_file(&file.path, &mut bytes)
+        .await
+        .map_err(|e| AttachmentError::Download(e.to_string()))?;
+    attachment.check_size(bytes.len() as u64, max_bytes)?;
+
+    let body = ingest_request(attachment, doc_type, &bytes, room_id, entity_id);
+    let ingest_error = |reason: String| AttachmentError::Ingest {
+        filename: attachment.filename.clone(),
+        reason,
+    };
+    let resp = client()
+        .post(format!("{}/knowledge/ingest", api_base))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = %e, "Knowledge ingest request failed");
+            ingest_error("the knowledge service is unavailable".to_string())
+        })?;
+    let status = resp.status();
+    match resp.json::<KnowledgeIngestResponse>().await {
+        Ok(result) if result.success => Ok(result.chunks_created.unwrap_or(0)),
+        Ok(result) => {
+            Err(ingest_error(result.error.unwrap_or_else(|| {
+                format!("the knowledge service answered {}", status)
+            })))
+        }
+        Err(_) => Err(ingest_error(format!(
+            "the knowledge service answered {}",
+            status
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(filename: &str, size: u64) -> Attachment {
+        Attachment {
+            kind: AttachmentKind::Document,
+            file_id: "file-1".to_string(),
+            filename: filename.to_string(),
+            mime_type: None,
+            size,
+            caption: None,
+        }
+    }
+
+    #[test]
+    fn test_from_message_picks_document_or_largest_photo() {
+        let message = |extra: serde_json::Value| -> Message {
+            let mut json = serde_json::json!({
+                "message_id": 5,
+                "date": 0,
+                "chat": { "id": 42, "type": "private", "first_name": "Ada" },
+                "from": { "id": 7, "is_bot": false, "first_name": "Ada" },
+                "caption": "  summarize this  ",
+            });
+            json.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            serde_json::from_value(json).unwrap()
+        };
+
+        let doc = message(serde_json::json!({
+            "document": {
+                "file_id": "doc-id",
+                "file_unique_id": "doc-u",
+                "file_size": 2048,
+                "file_name": "brief.pdf",
+                "mime_type": "application/pdf",
+            }
+        }));
+        let attachment = Attachment::from_message(&doc).unwrap();
+        assert_eq!(attachment.kind, AttachmentKind::Document);
+        assert_eq!(attachment.filename, "brief.pdf");
+        assert_eq!(attachment.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(attachment.size, 2048);
+        assert_eq!(attachment.caption.as_deref(), Some("summarize this"));
+
+        let photo = message(serde_json::json!({
+            "photo": [
+                { "file_id": "small", "file_unique_id": "s", "file_size": 10, "width": 90, "height": 90 },
+                { "file_id": "large", "file_unique_id": "l", "file_size": 900, "width": 800, "height": 800 },
+            ]
+        }));
+        let attachment = Attachment::from_message(&photo).unwrap();
+        assert_eq!(attachment.kind, AttachmentKind::Photo);
+        assert_eq!(attachment.file_id, "large");
+        assert_eq!(attachment.filename, "photo_l.jpg");
+
+        let text = message(serde_json::json!({ "text": "hi" }));
+        assert_eq!(Attachment::from_message(&text), None);
+    }
+
+    #[test]
+    fn test_check_refuses_unsupported_and_oversized_files() {
+        assert_eq!(
+            document("notes.md", 100).check(DEFAULT_MAX_ATTACHMENT_BYTES),
+            Ok(KnowledgeDocumentType::Markdown)
+        );
+
+        let err = document("song.mp3", 100)
+            .check(DEFAULT_MAX_ATTACHMENT_BYTES)
+            .unwrap_err();
+        assert_eq!(err.class(), "unsupported_type");
+        assert!(err.user_message().contains("song.mp3"));
+
+        let photo = Attachment {
+            kind: AttachmentKind::Photo,
+            filename: "photo_l.jpg".to_string(),
+            ..document("", 100)
+        };
+        let err = photo.check(DEFAULT_MAX_ATTACHMENT_BYTES).unwrap_err();
+        assert!(err.user_message().starts_with("I can't read photos"));
+
+        let err = document("big.pdf", DEFAULT_MAX_ATTACHMENT_BYTES + 1)
+            .check(DEFAULT_MAX_ATTACHMENT_BYTES)
+            .unwrap_err();
+        assert_eq!(
+            err.user_message(),
+            "big.pdf is 10.0 MB; I can take files up to 10.0 MB."
+        );
+    }
+
+    #[test]
+    fn test_ingest_request_encodes_binaries() {
+        let (room, entity) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let text = ingest_request(
+            &document("notes.txt", 5),
+            KnowledgeDocumentType::Text,
+            b"hello",
+            room,
+            entity,
+        );
+        assert_eq!(text["content"], "hello");
+        assert_eq!(text["base64_encoded"], false);
+        assert_eq!(text["mime_type"], "text/plain");
+        assert_eq!(text["room_id"], serde_json::json!(room));
+        assert_eq!(text["entity_id"], serde_json::json!(entity));
+        assert_eq!(text["metadata"]["source"], "telegram");
+
+        let pdf = ingest_request(
+            &document("brief.pdf", 4),
+            KnowledgeDocumentType::Pdf,
+            b"%PDF",
+            room,
+            entity,
+        );
+        assert_eq!(pdf["content"], "JVBERg==");
+        assert_eq!(pdf["base64_encoded"], true);
+        assert_eq!(pdf["mime_type"], "application/octet-stream");
+
+        assert_eq!(
+            acknowledgment("brief.pdf", 1),
+            "Added brief.pdf to this chat's knowledge (1 chunk)."
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Slash command registry
+//!
+//! Commands are registered as [`CommandSpec`]s on the handler, dispatched
+//! before the agent flow and published with `set_my_commands` so Telegram
+//! lists them in the command menu. Unregistered commands fall through to the
+//! agent like any other message.
+//!
+//! In groups a command may be addressed as `/name@bot`; commands addressed to
+//! another bot are ignored.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use teloxide::types::BotCommand;
+use teloxide::Bot;
+use tracing::warn;
+
+/// Longest command name Telegram accepts
+pub const MAX_COMMAND_NAME: usize = 32;
+
+/// Longest command description Telegram accepts
+pub const MAX_COMMAND_DESCRIPTION: usize = 256;
+
+/// The message a command was invoked with
+#[derive(Clone)]
+pub struct CommandContext {
+    pub bot: Bot,
+    pub chat_id: i64,
+    pub user_id: u64,
+    pub is_private: bool,
+    /// Text after the command name, trimmed
+    pub args: String,
+    /// Full message text, trimmed
+    pub text: String,
+}
+
+/// What happens after a command ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandOutcome {
+    /// The command replied itself; the agent is not asked
+    Handled,
+    /// Ask the agent `query` instead of the command text, speaking the reply when `voice`
+    Ask { query: String, voice: bool },
+}
+
+type HandlerFn =
+    dyn Fn(CommandContext) -> Pin<Box<dyn Future<Output = CommandOutcome> + Send>> + Send + Sync;
+
+/// A command shown in the menu and its handler
+#[derive(Clone)]
+pub struct CommandSpec {
+    /// Name without the slash, lowercase (e.g. "start")
+    pub name: String,
+    /// Shown next to the command in Telegram's menu
+    pub description: String,
+    pub handler: Arc<HandlerFn>,
+}
+
+impl CommandSpec {
+    pub fn new<F, Fut>(name: &str, description: &str, handler: F) -> Self
+    where
+        F: Fn(CommandContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = CommandOutcome> + Send + 'static,
+    {
+        Self {
+            name: name.trim_start_matches('/').to_lowercase(),
+            description: description.to_string(),
+            handler: Arc::new(move |ctx| Box::pin(handler(ctx))),
+        }
+    }
+
+    /// Whether Telegram accepts the name: 1-32 lowercase letters, digits or underscores
+    fn has_valid_name(&self) -> bool {
+        !self.name.is_empty()
+            && self.name.len() <= MAX_COMMAND_NAME
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    }
+}
+
+/// Split `/name@bot args` into the lowercase name and the arguments
+///
+/// `None` when the text is not a command or is addressed to another bot.
+pub fn parse_command<'a>(text: &'a str, bot_username: Option<&str>) -> Option<(String, &'a str)> {
+    let rest = text.trim().strip_prefix('/')?;
+    let (head, args) = match rest.find(char::is_whitespace) {
+        Some(i) => (&rest[..i], rest[i..].trim()),
+        None => (rest, ""),
+    };
+    let (name, target) = match head.split_once('@') {
+        Some((name, target)) => (name, Some(target)),
+        None => (head, None),
+    };
+    if let (Some(target), Some(username)) = (target, bot_username) {
+        if !target.eq_ignore_ascii_case(username) {
+            return None;
+        }
+    }
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.to_lowercase(), args))
+}
+
+/// Registered commands, in menu order
+#[derive(Clone, Default)]
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>,
+}
+
+impl CommandRegistry {
+    /// Add a command, replacing one with the same name
+    ///
+    /// Names Telegram would reject are skipped with a warning.
+    pub fn register(&mut self, spec: CommandSpec) {
+        if !spec.has_valid_name() {
+            warn!(command = %spec.name, "Ignoring Telegram command with an invalid name");
+            return;
+        }
+        match self.commands.iter_mut().find(|c| c.name == spec.name) {
+            Some(existing) => *existing = spec,
+            None => self.commands.push(spec),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
+    pub fn specs(&self) -> &[CommandSpec] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Run the command in `text`, or `None` when it is not a registered command
+    pub async fn dispatch(
+        &self,
+        bot: Bot,
+        bot_username: Option<&str>,
+        chat_id: i64,
+        user_id: u64,
+        is_private: bool,
+        text: &str,
+    ) -> Option<CommandOutcome> {
+        let (name, args) = parse_command(text, bot_username)?;
+        let spec = self.get(&name)?;
+        let ctx = CommandContext {
+            bot,
+            chat_id,
+            user_id,
+            is_private,
+            args: args.to_string(),
+            text: text.trim().to_string(),
+        };
+        Some((spec.handler)(ctx).await)
+    }
+
+    /// Commands for `set_my_commands`, descriptions clipped to Telegram's limit
+    pub fn bot_commands(&self) -> Vec<BotCommand> {
+        self.commands
+            .iter()
+            .map(|c| {
+                let description: String = if c.description.trim().is_empty() {
+                    c.name.clone()
+                } else {
+                    c.description
+                        .chars()
+                        .take(MAX_COMMAND_DESCRIPTION)
+                        .collect()
+                };
+                BotCommand::new(c.name.clone(), description)
+            })
+            .collect()
+    }
+
+    /// One line per command, for `/help`
+    pub fn help_text(&self) -> String {
+        self.commands
+            .iter()
+            .map(|c| format!("/{} - {}", c.name, c.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/start", None), Some(("start".into(), "")));
+        assert_eq!(
+            parse_command("  /Voice  What can you do? ", None),
+            Some(("voice".into(), "What can you do?"))
+        );
+        assert_eq!(
+            parse_command("/tier@zoey_bot set 5 paid", Some("Zoey_Bot")),
+            Some(("tier".into(), "set 5 paid"))
+        );
+        // Addressed to another bot in the group
+        assert_eq!(parse_command("/start@other_bot", Some("zoey_bot")), None);
+        assert_eq!(parse_command("hello /start", None), None);
+        assert_eq!(parse_command("/", None), None);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_runs_registered_commands() {
+        let mut registry = CommandRegistry::default();
+        registry.register(CommandSpec::new("echo", "Repeat", |ctx| async move {
+            CommandOutcome::Ask {
+                query: ctx.args,
+                voice: false,
+            }
+        }));
+        registry.register(CommandSpec::new("Bad-Name", "Rejected", |_| async {
+            CommandOutcome::Handled
+        }));
+        registry.register(CommandSpec::new("/echo", "Replaced", |ctx| async move {
+            CommandOutcome::Ask {
+                query: ctx.args.to_uppercase(),
+                voice: true,
+            }
+        }));
+        assert_eq!(registry.specs().len(), 1);
+        assert_eq!(registry.help_text(), "/echo - Replaced");
+        assert_eq!(registry.bot_commands()[0].command, "echo");
+
+        let bot = Bot::new("123:test");
+        let outcome = registry
+            .dispatch(bot.clone(), None, 1, 2, true, "/echo hi there")
+            .await;
+        assert_eq!(
+            outcome,
+            Some(CommandOutcome::Ask {
+                query: "HI THERE".into(),
+                voice: true
+            })
+        );
+        // Unknown commands go to the agent
+        assert_eq!(
+            registry.dispatch(bot, None, 1, 2, true, "/unknown").await,
+            None
+        );
+    }
+}
@@ -0,0 +1,611 @@
+//! Digest mode: periodic batched answers for busy groups
+//!
+//! Some groups want the bot to follow the discussion without answering every
+//! addressed message. With digest mode on (`/digest on`, admins only), an
+//! addressed group message gets a reaction and is queued instead of answered.
+//! Every `interval` the queued questions of each chat go to the backend in one
+//! request, attributed to their askers, and the answer is posted as a single
+//! digest. Chats with nothing queued are skipped.
+//!
+//! - Messages matching a bypass pattern (e.g. "urgent") or sent by admins are
+//!   still answered right away.
+//! - Queues are kept in the [`StateStore`] under `telegram:digest`, so a
+//!   restart does not lose them; the mode lives under `telegram:digest_mode`.
+//! - A queue reaching `max_pending` questions is digested immediately.
+//! - `/digest now` (admins) posts the digest early.
+//! - A digest the backend could not answer is put back for the next run.
+
+use regex::Regex;
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::ChatId;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use zoey_core::StateStore;
+
+use crate::polling::{self, PollConfig, PollOutcome};
+
+/// State store namespace holding each chat's queued questions
+const PENDING_NAMESPACE: &str = "telegram:digest";
+
+/// State store namespace holding whether digest mode is on in a chat
+const MODE_NAMESPACE: &str = "telegram:digest_mode";
+
+/// Telegram's limit on message length, in characters
+const MAX_MESSAGE_CHARS: usize = 4096;
+
+/// Default time between digests
+pub const DEFAULT_DIGEST_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default reaction acknowledging a queued question
+pub const DEFAULT_DIGEST_REACTION: &str = "✍";
+
+/// Default most questions queued per chat before the digest is posted early
+pub const DEFAULT_MAX_PENDING: usize = 30;
+
+/// Digest mode settings
+#[derive(Debug, Clone)]
+pub struct DigestConfig {
+    /// Time between digests
+    pub interval: Duration,
+    /// Questions queued per chat before the digest is posted early
+    pub max_pending: usize,
+    /// Case-insensitive regexes; matching messages are answered right away
+    pub bypass_patterns: Vec<String>,
+    /// Reaction acknowledging a queued question
+    pub reaction: String,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_DIGEST_INTERVAL,
+            max_pending: DEFAULT_MAX_PENDING,
+            bypass_patterns: vec![r"\burgent\b".to_string()],
+            reaction: DEFAULT_DIGEST_REACTION.to_string(),
+        }
+    }
+}
+
+/// A question waiting for the next digest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingQuestion {
+    pub message_id: i32,
+    pub user_id: u64,
+    /// Asker's display name, used for attribution
+    pub name: String,
+    pub text: String,
+    /// Unix timestamp of the question
+    pub asked_at: i64,
+}
+
+/// What queueing a question led to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Queued {
+    /// Waiting for the next digest, `pending` questions in the chat so far
+    Waiting { pending: usize },
+    /// The queue is full; these questions (the new one included) were taken for a digest now
+    Full(Vec<PendingQuestion>),
+}
+
+/// Result of `/digest`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DigestCommand {
+    /// Send this reply
+    Reply(String),
+    /// Post a digest of these questions now
+    Flush(Vec<PendingQuestion>),
+}
+
+/// Per-chat digest mode and queued questions
+pub struct Digests {
+    config: DigestConfig,
+    admins: HashSet<u64>,
+    bypass: Vec<Regex>,
+    store: Arc<dyn StateStore>,
+    polling: PollConfig,
+    /// Per-chat mode, cached from the store
+    enabled: RwLock<HashMap<i64, bool>>,
+    /// Serializes read-modify-write of the queues
+    queue_lock: tokio::sync::Mutex<()>,
+}
+
+impl Digests {
+    /// Create the digest state; bypass patterns that are not valid regexes are skipped with a warning
+    pub fn new(
+        config: DigestConfig,
+        admins: HashSet<u64>,
+        store: Arc<dyn StateStore>,
+        polling: PollConfig,
+    ) -> Self {
+        let bypass = config
+            .bypass_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(&format!("(?i){}", pattern)) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    warn!(pattern = %pattern, error = %e, "Ignoring invalid digest bypass pattern");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            config,
+            admins,
+            bypass,
+            store,
+            polling,
+            enabled: RwLock::new(HashMap::new()),
+            queue_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Time between digests
+    pub fn interval(&self) -> Duration {
+        self.config.interval
+    }
+
+    /// Reaction acknowledging a queued question
+    pub fn reaction(&self) -> &str {
+        &self.config.reaction
+    }
+
+    /// Whether a message is answered right away even in digest mode
+    pub fn bypasses(&self, user_id: u64, text: &str) -> bool {
+        self.admins.contains(&user_id) || self.bypass.iter().any(|re| re.is_match(text))
+    }
+
+    /// Whether digest mode is on in a chat (off unless an admin turned it on)
+    pub async fn is_enabled(&self, chat_id: i64) -> bool {
+        if let Some(&enabled) = self.enabled.read().unwrap().get(&chat_id) {
+            return enabled;
+        }
+        let enabled = match self.store.get(MODE_NAMESPACE, &chat_id.to_string()).await {
+            Ok(stored) => stored.and_then(|v| v.as_bool()).unwrap_or(false),
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Failed to load digest mode");
+                return false;
+            }
+        };
+        self.enabled.write().unwrap().insert(chat_id, enabled);
+        enabled
+    }
+
+    async fn set_enabled(&self, chat_id: i64, enabled: bool) {
+        if let Err(e) = self
+            .store
+            .set(
+                MODE_NAMESPACE,
+                &chat_id.to_string(),
+                serde_json::Value::Bool(enabled),
+                None,
+            )
+            .await
+        {
+            warn!(chat_id = %chat_id, error = %e, "Failed to persist digest mode");
+        }
+        self.enabled.write().unwrap().insert(chat_id, enabled);
+        info!(chat_id = %chat_id, enabled, "Telegram digest mode changed");
+    }
+
+    /// Questions queued in a chat, oldest first
+    pub async fn pending(&self, chat_id: i64) -> Vec<PendingQuestion> {
+        match self
+            .store
+            .get(PENDING_NAMESPACE, &chat_id.to_string())
+            .await
+        {
+            Ok(stored) => stored
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Failed to load digest queue");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn save(&self, chat_id: i64, questions: &[PendingQuestion]) {
+        let key = chat_id.to_string();
+        let result = if questions.is_empty() {
+            self.store.delete(PENDING_NAMESPACE, &key).await.map(|_| ())
+        } else {
+            self.store
+                .set(PENDING_NAMESPACE, &key, serde_json::json!(questions), None)
+                .await
+        };
+        if let Err(e) = result {
+            warn!(chat_id = %chat_id, error = %e, "Failed to persist digest queue");
+        }
+    }
+
+    /// Queue a question for the chat's next digest
+    pub async fn queue(&self, chat_id: i64, question: PendingQuestion) -> Queued {
+        let _guard = self.queue_lock.lock().await;
+        let mut questions = self.pending(chat_id).await;
+        questions.push(question);
+        if questions.len() >= self.config.max_pending.max(1) {
+            self.save(chat_id, &[]).await;
+            return Queued::Full(questions);
+        }
+        self.save(chat_id, &questions).await;
+        Queued::Waiting {
+            pending: questions.len(),
+        }
+    }
+
+    /// Take a chat's queued questions, leaving its queue empty
+    pub async fn take(&self, chat_id: i64) -> Vec<PendingQuestion> {
+        let _guard = self.queue_lock.lock().await;
+        let questions = self.pending(chat_id).await;
+        if !questions.is_empty() {
+            self.save(chat_id, &[]).await;
+        }
+        questions
+    }
+
+    /// Take the queued questions of every chat that has any
+    pub async fn take_all(&self) -> Vec<(i64, Vec<PendingQuestion>)> {
+        let _guard = self.queue_lock.lock().await;
+        let entries = match self.store.list(PENDING_NAMESPACE).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(error = %e, "Failed to list digest queues");
+                return Vec::new();
+            }
+        };
+        let mut batches = Vec::new();
+        for (key, value) in entries {
+            let Ok(chat_id) = key.parse::<i64>() else {
+                continue;
+            };
+            let questions: Vec<PendingQuestion> = serde_json::from_value(value).unwrap_or_default();
+            self.save(chat_id, &[]).await;
+            if !questions.is_empty() {
+                batches.push((chat_id, questions));
+            }
+        }
+        batches
+    }
+
+    /// Put questions whose digest failed back in front of the chat's queue
+    pub async fn requeue(&self, chat_id: i64, mut questions: Vec<PendingQuestion>) {
+        let _guard = self.queue_lock.lock().await;
+        questions.extend(self.pending(chat_id).await);
+        self.save(chat_id, &questions).await;
+    }
+
+    /// Handle `/digest [on|off|now]` (arguments only), returning what to do
+    pub async fn handle_command(
+        &self,
+        caller_id: u64,
+        chat_id: i64,
+        is_private: bool,
+        args: &str,
+    ) -> DigestCommand {
+        if is_private {
+            return DigestCommand::Reply("Digest mode only applies to group chats.".to_string());
+        }
+        let arg = args.split_whitespace().next();
+        if arg.is_some() && !self.admins.contains(&caller_id) {
+            return DigestCommand::Reply("Only admins can change digest mode.".to_string());
+        }
+        match arg {
+            None => {
+                let pending = self.pending(chat_id).await.len();
+                let reply = if self.is_enabled(chat_id).await {
+                    format!(
+                        "Digest mode is on in this chat: {} waiting, next digest within {} min.",
+                        questions(pending),
+                        self.config.interval.as_secs().div_ceil(60)
+                    )
+                } else {
+                    "Digest mode is off in this chat.".to_string()
+                };
+                DigestCommand::Reply(reply)
+            }
+            Some("on") => {
+                self.set_enabled(chat_id, true).await;
+                DigestCommand::Reply(format!(
+                    "Digest mode is on: I’ll collect questions and answer them together every {} min. Urgent questions still get an answer right away.",
+                    self.config.interval.as_secs().div_ceil(60)
+                ))
+            }
+            Some("off") => {
+                // Anything still queued goes out with the next scheduled run
+                self.set_enabled(chat_id, false).await;
+                DigestCommand::Reply("Digest mode is off in this chat.".to_string())
+            }
+            Some("now") => {
+                let questions = self.take(chat_id).await;
+                if questions.is_empty() {
+                    DigestCommand::Reply("No questions are waiting for the digest.".to_string())
+                } else {
+                    DigestCommand::Flush(questions)
+                }
+            }
+            Some(_) => DigestCommand::Reply("Usage: /digest [on|off|now]".to_string()),
+        }
+    }
+
+    /// Ask the backend about `questions` in one request and post the digest
+    ///
+    /// When the backend does not answer, the questions are queued again.
+    pub async fn flush(&self, bot: &Bot, chat_id: i64, questions: Vec<PendingQuestion>) {
+        if questions.is_empty() {
+            return;
+        }
+        let body = serde_json::json!({
+            "text": digest_prompt(&questions),
+            "roomId": crate::workspace::room_id_for_chat(chat_id),
+            "entityId": zoey_core::string_to_uuid(&format!("telegram-digest-{}", chat_id)),
+            "metadata": {
+                "digest": true,
+                "question_count": questions.len(),
+            }
+        });
+        let outcome =
+            polling::chat_via_task(&client(), &api_base(), &body, &self.polling, || async {}).await;
+        let answer = match outcome {
+            PollOutcome::Completed(raw) => crate::extract_final_text_from_xml(&raw),
+            outcome => {
+                warn!(chat_id = %chat_id, outcome = ?outcome, "Digest request failed, keeping the questions");
+                self.requeue(chat_id, questions).await;
+                return;
+            }
+        };
+        let text = digest_text(questions.len(), &answer);
+        match bot.send_message(ChatId(chat_id), text).await {
+            Ok(_) => info!(chat_id = %chat_id, questions = questions.len(), "Posted digest"),
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Failed to post digest, keeping the questions");
+                self.requeue(chat_id, questions).await;
+            }
+        }
+    }
+}
+
+/// "1 question" / "3 questions"
+fn questions(n: usize) -> String {
+    if n == 1 {
+        "1 question".to_string()
+    } else {
+        format!("{} questions", n)
+    }
+}
+
+/// Request text asking for one answer per queued question, with attribution
+pub fn digest_prompt(questions: &[PendingQuestion]) -> String {
+    let mut prompt = String::from(
+        "These questions were asked in the group since the last digest. \
+         Answer each one briefly, in order, as a numbered list starting with the asker's name.\n",
+    );
+    for (i, q) in questions.iter().enumerate() {
+        prompt.push_str(&format!("\n{}. {}: {}", i + 1, q.name, q.text.trim()));
+    }
+    prompt
+}
+
+/// Digest message: a header and the backend's answer, cut to Telegram's limit
+pub fn digest_text(count: usize, answer: &str) -> String {
+    let text = format!(
+        "Digest: {} since the last one\n\n{}",
+        questions(count),
+        answer.trim()
+    );
+    if text.chars().count() <= MAX_MESSAGE_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(MAX_MESSAGE_CHARS - 1).collect();
+    format!("{}…", cut)
+}
+
+fn api_base() -> String {
+    std::env::var("AGENT_API_URL")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| "http://127.0.0.1:9090/agent".to_string())
+}
+
+fn client() -> HttpClient {
+    static DIGEST_CLIENT: OnceLock<HttpClient> = OnceLock::new();
+    DIGEST_CLIENT.get_or_init(HttpClient::new).clone()
+}
+
+/// Post the digests of all chats with queued questions every interval until the task is aborted
+pub fn spawn_digest_flusher(digests: Arc<Digests>, bot: Bot) -> JoinHandle<()> {
+    let interval = digests.interval().max(Duration::from_secs(1));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately; questions restored after a restart wait a full interval
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            for (chat_id, questions) in digests.take_all().await {
+                digests.flush(&bot, chat_id, questions).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::MemoryStateStore;
+
+    const CHAT: i64 = -100;
+    const ADMIN: u64 = 1;
+    const MEMBER: u64 = 42;
+
+    fn digests(store: Arc<dyn StateStore>) -> Digests {
+        Digests::new(
+            DigestConfig {
+                max_pending: 3,
+                bypass_patterns: vec![r"\burgent\b".to_string(), "([".to_string()],
+                ..Default::default()
+            },
+            HashSet::from([ADMIN]),
+            store,
+            PollConfig::default(),
+        )
+    }
+
+    fn question(message_id: i32, text: &str) -> PendingQuestion {
+        PendingQuestion {
+            message_id,
+            user_id: MEMBER,
+            name: "Ana".to_string(),
+            text: text.to_string(),
+            asked_at: 1_700_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_questions_are_buffered_and_persisted() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let first = digests(store.clone());
+        assert!(!first.is_enabled(CHAT).await);
+        assert_eq!(
+            first.handle_command(MEMBER, CHAT, false, "on").await,
+            DigestCommand::Reply("Only admins can change digest mode.".to_string())
+        );
+        first.handle_command(ADMIN, CHAT, false, "on").await;
+        assert!(first.is_enabled(CHAT).await);
+
+        assert_eq!(
+            first
+                .queue(CHAT, question(10, "What's the deadline?"))
+                .await,
+            Queued::Waiting { pending: 1 }
+        );
+        assert_eq!(
+            first.queue(CHAT, question(11, "Who reviews it?")).await,
+            Queued::Waiting { pending: 2 }
+        );
+
+        // A fresh instance (e.g. after restart) sees the mode and the queue
+        let second = digests(store);
+        assert!(second.is_enabled(CHAT).await);
+        let pending = second.pending(CHAT).await;
+        assert_eq!(
+            pending,
+            [
+                question(10, "What's the deadline?"),
+                question(11, "Who reviews it?")
+            ]
+        );
+
+        // The cap digests the queue right away
+        let Queued::Full(batch) = second.queue(CHAT, question(12, "Where is it?")).await else {
+            panic!("expected a full queue");
+        };
+        assert_eq!(batch.len(), 3);
+        assert!(second.pending(CHAT).await.is_empty());
+
+        // Failed digests go back in front
+        second.queue(CHAT, question(13, "Later?")).await;
+        second.requeue(CHAT, batch).await;
+        let ids: Vec<i32> = second
+            .pending(CHAT)
+            .await
+            .iter()
+            .map(|q| q.message_id)
+            .collect();
+        assert_eq!(ids, [10, 11, 12, 13]);
+    }
+
+    #[tokio::test]
+    async fn test_bypass_pattern() {
+        let digests = digests(Arc::new(MemoryStateStore::new()));
+        assert!(digests.bypasses(MEMBER, "URGENT: the server is down"));
+        assert!(digests.bypasses(MEMBER, "this is urgent please"));
+        assert!(!digests.bypasses(MEMBER, "not so urgently needed"));
+        assert!(!digests.bypasses(MEMBER, "when is lunch?"));
+        // Admins are always answered right away
+        assert!(digests.bypasses(ADMIN, "when is lunch?"));
+        // The invalid pattern is skipped
+        assert!(!digests.bypasses(MEMBER, "(["));
+
+        let strict = Digests::new(
+            DigestConfig::default(),
+            HashSet::new(),
+            Arc::new(MemoryStateStore::new()),
+            PollConfig::default(),
+        );
+        assert!(strict.bypasses(MEMBER, "Urgent question"));
+        assert!(!strict.bypasses(ADMIN, "Regular question"));
+    }
+
+    #[tokio::test]
+    async fn test_interval_flush_skips_empty_buffers() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let digests = digests(store.clone());
+        assert!(digests.take_all().await.is_empty());
+
+        // Digest mode on in two chats, questions in only one
+        digests.handle_command(ADMIN, CHAT, false, "on").await;
+        digests.handle_command(ADMIN, CHAT - 1, false, "on").await;
+        digests
+            .queue(CHAT, question(10, "What's the deadline?"))
+            .await;
+        // A queue emptied by a manual flush leaves nothing behind
+        digests.queue(CHAT - 1, question(20, "Hi?")).await;
+        digests.take(CHAT - 1).await;
+
+        let batches = digests.take_all().await;
+        assert_eq!(
+            batches,
+            [(CHAT, vec![question(10, "What's the deadline?")])]
+        );
+        assert!(digests.take_all().await.is_empty());
+        assert!(store.list(PENDING_NAMESPACE).await.unwrap().is_empty());
+
+        let prompt = digest_prompt(&batches[0].1);
+        assert!(prompt.ends_with("\n\n1. Ana: What's the deadline?"));
+        assert!(
+            digest_text(1, "1. Ana: Friday.").starts_with("Digest: 1 question since the last one")
+        );
+        assert_eq!(
+            digest_text(2, &"x".repeat(5000)).chars().count(),
+            MAX_MESSAGE_CHARS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manual_flush_command() {
+        let digests = digests(Arc::new(MemoryStateStore::new()));
+        digests.handle_command(ADMIN, CHAT, false, "on").await;
+        assert_eq!(
+            digests.handle_command(ADMIN, CHAT, false, "now").await,
+            DigestCommand::Reply("No questions are waiting for the digest.".to_string())
+        );
+
+        digests
+            .queue(CHAT, question(10, "What's the deadline?"))
+            .await;
+        assert_eq!(
+            digests.handle_command(MEMBER, CHAT, false, "now").await,
+            DigestCommand::Reply("Only admins can change digest mode.".to_string())
+        );
+        // Anyone may ask for the status
+        let DigestCommand::Reply(status) = digests.handle_command(MEMBER, CHAT, false, "").await
+        else {
+            panic!("expected a status reply");
+        };
+        assert!(status.contains("1 question waiting"));
+
+        assert_eq!(
+            digests.handle_command(ADMIN, CHAT, false, "now").await,
+            DigestCommand::Flush(vec![question(10, "What's the deadline?")])
+        );
+        assert!(digests.pending(CHAT).await.is_empty());
+        assert_eq!(
+            digests.handle_command(ADMIN, CHAT, true, "now").await,
+            DigestCommand::Reply("Digest mode only applies to group chats.".to_string())
+        );
+    }
+}
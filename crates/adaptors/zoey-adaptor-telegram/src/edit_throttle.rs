@@ -0,0 +1,255 @@
+//! Pacing placeholder edits while a reply streams in
+//!
+//! Telegram rate-limits message edits and answers `429` with a `retry_after`
+//! when a chat is edited too often. [`EditThrottle`] decides when the next
+//! intermediate edit may go out: it waits out the cooldown Telegram asked
+//! for, doubles the edit interval (at least to `retry_after`, at most
+//! [`MAX_EDIT_INTERVAL`]) for the rest of the stream, and after
+//! [`EDIT_FAILURE_LIMIT`] failed edits in a row stops intermediate edits
+//! altogether so only the final answer is sent.
+//!
+//! The final answer is retried once after a rate limit
+//! ([`retry_rate_limited`]), and sent as a new message if the placeholder
+//! still can't be edited.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+use teloxide::{ApiError, RequestError};
+use tracing::warn;
+
+/// Longest the edit interval grows to after rate limits
+pub const MAX_EDIT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Failed edits in a row after which intermediate edits stop
+pub const EDIT_FAILURE_LIMIT: u32 = 3;
+
+/// What happened to an edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// The message shows the text (including "message is not modified")
+    Edited,
+    /// Telegram asked to wait this long before editing again
+    RateLimited(Duration),
+    /// Any other error
+    Failed,
+}
+
+impl EditOutcome {
+    /// Outcome of a Telegram request
+    pub fn of<T>(result: &Result<T, RequestError>) -> Self {
+        match result {
+            Ok(_) => Self::Edited,
+            Err(e) if is_not_modified(e) => Self::Edited,
+            Err(RequestError::RetryAfter(after)) => Self::RateLimited(after.duration()),
+            Err(_) => Self::Failed,
+        }
+    }
+}
+
+/// When a streaming reply's placeholder may be edited next
+#[derive(Debug, Clone)]
+pub struct EditThrottle {
+    interval: Duration,
+    failure_limit: u32,
+    last_edit: Instant,
+    cooldown_until: Option<Instant>,
+    consecutive_failures: u32,
+    suspended: bool,
+}
+
+impl EditThrottle {
+    /// Edit at most every `interval`, counting from `now`
+    pub fn new(interval: Duration, now: Instant) -> Self {
+        Self {
+            interval,
+            failure_limit: EDIT_FAILURE_LIMIT,
+            last_edit: now,
+            cooldown_until: None,
+            consecutive_failures: 0,
+            suspended: false,
+        }
+    }
+
+    /// Stop intermediate edits after `limit` failures in a row
+    pub fn with_failure_limit(mut self, limit: u32) -> Self {
+        self.failure_limit = limit.max(1);
+        self
+    }
+
+    /// Current time between edits
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether intermediate edits were given up on
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Whether an intermediate edit may be sent at `now`
+    pub fn should_edit(&self, now: Instant) -> bool {
+        !self.suspended
+            && self.cooldown(now).is_none()
+            && now.duration_since(self.last_edit) >= self.interval
+    }
+
+    /// Time left at `now` before Telegram accepts edits again
+    pub fn cooldown(&self, now: Instant) -> Option<Duration> {
+        self.cooldown_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// Record the outcome of an edit sent at `now`
+    pub fn record(&mut self, outcome: EditOutcome, now: Instant) {
+        self.last_edit = now;
+        match outcome {
+            EditOutcome::Edited => {
+                self.consecutive_failures = 0;
+                return;
+            }
+            EditOutcome::RateLimited(retry_after) => {
+                self.cooldown_until = Some(now + retry_after);
+                self.interval = (self.interval * 2).max(retry_after).min(MAX_EDIT_INTERVAL);
+                warn!(
+                    retry_after_secs = retry_after.as_secs(),
+                    interval_ms = self.interval.as_millis() as u64,
+                    "Telegram rate-limited a streaming edit, slowing down"
+                );
+            }
+            EditOutcome::Failed => {}
+        }
+        self.consecutive_failures += 1;
+        if !self.suspended && self.consecutive_failures >= self.failure_limit {
+            self.suspended = true;
+            warn!(
+                failures = self.consecutive_failures,
+                "Streaming edits keep failing, only the final answer will be sent"
+            );
+        }
+    }
+
+    /// Wait until Telegram accepts edits again
+    pub async fn cool_down(&self) {
+        if let Some(wait) = self.cooldown(Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Whether an edit failed only because the text was already there
+pub fn is_not_modified(error: &RequestError) -> bool {
+    matches!(error, RequestError::Api(ApiError::MessageNotModified))
+}
+
+/// Run `request`, and once more after the cooldown if Telegram rate-limits it
+pub async fn retry_rate_limited<T, F, Fut>(mut request: F) -> Result<T, RequestError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RequestError>>,
+{
+    match request().await {
+        Err(RequestError::RetryAfter(after)) => {
+            warn!(
+                retry_after_secs = after.seconds(),
+                "Telegram rate-limited the final reply, retrying"
+            );
+            tokio::time::sleep(after.duration()).await;
+            request().await
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::Seconds;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_rate_limit_backs_off_and_widens_interval() {
+        let start = Instant::now();
+        let mut throttle = EditThrottle::new(500 * MS, start);
+        assert!(!throttle.should_edit(start + 499 * MS));
+        assert!(throttle.should_edit(start + 500 * MS));
+
+        let at = start + 500 * MS;
+        throttle.record(EditOutcome::RateLimited(3000 * MS), at);
+        assert_eq!(throttle.cooldown(at + 1000 * MS), Some(2000 * MS));
+        assert!(!throttle.should_edit(at + 2999 * MS));
+        assert!(throttle.should_edit(at + 3000 * MS));
+        assert_eq!(throttle.interval(), 3000 * MS);
+
+        // Doubles on the next limit, up to the cap
+        let at = at + 3000 * MS;
+        throttle.record(EditOutcome::RateLimited(MS), at);
+        assert_eq!(throttle.interval(), 6000 * MS);
+        throttle.record(EditOutcome::RateLimited(MS), at);
+        assert_eq!(throttle.interval(), MAX_EDIT_INTERVAL);
+        assert_eq!(throttle.cooldown(at + 10 * MS), None);
+    }
+
+    #[test]
+    fn test_consecutive_failures_suspend_edits() {
+        let start = Instant::now();
+        let mut throttle = EditThrottle::new(100 * MS, start);
+        throttle.record(EditOutcome::Failed, start);
+        throttle.record(EditOutcome::RateLimited(MS), start);
+        // A success resets the streak
+        throttle.record(EditOutcome::Edited, start);
+        throttle.record(EditOutcome::Failed, start);
+        throttle.record(EditOutcome::Failed, start);
+        assert!(!throttle.is_suspended());
+
+        throttle.record(EditOutcome::Failed, start);
+        assert!(throttle.is_suspended());
+        assert!(!throttle.should_edit(start + MAX_EDIT_INTERVAL * 10));
+
+        let mut eager = EditThrottle::new(100 * MS, start).with_failure_limit(1);
+        eager.record(EditOutcome::Failed, start);
+        assert!(eager.is_suspended());
+    }
+
+    #[test]
+    fn test_outcome_of_telegram_errors() {
+        assert_eq!(
+            EditOutcome::of(&Ok::<_, RequestError>(())),
+            EditOutcome::Edited
+        );
+        let not_modified = RequestError::Api(ApiError::MessageNotModified);
+        assert_eq!(
+            EditOutcome::of::<()>(&Err(not_modified)),
+            EditOutcome::Edited
+        );
+        let limited = RequestError::RetryAfter(Seconds::from_seconds(7));
+        assert_eq!(
+            EditOutcome::of::<()>(&Err(limited)),
+            EditOutcome::RateLimited(Duration::from_secs(7))
+        );
+        let not_found = RequestError::Api(ApiError::MessageToEditNotFound);
+        assert_eq!(EditOutcome::of::<()>(&Err(not_found)), EditOutcome::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_final_request_retried_after_cooldown() {
+        let mut calls = 0;
+        let started = Instant::now();
+        let result = retry_rate_limited(|| {
+            calls += 1;
+            let first = calls == 1;
+            async move {
+                if first {
+                    Err(RequestError::RetryAfter(Seconds::from_seconds(1)))
+                } else {
+                    Ok("sent")
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "sent");
+        assert_eq!(calls, 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+}
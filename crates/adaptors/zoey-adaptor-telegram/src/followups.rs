@@ -0,0 +1,284 @@
+//! Tappable follow-up questions after an answer
+//!
+//! When enabled, chat requests carry `suggest_followups: true` and the model
+//! may end its reply with a `<followups><q>…</q></followups>` block. The block
+//! is stripped from the displayed text and up to three questions are offered
+//! as inline keyboard buttons under the reply. Tapping one posts the question
+//! and answers it as if the user had typed it.
+//!
+//! Telegram limits callback data to 64 bytes, so buttons only carry
+//! `fu:<offer>:<index>`; the full questions stay in a [`FollowupStore`] until
+//! one is tapped or the offer expires. Either way the keyboard is removed.
+//! Voice replies get no buttons.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+use teloxide::types::InlineKeyboardMarkup;
+use tokio::task::JoinHandle;
+
+use crate::offers::{self, OfferStore, OfferTap};
+
+/// Most follow-up questions offered under one answer
+pub const MAX_FOLLOWUPS: usize = 3;
+
+/// Default time the buttons stay under an answer
+pub const DEFAULT_FOLLOWUP_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Prefix of callback data produced by follow-up buttons
+const CALLBACK_PREFIX: &str = "fu:";
+
+/// Follow-up suggestion settings
+#[derive(Debug, Clone)]
+pub struct FollowupConfig {
+    /// How long the buttons stay before the keyboard is removed
+    pub ttl: Duration,
+    /// Questions offered per answer (capped at [`MAX_FOLLOWUPS`])
+    pub max_suggestions: usize,
+}
+
+impl Default for FollowupConfig {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_FOLLOWUP_TTL,
+            max_suggestions: MAX_FOLLOWUPS,
+        }
+    }
+}
+
+/// Split a response into the text without its `<followups>` block and the suggested questions
+///
+/// A block that is still streaming (no closing tag yet) is stripped up to the
+/// end and yields no questions.
+pub fn split_followups(raw: &str) -> (String, Vec<String>) {
+    let Some(start) = raw.find("<followups>") else {
+        return (raw.to_string(), Vec::new());
+    };
+    let inner_start = start + "<followups>".len();
+    let Some(inner_len) = raw[inner_start..].find("</followups>") else {
+        return (raw[..start].to_string(), Vec::new());
+    };
+    let inner = &raw[inner_start..inner_start + inner_len];
+    let rest = &raw[inner_start + inner_len + "</followups>".len()..];
+
+    let mut questions = Vec::new();
+    let mut remaining = inner;
+    while let Some(open) = remaining.find("<q>") {
+        let after = &remaining[open + "<q>".len()..];
+        let Some(close) = after.find("</q>") else {
+            break;
+        };
+        let question = after[..close].trim();
+        if !question.is_empty() {
+            questions.push(question.to_string());
+        }
+        remaining = &after[close + "</q>".len()..];
+    }
+    (format!("{}{}", &raw[..start], rest), questions)
+}
+
+/// Callback data referencing question `index` of `offer_id`
+pub fn callback_data(offer_id: u64, index: usize) -> String {
+    offers::callback_data(CALLBACK_PREFIX, offer_id, index)
+}
+
+/// Offer ID and question index from follow-up callback data
+pub fn parse_callback_data(data: &str) -> Option<(u64, usize)> {
+    offers::parse_callback_data(CALLBACK_PREFIX, data)
+}
+
+/// Result of a tapped follow-up button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FollowupTap {
+    /// The callback was not produced by a follow-up button
+    NotFollowup,
+    /// The offer expired, was already used, or belongs to another message
+    Expired,
+    /// The question the user chose
+    Chosen(String),
+}
+
+/// Offered follow-up questions, keyed by offer ID until tapped or expired
+pub struct FollowupStore {
+    max_suggestions: usize,
+    offers: Arc<OfferStore<String>>,
+}
+
+impl FollowupStore {
+    pub fn new(config: FollowupConfig) -> Self {
+        Self {
+            max_suggestions: config.max_suggestions.min(MAX_FOLLOWUPS),
+            offers: Arc::new(OfferStore::new(CALLBACK_PREFIX, config.ttl)),
+        }
+    }
+
+    /// Store up to `max_suggestions` questions and build their keyboard, one per row
+    ///
+    /// Returns `None` when there is nothing to offer. The offer only accepts
+    /// taps once [`FollowupStore::attach`] names the message carrying it.
+    pub fn offer(
+        &self,
+        chat_id: i64,
+        questions: Vec<String>,
+        now: Instant,
+    ) -> Option<(u64, InlineKeyboardMarkup)> {
+        let questions = questions.into_iter().take(self.max_suggestions).collect();
+        let (offer_id, buttons) = self.offers.offer(chat_id, questions, now, Clone::clone)?;
+        let rows = buttons
+            .into_iter()
+            .map(|button| vec![button])
+            .collect::<Vec<_>>();
+        Some((offer_id, InlineKeyboardMarkup::new(rows)))
+    }
+
+    /// Record the message the keyboard was posted with
+    pub fn attach(&self, offer_id: u64, message_id: i32) {
+        self.offers.attach(offer_id, message_id);
+    }
+
+    /// Drop an offer whose keyboard could not be posted
+    pub fn discard(&self, offer_id: u64) {
+        self.offers.discard(offer_id);
+    }
+
+    /// Resolve a tap on `message_id` in `chat_id`, consuming the whole offer
+    pub fn tap(&self, data: &str, chat_id: i64, message_id: i32, now: Instant) -> FollowupTap {
+        match self.offers.tap(data, chat_id, message_id, now) {
+            OfferTap::NotOffer => FollowupTap::NotFollowup,
+            OfferTap::Expired => FollowupTap::Expired,
+            OfferTap::Chosen(question) => FollowupTap::Chosen(question),
+        }
+    }
+
+    /// Remove expired offers, returning the messages whose keyboard should be cleared
+    pub fn expire(&self, now: Instant) -> Vec<(i64, i32)> {
+        self.offers.expire(now)
+    }
+
+    pub fn len(&self) -> usize {
+        self.offers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offers.is_empty()
+    }
+}
+
+/// Periodically remove expired offers and their keyboards until the task is aborted
+pub fn spawn_followup_sweeper(store: Arc<FollowupStore>, bot: Bot) -> JoinHandle<()> {
+    offers::spawn_offer_sweeper(store.offers.clone(), bot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::offers::{MAX_CALLBACK_DATA, MAX_LABEL_CHARS};
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    fn questions(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("Question {}?", i)).collect()
+    }
+
+    #[test]
+    fn test_block_is_parsed_and_stripped() {
+        let raw = "<response><text>Paris is the capital.\n<followups><q>What about Lyon?</q>\
+                   <q> </q><q>How big is Paris?</q></followups></text></response>";
+        let (text, questions) = split_followups(raw);
+        assert_eq!(
+            text,
+            "<response><text>Paris is the capital.\n</text></response>"
+        );
+        assert_eq!(questions, ["What about Lyon?", "How big is Paris?"]);
+
+        let (text, questions) = split_followups("Plain answer");
+        assert_eq!(text, "Plain answer");
+        assert!(questions.is_empty());
+
+        // Still streaming: hide the partial block
+        let (text, questions) = split_followups("Answer <followups><q>Half a que");
+        assert_eq!(text, "Answer ");
+        assert!(questions.is_empty());
+    }
+
+    #[test]
+    fn test_callback_data_fits_limit() {
+        let data = callback_data(u64::MAX, MAX_FOLLOWUPS - 1);
+        assert!(data.len() <= MAX_CALLBACK_DATA);
+        assert_eq!(parse_callback_data(&data), Some((u64::MAX, 2)));
+        assert_eq!(parse_callback_data("other:1:2"), None);
+        assert_eq!(parse_callback_data("fu:x:1"), None);
+
+        let long = "Could you explain in much more detail how the rate limiter handles bursts?";
+        let store = FollowupStore::new(FollowupConfig::default());
+        let (_, markup) = store
+            .offer(
+                1,
+                vec![long.to_string(), "Short?".to_string()],
+                Instant::now(),
+            )
+            .unwrap();
+        let button = &markup.inline_keyboard[0][0];
+        assert_eq!(button.text.chars().count(), MAX_LABEL_CHARS);
+        assert!(button.text.ends_with('…'));
+        assert_eq!(markup.inline_keyboard[1][0].text, "Short?");
+        match &button.kind {
+            InlineKeyboardButtonKind::CallbackData(data) => {
+                assert!(data.len() <= MAX_CALLBACK_DATA)
+            }
+            other => panic!("unexpected button kind {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_offer_is_consumed_once() {
+        let store = FollowupStore::new(FollowupConfig::default());
+        let now = Instant::now();
+        let (offer_id, markup) = store.offer(5, questions(4), now).unwrap();
+        assert_eq!(markup.inline_keyboard.len(), MAX_FOLLOWUPS);
+        assert!(store.offer(5, Vec::new(), now).is_none());
+
+        let data = callback_data(offer_id, 1);
+        // Not attached to a message yet
+        assert_eq!(store.tap(&data, 5, 9, now), FollowupTap::Expired);
+
+        let (offer_id, _) = store.offer(5, questions(2), now).unwrap();
+        store.attach(offer_id, 9);
+        let data = callback_data(offer_id, 1);
+        assert_eq!(store.tap(&data, 6, 9, now), FollowupTap::Expired);
+        assert_eq!(
+            store.tap(&data, 5, 9, now),
+            FollowupTap::Chosen("Question 1?".to_string())
+        );
+        assert_eq!(store.tap(&data, 5, 9, now), FollowupTap::Expired);
+        assert_eq!(store.tap("noop", 5, 9, now), FollowupTap::NotFollowup);
+    }
+
+    #[test]
+    fn test_offers_expire_after_ttl() {
+        let store = FollowupStore::new(FollowupConfig {
+            ttl: Duration::from_secs(60),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let (posted, _) = store.offer(1, questions(2), now).unwrap();
+        store.attach(posted, 10);
+        // Keyboard never posted
+        store.offer(2, questions(1), now).unwrap();
+
+        assert!(store.expire(now + Duration::from_secs(30)).is_empty());
+        assert_eq!(store.len(), 2);
+
+        let late = now + Duration::from_secs(61);
+        // Only the posted keyboard needs clearing
+        assert_eq!(store.expire(late), vec![(1, 10)]);
+        assert!(store.is_empty());
+
+        // Taps after the TTL are refused even before a sweep
+        let (offer_id, _) = store.offer(3, questions(1), now).unwrap();
+        store.attach(offer_id, 11);
+        assert_eq!(
+            store.tap(&callback_data(offer_id, 0), 3, 11, late),
+            FollowupTap::Expired
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! MarkdownV2 formatting for agent replies
+//!
+//! Models answer in common markdown: `**bold**`, `_italic_`, `- lists`,
+//! `# headings`, inline and fenced code, `[links](url)`. With
+//! `TelegramConfig::parse_mode` set to `MarkdownV2`, [`to_markdown_v2`]
+//! converts that into Telegram's dialect and escapes every reserved character
+//! outside code. Markers without a partner are escaped and shown literally, so
+//! half-streamed replies still parse; an unclosed code fence runs to the end.
+//!
+//! Telegram rejects malformed entities with a 400, so [`send_formatted`] and
+//! [`edit_formatted`] resend the plain text when that happens.
+
+use teloxide::prelude::*;
+use teloxide::types::{ChatId, InlineKeyboardMarkup, Message, MessageId, ParseMode};
+use teloxide::{ApiError, RequestError};
+use tracing::warn;
+
+use crate::group_replies;
+
+/// Characters MarkdownV2 requires escaping outside code
+const RESERVED: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!', '\\',
+];
+
+/// Text formatted for `mode`, or `None` when the reply should go out plain
+///
+/// Only `MarkdownV2` is converted; other modes send the text as is.
+pub fn format_reply(text: &str, mode: ParseMode) -> Option<String> {
+    match mode {
+        ParseMode::MarkdownV2 => Some(to_markdown_v2(text)),
+        _ => None,
+    }
+}
+
+/// Escape every MarkdownV2 reserved character in `text`
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if RESERVED.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape the characters MarkdownV2 reserves inside code
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+/// Escape the characters MarkdownV2 reserves inside a link target
+fn escape_url(url: &str) -> String {
+    url.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+/// Convert common markdown to MarkdownV2
+pub fn to_markdown_v2(text: &str) -> String {
+    let mut out = Vec::new();
+    let mut lines = text.split('\n');
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            // An unclosed fence (still streaming) runs to the end
+            let mut body = Vec::new();
+            for code_line in lines.by_ref() {
+                if code_line.trim_start().starts_with("```") {
+                    break;
+                }
+                body.push(code_line);
+            }
+            let lang = lang.trim();
+            let lang = if lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '_')
+            {
+                lang
+            } else {
+                ""
+            };
+            out.push(format!(
+                "```{}\n{}\n```",
+                lang,
+                escape_code(&body.join("\n"))
+            ));
+            continue;
+        }
+
+        let indent = &line[..line.len() - trimmed.len()];
+        let heading = trimmed.trim_start_matches('#');
+        if heading.len() < trimmed.len() && trimmed.len() - heading.len() <= 6 {
+            if let Some(title) = heading.strip_prefix(' ') {
+                out.push(format!("{}*{}*", indent, inline(title.trim())));
+                continue;
+            }
+        }
+        if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet))
+        {
+            out.push(format!("{}• {}", indent, inline(item)));
+            continue;
+        }
+        out.push(format!("{}{}", indent, inline(trimmed)));
+    }
+    out.join("\n")
+}
+
+/// Convert the inline markup of one line
+fn inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        if let Some((formatted, consumed)) = span(rest, prev) {
+            out.push_str(&formatted);
+            prev = rest[..consumed].chars().last();
+            rest = &rest[consumed..];
+            continue;
+        }
+        if RESERVED.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// A span of markup starting at `text`, converted, with the bytes it used
+fn span(text: &str, prev: Option<char>) -> Option<(String, usize)> {
+    let after_word = prev.is_some_and(|c| c.is_alphanumeric());
+
+    // Bare URLs pass through escaped, so `_` in a path never starts italics
+    if text.starts_with("https://") || text.starts_with("http://") {
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        return Some((escape(&text[..end]), end));
+    }
+    if let Some(code) = text.strip_prefix('`') {
+        let end = code.find('`').filter(|&end| end > 0)?;
+        return Some((format!("`{}`", escape_code(&code[..end])), end + 2));
+    }
+    if text.starts_with('[') {
+        let label_end = text.find("](")?;
+        let target = &text[label_end + 2..];
+        // Parentheses inside the URL must balance, as in markdown
+        let mut depth = 0usize;
+        let url_end = target.char_indices().find_map(|(i, c)| match c {
+            '(' => {
+                depth += 1;
+                None
+            }
+            ')' if depth == 0 => Some(i),
+            ')' => {
+                depth -= 1;
+                None
+            }
+            _ => None,
+        })?;
+        let url = &target[..url_end];
+        if label_end == 1 || url.is_empty() || url.contains(char::is_whitespace) {
+            return None;
+        }
+        return Some((
+            format!("[{}]({})", inline(&text[1..label_end]), escape_url(url)),
+            label_end + 2 + url_end + 1,
+        ));
+    }
+    for (marker, entity) in [("**", "*"), ("__", "*"), ("~~", "~")] {
+        if marker == "__" && after_word {
+            continue;
+        }
+        if let Some((inner, consumed)) = delimited(text, marker) {
+            return Some((format!("{}{}{}", entity, inline(inner), entity), consumed));
+        }
+    }
+    for marker in ["*", "_"] {
+        if text.starts_with(marker) && !text[1..].starts_with(marker) {
+            // snake_case words keep their underscores
+            if marker == "_" && after_word {
+                continue;
+            }
+            if let Some((inner, consumed)) = delimited(text, marker) {
+                let next = text[consumed..].chars().next();
+                if marker == "_" && next.is_some_and(|c| c.is_alphanumeric()) {
+                    continue;
+                }
+                return Some((format!("_{}_", inline(inner)), consumed));
+            }
+        }
+    }
+    None
+}
+
+/// Text between `marker` at the start of `text` and its closing partner
+///
+/// The inner text must not start or end with whitespace, like markdown's
+/// flanking rules. A single-character marker does not close on a doubled one,
+/// so `*see **this** too*` keeps its bold inside the italics.
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let body = text.strip_prefix(marker)?;
+    if body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let single = marker.len() == 1;
+    let mut from = 0;
+    while let Some(found) = body[from..].find(marker) {
+        let end = from + found;
+        let inner = &body[..end];
+        let doubled = single && (body[end + 1..].starts_with(marker) || inner.ends_with(marker));
+        if !inner.is_empty() && !doubled && !inner.ends_with(char::is_whitespace) {
+            return Some((inner, marker.len() * 2 + end));
+        }
+        from = end + marker.len();
+    }
+    None
+}
+
+/// Whether Telegram rejected a request for its entities
+fn is_entity_error(error: &RequestError) -> bool {
+    matches!(error, RequestError::Api(ApiError::CantParseEntities(_)))
+}
+
+/// Send `text` formatted for `parse_mode`, resending it plain if Telegram cannot parse it
+///
+/// With `reply_to` the message is sent as a reply to that message.
+pub async fn send_formatted(
+    bot: &Bot,
+    chat_id: ChatId,
+    text: &str,
+    parse_mode: Option<ParseMode>,
+    markup: Option<InlineKeyboardMarkup>,
+    reply_to: Option<MessageId>,
+) -> Result<Message, RequestError> {
+    if let Some((formatted, mode)) =
+        parse_mode.and_then(|mode| format_reply(text, mode).map(|f| (f, mode)))
+    {
+        let mut send = bot.send_message(chat_id, formatted).parse_mode(mode);
+        if let Some(ref markup) = markup {
+            send = send.reply_markup(markup.clone());
+        }
+        if let Some(message_id) = reply_to {
+            send = send.reply_parameters(group_replies::reply_parameters(message_id));
+        }
+        match send.await {
+            Err(e) if is_entity_error(&e) => {
+                warn!(error = %e, "Formatted reply rejected, sending plain text");
+            }
+            result => return result,
+        }
+    }
+    let mut send = bot.send_message(chat_id, text);
+    if let Some(markup) = markup {
+        send = send.reply_markup(markup);
+    }
+    if let Some(message_id) = reply_to {
+        send = send.reply_parameters(group_replies::reply_parameters(message_id));
+    }
+    send.await
+}
+
+/// Replace a message's text with `text` formatted for `parse_mode`, falling back to plain text
+pub async fn edit_formatted(
+    bot: &Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    text: &str,
+    parse_mode: Option<ParseMode>,
+    markup: Option<InlineKeyboardMarkup>,
+) -> Result<Message, RequestError> {
+    if let Some((formatted, mode)) =
+        parse_mode.and_then(|mode| format_reply(text, mode).map(|f| (f, mode)))
+    {
+        let mut edit = bot
+            .edit_message_text(chat_id, message_id, formatted)
+            .parse_mode(mode);
+        if let Some(ref markup) = markup {
+            edit = edit.reply_markup(markup.clone());
+        }
+        match edit.await {
+            Err(e) if is_entity_error(&e) => {
+                warn!(error = %e, "Formatted edit rejected, using plain text");
+            }
+            result => return result,
+        }
+    }
+    let mut edit = bot.edit_message_text(chat_id, message_id, text);
+    if let Some(markup) = markup {
+        edit = edit.reply_markup(markup);
+    }
+    edit.await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_characters_are_escaped() {
+        assert_eq!(
+            to_markdown_v2("Done! Costs $5.00 (approx) - see #3."),
+            "Done\\! Costs $5\\.00 \\(approx\\) \\- see \\#3\\."
+        );
+        // Unpaired markers show literally
+        assert_eq!(to_markdown_v2("2 * 3 = 6"), "2 \\* 3 \\= 6");
+        assert_eq!(to_markdown_v2("a ` b"), "a \\` b");
+        assert_eq!(to_markdown_v2("**unfinished"), "\\*\\*unfinished");
+        // snake_case stays intact
+        assert_eq!(
+            to_markdown_v2("call user_id_map now"),
+            "call user\\_id\\_map now"
+        );
+    }
+
+    #[test]
+    fn test_emphasis_and_nesting() {
+        assert_eq!(to_markdown_v2("**bold** and *it*"), "*bold* and _it_");
+        assert_eq!(
+            to_markdown_v2("__bold__ _it_ ~~gone~~"),
+            "*bold* _it_ ~gone~"
+        );
+        assert_eq!(
+            to_markdown_v2("**bold _italic_ text.**"),
+            "*bold _italic_ text\\.*"
+        );
+        assert_eq!(to_markdown_v2("*see **this** one*"), "_see *this* one_");
+    }
+
+    #[test]
+    fn test_code_is_kept_verbatim() {
+        assert_eq!(
+            to_markdown_v2("Run `cargo test -- --ignored` now."),
+            "Run `cargo test -- --ignored` now\\."
+        );
+        assert_eq!(
+            to_markdown_v2("```rust\nlet x = a_b * 2; // `q`\n```\nDone."),
+            "```rust\nlet x = a_b * 2; // \\`q\\`\n```\nDone\\."
+        );
+        // Still streaming: the fence closes at the end
+        assert_eq!(to_markdown_v2("```\nfn main() {"), "```\nfn main() {\n```");
+    }
+
+    #[test]
+    fn test_links_and_urls() {
+        assert_eq!(
+            to_markdown_v2("See [the docs](https://example.com/a_(b)) for more."),
+            "See [the docs](https://example.com/a_(b\\)) for more\\."
+        );
+        assert_eq!(
+            to_markdown_v2("Open https://example.com/my_file_v2.txt now"),
+            "Open https://example\\.com/my\\_file\\_v2\\.txt now"
+        );
+        assert_eq!(
+            to_markdown_v2("[**Bold link**](https://x.io)"),
+            "[*Bold link*](https://x.io)"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            to_markdown_v2("# Plan\n- one\n  * two\n1. three"),
+            "*Plan*\n• one\n  • two\n1\\. three"
+        );
+        assert_eq!(format_reply("a.b", ParseMode::Html), None);
+        assert_eq!(
+            format_reply("a.b", ParseMode::MarkdownV2).as_deref(),
+            Some("a\\.b")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Reply threading in group chats
+//!
+//! In a busy group an answer posted on its own is hard to match with its
+//! question, so outside private chats the placeholder and the final answer
+//! are sent as replies to the triggering message. If that message is deleted
+//! before the answer lands, Telegram sends the answer as a plain message
+//! instead of failing ([`reply_parameters`]).
+//!
+//! The `@botname` mention that addressed the bot says nothing to the model,
+//! so it is removed from the query ([`strip_bot_mention`]). When the user
+//! replied to a message, the replied-to text goes along as context under
+//! [`REPLY_CONTEXT_KEY`]; the conversation stays in the chat's room.
+
+use regex::Regex;
+use teloxide::types::{MessageId, ReplyParameters};
+
+/// Content and request metadata key holding the replied-to message
+pub const REPLY_CONTEXT_KEY: &str = "reply_to";
+
+/// Most characters of the replied-to text passed as context
+const MAX_REPLY_CONTEXT_CHARS: usize = 1000;
+
+/// Message the bot's messages should reply to, `None` in private chats
+pub fn reply_target(is_private: bool, message_id: i32) -> Option<MessageId> {
+    (!is_private).then_some(MessageId(message_id))
+}
+
+/// Reply to `message_id`, or send plainly if it no longer exists
+pub fn reply_parameters(message_id: MessageId) -> ReplyParameters {
+    ReplyParameters::new(message_id).allow_sending_without_reply()
+}
+
+/// `text` without `@bot_username` (any case) and the whitespace around it
+///
+/// A comma or colon right after the mention goes with it, so "@zoey, hi"
+/// becomes "hi". Text that is only the mention is returned unchanged, so
+/// the query is never empty.
+pub fn strip_bot_mention(text: &str, bot_username: &str) -> String {
+    let username = bot_username.trim_start_matches('@');
+    if username.is_empty() {
+        return text.trim().to_string();
+    }
+    let mention = Regex::new(&format!(
+        r"(?i)[ \t]*@{}\b[,:]?[ \t]*",
+        regex::escape(username)
+    ))
+    .expect("escaped username is a valid pattern");
+    let stripped = mention.replace_all(text, |caps: &regex::Captures| {
+        // Mid-line mentions keep a space between the words around them
+        let start = caps.get(0).map_or(0, |m| m.start());
+        if start == 0 || text[..start].ends_with('\n') {
+            ""
+        } else {
+            " "
+        }
+    });
+    let stripped = stripped.trim();
+    if stripped.is_empty() {
+        text.trim().to_string()
+    } else {
+        stripped.to_string()
+    }
+}
+
+/// Context for a reply to `message_id`, or `None` when it had no text
+pub fn reply_context(
+    message_id: i32,
+    text: Option<&str>,
+    from_bot: bool,
+) -> Option<serde_json::Value> {
+    let text = text.map(str::trim).filter(|t| !t.is_empty())?;
+    let text: String = text.chars().take(MAX_REPLY_CONTEXT_CHARS).collect();
+    Some(serde_json::json!({
+        "message_id": message_id,
+        "text": text,
+        "from_bot": from_bot,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_bot_mention() {
+        assert_eq!(
+            strip_bot_mention("@zoey_bot what is 2+2?", "zoey_bot"),
+            "what is 2+2?"
+        );
+        assert_eq!(
+            strip_bot_mention("@Zoey_Bot, summarize this", "zoey_bot"),
+            "summarize this"
+        );
+        assert_eq!(
+            strip_bot_mention("so  @zoey_bot  what now ", "@zoey_bot"),
+            "so what now"
+        );
+        assert_eq!(
+            strip_bot_mention("line one\n@zoey_bot: line two", "zoey_bot"),
+            "line one\nline two"
+        );
+        // Another bot whose name starts with ours is left alone
+        assert_eq!(
+            strip_bot_mention("ask @zoey_bot_dev", "zoey_bot"),
+            "ask @zoey_bot_dev"
+        );
+        assert_eq!(strip_bot_mention(" @zoey_bot ", "zoey_bot"), "@zoey_bot");
+    }
+
+    #[test]
+    fn test_reply_threading_and_context() {
+        assert_eq!(reply_target(true, 7), None);
+        assert_eq!(reply_target(false, 7), Some(MessageId(7)));
+        let params = reply_parameters(MessageId(7));
+        assert_eq!(params.message_id, MessageId(7));
+        assert_eq!(params.allow_sending_without_reply, Some(true));
+
+        assert_eq!(reply_context(3, Some("   "), true), None);
+        assert_eq!(reply_context(3, None, false), None);
+        let context = reply_context(3, Some(" The filing deadline is May 2. "), true).unwrap();
+        assert_eq!(
+            context,
+            serde_json::json!({ "message_id": 3, "text": "The filing deadline is May 2.", "from_bot": true })
+        );
+        let long = "x".repeat(MAX_REPLY_CONTEXT_CHARS + 10);
+        let context = reply_context(3, Some(&long), false).unwrap();
+        assert_eq!(
+            context["text"].as_str().unwrap().len(),
+            MAX_REPLY_CONTEXT_CHARS
+        );
+    }
+}
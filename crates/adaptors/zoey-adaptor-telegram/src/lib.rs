@@ -1,46 +1,132 @@
 use async_trait::async_trait;
 use zoey_core::{
     types::{service::Service, ChannelType, Content, Memory, Room},
-    validate_input, AgentRuntime, RateLimiter, Result,
+    validate_input, AgentRuntime, ContextOverflowPolicy, IdentityLinks, MemoryStateStore,
+    MessageTelemetry, RateLimiter, Result, StateStore, Telemetry, TelemetryConfig,
+    CONTEXT_OVERFLOW_MESSAGE,
 };
 use reqwest::Client as HttpClient;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use teloxide::prelude::*;
 #[cfg(feature = "voice")]
 use teloxide::types::InputFile;
-use teloxide::types::{ChatId, Message as TelegramMessage, MessageId};
+#[cfg(feature = "voice")]
+use zoey_provider_voice::SinkFormat;
+use teloxide::types::{
+    CallbackQuery, ChatId, ChatMemberUpdated, Message as TelegramMessage, MessageId, ReactionType,
+    UserId,
+};
 use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
+pub mod abuse_guard;
+pub mod access;
+pub mod actions;
+pub mod attachments;
+pub mod commands;
+pub mod digest;
+pub mod edit_throttle;
+pub mod followups;
+pub mod formatting;
+pub mod group_replies;
+pub mod linking;
+pub mod near_miss;
+pub mod offers;
+pub mod onboarding;
+pub mod polling;
+pub mod scheduler;
+pub mod speech_language;
+pub mod tiers;
 pub mod voice;
-pub use voice::{TelegramVoiceSettings, VoiceConfig, VoiceManager};
+pub mod workspace;
+pub use abuse_guard::{AbuseGuard, AbuseGuardConfig, AbuseSignal, Mute, Verdict};
+pub use access::{
+    AccessRequest, AccessRequestConfig, AccessRequests, AccessStatus, AccessStore,
+    MemoryAccessStore, StateAccessStore, UnknownUserPolicy,
+};
+pub use actions::{ActionConfig, ActionStore, ActionTap};
+pub use attachments::{Attachment, AttachmentError, DEFAULT_MAX_ATTACHMENT_BYTES};
+pub use commands::{CommandContext, CommandOutcome, CommandRegistry, CommandSpec};
+pub use digest::{DigestCommand, DigestConfig, Digests, PendingQuestion, Queued};
+pub use edit_throttle::{EditOutcome, EditThrottle};
+pub use followups::{FollowupConfig, FollowupStore, FollowupTap};
+pub use formatting::to_markdown_v2;
+pub use teloxide::types::ParseMode;
+pub use near_miss::{
+    AckSettingsStore, AdapterAckSettingsStore, MemoryAckSettingsStore, NearMissAck, NearMissConfig,
+};
+pub use offers::{OfferStore, OfferTap};
+pub use onboarding::{GroupMode, Onboarding, OnboardingConfig};
+pub use polling::{ChatMode, ChatModes, PollConfig, PollOutcome};
+pub use scheduler::{
+    MemoryScheduledSendStore, ScheduledSend, ScheduledSendStore, SendScheduler,
+    StateScheduledSendStore,
+};
+pub use speech_language::{HintSource, LanguageHint, ObservedLanguages, SpeechLanguage};
+pub use tiers::{
+    AdapterQuotaStore, MemoryQuotaStore, QuotaDecision, QuotaStore, Tier, TierManager,
+};
+pub use voice::{SpeechSource, TelegramVoiceSettings, VoiceConfig, VoiceManager};
+pub use workspace::WorkspaceLink;
 
 static TELEGRAM_DISPATCHER_HANDLE: OnceLock<JoinHandle<()>> = OnceLock::new();
+static FOLLOWUP_SWEEPER_HANDLE: OnceLock<JoinHandle<()>> = OnceLock::new();
+static ACTION_SWEEPER_HANDLE: OnceLock<JoinHandle<()>> = OnceLock::new();
+static DIGEST_FLUSHER_HANDLE: OnceLock<JoinHandle<()>> = OnceLock::new();
+static SEND_SCHEDULER_HANDLE: OnceLock<JoinHandle<()>> = OnceLock::new();
+
+/// State store namespace for messages already picked up
+const DEDUP_NAMESPACE: &str = "telegram:dedup";
+
+/// How long a handled message ID is remembered
+const DEDUP_TTL: Duration = Duration::from_secs(15 * 60);
 
 pub fn shutdown_telegram() {
     if let Some(h) = TELEGRAM_DISPATCHER_HANDLE.get() {
         h.abort();
     }
+    if let Some(h) = FOLLOWUP_SWEEPER_HANDLE.get() {
+        h.abort();
+    }
+    if let Some(h) = ACTION_SWEEPER_HANDLE.get() {
+        h.abort();
+    }
+    if let Some(h) = DIGEST_FLUSHER_HANDLE.get() {
+        h.abort();
+    }
+    if let Some(h) = SEND_SCHEDULER_HANDLE.get() {
+        h.abort();
+    }
 }
 
 /// Extract text content from a fully assembled XML response
 fn extract_final_text_from_xml(content: &str) -> String {
-    // Try to extract content from <text>...</text> tags
-    if let Some(start) = content.find("<text>") {
-        let after_tag = &content[start + 6..];
-        if let Some(end) = after_tag.find("</text>") {
-            return after_tag[..end].trim().to_string();
-        }
-        // Text tag opened but not closed - return what's after it
-        return after_tag.trim().to_string();
-    }
+    zoey_core::extract_response(content).text
+}
 
-    // No text tag found - return the original content trimmed
-    // This handles cases where the LLM didn't follow XML format
-    content.trim().to_string()
+/// Wrap a reply in the configured `response_template`
+fn render_reply(template: Option<&str>, text: &str, room: &str, character: &str) -> String {
+    zoey_core::render_response_template(
+        template,
+        zoey_core::ResponseTemplateVars {
+            text,
+            room,
+            character,
+        },
+    )
+}
+
+/// `{room}` for a send-handler target: its `room_name` metadata, else the room ID
+fn target_room_label(target: &zoey_core::types::messaging::TargetInfo) -> String {
+    target
+        .metadata
+        .get("room_name")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| target.room_id.to_string())
 }
 
 #[derive(Clone)]
@@ -49,10 +135,63 @@ pub struct TelegramConfig {
     pub token: String,
     pub allowed_chats: Option<Vec<i64>>,
     pub allowed_users: Option<Vec<u64>>,
+    /// What happens to messages from users outside `allowed_users` (dropped by default)
+    pub unknown_user_policy: UnknownUserPolicy,
+    /// Admin chat and replies for access requests
+    pub access_requests: AccessRequestConfig,
+    /// Where access requests and approvals are kept (the `state_store` when `None`)
+    pub access_store: Option<Arc<dyn AccessStore>>,
     /// Bot username (without @), used for detecting mentions in groups
     pub bot_username: Option<String>,
     /// Voice configuration for TTS
     pub voice: VoiceConfig,
+    /// Per-user tier assignments (Telegram user ID -> tier)
+    pub tiers: HashMap<u64, Tier>,
+    /// Tier for users without an explicit assignment
+    pub default_tier: Tier,
+    /// UTC hour (0-23) at which daily quotas reset
+    pub quota_reset_hour_utc: u32,
+    /// Users allowed to run admin commands such as `/tier set`
+    pub admin_users: Vec<u64>,
+    /// Detection patterns and retry budget for backend context-length errors
+    pub context_overflow: ContextOverflowPolicy,
+    /// Web UI URL opened by the "Open full workspace" button (disabled when `None`)
+    pub webapp_url: Option<String>,
+    /// Wraps text replies; placeholders `{text}`, `{room}`, `{character}` (default `{text}`)
+    pub response_template: Option<String>,
+    /// React to group messages that almost addressed the bot (disabled when `None`)
+    pub near_miss_ack: Option<NearMissConfig>,
+    /// Offer tappable follow-up questions under answers (disabled when `None`)
+    pub followups: Option<FollowupConfig>,
+    /// Offer the actions suggested in a reply's `<actions>` block as buttons (disabled when `None`)
+    pub actions: Option<ActionConfig>,
+    /// Welcome groups the bot is added to (disabled when `None`)
+    pub onboarding: Option<OnboardingConfig>,
+    /// Temporarily mute users flooding the bot (disabled when `None`)
+    pub abuse_guard: Option<AbuseGuardConfig>,
+    /// Let admins batch group questions into periodic digests with `/digest` (disabled when `None`)
+    pub digest: Option<DigestConfig>,
+    /// Force streaming (`true`) or task polling (`false`); `None` falls back to
+    /// polling when the backend has no `/chat/stream`
+    pub streaming: Option<bool>,
+    /// Backoff and deadline when polling chat tasks
+    pub polling: PollConfig,
+    /// Where message dedup keys are kept; share one store (e.g.
+    /// `MongoStateStore`) between processes serving the same bot
+    pub state_store: Arc<dyn StateStore>,
+    /// Account linking with the web UI (`/link`, `/unlink`, `/whoami`; disabled when `None`);
+    /// share the instance with the web adapter
+    pub identity_links: Option<Arc<IdentityLinks>>,
+    /// Where per-message lifecycle events go (tracing only by default)
+    pub telemetry: TelemetryConfig,
+    /// Format replies with this parse mode (plain text when `None`); with
+    /// `MarkdownV2` the model's markdown is converted and escaped
+    pub parse_mode: Option<ParseMode>,
+    /// Largest document added to a chat's knowledge when sent to the bot
+    pub max_attachment_bytes: u64,
+    /// Where sends scheduled with `deliver_at`/`delay_secs` are kept so they
+    /// survive a restart (memory only when `None`)
+    pub scheduled_send_store: Option<Arc<dyn ScheduledSendStore>>,
 }
 
 impl Default for TelegramConfig {
@@ -62,8 +201,32 @@ impl Default for TelegramConfig {
             token: String::new(),
             allowed_chats: None,
             allowed_users: None,
+            unknown_user_policy: UnknownUserPolicy::default(),
+            access_requests: AccessRequestConfig::default(),
+            access_store: None,
             bot_username: None,
             voice: VoiceConfig::default(),
+            tiers: HashMap::new(),
+            default_tier: Tier::default(),
+            quota_reset_hour_utc: 0,
+            admin_users: Vec::new(),
+            context_overflow: ContextOverflowPolicy::default(),
+            webapp_url: None,
+            response_template: None,
+            near_miss_ack: None,
+            followups: None,
+            actions: Some(ActionConfig::default()),
+            onboarding: None,
+            abuse_guard: None,
+            digest: None,
+            streaming: None,
+            polling: PollConfig::default(),
+            state_store: Arc::new(MemoryStateStore::new()),
+            identity_links: None,
+            telemetry: TelemetryConfig::default(),
+            parse_mode: None,
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
+            scheduled_send_store: None,
         }
     }
 }
@@ -73,6 +236,8 @@ pub struct TelegramAdapterService {
     runtime: Arc<RwLock<AgentRuntime>>,
     running: bool,
     limiter: Arc<RateLimiter>,
+    /// Commands registered on top of the built-in ones
+    extra_commands: Vec<CommandSpec>,
 }
 
 impl TelegramAdapterService {
@@ -83,18 +248,161 @@ impl TelegramAdapterService {
             runtime,
             running: false,
             limiter,
+            extra_commands: Vec::new(),
+        }
+    }
+
+    /// Add a slash command; takes effect on the next `start`
+    ///
+    /// A command with the same name as a built-in one replaces it.
+    pub fn register_command(&mut self, spec: CommandSpec) {
+        self.extra_commands.push(spec);
+    }
+}
+
+/// The message a turn replied to
+struct ReplyRef {
+    message_id: i32,
+    from_id: Option<u64>,
+    /// Its text or caption
+    text: Option<String>,
+}
+
+impl ReplyRef {
+    fn from_message(reply: &TelegramMessage) -> Self {
+        Self {
+            message_id: reply.id.0,
+            from_id: reply.from.as_ref().map(|from| from.id.0),
+            text: reply.text().or(reply.caption()).map(str::to_string),
         }
     }
 }
 
+/// One user message to answer, typed or chosen from follow-up buttons
+struct ChatTurn {
+    msg_id: i32,
+    chat_id: i64,
+    user_id: u64,
+    /// Sender's display name
+    user_name: String,
+    is_private: bool,
+    text: String,
+    /// Came from a voice note, so the reply may be spoken
+    from_voice: bool,
+    /// Language hint and detected language, for transcribed speech
+    speech_language: Option<SpeechLanguage>,
+    reply_to: Option<ReplyRef>,
+    /// Chosen from follow-up or action buttons; always addressed to the bot
+    followup: bool,
+}
+
+impl ChatTurn {
+    /// Turn for a tapped follow-up question or action, posted as message `msg_id`
+    fn followup(
+        chat_id: i64,
+        user_id: u64,
+        user_name: String,
+        is_private: bool,
+        msg_id: i32,
+        question: String,
+    ) -> Self {
+        Self {
+            msg_id,
+            chat_id,
+            user_id,
+            user_name,
+            is_private,
+            text: question,
+            from_voice: false,
+            speech_language: None,
+            reply_to: None,
+            followup: true,
+        }
+    }
+
+    /// Whether the bot was addressed: private chat, follow-up, mention, reply to it, or allowed chat
+    fn is_addressed(
+        &self,
+        bot_id: u64,
+        bot_username: Option<&str>,
+        allowed_chats: Option<&HashSet<i64>>,
+    ) -> bool {
+        let mentioned = bot_username
+            .map(|username| self.text.contains(&format!("@{}", username)))
+            .unwrap_or(false);
+        let is_reply_to_bot = self
+            .reply_to
+            .as_ref()
+            .and_then(|reply| reply.from_id)
+            .map(|from_id| from_id == bot_id)
+            .unwrap_or(false);
+        let in_allowed_chat = allowed_chats
+            .map(|set| set.contains(&self.chat_id))
+            .unwrap_or(false);
+        self.is_private || self.followup || mentioned || is_reply_to_bot || in_allowed_chat
+    }
+}
+
+/// Where and how a turn's final answer goes, fixed before the request is sent
+struct FinalDelivery<'a> {
+    bot: &'a Bot,
+    chat_id: i64,
+    placeholder_id: Option<i32>,
+    /// Message the answer replies to (the question, in groups)
+    reply_to: Option<MessageId>,
+    response_template: Option<&'a str>,
+    room_name: &'a str,
+    character: &'a str,
+    followups: Option<&'a FollowupStore>,
+    actions: Option<&'a ActionStore>,
+    /// Speak the answer instead of putting it in the placeholder
+    send_as_voice: bool,
+    /// Reports the delivered answer
+    telemetry: &'a MessageTelemetry,
+    parse_mode: Option<ParseMode>,
+    #[cfg(feature = "voice")]
+    voice_manager: &'a VoiceManager,
+}
+
 struct TelegramHandler {
     runtime: Arc<RwLock<AgentRuntime>>,
     limiter: Arc<RateLimiter>,
+    /// Shared dedup keys
+    state_store: Arc<dyn StateStore>,
     allowed_chats: Option<HashSet<i64>>,
-    allowed_users: Option<HashSet<u64>>,
+    /// `allowed_users` plus approved access requests
+    access: Arc<AccessRequests>,
     bot_username: Option<String>,
     bot_id: u64,
     voice_manager: Arc<VoiceManager>,
+    tier_manager: Arc<TierManager>,
+    context_overflow: ContextOverflowPolicy,
+    workspace: Option<Arc<WorkspaceLink>>,
+    response_template: Option<String>,
+    near_miss: Option<Arc<NearMissAck>>,
+    followups: Option<Arc<FollowupStore>>,
+    actions: Option<Arc<ActionStore>>,
+    onboarding: Option<Arc<Onboarding>>,
+    abuse_guard: Option<Arc<AbuseGuard>>,
+    /// Digest mode per group chat
+    digests: Option<Arc<Digests>>,
+    /// Admins notified of abuse mutes
+    admin_users: Vec<u64>,
+    /// Maps linked users to their web entity
+    identity_links: Option<Arc<IdentityLinks>>,
+    commands: Arc<CommandRegistry>,
+    /// Streaming or task polling, per backend
+    chat_modes: Arc<ChatModes>,
+    poll_config: PollConfig,
+    /// Per-message lifecycle events
+    telemetry: Telemetry,
+    /// Parse mode for reply text
+    parse_mode: Option<ParseMode>,
+    /// Largest document downloaded for knowledge ingestion
+    max_attachment_bytes: u64,
+    /// Languages detected in users' recent speech, for transcription hints
+    #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+    observed_languages: ObservedLanguages,
 }
 
 impl TelegramHandler {
@@ -106,40 +414,55 @@ impl TelegramHandler {
         text: &str,
         voice_manager: &VoiceManager,
         include_text: bool,
+        reply_to: Option<MessageId>,
     ) -> std::result::Result<(), String> {
         // Show recording audio action
         let _ = bot
             .send_chat_action(ChatId(chat_id), teloxide::types::ChatAction::RecordVoice)
             .await;
 
-        // Synthesize speech
-        let (audio_data, duration) = voice_manager.synthesize_for_telegram(text).await?;
+        // Synthesize speech; engines that cannot produce Opus fall back to an audio file
+        let sink = voice_manager.sink();
+        let (audio, duration) = match voice_manager.synthesize_for(text, sink).await {
+            Err(e) if sink == SinkFormat::TelegramVoice => {
+                warn!(error = %e, "Voice note synthesis failed, sending an audio file instead");
+                voice_manager.synthesize_for(text, SinkFormat::TelegramAudio).await?
+            }
+            result => result?,
+        };
 
         info!(
             chat_id = %chat_id,
             text_len = %text.len(),
-            audio_size = %audio_data.len(),
+            audio_size = %audio.data.len(),
+            format = %audio.format.as_str(),
             duration = %duration,
             "Sending voice message"
         );
 
-        // Choose sending method based on configured output format
-        let is_opus = voice_manager
-            .config
-            .output_format
-            .eq_ignore_ascii_case("opus");
-        let file_name = if is_opus { "voice.ogg" } else { "voice.mp3" };
-        let input_file = InputFile::memory(audio_data.clone()).file_name(file_name);
+        let is_opus = SinkFormat::TelegramVoice.accepts(&audio);
+        let input_file =
+            InputFile::memory(audio.data.clone()).file_name(VoiceManager::file_name(&audio));
+
+        let send_audio = |file: InputFile| {
+            let send = bot.send_audio(ChatId(chat_id), file).duration(duration);
+            match reply_to {
+                Some(id) => send.reply_parameters(group_replies::reply_parameters(id)),
+                None => send,
+            }
+        };
 
         // Prefer native voice messages for Opus-in-OGG; otherwise use audio
         let send_result = if is_opus {
-            bot.send_voice(ChatId(chat_id), input_file.clone())
-                .duration(duration)
-                .await
+            let send = bot
+                .send_voice(ChatId(chat_id), input_file.clone())
+                .duration(duration);
+            match reply_to {
+                Some(id) => send.reply_parameters(group_replies::reply_parameters(id)).await,
+                None => send.await,
+            }
         } else {
-            bot.send_audio(ChatId(chat_id), input_file.clone())
-                .duration(duration)
-                .await
+            send_audio(input_file.clone()).await
         };
 
         // Fallback: if sending as voice failed, try as regular audio
@@ -147,9 +470,7 @@ impl TelegramHandler {
             Ok(r) => Ok(r),
             Err(e) => {
                 warn!(error = %e, "Primary send failed, attempting audio fallback");
-                bot.send_audio(ChatId(chat_id), input_file)
-                    .duration(duration)
-                    .await
+                send_audio(input_file).await
             }
         };
 
@@ -169,55 +490,259 @@ impl TelegramHandler {
         }
     }
 
+    /// Put a text reply in the placeholder (or send it fresh, replying to
+    /// `reply_to`), with action or follow-up buttons when suggested (actions
+    /// win when there are both)
+    async fn send_text_reply(
+        bot: &Bot,
+        chat_id: i64,
+        placeholder_id: Option<i32>,
+        reply_to: Option<MessageId>,
+        reply_text: &str,
+        actions: Option<(&ActionStore, Vec<String>)>,
+        followups: Option<(&FollowupStore, Vec<String>)>,
+        parse_mode: Option<ParseMode>,
+    ) {
+        let now = std::time::Instant::now();
+        let action_offer = actions
+            .filter(|_| !reply_text.is_empty())
+            .and_then(|(store, labels)| {
+                store
+                    .offer(chat_id, labels, now)
+                    .map(|(offer_id, markup)| (store, offer_id, markup))
+            });
+        let offer = followups
+            .filter(|_| !reply_text.is_empty() && action_offer.is_none())
+            .and_then(|(store, questions)| {
+                store
+                    .offer(chat_id, questions, now)
+                    .map(|(offer_id, markup)| (store, offer_id, markup))
+            });
+        let markup = action_offer
+            .as_ref()
+            .map(|(_, _, markup)| markup.clone())
+            .or_else(|| offer.as_ref().map(|(_, _, markup)| markup.clone()));
+        let sent = if let Some(pid) = placeholder_id {
+            let edited = edit_throttle::retry_rate_limited(|| {
+                formatting::edit_formatted(
+                    bot,
+                    ChatId(chat_id),
+                    MessageId(pid),
+                    reply_text,
+                    parse_mode,
+                    markup.clone(),
+                )
+            })
+            .await;
+            match edited {
+                Ok(message) => Ok(message.id.0),
+                Err(e) if edit_throttle::is_not_modified(&e) => Ok(pid),
+                // Don't lose the answer to a placeholder that can't be edited
+                Err(e) if !reply_text.is_empty() => {
+                    warn!(error = %e, "Final edit failed, sending the reply as a new message");
+                    formatting::send_formatted(
+                        bot,
+                        ChatId(chat_id),
+                        reply_text,
+                        parse_mode,
+                        markup,
+                        reply_to,
+                    )
+                    .await
+                    .map(|m| m.id.0)
+                }
+                Err(e) => Err(e),
+            }
+        } else if !reply_text.is_empty() {
+            formatting::send_formatted(
+                bot,
+                ChatId(chat_id),
+                reply_text,
+                parse_mode,
+                markup,
+                reply_to,
+            )
+            .await
+            .map(|m| m.id.0)
+        } else {
+            return;
+        };
+        if let Some((store, offer_id, _)) = action_offer {
+            match sent {
+                Ok(message_id) => store.attach(offer_id, message_id),
+                Err(_) => store.discard(offer_id),
+            }
+        } else if let Some((store, offer_id, _)) = offer {
+            match sent {
+                Ok(message_id) => store.attach(offer_id, message_id),
+                Err(_) => store.discard(offer_id),
+            }
+        }
+    }
+
+    /// Deliver a finished answer, streamed or polled
+    ///
+    /// Follow-up suggestions are split off, suggested actions parsed and the
+    /// reply extracted from the XML, then spoken (falling back to text) or put
+    /// in the placeholder.
+    async fn deliver_final(delivery: &FinalDelivery<'_>, assembled: &str) {
+        let (answer, suggested) = followups::split_followups(assembled);
+        let suggested_actions = actions::parse_actions(&answer);
+        let display_text = extract_final_text_from_xml(&answer);
+        let final_content = if display_text.is_empty() {
+            answer
+        } else {
+            display_text
+        };
+        let reply_text = render_reply(
+            delivery.response_template,
+            &final_content,
+            delivery.room_name,
+            delivery.character,
+        );
+
+        #[allow(unused_mut)]
+        let mut spoken = false;
+        if delivery.send_as_voice {
+            #[cfg(feature = "voice")]
+            {
+                if let Some(pid) = delivery.placeholder_id {
+                    let _ = delivery
+                        .bot
+                        .delete_message(ChatId(delivery.chat_id), MessageId(pid))
+                        .await;
+                }
+                let voice_manager = delivery.voice_manager;
+                match Self::send_voice_message(
+                    delivery.bot,
+                    delivery.chat_id,
+                    &final_content,
+                    voice_manager,
+                    voice_manager.config.telegram.include_text,
+                    delivery.reply_to,
+                )
+                .await
+                {
+                    Ok(()) => spoken = true,
+                    Err(e) => {
+                        warn!(error = %e, "Voice synthesis failed, sending as text");
+                        let _ = formatting::send_formatted(
+                            delivery.bot,
+                            ChatId(delivery.chat_id),
+                            &reply_text,
+                            delivery.parse_mode,
+                            None,
+                            delivery.reply_to,
+                        )
+                        .await;
+                    }
+                }
+            }
+        } else {
+            Self::send_text_reply(
+                delivery.bot,
+                delivery.chat_id,
+                delivery.placeholder_id,
+                delivery.reply_to,
+                &reply_text,
+                delivery.actions.map(|store| (store, suggested_actions)),
+                delivery.followups.map(|store| (store, suggested)),
+                delivery.parse_mode,
+            )
+            .await;
+        }
+        delivery
+            .telemetry
+            .finalized(final_content.chars().count(), spoken);
+    }
+
     async fn handle_message(&self, bot: Bot, msg: TelegramMessage) {
-        // Check for voice message first (if STT is enabled)
+        let sender = msg.from.as_ref().map(|u| u.id.0.to_string()).unwrap_or_default();
+        let telemetry = self.telemetry.message(&msg.chat.id.0.to_string(), &sender);
+
+        // Documents and photos go to the chat's knowledge; their caption follows as a message
+        if let Some(attachment) = Attachment::from_message(&msg) {
+            self.handle_attachment(bot, &msg, attachment, telemetry).await;
+            return;
+        }
+
+        // Check for speech (voice note, audio file, video note) first (if STT is enabled)
         #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
-        let is_voice_message = msg.voice().is_some();
-        #[cfg(not(any(feature = "voice-whisper", feature = "voice-unmute")))]
-        let is_voice_message = false;
+        let speech = SpeechSource::from_message(&msg);
 
-        // Get text from message OR transcribe voice message
+        // Get text from message OR transcribe speech
         let text: String;
         let from_voice: bool;
+        let speech_language: Option<SpeechLanguage>;
 
         #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
         {
-            if is_voice_message && self.voice_manager.can_transcribe() {
-                // Handle voice message - transcribe it
-                let voice = msg.voice().unwrap();
-                let file_id = &voice.file.id;
-                
-                match VoiceManager::download_voice_message(&bot, file_id).await {
+            if let Some((source, file_id)) =
+                speech.filter(|_| self.voice_manager.can_transcribe())
+            {
+                // Handle speech message - transcribe it, hinting the speaker's language
+                let chat_id = msg.chat.id.0;
+                let speaker = msg.from.as_ref();
+                let observed = speaker
+                    .and_then(|user| self.observed_languages.observed(chat_id, user.id.0));
+                let hint = speech_language::choose_hint(
+                    self.voice_manager.config.telegram.force_language.as_deref(),
+                    observed.as_deref(),
+                    speaker.and_then(|user| user.language_code.as_deref()),
+                );
+                let hint_language = hint.as_ref().map(|h| h.language.as_str());
+                match VoiceManager::download_voice_message(&bot, &file_id).await {
                     Ok(audio_data) => {
-                        match self.voice_manager.transcribe_voice_message(&audio_data).await {
-                            Ok(transcribed) => {
+                        match self
+                            .voice_manager
+                            .transcribe_voice_message(&audio_data, hint_language)
+                            .await
+                        {
+                            Ok(transcription) => {
+                                if let (Some(user), Some(detected)) =
+                                    (speaker, transcription.language.as_deref())
+                                {
+                                    self.observed_languages.record(chat_id, user.id.0, detected);
+                                }
+                                let transcribed = transcription.text;
                                 if transcribed.trim().is_empty() {
-                                    info!("Voice message transcribed but empty, ignoring");
+                                    info!(source = %source.label(), "Speech transcribed but empty, ignoring");
+                                    telemetry.filtered("empty_transcription");
                                     return;
                                 }
                                 info!(
+                                    source = %source.label(),
                                     text_len = %transcribed.len(),
-                                    "Voice message transcribed successfully"
+                                    "Speech message transcribed successfully"
                                 );
                                 text = transcribed;
-                                from_voice = true;
+                                from_voice = source.responds_with_voice();
+                                speech_language = Some(SpeechLanguage {
+                                    hint,
+                                    detected: transcription.language,
+                                });
                             }
                             Err(e) => {
-                                warn!(error = %e, "Failed to transcribe voice message");
+                                warn!(source = %source.label(), error = %e, "Failed to transcribe speech message");
+                                telemetry.failed("transcribe", "transcription");
                                 return;
                             }
                         }
                     }
                     Err(e) => {
-                        warn!(error = %e, "Failed to download voice message");
+                        warn!(source = %source.label(), error = %e, "Failed to download speech message");
+                        telemetry.failed("transcribe", "download");
                         return;
                     }
                 }
             } else if let Some(t) = msg.text() {
                 text = t.to_string();
                 from_voice = false;
+                speech_language = None;
             } else {
-                return; // Ignore non-text, non-voice messages
+                // Ignore non-text, non-speech messages
+                telemetry.filtered("unsupported_content");
+                return;
             }
         }
 
@@ -225,37 +750,506 @@ impl TelegramHandler {
         {
             text = match msg.text() {
                 Some(t) => t.to_string(),
-                None => return, // Ignore non-text messages
+                None => {
+                    // Ignore non-text messages
+                    telemetry.filtered("unsupported_content");
+                    return;
+                }
             };
             from_voice = false;
+            speech_language = None;
         }
 
         let from = match msg.from {
             Some(ref u) => u,
-            None => return,
+            None => {
+                telemetry.filtered("no_sender");
+                return;
+            }
         };
 
         // Don't respond to bots
         if from.is_bot {
+            telemetry.filtered("bot_sender");
             return;
         }
 
         if validate_input(&text, 4096).is_err() {
+            telemetry.filtered("invalid_input");
             return;
         }
 
-        // Capture minimal data needed for processing
-        let msg_id = msg.id.0;
-        let chat_id = msg.chat.id.0;
-        let user_id = from.id.0;
-        let is_private = msg.chat.is_private();
+        let turn = ChatTurn {
+            msg_id: msg.id.0,
+            chat_id: msg.chat.id.0,
+            user_id: from.id.0,
+            user_name: from.full_name(),
+            is_private: msg.chat.is_private(),
+            text,
+            from_voice,
+            speech_language,
+            reply_to: msg.reply_to_message().map(ReplyRef::from_message),
+            followup: false,
+        };
+        let group_mode = self.group_mode(turn.chat_id).await;
+        self.run_turn(bot, turn, group_mode, telemetry);
+    }
+
+    /// Add a document to the chat's knowledge and acknowledge it, then answer its caption
+    ///
+    /// In groups the caption has to address the bot, as a text message would.
+    async fn handle_attachment(
+        &self,
+        bot: Bot,
+        msg: &TelegramMessage,
+        attachment: Attachment,
+        telemetry: MessageTelemetry,
+    ) {
+        let Some(from) = msg.from.as_ref() else {
+            telemetry.filtered("no_sender");
+            return;
+        };
+        if from.is_bot {
+            telemetry.filtered("bot_sender");
+            return;
+        }
+        let turn = ChatTurn {
+            msg_id: msg.id.0,
+            chat_id: msg.chat.id.0,
+            user_id: from.id.0,
+            user_name: from.full_name(),
+            is_private: msg.chat.is_private(),
+            text: attachment.caption.clone().unwrap_or_default(),
+            from_voice: false,
+            speech_language: None,
+            reply_to: msg.reply_to_message().map(ReplyRef::from_message),
+            followup: false,
+        };
+        let group_mode = self.group_mode(turn.chat_id).await;
+        let enabled_for_everyone = group_mode == Some(GroupMode::Everyone);
+        let addressed_to_me = enabled_for_everyone
+            || turn.is_addressed(
+                self.bot_id,
+                self.bot_username.as_deref(),
+                self.allowed_chats
+                    .as_ref()
+                    .filter(|_| group_mode != Some(GroupMode::Quiet)),
+            );
+        if !addressed_to_me {
+            telemetry.filtered("unaddressed_attachment");
+            return;
+        }
+        if let Some(ref set) = self.allowed_chats {
+            if !set.contains(&turn.chat_id) && !enabled_for_everyone {
+                telemetry.filtered("chat_not_allowed");
+                return;
+            }
+        }
+        if !self.access.is_allowed(turn.user_id) {
+            if self.limiter.check(&format!("{}:{}", turn.chat_id, turn.user_id)) {
+                let first_message = match turn.text.trim() {
+                    "" => format!("[file: {}]", attachment.filename),
+                    caption => caption.to_string(),
+                };
+                self.access
+                    .report(&bot, turn.user_id, &turn.user_name, turn.chat_id, &first_message)
+                    .await;
+            }
+            telemetry.filtered("user_not_allowed");
+            return;
+        }
+
+        // Separate key so the caption's turn isn't taken for a duplicate
+        let dedup_key = format!("{}:{}:{}:file", turn.chat_id, turn.user_id, turn.msg_id);
+        match self
+            .state_store
+            .set_if_absent(DEDUP_NAMESPACE, &dedup_key, serde_json::Value::Bool(true), Some(DEDUP_TTL))
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                telemetry.filtered("duplicate");
+                return;
+            }
+            Err(e) => warn!(key = %dedup_key, error = %e, "Dedup check failed, handling attachment anyway"),
+        }
+        if !self.limiter.check(&format!("{}:{}", turn.chat_id, turn.user_id)) {
+            telemetry.filtered("rate_limited");
+            return;
+        }
+        telemetry.addressed();
+
+        let chat_id = turn.chat_id;
+        let _ = bot
+            .send_chat_action(ChatId(chat_id), teloxide::types::ChatAction::UploadDocument)
+            .await;
+        let api_base = std::env::var("AGENT_API_URL")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "http://127.0.0.1:9090/agent".to_string());
+        let entity_id = linking::entity_for_user(self.identity_links.as_deref(), turn.user_id).await;
+        let result = attachments::ingest(
+            &bot,
+            &api_base,
+            &attachment,
+            self.max_attachment_bytes,
+            workspace::room_id_for_chat(chat_id),
+            entity_id,
+        )
+        .await;
+        let reply = match result {
+            Ok(chunks) => {
+                info!(chat_id = %chat_id, file = %attachment.filename, chunks, "Attachment ingested");
+                attachments::acknowledgment(&attachment.filename, chunks)
+            }
+            Err(ref e) => {
+                warn!(chat_id = %chat_id, file = %attachment.filename, error = ?e, "Attachment not ingested");
+                telemetry.failed("ingest", e.class());
+                e.user_message()
+            }
+        };
+        if let Err(e) = bot.send_message(ChatId(chat_id), reply.clone()).await {
+            warn!(chat_id = %chat_id, error = %e, "Failed to acknowledge attachment");
+        }
+        if result.is_err() {
+            return;
+        }
+        if turn.text.trim().is_empty() {
+            telemetry.finalized(reply.chars().count(), false);
+            return;
+        }
+        self.run_turn(bot, turn, group_mode, telemetry);
+    }
+
+    /// Mode chosen from the welcome buttons for a chat, if onboarding is on
+    async fn group_mode(&self, chat_id: i64) -> Option<GroupMode> {
+        match self.onboarding {
+            Some(ref onboarding) => onboarding.group_mode(chat_id).await,
+            None => None,
+        }
+    }
+
+    /// Welcome a group when a `my_chat_member` update shows the bot joining or being promoted
+    async fn handle_member_update(&self, bot: Bot, update: ChatMemberUpdated) {
+        let Some(onboarding) = self.onboarding.clone() else {
+            return;
+        };
+        let chat_id = update.chat.id.0;
+        let action = onboarding
+            .observe(
+                chat_id,
+                update.chat.is_private(),
+                (&update.old_chat_member.kind).into(),
+                (&update.new_chat_member.kind).into(),
+            )
+            .await;
+        let onboarding::OnboardingAction::Send { retry } = action else {
+            return;
+        };
+
+        let bot_name = self.runtime.read().unwrap().character.name.clone();
+        let voice_triggers = if self.voice_manager.is_enabled() {
+            self.voice_manager.config.triggers.clone()
+        } else {
+            Vec::new()
+        };
+        let text = onboarding::welcome_text(
+            &bot_name,
+            self.bot_username.as_deref(),
+            &voice_triggers,
+            &self.commands.help_text(),
+        );
+        match bot
+            .send_message(ChatId(chat_id), text)
+            .reply_markup(onboarding::keyboard())
+            .await
+        {
+            Ok(_) => info!(chat_id = %chat_id, retry, "Posted group welcome message"),
+            Err(e) => {
+                warn!(chat_id = %chat_id, retry, error = %e, "Failed to post group welcome message");
+                onboarding.send_failed(chat_id, retry).await;
+            }
+        }
+    }
+
+    /// Apply a welcome button, which only admins of the chat (or configured admins) may press
+    async fn handle_onboarding_callback(&self, bot: Bot, query: &CallbackQuery, onboarding: &Onboarding) {
+        let (Some(data), Some(message)) = (query.data.as_deref(), query.message.as_ref()) else {
+            return;
+        };
+        let chat_id = message.chat().id.0;
+        let is_chat_admin = bot
+            .get_chat_member(ChatId(chat_id), UserId(query.from.id.0))
+            .await
+            .map(|member| member.kind.is_privileged())
+            .unwrap_or(false);
+        match onboarding
+            .handle_callback(data, chat_id, query.from.id.0, is_chat_admin)
+            .await
+        {
+            onboarding::OnboardingTap::NotOnboarding => {}
+            onboarding::OnboardingTap::Denied => {
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text("Only group admins can change this.")
+                    .await;
+            }
+            onboarding::OnboardingTap::Applied(mode) => {
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text(mode.confirmation())
+                    .await;
+                offers::clear_keyboard(&bot, chat_id, message.id().0).await;
+            }
+        }
+    }
+
+    /// Approve or deny an access request and tell the user
+    async fn handle_access_callback(&self, bot: Bot, query: &CallbackQuery) {
+        let (Some(data), Some(message)) = (query.data.as_deref(), query.message.as_ref()) else {
+            return;
+        };
+        let chat_id = message.chat().id.0;
+        let request = match self.access.handle_callback(data, chat_id, query.from.id.0).await {
+            access::AccessTap::NotAccess => return,
+            access::AccessTap::Unauthorized => {
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text("Only admins can decide access requests.")
+                    .await;
+                return;
+            }
+            access::AccessTap::Unknown => {
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text("This request no longer exists.")
+                    .await;
+                offers::clear_keyboard(&bot, chat_id, message.id().0).await;
+                return;
+            }
+            access::AccessTap::AlreadyDecided(request) => {
+                let state = match request.status {
+                    AccessStatus::Approved => "approved",
+                    AccessStatus::Denied => "denied",
+                    _ => "handled",
+                };
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text(format!("Already {}.", state))
+                    .await;
+                offers::clear_keyboard(&bot, chat_id, message.id().0).await;
+                return;
+            }
+            access::AccessTap::Decided(request) => request,
+        };
+        let verdict = match request.status {
+            AccessStatus::Approved => "Approved",
+            _ => "Denied",
+        };
+        let _ = bot
+            .answer_callback_query(query.id.clone())
+            .text(format!("{} {}", verdict, request.name))
+            .await;
+        // Record the decision on the request; editing the text also drops the buttons
+        let decided = format!(
+            "{}\n\n{} by {}",
+            access::admin_notice(&request),
+            verdict,
+            query.from.full_name()
+        );
+        if bot
+            .edit_message_text(ChatId(chat_id), message.id(), decided)
+            .await
+            .is_err()
+        {
+            offers::clear_keyboard(&bot, chat_id, message.id().0).await;
+        }
+        let text = self.access.decision_message(&request).to_string();
+        if let Err(e) = bot.send_message(ChatId(request.chat_id), text).await {
+            warn!(user_id = %request.user_id, error = %e, "Failed to tell user about access decision");
+        }
+    }
+
+    /// Answer an inline button
+    ///
+    /// Access buttons approve or deny a request; welcome buttons set the group
+    /// mode; an action button marks the action as chosen and runs it as the
+    /// tapping user's turn; a follow-up button posts the question for the
+    /// tapping user and runs it as their turn.
+    async fn handle_callback(&self, bot: Bot, query: CallbackQuery) {
+        if query.data.as_deref().and_then(access::parse_callback).is_some() {
+            self.handle_access_callback(bot, &query).await;
+            return;
+        }
+        if let Some(onboarding) = self.onboarding.clone() {
+            if query.data.as_deref().and_then(onboarding::parse_callback).is_some() {
+                self.handle_onboarding_callback(bot, &query, &onboarding).await;
+                return;
+            }
+        }
+        if let Some(store) = self.actions.clone() {
+            if self.handle_action_callback(&bot, &query, &store).await {
+                return;
+            }
+        }
+        let Some(store) = self.followups.clone() else {
+            return;
+        };
+        let (Some(data), Some(message)) = (query.data.as_deref(), query.message.as_ref()) else {
+            return;
+        };
+        let chat_id = message.chat().id.0;
+        let keyboard_id = message.id().0;
+        let question = match store.tap(data, chat_id, keyboard_id, std::time::Instant::now()) {
+            FollowupTap::NotFollowup => return,
+            FollowupTap::Expired => {
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text("This suggestion has expired.")
+                    .await;
+                offers::clear_keyboard(&bot, chat_id, keyboard_id).await;
+                return;
+            }
+            FollowupTap::Chosen(question) => question,
+        };
+        let _ = bot.answer_callback_query(query.id.clone()).await;
+        offers::clear_keyboard(&bot, chat_id, keyboard_id).await;
+
+        // Show the question in the chat, attributed to whoever tapped it
+        let echo_id = match bot
+            .send_message(
+                ChatId(chat_id),
+                format!("{}: {}", query.from.full_name(), question),
+            )
+            .await
+        {
+            Ok(m) => m.id.0,
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Failed to post follow-up question");
+                keyboard_id
+            }
+        };
+        let telemetry = self
+            .telemetry
+            .message(&chat_id.to_string(), &query.from.id.0.to_string());
+        let turn = ChatTurn::followup(
+            chat_id,
+            query.from.id.0,
+            query.from.full_name(),
+            message.chat().is_private(),
+            echo_id,
+            question,
+        );
+        let group_mode = self.group_mode(chat_id).await;
+        self.run_turn(bot, turn, group_mode, telemetry);
+    }
+
+    /// Answer an action button; `false` if the callback is not from one
+    async fn handle_action_callback(&self, bot: &Bot, query: &CallbackQuery, store: &ActionStore) -> bool {
+        let (Some(data), Some(message)) = (query.data.as_deref(), query.message.as_ref()) else {
+            return false;
+        };
+        let chat_id = message.chat().id.0;
+        let keyboard_id = message.id().0;
+        let label = match store.tap(data, chat_id, keyboard_id, std::time::Instant::now()) {
+            ActionTap::NotAction => return false,
+            ActionTap::AlreadyChosen => {
+                let _ = bot.answer_callback_query(query.id.clone()).await;
+                return true;
+            }
+            ActionTap::Expired => {
+                let _ = bot
+                    .answer_callback_query(query.id.clone())
+                    .text("This action has expired.")
+                    .await;
+                offers::clear_keyboard(bot, chat_id, keyboard_id).await;
+                return true;
+            }
+            ActionTap::Chosen(label) => label,
+        };
+        let _ = bot.answer_callback_query(query.id.clone()).await;
+        actions::mark_chosen(bot, chat_id, keyboard_id, &label).await;
+
+        // Same room and entity as a typed message from the tapping user
+        let telemetry = self
+            .telemetry
+            .message(&chat_id.to_string(), &query.from.id.0.to_string());
+        let turn = ChatTurn::followup(
+            chat_id,
+            query.from.id.0,
+            query.from.full_name(),
+            message.chat().is_private(),
+            keyboard_id,
+            label,
+        );
+        let group_mode = self.group_mode(chat_id).await;
+        self.run_turn(bot.clone(), turn, group_mode, telemetry);
+        true
+    }
+
+    /// Answer one turn on a worker thread: commands, filters, quota, then the streamed reply
+    ///
+    /// `group_mode` overrides `allowed_chats`: `Everyone` answers (and admits)
+    /// every message in the chat, `Quiet` only mentions and replies.
+    /// `telemetry` follows the turn to its answer or the filter that stopped it.
+    fn run_turn(
+        &self,
+        bot: Bot,
+        turn: ChatTurn,
+        group_mode: Option<GroupMode>,
+        telemetry: MessageTelemetry,
+    ) {
+        let enabled_for_everyone = group_mode == Some(GroupMode::Everyone);
+        let addressed_to_me = enabled_for_everyone
+            || turn.is_addressed(
+                self.bot_id,
+                self.bot_username.as_deref(),
+                self.allowed_chats
+                    .as_ref()
+                    .filter(|_| group_mode != Some(GroupMode::Quiet)),
+            );
+        let ChatTurn {
+            msg_id,
+            chat_id,
+            user_id,
+            user_name,
+            is_private,
+            text,
+            from_voice,
+            speech_language,
+            reply_to,
+            ..
+        } = turn;
+        // In groups the answer replies to the question, which may quote another message
+        let reply_target = group_replies::reply_target(is_private, msg_id);
+        let reply_context = reply_to.as_ref().and_then(|reply| {
+            group_replies::reply_context(
+                reply.message_id,
+                reply.text.as_deref(),
+                reply.from_id == Some(self.bot_id),
+            )
+        });
 
         let runtime = self.runtime.clone();
         let limiter = self.limiter.clone();
+        let state_store = self.state_store.clone();
         let allowed_chats = self.allowed_chats.clone();
-        let allowed_users = self.allowed_users.clone();
+        let access = self.access.clone();
+        let tier_manager = self.tier_manager.clone();
+        let context_overflow = self.context_overflow.clone();
+        let response_template = self.response_template.clone();
+        let near_miss_ack = self.near_miss.clone();
+        let followup_store = self.followups.clone();
+        let action_store = self.actions.clone();
+        let abuse_guard = self.abuse_guard.clone();
+        let digests = self.digests.clone();
+        let admin_users = self.admin_users.clone();
+        let commands = self.commands.clone();
+        let identity_links = self.identity_links.clone();
         let bot_username = self.bot_username.clone();
-        let bot_id = self.bot_id;
+        let chat_modes = self.chat_modes.clone();
+        let poll_config = self.poll_config.clone();
+        let parse_mode = self.parse_mode;
         #[allow(unused_variables)]
         let voice_manager = self.voice_manager.clone();
         #[allow(unused_variables)]
@@ -272,90 +1266,176 @@ impl TelegramHandler {
                     .unwrap();
 
                 rt.block_on(async move {
-                    let trimmed = text.trim();
-                    if trimmed.starts_with('/') {
-                        if trimmed == "/start" {
-                            let _ = bot
-                                .send_message(ChatId(chat_id), "Hi! I’m Zoey. Ask me anything.")
-                                .await;
-                            return;
+                    // Flood protection for messages meant for us, commands included
+                    let counted = addressed_to_me || is_private || text.trim_start().starts_with('/');
+                    if let Some(guard) = abuse_guard.as_ref().filter(|_| counted) {
+                        match guard.check(user_id, &text, std::time::Instant::now()) {
+                            Verdict::Allow => {}
+                            Verdict::Ignore => {
+                                telemetry.filtered("flood");
+                                return;
+                            }
+                            Verdict::Muted(mute) => {
+                                warn!(
+                                    target: "zoey::abuse",
+                                    chat_id = %chat_id,
+                                    user_id = %user_id,
+                                    signal = ?mute.signal,
+                                    offense = mute.offense,
+                                    mute_secs = mute.duration.as_secs(),
+                                    "Telegram user muted for flooding"
+                                );
+                                let _ = bot.send_message(ChatId(chat_id), mute.user_notice()).await;
+                                let notice = mute.admin_notice(chat_id);
+                                for admin in &admin_users {
+                                    if let Err(e) = bot.send_message(ChatId(*admin as i64), notice.clone()).await {
+                                        warn!(admin = %admin, error = %e, "Failed to notify admin of abuse mute");
+                                    }
+                                }
+                                telemetry.filtered("muted");
+                                return;
+                            }
                         }
                     }
 
-                    // Voice command: request spoken AI response rather than reading user text
+                    // Registered commands run before the agent; some rewrite the query instead
                     let mut force_voice = false;
                     let mut user_query_text = text.clone();
-                    #[cfg(feature = "voice")]
-                    if voice_manager.is_enabled() {
-                        if voice_manager.config.is_voice_command(&text) {
-                            if let Some(q) = voice_manager.config.extract_voice_text(&text) {
-                                user_query_text = q;
-                                force_voice = true;
-                            } else {
-                                let _ = bot
-                                    .send_message(
-                                        ChatId(chat_id),
-                                        "Usage: /voice <question>\nExample: /voice What can you do?",
-                                    )
-                                    .await;
-                                return;
-                            }
+                    match commands
+                        .dispatch(bot.clone(), bot_username.as_deref(), chat_id, user_id, is_private, &text)
+                        .await
+                    {
+                        Some(CommandOutcome::Handled) => {
+                            telemetry.filtered("command");
+                            return;
+                        }
+                        Some(CommandOutcome::Ask { query, voice }) => {
+                            user_query_text = query;
+                            force_voice = voice;
                         }
+                        None => {}
+                    }
+                    if let Some(username) = bot_username.as_deref().filter(|_| !is_private) {
+                        user_query_text = group_replies::strip_bot_mention(&user_query_text, username);
                     }
 
-                    // Dedup check
-                    static ACTIVE: OnceLock<Arc<RwLock<HashSet<String>>>> = OnceLock::new();
-                    let active_store = ACTIVE
-                        .get_or_init(|| Arc::new(RwLock::new(HashSet::new())))
-                        .clone();
-                    let active_key = format!("{}:{}:{}", chat_id, user_id, msg_id);
+                    // Dedup check, shared with other processes through the state store
+                    let dedup_key = format!("{}:{}:{}", chat_id, user_id, msg_id);
+                    match state_store
+                        .set_if_absent(DEDUP_NAMESPACE, &dedup_key, serde_json::Value::Bool(true), Some(DEDUP_TTL))
+                        .await
                     {
-                        let mut guard = active_store.write().unwrap();
-                        if guard.contains(&active_key) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            telemetry.filtered("duplicate");
                             return;
                         }
-                        guard.insert(active_key.clone());
+                        Err(e) => warn!(key = %dedup_key, error = %e, "Dedup check failed, handling message anyway"),
                     }
 
                     // Rate limiting
                     let key = format!("{}:{}", chat_id, user_id);
                     if !limiter.check(&key) {
+                        telemetry.filtered("rate_limited");
                         return;
                     }
 
                     // Chat/user filters
                     if let Some(ref set) = allowed_chats {
-                        if !set.contains(&chat_id) {
+                        if !set.contains(&chat_id) && !enabled_for_everyone {
+                            telemetry.filtered("chat_not_allowed");
                             return;
                         }
                     }
-                    if let Some(ref set) = allowed_users {
-                        if !set.contains(&user_id) {
+                    if !access.is_allowed(user_id) {
+                        if addressed_to_me || is_private {
+                            access.report(&bot, user_id, &user_name, chat_id, &text).await;
+                        }
+                        telemetry.filtered("user_not_allowed");
+                        return;
+                    }
+
+                    // Let the author know a message that almost addressed us was seen
+                    if let Some(ack) = near_miss_ack.as_ref().filter(|_| !addressed_to_me) {
+                        let bot_name = runtime.read().unwrap().character.name.clone();
+                        let unaddressed = near_miss::UnaddressedMessage {
+                            chat_id,
+                            user_id,
+                            is_private,
+                            text: &text,
+                            reply_to: reply_to.as_ref().map(|reply| reply.message_id),
+                        };
+                        if ack.should_ack(unaddressed, &bot_name, std::time::Instant::now()).await {
+                            let reaction = ReactionType::Emoji {
+                                emoji: ack.emoji().to_string(),
+                            };
+                            if let Err(e) = bot
+                                .set_message_reaction(ChatId(chat_id), MessageId(msg_id))
+                                .reaction(vec![reaction])
+                                .await
+                            {
+                                warn!(chat_id = %chat_id, error = %e, "Failed to add seen reaction");
+                            }
+                        }
+                    }
+
+                    // Digest mode: queue group questions for the next digest unless urgent
+                    if let Some(digests) = digests.as_ref().filter(|_| addressed_to_me && !is_private) {
+                        if digests.is_enabled(chat_id).await && !digests.bypasses(user_id, &user_query_text) {
+                            let reaction = ReactionType::Emoji {
+                                emoji: digests.reaction().to_string(),
+                            };
+                            if let Err(e) = bot
+                                .set_message_reaction(ChatId(chat_id), MessageId(msg_id))
+                                .reaction(vec![reaction])
+                                .await
+                            {
+                                warn!(chat_id = %chat_id, error = %e, "Failed to acknowledge digest question");
+                            }
+                            let question = PendingQuestion {
+                                message_id: msg_id,
+                                user_id,
+                                name: user_name,
+                                text: user_query_text,
+                                asked_at: chrono::Utc::now().timestamp(),
+                            };
+                            if let Queued::Full(questions) = digests.queue(chat_id, question).await {
+                                digests.flush(&bot, chat_id, questions).await;
+                            }
+                            telemetry.filtered("digest_queued");
                             return;
                         }
                     }
 
-                    // Check if bot is mentioned or addressed
-                    let mentioned = if let Some(ref username) = bot_username {
-                        text.contains(&format!("@{}", username))
+                    // Daily quota, only spent on messages we are going to answer
+                    let tier = if !addressed_to_me {
+                        tier_manager.tier_for(user_id)
                     } else {
-                        false
+                        match tier_manager
+                            .check_and_consume(user_id, chrono::Utc::now())
+                            .await
+                        {
+                            Ok(QuotaDecision::Allowed { tier, .. }) => tier,
+                            Ok(QuotaDecision::Exceeded { tier, resets_at }) => {
+                                info!(user_id = %user_id, tier = %tier.name, "Telegram quota exceeded");
+                                let notice = tiers::quota_exceeded_message(
+                                    &tier,
+                                    resets_at,
+                                    chrono::Utc::now(),
+                                );
+                                let _ = bot.send_message(ChatId(chat_id), notice).await;
+                                telemetry.filtered("quota_exceeded");
+                                return;
+                            }
+                            Err(e) => {
+                                warn!(user_id = %user_id, error = %e, "Quota check failed, allowing message");
+                                tier_manager.tier_for(user_id)
+                            }
+                        }
                     };
-
-                    // Check if this is a reply to the bot
-                    let is_reply_to_bot = msg
-                        .reply_to_message()
-                        .and_then(|reply| reply.from.as_ref())
-                        .map(|from| from.id.0 == bot_id)
-                        .unwrap_or(false);
-
-                    let in_allowed_chat = allowed_chats
-                        .as_ref()
-                        .map(|set| set.contains(&chat_id))
-                        .unwrap_or(false);
-
-                    let addressed_to_me =
-                        is_private || mentioned || is_reply_to_bot || in_allowed_chat;
+                    if addressed_to_me {
+                        telemetry.addressed();
+                    }
 
                     // Get agent info
                     let (agent_id, world_id, char_name) = {
@@ -378,8 +1458,7 @@ impl TelegramHandler {
 
                     // Build room and memory
                     // Use deterministic room ID based on chat for consistent conversation history
-                    let room_id =
-                        zoey_core::string_to_uuid(&format!("telegram-room-{}", chat_id));
+                    let room_id = workspace::room_id_for_chat(chat_id);
                     let room = Room {
                         id: room_id,
                         agent_id: Some(agent_id),
@@ -397,9 +1476,9 @@ impl TelegramHandler {
                         created_at: Some(chrono::Utc::now().timestamp()),
                     };
 
-                    // Deterministic entity ID based on Telegram user ID for consistent message attribution
+                    // Deterministic entity ID based on Telegram user ID, or the linked web entity
                     let entity_id =
-                        zoey_core::string_to_uuid(&format!("telegram-user-{}", user_id));
+                        linking::entity_for_user(identity_links.as_deref(), user_id).await;
 
                     let mut content = Content {
                         text: user_query_text.clone(),
@@ -415,6 +1494,11 @@ impl TelegramHandler {
                         "addressed_to_me".to_string(),
                         serde_json::Value::Bool(addressed_to_me),
                     );
+                    if let Some(ref context) = reply_context {
+                        content
+                            .metadata
+                            .insert(group_replies::REPLY_CONTEXT_KEY.to_string(), context.clone());
+                    }
 
                     let memory = Memory {
                         id: uuid::Uuid::new_v4(),
@@ -431,8 +1515,17 @@ impl TelegramHandler {
 
                     // Send placeholder message
                     let placeholder_id: Option<i32> = if addressed_to_me || is_private {
-                        match bot.send_message(ChatId(chat_id), "...").await {
-                            Ok(m) => Some(m.id.0),
+                        let mut send = bot.send_message(ChatId(chat_id), "...");
+                        if let Some(message_id) = reply_target {
+                            send = send.reply_parameters(group_replies::reply_parameters(message_id));
+                        }
+                        match send.await {
+                            Ok(m) => {
+                                if let Some(ref ack) = near_miss_ack {
+                                    ack.record_bot_message(chat_id, m.id.0);
+                                }
+                                Some(m.id.0)
+                            }
                             Err(_) => None,
                         }
                     } else {
@@ -456,322 +1549,490 @@ impl TelegramHandler {
                         })
                         .clone();
 
-                    // Memory persistence is handled by Agent API's /chat/stream endpoint
+                    // Memory persistence is handled by the Agent API's chat endpoints
                     let _ = &runtime; // Keep runtime in scope
-                    let body = serde_json::json!({
-                        "text": user_query_text.clone(),
-                        "roomId": room.id,
-                        "entityId": memory.entity_id,
-                        "stream": true
-                    });
-                    let resp = tokio::time::timeout(
-                        std::time::Duration::from_secs(
-                            std::env::var("TELEGRAM_STREAM_REQUEST_TIMEOUT_SECS")
-                                .ok()
-                                .and_then(|s| s.parse::<u64>().ok())
-                                .unwrap_or(20),
-                        ),
-                        client
-                            .post(format!("{}/chat/stream", api_base))
-                            .header("accept", "text/event-stream")
-                            .json(&body)
-                            .send(),
-                    )
-                    .await;
-                    match resp {
-                        Ok(Ok(mut r)) => {
-                            let mut buffer = String::new();
-                            let mut assembled = String::new();
-                            let mut last_edit = std::time::Instant::now();
-                            let edit_interval = std::time::Duration::from_millis(
-                                std::env::var("TELEGRAM_EDIT_INTERVAL_MS")
-                                    .ok()
-                                    .and_then(|s| s.parse::<u64>().ok())
-                                    .unwrap_or(500), // Telegram has stricter rate limits, use longer interval
-                            );
-                            let mut replaced_ack = false;
-                            let mut finalized = false;
-                            let inactivity_ms = std::env::var("TELEGRAM_STREAM_INACTIVITY_MS")
-                                .ok()
-                                .and_then(|s| s.parse::<u64>().ok())
-                                .unwrap_or(2000);
-                            let inactivity_limit = std::time::Duration::from_millis(inactivity_ms);
-                            #[allow(unused_assignments)]
-                            let mut last_chunk_at = std::time::Instant::now();
-                            while let Ok(opt) = r.chunk().await {
-                                last_chunk_at = std::time::Instant::now();
-                                let chunk = match opt {
-                                    Some(c) => c,
-                                    None => break,
-                                };
-                                let s = String::from_utf8_lossy(&chunk);
-                                buffer.push_str(&s);
-                                let mut parts: Vec<&str> = buffer.split('\n').collect();
-                                let tail = parts.pop().unwrap_or("");
-                                for line in parts {
-                                    let l = line.trim();
-                                    if !l.starts_with("data:") {
-                                        continue;
+                    let mut base_metadata = tiers::request_metadata(&tier);
+                    if followup_store.is_some() {
+                        base_metadata["suggest_followups"] = serde_json::Value::Bool(true);
+                    }
+                    if let Some(ref speech) = speech_language {
+                        speech.annotate(&mut base_metadata);
+                    }
+                    if let Some(context) = reply_context {
+                        base_metadata[group_replies::REPLY_CONTEXT_KEY] = context;
+                    }
+
+                    // Check if we should send as voice message
+                    #[cfg(feature = "voice")]
+                    let send_as_voice = voice_manager.is_enabled()
+                        && (force_voice
+                            || voice_manager.config.telegram.auto_voice
+                            || voice_manager.config.is_voice_trigger(&user_query_text));
+
+                    #[cfg(not(feature = "voice"))]
+                    let send_as_voice = false;
+
+                    let delivery = FinalDelivery {
+                        bot: &bot,
+                        chat_id,
+                        placeholder_id,
+                        reply_to: reply_target,
+                        response_template: response_template.as_deref(),
+                        room_name: &room.name,
+                        character: &char_name,
+                        followups: followup_store.as_deref(),
+                        actions: action_store.as_deref(),
+                        send_as_voice,
+                        telemetry: &telemetry,
+                        parse_mode,
+                        #[cfg(feature = "voice")]
+                        voice_manager: &voice_manager,
+                    };
+
+                    let mut metadata = base_metadata.clone();
+                    let mut attempt: u32 = 0;
+                    loop {
+                        let body = serde_json::json!({
+                            "text": user_query_text.clone(),
+                            "roomId": room.id,
+                            "entityId": memory.entity_id,
+                            "stream": true,
+                            "metadata": metadata
+                        });
+                        let mut context_error = false;
+                        telemetry.stream_started();
+                        if chat_modes.mode(&api_base) == ChatMode::Polling {
+                            // Backends without SSE: submit a task and poll it
+                            let bot_ref = &bot;
+                            let outcome = polling::chat_via_task(
+                                &client,
+                                &api_base,
+                                &body,
+                                &poll_config,
+                                move || async move {
+                                    if let Some(pid) = placeholder_id {
+                                        let _ = bot_ref
+                                            .edit_message_text(
+                                                ChatId(chat_id),
+                                                MessageId(pid),
+                                                polling::STILL_THINKING_MESSAGE,
+                                            )
+                                            .await;
+                                    }
+                                },
+                            )
+                            .await;
+                            match outcome {
+                                PollOutcome::Completed(assembled) => {
+                                    Self::deliver_final(&delivery, &assembled).await;
+                                }
+                                PollOutcome::Failed(e) if context_overflow.is_context_error(None, &e) => {
+                                    context_error = true;
+                                }
+                                outcome => {
+                                    error!(outcome = ?outcome, "Chat task did not complete");
+                                    let error_class = match outcome {
+                                        PollOutcome::Expired => "deadline",
+                                        _ => "task_failed",
+                                    };
+                                    telemetry.failed("poll", error_class);
+                                    let text = outcome.failure_text().unwrap_or("Error");
+                                    if let Some(pid) = placeholder_id {
+                                        let _ = bot
+                                            .edit_message_text(ChatId(chat_id), MessageId(pid), text)
+                                            .await;
+                                    } else {
+                                        let _ = bot.send_message(ChatId(chat_id), text).await;
                                     }
-                                    let payload = l.trim_start_matches("data:").trim();
-                                    if payload.is_empty() {
+                                }
+                            }
+                        } else {
+                            let resp = tokio::time::timeout(
+                                std::time::Duration::from_secs(
+                                    std::env::var("TELEGRAM_STREAM_REQUEST_TIMEOUT_SECS")
+                                        .ok()
+                                        .and_then(|s| s.parse::<u64>().ok())
+                                        .unwrap_or(20),
+                                ),
+                                client
+                                    .post(format!("{}/chat/stream", api_base))
+                                    .header("accept", "text/event-stream")
+                                    .json(&body)
+                                    .send(),
+                            )
+                            .await;
+                            match resp {
+                                // Oversized requests surface as an HTTP error before any SSE
+                                Ok(Ok(r)) if !r.status().is_success() => {
+                                    let status = r.status().as_u16();
+                                    if chat_modes.observe_stream_status(&api_base, status) {
+                                        // No stream endpoint: ask again by polling (remembered per backend)
                                         continue;
                                     }
-                                    if let Ok(json) =
-                                        serde_json::from_str::<serde_json::Value>(payload)
-                                    {
-                                        if json.get("error").is_some() {
-                                            continue;
-                                        }
-                                        let is_final = json
-                                            .get("final")
-                                            .and_then(|v| v.as_bool())
-                                            .unwrap_or(false);
-                                        let text =
-                                            json.get("text").and_then(|v| v.as_str()).unwrap_or("");
-                                        if !text.is_empty() {
-                                            assembled.push_str(text);
+                                    let text = r.text().await.unwrap_or_default();
+                                    if context_overflow.is_context_error(Some(status), &text) {
+                                        context_error = true;
+                                    } else {
+                                        error!(status, error = %text, "Streaming request rejected");
+                                        telemetry.failed("stream", &format!("http_{}", status));
+                                        if let Some(pid) = placeholder_id {
+                                            let _ = bot
+                                                .edit_message_text(
+                                                    ChatId(chat_id),
+                                                    MessageId(pid),
+                                                    "Error",
+                                                )
+                                                .await;
                                         }
-                                        let now = std::time::Instant::now();
-                                        if now.duration_since(last_edit) >= edit_interval {
-                                            if let Some(pid) = placeholder_id {
-                                                if !replaced_ack && !assembled.is_empty() {
-                                                    replaced_ack = true;
+                                    }
+                                }
+                                Ok(Ok(mut r)) => {
+                                    let mut buffer = String::new();
+                                    let mut assembled = String::new();
+                                    let edit_interval = std::time::Duration::from_millis(
+                                        std::env::var("TELEGRAM_EDIT_INTERVAL_MS")
+                                            .ok()
+                                            .and_then(|s| s.parse::<u64>().ok())
+                                            .unwrap_or(500), // Telegram has stricter rate limits, use longer interval
+                                    );
+                                    // Backs off on 429s and gives up on edits that keep failing
+                                    let mut throttle =
+                                        EditThrottle::new(edit_interval, std::time::Instant::now());
+                                    let mut replaced_ack = false;
+                                    let mut finalized = false;
+                                    let inactivity_ms = std::env::var("TELEGRAM_STREAM_INACTIVITY_MS")
+                                        .ok()
+                                        .and_then(|s| s.parse::<u64>().ok())
+                                        .unwrap_or(2000);
+                                    let inactivity_limit = std::time::Duration::from_millis(inactivity_ms);
+                                    #[allow(unused_assignments)]
+                                    let mut last_chunk_at = std::time::Instant::now();
+                                    while let Ok(opt) = r.chunk().await {
+                                        last_chunk_at = std::time::Instant::now();
+                                        let chunk = match opt {
+                                            Some(c) => c,
+                                            None => break,
+                                        };
+                                        let s = String::from_utf8_lossy(&chunk);
+                                        buffer.push_str(&s);
+                                        let mut parts: Vec<&str> = buffer.split('\n').collect();
+                                        let tail = parts.pop().unwrap_or("");
+                                        for line in parts {
+                                            let l = line.trim();
+                                            if !l.starts_with("data:") {
+                                                continue;
+                                            }
+                                            let payload = l.trim_start_matches("data:").trim();
+                                            if payload.is_empty() {
+                                                continue;
+                                            }
+                                            if let Ok(json) =
+                                                serde_json::from_str::<serde_json::Value>(payload)
+                                            {
+                                                if json.get("error").is_some() {
+                                                    if assembled.is_empty()
+                                                        && context_overflow.is_context_error_payload(&json)
+                                                    {
+                                                        context_error = true;
+                                                        break;
+                                                    }
+                                                    continue;
                                                 }
-                                                // Extract text content from XML format for display
-                                                let display_text =
-                                                    extract_final_text_from_xml(&assembled);
-                                                if !display_text.is_empty() {
-                                                    let _ = bot
-                                                        .edit_message_text(
-                                                            ChatId(chat_id),
-                                                            MessageId(pid),
-                                                            &display_text,
-                                                        )
-                                                        .await;
+                                                let is_final = json
+                                                    .get("final")
+                                                    .and_then(|v| v.as_bool())
+                                                    .unwrap_or(false);
+                                                let text =
+                                                    json.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                                                if !text.is_empty() {
+                                                    assembled.push_str(text);
+                                                    telemetry.first_chunk();
                                                 }
-                                            }
-                                            last_edit = now;
-                                        }
-                                        if is_final && !finalized {
-                                            finalized = true;
-                                            // Extract text content from XML format for final display
-                                            let display_text =
-                                                extract_final_text_from_xml(&assembled);
-                                            let final_content = if display_text.is_empty() {
-                                                assembled.clone()
-                                            } else {
-                                                display_text.clone()
-                                            };
-
-                                            // Check if we should send as voice message
-                                            #[cfg(feature = "voice")]
-                                            let send_as_voice = voice_manager.is_enabled()
-                                                && (force_voice
-                                                    || voice_manager.config.telegram.auto_voice
-                                                    || voice_manager.config.is_voice_trigger(&user_query_text));
-
-                                            #[cfg(not(feature = "voice"))]
-                                            let send_as_voice = false;
-
-                                            if send_as_voice {
-                                                #[cfg(feature = "voice")]
-                                                {
-                                                    // Delete placeholder if exists
+                                                let now = std::time::Instant::now();
+                                                if throttle.should_edit(now) {
                                                     if let Some(pid) = placeholder_id {
-                                                        let _ = bot
-                                                            .delete_message(ChatId(chat_id), MessageId(pid))
+                                                        if !replaced_ack && !assembled.is_empty() {
+                                                            replaced_ack = true;
+                                                        }
+                                                        // Extract text content from XML format for display
+                                                        let display_text = extract_final_text_from_xml(
+                                                            &followups::split_followups(&assembled).0,
+                                                        );
+                                                        if !display_text.is_empty() {
+                                                            let edited = formatting::edit_formatted(
+                                                                &bot,
+                                                                ChatId(chat_id),
+                                                                MessageId(pid),
+                                                                &display_text,
+                                                                parse_mode,
+                                                                None,
+                                                            )
                                                             .await;
-                                                    }
-                                                    // Send as voice message
-                                                    match Self::send_voice_message(
-                                                        &bot,
-                                                        chat_id,
-                                                        &final_content,
-                                                        &voice_manager,
-                                                        voice_manager.config.telegram.include_text,
-                                                    )
-                                                    .await
-                                                    {
-                                                        Ok(_) => {}
-                                                        Err(e) => {
-                                                            warn!(error = %e, "Voice synthesis failed, sending as text");
-                                                            let _ = bot
-                                                                .send_message(ChatId(chat_id), &final_content)
-                                                                .await;
+                                                            throttle.record(
+                                                                EditOutcome::of(&edited),
+                                                                std::time::Instant::now(),
+                                                            );
                                                         }
                                                     }
                                                 }
-                                            } else {
-                                                // Send final text message to Telegram
-                                                if let Some(pid) = placeholder_id {
-                                                    let _ = bot
-                                                        .edit_message_text(
-                                                            ChatId(chat_id),
-                                                            MessageId(pid),
-                                                            &final_content,
-                                                        )
-                                                        .await;
-                                                } else if !final_content.is_empty() {
-                                                    let _ = bot
-                                                        .send_message(ChatId(chat_id), &final_content)
-                                                        .await;
+                                                if is_final && !finalized {
+                                                    finalized = true;
+                                                    throttle.cool_down().await;
+                                                    Self::deliver_final(&delivery, &assembled).await;
+                                                    break;
                                                 }
                                             }
-                                            break;
                                         }
-                                    }
-                                }
-                                buffer = tail.to_string();
-                                // Inactivity watchdog: finalize if no chunks for configured period
-                                if !finalized && last_chunk_at.elapsed() >= inactivity_limit {
-                                    finalized = true;
-                                    // Extract text content from XML format
-                                    let display_text = extract_final_text_from_xml(&assembled);
-                                    let final_content = if display_text.is_empty() {
-                                        assembled.clone()
-                                    } else {
-                                        display_text.clone()
-                                    };
-
-                                    // Check if we should send as voice message
-                                    #[cfg(feature = "voice")]
-                                    let send_as_voice = voice_manager.is_enabled()
-                                        && (force_voice
-                                            || voice_manager.config.telegram.auto_voice
-                                            || voice_manager.config.is_voice_trigger(&user_query_text));
-
-                                    #[cfg(not(feature = "voice"))]
-                                    let send_as_voice = false;
-
-                                    if send_as_voice {
-                                        #[cfg(feature = "voice")]
-                                        {
-                                            if let Some(pid) = placeholder_id {
-                                                let _ = bot
-                                                    .delete_message(ChatId(chat_id), MessageId(pid))
-                                                    .await;
-                                            }
-                                            match Self::send_voice_message(
-                                                &bot,
-                                                chat_id,
-                                                &final_content,
-                                                &voice_manager,
-                                                voice_manager.config.telegram.include_text,
-                                            )
-                                            .await
-                                            {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    warn!(error = %e, "Voice synthesis failed, sending as text");
-                                                    let _ = bot
-                                                        .send_message(ChatId(chat_id), &final_content)
-                                                        .await;
-                                                }
-                                            }
+                                        if context_error {
+                                            break;
                                         }
-                                    } else {
-                                        if let Some(pid) = placeholder_id {
-                                            let _ = bot
-                                                .edit_message_text(
-                                                    ChatId(chat_id),
-                                                    MessageId(pid),
-                                                    &final_content,
-                                                )
-                                                .await;
-                                        } else if !final_content.is_empty() {
-                                            let _ =
-                                                bot.send_message(ChatId(chat_id), &final_content).await;
+                                        buffer = tail.to_string();
+                                        // Inactivity watchdog: finalize if no chunks for configured period
+                                        if !finalized && last_chunk_at.elapsed() >= inactivity_limit {
+                                            finalized = true;
+                                            throttle.cool_down().await;
+                                            Self::deliver_final(&delivery, &assembled).await;
+                                            break;
                                         }
                                     }
-                                    break;
-                                }
-                            }
-                            // Ensure finalization after stream ends without explicit final
-                            if !finalized {
-                                // Extract text content from XML format
-                                let display_text = extract_final_text_from_xml(&assembled);
-                                let final_content = if display_text.is_empty() {
-                                    assembled.clone()
-                                } else {
-                                    display_text.clone()
-                                };
-
-                                // Check if we should send as voice message
-                                #[cfg(feature = "voice")]
-                                let send_as_voice = voice_manager.is_enabled()
-                                    && (force_voice
-                                        || voice_manager.config.telegram.auto_voice
-                                        || voice_manager.config.is_voice_trigger(&user_query_text));
-
-                                #[cfg(not(feature = "voice"))]
-                                let send_as_voice = false;
-
-                                if send_as_voice {
-                                    #[cfg(feature = "voice")]
-                                    {
-                                        if let Some(pid) = placeholder_id {
-                                            let _ = bot
-                                                .delete_message(ChatId(chat_id), MessageId(pid))
-                                                .await;
-                                        }
-                                        match Self::send_voice_message(
-                                            &bot,
-                                            chat_id,
-                                            &final_content,
-                                            &voice_manager,
-                                            voice_manager.config.telegram.include_text,
-                                        )
-                                        .await
-                                        {
-                                            Ok(_) => {}
-                                            Err(e) => {
-                                                warn!(error = %e, "Voice synthesis failed, sending as text");
-                                                let _ = bot
-                                                    .send_message(ChatId(chat_id), &final_content)
-                                                    .await;
-                                            }
-                                        }
+                                    // Ensure finalization after stream ends without explicit final
+                                    if !finalized && !context_error {
+                                        throttle.cool_down().await;
+                                        Self::deliver_final(&delivery, &assembled).await;
                                     }
-                                } else {
+                                }
+                                failed => {
+                                    error!(
+                                        error = %"stream send timeout or error",
+                                        "Streaming request failed"
+                                    );
+                                    let error_class = if failed.is_err() { "timeout" } else { "send" };
+                                    telemetry.failed("request", error_class);
                                     if let Some(pid) = placeholder_id {
                                         let _ = bot
-                                            .edit_message_text(
-                                                ChatId(chat_id),
-                                                MessageId(pid),
-                                                &final_content,
-                                            )
-                                            .await;
-                                    } else if !final_content.is_empty() {
-                                        let _ = bot
-                                            .send_message(ChatId(chat_id), &final_content)
+                                            .edit_message_text(ChatId(chat_id), MessageId(pid), "Error")
                                             .await;
                                     }
                                 }
-                            }
                         }
-                        _ => {
-                            error!(
-                                error = %"stream send timeout or error",
-                                "Streaming request failed"
-                            );
-                            if let Some(pid) = placeholder_id {
-                                let _ = bot
-                                    .edit_message_text(ChatId(chat_id), MessageId(pid), "Error")
-                                    .await;
+                        }
+                        if !context_error {
+                            break;
+                        }
+                        // Retry once with a reduced context budget; the retry marker tells
+                        // the backend not to persist the user's message again
+                        match context_overflow.retry_metadata(&base_metadata, attempt) {
+                            Some(retry) => {
+                                warn!(attempt, "Context length exceeded, retrying with reduced context");
+                                metadata = retry;
+                                attempt += 1;
+                            }
+                            None => {
+                                warn!("Context length exceeded after retry");
+                                telemetry.failed("context", "context_overflow");
+                                if let Some(pid) = placeholder_id {
+                                    let _ = bot
+                                        .edit_message_text(
+                                            ChatId(chat_id),
+                                            MessageId(pid),
+                                            CONTEXT_OVERFLOW_MESSAGE,
+                                        )
+                                        .await;
+                                } else {
+                                    let _ = bot
+                                        .send_message(ChatId(chat_id), CONTEXT_OVERFLOW_MESSAGE)
+                                        .await;
+                                }
+                                break;
                             }
                         }
                     }
-                    {
-                        let mut guard = active_store.write().unwrap();
-                        guard.remove(&active_key);
-                    }
                 });
             })
             .ok();
     }
 }
 
+/// Built-in slash commands, then `extra` (which may replace them), then `/help` listing them all
+fn builtin_commands(
+    workspace: Option<Arc<WorkspaceLink>>,
+    tier_manager: Arc<TierManager>,
+    near_miss: Option<Arc<NearMissAck>>,
+    abuse_guard: Option<Arc<AbuseGuard>>,
+    digests: Option<Arc<Digests>>,
+    voice_manager: Arc<VoiceManager>,
+    identity_links: Option<Arc<IdentityLinks>>,
+    extra: &[CommandSpec],
+) -> CommandRegistry {
+    let mut registry = CommandRegistry::default();
+
+    let link = workspace.clone();
+    registry.register(CommandSpec::new("start", "Say hello", move |ctx| {
+        let link = link.clone();
+        async move {
+            let greeting = ctx
+                .bot
+                .send_message(ChatId(ctx.chat_id), "Hi! I’m Zoey. Ask me anything.");
+            let _ = match link {
+                Some(link) => {
+                    greeting
+                        .reply_markup(link.markup(ctx.chat_id, ctx.user_id, ctx.is_private))
+                        .await
+                }
+                None => greeting.await,
+            };
+            CommandOutcome::Handled
+        }
+    }));
+
+    registry.register(CommandSpec::new(
+        "workspace",
+        "Open the web workspace",
+        move |ctx| {
+            let link = workspace.clone();
+            async move {
+                let _ = match link {
+                    Some(link) => {
+                        ctx.bot
+                            .send_message(
+                                ChatId(ctx.chat_id),
+                                "Open the workspace to upload files and browse this conversation.",
+                            )
+                            .reply_markup(link.markup(ctx.chat_id, ctx.user_id, ctx.is_private))
+                            .await
+                    }
+                    None => {
+                        ctx.bot
+                            .send_message(
+                                ChatId(ctx.chat_id),
+                                "The web workspace is not configured.",
+                            )
+                            .await
+                    }
+                };
+                CommandOutcome::Handled
+            }
+        },
+    ));
+
+    registry.register(CommandSpec::new(
+        "tier",
+        "Show your tier and quota",
+        move |ctx| {
+            let tier_manager = tier_manager.clone();
+            async move {
+                let reply = tier_manager.handle_command(ctx.user_id, &ctx.text).await;
+                let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                CommandOutcome::Handled
+            }
+        },
+    ));
+
+    if let Some(ack) = near_miss {
+        registry.register(CommandSpec::new(
+            "seen",
+            "Turn seen reactions on or off in this group",
+            move |ctx| {
+                let ack = ack.clone();
+                async move {
+                    let reply = ack
+                        .handle_command(ctx.user_id, ctx.chat_id, ctx.is_private, &ctx.text)
+                        .await;
+                    let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                    CommandOutcome::Handled
+                }
+            },
+        ));
+    }
+
+    if let Some(guard) = abuse_guard {
+        registry.register(CommandSpec::new(
+            "unmute",
+            "Lift an anti-spam mute (admins)",
+            move |ctx| {
+                let reply = guard.handle_command(ctx.user_id, &ctx.text, std::time::Instant::now());
+                async move {
+                    let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                    CommandOutcome::Handled
+                }
+            },
+        ));
+    }
+
+    if let Some(digests) = digests {
+        registry.register(CommandSpec::new(
+            "digest",
+            "Answer group questions in a periodic digest (admins)",
+            move |ctx| {
+                let digests = digests.clone();
+                async move {
+                    match digests
+                        .handle_command(ctx.user_id, ctx.chat_id, ctx.is_private, &ctx.args)
+                        .await
+                    {
+                        DigestCommand::Reply(reply) => {
+                            let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                        }
+                        DigestCommand::Flush(questions) => {
+                            digests.flush(&ctx.bot, ctx.chat_id, questions).await;
+                        }
+                    }
+                    CommandOutcome::Handled
+                }
+            },
+        ));
+    }
+
+    // Voice command: request spoken AI response rather than reading user text
+    #[cfg(feature = "voice")]
+    if voice_manager.is_enabled() {
+        for name in ["voice", "speak", "tts"] {
+            registry.register(CommandSpec::new(
+                name,
+                "Ask and get a spoken reply",
+                |ctx| async move {
+                    if ctx.args.is_empty() {
+                        let _ = ctx
+                            .bot
+                            .send_message(
+                                ChatId(ctx.chat_id),
+                                "Usage: /voice <question>\nExample: /voice What can you do?",
+                            )
+                            .await;
+                        return CommandOutcome::Handled;
+                    }
+                    CommandOutcome::Ask {
+                        query: ctx.args,
+                        voice: true,
+                    }
+                },
+            ));
+        }
+    }
+    #[cfg(not(feature = "voice"))]
+    let _ = voice_manager;
+
+    if let Some(links) = identity_links {
+        linking::register_commands(&mut registry, links);
+    }
+
+    for spec in extra {
+        registry.register(spec.clone());
+    }
+
+    if registry.get("help").is_none() {
+        let help = format!("{}\n/help - List commands", registry.help_text());
+        registry.register(CommandSpec::new("help", "List commands", move |ctx| {
+            let help = help.clone();
+            async move {
+                let _ = ctx.bot.send_message(ChatId(ctx.chat_id), help).await;
+                CommandOutcome::Handled
+            }
+        }));
+    }
+    registry
+}
+
 #[async_trait]
 impl Service for TelegramAdapterService {
     fn service_type(&self) -> &str {
@@ -823,35 +2084,191 @@ impl Service for TelegramAdapterService {
             );
         }
 
+        // Quota counters and `/tier set` assignments persist through the database adapter
+        // when one is configured
+        let adapter = self.runtime.read().unwrap().get_adapter();
+        let quota_store: Arc<dyn QuotaStore> = match adapter.clone() {
+            Some(adapter) => Arc::new(AdapterQuotaStore::new(adapter)),
+            None => Arc::new(MemoryQuotaStore::default()),
+        };
+        let tier_manager = Arc::new(TierManager::new(
+            self.config.tiers.clone(),
+            self.config.default_tier.clone(),
+            self.config.quota_reset_hour_utc,
+            self.config.admin_users.iter().cloned().collect(),
+            quota_store,
+            adapter,
+        ));
+        match tier_manager.load().await {
+            Ok(0) => {}
+            Ok(count) => info!(count, "Loaded Telegram tier assignments"),
+            Err(e) => warn!(error = %e, "Failed to load Telegram tier assignments"),
+        }
+
+        let workspace = self.config.webapp_url.as_deref().and_then(|url| {
+            let link = WorkspaceLink::new(url, &self.config.token);
+            if link.is_none() {
+                warn!(url = %url, "Invalid TELEGRAM webapp_url, workspace button disabled");
+            }
+            link.map(Arc::new)
+        });
+        let near_miss = self.config.near_miss_ack.clone().map(|config| {
+            // Per-chat toggles persist through the database adapter when one is configured
+            let store: Arc<dyn AckSettingsStore> = match self.runtime.read().unwrap().get_adapter() {
+                Some(adapter) => Arc::new(AdapterAckSettingsStore::new(adapter)),
+                None => Arc::new(MemoryAckSettingsStore::default()),
+            };
+            Arc::new(NearMissAck::new(
+                config,
+                self.config.admin_users.iter().cloned().collect(),
+                store,
+            ))
+        });
+
+        let onboarding = self.config.onboarding.clone().map(|config| {
+            Arc::new(Onboarding::new(
+                config,
+                self.config.admin_users.iter().cloned().collect(),
+                self.config.state_store.clone(),
+            ))
+        });
+
+        let abuse_guard = self.config.abuse_guard.clone().map(|config| {
+            Arc::new(AbuseGuard::new(
+                config,
+                self.config.admin_users.iter().cloned().collect(),
+            ))
+        });
+
+        let digests = self.config.digest.clone().map(|config| {
+            Arc::new(Digests::new(
+                config,
+                self.config.admin_users.iter().cloned().collect(),
+                self.config.state_store.clone(),
+                self.config.polling.clone(),
+            ))
+        });
+
+        let access_store = self.config.access_store.clone().unwrap_or_else(|| {
+            Arc::new(StateAccessStore::new(self.config.state_store.clone())) as Arc<dyn AccessStore>
+        });
+        let access = Arc::new(AccessRequests::new(
+            self.config.unknown_user_policy,
+            self.config.access_requests.clone(),
+            self.config
+                .allowed_users
+                .as_ref()
+                .map(|v| v.iter().cloned().collect()),
+            self.config.admin_users.iter().cloned().collect(),
+            access_store,
+        ));
+        match access.load().await {
+            Ok(0) => {}
+            Ok(count) => info!(count, "Loaded Telegram access requests"),
+            Err(e) => warn!(error = %e, "Failed to load Telegram access requests"),
+        }
+
+        let commands = Arc::new(builtin_commands(
+            workspace.clone(),
+            tier_manager.clone(),
+            near_miss.clone(),
+            abuse_guard.clone(),
+            digests.clone(),
+            voice_manager.clone(),
+            self.config.identity_links.clone(),
+            &self.extra_commands,
+        ));
+        // Publish the commands so Telegram lists them in the menu
+        if let Err(e) = bot.set_my_commands(commands.bot_commands()).await {
+            warn!(error = %e, "Failed to register Telegram commands");
+        }
+
+        // A sink that can't be set up must not keep the bot from starting
+        let telemetry = self.config.telemetry.build("telegram").unwrap_or_else(|e| {
+            warn!(error = %e, "Telemetry sink unavailable, logging events only");
+            Telemetry::tracing("telegram")
+        });
+
         let handler = TelegramHandler {
             runtime: self.runtime.clone(),
             limiter: self.limiter.clone(),
+            state_store: self.config.state_store.clone(),
             allowed_chats: self
                 .config
                 .allowed_chats
                 .as_ref()
                 .map(|v| v.iter().cloned().collect()),
-            allowed_users: self
-                .config
-                .allowed_users
-                .as_ref()
-                .map(|v| v.iter().cloned().collect()),
+            access,
             bot_username: bot_username.or(self.config.bot_username.clone()),
             bot_id,
             voice_manager,
+            tier_manager,
+            context_overflow: self.config.context_overflow.clone(),
+            workspace,
+            response_template: self.config.response_template.clone(),
+            near_miss,
+            followups: self.config.followups.clone().map(|config| Arc::new(FollowupStore::new(config))),
+            actions: self.config.actions.clone().map(|config| Arc::new(ActionStore::new(config))),
+            onboarding,
+            abuse_guard,
+            digests,
+            admin_users: self.config.admin_users.clone(),
+            identity_links: self.config.identity_links.clone(),
+            commands,
+            chat_modes: Arc::new(ChatModes::new(self.config.streaming)),
+            poll_config: self.config.polling.clone(),
+            telemetry,
+            parse_mode: self.config.parse_mode,
+            max_attachment_bytes: self.config.max_attachment_bytes,
+            #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+            observed_languages: ObservedLanguages::default(),
         };
 
+        // Expired follow-up and action keyboards are removed from the main runtime; workers are short-lived
+        if let Some(ref store) = handler.followups {
+            let _ = FOLLOWUP_SWEEPER_HANDLE
+                .set(followups::spawn_followup_sweeper(store.clone(), bot.clone()));
+        }
+        if let Some(ref store) = handler.actions {
+            let _ = ACTION_SWEEPER_HANDLE.set(actions::spawn_action_sweeper(store.clone(), bot.clone()));
+        }
+        if let Some(ref digests) = handler.digests {
+            let _ = DIGEST_FLUSHER_HANDLE.set(digest::spawn_digest_flusher(digests.clone(), bot.clone()));
+        }
+
         let handler = Arc::new(handler);
+        let callback_handler = handler.clone();
+        let member_handler = handler.clone();
 
         let handle = tokio::spawn(async move {
-            let update_handler =
-                Update::filter_message().endpoint(move |bot: Bot, msg: TelegramMessage| {
-                    let handler = handler.clone();
-                    async move {
-                        handler.handle_message(bot, msg).await;
-                        Ok::<(), std::convert::Infallible>(())
-                    }
-                });
+            let update_handler = dptree::entry()
+                .branch(Update::filter_message().endpoint(
+                    move |bot: Bot, msg: TelegramMessage| {
+                        let handler = handler.clone();
+                        async move {
+                            handler.handle_message(bot, msg).await;
+                            Ok::<(), std::convert::Infallible>(())
+                        }
+                    },
+                ))
+                .branch(Update::filter_callback_query().endpoint(
+                    move |bot: Bot, query: CallbackQuery| {
+                        let handler = callback_handler.clone();
+                        async move {
+                            handler.handle_callback(bot, query).await;
+                            Ok::<(), std::convert::Infallible>(())
+                        }
+                    },
+                ))
+                .branch(Update::filter_my_chat_member().endpoint(
+                    move |bot: Bot, update: ChatMemberUpdated| {
+                        let handler = member_handler.clone();
+                        async move {
+                            handler.handle_member_update(bot, update).await;
+                            Ok::<(), std::convert::Infallible>(())
+                        }
+                    },
+                ));
 
             Dispatcher::builder(bot, update_handler)
                 .enable_ctrlc_handler()
@@ -882,16 +2299,46 @@ impl Service for TelegramAdapterService {
 pub struct TelegramPlugin {
     config: TelegramConfig,
     runtime: Arc<RwLock<AgentRuntime>>,
+    scheduler: Arc<SendScheduler>,
 }
 
 impl TelegramPlugin {
     pub fn new(config: TelegramConfig, runtime: Arc<RwLock<AgentRuntime>>) -> Self {
-        Self { config, runtime }
+        let scheduler = Arc::new(SendScheduler::new(
+            config.scheduled_send_store.clone(),
+            scheduled_delivery(config.token.clone()),
+        ));
+        Self {
+            config,
+            runtime,
+            scheduler,
+        }
+    }
+
+    /// Sends queued for later delivery, e.g. to cancel a reminder by key
+    pub fn scheduler(&self) -> Arc<SendScheduler> {
+        self.scheduler.clone()
     }
 }
 
+/// Posts a scheduled send with a bot for `token`
+fn scheduled_delivery(token: String) -> scheduler::DeliverFunction {
+    Arc::new(move |send: ScheduledSend| {
+        let token = token.clone();
+        Box::pin(async move {
+            Bot::new(&token)
+                .send_message(ChatId(send.chat_id), send.text)
+                .await
+                .map(|_| ())
+                .map_err(|e| zoey_core::ZoeyError::other(format!("telegram send error: {:?}", e)))
+        })
+    })
+}
+
 #[async_trait]
 impl zoey_core::types::Plugin for TelegramPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "telegram"
     }
@@ -912,10 +2359,16 @@ impl zoey_core::types::Plugin for TelegramPlugin {
                     .cloned()
                     .collect::<std::collections::HashSet<i64>>()
             });
+            let response_template = self.config.response_template.clone();
+            let character = rt.character.name.clone();
+            let send_scheduler = self.scheduler.clone();
             let handler: zoey_core::types::messaging::SendHandlerFunction =
                 Arc::new(move |params| {
                     let token = token.clone();
                     let allowed_chats = allowed_chats.clone();
+                    let response_template = response_template.clone();
+                    let character = character.clone();
+                    let send_scheduler = send_scheduler.clone();
                     Box::pin(async move {
                         let bot = Bot::new(&token);
                         if let Some(cid) = params
@@ -930,13 +2383,38 @@ impl zoey_core::types::Plugin for TelegramPlugin {
                                     return Ok(());
                                 }
                             }
+                            let metadata = &params.target.metadata;
+                            if let Some(key) =
+                                metadata.get(scheduler::CANCEL_KEY).and_then(|v| v.as_str())
+                            {
+                                if !send_scheduler.cancel(key).await {
+                                    info!(key = %key, "No pending scheduled send to cancel");
+                                }
+                                return Ok(());
+                            }
                             // Extract text content from XML format for display
                             let display_text = extract_final_text_from_xml(&params.content.text);
-                            let content = if display_text.is_empty() {
-                                params.content.text.clone()
-                            } else {
-                                display_text
-                            };
+                            let content = render_reply(
+                                response_template.as_deref(),
+                                if display_text.is_empty() {
+                                    &params.content.text
+                                } else {
+                                    &display_text
+                                },
+                                &target_room_label(&params.target),
+                                &character,
+                            );
+                            let now = scheduler::unix_now();
+                            if let Some(deliver_at) = scheduler::requested_delivery(metadata, now) {
+                                return send_scheduler
+                                    .schedule(ScheduledSend {
+                                        key: scheduler::schedule_key(metadata),
+                                        chat_id: cid,
+                                        text: content,
+                                        deliver_at,
+                                    })
+                                    .await;
+                            }
                             bot.send_message(ChatId(cid), content).await.map_err(|e| {
                                 zoey_core::ZoeyError::other(format!(
                                     "telegram send error: {:?}",
@@ -953,6 +2431,9 @@ impl zoey_core::types::Plugin for TelegramPlugin {
                 });
             rt.register_send_handler("telegram".to_string(), handler);
         }
+        if SEND_SCHEDULER_HANDLE.get().is_none() {
+            let _ = SEND_SCHEDULER_HANDLE.set(self.scheduler.clone().start());
+        }
         Ok(())
     }
 
@@ -967,9 +2448,16 @@ impl zoey_core::types::Plugin for TelegramPlugin {
     }
 }
 
-pub async fn register_telegram_send(runtime: Arc<RwLock<AgentRuntime>>, token: String) {
+pub async fn register_telegram_send(
+    runtime: Arc<RwLock<AgentRuntime>>,
+    token: String,
+    response_template: Option<String>,
+) {
+    let character = runtime.read().unwrap().character.name.clone();
     let handler: zoey_core::types::messaging::SendHandlerFunction = Arc::new(move |params| {
         let token = token.clone();
+        let response_template = response_template.clone();
+        let character = character.clone();
         Box::pin(async move {
             let bot = Bot::new(&token);
             if let Some(cid) = params
@@ -981,11 +2469,16 @@ pub async fn register_telegram_send(runtime: Arc<RwLock<AgentRuntime>>, token: S
             {
                 // Extract text content from XML format for display
                 let display_text = extract_final_text_from_xml(&params.content.text);
-                let content = if display_text.is_empty() {
-                    params.content.text.clone()
-                } else {
-                    display_text
-                };
+                let content = render_reply(
+                    response_template.as_deref(),
+                    if display_text.is_empty() {
+                        &params.content.text
+                    } else {
+                        &display_text
+                    },
+                    &target_room_label(&params.target),
+                    &character,
+                );
                 bot.send_message(ChatId(cid), content).await.map_err(|e| {
                     zoey_core::ZoeyError::other(format!("telegram send error: {:?}", e))
                 })?;
@@ -1008,7 +2501,12 @@ pub async fn start_telegram(
     if !config.enabled {
         return Ok(());
     }
-    register_telegram_send(runtime.clone(), config.token.clone()).await;
+    register_telegram_send(
+        runtime.clone(),
+        config.token.clone(),
+        config.response_template.clone(),
+    )
+    .await;
     let mut svc = TelegramAdapterService::new(config.clone(), runtime.clone());
     svc.initialize(Arc::new(())).await?;
     svc.start().await?;
@@ -1030,3 +2528,66 @@ pub async fn start_telegram(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tapped_followup_runs_as_user_turn() {
+        let store = FollowupStore::new(FollowupConfig::default());
+        let now = std::time::Instant::now();
+        let (offer_id, _) = store
+            .offer(-100, vec!["Why?".to_string(), "How?".to_string()], now)
+            .unwrap();
+        store.attach(offer_id, 42);
+        let question = match store.tap(&followups::callback_data(offer_id, 1), -100, 42, now) {
+            FollowupTap::Chosen(question) => question,
+            other => panic!("unexpected tap {:?}", other),
+        };
+
+        let turn = ChatTurn::followup(-100, 7, "Ada".to_string(), false, 43, question);
+        assert_eq!(turn.text, "How?");
+        assert_eq!((turn.chat_id, turn.user_id, turn.msg_id), (-100, 7, 43));
+        assert!(!turn.from_voice);
+        // Answered in a group without a mention, like a reply to the bot
+        assert!(turn.is_addressed(1, Some("zoey_bot"), None));
+
+        let typed = ChatTurn {
+            followup: false,
+            ..ChatTurn::followup(-100, 7, "Ada".to_string(), false, 44, "How?".to_string())
+        };
+        assert!(!typed.is_addressed(1, Some("zoey_bot"), None));
+        let reply = ChatTurn {
+            reply_to: Some(ReplyRef {
+                message_id: 40,
+                from_id: Some(1),
+                text: Some("Earlier answer".to_string()),
+            }),
+            ..typed
+        };
+        assert!(reply.is_addressed(1, Some("zoey_bot"), None));
+    }
+
+    #[test]
+    fn test_tapped_action_runs_in_same_room() {
+        let assembled = "<response><actions>REPLY,Show pricing,Book a demo</actions>\
+                         <text>We have three plans.</text></response>";
+        let labels = actions::parse_actions(assembled);
+        assert_eq!(extract_final_text_from_xml(assembled), "We have three plans.");
+
+        let store = ActionStore::new(ActionConfig::default());
+        let now = std::time::Instant::now();
+        let (offer_id, _) = store.offer(-100, labels, now).unwrap();
+        store.attach(offer_id, 42);
+        let label = match store.tap(&actions::callback_data(offer_id, 1), -100, 42, now) {
+            ActionTap::Chosen(label) => label,
+            other => panic!("unexpected tap {:?}", other),
+        };
+
+        let turn = ChatTurn::followup(-100, 7, "Ada".to_string(), false, 42, label);
+        assert_eq!(turn.text, "Book a demo");
+        assert_eq!((turn.chat_id, turn.user_id), (-100, 7));
+        assert!(turn.is_addressed(1, Some("zoey_bot"), None));
+    }
+}
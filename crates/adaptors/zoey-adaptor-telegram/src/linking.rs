@@ -0,0 +1,224 @@
+//! Linking a Telegram account with the web UI
+//!
+//! When `TelegramConfig::identity_links` is set:
+//!
+//! - `/link` replies with a short-lived code to enter in the web UI
+//!   (`POST /agent/link/confirm`); only in private chats, so the code is not
+//!   posted in a group
+//! - `/unlink` removes the link
+//! - `/whoami` lists the linked accounts with their IDs redacted
+//!
+//! Once linked, messages are attributed to the web entity (see
+//! [`entity_for_user`]), so the user shares memory across every linked
+//! surface. Codes and aliases come from `zoey_core::IdentityLinks`, the same
+//! store the other adapters use.
+
+use crate::commands::{CommandOutcome, CommandRegistry, CommandSpec};
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::ChatId;
+use tracing::warn;
+use uuid::Uuid;
+use zoey_core::identity_links::LINK_CODE_TTL;
+use zoey_core::{redact_id, IdentityLinks, LinkedAccount, PlatformAccount};
+
+/// Platform name the Telegram adapter links accounts under
+pub const PLATFORM: &str = "telegram";
+
+/// The linkable account of a Telegram user
+pub fn account_for_user(user_id: u64) -> PlatformAccount {
+    PlatformAccount::new(PLATFORM, user_id)
+}
+
+/// Entity a Telegram user's messages are attributed to: the linked web entity, if any
+pub async fn entity_for_user(links: Option<&IdentityLinks>, user_id: u64) -> Uuid {
+    let own = crate::workspace::entity_id_for_user(user_id);
+    match links {
+        Some(links) => links.resolve(own).await,
+        None => own,
+    }
+}
+
+/// Reply to `/link` with a fresh code
+fn link_text(code: &str) -> String {
+    let (head, tail) = code.split_at(code.len() / 2);
+    format!(
+        "Your link code is {}-{}\n\nEnter it in the web workspace within {} minutes to share \
+         your conversations with Zoey between Telegram and the web. Use /unlink to undo.",
+        head,
+        tail,
+        LINK_CODE_TTL.as_secs() / 60
+    )
+}
+
+/// Reply to `/whoami` for `user_id`, given the accounts linked alongside it
+pub fn whoami_text(user_id: u64, linked: &[LinkedAccount]) -> String {
+    let me = account_for_user(user_id);
+    let Some(web) = linked.iter().find(|l| l.account == me).map(|l| l.entity_id) else {
+        return format!(
+            "This Telegram account ({}) is not linked. Use /link to connect it to the web workspace.",
+            redact_id(&me.user_id)
+        );
+    };
+    let mut lines = vec![
+        "Linked accounts:".to_string(),
+        format!("• Web: {}", redact_id(&web.to_string())),
+    ];
+    for l in linked {
+        let this = if l.account == me {
+            " (this account)"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "• {}: {}{}",
+            platform_label(&l.account.platform),
+            redact_id(&l.account.user_id),
+            this
+        ));
+    }
+    lines.join("\n")
+}
+
+fn platform_label(platform: &str) -> String {
+    let mut chars = platform.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Add `/link`, `/unlink` and `/whoami`
+pub fn register_commands(registry: &mut CommandRegistry, links: Arc<IdentityLinks>) {
+    let link = links.clone();
+    registry.register(CommandSpec::new(
+        "link",
+        "Link this account with the web workspace",
+        move |ctx| {
+            let links = link.clone();
+            async move {
+                let reply = if !ctx.is_private {
+                    "Send /link in a private chat with me to get your link code.".to_string()
+                } else {
+                    match links.issue_code(&account_for_user(ctx.user_id)).await {
+                        Ok(code) => link_text(&code),
+                        Err(e) => {
+                            warn!(user_id = %ctx.user_id, error = %e, "Failed to issue link code");
+                            "Couldn't create a link code right now. Please try again.".to_string()
+                        }
+                    }
+                };
+                let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                CommandOutcome::Handled
+            }
+        },
+    ));
+
+    let unlink = links.clone();
+    registry.register(CommandSpec::new(
+        "unlink",
+        "Unlink this account from the web workspace",
+        move |ctx| {
+            let links = unlink.clone();
+            async move {
+                let reply = match links.unlink(&account_for_user(ctx.user_id)).await {
+                    Ok(true) => "Unlinked. Your Telegram conversations are kept separate again.",
+                    Ok(false) => "This account is not linked.",
+                    Err(e) => {
+                        warn!(user_id = %ctx.user_id, error = %e, "Failed to unlink account");
+                        "Couldn't unlink right now. Please try again."
+                    }
+                };
+                let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                CommandOutcome::Handled
+            }
+        },
+    ));
+
+    registry.register(CommandSpec::new(
+        "whoami",
+        "Show which accounts are linked",
+        move |ctx| {
+            let links = links.clone();
+            async move {
+                let own = account_for_user(ctx.user_id).entity_id();
+                let reply = match links.linked_accounts(own).await {
+                    Ok(linked) => whoami_text(ctx.user_id, &linked),
+                    Err(e) => {
+                        warn!(user_id = %ctx.user_id, error = %e, "Failed to list linked accounts");
+                        "Couldn't look up your linked accounts right now.".to_string()
+                    }
+                };
+                let _ = ctx.bot.send_message(ChatId(ctx.chat_id), reply).await;
+                CommandOutcome::Handled
+            }
+        },
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::MemoryStateStore;
+
+    #[tokio::test]
+    async fn test_linked_users_resolve_to_the_web_entity() {
+        let links = IdentityLinks::new(Arc::new(MemoryStateStore::new()));
+        let web = Uuid::new_v4();
+        assert_eq!(
+            account_for_user(42).entity_id(),
+            crate::workspace::entity_id_for_user(42)
+        );
+        assert_eq!(
+            entity_for_user(Some(&links), 42).await,
+            crate::workspace::entity_id_for_user(42)
+        );
+
+        let code = links.issue_code(&account_for_user(42)).await.unwrap();
+        links.confirm(&code, web).await.unwrap();
+        assert_eq!(entity_for_user(Some(&links), 42).await, web);
+        assert_eq!(
+            entity_for_user(None, 42).await,
+            crate::workspace::entity_id_for_user(42)
+        );
+
+        links.unlink(&account_for_user(42)).await.unwrap();
+        assert_eq!(
+            entity_for_user(Some(&links), 42).await,
+            crate::workspace::entity_id_for_user(42)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_whoami_redacts_identifiers() {
+        let links = IdentityLinks::new(Arc::new(MemoryStateStore::new()));
+        let web: Uuid = "6f1c2a9e-0b7d-4c3e-9a51-2d8e4b7fc0d1".parse().unwrap();
+        assert_eq!(
+            whoami_text(123456789, &[]),
+            "This Telegram account (••••6789) is not linked. Use /link to connect it to the web workspace."
+        );
+
+        let code = links
+            .issue_code(&account_for_user(123456789))
+            .await
+            .unwrap();
+        links.confirm(&code, web).await.unwrap();
+        let discord = PlatformAccount::new("discord", 555000111222u64);
+        let code = links.issue_code(&discord).await.unwrap();
+        links.confirm(&code, web).await.unwrap();
+
+        let own = account_for_user(123456789).entity_id();
+        let text = whoami_text(123456789, &links.linked_accounts(own).await.unwrap());
+        assert_eq!(
+            text,
+            "Linked accounts:\n• Web: ••••c0d1\n• Discord: ••••1222\n• Telegram: ••••6789 (this account)"
+        );
+        assert!(!text.contains("123456789"));
+        assert!(!text.contains("555000111222"));
+    }
+
+    #[test]
+    fn test_link_text_splits_code() {
+        assert!(link_text("ABCDEFGH").starts_with("Your link code is ABCD-EFGH\n"));
+    }
+}
@@ -0,0 +1,384 @@
+//! "Seen" reactions for group messages that almost addressed the bot
+//!
+//! In groups the bot only replies when addressed. A message that scores close
+//! to addressed (a misspelled name between `lower_threshold` and
+//! `reply_threshold`, or a reply to a message right next to one of the bot's)
+//! gets an emoji reaction instead, so the author knows it was seen.
+//!
+//! - Private chats never get reactions; they are always answered.
+//! - Each user is acknowledged at most once per chat per `cooldown`.
+//! - Admins turn reactions off or on per chat with `/seen off|on`; the choice
+//!   is persisted through an [`AckSettingsStore`].
+//!
+//! Name scoring uses the shared [`WakeWordMatcher`].
+
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use zoey_core::nlp::WakeWordMatcher;
+use zoey_core::{types::Component, IDatabaseAdapter, Result};
+
+/// Component type used to persist per-chat settings
+const ACK_COMPONENT_TYPE: &str = "telegram_near_miss";
+
+/// Bot messages remembered per chat for the adjacency check
+const RECENT_BOT_MESSAGES: usize = 16;
+
+/// Default reaction emoji
+pub const DEFAULT_NEAR_MISS_EMOJI: &str = "👀";
+
+/// Default time before the same user can be acknowledged again in a chat
+pub const DEFAULT_NEAR_MISS_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+
+/// Near-miss acknowledgement settings
+#[derive(Debug, Clone)]
+pub struct NearMissConfig {
+    /// Emoji used for the reaction
+    pub emoji: String,
+    /// Lowest name score counted as a near miss
+    pub lower_threshold: f32,
+    /// Name score from which the message counts as naming the bot (no reaction)
+    pub reply_threshold: f32,
+    /// Per-user, per-chat cooldown between reactions
+    pub cooldown: Duration,
+}
+
+impl Default for NearMissConfig {
+    fn default() -> Self {
+        Self {
+            emoji: DEFAULT_NEAR_MISS_EMOJI.to_string(),
+            lower_threshold: 0.7,
+            reply_threshold: 0.9,
+            cooldown: DEFAULT_NEAR_MISS_COOLDOWN,
+        }
+    }
+}
+
+impl NearMissConfig {
+    /// Whether a name score falls in the near-miss band `[lower, reply)`
+    pub fn in_band(&self, score: f32) -> bool {
+        score >= self.lower_threshold && score < self.reply_threshold
+    }
+}
+
+/// Storage backend for per-chat acknowledgement settings
+#[async_trait]
+pub trait AckSettingsStore: Send + Sync {
+    /// Whether reactions are enabled in a chat, `None` when never set
+    async fn load(&self, chat_id: i64) -> Result<Option<bool>>;
+
+    /// Persist whether reactions are enabled in a chat
+    async fn save(&self, chat_id: i64, enabled: bool) -> Result<()>;
+}
+
+/// In-memory settings store (settings are lost on restart)
+#[derive(Default)]
+pub struct MemoryAckSettingsStore {
+    chats: RwLock<HashMap<i64, bool>>,
+}
+
+#[async_trait]
+impl AckSettingsStore for MemoryAckSettingsStore {
+    async fn load(&self, chat_id: i64) -> Result<Option<bool>> {
+        Ok(self.chats.read().unwrap().get(&chat_id).copied())
+    }
+
+    async fn save(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        self.chats.write().unwrap().insert(chat_id, enabled);
+        Ok(())
+    }
+}
+
+/// Settings store backed by the runtime's database adapter (entity components)
+pub struct AdapterAckSettingsStore {
+    adapter: Arc<dyn IDatabaseAdapter + Send + Sync>,
+}
+
+impl AdapterAckSettingsStore {
+    pub fn new(adapter: Arc<dyn IDatabaseAdapter + Send + Sync>) -> Self {
+        Self { adapter }
+    }
+
+    fn entity_id(chat_id: i64) -> uuid::Uuid {
+        zoey_core::string_to_uuid(&format!("telegram-chat-{}", chat_id))
+    }
+
+    fn world_id() -> uuid::Uuid {
+        zoey_core::string_to_uuid("telegram-near-miss")
+    }
+
+    async fn find(&self, chat_id: i64) -> Result<Option<Component>> {
+        self.adapter
+            .get_component(
+                Self::entity_id(chat_id),
+                ACK_COMPONENT_TYPE,
+                Some(Self::world_id()),
+                None,
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl AckSettingsStore for AdapterAckSettingsStore {
+    async fn load(&self, chat_id: i64) -> Result<Option<bool>> {
+        Ok(self
+            .find(chat_id)
+            .await?
+            .and_then(|c| c.data.get("enabled").and_then(|v| v.as_bool())))
+    }
+
+    async fn save(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        let data = serde_json::json!({ "enabled": enabled });
+        let now = Utc::now().timestamp();
+        match self.find(chat_id).await? {
+            Some(mut existing) => {
+                existing.data = data;
+                existing.updated_at = Some(now);
+                self.adapter.update_component(&existing).await
+            }
+            None => {
+                let component = Component {
+                    id: uuid::Uuid::new_v4(),
+                    entity_id: Self::entity_id(chat_id),
+                    world_id: Self::world_id(),
+                    source_entity_id: None,
+                    component_type: ACK_COMPONENT_TYPE.to_string(),
+                    data,
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                };
+                self.adapter.create_component(&component).await.map(|_| ())
+            }
+        }
+    }
+}
+
+/// A group message that was not addressed to the bot
+#[derive(Debug, Clone, Copy)]
+pub struct UnaddressedMessage<'a> {
+    pub chat_id: i64,
+    pub user_id: u64,
+    pub is_private: bool,
+    pub text: &'a str,
+    /// Message this one replies to, if any
+    pub reply_to: Option<i32>,
+}
+
+/// Decides when to react to near-miss messages and tracks cooldowns
+pub struct NearMissAck {
+    config: NearMissConfig,
+    admins: HashSet<u64>,
+    store: Arc<dyn AckSettingsStore>,
+    /// Per-chat enabled flag, cached from the store
+    enabled: RwLock<HashMap<i64, bool>>,
+    last_ack: Mutex<HashMap<(i64, u64), Instant>>,
+    bot_messages: Mutex<HashMap<i64, VecDeque<i32>>>,
+}
+
+impl NearMissAck {
+    pub fn new(config: NearMissConfig, admins: HashSet<u64>, store: Arc<dyn AckSettingsStore>) -> Self {
+        Self {
+            config,
+            admins,
+            store,
+            enabled: RwLock::new(HashMap::new()),
+            last_ack: Mutex::new(HashMap::new()),
+            bot_messages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Emoji to react with
+    pub fn emoji(&self) -> &str {
+        &self.config.emoji
+    }
+
+    /// Remember a message the bot sent, for the adjacency check
+    pub fn record_bot_message(&self, chat_id: i64, message_id: i32) {
+        let mut messages = self.bot_messages.lock().unwrap();
+        let recent = messages.entry(chat_id).or_default();
+        recent.push_back(message_id);
+        if recent.len() > RECENT_BOT_MESSAGES {
+            recent.pop_front();
+        }
+    }
+
+    /// Whether `message_id` sits directly before or after one of the bot's messages
+    fn adjacent_to_bot(&self, chat_id: i64, message_id: i32) -> bool {
+        self.bot_messages
+            .lock()
+            .unwrap()
+            .get(&chat_id)
+            .is_some_and(|recent| recent.iter().any(|&id| (id - message_id).abs() == 1))
+    }
+
+    /// Whether reactions are enabled in a chat (on unless an admin turned them off)
+    pub async fn is_enabled(&self, chat_id: i64) -> bool {
+        if let Some(&enabled) = self.enabled.read().unwrap().get(&chat_id) {
+            return enabled;
+        }
+        let enabled = match self.store.load(chat_id).await {
+            Ok(stored) => stored.unwrap_or(true),
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Failed to load near-miss setting");
+                true
+            }
+        };
+        self.enabled.write().unwrap().insert(chat_id, enabled);
+        enabled
+    }
+
+    /// Whether `message` should get a "seen" reaction now; starts the cooldown when it should
+    pub async fn should_ack(&self, message: UnaddressedMessage<'_>, bot_name: &str, now: Instant) -> bool {
+        if message.is_private {
+            return false;
+        }
+        let score = WakeWordMatcher::new(bot_name).score(message.text);
+        let near_miss = self.config.in_band(score)
+            || (score < self.config.reply_threshold
+                && message
+                    .reply_to
+                    .is_some_and(|id| self.adjacent_to_bot(message.chat_id, id)));
+        if !near_miss || !self.is_enabled(message.chat_id).await {
+            return false;
+        }
+        let mut last_ack = self.last_ack.lock().unwrap();
+        let key = (message.chat_id, message.user_id);
+        if let Some(at) = last_ack.get(&key) {
+            if now.saturating_duration_since(*at) < self.config.cooldown {
+                return false;
+            }
+        }
+        last_ack.insert(key, now);
+        true
+    }
+
+    /// Handle `/seen [on|off]`, returning the reply to send
+    pub async fn handle_command(&self, caller_id: u64, chat_id: i64, is_private: bool, text: &str) -> String {
+        let arg = text.split_whitespace().nth(1);
+        if is_private {
+            return "Seen reactions only apply to group chats.".to_string();
+        }
+        let enabled = match arg {
+            None => {
+                let state = if self.is_enabled(chat_id).await { "on" } else { "off" };
+                return format!("Seen reactions are {} in this chat.", state);
+            }
+            Some("on") => true,
+            Some("off") => false,
+            Some(_) => return "Usage: /seen [on|off]".to_string(),
+        };
+        if !self.admins.contains(&caller_id) {
+            return "Only admins can change seen reactions.".to_string();
+        }
+        if let Err(e) = self.store.save(chat_id, enabled).await {
+            warn!(chat_id = %chat_id, error = %e, "Failed to persist near-miss setting");
+        }
+        self.enabled.write().unwrap().insert(chat_id, enabled);
+        info!(chat_id = %chat_id, enabled, "Telegram seen reactions changed");
+        if enabled {
+            "Seen reactions are on in this chat.".to_string()
+        } else {
+            "Seen reactions are off in this chat.".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAT: i64 = -100;
+
+    fn ack(store: Arc<dyn AckSettingsStore>) -> NearMissAck {
+        NearMissAck::new(NearMissConfig::default(), HashSet::from([1]), store)
+    }
+
+    fn group_message(text: &str) -> UnaddressedMessage<'_> {
+        UnaddressedMessage {
+            chat_id: CHAT,
+            user_id: 42,
+            is_private: false,
+            text,
+            reply_to: None,
+        }
+    }
+
+    #[test]
+    fn test_band_boundaries() {
+        let config = NearMissConfig::default();
+        assert!(!config.in_band(0.69));
+        assert!(config.in_band(0.7));
+        assert!(config.in_band(0.89));
+        assert!(!config.in_band(0.9));
+        assert!(!config.in_band(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_scoring_band() {
+        let ack = ack(Arc::new(MemoryAckSettingsStore::default()));
+        let now = Instant::now();
+        // Exact name: addressed well enough, no reaction
+        assert!(!ack.should_ack(group_message("zoey can you look"), "Zoey", now).await);
+        // Unrelated text
+        assert!(!ack.should_ack(group_message("lunch anyone?"), "Zoey", now).await);
+        // Misspelled name
+        assert!(ack.should_ack(group_message("zoeu can you look"), "Zoey", now).await);
+    }
+
+    #[tokio::test]
+    async fn test_reply_adjacent_to_bot_message() {
+        let ack = ack(Arc::new(MemoryAckSettingsStore::default()));
+        ack.record_bot_message(CHAT, 500);
+        let now = Instant::now();
+        let far = UnaddressedMessage { reply_to: Some(490), ..group_message("what about this") };
+        assert!(!ack.should_ack(far, "Zoey", now).await);
+        let adjacent = UnaddressedMessage { reply_to: Some(501), ..group_message("what about this") };
+        assert!(ack.should_ack(adjacent, "Zoey", now).await);
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_per_user() {
+        let ack = ack(Arc::new(MemoryAckSettingsStore::default()));
+        let now = Instant::now();
+        assert!(ack.should_ack(group_message("zoeu?"), "Zoey", now).await);
+        assert!(!ack.should_ack(group_message("zoeu??"), "Zoey", now + Duration::from_secs(60)).await);
+        // Another user is not affected
+        let other = UnaddressedMessage { user_id: 43, ..group_message("zoeu?") };
+        assert!(ack.should_ack(other, "Zoey", now).await);
+        let later = now + DEFAULT_NEAR_MISS_COOLDOWN;
+        assert!(ack.should_ack(group_message("zoeu?"), "Zoey", later).await);
+    }
+
+    #[tokio::test]
+    async fn test_never_in_private_chats() {
+        let ack = ack(Arc::new(MemoryAckSettingsStore::default()));
+        let dm = UnaddressedMessage { is_private: true, ..group_message("zoeu?") };
+        assert!(!ack.should_ack(dm, "Zoey", Instant::now()).await);
+    }
+
+    #[tokio::test]
+    async fn test_admin_toggle_persists() {
+        let store: Arc<dyn AckSettingsStore> = Arc::new(MemoryAckSettingsStore::default());
+        let first = ack(store.clone());
+        assert_eq!(
+            first.handle_command(2, CHAT, false, "/seen off").await,
+            "Only admins can change seen reactions."
+        );
+        assert!(first.is_enabled(CHAT).await);
+        assert_eq!(
+            first.handle_command(1, CHAT, false, "/seen off").await,
+            "Seen reactions are off in this chat."
+        );
+        assert!(!first.should_ack(group_message("zoeu?"), "Zoey", Instant::now()).await);
+
+        // A fresh instance (e.g. after restart) sees the persisted setting
+        let second = ack(store);
+        assert!(!second.is_enabled(CHAT).await);
+        assert!(!second.should_ack(group_message("zoeu?"), "Zoey", Instant::now()).await);
+        assert!(second.is_enabled(CHAT + 1).await);
+    }
+}
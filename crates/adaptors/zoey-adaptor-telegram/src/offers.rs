@@ -0,0 +1,284 @@
+//! Inline keyboard offers that resolve to one tapped choice
+//!
+//! Follow-up questions and suggested actions both post a keyboard under a
+//! reply whose buttons only carry `<prefix><offer>:<index>` (Telegram limits
+//! callback data to 64 bytes). The choices themselves stay in an
+//! [`OfferStore`] until one is tapped, the keyboard could not be posted, or
+//! the offer expires and a sweeper removes the keyboard.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+use teloxide::types::{ChatId, InlineKeyboardButton, MessageId};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Telegram's limit on callback data, in bytes
+pub const MAX_CALLBACK_DATA: usize = 64;
+
+/// Longest button label before it is truncated
+pub const MAX_LABEL_CHARS: usize = 40;
+
+/// Longest pause between sweeps for expired offers
+const MAX_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Button label for a choice, cut to `max_chars` characters
+pub fn truncate_label(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Callback data referencing choice `index` of `offer_id`
+pub fn callback_data(prefix: &str, offer_id: u64, index: usize) -> String {
+    let data = format!("{}{}:{}", prefix, offer_id, index);
+    debug_assert!(data.len() <= MAX_CALLBACK_DATA);
+    data
+}
+
+/// Offer ID and choice index from callback data starting with `prefix`
+pub fn parse_callback_data(prefix: &str, data: &str) -> Option<(u64, usize)> {
+    let (offer_id, index) = data.strip_prefix(prefix)?.split_once(':')?;
+    Some((offer_id.parse().ok()?, index.parse().ok()?))
+}
+
+/// Result of a tapped offer button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OfferTap<T> {
+    /// The callback was not produced by this store's buttons
+    NotOffer,
+    /// The offer expired, was already used, or belongs to another message
+    Expired,
+    /// The choice the user tapped
+    Chosen(T),
+}
+
+struct Offer<T> {
+    chat_id: i64,
+    message_id: Option<i32>,
+    choices: Vec<T>,
+    created_at: Instant,
+}
+
+/// Offered choices of type `T`, keyed by offer ID until tapped or expired
+pub struct OfferStore<T> {
+    prefix: &'static str,
+    ttl: Duration,
+    next_id: AtomicU64,
+    offers: Mutex<HashMap<u64, Offer<T>>>,
+}
+
+impl<T> OfferStore<T> {
+    /// Create a store whose buttons carry `prefix` and stay for `ttl`
+    pub fn new(prefix: &'static str, ttl: Duration) -> Self {
+        Self {
+            prefix,
+            ttl,
+            // Random start so buttons left over from a previous run do not match new offers
+            next_id: AtomicU64::new(rand::random::<u32>() as u64),
+            offers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Store `choices` and build one button per choice, labelled by `label`
+    ///
+    /// Returns `None` when there is nothing to offer. The offer only accepts
+    /// taps once [`OfferStore::attach`] names the message carrying it.
+    pub fn offer(
+        &self,
+        chat_id: i64,
+        choices: Vec<T>,
+        now: Instant,
+        label: impl Fn(&T) -> String,
+    ) -> Option<(u64, Vec<InlineKeyboardButton>)> {
+        if choices.is_empty() {
+            return None;
+        }
+        let offer_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let buttons = choices
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                InlineKeyboardButton::callback(
+                    truncate_label(&label(choice), MAX_LABEL_CHARS),
+                    callback_data(self.prefix, offer_id, i),
+                )
+            })
+            .collect();
+        self.offers.lock().unwrap().insert(
+            offer_id,
+            Offer {
+                chat_id,
+                message_id: None,
+                choices,
+                created_at: now,
+            },
+        );
+        Some((offer_id, buttons))
+    }
+
+    /// Record the message the keyboard was posted with
+    pub fn attach(&self, offer_id: u64, message_id: i32) {
+        if let Some(offer) = self.offers.lock().unwrap().get_mut(&offer_id) {
+            offer.message_id = Some(message_id);
+        }
+    }
+
+    /// Drop an offer whose keyboard could not be posted
+    pub fn discard(&self, offer_id: u64) {
+        self.offers.lock().unwrap().remove(&offer_id);
+    }
+
+    /// Resolve a tap on `message_id` in `chat_id`, consuming the whole offer
+    pub fn tap(&self, data: &str, chat_id: i64, message_id: i32, now: Instant) -> OfferTap<T> {
+        let Some((offer_id, index)) = parse_callback_data(self.prefix, data) else {
+            return OfferTap::NotOffer;
+        };
+        let mut offers = self.offers.lock().unwrap();
+        let matches = offers
+            .get(&offer_id)
+            .is_some_and(|offer| offer.chat_id == chat_id && offer.message_id == Some(message_id));
+        if !matches {
+            return OfferTap::Expired;
+        }
+        let offer = offers.remove(&offer_id).expect("offer checked above");
+        if now.duration_since(offer.created_at) >= self.ttl {
+            return OfferTap::Expired;
+        }
+        match offer.choices.into_iter().nth(index) {
+            Some(choice) => OfferTap::Chosen(choice),
+            None => OfferTap::Expired,
+        }
+    }
+
+    /// Remove expired offers, returning the messages whose keyboard should be cleared
+    pub fn expire(&self, now: Instant) -> Vec<(i64, i32)> {
+        let ttl = self.ttl;
+        let mut cleared = Vec::new();
+        self.offers.lock().unwrap().retain(|_, offer| {
+            let live = now.duration_since(offer.created_at) < ttl;
+            if !live {
+                if let Some(message_id) = offer.message_id {
+                    cleared.push((offer.chat_id, message_id));
+                }
+            }
+            live
+        });
+        cleared
+    }
+
+    pub fn len(&self) -> usize {
+        self.offers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Remove the keyboard under a reply
+pub async fn clear_keyboard(bot: &Bot, chat_id: i64, message_id: i32) {
+    if let Err(e) = bot
+        .edit_message_reply_markup(ChatId(chat_id), MessageId(message_id))
+        .await
+    {
+        debug!(chat_id = %chat_id, error = %e, "Failed to remove offer keyboard");
+    }
+}
+
+/// Periodically remove expired offers and their keyboards until the task is aborted
+pub fn spawn_offer_sweeper<T: Send + 'static>(
+    store: Arc<OfferStore<T>>,
+    bot: Bot,
+) -> JoinHandle<()> {
+    let interval = store
+        .ttl
+        .min(MAX_SWEEP_INTERVAL)
+        .max(Duration::from_secs(1));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (chat_id, message_id) in store.expire(Instant::now()) {
+                clear_keyboard(&bot, chat_id, message_id).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Pick(u32);
+
+    fn store(ttl: u64) -> OfferStore<Pick> {
+        OfferStore::new("t:", Duration::from_secs(ttl))
+    }
+
+    #[test]
+    fn test_callback_data_round_trips_per_prefix() {
+        let data = callback_data("t:", u64::MAX, 3);
+        assert!(data.len() <= MAX_CALLBACK_DATA);
+        assert_eq!(parse_callback_data("t:", &data), Some((u64::MAX, 3)));
+        assert_eq!(parse_callback_data("u:", &data), None);
+        assert_eq!(parse_callback_data("t:", "t:x:1"), None);
+        assert_eq!(truncate_label("short", 10), "short");
+        assert_eq!(truncate_label("a longer label", 6), "a lon…");
+    }
+
+    #[test]
+    fn test_any_payload_is_tapped_once() {
+        let store = store(60);
+        let now = Instant::now();
+        assert!(store.offer(1, Vec::new(), now, |_| String::new()).is_none());
+        let (offer_id, buttons) = store
+            .offer(1, vec![Pick(7), Pick(8)], now, |p| format!("Pick {}", p.0))
+            .unwrap();
+        assert_eq!(buttons[1].text, "Pick 8");
+
+        let data = callback_data("t:", offer_id, 1);
+        // Not attached to a message yet
+        assert_eq!(store.tap(&data, 1, 5, now), OfferTap::Expired);
+        store.attach(offer_id, 5);
+        assert_eq!(store.tap(&data, 2, 5, now), OfferTap::Expired);
+        assert_eq!(store.tap(&data, 1, 5, now), OfferTap::Chosen(Pick(8)));
+        assert_eq!(store.tap(&data, 1, 5, now), OfferTap::Expired);
+        assert_eq!(store.tap("other:1:0", 1, 5, now), OfferTap::NotOffer);
+
+        let (offer_id, _) = store.offer(1, vec![Pick(1)], now, |_| "x".into()).unwrap();
+        store.discard(offer_id);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_offers_expire_after_ttl() {
+        let store = store(60);
+        let now = Instant::now();
+        let (posted, _) = store.offer(1, vec![Pick(1)], now, |_| "x".into()).unwrap();
+        store.attach(posted, 10);
+        // Keyboard never posted
+        store.offer(2, vec![Pick(2)], now, |_| "y".into()).unwrap();
+
+        assert!(store.expire(now + Duration::from_secs(30)).is_empty());
+        assert_eq!(store.len(), 2);
+
+        let late = now + Duration::from_secs(61);
+        // Only the posted keyboard needs clearing
+        assert_eq!(store.expire(late), vec![(1, 10)]);
+        assert!(store.is_empty());
+
+        // Taps after the TTL are refused even before a sweep
+        let (offer_id, _) = store.offer(3, vec![Pick(3)], now, |_| "z".into()).unwrap();
+        store.attach(offer_id, 11);
+        assert_eq!(
+            store.tap(&callback_data("t:", offer_id, 0), 3, 11, late),
+            OfferTap::Expired
+        );
+    }
+}
@@ -0,0 +1,547 @@
+//! Welcome message for groups the bot is added to
+//!
+//! When a `my_chat_member` update shows the bot joining a group, it posts a
+//! one-time message explaining how to address it (mention, reply, voice
+//! trigger words) and the key commands, with two buttons:
+//!
+//! - "Enable for everyone here" answers every message in the chat, and lets
+//!   the chat through `allowed_chats` when that list is set.
+//! - "Quiet mode" answers only mentions and replies; other messages get at
+//!   most a near-miss "seen" reaction.
+//!
+//! Only configured admins and the chat's own administrators can press them.
+//! The choice is kept in the [`StateStore`] under `telegram:group_mode`.
+//!
+//! That the message was shown is recorded under `telegram:onboarding` for
+//! [`OnboardingConfig::repeat_after`], so removing and re-adding the bot
+//! within that period stays quiet. When the bot joins without permission to
+//! post (or the send fails), it tries once more after being promoted to
+//! administrator.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use teloxide::types::{ChatMemberKind, InlineKeyboardButton, InlineKeyboardMarkup};
+use tracing::{info, warn};
+use zoey_core::StateStore;
+
+/// State store namespace recording chats that were shown the welcome message
+const SHOWN_NAMESPACE: &str = "telegram:onboarding";
+
+/// State store namespace holding each group's chosen [`GroupMode`]
+const MODE_NAMESPACE: &str = "telegram:group_mode";
+
+/// Prefix of callback data produced by the welcome buttons
+const CALLBACK_PREFIX: &str = "ob:";
+
+/// Default time before a re-added bot welcomes the group again
+pub const DEFAULT_ONBOARDING_REPEAT: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Group onboarding settings
+#[derive(Debug, Clone)]
+pub struct OnboardingConfig {
+    /// How long after the welcome message a re-add does not repeat it
+    pub repeat_after: Duration,
+}
+
+impl Default for OnboardingConfig {
+    fn default() -> Self {
+        Self {
+            repeat_after: DEFAULT_ONBOARDING_REPEAT,
+        }
+    }
+}
+
+/// The bot's membership in a chat, as far as onboarding cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberStatus {
+    /// Not in the chat (never joined, left or removed)
+    Absent,
+    /// In the chat but not allowed to send messages
+    Muted,
+    Member,
+    Administrator,
+}
+
+impl MemberStatus {
+    /// Whether the bot is in the chat
+    pub fn is_present(self) -> bool {
+        self != MemberStatus::Absent
+    }
+
+    /// Whether the bot may post in the chat
+    pub fn can_send(self) -> bool {
+        matches!(self, MemberStatus::Member | MemberStatus::Administrator)
+    }
+}
+
+impl From<&ChatMemberKind> for MemberStatus {
+    fn from(kind: &ChatMemberKind) -> Self {
+        match kind {
+            ChatMemberKind::Owner(_) | ChatMemberKind::Administrator(_) => {
+                MemberStatus::Administrator
+            }
+            ChatMemberKind::Member { .. } => MemberStatus::Member,
+            ChatMemberKind::Restricted(restricted) if !restricted.is_member => MemberStatus::Absent,
+            ChatMemberKind::Restricted(restricted) if restricted.can_send_messages => {
+                MemberStatus::Member
+            }
+            ChatMemberKind::Restricted(_) => MemberStatus::Muted,
+            ChatMemberKind::Left | ChatMemberKind::Banned(_) => MemberStatus::Absent,
+        }
+    }
+}
+
+/// What a membership change means for onboarding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// The bot was added to the chat
+    Joined,
+    /// The bot, already in the chat, became an administrator
+    Promoted,
+    /// The bot left or was removed
+    Removed,
+    /// Anything else (e.g. a permission change)
+    Unchanged,
+}
+
+/// Classify a `my_chat_member` update from the bot's old and new status
+pub fn transition(old: MemberStatus, new: MemberStatus) -> Transition {
+    match (old.is_present(), new.is_present()) {
+        (false, true) => Transition::Joined,
+        (true, false) => Transition::Removed,
+        (true, true)
+            if old != MemberStatus::Administrator && new == MemberStatus::Administrator =>
+        {
+            Transition::Promoted
+        }
+        _ => Transition::Unchanged,
+    }
+}
+
+/// How the bot behaves in a group, chosen from the welcome buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupMode {
+    /// Answer every message in the chat
+    Everyone,
+    /// Answer only mentions and replies; react to near misses
+    Quiet,
+}
+
+impl GroupMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            GroupMode::Everyone => "everyone",
+            GroupMode::Quiet => "quiet",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "everyone" => Some(GroupMode::Everyone),
+            "quiet" => Some(GroupMode::Quiet),
+            _ => None,
+        }
+    }
+
+    /// Confirmation shown to whoever pressed the button
+    pub fn confirmation(self) -> &'static str {
+        match self {
+            GroupMode::Everyone => "I’ll answer everyone in this chat.",
+            GroupMode::Quiet => "Quiet mode: I’ll only answer mentions and replies.",
+        }
+    }
+}
+
+/// Callback data for a welcome button
+pub fn callback_data(mode: GroupMode) -> String {
+    format!("{}{}", CALLBACK_PREFIX, mode.as_str())
+}
+
+/// The mode a welcome button selects, or `None` for other callback data
+pub fn parse_callback(data: &str) -> Option<GroupMode> {
+    data.strip_prefix(CALLBACK_PREFIX)
+        .and_then(GroupMode::parse)
+}
+
+/// Buttons posted under the welcome message
+pub fn keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            "Enable for everyone here",
+            callback_data(GroupMode::Everyone),
+        ),
+        InlineKeyboardButton::callback("Quiet mode", callback_data(GroupMode::Quiet)),
+    ]])
+}
+
+/// Welcome message text: how to address the bot, then `commands` (one per line)
+pub fn welcome_text(
+    bot_name: &str,
+    bot_username: Option<&str>,
+    voice_triggers: &[String],
+    commands: &str,
+) -> String {
+    let mut text = format!(
+        "Hi, I’m {}! Thanks for adding me.\n\nTo talk to me here:\n",
+        bot_name
+    );
+    if let Some(username) = bot_username {
+        text.push_str(&format!("• mention @{}\n", username));
+    }
+    text.push_str("• reply to one of my messages\n");
+    if !voice_triggers.is_empty() {
+        let quoted = voice_triggers
+            .iter()
+            .map(|t| format!("“{}”", t))
+            .collect::<Vec<_>>()
+            .join(", ");
+        text.push_str(&format!("• say {} for a spoken reply\n", quoted));
+    }
+    if !commands.is_empty() {
+        text.push_str(&format!("\nCommands:\n{}\n", commands));
+    }
+    text.push_str("\nAn admin can let me answer everyone here, or keep me quiet.");
+    text
+}
+
+/// What to do after a membership change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingAction {
+    /// Post the welcome message; `retry` when this is the second attempt
+    Send {
+        retry: bool,
+    },
+    Skip,
+}
+
+/// Result of pressing a welcome button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingTap {
+    /// The data did not come from a welcome button
+    NotOnboarding,
+    /// The caller is not an admin
+    Denied,
+    /// The mode was stored
+    Applied(GroupMode),
+}
+
+/// Tracks welcome messages and per-group modes
+pub struct Onboarding {
+    config: OnboardingConfig,
+    admins: HashSet<u64>,
+    store: Arc<dyn StateStore>,
+    /// Chats whose welcome message waits for the bot to be promoted
+    pending: Mutex<HashSet<i64>>,
+    /// Per-chat mode, cached from the store
+    modes: RwLock<HashMap<i64, Option<GroupMode>>>,
+}
+
+impl Onboarding {
+    pub fn new(config: OnboardingConfig, admins: HashSet<u64>, store: Arc<dyn StateStore>) -> Self {
+        Self {
+            config,
+            admins,
+            store,
+            pending: Mutex::new(HashSet::new()),
+            modes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Decide whether a `my_chat_member` update should post the welcome message
+    pub async fn observe(
+        &self,
+        chat_id: i64,
+        is_private: bool,
+        old: MemberStatus,
+        new: MemberStatus,
+    ) -> OnboardingAction {
+        if is_private {
+            return OnboardingAction::Skip;
+        }
+        match transition(old, new) {
+            Transition::Joined if new.can_send() => {
+                if self.claim(chat_id).await {
+                    OnboardingAction::Send { retry: false }
+                } else {
+                    OnboardingAction::Skip
+                }
+            }
+            Transition::Joined => {
+                if !self.was_shown(chat_id).await {
+                    self.pending.lock().unwrap().insert(chat_id);
+                    info!(chat_id = %chat_id, "Bot cannot post yet, welcome deferred until promotion");
+                }
+                OnboardingAction::Skip
+            }
+            Transition::Promoted => {
+                let deferred = self.pending.lock().unwrap().remove(&chat_id);
+                if deferred && self.claim(chat_id).await {
+                    OnboardingAction::Send { retry: true }
+                } else {
+                    OnboardingAction::Skip
+                }
+            }
+            Transition::Removed => {
+                self.pending.lock().unwrap().remove(&chat_id);
+                OnboardingAction::Skip
+            }
+            Transition::Unchanged => OnboardingAction::Skip,
+        }
+    }
+
+    /// The welcome message could not be posted; wait for a promotion unless this was the retry
+    pub async fn send_failed(&self, chat_id: i64, retry: bool) {
+        if let Err(e) = self
+            .store
+            .delete(SHOWN_NAMESPACE, &chat_id.to_string())
+            .await
+        {
+            warn!(chat_id = %chat_id, error = %e, "Failed to clear onboarding record");
+        }
+        if !retry {
+            self.pending.lock().unwrap().insert(chat_id);
+        }
+    }
+
+    /// Record the welcome message as shown, returning false if it already was
+    async fn claim(&self, chat_id: i64) -> bool {
+        let shown_at = serde_json::json!(chrono::Utc::now().timestamp());
+        match self
+            .store
+            .set_if_absent(
+                SHOWN_NAMESPACE,
+                &chat_id.to_string(),
+                shown_at,
+                Some(self.config.repeat_after),
+            )
+            .await
+        {
+            Ok(claimed) => claimed,
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Onboarding check failed, skipping welcome");
+                false
+            }
+        }
+    }
+
+    async fn was_shown(&self, chat_id: i64) -> bool {
+        matches!(
+            self.store.get(SHOWN_NAMESPACE, &chat_id.to_string()).await,
+            Ok(Some(_))
+        )
+    }
+
+    /// Mode chosen for a chat, `None` when no one pressed a button
+    pub async fn group_mode(&self, chat_id: i64) -> Option<GroupMode> {
+        if let Some(&mode) = self.modes.read().unwrap().get(&chat_id) {
+            return mode;
+        }
+        let mode = match self.store.get(MODE_NAMESPACE, &chat_id.to_string()).await {
+            Ok(stored) => stored
+                .as_ref()
+                .and_then(|v| v.as_str())
+                .and_then(GroupMode::parse),
+            Err(e) => {
+                warn!(chat_id = %chat_id, error = %e, "Failed to load group mode");
+                return None;
+            }
+        };
+        self.modes.write().unwrap().insert(chat_id, mode);
+        mode
+    }
+
+    /// Apply a welcome button press by `caller_id`
+    ///
+    /// `caller_is_chat_admin` is whether Telegram lists the caller as an
+    /// administrator of the chat; configured admins may press it anywhere.
+    pub async fn handle_callback(
+        &self,
+        data: &str,
+        chat_id: i64,
+        caller_id: u64,
+        caller_is_chat_admin: bool,
+    ) -> OnboardingTap {
+        let Some(mode) = parse_callback(data) else {
+            return OnboardingTap::NotOnboarding;
+        };
+        if !caller_is_chat_admin && !self.admins.contains(&caller_id) {
+            return OnboardingTap::Denied;
+        }
+        if let Err(e) = self
+            .store
+            .set(
+                MODE_NAMESPACE,
+                &chat_id.to_string(),
+                serde_json::Value::String(mode.as_str().to_string()),
+                None,
+            )
+            .await
+        {
+            warn!(chat_id = %chat_id, error = %e, "Failed to persist group mode");
+        }
+        self.modes.write().unwrap().insert(chat_id, Some(mode));
+        info!(chat_id = %chat_id, mode = %mode.as_str(), "Telegram group mode changed");
+        OnboardingTap::Applied(mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::MemoryStateStore;
+
+    const CHAT: i64 = -100;
+
+    fn onboarding(store: Arc<dyn StateStore>) -> Onboarding {
+        Onboarding::new(OnboardingConfig::default(), HashSet::from([1]), store)
+    }
+
+    #[test]
+    fn test_transition_detection() {
+        use MemberStatus::*;
+        assert_eq!(transition(Absent, Member), Transition::Joined);
+        assert_eq!(transition(Absent, Administrator), Transition::Joined);
+        assert_eq!(transition(Absent, Muted), Transition::Joined);
+        assert_eq!(transition(Member, Administrator), Transition::Promoted);
+        assert_eq!(transition(Muted, Administrator), Transition::Promoted);
+        assert_eq!(transition(Administrator, Member), Transition::Unchanged);
+        assert_eq!(transition(Member, Muted), Transition::Unchanged);
+        assert_eq!(transition(Member, Absent), Transition::Removed);
+        assert_eq!(transition(Absent, Absent), Transition::Unchanged);
+    }
+
+    #[tokio::test]
+    async fn test_welcome_shown_once() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let first = onboarding(store.clone());
+        use MemberStatus::*;
+        assert_eq!(
+            first.observe(CHAT, false, Absent, Member).await,
+            OnboardingAction::Send { retry: false }
+        );
+        assert_eq!(
+            first.observe(CHAT, false, Member, Absent).await,
+            OnboardingAction::Skip
+        );
+        assert_eq!(
+            first.observe(CHAT, false, Absent, Member).await,
+            OnboardingAction::Skip
+        );
+
+        // Another instance sharing the store (e.g. after restart) remembers it too
+        let second = onboarding(store);
+        assert_eq!(
+            second.observe(CHAT, false, Absent, Administrator).await,
+            OnboardingAction::Skip
+        );
+        assert_eq!(
+            second.observe(CHAT - 1, false, Absent, Member).await,
+            OnboardingAction::Send { retry: false }
+        );
+        assert_eq!(
+            second.observe(7, true, Absent, Member).await,
+            OnboardingAction::Skip
+        );
+    }
+
+    #[tokio::test]
+    async fn test_welcome_repeats_after_period() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let config = OnboardingConfig {
+            repeat_after: Duration::from_millis(20),
+        };
+        let onboarding = Onboarding::new(config, HashSet::new(), store);
+        use MemberStatus::*;
+        assert_eq!(
+            onboarding.observe(CHAT, false, Absent, Member).await,
+            OnboardingAction::Send { retry: false }
+        );
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(
+            onboarding.observe(CHAT, false, Absent, Member).await,
+            OnboardingAction::Send { retry: false }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_buttons_are_admin_only() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let gate = onboarding(store.clone());
+        let everyone = callback_data(GroupMode::Everyone);
+
+        assert_eq!(
+            gate.handle_callback("fu:1:0", CHAT, 1, true).await,
+            OnboardingTap::NotOnboarding
+        );
+        assert_eq!(
+            gate.handle_callback(&everyone, CHAT, 2, false).await,
+            OnboardingTap::Denied
+        );
+        assert_eq!(gate.group_mode(CHAT).await, None);
+
+        // A chat administrator, then a configured admin
+        assert_eq!(
+            gate.handle_callback(&everyone, CHAT, 2, true).await,
+            OnboardingTap::Applied(GroupMode::Everyone)
+        );
+        assert_eq!(
+            gate.handle_callback(&callback_data(GroupMode::Quiet), CHAT, 1, false)
+                .await,
+            OnboardingTap::Applied(GroupMode::Quiet)
+        );
+        assert_eq!(
+            onboarding(store).group_mode(CHAT).await,
+            Some(GroupMode::Quiet)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deferred_send_after_promotion() {
+        let onboarding = onboarding(Arc::new(MemoryStateStore::new()));
+        use MemberStatus::*;
+
+        // Joined without permission to post: nothing until promoted
+        assert_eq!(
+            onboarding.observe(CHAT, false, Absent, Muted).await,
+            OnboardingAction::Skip
+        );
+        assert_eq!(
+            onboarding.observe(CHAT, false, Muted, Administrator).await,
+            OnboardingAction::Send { retry: true }
+        );
+        assert_eq!(
+            onboarding.observe(CHAT, false, Administrator, Member).await,
+            OnboardingAction::Skip
+        );
+
+        // A failed first send is retried once after promotion, then given up
+        let other = CHAT - 1;
+        assert_eq!(
+            onboarding.observe(other, false, Absent, Member).await,
+            OnboardingAction::Send { retry: false }
+        );
+        onboarding.send_failed(other, false).await;
+        assert_eq!(
+            onboarding
+                .observe(other, false, Member, Administrator)
+                .await,
+            OnboardingAction::Send { retry: true }
+        );
+        onboarding.send_failed(other, true).await;
+        assert_eq!(
+            onboarding
+                .observe(other, false, Member, Administrator)
+                .await,
+            OnboardingAction::Skip
+        );
+
+        // Promotion without a deferred welcome posts nothing
+        assert_eq!(
+            onboarding
+                .observe(CHAT - 2, false, Member, Administrator)
+                .await,
+            OnboardingAction::Skip
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Task-polling fallback for backends without `/chat/stream`
+//!
+//! Some self-hosted backends only implement `POST /chat`, which answers with
+//! a task ID, and `GET /task/:id` to poll it. When `/chat/stream` answers 404
+//! or 405 the backend is remembered as polling-only, so later messages go
+//! straight to `/chat` instead of probing the stream endpoint again.
+//! [`TelegramConfig::streaming`] skips detection altogether.
+//!
+//! Polls back off exponentially up to a cap and give up at a deadline; while
+//! waiting, the placeholder is periodically replaced with "Still thinking…".
+//!
+//! [`TelegramConfig::streaming`]: crate::TelegramConfig::streaming
+
+use reqwest::Client as HttpClient;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Placeholder text while a polled task is still running
+pub const STILL_THINKING_MESSAGE: &str = "Still thinking…";
+
+/// Reply when a polled task misses the deadline
+pub const DEADLINE_MESSAGE: &str = "Sorry, that's taking too long. Please try again in a moment.";
+
+/// How replies are fetched from a backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatMode {
+    /// `POST /chat/stream` (server-sent events)
+    Streaming,
+    /// `POST /chat`, then poll `GET /task/:id`
+    Polling,
+}
+
+/// Chat mode per backend URL, detected on first use unless forced
+pub struct ChatModes {
+    forced: Option<ChatMode>,
+    detected: RwLock<HashMap<String, ChatMode>>,
+}
+
+impl ChatModes {
+    /// `streaming` forces a mode; `None` detects it per backend
+    pub fn new(streaming: Option<bool>) -> Self {
+        Self {
+            forced: streaming.map(|s| {
+                if s {
+                    ChatMode::Streaming
+                } else {
+                    ChatMode::Polling
+                }
+            }),
+            detected: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Mode to use for `api_base`; backends not yet known to lack streaming are streamed
+    pub fn mode(&self, api_base: &str) -> ChatMode {
+        if let Some(mode) = self.forced {
+            return mode;
+        }
+        self.detected
+            .read()
+            .unwrap()
+            .get(api_base)
+            .copied()
+            .unwrap_or(ChatMode::Streaming)
+    }
+
+    /// Record the outcome of a `/chat/stream` request
+    ///
+    /// Returns true when the status shows the backend has no stream endpoint
+    /// and it was switched to polling. Forced modes are never switched.
+    pub fn observe_stream_status(&self, api_base: &str, status: u16) -> bool {
+        if self.forced.is_some() || !matches!(status, 404 | 405) {
+            return false;
+        }
+        info!(api_base = %api_base, status, "Streaming unavailable, polling tasks for this backend");
+        self.detected
+            .write()
+            .unwrap()
+            .insert(api_base.to_string(), ChatMode::Polling);
+        true
+    }
+}
+
+impl Default for ChatModes {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Backoff and deadline for polling a chat task
+#[derive(Debug, Clone)]
+pub struct PollConfig {
+    /// Delay before the second poll; doubles after each poll
+    pub initial_interval: Duration,
+    /// Longest delay between polls
+    pub max_interval: Duration,
+    /// Give up once the task has been polled for this long
+    pub deadline: Duration,
+    /// How often the placeholder is refreshed with "Still thinking…"
+    pub still_thinking_interval: Duration,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(250),
+            max_interval: Duration::from_secs(4),
+            deadline: Duration::from_secs(90),
+            still_thinking_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl PollConfig {
+    /// Delay after the `attempt`th poll (0-based): exponential, capped at `max_interval`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial_interval
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_interval)
+    }
+}
+
+/// State of a chat task from `GET /task/:id`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskState {
+    Running,
+    Completed(serde_json::Value),
+    Failed(String),
+}
+
+impl TaskState {
+    /// Read a task status response (`{status, result, error}`)
+    pub fn from_response(json: &serde_json::Value) -> Self {
+        match json.get("status").and_then(|s| s.as_str()) {
+            Some("completed") => Self::Completed(json.get("result").cloned().unwrap_or_default()),
+            Some("failed") => Self::Failed(
+                json.get("error")
+                    .and_then(|e| e.as_str())
+                    .unwrap_or("Chat task failed")
+                    .to_string(),
+            ),
+            _ => Self::Running,
+        }
+    }
+}
+
+/// Text of the messages in a completed chat task result, joined by blank lines
+///
+/// The raw text is returned; callers extract the reply from its XML like a
+/// streamed response.
+pub fn task_result_text(result: &serde_json::Value) -> String {
+    result
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .map(|messages| {
+            messages
+                .iter()
+                .filter_map(|m| m.get("content")?.get("text")?.as_str())
+                .filter(|t| !t.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .unwrap_or_default()
+}
+
+/// How polling a chat task ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollOutcome {
+    /// Raw reply text from the task's messages
+    Completed(String),
+    Failed(String),
+    /// The deadline passed before the task finished
+    Expired,
+}
+
+impl PollOutcome {
+    /// Text shown instead of an answer when the task did not complete
+    pub fn failure_text(&self) -> Option<&'static str> {
+        match self {
+            Self::Completed(_) => None,
+            Self::Failed(_) => Some("Error"),
+            Self::Expired => Some(DEADLINE_MESSAGE),
+        }
+    }
+}
+
+/// Poll with `fetch` until the task finishes or the deadline passes
+///
+/// `still_thinking` runs every `still_thinking_interval` while waiting. Fetch
+/// errors are retried until the deadline.
+pub async fn poll_task<F, Fut, W, WFut>(
+    config: &PollConfig,
+    mut fetch: F,
+    mut still_thinking: W,
+) -> PollOutcome
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<TaskState, String>>,
+    W: FnMut() -> WFut,
+    WFut: Future<Output = ()>,
+{
+    let started = Instant::now();
+    let mut last_notice = started;
+    let mut attempt = 0;
+    loop {
+        match fetch().await {
+            Ok(TaskState::Completed(result)) => {
+                return PollOutcome::Completed(task_result_text(&result))
+            }
+            Ok(TaskState::Failed(error)) => return PollOutcome::Failed(error),
+            Ok(TaskState::Running) => {}
+            Err(e) => warn!(error = %e, attempt, "Task poll failed, retrying"),
+        }
+        let elapsed = started.elapsed();
+        if elapsed >= config.deadline {
+            return PollOutcome::Expired;
+        }
+        if last_notice.elapsed() >= config.still_thinking_interval {
+            still_thinking().await;
+            last_notice = Instant::now();
+        }
+        tokio::time::sleep(config.delay(attempt).min(config.deadline - elapsed)).await;
+        attempt += 1;
+    }
+}
+
+/// Submit `body` to `POST {api_base}/chat` and poll its task
+pub async fn chat_via_task<W, WFut>(
+    client: &HttpClient,
+    api_base: &str,
+    body: &serde_json::Value,
+    config: &PollConfig,
+    still_thinking: W,
+) -> PollOutcome
+where
+    W: FnMut() -> WFut,
+    WFut: Future<Output = ()>,
+{
+    let mut body = body.clone();
+    body["stream"] = serde_json::Value::Bool(false);
+    let task_id = match submit_chat(client, api_base, &body).await {
+        Ok(task_id) => task_id,
+        Err(e) => return PollOutcome::Failed(e),
+    };
+    let url = &format!("{}/task/{}", api_base, task_id);
+    poll_task(
+        config,
+        move || async move {
+            let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+            if !resp.status().is_success() {
+                return Err(format!("task status {}", resp.status()));
+            }
+            let json: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+            Ok(TaskState::from_response(&json))
+        },
+        still_thinking,
+    )
+    .await
+}
+
+/// `POST /chat`, returning the task ID to poll
+async fn submit_chat(
+    client: &HttpClient,
+    api_base: &str,
+    body: &serde_json::Value,
+) -> std::result::Result<String, String> {
+    let resp = client
+        .post(format!("{}/chat", api_base))
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| format!("chat request failed: {}", e))?;
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(if text.is_empty() {
+            format!("chat request rejected: {}", status)
+        } else {
+            text
+        });
+    }
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| json.get("taskId")?.as_str().map(str::to_string))
+        .ok_or_else(|| "chat response has no taskId".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_mode_detection_is_cached_per_backend() {
+        let modes = ChatModes::default();
+        let a = "http://a/agent";
+        let b = "http://b/agent";
+        assert_eq!(modes.mode(a), ChatMode::Streaming);
+
+        // Other failures don't mean streaming is missing
+        assert!(!modes.observe_stream_status(a, 500));
+        assert_eq!(modes.mode(a), ChatMode::Streaming);
+
+        assert!(modes.observe_stream_status(a, 404));
+        assert_eq!(modes.mode(a), ChatMode::Polling);
+        assert_eq!(modes.mode(a), ChatMode::Polling);
+        assert_eq!(modes.mode(b), ChatMode::Streaming);
+        assert!(modes.observe_stream_status(b, 405));
+        assert_eq!(modes.mode(b), ChatMode::Polling);
+
+        // A forced mode is never switched
+        let forced = ChatModes::new(Some(true));
+        assert!(!forced.observe_stream_status(a, 404));
+        assert_eq!(forced.mode(a), ChatMode::Streaming);
+        assert_eq!(ChatModes::new(Some(false)).mode(a), ChatMode::Polling);
+    }
+
+    #[test]
+    fn test_backoff_schedule() {
+        let config = PollConfig {
+            initial_interval: Duration::from_millis(250),
+            max_interval: Duration::from_secs(2),
+            ..PollConfig::default()
+        };
+        let schedule: Vec<u64> = (0..6).map(|n| config.delay(n).as_millis() as u64).collect();
+        assert_eq!(schedule, vec![250, 500, 1000, 2000, 2000, 2000]);
+        assert_eq!(config.delay(u32::MAX), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_result_extraction_from_task_payload() {
+        let payload = serde_json::json!({
+            "taskId": "5b1c6a9e-3f55-4a4b-9d0e-6f3f1f7f2a10",
+            "status": "completed",
+            "result": {
+                "success": true,
+                "messages": [
+                    { "id": "m1", "content": { "text": "<response><thought>greet</thought><text>Hi there!</text></response>" } },
+                    { "id": "m2", "content": { "text": "  " } },
+                    { "id": "m3", "content": { "source": "agent" } }
+                ]
+            },
+            "durationMs": 812
+        });
+        let TaskState::Completed(result) = TaskState::from_response(&payload) else {
+            panic!("expected a completed task");
+        };
+        let text = task_result_text(&result);
+        assert_eq!(
+            text,
+            "<response><thought>greet</thought><text>Hi there!</text></response>"
+        );
+        assert_eq!(crate::extract_final_text_from_xml(&text), "Hi there!");
+
+        let failed = serde_json::json!({ "status": "failed", "error": "Chat task timed out" });
+        assert_eq!(
+            TaskState::from_response(&failed),
+            TaskState::Failed("Chat task timed out".into())
+        );
+        assert_eq!(
+            TaskState::from_response(&serde_json::json!({ "status": "running" })),
+            TaskState::Running
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadline_expiry() {
+        let config = PollConfig {
+            initial_interval: Duration::from_millis(5),
+            max_interval: Duration::from_millis(10),
+            deadline: Duration::from_millis(60),
+            still_thinking_interval: Duration::from_millis(20),
+        };
+        let (polls, notices) = (&AtomicU32::new(0), &AtomicU32::new(0));
+        let outcome = poll_task(
+            &config,
+            move || async move {
+                polls.fetch_add(1, Ordering::SeqCst);
+                Ok(TaskState::Running)
+            },
+            move || async move {
+                notices.fetch_add(1, Ordering::SeqCst);
+            },
+        )
+        .await;
+        assert_eq!(outcome, PollOutcome::Expired);
+        assert_eq!(outcome.failure_text(), Some(DEADLINE_MESSAGE));
+        assert!(polls.load(Ordering::SeqCst) >= 3);
+        assert!(notices.load(Ordering::SeqCst) >= 1);
+
+        // A task finishing before the deadline is returned
+        let polls = &AtomicU32::new(0);
+        let outcome = poll_task(
+            &config,
+            move || async move {
+                if polls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err("connection reset".to_string())
+                } else {
+                    Ok(TaskState::Completed(serde_json::json!({
+                        "messages": [{ "content": { "text": "done" } }]
+                    })))
+                }
+            },
+            || async {},
+        )
+        .await;
+        assert_eq!(outcome, PollOutcome::Completed("done".into()));
+        assert_eq!(outcome.failure_text(), None);
+    }
+}
@@ -0,0 +1,459 @@
+//! Scheduled sends: reminders the agent promised to deliver later
+//!
+//! The `telegram` send handler registered by `TelegramPlugin` posts a
+//! message right away unless the target's metadata asks for later delivery
+//! with [`DELIVER_AT_KEY`] (unix seconds) or [`DELAY_SECS_KEY`]. Such a
+//! message is rendered as usual and queued in the [`SendScheduler`], whose
+//! task ([`SendScheduler::start`]) posts it once due. A time that has
+//! already passed sends immediately.
+//!
+//! - Every scheduled send has a key, [`SCHEDULE_KEY`] or a generated one;
+//!   scheduling under a pending key replaces that send.
+//! - A send to the same target with [`CANCEL_KEY`] revokes the pending send
+//!   with that key instead of posting anything, so a follow-up action can
+//!   take a reminder back. [`SendScheduler::cancel`] does the same in code.
+//! - With a [`ScheduledSendStore`] pending sends are saved as they are
+//!   queued and reloaded when the scheduler starts, so reminders survive a
+//!   restart; [`StateScheduledSendStore`] keeps them in a [`StateStore`].
+//!   Without one they live in memory only.
+//! - A send that fails is logged and dropped rather than retried.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use zoey_core::{Result, StateStore, ZoeyError};
+
+/// Target metadata key: unix timestamp (seconds) to deliver the message at
+pub const DELIVER_AT_KEY: &str = "deliver_at";
+
+/// Target metadata key: seconds from now to deliver the message after
+pub const DELAY_SECS_KEY: &str = "delay_secs";
+
+/// Target metadata key naming a scheduled send, so it can be cancelled later
+pub const SCHEDULE_KEY: &str = "schedule_key";
+
+/// Target metadata key: cancel the pending send with this key instead of sending
+pub const CANCEL_KEY: &str = "cancel_schedule";
+
+/// State store namespace holding pending sends
+const SCHEDULED_NAMESPACE: &str = "telegram:scheduled";
+
+/// Longest the scheduler sleeps without re-checking the queue
+const MAX_IDLE: Duration = Duration::from_secs(60);
+
+/// A message waiting to be posted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledSend {
+    /// Identifies the send for cancelling
+    pub key: String,
+    pub chat_id: i64,
+    /// Rendered text, as it will be posted
+    pub text: String,
+    /// Unix timestamp (seconds) at which it is due
+    pub deliver_at: i64,
+}
+
+/// Persistence for pending sends
+#[async_trait]
+pub trait ScheduledSendStore: Send + Sync {
+    /// Save a queued send, replacing one with the same key
+    async fn save(&self, send: &ScheduledSend) -> Result<()>;
+
+    /// Forget a delivered or cancelled send
+    async fn remove(&self, key: &str) -> Result<()>;
+
+    /// Every saved send
+    async fn load(&self) -> Result<Vec<ScheduledSend>>;
+}
+
+/// In-memory store (pending sends survive a scheduler restart, not a process restart)
+#[derive(Default)]
+pub struct MemoryScheduledSendStore {
+    sends: RwLock<HashMap<String, ScheduledSend>>,
+}
+
+#[async_trait]
+impl ScheduledSendStore for MemoryScheduledSendStore {
+    async fn save(&self, send: &ScheduledSend) -> Result<()> {
+        self.sends
+            .write()
+            .unwrap()
+            .insert(send.key.clone(), send.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.sends.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Vec<ScheduledSend>> {
+        Ok(self.sends.read().unwrap().values().cloned().collect())
+    }
+}
+
+/// Store keeping pending sends in a [`StateStore`] under `telegram:scheduled`
+pub struct StateScheduledSendStore {
+    store: Arc<dyn StateStore>,
+}
+
+impl StateScheduledSendStore {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl ScheduledSendStore for StateScheduledSendStore {
+    async fn save(&self, send: &ScheduledSend) -> Result<()> {
+        let value = serde_json::to_value(send)
+            .map_err(|e| ZoeyError::other(format!("scheduled send not serializable: {}", e)))?;
+        self.store
+            .set(SCHEDULED_NAMESPACE, &send.key, value, None)
+            .await
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.store
+            .delete(SCHEDULED_NAMESPACE, key)
+            .await
+            .map(|_| ())
+    }
+
+    async fn load(&self) -> Result<Vec<ScheduledSend>> {
+        let entries = self.store.list(SCHEDULED_NAMESPACE).await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(key, value)| match serde_json::from_value(value) {
+                Ok(send) => Some(send),
+                Err(e) => {
+                    warn!(key = %key, error = %e, "Skipping unreadable scheduled send");
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Posts a due send
+pub type DeliverFunction =
+    Arc<dyn Fn(ScheduledSend) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+/// Integer metadata value, given as a number or a numeric string
+fn metadata_number(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// When target metadata asks for a message to be delivered, if after `now`
+///
+/// `deliver_at` wins over `delay_secs`; `None` means send right away.
+pub fn requested_delivery(metadata: &HashMap<String, Value>, now: i64) -> Option<i64> {
+    let at = match metadata.get(DELIVER_AT_KEY).and_then(metadata_number) {
+        Some(at) => at,
+        None => now.saturating_add(metadata.get(DELAY_SECS_KEY).and_then(metadata_number)?),
+    };
+    (at > now).then_some(at)
+}
+
+/// Key of a send with this target metadata, generated if none was given
+pub fn schedule_key(metadata: &HashMap<String, Value>) -> String {
+    metadata
+        .get(SCHEDULE_KEY)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Current unix time in seconds
+pub fn unix_now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Pending sends ordered by due time, then by when they were queued
+///
+/// Cancelled and replaced sends stay in the heap until they reach the top;
+/// only the entry whose sequence number matches `pending` is live.
+#[derive(Default)]
+struct Queue {
+    heap: BinaryHeap<Reverse<(i64, u64, String)>>,
+    pending: HashMap<String, (u64, ScheduledSend)>,
+    seq: u64,
+}
+
+impl Queue {
+    fn push(&mut self, send: ScheduledSend) {
+        self.seq += 1;
+        self.heap
+            .push(Reverse((send.deliver_at, self.seq, send.key.clone())));
+        self.pending.insert(send.key.clone(), (self.seq, send));
+    }
+
+    fn cancel(&mut self, key: &str) -> bool {
+        self.pending.remove(key).is_some()
+    }
+
+    /// Due time of the next live send, dropping dead entries on the way
+    fn next_at(&mut self) -> Option<i64> {
+        while let Some(Reverse((at, seq, key))) = self.heap.peek() {
+            if self.pending.get(key).is_some_and(|(live, _)| live == seq) {
+                return Some(*at);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+
+    /// Remove and return the sends due at `now`, earliest first
+    fn take_due(&mut self, now: i64) -> Vec<ScheduledSend> {
+        let mut due = Vec::new();
+        while self.next_at().is_some_and(|at| at <= now) {
+            if let Some(Reverse((_, _, key))) = self.heap.pop() {
+                if let Some((_, send)) = self.pending.remove(&key) {
+                    due.push(send);
+                }
+            }
+        }
+        due
+    }
+}
+
+/// Queue of sends to post later, and the task that posts them
+pub struct SendScheduler {
+    queue: Mutex<Queue>,
+    wake: Notify,
+    store: Option<Arc<dyn ScheduledSendStore>>,
+    deliver: DeliverFunction,
+}
+
+impl SendScheduler {
+    pub fn new(store: Option<Arc<dyn ScheduledSendStore>>, deliver: DeliverFunction) -> Self {
+        Self {
+            queue: Mutex::new(Queue::default()),
+            wake: Notify::new(),
+            store,
+            deliver,
+        }
+    }
+
+    /// Queue `send`, saving it to the store first
+    pub async fn schedule(&self, send: ScheduledSend) -> Result<()> {
+        if let Some(store) = &self.store {
+            store.save(&send).await?;
+        }
+        self.queue.lock().unwrap().push(send);
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Revoke the pending send with `key`, returning whether there was one
+    pub async fn cancel(&self, key: &str) -> bool {
+        let cancelled = self.queue.lock().unwrap().cancel(key);
+        if cancelled {
+            self.forget(key).await;
+        }
+        cancelled
+    }
+
+    /// Pending sends, earliest first
+    pub fn pending(&self) -> Vec<ScheduledSend> {
+        let queue = self.queue.lock().unwrap();
+        let mut sends: Vec<_> = queue.pending.values().collect();
+        sends.sort_by_key(|(seq, send)| (send.deliver_at, *seq));
+        sends.into_iter().map(|(_, send)| send.clone()).collect()
+    }
+
+    /// Queue the sends saved in the store, returning how many there were
+    pub async fn rehydrate(&self) -> Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let mut sends = store.load().await?;
+        sends.sort_by_key(|send| send.deliver_at);
+        let count = sends.len();
+        let mut queue = self.queue.lock().unwrap();
+        for send in sends {
+            queue.push(send);
+        }
+        Ok(count)
+    }
+
+    /// Post the sends due at `now`, earliest first, returning how many were due
+    pub async fn deliver_due(&self, now: i64) -> usize {
+        let due = self.queue.lock().unwrap().take_due(now);
+        for send in &due {
+            if let Err(e) = (self.deliver)(send.clone()).await {
+                warn!(key = %send.key, chat_id = %send.chat_id, error = %e, "Scheduled send failed");
+            }
+            self.forget(&send.key).await;
+        }
+        due.len()
+    }
+
+    async fn forget(&self, key: &str) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.remove(key).await {
+                warn!(key = %key, error = %e, "Failed to remove scheduled send from store");
+            }
+        }
+    }
+
+    /// Reload saved sends, then post each one when it is due
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            match self.rehydrate().await {
+                Ok(0) => {}
+                Ok(count) => info!(count, "Restored scheduled Telegram sends"),
+                Err(e) => warn!(error = %e, "Failed to restore scheduled Telegram sends"),
+            }
+            loop {
+                let now = unix_now();
+                self.deliver_due(now).await;
+                let next_at = self.queue.lock().unwrap().next_at();
+                let idle = next_at
+                    .map(|at| Duration::from_secs(at.saturating_sub(now).max(1) as u64))
+                    .map_or(MAX_IDLE, |wait| wait.min(MAX_IDLE));
+                tokio::select! {
+                    _ = tokio::time::sleep(idle) => {}
+                    _ = self.wake.notified() => {}
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn send(key: &str, deliver_at: i64) -> ScheduledSend {
+        ScheduledSend {
+            key: key.to_string(),
+            chat_id: -100,
+            text: format!("Reminder {}", key),
+            deliver_at,
+        }
+    }
+
+    /// Scheduler reporting every posted send on a channel
+    fn scheduler(
+        store: Option<Arc<dyn ScheduledSendStore>>,
+    ) -> (Arc<SendScheduler>, mpsc::UnboundedReceiver<ScheduledSend>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let deliver: DeliverFunction = Arc::new(move |send| {
+            let tx = tx.clone();
+            Box::pin(async move {
+                let _ = tx.send(send);
+                Ok(())
+            })
+        });
+        (Arc::new(SendScheduler::new(store, deliver)), rx)
+    }
+
+    fn keys(rx: &mut mpsc::UnboundedReceiver<ScheduledSend>) -> Vec<String> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|send| send.key)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pending_sends_go_out_in_due_order() {
+        let (scheduler, mut rx) = scheduler(None);
+        for (key, at) in [("c", 300), ("a", 100), ("b", 200), ("tie", 200), ("x", 150)] {
+            scheduler.schedule(send(key, at)).await.unwrap();
+        }
+        assert!(scheduler.cancel("x").await);
+        assert!(!scheduler.cancel("x").await);
+        // Rescheduling under a pending key replaces it
+        scheduler.schedule(send("c", 50)).await.unwrap();
+
+        assert_eq!(scheduler.deliver_due(40).await, 0);
+        assert_eq!(scheduler.deliver_due(250).await, 4);
+        assert_eq!(keys(&mut rx), vec!["c", "a", "b", "tie"]);
+        assert!(scheduler.pending().is_empty());
+        assert_eq!(scheduler.deliver_due(1_000).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_restart_rehydrates_from_the_store() {
+        let store: Arc<dyn ScheduledSendStore> = Arc::new(MemoryScheduledSendStore::default());
+        let (before, _) = scheduler(Some(store.clone()));
+        let now = unix_now();
+        before.schedule(send("later", now + 3_600)).await.unwrap();
+        before.schedule(send("overdue", now - 60)).await.unwrap();
+        before.schedule(send("revoked", now + 60)).await.unwrap();
+        before.cancel("revoked").await;
+        drop(before);
+
+        let (after, _) = scheduler(Some(store.clone()));
+        assert_eq!(after.rehydrate().await.unwrap(), 2);
+        let pending: Vec<_> = after.pending().into_iter().map(|s| s.key).collect();
+        assert_eq!(pending, vec!["overdue", "later"]);
+
+        // The task restores the queue itself and posts what came due while down
+        let (after, mut rx) = scheduler(Some(store.clone()));
+        let task = after.clone().start();
+        let delivered = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        task.abort();
+        assert_eq!(delivered.key, "overdue");
+        assert_eq!(delivered.text, "Reminder overdue");
+        let stored: Vec<_> = store
+            .load()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.key)
+            .collect();
+        assert_eq!(stored, vec!["later"]);
+        assert!(keys(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn test_requested_delivery_from_metadata() {
+        let metadata = |pairs: &[(&str, Value)]| -> HashMap<String, Value> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect()
+        };
+        let now = 1_700_000_000;
+        assert_eq!(requested_delivery(&metadata(&[]), now), None);
+        assert_eq!(
+            requested_delivery(&metadata(&[(DELAY_SECS_KEY, Value::from(7_200))]), now),
+            Some(now + 7_200)
+        );
+        assert_eq!(
+            requested_delivery(
+                &metadata(&[(DELIVER_AT_KEY, Value::from("1700000100"))]),
+                now
+            ),
+            Some(now + 100)
+        );
+        // deliver_at wins; a time already passed means send now
+        let both = metadata(&[
+            (DELIVER_AT_KEY, Value::from(now - 5)),
+            (DELAY_SECS_KEY, Value::from(60)),
+        ]);
+        assert_eq!(requested_delivery(&both, now), None);
+        assert_eq!(
+            schedule_key(&metadata(&[(SCHEDULE_KEY, Value::from("tea"))])),
+            "tea"
+        );
+    }
+}
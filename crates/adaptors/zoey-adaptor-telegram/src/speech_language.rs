@@ -0,0 +1,264 @@
+//! Language hints for transcribing speech
+//!
+//! Whisper autodetects the language by default, which can turn a short
+//! Spanish voice note into English nonsense. Telegram does not say what
+//! language a voice note is in, so the hint passed to STT is chosen from, in
+//! order:
+//!
+//! 1. `TelegramVoiceSettings::force_language`, overriding everything
+//! 2. the language STT detected for the user's last [`DEFAULT_OBSERVATIONS`]
+//!    speech messages in the chat, when they all agree
+//! 3. the user's profile `language_code`
+//!
+//! Observations are kept in memory for [`DEFAULT_OBSERVATION_TTL`] after the
+//! user's last speech message. The hint and the detected language are added
+//! to the chat request's metadata ([`SpeechLanguage::annotate`]).
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Speech messages in a row that must agree before their language is preferred
+pub const DEFAULT_OBSERVATIONS: usize = 2;
+
+/// How long a user's detected languages are remembered
+pub const DEFAULT_OBSERVATION_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Where a language hint came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintSource {
+    Forced,
+    Observed,
+    Profile,
+}
+
+impl HintSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            HintSource::Forced => "forced",
+            HintSource::Observed => "observed",
+            HintSource::Profile => "profile",
+        }
+    }
+}
+
+/// Language passed to STT as the initial hint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageHint {
+    /// ISO 639-1 code, e.g. "es"
+    pub language: String,
+    pub source: HintSource,
+}
+
+/// Primary subtag of a language tag, lowercased ("pt-BR" -> "pt")
+pub fn normalize_language(tag: &str) -> Option<String> {
+    let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+    let valid = (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_lowercase());
+    valid.then_some(primary)
+}
+
+/// Hint by precedence: forced, then observed, then profile; `None` autodetects
+pub fn choose_hint(
+    forced: Option<&str>,
+    observed: Option<&str>,
+    profile: Option<&str>,
+) -> Option<LanguageHint> {
+    [
+        (forced, HintSource::Forced),
+        (observed, HintSource::Observed),
+        (profile, HintSource::Profile),
+    ]
+    .into_iter()
+    .find_map(|(tag, source)| {
+        Some(LanguageHint {
+            language: normalize_language(tag?)?,
+            source,
+        })
+    })
+}
+
+/// Latest detections for one user in one chat, oldest first
+type Recent = (VecDeque<String>, Instant);
+
+/// Languages STT detected per user and chat
+pub struct ObservedLanguages {
+    users: Mutex<HashMap<(i64, u64), Recent>>,
+    /// Detections in a row that must agree
+    observations: usize,
+    ttl: Duration,
+}
+
+impl Default for ObservedLanguages {
+    fn default() -> Self {
+        Self::new(DEFAULT_OBSERVATIONS, DEFAULT_OBSERVATION_TTL)
+    }
+}
+
+impl ObservedLanguages {
+    pub fn new(observations: usize, ttl: Duration) -> Self {
+        Self {
+            users: Mutex::new(HashMap::new()),
+            observations: observations.max(1),
+            ttl,
+        }
+    }
+
+    /// Language of the user's last speech messages in the chat, if they agree
+    pub fn observed(&self, chat_id: i64, user_id: u64) -> Option<String> {
+        let users = self.users.lock().unwrap();
+        let (recent, at) = users.get(&(chat_id, user_id))?;
+        if at.elapsed() >= self.ttl || recent.len() < self.observations {
+            return None;
+        }
+        let first = recent.front()?;
+        recent
+            .iter()
+            .all(|language| language == first)
+            .then(|| first.clone())
+    }
+
+    /// Record the language STT detected for a speech message
+    pub fn record(&self, chat_id: i64, user_id: u64, detected: &str) {
+        let Some(language) = normalize_language(detected) else {
+            return;
+        };
+        let mut users = self.users.lock().unwrap();
+        users.retain(|_, (_, at)| at.elapsed() < self.ttl);
+        let (recent, at) = users
+            .entry((chat_id, user_id))
+            .or_insert_with(|| (VecDeque::new(), Instant::now()));
+        recent.push_back(language);
+        while recent.len() > self.observations {
+            recent.pop_front();
+        }
+        *at = Instant::now();
+    }
+}
+
+/// Hint used for a transcribed message and the language STT reported
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpeechLanguage {
+    pub hint: Option<LanguageHint>,
+    pub detected: Option<String>,
+}
+
+impl SpeechLanguage {
+    /// Add `transcription_language_hint`, `transcription_hint_source` and
+    /// `transcription_detected_language` to request metadata
+    pub fn annotate(&self, metadata: &mut serde_json::Value) {
+        let Some(metadata) = metadata.as_object_mut() else {
+            return;
+        };
+        if let Some(ref hint) = self.hint {
+            metadata.insert(
+                "transcription_language_hint".to_string(),
+                hint.language.clone().into(),
+            );
+            metadata.insert(
+                "transcription_hint_source".to_string(),
+                hint.source.label().into(),
+            );
+        }
+        if let Some(ref detected) = self.detected {
+            metadata.insert(
+                "transcription_detected_language".to_string(),
+                detected.clone().into(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(language: &str, source: HintSource) -> Option<LanguageHint> {
+        Some(LanguageHint {
+            language: language.to_string(),
+            source,
+        })
+    }
+
+    #[test]
+    fn test_hint_precedence() {
+        assert_eq!(
+            choose_hint(Some("de"), Some("es"), Some("en")),
+            hint("de", HintSource::Forced)
+        );
+        assert_eq!(
+            choose_hint(None, Some("es"), Some("en")),
+            hint("es", HintSource::Observed)
+        );
+        assert_eq!(
+            choose_hint(None, None, Some("pt-BR")),
+            hint("pt", HintSource::Profile)
+        );
+        assert_eq!(choose_hint(None, None, None), None);
+        // Unusable tags fall through to the next source
+        assert_eq!(
+            choose_hint(Some(" "), None, Some("en")),
+            hint("en", HintSource::Profile)
+        );
+        assert_eq!(choose_hint(None, Some("??"), None), None);
+    }
+
+    #[test]
+    fn test_observations_must_agree() {
+        let observed = ObservedLanguages::new(2, DEFAULT_OBSERVATION_TTL);
+        assert_eq!(observed.observed(1, 7), None);
+
+        observed.record(1, 7, "es");
+        // One detection is not enough
+        assert_eq!(observed.observed(1, 7), None);
+        observed.record(1, 7, "ES");
+        assert_eq!(observed.observed(1, 7).as_deref(), Some("es"));
+        // Other users and chats are separate
+        assert_eq!(observed.observed(2, 7), None);
+        assert_eq!(observed.observed(1, 8), None);
+
+        // A switch only takes over once it repeats
+        observed.record(1, 7, "en");
+        assert_eq!(observed.observed(1, 7), None);
+        observed.record(1, 7, "en");
+        assert_eq!(observed.observed(1, 7).as_deref(), Some("en"));
+
+        // Bogus detections are ignored
+        observed.record(1, 7, "");
+        assert_eq!(observed.observed(1, 7).as_deref(), Some("en"));
+
+        let expired = ObservedLanguages::new(1, Duration::ZERO);
+        expired.record(1, 7, "es");
+        assert_eq!(expired.observed(1, 7), None);
+    }
+
+    #[test]
+    fn test_metadata_records_hint_and_detection() {
+        let mut metadata = serde_json::json!({ "tier": "free" });
+        SpeechLanguage {
+            hint: hint("es", HintSource::Observed),
+            detected: Some("en".to_string()),
+        }
+        .annotate(&mut metadata);
+        assert_eq!(
+            metadata,
+            serde_json::json!({
+                "tier": "free",
+                "transcription_language_hint": "es",
+                "transcription_hint_source": "observed",
+                "transcription_detected_language": "en",
+            })
+        );
+
+        // Autodetected: only the detection is recorded
+        let mut metadata = serde_json::json!({});
+        SpeechLanguage {
+            hint: None,
+            detected: Some("fr".to_string()),
+        }
+        .annotate(&mut metadata);
+        assert_eq!(
+            metadata,
+            serde_json::json!({ "transcription_detected_language": "fr" })
+        );
+    }
+}
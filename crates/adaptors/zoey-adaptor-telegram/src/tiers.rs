@@ -0,0 +1,621 @@
+//! Telegram Paid-Tier Quotas
+//!
+//! Maps Telegram users to a [`Tier`] that controls how many messages they may
+//! send per day and how large a response budget the backend should use.
+//!
+//! - Daily counters are persisted through a [`QuotaStore`] (the runtime's
+//!   database adapter when one is configured, memory otherwise) and reset at a
+//!   configurable UTC hour. Consuming a message is atomic, so concurrent
+//!   messages can't push a user past their limit.
+//! - Every chat request carries `response_budget`, `priority` and `tier`
+//!   metadata so the backend can adjust generation. There is no per-entity
+//!   request queue yet; once one exists it should schedule by `priority`.
+//! - Admins manage assignments at runtime with `/tier set <user> <tier>`;
+//!   those are persisted through the database adapter too and reloaded on
+//!   start.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+use uuid::Uuid;
+use zoey_core::{types::Component, IDatabaseAdapter, Result};
+
+/// Component type used to persist quota counters
+const QUOTA_COMPONENT_TYPE: &str = "telegram_quota";
+
+/// Component type holding tiers assigned with `/tier set`, keyed by user ID
+const TIER_COMPONENT_TYPE: &str = "telegram_tiers";
+
+/// Service tier assigned to a Telegram user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tier {
+    /// Tier name used by the admin command (e.g. "free", "paid")
+    pub name: String,
+    /// Messages allowed per quota window (one day)
+    pub daily_messages: u32,
+    /// Maximum response budget (tokens) passed to the backend
+    pub max_response_budget: u32,
+    /// Scheduling priority (higher is served first)
+    pub priority: u8,
+}
+
+impl Tier {
+    /// Shared, constrained tier for non-subscribers
+    pub fn free() -> Self {
+        Self {
+            name: "free".to_string(),
+            daily_messages: 30,
+            max_response_budget: 512,
+            priority: 0,
+        }
+    }
+
+    /// Subscriber tier with faster, longer responses
+    pub fn paid() -> Self {
+        Self {
+            name: "paid".to_string(),
+            daily_messages: 500,
+            max_response_budget: 2048,
+            priority: 10,
+        }
+    }
+}
+
+impl Default for Tier {
+    fn default() -> Self {
+        Self::free()
+    }
+}
+
+/// Persisted daily counter for one user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaRecord {
+    /// Messages consumed in the current window
+    pub count: u32,
+    /// Unix timestamp (seconds) of the window start
+    pub window_start: i64,
+}
+
+/// Start of the quota window containing `now`
+pub fn window_start(now: DateTime<Utc>, reset_hour_utc: u32) -> DateTime<Utc> {
+    let hour = reset_hour_utc.min(23);
+    let today_reset = Utc
+        .with_ymd_and_hms(now.year(), now.month(), now.day(), hour, 0, 0)
+        .single()
+        .unwrap_or(now);
+    if now >= today_reset {
+        today_reset
+    } else {
+        today_reset - ChronoDuration::days(1)
+    }
+}
+
+/// Next quota reset after `now`
+pub fn next_reset(now: DateTime<Utc>, reset_hour_utc: u32) -> DateTime<Utc> {
+    window_start(now, reset_hour_utc) + ChronoDuration::days(1)
+}
+
+/// Friendly rejection sent to users who exhausted their quota
+pub fn quota_exceeded_message(tier: &Tier, resets_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let remaining = (resets_at - now).max(ChronoDuration::zero());
+    let hours = remaining.num_hours();
+    let minutes = remaining.num_minutes() % 60;
+    format!(
+        "You've reached your daily limit of {} messages on the {} tier. \
+         Your quota resets at {:02}:00 UTC (in {}h {}m).",
+        tier.daily_messages,
+        tier.name,
+        resets_at.hour(),
+        hours,
+        minutes
+    )
+}
+
+/// Chat request metadata describing the user's tier
+pub fn request_metadata(tier: &Tier) -> serde_json::Value {
+    serde_json::json!({
+        "response_budget": tier.max_response_budget,
+        "priority": tier.priority,
+        "tier": tier.name,
+    })
+}
+
+/// Storage backend for quota counters
+#[async_trait]
+pub trait QuotaStore: Send + Sync {
+    /// Load the counter for a user
+    async fn load(&self, user_id: u64) -> Result<Option<QuotaRecord>>;
+
+    /// Consume one message in the window starting at `window_start`, returning
+    /// the updated counter, or `None` once `limit` messages were consumed
+    ///
+    /// Must be atomic: two messages racing for the last slot may not both get it.
+    async fn consume(
+        &self,
+        user_id: u64,
+        window_start: i64,
+        limit: u32,
+    ) -> Result<Option<QuotaRecord>>;
+}
+
+/// In-memory quota store (counters are lost on restart)
+#[derive(Default)]
+pub struct MemoryQuotaStore {
+    records: RwLock<HashMap<u64, QuotaRecord>>,
+}
+
+#[async_trait]
+impl QuotaStore for MemoryQuotaStore {
+    async fn load(&self, user_id: u64) -> Result<Option<QuotaRecord>> {
+        Ok(self.records.read().unwrap().get(&user_id).copied())
+    }
+
+    async fn consume(
+        &self,
+        user_id: u64,
+        window_start: i64,
+        limit: u32,
+    ) -> Result<Option<QuotaRecord>> {
+        let mut records = self.records.write().unwrap();
+        let record = records.entry(user_id).or_insert(QuotaRecord {
+            count: 0,
+            window_start,
+        });
+        if record.window_start < window_start {
+            *record = QuotaRecord {
+                count: 0,
+                window_start,
+            };
+        }
+        if record.count >= limit {
+            return Ok(None);
+        }
+        record.count += 1;
+        Ok(Some(*record))
+    }
+}
+
+/// World the quota and tier components are stored under
+fn tiers_world_id() -> Uuid {
+    zoey_core::string_to_uuid("telegram-tiers")
+}
+
+async fn find_component(
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    entity_id: Uuid,
+    component_type: &str,
+) -> Result<Option<Component>> {
+    adapter
+        .get_component(entity_id, component_type, Some(tiers_world_id()), None)
+        .await
+}
+
+/// Store `data` as the entity's component of `component_type`, creating it if needed
+async fn save_component(
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    entity_id: Uuid,
+    component_type: &str,
+    data: serde_json::Value,
+) -> Result<()> {
+    let now = Utc::now().timestamp();
+    match find_component(adapter, entity_id, component_type).await? {
+        Some(mut existing) => {
+            existing.data = data;
+            existing.updated_at = Some(now);
+            adapter.update_component(&existing).await
+        }
+        None => {
+            let component = Component {
+                id: Uuid::new_v4(),
+                entity_id,
+                world_id: tiers_world_id(),
+                source_entity_id: None,
+                component_type: component_type.to_string(),
+                data,
+                created_at: Some(now),
+                updated_at: Some(now),
+            };
+            adapter.create_component(&component).await.map(|_| ())
+        }
+    }
+}
+
+/// Quota store backed by the runtime's database adapter (entity components)
+///
+/// Components have no compare-and-set, so consuming is serialized within
+/// this process; Telegram hands a bot's updates to a single poller, so one
+/// process per bot token is the only writer.
+pub struct AdapterQuotaStore {
+    adapter: Arc<dyn IDatabaseAdapter + Send + Sync>,
+    consume_lock: tokio::sync::Mutex<()>,
+}
+
+impl AdapterQuotaStore {
+    pub fn new(adapter: Arc<dyn IDatabaseAdapter + Send + Sync>) -> Self {
+        Self {
+            adapter,
+            consume_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn entity_id(user_id: u64) -> Uuid {
+        zoey_core::string_to_uuid(&format!("telegram-user-{}", user_id))
+    }
+}
+
+#[async_trait]
+impl QuotaStore for AdapterQuotaStore {
+    async fn load(&self, user_id: u64) -> Result<Option<QuotaRecord>> {
+        let entity_id = Self::entity_id(user_id);
+        Ok(find_component(self.adapter.as_ref(), entity_id, QUOTA_COMPONENT_TYPE)
+            .await?
+            .and_then(|c| serde_json::from_value(c.data).ok()))
+    }
+
+    async fn consume(
+        &self,
+        user_id: u64,
+        window_start: i64,
+        limit: u32,
+    ) -> Result<Option<QuotaRecord>> {
+        let _guard = self.consume_lock.lock().await;
+        let count = match self.load(user_id).await? {
+            Some(r) if r.window_start == window_start => r.count,
+            _ => 0,
+        };
+        if count >= limit {
+            return Ok(None);
+        }
+        let record = QuotaRecord {
+            count: count + 1,
+            window_start,
+        };
+        save_component(
+            self.adapter.as_ref(),
+            Self::entity_id(user_id),
+            QUOTA_COMPONENT_TYPE,
+            serde_json::to_value(record)?,
+        )
+        .await?;
+        Ok(Some(record))
+    }
+}
+
+/// Result of a quota check
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuotaDecision {
+    /// Message allowed; `remaining` messages left in this window
+    Allowed { tier: Tier, remaining: u32 },
+    /// Quota exhausted until `resets_at`
+    Exceeded { tier: Tier, resets_at: DateTime<Utc> },
+}
+
+/// Tracks tier assignments and per-user daily counters
+pub struct TierManager {
+    assignments: RwLock<HashMap<u64, Tier>>,
+    default_tier: Tier,
+    reset_hour_utc: u32,
+    admins: HashSet<u64>,
+    store: Arc<dyn QuotaStore>,
+    adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
+}
+
+impl TierManager {
+    pub fn new(
+        assignments: HashMap<u64, Tier>,
+        default_tier: Tier,
+        reset_hour_utc: u32,
+        admins: HashSet<u64>,
+        store: Arc<dyn QuotaStore>,
+        adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
+    ) -> Self {
+        Self {
+            assignments: RwLock::new(assignments),
+            default_tier,
+            reset_hour_utc: reset_hour_utc.min(23),
+            admins,
+            store,
+            adapter,
+        }
+    }
+
+    /// Reload assignments made with `/tier set`, returning how many were found
+    pub async fn load(&self) -> Result<usize> {
+        let Some(adapter) = &self.adapter else {
+            return Ok(0);
+        };
+        let Some(component) =
+            find_component(adapter.as_ref(), tiers_world_id(), TIER_COMPONENT_TYPE).await?
+        else {
+            return Ok(0);
+        };
+        let stored = parse_assignments(component.data)?;
+        let loaded = stored.len();
+        self.assignments.write().unwrap().extend(stored);
+        Ok(loaded)
+    }
+
+    /// Tier currently assigned to a user
+    pub fn tier_for(&self, user_id: u64) -> Tier {
+        self.assignments
+            .read()
+            .unwrap()
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_else(|| self.default_tier.clone())
+    }
+
+    /// Whether a user may run admin commands
+    pub fn is_admin(&self, user_id: u64) -> bool {
+        self.admins.contains(&user_id)
+    }
+
+    /// Resolve a tier by name from the default, configured, and built-in tiers
+    pub fn resolve_tier(&self, name: &str) -> Option<Tier> {
+        let name = name.to_lowercase();
+        if self.default_tier.name.eq_ignore_ascii_case(&name) {
+            return Some(self.default_tier.clone());
+        }
+        if let Some(tier) = self
+            .assignments
+            .read()
+            .unwrap()
+            .values()
+            .find(|t| t.name.eq_ignore_ascii_case(&name))
+        {
+            return Some(tier.clone());
+        }
+        [Tier::free(), Tier::paid()]
+            .into_iter()
+            .find(|t| t.name == name)
+    }
+
+    /// Assign a tier to a user and persist it
+    pub async fn set_tier(&self, user_id: u64, tier: Tier) -> Result<()> {
+        if let Some(adapter) = &self.adapter {
+            let existing =
+                find_component(adapter.as_ref(), tiers_world_id(), TIER_COMPONENT_TYPE).await?;
+            let mut stored = match existing {
+                Some(component) => parse_assignments(component.data)?,
+                None => HashMap::new(),
+            };
+            stored.insert(user_id, tier.clone());
+            let data: HashMap<String, &Tier> =
+                stored.iter().map(|(id, t)| (id.to_string(), t)).collect();
+            save_component(
+                adapter.as_ref(),
+                tiers_world_id(),
+                TIER_COMPONENT_TYPE,
+                serde_json::to_value(data)?,
+            )
+            .await?;
+        }
+        info!(user_id = %user_id, tier = %tier.name, "Telegram tier assigned");
+        self.assignments.write().unwrap().insert(user_id, tier);
+        Ok(())
+    }
+
+    /// Check the user's quota and consume one message if allowed
+    pub async fn check_and_consume(&self, user_id: u64, now: DateTime<Utc>) -> Result<QuotaDecision> {
+        let tier = self.tier_for(user_id);
+        let current_window = window_start(now, self.reset_hour_utc).timestamp();
+        match self
+            .store
+            .consume(user_id, current_window, tier.daily_messages)
+            .await?
+        {
+            Some(record) => Ok(QuotaDecision::Allowed {
+                remaining: tier.daily_messages.saturating_sub(record.count),
+                tier,
+            }),
+            None => Ok(QuotaDecision::Exceeded {
+                tier,
+                resets_at: next_reset(now, self.reset_hour_utc),
+            }),
+        }
+    }
+
+    /// Handle `/tier` admin commands, returning the reply to send
+    pub async fn handle_command(&self, caller_id: u64, text: &str) -> String {
+        let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+        match args.as_slice() {
+            [] => {
+                let tier = self.tier_for(caller_id);
+                format!(
+                    "Your tier: {} ({} messages/day)",
+                    tier.name, tier.daily_messages
+                )
+            }
+            ["set", user, tier_name] => {
+                if !self.is_admin(caller_id) {
+                    return "Only admins can change tiers.".to_string();
+                }
+                let Ok(user_id) = user.trim_start_matches('@').parse::<u64>() else {
+                    return format!("Invalid user id: {}", user);
+                };
+                match self.resolve_tier(tier_name) {
+                    Some(tier) => {
+                        let name = tier.name.clone();
+                        match self.set_tier(user_id, tier).await {
+                            Ok(()) => format!("User {} is now on the {} tier.", user_id, name),
+                            Err(e) => {
+                                warn!(user_id = %user_id, error = %e, "Failed to save tier assignment");
+                                format!("Couldn't save the tier for user {}, try again.", user_id)
+                            }
+                        }
+                    }
+                    None => format!("Unknown tier: {}", tier_name),
+                }
+            }
+            _ => "Usage: /tier set <user_id> <tier>".to_string(),
+        }
+    }
+}
+
+/// Tier assignments from persisted component data, skipping unreadable entries
+fn parse_assignments(data: serde_json::Value) -> Result<HashMap<u64, Tier>> {
+    let stored: HashMap<String, serde_json::Value> = serde_json::from_value(data)?;
+    let mut assignments = HashMap::new();
+    for (key, value) in stored {
+        match (key.parse::<u64>(), serde_json::from_value::<Tier>(value)) {
+            (Ok(user_id), Ok(tier)) => {
+                assignments.insert(user_id, tier);
+            }
+            _ => warn!(key = %key, "Skipping unreadable tier assignment"),
+        }
+    }
+    Ok(assignments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 10, h, m, 0).unwrap()
+    }
+
+    fn manager(store: Arc<dyn QuotaStore>, daily: u32) -> TierManager {
+        let default_tier = Tier {
+            daily_messages: daily,
+            ..Tier::free()
+        };
+        TierManager::new(
+            HashMap::from([(7, Tier::paid())]),
+            default_tier,
+            4,
+            HashSet::from([1]),
+            store,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_window_start_before_and_after_reset_hour() {
+        assert_eq!(window_start(at(3, 59), 4), Utc.with_ymd_and_hms(2025, 3, 9, 4, 0, 0).unwrap());
+        assert_eq!(window_start(at(4, 0), 4), at(4, 0));
+        assert_eq!(next_reset(at(23, 0), 4), Utc.with_ymd_and_hms(2025, 3, 11, 4, 0, 0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_counter_persists_across_managers() {
+        let store: Arc<dyn QuotaStore> = Arc::new(MemoryQuotaStore::default());
+        let first = manager(store.clone(), 2);
+        first.check_and_consume(42, at(10, 0)).await.unwrap();
+
+        // A fresh manager (e.g. after restart) sees the persisted count
+        let second = manager(store.clone(), 2);
+        let decision = second.check_and_consume(42, at(11, 0)).await.unwrap();
+        assert!(matches!(decision, QuotaDecision::Allowed { remaining: 0, .. }));
+        let decision = second.check_and_consume(42, at(12, 0)).await.unwrap();
+        assert!(matches!(decision, QuotaDecision::Exceeded { .. }));
+        assert_eq!(store.load(42).await.unwrap().unwrap().count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_counter_resets_at_configured_hour() {
+        let store: Arc<dyn QuotaStore> = Arc::new(MemoryQuotaStore::default());
+        let mgr = manager(store, 1);
+        mgr.check_and_consume(42, at(5, 0)).await.unwrap();
+        let decision = mgr.check_and_consume(42, at(23, 0)).await.unwrap();
+        match decision {
+            QuotaDecision::Exceeded { resets_at, .. } => {
+                assert_eq!(resets_at, Utc.with_ymd_and_hms(2025, 3, 11, 4, 0, 0).unwrap())
+            }
+            other => panic!("expected exceeded, got {:?}", other),
+        }
+        let next_day = Utc.with_ymd_and_hms(2025, 3, 11, 4, 1, 0).unwrap();
+        let decision = mgr.check_and_consume(42, next_day).await.unwrap();
+        assert!(matches!(decision, QuotaDecision::Allowed { .. }));
+    }
+
+    #[test]
+    fn test_rejection_message_contents() {
+        let resets_at = Utc.with_ymd_and_hms(2025, 3, 11, 4, 0, 0).unwrap();
+        let msg = quota_exceeded_message(&Tier::free(), resets_at, at(22, 30));
+        assert!(msg.contains("30 messages"));
+        assert!(msg.contains("free tier"));
+        assert!(msg.contains("04:00 UTC"));
+        assert!(msg.contains("5h 30m"));
+    }
+
+    #[tokio::test]
+    async fn test_paid_tier_priority() {
+        let mgr = manager(Arc::new(MemoryQuotaStore::default()), 5);
+        match mgr.check_and_consume(7, at(10, 0)).await.unwrap() {
+            QuotaDecision::Allowed { tier, .. } => {
+                assert_eq!(tier.priority, Tier::paid().priority);
+                assert_eq!(tier.max_response_budget, Tier::paid().max_response_budget);
+            }
+            other => panic!("expected allowed, got {:?}", other),
+        }
+        assert_eq!(mgr.tier_for(8).priority, 0);
+    }
+
+    #[test]
+    fn test_request_metadata() {
+        let meta = request_metadata(&Tier::paid());
+        assert_eq!(meta["response_budget"], 2048);
+        assert_eq!(meta["priority"], 10);
+        assert_eq!(meta["tier"], "paid");
+    }
+
+    #[tokio::test]
+    async fn test_admin_command_permission_check() {
+        let mgr = manager(Arc::new(MemoryQuotaStore::default()), 5);
+        let denied = mgr.handle_command(2, "/tier set 99 paid").await;
+        assert_eq!(denied, "Only admins can change tiers.");
+        assert_eq!(mgr.tier_for(99).name, "free");
+
+        let ok = mgr.handle_command(1, "/tier set 99 paid").await;
+        assert!(ok.contains("paid tier"));
+        assert_eq!(mgr.tier_for(99).name, "paid");
+
+        assert!(mgr.handle_command(1, "/tier set 99 gold").await.contains("Unknown tier"));
+        assert!(mgr.handle_command(1, "/tier bogus").await.starts_with("Usage"));
+    }
+
+    #[test]
+    fn test_persisted_assignments_parse() {
+        let data = serde_json::json!({
+            "99": Tier::paid(),
+            "100": Tier::free(),
+            "not-a-user": Tier::paid(),
+            "101": "garbage",
+        });
+        let stored = parse_assignments(data).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[&99], Tier::paid());
+        assert_eq!(stored[&100], Tier::free());
+        assert!(parse_assignments(serde_json::json!("garbage")).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_messages_never_exceed_quota() {
+        let quotas: Arc<dyn QuotaStore> = Arc::new(MemoryQuotaStore::default());
+        let mgr = Arc::new(manager(quotas.clone(), 5));
+
+        let handles: Vec<_> = (0..20)
+            .map(|_| {
+                let mgr = mgr.clone();
+                tokio::spawn(async move { mgr.check_and_consume(42, at(10, 0)).await.unwrap() })
+            })
+            .collect();
+        let mut allowed = 0;
+        for handle in handles {
+            if matches!(handle.await.unwrap(), QuotaDecision::Allowed { .. }) {
+                allowed += 1;
+            }
+        }
+        assert_eq!(allowed, 5);
+        assert_eq!(quotas.load(42).await.unwrap().unwrap().count, 5);
+
+        // The next window starts from zero again
+        let next_day = Utc.with_ymd_and_hms(2025, 3, 11, 4, 1, 0).unwrap();
+        let decision = mgr.check_and_consume(42, next_day).await.unwrap();
+        assert!(matches!(decision, QuotaDecision::Allowed { remaining: 4, .. }));
+    }
+}
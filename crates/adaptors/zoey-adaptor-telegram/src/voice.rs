@@ -2,14 +2,20 @@
 //!
 //! Handles voice message (audio note) generation and TTS integration for Telegram.
 //! Supports both TTS (text-to-speech) for sending voice messages and
-//! STT (speech-to-text) for transcribing received voice messages.
+//! STT (speech-to-text) for transcribing received voice notes, audio files
+//! and round video notes.
 //! Respects voice configuration from character XML.
 
 #[cfg(any(feature = "voice", feature = "voice-whisper", feature = "voice-unmute"))]
 use tracing::{info, warn};
 
 #[cfg(feature = "voice")]
-use zoey_provider_voice::VoicePlugin;
+use zoey_provider_voice::{
+    length_limit::DEFAULT_MAX_TTS_CHARS, AudioData, AudioFormat, EffectConfig, LengthPolicy,
+    SinkFormat, TranscriptionResult, VoicePlugin,
+};
+#[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+use zoey_provider_voice::audio::PcmAudio;
 
 /// Voice configuration from character XML
 #[derive(Debug, Clone)]
@@ -38,6 +44,8 @@ pub struct VoiceConfig {
     pub similarity_boost: Option<f32>,
     /// Local TTS endpoint
     pub local_endpoint: Option<String>,
+    /// Effect entries under `voice.effects`, parsed by the voice provider
+    pub effects: Vec<serde_json::Value>,
     /// Trigger phrases that initiate voice mode
     pub triggers: Vec<String>,
     /// Telegram-specific settings
@@ -55,6 +63,9 @@ pub struct TelegramVoiceSettings {
     pub include_text: bool,
     /// Convert received voice messages to text (STT)
     pub transcribe_voice: bool,
+    /// Transcribe every message in this language instead of choosing a hint
+    /// (see [`crate::speech_language`])
+    pub force_language: Option<String>,
 }
 
 impl Default for TelegramVoiceSettings {
@@ -64,6 +75,7 @@ impl Default for TelegramVoiceSettings {
             max_text_length: 4096,
             include_text: false,
             transcribe_voice: false,
+            force_language: None,
         }
     }
 }
@@ -83,6 +95,7 @@ impl Default for VoiceConfig {
             stability: Some(0.5),
             similarity_boost: Some(0.75),
             local_endpoint: None,
+            effects: Vec::new(),
             triggers: default_triggers(),
             telegram: TelegramVoiceSettings::default(),
         }
@@ -224,6 +237,12 @@ impl VoiceConfig {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let effects = voice
+            .get("effects")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+
         // Parse triggers
         let triggers = voice
             .get("triggers")
@@ -290,6 +309,11 @@ impl VoiceConfig {
                         .map(|s| s == "true")
                 })
                 .unwrap_or(false),
+            force_language: telegram_settings
+                .get("force_language")
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
         };
 
         Self {
@@ -305,6 +329,7 @@ impl VoiceConfig {
             stability,
             similarity_boost,
             local_endpoint,
+            effects,
             triggers,
             telegram,
         }
@@ -348,6 +373,50 @@ impl VoiceConfig {
     }
 }
 
+/// Telegram message types that carry speech worth transcribing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechSource {
+    /// Voice note (OGG/Opus)
+    Voice,
+    /// Audio file (MP3, M4A, OGG, WAV, ...)
+    Audio,
+    /// Round video message (MP4 with AAC audio)
+    VideoNote,
+}
+
+impl SpeechSource {
+    /// Find the speech-carrying attachment of a message, returning its file ID
+    pub fn from_message(msg: &teloxide::types::Message) -> Option<(Self, String)> {
+        if let Some(voice) = msg.voice() {
+            return Some((Self::Voice, voice.file.id.clone()));
+        }
+        if let Some(audio) = msg.audio() {
+            return Some((Self::Audio, audio.file.id.clone()));
+        }
+        if let Some(note) = msg.video_note() {
+            return Some((Self::VideoNote, note.file.id.clone()));
+        }
+        None
+    }
+
+    /// Whether a reply to this kind of message should be spoken
+    ///
+    /// Forwarded audio files are often music or recordings, so only voice
+    /// and video notes (the user speaking to us) get a voice reply.
+    pub fn responds_with_voice(&self) -> bool {
+        !matches!(self, Self::Audio)
+    }
+
+    /// Short label for logging
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Voice => "voice",
+            Self::Audio => "audio",
+            Self::VideoNote => "video_note",
+        }
+    }
+}
+
 /// Voice manager for handling Telegram voice messages
 pub struct VoiceManager {
     /// Voice configuration
@@ -376,7 +445,7 @@ impl VoiceManager {
 
     #[cfg(feature = "voice")]
     fn create_tts_plugin(config: &VoiceConfig) -> VoicePlugin {
-        match config.engine.as_str() {
+        let mut plugin = match config.engine.as_str() {
             "elevenlabs" => VoicePlugin::with_elevenlabs(None),
             "piper" => {
                 // Piper TTS - ultra low latency (~50ms)
@@ -413,7 +482,11 @@ impl VoiceManager {
                 VoicePlugin::with_local(endpoint)
             }
             _ => VoicePlugin::with_openai(None), // Default to OpenAI
-        }
+        };
+        plugin.set_effects(EffectConfig::parse_list(&config.effects));
+        // A runaway reply is cut short rather than sent as a long voice message
+        plugin.set_length_limit(DEFAULT_MAX_TTS_CHARS, LengthPolicy::TruncateWithNotice);
+        plugin
     }
 
     /// Check if voice is enabled and configured
@@ -421,16 +494,10 @@ impl VoiceManager {
         self.config.enabled
     }
 
-    /// Synthesize text to speech audio
+    /// Truncate text to the configured TTS limit
     #[cfg(feature = "voice")]
-    pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>, String> {
-        let tts = self
-            .tts
-            .as_ref()
-            .ok_or_else(|| "TTS not initialized".to_string())?;
-
-        // Truncate text if too long
-        let text = if text.len() > self.config.telegram.max_text_length {
+    fn tts_text<'a>(&self, text: &'a str) -> &'a str {
+        if text.len() > self.config.telegram.max_text_length {
             warn!(
                 "Text too long for TTS ({} chars), truncating to {}",
                 text.len(),
@@ -439,7 +506,17 @@ impl VoiceManager {
             &text[..self.config.telegram.max_text_length]
         } else {
             text
-        };
+        }
+    }
+
+    /// Synthesize text to speech audio
+    #[cfg(feature = "voice")]
+    pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>, String> {
+        let tts = self
+            .tts
+            .as_ref()
+            .ok_or_else(|| "TTS not initialized".to_string())?;
+        let text = self.tts_text(text);
 
         info!(
             engine = %self.config.engine,
@@ -462,17 +539,56 @@ impl VoiceManager {
         Ok(audio.data.to_vec())
     }
 
-    /// Synthesize text and return audio data suitable for Telegram voice message
-    /// Returns the audio bytes and estimated duration in seconds
+    /// Sink replies are synthesized for: voice notes when `output_format` is opus,
+    /// audio files otherwise
+    #[cfg(feature = "voice")]
+    pub fn sink(&self) -> SinkFormat {
+        if self.config.output_format.eq_ignore_ascii_case("opus") {
+            SinkFormat::TelegramVoice
+        } else {
+            SinkFormat::TelegramAudio
+        }
+    }
+
+    /// Synthesize text in the format `sink` requires
+    /// Returns the audio and its duration in seconds
     #[cfg(feature = "voice")]
-    pub async fn synthesize_for_telegram(&self, text: &str) -> Result<(Vec<u8>, u32), String> {
-        let audio_data = self.synthesize(text).await?;
+    pub async fn synthesize_for(&self, text: &str, sink: SinkFormat) -> Result<(AudioData, u32), String> {
+        let tts = self
+            .tts
+            .as_ref()
+            .ok_or_else(|| "TTS not initialized".to_string())?;
+        let text = self.tts_text(text);
 
-        // Estimate duration based on audio size
-        // For MP3 at typical speech bitrate (~20-32 kbps): ~3KB per second
-        let estimated_duration = (audio_data.len() as f64 / 3000.0).ceil() as u32;
+        info!(
+            engine = %self.config.engine,
+            voice = %self.config.voice_id,
+            sink = %sink,
+            text_len = %text.len(),
+            "Synthesizing speech"
+        );
 
-        Ok((audio_data, estimated_duration.max(1)))
+        let audio = tts
+            .synthesize_for(text, sink)
+            .await
+            .map_err(|e| format!("TTS synthesis failed: {}", e))?;
+
+        let duration = match audio.duration_ms {
+            Some(ms) => ms.div_ceil(1000) as u32,
+            // For MP3/Opus at typical speech bitrate (~20-32 kbps): ~3KB per second
+            None => (audio.data.len() as f64 / 3000.0).ceil() as u32,
+        };
+        Ok((audio, duration.max(1)))
+    }
+
+    /// File name Telegram shows for synthesized audio
+    #[cfg(feature = "voice")]
+    pub fn file_name(audio: &AudioData) -> &'static str {
+        match audio.format {
+            AudioFormat::Opus => "voice.ogg",
+            AudioFormat::Wav => "voice.wav",
+            _ => "voice.mp3",
+        }
     }
 
     /// Check if STT (transcription) is available
@@ -530,11 +646,15 @@ impl VoiceManager {
     }
 
     /// Transcribe a voice message from Telegram
-    /// Takes OGG/Opus audio data and returns transcribed text
+    /// Takes voice note, audio file or video note bytes and returns the transcription,
+    /// including the language STT detected; `language` is the initial hint
     #[cfg(feature = "voice-whisper")]
-    pub async fn transcribe_voice_message(&self, audio_data: &[u8]) -> Result<String, String> {
-        use zoey_provider_voice::{AudioData, AudioFormat, VoicePlugin, WhisperModel};
-        use bytes::Bytes;
+    pub async fn transcribe_voice_message(
+        &self,
+        audio_data: &[u8],
+        language: Option<&str>,
+    ) -> Result<TranscriptionResult, String> {
+        use zoey_provider_voice::{VoicePlugin, WhisperModel};
 
         if !self.can_transcribe() {
             return Err("Voice transcription not enabled".to_string());
@@ -545,29 +665,18 @@ impl VoiceManager {
             "Transcribing voice message"
         );
 
-        // Convert OGG/Opus to PCM samples
-        let pcm_samples = Self::decode_ogg_opus(audio_data)?;
+        // Decode to PCM; the plugin converts it to what the engine expects
+        let pcm = Self::decode_speech_audio(audio_data)?;
         
-        if pcm_samples.is_empty() {
+        if pcm.samples.is_empty() {
             return Err("No audio data after decoding".to_string());
         }
 
-        // Convert i16 samples to bytes
-        let pcm_bytes: Vec<u8> = pcm_samples
-            .iter()
-            .flat_map(|&s| s.to_le_bytes())
-            .collect();
-
-        let audio = AudioData {
-            data: Bytes::from(pcm_bytes),
-            format: AudioFormat::Pcm,
-            sample_rate: 16000, // After resampling
-            duration_ms: Some((pcm_samples.len() as u64 * 1000) / 16000),
-            character_count: 0,
-        };
+        let audio = Self::pcm_to_audio_data(&pcm);
 
         // Create Whisper plugin and transcribe
-        let plugin = VoicePlugin::with_whisper(WhisperModel::Base);
+        let mut plugin = VoicePlugin::with_whisper(WhisperModel::Base);
+        plugin.set_stt_language(language.map(str::to_string));
         
         let result = plugin
             .transcribe(&audio)
@@ -576,55 +685,65 @@ impl VoiceManager {
 
         info!(
             text_len = %result.text.len(),
+            hint = ?language,
+            detected = ?result.language,
             "Voice transcription complete"
         );
 
-        Ok(result.text)
+        Ok(result)
     }
 
     /// Transcribe using Unmute engine
     #[cfg(all(feature = "voice-unmute", not(feature = "voice-whisper")))]
-    pub async fn transcribe_voice_message(&self, audio_data: &[u8]) -> Result<String, String> {
-        use zoey_provider_voice::{AudioData, AudioFormat, VoicePlugin};
-        use bytes::Bytes;
+    pub async fn transcribe_voice_message(
+        &self,
+        audio_data: &[u8],
+        language: Option<&str>,
+    ) -> Result<TranscriptionResult, String> {
+        use zoey_provider_voice::VoicePlugin;
 
         if !self.can_transcribe() {
             return Err("Voice transcription not enabled".to_string());
         }
 
-        // Convert OGG/Opus to PCM
-        let pcm_samples = Self::decode_ogg_opus(audio_data)?;
-        
-        let pcm_bytes: Vec<u8> = pcm_samples
-            .iter()
-            .flat_map(|&s| s.to_le_bytes())
-            .collect();
-
-        let audio = AudioData {
-            data: Bytes::from(pcm_bytes),
-            format: AudioFormat::Pcm,
-            sample_rate: 16000,
-            duration_ms: Some((pcm_samples.len() as u64 * 1000) / 16000),
-            character_count: 0,
-        };
+        // Decode to PCM; the plugin converts it to what the engine expects
+        let pcm = Self::decode_speech_audio(audio_data)?;
+        let audio = Self::pcm_to_audio_data(&pcm);
 
         // Use Unmute for transcription
         let endpoint = self.config.local_endpoint
             .as_deref()
             .unwrap_or("ws://localhost:8000");
-        let plugin = VoicePlugin::with_unmute(endpoint);
+        let mut plugin = VoicePlugin::with_unmute(endpoint);
+        plugin.set_stt_language(language.map(str::to_string));
         
         let result = plugin
             .transcribe(&audio)
             .await
             .map_err(|e| format!("Transcription failed: {}", e))?;
 
-        Ok(result.text)
+        Ok(result)
     }
 
-    /// Decode OGG/Opus audio to PCM samples at 16kHz mono
+    /// Wrap decoded samples for the voice plugin, keeping their native spec
     #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
-    fn decode_ogg_opus(data: &[u8]) -> Result<Vec<i16>, String> {
+    fn pcm_to_audio_data(pcm: &PcmAudio) -> zoey_provider_voice::AudioData {
+        use zoey_provider_voice::{audio::samples_to_pcm16, AudioData, AudioFormat};
+
+        let frames = pcm.samples.len() as u64 / pcm.channels.max(1) as u64;
+        let mut audio = AudioData::new(
+            bytes::Bytes::from(samples_to_pcm16(&pcm.samples)),
+            AudioFormat::Pcm,
+            pcm.sample_rate,
+        )
+        .with_channels(pcm.channels);
+        audio.duration_ms = Some(frames * 1000 / pcm.sample_rate.max(1) as u64);
+        audio
+    }
+
+    /// Decode OGG audio to interleaved PCM samples
+    #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+    fn decode_ogg_opus(data: &[u8]) -> Result<PcmAudio, String> {
         use std::io::Cursor;
 
         // Use lewton for OGG/Vorbis or opus decoder
@@ -635,33 +754,19 @@ impl VoiceManager {
         match lewton::inside_ogg::OggStreamReader::new(cursor) {
             Ok(mut reader) => {
                 let sample_rate = reader.ident_hdr.audio_sample_rate;
-                let channels = reader.ident_hdr.audio_channels as usize;
+                let channels = reader.ident_hdr.audio_channels as u16;
                 
-                let mut all_samples = Vec::new();
+                let mut samples = Vec::new();
                 
                 while let Ok(Some(packet)) = reader.read_dec_packet_itl() {
-                    all_samples.extend(packet);
+                    samples.extend(packet);
                 }
                 
-                // Convert to mono if stereo
-                let mono_samples: Vec<i16> = if channels > 1 {
-                    all_samples
-                        .chunks(channels)
-                        .map(|chunk| {
-                            let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
-                            (sum / channels as i32) as i16
-                        })
-                        .collect()
-                } else {
-                    all_samples
-                };
-                
-                // Resample to 16kHz if needed
-                if sample_rate != 16000 {
-                    Ok(Self::resample(&mono_samples, sample_rate, 16000))
-                } else {
-                    Ok(mono_samples)
-                }
+                Ok(PcmAudio {
+                    samples,
+                    sample_rate,
+                    channels: channels.max(1),
+                })
             }
             Err(e) => {
                 // If lewton fails, the file might be Opus-encoded
@@ -672,31 +777,97 @@ impl VoiceManager {
         }
     }
 
-    /// Simple linear resampling
+    /// Decode any supported speech container to interleaved PCM samples
+    ///
+    /// OGG streams go through lewton; everything else (MP3, M4A, the MP4
+    /// container of video notes) goes through symphonia, which picks the
+    /// first decodable audio track and ignores video.
     #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
-    fn resample(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
-        if from_rate == to_rate {
-            return samples.to_vec();
+    fn decode_speech_audio(data: &[u8]) -> Result<PcmAudio, String> {
+        if data.starts_with(b"OggS") {
+            Self::decode_ogg_opus(data)
+        } else {
+            Self::decode_with_symphonia(data)
         }
+    }
 
-        let ratio = from_rate as f64 / to_rate as f64;
-        let new_len = (samples.len() as f64 / ratio) as usize;
-        let mut resampled = Vec::with_capacity(new_len);
-
-        for i in 0..new_len {
-            let src_idx = i as f64 * ratio;
-            let idx = src_idx as usize;
-            let frac = src_idx - idx as f64;
-
-            if idx + 1 < samples.len() {
-                let sample = samples[idx] as f64 * (1.0 - frac) + samples[idx + 1] as f64 * frac;
-                resampled.push(sample as i16);
-            } else if idx < samples.len() {
-                resampled.push(samples[idx]);
+    /// Decode audio (or the audio track of a video) with symphonia
+    #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+    fn decode_with_symphonia(data: &[u8]) -> Result<PcmAudio, String> {
+        use std::io::Cursor;
+        use symphonia::core::audio::SampleBuffer;
+        use symphonia::core::codecs::DecoderOptions;
+        use symphonia::core::errors::Error as SymphoniaError;
+        use symphonia::core::formats::FormatOptions;
+        use symphonia::core::io::MediaSourceStream;
+        use symphonia::core::meta::MetadataOptions;
+        use symphonia::core::probe::Hint;
+
+        let mss = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+        let probed = symphonia::default::get_probe()
+            .format(
+                &Hint::new(),
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| format!("Unsupported audio container: {}", e))?;
+        let mut format = probed.format;
+
+        // Video notes also carry a video track; take the first track we can decode
+        let codecs = symphonia::default::get_codecs();
+        let (track_id, sample_rate, mut decoder) = format
+            .tracks()
+            .iter()
+            .find_map(|track| {
+                codecs
+                    .make(&track.codec_params, &DecoderOptions::default())
+                    .ok()
+                    .map(|decoder| {
+                        (
+                            track.id,
+                            track.codec_params.sample_rate.unwrap_or(48000),
+                            decoder,
+                        )
+                    })
+            })
+            .ok_or_else(|| "No decodable audio track found".to_string())?;
+
+        let mut samples = Vec::new();
+        let mut channels = 1u16;
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    break
+                }
+                Err(SymphoniaError::ResetRequired) => break,
+                Err(e) => return Err(format!("Failed to read audio packet: {}", e)),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+            match decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    channels = spec.channels.count().max(1) as u16;
+                    let mut buf = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+                    buf.copy_interleaved_ref(decoded);
+                    samples.extend_from_slice(buf.samples());
+                }
+                // Corrupt packets are skipped rather than failing the whole message
+                Err(SymphoniaError::DecodeError(e)) => {
+                    warn!(error = %e, "Skipping undecodable audio packet");
+                }
+                Err(e) => return Err(format!("Failed to decode audio: {}", e)),
             }
         }
 
-        resampled
+        Ok(PcmAudio {
+            samples,
+            sample_rate,
+            channels,
+        })
     }
 }
 
@@ -706,16 +877,16 @@ impl VoiceManager {
     pub async fn synthesize(&self, _text: &str) -> Result<Vec<u8>, String> {
         Err("Voice feature not enabled. Compile with --features voice".to_string())
     }
-
-    pub async fn synthesize_for_telegram(&self, _text: &str) -> Result<(Vec<u8>, u32), String> {
-        Err("Voice feature not enabled. Compile with --features voice".to_string())
-    }
 }
 
 // STT stub when no STT features
 #[cfg(all(feature = "voice", not(any(feature = "voice-whisper", feature = "voice-unmute"))))]
 impl VoiceManager {
-    pub async fn transcribe_voice_message(&self, _audio_data: &[u8]) -> Result<String, String> {
+    pub async fn transcribe_voice_message(
+        &self,
+        _audio_data: &[u8],
+        _language: Option<&str>,
+    ) -> Result<TranscriptionResult, String> {
         Err("STT not available. Compile with --features voice-whisper or voice-unmute".to_string())
     }
 }
@@ -775,6 +946,34 @@ mod tests {
         assert_eq!(config.extract_voice_text("Hello"), None); // Not a command
     }
 
+    #[test]
+    fn test_speech_source_voice_reply() {
+        assert!(SpeechSource::Voice.responds_with_voice());
+        assert!(SpeechSource::VideoNote.responds_with_voice());
+        assert!(!SpeechSource::Audio.responds_with_voice());
+        assert_eq!(SpeechSource::VideoNote.label(), "video_note");
+    }
+
+    #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+    #[test]
+    fn test_pcm_to_audio_data_keeps_native_spec() {
+        let pcm = PcmAudio {
+            samples: vec![0; 96000],
+            sample_rate: 48000,
+            channels: 2,
+        };
+        let audio = VoiceManager::pcm_to_audio_data(&pcm);
+        assert_eq!(audio.sample_rate, 48000);
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.duration_ms, Some(1000));
+    }
+
+    #[cfg(any(feature = "voice-whisper", feature = "voice-unmute"))]
+    #[test]
+    fn test_decode_rejects_unknown_container() {
+        assert!(VoiceManager::decode_speech_audio(b"not audio at all").is_err());
+    }
+
     #[test]
     fn test_voice_config_parsing() {
         let settings = serde_json::json!({
@@ -0,0 +1,112 @@
+//! "Open full workspace" button
+//!
+//! When `TelegramConfig::webapp_url` is set, `/start` and `/workspace` reply
+//! with an inline button that opens the web UI bound to the chat's room. The
+//! URL carries a short-lived signed token (see `zoey_core::utils::webapp_auth`)
+//! which the web UI exchanges for a session cookie.
+//!
+//! Telegram only allows WebApp buttons in private chats, so groups get a plain
+//! URL button that opens in the browser instead.
+
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, WebAppInfo};
+use uuid::Uuid;
+use zoey_core::utils::webapp_auth::{
+    sign_webapp_token, webapp_link_key, WebAppClaims, WEBAPP_LINK_TTL_SECS,
+};
+
+/// Label of the workspace button
+const BUTTON_TEXT: &str = "Open full workspace";
+
+/// Deterministic room ID for a Telegram chat
+pub fn room_id_for_chat(chat_id: i64) -> Uuid {
+    zoey_core::string_to_uuid(&format!("telegram-room-{}", chat_id))
+}
+
+/// Deterministic entity ID for a Telegram user
+pub fn entity_id_for_user(user_id: u64) -> Uuid {
+    zoey_core::string_to_uuid(&format!("telegram-user-{}", user_id))
+}
+
+/// Builds signed workspace links for the configured web UI
+pub struct WorkspaceLink {
+    webapp_url: reqwest::Url,
+    key: Vec<u8>,
+}
+
+impl WorkspaceLink {
+    /// `None` when `webapp_url` is not a valid URL
+    pub fn new(webapp_url: &str, bot_token: &str) -> Option<Self> {
+        let webapp_url = reqwest::Url::parse(webapp_url).ok()?;
+        Some(Self {
+            webapp_url,
+            key: webapp_link_key(bot_token),
+        })
+    }
+
+    /// Web UI URL bound to `chat_id`'s room and `user_id`'s entity
+    pub fn url_for(&self, chat_id: i64, user_id: u64, now: i64) -> reqwest::Url {
+        let claims = WebAppClaims::new(
+            room_id_for_chat(chat_id),
+            entity_id_for_user(user_id),
+            WEBAPP_LINK_TTL_SECS,
+            now,
+        );
+        let mut url = self.webapp_url.clone();
+        url.query_pairs_mut()
+            .append_pair("t", &sign_webapp_token(&self.key, &claims));
+        url
+    }
+
+    /// Inline keyboard with the workspace button
+    pub fn markup(&self, chat_id: i64, user_id: u64, is_private: bool) -> InlineKeyboardMarkup {
+        let url = self.url_for(chat_id, user_id, chrono::Utc::now().timestamp());
+        let button = if is_private {
+            InlineKeyboardButton::web_app(BUTTON_TEXT, WebAppInfo { url })
+        } else {
+            InlineKeyboardButton::url(BUTTON_TEXT, url)
+        };
+        InlineKeyboardMarkup::new(vec![vec![button]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::InlineKeyboardButtonKind;
+    use zoey_core::utils::webapp_auth::verify_webapp_token;
+
+    const BOT_TOKEN: &str = "123456:TEST-fixture-token";
+
+    #[test]
+    fn test_url_carries_verifiable_token() {
+        let link = WorkspaceLink::new("https://ui.example.com/webapp?lang=de", BOT_TOKEN).unwrap();
+        let url = link.url_for(-100_42, 7, 1_000);
+        assert!(url.as_str().starts_with("https://ui.example.com/webapp?lang=de&t="));
+
+        let token = url
+            .query_pairs()
+            .find(|(k, _)| k == "t")
+            .map(|(_, v)| v.into_owned())
+            .unwrap();
+        let claims = verify_webapp_token(&webapp_link_key(BOT_TOKEN), &token, 1_000).unwrap();
+        assert_eq!(claims.room_id, room_id_for_chat(-100_42));
+        assert_eq!(claims.entity_id, entity_id_for_user(7));
+        assert_eq!(claims.expires_at, 1_000 + WEBAPP_LINK_TTL_SECS);
+    }
+
+    #[test]
+    fn test_button_kind_depends_on_chat_type() {
+        let link = WorkspaceLink::new("https://ui.example.com/webapp", BOT_TOKEN).unwrap();
+        let private = link.markup(1, 1, true);
+        assert!(matches!(
+            private.inline_keyboard[0][0].kind,
+            InlineKeyboardButtonKind::WebApp(_)
+        ));
+        let group = link.markup(-1, 1, false);
+        assert!(matches!(
+            group.inline_keyboard[0][0].kind,
+            InlineKeyboardButtonKind::Url(_)
+        ));
+        assert!(WorkspaceLink::new("not a url", BOT_TOKEN).is_none());
+    }
+}
@@ -0,0 +1,422 @@
+//! Operator admin routes for inspecting and evicting rooms
+//!
+//! These routes are served directly by the web adapter (not proxied) and read
+//! from the runtime's memory store:
+//!
+//! - `GET  /agent/admin/rooms`            list rooms with turn counts and last activity
+//! - `GET  /agent/admin/room/:id`         room detail with participants and a page of turns
+//!   (`?limit=N&before=<cursor>`, newest first; `nextCursor` fetches older turns)
+//! - `POST /agent/admin/room/:id/clear`   purge the room's messages, thoughts and UI context
+//! - `POST /agent/admin/cleanup`          delete stale rooms in bulk (see [`crate::cleanup`])
+//!
+//! All routes require `Authorization: Bearer <admin_token>`. When no admin
+//! token is configured the routes are disabled and always return 403.
+
+use crate::error::{WebError, WebResult};
+use crate::openapi::Success;
+use crate::SimpleUiServer;
+use axum::extract::rejection::PathRejection;
+use axum::extract::{Path, Query, State as AxumState};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use zoey_core::{IDatabaseAdapter, MemoryCursor, MemoryQuery, Pagination, Participant, Room};
+
+/// Memory tables that hold per-room conversation state
+const ROOM_TABLES: [&str; 2] = ["messages", "thoughts"];
+
+/// A room counts as active if it saw a message within this window (ms)
+const ACTIVE_WINDOW_MS: i64 = 15 * 60 * 1000;
+
+/// Number of recent turns returned in room detail
+const DETAIL_RECENT_TURNS: usize = 20;
+
+/// Largest turn page a room detail request may ask for
+const DETAIL_MAX_TURNS: usize = 200;
+
+/// Summary row for the room list
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub source: String,
+    pub turn_count: usize,
+    pub thought_count: usize,
+    #[schemars(required)]
+    pub last_activity: Option<i64>,
+    pub active: bool,
+}
+
+impl RoomSummary {
+    fn new(room: &Room, turn_count: usize, thought_count: usize, last_activity: Option<i64>, now_ms: i64) -> Self {
+        Self {
+            id: room.id,
+            name: room.name.clone(),
+            source: room.source.clone(),
+            turn_count,
+            thought_count,
+            last_activity,
+            active: is_active(last_activity, now_ms),
+        }
+    }
+}
+
+/// A single turn in the room detail view
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomTurn {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub text: String,
+    pub created_at: i64,
+}
+
+/// Room detail response
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomDetail {
+    #[serde(flatten)]
+    pub summary: RoomSummary,
+    #[schemars(with = "Vec<ParticipantSchema>")]
+    pub participants: Vec<Participant>,
+    pub recent_turns: Vec<RoomTurn>,
+    /// Cursor for the next (older) page of turns
+    #[schemars(required)]
+    pub next_cursor: Option<String>,
+}
+
+/// How a [`Participant`] of the runtime serializes
+#[derive(JsonSchema)]
+#[schemars(rename = "Participant")]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct ParticipantSchema {
+    entity_id: Uuid,
+    room_id: Uuid,
+    joined_at: Option<i64>,
+    metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `GET /agent/admin/rooms` reply
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RoomList {
+    rooms: Vec<RoomSummary>,
+}
+
+/// `GET /agent/admin/room/:id` reply
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RoomDetailResponse {
+    room: RoomDetail,
+}
+
+/// `POST /agent/admin/room/:id/clear` reply
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ClearRoomResponse {
+    room_id: Uuid,
+    /// Memories removed per table
+    removed: BTreeMap<String, usize>,
+}
+
+/// Turn paging for room detail
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub(crate) struct TurnPageQuery {
+    /// Turns per page (at most 200)
+    limit: Option<usize>,
+    /// Cursor from a previous page's `nextCursor`
+    before: Option<String>,
+}
+
+impl TurnPageQuery {
+    fn pagination(&self) -> WebResult<Pagination> {
+        let before = match self.before.as_deref() {
+            Some(raw) => Some(
+                MemoryCursor::parse(raw)
+                    .ok_or_else(|| WebError::bad_request("invalid_cursor", "Invalid page cursor"))?,
+            ),
+            None => None,
+        };
+        Ok(Pagination {
+            before,
+            limit: self.limit.unwrap_or(DETAIL_RECENT_TURNS).clamp(1, DETAIL_MAX_TURNS),
+        })
+    }
+}
+
+fn is_active(last_activity: Option<i64>, now_ms: i64) -> bool {
+    last_activity
+        .map(|ts| now_ms.saturating_sub(ts) <= ACTIVE_WINDOW_MS)
+        .unwrap_or(false)
+}
+
+/// Sort summaries so the most recently active rooms come first
+fn sort_by_activity(rooms: &mut [RoomSummary]) {
+    rooms.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+}
+
+/// Check the request carries the configured admin bearer token
+pub(crate) fn authorize(state: &SimpleUiServer, headers: &HeaderMap) -> WebResult<()> {
+    let Some(expected) = state.config.admin_token.as_deref().filter(|t| !t.is_empty()) else {
+        return Err(WebError::forbidden("admin_disabled", "Admin routes are disabled"));
+    };
+    let provided = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        Some(_) => Err(WebError::forbidden("invalid_token", "Invalid admin token")),
+        None => Err(WebError::unauthorized("missing_token", "Missing admin token")),
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub(crate) fn adapter_for(state: &SimpleUiServer) -> WebResult<(Uuid, Arc<dyn IDatabaseAdapter + Send + Sync>)> {
+    let rt = crate::read_runtime(state)?;
+    match rt.get_adapter() {
+        Some(adapter) => Ok((rt.agent_id, adapter)),
+        None => Err(WebError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "no_database",
+            "No database adapter configured",
+        )),
+    }
+}
+
+fn room_query(room_id: Uuid, table: &str, count: Option<usize>) -> MemoryQuery {
+    MemoryQuery {
+        room_id: Some(room_id),
+        table_name: table.to_string(),
+        count,
+        ..Default::default()
+    }
+}
+
+async fn summarize(adapter: &(dyn IDatabaseAdapter + Send + Sync), room: &Room, now_ms: i64) -> RoomSummary {
+    let turn_count = adapter
+        .count_memories(room_query(room.id, "messages", None))
+        .await
+        .unwrap_or(0);
+    let thought_count = adapter
+        .count_memories(room_query(room.id, "thoughts", None))
+        .await
+        .unwrap_or(0);
+    // Memories are returned newest first
+    let last_activity = adapter
+        .get_memories(room_query(room.id, "messages", Some(1)))
+        .await
+        .ok()
+        .and_then(|m| m.first().map(|m| m.created_at));
+    RoomSummary::new(room, turn_count, thought_count, last_activity, now_ms)
+}
+
+/// `GET /agent/admin/rooms`
+pub(crate) async fn list_rooms(
+    AxumState(state): AxumState<SimpleUiServer>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<RoomList>>> {
+    authorize(&state, &headers)?;
+    let (agent_id, adapter) = adapter_for(&state)?;
+    let rooms = adapter
+        .get_rooms_for_agent(agent_id)
+        .await
+        .map_err(|e| WebError::internal("database_error", e.to_string()))?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let mut summaries = Vec::with_capacity(rooms.len());
+    for room in &rooms {
+        summaries.push(summarize(adapter.as_ref(), room, now_ms).await);
+    }
+    sort_by_activity(&mut summaries);
+    Ok(Json(Success::new(RoomList { rooms: summaries })))
+}
+
+/// `GET /agent/admin/room/:id`
+pub(crate) async fn room_detail(
+    AxumState(state): AxumState<SimpleUiServer>,
+    room_id: Result<Path<Uuid>, PathRejection>,
+    Query(page): Query<TurnPageQuery>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<RoomDetailResponse>>> {
+    authorize(&state, &headers)?;
+    let Path(room_id) = room_id?;
+    let pagination = page.pagination()?;
+    let (_, adapter) = adapter_for(&state)?;
+    let room = adapter
+        .get_room(room_id)
+        .await
+        .map_err(|e| WebError::internal("database_error", e.to_string()))?
+        .ok_or_else(|| WebError::not_found("room_not_found", "Room not found"))?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let summary = summarize(adapter.as_ref(), &room, now_ms).await;
+    let participants = adapter.get_participants(room_id).await.unwrap_or_default();
+    let turns = adapter
+        .get_memories_page(room_id, "messages", pagination)
+        .await
+        .map_err(|e| WebError::internal("database_error", e.to_string()))?;
+    let next_cursor = turns.next_cursor.map(|c| c.to_string());
+    let recent_turns = turns
+        .memories
+        .into_iter()
+        .map(|m| RoomTurn {
+            id: m.id,
+            entity_id: m.entity_id,
+            text: m.content.text,
+            created_at: m.created_at,
+        })
+        .collect();
+    let detail = RoomDetail {
+        summary,
+        participants,
+        recent_turns,
+        next_cursor,
+    };
+    Ok(Json(Success::new(RoomDetailResponse { room: detail })))
+}
+
+/// Remove the room's messages and thoughts, returning the count per table
+pub(crate) async fn purge_room_memories(
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    room_id: Uuid,
+) -> BTreeMap<String, usize> {
+    let mut removed = BTreeMap::new();
+    for table in ROOM_TABLES {
+        let mut count = 0usize;
+        if let Ok(memories) = adapter.get_memories(room_query(room_id, table, None)).await {
+            for m in memories {
+                if adapter.remove_memory(m.id, table).await.unwrap_or(false) {
+                    count += 1;
+                }
+            }
+        }
+        removed.insert(table.to_string(), count);
+    }
+    removed
+}
+
+/// Blank any cached `ui:lastThought` context for the room
+pub(crate) fn clear_room_context(state: &SimpleUiServer, room_id: Uuid) -> WebResult<()> {
+    let prefix = format!("ui:lastThought:{}:", room_id);
+    let mut rt = state.runtime.write().map_err(|_| {
+        tracing::error!("Runtime lock poisoned, room context not cleared");
+        WebError::runtime_unavailable()
+    })?;
+    let keys: Vec<String> = rt
+        .get_settings_with_prefix(&prefix)
+        .into_iter()
+        .map(|(k, _)| k)
+        .collect();
+    for key in keys {
+        rt.set_setting(&key, serde_json::Value::Null, false);
+    }
+    Ok(())
+}
+
+/// `POST /agent/admin/room/:id/clear`
+///
+/// Removes the room's messages and thoughts and blanks any cached
+/// `ui:lastThought` context so the next turn starts fresh.
+pub(crate) async fn clear_room(
+    AxumState(state): AxumState<SimpleUiServer>,
+    room_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<ClearRoomResponse>>> {
+    authorize(&state, &headers)?;
+    let Path(room_id) = room_id?;
+    let (_, adapter) = adapter_for(&state)?;
+    let removed = purge_room_memories(adapter.as_ref(), room_id).await;
+    clear_room_context(&state, room_id)?;
+
+    tracing::info!(room_id = %room_id, removed = ?removed, "Admin cleared room");
+    Ok(Json(Success::new(ClearRoomResponse { room_id, removed })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleUiConfig;
+
+    async fn server(admin_token: Option<&str>) -> SimpleUiServer {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        SimpleUiServer::new(
+            SimpleUiConfig {
+                admin_token: admin_token.map(str::to_string),
+                ..Default::default()
+            },
+            runtime,
+        )
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_authorize() {
+        let disabled = server(None).await;
+        let err = authorize(&disabled, &bearer("anything")).unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+
+        let enabled = server(Some("s3cret")).await;
+        assert!(authorize(&enabled, &bearer("s3cret")).is_ok());
+        let err = authorize(&enabled, &bearer("wrong")).unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let err = authorize(&enabled, &HeaderMap::new()).unwrap_err();
+        assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_turn_page_query() {
+        let default = TurnPageQuery::default().pagination().unwrap();
+        assert_eq!(default, Pagination::first(DETAIL_RECENT_TURNS));
+
+        let cursor = format!("1700000000000_{}", Uuid::nil());
+        let page = TurnPageQuery { limit: Some(10_000), before: Some(cursor) };
+        let pagination = page.pagination().unwrap();
+        assert_eq!(pagination.limit, DETAIL_MAX_TURNS);
+        assert_eq!(pagination.before.unwrap().created_at, 1_700_000_000_000);
+
+        let bad = TurnPageQuery { limit: None, before: Some("garbage".into()) };
+        assert_eq!(bad.pagination().unwrap_err().status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_activity_and_sorting() {
+        let now = 10 * ACTIVE_WINDOW_MS;
+        assert!(is_active(Some(now - 1000), now));
+        assert!(!is_active(Some(now - ACTIVE_WINDOW_MS - 1), now));
+        assert!(!is_active(None, now));
+
+        let summary = |last: Option<i64>| RoomSummary {
+            id: Uuid::new_v4(),
+            name: String::new(),
+            source: "web".into(),
+            turn_count: 0,
+            thought_count: 0,
+            last_activity: last,
+            active: is_active(last, now),
+        };
+        let mut rooms = vec![summary(None), summary(Some(5)), summary(Some(50))];
+        sort_by_activity(&mut rooms);
+        let order: Vec<_> = rooms.iter().map(|r| r.last_activity).collect();
+        assert_eq!(order, vec![Some(50), Some(5), None]);
+    }
+}
@@ -0,0 +1,208 @@
+//! Hashed script assets for the Simple UI templates
+//!
+//! Each template loads its script from `/assets/js/<name>.<hash>.js`. The
+//! hash is taken from the bundled content when the server starts, so the URL
+//! changes whenever the script does and responses can be cached for a year.
+//!
+//! Per-request values (token, locale strings, session binding) are passed in
+//! a `<script type="application/json" id="zoey-config">` block built by
+//! [`config_block`] and read by [`CONFIG_JS`]. With no inline code left on the
+//! page, `index` can send [`CONTENT_SECURITY_POLICY`] without `unsafe-inline`
+//! for scripts.
+
+use crate::error::{WebError, WebResult};
+use crate::i18n::I18N_RUNTIME_JS;
+use crate::templates::{escape_script_json, MODEL_CONTROLS_JS, WS_CHAT_JS};
+use axum::extract::Path;
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use std::sync::OnceLock;
+
+/// Policy sent with the index page unless `SimpleUiConfig::content_security_policy` is off
+///
+/// Styles stay inline (the templates carry their own `<style>` and `style`
+/// attributes); scripts only load from this origin.
+pub(crate) const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; \
+     script-src 'self'; \
+     style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; \
+     font-src 'self' https://fonts.gstatic.com; \
+     img-src 'self' data:; \
+     connect-src 'self'; \
+     object-src 'none'; \
+     base-uri 'self'";
+
+/// Script assets are immutable under their hashed URL
+pub(crate) const ASSET_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Prelude defining the globals the page scripts expect from `#zoey-config`
+///
+/// A `null` token falls back to the one saved in `localStorage`.
+const CONFIG_JS: &str = r#"
+const ZOEY_CONFIG = JSON.parse(document.getElementById('zoey-config').textContent);
+const API = '/agent';
+const TOKEN = ZOEY_CONFIG.token ?? (localStorage.getItem('zoey_token') || '');
+const LOGS_ENABLED = ZOEY_CONFIG.logsEnabled === true;
+const LOCALE = ZOEY_CONFIG.locale;
+const I18N = ZOEY_CONFIG.i18n || {};
+const MODEL_CONTROLS = ZOEY_CONFIG.modelControls === true;
+const SESSION = ZOEY_CONFIG.session || null;
+"#;
+
+/// A bundled page script and its content hash
+pub(crate) struct Script {
+    name: &'static str,
+    hash: String,
+    body: String,
+}
+
+impl Script {
+    fn new(name: &'static str, page: &str) -> Self {
+        let body = [
+            CONFIG_JS,
+            I18N_RUNTIME_JS,
+            WS_CHAT_JS,
+            MODEL_CONTROLS_JS,
+            page,
+        ]
+        .concat();
+        Self {
+            name,
+            hash: content_hash(body.as_bytes()),
+            body,
+        }
+    }
+
+    /// URL the template loads the script from
+    pub(crate) fn src(&self) -> String {
+        format!("/assets/js/{}", self.file_name())
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}.{}.js", self.name, self.hash)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+fn scripts() -> &'static [Script; 2] {
+    static SCRIPTS: OnceLock<[Script; 2]> = OnceLock::new();
+    SCRIPTS.get_or_init(|| {
+        [
+            Script::new("ui", include_str!("assets/ui.js")),
+            Script::new("lawyer", include_str!("assets/lawyer.js")),
+        ]
+    })
+}
+
+/// Script for the default chat template
+pub(crate) fn ui_script() -> &'static Script {
+    &scripts()[0]
+}
+
+/// Script for the Zoey Lawyer case management template
+pub(crate) fn lawyer_script() -> &'static Script {
+    &scripts()[1]
+}
+
+/// Hash every bundle so the first page load doesn't pay for it
+pub(crate) fn warm() {
+    scripts();
+}
+
+/// 64-bit FNV-1a of `bytes` as hex, stable across builds and platforms
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// `<script type="application/json">` block holding the page config
+///
+/// `<`, `>` and `&` are written as JSON unicode escapes so no value (a
+/// translation, say) can close the element early; `JSON.parse` restores them.
+pub(crate) fn config_block(config: &serde_json::Value) -> String {
+    format!(
+        r#"<script type="application/json" id="zoey-config">{}</script>"#,
+        escape_script_json(&config.to_string())
+    )
+}
+
+/// `GET /assets/js/:file`: a bundled script under its current hashed name
+///
+/// Names with an old hash are not found rather than served stale content
+/// under a URL that promises to be immutable.
+pub(crate) async fn script(Path(file): Path<String>) -> WebResult<Response> {
+    let script = scripts()
+        .iter()
+        .find(|s| s.file_name() == file)
+        .ok_or_else(|| WebError::not_found("asset_not_found", "No such asset"))?;
+    let mut resp = script.body.clone().into_response();
+    let headers = resp.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/javascript; charset=utf-8"),
+    );
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(ASSET_CACHE_CONTROL),
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_stable_and_content_addressed() {
+        // Reference FNV-1a 64 values
+        assert_eq!(content_hash(b""), "cbf29ce484222325");
+        assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+
+        let ui = ui_script();
+        assert_eq!(ui.hash, content_hash(ui.body.as_bytes()));
+        assert_eq!(
+            Script::new("ui", include_str!("assets/ui.js")).src(),
+            ui.src()
+        );
+        assert_ne!(ui.hash, lawyer_script().hash);
+        assert!(ui.src().starts_with("/assets/js/ui."));
+        assert!(ui.src().ends_with(".js"));
+    }
+
+    #[test]
+    fn test_config_block_escapes_markup() {
+        let block = config_block(&serde_json::json!({
+            "i18n": { "x": "</script><script>alert(1)</script> & <!--" }
+        }));
+        let inner = block
+            .strip_prefix(r#"<script type="application/json" id="zoey-config">"#)
+            .and_then(|b| b.strip_suffix("</script>"))
+            .unwrap();
+        assert!(!inner.contains('<'));
+        assert!(!inner.contains('>'));
+        let parsed: serde_json::Value = serde_json::from_str(inner).unwrap();
+        assert_eq!(
+            parsed["i18n"]["x"],
+            "</script><script>alert(1)</script> & <!--"
+        );
+    }
+
+    #[test]
+    fn test_policy_disallows_inline_scripts() {
+        let script_src = CONTENT_SECURITY_POLICY
+            .split(';')
+            .map(str::trim)
+            .find(|d| d.starts_with("script-src"))
+            .unwrap();
+        assert_eq!(script_src, "script-src 'self'");
+    }
+}
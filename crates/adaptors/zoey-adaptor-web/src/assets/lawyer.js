@@ -0,0 +1,1177 @@
+// Entity ID (user identifier), bound by a Telegram workspace session if any
+const entityId = (SESSION && SESSION.entityId) || localStorage.getItem('zoey_entity') || uuid();
+localStorage.setItem('zoey_entity', entityId);
+const PRESENCE_HEARTBEAT_MS = 30000;
+
+// Case state: the web adapter's case store, with localStorage as the offline copy
+let cases = JSON.parse(localStorage.getItem('zoey_cases') || '[]');
+let activeCase = null;
+let messageCount = 0;
+// Reply still being generated, and the index of the user message being edited
+let activeGeneration = null;
+let editingIndex = null;
+// Who else is viewing the open case, kept current by heartbeats and the case event feed
+let presence = { caseId: null, viewers: [], timer: null, feed: null };
+let participantData = null;
+
+function uuid() {
+  try { if (crypto?.randomUUID) return crypto.randomUUID(); } catch {}
+  const rv = () => crypto?.getRandomValues ? (crypto.getRandomValues(new Uint8Array(1))[0] & 15) : (Math.random() * 16 | 0);
+  return 'xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx'.replace(/[xy]/g, c => {
+    const v = rv();
+    return (c === 'x' ? v : (v & 0x3 | 0x8)).toString(16);
+  });
+}
+
+function showToast(message) {
+  const toast = document.getElementById('toast');
+  toast.textContent = message;
+  toast.classList.add('show');
+  setTimeout(() => toast.classList.remove('show'), 3000);
+}
+
+function saveCases() {
+  localStorage.setItem('zoey_cases', JSON.stringify(cases));
+}
+
+function caseHeaders() {
+  const headers = { 'Content-Type': 'application/json', 'X-Entity-Id': entityId };
+  if (TOKEN) headers['Authorization'] = 'Bearer ' + TOKEN;
+  return headers;
+}
+
+// Case metadata is kept by the web adapter under /agent/cases; cases it
+// returned are marked `synced`, anything else (invites joined, cases created
+// while it was unreachable) stays local only
+function fromServer(c) {
+  return {
+    id: c.id,
+    name: c.name,
+    matterNumber: c.matterNumber,
+    status: c.status,
+    isOwner: true,
+    inviteToken: c.inviteToken,
+    createdAt: c.createdAt,
+    lastActivity: c.lastActivity,
+    messageCount: c.messageCount,
+    synced: true
+  };
+}
+
+async function loadServerCases() {
+  try {
+    const res = await fetch(`${API}/cases?entity_id=${encodeURIComponent(entityId)}`, { headers: caseHeaders() });
+    if (!res.ok) return;
+    const data = await res.json();
+    const remote = [...(data.active || []), ...(data.closed || [])].map(fromServer);
+    const ids = new Set(remote.map(c => c.id));
+    cases = [...remote, ...cases.filter(c => !ids.has(c.id))];
+    if (activeCase) activeCase = cases.find(c => c.id === activeCase.id) || activeCase;
+    saveCases();
+    renderCaseList();
+  } catch {}
+}
+
+async function patchCase(c, fields) {
+  if (!c.synced) return;
+  try {
+    await fetch(`${API}/cases/${c.id}`, {
+      method: 'PATCH',
+      headers: caseHeaders(),
+      body: JSON.stringify(fields)
+    });
+  } catch {}
+}
+
+// Participants are managed by the web adapter under /agent/cases/:id
+async function registerInvite(c) {
+  if (!c.isOwner || !c.inviteToken) return;
+  try {
+    await fetch(`${API}/cases/${c.id}/invite`, {
+      method: 'PUT',
+      headers: caseHeaders(),
+      body: JSON.stringify({ inviteToken: c.inviteToken, displayName: i18nText('legal.owner') })
+    });
+  } catch {}
+}
+
+function renderLocalParticipants() {
+  document.getElementById('participantList').innerHTML = `
+    <div class="participant">
+      <div class="participant-avatar">${escapeHtml(i18nText('legal.you').charAt(0))}</div>
+      <div class="participant-info">
+        <div class="participant-name">${i18n('legal.you')}</div>
+        <div class="participant-role"><span class="role-badge ${activeCase?.isOwner ? 'owner' : 'viewer'}">${i18n(activeCase?.isOwner ? 'legal.owner' : 'legal.viewer')}</span></div>
+      </div>
+    </div>`;
+}
+
+async function loadParticipants() {
+  const c = activeCase;
+  if (!c) return;
+  let data = null;
+  try {
+    const res = await fetch(`${API}/cases/${c.id}/participants`, { headers: caseHeaders() });
+    if (res.ok) data = await res.json();
+  } catch {}
+  if (activeCase !== c) return;
+  participantData = data;
+  if (!data) {
+    renderLocalParticipants();
+    return;
+  }
+  c.role = data.role;
+  renderParticipants();
+}
+
+function renderParticipants() {
+  const data = participantData;
+  if (!data) return;
+  const isOwner = data.role === 'owner';
+  const viewing = new Set(presence.viewers.map(v => v.entityId));
+  document.getElementById('participantList').innerHTML = data.participants.map(p => {
+    const name = p.entityId === entityId ? i18nText('legal.you') : p.displayName;
+    const actions = isOwner && p.role !== 'owner' ? `
+      <div class="participant-actions">
+        <select data-change="changeParticipantRole" data-id="${p.entityId}">
+          <option value="viewer" ${p.role === 'viewer' ? 'selected' : ''}>${i18n('legal.viewer')}</option>
+          <option value="collaborator" ${p.role === 'collaborator' ? 'selected' : ''}>${i18n('legal.collaborator')}</option>
+        </select>
+        <button data-action="removeParticipant" data-id="${p.entityId}" data-name="${escapeHtml(p.displayName)}">${i18n('legal.remove')}</button>
+      </div>` : '';
+    return `
+      <div class="participant">
+        <div class="participant-avatar">${escapeHtml(name.charAt(0).toUpperCase())}</div>
+        <div class="participant-info">
+          <div class="participant-name">${escapeHtml(name)}${viewing.has(p.entityId) ? `<span class="presence-dot" title="${i18n('legal.viewing_now')}"></span>` : ''}</div>
+          <div class="participant-role"><span class="role-badge ${p.role}">${i18n('legal.' + p.role)}</span></div>
+        </div>
+        ${actions}
+      </div>`;
+  }).join('');
+}
+
+function presenceSummary(viewers) {
+  const names = viewers.filter(v => v.entityId !== entityId).map(v => v.displayName);
+  if (names.length === 0) return '';
+  if (names.length === 1) return i18nText('legal.viewing_one', { name: names[0] });
+  if (names.length === 2) return i18nText('legal.viewing_two', { first: names[0], second: names[1] });
+  return i18nText('legal.viewing_many', { name: names[0], count: names.length - 1 });
+}
+
+function applyPresence(caseId, viewers) {
+  if (presence.caseId !== caseId) return;
+  presence.viewers = viewers || [];
+  document.getElementById('presenceSummary').textContent = presenceSummary(presence.viewers);
+  renderParticipants();
+}
+
+async function sendHeartbeat(caseId) {
+  try {
+    const res = await fetch(`${API}/cases/${caseId}/presence/heartbeat`, {
+      method: 'POST',
+      headers: caseHeaders()
+    });
+    // Cases that are not shared (yet) have no presence
+    if (!res.ok) return;
+    const data = await res.json();
+    applyPresence(caseId, data.viewers);
+    if (!presence.feed && presence.caseId === caseId) watchCaseEvents(caseId);
+  } catch {}
+}
+
+// Server-sent `presence` events from the case event feed
+async function watchCaseEvents(caseId) {
+  const feed = new AbortController();
+  presence.feed = feed;
+  try {
+    const res = await fetch(`${API}/cases/${caseId}/events`, { headers: caseHeaders(), signal: feed.signal });
+    if (!res.ok) throw new Error(res.status);
+    const reader = res.body.getReader();
+    const decoder = new TextDecoder();
+    let buffer = '';
+    while (true) {
+      const { value, done } = await reader.read();
+      if (done) break;
+      buffer += decoder.decode(value, { stream: true });
+      const events = buffer.split('\n\n');
+      buffer = events.pop();
+      for (const event of events) {
+        const data = event.split('\n').find(line => line.startsWith('data:'));
+        if (!event.includes('event: presence') || !data) continue;
+        try {
+          const payload = JSON.parse(data.slice(5));
+          applyPresence(caseId, payload.viewers);
+          if (payload.change) loadParticipants();
+        } catch {}
+      }
+    }
+  } catch {}
+  // Reconnected by the next heartbeat
+  if (presence.feed === feed) presence.feed = null;
+}
+
+function stopPresence() {
+  clearInterval(presence.timer);
+  presence.feed?.abort();
+  presence = { caseId: null, viewers: [], timer: null, feed: null };
+  document.getElementById('presenceSummary').textContent = '';
+}
+
+function startPresence(caseId) {
+  stopPresence();
+  presence.caseId = caseId;
+  sendHeartbeat(caseId);
+  presence.timer = setInterval(() => sendHeartbeat(caseId), PRESENCE_HEARTBEAT_MS);
+}
+
+async function changeParticipantRole(participantId, role) {
+  if (!activeCase) return;
+  try {
+    const res = await fetch(`${API}/cases/${activeCase.id}/participants/${participantId}`, {
+      method: 'PATCH',
+      headers: caseHeaders(),
+      body: JSON.stringify({ role })
+    });
+    if (!res.ok) showToast(i18nText('legal.remove_failed'));
+  } catch {
+    showToast(i18nText('legal.remove_failed'));
+  }
+  loadParticipants();
+}
+
+async function removeParticipant(participantId, name) {
+  if (!activeCase || !confirm(i18nText('legal.confirm_remove', { name }))) return;
+  try {
+    const res = await fetch(`${API}/cases/${activeCase.id}/participants/${participantId}`, {
+      method: 'DELETE',
+      headers: caseHeaders()
+    });
+    showToast(i18nText(res.ok ? 'legal.participant_removed' : 'legal.remove_failed'));
+  } catch {
+    showToast(i18nText('legal.remove_failed'));
+  }
+  loadParticipants();
+}
+
+function formatDate(timestamp) {
+  const d = new Date(timestamp);
+  const now = new Date();
+  const diffMs = now - d;
+  const diffDays = Math.floor(diffMs / (1000 * 60 * 60 * 24));
+  if (diffDays === 0) return i18nText('legal.today');
+  if (diffDays === 1) return i18nText('legal.yesterday');
+  if (diffDays < 7) return i18nText('legal.days_ago', { count: diffDays });
+  return d.toLocaleDateString();
+}
+
+function renderCaseList() {
+  const activeCasesEl = document.getElementById('activeCases');
+  const closedCasesEl = document.getElementById('closedCases');
+
+  const activeCases = cases.filter(c => c.status === 'active');
+  const closedCases = cases.filter(c => c.status === 'closed');
+
+  activeCasesEl.innerHTML = activeCases.length === 0
+    ? `<div class="empty-state"><div style="font-size: 14px;">${i18n('legal.no_active_cases')}</div></div>`
+    : activeCases.map(c => `
+      <div class="case-item ${activeCase?.id === c.id ? 'active' : ''}" data-action="selectCase" data-id="${c.id}">
+        <div class="case-item-name">${escapeHtml(c.name)}</div>
+        <div class="case-item-meta">
+          <span class="case-status active">${i18n('legal.status_active')}</span>
+          <span>${formatDate(c.lastActivity || c.createdAt)}</span>
+        </div>
+      </div>
+    `).join('');
+
+  closedCasesEl.innerHTML = closedCases.length === 0
+    ? `<div class="empty-state" style="padding: 16px;"><div style="font-size: 13px;">${i18n('legal.no_closed_cases')}</div></div>`
+    : closedCases.map(c => `
+      <div class="case-item ${activeCase?.id === c.id ? 'active' : ''}" data-action="selectCase" data-id="${c.id}">
+        <div class="case-item-name">${escapeHtml(c.name)}</div>
+        <div class="case-item-meta">
+          <span class="case-status closed">${i18n('legal.status_closed')}</span>
+        </div>
+      </div>
+    `).join('');
+}
+
+function escapeHtml(text) {
+  const div = document.createElement('div');
+  div.textContent = text;
+  return div.innerHTML;
+}
+
+function selectCase(caseId) {
+  const c = cases.find(x => x.id === caseId);
+  if (!c) return;
+
+  activeCase = c;
+  editingIndex = null;
+  participantData = null;
+  document.getElementById('caseHeader').style.display = 'flex';
+  document.getElementById('inputContainer').style.display = 'block';
+  document.getElementById('detailSidebar').style.display = 'flex';
+  document.getElementById('welcomeMessage').style.display = 'none';
+  document.getElementById('currentCaseTitle').textContent = c.name;
+  document.getElementById('caseCreated').textContent = formatDate(c.createdAt);
+  document.getElementById('caseMessages').textContent = c.messageCount || 0;
+  document.getElementById('caseStatus').textContent = c.status === 'active' ? i18nText('legal.status_active') : i18nText('legal.status_closed');
+
+  // Load case messages from localStorage
+  const messagesKey = `zoey_case_messages_${caseId}`;
+  const messages = JSON.parse(localStorage.getItem(messagesKey) || '[]');
+  renderMessages(messages);
+  messageCount = messages.length;
+
+  renderCaseList();
+  renderLocalParticipants();
+  loadParticipants();
+  startPresence(c.id);
+}
+
+function renderMessages(messages) {
+  const chat = document.getElementById('chat');
+  if (messages.length === 0) {
+    chat.innerHTML = `
+      <div class="welcome-message">
+        <div class="welcome-icon">Z</div>
+        <div class="welcome-title">${i18n('legal.case_heading', { name: activeCase.name })}</div>
+        <div class="welcome-subtitle">${i18n('legal.case_intro')}</div>
+      </div>
+    `;
+    return;
+  }
+  chat.innerHTML = messages.map((m, i) => `
+    <div class="msg ${m.role}">
+      <div class="msg-avatar">${m.role === 'agent' ? 'Z' : 'Y'}</div>
+      <div class="bubble">${escapeHtml(m.text)}</div>
+      ${m.role === 'user' ? editButton(i) : ''}
+    </div>
+  `).join('');
+  chat.scrollTop = chat.scrollHeight;
+}
+
+function addMessage(role, text) {
+  if (!activeCase) return;
+  const messagesKey = `zoey_case_messages_${activeCase.id}`;
+  const messages = JSON.parse(localStorage.getItem(messagesKey) || '[]');
+  messages.push({ role, text, timestamp: Date.now() });
+  localStorage.setItem(messagesKey, JSON.stringify(messages));
+
+  activeCase.messageCount = messages.length;
+  activeCase.lastActivity = Date.now();
+  saveCases();
+  patchCase(activeCase, { messageCount: activeCase.messageCount, lastActivity: activeCase.lastActivity });
+  document.getElementById('caseMessages').textContent = messages.length;
+
+  const chat = document.getElementById('chat');
+  const welcomeMsg = chat.querySelector('.welcome-message');
+  if (welcomeMsg) welcomeMsg.remove();
+
+  const msgEl = document.createElement('div');
+  msgEl.className = `msg ${role}`;
+  msgEl.innerHTML = `
+    <div class="msg-avatar">${role === 'agent' ? 'Z' : 'Y'}</div>
+    <div class="bubble">${escapeHtml(text)}</div>
+    ${role === 'user' ? editButton(messages.length - 1) : ''}
+  `;
+  chat.appendChild(msgEl);
+  chat.scrollTop = chat.scrollHeight;
+}
+
+function showTyping() {
+  const chat = document.getElementById('chat');
+  let typing = document.getElementById('typingIndicator');
+  if (!typing) {
+    typing = document.createElement('div');
+    typing.id = 'typingIndicator';
+    typing.className = 'msg agent';
+    typing.innerHTML = `
+      <div class="msg-avatar">Z</div>
+      <div class="bubble"><div class="typing-indicator"><span></span><span></span><span></span></div></div>
+    `;
+    chat.appendChild(typing);
+  }
+  chat.scrollTop = chat.scrollHeight;
+}
+
+function hideTyping() {
+  const typing = document.getElementById('typingIndicator');
+  if (typing) typing.remove();
+}
+
+function editButton(index) {
+  return `<button class="msg-edit" type="button" title="${i18n('chat.edit')}" data-action="editMessage" data-index="${index}">✎</button>`;
+}
+
+function editMessage(index) {
+  if (!activeCase) return;
+  if (activeCase.role === 'viewer') {
+    showToast(i18nText('legal.viewer_read_only'));
+    return;
+  }
+  const messages = JSON.parse(localStorage.getItem(`zoey_case_messages_${activeCase.id}`) || '[]');
+  const m = messages[index];
+  if (!m || m.role !== 'user') return;
+  editingIndex = index;
+  const input = document.getElementById('messageInput');
+  input.value = m.text;
+  input.focus();
+  showToast(i18nText('chat.editing'));
+}
+
+// Drops the edited message and everything after it, cancelling a reply
+// that is still being generated for the old conversation
+function truncateForResend() {
+  if (editingIndex === null || !activeCase) return;
+  if (activeGeneration) activeGeneration.abort();
+  const messagesKey = `zoey_case_messages_${activeCase.id}`;
+  const messages = JSON.parse(localStorage.getItem(messagesKey) || '[]').slice(0, editingIndex);
+  editingIndex = null;
+  localStorage.setItem(messagesKey, JSON.stringify(messages));
+  activeCase.messageCount = messages.length;
+  saveCases();
+  document.getElementById('caseMessages').textContent = messages.length;
+  renderMessages(messages);
+}
+
+async function sendMessage() {
+  if (!activeCase) {
+    showToast(i18nText('legal.select_case_first'));
+    return;
+  }
+
+  const input = document.getElementById('messageInput');
+  const text = input.value.trim();
+  if (!text) return;
+  if (activeCase.role === 'viewer') {
+    showToast(i18nText('legal.viewer_read_only'));
+    return;
+  }
+
+  input.value = '';
+  truncateForResend();
+  addMessage('user', text);
+  showTyping();
+  const generation = new AbortController();
+  activeGeneration = generation;
+
+  let wsAssembled = '';
+  let wsFailed = false;
+  const handledByWs = await wsChat(withModelParams({ text, roomId: activeCase.id, entityId }), {
+    onChunk: (chunk) => { wsAssembled += chunk; },
+    onDone: (last) => { wsAssembled += last; },
+    onError: () => { wsFailed = true; },
+    signal: generation.signal,
+  });
+  if (generation.signal.aborted) return;
+  if (handledByWs) {
+    hideTyping();
+    if (wsFailed && !wsAssembled.trim()) {
+      addMessage('agent', i18nText('legal.connection_error'));
+    } else if (wsAssembled.trim()) {
+      addMessage('agent', parseReply(wsAssembled));
+    } else {
+      addMessage('agent', 'I apologize, but I couldn\'t process your request. Please try again.');
+    }
+    return;
+  }
+
+  try {
+    const headers = { 'Content-Type': 'application/json' };
+    if (TOKEN) headers['Authorization'] = 'Bearer ' + TOKEN;
+
+    const res = await fetch(API + '/chat/stream', {
+      method: 'POST',
+      headers,
+      body: JSON.stringify(withModelParams({
+        text,
+        roomId: activeCase.id,
+        entityId,
+        stream: true
+      })),
+      signal: generation.signal
+    });
+    if (res.status === 403) {
+      hideTyping();
+      showToast(i18nText('legal.viewer_read_only'));
+      loadParticipants();
+      return;
+    }
+
+    const reader = res.body.getReader();
+    const decoder = new TextDecoder();
+    let buffer = '';
+    let assembled = '';
+
+    while (true) {
+      const { value, done } = await reader.read();
+      if (done) break;
+
+      buffer += decoder.decode(value, { stream: true });
+      const lines = buffer.split('\n');
+      buffer = lines.pop();
+
+      for (const line of lines) {
+        if (line.startsWith('data:')) {
+          try {
+            const payload = JSON.parse(line.slice(5));
+            if (payload.text) {
+              assembled += payload.text;
+            }
+          } catch {}
+        }
+      }
+    }
+
+    hideTyping();
+    if (assembled.trim()) {
+      // Parse reply from response
+      const reply = parseReply(assembled);
+      addMessage('agent', reply);
+    } else {
+      addMessage('agent', 'I apologize, but I couldn\'t process your request. Please try again.');
+    }
+  } catch (e) {
+    if (generation.signal.aborted) return;
+    hideTyping();
+    addMessage('agent', i18nText('legal.connection_error'));
+  }
+}
+
+function parseReply(text) {
+  // Try to extract reply from various formats
+  const replyMatch = text.match(/<reply>([\s\S]*?)<\/reply>/i);
+  if (replyMatch) return replyMatch[1].trim();
+
+  const textMatch = text.match(/<text>([\s\S]*?)<\/text>/i);
+  if (textMatch) return textMatch[1].trim();
+
+  return text.trim();
+}
+
+// New Case Modal
+function showNewCaseModal() {
+  document.getElementById('newCaseModal').classList.add('active');
+  document.getElementById('newCaseName').focus();
+}
+
+function hideNewCaseModal() {
+  document.getElementById('newCaseModal').classList.remove('active');
+  document.getElementById('newCaseName').value = '';
+  document.getElementById('newCaseMatter').value = '';
+}
+
+async function createCase() {
+  const name = document.getElementById('newCaseName').value.trim();
+  const matter = document.getElementById('newCaseMatter').value.trim();
+
+  if (!name) {
+    showToast(i18nText('legal.enter_case_name'));
+    return;
+  }
+
+  // The server picks the ID and invite token and registers the invite
+  let newCase = null;
+  try {
+    const res = await fetch(`${API}/cases`, {
+      method: 'POST',
+      headers: caseHeaders(),
+      body: JSON.stringify({ name, matterNumber: matter || null, displayName: i18nText('legal.owner') })
+    });
+    if (res.ok) newCase = fromServer((await res.json()).case);
+  } catch {}
+  const local = !newCase;
+  if (local) {
+    newCase = {
+      id: uuid(),
+      name,
+      matterNumber: matter || null,
+      status: 'active',
+      isOwner: true,
+      inviteToken: uuid().replace(/-/g, '').slice(0, 16),
+      createdAt: Date.now(),
+      lastActivity: Date.now(),
+      messageCount: 0
+    };
+  }
+
+  cases.unshift(newCase);
+  saveCases();
+  hideNewCaseModal();
+  selectCase(newCase.id);
+  showToast(i18nText('legal.case_created'));
+  if (local) registerInvite(newCase).then(loadParticipants);
+}
+
+// Share Modal
+function showShareModal() {
+  if (!activeCase) return;
+  const url = `${window.location.origin}${window.location.pathname}?case=${activeCase.id}&invite=${activeCase.inviteToken}`;
+  document.getElementById('shareLink').value = url;
+  registerInvite(activeCase);
+  document.getElementById('shareModal').classList.add('active');
+}
+
+function hideShareModal() {
+  document.getElementById('shareModal').classList.remove('active');
+}
+
+function copyShareLink() {
+  const input = document.getElementById('shareLink');
+  input.select();
+  navigator.clipboard.writeText(input.value);
+  showToast(i18nText('legal.link_copied'));
+}
+
+// Closed cases toggle
+function toggleClosedCases() {
+  const closedCases = document.getElementById('closedCases');
+  const chevron = document.getElementById('closedChevron');
+  closedCases.classList.toggle('expanded');
+  chevron.textContent = closedCases.classList.contains('expanded') ? '▾' : '▸';
+}
+
+// Case actions
+function closeCaseAction() {
+  if (!activeCase || !activeCase.isOwner) {
+    showToast(i18nText('legal.owner_only_close'));
+    return;
+  }
+  if (confirm(i18nText('legal.confirm_close'))) {
+    activeCase.status = 'closed';
+    saveCases();
+    patchCase(activeCase, { status: 'closed' });
+    renderCaseList();
+    document.getElementById('caseStatus').textContent = i18nText('legal.status_closed');
+    showToast(i18nText('legal.case_closed'));
+  }
+}
+
+async function deleteCaseAction() {
+  if (!activeCase || !activeCase.isOwner) {
+    showToast(i18nText('legal.owner_only_delete'));
+    return;
+  }
+  if (confirm(i18nText('legal.confirm_delete'))) {
+    try {
+      // The case endpoint also deletes the room; local-only cases go straight to the agent
+      let deleted = false;
+      if (activeCase.synced) {
+        const res = await fetch(`${API}/cases/${activeCase.id}`, { method: 'DELETE', headers: caseHeaders() });
+        deleted = res.ok;
+      }
+      if (!deleted) {
+        const headers = { 'Content-Type': 'application/json' };
+        if (TOKEN) headers['Authorization'] = 'Bearer ' + TOKEN;
+
+        await fetch(API + '/room/delete', {
+          method: 'POST',
+          headers,
+          body: JSON.stringify({
+            room_id: activeCase.id,
+            entity_id: entityId,
+            purge_memories: true
+          })
+        });
+      }
+
+      // Remove local data
+      localStorage.removeItem(`zoey_case_messages_${activeCase.id}`);
+      cases = cases.filter(c => c.id !== activeCase.id);
+      saveCases();
+
+      activeCase = null;
+      stopPresence();
+      document.getElementById('caseHeader').style.display = 'none';
+      document.getElementById('inputContainer').style.display = 'none';
+      document.getElementById('detailSidebar').style.display = 'none';
+      document.getElementById('welcomeMessage').style.display = 'block';
+      document.getElementById('chat').innerHTML = document.getElementById('welcomeMessage').outerHTML;
+
+      renderCaseList();
+      showToast(i18nText('legal.case_deleted'));
+    } catch (e) {
+      showToast(i18nText('legal.delete_failed'));
+    }
+  }
+}
+
+// Handle invite links: joins as a viewer, the owner can promote to collaborator
+async function handleInviteLink() {
+  const params = new URLSearchParams(window.location.search);
+  const caseId = params.get('case');
+  const inviteToken = params.get('invite');
+
+  if (caseId && inviteToken) {
+    // Check if we already have this case
+    let existingCase = cases.find(c => c.id === caseId);
+
+    if (!existingCase) {
+      const displayName = prompt(i18nText('legal.display_name_prompt')) || '';
+      try {
+        const res = await fetch(`${API}/cases/${caseId}/participants`, {
+          method: 'POST',
+          headers: caseHeaders(),
+          body: JSON.stringify({ inviteToken, displayName, role: 'viewer' })
+        });
+        if (!res.ok) {
+          const err = await res.json().catch(() => null);
+          showToast(i18nText('legal.join_failed', { error: err?.error?.message || res.status }));
+          window.history.replaceState({}, document.title, window.location.pathname);
+          return;
+        }
+      } catch {
+        showToast(i18nText('legal.connection_failed'));
+        return;
+      }
+      // Add as invited case
+      existingCase = {
+        id: caseId,
+        name: i18nText('legal.shared_case'),
+        status: 'active',
+        isOwner: false,
+        inviteToken,
+        createdAt: Date.now(),
+        lastActivity: Date.now(),
+        messageCount: 0
+      };
+      cases.push(existingCase);
+      saveCases();
+      showToast(i18nText('legal.joined_shared'));
+    }
+
+    selectCase(caseId);
+    // Clean URL
+    window.history.replaceState({}, document.title, window.location.pathname);
+  }
+}
+
+// Enter key handler
+document.getElementById('messageInput').addEventListener('keydown', (e) => {
+  if (e.key === 'Enter' && !e.shiftKey) {
+    e.preventDefault();
+    sendMessage();
+  } else if (e.key === 'Escape' && editingIndex !== null) {
+    editingIndex = null;
+    e.target.value = '';
+  }
+});
+
+// ========== FILE UPLOAD FUNCTIONALITY ==========
+
+// Case files storage
+function getCaseFiles(caseId) {
+  return JSON.parse(localStorage.getItem(`zoey_case_files_${caseId}`) || '[]');
+}
+
+function saveCaseFiles(caseId, files) {
+  localStorage.setItem(`zoey_case_files_${caseId}`, JSON.stringify(files));
+}
+
+// File type detection
+function getFileType(filename) {
+  const ext = filename.split('.').pop().toLowerCase();
+  const typeMap = {
+    'pdf': 'PDF', 'txt': 'Text', 'md': 'Markdown',
+    'csv': 'CSV', 'json': 'JSON', 'doc': 'Document', 'docx': 'Document'
+  };
+  return typeMap[ext] || 'File';
+}
+
+function getFileIcon(filename) {
+  const ext = filename.split('.').pop().toLowerCase();
+  const iconMap = {
+    'pdf': '📕', 'txt': '📄', 'md': '📝',
+    'csv': '📊', 'json': '📋', 'xlsx': '📗', 'xls': '📗'
+  };
+  return iconMap[ext] || '📄';
+}
+
+// Render file list with ingestion status
+function renderFileList() {
+  const fileList = document.getElementById('fileList');
+  if (!activeCase || !fileList) return;
+
+  const files = getCaseFiles(activeCase.id);
+
+  if (files.length === 0) {
+    fileList.innerHTML = '';
+    return;
+  }
+
+  fileList.innerHTML = files.map((f, idx) => {
+    // Determine status badge style and text
+    let statusClass = '';
+    let statusText = getFileType(f.name);
+    if (f.status === 'uploading') {
+      statusClass = ' uploading';
+      if (f.stage === 'queued') {
+        statusText = i18n('legal.file_queued');
+      } else if (f.chunksTotal) {
+        statusText = i18n('legal.file_progress', { done: f.chunksDone || 0, total: f.chunksTotal });
+      } else if (f.stage === 'forwarding' || f.stage === 'chunking') {
+        statusText = i18n('legal.file_forwarding');
+      } else {
+        statusText = i18n('legal.file_processing');
+      }
+    } else if (f.status === 'ingested') {
+      statusClass = '';
+      statusText = f.chunksCreated ? i18n('legal.file_chunks', { count: f.chunksCreated }) : i18n('legal.file_ingested');
+    } else if (f.status === 'error') {
+      statusClass = ' error';
+      statusText = i18n('legal.file_error');
+    }
+
+    return `
+      <div class="file-item">
+        <span class="file-item-icon">${getFileIcon(f.name)}</span>
+        <span class="file-item-name" title="${escapeHtml(f.name)}${f.wordCount ? ' (' + f.wordCount + ' words)' : ''}">${escapeHtml(f.name)}</span>
+        <span class="file-item-status${statusClass}">${statusText}</span>
+        <button class="file-item-remove" data-action="removeFile" data-index="${idx}" title="Remove">×</button>
+      </div>
+    `;
+  }).join('');
+}
+
+// Remove file
+function removeFile(idx) {
+  if (!activeCase) return;
+  const files = getCaseFiles(activeCase.id);
+  files.splice(idx, 1);
+  saveCaseFiles(activeCase.id, files);
+  renderFileList();
+  showToast(i18nText('legal.file_removed'));
+}
+
+// Upload file to Knowledge Ingestion API (secure document processing)
+async function uploadFile(file) {
+  if (!activeCase) {
+    showToast(i18nText('legal.select_case'));
+    return;
+  }
+  if (activeCase.role === 'viewer') {
+    showToast(i18nText('legal.viewer_read_only'));
+    return Promise.reject(new Error('Read-only participant'));
+  }
+
+  // Validate file type - now includes PDF and Excel
+  const textExtensions = ['txt', 'md', 'markdown', 'csv', 'json'];
+  const binaryExtensions = ['pdf', 'xlsx', 'xls'];
+  const allowedExtensions = [...textExtensions, ...binaryExtensions];
+  const ext = file.name.split('.').pop()?.toLowerCase() || '';
+  if (!allowedExtensions.includes(ext)) {
+    showToast(i18nText('legal.unsupported_file', { ext }));
+    return Promise.reject(new Error('Unsupported file type'));
+  }
+
+  // Validate file size (10MB max)
+  const maxSize = 10 * 1024 * 1024;
+  if (file.size > maxSize) {
+    showToast(i18nText('legal.file_too_large'));
+    return Promise.reject(new Error('File too large'));
+  }
+
+  // Determine if file needs base64 encoding (binary files)
+  const isBinary = binaryExtensions.includes(ext);
+
+  return new Promise((resolve, reject) => {
+    const reader = new FileReader();
+    reader.onload = async (e) => {
+      let content;
+      let base64Encoded = false;
+
+      if (isBinary) {
+        // For binary files, encode as base64
+        const arrayBuffer = e.target.result;
+        const bytes = new Uint8Array(arrayBuffer);
+        let binary = '';
+        for (let i = 0; i < bytes.byteLength; i++) {
+          binary += String.fromCharCode(bytes[i]);
+        }
+        content = btoa(binary);
+        base64Encoded = true;
+      } else {
+        // For text files, use as-is
+        content = e.target.result;
+      }
+
+      // Store file metadata locally (will update with server response)
+      const caseId = activeCase.id;
+      const files = getCaseFiles(caseId);
+      const fileRecord = {
+        id: uuid(),
+        name: file.name,
+        type: file.type,
+        size: file.size,
+        uploadedAt: Date.now(),
+        status: 'uploading'
+      };
+      files.push(fileRecord);
+      saveCaseFiles(caseId, files);
+      renderFileList();
+
+      // Send file to Knowledge Ingestion endpoint
+      try {
+        const headers = { 'Content-Type': 'application/json' };
+        if (TOKEN) headers['Authorization'] = 'Bearer ' + TOKEN;
+
+        // Use the secure knowledge ingestion endpoint
+        const response = await fetch(API + '/knowledge/ingest', {
+          method: 'POST',
+          headers,
+          body: JSON.stringify({
+            room_id: caseId,
+            entity_id: entityId,
+            filename: file.name,
+            content: content,
+            base64_encoded: base64Encoded,
+            mime_type: file.type || (isBinary ? 'application/octet-stream' : 'text/plain'),
+            // Let the Agent API chunk large documents in the background
+            async: true,
+            metadata: {
+              original_size: file.size,
+              upload_timestamp: Date.now(),
+              client_id: fileRecord.id
+            }
+          })
+        });
+
+        const accepted = await response.json();
+        let result;
+        if (response.status === 429) {
+          result = { status: 'failed', error: i18nText('legal.upload_busy', { seconds: response.headers.get('Retry-After') || 5 }) };
+        } else if (!response.ok) {
+          result = { status: 'failed', error: (accepted.error && accepted.error.message) || accepted.error };
+        } else {
+          // Large documents take a while to chunk; follow the upload through its stages
+          result = await followIngest(accepted, headers, (progress) => {
+            const stagedFiles = getCaseFiles(caseId);
+            const idx = stagedFiles.findIndex(f => f.id === fileRecord.id);
+            const staged = idx !== -1 && stagedFiles[idx];
+            if (staged && (staged.stage !== progress.stage
+                || staged.chunksDone !== progress.chunks_done
+                || staged.chunksTotal !== progress.chunks_total)) {
+              staged.stage = progress.stage;
+              staged.chunksDone = progress.chunks_done;
+              staged.chunksTotal = progress.chunks_total;
+              saveCaseFiles(caseId, stagedFiles);
+              renderFileList();
+            }
+          });
+        }
+
+        if (result.status === 'done') {
+          // Update file record with server info
+          const updatedFiles = getCaseFiles(caseId);
+          const idx = updatedFiles.findIndex(f => f.id === fileRecord.id);
+          if (idx !== -1) {
+            updatedFiles[idx].status = 'ingested';
+            delete updatedFiles[idx].stage;
+            delete updatedFiles[idx].chunksDone;
+            delete updatedFiles[idx].chunksTotal;
+            updatedFiles[idx].documentId = result.document_id;
+            updatedFiles[idx].chunksCreated = result.chunks_created;
+            updatedFiles[idx].wordCount = result.word_count;
+            saveCaseFiles(caseId, updatedFiles);
+            renderFileList();
+          }
+
+          // Show success with details
+          let msg = i18nText('legal.ingested', { name: file.name });
+          if (result.chunks_created) msg += ` (${result.chunks_created} chunks)`;
+          if (result.warnings && result.warnings.length > 0) {
+            msg += ` - Note: ${result.warnings[0]}`;
+          }
+          showToast(msg);
+          resolve(fileRecord);
+        } else {
+          // Remove failed file from list
+          const updatedFiles = getCaseFiles(caseId).filter(f => f.id !== fileRecord.id);
+          saveCaseFiles(caseId, updatedFiles);
+          renderFileList();
+
+          const uploadError = result.error;
+          showToast(i18nText('legal.upload_failed', { error: uploadError || i18nText('legal.unknown_error') }));
+          reject(new Error(uploadError || 'Upload failed'));
+        }
+      } catch (err) {
+        // Remove failed file from list
+        const updatedFiles = getCaseFiles(caseId).filter(f => f.id !== fileRecord.id);
+        saveCaseFiles(caseId, updatedFiles);
+        renderFileList();
+
+        showToast(i18nText('legal.upload_error', { error: err.message || i18nText('legal.connection_failed') }));
+        reject(err);
+      }
+    };
+    reader.onerror = () => reject(reader.error);
+
+    // Read as appropriate type
+    if (isBinary) {
+      reader.readAsArrayBuffer(file);
+    } else {
+      reader.readAsText(file);
+    }
+  });
+}
+
+// Follow an accepted upload over its progress stream, polling when there is none
+function followIngest(accepted, headers, onProgress) {
+  if (!accepted.progressUrl || typeof EventSource === 'undefined') {
+    return pollIngest(accepted.ingestId, headers, onProgress);
+  }
+  return new Promise((resolve) => {
+    const source = new EventSource(accepted.progressUrl);
+    source.addEventListener('progress', (e) => onProgress(JSON.parse(e.data)));
+    source.addEventListener('final', (e) => {
+      source.close();
+      const data = JSON.parse(e.data);
+      resolve({
+        status: data.stage === 'done' ? 'done' : 'failed',
+        document_id: data.documentId,
+        chunks_created: data.chunksCreated,
+        word_count: data.wordCount,
+        warnings: data.warnings,
+        error: data.error
+      });
+    });
+    // Stream unavailable or dropped before the end: fall back to polling
+    source.onerror = () => {
+      source.close();
+      resolve(pollIngest(accepted.ingestId, headers, onProgress));
+    };
+  });
+}
+
+// Poll an accepted upload until it is done or failed, reporting its progress
+async function pollIngest(ingestId, headers, onProgress) {
+  while (true) {
+    await new Promise(r => setTimeout(r, 1000));
+    const res = await fetch(`${API}/knowledge/ingest/${ingestId}/status`, { headers });
+    const record = await res.json();
+    if (!res.ok) {
+      return { status: 'failed', error: (record.error && record.error.message) || record.error };
+    }
+    if (record.status === 'done' || record.status === 'failed') return record;
+    onProgress({
+      stage: record.stage || record.status,
+      chunks_done: record.chunks_done,
+      chunks_total: record.chunks_total
+    });
+  }
+}
+
+// Handle file drop
+function setupFileDropZone() {
+  const dropZone = document.getElementById('fileDropZone');
+  const fileInput = document.getElementById('fileInput');
+
+  if (!dropZone || !fileInput) return;
+
+  // Click to select files
+  dropZone.addEventListener('click', () => {
+    if (activeCase) fileInput.click();
+    else showToast(i18nText('legal.select_case'));
+  });
+
+  // File input change
+  fileInput.addEventListener('change', async (e) => {
+    const files = Array.from(e.target.files);
+    for (const file of files) {
+      await uploadFile(file);
+    }
+    fileInput.value = '';
+  });
+
+  // Drag and drop events
+  dropZone.addEventListener('dragover', (e) => {
+    e.preventDefault();
+    dropZone.classList.add('dragover');
+  });
+
+  dropZone.addEventListener('dragleave', (e) => {
+    e.preventDefault();
+    dropZone.classList.remove('dragover');
+  });
+
+  dropZone.addEventListener('drop', async (e) => {
+    e.preventDefault();
+    dropZone.classList.remove('dragover');
+
+    if (!activeCase) {
+      showToast(i18nText('legal.select_case'));
+      return;
+    }
+
+    const files = Array.from(e.dataTransfer.files);
+    for (const file of files) {
+      await uploadFile(file);
+    }
+  });
+}
+
+// Show/hide file section when case is selected
+function updateFileSectionVisibility() {
+  const fileSection = document.getElementById('fileDropSection');
+  if (fileSection) {
+    fileSection.style.display = activeCase ? 'block' : 'none';
+  }
+}
+
+// Patch selectCase to show files
+const originalSelectCase = selectCase;
+selectCase = function(caseId) {
+  originalSelectCase(caseId);
+  updateFileSectionVisibility();
+  renderFileList();
+};
+
+// Open the Telegram chat's room when arriving from the workspace button
+function openSessionRoom() {
+  if (!SESSION || !SESSION.roomId) return;
+  if (!cases.find(c => c.id === SESSION.roomId)) {
+    cases.push({
+      id: SESSION.roomId,
+      name: i18nText('legal.telegram_case'),
+      status: 'active',
+      isOwner: true,
+      createdAt: Date.now(),
+      lastActivity: Date.now(),
+      messageCount: 0
+    });
+    saveCases();
+    renderCaseList();
+  }
+  selectCase(SESSION.roomId);
+}
+
+// Inline handlers are blocked by the Content-Security-Policy, so markup names
+// its action in data-action / data-change and events are delegated here
+const ACTIONS = {
+  showNewCaseModal, hideNewCaseModal, createCase, toggleClosedCases,
+  showShareModal, hideShareModal, copyShareLink, closeCaseAction, deleteCaseAction,
+  sendMessage: () => sendMessage(),
+  selectCase: el => selectCase(el.dataset.id),
+  editMessage: el => editMessage(Number(el.dataset.index)),
+  removeFile: el => removeFile(Number(el.dataset.index)),
+  removeParticipant: el => removeParticipant(el.dataset.id, el.dataset.name),
+};
+document.addEventListener('click', (e) => {
+  const el = e.target.closest('[data-action]');
+  if (el && Object.prototype.hasOwnProperty.call(ACTIONS, el.dataset.action)) {
+    ACTIONS[el.dataset.action](el);
+  }
+});
+document.addEventListener('change', (e) => {
+  const el = e.target.closest('[data-change="changeParticipantRole"]');
+  if (el) changeParticipantRole(el.dataset.id, el.value);
+});
+
+// Initialize
+renderCaseList();
+loadServerCases();
+handleInviteLink();
+openSessionRoom();
+setupFileDropZone();
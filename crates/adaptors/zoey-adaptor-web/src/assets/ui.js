@@ -0,0 +1,687 @@
+const chat = document.getElementById('chat');
+const input = document.getElementById('t');
+const btn = document.getElementById('send');
+// Reply still being generated, and the user message being edited
+let activeGeneration = null;
+let editing = null;
+
+function uuid(){
+  try{ if (typeof crypto!== 'undefined' && crypto && typeof crypto.randomUUID==='function') { return crypto.randomUUID(); } }catch(e){}
+  function rv(){ if (typeof crypto!== 'undefined' && crypto && typeof crypto.getRandomValues==='function') { return (crypto.getRandomValues(new Uint8Array(1))[0] & 15); } return (Math.floor(Math.random()*16) & 15); }
+  return 'xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx'.replace(/[xy]/g, function(c){ const v = rv(); const n = c==='x' ? v : ((v & 0x3) | 0x8); return n.toString(16); });
+}
+// A Telegram workspace session binds the page to that chat's room and user;
+// otherwise the room is kept so a refresh restores its history
+const roomId = (SESSION && SESSION.roomId) || localStorage.getItem('zoey_room') || uuid();
+if (!(SESSION && SESSION.roomId)) localStorage.setItem('zoey_room', roomId);
+const entityId = (SESSION && SESSION.entityId) || localStorage.getItem('zoey_entity') || uuid();
+localStorage.setItem('zoey_entity', entityId);
+document.getElementById('room').textContent = roomId;
+const charSelect = document.getElementById('character');
+const applyChar = document.getElementById('applyChar');
+const logsEl = document.getElementById('logs');
+const logsBuf = [];
+function ts(){ const d=new Date(); return d.toISOString().split('T')[1].replace('Z',''); }
+function addLog(level, msg){ const line = '['+ts()+'] '+String(level||'info').toUpperCase()+': '+String(msg); logsBuf.push(line); if (logsBuf.length>500) logsBuf.shift(); if (logsEl){ const item=document.createElement('div'); item.textContent=line; logsEl.appendChild(item); logsEl.scrollTop = logsEl.scrollHeight; } }
+async function fetchWithLog(url, opts, tag){
+  addLog('info', 'Request ' + (tag||'') + ' ' + url);
+  try {
+    const res = await fetch(url, opts);
+    addLog('info', 'Response ' + (tag||'') + ' ' + res.status);
+    return res;
+  } catch(e) {
+    addLog('error', 'Fetch ' + (tag||'') + ' failed');
+    return new Response(null, { status: 0, statusText: 'network_error' });
+  }
+}
+document.getElementById('clearLogs').addEventListener('click', ()=>{ logsBuf.length=0; if (logsEl) logsEl.innerHTML=''; });
+document.getElementById('copyLogs').addEventListener('click', async ()=>{ try { await navigator.clipboard.writeText(logsBuf.join('\n')); addLog('info','Logs copied'); } catch(e){ addLog('error','Copy failed'); } });
+addLog('info','UI ready'); addLog('info','API '+API); addLog('info','Room '+roomId); addLog('info','Entity '+entityId);
+// Server logs SSE
+if (typeof LOGS_ENABLED !== 'undefined' && LOGS_ENABLED) {
+  (function startSSE(){
+    const urls = [API + '/logs', '/logs'];
+    const idx = (window.__sseLogIdx||0);
+    const url = urls[idx];
+    try {
+      const es = new EventSource(url);
+      es.onopen = ()=>{ addLog('info','SSE connected '+url); };
+      es.onmessage = (ev)=>{
+        try{ const data = JSON.parse(ev.data); const level = (data.level||'info'); const msg = '['+(data.target||'')+'] '+(data.message||''); addLog(level, msg); }
+        catch(e){ addLog('error','Log parse failed: '+(e && e.message ? e.message : String(e))); }
+      };
+      es.onerror = (ev)=>{
+        const states = ['CONNECTING','OPEN','CLOSED'];
+        const st = states[es.readyState] || String(es.readyState);
+        addLog('error','SSE error ('+st+') '+(ev && ev.type ? ev.type : ''));
+        try{ es.close(); } catch{}
+        window.__sseLogIdx = (idx+1) % urls.length;
+        setTimeout(startSSE, 2000);
+      };
+      setTimeout(()=>{ try{ es.close(); }catch{}; }, 60000);
+    } catch(e) { addLog('error','SSE init failed: '+(e && e.message ? e.message : String(e))); }
+  })();
+}
+
+async function loadCharacters() {
+  try {
+    const url = API.replace(/\/$/, '') + '/characters';
+    const res = await fetchWithLog(url, undefined, 'characters');
+    if (!res.ok) throw new Error('failed');
+    const data = await res.json();
+    const list = (data.characters || []);
+    const current = (data.current || '').toLowerCase().replace(/[^a-z0-9]/g, '');
+    charSelect.innerHTML = '';
+    if (!list.length) {
+      const opt = document.createElement('option');
+      opt.value = ''; opt.textContent = i18nText('character.none'); charSelect.appendChild(opt);
+      return;
+    }
+    let selectedIdx = 0;
+    list.forEach((name, idx) => {
+      const opt = document.createElement('option');
+      opt.value = name; opt.textContent = name; charSelect.appendChild(opt);
+      // Match current character by comparing normalized names
+      const normalized = name.replace('.xml','').toLowerCase().replace(/[^a-z0-9]/g, '');
+      if (current && (normalized.includes(current) || current.includes(normalized))) {
+        selectedIdx = idx;
+      }
+    });
+    charSelect.selectedIndex = selectedIdx;
+  } catch (e) {
+    // Fallback: show a message, avoid crashing UI
+    charSelect.innerHTML = '';
+    const opt = document.createElement('option');
+    opt.value = ''; opt.textContent = i18nText('character.unavailable'); charSelect.appendChild(opt);
+    addLog('error','Characters unavailable');
+  }
+}
+applyChar.addEventListener('click', async () => {
+  const filename = charSelect.value;
+  if (!filename) return;
+  await fetchWithLog(API.replace(/\/$/, '') + '/character/select', { method:'POST', headers:{'Content-Type':'application/json'}, body: JSON.stringify({ filename }) }, 'character.select');
+  addLog('info','Character applied '+filename);
+  // Refresh state panel
+  await fetchState({ 'Content-Type': 'application/json' });
+});
+loadCharacters();
+
+// Renders the room's recent messages, oldest first, after a refresh
+async function loadHistory() {
+  const headers = {};
+  if (typeof TOKEN === 'string' && TOKEN) headers['Authorization'] = 'Bearer ' + TOKEN;
+  try {
+    const res = await fetchWithLog(API + '/history?room_id=' + encodeURIComponent(roomId) + '&limit=50', { headers }, 'history');
+    if (!res.ok) return;
+    const data = await res.json();
+    const messages = (data.messages || []).filter(m => m && m.text);
+    messages.forEach(m => { if (m.role === 'agent') addAgent(m.text); else addUser(m.text); });
+    if (messages.length) addLog('info','History restored ('+messages.length+' messages)');
+  } catch (e) {
+    addLog('error','History unavailable');
+  }
+}
+loadHistory();
+
+function addUser(text) {
+  const el = document.createElement('div');
+  el.className = 'msg user';
+  el.innerHTML = `<div class="bubble">${text}</div><button class="msg-edit" type="button" title="${i18n('chat.edit')}">✎</button>`;
+  el.querySelector('.msg-edit').addEventListener('click', () => editUser(el, text));
+  chat.appendChild(el);
+  chat.scrollTop = chat.scrollHeight;
+}
+
+function editUser(el, text) {
+  if (editing) editing.classList.remove('editing');
+  editing = el;
+  el.classList.add('editing');
+  input.value = text;
+  input.focus();
+}
+
+function cancelEdit() {
+  if (editing) editing.classList.remove('editing');
+  editing = null;
+}
+
+// Removes the edited message and everything rendered after it,
+// cancelling a reply still being generated for the old conversation
+function truncateForResend() {
+  if (!editing) return;
+  if (activeGeneration) activeGeneration.abort();
+  while (editing.nextSibling) editing.nextSibling.remove();
+  editing.remove();
+  editing = null;
+}
+
+function addAgent(text, thought) {
+  if (thought) {
+    const tEl = document.createElement('div');
+    tEl.className = 'msg agent';
+    const items = String(thought).split(/\r?\n/).map(s => s.replace(/^[-]\s*/, '').trim()).filter(Boolean);
+    const paragraph = items.join(' ');
+    tEl.innerHTML = `<div class="thoughts"><b>${i18n('thought.label')}</b>: ${paragraph}</div>`;
+    chat.appendChild(tEl);
+  }
+  const el = document.createElement('div');
+  el.className = 'msg agent';
+  el.innerHTML = `<div class="bubble">${text}</div>`;
+  chat.appendChild(el);
+  chat.scrollTop = chat.scrollHeight;
+}
+
+function typing(show) {
+  let t = document.getElementById('typing');
+  if (show && !t) {
+    t = document.createElement('div');
+    t.id = 'typing';
+    t.className = 'msg agent';
+    t.innerHTML = `<div class="bubble"><span class="typing"><span></span><span></span><span></span></span></div>`;
+    chat.appendChild(t);
+  } else if (!show && t) {
+    t.remove();
+  }
+  chat.scrollTop = chat.scrollHeight;
+}
+
+function extractThought(text) {
+  const m1 = text.match(/<thought>([\s\S]*?)<\/thought>/i);
+  if (m1) return m1[1].trim();
+  const m2 = text.match(/\b[Tt]houghts?:\s*([^\n]+)/);
+  if (m2) return m2[1].trim();
+  return null;
+}
+
+function parseReplyAndThought(text) {
+  const src = String(text || '');
+  const fence = src.match(/```(?:xml|reply|thought)?([\s\S]*?)```/i);
+  let raw = fence ? fence[1] : src;
+  let reply = null;
+  let thought = null;
+
+  // <text>...</text> common wrapper
+  const textTag = raw.match(/<text>([\s\S]*?)<\/text>/i);
+  if (textTag) reply = textTag[1].trim();
+
+  // <reply>...</reply> multiline block
+  const replyTag = raw.match(/<reply>([\s\S]*?)<\/reply>/i);
+  if (replyTag) reply = replyTag[1].trim();
+
+  // REPLY marker until next thought marker or end
+  if (!reply) {
+    const r = raw.match(/\bREPLY\b[\s\S]*?(.*?)(?=\n\s*(?:Thoughts?|THOUGHTS?|Chain[- ]?of[- ]?thought|Reasoning|COT)\b|$)/i);
+    if (r) reply = r[1].trim();
+  }
+
+  // Thought markers (prefer explicit)
+  const thoughtTag = raw.match(/<thought>([\s\S]*?)<\/thought>/i);
+  if (thoughtTag) thought = thoughtTag[1].trim();
+  if (!thought) {
+    const t1 = raw.match(/\b(T[hH]oughts?|COT|Chain[- ]?of[- ]?thought|Reasoning)\b[:\-]?\s*([\s\S]*?)(?=```|<\/reply>|$)/);
+    if (t1) thought = t1[2].trim();
+  }
+
+  // Fallbacks
+  if (!reply) reply = src.trim();
+  if (!thought) thought = extractThought(src);
+  return { reply, thought };
+}
+
+function splitThoughtSteps(thought) {
+  if (!thought) return [];
+  const t = thought.trim();
+  const lines = t.split(/\r?\n/).map(s => s.trim()).filter(Boolean);
+  if (lines.length > 1) return lines;
+  // Try bullet/numbered split in a single line
+  const bullets = t.split(/\s*[-•]\s+/).map(s => s.trim()).filter(Boolean);
+  if (bullets.length > 1) return bullets;
+  const numbered = t.split(/\s*\d+\.?\s+/).map(s => s.trim()).filter(Boolean);
+  if (numbered.length > 1) return numbered;
+  return [t];
+}
+
+function scoreConfidence(text) {
+  const t = String(text || '').toLowerCase();
+  const hedges = ['maybe','might','perhaps','possibly','likely','seems','apparently'];
+  let hits = 0; hedges.forEach(h=>{ if(t.includes(h)) hits++; });
+  if (hits <= 1 && t.length > 80) return i18nText('confidence.high');
+  if (hits <= 2) return i18nText('confidence.medium');
+  return i18nText('confidence.low');
+}
+
+function composeReflection(userText, replyText, thought, state) {
+  const topics = (userText || '').toLowerCase().split(/[^a-z0-9]+/).filter(w=>w.length>3);
+  const uniqTopics = Array.from(new Set(topics)).slice(0,4).join(', ');
+  const strategy = (replyText || '').toLowerCase().includes('example') ? 'example-led' : 'explanatory';
+  const confidence = scoreConfidence(replyText || thought || '');
+  const items = [];
+  items.push(i18n('reflection.intent'));
+  if (uniqTopics) items.push(i18n('reflection.topics', { topics: uniqTopics }));
+  items.push(i18n('reflection.strategy', { strategy }));
+  items.push(i18n('reflection.confidence', { level: confidence }));
+  if (thought && thought.length > 0) items.push(i18n('reflection.reasoning', { text: thought.slice(0, 120) }));
+  items.push(i18n('reflection.follow_up'));
+  return items;
+}
+
+async function addContextHint(key, value) {
+  try {
+    const headers = { 'Content-Type': 'application/json' };
+    await fetchWithLog(API + '/context/add', { method:'POST', headers, body: JSON.stringify({ room_id: roomId, key, value }) }, 'context.add');
+  } catch {}
+}
+
+async function saveThoughtSteps(steps) {
+  try {
+    const headers = { 'Content-Type': 'application/json' };
+    await fetchWithLog(API + '/context/save', { method:'POST', headers, body: JSON.stringify({ room_id: roomId, steps }) }, 'context.save');
+  } catch {}
+}
+
+const thoughtGroups = [];
+function addGroup(title) {
+  const id = uuid();
+  const group = { id, title, items: [], expanded: false, committed: false };
+  thoughtGroups.push(group);
+  return group;
+}
+function addGroupItem(group, text) {
+  group.items.push(text);
+}
+function toggleGroup(id) {
+  const g = thoughtGroups.find(x => x.id === id);
+  if (!g) return;
+  g.expanded = !g.expanded;
+  renderChain();
+}
+function renderChain() {
+  const el = document.getElementById('chain');
+  if (!el) return;
+  el.innerHTML = '';
+  if (thoughtGroups.length === 0) {
+    el.textContent = i18nText('chain.empty');
+    return;
+  }
+  thoughtGroups.forEach((g, gi) => {
+    const groupEl = document.createElement('div');
+    const header = document.createElement('div');
+    header.style.cssText = 'display:flex; align-items:center; gap:8px; cursor:pointer; padding:6px 8px; border:1px solid rgba(34,211,238,.25); border-radius:8px; background:rgba(34,211,238,.08);';
+    const idx = document.createElement('div');
+    idx.style.cssText = 'min-width:22px; height:22px; border-radius:50%; background:rgba(34,211,238,.15); border:1px solid rgba(34,211,238,.4); color:#67e8f9; display:flex; align-items:center; justify-content:center; font-size:12px;';
+    idx.textContent = String(gi+1);
+    const title = document.createElement('div');
+    title.style.cssText = 'flex:1;';
+    const preview = (g.title || '').slice(0, 80);
+    title.textContent = i18nText('chain.prompt', { text: preview });
+    const chevron = document.createElement('div');
+    chevron.style.cssText = 'color:#67e8f9; font-size:12px;';
+    chevron.textContent = g.expanded ? '▾' : '▸';
+    const useBtn = document.createElement('button');
+    useBtn.id = `usectx_${g.id}`;
+    useBtn.textContent = g.committed ? i18nText('chain.remove_context') : i18nText('chain.add_context');
+    useBtn.disabled = false;
+    useBtn.style.cssText = g.committed
+      ? 'padding:6px 10px; border-radius:8px; border:0; background:#1f2937; color:#9ca3af; font-weight:600; cursor:pointer;'
+      : 'padding:6px 10px; border-radius:8px; border:0; background:linear-gradient(90deg, #22d3ee, #10b981); color:#051018; font-weight:600; cursor:pointer;';
+    useBtn.onclick = (e) => { e.stopPropagation(); toggleGroupContext(g.id); };
+    header.appendChild(idx);
+    header.appendChild(title);
+    header.appendChild(chevron);
+    header.appendChild(useBtn);
+    header.onclick = () => toggleGroup(g.id);
+    groupEl.appendChild(header);
+    const body = document.createElement('div');
+    body.style.cssText = 'margin-top:6px; padding-left:2px; display:flex; flex-direction:column; gap:6px;';
+    if (g.expanded) {
+      g.items.forEach((t, i) => {
+        const item = document.createElement('div');
+        item.innerHTML = `<div style="display:flex; gap:8px; align-items:flex-start;">
+          <div style="min-width:22px; height:22px; border-radius:50%; background:rgba(34,211,238,.15); border:1px solid rgba(34,211,238,.4); color:#67e8f9; display:flex; align-items:center; justify-content:center; font-size:12px;">${i+1}</div>
+          <div style="flex:1;">${t}</div>
+        </div>`;
+        body.appendChild(item);
+      });
+    }
+    groupEl.appendChild(body);
+    el.appendChild(groupEl);
+  });
+}
+
+async function useGroupContext(id) {
+  const g = thoughtGroups.find(x => x.id === id);
+  if (!g) return;
+  const steps = ['Prompt: ' + (g.title || '')].concat(g.items);
+  if (steps.length) {
+    await addContextHint('lastThought', steps[0]);
+    await saveThoughtSteps(steps);
+    g.committed = true;
+    const btn = document.getElementById(`usectx_${id}`);
+    if (btn) {
+      btn.textContent = i18nText('chain.remove_context');
+      btn.disabled = false;
+      btn.style.background = '#1f2937';
+      btn.style.color = '#9ca3af';
+      btn.style.cursor = 'pointer';
+    }
+    renderChain();
+  }
+}
+
+async function removeGroupContext(id) {
+  const g = thoughtGroups.find(x => x.id === id);
+  if (!g) return;
+  try {
+    const headers = { 'Content-Type': 'application/json' };
+    await fetchWithLog(API + '/context/remove', { method:'POST', headers, body: JSON.stringify({ room_id: roomId, id }) }, 'context.remove');
+  } catch {}
+  g.committed = false;
+  const btn = document.getElementById(`usectx_${id}`);
+  if (btn) {
+    btn.textContent = i18nText('chain.add_context');
+    btn.disabled = false;
+    btn.style.background = 'linear-gradient(90deg, #22d3ee, #10b981)';
+    btn.style.color = '#051018';
+    btn.style.cursor = 'pointer';
+  }
+  renderChain();
+}
+
+async function toggleGroupContext(id) {
+  const g = thoughtGroups.find(x => x.id === id);
+  if (!g) return;
+  if (!g.committed) {
+    await useGroupContext(id);
+  } else {
+    await removeGroupContext(id);
+  }
+}
+
+function inferPlan(text) {
+  const lower = String(text || '').toLowerCase();
+  const isQuestion = /\?|\b(how|what|why|when|where|who)\b/.test(lower);
+  const steps = isQuestion
+    ? ['clarify intent','identify topics','retrieve knowledge','compose answer']
+    : ['determine goal','identify topics','plan structure','generate answer'];
+  return steps.join(' → ');
+}
+
+async function fetchState(headers) {
+  try {
+    const res = await fetchWithLog(API + '/state', { method:'POST', headers, body: JSON.stringify({ roomId }) }, 'state');
+    const data = await res.json();
+    if (data.success && data.state) {
+      document.getElementById('state').textContent = i18nText('state.ready');
+      // Adapt thought chain from real agent state
+      const steps = summarizeState(data.state);
+      steps.forEach(s => { thoughtsChain.push(s); });
+      renderChain();
+      addLog('info','State updated');
+    }
+  } catch {}
+}
+
+function summarizeState(state) {
+  // Defensive parsing – state may be arbitrary JSON
+  const s = state || {};
+  const data = s.data || s;
+  const steps = [];
+
+  // Preferred tone
+  const tone = data?.characterSettings?.preferredTone;
+  if (typeof tone === 'string' && tone.length > 0) {
+    steps.push(`Tone set: ${tone}`);
+  }
+
+  // Topics or entities
+  const entities = data?.entities || data?.keyEntities || data?.topics;
+  if (Array.isArray(entities) && entities.length > 0) {
+    steps.push(`Entities detected (${entities.length})`);
+  }
+
+  // Memory recall
+  const memories = data?.recentMemories || data?.memories;
+  if (Array.isArray(memories)) {
+    const count = memories.length;
+    steps.push(`Memory recall: ${count}`);
+  }
+
+  // Context size
+  const ctx = data?.context || data?.promptContext || s?.context;
+  if (typeof ctx === 'string' && ctx.length > 0) {
+    steps.push(`Context composed (${Math.min(ctx.length, 200)} chars)`);
+  }
+
+  // Intent/goal
+  const intent = data?.intent || data?.goal || data?.task;
+  if (typeof intent === 'string' && intent.length > 0) {
+    steps.push(`Intent: ${intent.slice(0, 60)}${intent.length > 60 ? '…' : ''}`);
+  }
+
+  // Fallback if empty
+  if (steps.length === 0) {
+    steps.push(i18n('chain.state_composed'));
+  }
+  return steps;
+}
+
+async function pollTaskAndRender(headers, taskId) {
+  let tries = 0;
+  while (tries < 60) {
+    const tr = await fetchWithLog(API + '/task/' + taskId, { headers }, 'task');
+    const td = await tr.json();
+    if (td.status === 'completed' && td.result) {
+      typing(false);
+      const msgs = (td.result && td.result.messages) ? td.result.messages : [];
+      for (const m of msgs) {
+        const textRaw = (m.content && m.content.text) ? m.content.text : JSON.stringify(m);
+        const pt = parseReplyAndThought(textRaw);
+        const replyText = pt.reply || textRaw;
+        addAgent(replyText, pt.thought || null);
+      }
+      return true;
+    } else if (td.status === 'failed') {
+      typing(false);
+      addAgent(i18n('error.streaming'));
+      return false;
+    }
+    await new Promise(r => setTimeout(r, 500));
+    tries++;
+  }
+  typing(false);
+  addAgent(i18n('error.streaming'));
+  return false;
+}
+
+async function sendMessage(text) {
+  const headers = { 'Content-Type': 'application/json' };
+  if (typeof TOKEN === 'string' && TOKEN) headers['Authorization'] = 'Bearer ' + TOKEN;
+  const promptPlan = inferPlan(text);
+  const group = addGroup(text);
+  addGroupItem(group, i18n('chain.prompt_plan', { plan: promptPlan }));
+  renderChain();
+  window.lastUserText = text;
+  typing(true);
+  const generation = new AbortController();
+  activeGeneration = generation;
+  const doStream = true;
+  if (doStream) {
+    let wsAssembled = '';
+    const handledByWs = await wsChat(withModelParams({ text, roomId, entityId }), {
+      onChunk: (chunk) => {
+        if (generation.signal.aborted) return;
+        wsAssembled += chunk;
+        const tnode = document.getElementById('typing');
+        if (tnode) { tnode.querySelector('.bubble').textContent = wsAssembled; }
+      },
+      onDone: (last) => {
+        if (generation.signal.aborted) return;
+        wsAssembled += last;
+        typing(false);
+        const pt = parseReplyAndThought(wsAssembled);
+        const replyText = pt.reply || wsAssembled;
+        if (replyText && replyText.trim().length > 0) {
+          addAgent(replyText, pt.thought || null);
+        } else {
+          addAgent(i18n('error.empty_response'));
+        }
+      },
+      onError: (err) => {
+        if (generation.signal.aborted) return;
+        typing(false);
+        addLog('error', 'WS chat error ' + err);
+        addAgent(i18n('error.connection_interrupted'));
+      },
+      signal: generation.signal,
+    });
+    if (generation.signal.aborted) { addLog('info', 'Response chat.ws superseded'); return; }
+    if (handledByWs) { addLog('info', 'Response chat.ws complete'); return; }
+    addLog('info', 'WebSocket unavailable, using fetch stream');
+    try {
+      const res = await fetchWithLog(API + '/chat/stream', { method:'POST', headers, body: JSON.stringify(withModelParams({ text, roomId, entityId, stream:true })), signal: generation.signal }, 'chat.stream');
+      const reader = res.body.getReader();
+      const decoder = new TextDecoder();
+      let buffer = '';
+      let assembled = '';
+      let currentEvent = '';
+      let closed = false;
+      let streamError = false;
+      let committed = false;
+      let sawFinal = false;
+      let lastChunkAt = Date.now();
+      let firstChunkReceived = false;
+      // No timeout before first chunk - Ollama can take minutes for large prompts
+      // After first chunk arrives, 60s timeout between chunks
+      let watchdog = setInterval(()=>{
+        if (firstChunkReceived && !committed && (Date.now() - lastChunkAt) > 60000) {
+          streamError = true;
+        }
+      }, 1000);
+      while (true) {
+        const { value, done } = await reader.read();
+        if (done) { closed = true; break; }
+        buffer += decoder.decode(value, { stream: true });
+        const lines = buffer.split('\n');
+        buffer = lines.pop();
+        for (const line of lines) {
+          if (line.startsWith('event:')) {
+            currentEvent = line.slice(6).trim();
+            if (currentEvent === 'error') { streamError = true; }
+          } else if (line.startsWith('data:')) {
+            try {
+              const payload = JSON.parse(line.slice(5));
+              if (payload.error) { streamError = true; }
+              const isFinal = payload.final || (currentEvent === 'complete');
+              if (streamError) { break; }
+
+              if (isFinal && !committed) {
+                sawFinal = true;
+                typing(false);
+                const finalChunk = payload.text || '';
+                if (finalChunk) {
+                  assembled += finalChunk;
+                  const tnode = document.getElementById('typing');
+                  if (tnode) { tnode.querySelector('.bubble').textContent = assembled; }
+                }
+                // Show whatever was assembled (don't send fallback requests)
+                const pt = parseReplyAndThought(assembled);
+                const replyText = pt.reply || assembled;
+                if (replyText && replyText.trim().length > 0) {
+                  addAgent(replyText, pt.thought || null);
+                } else {
+                  addAgent(i18n('error.empty_response'));
+                }
+                committed = true;
+                try { clearInterval(watchdog); } catch {}
+              } else {
+                const chunk = payload.text || '';
+                if (chunk) {
+                  assembled += chunk;
+                  firstChunkReceived = true;
+                  const tnode = document.getElementById('typing');
+                  if (tnode) { tnode.querySelector('.bubble').textContent = assembled; }
+                  lastChunkAt = Date.now();
+                }
+              }
+            } catch { streamError = true; }
+          }
+        }
+        if (streamError) { break; }
+      }
+      if (streamError && !committed) {
+        // Don't send fallback requests - they can block the server
+        typing(false);
+        addAgent(i18n('error.connection_interrupted'));
+        committed = true;
+        try { clearInterval(watchdog); } catch {}
+      } else {
+        if (closed && !committed) {
+          typing(false);
+          if (assembled && assembled.trim().length > 0) {
+            const pt = parseReplyAndThought(assembled);
+            addAgent(pt.reply || assembled, pt.thought || null);
+          } else {
+            addAgent(i18n('error.no_response'));
+          }
+          committed = true;
+          try { clearInterval(watchdog); } catch {}
+        }
+      }
+    } catch (e) {
+      if (generation.signal.aborted) { addLog('info', 'Response chat.stream superseded'); return; }
+      // Don't send fallback requests - they can block the server
+      typing(false);
+      addAgent(i18n('error.request_failed'));
+    }
+  } else {
+    const res = await fetchWithLog(API + '/chat', { method:'POST', headers, body: JSON.stringify({ text, roomId, entityId, stream:false }) }, 'chat');
+    const data = await res.json();
+    if (!data.success) {
+      typing(false);
+      const chatError = (data.error && data.error.message) || data.error;
+      addAgent(i18n('error.generic', { error: chatError || i18nText('error.unknown') }));
+      addLog('error','Chat error '+(chatError || 'unknown'));
+      return;
+    }
+    const taskId = data.taskId;
+    let tries = 0;
+    while (tries < 60) {
+      const tr = await fetchWithLog(API + '/task/' + taskId, { headers }, 'task');
+      const td = await tr.json();
+      if (td.status === 'completed' && td.result) {
+        typing(false);
+        const msgs = (td.result && td.result.messages) ? td.result.messages : [];
+        addLog('info','Task completed with '+msgs.length+' messages');
+        for (const m of msgs) {
+          const textRaw = (m.content && m.content.text) ? m.content.text : JSON.stringify(m);
+          const pt = parseReplyAndThought(textRaw);
+          const replyText = pt.reply || textRaw;
+          const thought = pt.thought || null;
+          addAgent(replyText, thought);
+        }
+        fetchState(headers);
+        break;
+      } else if (td.status === 'failed') {
+        typing(false);
+        addAgent(i18n('error.task_failed', { error: td.error || i18nText('error.unknown') }));
+        addLog('error','Task failed '+(td.error || 'unknown'));
+        break;
+      }
+      await new Promise(r => setTimeout(r, 500));
+      addLog('info','Polling task '+String(++tries));
+    }
+  }
+}
+
+btn.addEventListener('click', async () => {
+  const text = input.value.trim();
+  if (!text) return;
+  truncateForResend();
+  addUser(text);
+  input.value = '';
+  await sendMessage(text);
+});
+input.addEventListener('keydown', async (e) => {
+  if (e.key === 'Enter') { e.preventDefault(); btn.click(); }
+  else if (e.key === 'Escape' && editing) { cancelEdit(); input.value = ''; }
+});
@@ -0,0 +1,353 @@
+//! UI token checked on every route
+//!
+//! With [`SimpleUiConfig::token`](crate::SimpleUiConfig::token) set, every
+//! request (the page and its scripts, local `/agent` routes, the proxy,
+//! `/logs`, the WebSockets) has to carry the token as `Authorization: Bearer`,
+//! as `?token=`, or in the [`TOKEN_COOKIE`] a valid `?token=` sets. The admin
+//! token is accepted as well. Without it the index answers `401` with a login
+//! form, other routes `401 missing_token` or `403 invalid_token`.
+//!
+//! `/webapp` checks Telegram's signed launch data itself, and the session
+//! cookie it sets is accepted in place of the token.
+//!
+//! Once checked, the UI token is taken off the request so it never reaches
+//! the Agent API. With
+//! [`SimpleUiConfig::forward_token`](crate::SimpleUiConfig::forward_token) it
+//! is passed on as `Authorization: Bearer` instead, for backends that check
+//! the same token. An admin token given as `Authorization: Bearer` is taken
+//! off as well, with or without a UI token, except on the local admin routes
+//! that check it.
+
+use crate::admin::constant_time_eq;
+use crate::error::{WebError, WebResult};
+use crate::{openapi, telegram_webapp, SimpleUiConfig, SimpleUiServer};
+use axum::extract::{Request, State as AxumState};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+
+/// Cookie holding the UI token once it was given as `?token=`
+pub(crate) const TOKEN_COOKIE: &str = "zoey_ui_token";
+
+/// Query parameter the token can be given in
+const TOKEN_PARAM: &str = "token";
+
+/// Routes that authenticate with Telegram's launch data instead
+const EXEMPT_PATHS: [&str; 2] = ["/webapp", "/webapp/session"];
+
+/// Served with `401` at `/` until the browser has the token
+const LOGIN_PAGE: &str = r#"<!doctype html>
+<html lang="en">
+<head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1"><title>Zoey</title></head>
+<body style="font-family: sans-serif; max-width: 22rem; margin: 20vh auto; padding: 0 1rem">
+<h1 style="font-size: 1.25rem">Zoey</h1>
+<form method="get" action="/">
+<label for="token">Access token</label><br>
+<input id="token" name="token" type="password" autocomplete="current-password" autofocus required style="width: 100%; margin: .5rem 0">
+<button type="submit">Sign in</button>
+</form>
+</body>
+</html>
+"#;
+
+/// Middleware requiring the UI token when one is configured
+pub(crate) async fn require_token(
+    AxumState(state): AxumState<SimpleUiServer>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    // Only the local admin routes read the admin token; nothing else may pass it on
+    let admin_token = state
+        .config
+        .admin_token
+        .clone()
+        .filter(|t| !t.is_empty())
+        .filter(|_| !openapi::is_admin_route(req.method().as_str(), req.uri().path()));
+    let Some(expected) = state.config.token.clone().filter(|t| !t.is_empty()) else {
+        if let Some(admin) = &admin_token {
+            remove_bearer(req.headers_mut(), admin);
+        }
+        return next.run(req).await;
+    };
+    if EXEMPT_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let query_token = query_token(req.uri());
+    let is_index = req.uri().path() == "/" && req.method() == Method::GET;
+    if let Err(err) = check(
+        &state.config,
+        &expected,
+        req.headers(),
+        query_token.as_deref(),
+    ) {
+        if is_index {
+            return (StatusCode::UNAUTHORIZED, Html(LOGIN_PAGE)).into_response();
+        }
+        return err.into_response();
+    }
+
+    // A valid `?token=` signs the browser in; the index drops it from the address bar
+    let set_cookie = query_token
+        .filter(|t| constant_time_eq(t.as_bytes(), expected.as_bytes()))
+        .and_then(|t| token_cookie(&t));
+    strip_token(
+        &mut req,
+        &expected,
+        admin_token.as_deref(),
+        state.config.forward_token,
+    );
+    let mut resp = match (&set_cookie, is_index) {
+        (Some(_), true) => Redirect::to(&req.uri().to_string()).into_response(),
+        _ => next.run(req).await,
+    };
+    if let Some(cookie) = set_cookie {
+        resp.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+    resp
+}
+
+/// Check the request carries the UI token, the admin token or a Telegram session
+fn check(
+    config: &SimpleUiConfig,
+    expected: &str,
+    headers: &HeaderMap,
+    query_token: Option<&str>,
+) -> WebResult<()> {
+    let admin = config.admin_token.as_deref().filter(|t| !t.is_empty());
+    let provided: Vec<&str> = bearer(headers)
+        .into_iter()
+        .chain(query_token)
+        .chain(cookie(headers, TOKEN_COOKIE))
+        .collect();
+    let valid = |token: &&str| {
+        constant_time_eq(token.as_bytes(), expected.as_bytes())
+            || admin.is_some_and(|a| constant_time_eq(token.as_bytes(), a.as_bytes()))
+    };
+    if provided.iter().any(valid)
+        || telegram_webapp::has_session(config.telegram_bot_token.as_deref(), headers)
+    {
+        return Ok(());
+    }
+    if provided.is_empty() {
+        Err(WebError::unauthorized("missing_token", "Missing token"))
+    } else {
+        Err(WebError::forbidden("invalid_token", "Invalid token"))
+    }
+}
+
+/// Take the UI token off `req`, or leave it only as a bearer token when forwarding
+///
+/// A bearer `admin` token is taken off too.
+fn strip_token(req: &mut Request, expected: &str, admin: Option<&str>, forward: bool) {
+    let headers = req.headers_mut();
+    remove_bearer(headers, expected);
+    if let Some(admin) = admin {
+        remove_bearer(headers, admin);
+    }
+    let cookies: Vec<String> = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .map(str::trim)
+        .filter(|pair| pair.split_once('=').map_or(*pair, |(name, _)| name) != TOKEN_COOKIE)
+        .map(str::to_string)
+        .collect();
+    headers.remove(header::COOKIE);
+    if !cookies.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&cookies.join("; ")) {
+            headers.insert(header::COOKIE, value);
+        }
+    }
+    if forward && !headers.contains_key(header::AUTHORIZATION) {
+        if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", expected)) {
+            headers.insert(header::AUTHORIZATION, value);
+        }
+    }
+    if let Some(uri) = without_token_param(req.uri()) {
+        *req.uri_mut() = uri;
+    }
+}
+
+/// Remove `Authorization: Bearer <token>`, leaving other credentials
+fn remove_bearer(headers: &mut HeaderMap, token: &str) {
+    if bearer(headers).is_some_and(|t| constant_time_eq(t.as_bytes(), token.as_bytes())) {
+        headers.remove(header::AUTHORIZATION);
+    }
+}
+
+/// `uri` minus its `token` query parameters, `None` when it has none
+fn without_token_param(uri: &Uri) -> Option<Uri> {
+    let query = uri.query()?;
+    let is_token =
+        |pair: &&str| pair.split_once('=').map_or(*pair, |(name, _)| name) == TOKEN_PARAM;
+    if !query.split('&').any(|pair| is_token(&pair)) {
+        return None;
+    }
+    let rest: Vec<&str> = query.split('&').filter(|pair| !is_token(pair)).collect();
+    let path_and_query = if rest.is_empty() {
+        uri.path().to_string()
+    } else {
+        format!("{}?{}", uri.path(), rest.join("&"))
+    };
+    path_and_query.parse().ok()
+}
+
+/// Token given as `?token=`, decoded
+fn query_token(uri: &Uri) -> Option<String> {
+    let axum::extract::Query(params) =
+        axum::extract::Query::<std::collections::HashMap<String, String>>::try_from_uri(uri)
+            .ok()?;
+    params
+        .get(TOKEN_PARAM)
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// Token in an `Authorization: Bearer` header
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+/// Value of the cookie `name`, if the request sent it
+pub(crate) fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(n, _)| *n == name)
+        .map(|(_, value)| value)
+}
+
+/// `Set-Cookie` signing the browser in, `None` for tokens a cookie can't hold
+fn token_cookie(token: &str) -> Option<HeaderValue> {
+    let cookie_safe = token
+        .bytes()
+        .all(|b| b.is_ascii_graphic() && !matches!(b, b'"' | b',' | b';' | b'\\'));
+    if !cookie_safe {
+        return None;
+    }
+    HeaderValue::from_str(&format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax",
+        TOKEN_COOKIE, token
+    ))
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(token: &str) -> SimpleUiConfig {
+        SimpleUiConfig {
+            token: Some(token.to_string()),
+            admin_token: Some("admin".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(name.clone(), value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_token_from_header_query_or_cookie() {
+        let config = config("s3cret");
+        let check = |h: &HeaderMap, q: Option<&str>| check(&config, "s3cret", h, q);
+        assert!(check(&headers(&[(header::AUTHORIZATION, "Bearer s3cret")]), None).is_ok());
+        assert!(check(&HeaderMap::new(), Some("s3cret")).is_ok());
+        let cookies = headers(&[(header::COOKIE, "lang=de; zoey_ui_token=s3cret")]);
+        assert!(check(&cookies, None).is_ok());
+        assert!(check(&headers(&[(header::AUTHORIZATION, "Bearer admin")]), None).is_ok());
+
+        let missing = check(&HeaderMap::new(), None).unwrap_err();
+        assert_eq!(missing.status, StatusCode::UNAUTHORIZED);
+        let wrong = check(&HeaderMap::new(), Some("guess")).unwrap_err();
+        assert_eq!(wrong.status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_token_stripped_unless_forwarded() {
+        let request = || {
+            Request::builder()
+                .uri("/agent/chat?token=s3cret&lang=de")
+                .header(header::AUTHORIZATION, "Bearer s3cret")
+                .header(header::COOKIE, "zoey_ui_token=s3cret; zoey_session=abc")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let mut req = request();
+        strip_token(&mut req, "s3cret", Some("admin"), false);
+        assert_eq!(req.uri(), "/agent/chat?lang=de");
+        assert!(req.headers().get(header::AUTHORIZATION).is_none());
+        assert_eq!(req.headers()[header::COOKIE], "zoey_session=abc");
+
+        let mut req = request();
+        strip_token(&mut req, "s3cret", Some("admin"), true);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer s3cret");
+        assert_eq!(req.headers()[header::COOKIE], "zoey_session=abc");
+
+        // The admin token is not passed on either; forwarding swaps in the UI token
+        let admin_request = || {
+            Request::builder()
+                .uri("/agent/chat")
+                .header(header::AUTHORIZATION, "Bearer admin")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let mut req = admin_request();
+        strip_token(&mut req, "s3cret", Some("admin"), false);
+        assert!(req.headers().get(header::AUTHORIZATION).is_none());
+        let mut req = admin_request();
+        strip_token(&mut req, "s3cret", Some("admin"), true);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer s3cret");
+        // Unless the route checks it
+        let mut req = admin_request();
+        strip_token(&mut req, "s3cret", None, false);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer admin");
+
+        let mut only_cookie = Request::builder()
+            .uri("/logs?token=s3cret")
+            .header(header::COOKIE, "zoey_ui_token=s3cret")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        strip_token(&mut only_cookie, "s3cret", None, false);
+        assert_eq!(only_cookie.uri(), "/logs");
+        assert!(only_cookie.headers().get(header::COOKIE).is_none());
+    }
+
+    #[test]
+    fn test_admin_routes_keep_admin_token() {
+        assert!(openapi::is_admin_route("GET", "/agent/admin/rooms"));
+        assert!(openapi::is_admin_route(
+            "POST",
+            "/api/v1/admin/room/42/clear"
+        ));
+        assert!(!openapi::is_admin_route("POST", "/agent/admin/rooms"));
+        assert!(!openapi::is_admin_route("GET", "/agent/admin/room/"));
+        assert!(!openapi::is_admin_route("GET", "/agent/admin/rooms/extra"));
+        assert!(!openapi::is_admin_route("POST", "/agent/chat"));
+    }
+
+    #[test]
+    fn test_token_cookie() {
+        let cookie = token_cookie("s3cret").unwrap();
+        assert_eq!(
+            cookie,
+            "zoey_ui_token=s3cret; Path=/; HttpOnly; SameSite=Lax"
+        );
+        assert!(token_cookie("has;semicolon").is_none());
+        assert!(token_cookie("has space").is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Case metadata kept on the server
+//!
+//! The legal UI's case list (names, matter numbers, status, invite tokens,
+//! message counts) lives in a [`CaseStore`] so it follows a lawyer across
+//! machines. [`MemoryCaseStore`] is the default; [`AdapterCaseStore`] keeps
+//! cases as entity components through the runtime's database adapter (e.g.
+//! `MongoAdapter`). Participants and roles stay in [`crate::cases`].
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+use zoey_core::{types::Component, IDatabaseAdapter, Result};
+
+/// Component type used to persist cases
+const CASE_COMPONENT_TYPE: &str = "web_case";
+
+/// Whether a case is still being worked on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseStatus {
+    /// Open for chat and uploads
+    Active,
+    /// Kept for reference
+    Closed,
+}
+
+/// A case as listed in the legal UI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseSummary {
+    /// Case ID, also the ID of the case's room
+    pub id: Uuid,
+    /// Entity that created the case
+    pub owner: Uuid,
+    /// Display name
+    pub name: String,
+    /// Firm's matter number, if any
+    #[schemars(required)]
+    pub matter_number: Option<String>,
+    /// Active or closed
+    pub status: CaseStatus,
+    /// Token in the case's share link
+    pub invite_token: String,
+    /// Creation time, epoch ms
+    pub created_at: i64,
+    /// Last message or change, epoch ms
+    pub last_activity: i64,
+    /// Messages exchanged in the case
+    pub message_count: u64,
+}
+
+/// Where case metadata is kept
+#[async_trait]
+pub trait CaseStore: Send + Sync {
+    /// Cases owned by `owner`, in no particular order
+    async fn list(&self, owner: Uuid) -> Result<Vec<CaseSummary>>;
+
+    /// One of `owner`'s cases
+    async fn get(&self, owner: Uuid, case_id: Uuid) -> Result<Option<CaseSummary>>;
+
+    /// Insert or replace a case
+    async fn save(&self, case: &CaseSummary) -> Result<()>;
+
+    /// Remove one of `owner`'s cases; `false` if there was none
+    async fn delete(&self, owner: Uuid, case_id: Uuid) -> Result<bool>;
+}
+
+/// In-memory case store (cases are lost on restart)
+#[derive(Default)]
+pub struct MemoryCaseStore {
+    cases: RwLock<HashMap<Uuid, CaseSummary>>,
+}
+
+impl MemoryCaseStore {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CaseStore for MemoryCaseStore {
+    async fn list(&self, owner: Uuid) -> Result<Vec<CaseSummary>> {
+        let cases = self.cases.read().unwrap_or_else(|e| e.into_inner());
+        Ok(cases
+            .values()
+            .filter(|c| c.owner == owner)
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, owner: Uuid, case_id: Uuid) -> Result<Option<CaseSummary>> {
+        let cases = self.cases.read().unwrap_or_else(|e| e.into_inner());
+        Ok(cases.get(&case_id).filter(|c| c.owner == owner).cloned())
+    }
+
+    async fn save(&self, case: &CaseSummary) -> Result<()> {
+        self.cases
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(case.id, case.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, owner: Uuid, case_id: Uuid) -> Result<bool> {
+        let mut cases = self.cases.write().unwrap_or_else(|e| e.into_inner());
+        if cases.get(&case_id).is_some_and(|c| c.owner == owner) {
+            cases.remove(&case_id);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+/// Case store backed by the runtime's database adapter
+///
+/// Each case is a component of its owner's entity whose ID is the case ID.
+pub struct AdapterCaseStore {
+    adapter: Arc<dyn IDatabaseAdapter + Send + Sync>,
+}
+
+impl AdapterCaseStore {
+    /// Store cases through `adapter`
+    pub fn new(adapter: Arc<dyn IDatabaseAdapter + Send + Sync>) -> Self {
+        Self { adapter }
+    }
+
+    fn world_id() -> Uuid {
+        zoey_core::string_to_uuid("web-cases")
+    }
+
+    async fn components(&self, owner: Uuid) -> Result<Vec<Component>> {
+        Ok(self
+            .adapter
+            .get_components(owner, Some(Self::world_id()), None)
+            .await?
+            .into_iter()
+            .filter(|c| c.component_type == CASE_COMPONENT_TYPE)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CaseStore for AdapterCaseStore {
+    async fn list(&self, owner: Uuid) -> Result<Vec<CaseSummary>> {
+        Ok(self
+            .components(owner)
+            .await?
+            .into_iter()
+            .filter_map(|c| serde_json::from_value(c.data).ok())
+            .collect())
+    }
+
+    async fn get(&self, owner: Uuid, case_id: Uuid) -> Result<Option<CaseSummary>> {
+        Ok(self
+            .components(owner)
+            .await?
+            .into_iter()
+            .find(|c| c.id == case_id)
+            .and_then(|c| serde_json::from_value(c.data).ok()))
+    }
+
+    async fn save(&self, case: &CaseSummary) -> Result<()> {
+        let data = serde_json::to_value(case)?;
+        let now = chrono::Utc::now().timestamp();
+        let existing = self
+            .components(case.owner)
+            .await?
+            .into_iter()
+            .find(|c| c.id == case.id);
+        match existing {
+            Some(mut existing) => {
+                existing.data = data;
+                existing.updated_at = Some(now);
+                self.adapter.update_component(&existing).await
+            }
+            None => {
+                let component = Component {
+                    id: case.id,
+                    entity_id: case.owner,
+                    world_id: Self::world_id(),
+                    source_entity_id: None,
+                    component_type: CASE_COMPONENT_TYPE.to_string(),
+                    data,
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                };
+                self.adapter.create_component(&component).await.map(|_| ())
+            }
+        }
+    }
+
+    async fn delete(&self, owner: Uuid, case_id: Uuid) -> Result<bool> {
+        if self.get(owner, case_id).await?.is_none() {
+            return Ok(false);
+        }
+        self.adapter.delete_component(case_id).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(owner: Uuid, status: CaseStatus) -> CaseSummary {
+        CaseSummary {
+            id: Uuid::new_v4(),
+            owner,
+            name: "Smith v. Jones".into(),
+            matter_number: None,
+            status,
+            invite_token: "0123456789abcdef".into(),
+            created_at: 0,
+            last_activity: 0,
+            message_count: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_is_scoped_to_owner() {
+        let store = MemoryCaseStore::new();
+        let (owner, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let mine = case(owner, CaseStatus::Active);
+        store.save(&mine).await.unwrap();
+        store.save(&case(other, CaseStatus::Closed)).await.unwrap();
+
+        assert_eq!(store.list(owner).await.unwrap(), vec![mine.clone()]);
+        assert_eq!(store.get(other, mine.id).await.unwrap(), None);
+        assert!(!store.delete(other, mine.id).await.unwrap());
+
+        let renamed = CaseSummary {
+            name: "Renamed".into(),
+            ..mine.clone()
+        };
+        store.save(&renamed).await.unwrap();
+        assert_eq!(store.get(owner, mine.id).await.unwrap(), Some(renamed));
+        assert!(store.delete(owner, mine.id).await.unwrap());
+        assert!(store.list(owner).await.unwrap().is_empty());
+    }
+}
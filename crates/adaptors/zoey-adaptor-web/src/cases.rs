@@ -0,0 +1,1054 @@
+//! Cases, participants and roles for the legal workspace
+//!
+//! A case is a room shared through an invite link. Its metadata (name,
+//! status, message count) lives in the configured [`crate::CaseStore`]; its
+//! participants are kept in the runtime settings under `case:<room_id>`.
+//! Both are served directly by the web adapter (not proxied):
+//!
+//! - `POST   /agent/cases`                              create a case; the server picks the ID and invite token
+//! - `GET    /agent/cases?entity_id=...`                the caller's cases, active and closed
+//! - `PATCH  /agent/cases/:id`                          owner renames, closes or reopens a case
+//! - `DELETE /agent/cases/:id`                          owner deletes a case and its room
+//! - `PUT    /agent/cases/:id/invite`                   owner registers or rotates the invite token
+//! - `POST   /agent/cases/:id/participants`             join with an invite token as viewer or collaborator
+//! - `GET    /agent/cases/:id/participants`             list participants (members only)
+//! - `PATCH  /agent/cases/:id/participants/:entity_id`  owner changes a participant's role
+//! - `DELETE /agent/cases/:id/participants/:entity_id`  owner removes a participant
+//!
+//! Who is currently viewing a case is tracked separately, in `presence`.
+//!
+//! The caller is identified by the `X-Entity-Id` header. Rooms without a
+//! registered case are unrestricted. Once registered, chat and uploads need
+//! the owner or collaborator role and search and history need membership;
+//! every check reads the stored record, so removals apply immediately.
+
+use crate::admin::{self, constant_time_eq};
+use crate::case_store::{CaseStatus, CaseSummary};
+use crate::error::{WebError, WebResult};
+use crate::openapi::{Done, Success};
+use crate::SimpleUiServer;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::{Path, Query, State as AxumState};
+use axum::http::HeaderMap;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Header identifying the entity making a case request
+pub(crate) const ENTITY_HEADER: &str = "x-entity-id";
+
+/// Longest display name kept for a participant
+const MAX_DISPLAY_NAME_CHARS: usize = 64;
+
+/// Shortest invite token the owner may register
+const MIN_INVITE_TOKEN_LEN: usize = 16;
+
+/// Longest case name or matter number kept
+const MAX_CASE_NAME_CHARS: usize = 200;
+
+/// Participant role within a case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseRole {
+    Owner,
+    Collaborator,
+    Viewer,
+}
+
+impl CaseRole {
+    fn allows(self, action: CaseAction) -> bool {
+        match action {
+            CaseAction::Read => true,
+            CaseAction::Chat | CaseAction::Upload => self != CaseRole::Viewer,
+        }
+    }
+}
+
+/// What a request does with a case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CaseAction {
+    /// History and search
+    Read,
+    /// Chat requests
+    Chat,
+    /// Document uploads
+    Upload,
+}
+
+/// A member of a case
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseParticipant {
+    pub entity_id: Uuid,
+    pub display_name: String,
+    pub role: CaseRole,
+    pub joined_at: i64,
+}
+
+/// Stored participants and invite token of one case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CaseRecord {
+    invite_token: String,
+    participants: Vec<CaseParticipant>,
+}
+
+impl CaseRecord {
+    fn new(owner: Uuid, display_name: String, invite_token: String, now_ms: i64) -> Self {
+        Self {
+            invite_token,
+            participants: vec![CaseParticipant {
+                entity_id: owner,
+                display_name,
+                role: CaseRole::Owner,
+                joined_at: now_ms,
+            }],
+        }
+    }
+
+    fn owner(&self) -> Option<Uuid> {
+        self.participants
+            .iter()
+            .find(|p| p.role == CaseRole::Owner)
+            .map(|p| p.entity_id)
+    }
+
+    pub(crate) fn participant(&self, entity_id: Uuid) -> Option<&CaseParticipant> {
+        self.participants.iter().find(|p| p.entity_id == entity_id)
+    }
+
+    fn role_of(&self, entity_id: Uuid) -> Option<CaseRole> {
+        self.participants
+            .iter()
+            .find(|p| p.entity_id == entity_id)
+            .map(|p| p.role)
+    }
+
+    /// Allow `action` for `entity_id`, or explain why not
+    pub(crate) fn check(&self, entity_id: Option<Uuid>, action: CaseAction) -> WebResult<()> {
+        let role = entity_id.and_then(|id| self.role_of(id)).ok_or_else(|| {
+            WebError::forbidden(
+                "not_a_participant",
+                "You are not a participant in this case",
+            )
+        })?;
+        if role.allows(action) {
+            Ok(())
+        } else {
+            Err(WebError::forbidden(
+                "viewer_read_only",
+                "Viewers cannot chat or upload in this case",
+            ))
+        }
+    }
+
+    fn require_owner(&self, actor: Uuid) -> WebResult<()> {
+        if self.owner() == Some(actor) {
+            Ok(())
+        } else {
+            Err(WebError::forbidden(
+                "owner_only",
+                "Only the case owner can manage participants",
+            ))
+        }
+    }
+
+    /// Add `entity_id` with a valid invite token; existing members only update their name
+    fn join(
+        &mut self,
+        invite_token: &str,
+        entity_id: Uuid,
+        display_name: String,
+        role: CaseRole,
+        now_ms: i64,
+    ) -> WebResult<CaseParticipant> {
+        if !constant_time_eq(invite_token.as_bytes(), self.invite_token.as_bytes()) {
+            return Err(WebError::forbidden(
+                "invalid_invite",
+                "Invalid invite token",
+            ));
+        }
+        if role == CaseRole::Owner {
+            return Err(WebError::bad_request(
+                "invalid_role",
+                "Participants join as viewer or collaborator",
+            ));
+        }
+        if let Some(existing) = self
+            .participants
+            .iter_mut()
+            .find(|p| p.entity_id == entity_id)
+        {
+            existing.display_name = display_name;
+            return Ok(existing.clone());
+        }
+        let participant = CaseParticipant {
+            entity_id,
+            display_name,
+            role,
+            joined_at: now_ms,
+        };
+        self.participants.push(participant.clone());
+        Ok(participant)
+    }
+
+    fn set_role(
+        &mut self,
+        actor: Uuid,
+        target: Uuid,
+        role: CaseRole,
+    ) -> WebResult<CaseParticipant> {
+        self.require_owner(actor)?;
+        if role == CaseRole::Owner {
+            return Err(WebError::bad_request(
+                "invalid_role",
+                "Ownership cannot be transferred",
+            ));
+        }
+        if target == actor {
+            return Err(WebError::bad_request(
+                "owner_immutable",
+                "The owner's role cannot be changed",
+            ));
+        }
+        let participant = self
+            .participants
+            .iter_mut()
+            .find(|p| p.entity_id == target)
+            .ok_or_else(|| WebError::not_found("participant_not_found", "Participant not found"))?;
+        participant.role = role;
+        Ok(participant.clone())
+    }
+
+    fn remove(&mut self, actor: Uuid, target: Uuid) -> WebResult<()> {
+        self.require_owner(actor)?;
+        if target == actor {
+            return Err(WebError::bad_request(
+                "owner_immutable",
+                "The owner cannot be removed",
+            ));
+        }
+        let before = self.participants.len();
+        self.participants.retain(|p| p.entity_id != target);
+        if self.participants.len() == before {
+            return Err(WebError::not_found(
+                "participant_not_found",
+                "Participant not found",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn case_key(case_id: Uuid) -> String {
+    format!("case:{}", case_id)
+}
+
+fn room_owner_key(case_id: Uuid) -> String {
+    format!("ROOM_OWNER:{}", case_id)
+}
+
+/// Whether a case record is registered for room `case_id`
+pub(crate) fn has_case(state: &SimpleUiServer, case_id: Uuid) -> WebResult<bool> {
+    let rt = crate::read_runtime(state)?;
+    Ok(rt
+        .get_setting(&case_key(case_id))
+        .is_some_and(|v| !v.is_null()))
+}
+
+pub(crate) fn load_case(state: &SimpleUiServer, case_id: Uuid) -> WebResult<Option<CaseRecord>> {
+    let rt = crate::read_runtime(state)?;
+    Ok(rt
+        .get_setting(&case_key(case_id))
+        .filter(|v| !v.is_null())
+        .and_then(|v| serde_json::from_value(v).ok()))
+}
+
+/// Apply `f` to the stored record under the runtime write lock and save the result
+fn update_case<T>(
+    state: &SimpleUiServer,
+    case_id: Uuid,
+    f: impl FnOnce(Option<CaseRecord>, Option<Uuid>) -> WebResult<(CaseRecord, T)>,
+) -> WebResult<T> {
+    let mut rt = state.runtime.write().map_err(|_| {
+        tracing::error!("Runtime lock poisoned, case not updated");
+        WebError::runtime_unavailable()
+    })?;
+    let current = rt
+        .get_setting(&case_key(case_id))
+        .filter(|v| !v.is_null())
+        .and_then(|v| serde_json::from_value(v).ok());
+    // The agent API records the first entity to chat in a room as its owner
+    let room_owner = rt
+        .get_setting(&room_owner_key(case_id))
+        .and_then(|v| v.as_str().and_then(|s| s.parse().ok()));
+    let (record, out) = f(current, room_owner)?;
+    let value = serde_json::to_value(&record)
+        .map_err(|e| WebError::internal("case_encoding", e.to_string()))?;
+    rt.set_setting(&case_key(case_id), value, false);
+    Ok(out)
+}
+
+pub(crate) fn caller(headers: &HeaderMap) -> WebResult<Uuid> {
+    let raw = headers
+        .get(ENTITY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| WebError::unauthorized("missing_entity", "Missing X-Entity-Id header"))?;
+    raw.parse()
+        .map_err(|_| WebError::bad_request("invalid_entity", "X-Entity-Id must be a UUID"))
+}
+
+/// Trimmed and capped case name or matter number, `None` when blank
+fn clean_case_field(value: &str) -> Option<String> {
+    let value: String = value.trim().chars().take(MAX_CASE_NAME_CHARS).collect();
+    (!value.is_empty()).then_some(value)
+}
+
+fn store_error(e: zoey_core::ZoeyError) -> WebError {
+    WebError::internal("case_store_error", e.to_string())
+}
+
+fn clean_display_name(name: &str) -> String {
+    let name: String = name.trim().chars().take(MAX_DISPLAY_NAME_CHARS).collect();
+    if name.is_empty() {
+        "Guest".to_string()
+    } else {
+        name
+    }
+}
+
+/// Allow `action` on room `case_id` for `entity_id`; rooms without a case are unrestricted
+pub(crate) fn authorize(
+    state: &SimpleUiServer,
+    case_id: Uuid,
+    entity_id: Option<Uuid>,
+    action: CaseAction,
+) -> WebResult<()> {
+    match load_case(state, case_id)? {
+        Some(record) => record.check(entity_id, action),
+        None => Ok(()),
+    }
+}
+
+/// Case check for a chat sent over the WebSocket channel
+pub(crate) fn authorize_chat(
+    state: &SimpleUiServer,
+    room_id: Option<&str>,
+    entity_id: Option<&str>,
+) -> WebResult<()> {
+    match room_id.and_then(|r| r.parse().ok()) {
+        Some(case_id) => authorize(
+            state,
+            case_id,
+            entity_id.and_then(|e| e.parse().ok()),
+            CaseAction::Chat,
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Whether [`authorize_proxy`] reads the body of a call to `rest`, so the
+/// proxy has to buffer it rather than stream it
+pub(crate) fn proxy_reads_body(rest: &str) -> bool {
+    matches!(
+        rest.trim_start_matches('/'),
+        "chat" | "chat/stream" | "knowledge/ingest" | "knowledge/query"
+    )
+}
+
+/// Case check for a proxied Agent API call, from its path, body and headers
+pub(crate) fn authorize_proxy(
+    state: &SimpleUiServer,
+    rest: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> WebResult<()> {
+    let rest = rest.trim_start_matches('/');
+    let list_room = rest.strip_prefix("knowledge/list/");
+    let action = match rest {
+        "chat" | "chat/stream" => CaseAction::Chat,
+        "knowledge/ingest" => CaseAction::Upload,
+        "knowledge/query" => CaseAction::Read,
+        _ if list_room.is_some() => CaseAction::Read,
+        _ => return Ok(()),
+    };
+    let json: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+    let field = |camel: &str, snake: &str| {
+        json.get(camel)
+            .or_else(|| json.get(snake))
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse::<Uuid>().ok())
+    };
+    // Malformed requests are left for the Agent API to reject
+    let Some(case_id) = list_room
+        .and_then(|r| r.parse().ok())
+        .or_else(|| field("roomId", "room_id"))
+    else {
+        return Ok(());
+    };
+    let entity_id = field("entityId", "entity_id").or_else(|| caller(headers).ok());
+    authorize(state, case_id, entity_id, action)
+}
+
+/// `POST /agent/cases` body
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "CreateCaseRequest")]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CreateCaseBody {
+    name: String,
+    #[serde(default)]
+    matter_number: Option<String>,
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+/// `GET /agent/cases` query
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct ListCasesQuery {
+    /// Owner to list; defaults to `X-Entity-Id` and must match it when both are sent
+    #[serde(default)]
+    #[schemars(with = "Option<Uuid>")]
+    entity_id: Option<String>,
+}
+
+/// `PATCH /agent/cases/:id` body; absent fields are left unchanged
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "UpdateCaseRequest")]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UpdateCaseBody {
+    #[serde(default)]
+    name: Option<String>,
+    /// An empty string clears the matter number
+    #[serde(default)]
+    matter_number: Option<String>,
+    #[serde(default)]
+    status: Option<CaseStatus>,
+    #[serde(default)]
+    message_count: Option<u64>,
+    #[serde(default)]
+    last_activity: Option<i64>,
+}
+
+/// `PUT /agent/cases/:id/invite` body
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "InviteRequest")]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InviteBody {
+    #[schemars(length(min = 16))]
+    invite_token: String,
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+/// `POST /agent/cases/:id/participants` body
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "JoinRequest")]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JoinBody {
+    invite_token: String,
+    #[serde(default)]
+    display_name: String,
+    #[serde(default = "default_join_role")]
+    role: CaseRole,
+}
+
+fn default_join_role() -> CaseRole {
+    CaseRole::Viewer
+}
+
+/// `PATCH /agent/cases/:id/participants/:entity_id` body
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "RoleRequest")]
+pub(crate) struct RoleBody {
+    role: CaseRole,
+}
+
+/// Reply carrying one case
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CaseResponse {
+    case: CaseSummary,
+}
+
+/// `GET /agent/cases` reply
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CaseList {
+    active: Vec<CaseSummary>,
+    closed: Vec<CaseSummary>,
+}
+
+/// `DELETE /agent/cases/:id` reply
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeleteCaseResponse {
+    case_id: Uuid,
+    /// Whether the case's room was deleted from the database too
+    room_deleted: bool,
+}
+
+/// `PUT /agent/cases/:id/invite` reply
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InviteResponse {
+    case_id: Uuid,
+}
+
+/// Reply carrying one participant
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ParticipantResponse {
+    participant: CaseParticipant,
+}
+
+/// `GET /agent/cases/:id/participants` reply
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ParticipantList {
+    participants: Vec<CaseParticipant>,
+    /// The caller's role
+    #[schemars(required)]
+    role: Option<CaseRole>,
+}
+
+/// `PUT /agent/cases/:id/invite`
+///
+/// Registers the case on first use. Only the room's owner (the first entity
+/// to chat in it, if any has) may register or rotate the token.
+pub(crate) async fn register_invite(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+    body: Result<Json<InviteBody>, JsonRejection>,
+) -> WebResult<Json<Success<InviteResponse>>> {
+    let Path(case_id) = case_id?;
+    let actor = caller(&headers)?;
+    let Json(body) = body?;
+    if body.invite_token.len() < MIN_INVITE_TOKEN_LEN {
+        return Err(WebError::bad_request(
+            "invalid_invite",
+            "Invite token is too short",
+        ));
+    }
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    update_case(&state, case_id, |current, room_owner| {
+        let record = match current {
+            Some(mut record) => {
+                record.require_owner(actor)?;
+                record.invite_token = body.invite_token;
+                record
+            }
+            None => {
+                if room_owner.is_some_and(|owner| owner != actor) {
+                    return Err(WebError::forbidden(
+                        "owner_only",
+                        "Only the room owner can share this case",
+                    ));
+                }
+                let name = clean_display_name(body.display_name.as_deref().unwrap_or_default());
+                CaseRecord::new(actor, name, body.invite_token, now_ms)
+            }
+        };
+        Ok((record, ()))
+    })?;
+    Ok(Json(Success::new(InviteResponse { case_id })))
+}
+
+/// `POST /agent/cases`
+///
+/// The caller becomes the owner; the invite token is generated here and
+/// registered right away, so the case can be shared without a second call.
+pub(crate) async fn create_case(
+    AxumState(state): AxumState<SimpleUiServer>,
+    headers: HeaderMap,
+    body: Result<Json<CreateCaseBody>, JsonRejection>,
+) -> WebResult<Json<Success<CaseResponse>>> {
+    let actor = caller(&headers)?;
+    let Json(body) = body?;
+    let name = clean_case_field(&body.name)
+        .ok_or_else(|| WebError::bad_request("invalid_name", "Case name is required"))?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let case = CaseSummary {
+        id: Uuid::new_v4(),
+        owner: actor,
+        name,
+        matter_number: body.matter_number.as_deref().and_then(clean_case_field),
+        status: CaseStatus::Active,
+        invite_token: Uuid::new_v4().simple().to_string(),
+        created_at: now_ms,
+        last_activity: now_ms,
+        message_count: 0,
+    };
+    state
+        .config
+        .case_store
+        .save(&case)
+        .await
+        .map_err(store_error)?;
+    let display_name = clean_display_name(body.display_name.as_deref().unwrap_or_default());
+    let record = CaseRecord::new(actor, display_name, case.invite_token.clone(), now_ms);
+    update_case(&state, case.id, |_, _| Ok((record, ())))?;
+    tracing::info!(case_id = %case.id, entity_id = %actor, "Case created");
+    Ok(Json(Success::new(CaseResponse { case })))
+}
+
+/// `GET /agent/cases`
+///
+/// The entity comes from `entity_id` or, failing that, `X-Entity-Id`; when
+/// both are given they must agree. Each list is most recently active first.
+pub(crate) async fn list_cases(
+    AxumState(state): AxumState<SimpleUiServer>,
+    Query(query): Query<ListCasesQuery>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<CaseList>>> {
+    let header = caller(&headers).ok();
+    let entity = match query.entity_id.as_deref().map(str::trim) {
+        Some(raw) if !raw.is_empty() => {
+            let id: Uuid = raw
+                .parse()
+                .map_err(|_| WebError::bad_request("invalid_entity", "entity_id must be a UUID"))?;
+            if header.is_some_and(|h| h != id) {
+                return Err(WebError::forbidden(
+                    "entity_mismatch",
+                    "entity_id does not match X-Entity-Id",
+                ));
+            }
+            id
+        }
+        _ => caller(&headers)?,
+    };
+    let mut cases = state
+        .config
+        .case_store
+        .list(entity)
+        .await
+        .map_err(store_error)?;
+    cases.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+    let (active, closed): (Vec<_>, Vec<_>) = cases
+        .into_iter()
+        .partition(|c| c.status == CaseStatus::Active);
+    Ok(Json(Success::new(CaseList { active, closed })))
+}
+
+/// `PATCH /agent/cases/:id`
+pub(crate) async fn update_case_info(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+    body: Result<Json<UpdateCaseBody>, JsonRejection>,
+) -> WebResult<Json<Success<CaseResponse>>> {
+    let Path(case_id) = case_id?;
+    let actor = caller(&headers)?;
+    let Json(body) = body?;
+    let store = &state.config.case_store;
+    let mut case = store
+        .get(actor, case_id)
+        .await
+        .map_err(store_error)?
+        .ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+    if let Some(name) = body.name {
+        case.name = clean_case_field(&name)
+            .ok_or_else(|| WebError::bad_request("invalid_name", "Case name is required"))?;
+    }
+    if let Some(matter_number) = body.matter_number {
+        case.matter_number = clean_case_field(&matter_number);
+    }
+    if let Some(status) = body.status {
+        case.status = status;
+    }
+    if let Some(count) = body.message_count {
+        case.message_count = count;
+    }
+    case.last_activity = body
+        .last_activity
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis())
+        .max(case.last_activity);
+    store.save(&case).await.map_err(store_error)?;
+    Ok(Json(Success::new(CaseResponse { case })))
+}
+
+/// `DELETE /agent/cases/:id`
+///
+/// Removes the case, its participants and, when a database is configured,
+/// the room with its memories, as `POST /agent/room/delete` would.
+pub(crate) async fn delete_case(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<DeleteCaseResponse>>> {
+    let Path(case_id) = case_id?;
+    let actor = caller(&headers)?;
+    let store = &state.config.case_store;
+    store
+        .get(actor, case_id)
+        .await
+        .map_err(store_error)?
+        .ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+    let mut room_deleted = false;
+    if let Ok((_, adapter)) = admin::adapter_for(&state) {
+        admin::purge_room_memories(adapter.as_ref(), case_id).await;
+        admin::clear_room_context(&state, case_id)?;
+        room_deleted = adapter.delete_room(case_id).await.unwrap_or_else(|e| {
+            tracing::warn!(case_id = %case_id, error = %e, "Failed to delete case room");
+            false
+        });
+    }
+    {
+        let mut rt = state.runtime.write().map_err(|_| {
+            tracing::error!("Runtime lock poisoned, case not deleted");
+            WebError::runtime_unavailable()
+        })?;
+        rt.set_setting(&case_key(case_id), serde_json::Value::Null, false);
+    }
+    store.delete(actor, case_id).await.map_err(store_error)?;
+    tracing::info!(case_id = %case_id, entity_id = %actor, room_deleted, "Case deleted");
+    Ok(Json(Success::new(DeleteCaseResponse {
+        case_id,
+        room_deleted,
+    })))
+}
+
+/// `POST /agent/cases/:id/participants`
+pub(crate) async fn join(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+    body: Result<Json<JoinBody>, JsonRejection>,
+) -> WebResult<Json<Success<ParticipantResponse>>> {
+    let Path(case_id) = case_id?;
+    let actor = caller(&headers)?;
+    let Json(body) = body?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let participant = update_case(&state, case_id, |current, _| {
+        let mut record =
+            current.ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+        let participant = record.join(
+            &body.invite_token,
+            actor,
+            clean_display_name(&body.display_name),
+            body.role,
+            now_ms,
+        )?;
+        Ok((record, participant))
+    })?;
+    tracing::info!(case_id = %case_id, entity_id = %actor, role = ?participant.role, "Participant joined case");
+    Ok(Json(Success::new(ParticipantResponse { participant })))
+}
+
+/// `GET /agent/cases/:id/participants`
+pub(crate) async fn list(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<ParticipantList>>> {
+    let Path(case_id) = case_id?;
+    let actor = caller(&headers)?;
+    let record = load_case(&state, case_id)?
+        .ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+    record.check(Some(actor), CaseAction::Read)?;
+    Ok(Json(Success::new(ParticipantList {
+        role: record.role_of(actor),
+        participants: record.participants,
+    })))
+}
+
+/// `PATCH /agent/cases/:id/participants/:entity_id`
+pub(crate) async fn update_role(
+    AxumState(state): AxumState<SimpleUiServer>,
+    ids: Result<Path<(Uuid, Uuid)>, PathRejection>,
+    headers: HeaderMap,
+    body: Result<Json<RoleBody>, JsonRejection>,
+) -> WebResult<Json<Success<ParticipantResponse>>> {
+    let Path((case_id, target)) = ids?;
+    let actor = caller(&headers)?;
+    let Json(body) = body?;
+    let participant = update_case(&state, case_id, |current, _| {
+        let mut record =
+            current.ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+        let participant = record.set_role(actor, target, body.role)?;
+        Ok((record, participant))
+    })?;
+    Ok(Json(Success::new(ParticipantResponse { participant })))
+}
+
+/// `DELETE /agent/cases/:id/participants/:entity_id`
+pub(crate) async fn remove(
+    AxumState(state): AxumState<SimpleUiServer>,
+    ids: Result<Path<(Uuid, Uuid)>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<Done>>> {
+    let Path((case_id, target)) = ids?;
+    let actor = caller(&headers)?;
+    update_case(&state, case_id, |current, _| {
+        let mut record =
+            current.ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+        record.remove(actor, target)?;
+        Ok((record, ()))
+    })?;
+    tracing::info!(case_id = %case_id, entity_id = %target, "Participant removed from case");
+    Ok(Json(Success::new(Done {})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn case() -> (CaseRecord, Uuid) {
+        let owner = Uuid::new_v4();
+        (
+            CaseRecord::new(owner, "Owner".into(), TOKEN.into(), 0),
+            owner,
+        )
+    }
+
+    #[test]
+    fn test_join_flow() {
+        let (mut record, owner) = case();
+        let guest = Uuid::new_v4();
+
+        let err = record
+            .join("wrong", guest, "Guest".into(), CaseRole::Viewer, 1)
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let err = record
+            .join(TOKEN, guest, "Guest".into(), CaseRole::Owner, 1)
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+
+        let joined = record
+            .join(TOKEN, guest, "Ada".into(), CaseRole::Collaborator, 1)
+            .unwrap();
+        assert_eq!(joined.role, CaseRole::Collaborator);
+        // Rejoining keeps the role and only refreshes the name
+        let again = record
+            .join(TOKEN, guest, "Ada L.".into(), CaseRole::Viewer, 2)
+            .unwrap();
+        assert_eq!(
+            (again.role, again.display_name.as_str()),
+            (CaseRole::Collaborator, "Ada L.")
+        );
+        assert_eq!(record.participants.len(), 2);
+        assert_eq!(record.owner(), Some(owner));
+    }
+
+    #[test]
+    fn test_role_enforcement() {
+        let (mut record, owner) = case();
+        let viewer = Uuid::new_v4();
+        let collaborator = Uuid::new_v4();
+        record
+            .join(TOKEN, viewer, "V".into(), CaseRole::Viewer, 1)
+            .unwrap();
+        record
+            .join(TOKEN, collaborator, "C".into(), CaseRole::Collaborator, 1)
+            .unwrap();
+
+        for action in [CaseAction::Read, CaseAction::Chat, CaseAction::Upload] {
+            assert!(record.check(Some(owner), action).is_ok());
+            assert!(record.check(Some(collaborator), action).is_ok());
+        }
+        assert!(record.check(Some(viewer), CaseAction::Read).is_ok());
+        for action in [CaseAction::Chat, CaseAction::Upload] {
+            let err = record.check(Some(viewer), action).unwrap_err();
+            assert_eq!(
+                (err.status, err.code),
+                (StatusCode::FORBIDDEN, "viewer_read_only")
+            );
+        }
+        let err = record
+            .check(Some(Uuid::new_v4()), CaseAction::Read)
+            .unwrap_err();
+        assert_eq!(err.code, "not_a_participant");
+        assert!(record.check(None, CaseAction::Read).is_err());
+
+        // Only the owner changes roles
+        assert!(record
+            .set_role(collaborator, viewer, CaseRole::Collaborator)
+            .is_err());
+        record
+            .set_role(owner, viewer, CaseRole::Collaborator)
+            .unwrap();
+        assert!(record.check(Some(viewer), CaseAction::Chat).is_ok());
+    }
+
+    #[test]
+    fn test_removal_revokes_access() {
+        let (mut record, owner) = case();
+        let guest = Uuid::new_v4();
+        record
+            .join(TOKEN, guest, "G".into(), CaseRole::Collaborator, 1)
+            .unwrap();
+        assert!(record.check(Some(guest), CaseAction::Read).is_ok());
+
+        let err = record.remove(guest, guest).unwrap_err();
+        assert_eq!(err.code, "owner_only");
+        record.remove(owner, guest).unwrap();
+        assert!(record.check(Some(guest), CaseAction::Read).is_err());
+        assert_eq!(
+            record.remove(owner, guest).unwrap_err().status,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_owner_cannot_be_removed_or_downgraded() {
+        let (mut record, owner) = case();
+        assert_eq!(
+            record.remove(owner, owner).unwrap_err().code,
+            "owner_immutable"
+        );
+        assert_eq!(
+            record
+                .set_role(owner, owner, CaseRole::Viewer)
+                .unwrap_err()
+                .code,
+            "owner_immutable"
+        );
+        let guest = Uuid::new_v4();
+        record
+            .join(TOKEN, guest, "G".into(), CaseRole::Viewer, 1)
+            .unwrap();
+        assert_eq!(
+            record
+                .set_role(owner, guest, CaseRole::Owner)
+                .unwrap_err()
+                .code,
+            "invalid_role"
+        );
+        assert_eq!(record.owner(), Some(owner));
+    }
+
+    #[tokio::test]
+    async fn test_case_lifecycle() {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let state = SimpleUiServer::new(crate::SimpleUiConfig::default(), runtime);
+        let owner = Uuid::new_v4();
+        let mut headers = HeaderMap::new();
+        headers.insert(ENTITY_HEADER, owner.to_string().parse().unwrap());
+        fn body<T: serde::de::DeserializeOwned>(
+            v: serde_json::Value,
+        ) -> Result<Json<T>, JsonRejection> {
+            Ok(Json(serde_json::from_value(v).unwrap()))
+        }
+
+        let Json(created) = create_case(
+            AxumState(state.clone()),
+            headers.clone(),
+            body(serde_json::json!({ "name": "  Smith v. Jones ", "displayName": "Ada" })),
+        )
+        .await
+        .unwrap();
+        let created = serde_json::to_value(created).unwrap();
+        assert_eq!(created["success"], true);
+        let case: CaseSummary = serde_json::from_value(created["case"].clone()).unwrap();
+        assert_eq!(case.name, "Smith v. Jones");
+        assert!(case.invite_token.len() >= MIN_INVITE_TOKEN_LEN);
+        // The participant record is registered with the generated token
+        let record = load_case(&state, case.id).unwrap().unwrap();
+        assert_eq!(record.owner(), Some(owner));
+        assert_eq!(record.invite_token, case.invite_token);
+
+        let err = update_case_info(
+            AxumState(state.clone()),
+            Ok(Path(case.id)),
+            HeaderMap::new(),
+            body(serde_json::json!({ "status": "closed" })),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+        update_case_info(
+            AxumState(state.clone()),
+            Ok(Path(case.id)),
+            headers.clone(),
+            body(serde_json::json!({ "status": "closed", "messageCount": 3 })),
+        )
+        .await
+        .unwrap();
+
+        let query = |entity_id: Option<Uuid>| {
+            Query(ListCasesQuery {
+                entity_id: entity_id.map(|id| id.to_string()),
+            })
+        };
+        let Json(listed) = list_cases(
+            AxumState(state.clone()),
+            query(Some(owner)),
+            HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        let listed = serde_json::to_value(listed).unwrap();
+        assert_eq!(listed["active"], serde_json::json!([]));
+        assert_eq!(listed["closed"][0]["messageCount"], 3);
+        let err = list_cases(
+            AxumState(state.clone()),
+            query(Some(Uuid::new_v4())),
+            headers.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.code, "entity_mismatch");
+
+        // Without a database only the case and its participants go
+        let Json(deleted) =
+            delete_case(AxumState(state.clone()), Ok(Path(case.id)), headers.clone())
+                .await
+                .unwrap();
+        let deleted = serde_json::to_value(deleted).unwrap();
+        assert_eq!(deleted["roomDeleted"], false);
+        assert!(!has_case(&state, case.id).unwrap());
+        let err = delete_case(AxumState(state.clone()), Ok(Path(case.id)), headers)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_enforcement() {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let state = SimpleUiServer::new(crate::SimpleUiConfig::default(), runtime);
+        let (mut record, owner) = case();
+        let viewer = Uuid::new_v4();
+        record
+            .join(TOKEN, viewer, "V".into(), CaseRole::Viewer, 1)
+            .unwrap();
+        let case_id = Uuid::new_v4();
+        update_case(&state, case_id, |_, _| Ok((record, ()))).unwrap();
+
+        let chat = |entity: Uuid| {
+            serde_json::to_vec(
+                &serde_json::json!({ "text": "hi", "roomId": case_id, "entityId": entity }),
+            )
+            .unwrap()
+        };
+        let headers = HeaderMap::new();
+        assert!(authorize_proxy(&state, "chat/stream", &headers, &chat(owner)).is_ok());
+        let err = authorize_proxy(&state, "chat/stream", &headers, &chat(viewer)).unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+
+        // Search has no entity in the body, so the header identifies the caller
+        let query =
+            serde_json::to_vec(&serde_json::json!({ "roomId": case_id, "query": "x" })).unwrap();
+        let mut viewer_headers = HeaderMap::new();
+        viewer_headers.insert(ENTITY_HEADER, viewer.to_string().parse().unwrap());
+        assert!(authorize_proxy(&state, "knowledge/query", &viewer_headers, &query).is_ok());
+        assert!(authorize_proxy(&state, "knowledge/query", &headers, &query).is_err());
+        let list = format!("knowledge/list/{}", case_id);
+        assert!(authorize_proxy(&state, &list, &viewer_headers, &[]).is_ok());
+
+        // Unregistered rooms and unrelated routes are untouched
+        let other = serde_json::to_vec(
+            &serde_json::json!({ "roomId": Uuid::new_v4(), "entityId": viewer }),
+        )
+        .unwrap();
+        assert!(authorize_proxy(&state, "chat", &headers, &other).is_ok());
+        assert!(authorize_proxy(&state, "characters", &headers, &[]).is_ok());
+    }
+}
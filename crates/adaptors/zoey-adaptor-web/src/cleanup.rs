@@ -0,0 +1,650 @@
+//! Bulk cleanup of stale rooms
+//!
+//! The generic UI opens a new room per page load, so the memory store fills
+//! up with one-message rooms that slow down room listing. This route removes
+//! them:
+//!
+//! - `POST /agent/admin/cleanup`   delete stale rooms, or only report them with `dry_run`
+//!
+//! The body is a [`CleanupPolicy`]. A room is stale when it has had no
+//! message for `older_than_days` and holds at most `max_messages` messages.
+//! Two kinds of rooms are never touched:
+//!
+//! - rooms with a registered case record (see [`crate::cases`])
+//! - rooms soft-deleted (`deletedAt` in the room metadata, epoch ms) less
+//!   than `retention_days` ago
+//!
+//! Deleting a room purges its messages, thoughts and UI context and then the
+//! room itself, `batch_size` rooms at a time. Adding
+//! `"schedule": {"interval_hours": N}` also stores the policy in the runtime
+//! settings under [`SCHEDULE_SETTING`], and the server re-runs it every N
+//! hours; `interval_hours: 0` removes the schedule.
+//!
+//! Like the other admin routes this requires `Authorization: Bearer <admin_token>`.
+
+use crate::admin;
+use crate::cases;
+use crate::error::{WebError, WebResult};
+use crate::openapi::Success;
+use crate::SimpleUiServer;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State as AxumState;
+use axum::http::HeaderMap;
+use axum::Json;
+use futures_util::future::join_all;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use zoey_core::{IDatabaseAdapter, Pagination, Room};
+
+/// Runtime setting holding the recurring cleanup
+pub(crate) const SCHEDULE_SETTING: &str = "ui:cleanupSchedule";
+
+/// Room metadata key marking a soft-deleted room (epoch ms)
+pub(crate) const SOFT_DELETED_KEY: &str = "deletedAt";
+
+/// How often the scheduler checks whether a cleanup is due
+const SCHEDULE_POLL: Duration = Duration::from_secs(60);
+
+/// Largest number of rooms deleted concurrently
+const MAX_BATCH_SIZE: usize = 500;
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+
+fn default_max_messages() -> usize {
+    1
+}
+
+fn default_batch_size() -> usize {
+    50
+}
+
+fn default_retention_days() -> u32 {
+    30
+}
+
+/// Which rooms to clean up and how
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct CleanupPolicy {
+    /// Rooms with a message newer than this many days are kept
+    #[schemars(range(min = 1))]
+    pub older_than_days: u32,
+    /// Rooms with more messages than this are kept
+    #[serde(default = "default_max_messages")]
+    pub max_messages: usize,
+    /// Report matching rooms without deleting them
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Rooms deleted concurrently per batch
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Soft-deleted rooms are kept for this many days
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+}
+
+impl CleanupPolicy {
+    fn validate(&self) -> WebResult<()> {
+        if self.older_than_days == 0 {
+            return Err(WebError::bad_request(
+                "invalid_policy",
+                "older_than_days must be at least 1",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Messages older than this (epoch ms) no longer count as activity
+    fn cutoff(&self, now_ms: i64) -> i64 {
+        now_ms.saturating_sub(i64::from(self.older_than_days) * DAY_MS)
+    }
+}
+
+/// Recurrence requested alongside a cleanup
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[schemars(rename = "CleanupScheduleRequest")]
+pub(crate) struct ScheduleBody {
+    /// Hours between runs; `0` removes the schedule
+    interval_hours: u32,
+}
+
+/// `POST /agent/admin/cleanup` body
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "CleanupRequest")]
+pub(crate) struct CleanupBody {
+    #[serde(flatten)]
+    policy: CleanupPolicy,
+    #[serde(default)]
+    schedule: Option<ScheduleBody>,
+}
+
+/// Stored recurring cleanup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub(crate) struct CleanupSchedule {
+    pub interval_hours: u32,
+    pub policy: CleanupPolicy,
+    /// When the schedule last ran (epoch ms)
+    #[serde(default)]
+    pub last_run: Option<i64>,
+}
+
+impl CleanupSchedule {
+    fn is_due(&self, now_ms: i64) -> bool {
+        match self.last_run {
+            Some(last) => now_ms.saturating_sub(last) >= i64::from(self.interval_hours) * HOUR_MS,
+            None => true,
+        }
+    }
+}
+
+/// What the selection needs to know about a room
+#[derive(Debug, Clone)]
+pub(crate) struct RoomFacts {
+    pub id: Uuid,
+    pub message_count: usize,
+    /// Newest message, or room creation when it has none (epoch ms)
+    pub last_activity: Option<i64>,
+    pub case_linked: bool,
+    /// When the room was soft-deleted (epoch ms)
+    pub deleted_at: Option<i64>,
+}
+
+/// Outcome of the staleness check for one room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Selection {
+    /// Recently active or too many messages
+    Keep,
+    /// Soft-deleted and still within retention
+    Retained,
+    /// Stale, but protected by a case record
+    CaseLinked,
+    /// Stale and safe to delete
+    Stale,
+}
+
+/// Decide whether `room` may be deleted under `policy`
+pub(crate) fn select(room: &RoomFacts, policy: &CleanupPolicy, now_ms: i64) -> Selection {
+    if let Some(deleted_at) = room.deleted_at {
+        if now_ms.saturating_sub(deleted_at) < i64::from(policy.retention_days) * DAY_MS {
+            return Selection::Retained;
+        }
+    }
+    let idle = match room.last_activity {
+        Some(ts) => ts < policy.cutoff(now_ms),
+        None => true,
+    };
+    if !idle || room.message_count > policy.max_messages {
+        Selection::Keep
+    } else if room.case_linked {
+        Selection::CaseLinked
+    } else {
+        Selection::Stale
+    }
+}
+
+/// Result of one cleanup run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub(crate) struct CleanupSummary {
+    pub scanned: usize,
+    pub matched: usize,
+    pub deleted: usize,
+    pub skipped_cases: usize,
+    /// Soft-deleted rooms kept because they are within retention
+    pub skipped_retained: usize,
+    pub dry_run: bool,
+    /// Rooms a dry run would delete
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub would_delete: Vec<Uuid>,
+}
+
+/// Select stale rooms and delete them batch by batch with `delete`
+///
+/// `delete` reports whether the room was removed; a batch's deletions run
+/// concurrently. A dry run calls nothing and lists the rooms instead.
+pub(crate) async fn run<F, Fut>(
+    rooms: &[RoomFacts],
+    policy: &CleanupPolicy,
+    now_ms: i64,
+    mut delete: F,
+) -> CleanupSummary
+where
+    F: FnMut(Uuid) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut summary = CleanupSummary {
+        scanned: rooms.len(),
+        dry_run: policy.dry_run,
+        ..Default::default()
+    };
+    let mut stale = Vec::new();
+    for room in rooms {
+        match select(room, policy, now_ms) {
+            Selection::Stale => stale.push(room.id),
+            Selection::CaseLinked => summary.skipped_cases += 1,
+            Selection::Retained => summary.skipped_retained += 1,
+            Selection::Keep => {}
+        }
+    }
+    summary.matched = stale.len();
+    if policy.dry_run {
+        summary.would_delete = stale;
+        return summary;
+    }
+
+    let batch_size = policy.batch_size.clamp(1, MAX_BATCH_SIZE);
+    let batches = stale.len().div_ceil(batch_size);
+    for (i, batch) in stale.chunks(batch_size).enumerate() {
+        let results = join_all(batch.iter().map(|&room_id| delete(room_id))).await;
+        summary.deleted += results.into_iter().filter(|&deleted| deleted).count();
+        tracing::info!(
+            batch = i + 1,
+            batches,
+            deleted = summary.deleted,
+            matched = summary.matched,
+            "Room cleanup progress"
+        );
+    }
+    summary
+}
+
+/// Room creation time in epoch ms
+///
+/// Adapters store room creation in seconds but memories in milliseconds.
+fn created_ms(created_at: i64) -> i64 {
+    if created_at < 100_000_000_000 {
+        created_at.saturating_mul(1000)
+    } else {
+        created_at
+    }
+}
+
+fn database_error(e: zoey_core::ZoeyError) -> WebError {
+    WebError::internal("database_error", e.to_string())
+}
+
+async fn room_facts(
+    state: &SimpleUiServer,
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    room: &Room,
+) -> WebResult<RoomFacts> {
+    let message_count = adapter
+        .count_room_memories(room.id, "messages")
+        .await
+        .map_err(database_error)?;
+    let newest = adapter
+        .get_memories_page(room.id, "messages", Pagination::first(1))
+        .await
+        .map_err(database_error)?;
+    let last_activity = newest
+        .memories
+        .first()
+        .map(|m| m.created_at)
+        .or(room.created_at.map(created_ms));
+    Ok(RoomFacts {
+        id: room.id,
+        message_count,
+        last_activity,
+        case_linked: cases::has_case(state, room.id)?,
+        deleted_at: room.metadata.get(SOFT_DELETED_KEY).and_then(|v| v.as_i64()),
+    })
+}
+
+/// Purge a room's memories and context, then the room itself
+async fn delete_room(
+    state: &SimpleUiServer,
+    adapter: &(dyn IDatabaseAdapter + Send + Sync),
+    room_id: Uuid,
+) -> bool {
+    // A case may have been registered since the scan
+    if cases::has_case(state, room_id).unwrap_or(true) {
+        return false;
+    }
+    admin::purge_room_memories(adapter, room_id).await;
+    if admin::clear_room_context(state, room_id).is_err() {
+        return false;
+    }
+    match adapter.delete_room(room_id).await {
+        Ok(deleted) => deleted,
+        Err(e) => {
+            tracing::warn!(room_id = %room_id, error = %e, "Failed to delete room");
+            false
+        }
+    }
+}
+
+/// Scan the agent's rooms and apply `policy`
+async fn cleanup(state: &SimpleUiServer, policy: &CleanupPolicy) -> WebResult<CleanupSummary> {
+    let (agent_id, adapter) = admin::adapter_for(state)?;
+    let rooms = adapter
+        .get_rooms_for_agent(agent_id)
+        .await
+        .map_err(database_error)?;
+    let mut facts = Vec::with_capacity(rooms.len());
+    for room in &rooms {
+        facts.push(room_facts(state, adapter.as_ref(), room).await?);
+    }
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let summary = run(&facts, policy, now_ms, move |room_id| {
+        let adapter = Arc::clone(&adapter);
+        async move { delete_room(state, adapter.as_ref(), room_id).await }
+    })
+    .await;
+    tracing::info!(
+        scanned = summary.scanned,
+        matched = summary.matched,
+        deleted = summary.deleted,
+        skipped_cases = summary.skipped_cases,
+        dry_run = summary.dry_run,
+        "Room cleanup finished"
+    );
+    Ok(summary)
+}
+
+fn load_schedule(state: &SimpleUiServer) -> WebResult<Option<CleanupSchedule>> {
+    let rt = crate::read_runtime(state)?;
+    Ok(rt
+        .get_setting(SCHEDULE_SETTING)
+        .filter(|v| !v.is_null())
+        .and_then(|v| serde_json::from_value(v).ok()))
+}
+
+fn save_schedule(state: &SimpleUiServer, schedule: Option<&CleanupSchedule>) -> WebResult<()> {
+    let value = match schedule {
+        Some(schedule) => serde_json::to_value(schedule)
+            .map_err(|e| WebError::internal("schedule_encoding", e.to_string()))?,
+        None => serde_json::Value::Null,
+    };
+    let mut rt = state.runtime.write().map_err(|_| {
+        tracing::error!("Runtime lock poisoned, cleanup schedule not saved");
+        WebError::runtime_unavailable()
+    })?;
+    rt.set_setting(SCHEDULE_SETTING, value, false);
+    Ok(())
+}
+
+/// `POST /agent/admin/cleanup` reply
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct CleanupResponse {
+    summary: CleanupSummary,
+    /// The stored schedule, if any
+    #[schemars(required)]
+    schedule: Option<CleanupSchedule>,
+}
+
+/// `POST /agent/admin/cleanup`
+pub(crate) async fn cleanup_rooms(
+    AxumState(state): AxumState<SimpleUiServer>,
+    headers: HeaderMap,
+    body: Result<Json<CleanupBody>, JsonRejection>,
+) -> WebResult<Json<Success<CleanupResponse>>> {
+    admin::authorize(&state, &headers)?;
+    let Json(body) = body?;
+    body.policy.validate()?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let schedule = match body.schedule {
+        Some(ScheduleBody { interval_hours: 0 }) => {
+            save_schedule(&state, None)?;
+            None
+        }
+        Some(ScheduleBody { interval_hours }) => {
+            let schedule = CleanupSchedule {
+                interval_hours,
+                policy: body.policy.clone(),
+                // This request is the first run
+                last_run: Some(now_ms),
+            };
+            save_schedule(&state, Some(&schedule))?;
+            Some(schedule)
+        }
+        None => load_schedule(&state)?,
+    };
+    let summary = cleanup(&state, &body.policy).await?;
+    Ok(Json(Success::new(CleanupResponse { summary, schedule })))
+}
+
+/// Run the stored schedule if it is due
+async fn run_due(state: &SimpleUiServer) {
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let schedule = match load_schedule(state) {
+        Ok(Some(schedule)) if schedule.is_due(now_ms) => schedule,
+        _ => return,
+    };
+    // Record the run first so a failing cleanup waits a full interval
+    let next = CleanupSchedule {
+        last_run: Some(now_ms),
+        ..schedule.clone()
+    };
+    if save_schedule(state, Some(&next)).is_err() {
+        return;
+    }
+    if let Err(e) = cleanup(state, &schedule.policy).await {
+        tracing::warn!(code = e.code, error = %e.message, "Scheduled room cleanup failed");
+    }
+}
+
+/// Start the background task that executes the stored schedule
+pub(crate) fn spawn_scheduler(state: SimpleUiServer) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SCHEDULE_POLL);
+        loop {
+            ticker.tick().await;
+            run_due(&state).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const NOW: i64 = 1_000 * DAY_MS;
+
+    fn policy(dry_run: bool) -> CleanupPolicy {
+        serde_json::from_value(serde_json::json!({
+            "older_than_days": 7,
+            "dry_run": dry_run,
+        }))
+        .unwrap()
+    }
+
+    fn stale_room() -> RoomFacts {
+        RoomFacts {
+            id: Uuid::new_v4(),
+            message_count: 1,
+            last_activity: Some(NOW - 30 * DAY_MS),
+            case_linked: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_selection_predicate() {
+        let policy = policy(false);
+        assert_eq!(policy.max_messages, 1);
+        assert_eq!(select(&stale_room(), &policy, NOW), Selection::Stale);
+
+        let cases = [
+            (
+                RoomFacts {
+                    last_activity: Some(NOW - DAY_MS),
+                    ..stale_room()
+                },
+                Selection::Keep,
+            ),
+            (
+                RoomFacts {
+                    message_count: 2,
+                    ..stale_room()
+                },
+                Selection::Keep,
+            ),
+            (
+                RoomFacts {
+                    message_count: 0,
+                    last_activity: None,
+                    ..stale_room()
+                },
+                Selection::Stale,
+            ),
+            (
+                RoomFacts {
+                    case_linked: true,
+                    ..stale_room()
+                },
+                Selection::CaseLinked,
+            ),
+            (
+                RoomFacts {
+                    case_linked: true,
+                    last_activity: Some(NOW - DAY_MS),
+                    ..stale_room()
+                },
+                Selection::Keep,
+            ),
+            (
+                RoomFacts {
+                    deleted_at: Some(NOW - 10 * DAY_MS),
+                    ..stale_room()
+                },
+                Selection::Retained,
+            ),
+            (
+                RoomFacts {
+                    deleted_at: Some(NOW - 31 * DAY_MS),
+                    ..stale_room()
+                },
+                Selection::Stale,
+            ),
+        ];
+        for (room, expected) in cases {
+            assert_eq!(select(&room, &policy, NOW), expected, "{:?}", room);
+        }
+
+        assert_eq!(created_ms(1_700_000_000), 1_700_000_000_000);
+        assert_eq!(created_ms(1_700_000_000_000), 1_700_000_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_without_deleting() {
+        let rooms = vec![
+            stale_room(),
+            stale_room(),
+            RoomFacts {
+                message_count: 5,
+                ..stale_room()
+            },
+        ];
+        let deleted = RefCell::new(Vec::new());
+        let delete = |room_id| {
+            deleted.borrow_mut().push(room_id);
+            async { true }
+        };
+
+        let summary = run(&rooms, &policy(true), NOW, delete).await;
+        assert!(summary.dry_run);
+        assert_eq!(
+            (summary.scanned, summary.matched, summary.deleted),
+            (3, 2, 0)
+        );
+        assert_eq!(summary.would_delete, vec![rooms[0].id, rooms[1].id]);
+        assert!(deleted.borrow().is_empty());
+
+        let summary = run(&rooms, &policy(false), NOW, delete).await;
+        assert_eq!(
+            (summary.scanned, summary.matched, summary.deleted),
+            (3, 2, 2)
+        );
+        assert!(summary.would_delete.is_empty());
+        assert_eq!(*deleted.borrow(), vec![rooms[0].id, rooms[1].id]);
+    }
+
+    #[tokio::test]
+    async fn test_deletes_in_batches() {
+        let rooms: Vec<RoomFacts> = (0..7).map(|_| stale_room()).collect();
+        let policy = CleanupPolicy {
+            batch_size: 3,
+            ..policy(false)
+        };
+        // Every deletion in a batch starts before any of them completes
+        let started = Rc::new(RefCell::new(0usize));
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let failing = rooms[4].id;
+        let summary = run(&rooms, &policy, NOW, |room_id| {
+            *started.borrow_mut() += 1;
+            let (started, seen) = (started.clone(), seen.clone());
+            async move {
+                seen.borrow_mut().push(*started.borrow());
+                room_id != failing
+            }
+        })
+        .await;
+
+        assert_eq!(summary.matched, 7);
+        assert_eq!(summary.deleted, 6);
+        let mut batches = seen.borrow().clone();
+        batches.dedup();
+        assert_eq!(batches, vec![3, 6, 7]);
+    }
+
+    #[tokio::test]
+    async fn test_case_linked_rooms_are_never_deleted() {
+        let linked = RoomFacts {
+            case_linked: true,
+            message_count: 0,
+            last_activity: None,
+            ..stale_room()
+        };
+        let retained = RoomFacts {
+            deleted_at: Some(NOW - DAY_MS),
+            ..stale_room()
+        };
+        let free = stale_room();
+        let rooms = vec![linked.clone(), retained.clone(), free.clone()];
+        let deleted = RefCell::new(Vec::new());
+
+        let summary = run(&rooms, &policy(false), NOW, |room_id| {
+            deleted.borrow_mut().push(room_id);
+            async { true }
+        })
+        .await;
+        assert_eq!(*deleted.borrow(), vec![free.id]);
+        assert_eq!(summary.skipped_cases, 1);
+        assert_eq!(summary.skipped_retained, 1);
+        assert_eq!(summary.deleted, 1);
+    }
+
+    #[test]
+    fn test_policy_and_schedule() {
+        let body: CleanupBody = serde_json::from_value(serde_json::json!({
+            "older_than_days": 0,
+            "max_messages": 3,
+            "schedule": { "interval_hours": 24 },
+        }))
+        .unwrap();
+        assert_eq!(body.policy.batch_size, 50);
+        assert_eq!(body.schedule.unwrap().interval_hours, 24);
+        assert_eq!(
+            body.policy.validate().unwrap_err().status,
+            axum::http::StatusCode::BAD_REQUEST
+        );
+
+        let schedule = CleanupSchedule {
+            interval_hours: 24,
+            policy: policy(false),
+            last_run: None,
+        };
+        assert!(schedule.is_due(NOW));
+        let ran = CleanupSchedule {
+            last_run: Some(NOW - 23 * HOUR_MS),
+            ..schedule
+        };
+        assert!(!ran.is_due(NOW));
+        assert!(ran.is_due(NOW + HOUR_MS));
+    }
+}
@@ -0,0 +1,405 @@
+//! Simple UI settings and the [`SimpleUiServer`] builder
+//!
+//! [`SimpleUiConfig`] holds every setting with its default; the builder sets
+//! them one typed method at a time and adds what is not plain configuration
+//! (a custom [`UiTemplate`]).
+
+use crate::case_store::{CaseStore, MemoryCaseStore};
+use crate::ingest::DEFAULT_INGEST_JOB_TTL;
+use crate::limits::DEFAULT_MAX_STREAMS_PER_IP;
+use crate::proxy::DEFAULT_PROXY_MAX_UPLOAD_BYTES;
+use crate::templates::UiTemplate;
+use crate::timeouts::{DEFAULT_PROXY_TIMEOUT, DEFAULT_STREAM_IDLE_TIMEOUT};
+use crate::ws_chat::DEFAULT_WS_IDLE_TIMEOUT;
+use crate::{i18n, SimpleUiServer};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use zoey_core::AgentRuntime;
+
+/// Simple UI settings
+#[derive(Clone)]
+pub struct SimpleUiConfig {
+    /// Serve the UI at all; `start` is a no-op when off
+    pub enabled: bool,
+    /// Interface to bind
+    pub host: String,
+    /// Port to bind
+    pub port: u16,
+    /// Agent API root that `/agent/*` calls are proxied to
+    pub agent_api_url: String,
+    /// Whether the UI prefers streamed replies
+    pub use_streaming: bool,
+    /// Token required on every route and handed to the page; `None` leaves
+    /// the UI open and lets the user supply an Agent API token
+    pub token: Option<String>,
+    /// Pass the UI token on to the Agent API as `Authorization: Bearer`,
+    /// for backends checking the same token; stripped from proxied requests otherwise
+    pub forward_token: bool,
+    /// Serve the scrubbed live log feed at `/logs`
+    pub logs_enabled: bool,
+    /// Bearer token required by the `/agent/admin/*` routes (disabled when `None`)
+    pub admin_token: Option<String>,
+    /// Default UI locale; overridden per request by `?lang=` or `Accept-Language`
+    pub locale: String,
+    /// Concurrent chat streams (SSE or WebSocket) allowed per client IP; 0 disables the limit
+    pub max_streams_per_ip: usize,
+    /// Extra origins allowed to open the chat WebSocket besides the UI's own host
+    pub allowed_origins: Vec<String>,
+    /// Show the model settings panel (temperature, max tokens, model override)
+    pub model_controls: bool,
+    /// Telegram bot token; enables `/webapp` links from the Telegram workspace button
+    pub telegram_bot_token: Option<String>,
+    /// Send a `Content-Security-Policy` without `unsafe-inline` scripts with the
+    /// index page; turn off when embedding pages that inject their own scripts
+    pub content_security_policy: bool,
+    /// Confirms account link codes from the chat adapters (`/agent/link/confirm`;
+    /// disabled when `None`); share the instance with those adapters
+    pub identity_links: Option<Arc<zoey_core::IdentityLinks>>,
+    /// Overall timeout for proxied Agent API requests, chat streams excepted
+    pub proxy_timeout: Duration,
+    /// Proxied chat streams are cut off after this long without any bytes
+    pub proxy_stream_idle_timeout: Duration,
+    /// Largest request body streamed through the proxy (uploads and other
+    /// calls whose body the UI does not inspect); larger ones get `413`
+    pub proxy_max_upload_bytes: usize,
+    /// Where the legal UI's cases are kept (`/agent/cases`); in memory by default
+    pub case_store: Arc<dyn CaseStore>,
+    /// Chat WebSockets are closed after this long without a frame either way
+    pub ws_idle_timeout: Duration,
+    /// Finished ingest jobs, and backend ingest jobs gone quiet, are dropped
+    /// after this long
+    pub ingest_job_ttl: Duration,
+}
+
+impl Default for SimpleUiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".into(),
+            port: 4000,
+            agent_api_url: "http://127.0.0.1:9090/agent".into(),
+            use_streaming: false,
+            token: None,
+            forward_token: false,
+            logs_enabled: false,
+            admin_token: None,
+            locale: i18n::DEFAULT_LOCALE.to_string(),
+            max_streams_per_ip: DEFAULT_MAX_STREAMS_PER_IP,
+            allowed_origins: Vec::new(),
+            model_controls: false,
+            telegram_bot_token: None,
+            content_security_policy: true,
+            identity_links: None,
+            proxy_timeout: DEFAULT_PROXY_TIMEOUT,
+            proxy_stream_idle_timeout: DEFAULT_STREAM_IDLE_TIMEOUT,
+            proxy_max_upload_bytes: DEFAULT_PROXY_MAX_UPLOAD_BYTES,
+            case_store: Arc::new(MemoryCaseStore::new()),
+            ws_idle_timeout: DEFAULT_WS_IDLE_TIMEOUT,
+            ingest_job_ttl: DEFAULT_INGEST_JOB_TTL,
+        }
+    }
+}
+
+/// Builder for [`SimpleUiServer`], started from the [`SimpleUiConfig`] defaults
+///
+/// ```ignore
+/// let ui = SimpleUiServer::builder()
+///     .port(4000)
+///     .backend("http://127.0.0.1:9090/agent")
+///     .logs(true)
+///     .build(runtime);
+/// ```
+#[derive(Default)]
+pub struct SimpleUiServerBuilder {
+    config: SimpleUiConfig,
+    template: Option<Box<dyn UiTemplate>>,
+}
+
+impl SimpleUiServerBuilder {
+    /// Builder with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the UI at all
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.config.enabled = enabled;
+        self
+    }
+
+    /// Interface to bind
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.config.host = host.into();
+        self
+    }
+
+    /// Port to bind
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port = port;
+        self
+    }
+
+    /// Agent API root to proxy `/agent/*` calls to
+    pub fn backend(mut self, url: impl Into<String>) -> Self {
+        self.config.agent_api_url = url.into();
+        self
+    }
+
+    /// Prefer streamed replies
+    pub fn streaming(mut self, enabled: bool) -> Self {
+        self.config.use_streaming = enabled;
+        self
+    }
+
+    /// Require this token on every route and hand it to the page
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.config.token = Some(token.into());
+        self
+    }
+
+    /// Pass the UI token on to the Agent API
+    pub fn forward_token(mut self, enabled: bool) -> Self {
+        self.config.forward_token = enabled;
+        self
+    }
+
+    /// Serve the live log feed at `/logs`
+    pub fn logs(mut self, enabled: bool) -> Self {
+        self.config.logs_enabled = enabled;
+        self
+    }
+
+    /// Enable the `/agent/admin/*` routes behind this bearer token
+    pub fn admin_token(mut self, token: impl Into<String>) -> Self {
+        self.config.admin_token = Some(token.into());
+        self
+    }
+
+    /// Default UI locale
+    pub fn locale(mut self, locale: impl Into<String>) -> Self {
+        self.config.locale = locale.into();
+        self
+    }
+
+    /// Concurrent chat streams per client IP; 0 disables the limit
+    pub fn max_streams_per_ip(mut self, max: usize) -> Self {
+        self.config.max_streams_per_ip = max;
+        self
+    }
+
+    /// Extra origins allowed to open `/agent/ws/chat`
+    pub fn allowed_origins<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.allowed_origins = origins.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Show the model settings panel
+    pub fn model_controls(mut self, enabled: bool) -> Self {
+        self.config.model_controls = enabled;
+        self
+    }
+
+    /// Enable `/webapp` links signed with this Telegram bot token
+    pub fn telegram_bot_token(mut self, token: impl Into<String>) -> Self {
+        self.config.telegram_bot_token = Some(token.into());
+        self
+    }
+
+    /// Send the strict `Content-Security-Policy` with the index page
+    pub fn content_security_policy(mut self, enabled: bool) -> Self {
+        self.config.content_security_policy = enabled;
+        self
+    }
+
+    /// Confirm account link codes issued by the chat adapters
+    pub fn identity_links(mut self, links: Arc<zoey_core::IdentityLinks>) -> Self {
+        self.config.identity_links = Some(links);
+        self
+    }
+
+    /// Overall timeout for proxied Agent API requests, chat streams excepted
+    pub fn proxy_timeout(mut self, timeout: Duration) -> Self {
+        self.config.proxy_timeout = timeout;
+        self
+    }
+
+    /// Idle limit for proxied chat streams
+    pub fn proxy_stream_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.proxy_stream_idle_timeout = timeout;
+        self
+    }
+
+    /// Largest request body streamed through the proxy
+    pub fn proxy_max_upload_bytes(mut self, max: usize) -> Self {
+        self.config.proxy_max_upload_bytes = max;
+        self
+    }
+
+    /// Keep cases in `store`, e.g. an [`crate::AdapterCaseStore`]
+    pub fn case_store(mut self, store: Arc<dyn CaseStore>) -> Self {
+        self.config.case_store = store;
+        self
+    }
+
+    /// Idle limit for chat WebSockets
+    pub fn ws_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.ws_idle_timeout = timeout;
+        self
+    }
+
+    /// How long ingest jobs are kept once finished or quiet
+    pub fn ingest_job_ttl(mut self, ttl: Duration) -> Self {
+        self.config.ingest_job_ttl = ttl;
+        self
+    }
+
+    /// Serve this page at `/` instead of picking one by character
+    pub fn template(mut self, template: Box<dyn UiTemplate>) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Settings so far
+    pub fn config(&self) -> &SimpleUiConfig {
+        &self.config
+    }
+
+    /// Server for `runtime`; call [`SimpleUiServer::start`] to serve it
+    pub fn build(self, runtime: Arc<RwLock<AgentRuntime>>) -> SimpleUiServer {
+        SimpleUiServer::with_template(self.config, self.template.map(Arc::from), runtime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::{IdentityLinks, MemoryStateStore};
+
+    /// Setting every field through the builder; adding a config field without
+    /// a builder method fails to compile here
+    #[test]
+    fn test_builder_covers_every_config_field() {
+        let links = Arc::new(IdentityLinks::new(Arc::new(MemoryStateStore::new())));
+        let cases: Arc<dyn CaseStore> = Arc::new(MemoryCaseStore::new());
+        let expected = SimpleUiConfig {
+            enabled: false,
+            host: "0.0.0.0".to_string(),
+            port: 4100,
+            agent_api_url: "http://backend:9090/agent".to_string(),
+            use_streaming: true,
+            token: Some("ui-token".to_string()),
+            forward_token: true,
+            logs_enabled: true,
+            admin_token: Some("admin-token".to_string()),
+            locale: "de".to_string(),
+            max_streams_per_ip: 7,
+            allowed_origins: vec!["https://app.example".to_string()],
+            model_controls: true,
+            telegram_bot_token: Some("123:abc".to_string()),
+            content_security_policy: false,
+            identity_links: Some(links.clone()),
+            proxy_timeout: Duration::from_secs(3),
+            proxy_stream_idle_timeout: Duration::from_secs(4),
+            proxy_max_upload_bytes: 1024,
+            case_store: cases.clone(),
+            ws_idle_timeout: Duration::from_secs(5),
+            ingest_job_ttl: Duration::from_secs(6),
+        };
+        let SimpleUiConfig {
+            enabled,
+            host,
+            port,
+            agent_api_url,
+            use_streaming,
+            token,
+            forward_token,
+            logs_enabled,
+            admin_token,
+            locale,
+            max_streams_per_ip,
+            allowed_origins,
+            model_controls,
+            telegram_bot_token,
+            content_security_policy,
+            identity_links,
+            proxy_timeout,
+            proxy_stream_idle_timeout,
+            proxy_max_upload_bytes,
+            case_store,
+            ws_idle_timeout,
+            ingest_job_ttl,
+        } = expected.clone();
+
+        let builder = SimpleUiServerBuilder::new()
+            .enabled(enabled)
+            .host(host)
+            .port(port)
+            .backend(agent_api_url)
+            .streaming(use_streaming)
+            .token(token.unwrap())
+            .forward_token(forward_token)
+            .logs(logs_enabled)
+            .admin_token(admin_token.unwrap())
+            .locale(locale)
+            .max_streams_per_ip(max_streams_per_ip)
+            .allowed_origins(allowed_origins)
+            .model_controls(model_controls)
+            .telegram_bot_token(telegram_bot_token.unwrap())
+            .content_security_policy(content_security_policy)
+            .identity_links(identity_links.unwrap())
+            .proxy_timeout(proxy_timeout)
+            .proxy_stream_idle_timeout(proxy_stream_idle_timeout)
+            .proxy_max_upload_bytes(proxy_max_upload_bytes)
+            .case_store(case_store)
+            .ws_idle_timeout(ws_idle_timeout)
+            .ingest_job_ttl(ingest_job_ttl);
+        let built = builder.config();
+
+        assert_eq!(built.enabled, expected.enabled);
+        assert_eq!(built.host, expected.host);
+        assert_eq!(built.port, expected.port);
+        assert_eq!(built.agent_api_url, expected.agent_api_url);
+        assert_eq!(built.use_streaming, expected.use_streaming);
+        assert_eq!(built.token, expected.token);
+        assert_eq!(built.forward_token, expected.forward_token);
+        assert_eq!(built.logs_enabled, expected.logs_enabled);
+        assert_eq!(built.admin_token, expected.admin_token);
+        assert_eq!(built.locale, expected.locale);
+        assert_eq!(built.max_streams_per_ip, expected.max_streams_per_ip);
+        assert_eq!(built.allowed_origins, expected.allowed_origins);
+        assert_eq!(built.model_controls, expected.model_controls);
+        assert_eq!(built.telegram_bot_token, expected.telegram_bot_token);
+        assert_eq!(
+            built.content_security_policy,
+            expected.content_security_policy
+        );
+        assert!(Arc::ptr_eq(built.identity_links.as_ref().unwrap(), &links));
+        assert_eq!(built.proxy_timeout, expected.proxy_timeout);
+        assert_eq!(
+            built.proxy_stream_idle_timeout,
+            expected.proxy_stream_idle_timeout
+        );
+        assert_eq!(
+            built.proxy_max_upload_bytes,
+            expected.proxy_max_upload_bytes
+        );
+        assert!(Arc::ptr_eq(&built.case_store, &cases));
+        assert_eq!(built.ws_idle_timeout, expected.ws_idle_timeout);
+        assert_eq!(built.ingest_job_ttl, expected.ingest_job_ttl);
+    }
+
+    #[test]
+    fn test_builder_starts_from_defaults() {
+        let built = SimpleUiServerBuilder::new().port(4100);
+        let defaults = SimpleUiConfig::default();
+        assert_eq!(built.config().port, 4100);
+        assert_eq!(built.config().host, defaults.host);
+        assert_eq!(built.config().agent_api_url, defaults.agent_api_url);
+        assert!(built.config().content_security_policy);
+        assert_eq!(built.config().proxy_timeout, DEFAULT_PROXY_TIMEOUT);
+    }
+}
@@ -0,0 +1,395 @@
+//! Error responses for every route served by the web adapter
+//!
+//! Handlers return [`WebError`], which always renders as
+//!
+//! ```json
+//! { "error": { "code": "room_not_found", "message": "Room not found", "request_id": "..." } }
+//! ```
+//!
+//! or, when the client prefers `text/html` (a browser navigating to the
+//! page), as a minimal styled error page carrying the same fields.
+//!
+//! [`request_context`] runs around every route: it assigns the request ID
+//! (reusing a well-formed incoming `X-Request-Id`), records it on a tracing
+//! span, echoes it on the response and renders any [`WebError`] for the
+//! request's `Accept` header. The ID is also set on the request headers so
+//! the proxy forwards it to the Agent API.
+
+use crate::templates::escape_html;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::extract::ws::rejection::WebSocketUpgradeRejection;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::Instrument;
+
+/// Header carrying the per-request ID
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest incoming request ID that is reused instead of replaced
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+pub(crate) type WebResult<T> = std::result::Result<T, WebError>;
+
+/// JSON body of every error reply
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ErrorEnvelope {
+    pub(crate) error: ErrorBody,
+}
+
+/// The `error` object of an [`ErrorEnvelope`]
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ErrorBody {
+    /// Machine-readable code, stable across releases
+    pub(crate) code: String,
+    pub(crate) message: String,
+    pub(crate) request_id: String,
+}
+
+/// An error returned by a web adapter handler
+#[derive(Debug, Clone)]
+pub(crate) struct WebError {
+    pub(crate) status: StatusCode,
+    /// Machine-readable code, stable across releases
+    pub(crate) code: &'static str,
+    /// Human-readable message, safe to show to the client
+    pub(crate) message: String,
+    /// Seconds the client should wait before retrying, sent as `Retry-After`
+    pub(crate) retry_after: Option<u64>,
+}
+
+impl WebError {
+    pub(crate) fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    /// Ask the client to retry after `secs` seconds
+    pub(crate) fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after = Some(secs);
+        self
+    }
+
+    pub(crate) fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub(crate) fn unauthorized(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, code, message)
+    }
+
+    pub(crate) fn forbidden(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, code, message)
+    }
+
+    pub(crate) fn not_found(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    pub(crate) fn internal(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, code, message)
+    }
+
+    /// The client already holds its limit of open chat streams
+    pub(crate) fn too_many_streams() -> Self {
+        Self::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "too_many_streams",
+            "Too many open chat streams",
+        )
+    }
+
+    /// The runtime lock was poisoned by a panicking writer
+    pub(crate) fn runtime_unavailable() -> Self {
+        Self::internal("runtime_unavailable", "Agent runtime is unavailable")
+    }
+
+    /// JSON envelope for this error
+    pub(crate) fn envelope(&self, request_id: &str) -> ErrorEnvelope {
+        ErrorEnvelope {
+            error: ErrorBody {
+                code: self.code.to_string(),
+                message: self.message.clone(),
+                request_id: request_id.to_string(),
+            },
+        }
+    }
+
+    fn render(&self, request_id: &str, html: bool) -> Response {
+        let mut resp = if html {
+            (self.status, Html(error_page(self, request_id))).into_response()
+        } else {
+            (self.status, Json(self.envelope(request_id))).into_response()
+        };
+        if let Some(secs) = self.retry_after {
+            resp.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        resp
+    }
+}
+
+impl std::fmt::Display for WebError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl IntoResponse for WebError {
+    /// Renders JSON without a request ID; [`request_context`] re-renders it
+    /// with the ID and the negotiated format
+    fn into_response(self) -> Response {
+        let mut resp = self.render("", false);
+        resp.extensions_mut().insert(self);
+        resp
+    }
+}
+
+// Extractor rejections, taken as `Result<Extractor, Rejection>` by handlers so
+// malformed requests get the same envelope as every other error
+
+impl From<JsonRejection> for WebError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), "invalid_body", rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for WebError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), "invalid_path", rejection.body_text())
+    }
+}
+
+impl From<WebSocketUpgradeRejection> for WebError {
+    fn from(rejection: WebSocketUpgradeRejection) -> Self {
+        Self::new(rejection.status(), "websocket_required", rejection.body_text())
+    }
+}
+
+/// ID assigned to the current request, available as a request extension
+#[derive(Debug, Clone)]
+pub(crate) struct RequestId(pub(crate) String);
+
+/// Middleware assigning request IDs and rendering [`WebError`] responses
+pub(crate) async fn request_context(mut req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+    let html = prefers_html(req.headers());
+    let header_value = HeaderValue::from_str(&request_id).expect("request IDs are header-safe");
+    req.headers_mut().insert(REQUEST_ID_HEADER, header_value.clone());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let span = tracing::info_span!(
+        "web_request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+    let mut resp = next.run(req).instrument(span.clone()).await;
+
+    if let Some(err) = resp.extensions_mut().remove::<WebError>() {
+        span.in_scope(|| {
+            if err.status.is_server_error() {
+                tracing::warn!(status = %err.status.as_u16(), code = err.code, "{}", err.message);
+            } else {
+                tracing::debug!(status = %err.status.as_u16(), code = err.code, "{}", err.message);
+            }
+        });
+        resp = err.render(&request_id, html);
+    }
+    resp.headers_mut().insert(REQUEST_ID_HEADER, header_value);
+    resp
+}
+
+/// Fallback for paths no route matches
+pub(crate) async fn not_found() -> WebError {
+    WebError::not_found("not_found", "No such page")
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Whether `Accept` ranks `text/html` above `application/json`
+///
+/// Wildcards are ignored, so `fetch()` and curl (`*/*`) get JSON while
+/// browser navigations (`text/html,...,*/*;q=0.8`) get the HTML page.
+fn prefers_html(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let quality = |media: &str| {
+        accept
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let name = parts.next()?;
+                if !name.eq_ignore_ascii_case(media) {
+                    return None;
+                }
+                let q = parts
+                    .find_map(|p| p.strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some(q)
+            })
+            .fold(0.0_f32, f32::max)
+    };
+    let html = quality("text/html");
+    html > 0.0 && html > quality("application/json")
+}
+
+fn error_page(err: &WebError, request_id: &str) -> String {
+    let reason = err.status.canonical_reason().unwrap_or("Error");
+    format!(
+        r#"<!doctype html>
+<html lang="en"><head><meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{status} {reason}</title>
+<style>
+  body {{ font-family: system-ui, sans-serif; background: #0f1115; color: #e6e6e6; display: flex; align-items: center; justify-content: center; min-height: 100vh; margin: 0; }}
+  main {{ max-width: 480px; padding: 32px; border: 1px solid #2a2f3a; border-radius: 12px; background: #161a22; }}
+  h1 {{ font-size: 20px; margin: 0 0 12px; }}
+  p {{ margin: 0 0 16px; line-height: 1.5; }}
+  code {{ color: #8b93a7; font-size: 12px; }}
+  a {{ color: #7aa2f7; }}
+</style></head>
+<body><main>
+<h1>{status} {reason}</h1>
+<p>{message}</p>
+<p><code>{code} · request {request_id}</code></p>
+<p><a href="/">Back to Zoey</a></p>
+</main></body></html>"#,
+        status = err.status.as_u16(),
+        reason = reason,
+        message = escape_html(&err.message),
+        code = err.code,
+        request_id = escape_html(request_id),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use std::net::SocketAddr;
+
+    async fn serve(router: Router) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router.layer(axum::middleware::from_fn(request_context));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    async fn failing() -> WebResult<&'static str> {
+        Err(WebError::forbidden("nope", "<b>Not allowed</b>"))
+    }
+
+    #[test]
+    fn test_prefers_html() {
+        let accept = |v: &'static str| {
+            let mut h = HeaderMap::new();
+            h.insert(header::ACCEPT, HeaderValue::from_static(v));
+            h
+        };
+        assert!(prefers_html(&accept(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+        )));
+        assert!(!prefers_html(&accept("*/*")));
+        assert!(!prefers_html(&accept("application/json, text/html;q=0.5")));
+        assert!(!prefers_html(&accept("text/html;q=0")));
+        assert!(!prefers_html(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_error_negotiates_json_and_html() {
+        let addr = serve(Router::new().route("/x", get(failing))).await;
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .get(format!("http://{}/x", addr))
+            .header("accept", "application/json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+        let request_id = resp.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "nope");
+        assert_eq!(body["error"]["message"], "<b>Not allowed</b>");
+        assert_eq!(body["error"]["request_id"], request_id.as_str());
+
+        let resp = client
+            .get(format!("http://{}/x", addr))
+            .header("accept", "text/html,*/*;q=0.8")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+        assert!(resp.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        let request_id = resp.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        let page = resp.text().await.unwrap();
+        assert!(page.contains("&lt;b&gt;Not allowed&lt;/b&gt;"));
+        assert!(page.contains(&request_id));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_propagation() {
+        let echo = |req: Request| async move {
+            let ext = req.extensions().get::<RequestId>().unwrap().0.clone();
+            let header = req.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+            format!("{} {}", ext, header)
+        };
+        let addr = serve(Router::new().route("/echo", get(echo)).fallback(not_found)).await;
+        let client = reqwest::Client::new();
+
+        // A well-formed incoming ID is kept end to end
+        let resp = client
+            .get(format!("http://{}/echo", addr))
+            .header(REQUEST_ID_HEADER, "client-abc.1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[REQUEST_ID_HEADER], "client-abc.1");
+        assert_eq!(resp.text().await.unwrap(), "client-abc.1 client-abc.1");
+
+        // Anything else is replaced with a fresh ID seen by the handler too
+        let resp = client
+            .get(format!("http://{}/echo", addr))
+            .header(REQUEST_ID_HEADER, "bad id with spaces")
+            .send()
+            .await
+            .unwrap();
+        let request_id = resp.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert_ne!(request_id, "bad id with spaces");
+        assert_eq!(resp.text().await.unwrap(), format!("{0} {0}", request_id));
+
+        let resp = client.get(format!("http://{}/missing", addr)).send().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+        let request_id = resp.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "not_found");
+        assert_eq!(body["error"]["request_id"], request_id.as_str());
+    }
+}
@@ -0,0 +1,301 @@
+//! Chat history for restoring a conversation after a page refresh
+//!
+//! The chat page keeps messages only in the DOM, so it persists its room in
+//! `localStorage` and on load asks `GET /agent/history?room_id=<id>` for the
+//! room's recent messages from the runtime's memory store. Each page holds
+//! up to `limit` messages, oldest first; `nextCursor` is passed back as
+//! `before` to fetch the messages preceding it. Rooms without messages,
+//! including ones the store has never seen, return an empty page.
+//!
+//! Agent messages are stored as the model wrote them, so their reply text is
+//! extracted with [`zoey_core::extract_response`] ([`extract_reply`]).
+
+use crate::admin::adapter_for;
+use crate::error::{WebError, WebResult};
+use crate::openapi::Success;
+use crate::SimpleUiServer;
+use axum::extract::{Query, State as AxumState};
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zoey_core::{Memory, MemoryCursor, MemoryPage, Pagination};
+
+/// Messages per page when the request names no limit
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// Largest page a history request may ask for
+const MAX_HISTORY_LIMIT: usize = 200;
+
+/// Who sent a history message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+    Agent,
+    User,
+}
+
+/// One message of a room's history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub(crate) struct HistoryMessage {
+    pub role: Role,
+    pub text: String,
+    /// Epoch milliseconds
+    pub timestamp: i64,
+}
+
+/// `GET /agent/history` query
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub(crate) struct HistoryQuery {
+    /// Room to read (required)
+    #[serde(default)]
+    #[schemars(with = "Option<Uuid>")]
+    room_id: Option<String>,
+    /// Messages per page (at most 200)
+    #[schemars(range(min = 1, max = 200))]
+    limit: Option<usize>,
+    /// `nextCursor` of the previous page, for older messages
+    before: Option<String>,
+}
+
+/// `GET /agent/history` reply
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryResponse {
+    /// Oldest first
+    messages: Vec<HistoryMessage>,
+    /// Cursor for the page before this one, `null` at the start of the room
+    #[schemars(required)]
+    next_cursor: Option<String>,
+}
+
+impl HistoryQuery {
+    fn room_id(&self) -> WebResult<Uuid> {
+        self.room_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|raw| !raw.is_empty())
+            .ok_or_else(|| WebError::bad_request("missing_room", "room_id is required"))?
+            .parse()
+            .map_err(|_| WebError::bad_request("invalid_room", "room_id must be a UUID"))
+    }
+
+    fn pagination(&self) -> WebResult<Pagination> {
+        let before = self
+            .before
+            .as_deref()
+            .map(|raw| {
+                MemoryCursor::parse(raw)
+                    .ok_or_else(|| WebError::bad_request("invalid_cursor", "Invalid page cursor"))
+            })
+            .transpose()?;
+        Ok(Pagination {
+            before,
+            limit: self
+                .limit
+                .unwrap_or(DEFAULT_HISTORY_LIMIT)
+                .clamp(1, MAX_HISTORY_LIMIT),
+        })
+    }
+}
+
+/// Reply text of a stored agent message
+///
+/// Uses the same extraction as the Discord and Telegram adaptors, so the
+/// model's thought and actions never reach the page.
+pub(crate) fn extract_reply(text: &str) -> String {
+    zoey_core::extract_response(text).text
+}
+
+/// History message for a stored memory
+fn message(memory: Memory, agent_id: Uuid) -> HistoryMessage {
+    if memory.entity_id == agent_id {
+        HistoryMessage {
+            role: Role::Agent,
+            text: extract_reply(&memory.content.text),
+            timestamp: memory.created_at,
+        }
+    } else {
+        HistoryMessage {
+            role: Role::User,
+            text: memory.content.text,
+            timestamp: memory.created_at,
+        }
+    }
+}
+
+/// A newest-first memory page as messages, oldest first, with its cursor
+fn history_page(page: MemoryPage, agent_id: Uuid) -> (Vec<HistoryMessage>, Option<String>) {
+    let next_cursor = page.next_cursor.map(|c| c.to_string());
+    let messages = page
+        .memories
+        .into_iter()
+        .rev()
+        .map(|m| message(m, agent_id))
+        .collect();
+    (messages, next_cursor)
+}
+
+/// `GET /agent/history`
+pub(crate) async fn history(
+    AxumState(state): AxumState<SimpleUiServer>,
+    Query(query): Query<HistoryQuery>,
+) -> WebResult<Json<Success<HistoryResponse>>> {
+    let room_id = query.room_id()?;
+    let pagination = query.pagination()?;
+    let (agent_id, adapter) = adapter_for(&state)?;
+    let page = adapter
+        .get_memories_page(room_id, "messages", pagination)
+        .await
+        .map_err(|e| WebError::internal("database_error", e.to_string()))?;
+    let (messages, next_cursor) = history_page(page, agent_id);
+    Ok(Json(Success::new(HistoryResponse {
+        messages,
+        next_cursor,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use zoey_core::Content;
+
+    const AGENT: Uuid = Uuid::from_u128(1);
+    const USER: Uuid = Uuid::from_u128(2);
+
+    fn memory(created_at: i64, entity_id: Uuid, text: &str) -> Memory {
+        Memory {
+            id: Uuid::from_u128(created_at as u128),
+            entity_id,
+            agent_id: AGENT,
+            room_id: Uuid::nil(),
+            content: Content {
+                text: text.to_string(),
+                ..Default::default()
+            },
+            embedding: None,
+            metadata: None,
+            created_at,
+            unique: None,
+            similarity: None,
+        }
+    }
+
+    /// Four turns: user at 10 and 30, agent at 20 and 40
+    fn room() -> Vec<Memory> {
+        vec![
+            memory(30, USER, "And tomorrow?"),
+            memory(10, USER, "Weather today?"),
+            memory(
+                40,
+                AGENT,
+                "<response><thought>check</thought><text>Rain.</text></response>",
+            ),
+            memory(20, AGENT, "Sunny."),
+        ]
+    }
+
+    fn timestamps(messages: &[HistoryMessage]) -> Vec<i64> {
+        messages.iter().map(|m| m.timestamp).collect()
+    }
+
+    #[test]
+    fn test_pages_are_oldest_first_with_cursor() {
+        let (messages, cursor) =
+            history_page(MemoryPage::paginate(room(), &Pagination::first(3)), AGENT);
+        assert_eq!(timestamps(&messages), vec![20, 30, 40]);
+        assert_eq!(messages[0].role, Role::Agent);
+        assert_eq!(messages[1].role, Role::User);
+        assert_eq!(messages[2].text, "Rain.");
+
+        let query = HistoryQuery {
+            room_id: Some(Uuid::nil().to_string()),
+            limit: Some(3),
+            before: cursor,
+        };
+        let (older, cursor) = history_page(
+            MemoryPage::paginate(room(), &query.pagination().unwrap()),
+            AGENT,
+        );
+        assert_eq!(timestamps(&older), vec![10]);
+        assert_eq!(older[0].text, "Weather today?");
+        assert!(cursor.is_none());
+    }
+
+    #[test]
+    fn test_page_boundaries() {
+        // Exactly `limit` messages fit one page with no cursor
+        let (messages, cursor) =
+            history_page(MemoryPage::paginate(room(), &Pagination::first(4)), AGENT);
+        assert_eq!(timestamps(&messages), vec![10, 20, 30, 40]);
+        assert!(cursor.is_none());
+
+        let (messages, cursor) = history_page(
+            MemoryPage::paginate(Vec::new(), &Pagination::first(4)),
+            AGENT,
+        );
+        assert!(messages.is_empty());
+        assert!(cursor.is_none());
+
+        let query = HistoryQuery {
+            limit: Some(10_000),
+            ..Default::default()
+        };
+        assert_eq!(query.pagination().unwrap().limit, MAX_HISTORY_LIMIT);
+        let query = HistoryQuery {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(query.pagination().unwrap().limit, 1);
+        let query = HistoryQuery {
+            before: Some("garbage".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.pagination().unwrap_err().status,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            HistoryQuery::default().room_id().unwrap_err().status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_extract_reply() {
+        assert_eq!(
+            extract_reply("<response><thought>t</thought><text> Hi there </text></response>"),
+            "Hi there"
+        );
+        assert_eq!(
+            extract_reply("```xml\n<reply>Fenced</reply>\n```\n<text>outside</text>"),
+            "Fenced"
+        );
+        assert_eq!(extract_reply("<text>a</text><reply>b</reply>"), "b");
+        assert_eq!(
+            extract_reply("  I'll reply once I know more.  "),
+            "I'll reply once I know more."
+        );
+    }
+
+    #[test]
+    fn test_thought_only_reply_hides_the_thought() {
+        let stored = memory(
+            50,
+            AGENT,
+            "<response><thought>User seems upset</thought><actions>REPLY</actions>\
+             Sorry about that.</response>",
+        );
+        let (messages, _) = history_page(
+            MemoryPage::paginate(vec![stored], &Pagination::first(1)),
+            AGENT,
+        );
+        assert_eq!(messages[0].text, "Sorry about that.");
+        assert!(!messages[0].text.contains("upset"));
+        assert_eq!(
+            extract_reply("<response><thought>Nothing to say</thought></response>"),
+            ""
+        );
+    }
+}
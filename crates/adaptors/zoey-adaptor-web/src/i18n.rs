@@ -0,0 +1,619 @@
+//! Locale bundles for the Simple UI templates
+//!
+//! Templates reference strings in two ways:
+//!
+//! - `{{t:key}}` in markup, substituted server-side by [`render`]
+//! - `i18n('key', {name: value})` / `i18nText(...)` in scripts, resolved in the
+//!   browser against the `I18N` object from the page config (see
+//!   [`I18N_RUNTIME_JS`])
+//!
+//! `en` is the complete fallback bundle. Lookups fall back from the selected
+//! locale to `en` and finally to the key itself, so a missing translation is
+//! visible rather than blank. Placeholders (`{name}`, `{count}`) are
+//! interpolated with HTML-escaped values.
+
+use crate::templates::escape_html;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Locale used when nothing else matches
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Locale code -> (key -> translated string)
+pub type LocaleBundles = HashMap<&'static str, HashMap<&'static str, String>>;
+
+const EN: &[(&str, &str)] = &[
+    ("locale.name", "English"),
+    // Default chat UI
+    ("app.title", "ZoeyAI Tester"),
+    ("app.brand", "Zoey Simple UI"),
+    ("character.use", "Use Character"),
+    ("character.none", "No characters found"),
+    ("character.unavailable", "API unavailable"),
+    ("session.title", "Session"),
+    ("session.room", "Room:"),
+    ("state.title", "Agent State"),
+    ("state.pending", "Compose after first reply…"),
+    ("state.ready", "Ready"),
+    ("chain.title", "Thought Chain"),
+    ("chain.empty", "No thoughts yet"),
+    ("chain.prompt", "Prompt: {text}"),
+    ("chain.prompt_plan", "Prompt plan: {plan}"),
+    ("chain.state_composed", "State composed"),
+    ("chain.add_context", "Add to Context"),
+    ("chain.remove_context", "Remove from Context"),
+    ("logs.title", "Runtime Logs"),
+    ("logs.clear", "Clear"),
+    ("logs.copy", "Copy"),
+    ("input.placeholder", "Ask Zoey anything…"),
+    ("input.send", "Send"),
+    ("chat.edit", "Edit and resend"),
+    ("chat.editing", "Editing an earlier message. Sending it replaces the replies that followed."),
+    ("thought.label", "Internal Thought"),
+    ("reflection.intent", "Intent: respond and resolve request"),
+    ("reflection.topics", "Topics: {topics}"),
+    ("reflection.strategy", "Strategy: {strategy}"),
+    ("reflection.confidence", "Confidence: {level}"),
+    ("reflection.reasoning", "Reasoning: {text}"),
+    ("reflection.follow_up", "Follow-up: ask for missing constraints if needed"),
+    ("confidence.high", "High"),
+    ("confidence.medium", "Medium"),
+    ("confidence.low", "Low"),
+    ("error.streaming", "Streaming error"),
+    ("error.empty_response", "Empty response. Please try again."),
+    ("error.connection_interrupted", "Connection interrupted. Please try again."),
+    ("error.no_response", "No response received. Please try again."),
+    ("error.request_failed", "Request failed. Please try again."),
+    ("error.generic", "Error: {error}"),
+    ("error.task_failed", "Task failed: {error}"),
+    ("error.unknown", "unknown"),
+    // Model settings panel
+    ("settings.title", "Model settings"),
+    ("settings.temperature", "Temperature"),
+    ("settings.max_tokens", "Max tokens"),
+    ("settings.model", "Model"),
+    ("settings.default", "Default"),
+    ("settings.reset", "Reset"),
+    // Legal case management UI
+    ("legal.title", "Zoey Legal Assistant - Case Management"),
+    ("legal.subtitle", "Legal Case Assistant"),
+    ("legal.new_case", "New Case"),
+    ("legal.active_cases", "Active Cases"),
+    ("legal.closed_cases", "Closed Cases"),
+    ("legal.no_active_cases", "No active cases"),
+    ("legal.no_closed_cases", "No closed cases"),
+    ("legal.status_active", "Active"),
+    ("legal.status_closed", "Closed"),
+    ("legal.documents", "Case Documents"),
+    ("legal.drop_files", "Drop files here"),
+    ("legal.no_case_selected", "No Case Selected"),
+    ("legal.share_case", "Share Case"),
+    ("legal.welcome_title", "Welcome to Zoey Legal Assistant"),
+    (
+        "legal.welcome_subtitle",
+        "Create a new case or select an existing one to start working with your AI legal assistant. All case data is isolated and secure.",
+    ),
+    ("legal.case_heading", "Case: {name}"),
+    (
+        "legal.case_intro",
+        "Start chatting with Zoey about this case. All conversations are private to this case.",
+    ),
+    ("legal.input_placeholder", "Ask Zoey about this case..."),
+    ("legal.send", "Send"),
+    ("legal.case_details", "Case Details"),
+    ("legal.participants", "Participants"),
+    ("legal.you", "You"),
+    ("legal.owner", "Owner"),
+    ("legal.collaborator", "Collaborator"),
+    ("legal.viewer", "Viewer"),
+    ("legal.remove", "Remove"),
+    ("legal.case_information", "Case Information"),
+    ("legal.created", "Created"),
+    ("legal.messages", "Messages"),
+    ("legal.status", "Status"),
+    ("legal.actions", "Actions"),
+    ("legal.close_case", "Close Case"),
+    ("legal.delete_case", "Delete Case Data"),
+    ("legal.create_case_title", "Create New Case"),
+    ("legal.case_name_placeholder", "Case name (e.g., Smith v. ACME Corp)"),
+    ("legal.matter_placeholder", "Matter number (optional)"),
+    ("legal.cancel", "Cancel"),
+    ("legal.create_case", "Create Case"),
+    ("legal.share_hint", "Share this link with others to invite them to this case:"),
+    ("legal.copy", "Copy"),
+    ("legal.close", "Close"),
+    ("legal.today", "Today"),
+    ("legal.yesterday", "Yesterday"),
+    ("legal.days_ago", "{count} days ago"),
+    ("legal.select_case_first", "Please select or create a case first"),
+    ("legal.select_case", "Please select a case first"),
+    ("legal.connection_error", "Connection error. Please try again."),
+    ("legal.enter_case_name", "Please enter a case name"),
+    ("legal.case_created", "Case created successfully"),
+    ("legal.link_copied", "Link copied to clipboard"),
+    ("legal.owner_only_close", "Only the case owner can close this case"),
+    (
+        "legal.confirm_close",
+        "Are you sure you want to close this case? It can be reopened later.",
+    ),
+    ("legal.case_closed", "Case closed"),
+    ("legal.owner_only_delete", "Only the case owner can delete case data"),
+    (
+        "legal.confirm_delete",
+        "Are you sure you want to DELETE all data for this case? This action cannot be undone.",
+    ),
+    ("legal.case_deleted", "Case data deleted"),
+    ("legal.delete_failed", "Failed to delete case data"),
+    ("legal.shared_case", "Shared Case"),
+    ("legal.telegram_case", "Telegram Chat"),
+    ("legal.joined_shared", "Joined shared case"),
+    ("legal.file_processing", "Processing..."),
+    ("legal.file_queued", "Queued..."),
+    ("legal.file_forwarding", "Chunking..."),
+    ("legal.file_progress", "Chunking {done}/{total}..."),
+    ("legal.file_chunks", "{count} chunks"),
+    ("legal.file_ingested", "Ingested"),
+    ("legal.file_error", "Error"),
+    ("legal.file_removed", "File removed"),
+    (
+        "legal.unsupported_file",
+        "Unsupported file type: .{ext}. Allowed: .txt, .md, .csv, .json, .pdf, .xlsx, .xls",
+    ),
+    ("legal.file_too_large", "File too large (max 10MB)"),
+    ("legal.ingested", "Ingested: {name}"),
+    ("legal.upload_failed", "Failed: {error}"),
+    ("legal.upload_error", "Upload error: {error}"),
+    ("legal.upload_busy", "Too many documents are processing, try again in {seconds}s"),
+    ("legal.unknown_error", "Unknown error"),
+    ("legal.connection_failed", "Connection failed"),
+    ("legal.display_name_prompt", "Your name as shown to other participants:"),
+    ("legal.viewer_read_only", "Viewers can read this case but not chat or upload"),
+    ("legal.confirm_remove", "Remove {name} from this case?"),
+    ("legal.participant_removed", "Participant removed"),
+    ("legal.remove_failed", "Failed to update participant"),
+    ("legal.join_failed", "Could not join this case: {error}"),
+    ("legal.viewing_now", "Viewing now"),
+    ("legal.viewing_one", "{name} is viewing"),
+    ("legal.viewing_two", "{first} and {second} are viewing"),
+    ("legal.viewing_many", "{name} and {count} others are viewing"),
+];
+
+const DE: &[(&str, &str)] = &[
+    ("locale.name", "Deutsch"),
+    ("app.brand", "Zoey Simple UI"),
+    ("character.use", "Charakter verwenden"),
+    ("character.none", "Keine Charaktere gefunden"),
+    ("character.unavailable", "API nicht erreichbar"),
+    ("session.title", "Sitzung"),
+    ("session.room", "Raum:"),
+    ("state.title", "Agentenzustand"),
+    ("state.pending", "Wird nach der ersten Antwort erstellt…"),
+    ("state.ready", "Bereit"),
+    ("chain.title", "Gedankenkette"),
+    ("chain.empty", "Noch keine Gedanken"),
+    ("chain.prompt", "Eingabe: {text}"),
+    ("chain.prompt_plan", "Eingabeplan: {plan}"),
+    ("chain.state_composed", "Zustand erstellt"),
+    ("chain.add_context", "Zum Kontext hinzufügen"),
+    ("chain.remove_context", "Aus Kontext entfernen"),
+    ("logs.title", "Laufzeitprotokoll"),
+    ("logs.clear", "Leeren"),
+    ("logs.copy", "Kopieren"),
+    ("input.placeholder", "Frag Zoey etwas…"),
+    ("input.send", "Senden"),
+    ("chat.edit", "Bearbeiten und erneut senden"),
+    (
+        "chat.editing",
+        "Du bearbeitest eine frühere Nachricht. Beim Senden werden die folgenden Antworten ersetzt.",
+    ),
+    ("thought.label", "Interner Gedanke"),
+    ("reflection.intent", "Absicht: Anfrage beantworten und lösen"),
+    ("reflection.topics", "Themen: {topics}"),
+    ("reflection.strategy", "Strategie: {strategy}"),
+    ("reflection.confidence", "Sicherheit: {level}"),
+    ("reflection.reasoning", "Begründung: {text}"),
+    ("reflection.follow_up", "Nachfrage: fehlende Angaben bei Bedarf erfragen"),
+    ("confidence.high", "Hoch"),
+    ("confidence.medium", "Mittel"),
+    ("confidence.low", "Niedrig"),
+    ("error.streaming", "Streaming-Fehler"),
+    ("error.empty_response", "Leere Antwort. Bitte erneut versuchen."),
+    ("error.connection_interrupted", "Verbindung unterbrochen. Bitte erneut versuchen."),
+    ("error.no_response", "Keine Antwort erhalten. Bitte erneut versuchen."),
+    ("error.request_failed", "Anfrage fehlgeschlagen. Bitte erneut versuchen."),
+    ("error.generic", "Fehler: {error}"),
+    ("error.task_failed", "Aufgabe fehlgeschlagen: {error}"),
+    ("error.unknown", "unbekannt"),
+    ("settings.title", "Modelleinstellungen"),
+    ("settings.temperature", "Temperatur"),
+    ("settings.max_tokens", "Max. Tokens"),
+    ("settings.model", "Modell"),
+    ("settings.default", "Standard"),
+    ("settings.reset", "Zurücksetzen"),
+    ("legal.title", "Zoey Rechtsassistentin - Fallverwaltung"),
+    ("legal.subtitle", "Assistentin für Rechtsfälle"),
+    ("legal.new_case", "Neuer Fall"),
+    ("legal.active_cases", "Aktive Fälle"),
+    ("legal.closed_cases", "Geschlossene Fälle"),
+    ("legal.no_active_cases", "Keine aktiven Fälle"),
+    ("legal.no_closed_cases", "Keine geschlossenen Fälle"),
+    ("legal.status_active", "Aktiv"),
+    ("legal.status_closed", "Geschlossen"),
+    ("legal.documents", "Falldokumente"),
+    ("legal.drop_files", "Dateien hier ablegen"),
+    ("legal.no_case_selected", "Kein Fall ausgewählt"),
+    ("legal.share_case", "Fall teilen"),
+    ("legal.welcome_title", "Willkommen bei der Zoey Rechtsassistentin"),
+    ("legal.case_heading", "Fall: {name}"),
+    ("legal.input_placeholder", "Frag Zoey zu diesem Fall..."),
+    ("legal.send", "Senden"),
+    ("legal.case_details", "Falldetails"),
+    ("legal.participants", "Beteiligte"),
+    ("legal.you", "Du"),
+    ("legal.owner", "Inhaber"),
+    ("legal.collaborator", "Mitarbeit"),
+    ("legal.viewer", "Lesezugriff"),
+    ("legal.remove", "Entfernen"),
+    ("legal.case_information", "Fallinformationen"),
+    ("legal.created", "Erstellt"),
+    ("legal.messages", "Nachrichten"),
+    ("legal.status", "Status"),
+    ("legal.actions", "Aktionen"),
+    ("legal.close_case", "Fall schließen"),
+    ("legal.delete_case", "Falldaten löschen"),
+    ("legal.create_case_title", "Neuen Fall anlegen"),
+    ("legal.cancel", "Abbrechen"),
+    ("legal.create_case", "Fall anlegen"),
+    ("legal.copy", "Kopieren"),
+    ("legal.close", "Schließen"),
+    ("legal.today", "Heute"),
+    ("legal.yesterday", "Gestern"),
+    ("legal.days_ago", "vor {count} Tagen"),
+    ("legal.select_case", "Bitte zuerst einen Fall auswählen"),
+    ("legal.case_created", "Fall erfolgreich angelegt"),
+    ("legal.link_copied", "Link in die Zwischenablage kopiert"),
+    ("legal.case_closed", "Fall geschlossen"),
+    ("legal.case_deleted", "Falldaten gelöscht"),
+    ("legal.file_removed", "Datei entfernt"),
+    ("legal.file_too_large", "Datei zu groß (max. 10 MB)"),
+    ("legal.viewer_read_only", "Mit Lesezugriff kannst du nicht chatten oder hochladen"),
+    ("legal.participant_removed", "Teilnehmer entfernt"),
+    ("legal.viewing_now", "Gerade aktiv"),
+    ("legal.viewing_one", "{name} sieht sich den Fall an"),
+    ("legal.viewing_two", "{first} und {second} sehen sich den Fall an"),
+    ("legal.viewing_many", "{name} und {count} weitere sehen sich den Fall an"),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("locale.name", "Español"),
+    ("app.brand", "Zoey Simple UI"),
+    ("character.use", "Usar personaje"),
+    ("character.none", "No se encontraron personajes"),
+    ("character.unavailable", "API no disponible"),
+    ("session.title", "Sesión"),
+    ("session.room", "Sala:"),
+    ("state.title", "Estado del agente"),
+    ("state.pending", "Se compone tras la primera respuesta…"),
+    ("state.ready", "Listo"),
+    ("chain.title", "Cadena de pensamiento"),
+    ("chain.empty", "Aún no hay pensamientos"),
+    ("chain.prompt", "Consulta: {text}"),
+    ("chain.prompt_plan", "Plan de consulta: {plan}"),
+    ("chain.state_composed", "Estado compuesto"),
+    ("chain.add_context", "Añadir al contexto"),
+    ("chain.remove_context", "Quitar del contexto"),
+    ("logs.title", "Registros de ejecución"),
+    ("logs.clear", "Limpiar"),
+    ("logs.copy", "Copiar"),
+    ("input.placeholder", "Pregúntale a Zoey lo que quieras…"),
+    ("input.send", "Enviar"),
+    ("chat.edit", "Editar y reenviar"),
+    (
+        "chat.editing",
+        "Estás editando un mensaje anterior. Al enviarlo se reemplazan las respuestas posteriores.",
+    ),
+    ("thought.label", "Pensamiento interno"),
+    ("reflection.intent", "Intención: responder y resolver la solicitud"),
+    ("reflection.topics", "Temas: {topics}"),
+    ("reflection.strategy", "Estrategia: {strategy}"),
+    ("reflection.confidence", "Confianza: {level}"),
+    ("reflection.reasoning", "Razonamiento: {text}"),
+    ("reflection.follow_up", "Seguimiento: pedir los datos que falten si es necesario"),
+    ("confidence.high", "Alta"),
+    ("confidence.medium", "Media"),
+    ("confidence.low", "Baja"),
+    ("error.streaming", "Error de transmisión"),
+    ("error.empty_response", "Respuesta vacía. Inténtalo de nuevo."),
+    ("error.connection_interrupted", "Conexión interrumpida. Inténtalo de nuevo."),
+    ("error.no_response", "No se recibió respuesta. Inténtalo de nuevo."),
+    ("error.request_failed", "La solicitud falló. Inténtalo de nuevo."),
+    ("error.generic", "Error: {error}"),
+    ("error.task_failed", "La tarea falló: {error}"),
+    ("error.unknown", "desconocido"),
+    ("settings.title", "Ajustes del modelo"),
+    ("settings.temperature", "Temperatura"),
+    ("settings.max_tokens", "Tokens máximos"),
+    ("settings.model", "Modelo"),
+    ("settings.default", "Predeterminado"),
+    ("settings.reset", "Restablecer"),
+    ("legal.title", "Zoey Asistente Legal - Gestión de casos"),
+    ("legal.subtitle", "Asistente de casos legales"),
+    ("legal.new_case", "Nuevo caso"),
+    ("legal.active_cases", "Casos activos"),
+    ("legal.closed_cases", "Casos cerrados"),
+    ("legal.no_active_cases", "No hay casos activos"),
+    ("legal.no_closed_cases", "No hay casos cerrados"),
+    ("legal.status_active", "Activo"),
+    ("legal.status_closed", "Cerrado"),
+    ("legal.documents", "Documentos del caso"),
+    ("legal.drop_files", "Suelta los archivos aquí"),
+    ("legal.no_case_selected", "Ningún caso seleccionado"),
+    ("legal.share_case", "Compartir caso"),
+    ("legal.welcome_title", "Bienvenido a Zoey Asistente Legal"),
+    ("legal.case_heading", "Caso: {name}"),
+    ("legal.input_placeholder", "Pregúntale a Zoey sobre este caso..."),
+    ("legal.send", "Enviar"),
+    ("legal.case_details", "Detalles del caso"),
+    ("legal.participants", "Participantes"),
+    ("legal.you", "Tú"),
+    ("legal.owner", "Propietario"),
+    ("legal.collaborator", "Colaborador"),
+    ("legal.viewer", "Lector"),
+    ("legal.remove", "Quitar"),
+    ("legal.case_information", "Información del caso"),
+    ("legal.created", "Creado"),
+    ("legal.messages", "Mensajes"),
+    ("legal.status", "Estado"),
+    ("legal.actions", "Acciones"),
+    ("legal.close_case", "Cerrar caso"),
+    ("legal.delete_case", "Eliminar datos del caso"),
+    ("legal.create_case_title", "Crear nuevo caso"),
+    ("legal.cancel", "Cancelar"),
+    ("legal.create_case", "Crear caso"),
+    ("legal.copy", "Copiar"),
+    ("legal.close", "Cerrar"),
+    ("legal.today", "Hoy"),
+    ("legal.yesterday", "Ayer"),
+    ("legal.days_ago", "hace {count} días"),
+    ("legal.select_case", "Selecciona un caso primero"),
+    ("legal.case_created", "Caso creado correctamente"),
+    ("legal.link_copied", "Enlace copiado al portapapeles"),
+    ("legal.case_closed", "Caso cerrado"),
+    ("legal.case_deleted", "Datos del caso eliminados"),
+    ("legal.file_removed", "Archivo eliminado"),
+    ("legal.file_too_large", "Archivo demasiado grande (máx. 10 MB)"),
+    ("legal.viewer_read_only", "Los lectores no pueden chatear ni subir archivos"),
+    ("legal.participant_removed", "Participante quitado"),
+    ("legal.viewing_now", "Viendo ahora"),
+    ("legal.viewing_one", "{name} está viendo el caso"),
+    ("legal.viewing_two", "{first} y {second} están viendo el caso"),
+    ("legal.viewing_many", "{name} y {count} más están viendo el caso"),
+];
+
+/// All built-in locale bundles
+pub fn bundles() -> &'static LocaleBundles {
+    static BUNDLES: OnceLock<LocaleBundles> = OnceLock::new();
+    BUNDLES.get_or_init(|| {
+        let mut bundles = LocaleBundles::new();
+        for (code, entries) in [("en", EN), ("de", DE), ("es", ES)] {
+            let bundle = entries.iter().map(|(k, v)| (*k, v.to_string())).collect();
+            bundles.insert(code, bundle);
+        }
+        bundles
+    })
+}
+
+/// Available locale codes, sorted
+pub fn available_locales() -> Vec<&'static str> {
+    let mut codes: Vec<&'static str> = bundles().keys().copied().collect();
+    codes.sort_unstable();
+    codes
+}
+
+/// Map a requested tag (e.g. "de-AT", "ES") to a supported locale code
+fn match_locale(tag: &str) -> Option<&'static str> {
+    let tag = tag.trim().to_ascii_lowercase();
+    if tag.is_empty() {
+        return None;
+    }
+    let primary = tag.split(['-', '_']).next().unwrap_or_default();
+    bundles()
+        .keys()
+        .copied()
+        .find(|code| *code == tag || *code == primary)
+}
+
+/// Pick the best supported locale from an `Accept-Language` header
+fn from_accept_language(header: &str) -> Option<&'static str> {
+    let mut candidates: Vec<(&str, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.split(';');
+            let tag = pieces.next()?.trim();
+            let q = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    // Stable sort keeps header order for equal weights
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    candidates.into_iter().find_map(|(tag, _)| match_locale(tag))
+}
+
+/// Resolve the locale for a request
+///
+/// Precedence: `?lang=` query parameter, then `Accept-Language`, then the
+/// configured locale, then [`DEFAULT_LOCALE`]. Unsupported values are skipped.
+pub fn resolve_locale(
+    query_lang: Option<&str>,
+    accept_language: Option<&str>,
+    configured: &str,
+) -> &'static str {
+    query_lang
+        .and_then(match_locale)
+        .or_else(|| accept_language.and_then(from_accept_language))
+        .or_else(|| match_locale(configured))
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Bundle for a locale with `en` filled in for missing keys
+pub fn merged_bundle(locale: &str) -> HashMap<&'static str, String> {
+    let all = bundles();
+    let mut merged = all.get(DEFAULT_LOCALE).cloned().unwrap_or_default();
+    if let Some(bundle) = all.get(locale) {
+        merged.extend(bundle.iter().map(|(k, v)| (*k, v.clone())));
+    }
+    merged
+}
+
+/// Look up a string: selected locale, then `en`, then the key itself
+pub fn lookup<'a>(locale: &str, key: &'a str) -> std::borrow::Cow<'a, str> {
+    let all = bundles();
+    all.get(locale)
+        .and_then(|b| b.get(key))
+        .or_else(|| all.get(DEFAULT_LOCALE).and_then(|b| b.get(key)))
+        .map(|s| std::borrow::Cow::Owned(s.clone()))
+        .unwrap_or(std::borrow::Cow::Borrowed(key))
+}
+
+fn placeholder_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap())
+}
+
+/// Replace `{name}` placeholders with HTML-escaped values
+///
+/// Placeholders without a matching parameter are left as-is.
+pub fn interpolate(template: &str, params: &[(&str, &str)]) -> String {
+    placeholder_re()
+        .replace_all(template, |caps: &regex::Captures| {
+            let name = &caps[1];
+            params
+                .iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| escape_html(v))
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Translate a key with interpolation
+pub fn translate(locale: &str, key: &str, params: &[(&str, &str)]) -> String {
+    interpolate(&lookup(locale, key), params)
+}
+
+fn markup_key_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{t:([A-Za-z0-9_.]+)\}\}").unwrap())
+}
+
+/// Substitute `{{t:key}}` markup placeholders with escaped translations
+pub fn render(html: &str, locale: &str) -> String {
+    markup_key_re()
+        .replace_all(html, |caps: &regex::Captures| escape_html(&lookup(locale, &caps[1])))
+        .into_owned()
+}
+
+/// Keys referenced by a template, in markup or script form
+pub fn template_keys(html: &str) -> Vec<String> {
+    static SCRIPT_RE: OnceLock<Regex> = OnceLock::new();
+    // A key ending in `.` is a prefix completed at runtime (`'legal.' + role`)
+    let script_re = SCRIPT_RE.get_or_init(|| {
+        Regex::new(r#"i18n(?:Text)?\(\s*'([A-Za-z0-9_.]*[A-Za-z0-9_])'"#).unwrap()
+    });
+    let mut keys: Vec<String> = markup_key_re()
+        .captures_iter(html)
+        .chain(script_re.captures_iter(html))
+        .map(|c| c[1].to_string())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Script functions `i18n()` and `i18nText()`, resolved against `I18N`
+///
+/// `I18N` is the [`merged_bundle`] passed in the page config. `i18n`
+/// HTML-escapes interpolated values for use with `innerHTML`; `i18nText` does
+/// not and is meant for `textContent`, `alert` and `confirm`.
+pub const I18N_RUNTIME_JS: &str = r#"
+function i18nEscape(s){ return String(s).replace(/[&<>"']/g, c => ({'&':'&amp;','<':'&lt;','>':'&gt;','"':'&quot;',"'":'&#39;'})[c]); }
+function i18nFormat(key, params, esc){
+  let s = Object.prototype.hasOwnProperty.call(I18N, key) ? I18N[key] : key;
+  if (params) { s = s.replace(/\{([A-Za-z_][A-Za-z0-9_]*)\}/g, (m, k) => Object.prototype.hasOwnProperty.call(params, k) ? (esc ? i18nEscape(params[k]) : String(params[k])) : m); }
+  return s;
+}
+function i18n(key, params){ return i18nFormat(key, params, true); }
+function i18nText(key, params){ return i18nFormat(key, params, false); }
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_fallback_chain() {
+        assert_eq!(lookup("de", "input.send"), "Senden");
+        // Missing in de: falls back to en
+        assert_eq!(
+            lookup("de", "legal.confirm_close"),
+            lookup("en", "legal.confirm_close")
+        );
+        // Missing everywhere: the key itself is shown
+        assert_eq!(lookup("de", "does.not.exist"), "does.not.exist");
+        // Unknown locale behaves like en
+        assert_eq!(lookup("fr", "input.send"), "Send");
+    }
+
+    #[test]
+    fn test_resolve_locale_precedence() {
+        // Query parameter wins over header and config
+        assert_eq!(resolve_locale(Some("es"), Some("de-DE,de;q=0.9"), "en"), "es");
+        // Header wins over config, honouring q weights
+        assert_eq!(resolve_locale(None, Some("fr;q=1.0, es;q=0.5, de;q=0.8"), "en"), "de");
+        // Unsupported query falls through to header
+        assert_eq!(resolve_locale(Some("xx"), Some("es-MX"), "de"), "es");
+        // No query or usable header: configured locale
+        assert_eq!(resolve_locale(None, Some("fr, *"), "de"), "de");
+        // Nothing usable at all
+        assert_eq!(resolve_locale(None, None, "klingon"), DEFAULT_LOCALE);
+    }
+
+    #[test]
+    fn test_interpolation_escapes_values() {
+        let out = translate("en", "legal.case_heading", &[("name", "<script>\"x\" & 'y'</script>")]);
+        assert_eq!(
+            out,
+            "Case: &lt;script&gt;&quot;x&quot; &amp; &#39;y&#39;&lt;/script&gt;"
+        );
+        // Unknown placeholders are left intact
+        assert_eq!(interpolate("{count} of {total}", &[("count", "3")]), "3 of {total}");
+    }
+
+    #[test]
+    fn test_render_markup() {
+        let html = render("<button>{{t:input.send}}</button><i>{{t:missing.key}}</i>", "es");
+        assert_eq!(html, "<button>Enviar</button><i>missing.key</i>");
+    }
+
+    #[test]
+    fn test_merged_bundle_is_complete() {
+        let bundle = merged_bundle("de");
+        assert_eq!(bundle["input.send"], "Senden");
+        assert_eq!(bundle["legal.confirm_close"], lookup("en", "legal.confirm_close"));
+        assert!(I18N_RUNTIME_JS.contains("function i18nText("));
+    }
+
+    #[test]
+    fn test_translations_only_use_known_keys() {
+        let en = &bundles()[DEFAULT_LOCALE];
+        for (code, bundle) in bundles() {
+            for key in bundle.keys() {
+                assert!(en.contains_key(key), "{} has key {} missing from en", code, key);
+            }
+        }
+    }
+}
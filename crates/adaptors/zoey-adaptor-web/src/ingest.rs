@@ -0,0 +1,654 @@
+//! Asynchronous knowledge ingestion
+//!
+//! `POST /agent/knowledge/ingest` is served locally instead of being proxied:
+//! after validating the upload it answers `202 {ingestId, status: "accepted"}`
+//! and forwards the document to the Agent API in the background. The UI polls
+//! `GET /agent/knowledge/ingest/:ingestId/status` for
+//!
+//! ```json
+//! { "status": "forwarding", "chunks_created": null, "word_count": null, "error": null }
+//! ```
+//!
+//! Progress is staged locally: `queued` → `forwarding` → `done` or `failed`.
+//! An upload sent with `"async": true` may be answered by the Agent API with
+//! a job ID instead of the result; the ingest then follows that job and
+//! records its stage and chunk counts until it finishes (see
+//! [`crate::ingest_progress`], which also streams them to the page). At most
+//! [`INGEST_QUEUE_CAPACITY`] uploads are queued or forwarding at once; further
+//! uploads get `429` with a `Retry-After` hint. Finished records are kept for
+//! [`SimpleUiConfig::ingest_job_ttl`](crate::SimpleUiConfig::ingest_job_ttl)
+//! so a slow poller still sees the outcome.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::body::{self, Bytes};
+use axum::extract::{Path, Request, State as AxumState};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Semaphore};
+
+use crate::error::{WebError, WebResult};
+use crate::{cases, ingest_progress, SimpleUiServer};
+
+/// Uploads queued or being forwarded at once
+pub(crate) const INGEST_QUEUE_CAPACITY: usize = 8;
+
+/// Uploads forwarded to the Agent API concurrently
+pub(crate) const INGEST_WORKERS: usize = 2;
+
+/// Default time a finished ingest can still be polled, see
+/// [`SimpleUiConfig::ingest_job_ttl`](crate::SimpleUiConfig::ingest_job_ttl)
+pub const DEFAULT_INGEST_JOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Record changes buffered per progress subscriber before it lags
+const INGEST_CHANGES_CAPACITY: usize = 64;
+
+/// Largest upload accepted; a 10 MB file grows by a third when base64 encoded
+const INGEST_MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Seconds a client is asked to wait when the queue is full
+const INGEST_RETRY_AFTER_SECS: u64 = 5;
+
+/// `POST /agent/knowledge/ingest` body
+///
+/// Only the document is checked here; the whole body, including the fields
+/// not read, is forwarded to the Agent API as sent.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "IngestRequest")]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IngestUpload {
+    #[schemars(length(min = 1))]
+    filename: String,
+    /// Document text, or base64 for binary files
+    #[schemars(length(min = 1))]
+    content: String,
+    #[serde(default)]
+    #[schemars(with = "Option<uuid::Uuid>")]
+    room_id: Option<String>,
+    #[serde(default)]
+    #[schemars(with = "Option<uuid::Uuid>")]
+    entity_id: Option<String>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `POST /agent/knowledge/ingest` reply
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IngestAccepted {
+    /// ID to poll the status with
+    ingest_id: String,
+    status: Accepted,
+    /// Progress feed (SSE) for the ingest
+    progress_url: String,
+}
+
+/// Status of a freshly queued ingest
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum Accepted {
+    Accepted,
+}
+
+/// Stage of an ingest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum IngestStatus {
+    /// Waiting for a free worker
+    Queued,
+    /// Sent to the Agent API, waiting for its reply
+    Forwarding,
+    Done,
+    Failed,
+}
+
+impl IngestStatus {
+    /// Name as serialized
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Forwarding => "forwarding",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Whether the ingest is over
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Failed)
+    }
+}
+
+/// What the status endpoint reports for one ingest
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub(crate) struct IngestRecord {
+    pub(crate) status: IngestStatus,
+    /// Stage the Agent API reports for its job (`extracting`, `chunking`, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) stage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) chunks_done: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) chunks_total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) document_id: Option<String>,
+    #[schemars(required)]
+    pub(crate) chunks_created: Option<u64>,
+    #[schemars(required)]
+    pub(crate) word_count: Option<u64>,
+    #[schemars(required)]
+    pub(crate) error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) warnings: Vec<String>,
+    /// When the ingest reached `done` or `failed`
+    #[serde(skip)]
+    finished_at: Option<Instant>,
+}
+
+impl IngestRecord {
+    fn queued() -> Self {
+        Self {
+            status: IngestStatus::Queued,
+            stage: None,
+            chunks_done: None,
+            chunks_total: None,
+            document_id: None,
+            chunks_created: None,
+            word_count: None,
+            error: None,
+            warnings: Vec::new(),
+            finished_at: None,
+        }
+    }
+}
+
+/// Result of a successful ingest, as reported by the Agent API
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct IngestResult {
+    pub(crate) document_id: Option<String>,
+    pub(crate) chunks_created: Option<u64>,
+    pub(crate) word_count: Option<u64>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Progress of a job the Agent API runs in the background
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct JobProgress {
+    pub(crate) stage: Option<String>,
+    pub(crate) chunks_done: Option<u64>,
+    pub(crate) chunks_total: Option<u64>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Where a forward reports the progress of its ingest
+#[derive(Clone)]
+pub(crate) struct IngestProgress {
+    id: String,
+    records: Arc<Mutex<HashMap<String, IngestRecord>>>,
+    changes: broadcast::Sender<String>,
+}
+
+impl IngestProgress {
+    /// Record the backend job's latest progress
+    pub(crate) fn report(&self, progress: JobProgress) {
+        self.update(|r| {
+            r.stage = progress.stage;
+            r.chunks_done = progress.chunks_done;
+            r.chunks_total = progress.chunks_total;
+            r.warnings = progress.warnings;
+        });
+    }
+
+    /// Apply `f` to the record and tell the subscribers
+    fn update(&self, f: impl FnOnce(&mut IngestRecord)) {
+        update(&self.records, &self.id, f);
+        // Nobody listening is fine
+        let _ = self.changes.send(self.id.clone());
+    }
+}
+
+/// Bounded queue of uploads being forwarded, with their polled status
+pub(crate) struct IngestQueue {
+    records: Arc<Mutex<HashMap<String, IngestRecord>>>,
+    /// IDs of records as they change
+    changes: broadcast::Sender<String>,
+    /// One permit per upload queued or forwarding
+    slots: Arc<Semaphore>,
+    /// One permit per upload being forwarded
+    workers: Arc<Semaphore>,
+    ttl: Duration,
+}
+
+impl Default for IngestQueue {
+    fn default() -> Self {
+        Self::new(
+            INGEST_QUEUE_CAPACITY,
+            INGEST_WORKERS,
+            DEFAULT_INGEST_JOB_TTL,
+        )
+    }
+}
+
+impl IngestQueue {
+    pub(crate) fn new(capacity: usize, workers: usize, ttl: Duration) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(HashMap::new())),
+            changes: broadcast::channel(INGEST_CHANGES_CAPACITY).0,
+            slots: Arc::new(Semaphore::new(capacity)),
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+            ttl,
+        }
+    }
+
+    /// Queue `forward` and return its ingest ID, or `None` when the queue is full
+    ///
+    /// `forward` is handed the ingest's [`IngestProgress`] for reporting a
+    /// backend job's progress.
+    pub(crate) fn submit<F, Fut>(&self, forward: F) -> Option<String>
+    where
+        F: FnOnce(IngestProgress) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<IngestResult, String>> + Send + 'static,
+    {
+        self.sweep(Instant::now());
+        let slot = self.slots.clone().try_acquire_owned().ok()?;
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        self.lock().insert(id.clone(), IngestRecord::queued());
+
+        let progress = IngestProgress {
+            id: id.clone(),
+            records: self.records.clone(),
+            changes: self.changes.clone(),
+        };
+        let workers = self.workers.clone();
+        tokio::spawn(async move {
+            let _slot = slot;
+            let Ok(_worker) = workers.acquire_owned().await else {
+                return;
+            };
+            progress.update(|r| r.status = IngestStatus::Forwarding);
+            let outcome = forward(progress.clone()).await;
+            if let Err(ref e) = outcome {
+                tracing::warn!(ingest_id = %progress.id, error = %e, "Knowledge ingest failed");
+            }
+            progress.update(|r| {
+                match outcome {
+                    Ok(result) => {
+                        r.status = IngestStatus::Done;
+                        r.document_id = result.document_id;
+                        r.chunks_created = result.chunks_created;
+                        r.word_count = result.word_count;
+                        r.warnings = result.warnings;
+                    }
+                    Err(e) => {
+                        r.status = IngestStatus::Failed;
+                        r.error = Some(e);
+                    }
+                }
+                r.finished_at = Some(Instant::now());
+            });
+        });
+        Some(id)
+    }
+
+    /// IDs of records as they change, for following an ingest's progress
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.changes.subscribe()
+    }
+
+    /// How long finished records are kept
+    pub(crate) fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Current record for `id`; `None` when unknown or expired
+    pub(crate) fn status(&self, id: &str) -> Option<IngestRecord> {
+        self.sweep(Instant::now());
+        self.lock().get(id).cloned()
+    }
+
+    /// Drop records that finished more than the TTL before `now`
+    pub(crate) fn sweep(&self, now: Instant) {
+        let ttl = self.ttl;
+        self.lock().retain(|_, record| match record.finished_at {
+            Some(finished) => now.saturating_duration_since(finished) < ttl,
+            None => true,
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, IngestRecord>> {
+        // Only plain records live behind the lock, so a poisoned map is still usable
+        self.records.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Apply `f` to the record for `id` if it has not been swept
+fn update(
+    records: &Mutex<HashMap<String, IngestRecord>>,
+    id: &str,
+    f: impl FnOnce(&mut IngestRecord),
+) {
+    let mut records = records.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(record) = records.get_mut(id) {
+        f(record);
+    }
+}
+
+/// Outcome from an Agent API `KnowledgeIngestResponse` (or error envelope)
+pub(crate) fn backend_outcome(
+    status: u16,
+    body: &serde_json::Value,
+) -> Result<IngestResult, String> {
+    if body.get("success").and_then(|v| v.as_bool()) == Some(true) {
+        let count = |key: &str| body.get(key).and_then(|v| v.as_u64());
+        return Ok(IngestResult {
+            document_id: body
+                .get("documentId")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            chunks_created: count("chunksCreated"),
+            word_count: count("wordCount"),
+            warnings: warnings(body),
+        });
+    }
+    let error = body.get("error");
+    let message = error.and_then(|e| e.as_str()).or_else(|| {
+        error
+            .and_then(|e| e.get("message"))
+            .and_then(|m| m.as_str())
+    });
+    Err(match message {
+        Some(message) => message.to_string(),
+        None => format!("Agent API returned status {}", status),
+    })
+}
+
+/// The `warnings` strings of an Agent API reply
+pub(crate) fn warnings(body: &serde_json::Value) -> Vec<String> {
+    body.get("warnings")
+        .and_then(|v| v.as_array())
+        .map(|w| {
+            w.iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// ID of the background job an upload reply points to, if it is not the result
+pub(crate) fn backend_job_id(body: &serde_json::Value) -> Option<String> {
+    if body.get("documentId").is_some() {
+        return None;
+    }
+    body.get("jobId")
+        .or_else(|| body.get("job_id"))
+        .and_then(|v| v.as_str())
+        .filter(|id| !id.trim().is_empty())
+        .map(str::to_string)
+}
+
+/// `POST /agent/knowledge/ingest`: validate, queue and answer with the ingest ID
+pub(crate) async fn submit(
+    AxumState(state): AxumState<SimpleUiServer>,
+    req: Request,
+) -> WebResult<(StatusCode, Json<IngestAccepted>)> {
+    let headers = req.headers().clone();
+    let body = body::to_bytes(req.into_body(), INGEST_MAX_BODY_BYTES)
+        .await
+        .map_err(|_| {
+            WebError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "body_too_large",
+                "Document is too large",
+            )
+        })?;
+    validate(&body)?;
+    cases::authorize_proxy(&state, "knowledge/ingest", &headers, &body)?;
+
+    let base = state.config.agent_api_url.clone();
+    let client = state.http.clone();
+    let ttl = state.ingests.ttl();
+    let ingest_id = state
+        .ingests
+        .submit(move |progress| forward(client, base, headers, body, progress, ttl))
+        .ok_or_else(|| {
+            WebError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "ingest_queue_full",
+                format!(
+                    "Too many documents are being processed, retry in {} seconds",
+                    INGEST_RETRY_AFTER_SECS
+                ),
+            )
+            .with_retry_after(INGEST_RETRY_AFTER_SECS)
+        })?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(IngestAccepted {
+            progress_url: ingest_progress::progress_url(&ingest_id),
+            ingest_id,
+            status: Accepted::Accepted,
+        }),
+    ))
+}
+
+/// `GET /agent/knowledge/ingest/:ingestId/status`
+pub(crate) async fn status(
+    AxumState(state): AxumState<SimpleUiServer>,
+    Path(ingest_id): Path<String>,
+) -> WebResult<Json<IngestRecord>> {
+    state
+        .ingests
+        .status(&ingest_id)
+        .map(Json)
+        .ok_or_else(|| WebError::not_found("ingest_not_found", "Unknown or expired ingest"))
+}
+
+/// Reject uploads the Agent API would refuse before they take a queue slot
+fn validate(body: &[u8]) -> WebResult<()> {
+    let upload: IngestUpload = serde_json::from_slice(body)
+        .map_err(|e| WebError::bad_request("invalid_body", e.to_string()))?;
+    for (key, value) in [("filename", &upload.filename), ("content", &upload.content)] {
+        if value.trim().is_empty() {
+            return Err(WebError::bad_request(
+                "invalid_body",
+                format!("`{}` is required", key),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `rb` with the caller's headers, minus the ones describing its own request
+pub(crate) fn with_caller_headers(
+    mut rb: reqwest::RequestBuilder,
+    headers: &HeaderMap,
+) -> reqwest::RequestBuilder {
+    for (k, v) in headers.iter() {
+        if k == header::HOST || k == header::CONTENT_LENGTH {
+            continue;
+        }
+        if let Ok(v) = v.to_str() {
+            rb = rb.header(k.as_str(), v);
+        }
+    }
+    rb
+}
+
+/// Send the upload to the Agent API with the caller's headers
+///
+/// A reply naming a background job is followed until the job finishes, or
+/// until it has been quiet for `quiet`.
+async fn forward(
+    client: reqwest::Client,
+    base: String,
+    headers: HeaderMap,
+    body: Bytes,
+    progress: IngestProgress,
+    quiet: Duration,
+) -> Result<IngestResult, String> {
+    let url = format!("{}/knowledge/ingest", base.trim_end_matches('/'));
+    let resp = with_caller_headers(client.post(&url), &headers)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| crate::proxy::proxy_error(e).message)?;
+    let status = resp.status().as_u16();
+    let json: serde_json::Value = resp.json().await.unwrap_or_default();
+    match backend_job_id(&json) {
+        Some(job_id) => {
+            let job = ingest_progress::BackendJob::new(client, &base, &job_id, headers);
+            job.follow(&progress, quiet).await
+        }
+        None => backend_outcome(status, &json),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    /// Poll until `id` reaches `status`
+    async fn wait_for(queue: &IngestQueue, id: &str, status: IngestStatus) -> IngestRecord {
+        for _ in 0..200 {
+            if let Some(record) = queue.status(id).filter(|r| r.status == status) {
+                return record;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("{} never reached {:?}", id, status);
+    }
+
+    /// A forward that finishes with whatever the test sends
+    async fn gate(
+        rx: oneshot::Receiver<Result<IngestResult, String>>,
+    ) -> Result<IngestResult, String> {
+        rx.await.unwrap_or_else(|_| Err("dropped".into()))
+    }
+
+    #[tokio::test]
+    async fn test_status_transitions() {
+        let queue = IngestQueue::new(4, 1, DEFAULT_INGEST_JOB_TTL);
+        let (first_tx, first) = oneshot::channel();
+        let (second_tx, second) = oneshot::channel();
+        let first_id = queue.submit(move |_| gate(first)).unwrap();
+        wait_for(&queue, &first_id, IngestStatus::Forwarding).await;
+
+        // The only worker is busy, so the next upload waits
+        let second_id = queue.submit(move |_| gate(second)).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            queue.status(&second_id).unwrap().status,
+            IngestStatus::Queued
+        );
+
+        first_tx
+            .send(Ok(IngestResult {
+                chunks_created: Some(12),
+                word_count: Some(3400),
+                ..Default::default()
+            }))
+            .unwrap();
+        let done = wait_for(&queue, &first_id, IngestStatus::Done).await;
+        assert_eq!(done.chunks_created, Some(12));
+        assert_eq!(done.word_count, Some(3400));
+        assert_eq!(done.error, None);
+
+        wait_for(&queue, &second_id, IngestStatus::Forwarding).await;
+        second_tx.send(Ok(IngestResult::default())).unwrap();
+        wait_for(&queue, &second_id, IngestStatus::Done).await;
+
+        let json = serde_json::to_value(queue.status(&first_id).unwrap()).unwrap();
+        assert_eq!(json["status"], "done");
+        assert_eq!(json["chunks_created"], 12);
+        assert!(json["error"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_uploads() {
+        let queue = IngestQueue::new(2, 1, DEFAULT_INGEST_JOB_TTL);
+        let (first_tx, first) = oneshot::channel();
+        let (_second_tx, second) = oneshot::channel();
+        let (_third_tx, third) = oneshot::channel();
+        let first_id = queue.submit(move |_| gate(first)).unwrap();
+        queue.submit(move |_| gate(second)).unwrap();
+        assert!(queue.submit(move |_| gate(third)).is_none());
+
+        // A finished upload frees its slot
+        first_tx.send(Ok(IngestResult::default())).unwrap();
+        wait_for(&queue, &first_id, IngestStatus::Done).await;
+        let (_fourth_tx, fourth) = oneshot::channel();
+        assert!(queue.submit(move |_| gate(fourth)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failure_is_reported_in_status() {
+        let queue = IngestQueue::default();
+        let body = serde_json::json!({
+            "success": false,
+            "error": "Failed to extract text from PDF: encrypted"
+        });
+        let id = queue
+            .submit(move |_| async move { backend_outcome(200, &body) })
+            .unwrap();
+        let failed = wait_for(&queue, &id, IngestStatus::Failed).await;
+        assert_eq!(
+            failed.error.as_deref(),
+            Some("Failed to extract text from PDF: encrypted")
+        );
+        assert_eq!(failed.chunks_created, None);
+    }
+
+    #[tokio::test]
+    async fn test_finished_records_expire() {
+        let ttl = Duration::from_secs(60);
+        let queue = IngestQueue::new(4, 1, ttl);
+        let done_id = queue
+            .submit(|_| async { Ok(IngestResult::default()) })
+            .unwrap();
+        wait_for(&queue, &done_id, IngestStatus::Done).await;
+        let (_pending_tx, pending) = oneshot::channel();
+        let pending_id = queue.submit(move |_| gate(pending)).unwrap();
+
+        queue.sweep(Instant::now() + ttl / 2);
+        assert!(queue.status(&done_id).is_some());
+        queue.sweep(Instant::now() + ttl);
+        assert!(queue.status(&done_id).is_none());
+        // Unfinished uploads are never swept
+        assert!(queue.status(&pending_id).is_some());
+    }
+
+    #[test]
+    fn test_backend_outcome() {
+        let ok = backend_outcome(
+            200,
+            &serde_json::json!({
+                "success": true,
+                "documentId": "doc-1",
+                "chunksCreated": 3,
+                "wordCount": 250,
+                "warnings": ["Content truncated"]
+            }),
+        )
+        .unwrap();
+        assert_eq!(ok.document_id.as_deref(), Some("doc-1"));
+        assert_eq!(ok.chunks_created, Some(3));
+        assert_eq!(ok.warnings, vec!["Content truncated".to_string()]);
+
+        let envelope = serde_json::json!({ "error": { "code": "forbidden", "message": "Viewers cannot upload" } });
+        assert_eq!(
+            backend_outcome(403, &envelope).unwrap_err(),
+            "Viewers cannot upload"
+        );
+        assert_eq!(
+            backend_outcome(502, &serde_json::Value::Null).unwrap_err(),
+            "Agent API returned status 502"
+        );
+    }
+}
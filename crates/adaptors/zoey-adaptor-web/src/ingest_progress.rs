@@ -0,0 +1,605 @@
+//! Progress of knowledge ingests, followed from the Agent API and streamed to the page
+//!
+//! When the Agent API answers an upload with `{"jobId": ...}` instead of the
+//! ingest result, [`BackendJob`] follows that job: it relays
+//! `GET <api>/knowledge/ingest/<jobId>/events` when the backend serves it as
+//! SSE, and otherwise polls `GET <api>/knowledge/ingest/<jobId>/status`.
+//! Both carry the job's progress,
+//!
+//! ```json
+//! { "stage": "chunking", "chunksDone": 40, "chunksTotal": 180, "warnings": [] }
+//! ```
+//!
+//! until the ingest response itself (`success`, `documentId`,
+//! `chunksCreated`, ...) marks the end. A job that reports nothing new for
+//! the ingest TTL is given up on.
+//!
+//! `GET /agent-local/ingest/progress?job_id=<ingestId>` streams an ingest's
+//! progress to the page as SSE: a `progress` event
+//! (`{stage, chunks_done, chunks_total, warnings}`) whenever it changes, then
+//! one `final` event (`{stage, documentId, chunksCreated, wordCount,
+//! warnings, error}`) that ends the stream. Uploads the backend ingests
+//! within the request only go through the local stages (`queued`,
+//! `forwarding`).
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Query, State as AxumState};
+use axum::http::{header, HeaderMap};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+
+use crate::error::{WebError, WebResult};
+use crate::ingest::{
+    backend_outcome, warnings, with_caller_headers, IngestProgress, IngestQueue, IngestRecord,
+    IngestResult, JobProgress,
+};
+use crate::{timeouts, SimpleUiServer};
+
+/// Route of the progress stream
+pub(crate) const PROGRESS_PATH: &str = "/agent-local/ingest/progress";
+
+/// Interval between status polls of a backend job without an event stream
+pub(crate) const JOB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Progress stream of an ingest, as handed to the page
+pub(crate) fn progress_url(ingest_id: &str) -> String {
+    format!("{}?job_id={}", PROGRESS_PATH, ingest_id)
+}
+
+/// What a backend job reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum JobUpdate {
+    /// Still running
+    Progress(JobProgress),
+    /// Over, with the ingest's outcome
+    Finished(Result<IngestResult, String>),
+}
+
+/// Read a job status or event sent with HTTP `status`
+///
+/// The ingest response (it has `success`), an error, or a failed request
+/// ends the job; anything else is progress.
+pub(crate) fn job_update(status: u16, body: &Value) -> JobUpdate {
+    let failed = body.get("error").is_some_and(|e| !e.is_null());
+    if body.get("success").is_some() || failed || !(200..300).contains(&status) {
+        return JobUpdate::Finished(backend_outcome(status, body));
+    }
+    let count = |camel: &str, snake: &str| {
+        body.get(camel)
+            .or_else(|| body.get(snake))
+            .and_then(|v| v.as_u64())
+    };
+    JobUpdate::Progress(JobProgress {
+        stage: body
+            .get("stage")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        chunks_done: count("chunksDone", "chunks_done"),
+        chunks_total: count("chunksTotal", "chunks_total"),
+        warnings: warnings(body),
+    })
+}
+
+/// Incremental SSE reader yielding each event's JSON `data`
+#[derive(Debug, Default)]
+struct SseData {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseData {
+    /// Feed raw bytes, returning the data of every event completed by them
+    fn push(&mut self, bytes: &[u8]) -> Vec<Value> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                let data = std::mem::take(&mut self.data).join("\n");
+                // Comments and keep-alives carry no data
+                if let Ok(value) = serde_json::from_str(&data) {
+                    events.push(value);
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data.push(data.trim_start().to_string());
+            }
+        }
+        events
+    }
+}
+
+fn went_quiet(quiet: Duration) -> String {
+    format!(
+        "The Agent API reported no ingest progress for {} seconds",
+        quiet.as_secs()
+    )
+}
+
+/// A knowledge ingest job running in the Agent API
+pub(crate) struct BackendJob {
+    client: reqwest::Client,
+    /// `<api>/knowledge/ingest/<jobId>`
+    url: String,
+    /// The uploader's headers, for the backend's auth
+    headers: HeaderMap,
+    poll_interval: Duration,
+}
+
+impl BackendJob {
+    pub(crate) fn new(
+        client: reqwest::Client,
+        base: &str,
+        job_id: &str,
+        headers: HeaderMap,
+    ) -> Self {
+        Self {
+            client,
+            url: format!("{}/knowledge/ingest/{}", base.trim_end_matches('/'), job_id),
+            headers,
+            poll_interval: JOB_POLL_INTERVAL,
+        }
+    }
+
+    /// Report the job's progress until it finishes, returning its outcome
+    pub(crate) async fn follow(
+        &self,
+        progress: &IngestProgress,
+        quiet: Duration,
+    ) -> Result<IngestResult, String> {
+        match self.relay(progress, quiet).await {
+            Some(outcome) => outcome,
+            None => self.poll(progress, quiet).await,
+        }
+    }
+
+    /// Relay the job's event stream; `None` when there is none or it ends early
+    async fn relay(
+        &self,
+        progress: &IngestProgress,
+        quiet: Duration,
+    ) -> Option<Result<IngestResult, String>> {
+        let resp = with_caller_headers(
+            self.client.get(format!("{}/events", self.url)),
+            &self.headers,
+        )
+        .header(header::ACCEPT.as_str(), "text/event-stream")
+        .send()
+        .await
+        .ok()?;
+        let is_sse = resp
+            .headers()
+            .get(header::CONTENT_TYPE.as_str())
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !resp.status().is_success() || !is_sse {
+            return None;
+        }
+        let mut reader = SseData::default();
+        let mut chunks = timeouts::until_idle(resp.bytes_stream(), quiet);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = match chunk {
+                Ok(Ok(chunk)) => chunk,
+                // Connection lost: the status endpoint still has the outcome
+                Ok(Err(_)) => return None,
+                Err(()) => return Some(Err(went_quiet(quiet))),
+            };
+            for data in reader.push(&chunk) {
+                match job_update(200, &data) {
+                    JobUpdate::Progress(update) => progress.report(update),
+                    JobUpdate::Finished(outcome) => return Some(outcome),
+                }
+            }
+        }
+        None
+    }
+
+    /// Poll the job's status until it finishes or goes quiet
+    async fn poll(
+        &self,
+        progress: &IngestProgress,
+        quiet: Duration,
+    ) -> Result<IngestResult, String> {
+        let url = format!("{}/status", self.url);
+        let mut last = None;
+        let mut last_change = Instant::now();
+        loop {
+            tokio::time::sleep(self.poll_interval).await;
+            if last_change.elapsed() >= quiet {
+                return Err(went_quiet(quiet));
+            }
+            let resp = match with_caller_headers(self.client.get(&url), &self.headers)
+                .timeout(quiet)
+                .send()
+                .await
+            {
+                Ok(resp) => resp,
+                Err(e) => {
+                    tracing::debug!(error = %e.without_url(), "Ingest job status poll failed");
+                    continue;
+                }
+            };
+            let status = resp.status();
+            if status == reqwest::StatusCode::NOT_FOUND {
+                return Err("The Agent API no longer knows the ingest job".to_string());
+            }
+            // Server errors are retried until the job goes quiet
+            if status.is_server_error() {
+                continue;
+            }
+            let json: Value = resp.json().await.unwrap_or_default();
+            match job_update(status.as_u16(), &json) {
+                JobUpdate::Progress(update) => {
+                    if last.as_ref() != Some(&update) {
+                        last = Some(update.clone());
+                        last_change = Instant::now();
+                        progress.report(update);
+                    }
+                }
+                JobUpdate::Finished(outcome) => return outcome,
+            }
+        }
+    }
+}
+
+/// `progress` event data of an ingest still running
+fn progress_data(record: &IngestRecord) -> Value {
+    serde_json::json!({
+        "stage": record.stage.as_deref().unwrap_or(record.status.as_str()),
+        "chunks_done": record.chunks_done,
+        "chunks_total": record.chunks_total,
+        "warnings": record.warnings,
+    })
+}
+
+/// `final` event data of a finished ingest, or of one that expired
+fn final_data(record: Option<&IngestRecord>) -> Value {
+    match record {
+        Some(record) => serde_json::json!({
+            "stage": record.status.as_str(),
+            "documentId": record.document_id,
+            "chunksCreated": record.chunks_created,
+            "wordCount": record.word_count,
+            "warnings": record.warnings,
+            "error": record.error,
+        }),
+        None => serde_json::json!({
+            "stage": "failed",
+            "documentId": null,
+            "chunksCreated": null,
+            "wordCount": null,
+            "warnings": [],
+            "error": "Unknown or expired ingest",
+        }),
+    }
+}
+
+/// State of one progress stream
+struct Feed {
+    queue: Arc<IngestQueue>,
+    id: String,
+    changes: broadcast::Receiver<String>,
+    last: Option<Value>,
+    done: bool,
+}
+
+/// `(event, data)` pairs for ingest `id`: progress as it changes, then `final`
+///
+/// `changes` must be subscribed before the record is first read, so no
+/// change in between is missed.
+pub(crate) fn job_events(
+    queue: Arc<IngestQueue>,
+    id: String,
+    changes: broadcast::Receiver<String>,
+) -> impl Stream<Item = (&'static str, Value)> + Send + 'static {
+    let feed = Feed {
+        queue,
+        id,
+        changes,
+        last: None,
+        done: false,
+    };
+    stream::unfold(feed, |mut feed| async move {
+        if feed.done {
+            return None;
+        }
+        loop {
+            match feed.queue.status(&feed.id) {
+                Some(record) if !record.status.is_finished() => {
+                    let data = progress_data(&record);
+                    if feed.last.as_ref() != Some(&data) {
+                        feed.last = Some(data.clone());
+                        return Some((("progress", data), feed));
+                    }
+                }
+                record => {
+                    feed.done = true;
+                    return Some((("final", final_data(record.as_ref())), feed));
+                }
+            }
+            loop {
+                match feed.changes.recv().await {
+                    Ok(changed) if changed == feed.id => break,
+                    Ok(_) => continue,
+                    // Missed changes: read the record again
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    })
+}
+
+/// `GET /agent-local/ingest/progress` query
+#[derive(Debug, Deserialize)]
+pub(crate) struct ProgressQuery {
+    job_id: Option<String>,
+}
+
+/// `GET /agent-local/ingest/progress?job_id=<ingestId>`
+pub(crate) async fn progress(
+    AxumState(state): AxumState<SimpleUiServer>,
+    Query(query): Query<ProgressQuery>,
+) -> WebResult<Sse<BoxStream<'static, Result<Event, Infallible>>>> {
+    let id = query
+        .job_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| WebError::bad_request("missing_job", "job_id is required"))?;
+    let changes = state.ingests.subscribe();
+    if state.ingests.status(&id).is_none() {
+        return Err(WebError::not_found(
+            "ingest_not_found",
+            "Unknown or expired ingest",
+        ));
+    }
+    let stream = job_events(state.ingests.clone(), id, changes)
+        .map(|(event, data)| Ok(Event::default().event(event).data(data.to_string())))
+        .boxed();
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::DEFAULT_INGEST_JOB_TTL;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::sync::{mpsc, oneshot};
+
+    fn progress(stage: &str, done: u64, total: u64) -> JobProgress {
+        JobProgress {
+            stage: Some(stage.to_string()),
+            chunks_done: Some(done),
+            chunks_total: Some(total),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_job_update() {
+        assert_eq!(
+            job_update(
+                200,
+                &serde_json::json!({ "stage": "chunking", "chunksDone": 4, "chunks_total": 9 })
+            ),
+            JobUpdate::Progress(progress("chunking", 4, 9))
+        );
+        assert_eq!(
+            job_update(
+                200,
+                &serde_json::json!({ "success": true, "documentId": "doc-1", "chunksCreated": 9 })
+            ),
+            JobUpdate::Finished(Ok(IngestResult {
+                document_id: Some("doc-1".to_string()),
+                chunks_created: Some(9),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            job_update(200, &serde_json::json!({ "error": "PDF is encrypted" })),
+            JobUpdate::Finished(Err("PDF is encrypted".to_string()))
+        );
+        assert!(matches!(
+            job_update(401, &Value::Null),
+            JobUpdate::Finished(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_sse_data_across_chunks() {
+        let mut reader = SseData::default();
+        assert!(reader
+            .push(b": keep-alive\n\nevent: progress\ndata: {\"stage\"")
+            .is_empty());
+        let events = reader.push(b": \"chunking\"}\n\ndata: {\"success\": true}\r\n\r\n");
+        assert_eq!(
+            events,
+            vec![
+                serde_json::json!({ "stage": "chunking" }),
+                serde_json::json!({ "success": true })
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_follow_progress_then_end_with_final() {
+        let queue = Arc::new(IngestQueue::default());
+        let (handle_tx, handle_rx) = oneshot::channel();
+        let (finish_tx, finish_rx) = oneshot::channel();
+        let id = queue
+            .submit(move |handle: IngestProgress| async move {
+                handle.report(JobProgress {
+                    stage: Some("extracting".to_string()),
+                    ..Default::default()
+                });
+                let _ = handle_tx.send(handle);
+                finish_rx.await.unwrap_or_else(|_| Err("dropped".into()))
+            })
+            .unwrap();
+        let handle = handle_rx.await.unwrap();
+
+        let events = job_events(queue.clone(), id.clone(), queue.subscribe());
+        let mut events = Box::pin(events);
+        let (event, data) = events.next().await.unwrap();
+        assert_eq!(event, "progress");
+        assert_eq!(data["stage"], "extracting");
+
+        handle.report(progress("chunking", 40, 180));
+        let (event, data) = events.next().await.unwrap();
+        assert_eq!(event, "progress");
+        assert_eq!(data["chunks_done"], 40);
+        assert_eq!(data["chunks_total"], 180);
+
+        finish_tx
+            .send(Ok(IngestResult {
+                document_id: Some("doc-7".to_string()),
+                chunks_created: Some(180),
+                warnings: vec!["Some PII patterns were automatically redacted".to_string()],
+                ..Default::default()
+            }))
+            .unwrap();
+        let (event, data) = events.next().await.unwrap();
+        assert_eq!(event, "final");
+        assert_eq!(data["stage"], "done");
+        assert_eq!(data["documentId"], "doc-7");
+        assert_eq!(data["chunksCreated"], 180);
+        assert_eq!(
+            data["warnings"][0],
+            "Some PII patterns were automatically redacted"
+        );
+        assert!(events.next().await.is_none());
+
+        // An expired ingest ends at once
+        let mut events = Box::pin(job_events(queue.clone(), "gone".into(), queue.subscribe()));
+        let (event, data) = events.next().await.unwrap();
+        assert_eq!(event, "final");
+        assert_eq!(data["stage"], "failed");
+        assert!(events.next().await.is_none());
+    }
+
+    /// Agent API stub for job `job-1`: `/events` relays what the test sends
+    /// when `sse` is set and is missing otherwise; `/status` walks through
+    /// `statuses`, repeating the last one
+    async fn job_backend(sse: Option<mpsc::Receiver<String>>, statuses: Vec<Value>) -> String {
+        let sse = Arc::new(tokio::sync::Mutex::new(sse));
+        let statuses = Arc::new(std::sync::Mutex::new(statuses));
+        let events = move || async move {
+            match sse.lock().await.take() {
+                Some(rx) => axum::response::Response::builder()
+                    .header("content-type", "text/event-stream")
+                    .body(Body::from_stream(
+                        tokio_stream::wrappers::ReceiverStream::new(rx).map(Ok::<_, Infallible>),
+                    ))
+                    .unwrap(),
+                None => axum::response::Response::builder()
+                    .status(404)
+                    .body(Body::empty())
+                    .unwrap(),
+            }
+        };
+        let status = move || async move {
+            let mut statuses = statuses.lock().unwrap();
+            let next = if statuses.len() > 1 {
+                statuses.remove(0)
+            } else {
+                statuses[0].clone()
+            };
+            axum::Json(next)
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/agent/knowledge/ingest/job-1/events", get(events))
+            .route("/agent/knowledge/ingest/job-1/status", get(status));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}/agent", addr)
+    }
+
+    /// Follow `job-1` of `base` as an ingest of `queue`, returning its ID
+    fn follow(queue: &IngestQueue, base: String, quiet: Duration) -> String {
+        queue
+            .submit(move |progress: IngestProgress| async move {
+                let mut job =
+                    BackendJob::new(reqwest::Client::new(), &base, "job-1", HeaderMap::new());
+                job.poll_interval = Duration::from_millis(10);
+                job.follow(&progress, quiet).await
+            })
+            .unwrap()
+    }
+
+    async fn finished(queue: &IngestQueue, id: &str) -> IngestRecord {
+        for _ in 0..300 {
+            if let Some(record) = queue.status(id).filter(|r| r.status.is_finished()) {
+                return record;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{} never finished", id);
+    }
+
+    #[tokio::test]
+    async fn test_backend_job_is_relayed_from_its_event_stream() {
+        let (tx, rx) = mpsc::channel(8);
+        let base = job_backend(Some(rx), vec![Value::Null]).await;
+        let queue = Arc::new(IngestQueue::default());
+        let id = follow(&queue, base, DEFAULT_INGEST_JOB_TTL);
+        let mut events = Box::pin(job_events(queue.clone(), id.clone(), queue.subscribe()));
+
+        tx.send(
+            "data: {\"stage\": \"chunking\", \"chunksDone\": 2, \"chunksTotal\": 5}\n\n".into(),
+        )
+        .await
+        .unwrap();
+        loop {
+            let (event, data) = events.next().await.unwrap();
+            assert_eq!(event, "progress");
+            if data["stage"] == "chunking" {
+                assert_eq!(data["chunks_done"], 2);
+                break;
+            }
+        }
+        tx.send(
+            "data: {\"success\": true, \"documentId\": \"doc-2\", \"chunksCreated\": 5}\n\n".into(),
+        )
+        .await
+        .unwrap();
+        let (event, data) = events.next().await.unwrap();
+        assert_eq!(event, "final");
+        assert_eq!(data["documentId"], "doc-2");
+        assert_eq!(finished(&queue, &id).await.chunks_created, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_backend_job_is_polled_without_event_stream() {
+        let base = job_backend(
+            None,
+            vec![
+                serde_json::json!({ "stage": "extracting" }),
+                serde_json::json!({ "stage": "chunking", "chunks_done": 3, "chunks_total": 3 }),
+                serde_json::json!({ "success": true, "documentId": "doc-3", "chunksCreated": 3 }),
+            ],
+        )
+        .await;
+        let queue = IngestQueue::default();
+        let id = follow(&queue, base, DEFAULT_INGEST_JOB_TTL);
+        let record = finished(&queue, &id).await;
+        assert_eq!(record.document_id.as_deref(), Some("doc-3"));
+        assert_eq!(record.chunks_done, Some(3));
+
+        // A job stuck on the same status is given up on
+        let base = job_backend(None, vec![serde_json::json!({ "stage": "chunking" })]).await;
+        let id = follow(&queue, base, Duration::from_millis(100));
+        let record = finished(&queue, &id).await;
+        assert!(record.error.unwrap().contains("no ingest progress"));
+    }
+}
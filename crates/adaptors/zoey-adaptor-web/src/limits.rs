@@ -0,0 +1,99 @@
+//! Per-IP limits on concurrent chat streams
+//!
+//! The proxied `/agent/chat/stream` holds a [`StreamPermit`] for as long as
+//! the stream is open and the `/agent/ws/chat` WebSocket holds one per
+//! in-flight room reply, so a single client cannot tie up the backend with an
+//! unbounded number of generations.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use axum::extract::ConnectInfo;
+
+/// Default number of concurrent chat streams allowed per client IP
+pub const DEFAULT_MAX_STREAMS_PER_IP: usize = 4;
+
+/// Tracks open chat streams per client IP
+pub(crate) struct StreamLimiter {
+    /// Maximum concurrent streams per IP (0 disables the limit)
+    max_per_ip: usize,
+    active: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl StreamLimiter {
+    pub(crate) fn new(max_per_ip: usize) -> Self {
+        Self {
+            max_per_ip,
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Reserve a stream slot for `ip`, or `None` when the IP is at its limit
+    pub(crate) fn acquire(&self, ip: IpAddr) -> Option<StreamPermit> {
+        // Only counters live behind the lock, so a poisoned map is still usable
+        let mut active = self.active.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = active.entry(ip).or_insert(0);
+        if self.max_per_ip > 0 && *count >= self.max_per_ip {
+            return None;
+        }
+        *count += 1;
+        Some(StreamPermit {
+            ip,
+            active: self.active.clone(),
+        })
+    }
+}
+
+/// An open stream slot, released when dropped
+pub(crate) struct StreamPermit {
+    ip: IpAddr,
+    active: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let mut active = self.active.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = active.get_mut(&self.ip) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                active.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Client IP from connection info, falling back to a shared bucket when unknown
+pub(crate) fn client_ip(connect_info: Option<ConnectInfo<SocketAddr>>) -> IpAddr {
+    connect_info
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_per_ip() {
+        let limiter = StreamLimiter::new(2);
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let first = limiter.acquire(a).unwrap();
+        let _second = limiter.acquire(a).unwrap();
+        assert!(limiter.acquire(a).is_none());
+        assert!(limiter.acquire(b).is_some());
+
+        drop(first);
+        assert!(limiter.acquire(a).is_some());
+    }
+
+    #[test]
+    fn test_zero_disables_limit() {
+        let limiter = StreamLimiter::new(0);
+        let ip = client_ip(None);
+        let permits: Vec<_> = (0..16).filter_map(|_| limiter.acquire(ip)).collect();
+        assert_eq!(permits.len(), 16);
+    }
+}
@@ -0,0 +1,160 @@
+//! Confirming account link codes
+//!
+//! `POST /agent/link/confirm` takes `{"code": "..."}` issued by a chat
+//! adapter's `/link` command (Telegram, Discord) and links that account to
+//! the caller, identified by the `X-Entity-Id` header. From then on the chat
+//! adapter attributes the account's messages to the caller's entity, so the
+//! conversations share memory. Served locally (not proxied) and only when
+//! `SimpleUiConfig::identity_links` is set.
+
+use crate::cases::caller;
+use crate::error::{WebError, WebResult};
+use crate::openapi::Success;
+use crate::SimpleUiServer;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State as AxumState;
+use axum::http::HeaderMap;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zoey_core::{redact_id, ZoeyError};
+
+/// `POST /agent/link/confirm` body
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "LinkConfirmRequest")]
+pub(crate) struct ConfirmBody {
+    /// Code shown by the chat adapter's `/link` command
+    code: String,
+}
+
+/// `POST /agent/link/confirm` reply
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LinkConfirmResponse {
+    /// Adapter the account belongs to, e.g. `telegram`
+    platform: String,
+    /// The platform user ID, redacted
+    user_id: String,
+    entity_id: Uuid,
+}
+
+/// `POST /agent/link/confirm`
+pub(crate) async fn confirm(
+    AxumState(state): AxumState<SimpleUiServer>,
+    headers: HeaderMap,
+    body: Result<Json<ConfirmBody>, JsonRejection>,
+) -> WebResult<Json<Success<LinkConfirmResponse>>> {
+    let Some(links) = state.config.identity_links.as_ref() else {
+        return Err(WebError::not_found(
+            "linking_disabled",
+            "Account linking is not enabled",
+        ));
+    };
+    let entity_id = caller(&headers)?;
+    let Json(body) = body?;
+    let linked = links
+        .confirm(&body.code, entity_id)
+        .await
+        .map_err(|e| match e {
+            ZoeyError::NotFound(msg) => WebError::not_found("invalid_link_code", msg),
+            ZoeyError::Validation(msg) => WebError::bad_request("link_to_self", msg),
+            e => {
+                tracing::warn!(error = %e, "Failed to confirm link code");
+                WebError::internal("link_failed", "Could not link the account")
+            }
+        })?;
+    tracing::info!(
+        platform = %linked.account.platform,
+        entity_id = %linked.entity_id,
+        "Account linked"
+    );
+    Ok(Json(Success::new(LinkConfirmResponse {
+        user_id: redact_id(&linked.account.user_id),
+        platform: linked.account.platform,
+        entity_id: linked.entity_id,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SimpleUiConfig, SimpleUiServer};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use zoey_core::{IdentityLinks, MemoryStateStore, PlatformAccount};
+
+    async fn ui(links: Option<Arc<IdentityLinks>>) -> SocketAddr {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let server = SimpleUiServer::new(
+            SimpleUiConfig {
+                identity_links: links,
+                ..Default::default()
+            },
+            runtime,
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = server
+            .router()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    async fn confirm(addr: SocketAddr, entity: Uuid, code: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/agent/link/confirm", addr))
+            .header("X-Entity-Id", entity.to_string())
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_confirm_accepts_codes_from_every_platform() {
+        let links = Arc::new(IdentityLinks::new(Arc::new(MemoryStateStore::new())));
+        let addr = ui(Some(links.clone())).await;
+        let web = Uuid::new_v4();
+
+        for account in [
+            PlatformAccount::new("telegram", 123456789u64),
+            PlatformAccount::new("discord", 555000111222u64),
+        ] {
+            let code = links.issue_code(&account).await.unwrap();
+            let resp = confirm(addr, web, &code).await;
+            assert_eq!(resp.status(), reqwest::StatusCode::OK);
+            let body: serde_json::Value = resp.json().await.unwrap();
+            assert_eq!(body["platform"], account.platform);
+            assert_eq!(body["entityId"], web.to_string());
+            assert!(!body["userId"].as_str().unwrap().contains(&account.user_id));
+            assert_eq!(links.resolve(account.entity_id()).await, web);
+
+            // Spent
+            let again = confirm(addr, web, &code).await;
+            assert_eq!(again.status(), reqwest::StatusCode::NOT_FOUND);
+            let body: serde_json::Value = again.json().await.unwrap();
+            assert_eq!(body["error"]["code"], "invalid_link_code");
+        }
+
+        let resp = reqwest::Client::new()
+            .post(format!("http://{}/agent/link/confirm", addr))
+            .json(&serde_json::json!({ "code": "ABCDEFGH" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_without_linking_is_not_found() {
+        let addr = ui(None).await;
+        let resp = confirm(addr, Uuid::new_v4(), "ABCDEFGH").await;
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "linking_disabled");
+    }
+}
@@ -0,0 +1,229 @@
+//! Live log feed for the UI (`GET /logs`, when `logs_enabled`)
+//!
+//! Log events from the core logger are relayed as SSE `data:` frames. Messages
+//! pass through [`scrub_message`] first, since the page may be open on a
+//! shared screen.
+//!
+//! - `?level=warn` only relays events at that level or above
+//! - `?tail=100` replays up to that many recent events before the live ones
+//!
+//! A heartbeat comment every [`LOG_HEARTBEAT_INTERVAL`] keeps proxies from
+//! closing idle feeds.
+
+use crate::error::{WebError, WebResult};
+use axum::extract::Query;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use regex::Regex;
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tracing::Level;
+use zoey_core::utils::logger::{subscribe_logs_with_backlog, LogEvent, LOG_BACKLOG_CAPACITY};
+
+/// Longest message relayed, in characters
+pub const MAX_LOG_MESSAGE_CHARS: usize = 2000;
+
+/// Interval of the keep-alive comment on an idle feed
+pub const LOG_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Patterns redacted from relayed messages and their replacements
+fn redactions() -> &'static [(Regex, &'static str)] {
+    static REDACTIONS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    REDACTIONS.get_or_init(|| {
+        vec![
+            (Regex::new(r"sk-[A-Za-z0-9]{20,}").unwrap(), "sk-REDACTED"),
+            (
+                Regex::new(r"(?i)api[_-]?key\s*[:=]?\s*[A-Za-z0-9-_]{12,}").unwrap(),
+                "api_key=REDACTED",
+            ),
+            (
+                Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap(),
+                "email@redacted",
+            ),
+            (
+                Regex::new(r"\b\+?\d[\d\s-]{8,}\b").unwrap(),
+                "PHONE_REDACTED",
+            ),
+        ]
+    })
+}
+
+/// Truncate a log message and redact API keys, emails and phone numbers
+pub fn scrub_message(mut s: String) -> String {
+    if s.len() > MAX_LOG_MESSAGE_CHARS {
+        s = s.chars().take(MAX_LOG_MESSAGE_CHARS).collect();
+    }
+    for (re, rep) in redactions() {
+        s = re.replace_all(&s, *rep).into_owned();
+    }
+    s
+}
+
+/// Query of `GET /logs`
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct LogsQuery {
+    /// Minimum level (`trace`, `debug`, `info`, `warn`, `error`)
+    level: Option<String>,
+    /// Recent events to replay first
+    tail: Option<usize>,
+}
+
+/// Minimum level from `?level=`; `None` relays everything
+fn parse_level(raw: Option<&str>) -> WebResult<Option<Level>> {
+    match raw.map(str::trim).filter(|s| !s.is_empty()) {
+        None => Ok(None),
+        Some(raw) => raw.parse::<Level>().map(Some).map_err(|_| {
+            WebError::bad_request(
+                "invalid_level",
+                "level must be one of trace, debug, info, warn, error",
+            )
+        }),
+    }
+}
+
+/// Whether `ev` is at `min` or above; events with an unknown level always pass
+fn at_least(ev: &LogEvent, min: Option<Level>) -> bool {
+    match (min, ev.level.parse::<Level>()) {
+        // `Level` orders more verbose levels higher
+        (Some(min), Ok(level)) => level <= min,
+        _ => true,
+    }
+}
+
+/// `backlog` then live events from `rx`, filtered by level and scrubbed
+fn log_events(
+    backlog: Vec<LogEvent>,
+    rx: broadcast::Receiver<LogEvent>,
+    min: Option<Level>,
+) -> impl Stream<Item = LogEvent> + Send + 'static {
+    let live = BroadcastStream::new(rx).filter_map(|item| async move { item.ok() });
+    stream::iter(backlog)
+        .chain(live)
+        .filter(move |ev| std::future::ready(at_least(ev, min)))
+        .map(|mut ev| {
+            ev.message = scrub_message(ev.message);
+            ev
+        })
+}
+
+/// `GET /logs`: scrubbed log events as SSE
+pub(crate) async fn ui_logs_sse(
+    Query(query): Query<LogsQuery>,
+) -> WebResult<Sse<BoxStream<'static, Result<Event, Infallible>>>> {
+    let min = parse_level(query.level.as_deref())?;
+    let tail = query.tail.unwrap_or(0).min(LOG_BACKLOG_CAPACITY);
+    let (backlog, rx) = subscribe_logs_with_backlog(tail).unwrap_or_else(|| {
+        let (tx, rx) = broadcast::channel::<LogEvent>(1);
+        let _ = tx.send(LogEvent {
+            level: "INFO".into(),
+            target: "init".into(),
+            message: "logging not initialized".into(),
+            file: None,
+            line: None,
+            time: chrono::Utc::now().to_rfc3339(),
+        });
+        (Vec::new(), rx)
+    });
+    let stream = log_events(backlog, rx, min)
+        .map(|ev| {
+            let data = serde_json::to_string(&ev).unwrap_or_else(|_| "{}".to_string());
+            Ok(Event::default().data(data))
+        })
+        .boxed();
+    Ok(Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(LOG_HEARTBEAT_INTERVAL)
+            .text("heartbeat"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::utils::logger::LogHub;
+
+    fn event(level: &str, message: &str) -> LogEvent {
+        LogEvent {
+            level: level.into(),
+            target: "test".into(),
+            message: message.into(),
+            file: None,
+            line: None,
+            time: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_level_filter_drops_less_severe_events() {
+        let (tx, rx) = broadcast::channel(16);
+        for (level, message) in [
+            ("INFO", "live info"),
+            ("ERROR", "live error"),
+            ("WARN", "live warn"),
+        ] {
+            tx.send(event(level, message)).unwrap();
+        }
+        drop(tx);
+        let backlog = vec![event("INFO", "old info"), event("ERROR", "old error")];
+        let min = parse_level(Some("error")).unwrap();
+        let received: Vec<LogEvent> = log_events(backlog, rx, min).collect().await;
+        let messages: Vec<&str> = received.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["old error", "live error"]);
+        assert!(received.iter().all(|e| e.level == "ERROR"));
+
+        assert_eq!(parse_level(Some("WARN")).unwrap(), Some(Level::WARN));
+        assert_eq!(parse_level(None).unwrap(), None);
+        assert_eq!(parse_level(Some("loud")).unwrap_err().code, "invalid_level");
+    }
+
+    #[tokio::test]
+    async fn test_tail_replays_recent_events_in_order_then_live() {
+        let hub = LogHub::new(LOG_BACKLOG_CAPACITY);
+        for i in 1..=5 {
+            hub.publish(event("INFO", &format!("event {} mail a@example.com", i)));
+        }
+        let (backlog, rx) = hub.subscribe_with_backlog(3);
+        hub.publish(event("INFO", "event 6"));
+        drop(hub);
+        let received: Vec<LogEvent> = log_events(backlog, rx, None).collect().await;
+        let messages: Vec<&str> = received.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "event 3 mail email@redacted",
+                "event 4 mail email@redacted",
+                "event 5 mail email@redacted",
+                "event 6",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scrubs_secrets_and_contact_details() {
+        let scrubbed = scrub_message(
+            "key sk-abcdefghijklmnopqrstuvwx api_key=ABCDEF123456789 \
+             mail jane.doe+x@example.co.uk call 0555 123-4567"
+                .to_string(),
+        );
+        assert_eq!(
+            scrubbed,
+            "key sk-REDACTED api_key=REDACTED mail email@redacted call PHONE_REDACTED"
+        );
+
+        // Short tokens and numbers are left alone
+        let plain = "sk-short apikey 12345 room 42".to_string();
+        assert_eq!(scrub_message(plain.clone()), plain);
+    }
+
+    #[test]
+    fn test_truncates_long_messages_on_char_boundaries() {
+        let long = "é".repeat(MAX_LOG_MESSAGE_CHARS + 10);
+        let scrubbed = scrub_message(long);
+        assert_eq!(scrubbed.chars().count(), MAX_LOG_MESSAGE_CHARS);
+        assert_eq!(scrub_message("ok".to_string()), "ok");
+    }
+}
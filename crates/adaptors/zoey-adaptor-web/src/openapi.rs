@@ -0,0 +1,858 @@
+//! Versioned JSON API and its OpenAPI document
+//!
+//! Every route the web adapter serves itself (admin, cases, presence,
+//! linking, ingest, locales, chat history, WebSocket chat) is listed once in
+//! [`ROUTES`].
+//! The router mounts each entry under `/agent` (the path the bundled pages
+//! use) and under [`API_V1_PREFIX`]; the blind proxy stays on `/agent/*` only.
+//!
+//! [`spec`] assembles the OpenAPI 3.1 document from the same table, served at
+//! `GET /api/v1/openapi.json` and rendered as plain HTML at `GET /api/docs`.
+//! Schemas are derived with `schemars` from the types the handlers extract
+//! and reply with, so they follow the handlers' serde attributes; doc
+//! comments on those types become descriptions.
+//!
+//! A snapshot of the document is checked in per released [`API_VERSION`]
+//! under `tests/fixtures/openapi/`. Changing a route or schema fails the
+//! snapshot test until the snapshot is rewritten with `ZOEY_UPDATE_OPENAPI=1`:
+//! in place while the version is unreleased, under a bumped version once it
+//! has shipped. Released snapshots are never rewritten, and the current
+//! document must keep every path, parameter and field they have.
+
+use crate::error::ErrorEnvelope;
+use crate::templates::escape_html;
+use crate::{admin, cases, cleanup, history, ingest, linking, presence, server, ws_chat};
+use axum::response::Html;
+use axum::Json;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/// Version of the local API contract; bump on the first route or schema change after a release
+pub const API_VERSION: &str = "1.0.0";
+
+/// Prefix of the versioned API routes
+pub(crate) const API_V1_PREFIX: &str = "/api/v1";
+
+/// Prefix the bundled pages call, shared with the proxy
+pub(crate) const AGENT_PREFIX: &str = "/agent";
+
+/// Environment variable that makes the snapshot test write the current version's snapshot
+pub const UPDATE_SNAPSHOT_ENV: &str = "ZOEY_UPDATE_OPENAPI";
+
+/// Schema of a handler type, registering the named types it refers to
+pub(crate) type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// `{ "success": true, ... }` reply carrying the fields of `T`
+///
+/// Documented under `T`'s schema name.
+#[derive(Debug, Serialize)]
+pub(crate) struct Success<T> {
+    success: bool,
+    #[serde(flatten)]
+    body: T,
+}
+
+impl<T> Success<T> {
+    pub(crate) fn new(body: T) -> Self {
+        Self {
+            success: true,
+            body,
+        }
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Success<T> {
+    fn schema_name() -> String {
+        T::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = T::json_schema(gen).into_object();
+        let object = schema.object();
+        object
+            .properties
+            .insert("success".to_string(), gen.subschema_for::<bool>());
+        object.required.insert("success".to_string());
+        Schema::Object(schema)
+    }
+}
+
+/// Body of a reply that only reports success
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "SuccessResponse")]
+pub(crate) struct Done {}
+
+/// Who may call a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Auth {
+    /// Anyone
+    None,
+    /// `Authorization: Bearer <admin token>`
+    Admin,
+    /// Caller's entity in the `X-Entity-Id` header
+    Entity,
+}
+
+/// What a route answers with on success
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Reply {
+    /// JSON body with the status code and schema
+    Json(u16, SchemaFn),
+    /// Server-sent events whose data has the schema
+    EventStream(SchemaFn),
+    /// WebSocket upgrade exchanging the client and server frames
+    WebSocket(SchemaFn, SchemaFn),
+}
+
+/// A route served locally rather than proxied
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ApiRoute {
+    /// Lowercase HTTP method
+    pub(crate) method: &'static str,
+    /// Path below the prefix, with `{name}` parameters
+    pub(crate) path: &'static str,
+    pub(crate) operation_id: &'static str,
+    pub(crate) summary: &'static str,
+    pub(crate) tag: &'static str,
+    pub(crate) auth: Auth,
+    /// Query string type; each field is one parameter
+    pub(crate) query: Option<SchemaFn>,
+    /// JSON request body
+    pub(crate) request: Option<SchemaFn>,
+    pub(crate) reply: Reply,
+}
+
+impl ApiRoute {
+    /// Path under `prefix` in axum's `:name` syntax
+    pub(crate) fn axum_path(&self, prefix: &str) -> String {
+        format!("{}{}", prefix, self.path.replace('{', ":").replace('}', ""))
+    }
+
+    /// Names of the `{name}` path parameters, in order
+    fn path_params(&self) -> impl Iterator<Item = &'static str> {
+        self.path
+            .split('/')
+            .filter_map(|s| s.strip_prefix('{')?.strip_suffix('}'))
+    }
+
+    /// Whether a request for `path` under `prefix` is served by this route
+    fn matches(&self, prefix: &str, path: &str) -> bool {
+        let Some(rest) = path.strip_prefix(prefix) else {
+            return false;
+        };
+        let (mut want, mut got) = (self.path.split('/'), rest.split('/'));
+        loop {
+            match (want.next(), got.next()) {
+                (None, None) => return true,
+                (Some(w), Some(g)) if w == g || (w.starts_with('{') && !g.is_empty()) => {}
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Whether `method` `path` is a local route taking the admin token
+pub(crate) fn is_admin_route(method: &str, path: &str) -> bool {
+    ROUTES.iter().any(|route| {
+        route.auth == Auth::Admin
+            && route.method.eq_ignore_ascii_case(method)
+            && [AGENT_PREFIX, API_V1_PREFIX]
+                .iter()
+                .any(|prefix| route.matches(prefix, path))
+    })
+}
+
+/// Schema of `T`, as a reference when `T` is a named type
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// Every locally served API route
+pub(crate) const ROUTES: &[ApiRoute] = &[
+    ApiRoute {
+        method: "get",
+        path: "/admin/rooms",
+        operation_id: "listRooms",
+        summary: "List the agent's rooms, most recently active first",
+        tag: "admin",
+        auth: Auth::Admin,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<admin::RoomList>>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/admin/room/{roomId}",
+        operation_id: "getRoom",
+        summary: "Room summary, participants and a page of recent turns",
+        tag: "admin",
+        auth: Auth::Admin,
+        query: Some(admin::TurnPageQuery::json_schema),
+        request: None,
+        reply: Reply::Json(200, schema::<Success<admin::RoomDetailResponse>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/admin/room/{roomId}/clear",
+        operation_id: "clearRoom",
+        summary: "Remove a room's messages and thoughts",
+        tag: "admin",
+        auth: Auth::Admin,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<admin::ClearRoomResponse>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/admin/cleanup",
+        operation_id: "cleanupRooms",
+        summary: "Delete stale rooms, optionally on a schedule",
+        tag: "admin",
+        auth: Auth::Admin,
+        query: None,
+        request: Some(schema::<cleanup::CleanupBody>),
+        reply: Reply::Json(200, schema::<Success<cleanup::CleanupResponse>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/cases",
+        operation_id: "createCase",
+        summary: "Create a case owned by the caller, with a fresh invite token",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: Some(schema::<cases::CreateCaseBody>),
+        reply: Reply::Json(200, schema::<Success<cases::CaseResponse>>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/cases",
+        operation_id: "listCases",
+        summary: "The caller's cases, active and closed, most recently active first",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: Some(cases::ListCasesQuery::json_schema),
+        request: None,
+        reply: Reply::Json(200, schema::<Success<cases::CaseList>>),
+    },
+    ApiRoute {
+        method: "patch",
+        path: "/cases/{caseId}",
+        operation_id: "updateCase",
+        summary: "Rename, close or reopen a case (owner only)",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: Some(schema::<cases::UpdateCaseBody>),
+        reply: Reply::Json(200, schema::<Success<cases::CaseResponse>>),
+    },
+    ApiRoute {
+        method: "delete",
+        path: "/cases/{caseId}",
+        operation_id: "deleteCase",
+        summary: "Delete a case, its participants and its room (owner only)",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<cases::DeleteCaseResponse>>),
+    },
+    ApiRoute {
+        method: "put",
+        path: "/cases/{caseId}/invite",
+        operation_id: "registerInvite",
+        summary: "Share a case, or rotate its invite token",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: Some(schema::<cases::InviteBody>),
+        reply: Reply::Json(200, schema::<Success<cases::InviteResponse>>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/cases/{caseId}/participants",
+        operation_id: "listParticipants",
+        summary: "Participants of a case and the caller's role",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<cases::ParticipantList>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/cases/{caseId}/participants",
+        operation_id: "joinCase",
+        summary: "Join a case with its invite token",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: Some(schema::<cases::JoinBody>),
+        reply: Reply::Json(200, schema::<Success<cases::ParticipantResponse>>),
+    },
+    ApiRoute {
+        method: "patch",
+        path: "/cases/{caseId}/participants/{entityId}",
+        operation_id: "updateParticipantRole",
+        summary: "Change a participant's role (owner only)",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: Some(schema::<cases::RoleBody>),
+        reply: Reply::Json(200, schema::<Success<cases::ParticipantResponse>>),
+    },
+    ApiRoute {
+        method: "delete",
+        path: "/cases/{caseId}/participants/{entityId}",
+        operation_id: "removeParticipant",
+        summary: "Remove a participant (owner only)",
+        tag: "cases",
+        auth: Auth::Entity,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<Done>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/cases/{caseId}/presence/heartbeat",
+        operation_id: "presenceHeartbeat",
+        summary: "Mark the caller as viewing the case",
+        tag: "presence",
+        auth: Auth::Entity,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<presence::PresenceResponse>>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/cases/{caseId}/presence",
+        operation_id: "listPresence",
+        summary: "Participants currently viewing the case",
+        tag: "presence",
+        auth: Auth::Entity,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<Success<presence::PresenceResponse>>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/cases/{caseId}/events",
+        operation_id: "presenceEvents",
+        summary: "Feed of `presence` events, starting with the current viewers",
+        tag: "presence",
+        auth: Auth::Entity,
+        query: None,
+        request: None,
+        reply: Reply::EventStream(schema::<presence::PresenceFeedEvent<'static>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/link/confirm",
+        operation_id: "confirmLink",
+        summary: "Link a chat platform account with a one-time code",
+        tag: "linking",
+        auth: Auth::Entity,
+        query: None,
+        request: Some(schema::<linking::ConfirmBody>),
+        reply: Reply::Json(200, schema::<Success<linking::LinkConfirmResponse>>),
+    },
+    ApiRoute {
+        method: "post",
+        path: "/knowledge/ingest",
+        operation_id: "submitIngest",
+        summary: "Queue a document for the Agent API's knowledge ingest",
+        tag: "knowledge",
+        auth: Auth::None,
+        query: None,
+        request: Some(schema::<ingest::IngestUpload>),
+        reply: Reply::Json(202, schema::<ingest::IngestAccepted>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/knowledge/ingest/{ingestId}/status",
+        operation_id: "ingestStatus",
+        summary: "Stage and outcome of a queued ingest",
+        tag: "knowledge",
+        auth: Auth::None,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<ingest::IngestRecord>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/ui/locales",
+        operation_id: "listLocales",
+        summary: "Locales the UI can be shown in",
+        tag: "ui",
+        auth: Auth::None,
+        query: None,
+        request: None,
+        reply: Reply::Json(200, schema::<server::LocaleList>),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/ws/chat",
+        operation_id: "chatSocket",
+        summary: "Chat over a WebSocket, one streamed reply per room at a time",
+        tag: "chat",
+        auth: Auth::None,
+        query: None,
+        request: None,
+        reply: Reply::WebSocket(
+            schema::<ws_chat::ClientFrame>,
+            schema::<ws_chat::RoomFrame<'static>>,
+        ),
+    },
+    ApiRoute {
+        method: "get",
+        path: "/history",
+        operation_id: "getHistory",
+        summary: "A page of a room's messages, oldest first, for restoring the chat",
+        tag: "chat",
+        auth: Auth::None,
+        query: Some(history::HistoryQuery::json_schema),
+        request: None,
+        reply: Reply::Json(200, schema::<Success<history::HistoryResponse>>),
+    },
+];
+
+/// Generator for the document's components
+fn component_generator() -> SchemaGenerator {
+    let mut settings = SchemaSettings::draft2019_09();
+    settings.definitions_path = "#/components/schemas/".to_string();
+    settings.meta_schema = None;
+    settings.into_generator()
+}
+
+/// Generator for query parameters, which are absent rather than `null`
+fn query_generator() -> SchemaGenerator {
+    let mut settings = SchemaSettings::draft2019_09();
+    settings.option_add_null_type = false;
+    settings.meta_schema = None;
+    settings.into_generator()
+}
+
+fn to_value(schema: Schema) -> Value {
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+/// Query parameters for the fields of a query string type
+fn query_params(query: SchemaFn) -> Vec<Value> {
+    let schema = query(&mut query_generator()).into_object();
+    let Some(object) = schema.object else {
+        return Vec::new();
+    };
+    object
+        .properties
+        .into_iter()
+        .map(|(name, property)| {
+            let mut property = property.into_object();
+            let description = property
+                .metadata
+                .as_mut()
+                .and_then(|m| m.description.take());
+            let mut param = json!({
+                "name": name,
+                "in": "query",
+                "required": object.required.contains(&name),
+                "schema": to_value(Schema::Object(property)),
+            });
+            if let Some(description) = description {
+                param["description"] = json!(description);
+            }
+            param
+        })
+        .collect()
+}
+
+/// OpenAPI operation object for `route`, adding its schemas to `gen`
+fn operation(route: &ApiRoute, gen: &mut SchemaGenerator) -> Value {
+    let mut parameters: Vec<Value> = route
+        .path_params()
+        .map(|name| {
+            let schema = if name == "ingestId" {
+                gen.subschema_for::<String>()
+            } else {
+                gen.subschema_for::<Uuid>()
+            };
+            json!({ "name": name, "in": "path", "required": true, "schema": to_value(schema) })
+        })
+        .collect();
+    parameters.extend(route.query.map(query_params).unwrap_or_default());
+
+    let mut responses = Map::new();
+    match route.reply {
+        Reply::Json(status, schema) => {
+            responses.insert(
+                status.to_string(),
+                json!({ "description": "Success", "content": json_content(to_value(schema(gen))) }),
+            );
+        }
+        Reply::EventStream(schema) => {
+            responses.insert(
+                "200".to_string(),
+                json!({
+                    "description": "Server-sent events; each event's data is one object",
+                    "content": { "text/event-stream": { "schema": to_value(schema(gen)) } },
+                }),
+            );
+        }
+        Reply::WebSocket(client, server) => {
+            responses.insert(
+                "101".to_string(),
+                json!({
+                    "description": "Switches to a WebSocket carrying JSON text frames",
+                    "x-client-frames": to_value(client(gen)),
+                    "x-server-frames": to_value(server(gen)),
+                }),
+            );
+        }
+    }
+    responses.insert(
+        "default".to_string(),
+        json!({
+            "description": "Error",
+            "content": json_content(to_value(gen.subschema_for::<ErrorEnvelope>())),
+        }),
+    );
+
+    let mut op = json!({
+        "operationId": route.operation_id,
+        "summary": route.summary,
+        "tags": [route.tag],
+        "parameters": parameters,
+        "responses": responses,
+    });
+    if let Some(request) = route.request {
+        op["requestBody"] =
+            json!({ "required": true, "content": json_content(to_value(request(gen))) });
+    }
+    match route.auth {
+        Auth::None => {}
+        Auth::Admin => op["security"] = json!([{ "adminToken": [] }]),
+        Auth::Entity => op["security"] = json!([{ "entityId": [] }]),
+    }
+    op
+}
+
+/// The OpenAPI document for [`ROUTES`] under [`API_V1_PREFIX`]
+pub fn spec() -> Value {
+    let mut gen = component_generator();
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let path = format!("{}{}", API_V1_PREFIX, route.path);
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+        item[route.method] = operation(route, &mut gen);
+    }
+    let schemas: Map<String, Value> = gen
+        .take_definitions()
+        .into_iter()
+        .map(|(name, schema)| (name, to_value(schema)))
+        .collect();
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Zoey web adapter API",
+            "version": API_VERSION,
+            "description": "Routes served by the web adapter itself. Each is also \
+                available under `/agent` in place of `/api/v1`; other `/agent` \
+                paths are proxied to the Agent API and not described here.",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "adminToken": { "type": "http", "scheme": "bearer" },
+                "entityId": { "type": "apiKey", "in": "header", "name": "X-Entity-Id" },
+            },
+        },
+    })
+}
+
+/// `GET /api/v1/openapi.json`
+pub(crate) async fn spec_json() -> Json<Value> {
+    Json(spec())
+}
+
+/// `GET /api/docs`: the document as a plain HTML page, no scripts
+pub(crate) async fn docs() -> Html<String> {
+    Html(render_docs(&spec()))
+}
+
+fn render_docs(spec: &Value) -> String {
+    let mut html = format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>{title} {version}</title>\
+         <style>body{{font-family:system-ui,sans-serif;max-width:60rem;margin:2rem auto;padding:0 1rem}}\
+         code,pre{{background:#f4f4f4;padding:.1rem .3rem}}pre{{padding:.5rem;overflow-x:auto}}\
+         .m{{display:inline-block;min-width:4rem;font-weight:bold;text-transform:uppercase}}</style>\
+         </head><body><h1>{title} <small>{version}</small></h1>\
+         <p>{description}</p><p><a href=\"{prefix}/openapi.json\">OpenAPI JSON</a></p><h2>Routes</h2>",
+        title = escape_html(spec["info"]["title"].as_str().unwrap_or_default()),
+        version = escape_html(spec["info"]["version"].as_str().unwrap_or_default()),
+        description = escape_html(spec["info"]["description"].as_str().unwrap_or_default()),
+        prefix = API_V1_PREFIX,
+    );
+    let paths = spec["paths"].as_object().cloned().unwrap_or_default();
+    for (path, item) in &paths {
+        for (method, op) in item.as_object().into_iter().flatten() {
+            html.push_str(&format!(
+                "<h3><span class=\"m\">{}</span> <code>{}</code></h3><p>{}</p><ul>",
+                escape_html(method),
+                escape_html(path),
+                escape_html(op["summary"].as_str().unwrap_or_default()),
+            ));
+            if let Some(body) =
+                op["requestBody"]["content"]["application/json"]["schema"]["$ref"].as_str()
+            {
+                html.push_str(&format!("<li>Request: {}</li>", schema_link(body)));
+            }
+            for (status, response) in op["responses"].as_object().into_iter().flatten() {
+                let schema = response["content"]
+                    .as_object()
+                    .and_then(|c| c.values().next())
+                    .and_then(|c| c["schema"]["$ref"].as_str())
+                    .map(schema_link)
+                    .unwrap_or_else(|| {
+                        escape_html(response["description"].as_str().unwrap_or_default())
+                    });
+                html.push_str(&format!("<li>{}: {}</li>", escape_html(status), schema));
+            }
+            html.push_str("</ul>");
+        }
+    }
+    html.push_str("<h2>Schemas</h2>");
+    let schemas = spec["components"]["schemas"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    for (name, schema) in &schemas {
+        html.push_str(&format!(
+            "<h3 id=\"{name}\">{name}</h3><pre>{}</pre>",
+            escape_html(&serde_json::to_string_pretty(schema).unwrap_or_default()),
+            name = escape_html(name),
+        ));
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+/// Link to a schema's section for a `#/components/schemas/Name` reference
+fn schema_link(reference: &str) -> String {
+    let name = escape_html(reference.rsplit('/').next().unwrap_or(reference));
+    format!("<a href=\"#{name}\">{name}</a>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    fn snapshot_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/openapi")
+    }
+
+    fn snapshot_path() -> PathBuf {
+        snapshot_dir().join(format!("v{}.json", API_VERSION))
+    }
+
+    #[test]
+    fn spec_matches_snapshot_for_version() {
+        let path = snapshot_path();
+        let actual = spec();
+        if std::env::var(UPDATE_SNAPSHOT_ENV).is_ok_and(|v| v == "1") {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            return;
+        }
+        let Ok(stored) = std::fs::read_to_string(&path) else {
+            panic!(
+                "no snapshot for API {}; write one with {}=1",
+                API_VERSION, UPDATE_SNAPSHOT_ENV
+            );
+        };
+        let stored: Value = serde_json::from_str(&stored).unwrap();
+        assert!(
+            stored == actual,
+            "the API changed since the {} snapshot: rewrite it with {}=1 if {} is unreleased, \
+             otherwise bump API_VERSION first (snapshot: {})",
+            API_VERSION,
+            UPDATE_SNAPSHOT_ENV,
+            API_VERSION,
+            path.display()
+        );
+    }
+
+    /// Dotted names of every field `schema` describes, following references
+    fn fields(spec: &Value, schema: &Value, prefix: &str, depth: usize, out: &mut HashSet<String>) {
+        if depth > 8 {
+            return;
+        }
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            let target = &spec["components"]["schemas"][name];
+            return fields(spec, target, prefix, depth + 1, out);
+        }
+        for (name, property) in schema["properties"].as_object().into_iter().flatten() {
+            let field = format!("{}{}", prefix, name);
+            fields(spec, property, &format!("{}.", field), depth + 1, out);
+            out.insert(field);
+        }
+        for key in ["allOf", "anyOf", "oneOf"] {
+            for variant in schema[key].as_array().into_iter().flatten() {
+                fields(spec, variant, prefix, depth + 1, out);
+            }
+        }
+        if schema["items"].is_object() {
+            fields(
+                spec,
+                &schema["items"],
+                &format!("{}[].", prefix),
+                depth + 1,
+                out,
+            );
+        }
+    }
+
+    /// Parameters and request, reply and frame fields of one operation
+    fn operation_fields(spec: &Value, op: &Value) -> HashSet<String> {
+        let mut out = HashSet::new();
+        for param in op["parameters"].as_array().into_iter().flatten() {
+            out.insert(format!("{} parameter {}", param["in"], param["name"]));
+        }
+        for content in op["requestBody"]["content"]
+            .as_object()
+            .into_iter()
+            .flatten()
+        {
+            fields(spec, &content.1["schema"], "request ", 0, &mut out);
+        }
+        for (status, reply) in op["responses"].as_object().into_iter().flatten() {
+            for content in reply["content"].as_object().into_iter().flatten() {
+                fields(
+                    spec,
+                    &content.1["schema"],
+                    &format!("{} ", status),
+                    0,
+                    &mut out,
+                );
+            }
+            for frames in ["x-client-frames", "x-server-frames"] {
+                fields(spec, &reply[frames], &format!("{} ", frames), 0, &mut out);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn spec_keeps_everything_released_versions_had() {
+        let current = spec();
+        let mut checked = 0;
+        for entry in std::fs::read_dir(snapshot_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            let old: Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            for (route, item) in old["paths"].as_object().unwrap() {
+                for (method, op) in item.as_object().unwrap() {
+                    let now = &current["paths"][route][method];
+                    assert!(
+                        now.is_object(),
+                        "{} {} from {} was removed",
+                        method,
+                        route,
+                        path.display()
+                    );
+                    let kept = operation_fields(&current, now);
+                    for field in operation_fields(&old, op) {
+                        assert!(
+                            kept.contains(&field),
+                            "{} {}: {} from {} was removed",
+                            method,
+                            route,
+                            field,
+                            path.display()
+                        );
+                    }
+                }
+            }
+            checked += 1;
+        }
+        assert!(checked > 0, "no snapshots to compare against");
+    }
+
+    /// Every `$ref` in `value`
+    fn refs(value: &Value, out: &mut HashSet<String>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(r)) = map.get("$ref") {
+                    out.insert(r.trim_start_matches("#/components/schemas/").to_string());
+                }
+                map.values().for_each(|v| refs(v, out));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn every_route_is_documented_with_known_schemas() {
+        let spec = spec();
+        let mut operations = HashSet::new();
+        for route in ROUTES {
+            assert!(
+                operations.insert(route.operation_id),
+                "{}",
+                route.operation_id
+            );
+            let op = &spec["paths"][format!("{}{}", API_V1_PREFIX, route.path)][route.method];
+            assert_eq!(op["operationId"], route.operation_id);
+            let params = op["parameters"].as_array().unwrap();
+            assert_eq!(
+                params.iter().filter(|p| p["in"] == "path").count(),
+                route.path_params().count()
+            );
+        }
+        let mut used = HashSet::new();
+        refs(&spec, &mut used);
+        let defined = spec["components"]["schemas"].as_object().unwrap();
+        for name in &used {
+            assert!(defined.contains_key(name), "undefined schema {}", name);
+        }
+        for name in defined.keys() {
+            assert!(used.contains(name), "unused schema {}", name);
+        }
+    }
+
+    #[test]
+    fn axum_paths_use_colon_params() {
+        let route = ROUTES
+            .iter()
+            .find(|r| r.operation_id == "updateParticipantRole")
+            .unwrap();
+        assert_eq!(
+            route.axum_path(AGENT_PREFIX),
+            "/agent/cases/:caseId/participants/:entityId"
+        );
+        assert_eq!(
+            route.axum_path(API_V1_PREFIX),
+            "/api/v1/cases/:caseId/participants/:entityId"
+        );
+    }
+
+    #[test]
+    fn docs_escape_and_link_schemas() {
+        let html = render_docs(&spec());
+        assert!(html.contains("<code>/api/v1/admin/rooms</code>"));
+        assert!(html.contains("<a href=\"#RoomList\">RoomList</a>"));
+        assert!(html.contains("<h3 id=\"ErrorEnvelope\">"));
+        assert!(!html.contains("<script"));
+    }
+}
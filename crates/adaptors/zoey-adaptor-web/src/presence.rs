@@ -0,0 +1,592 @@
+//! Live presence of participants in shared cases
+//!
+//! The legal UI sends a heartbeat every [`HEARTBEAT_INTERVAL`] while a case is
+//! open, so participants can see who else is working on it:
+//!
+//! - `POST /agent/cases/:id/presence/heartbeat`  mark the caller as viewing (participants only)
+//! - `GET  /agent/cases/:id/presence`            participants viewing right now
+//! - `GET  /agent/cases/:id/events`              change feed (SSE) with a `presence` event
+//!   whenever someone starts or stops viewing
+//!
+//! Presence is kept in memory only. A viewer counts as active for
+//! [`PRESENCE_TTL`] after their last heartbeat; a background sweep
+//! ([`spawn_sweeper`]) drops expired viewers and reports them as having left.
+//! At most [`MAX_TRACKED_CASES`] cases are tracked; beyond that the case
+//! idle the longest is forgotten.
+
+use crate::cases::{self, CaseAction, CaseRecord};
+use crate::error::{WebError, WebResult};
+use crate::openapi::Success;
+use crate::SimpleUiServer;
+use axum::extract::rejection::PathRejection;
+use axum::extract::{Path, State as AxumState};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::Json;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use uuid::Uuid;
+
+/// How often the UI sends a heartbeat for the open case
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a viewer stays active after a heartbeat; allows one missed beat
+pub const PRESENCE_TTL: Duration = Duration::from_secs(75);
+
+/// How often expired viewers are swept
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Most cases tracked at once
+pub const MAX_TRACKED_CASES: usize = 10_000;
+
+/// Presence changes buffered for slow feed subscribers
+const EVENT_BUFFER: usize = 256;
+
+/// Whether a viewer arrived or went away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceChange {
+    Joined,
+    Left,
+}
+
+/// A participant viewing a case
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Viewer {
+    pub entity_id: Uuid,
+    pub display_name: String,
+}
+
+/// A viewer joined or left a case
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceEvent {
+    pub case_id: Uuid,
+    pub change: PresenceChange,
+    pub entity_id: Uuid,
+    pub display_name: String,
+    /// Everyone viewing after the change
+    pub viewers: Vec<Viewer>,
+}
+
+struct Seen {
+    display_name: String,
+    at: Instant,
+}
+
+/// Viewers per case, each with the time of their last heartbeat
+pub(crate) struct PresenceMap {
+    cases: Mutex<HashMap<Uuid, HashMap<Uuid, Seen>>>,
+    events: broadcast::Sender<PresenceEvent>,
+    ttl: Duration,
+    max_cases: usize,
+}
+
+impl Default for PresenceMap {
+    fn default() -> Self {
+        Self::new(PRESENCE_TTL, MAX_TRACKED_CASES)
+    }
+}
+
+fn viewers_of(case: &HashMap<Uuid, Seen>) -> Vec<Viewer> {
+    let mut viewers: Vec<Viewer> = case
+        .iter()
+        .map(|(id, seen)| Viewer {
+            entity_id: *id,
+            display_name: seen.display_name.clone(),
+        })
+        .collect();
+    viewers.sort_by(|a, b| {
+        a.display_name
+            .cmp(&b.display_name)
+            .then(a.entity_id.cmp(&b.entity_id))
+    });
+    viewers
+}
+
+/// Remove viewers of `case` last seen before `cutoff`, one `Left` event each
+fn expire(
+    case_id: Uuid,
+    case: &mut HashMap<Uuid, Seen>,
+    cutoff: impl Fn(Instant) -> bool,
+) -> Vec<PresenceEvent> {
+    let expired: Vec<Uuid> = case
+        .iter()
+        .filter(|(_, seen)| cutoff(seen.at))
+        .map(|(id, _)| *id)
+        .collect();
+    expired
+        .into_iter()
+        .filter_map(|id| {
+            let seen = case.remove(&id)?;
+            Some(PresenceEvent {
+                case_id,
+                change: PresenceChange::Left,
+                entity_id: id,
+                display_name: seen.display_name,
+                viewers: viewers_of(case),
+            })
+        })
+        .collect()
+}
+
+impl PresenceMap {
+    pub(crate) fn new(ttl: Duration, max_cases: usize) -> Self {
+        Self {
+            cases: Mutex::new(HashMap::new()),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            ttl,
+            max_cases: max_cases.max(1),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<PresenceEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, events: Vec<PresenceEvent>) {
+        for event in events {
+            // No subscribers is fine
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Record a heartbeat; returns whether the viewer just became active
+    pub(crate) fn heartbeat(
+        &self,
+        case_id: Uuid,
+        entity_id: Uuid,
+        display_name: &str,
+        now: Instant,
+    ) -> bool {
+        let mut events = Vec::new();
+        let joined = {
+            let mut cases = self.cases.lock().unwrap_or_else(|e| e.into_inner());
+            if !cases.contains_key(&case_id) && cases.len() >= self.max_cases {
+                events.extend(self.sweep_locked(&mut cases, now));
+                if cases.len() >= self.max_cases {
+                    events.extend(evict_idlest(&mut cases));
+                }
+            }
+            let case = cases.entry(case_id).or_default();
+            events.extend(expire(case_id, case, |at| {
+                now.saturating_duration_since(at) >= self.ttl
+            }));
+            let active = case.contains_key(&entity_id);
+            case.insert(
+                entity_id,
+                Seen {
+                    display_name: display_name.to_string(),
+                    at: now,
+                },
+            );
+            if !active {
+                events.push(PresenceEvent {
+                    case_id,
+                    change: PresenceChange::Joined,
+                    entity_id,
+                    display_name: display_name.to_string(),
+                    viewers: viewers_of(case),
+                });
+            }
+            !active
+        };
+        self.publish(events);
+        joined
+    }
+
+    /// Viewers of `case_id` whose last heartbeat is within the TTL
+    pub(crate) fn active(&self, case_id: Uuid, now: Instant) -> Vec<Viewer> {
+        let cases = self.cases.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(case) = cases.get(&case_id) else {
+            return Vec::new();
+        };
+        viewers_of(case)
+            .into_iter()
+            .filter(|v| {
+                case.get(&v.entity_id)
+                    .is_some_and(|seen| now.saturating_duration_since(seen.at) < self.ttl)
+            })
+            .collect()
+    }
+
+    /// Drop expired viewers and empty cases; returns how many viewers left
+    pub(crate) fn sweep(&self, now: Instant) -> usize {
+        let events = {
+            let mut cases = self.cases.lock().unwrap_or_else(|e| e.into_inner());
+            self.sweep_locked(&mut cases, now)
+        };
+        let left = events.len();
+        self.publish(events);
+        left
+    }
+
+    fn sweep_locked(
+        &self,
+        cases: &mut HashMap<Uuid, HashMap<Uuid, Seen>>,
+        now: Instant,
+    ) -> Vec<PresenceEvent> {
+        let mut events = Vec::new();
+        cases.retain(|case_id, case| {
+            events.extend(expire(*case_id, case, |at| {
+                now.saturating_duration_since(at) >= self.ttl
+            }));
+            !case.is_empty()
+        });
+        events
+    }
+
+    #[cfg(test)]
+    fn tracked_cases(&self) -> usize {
+        self.cases.lock().unwrap().len()
+    }
+}
+
+/// Forget the case whose latest heartbeat is oldest
+fn evict_idlest(cases: &mut HashMap<Uuid, HashMap<Uuid, Seen>>) -> Vec<PresenceEvent> {
+    let idlest = cases
+        .iter()
+        .min_by_key(|(_, case)| case.values().map(|seen| seen.at).max())
+        .map(|(id, _)| *id);
+    let Some(case_id) = idlest else {
+        return Vec::new();
+    };
+    let mut case = cases.remove(&case_id).unwrap_or_default();
+    expire(case_id, &mut case, |_| true)
+}
+
+/// Sweep expired viewers every [`SWEEP_INTERVAL`]
+pub(crate) fn spawn_sweeper(state: SimpleUiServer) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let left = state.presence.sweep(Instant::now());
+            if left > 0 {
+                tracing::debug!(left, "Swept expired case viewers");
+            }
+        }
+    });
+}
+
+/// "Alice is viewing", leaving out `except` (the reader)
+pub(crate) fn summary(viewers: &[Viewer], except: Uuid) -> Option<String> {
+    let names: Vec<&str> = viewers
+        .iter()
+        .filter(|v| v.entity_id != except)
+        .map(|v| v.display_name.as_str())
+        .collect();
+    match names.as_slice() {
+        [] => None,
+        [one] => Some(format!("{} is viewing", one)),
+        [one, two] => Some(format!("{} and {} are viewing", one, two)),
+        [one, rest @ ..] => Some(format!("{} and {} others are viewing", one, rest.len())),
+    }
+}
+
+/// Stored record of a shared case where `actor` is a participant
+fn member_record(state: &SimpleUiServer, case_id: Uuid, actor: Uuid) -> WebResult<CaseRecord> {
+    let record = cases::load_case(state, case_id)?
+        .ok_or_else(|| WebError::not_found("case_not_found", "Case not found"))?;
+    record.check(Some(actor), CaseAction::Read)?;
+    Ok(record)
+}
+
+/// Active viewers that are still participants, with their current names
+fn current_viewers(state: &SimpleUiServer, record: &CaseRecord, case_id: Uuid) -> Vec<Viewer> {
+    state
+        .presence
+        .active(case_id, Instant::now())
+        .into_iter()
+        .filter_map(|v| {
+            let participant = record.participant(v.entity_id)?;
+            Some(Viewer {
+                entity_id: v.entity_id,
+                display_name: participant.display_name.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Presence reply for the heartbeat and list routes
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PresenceResponse {
+    /// Who else is viewing, e.g. "Alice and Bob are viewing"
+    #[schemars(required)]
+    summary: Option<String>,
+    viewers: Vec<Viewer>,
+    /// How often the UI should send a heartbeat
+    heartbeat_secs: u64,
+}
+
+impl PresenceResponse {
+    fn new(viewers: Vec<Viewer>, actor: Uuid) -> Success<Self> {
+        Success::new(Self {
+            summary: summary(&viewers, actor),
+            viewers,
+            heartbeat_secs: HEARTBEAT_INTERVAL.as_secs(),
+        })
+    }
+}
+
+/// Data of a `presence` event on the case feed
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PresenceFeedEvent<'a> {
+    /// `null` on the first event, which reports the current viewers
+    #[schemars(required)]
+    change: Option<PresenceChange>,
+    #[schemars(required)]
+    entity_id: Option<Uuid>,
+    #[schemars(required)]
+    display_name: Option<&'a str>,
+    viewers: &'a [Viewer],
+    #[schemars(required)]
+    summary: Option<String>,
+}
+
+/// `POST /agent/cases/:id/presence/heartbeat`
+pub(crate) async fn heartbeat(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<PresenceResponse>>> {
+    let Path(case_id) = case_id?;
+    let actor = cases::caller(&headers)?;
+    let record = member_record(&state, case_id, actor)?;
+    let name = record
+        .participant(actor)
+        .map(|p| p.display_name.clone())
+        .unwrap_or_default();
+    if state
+        .presence
+        .heartbeat(case_id, actor, &name, Instant::now())
+    {
+        tracing::debug!(case_id = %case_id, entity_id = %actor, "Participant started viewing case");
+    }
+    Ok(Json(PresenceResponse::new(
+        current_viewers(&state, &record, case_id),
+        actor,
+    )))
+}
+
+/// `GET /agent/cases/:id/presence`
+pub(crate) async fn list(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Json<Success<PresenceResponse>>> {
+    let Path(case_id) = case_id?;
+    let actor = cases::caller(&headers)?;
+    let record = member_record(&state, case_id, actor)?;
+    Ok(Json(PresenceResponse::new(
+        current_viewers(&state, &record, case_id),
+        actor,
+    )))
+}
+
+fn presence_sse(viewers: &[Viewer], change: Option<&PresenceEvent>, actor: Uuid) -> Event {
+    let data = PresenceFeedEvent {
+        change: change.map(|e| e.change),
+        entity_id: change.map(|e| e.entity_id),
+        display_name: change.map(|e| e.display_name.as_str()),
+        viewers,
+        summary: summary(viewers, actor),
+    };
+    Event::default()
+        .event("presence")
+        .data(serde_json::to_string(&data).unwrap_or_default())
+}
+
+/// `GET /agent/cases/:id/events`
+///
+/// Starts with the current presence, then sends one `presence` event per
+/// join or leave. The feed ends once the caller is no longer a participant.
+pub(crate) async fn events(
+    AxumState(state): AxumState<SimpleUiServer>,
+    case_id: Result<Path<Uuid>, PathRejection>,
+    headers: HeaderMap,
+) -> WebResult<Sse<BoxStream<'static, Result<Event, Infallible>>>> {
+    let Path(case_id) = case_id?;
+    let actor = cases::caller(&headers)?;
+    let rx = state.presence.subscribe();
+    let record = member_record(&state, case_id, actor)?;
+    let initial = presence_sse(&current_viewers(&state, &record, case_id), None, actor);
+    let changes = BroadcastStream::new(rx)
+        .filter_map(move |item| {
+            let event = item.ok().filter(|e| e.case_id == case_id);
+            async move { event }
+        })
+        .map(move |event| {
+            // Removed participants lose the feed with their next event
+            let record = member_record(&state, case_id, actor).ok()?;
+            let viewers: Vec<Viewer> = event
+                .viewers
+                .iter()
+                .filter_map(|v| {
+                    Some(Viewer {
+                        entity_id: v.entity_id,
+                        display_name: record.participant(v.entity_id)?.display_name.clone(),
+                    })
+                })
+                .collect();
+            Some(Ok(presence_sse(&viewers, Some(&event), actor)))
+        })
+        .take_while(|event| std::future::ready(event.is_some()))
+        .filter_map(std::future::ready);
+    let stream = stream::once(async move { Ok(initial) })
+        .chain(changes)
+        .boxed();
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(75);
+
+    fn names(viewers: &[Viewer]) -> Vec<&str> {
+        viewers.iter().map(|v| v.display_name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_viewers_expire_after_ttl() {
+        let map = PresenceMap::new(TTL, 16);
+        let case_id = Uuid::new_v4();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let start = Instant::now();
+
+        assert!(map.heartbeat(case_id, alice, "Alice", start));
+        assert!(map.heartbeat(case_id, bob, "Bob", start + Duration::from_secs(40)));
+        // A repeated heartbeat within the TTL is not a new arrival
+        assert!(!map.heartbeat(case_id, alice, "Alice", start + Duration::from_secs(30)));
+        assert_eq!(
+            names(&map.active(case_id, start + Duration::from_secs(60))),
+            ["Alice", "Bob"]
+        );
+
+        // Alice's last beat was at 30s, Bob's at 40s
+        assert_eq!(
+            names(&map.active(case_id, start + Duration::from_secs(105))),
+            ["Bob"]
+        );
+        assert!(map
+            .active(case_id, start + Duration::from_secs(115))
+            .is_empty());
+        assert!(map.active(Uuid::new_v4(), start).is_empty());
+
+        // Coming back after expiry counts as joining again
+        assert!(map.heartbeat(case_id, alice, "Alice", start + Duration::from_secs(200)));
+    }
+
+    #[test]
+    fn test_sweep_under_many_cases() {
+        let map = PresenceMap::new(TTL, MAX_TRACKED_CASES);
+        let start = Instant::now();
+        let later = start + Duration::from_secs(60);
+        let fresh: Vec<Uuid> = (0..2_000).map(|_| Uuid::new_v4()).collect();
+        for i in 0..3_000 {
+            map.heartbeat(Uuid::new_v4(), Uuid::new_v4(), "Stale", start);
+            if i % 2 == 0 {
+                map.heartbeat(Uuid::new_v4(), Uuid::new_v4(), "Stale too", start);
+            }
+        }
+        for case_id in &fresh {
+            map.heartbeat(*case_id, Uuid::new_v4(), "Fresh", later);
+        }
+        assert_eq!(map.tracked_cases(), 6_500);
+
+        let left = map.sweep(start + TTL + Duration::from_secs(1));
+        assert_eq!(left, 4_500);
+        assert_eq!(map.tracked_cases(), 2_000);
+        assert!(fresh.iter().all(|id| map.active(*id, later).len() == 1));
+        assert_eq!(map.sweep(later + TTL), 2_000);
+        assert_eq!(map.tracked_cases(), 0);
+    }
+
+    #[test]
+    fn test_tracked_cases_are_bounded() {
+        let map = PresenceMap::new(TTL, 3);
+        let start = Instant::now();
+        let cases: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        for (i, case_id) in cases.iter().enumerate() {
+            map.heartbeat(
+                *case_id,
+                Uuid::new_v4(),
+                "V",
+                start + Duration::from_secs(i as u64),
+            );
+        }
+        // The case idle the longest made room for the new one
+        assert_eq!(map.tracked_cases(), 3);
+        let now = start + Duration::from_secs(5);
+        assert!(map.active(cases[0], now).is_empty());
+        assert!(cases[1..].iter().all(|id| !map.active(*id, now).is_empty()));
+    }
+
+    #[test]
+    fn test_join_and_leave_emit_events() {
+        let map = PresenceMap::new(TTL, 16);
+        let mut rx = map.subscribe();
+        let case_id = Uuid::new_v4();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let start = Instant::now();
+
+        map.heartbeat(case_id, alice, "Alice", start);
+        let joined = rx.try_recv().unwrap();
+        assert_eq!(
+            (joined.change, joined.entity_id, joined.case_id),
+            (PresenceChange::Joined, alice, case_id)
+        );
+        assert_eq!(names(&joined.viewers), ["Alice"]);
+
+        // Staying active emits nothing
+        map.heartbeat(case_id, alice, "Alice", start + Duration::from_secs(30));
+        assert!(rx.try_recv().is_err());
+
+        map.heartbeat(case_id, bob, "Bob", start + Duration::from_secs(90));
+        assert_eq!(names(&rx.try_recv().unwrap().viewers), ["Alice", "Bob"]);
+
+        map.sweep(start + Duration::from_secs(120));
+        let left = rx.try_recv().unwrap();
+        assert_eq!(
+            (left.change, left.display_name.as_str()),
+            (PresenceChange::Left, "Alice")
+        );
+        assert_eq!(names(&left.viewers), ["Bob"]);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_summary_leaves_out_the_reader() {
+        let viewer = |name: &str| Viewer {
+            entity_id: Uuid::new_v4(),
+            display_name: name.to_string(),
+        };
+        let viewers = vec![viewer("Alice"), viewer("Bob"), viewer("Carol")];
+        let me = viewers[1].entity_id;
+        assert_eq!(
+            summary(&viewers[..1], me).as_deref(),
+            Some("Alice is viewing")
+        );
+        assert_eq!(summary(&viewers[1..2], me), None);
+        assert_eq!(
+            summary(&viewers, me).as_deref(),
+            Some("Alice and Carol are viewing")
+        );
+        assert_eq!(
+            summary(&viewers, Uuid::nil()).as_deref(),
+            Some("Alice and 2 others are viewing")
+        );
+    }
+}
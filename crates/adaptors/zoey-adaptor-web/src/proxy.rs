@@ -0,0 +1,357 @@
+//! Proxy from the UI's `/agent/*` routes to the Agent API backend
+//!
+//! Routes the UI serves itself (admin, cases, ingest, ...) are matched first;
+//! everything else under `/agent/` is forwarded to
+//! [`SimpleUiConfig::agent_api_url`](crate::SimpleUiConfig::agent_api_url).
+//! Requests carry the caller's headers, minus the admin token and the UI
+//! token unless `forward_token` is set (see `crate::auth`), and the remaining
+//! deadline (`X-Zoey-Deadline-Ms`); chat streams are capped per client and
+//! end with an `event: error` frame rather than going silent when the backend
+//! fails.
+//!
+//! Bodies the case checks read (chat, knowledge queries) are buffered up to
+//! [`PROXY_MAX_BODY_BYTES`]; all others are streamed to the backend as they
+//! arrive, up to [`SimpleUiConfig::proxy_max_upload_bytes`](crate::SimpleUiConfig::proxy_max_upload_bytes).
+//! Either cap answers `413 body_too_large` instead of forwarding a cut-off body.
+
+use crate::error;
+use crate::{cases, limits, timeouts, SimpleUiServer};
+use axum::body::{self, Body};
+use axum::extract::{ConnectInfo, Path, Request, State as AxumState};
+use axum::http::StatusCode;
+use axum::response::Response;
+use futures_util::stream::StreamExt;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Response headers that describe the hop to the backend and are not forwarded
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+/// Backend URL for the path after `/agent/`
+///
+/// `base` is the Agent API root, with or without a trailing slash.
+pub fn backend_url(base: &str, rest: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), rest)
+}
+
+/// Method forwarded to the backend, the caller's own (`HEAD` and `OPTIONS` included)
+pub fn backend_method(method: &axum::http::Method) -> reqwest::Method {
+    // axum and reqwest use different `http` versions, so convert by name
+    reqwest::Method::from_bytes(method.as_str().as_bytes()).unwrap_or(reqwest::Method::GET)
+}
+
+/// Whether a backend response header is dropped instead of forwarded
+///
+/// Besides hop-by-hop headers this covers `content-length`, since the body is
+/// re-framed (and streams are never sized).
+pub fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// `/agent/*rest` not served locally: forward to the Agent API
+pub(crate) async fn agent_proxy(
+    AxumState(state): AxumState<SimpleUiServer>,
+    connect_info: Option<ConnectInfo<std::net::SocketAddr>>,
+    Path(rest): Path<String>,
+    req: Request,
+) -> error::WebResult<Response> {
+    let started = std::time::Instant::now();
+    let is_stream = rest.ends_with("chat/stream");
+    // Streams are held open for the whole generation, so cap them per client
+    let permit = if is_stream {
+        match state.stream_limits.acquire(limits::client_ip(connect_info)) {
+            Some(permit) => Some(permit),
+            None => return Err(error::WebError::too_many_streams()),
+        }
+    } else {
+        None
+    };
+
+    let url = backend_url(&state.config.agent_api_url, &rest);
+    let method = backend_method(req.method());
+    let headers = req.headers().clone();
+    let request_id = headers
+        .get(error::REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let body = if cases::proxy_reads_body(&rest) {
+        let bytes = body::to_bytes(req.into_body(), PROXY_MAX_BODY_BYTES)
+            .await
+            .map_err(|_| body_too_large())?;
+        cases::authorize_proxy(&state, &rest, &headers, &bytes)?;
+        reqwest::Body::from(bytes)
+    } else {
+        let limit = state.config.proxy_max_upload_bytes;
+        let declared = headers
+            .get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        if declared.is_some_and(|len| len > limit as u64) {
+            return Err(body_too_large());
+        }
+        cases::authorize_proxy(&state, &rest, &headers, &[])?;
+        limited_body(req.into_body(), limit, exceeded.clone())
+    };
+    // A body cut off at the cap fails the send; report that rather than a backend error
+    let send_error = |e: reqwest::Error| {
+        if exceeded.load(Ordering::Relaxed) {
+            body_too_large()
+        } else {
+            proxy_error(e)
+        }
+    };
+
+    let mut rb = state.http.request(method, &url);
+    // Copy headers (as strings); the deadline is recomputed below
+    for (k, v) in headers.iter() {
+        let k_str = k.as_str();
+        if k_str == timeouts::DEADLINE_HEADER {
+            continue;
+        }
+        if let Ok(v_str) = v.to_str() {
+            rb = rb.header(k_str, v_str);
+        }
+    }
+    let idle = state.config.proxy_stream_idle_timeout;
+    let resp = if is_stream {
+        // Streams have no overall deadline, only an idle limit
+        match tokio::time::timeout(idle, rb.body(body).send()).await {
+            Ok(resp) => resp.map_err(send_error),
+            Err(_) => Err(timeouts::stream_idle(idle)),
+        }
+    } else {
+        let remaining = timeouts::remaining(
+            state.config.proxy_timeout,
+            started.elapsed(),
+            headers.get(timeouts::DEADLINE_HEADER),
+        );
+        if remaining.is_zero() {
+            return Err(timeouts::deadline_exceeded());
+        }
+        // The timeout also covers reading the body, which is buffered below
+        rb.timeout(remaining)
+            .header(timeouts::DEADLINE_HEADER, remaining.as_millis().to_string())
+            .body(body)
+            .send()
+            .await
+            .map_err(send_error)
+    };
+
+    match resp {
+        Ok(r) => {
+            let status =
+                StatusCode::from_u16(r.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            let mut headers_out = axum::http::HeaderMap::new();
+            for (k, v) in r.headers().iter() {
+                let name_str = k.as_str();
+                if is_hop_by_hop(name_str) {
+                    continue;
+                }
+                if let Ok(name) = axum::http::HeaderName::from_bytes(name_str.as_bytes()) {
+                    if let Ok(val_str) = v.to_str() {
+                        if let Ok(val) = axum::http::HeaderValue::from_str(val_str) {
+                            headers_out.insert(name, val);
+                        }
+                    }
+                }
+            }
+            if is_stream {
+                headers_out.insert(
+                    axum::http::header::CONTENT_TYPE,
+                    axum::http::HeaderValue::from_static("text/event-stream"),
+                );
+                headers_out.insert(
+                    axum::http::header::CACHE_CONTROL,
+                    axum::http::HeaderValue::from_static("no-cache, no-transform"),
+                );
+                headers_out.insert(
+                    axum::http::HeaderName::from_static("x-accel-buffering"),
+                    axum::http::HeaderValue::from_static("no"),
+                );
+            }
+            let body = if is_stream {
+                // The permit is released once the response body is dropped. A stream
+                // cut off mid-reply ends with an error frame instead of going silent.
+                let stream = timeouts::until_idle(r.bytes_stream(), idle).map(move |chunk| {
+                    let _ = &permit;
+                    match chunk {
+                        Ok(Ok(bytes)) => Ok::<_, Infallible>(bytes),
+                        Ok(Err(e)) => Ok(sse_error_frame(&proxy_error(e), &request_id)),
+                        Err(()) => {
+                            tracing::warn!("Agent API stream {} went idle for {:?}", rest, idle);
+                            Ok(sse_error_frame(&timeouts::stream_idle(idle), &request_id))
+                        }
+                    }
+                });
+                Body::from_stream(stream)
+            } else {
+                let bytes = r.bytes().await.map_err(|e| {
+                    let err = proxy_error(e);
+                    tracing::warn!("Agent API proxy {} failed: {}", rest, err);
+                    err
+                })?;
+                Body::from(bytes)
+            };
+            let mut resp_out = axum::response::Response::new(body);
+            *resp_out.status_mut() = status;
+            *resp_out.headers_mut() = headers_out;
+            Ok(resp_out)
+        }
+        Err(err) => {
+            tracing::warn!("Agent API proxy {} failed: {}", rest, err);
+            if !is_stream {
+                return Err(err);
+            }
+            // Stream clients read SSE, so the failure is delivered as an error event
+            let mut resp_out =
+                axum::response::Response::new(Body::from(sse_error_frame(&err, &request_id)));
+            *resp_out.status_mut() = err.status;
+            resp_out.headers_mut().insert(
+                axum::http::header::CONTENT_TYPE,
+                axum::http::HeaderValue::from_static("text/event-stream"),
+            );
+            Ok(resp_out)
+        }
+    }
+}
+
+/// Largest buffered request body (chat and queries) forwarded to the Agent API
+pub const PROXY_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Default cap on streamed request bodies, see
+/// [`SimpleUiConfig::proxy_max_upload_bytes`](crate::SimpleUiConfig::proxy_max_upload_bytes)
+pub const DEFAULT_PROXY_MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+fn body_too_large() -> error::WebError {
+    error::WebError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "body_too_large",
+        "Request body is too large",
+    )
+}
+
+/// Stream `body` to the backend, failing it (and setting `exceeded`) once
+/// more than `limit` bytes have arrived
+///
+/// reqwest needs a `Sync` stream and axum's body is not, so chunks are handed
+/// over through a channel.
+fn limited_body(body: Body, limit: usize, exceeded: Arc<AtomicBool>) -> reqwest::Body {
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<body::Bytes>>(8);
+    tokio::spawn(async move {
+        let mut chunks = body.into_data_stream();
+        let mut total = 0usize;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    let _ = tx.send(Err(std::io::Error::other(e))).await;
+                    return;
+                }
+            };
+            total += chunk.len();
+            if total > limit {
+                exceeded.store(true, Ordering::Relaxed);
+                let _ = tx
+                    .send(Err(std::io::Error::other("request body too large")))
+                    .await;
+                return;
+            }
+            if tx.send(Ok(chunk)).await.is_err() {
+                return;
+            }
+        }
+    });
+    reqwest::Body::wrap_stream(tokio_stream::wrappers::ReceiverStream::new(rx))
+}
+
+/// Failure reaching the Agent API backend
+///
+/// Codes: `backend_unreachable`, `backend_timeout`, `backend_error`.
+pub(crate) fn proxy_error(e: reqwest::Error) -> error::WebError {
+    let (status, code) = if e.is_timeout() {
+        (StatusCode::GATEWAY_TIMEOUT, "backend_timeout")
+    } else if e.is_connect() {
+        (StatusCode::BAD_GATEWAY, "backend_unreachable")
+    } else {
+        (StatusCode::BAD_GATEWAY, "backend_error")
+    };
+    // The backend address is internal, keep it out of client-facing errors
+    error::WebError::new(status, code, e.without_url().to_string())
+}
+
+/// Terminal `event: error` SSE frame, handled by the UI's `streamError` path
+fn sse_error_frame(err: &error::WebError, request_id: &str) -> body::Bytes {
+    let data = serde_json::json!({
+        "error": err.code,
+        "detail": err.message,
+        "request_id": request_id,
+        "final": true,
+    });
+    body::Bytes::from(format!("event: error\ndata: {}\n\n", data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_url_joins_base_and_path() {
+        assert_eq!(
+            backend_url("http://127.0.0.1:9090/agent", "chat/stream"),
+            "http://127.0.0.1:9090/agent/chat/stream"
+        );
+        assert_eq!(
+            backend_url("http://127.0.0.1:9090/agent/", "health"),
+            "http://127.0.0.1:9090/agent/health"
+        );
+        assert_eq!(
+            backend_url("http://backend//", "rooms/abc/messages"),
+            "http://backend/rooms/abc/messages"
+        );
+        // The catch-all path is forwarded as captured
+        assert_eq!(backend_url("http://backend", ""), "http://backend/");
+    }
+
+    #[test]
+    fn test_backend_method_and_forwarded_headers() {
+        use axum::http::Method;
+        assert_eq!(backend_method(&Method::PATCH), reqwest::Method::PATCH);
+        assert_eq!(backend_method(&Method::DELETE), reqwest::Method::DELETE);
+        assert_eq!(backend_method(&Method::OPTIONS), reqwest::Method::OPTIONS);
+        assert_eq!(backend_method(&Method::HEAD), reqwest::Method::HEAD);
+
+        assert!(is_hop_by_hop("Transfer-Encoding"));
+        assert!(is_hop_by_hop("content-length"));
+        assert!(!is_hop_by_hop("content-type"));
+        assert!(!is_hop_by_hop("x-request-id"));
+    }
+
+    #[test]
+    fn test_sse_error_frame_is_terminal_event() {
+        let err = error::WebError::new(StatusCode::BAD_GATEWAY, "backend_error", "boom");
+        let frame = sse_error_frame(&err, "req-1");
+        let text = std::str::from_utf8(&frame).unwrap();
+        let data = text
+            .strip_prefix("event: error\ndata: ")
+            .and_then(|d| d.strip_suffix("\n\n"))
+            .unwrap();
+        let data: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(data["error"], "backend_error");
+        assert_eq!(data["detail"], "boom");
+        assert_eq!(data["request_id"], "req-1");
+        assert_eq!(data["final"], true);
+    }
+}
@@ -0,0 +1,1061 @@
+//! [`SimpleUiServer`]: routes, the index page and startup
+//!
+//! Routes under `/agent/` that the UI serves itself (admin, cases, presence,
+//! ingest, links, locales, chat history, WebSocket chat) are registered before the
+//! catch-all proxy ([`crate::proxy`]), so they take precedence over the
+//! backend. They are listed in [`openapi::ROUTES`] and also mounted under
+//! `/api/v1/`. With a UI token configured, every route requires it
+//! ([`crate::auth`]).
+
+use crate::config::{SimpleUiConfig, SimpleUiServerBuilder};
+use crate::templates::{self, UiTemplate};
+use crate::{
+    admin, assets, auth, cases, cleanup, error, history, i18n, ingest, ingest_progress, limits,
+    linking, logs, openapi, presence, proxy, telegram_webapp, timeouts, ws_chat,
+};
+use axum::extract::{Query, State as AxumState};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{any, delete, MethodRouter};
+use axum::Json;
+use axum::{routing::get, routing::patch, routing::post, routing::put, Router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use zoey_core::{AgentRuntime, Result};
+
+/// Simple UI server: the page, its assets, local `/agent` routes and the proxy
+#[derive(Clone)]
+pub struct SimpleUiServer {
+    /// Settings the server was built with
+    pub config: Arc<SimpleUiConfig>,
+    /// Runtime whose character picks the page and whose rooms the admin routes manage
+    pub runtime: Arc<RwLock<AgentRuntime>>,
+    /// Page served at `/`; `None` picks one by character
+    template: Option<Arc<dyn UiTemplate>>,
+    pub(crate) stream_limits: Arc<limits::StreamLimiter>,
+    pub(crate) webapp_nonces: Arc<zoey_core::utils::NonceCache>,
+    pub(crate) ingests: Arc<ingest::IngestQueue>,
+    pub(crate) presence: Arc<presence::PresenceMap>,
+    /// Client for the Agent API, shared so connections are pooled
+    pub(crate) http: reqwest::Client,
+}
+
+/// Chat message body
+#[derive(Deserialize)]
+pub struct ChatInput {
+    /// Message text
+    pub text: String,
+}
+
+/// Chat reply body
+#[derive(Serialize)]
+pub struct ChatOutput {
+    /// Whether the agent handled the message
+    pub success: bool,
+    /// Reply messages, in order
+    pub messages: Vec<String>,
+}
+
+impl SimpleUiServer {
+    /// Builder starting from the default [`SimpleUiConfig`]
+    pub fn builder() -> SimpleUiServerBuilder {
+        SimpleUiServerBuilder::new()
+    }
+
+    /// Server for a complete config; same as building one with [`Self::builder`]
+    pub fn new(config: SimpleUiConfig, runtime: Arc<RwLock<AgentRuntime>>) -> Self {
+        Self::with_template(config, None, runtime)
+    }
+
+    pub(crate) fn with_template(
+        config: SimpleUiConfig,
+        template: Option<Arc<dyn UiTemplate>>,
+        runtime: Arc<RwLock<AgentRuntime>>,
+    ) -> Self {
+        let stream_limits = Arc::new(limits::StreamLimiter::new(config.max_streams_per_ip));
+        assets::warm();
+        let ingests = Arc::new(ingest::IngestQueue::new(
+            ingest::INGEST_QUEUE_CAPACITY,
+            ingest::INGEST_WORKERS,
+            config.ingest_job_ttl,
+        ));
+        Self {
+            config: Arc::new(config),
+            runtime,
+            template,
+            stream_limits,
+            webapp_nonces: Arc::new(zoey_core::utils::NonceCache::new()),
+            ingests,
+            presence: Arc::new(presence::PresenceMap::default()),
+            http: timeouts::http_client(),
+        }
+    }
+
+    pub(crate) fn router(&self) -> Router {
+        let mut r = Router::new()
+            .route("/", get(index))
+            .route("/assets/js/:file", get(assets::script));
+        // Local routes are served under both prefixes and take precedence over the proxy
+        for prefix in [openapi::AGENT_PREFIX, openapi::API_V1_PREFIX] {
+            for route in openapi::ROUTES {
+                r = r.route(&route.axum_path(prefix), local_handler(route));
+            }
+        }
+        let mut r = r
+            .route("/api/v1/openapi.json", get(openapi::spec_json))
+            .route("/api/docs", get(openapi::docs))
+            .route("/ws", get(ws_chat::ws_chat))
+            .route(ingest_progress::PROGRESS_PATH, get(ingest_progress::progress))
+            // Proxy all other /agent/... calls to configured Agent API backend
+            .route("/agent/*rest", any(proxy::agent_proxy));
+        if self.config.logs_enabled {
+            r = r.route("/logs", get(logs::ui_logs_sse));
+        }
+        if self.config.telegram_bot_token.is_some() {
+            r = r
+                .route("/webapp", get(telegram_webapp::webapp_page))
+                .route("/webapp/session", post(telegram_webapp::create_session));
+        }
+        // Applied last so every route, including the fallback, gets a request ID
+        // and is behind the UI token
+        r.with_state(self.clone())
+            .fallback(error::not_found)
+            .layer(axum::middleware::from_fn_with_state(
+                self.clone(),
+                auth::require_token,
+            ))
+            .layer(axum::middleware::from_fn(error::request_context))
+    }
+
+    /// Bind and serve in the background until Ctrl-C; returns once listening
+    pub async fn start(&self) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let addr = format!("{}:{}", self.config.host, self.config.port);
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        let router = self
+            .router()
+            .into_make_service_with_connect_info::<std::net::SocketAddr>();
+        if self.config.admin_token.is_some() {
+            cleanup::spawn_scheduler(self.clone());
+        }
+        presence::spawn_sweeper(self.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await
+        });
+        Ok(())
+    }
+
+    /// Nothing to release; the server stops with the process
+    pub async fn stop(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Handler for a route listed in [`openapi::ROUTES`]
+fn local_handler(route: &openapi::ApiRoute) -> MethodRouter<SimpleUiServer> {
+    match route.operation_id {
+        "listRooms" => get(admin::list_rooms),
+        "getRoom" => get(admin::room_detail),
+        "clearRoom" => post(admin::clear_room),
+        "cleanupRooms" => post(cleanup::cleanup_rooms),
+        "createCase" => post(cases::create_case),
+        "listCases" => get(cases::list_cases),
+        "updateCase" => patch(cases::update_case_info),
+        "deleteCase" => delete(cases::delete_case),
+        "registerInvite" => put(cases::register_invite),
+        "listParticipants" => get(cases::list),
+        "joinCase" => post(cases::join),
+        "updateParticipantRole" => patch(cases::update_role),
+        "removeParticipant" => delete(cases::remove),
+        "presenceHeartbeat" => post(presence::heartbeat),
+        "listPresence" => get(presence::list),
+        "presenceEvents" => get(presence::events),
+        "confirmLink" => post(linking::confirm),
+        "submitIngest" => post(ingest::submit),
+        "ingestStatus" => get(ingest::status),
+        "listLocales" => get(ui_locales),
+        "chatSocket" => get(ws_chat::ws_chat),
+        "getHistory" => get(history::history),
+        other => unreachable!("API route {} has no handler", other),
+    }
+}
+
+/// `GET /`: the page for the current character (or the configured template)
+async fn index(
+    axum::extract::State(state): axum::extract::State<SimpleUiServer>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let accept_language = headers
+        .get(axum::http::header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok());
+    let locale = i18n::resolve_locale(
+        params.get("lang").map(String::as_str),
+        accept_language,
+        &state.config.locale,
+    );
+    // Read by the script bundle's prelude; `token: null` means use localStorage
+    let config = assets::config_block(&serde_json::json!({
+        "token": state.config.token,
+        "logsEnabled": state.config.logs_enabled,
+        "locale": locale,
+        "i18n": i18n::merged_bundle(locale),
+        "modelControls": state.config.model_controls,
+        "session": telegram_webapp::session_config(
+            state.config.telegram_bot_token.as_deref(),
+            &headers,
+        ),
+    }));
+
+    // Zoey Lawyer gets the case management UI unless a page was configured
+    let html = match &state.template {
+        Some(template) => template.render(&config),
+        None => templates::for_character(&get_current_character(&state)).render(&config),
+    };
+    let mut resp = Html(i18n::render(&html, locale)).into_response();
+    if state.config.content_security_policy {
+        resp.headers_mut().insert(
+            axum::http::header::CONTENT_SECURITY_POLICY,
+            axum::http::HeaderValue::from_static(assets::CONTENT_SECURITY_POLICY),
+        );
+    }
+    resp
+}
+
+/// Read the runtime, failing with a 500 envelope if a panicking writer poisoned the lock
+pub(crate) fn read_runtime(
+    state: &SimpleUiServer,
+) -> error::WebResult<RwLockReadGuard<'_, AgentRuntime>> {
+    state.runtime.read().map_err(|_| {
+        tracing::error!("Runtime lock poisoned");
+        error::WebError::runtime_unavailable()
+    })
+}
+
+/// Get the current character name from runtime state
+///
+/// Falls back to an empty name (the default UI) when the runtime is unavailable
+/// so the page still loads.
+fn get_current_character(state: &SimpleUiServer) -> String {
+    match read_runtime(state) {
+        Ok(rt) => rt.character.name.clone(),
+        Err(_) => {
+            tracing::warn!("Serving the default UI without character information");
+            String::new()
+        }
+    }
+}
+
+/// `GET /ui/locales` reply
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct LocaleList {
+    /// Locale the UI uses when the browser names none
+    default: String,
+    locales: Vec<Locale>,
+}
+
+/// A locale the UI is translated to
+#[derive(Serialize, JsonSchema)]
+struct Locale {
+    code: String,
+    /// Name of the locale in its own language
+    name: String,
+}
+
+/// List locales available to the UI so a selector can be offered
+async fn ui_locales(AxumState(state): AxumState<SimpleUiServer>) -> Json<LocaleList> {
+    let locales = i18n::available_locales()
+        .into_iter()
+        .map(|code| Locale {
+            code: code.to_string(),
+            name: i18n::lookup(code, "locale.name").to_string(),
+        })
+        .collect();
+    Json(LocaleList {
+        default: i18n::resolve_locale(None, None, &state.config.locale).to_string(),
+        locales,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DEFAULT_MAX_STREAMS_PER_IP, DEFAULT_PROXY_TIMEOUT, DEFAULT_STREAM_IDLE_TIMEOUT};
+    use axum::body::Body;
+    use futures_util::stream::StreamExt;
+    use std::convert::Infallible;
+    use std::net::TcpListener;
+
+    #[tokio::test]
+    #[ignore]
+    async fn simpleui_serves_index() {
+        // bind an ephemeral port
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        // create dummy runtime
+        let opts = zoey_core::RuntimeOpts::default();
+        let runtime = zoey_core::AgentRuntime::new(opts).await.unwrap();
+
+        // start UI
+        let ui = SimpleUiServer::builder()
+            .host("127.0.0.1")
+            .port(port)
+            .backend("http://127.0.0.1:9090/agent")
+            .locale("en")
+            .max_streams_per_ip(DEFAULT_MAX_STREAMS_PER_IP)
+            .proxy_timeout(DEFAULT_PROXY_TIMEOUT)
+            .proxy_stream_idle_timeout(DEFAULT_STREAM_IDLE_TIMEOUT)
+            .build(runtime);
+        let _ = tokio::spawn(async move {
+            let _ = ui.start().await;
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        // fetch index
+        let body = reqwest::get(format!("http://127.0.0.1:{}/", port))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("Zoey Simple UI"));
+        assert!(body.contains("id=\"zoey-config\""));
+    }
+
+    async fn serve(server: SimpleUiServer) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = server
+            .router()
+            .into_make_service_with_connect_info::<std::net::SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    /// UI server pointed at a port nothing listens on
+    async fn ui_with_dead_backend() -> std::net::SocketAddr {
+        let dead = TcpListener::bind("127.0.0.1:0").unwrap();
+        let agent_api_url = format!("http://{}/agent", dead.local_addr().unwrap());
+        drop(dead);
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        serve(
+            SimpleUiServer::builder()
+                .backend(agent_api_url)
+                .build(runtime),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn proxy_reports_unreachable_backend_as_json() {
+        let addr = ui_with_dead_backend().await;
+        let resp = reqwest::get(format!("http://{}/agent/health", addr))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::BAD_GATEWAY);
+        let request_id = resp.headers()[error::REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "backend_unreachable");
+        assert_eq!(body["error"]["request_id"], request_id.as_str());
+        let detail = body["error"]["message"].as_str().unwrap();
+        assert!(!detail.is_empty());
+        assert!(
+            !detail.contains("127.0.0.1"),
+            "backend address leaked: {}",
+            detail
+        );
+    }
+
+    #[tokio::test]
+    async fn poisoned_runtime_lock_yields_error_envelope() {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let poisoner = runtime.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.write().unwrap();
+            panic!("poison the runtime lock");
+        })
+        .join();
+        assert!(runtime.is_poisoned());
+        let addr = serve(
+            SimpleUiServer::builder()
+                .admin_token("s3cret")
+                .build(runtime),
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .get(format!("http://{}/agent/admin/rooms", addr))
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+        let request_id = resp.headers()[error::REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "runtime_unavailable");
+        assert_eq!(body["error"]["request_id"], request_id.as_str());
+
+        // The index degrades to the default UI instead of failing
+        let resp = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert!(resp
+            .text()
+            .await
+            .unwrap()
+            .contains("<title>ZoeyAI Tester</title>"));
+    }
+
+    #[tokio::test]
+    async fn ingest_is_accepted_then_reports_backend_failure() {
+        let addr = ui_with_dead_backend().await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/agent/knowledge/ingest", addr);
+
+        let resp = client
+            .post(&url)
+            .json(&serde_json::json!({ "content": "no filename" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let resp = client
+            .post(&url)
+            .json(&serde_json::json!({ "filename": "brief.txt", "content": "Statement of facts" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["status"], "accepted");
+        let status_url = format!("{}/{}/status", url, body["ingestId"].as_str().unwrap());
+
+        let mut record = serde_json::Value::Null;
+        for _ in 0..100 {
+            record = client
+                .get(&status_url)
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if record["status"] == "failed" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(record["status"], "failed");
+        assert!(!record["error"].as_str().unwrap().contains("127.0.0.1"));
+
+        let resp = client
+            .get(format!("{}/unknown/status", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn proxy_ends_chat_stream_with_error_event() {
+        let addr = ui_with_dead_backend().await;
+        let resp = reqwest::Client::new()
+            .post(format!("http://{}/agent/chat/stream", addr))
+            .json(&serde_json::json!({ "text": "hi", "stream": true }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            resp.headers()[reqwest::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let request_id = resp.headers()[error::REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = resp.text().await.unwrap();
+        assert!(body.starts_with("event: error\ndata: "));
+        assert!(body.ends_with("\n\n"));
+        let data: serde_json::Value =
+            serde_json::from_str(body.trim_end().trim_start_matches("event: error\ndata: "))
+                .unwrap();
+        assert_eq!(data["error"], "backend_unreachable");
+        assert_eq!(data["request_id"], request_id.as_str());
+        assert_eq!(data["final"], true);
+    }
+
+    /// Agent API stub: `/agent/slow` answers after 5s, `/agent/chat/stream`
+    /// sends `data: N` every 50ms, stalling after `drip` events
+    async fn stub_backend(drip: usize) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let deadlines = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = deadlines.clone();
+        let slow = move |headers: HeaderMap| async move {
+            let deadline = headers
+                .get(timeouts::DEADLINE_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            seen.lock().unwrap().push(deadline);
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            "late"
+        };
+        let stream = move || async move {
+            let events = futures_util::stream::iter(0..).then(move |i| async move {
+                let delay = if i < drip { 50 } else { 60_000 };
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                Ok::<_, Infallible>(format!("data: {}\n\n", i))
+            });
+            Body::from_stream(events)
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/agent/slow", any(slow))
+            .route("/agent/chat/stream", post(stream));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}/agent", addr), deadlines)
+    }
+
+    async fn ui_with_timeouts(agent_api_url: String) -> std::net::SocketAddr {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        serve(
+            SimpleUiServer::builder()
+                .backend(agent_api_url)
+                .proxy_timeout(std::time::Duration::from_millis(300))
+                .proxy_stream_idle_timeout(std::time::Duration::from_millis(200))
+                .build(runtime),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn proxy_times_out_hung_backend() {
+        let (backend, deadlines) = stub_backend(0).await;
+        let addr = ui_with_timeouts(backend).await;
+        let client = reqwest::Client::new();
+
+        let started = std::time::Instant::now();
+        let resp = client
+            .get(format!("http://{}/agent/slow", addr))
+            .send()
+            .await
+            .unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(resp.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        let request_id = resp.headers()[error::REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "backend_timeout");
+        assert_eq!(body["error"]["request_id"], request_id.as_str());
+
+        // The backend is told how long it has, never more than the caller allows
+        let resp = client
+            .get(format!("http://{}/agent/slow", addr))
+            .header(timeouts::DEADLINE_HEADER, "100")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        let deadlines = deadlines.lock().unwrap().clone();
+        assert_eq!(deadlines.len(), 2);
+        let first: u64 = deadlines[0].parse().unwrap();
+        let second: u64 = deadlines[1].parse().unwrap();
+        assert!(first > 0 && first <= 300, "deadline {}", first);
+        assert!(second > 0 && second <= 100, "deadline {}", second);
+
+        // An already expired deadline is not forwarded at all
+        let resp = client
+            .get(format!("http://{}/agent/slow", addr))
+            .header(timeouts::DEADLINE_HEADER, "0")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(deadlines.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn proxy_ends_idle_chat_stream() {
+        // Six events 50ms apart outlast the 200ms idle limit in total but never go idle
+        let (backend, _) = stub_backend(6).await;
+        let addr = ui_with_timeouts(backend).await;
+        let resp = reqwest::Client::new()
+            .post(format!("http://{}/agent/chat/stream", addr))
+            .json(&serde_json::json!({ "text": "hi", "stream": true }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        let request_id = resp.headers()[error::REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = resp.text().await.unwrap();
+        let (events, error) = body.split_once("event: error\ndata: ").unwrap();
+        let expected: String = (0..6).map(|i| format!("data: {}\n\n", i)).collect();
+        assert_eq!(events, expected);
+        let data: serde_json::Value = serde_json::from_str(error.trim_end()).unwrap();
+        assert_eq!(data["error"], "stream_idle_timeout");
+        assert_eq!(data["request_id"], request_id.as_str());
+        assert_eq!(data["final"], true);
+    }
+
+    #[tokio::test]
+    async fn proxy_streams_large_uploads_and_keeps_methods() {
+        // Agent API stub answering with the method it saw and the body size it got
+        let echo = |method: axum::http::Method, body: axum::body::Bytes| async move {
+            ([("x-method", method.to_string())], body.len().to_string())
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/agent/*rest", any(echo))
+            .layer(axum::extract::DefaultBodyLimit::disable());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let ui = |max_upload: usize| async move {
+            let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+                .await
+                .unwrap();
+            let addr = serve(
+                SimpleUiServer::builder()
+                    .backend(format!("http://{}/agent", backend))
+                    .proxy_max_upload_bytes(max_upload)
+                    .build(runtime),
+            )
+            .await;
+            format!("http://{}/agent/documents/upload", addr)
+        };
+        let client = reqwest::Client::new();
+        let url = ui(8 * 1024 * 1024).await;
+
+        let resp = client
+            .post(&url)
+            .body(vec![b'x'; 5 * 1024 * 1024])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), (5 * 1024 * 1024).to_string());
+
+        // Past the cap the caller gets a JSON 413, whether the size is declared or not
+        let capped = ui(1024).await;
+        let resp = client
+            .post(&capped)
+            .body(vec![b'x'; 4096])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "body_too_large");
+        let chunks =
+            futures_util::stream::iter((0..4).map(|_| Ok::<_, Infallible>(vec![b'x'; 1024])));
+        let resp = client
+            .post(&capped)
+            .body(reqwest::Body::wrap_stream(chunks))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+
+        for method in [reqwest::Method::HEAD, reqwest::Method::OPTIONS] {
+            let resp = client.request(method.clone(), &url).send().await.unwrap();
+            assert_eq!(resp.headers()["x-method"], method.as_str());
+        }
+    }
+
+    #[tokio::test]
+    async fn presence_is_for_participants_and_feeds_joins() {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let addr = serve(SimpleUiServer::new(SimpleUiConfig::default(), runtime)).await;
+        let client = reqwest::Client::new();
+        let case_id = uuid::Uuid::new_v4();
+        let (owner, outsider) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let url = |path: &str| format!("http://{}/agent/cases/{}/{}", addr, case_id, path);
+        let as_entity = |req: reqwest::RequestBuilder, id: uuid::Uuid| {
+            req.header(cases::ENTITY_HEADER, id.to_string())
+        };
+
+        let resp = as_entity(client.put(url("invite")), owner)
+            .json(&serde_json::json!({ "inviteToken": "0123456789abcdef", "displayName": "Alice" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+
+        let resp = as_entity(client.post(url("presence/heartbeat")), outsider)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "not_a_participant");
+        let resp = as_entity(client.get(url("events")), outsider)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let feed = as_entity(client.get(url("events")), owner)
+            .send()
+            .await
+            .unwrap();
+        let mut feed = feed.bytes_stream();
+        let initial = feed.next().await.unwrap().unwrap();
+        assert!(String::from_utf8_lossy(&initial).contains("\"viewers\":[]"));
+
+        let resp = as_entity(client.post(url("presence/heartbeat")), owner)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        let joined = feed.next().await.unwrap().unwrap();
+        let joined = String::from_utf8_lossy(&joined);
+        assert!(joined.starts_with("event: presence"), "{}", joined);
+        assert!(joined.contains("\"change\":\"joined\""), "{}", joined);
+
+        let body: serde_json::Value = as_entity(client.get(url("presence")), owner)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["viewers"][0]["displayName"], "Alice");
+        assert_eq!(body["viewers"][0]["entityId"], owner.to_string());
+    }
+
+    #[tokio::test]
+    async fn index_sends_csp_and_hashed_cacheable_script() {
+        let addr = ui_with_dead_backend().await;
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.headers()[reqwest::header::CONTENT_SECURITY_POLICY],
+            assets::CONTENT_SECURITY_POLICY
+        );
+        let html = resp.text().await.unwrap();
+        let src = assets::ui_script().src();
+        assert!(html.contains(&format!("<script src=\"{}\"></script>", src)));
+
+        let resp = client
+            .get(format!("http://{}{}", addr, src))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            resp.headers()[reqwest::header::CACHE_CONTROL],
+            assets::ASSET_CACHE_CONTROL
+        );
+        assert!(resp.headers()[reqwest::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/javascript"));
+        assert_eq!(resp.text().await.unwrap(), assets::ui_script().body());
+
+        // A stale hash is not served under an immutable URL
+        let resp = client
+            .get(format!("http://{}/assets/js/ui.0000000000000000.js", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn index_csp_can_be_disabled() {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let addr = serve(SimpleUiServer::new(
+            SimpleUiConfig {
+                content_security_policy: false,
+                ..Default::default()
+            },
+            runtime,
+        ))
+        .await;
+        let resp = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert!(!resp
+            .headers()
+            .contains_key(reqwest::header::CONTENT_SECURITY_POLICY));
+    }
+
+    #[tokio::test]
+    async fn api_routes_are_served_locally_under_both_prefixes() {
+        let addr = ui_with_dead_backend().await;
+        let client = reqwest::Client::new();
+        let id = uuid::Uuid::new_v4().to_string();
+        for route in openapi::ROUTES {
+            let method = reqwest::Method::from_bytes(route.method.to_uppercase().as_bytes()).unwrap();
+            for prefix in [openapi::AGENT_PREFIX, openapi::API_V1_PREFIX] {
+                let path = route
+                    .path
+                    .split('/')
+                    .map(|s| if s.starts_with('{') { id.as_str() } else { s })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let resp = client
+                    .request(method.clone(), format!("http://{}{}{}", addr, prefix, path))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_ne!(resp.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED, "{}", path);
+                let body: serde_json::Value = resp.json().await.unwrap_or_default();
+                let code = body["error"]["code"].as_str().unwrap_or_default();
+                assert!(
+                    code != "not_found" && code != "backend_unreachable",
+                    "{} {}{} was not served locally: {}",
+                    route.method,
+                    prefix,
+                    path,
+                    code
+                );
+            }
+        }
+
+        // Other /agent paths still reach the proxy; /api/v1 has no proxy
+        let resp = client
+            .get(format!("http://{}/agent/health", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::BAD_GATEWAY);
+        let resp = client
+            .get(format!("http://{}/api/v1/health", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn serves_openapi_document_and_docs_page() {
+        let addr = ui_with_dead_backend().await;
+        let spec: serde_json::Value = reqwest::get(format!("http://{}/api/v1/openapi.json", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(spec["info"]["version"], openapi::API_VERSION);
+        assert_eq!(spec, openapi::spec());
+
+        let resp = reqwest::get(format!("http://{}/api/docs", addr)).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert!(resp.text().await.unwrap().contains("/api/v1/openapi.json"));
+    }
+
+    /// Page that only shows its config block
+    struct BarePage;
+
+    impl UiTemplate for BarePage {
+        fn render(&self, config: &str) -> String {
+            format!(
+                "<!doctype html><title>{{{{t:app.title}}}}</title>{}",
+                config
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn index_serves_configured_template() {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let addr = serve(
+            SimpleUiServer::builder()
+                .token("ui-token")
+                .template(Box::new(BarePage))
+                .build(runtime),
+        )
+        .await;
+        let html = reqwest::Client::new()
+            .get(format!("http://{}/?lang=de", addr))
+            .bearer_auth("ui-token")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        // Translated and given the same config as the built-in pages
+        assert!(html.starts_with("<!doctype html><title>"));
+        assert!(!html.contains("{{t:"));
+        assert!(!html.contains("id=\"t\""));
+        assert!(html.contains("\"token\":\"ui-token\""));
+        assert!(html.contains("\"locale\":\"de\""));
+    }
+
+    /// UI with token `s3cret` in front of a backend answering every `/agent`
+    /// call with the `Authorization` and `Cookie` headers it received
+    async fn ui_with_token(forward_token: bool) -> String {
+        let echo = |headers: HeaderMap| async move {
+            let header = |name| {
+                headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("-")
+                    .to_string()
+            };
+            format!(
+                "auth={} cookie={}",
+                header(axum::http::header::AUTHORIZATION),
+                header(axum::http::header::COOKIE)
+            )
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend = listener.local_addr().unwrap();
+        let app = Router::new().route("/agent/*rest", any(echo));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let addr = serve(
+            SimpleUiServer::builder()
+                .backend(format!("http://{}/agent", backend))
+                .token("s3cret")
+                .forward_token(forward_token)
+                .logs(true)
+                .build(runtime),
+        )
+        .await;
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn token_required_on_every_route() {
+        let ui = ui_with_token(false).await;
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let stream = format!("{}/agent/chat/stream", ui);
+        let chat = serde_json::json!({ "text": "hi", "stream": true });
+
+        let resp = client.post(&stream).json(&chat).send().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "missing_token");
+        let resp = client
+            .post(&stream)
+            .bearer_auth("guess")
+            .json(&chat)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+        for path in ["/logs", "/api/v1/openapi.json", "/agent/ui/locales"] {
+            let resp = client.get(format!("{}{}", ui, path)).send().await.unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED, "{}", path);
+        }
+
+        // The index offers a login form instead of the page and its token
+        let resp = client.get(format!("{}/", ui)).send().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let html = resp.text().await.unwrap();
+        assert!(html.contains("name=\"token\""));
+        assert!(!html.contains("s3cret"));
+
+        // Authorized streams go through, without the UI token
+        let resp = client
+            .post(&stream)
+            .bearer_auth("s3cret")
+            .json(&chat)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "auth=- cookie=-");
+    }
+
+    #[tokio::test]
+    async fn token_query_sets_cookie_and_can_be_forwarded() {
+        let ui = ui_with_token(false).await;
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+
+        let resp = client
+            .get(format!("{}/?lang=de&token=s3cret", ui))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::SEE_OTHER);
+        assert_eq!(resp.headers()[reqwest::header::LOCATION], "/?lang=de");
+        let cookie = resp.headers()[reqwest::header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        assert_eq!(cookie, "zoey_ui_token=s3cret");
+
+        let resp = client
+            .get(format!("{}/?lang=de", ui))
+            .header(reqwest::header::COOKIE, &cookie)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        let resp = client
+            .post(format!("{}/agent/chat/stream", ui))
+            .header(reqwest::header::COOKIE, format!("{}; theme=dark", cookie))
+            .json(&serde_json::json!({ "text": "hi" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "auth=- cookie=theme=dark");
+
+        // With forward_token the backend gets it as a bearer token
+        let forwarding = ui_with_token(true).await;
+        let resp = client
+            .get(format!("{}/agent/health", forwarding))
+            .header(reqwest::header::COOKIE, &cookie)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.text().await.unwrap(), "auth=Bearer s3cret cookie=-");
+    }
+}
@@ -0,0 +1,83 @@
+//! Generic chat page
+//!
+//! Chat, character picker, session state, reasoning chain and live logs for
+//! any character; driven by `assets/ui.js`.
+
+use super::UiTemplate;
+use crate::assets;
+
+/// Default chat UI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTemplate;
+
+impl UiTemplate for DefaultTemplate {
+    fn render(&self, config: &str) -> String {
+        PAGE.replace("{SCRIPT_SRC}", &assets::ui_script().src())
+            .replace("{ZOEY_CONFIG}", config)
+    }
+}
+
+const PAGE: &str = r#"<!doctype html><html><head><meta charset='utf-8'><title>{{t:app.title}}</title>
+    <style>
+      :root { --bg:#0f172a; --panel:#111827; --accent:#22d3ee; --text:#e5e7eb; --muted:#94a3b8; --agent:#10b981; }
+      body { margin:0; background: radial-gradient(1200px 600px at 10% 10%, #0b1220 0%, #0f172a 60%, #0b1020 100%); color:var(--text); font-family: Inter, system-ui, -apple-system, Segoe UI, Roboto, sans-serif; }
+      .wrap { display:grid; grid-template-columns: 1fr 320px; gap:24px; padding:24px; }
+      header { grid-column: 1 / -1; display:flex; align-items:center; justify-content:space-between; padding:16px 20px; background: rgba(255,255,255,0.03); border:1px solid rgba(255,255,255,0.08); border-radius:12px; backdrop-filter:saturate(120%) blur(6px); }
+      .brand { display:flex; align-items:center; gap:12px; font-weight:600; letter-spacing:.3px; }
+      .dot { width:10px; height:10px; border-radius:50%; background:var(--agent); box-shadow:0 0 12px var(--agent); }
+      .chat { background: rgba(255,255,255,0.03); border:1px solid rgba(255,255,255,0.08); border-radius:12px; padding:16px; min-height:420px; display:flex; flex-direction:column; gap:8px; overflow-y: auto; }
+      .msg { display:flex; gap:10px; align-items:flex-start; }
+      .bubble { max-width: 68ch; padding:10px 12px; border-radius:14px; line-height:1.4; font-size:15px; white-space: pre-wrap; overflow-wrap: anywhere; word-break: break-word; }
+      .user .bubble { background:#1f2937; border:1px solid #374151; }
+      .agent .bubble { background:#0b2a22; border:1px solid #134e4a; }
+      .msg-edit { align-self:center; padding:4px 6px; border:0; border-radius:6px; background:transparent; color:var(--muted); cursor:pointer; opacity:0; transition:opacity .15s; }
+      .msg.user:hover .msg-edit, .msg.user.editing .msg-edit { opacity:1; }
+      .msg.user.editing .bubble { border-color:var(--accent); }
+      .thoughts { margin-top:6px; padding:8px 10px; border-left:3px solid var(--accent); background:rgba(34, 211, 238, .08); color:#a5f3fc; border-radius:8px; font-size:13px; }
+      .input { display:flex; gap:10px; margin-top:10px; }
+      .input input { flex:1; padding:12px 14px; border-radius:10px; border:1px solid rgba(255,255,255,0.1); background:#0b1220; color:var(--text); }
+      .input button { padding:12px 18px; border-radius:10px; border:0; background:linear-gradient(90deg, #22d3ee, #10b981); color:#051018; font-weight:600; cursor:pointer; }
+      .panel { background: rgba(255,255,255,0.03); border:1px solid rgba(255,255,255,0.08); border-radius:12px; padding:16px; }
+      .muted { color:var(--muted); font-size:13px; }
+      .typing { display:inline-block; }
+      .typing span { display:inline-block; width:6px; height:6px; margin-right:4px; background:var(--muted); border-radius:50%; animation: blink 1.2s infinite; }
+      .typing span:nth-child(2) { animation-delay: .2s }
+      .typing span:nth-child(3) { animation-delay: .4s }
+      @keyframes blink { 0%, 80%, 100% { opacity:.2 } 40% { opacity:1 } }
+      @media (max-width: 980px) { .wrap { grid-template-columns: 1fr } }
+    </style>
+    </head>
+    <body>
+      <div class="wrap">
+        <header>
+          <div class="brand"><div class="dot"></div> {{t:app.brand}}</div>
+          <div style="display:flex; gap:10px; align-items:center;">
+            <select id="character" style="background:#0b1220; color:var(--text); border:1px solid rgba(255,255,255,.1); border-radius:8px; padding:8px 10px;"></select>
+            <button id="applyChar" style="padding:8px 12px; border-radius:8px; border:0; background:linear-gradient(90deg, #22d3ee, #10b981); color:#051018; font-weight:600; cursor:pointer;">{{t:character.use}}</button>
+            
+          </div>
+        </header>
+        <div class="chat" id="chat"></div>
+        <aside class="panel">
+          <div style="font-weight:600; margin-bottom:8px;">{{t:session.title}}</div>
+          <div class="muted" id="session">{{t:session.room}} <span id="room"></span></div>
+          <div style="font-weight:600; margin:12px 0 8px;">{{t:state.title}}</div>
+          <div class="muted" id="state">{{t:state.pending}}</div>
+          
+          <div style="font-weight:600; margin:12px 0 8px;">{{t:chain.title}}</div>
+          <div id="chain" class="muted" style="display:flex; flex-direction:column; gap:8px;"></div>
+          <div style="font-weight:600; margin:12px 0 8px;">{{t:logs.title}}</div>
+          <div id="logs" class="muted" style="display:flex; flex-direction:column; gap:6px; max-height:180px; overflow:auto; border:1px solid rgba(255,255,255,0.08); border-radius:8px; padding:8px;"></div>
+          <div style="display:flex; gap:8px; margin-top:6px;">
+            <button id="clearLogs" style="padding:6px 10px; border-radius:8px; border:0; background:#1f2937; color:#9ca3af; font-weight:600; cursor:pointer;">{{t:logs.clear}}</button>
+            <button id="copyLogs" style="padding:6px 10px; border-radius:8px; border:0; background:linear-gradient(90deg, #22d3ee, #10b981); color:#051018; font-weight:600; cursor:pointer;">{{t:logs.copy}}</button>
+          </div>
+        </aside>
+        <div class="input" style="grid-column: 1 / -1">
+          <input id="t" placeholder="{{t:input.placeholder}}" />
+          <button id="send">{{t:input.send}}</button>
+        </div>
+      </div>
+      {ZOEY_CONFIG}
+      <script src="{SCRIPT_SRC}"></script>
+    </body></html>"#;
@@ -0,0 +1,63 @@
+//! Escaping for values placed into served pages
+//!
+//! Markup text and attributes go through [`escape_html`]. JSON embedded in a
+//! `<script type="application/json">` element goes through
+//! [`escape_script_json`], which keeps it valid JSON while making sure no
+//! value can close the element.
+
+/// Escape a value for safe inclusion in HTML text or attributes
+pub fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Serialized JSON with `<`, `>` and `&` written as unicode escapes
+///
+/// `JSON.parse` restores them, so the data is unchanged for the page script.
+pub fn escape_script_json(json: &str) -> String {
+    json.replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html_covers_markup_and_quotes() {
+        assert_eq!(
+            escape_html(r#"<a href="x" title='y'>Tom & Jerry</a>"#),
+            "&lt;a href=&quot;x&quot; title=&#39;y&#39;&gt;Tom &amp; Jerry&lt;/a&gt;"
+        );
+        // Already escaped text is escaped again rather than trusted
+        assert_eq!(escape_html("&amp;"), "&amp;amp;");
+        assert_eq!(escape_html("Zoë 👋"), "Zoë 👋");
+        assert_eq!(escape_html(""), "");
+    }
+
+    #[test]
+    fn test_script_json_round_trips() {
+        let value = serde_json::json!({
+            "x": "</script><!-- & -->",
+            "nested": ["<b>", { "y": "a&b" }],
+        });
+        let escaped = escape_script_json(&value.to_string());
+        assert!(!escaped.contains('<'));
+        assert!(!escaped.contains('>'));
+        assert!(!escaped.contains('&'));
+        assert!(!escaped.to_lowercase().contains("</script"));
+        let parsed: serde_json::Value = serde_json::from_str(&escaped).unwrap();
+        assert_eq!(parsed, value);
+    }
+}
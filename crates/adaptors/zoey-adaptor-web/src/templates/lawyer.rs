@@ -0,0 +1,935 @@
+//! Zoey Lawyer case management page
+//!
+//! Served instead of the generic chat when the character is the legal
+//! assistant (see [`for_character`](super::for_character)). Cases, documents
+//! and the chat pane are driven by `assets/lawyer.js`.
+
+use super::UiTemplate;
+use crate::assets;
+
+/// Case management UI for the Zoey Lawyer character
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LawyerTemplate;
+
+impl UiTemplate for LawyerTemplate {
+    fn render(&self, config: &str) -> String {
+        PAGE.replace("{SCRIPT_SRC}", &assets::lawyer_script().src())
+            .replace("{ZOEY_CONFIG}", config)
+    }
+}
+
+const PAGE: &str = r##"<!doctype html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>{{t:legal.title}}</title>
+  <link rel="preconnect" href="https://fonts.googleapis.com">
+  <link rel="preconnect" href="https://fonts.gstatic.com" crossorigin>
+  <link href="https://fonts.googleapis.com/css2?family=Inter:wght@400;500;600;700&display=swap" rel="stylesheet">
+  <style>
+    :root {
+      --bg: #f8fafc;
+      --card: #ffffff;
+      --primary: #22d3ee;
+      --secondary: #10b981;
+      --text: #1e293b;
+      --muted: #64748b;
+      --border: rgba(0,0,0,0.08);
+      --sidebar-bg: #f1f5f9;
+      --hover: #e2e8f0;
+      --danger: #ef4444;
+      --warning: #f59e0b;
+    }
+    * { box-sizing: border-box; margin: 0; padding: 0; }
+    body {
+      font-family: 'Inter', system-ui, -apple-system, sans-serif;
+      background: var(--bg);
+      color: var(--text);
+      min-height: 100vh;
+    }
+    
+    /* Main Layout */
+    .app-container {
+      display: grid;
+      grid-template-columns: 280px 1fr 300px;
+      min-height: 100vh;
+    }
+    
+    /* Left Sidebar - Cases */
+    .case-sidebar {
+      background: var(--sidebar-bg);
+      border-right: 1px solid var(--border);
+      display: flex;
+      flex-direction: column;
+      overflow: hidden;
+    }
+    .sidebar-header {
+      padding: 20px;
+      border-bottom: 1px solid var(--border);
+      background: var(--card);
+    }
+    .brand {
+      display: flex;
+      align-items: center;
+      gap: 12px;
+      margin-bottom: 4px;
+    }
+    .brand-icon {
+      width: 40px;
+      height: 40px;
+      border-radius: 10px;
+      background: linear-gradient(135deg, var(--primary), var(--secondary));
+      display: flex;
+      align-items: center;
+      justify-content: center;
+      color: white;
+      font-weight: 700;
+      font-size: 18px;
+    }
+    .brand-text {
+      font-weight: 700;
+      font-size: 18px;
+      color: var(--text);
+    }
+    .brand-subtitle {
+      font-size: 12px;
+      color: var(--muted);
+      margin-left: 52px;
+    }
+    
+    .new-case-btn {
+      width: 100%;
+      padding: 12px 16px;
+      margin-top: 16px;
+      border: 2px dashed var(--border);
+      border-radius: 10px;
+      background: transparent;
+      color: var(--muted);
+      font-weight: 600;
+      font-size: 14px;
+      cursor: pointer;
+      transition: all 0.2s;
+      display: flex;
+      align-items: center;
+      justify-content: center;
+      gap: 8px;
+    }
+    .new-case-btn:hover {
+      border-color: var(--primary);
+      color: var(--primary);
+      background: rgba(34, 211, 238, 0.05);
+    }
+    
+    .case-list {
+      flex: 1;
+      overflow-y: auto;
+      padding: 12px;
+    }
+    .case-section-title {
+      font-size: 11px;
+      font-weight: 600;
+      text-transform: uppercase;
+      letter-spacing: 0.5px;
+      color: var(--muted);
+      padding: 8px 12px;
+      margin-top: 8px;
+    }
+    .case-item {
+      padding: 12px 14px;
+      border-radius: 10px;
+      cursor: pointer;
+      transition: all 0.15s;
+      margin-bottom: 4px;
+      border: 1px solid transparent;
+    }
+    .case-item:hover {
+      background: var(--hover);
+    }
+    .case-item.active {
+      background: var(--card);
+      border-color: var(--primary);
+      box-shadow: 0 2px 8px rgba(34, 211, 238, 0.15);
+    }
+    .case-item-name {
+      font-weight: 600;
+      font-size: 14px;
+      margin-bottom: 4px;
+      white-space: nowrap;
+      overflow: hidden;
+      text-overflow: ellipsis;
+    }
+    .case-item-meta {
+      font-size: 12px;
+      color: var(--muted);
+      display: flex;
+      align-items: center;
+      gap: 8px;
+    }
+    .case-status {
+      display: inline-flex;
+      align-items: center;
+      gap: 4px;
+      font-size: 11px;
+      font-weight: 500;
+      padding: 2px 8px;
+      border-radius: 12px;
+    }
+    .case-status.active {
+      background: rgba(16, 185, 129, 0.1);
+      color: #059669;
+    }
+    .case-status.closed {
+      background: rgba(100, 116, 139, 0.1);
+      color: var(--muted);
+    }
+    .closed-section {
+      margin-top: 8px;
+    }
+    .closed-toggle {
+      display: flex;
+      align-items: center;
+      gap: 8px;
+      padding: 8px 12px;
+      font-size: 12px;
+      color: var(--muted);
+      cursor: pointer;
+      border-radius: 8px;
+    }
+    .closed-toggle:hover {
+      background: var(--hover);
+    }
+    .closed-cases {
+      display: none;
+    }
+    .closed-cases.expanded {
+      display: block;
+    }
+    
+    /* Main Chat Area */
+    .main-content {
+      display: flex;
+      flex-direction: column;
+      background: var(--bg);
+    }
+    .case-header {
+      padding: 16px 24px;
+      background: var(--card);
+      border-bottom: 1px solid var(--border);
+      display: flex;
+      align-items: center;
+      justify-content: space-between;
+    }
+    .case-title {
+      font-size: 18px;
+      font-weight: 700;
+    }
+    .case-header-actions {
+      display: flex;
+      gap: 10px;
+    }
+    .share-btn {
+      padding: 8px 16px;
+      border-radius: 8px;
+      border: 1px solid var(--border);
+      background: var(--card);
+      color: var(--text);
+      font-weight: 600;
+      font-size: 13px;
+      cursor: pointer;
+      display: flex;
+      align-items: center;
+      gap: 6px;
+      transition: all 0.15s;
+    }
+    .share-btn:hover {
+      border-color: var(--primary);
+      color: var(--primary);
+    }
+    
+    .chat-container {
+      flex: 1;
+      overflow-y: auto;
+      padding: 24px;
+      display: flex;
+      flex-direction: column;
+      gap: 16px;
+    }
+    .welcome-message {
+      text-align: center;
+      padding: 60px 40px;
+      color: var(--muted);
+    }
+    .welcome-icon {
+      width: 80px;
+      height: 80px;
+      border-radius: 20px;
+      background: linear-gradient(135deg, var(--primary), var(--secondary));
+      display: flex;
+      align-items: center;
+      justify-content: center;
+      margin: 0 auto 20px;
+      font-size: 36px;
+      color: white;
+    }
+    .welcome-title {
+      font-size: 20px;
+      font-weight: 700;
+      color: var(--text);
+      margin-bottom: 8px;
+    }
+    .welcome-subtitle {
+      font-size: 14px;
+      max-width: 400px;
+      margin: 0 auto;
+    }
+    
+    .msg {
+      display: flex;
+      gap: 12px;
+      max-width: 85%;
+    }
+    .msg.user {
+      margin-left: auto;
+      flex-direction: row-reverse;
+    }
+    .msg-avatar {
+      width: 36px;
+      height: 36px;
+      border-radius: 10px;
+      display: flex;
+      align-items: center;
+      justify-content: center;
+      font-weight: 600;
+      font-size: 14px;
+      flex-shrink: 0;
+    }
+    .msg.agent .msg-avatar {
+      background: linear-gradient(135deg, var(--primary), var(--secondary));
+      color: white;
+    }
+    .msg.user .msg-avatar {
+      background: var(--hover);
+      color: var(--muted);
+    }
+    .bubble {
+      padding: 12px 16px;
+      border-radius: 16px;
+      font-size: 14px;
+      line-height: 1.5;
+      white-space: pre-wrap;
+      word-break: break-word;
+    }
+    .msg.agent .bubble {
+      background: var(--card);
+      border: 1px solid var(--border);
+      border-bottom-left-radius: 4px;
+    }
+    .msg.user .bubble {
+      background: linear-gradient(135deg, var(--primary), var(--secondary));
+      color: white;
+      border-bottom-right-radius: 4px;
+    }
+    .msg-edit {
+      align-self: center;
+      padding: 4px 6px;
+      border: 0;
+      border-radius: 6px;
+      background: transparent;
+      color: var(--muted);
+      cursor: pointer;
+      opacity: 0;
+      transition: opacity 0.15s;
+    }
+    .msg.user:hover .msg-edit {
+      opacity: 1;
+    }
+    
+    .input-container {
+      padding: 16px 24px 24px;
+      background: var(--card);
+      border-top: 1px solid var(--border);
+    }
+    .input-wrap {
+      display: flex;
+      gap: 12px;
+      align-items: flex-end;
+    }
+    .input-wrap input {
+      flex: 1;
+      padding: 14px 18px;
+      border-radius: 12px;
+      border: 1px solid var(--border);
+      background: var(--bg);
+      font-size: 14px;
+      color: var(--text);
+      outline: none;
+      transition: all 0.15s;
+    }
+    .input-wrap input:focus {
+      border-color: var(--primary);
+      box-shadow: 0 0 0 3px rgba(34, 211, 238, 0.1);
+    }
+    .input-wrap input::placeholder {
+      color: var(--muted);
+    }
+    .send-btn {
+      padding: 14px 24px;
+      border-radius: 12px;
+      border: none;
+      background: linear-gradient(135deg, var(--primary), var(--secondary));
+      color: white;
+      font-weight: 600;
+      font-size: 14px;
+      cursor: pointer;
+      transition: all 0.15s;
+    }
+    .send-btn:hover {
+      transform: translateY(-1px);
+      box-shadow: 0 4px 12px rgba(34, 211, 238, 0.3);
+    }
+    .send-btn:disabled {
+      opacity: 0.5;
+      cursor: not-allowed;
+      transform: none;
+      box-shadow: none;
+    }
+    
+    /* Right Sidebar - Case Details */
+    .detail-sidebar {
+      background: var(--card);
+      border-left: 1px solid var(--border);
+      display: flex;
+      flex-direction: column;
+      overflow: hidden;
+    }
+    .detail-header {
+      padding: 20px;
+      border-bottom: 1px solid var(--border);
+      font-weight: 700;
+      font-size: 14px;
+    }
+    .detail-content {
+      flex: 1;
+      overflow-y: auto;
+      padding: 16px 20px;
+    }
+    .detail-section {
+      margin-bottom: 24px;
+    }
+    .detail-section-title {
+      font-size: 11px;
+      font-weight: 600;
+      text-transform: uppercase;
+      letter-spacing: 0.5px;
+      color: var(--muted);
+      margin-bottom: 12px;
+    }
+    .participant-list {
+      display: flex;
+      flex-direction: column;
+      gap: 8px;
+    }
+    .participant {
+      display: flex;
+      align-items: center;
+      gap: 10px;
+      padding: 8px 10px;
+      border-radius: 8px;
+      background: var(--bg);
+    }
+    .participant-avatar {
+      width: 32px;
+      height: 32px;
+      border-radius: 8px;
+      background: var(--hover);
+      display: flex;
+      align-items: center;
+      justify-content: center;
+      font-size: 12px;
+      font-weight: 600;
+      color: var(--muted);
+    }
+    .participant-info {
+      flex: 1;
+    }
+    .participant-name {
+      font-size: 13px;
+      font-weight: 600;
+    }
+    .participant-role {
+      font-size: 11px;
+      color: var(--muted);
+    }
+    .role-badge {
+      display: inline-block;
+      padding: 1px 6px;
+      border-radius: 4px;
+      font-size: 10px;
+      font-weight: 600;
+      text-transform: uppercase;
+      background: var(--hover);
+    }
+    .role-badge.owner { background: rgba(99, 102, 241, 0.15); color: #6366f1; }
+    .role-badge.collaborator { background: rgba(34, 197, 94, 0.15); color: #16a34a; }
+    .presence-dot {
+      display: inline-block;
+      width: 8px;
+      height: 8px;
+      margin-left: 6px;
+      border-radius: 50%;
+      background: #22c55e;
+      box-shadow: 0 0 0 2px rgba(34, 197, 94, 0.2);
+      vertical-align: middle;
+    }
+    .presence-summary {
+      font-size: 12px;
+      color: var(--muted);
+      margin-bottom: 8px;
+    }
+    .presence-summary:empty {
+      display: none;
+    }
+    .participant-actions {
+      display: flex;
+      gap: 4px;
+    }
+    .participant-actions select,
+    .participant-actions button {
+      font-size: 11px;
+      padding: 2px 6px;
+      border: 1px solid var(--border);
+      border-radius: 4px;
+      background: var(--bg);
+      color: inherit;
+      cursor: pointer;
+    }
+    
+    .case-info-item {
+      display: flex;
+      justify-content: space-between;
+      padding: 8px 0;
+      border-bottom: 1px solid var(--border);
+      font-size: 13px;
+    }
+    .case-info-label {
+      color: var(--muted);
+    }
+    .case-info-value {
+      font-weight: 500;
+    }
+    
+    .action-buttons {
+      display: flex;
+      flex-direction: column;
+      gap: 8px;
+    }
+    .action-btn {
+      width: 100%;
+      padding: 10px 14px;
+      border-radius: 8px;
+      border: 1px solid var(--border);
+      background: var(--card);
+      color: var(--text);
+      font-weight: 600;
+      font-size: 13px;
+      cursor: pointer;
+      transition: all 0.15s;
+    }
+    .action-btn:hover {
+      background: var(--bg);
+    }
+    .action-btn.danger {
+      border-color: rgba(239, 68, 68, 0.3);
+      color: var(--danger);
+    }
+    .action-btn.danger:hover {
+      background: rgba(239, 68, 68, 0.05);
+    }
+    
+    /* Modal */
+    .modal-overlay {
+      position: fixed;
+      inset: 0;
+      background: rgba(0,0,0,0.5);
+      display: none;
+      align-items: center;
+      justify-content: center;
+      z-index: 1000;
+    }
+    .modal-overlay.active {
+      display: flex;
+    }
+    .modal {
+      background: var(--card);
+      border-radius: 16px;
+      padding: 24px;
+      width: 100%;
+      max-width: 420px;
+      box-shadow: 0 20px 60px rgba(0,0,0,0.2);
+    }
+    .modal-title {
+      font-size: 18px;
+      font-weight: 700;
+      margin-bottom: 16px;
+    }
+    .modal-input {
+      width: 100%;
+      padding: 12px 14px;
+      border-radius: 10px;
+      border: 1px solid var(--border);
+      font-size: 14px;
+      margin-bottom: 12px;
+      outline: none;
+    }
+    .modal-input:focus {
+      border-color: var(--primary);
+    }
+    .modal-actions {
+      display: flex;
+      gap: 10px;
+      justify-content: flex-end;
+      margin-top: 16px;
+    }
+    .modal-btn {
+      padding: 10px 20px;
+      border-radius: 8px;
+      font-weight: 600;
+      font-size: 14px;
+      cursor: pointer;
+      border: 1px solid var(--border);
+      background: var(--card);
+      color: var(--text);
+    }
+    .modal-btn.primary {
+      background: linear-gradient(135deg, var(--primary), var(--secondary));
+      color: white;
+      border: none;
+    }
+    
+    /* Share Modal */
+    .share-link-container {
+      display: flex;
+      gap: 8px;
+      margin-top: 8px;
+    }
+    .share-link-input {
+      flex: 1;
+      padding: 10px 14px;
+      border-radius: 8px;
+      border: 1px solid var(--border);
+      background: var(--bg);
+      font-size: 13px;
+      color: var(--muted);
+    }
+    .copy-btn {
+      padding: 10px 16px;
+      border-radius: 8px;
+      border: none;
+      background: var(--primary);
+      color: white;
+      font-weight: 600;
+      font-size: 13px;
+      cursor: pointer;
+    }
+    
+    /* Typing indicator */
+    .typing-indicator {
+      display: flex;
+      gap: 4px;
+      padding: 8px 12px;
+    }
+    .typing-indicator span {
+      width: 8px;
+      height: 8px;
+      background: var(--muted);
+      border-radius: 50%;
+      animation: typing 1.2s infinite;
+    }
+    .typing-indicator span:nth-child(2) { animation-delay: 0.2s; }
+    .typing-indicator span:nth-child(3) { animation-delay: 0.4s; }
+    @keyframes typing {
+      0%, 80%, 100% { opacity: 0.3; transform: scale(0.8); }
+      40% { opacity: 1; transform: scale(1); }
+    }
+    
+    /* Empty state */
+    .empty-state {
+      text-align: center;
+      padding: 40px 20px;
+      color: var(--muted);
+    }
+    .empty-state-icon {
+      font-size: 48px;
+      margin-bottom: 16px;
+      opacity: 0.5;
+    }
+    
+    /* Toast notification */
+    .toast {
+      position: fixed;
+      bottom: 24px;
+      right: 24px;
+      padding: 14px 20px;
+      background: var(--text);
+      color: white;
+      border-radius: 10px;
+      font-size: 14px;
+      font-weight: 500;
+      box-shadow: 0 4px 20px rgba(0,0,0,0.2);
+      transform: translateY(100px);
+      opacity: 0;
+      transition: all 0.3s;
+      z-index: 1001;
+    }
+    .toast.show {
+      transform: translateY(0);
+      opacity: 1;
+    }
+    
+    /* File Drop Zone */
+    .file-drop-section {
+      padding: 12px;
+      border-top: 1px solid var(--border);
+      background: var(--card);
+    }
+    .file-drop-title {
+      font-size: 11px;
+      font-weight: 600;
+      text-transform: uppercase;
+      letter-spacing: 0.5px;
+      color: var(--muted);
+      margin-bottom: 10px;
+    }
+    .file-drop-zone {
+      border: 2px dashed var(--border);
+      border-radius: 10px;
+      padding: 20px 16px;
+      text-align: center;
+      cursor: pointer;
+      transition: all 0.2s;
+      background: var(--bg);
+    }
+    .file-drop-zone:hover, .file-drop-zone.dragover {
+      border-color: var(--primary);
+      background: rgba(34, 211, 238, 0.05);
+    }
+    .file-drop-zone.dragover {
+      transform: scale(1.02);
+    }
+    .file-drop-icon {
+      font-size: 28px;
+      margin-bottom: 8px;
+      opacity: 0.6;
+    }
+    .file-drop-text {
+      font-size: 13px;
+      color: var(--muted);
+      margin-bottom: 4px;
+    }
+    .file-drop-hint {
+      font-size: 11px;
+      color: var(--muted);
+      opacity: 0.7;
+    }
+    .file-list {
+      margin-top: 12px;
+      display: flex;
+      flex-direction: column;
+      gap: 6px;
+      max-height: 150px;
+      overflow-y: auto;
+    }
+    .file-item {
+      display: flex;
+      align-items: center;
+      gap: 8px;
+      padding: 8px 10px;
+      background: var(--bg);
+      border-radius: 8px;
+      font-size: 12px;
+    }
+    .file-item-icon {
+      font-size: 16px;
+    }
+    .file-item-name {
+      flex: 1;
+      white-space: nowrap;
+      overflow: hidden;
+      text-overflow: ellipsis;
+    }
+    .file-item-status {
+      font-size: 10px;
+      padding: 2px 6px;
+      border-radius: 4px;
+      background: rgba(16, 185, 129, 0.1);
+      color: #059669;
+    }
+    .file-item-status.uploading {
+      background: rgba(245, 158, 11, 0.1);
+      color: #d97706;
+    }
+    .file-item-remove {
+      background: none;
+      border: none;
+      color: var(--muted);
+      cursor: pointer;
+      font-size: 14px;
+      padding: 2px;
+    }
+    .file-item-remove:hover {
+      color: var(--danger);
+    }
+    
+    @media (max-width: 1100px) {
+      .app-container {
+        grid-template-columns: 1fr;
+      }
+      .case-sidebar, .detail-sidebar {
+        display: none;
+      }
+    }
+  </style>
+</head>
+<body>
+  <div class="app-container">
+    <!-- Left Sidebar - Cases -->
+    <aside class="case-sidebar">
+      <div class="sidebar-header">
+        <div class="brand">
+          <div class="brand-icon">Z</div>
+          <div class="brand-text">Zoey</div>
+        </div>
+        <div class="brand-subtitle">{{t:legal.subtitle}}</div>
+        <button class="new-case-btn" data-action="showNewCaseModal">
+          <span>+</span> {{t:legal.new_case}}
+        </button>
+      </div>
+      <div class="case-list" id="caseList">
+        <div class="case-section-title">{{t:legal.active_cases}}</div>
+        <div id="activeCases"></div>
+        <div class="closed-section">
+          <div class="closed-toggle" data-action="toggleClosedCases">
+            <span id="closedChevron">▸</span> {{t:legal.closed_cases}}
+          </div>
+          <div class="closed-cases" id="closedCases"></div>
+        </div>
+      </div>
+      <!-- File Drop Zone -->
+      <div class="file-drop-section" id="fileDropSection" style="display: none;">
+        <div class="file-drop-title">{{t:legal.documents}}</div>
+        <div class="file-drop-zone" id="fileDropZone">
+          <div class="file-drop-icon">📄</div>
+          <div class="file-drop-text">{{t:legal.drop_files}}</div>
+          <div class="file-drop-hint">PDF, Excel, TXT, MD, CSV, JSON</div>
+        </div>
+        <input type="file" id="fileInput" multiple accept=".pdf,.xlsx,.xls,.txt,.md,.csv,.json" style="display: none;" />
+        <div class="file-list" id="fileList"></div>
+      </div>
+    </aside>
+    
+    <!-- Main Chat Area -->
+    <main class="main-content">
+      <div class="case-header" id="caseHeader" style="display: none;">
+        <div class="case-title" id="currentCaseTitle">{{t:legal.no_case_selected}}</div>
+        <div class="case-header-actions">
+          <button class="share-btn" data-action="showShareModal">
+            <span>🔗</span> {{t:legal.share_case}}
+          </button>
+        </div>
+      </div>
+      <div class="chat-container" id="chat">
+        <div class="welcome-message" id="welcomeMessage">
+          <div class="welcome-icon">Z</div>
+          <div class="welcome-title">{{t:legal.welcome_title}}</div>
+          <div class="welcome-subtitle">{{t:legal.welcome_subtitle}}</div>
+        </div>
+      </div>
+      <div class="input-container" id="inputContainer" style="display: none;">
+        <div class="input-wrap">
+          <input type="text" id="messageInput" placeholder="{{t:legal.input_placeholder}}" />
+          <button class="send-btn" id="sendBtn" data-action="sendMessage">{{t:legal.send}}</button>
+        </div>
+      </div>
+    </main>
+    
+    <!-- Right Sidebar - Case Details -->
+    <aside class="detail-sidebar" id="detailSidebar" style="display: none;">
+      <div class="detail-header">{{t:legal.case_details}}</div>
+      <div class="detail-content">
+        <div class="detail-section">
+          <div class="detail-section-title">{{t:legal.participants}}</div>
+          <div class="presence-summary" id="presenceSummary"></div>
+          <div class="participant-list" id="participantList">
+            <div class="participant">
+              <div class="participant-avatar">Y</div>
+              <div class="participant-info">
+                <div class="participant-name">{{t:legal.you}}</div>
+                <div class="participant-role">{{t:legal.owner}}</div>
+              </div>
+            </div>
+          </div>
+        </div>
+        <div class="detail-section">
+          <div class="detail-section-title">{{t:legal.case_information}}</div>
+          <div id="caseInfo">
+            <div class="case-info-item">
+              <span class="case-info-label">{{t:legal.created}}</span>
+              <span class="case-info-value" id="caseCreated">-</span>
+            </div>
+            <div class="case-info-item">
+              <span class="case-info-label">{{t:legal.messages}}</span>
+              <span class="case-info-value" id="caseMessages">0</span>
+            </div>
+            <div class="case-info-item">
+              <span class="case-info-label">{{t:legal.status}}</span>
+              <span class="case-info-value" id="caseStatus">{{t:legal.status_active}}</span>
+            </div>
+          </div>
+        </div>
+        <div class="detail-section">
+          <div class="detail-section-title">{{t:legal.actions}}</div>
+          <div class="action-buttons">
+            <button class="action-btn" data-action="closeCaseAction">{{t:legal.close_case}}</button>
+            <button class="action-btn danger" data-action="deleteCaseAction">{{t:legal.delete_case}}</button>
+          </div>
+        </div>
+      </div>
+    </aside>
+  </div>
+  
+  <!-- New Case Modal -->
+  <div class="modal-overlay" id="newCaseModal">
+    <div class="modal">
+      <div class="modal-title">{{t:legal.create_case_title}}</div>
+      <input type="text" class="modal-input" id="newCaseName" placeholder="{{t:legal.case_name_placeholder}}" />
+      <input type="text" class="modal-input" id="newCaseMatter" placeholder="{{t:legal.matter_placeholder}}" />
+      <div class="modal-actions">
+        <button class="modal-btn" data-action="hideNewCaseModal">{{t:legal.cancel}}</button>
+        <button class="modal-btn primary" data-action="createCase">{{t:legal.create_case}}</button>
+      </div>
+    </div>
+  </div>
+  
+  <!-- Share Modal -->
+  <div class="modal-overlay" id="shareModal">
+    <div class="modal">
+      <div class="modal-title">{{t:legal.share_case}}</div>
+      <p style="color: var(--muted); font-size: 14px; margin-bottom: 12px;">{{t:legal.share_hint}}</p>
+      <div class="share-link-container">
+        <input type="text" class="share-link-input" id="shareLink" readonly />
+        <button class="copy-btn" data-action="copyShareLink">{{t:legal.copy}}</button>
+      </div>
+      <div class="modal-actions">
+        <button class="modal-btn" data-action="hideShareModal">{{t:legal.close}}</button>
+      </div>
+    </div>
+  </div>
+  
+  <!-- Toast -->
+  <div class="toast" id="toast"></div>
+  
+  {ZOEY_CONFIG}
+  <script src="{SCRIPT_SRC}"></script>
+</body>
+</html>"##;
@@ -0,0 +1,289 @@
+//! Pages served at `/`
+//!
+//! A page is a [`UiTemplate`]: HTML with a `{ZOEY_CONFIG}` slot for the
+//! per-request config block and `{{t:key}}` placeholders translated after
+//! rendering. The script that drives a built-in page is served as a hashed
+//! asset under `/assets/js/`, bundled with the WebSocket chat client and the
+//! model settings panel defined here.
+//!
+//! Without a template set on the server, [`for_character`] picks the case
+//! management page for the Zoey Lawyer character and the generic chat
+//! otherwise.
+
+mod default;
+mod escape;
+mod lawyer;
+
+pub use default::DefaultTemplate;
+pub use escape::{escape_html, escape_script_json};
+pub use lawyer::LawyerTemplate;
+
+/// HTML page served at `/`
+///
+/// Pages are sent with a `Content-Security-Policy` that only allows scripts
+/// from the UI's own origin (unless the server turns it off), so a custom
+/// template should load its code with `<script src>` rather than inline.
+pub trait UiTemplate: Send + Sync {
+    /// Full page with `config`, the `#zoey-config` JSON block, embedded
+    fn render(&self, config: &str) -> String;
+}
+
+/// Built-in page for a character: case management for Zoey Lawyer, chat otherwise
+pub fn for_character(character_name: &str) -> &'static dyn UiTemplate {
+    if is_zoey_lawyer(character_name) {
+        &LawyerTemplate
+    } else {
+        &DefaultTemplate
+    }
+}
+
+/// Check if the current character is Zoey Lawyer
+fn is_zoey_lawyer(character_name: &str) -> bool {
+    let lower = character_name.to_lowercase();
+    lower.contains("legal") && lower.contains("zoey")
+}
+
+/// Client for `/agent/ws/chat` shared by the templates
+///
+/// `wsChat(payload, handlers)` resolves `false` when WebSocket is unavailable
+/// or the upgrade is refused, in which case the caller falls back to the
+/// fetch-based SSE stream. Otherwise it calls `onChunk(text)` per chunk and
+/// `onDone(lastText)` or `onError(error)` once, then resolves `true`. Aborting
+/// `handlers.signal` sends a cancel frame.
+pub(crate) const WS_CHAT_JS: &str = r#"
+    let wsChatAvailable = typeof WebSocket !== 'undefined';
+    function wsChatUrl() {
+      const u = new URL(API + '/ws/chat', location.href);
+      u.protocol = u.protocol === 'https:' ? 'wss:' : 'ws:';
+      if (typeof TOKEN === 'string' && TOKEN) u.searchParams.set('token', TOKEN);
+      return u.toString();
+    }
+    function wsChat(payload, handlers) {
+      return new Promise((resolve) => {
+        if (!wsChatAvailable) { resolve(false); return; }
+        let ws;
+        try { ws = new WebSocket(wsChatUrl()); } catch { wsChatAvailable = false; resolve(false); return; }
+        let opened = false;
+        let settled = false;
+        const finish = () => { if (settled) return; settled = true; try { ws.close(); } catch {} resolve(true); };
+        ws.onopen = () => { opened = true; ws.send(JSON.stringify(Object.assign({ type: 'chat' }, payload))); };
+        ws.onmessage = (ev) => {
+          let frame;
+          try { frame = JSON.parse(ev.data); } catch { return; }
+          if (frame.type === 'chunk') { if (handlers.onChunk) handlers.onChunk(frame.text || ''); }
+          else if (frame.type === 'final') { if (handlers.onDone) handlers.onDone(frame.text || ''); finish(); }
+          else if (frame.type === 'error') { if (handlers.onError) handlers.onError(frame.error || 'error'); finish(); }
+        };
+        ws.onclose = () => {
+          if (settled) return;
+          if (!opened) { wsChatAvailable = false; settled = true; resolve(false); return; }
+          if (handlers.onError) handlers.onError('connection_interrupted');
+          finish();
+        };
+        if (handlers.signal) {
+          handlers.signal.addEventListener('abort', () => { try { ws.send(JSON.stringify({ type: 'cancel' })); } catch {} });
+        }
+      });
+    }
+"#;
+
+/// Optional model settings panel, shown when `SimpleUiConfig::model_controls`
+/// is set (`MODEL_CONTROLS` is injected via `{MODEL_JS}`).
+///
+/// Values are kept in `localStorage` and merged into chat bodies by
+/// `withModelParams(body)` as `params: { temperature, max_tokens }` plus an
+/// optional `model`. The backend validates the ranges; the inputs only hint
+/// them.
+pub(crate) const MODEL_CONTROLS_JS: &str = r#"
+    const MODEL_SETTINGS_KEY = 'zoey_model_settings';
+    function modelControlsEnabled() { return typeof MODEL_CONTROLS !== 'undefined' && MODEL_CONTROLS; }
+    function loadModelSettings() {
+      try { return JSON.parse(localStorage.getItem(MODEL_SETTINGS_KEY) || '{}') || {}; } catch { return {}; }
+    }
+    function withModelParams(body) {
+      if (!modelControlsEnabled()) return body;
+      const s = loadModelSettings();
+      const params = {};
+      if (s.temperature !== undefined && s.temperature !== '') params.temperature = Number(s.temperature);
+      if (s.maxTokens) params.max_tokens = parseInt(s.maxTokens, 10);
+      if (Object.keys(params).length) body.params = params;
+      if (s.model) body.model = s.model;
+      return body;
+    }
+    function setupModelControls() {
+      if (!modelControlsEnabled() || document.getElementById('modelControls')) return;
+      const toggle = document.createElement('button');
+      toggle.type = 'button';
+      toggle.textContent = '⚙';
+      toggle.title = i18nText('settings.title');
+      toggle.style.cssText = 'position:fixed;right:16px;bottom:16px;z-index:1000;width:40px;height:40px;border-radius:50%;border:1px solid rgba(127,127,127,.3);cursor:pointer;font-size:18px;';
+      const panel = document.createElement('div');
+      panel.id = 'modelControls';
+      panel.style.cssText = 'position:fixed;right:16px;bottom:64px;z-index:1000;display:none;min-width:240px;padding:12px;border-radius:10px;border:1px solid rgba(127,127,127,.3);background:#fff;color:#1e293b;font:13px system-ui,sans-serif;box-shadow:0 8px 24px rgba(0,0,0,.2);';
+      panel.innerHTML = `
+        <div style="font-weight:600;margin-bottom:8px">${i18n('settings.title')}</div>
+        <label style="display:block;margin-bottom:8px">${i18n('settings.temperature')} <span id="mcTempValue"></span>
+          <input id="mcTemperature" type="range" min="0" max="2" step="0.1" style="width:100%"></label>
+        <label style="display:block;margin-bottom:8px">${i18n('settings.max_tokens')}
+          <input id="mcMaxTokens" type="number" min="1" max="32768" step="1" style="width:100%"></label>
+        <label style="display:block;margin-bottom:8px">${i18n('settings.model')}
+          <input id="mcModel" type="text" style="width:100%"></label>
+        <button id="mcReset" type="button">${i18n('settings.reset')}</button>`;
+      document.body.appendChild(panel);
+      document.body.appendChild(toggle);
+      const temp = panel.querySelector('#mcTemperature');
+      const tempValue = panel.querySelector('#mcTempValue');
+      const maxTokens = panel.querySelector('#mcMaxTokens');
+      const model = panel.querySelector('#mcModel');
+      maxTokens.placeholder = i18nText('settings.default');
+      model.placeholder = i18nText('settings.default');
+      const render = () => {
+        const cur = loadModelSettings();
+        temp.value = cur.temperature !== undefined ? cur.temperature : 0.7;
+        tempValue.textContent = cur.temperature !== undefined ? cur.temperature : i18nText('settings.default');
+        maxTokens.value = cur.maxTokens || '';
+        model.value = cur.model || '';
+      };
+      const save = () => {
+        const next = { temperature: temp.value };
+        const n = parseInt(maxTokens.value, 10);
+        if (n >= 1 && n <= 32768) next.maxTokens = n;
+        if (model.value.trim()) next.model = model.value.trim();
+        localStorage.setItem(MODEL_SETTINGS_KEY, JSON.stringify(next));
+        render();
+      };
+      temp.addEventListener('input', save);
+      maxTokens.addEventListener('change', save);
+      model.addEventListener('change', save);
+      panel.querySelector('#mcReset').addEventListener('click', () => { localStorage.removeItem(MODEL_SETTINGS_KEY); render(); });
+      toggle.addEventListener('click', () => { panel.style.display = panel.style.display === 'none' ? 'block' : 'none'; });
+      render();
+    }
+    if (document.readyState === 'loading') document.addEventListener('DOMContentLoaded', setupModelControls);
+    else setupModelControls();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assets, i18n};
+    use regex::Regex;
+
+    const MODEL_CONTROLS_ON: &str = "\"modelControls\":true";
+
+    /// Each template followed by the script it loads, as the browser sees them
+    fn pages(config: &serde_json::Value) -> [String; 2] {
+        let config = assets::config_block(config);
+        [
+            DefaultTemplate.render(&config) + assets::ui_script().body(),
+            LawyerTemplate.render(&config) + assets::lawyer_script().body(),
+        ]
+    }
+
+    #[test]
+    fn templates_follow_the_character() {
+        let config = assets::config_block(&serde_json::json!({}));
+        let lawyer = LawyerTemplate.render(&config);
+        assert_eq!(
+            for_character("Zoey Legal Assistant").render(&config),
+            lawyer
+        );
+        assert_eq!(for_character("zoey (LEGAL)").render(&config), lawyer);
+        assert_eq!(
+            for_character("Zoey").render(&config),
+            DefaultTemplate.render(&config)
+        );
+        assert_eq!(
+            for_character("").render(&config),
+            DefaultTemplate.render(&config)
+        );
+    }
+
+    #[test]
+    fn templates_have_no_inline_code() {
+        let config = assets::config_block(&serde_json::json!({ "token": null }));
+        let script_re = Regex::new(r"(?s)<script([^>]*)>(.*?)</script>").unwrap();
+        let handler_re = Regex::new(r#"\son[a-z]+\s*="#).unwrap();
+        for html in [
+            DefaultTemplate.render(&config),
+            LawyerTemplate.render(&config),
+        ] {
+            let mut external = 0;
+            for caps in script_re.captures_iter(&html) {
+                if caps[1].contains(r#"type="application/json""#) {
+                    continue;
+                }
+                assert!(
+                    caps[1].contains(" src=\"/assets/js/"),
+                    "inline script: {}",
+                    &caps[0]
+                );
+                assert!(caps[2].trim().is_empty());
+                external += 1;
+            }
+            assert_eq!(external, 1);
+            assert!(!handler_re.is_match(&html), "inline event handler");
+        }
+        // Markup built by the lawyer script is delegated too
+        assert!(!handler_re.is_match(assets::lawyer_script().body()));
+    }
+
+    #[test]
+    fn templates_have_english_strings() {
+        let en = &i18n::bundles()[i18n::DEFAULT_LOCALE];
+        for html in pages(&serde_json::json!({ "modelControls": true })) {
+            let keys = i18n::template_keys(&html);
+            assert!(!keys.is_empty());
+            for key in keys {
+                assert!(
+                    en.contains_key(key.as_str()),
+                    "missing en string for {}",
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn templates_prefer_ws_chat_with_fetch_fallback() {
+        for html in pages(&serde_json::json!({ "modelControls": true })) {
+            assert!(!html.contains("{SCRIPT_SRC}"));
+            assert!(html.contains("function wsChat("));
+            assert!(html.contains("await wsChat("));
+            assert!(html.contains("/chat/stream"));
+        }
+    }
+
+    #[test]
+    fn templates_send_model_params_when_enabled() {
+        for html in pages(&serde_json::json!({ "modelControls": true })) {
+            assert!(!html.contains("{ZOEY_CONFIG}"));
+            assert!(html.contains(MODEL_CONTROLS_ON));
+            assert!(html.contains("const MODEL_CONTROLS = ZOEY_CONFIG.modelControls === true;"));
+            assert!(html.contains("function setupModelControls("));
+            assert!(html.contains("wsChat(withModelParams("));
+            assert!(html.contains("JSON.stringify(withModelParams("));
+        }
+    }
+
+    #[test]
+    fn templates_offer_edit_and_resend() {
+        for html in pages(&serde_json::json!({})) {
+            assert!(html.contains("class=\"msg-edit\""));
+            assert!(html.contains("function truncateForResend("));
+            assert!(html.contains("signal: generation.signal"));
+        }
+    }
+
+    #[test]
+    fn templates_render_selected_locale() {
+        let config = assets::config_block(&serde_json::json!({
+            "locale": "de",
+            "i18n": i18n::merged_bundle("de"),
+        }));
+        let rendered = i18n::render(&DefaultTemplate.render(&config), "de");
+        assert!(rendered.contains(">Senden</button>"));
+        assert!(rendered.contains("\"input.send\":\"Senden\""));
+        assert!(!rendered.contains("{{t:"));
+    }
+}
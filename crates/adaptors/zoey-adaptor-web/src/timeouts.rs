@@ -0,0 +1,149 @@
+//! Timeouts for requests proxied to the Agent API
+//!
+//! A backend that hangs would otherwise hold the browser's connection open
+//! indefinitely. The proxy therefore bounds every request:
+//!
+//! - ordinary routes get an overall budget, `SimpleUiConfig::proxy_timeout`,
+//!   covering the response headers and body; the time left is sent to the
+//!   backend as `X-Zoey-Deadline-Ms` so it can give up early, and a shorter
+//!   deadline from the caller is honored
+//! - chat streams can legitimately run for minutes, so instead of a total
+//!   they are cut off when no bytes arrive for
+//!   `SimpleUiConfig::proxy_stream_idle_timeout` ([`until_idle`])
+//!
+//! Expiry is reported as `backend_timeout` for ordinary routes and
+//! `stream_idle_timeout` for streams, both `504 Gateway Timeout`.
+
+use crate::error::WebError;
+use axum::http::{HeaderValue, StatusCode};
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use std::time::Duration;
+
+/// Default overall timeout for proxied non-streaming requests
+pub const DEFAULT_PROXY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a proxied stream may go without sending any bytes
+pub const DEFAULT_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Time allowed to establish a connection to the Agent API
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Milliseconds the backend has left to answer
+pub(crate) const DEADLINE_HEADER: &str = "x-zoey-deadline-ms";
+
+/// Client shared by every request to the Agent API
+///
+/// Only connecting is bounded here; each request sets its own timeout.
+pub(crate) fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .tcp_keepalive(Duration::from_secs(60))
+        .build()
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to build the Agent API client, using defaults");
+            reqwest::Client::new()
+        })
+}
+
+/// Time left of `timeout` after `elapsed`, capped by the caller's own deadline
+///
+/// `caller` is an incoming `X-Zoey-Deadline-Ms`, measured from when the
+/// request arrived; values that do not parse are ignored.
+pub(crate) fn remaining(
+    timeout: Duration,
+    elapsed: Duration,
+    caller: Option<&HeaderValue>,
+) -> Duration {
+    let caller = caller
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_millis);
+    let budget = caller.map_or(timeout, |caller| caller.min(timeout));
+    budget.saturating_sub(elapsed)
+}
+
+/// The request's budget ran out before the backend answered
+pub(crate) fn deadline_exceeded() -> WebError {
+    WebError::new(
+        StatusCode::GATEWAY_TIMEOUT,
+        "backend_timeout",
+        "Deadline expired before the Agent API answered",
+    )
+}
+
+/// A proxied stream went quiet for `idle`
+pub(crate) fn stream_idle(idle: Duration) -> WebError {
+    WebError::new(
+        StatusCode::GATEWAY_TIMEOUT,
+        "stream_idle_timeout",
+        format!("Agent API sent nothing for {}s", idle.as_secs_f64()),
+    )
+}
+
+/// Items of `inner` until it ends or yields nothing for `idle`
+///
+/// An idle timeout yields one `Err` and ends the stream, dropping `inner`.
+pub(crate) fn until_idle<S>(inner: S, idle: Duration) -> BoxStream<'static, Result<S::Item, ()>>
+where
+    S: Stream + Send + 'static,
+{
+    stream::unfold(Some(Box::pin(inner)), move |inner| async move {
+        let mut inner = inner?;
+        match tokio::time::timeout(idle, inner.next()).await {
+            Ok(Some(item)) => Some((Ok(item), Some(inner))),
+            Ok(None) => None,
+            Err(_) => Some((Err(()), None)),
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining_deadline() {
+        let ms = Duration::from_millis;
+        let header = |v: &'static str| HeaderValue::from_static(v);
+        assert_eq!(remaining(ms(30_000), ms(1_200), None), ms(28_800));
+        // The caller's deadline is tighter than ours
+        assert_eq!(
+            remaining(ms(30_000), ms(1_200), Some(&header("5000"))),
+            ms(3_800)
+        );
+        // ... or looser, in which case ours applies
+        assert_eq!(
+            remaining(ms(30_000), ms(0), Some(&header("90000"))),
+            ms(30_000)
+        );
+        assert_eq!(
+            remaining(ms(30_000), ms(10), Some(&header("soon"))),
+            ms(29_990)
+        );
+        assert_eq!(remaining(ms(30_000), ms(31_000), None), Duration::ZERO);
+        assert_eq!(
+            remaining(ms(30_000), ms(0), Some(&header("0"))),
+            Duration::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn test_until_idle_resets_on_each_item() {
+        let drip = stream::iter(1..=4).then(|i| async move {
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            i
+        });
+        let items: Vec<_> = until_idle(drip, Duration::from_millis(100)).collect().await;
+        assert_eq!(items, vec![Ok(1), Ok(2), Ok(3), Ok(4)]);
+
+        let stalls = stream::iter([0u64, 0, 500]).then(|delay| async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            delay
+        });
+        let items: Vec<_> = until_idle(stalls, Duration::from_millis(100))
+            .collect()
+            .await;
+        assert_eq!(items, vec![Ok(0), Ok(0), Err(())]);
+    }
+}
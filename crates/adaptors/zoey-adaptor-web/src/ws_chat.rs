@@ -0,0 +1,739 @@
+//! WebSocket chat channel
+//!
+//! `GET /agent/ws/chat` (also mounted at `/ws`) is an alternative to reading `/chat/stream` through a
+//! long-lived fetch body, which some corporate proxies and mobile browsers cut
+//! off mid-reply. The adapter drives the same backend `/chat/stream` call and
+//! relays it as JSON frames:
+//!
+//! - client → server: `{"type":"chat","text","roomId","entityId","params"?,"model"?}` and `{"type":"cancel","roomId"?}`
+//! - server → client: `{"type":"chunk","text"}`, `{"type":"final","text"}` and `{"type":"error","error"}`,
+//!   each with the `roomId` of the chat frame it answers
+//!
+//! A `final` frame carries the last piece of text (possibly empty) rather than
+//! the whole reply, matching the SSE stream. One socket can carry replies for
+//! several rooms at once, one per room; a `cancel` without `roomId` cancels
+//! all of them. Sockets that send and receive nothing for
+//! `SimpleUiConfig::ws_idle_timeout` are closed. Like every route the upgrade
+//! requires the UI token when one is configured (see `crate::auth`); it also
+//! rejects browsers from other origins. Every in-flight reply counts towards
+//! the same per-IP stream limit as the proxied `/chat/stream`; a chat frame
+//! arriving with no slot left gets an error frame instead of a reply.
+
+use crate::cases;
+use crate::error::{WebError, WebResult};
+use crate::limits::{client_ip, StreamPermit};
+use crate::SimpleUiServer;
+use axum::extract::ws::rejection::WebSocketUpgradeRejection;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, Query, State as AxumState};
+use axum::http::{header, HeaderMap};
+use axum::response::Response;
+use futures_util::{SinkExt, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Default time a chat socket may stay silent in both directions before it is closed
+pub const DEFAULT_WS_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Error sent when the client cancels the in-flight reply
+pub(crate) const CANCELLED: &str = "cancelled";
+
+/// Close code for sockets closed after the idle timeout (going away)
+const IDLE_CLOSE_CODE: u16 = 1001;
+
+/// Frames sent by the browser
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "ChatClientFrame")]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ClientFrame {
+    Chat {
+        text: String,
+        #[serde(rename = "roomId", default)]
+        room_id: Option<String>,
+        #[serde(rename = "entityId", default)]
+        entity_id: Option<String>,
+        /// Generation overrides from the model settings panel
+        #[serde(default)]
+        params: Option<serde_json::Value>,
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// Cancel the reply for `roomId`, or every reply when it is absent
+    Cancel {
+        #[serde(rename = "roomId", default)]
+        room_id: Option<String>,
+    },
+}
+
+/// Frames sent to the browser
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ServerFrame {
+    Chunk { text: String },
+    Final { text: String },
+    Error { error: String },
+}
+
+/// A [`ServerFrame`] tagged with the room it belongs to
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "ChatServerFrame")]
+pub(crate) struct RoomFrame<'a> {
+    #[serde(rename = "roomId", skip_serializing_if = "Option::is_none")]
+    room_id: Option<&'a str>,
+    #[serde(flatten)]
+    frame: &'a ServerFrame,
+}
+
+/// A reply being relayed for one room
+struct InFlight {
+    generation: u64,
+    handle: JoinHandle<()>,
+}
+
+impl ServerFrame {
+    fn error(message: impl Into<String>) -> Self {
+        Self::Error {
+            error: message.into(),
+        }
+    }
+
+    /// Whether this frame ends the reply
+    fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Chunk { .. })
+    }
+}
+
+pub(crate) async fn ws_chat(
+    AxumState(state): AxumState<SimpleUiServer>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> WebResult<Response> {
+    let token = backend_token(&headers, params.get("token").map(String::as_str));
+    if !origin_allowed(&headers, &state.config.allowed_origins) {
+        return Err(WebError::forbidden("origin_not_allowed", "Origin not allowed"));
+    }
+    let ws = ws?;
+    let ip = client_ip(connect_info);
+    // An idle socket holds no slot, but there is no point opening one without a free slot
+    if state.stream_limits.acquire(ip).is_none() {
+        return Err(WebError::too_many_streams());
+    }
+    Ok(ws.on_upgrade(move |socket| run_session(socket, state, token, ip)))
+}
+
+/// Token to forward to the backend: the caller's own Agent API token, or the
+/// UI token when `forward_token` left it on the request
+fn backend_token(headers: &HeaderMap, query_token: Option<&str>) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or(query_token)
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+/// Browsers always send `Origin` on WebSocket upgrades; it must match the
+/// host serving the UI or one of the configured origins
+fn origin_allowed(headers: &HeaderMap, allowed_origins: &[String]) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    if allowed_origins
+        .iter()
+        .any(|a| a.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    {
+        return true;
+    }
+    let Some((_, origin_host)) = origin.split_once("://") else {
+        return false;
+    };
+    headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .map(|host| host.eq_ignore_ascii_case(origin_host))
+        .unwrap_or(false)
+}
+
+async fn run_session(
+    socket: WebSocket,
+    state: SimpleUiServer,
+    token: Option<String>,
+    ip: IpAddr,
+) {
+    let (mut sink, mut incoming) = socket.split();
+    let url = format!(
+        "{}/chat/stream",
+        state.config.agent_api_url.trim_end_matches('/')
+    );
+    let client = state.http.clone();
+    let idle = state.config.ws_idle_timeout;
+    // Frames are tagged with their room and the reply they belong to so
+    // nothing from a cancelled reply reaches the client after the cancellation
+    let (tx, mut rx) = mpsc::channel::<(String, u64, ServerFrame)>(64);
+    let mut generation = 0u64;
+    let mut in_flight: HashMap<String, InFlight> = HashMap::new();
+    let mut last_activity = Instant::now();
+
+    loop {
+        let frames: Vec<(Option<String>, ServerFrame)> = tokio::select! {
+            msg = incoming.next() => {
+                last_activity = Instant::now();
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                match serde_json::from_str::<ClientFrame>(&text) {
+                    Ok(ClientFrame::Chat { text, room_id, entity_id, params, model }) => {
+                        let room = room_id.clone().unwrap_or_default();
+                        let frame = if in_flight.get(&room).is_some_and(|f| !f.handle.is_finished()) {
+                            ServerFrame::error("A reply is already in progress")
+                        } else if let Err(e) =
+                            cases::authorize_chat(&state, room_id.as_deref(), entity_id.as_deref())
+                        {
+                            ServerFrame::error(e.message)
+                        } else if let Some(permit) = state.stream_limits.acquire(ip) {
+                            generation += 1;
+                            let mut body = serde_json::json!({
+                                "text": text,
+                                "roomId": room_id,
+                                "entityId": entity_id,
+                                "stream": true,
+                            });
+                            if let Some(params) = params {
+                                body["params"] = params;
+                            }
+                            if let Some(model) = model {
+                                body["model"] = serde_json::json!(model);
+                            }
+                            let handle = tokio::spawn(relay_backend_stream(
+                                client.clone(),
+                                url.clone(),
+                                token.clone(),
+                                body,
+                                (room.clone(), generation),
+                                tx.clone(),
+                                permit,
+                            ));
+                            in_flight.insert(room, InFlight { generation, handle });
+                            continue;
+                        } else {
+                            ServerFrame::error(WebError::too_many_streams().message)
+                        };
+                        vec![(room_id, frame)]
+                    }
+                    Ok(ClientFrame::Cancel { room_id: Some(room_id) }) => match in_flight.remove(&room_id) {
+                        Some(reply) if !reply.handle.is_finished() => {
+                            // Dropping the backend response aborts the request
+                            reply.handle.abort();
+                            vec![(Some(room_id), ServerFrame::error(CANCELLED))]
+                        }
+                        _ => continue,
+                    },
+                    Ok(ClientFrame::Cancel { room_id: None }) => in_flight
+                        .drain()
+                        .filter(|(_, reply)| !reply.handle.is_finished())
+                        .map(|(room, reply)| {
+                            reply.handle.abort();
+                            (Some(room), ServerFrame::error(CANCELLED))
+                        })
+                        .collect(),
+                    Err(e) => vec![(None, ServerFrame::error(format!("Invalid frame: {}", e)))],
+                }
+            }
+            Some((room, gen, frame)) = rx.recv() => {
+                if in_flight.get(&room).map(|f| f.generation) != Some(gen) {
+                    continue;
+                }
+                if frame.is_terminal() {
+                    in_flight.remove(&room);
+                }
+                last_activity = Instant::now();
+                vec![(Some(room), frame)]
+            }
+            _ = tokio::time::sleep_until(last_activity + idle) => {
+                let _ = sink
+                    .send(Message::Close(Some(CloseFrame {
+                        code: IDLE_CLOSE_CODE,
+                        reason: "idle timeout".into(),
+                    })))
+                    .await;
+                break;
+            }
+        };
+        let mut open = true;
+        for (room, frame) in &frames {
+            open = open && send_frame(&mut sink, room.as_deref(), frame).await;
+        }
+        if !open {
+            break;
+        }
+    }
+
+    for reply in in_flight.into_values() {
+        reply.handle.abort();
+    }
+}
+
+/// Send `frame` for `room`; `false` once the socket is gone
+async fn send_frame<S>(sink: &mut S, room: Option<&str>, frame: &ServerFrame) -> bool
+where
+    S: futures_util::Sink<Message> + Unpin,
+{
+    let Ok(payload) = serde_json::to_string(&RoomFrame {
+        room_id: room.filter(|r| !r.is_empty()),
+        frame,
+    }) else {
+        return true;
+    };
+    sink.send(Message::Text(payload)).await.is_ok()
+}
+
+/// Post `body` to the backend and relay its SSE stream as frames until the reply ends
+///
+/// `_permit` holds the reply's stream slot until it ends or is cancelled.
+async fn relay_backend_stream(
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    body: serde_json::Value,
+    (room, generation): (String, u64),
+    tx: mpsc::Sender<(String, u64, ServerFrame)>,
+    _permit: StreamPermit,
+) {
+    let emit = |frame: ServerFrame| {
+        let tx = tx.clone();
+        let room = room.clone();
+        async move { tx.send((room, generation, frame)).await.is_ok() }
+    };
+    let mut rb = client.post(&url).json(&body);
+    if let Some(token) = token {
+        rb = rb.bearer_auth(token);
+    }
+    let resp = match rb.send().await {
+        Ok(r) if r.status().is_success() => r,
+        Ok(r) => {
+            let status = r.status();
+            let detail = r.text().await.unwrap_or_default();
+            emit(ServerFrame::error(format!(
+                "Backend returned {}: {}",
+                status,
+                detail.trim()
+            )))
+            .await;
+            return;
+        }
+        Err(e) => {
+            emit(ServerFrame::error(format!("Backend unavailable: {}", e))).await;
+            return;
+        }
+    };
+
+    let mut parser = SseParser::default();
+    let mut bytes = resp.bytes_stream();
+    while let Some(chunk) = bytes.next().await {
+        let Ok(chunk) = chunk else {
+            emit(ServerFrame::error("Connection interrupted")).await;
+            return;
+        };
+        for frame in parser.push(&chunk) {
+            let terminal = frame.is_terminal();
+            if !emit(frame).await || terminal {
+                return;
+            }
+        }
+    }
+    // Backend closed the stream without a final event
+    emit(ServerFrame::Final {
+        text: String::new(),
+    })
+    .await;
+}
+
+/// Incremental parser turning backend SSE bytes into [`ServerFrame`]s
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+}
+
+impl SseParser {
+    /// Feed raw bytes, returning frames for every complete `data:` line
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Vec<ServerFrame> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                self.event.clear();
+            } else if let Some(event) = line.strip_prefix("event:") {
+                self.event = event.trim().to_string();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                frames.extend(self.frame(data.trim()));
+            }
+        }
+        frames
+    }
+
+    fn frame(&self, data: &str) -> Option<ServerFrame> {
+        let Ok(payload) = serde_json::from_str::<serde_json::Value>(data) else {
+            return Some(ServerFrame::error("Malformed stream event"));
+        };
+        if let Some(err) = payload.get("error") {
+            let message = err.as_str().map(str::to_string).unwrap_or_else(|| err.to_string());
+            return Some(ServerFrame::error(message));
+        }
+        let text = payload
+            .get("text")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string();
+        if self.event == "error" {
+            return Some(ServerFrame::error(text));
+        }
+        let is_final = payload.get("final").and_then(|f| f.as_bool()).unwrap_or(false)
+            || self.event == "complete";
+        if is_final {
+            Some(ServerFrame::Final { text })
+        } else if text.is_empty() {
+            None
+        } else {
+            Some(ServerFrame::Chunk { text })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleUiConfig;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::Router;
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    /// Fake backend streaming two chunks then a final event; with `slow` it
+    /// pauses between events and records when the request is dropped
+    async fn fake_backend(slow: bool, dropped: Option<mpsc::Sender<()>>) -> String {
+        struct DropSignal(Option<mpsc::Sender<()>>);
+        impl Drop for DropSignal {
+            fn drop(&mut self) {
+                if let Some(tx) = self.0.take() {
+                    let _ = tx.try_send(());
+                }
+            }
+        }
+
+        let app = Router::new().route(
+            "/agent/chat/stream",
+            post(move || {
+                let dropped = dropped.clone();
+                async move {
+                    let events = [
+                        "data: {\"text\":\"Hel\",\"final\":false}\n\n",
+                        "data: {\"text\":\"lo\",\"final\":false}\n\n",
+                        "event: complete\ndata: {\"text\":\"!\",\"final\":true}\n\n",
+                    ];
+                    let signal = DropSignal(dropped);
+                    let stream = futures_util::stream::iter(events).then(move |event| {
+                        let _keep = &signal;
+                        async move {
+                            if slow {
+                                tokio::time::sleep(Duration::from_millis(300)).await;
+                            }
+                            Ok::<_, std::convert::Infallible>(event)
+                        }
+                    });
+                    axum::body::Body::from_stream(stream)
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}/agent", addr)
+    }
+
+    async fn ui(agent_api_url: String, token: Option<&str>) -> SocketAddr {
+        ui_with_config(SimpleUiConfig {
+            agent_api_url,
+            token: token.map(str::to_string),
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn ui_with_config(config: SimpleUiConfig) -> SocketAddr {
+        let runtime = zoey_core::AgentRuntime::new(zoey_core::RuntimeOpts::default())
+            .await
+            .unwrap();
+        let server = SimpleUiServer::new(config, runtime);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = server
+            .router()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    fn chat_frame() -> WsMessage {
+        WsMessage::Text(r#"{"type":"chat","text":"hi","roomId":"r1","entityId":"e1"}"#.into())
+    }
+
+    async fn next_frame<S>(ws: &mut S) -> serde_json::Value
+    where
+        S: futures_util::Stream<Item = std::result::Result<WsMessage, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+    {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("frame before timeout")
+                .expect("open socket")
+                .unwrap();
+            if let WsMessage::Text(text) = msg {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        let mut frames = parser.push(b"data: {\"text\":\"Hel\"}\n\ndata: {\"te");
+        frames.extend(parser.push(b"xt\":\"lo\"}\n\nevent: complete\ndata: {\"text\":\"\"}\n\n"));
+        assert_eq!(
+            frames,
+            vec![
+                ServerFrame::Chunk { text: "Hel".into() },
+                ServerFrame::Chunk { text: "lo".into() },
+                ServerFrame::Final { text: String::new() },
+            ]
+        );
+        assert_eq!(
+            parser.push(b"data: {\"error\":\"context length exceeded\"}\n"),
+            vec![ServerFrame::error("context length exceeded")]
+        );
+    }
+
+    #[test]
+    fn test_origin_check() {
+        let headers = |origin: Option<&str>| {
+            let mut h = HeaderMap::new();
+            h.insert(header::HOST, "127.0.0.1:4000".parse().unwrap());
+            if let Some(o) = origin {
+                h.insert(header::ORIGIN, o.parse().unwrap());
+            }
+            h
+        };
+        let allowed = vec!["https://zoey.example.com/".to_string()];
+        assert!(origin_allowed(&headers(None), &[]));
+        assert!(origin_allowed(&headers(Some("http://127.0.0.1:4000")), &[]));
+        assert!(!origin_allowed(&headers(Some("https://evil.example")), &[]));
+        assert!(!origin_allowed(&headers(Some("null")), &[]));
+        assert!(origin_allowed(&headers(Some("https://zoey.example.com")), &allowed));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_and_final_sequence() {
+        let backend = fake_backend(false, None).await;
+        let addr = ui(backend, None).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/agent/ws/chat", addr))
+            .await
+            .unwrap();
+        ws.send(chat_frame()).await.unwrap();
+
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "chunk", "text": "Hel", "roomId": "r1"})
+        );
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "chunk", "text": "lo", "roomId": "r1"})
+        );
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "final", "text": "!", "roomId": "r1"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_rooms_on_one_socket() {
+        let backend = fake_backend(true, None).await;
+        let addr = ui(backend, Some("s3cret")).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws?token=s3cret", addr))
+            .await
+            .unwrap();
+        ws.send(chat_frame()).await.unwrap();
+        ws.send(WsMessage::Text(
+            r#"{"type":"chat","text":"hi","roomId":"r2","entityId":"e1"}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+        // Both replies stream at once; each room's frames arrive in order
+        let mut replies: HashMap<String, String> = HashMap::new();
+        let mut finished = 0;
+        while finished < 2 {
+            let frame = next_frame(&mut ws).await;
+            let room = frame["roomId"].as_str().expect("frame has a room").to_string();
+            replies
+                .entry(room)
+                .or_default()
+                .push_str(frame["text"].as_str().unwrap());
+            if frame["type"] == "final" {
+                finished += 1;
+            }
+        }
+        assert_eq!(replies["r1"], "Hello!");
+        assert_eq!(replies["r2"], "Hello!");
+    }
+
+    #[tokio::test]
+    async fn test_room_replies_share_the_per_ip_stream_limit() {
+        let backend = fake_backend(true, None).await;
+        let addr = ui_with_config(SimpleUiConfig {
+            agent_api_url: backend,
+            max_streams_per_ip: 2,
+            ..Default::default()
+        })
+        .await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        let room_chat = |room: &str| {
+            WsMessage::Text(format!(
+                r#"{{"type":"chat","text":"hi","roomId":"{}","entityId":"e1"}}"#,
+                room
+            ))
+        };
+        for room in ["r1", "r2", "r3"] {
+            ws.send(room_chat(room)).await.unwrap();
+        }
+
+        // The third room has no slot left; the other two stream normally
+        let mut finished = Vec::new();
+        let mut refused = Vec::new();
+        while finished.len() + refused.len() < 3 {
+            let frame = next_frame(&mut ws).await;
+            let room = frame["roomId"].as_str().unwrap().to_string();
+            match frame["type"].as_str().unwrap() {
+                "final" => finished.push(room),
+                "error" => {
+                    assert_eq!(frame["error"], "Too many open chat streams");
+                    refused.push(room);
+                }
+                _ => {}
+            }
+        }
+        finished.sort();
+        assert_eq!(finished, ["r1", "r2"]);
+        assert_eq!(refused, ["r3"]);
+
+        // Finished replies give their slots back
+        ws.send(room_chat("r3")).await.unwrap();
+        loop {
+            let frame = next_frame(&mut ws).await;
+            assert_ne!(frame["type"], "error");
+            if frame["type"] == "final" {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idle_socket_is_closed() {
+        let backend = fake_backend(false, None).await;
+        let addr = ui_with_config(SimpleUiConfig {
+            agent_api_url: backend,
+            ws_idle_timeout: Duration::from_millis(300),
+            ..Default::default()
+        })
+        .await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        // A reply counts as activity
+        ws.send(chat_frame()).await.unwrap();
+        for _ in 0..3 {
+            next_frame(&mut ws).await;
+        }
+
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("socket closed before timeout")
+            .expect("close frame")
+            .unwrap();
+        let WsMessage::Close(Some(close)) = msg else {
+            panic!("expected a close frame, got {:?}", msg);
+        };
+        assert_eq!(u16::from(close.code), IDLE_CLOSE_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_mid_stream() {
+        let (dropped_tx, mut dropped_rx) = mpsc::channel(1);
+        let backend = fake_backend(true, Some(dropped_tx)).await;
+        let addr = ui(backend, None).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/agent/ws/chat", addr))
+            .await
+            .unwrap();
+        ws.send(chat_frame()).await.unwrap();
+        assert_eq!(next_frame(&mut ws).await["type"], "chunk");
+
+        ws.send(WsMessage::Text(r#"{"type":"cancel"}"#.into())).await.unwrap();
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "error", "error": CANCELLED, "roomId": "r1"})
+        );
+        // The backend request is torn down and nothing else is relayed
+        tokio::time::timeout(Duration::from_secs(5), dropped_rx.recv())
+            .await
+            .expect("backend request aborted");
+        assert!(tokio::time::timeout(Duration::from_millis(800), ws.next()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_missing_or_wrong_token() {
+        let backend = fake_backend(false, None).await;
+        let addr = ui(backend, Some("s3cret")).await;
+        let url = format!("ws://{}/agent/ws/chat", addr);
+
+        let err = tokio_tungstenite::connect_async(url.as_str()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            tokio_tungstenite::tungstenite::Error::Http(ref resp) if resp.status() == StatusCode::UNAUTHORIZED
+        ));
+        let err = tokio_tungstenite::connect_async(format!("{}?token=wrong", url))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            tokio_tungstenite::tungstenite::Error::Http(ref resp) if resp.status() == StatusCode::FORBIDDEN
+        ));
+
+        let mut request = url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(tokio_tungstenite::connect_async(request).await.is_ok());
+        assert!(tokio_tungstenite::connect_async(format!("{}?token=s3cret", url))
+            .await
+            .is_ok());
+    }
+}
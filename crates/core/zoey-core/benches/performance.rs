@@ -103,6 +103,8 @@ struct BenchPlugin {
 
 #[async_trait]
 impl Plugin for BenchPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -0,0 +1,765 @@
+//! Per-message lifecycle events emitted by chat adapters
+//!
+//! Adapters report what happened to every incoming message (received,
+//! filtered, addressed, streamed, answered or failed) to a [`TelemetrySink`]
+//! so funnels can be built without an analytics SDK in the message handler.
+//!
+//! - [`TracingSink`] logs events under the `zoey::adapter_events` target (the default)
+//! - [`JsonlSink`] appends one JSON object per line to a size-rotated file
+//! - [`HttpBatchSink`] POSTs batches of events to an endpoint
+//!
+//! Sinks other than tracing sit behind a [`QueuedSink`]: emitting only tries
+//! to push onto a bounded queue drained by a background thread, and events
+//! that don't fit are dropped and counted, so a slow disk or endpoint never
+//! blocks or fails a reply. Rooms and entities are identified by salted
+//! hashes ([`hash_id`]), never by platform IDs.
+
+use crate::{Result, ZoeyError};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Default number of events waiting for a queued sink
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Default size at which a JSONL file is rotated
+pub const DEFAULT_JSONL_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated JSONL files kept next to the live one
+pub const DEFAULT_JSONL_KEEP: usize = 3;
+
+/// Default events per HTTP POST
+pub const DEFAULT_HTTP_BATCH_SIZE: usize = 100;
+
+/// Default interval after which a partial batch is flushed
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Hex characters kept from an identifier's hash
+const HASH_LEN: usize = 16;
+
+/// What happened to a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AdapterEventKind {
+    /// A message arrived (any kind, before filtering)
+    Received,
+    /// Dropped before reaching the agent (rate limit, allow list, command, ...)
+    Filtered {
+        /// Short snake_case reason, e.g. `"rate_limited"`
+        reason: String,
+    },
+    /// Meant for the bot (mention, reply, DM); unaddressed messages may still be answered
+    Addressed,
+    /// The agent request was sent
+    StreamStarted,
+    /// The first piece of the answer arrived
+    FirstChunk,
+    /// The answer was delivered; `latency_ms` is measured from `Received`
+    Finalized {
+        /// Milliseconds since the message was received
+        latency_ms: u64,
+        /// Characters in the delivered answer
+        chars: usize,
+        /// Whether the answer was spoken
+        voice: bool,
+    },
+    /// Answering gave up
+    Failed {
+        /// Step that failed, e.g. `"stream"` or `"transcribe"`
+        stage: String,
+        /// Short category, never the error message itself
+        error_class: String,
+    },
+}
+
+/// One lifecycle event of one message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AdapterEvent {
+    /// Adapter name, e.g. `"telegram"`
+    pub adapter: String,
+    /// Shared by all events of the same message
+    pub message: Uuid,
+    /// Hashed room (chat/channel) identifier
+    pub room: String,
+    /// Hashed sender identifier
+    pub entity: String,
+    /// Epoch milliseconds
+    pub at: i64,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: AdapterEventKind,
+}
+
+/// Salted, truncated SHA-256 of a platform identifier
+///
+/// Stable for a given salt so funnels can follow a room or user, while the
+/// raw ID can't be read back (or brute-forced without the salt).
+pub fn hash_id(salt: &str, raw: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(b":");
+    hasher.update(raw.as_bytes());
+    let mut hex = hex::encode(hasher.finalize());
+    hex.truncate(HASH_LEN);
+    hex
+}
+
+/// Receives adapter events
+///
+/// `emit` is called on the message path and must return quickly; sinks that
+/// do I/O are wrapped in a [`QueuedSink`], which calls them from its own thread.
+pub trait TelemetrySink: Send + Sync {
+    /// Record `event`
+    fn emit(&self, event: AdapterEvent);
+
+    /// Push out anything buffered; called periodically and on shutdown
+    fn flush(&self) {}
+}
+
+/// Logs events through `tracing`
+#[derive(Debug, Default)]
+pub struct TracingSink;
+
+impl TelemetrySink for TracingSink {
+    fn emit(&self, event: AdapterEvent) {
+        info!(
+            target: "zoey::adapter_events",
+            adapter = %event.adapter,
+            message = %event.message,
+            room = %event.room,
+            entity = %event.entity,
+            event = ?event.kind,
+            "Adapter event"
+        );
+    }
+}
+
+/// Appends events as JSON lines, rotating the file when it grows too large
+///
+/// On rotation `events.jsonl` becomes `events.jsonl.1`, `.1` becomes `.2`
+/// and so on; files beyond `keep` are deleted.
+pub struct JsonlSink {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl JsonlSink {
+    /// Append to `path` (created with its directory if missing), keeping `keep` rotated files
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64, keep: usize) -> Result<Self> {
+        let sink = Self {
+            path: path.into(),
+            max_bytes,
+            keep,
+            file: Mutex::new(None),
+        };
+        *sink.file.lock().unwrap() = Some(sink.open()?);
+        Ok(sink)
+    }
+
+    fn open(&self) -> Result<(File, u64)> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let len = file.metadata()?.len();
+        Ok((file, len))
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&self) -> Result<()> {
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        let _ = std::fs::remove_file(self.rotated(self.keep));
+        for index in (1..self.keep).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        Ok(())
+    }
+
+    fn write(&self, line: &[u8]) -> Result<()> {
+        let mut guard = self.file.lock().unwrap();
+        let needs_rotation = match guard.as_ref() {
+            Some((_, len)) => *len > 0 && *len + line.len() as u64 > self.max_bytes,
+            None => false,
+        };
+        if needs_rotation {
+            *guard = None;
+            self.rotate()?;
+        }
+        if guard.is_none() {
+            *guard = Some(self.open()?);
+        }
+        let (file, len) = guard.as_mut().expect("opened above");
+        file.write_all(line)?;
+        *len += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl TelemetrySink for JsonlSink {
+    fn emit(&self, event: AdapterEvent) {
+        let mut line = match serde_json::to_vec(&event) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!(error = %e, "Failed to serialize adapter event");
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = self.write(&line) {
+            warn!(path = %self.path.display(), error = %e, "Failed to write adapter event");
+        }
+    }
+
+    fn flush(&self) {
+        if let Some((file, _)) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// POSTs events to an endpoint as a JSON array, `batch_size` at a time
+///
+/// Sends block until the endpoint answers (or `timeout` passes), so this
+/// sink is meant to sit behind a [`QueuedSink`]. A failed batch is logged
+/// and dropped.
+pub struct HttpBatchSink {
+    endpoint: String,
+    batch_size: usize,
+    client: reqwest::Client,
+    /// Built on first send, on the thread that sends
+    runtime: OnceLock<tokio::runtime::Runtime>,
+    pending: Mutex<Vec<AdapterEvent>>,
+}
+
+impl HttpBatchSink {
+    /// POST to `endpoint`, giving up on a batch after `timeout`
+    pub fn new(endpoint: impl Into<String>, batch_size: usize, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            endpoint: endpoint.into(),
+            batch_size: batch_size.max(1),
+            client: reqwest::Client::builder().timeout(timeout).build()?,
+            runtime: OnceLock::new(),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn send(&self, batch: Vec<AdapterEvent>) {
+        if batch.is_empty() {
+            return;
+        }
+        let runtime = match self.runtime.get() {
+            Some(runtime) => runtime,
+            None => match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => self.runtime.get_or_init(|| runtime),
+                Err(e) => {
+                    warn!(error = %e, "Failed to start runtime for adapter event batches");
+                    return;
+                }
+            },
+        };
+        let request = self.client.post(&self.endpoint).json(&batch).send();
+        match runtime.block_on(request) {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => {
+                warn!(endpoint = %self.endpoint, status = %response.status(), events = batch.len(), "Adapter event batch rejected");
+            }
+            Err(e) => {
+                warn!(endpoint = %self.endpoint, error = %e, events = batch.len(), "Failed to send adapter event batch");
+            }
+        }
+    }
+}
+
+impl TelemetrySink for HttpBatchSink {
+    fn emit(&self, event: AdapterEvent) {
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push(event);
+            (pending.len() >= self.batch_size).then(|| std::mem::take(&mut *pending))
+        };
+        if let Some(batch) = full {
+            self.send(batch);
+        }
+    }
+
+    fn flush(&self) {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+        self.send(batch);
+    }
+}
+
+/// Hands events to another sink on a background thread through a bounded queue
+///
+/// `emit` never blocks: when the queue is full the event is dropped and
+/// counted in [`dropped`](Self::dropped). The inner sink is flushed every
+/// `flush_interval` without events and once more when the queue is dropped.
+pub struct QueuedSink {
+    sender: Mutex<Option<SyncSender<AdapterEvent>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl QueuedSink {
+    /// Queue up to `capacity` events for `inner`
+    pub fn new(inner: Box<dyn TelemetrySink>, capacity: usize, flush_interval: Duration) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<AdapterEvent>(capacity);
+        let spawned = std::thread::Builder::new()
+            .name("adapter_telemetry".to_string())
+            .spawn(move || loop {
+                match receiver.recv_timeout(flush_interval) {
+                    Ok(event) => inner.emit(event),
+                    Err(RecvTimeoutError::Timeout) => inner.flush(),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        inner.flush();
+                        break;
+                    }
+                }
+            });
+        let sender = match spawned {
+            Ok(_) => Some(sender),
+            Err(e) => {
+                warn!(error = %e, "Failed to start adapter telemetry thread, events will be dropped");
+                None
+            }
+        };
+        Self {
+            sender: Mutex::new(sender),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Events dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl TelemetrySink for QueuedSink {
+    fn emit(&self, event: AdapterEvent) {
+        let sent = match self.sender.lock().unwrap().as_ref() {
+            Some(sender) => match sender.try_send(event) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+            None => false,
+        };
+        if !sent {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for QueuedSink {
+    fn drop(&mut self) {
+        // Closing the queue lets the thread flush and exit
+        self.sender.lock().unwrap().take();
+    }
+}
+
+/// Where an adapter's events go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TelemetrySinkConfig {
+    /// [`TracingSink`]
+    Tracing,
+    /// [`JsonlSink`]
+    Jsonl {
+        /// Live file; rotated files get `.1`, `.2`, ... appended
+        path: PathBuf,
+        /// Size at which the file is rotated
+        max_bytes: u64,
+        /// Rotated files kept
+        keep: usize,
+    },
+    /// [`HttpBatchSink`]
+    Http {
+        /// URL the batches are POSTed to
+        endpoint: String,
+        /// Events per POST
+        batch_size: usize,
+    },
+}
+
+/// Adapter telemetry settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelemetryConfig {
+    /// Where events go
+    pub sink: TelemetrySinkConfig,
+    /// Events that can wait for a JSONL or HTTP sink before new ones are dropped
+    pub queue_capacity: usize,
+    /// How often a partial HTTP batch is sent (and the JSONL file flushed)
+    pub flush_interval: Duration,
+    /// Mixed into identifier hashes; set one per deployment
+    pub salt: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            sink: TelemetrySinkConfig::Tracing,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            salt: String::new(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Build the configured sink for `adapter`
+    pub fn build(&self, adapter: &str) -> Result<Telemetry> {
+        let inner: Box<dyn TelemetrySink> = match &self.sink {
+            TelemetrySinkConfig::Tracing => {
+                return Ok(Telemetry::new(adapter, &self.salt, Arc::new(TracingSink)));
+            }
+            TelemetrySinkConfig::Jsonl {
+                path,
+                max_bytes,
+                keep,
+            } => Box::new(JsonlSink::new(path.clone(), *max_bytes, *keep)?),
+            TelemetrySinkConfig::Http {
+                endpoint,
+                batch_size,
+            } => {
+                if endpoint.trim().is_empty() {
+                    return Err(ZoeyError::config("telemetry endpoint is empty"));
+                }
+                Box::new(HttpBatchSink::new(
+                    endpoint.clone(),
+                    *batch_size,
+                    Duration::from_secs(10),
+                )?)
+            }
+        };
+        let queued = Arc::new(QueuedSink::new(
+            inner,
+            self.queue_capacity,
+            self.flush_interval,
+        ));
+        Ok(Telemetry {
+            queue: Some(queued.clone()),
+            ..Telemetry::new(adapter, &self.salt, queued)
+        })
+    }
+}
+
+/// An adapter's event sink, handing out per-message trackers
+#[derive(Clone)]
+pub struct Telemetry {
+    adapter: Arc<str>,
+    salt: Arc<str>,
+    sink: Arc<dyn TelemetrySink>,
+    queue: Option<Arc<QueuedSink>>,
+}
+
+impl Telemetry {
+    /// Events of `adapter` go to `sink`, identifiers hashed with `salt`
+    pub fn new(adapter: &str, salt: &str, sink: Arc<dyn TelemetrySink>) -> Self {
+        Self {
+            adapter: adapter.into(),
+            salt: salt.into(),
+            sink,
+            queue: None,
+        }
+    }
+
+    /// Tracing-only telemetry
+    pub fn tracing(adapter: &str) -> Self {
+        Self::new(adapter, "", Arc::new(TracingSink))
+    }
+
+    /// Start tracking a message: emits `Received`
+    pub fn message(&self, room: &str, entity: &str) -> MessageTelemetry {
+        let tracker = MessageTelemetry {
+            telemetry: self.clone(),
+            message: Uuid::new_v4(),
+            room: hash_id(&self.salt, room),
+            entity: hash_id(&self.salt, entity),
+            received_at: Instant::now(),
+            stream_started: AtomicBool::new(false),
+            first_chunk: AtomicBool::new(false),
+            done: AtomicBool::new(false),
+        };
+        tracker.emit(AdapterEventKind::Received);
+        tracker
+    }
+
+    /// Events dropped by a full queue (always 0 for tracing)
+    pub fn dropped(&self) -> u64 {
+        self.queue.as_ref().map(|q| q.dropped()).unwrap_or(0)
+    }
+}
+
+/// Lifecycle of one message
+///
+/// Keeps events in order: `StreamStarted` and `FirstChunk` are emitted once
+/// each, and nothing follows the first `Filtered`, `Finalized` or `Failed`.
+pub struct MessageTelemetry {
+    telemetry: Telemetry,
+    message: Uuid,
+    room: String,
+    entity: String,
+    received_at: Instant,
+    stream_started: AtomicBool,
+    first_chunk: AtomicBool,
+    done: AtomicBool,
+}
+
+impl MessageTelemetry {
+    fn emit(&self, kind: AdapterEventKind) {
+        self.telemetry.sink.emit(AdapterEvent {
+            adapter: self.telemetry.adapter.to_string(),
+            message: self.message,
+            room: self.room.clone(),
+            entity: self.entity.clone(),
+            at: chrono::Utc::now().timestamp_millis(),
+            kind,
+        });
+    }
+
+    fn open(&self) -> bool {
+        !self.done.load(Ordering::Relaxed)
+    }
+
+    fn finish(&self, kind: AdapterEventKind) {
+        if !self.done.swap(true, Ordering::Relaxed) {
+            self.emit(kind);
+        }
+    }
+
+    /// The message was dropped before reaching the agent
+    pub fn filtered(&self, reason: &str) {
+        self.finish(AdapterEventKind::Filtered {
+            reason: reason.to_string(),
+        });
+    }
+
+    /// The message was meant for the bot
+    pub fn addressed(&self) {
+        if self.open() {
+            self.emit(AdapterEventKind::Addressed);
+        }
+    }
+
+    /// The agent request was sent (retries don't repeat the event)
+    pub fn stream_started(&self) {
+        if self.open() && !self.stream_started.swap(true, Ordering::Relaxed) {
+            self.emit(AdapterEventKind::StreamStarted);
+        }
+    }
+
+    /// Part of the answer arrived (only the first call after `stream_started` counts)
+    pub fn first_chunk(&self) {
+        if self.open()
+            && self.stream_started.load(Ordering::Relaxed)
+            && !self.first_chunk.swap(true, Ordering::Relaxed)
+        {
+            self.emit(AdapterEventKind::FirstChunk);
+        }
+    }
+
+    /// The answer of `chars` characters was delivered, spoken if `voice`
+    pub fn finalized(&self, chars: usize, voice: bool) {
+        self.finish(AdapterEventKind::Finalized {
+            latency_ms: self.received_at.elapsed().as_millis() as u64,
+            chars,
+            voice,
+        });
+    }
+
+    /// Answering failed at `stage`
+    pub fn failed(&self, stage: &str, error_class: &str) {
+        self.finish(AdapterEventKind::Failed {
+            stage: stage.to_string(),
+            error_class: error_class.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<AdapterEvent>>,
+    }
+
+    impl TelemetrySink for RecordingSink {
+        fn emit(&self, event: AdapterEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    /// Holds every event until `release` is set
+    struct BlockedSink {
+        release: Arc<AtomicBool>,
+    }
+
+    impl TelemetrySink for BlockedSink {
+        fn emit(&self, _event: AdapterEvent) {
+            while !self.release.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    fn event(n: usize) -> AdapterEvent {
+        AdapterEvent {
+            adapter: "test".to_string(),
+            message: Uuid::nil(),
+            room: format!("room-{}", n),
+            entity: "entity".to_string(),
+            at: 0,
+            kind: AdapterEventKind::Received,
+        }
+    }
+
+    #[test]
+    fn test_message_lifecycle_order() {
+        let sink = Arc::new(RecordingSink::default());
+        let telemetry = Telemetry::new("telegram", "salt", sink.clone());
+
+        let message = telemetry.message("-100", "7");
+        message.addressed();
+        message.first_chunk(); // before the stream started: ignored
+        message.stream_started();
+        message.stream_started();
+        message.first_chunk();
+        message.first_chunk();
+        message.finalized(42, true);
+        message.failed("stream", "timeout"); // after the terminal event: ignored
+
+        let filtered = telemetry.message("-100", "8");
+        filtered.filtered("rate_limited");
+        filtered.addressed();
+
+        let events = sink.events.lock().unwrap();
+        let kinds: Vec<_> = events.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(kinds.len(), 7);
+        assert_eq!(
+            kinds[..4],
+            [
+                AdapterEventKind::Received,
+                AdapterEventKind::Addressed,
+                AdapterEventKind::StreamStarted,
+                AdapterEventKind::FirstChunk,
+            ]
+        );
+        assert!(matches!(
+            kinds[4],
+            AdapterEventKind::Finalized {
+                chars: 42,
+                voice: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            kinds[5..],
+            [
+                AdapterEventKind::Received,
+                AdapterEventKind::Filtered {
+                    reason: "rate_limited".to_string()
+                },
+            ]
+        );
+        assert!(events[..5].iter().all(|e| e.message == events[0].message));
+        assert_ne!(events[5].message, events[0].message);
+        assert!(events.iter().all(|e| e.adapter == "telegram"));
+
+        let json = serde_json::to_value(&events[4]).unwrap();
+        assert_eq!(json["event"], "finalized");
+        assert_eq!(json["chars"], 42);
+    }
+
+    #[test]
+    fn test_identifiers_are_hashed() {
+        let sink = Arc::new(RecordingSink::default());
+        Telemetry::new("telegram", "salt", sink.clone()).message("-1001234567", "987654321");
+
+        let events = sink.events.lock().unwrap();
+        assert_eq!(events[0].room, hash_id("salt", "-1001234567"));
+        assert_eq!(events[0].entity, hash_id("salt", "987654321"));
+        assert_eq!(events[0].room.len(), HASH_LEN);
+        let json = serde_json::to_string(&events[0]).unwrap();
+        assert!(!json.contains("1234567"));
+        assert!(!json.contains("987654321"));
+
+        // Stable per salt, different across salts
+        assert_eq!(hash_id("salt", "7"), hash_id("salt", "7"));
+        assert_ne!(hash_id("salt", "7"), hash_id("pepper", "7"));
+        assert_ne!(hash_id("salt", "7"), hash_id("salt", "8"));
+    }
+
+    #[test]
+    fn test_full_queue_drops_and_counts() {
+        let release = Arc::new(AtomicBool::new(false));
+        let queued = QueuedSink::new(
+            Box::new(BlockedSink {
+                release: release.clone(),
+            }),
+            2,
+            DEFAULT_FLUSH_INTERVAL,
+        );
+        let started = Instant::now();
+        for n in 0..10 {
+            queued.emit(event(n));
+        }
+        // Never waits for the stuck sink
+        assert!(started.elapsed() < Duration::from_secs(1));
+        // At most one event in the sink plus two queued
+        assert!(queued.dropped() >= 7, "dropped {}", queued.dropped());
+        release.store(true, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_jsonl_sink_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let line_len = serde_json::to_vec(&event(0)).unwrap().len() as u64 + 1;
+        let sink = JsonlSink::new(&path, line_len * 2, 2).unwrap();
+
+        for n in 0..7 {
+            sink.emit(event(n));
+        }
+        sink.flush();
+
+        let read = |p: &std::path::Path| -> Vec<String> {
+            std::fs::read_to_string(p)
+                .unwrap()
+                .lines()
+                .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["room"].to_string())
+                .collect()
+        };
+        assert_eq!(read(&path), vec!["\"room-6\""]);
+        assert_eq!(read(&sink.rotated(1)), vec!["\"room-4\"", "\"room-5\""]);
+        assert_eq!(read(&sink.rotated(2)), vec!["\"room-2\"", "\"room-3\""]);
+        // Older files beyond `keep` are gone
+        assert!(!sink.rotated(3).exists());
+    }
+}
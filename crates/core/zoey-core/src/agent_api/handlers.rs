@@ -34,11 +34,18 @@ use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// Sampling temperature used when a request does not override it
+const DEFAULT_TEMPERATURE: f32 = 0.7;
+
 async fn run_chat_stream_job(
     runtime: Arc<RwLock<AgentRuntime>>,
     req_clone: ChatRequest,
     stream_handler: StreamHandler,
 ) {
+    // Generation overrides from the request (validated by the handlers)
+    let params = req_clone.params.unwrap_or_default();
+    let temperature = params.temperature.unwrap_or(DEFAULT_TEMPERATURE);
+
     let (provider, available_providers) = {
         let rt_guard = runtime.read().unwrap();
         let pref = rt_guard
@@ -73,7 +80,8 @@ async fn run_chat_stream_job(
                     let rt = runtime.read().unwrap();
                     rt.character.name.clone()
                 },
-                5, // Reduced from 10 to prevent context explosion
+                // Reduced from 10 to prevent context explosion
+                crate::utils::context_turn_limit(&req_clone.metadata, 5),
             )
             .await
         } else {
@@ -238,14 +246,17 @@ async fn run_chat_stream_job(
                     .unwrap_or_else(|_| reqwest::Client::new())
             })
             .clone();
-        let req_body = serde_json::json!({
+        let mut req_body = serde_json::json!({
             "model": model,
             "stream": true,
-            "max_tokens": std::cmp::max(dynamic_max, 2048),
+            "max_tokens": params.max_tokens.unwrap_or_else(|| std::cmp::max(dynamic_max, 2048)),
             "messages": [
                 {"role": "user", "content": prompt}
             ]
         });
+        if let Some(t) = params.temperature {
+            req_body["temperature"] = serde_json::json!(t);
+        }
         let stream_timeout = std::env::var("OPENAI_STREAM_TIMEOUT_SECS")
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
@@ -352,7 +363,7 @@ async fn run_chat_stream_job(
                                     conversation_id: Some(req_clone.room_id),
                                     action_name: None,
                                     evaluator_name: None,
-                                    temperature: Some(0.7),
+                                    temperature: Some(temperature),
                                     cached_tokens: None,
                                     ttft_ms: None,
                                     prompt_hash: None,
@@ -535,8 +546,8 @@ async fn run_chat_stream_job(
             let model = req_clone.model.clone()
                 .or_else(|| rt.get_setting("LOCAL_LLM_MODEL").and_then(|v| v.as_str().map(|s| s.to_string())))
                 .unwrap_or_else(|| std::env::var("OLLAMA_MODEL").unwrap_or_else(|_| "llama3.2".to_string()));
-            let max = rt.get_setting("LOCAL_LLM_MAX_TOKENS")
-                .and_then(|v| v.as_u64().map(|u| u as usize))
+            let max = params.max_tokens
+                .or_else(|| rt.get_setting("LOCAL_LLM_MAX_TOKENS").and_then(|v| v.as_u64().map(|u| u as usize)))
                 .unwrap_or(800);
             (base, model, max)
         };
@@ -578,8 +589,27 @@ async fn run_chat_stream_job(
             }
         }
 
-        // Inject relevant knowledge context from uploaded documents
-        if let Some(knowledge_context) = retrieve_knowledge_context(req_clone.room_id, &req_clone.text, 5) {
+        // Inject relevant knowledge context from uploaded documents, noting any
+        // links the adapter fetched into the room for this message
+        let ingested_urls: Vec<&str> = req_clone
+            .metadata
+            .get("ingested_urls")
+            .and_then(|v| v.as_array())
+            .map(|urls| urls.iter().filter_map(|u| u.as_str()).collect())
+            .unwrap_or_default();
+        let knowledge_context = match retrieve_knowledge_context(req_clone.room_id, &req_clone.text, 5) {
+            Some(context) if !ingested_urls.is_empty() => Some(format!(
+                "Linked pages read for this message: {}\n\n{}",
+                ingested_urls.join(", "),
+                context
+            )),
+            None if !ingested_urls.is_empty() => Some(format!(
+                "Linked pages read for this message: {}",
+                ingested_urls.join(", ")
+            )),
+            other => other,
+        };
+        if let Some(knowledge_context) = knowledge_context {
             info!(
                 "KNOWLEDGE_CONTEXT_INJECTED room_id={} context_len={}",
                 req_clone.room_id,
@@ -717,7 +747,7 @@ async fn run_chat_stream_job(
             "messages": messages,
             "stream": true,
             "options": {
-                "temperature": 0.7,
+                "temperature": temperature,
                 "num_predict": max_tokens
             }
         });
@@ -884,7 +914,7 @@ async fn run_chat_stream_job(
                                         conversation_id: Some(req_clone.room_id),
                                         action_name: None,
                                         evaluator_name: None,
-                                        temperature: Some(0.7),
+                                        temperature: Some(temperature),
                                         cached_tokens: None,
                                         ttft_ms: None,
                                         prompt_hash: None,
@@ -948,7 +978,7 @@ async fn run_chat_stream_job(
                     let rt = runtime.read().unwrap();
                     rt.character.name.clone()
                 },
-                5,
+                crate::utils::context_turn_limit(&req_clone.metadata, 5),
             )
             .await
         } else {
@@ -1032,8 +1062,9 @@ async fn run_chat_stream_job(
             }
         }
 
-        // Store user message
-        if let Some(ref adapter) = adapter {
+        // Store user message (a context retry reuses the one stored by the first attempt)
+        let is_retry = crate::utils::is_context_retry(&req_clone.metadata);
+        if let Some(adapter) = adapter.as_ref().filter(|_| !is_retry) {
             let user_mem = Memory {
                 id: Uuid::new_v4(),
                 entity_id,
@@ -1094,16 +1125,16 @@ async fn run_chat_stream_job(
             serde_json::json!({
                 "model": model,
                 "messages": [{"role": "user", "content": prompt}],
-                "max_completion_tokens": 2048,
-                "temperature": 0.7,
+                "max_completion_tokens": params.max_tokens.unwrap_or(2048),
+                "temperature": temperature,
                 "stream": true
             })
         } else {
             serde_json::json!({
                 "model": model,
                 "messages": [{"role": "user", "content": prompt}],
-                "max_tokens": 2048,
-                "temperature": 0.7,
+                "max_tokens": params.max_tokens.unwrap_or(2048),
+                "temperature": temperature,
                 "stream": true
             })
         };
@@ -1229,7 +1260,7 @@ async fn run_chat_stream_job(
                                     conversation_id: Some(req_clone.room_id),
                                     action_name: None,
                                     evaluator_name: None,
-                                    temperature: Some(0.7),
+                                    temperature: Some(temperature),
                                     cached_tokens: None,
                                     ttft_ms,
                                     prompt_hash: None,
@@ -1302,7 +1333,7 @@ async fn run_chat_stream_job(
                     let rt = runtime.read().unwrap();
                     rt.character.name.clone()
                 },
-                5,
+                crate::utils::context_turn_limit(&req_clone.metadata, 5),
             )
             .await
         } else {
@@ -1386,8 +1417,9 @@ async fn run_chat_stream_job(
             }
         }
 
-        // Store user message
-        if let Some(ref adapter) = adapter {
+        // Store user message (a context retry reuses the one stored by the first attempt)
+        let is_retry = crate::utils::is_context_retry(&req_clone.metadata);
+        if let Some(adapter) = adapter.as_ref().filter(|_| !is_retry) {
             let user_mem = Memory {
                 id: Uuid::new_v4(),
                 entity_id,
@@ -1440,12 +1472,15 @@ async fn run_chat_stream_job(
 
         let model = std::env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| "claude-3-haiku-20240307".to_string());
 
-        let req_body = serde_json::json!({
+        let mut req_body = serde_json::json!({
             "model": model,
-            "max_tokens": 2048,
+            "max_tokens": params.max_tokens.unwrap_or(2048),
             "messages": [{"role": "user", "content": prompt}],
             "stream": true
         });
+        if let Some(t) = params.temperature {
+            req_body["temperature"] = serde_json::json!(t);
+        }
 
         static ANTHROPIC_CLIENT: OnceLock<HttpClient> = OnceLock::new();
         let client = ANTHROPIC_CLIENT
@@ -1590,7 +1625,7 @@ async fn run_chat_stream_job(
                                                 conversation_id: Some(req_clone.room_id),
                                                 action_name: None,
                                                 evaluator_name: None,
-                                                temperature: Some(0.7),
+                                                temperature: Some(temperature),
                                                 cached_tokens: None,
                                                 ttft_ms,
                                                 prompt_hash: None,
@@ -1925,6 +1960,9 @@ pub async fn chat_handler(
     if request.text.trim().is_empty() {
         return ApiError::BadRequest("Message text cannot be empty".to_string()).into_response();
     }
+    if let Some(Err(e)) = request.params.map(|p| p.validate()) {
+        return ApiError::BadRequest(e).into_response();
+    }
     let max_len = std::env::var("API_MAX_MESSAGE_BYTES")
         .ok()
         .and_then(|s| s.parse::<usize>().ok())
@@ -2018,6 +2056,9 @@ pub async fn chat_stream_handler(
     if request.text.trim().is_empty() {
         return ApiError::BadRequest("Message text cannot be empty".to_string()).into_response();
     }
+    if let Some(Err(e)) = request.params.map(|p| p.validate()) {
+        return ApiError::BadRequest(e).into_response();
+    }
     {
         let max_len = std::env::var("API_MAX_MESSAGE_BYTES")
             .ok()
@@ -2432,18 +2473,38 @@ async fn process_state_task(
     };
 
     // Compose state
-    let agent_state = {
+    let mut agent_state = {
         let rt = runtime.read().unwrap();
         rt.compose_state(&message, None, false, false).await?
     };
-    let agent_name = {
+    let (agent_name, adapter) = {
         let rt = runtime.read().unwrap();
-        rt.character.name.clone()
+        (rt.character.name.clone(), rt.get_adapter())
     };
+
+    // Authoritative room counters so clients don't reconstruct them from provider output
+    let turn_count = match adapter {
+        Some(adapter) => adapter
+            .count_room_memories(request.room_id, "messages")
+            .await
+            .unwrap_or(0),
+        None => 0,
+    };
+    let document_count = get_room_documents(request.room_id).len();
+    agent_state.set_data(
+        "roomStats",
+        serde_json::json!({
+            "turnCount": turn_count,
+            "documentCount": document_count,
+            "persona": agent_name,
+        }),
+    );
     info!(
-        "[{}] state composed values={}",
+        "[{}] state composed values={} turns={} documents={}",
         agent_name,
-        agent_state.values.len()
+        agent_state.values.len(),
+        turn_count,
+        document_count
     );
 
     Ok(StateResponse {
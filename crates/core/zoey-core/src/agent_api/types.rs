@@ -77,12 +77,57 @@ pub struct ChatRequest {
     /// Optional model override for local LLM (e.g., "tinyllama:1.1b", "llama3.2")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+
+    /// Optional generation parameter overrides (validated against [`GenerationParams::validate`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<GenerationParams>,
 }
 
 fn default_source() -> String {
     "api".to_string()
 }
 
+/// Highest sampling temperature accepted in [`GenerationParams`]
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// Highest completion token limit accepted in [`GenerationParams`]
+pub const MAX_GENERATION_TOKENS: usize = 32_768;
+
+/// Per-request generation overrides, e.g. from the web UI's model controls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationParams {
+    /// Sampling temperature (0.0 - 2.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// Maximum tokens to generate (1 - 32768)
+    #[serde(skip_serializing_if = "Option::is_none", alias = "maxTokens")]
+    pub max_tokens: Option<usize>,
+}
+
+impl GenerationParams {
+    /// Check that every provided parameter is within range
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(t) = self.temperature {
+            if !t.is_finite() || !(0.0..=MAX_TEMPERATURE).contains(&t) {
+                return Err(format!(
+                    "temperature must be between 0 and {}",
+                    MAX_TEMPERATURE
+                ));
+            }
+        }
+        if let Some(m) = self.max_tokens {
+            if !(1..=MAX_GENERATION_TOKENS).contains(&m) {
+                return Err(format!(
+                    "max_tokens must be between 1 and {}",
+                    MAX_GENERATION_TOKENS
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Response from chat endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -643,6 +688,29 @@ mod tests {
         assert_eq!(req.text, "Hello");
     }
 
+    #[test]
+    fn test_generation_params_validation() {
+        let req: ChatRequest = serde_json::from_value(serde_json::json!({
+            "text": "hi",
+            "roomId": Uuid::new_v4(),
+            "params": { "temperature": 0.2, "max_tokens": 256 }
+        }))
+        .unwrap();
+        let params = req.params.unwrap();
+        assert_eq!(params.temperature, Some(0.2));
+        assert_eq!(params.max_tokens, Some(256));
+        assert!(params.validate().is_ok());
+        assert!(GenerationParams::default().validate().is_ok());
+
+        let bad = |temperature, max_tokens| GenerationParams { temperature, max_tokens }.validate();
+        assert!(bad(Some(-0.1), None).is_err());
+        assert!(bad(Some(2.5), None).is_err());
+        assert!(bad(Some(f32::NAN), None).is_err());
+        assert!(bad(None, Some(0)).is_err());
+        assert!(bad(None, Some(MAX_GENERATION_TOKENS + 1)).is_err());
+        assert!(bad(Some(MAX_TEMPERATURE), Some(MAX_GENERATION_TOKENS)).is_ok());
+    }
+
     #[test]
     fn test_api_response() {
         let response = ApiResponse::success("test data");
@@ -288,6 +288,22 @@ pub fn parse_character_xml(xml: &str) -> Result<Character> {
                 character.storage.embedding_dimension = Some(d);
             }
         }
+        if let Some(model) = extract_tag_content(&storage_section, "embedding_model") {
+            character.storage.embedding_model = Some(model);
+        }
+        if let Some(backend) = extract_tag_content(&storage_section, "vector_search") {
+            if let Ok(vector_search) = backend.parse::<VectorSearchType>() {
+                character.storage.vector_search = vector_search;
+            }
+        }
+        if let Some(path) = extract_tag_content(&storage_section, "vector_snapshot") {
+            character.storage.vector_snapshot = Some(path);
+        }
+        if let Some(ensure) = extract_tag_content(&storage_section, "ensure_vector_indexes") {
+            if let Ok(ensure) = ensure.parse::<bool>() {
+                character.storage.ensure_vector_indexes = ensure;
+            }
+        }
     }
 
     // Extract voice configuration as a nested object
@@ -354,6 +370,38 @@ pub fn parse_character_xml(xml: &str) -> Result<Character> {
             }
         }
 
+        // Effects chain, applied in order: <effect type="reverb" mix="0.3"/>
+        if let Some(effects_section) = extract_section(&voice_section, "effects") {
+            let mut effects = Vec::new();
+            let mut pos = 0;
+            while let Some(start) = effects_section[pos..].find("<effect ") {
+                let tag_start = pos + start;
+                if let Some(end) = effects_section[tag_start..].find("/>") {
+                    let tag = &effects_section[tag_start..tag_start + end + 2];
+                    let mut effect = serde_json::Map::new();
+                    for (name, value) in extract_attributes(tag) {
+                        let value = if name == "type" {
+                            serde_json::json!(value)
+                        } else if let Ok(n) = value.parse::<i64>() {
+                            serde_json::json!(n)
+                        } else if let Ok(f) = value.parse::<f64>() {
+                            serde_json::json!(f)
+                        } else {
+                            serde_json::json!(value)
+                        };
+                        effect.insert(name, value);
+                    }
+                    effects.push(serde_json::Value::Object(effect));
+                    pos = tag_start + end + 2;
+                } else {
+                    break;
+                }
+            }
+            if !effects.is_empty() {
+                voice_config.insert("effects".to_string(), serde_json::Value::Array(effects));
+            }
+        }
+
         // Discord-specific settings
         if let Some(discord_section) = extract_section(&voice_section, "discord") {
             let mut discord = serde_json::Map::new();
@@ -374,6 +422,11 @@ pub fn parse_character_xml(xml: &str) -> Result<Character> {
                     discord.insert("idle_timeout_seconds".to_string(), serde_json::json!(t));
                 }
             }
+            if let Some(timeout) = extract_tag_content(&discord_section, "alone_timeout_seconds") {
+                if let Ok(t) = timeout.parse::<u64>() {
+                    discord.insert("alone_timeout_seconds".to_string(), serde_json::json!(t));
+                }
+            }
             if let Some(speak) = extract_tag_content(&discord_section, "speak_responses") {
                 discord.insert(
                     "speak_responses".to_string(),
@@ -464,6 +517,28 @@ fn extract_attribute(tag: &str, attr: &str) -> Option<String> {
     None
 }
 
+/// Extract every `name="value"` attribute from an XML tag, in order
+fn extract_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag;
+    while let Some(eq) = rest.find("=\"") {
+        let name = rest[..eq]
+            .rsplit(|c: char| c.is_whitespace())
+            .next()
+            .unwrap_or("")
+            .to_string();
+        let value_start = eq + 2;
+        let Some(quote_end) = rest[value_start..].find('"') else {
+            break;
+        };
+        if !name.is_empty() {
+            attributes.push((name, rest[value_start..value_start + quote_end].to_string()));
+        }
+        rest = &rest[value_start + quote_end + 1..];
+    }
+    attributes
+}
+
 /// Extract CDATA content from XML tag
 fn extract_cdata_content(xml: &str, tag: &str) -> Option<String> {
     let start_tag = format!("<{}>", tag);
@@ -708,6 +783,30 @@ mod tests {
         assert_eq!(extract_attribute(tag, "missing"), None);
     }
 
+    #[test]
+    fn test_parse_voice_effects() {
+        let xml = r#"<character><name>Radio</name><bio><entry>Crackles</entry></bio>
+            <voice>
+                <enabled>true</enabled>
+                <effects>
+                    <effect type="high_pass" cutoff_hz="300"/>
+                    <effect type="reverb" delay_ms="45.5" mix="0.2" />
+                    <effect type="robot"/>
+                </effects>
+            </voice>
+        </character>"#;
+        let character = parse_character_xml(xml).unwrap();
+        let effects = &character.settings["voice"]["effects"];
+        assert_eq!(
+            effects,
+            &serde_json::json!([
+                { "type": "high_pass", "cutoff_hz": 300 },
+                { "type": "reverb", "delay_ms": 45.5, "mix": 0.2 },
+                { "type": "robot" }
+            ])
+        );
+    }
+
     #[test]
     fn test_extract_cdata() {
         let xml = "<template><![CDATA[Content here]]></template>";
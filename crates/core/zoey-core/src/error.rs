@@ -121,6 +121,23 @@ pub enum ZoeyError {
         expected_dimension: usize,
     },
 
+    /// Embeddings written or queried with a different dimension than a collection holds
+    #[error(
+        "Embedding dimension mismatch in '{collection}': it holds {stored_dimension}-dimension embeddings from '{stored_model}', but '{model}' produces {dimension}. Re-embed the collection or configure the original embedding model"
+    )]
+    DimensionMismatch {
+        /// Collection (table) name
+        collection: String,
+        /// Embedding model recorded for the collection
+        stored_model: String,
+        /// Embedding dimension recorded for the collection
+        stored_dimension: usize,
+        /// Embedding model in use
+        model: String,
+        /// Embedding dimension in use
+        dimension: usize,
+    },
+
     /// Missing required field
     #[error("Missing required field '{field}' in {context}. {suggestion}")]
     MissingField {
@@ -268,6 +285,23 @@ impl ZoeyError {
         }
     }
 
+    /// Create an embedding dimension mismatch error
+    pub fn dimension_mismatch(
+        collection: impl Into<String>,
+        stored_model: impl Into<String>,
+        stored_dimension: usize,
+        model: impl Into<String>,
+        dimension: usize,
+    ) -> Self {
+        ZoeyError::DimensionMismatch {
+            collection: collection.into(),
+            stored_model: stored_model.into(),
+            stored_dimension,
+            model: model.into(),
+            dimension,
+        }
+    }
+
     /// Create a missing field error
     pub fn missing_field(
         field: impl Into<String>,
@@ -310,6 +344,21 @@ mod tests {
         assert_eq!(err.to_string(), "Runtime error: test runtime error");
     }
 
+    #[test]
+    fn test_dimension_mismatch_is_actionable() {
+        let err = ZoeyError::dimension_mismatch(
+            "memories",
+            "text-embedding-3-small",
+            1536,
+            "nomic-embed-text",
+            768,
+        );
+        let msg = err.to_string();
+        assert!(msg.contains("'memories'"));
+        assert!(msg.contains("1536-dimension embeddings from 'text-embedding-3-small'"));
+        assert!(msg.contains("'nomic-embed-text' produces 768"));
+    }
+
     #[test]
     fn test_result_type() {
         fn returns_result() -> Result<i32> {
@@ -0,0 +1,358 @@
+//! One person, several accounts
+//!
+//! Each adapter derives entity IDs from its own user IDs
+//! (`telegram-user-<id>`, `discord-user-<id>`), so the same person talking on
+//! Telegram, Discord and the web UI ends up as three entities with three
+//! separate memories. [`IdentityLinks`] lets them pair their accounts:
+//!
+//! 1. A chat adapter issues a short-lived code for the platform account
+//!    ([`IdentityLinks::issue_code`], e.g. Telegram's `/link`)
+//! 2. The user enters it in the web UI, which confirms it for its own entity
+//!    ([`IdentityLinks::confirm`], `POST /agent/link/confirm`)
+//! 3. From then on the adapter maps the platform entity to the web entity
+//!    ([`IdentityLinks::resolve`]) before building memories
+//!
+//! Codes and aliases live in a [`StateStore`], so every adapter sharing the
+//! store shares them too. Resolved aliases are cached for
+//! [`ALIAS_CACHE_TTL`]; [`IdentityLinks::unlink`] drops the cached entry right
+//! away, other processes pick it up when their cache entry expires.
+
+use crate::runtime::legacy::LockRecovery;
+use crate::{Result, StateStore, ZoeyError};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How long a link code can be confirmed
+pub const LINK_CODE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long a resolved alias is trusted before the store is read again
+pub const ALIAS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Namespace of pending codes (code -> [`PlatformAccount`])
+const CODES_NAMESPACE: &str = "identity:link-codes";
+
+/// Namespace of aliases (platform entity ID -> [`LinkedAccount`])
+const ALIASES_NAMESPACE: &str = "identity:aliases";
+
+/// Code characters, without the easily confused 0/O and 1/I
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+const CODE_LEN: usize = 8;
+
+/// Attempts at finding an unused code before giving up
+const CODE_ATTEMPTS: usize = 5;
+
+/// A user account on a chat platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformAccount {
+    /// Adapter name, lowercase (`telegram`, `discord`)
+    pub platform: String,
+    /// The platform's user ID
+    pub user_id: String,
+}
+
+impl PlatformAccount {
+    pub fn new(platform: &str, user_id: impl ToString) -> Self {
+        Self {
+            platform: platform.to_lowercase(),
+            user_id: user_id.to_string(),
+        }
+    }
+
+    /// Entity ID the adapter uses for this account when it is not linked
+    pub fn entity_id(&self) -> Uuid {
+        crate::string_to_uuid(&format!("{}-user-{}", self.platform, self.user_id))
+    }
+}
+
+/// A platform account linked to a web entity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedAccount {
+    #[serde(flatten)]
+    pub account: PlatformAccount,
+    /// The web entity the account's messages are attributed to
+    pub entity_id: Uuid,
+    /// Unix timestamp of the confirmation
+    pub linked_at: i64,
+}
+
+struct CachedEntity {
+    entity_id: Uuid,
+    at: Instant,
+}
+
+/// Link codes and account aliases over a [`StateStore`]
+pub struct IdentityLinks {
+    store: Arc<dyn StateStore>,
+    cache: RwLock<HashMap<Uuid, CachedEntity>>,
+}
+
+impl IdentityLinks {
+    pub fn new(store: Arc<dyn StateStore>) -> Self {
+        Self {
+            store,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// New single-use code linking `account`, valid for [`LINK_CODE_TTL`]
+    pub async fn issue_code(&self, account: &PlatformAccount) -> Result<String> {
+        let value = serde_json::to_value(account)?;
+        for _ in 0..CODE_ATTEMPTS {
+            let code = new_code();
+            if self
+                .store
+                .set_if_absent(CODES_NAMESPACE, &code, value.clone(), Some(LINK_CODE_TTL))
+                .await?
+            {
+                return Ok(code);
+            }
+        }
+        Err(ZoeyError::other("Could not allocate a link code"))
+    }
+
+    /// Link the account behind `code` to `entity_id` and spend the code
+    ///
+    /// Codes are case-insensitive and may contain spaces or dashes. When
+    /// `entity_id` is itself a linked account, the account is linked to the
+    /// same web entity. Fails with [`ZoeyError::NotFound`] for unknown,
+    /// expired or already used codes.
+    pub async fn confirm(&self, code: &str, entity_id: Uuid) -> Result<LinkedAccount> {
+        let code = normalize_code(code);
+        let invalid = || ZoeyError::not_found("Link code is invalid or has expired");
+        if code.len() != CODE_LEN {
+            return Err(invalid());
+        }
+        let value = self
+            .store
+            .get(CODES_NAMESPACE, &code)
+            .await?
+            .ok_or_else(invalid)?;
+        // Whoever deletes the code first gets to use it
+        if !self.store.delete(CODES_NAMESPACE, &code).await? {
+            return Err(invalid());
+        }
+        let account: PlatformAccount = serde_json::from_value(value)?;
+        let target = self.resolve(entity_id).await;
+        if target == account.entity_id() {
+            return Err(ZoeyError::validation(
+                "An account cannot be linked to itself",
+            ));
+        }
+        let alias = LinkedAccount {
+            account,
+            entity_id: target,
+            linked_at: chrono::Utc::now().timestamp(),
+        };
+        let key = alias.account.entity_id();
+        self.store
+            .set(
+                ALIASES_NAMESPACE,
+                &key.to_string(),
+                serde_json::to_value(&alias)?,
+                None,
+            )
+            .await?;
+        self.forget(key);
+        Ok(alias)
+    }
+
+    /// Entity the adapter should attribute `entity_id`'s messages to
+    ///
+    /// Unlinked entities (and store failures) resolve to themselves.
+    pub async fn resolve(&self, entity_id: Uuid) -> Uuid {
+        if let Some(cached) = self.cache.read_or_recover().get(&entity_id) {
+            if cached.at.elapsed() < ALIAS_CACHE_TTL {
+                return cached.entity_id;
+            }
+        }
+        let resolved = match self.alias(entity_id).await {
+            Ok(alias) => alias.map_or(entity_id, |alias| alias.entity_id),
+            Err(e) => {
+                tracing::warn!(entity_id = %entity_id, error = %e, "Alias lookup failed");
+                return entity_id;
+            }
+        };
+        self.cache.write_or_recover().insert(
+            entity_id,
+            CachedEntity {
+                entity_id: resolved,
+                at: Instant::now(),
+            },
+        );
+        resolved
+    }
+
+    /// Stored alias of a platform entity
+    pub async fn alias(&self, entity_id: Uuid) -> Result<Option<LinkedAccount>> {
+        match self
+            .store
+            .get(ALIASES_NAMESPACE, &entity_id.to_string())
+            .await?
+        {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove `account`'s link, returning whether it was linked
+    pub async fn unlink(&self, account: &PlatformAccount) -> Result<bool> {
+        let key = account.entity_id();
+        let removed = self
+            .store
+            .delete(ALIASES_NAMESPACE, &key.to_string())
+            .await?;
+        self.forget(key);
+        Ok(removed)
+    }
+
+    /// Every account linked to the same web entity as `entity_id`
+    ///
+    /// Scans all aliases; fine for the handful of links a deployment has.
+    pub async fn linked_accounts(&self, entity_id: Uuid) -> Result<Vec<LinkedAccount>> {
+        let target = match self.alias(entity_id).await? {
+            Some(alias) => alias.entity_id,
+            None => entity_id,
+        };
+        let mut aliases: Vec<LinkedAccount> = self
+            .store
+            .list(ALIASES_NAMESPACE)
+            .await?
+            .into_iter()
+            .filter_map(|(_, value)| serde_json::from_value::<LinkedAccount>(value).ok())
+            .filter(|alias| alias.entity_id == target)
+            .collect();
+        aliases.sort_by(|a, b| {
+            (&a.account.platform, a.linked_at).cmp(&(&b.account.platform, b.linked_at))
+        });
+        Ok(aliases)
+    }
+
+    fn forget(&self, entity_id: Uuid) {
+        self.cache.write_or_recover().remove(&entity_id);
+    }
+}
+
+fn new_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LEN)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// `id` with all but its last four characters masked, for showing back to users
+pub fn redact_id(id: &str) -> String {
+    let chars: Vec<char> = id.chars().collect();
+    if chars.len() <= 4 {
+        return "•".repeat(chars.len().max(4));
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("••••{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStateStore;
+
+    fn links() -> IdentityLinks {
+        IdentityLinks::new(Arc::new(MemoryStateStore::new()))
+    }
+
+    #[tokio::test]
+    async fn test_codes_from_any_platform_link_to_the_web_entity() {
+        let links = links();
+        let web = Uuid::new_v4();
+        let telegram = PlatformAccount::new("telegram", 4242u64);
+        let discord = PlatformAccount::new("Discord", 9001u64);
+
+        let code = links.issue_code(&telegram).await.unwrap();
+        assert_eq!(code.len(), CODE_LEN);
+        let alias = links.confirm(&code.to_lowercase(), web).await.unwrap();
+        assert_eq!(alias.account, telegram);
+        assert_eq!(alias.entity_id, web);
+        // Single use
+        assert!(matches!(
+            links.confirm(&code, web).await,
+            Err(ZoeyError::NotFound(_))
+        ));
+
+        // Confirming from the Telegram entity links Discord to the same web entity
+        let code = links.issue_code(&discord).await.unwrap();
+        let spaced = format!("{}-{}", &code[..4], &code[4..]);
+        let alias = links.confirm(&spaced, telegram.entity_id()).await.unwrap();
+        assert_eq!(alias.entity_id, web);
+        assert_eq!(links.resolve(discord.entity_id()).await, web);
+        assert_eq!(links.resolve(telegram.entity_id()).await, web);
+        assert_eq!(links.resolve(web).await, web);
+
+        let linked = links.linked_accounts(telegram.entity_id()).await.unwrap();
+        let platforms: Vec<_> = linked.iter().map(|a| a.account.platform.as_str()).collect();
+        assert_eq!(platforms, ["discord", "telegram"]);
+
+        // An account cannot vouch for itself
+        let other = PlatformAccount::new("telegram", 1u64);
+        let code = links.issue_code(&other).await.unwrap();
+        assert!(links.confirm(&code, other.entity_id()).await.is_err());
+        assert!(links.confirm("NOPE", web).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unlink_invalidates_cached_alias() {
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let links = IdentityLinks::new(store.clone());
+        let web = Uuid::new_v4();
+        let account = PlatformAccount::new("telegram", 7u64);
+        let code = links.issue_code(&account).await.unwrap();
+        links.confirm(&code, web).await.unwrap();
+        assert_eq!(links.resolve(account.entity_id()).await, web);
+
+        // The cache answers even when the store no longer has the alias...
+        store
+            .delete(ALIASES_NAMESPACE, &account.entity_id().to_string())
+            .await
+            .unwrap();
+        assert_eq!(links.resolve(account.entity_id()).await, web);
+        // ...until the alias is unlinked through the service
+        store
+            .set(
+                ALIASES_NAMESPACE,
+                &account.entity_id().to_string(),
+                serde_json::json!({
+                    "platform": "telegram",
+                    "userId": "7",
+                    "entityId": web,
+                    "linkedAt": 0
+                }),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(links.unlink(&account).await.unwrap());
+        assert_eq!(
+            links.resolve(account.entity_id()).await,
+            account.entity_id()
+        );
+        assert!(!links.unlink(&account).await.unwrap());
+    }
+
+    #[test]
+    fn test_redact_id() {
+        assert_eq!(redact_id("123456789"), "••••6789");
+        assert_eq!(redact_id("12"), "••••");
+        assert_eq!(redact_id(""), "••••");
+    }
+}
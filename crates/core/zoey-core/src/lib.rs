@@ -34,6 +34,7 @@ pub use uuid::Uuid;
 
 // Core modules
 pub mod actions;
+pub mod adapter_telemetry;
 pub mod agent_api;
 pub mod character_loader;
 pub mod config;
@@ -43,6 +44,7 @@ pub mod zoeyos;
 pub mod entities;
 pub mod error;
 pub mod function_calling;
+pub mod identity_links;
 pub mod infrastructure;
 pub mod ipo;
 pub mod message;
@@ -59,6 +61,7 @@ pub mod runtime;
 pub mod runtime_ref;
 pub mod secrets;
 pub mod security;
+pub mod state_store;
 pub mod streaming;
 pub mod templates;
 pub mod testing;
@@ -76,6 +79,10 @@ pub mod workers;
 
 // Re-export main types
 pub use actions::{compose_action_examples, format_action_names, format_actions};
+pub use adapter_telemetry::{
+    AdapterEvent, AdapterEventKind, MessageTelemetry, Telemetry, TelemetryConfig, TelemetrySink,
+    TelemetrySinkConfig,
+};
 pub use character_loader::{load_character_from_xml, parse_character_xml};
 pub use config::{
     get_env_bool, get_env_float, get_env_int, get_env_or, get_required_env, load_env,
@@ -102,6 +109,7 @@ pub use function_calling::{
     create_function_definition, FunctionCall, FunctionDefinition, FunctionHandler,
     FunctionRegistry, FunctionResult,
 };
+pub use identity_links::{redact_id, IdentityLinks, LinkedAccount, PlatformAccount};
 pub use integration::{
     AdaptiveCapable,
     AlternativeOption,
@@ -165,8 +173,9 @@ pub use planner::{
     TokenBudget, TokenCounter, TokenEstimate, TokenTracker,
 };
 pub use plugin::{
-    get_plugin_actions, get_plugin_evaluators, get_plugin_providers, get_plugin_services,
-    initialize_plugins, load_plugins, resolve_plugin_dependencies, validate_plugin,
+    check_plugin_core_version, core_compatibility, get_plugin_actions, get_plugin_evaluators,
+    get_plugin_providers, get_plugin_services, initialize_plugins, load_plugins,
+    resolve_plugin_dependencies, validate_plugin, CoreCompatibility,
 };
 pub use resilience::{
     retry_with_backoff, CircuitBreaker, CircuitState, HealthCheck, HealthChecker, HealthStatus,
@@ -175,7 +184,9 @@ pub use resilience::{
 pub use roles::{
     find_worlds_for_owner, get_user_world_role, is_admin_or_owner, is_moderator_or_higher, Role,
 };
-pub use runtime::{AgentRuntime, RuntimeOpts};
+pub use runtime::{
+    AgentRuntime, RuntimeOpts, SnapshotContributor, SnapshotFilePersister, StateSnapshot,
+};
 pub use runtime_ref::{downcast_runtime_ref, RuntimeRef};
 pub use secrets::{
     get_secret, has_character_secrets, load_secret_from_env, remove_secret,
@@ -185,6 +196,7 @@ pub use security::{
     decrypt_secret, encrypt_secret, hash_password, sanitize_input, validate_input, verify_password,
     RateLimiter,
 };
+pub use state_store::{MemoryStateStore, StateStore};
 pub use streaming::{
     collect_stream, create_text_stream, StreamHandler, TextChunk, TextStream, TextStreamSender,
 };
@@ -197,7 +209,11 @@ pub use training::{
     TrainingConfig, TrainingFormat, TrainingSample,
 };
 pub use types::*;
-pub use utils::{create_unique_uuid, string_to_uuid, Logger, BM25};
+pub use utils::{
+    create_unique_uuid, extract_response, extract_streaming_text, render_response_template,
+    string_to_uuid, ContextOverflowPolicy, ExtractedResponse, Logger, ResponseTemplateVars, BM25,
+    CONTEXT_OVERFLOW_MESSAGE,
+};
 
 // Extension traits for enterprise
 pub use extensions::{
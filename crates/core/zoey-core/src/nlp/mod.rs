@@ -1,5 +1,9 @@
 use std::cmp::min;
 
+mod wake_word;
+
+pub use wake_word::WakeWordMatcher;
+
 pub fn double_metaphone(s: &str) -> (String, String) {
     let mut primary = String::new();
     let mut secondary = String::new();
@@ -0,0 +1,126 @@
+//! Fuzzy detection of a character's name in text or transcribed speech
+
+use super::normalized_similarity;
+
+/// Detects a character's name in transcribed speech or chat text
+///
+/// STT often mangles names ("Zoey" becomes "Zoe", "Zowie", ...), so the
+/// matcher expands spelling variants for the full name and its first word
+/// and also accepts short words sharing the name's prefix.
+#[derive(Debug, Clone)]
+pub struct WakeWordMatcher {
+    name: String,
+    variants: Vec<String>,
+}
+
+impl WakeWordMatcher {
+    /// Matcher for `name`, with variants for the full name and its first word
+    pub fn new(name: &str) -> Self {
+        let name_lower = name.trim().to_lowercase();
+        let mut variants = Vec::new();
+        let first_word = name_lower.split_whitespace().next().unwrap_or_default();
+        for base_name in [name_lower.as_str(), first_word] {
+            if base_name.is_empty() {
+                continue;
+            }
+            variants.push(base_name.to_string());
+            if base_name.ends_with("ey") {
+                // For names ending in 'ey' (like Zoey)
+                let base = &base_name[..base_name.len() - 2];
+                variants.push(format!("{}e", base)); // zoey -> zoe
+                variants.push(format!("{}ie", base)); // zoey -> zoie
+                variants.push(format!("{}owie", base)); // zoey -> zowie
+                variants.push(format!("{}oy", base)); // zoey -> zoy
+                variants.push(format!("{}oi", base)); // zoey -> zoi
+                variants.push(format!("{}o e", base)); // zoey -> zo e (space)
+                variants.push(format!("{}o-i", base)); // zoey -> zo-i (hyphen)
+                if base.len() >= 2 {
+                    variants.push(base.to_string());
+                }
+            } else if base_name.ends_with('y') {
+                // For names ending in 'y' (like Joey, Amy)
+                let base = &base_name[..base_name.len() - 1];
+                variants.push(base.to_string()); // joey -> joe
+                variants.push(format!("{}ie", base)); // joey -> joie
+                variants.push(format!("{}i", base)); // joey -> joi
+            }
+        }
+        variants.sort();
+        variants.dedup();
+        Self {
+            name: name_lower,
+            variants,
+        }
+    }
+
+    /// Name this matcher listens for (lowercased)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the text mentions the name or one of its variants
+    pub fn matches(&self, text: &str) -> bool {
+        let text_lower = text.to_lowercase();
+        if self.variants.iter().any(|v| text_lower.contains(v.as_str())) {
+            return true;
+        }
+        // Fallback: partial phonetic match on the first word's prefix
+        let first_word = self.name.split_whitespace().next().unwrap_or_default();
+        if first_word.chars().count() < 4 {
+            return false;
+        }
+        let prefix: String = first_word.chars().take(4).collect();
+        text_lower.split_whitespace().any(|word| {
+            word.starts_with(prefix.as_str()) && word.chars().count() <= prefix.chars().count() + 2
+        })
+    }
+
+    /// How closely any word of `text` resembles the name, from 0.0 to 1.0
+    ///
+    /// Unlike [`matches`](Self::matches) this compares whole words, so typed
+    /// text scores 1.0 only when a word is the name or one of its variants
+    /// and misspellings land in between.
+    pub fn score(&self, text: &str) -> f32 {
+        let text_lower = text.to_lowercase();
+        text_lower
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.chars().count() >= 2)
+            .flat_map(|word| {
+                self.variants
+                    .iter()
+                    .filter(|v| !v.contains([' ', '-']))
+                    .map(move |v| normalized_similarity(word, v))
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wake_word_matcher() {
+        let zoey = WakeWordMatcher::new("Zoey");
+        assert!(zoey.matches("hey zoe what's up"));
+        assert!(zoey.matches("Zowie, are you there?"));
+        assert!(!zoey.matches("hello there"));
+
+        // Multi-word character names also wake on the first word
+        let support = WakeWordMatcher::new("Max Support");
+        assert_eq!(support.name(), "max support");
+        assert!(support.matches("max can you help"));
+        assert!(!support.matches("zoey can you help"));
+    }
+
+    #[test]
+    fn test_wake_word_score() {
+        let zoey = WakeWordMatcher::new("Zoey");
+        assert_eq!(zoey.score("Zoey, are you there?"), 1.0);
+        assert_eq!(zoey.score("hey zoe"), 1.0);
+        // One edit away from a four-letter variant
+        assert_eq!(zoey.score("zoeu can you check this"), 0.75);
+        assert!(zoey.score("hello there") < 0.5);
+        assert_eq!(zoey.score(""), 0.0);
+    }
+}
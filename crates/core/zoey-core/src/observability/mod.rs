@@ -96,6 +96,8 @@ impl ObservabilityPlugin {
 
 #[async_trait::async_trait]
 impl Plugin for ObservabilityPlugin {
+    crate::plugin_core_version!();
+
     fn name(&self) -> &str {
         "observability"
     }
@@ -1,9 +1,82 @@
 //! Plugin loading and management utilities
 
-use crate::types::Plugin;
+use crate::types::{Plugin, CORE_VERSION};
 use crate::{ZoeyError, Result};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use tracing::warn;
+
+/// Compatibility of a plugin's core version with the running core
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreCompatibility {
+    /// Same ABI; safe to register
+    Compatible,
+    /// Probably fine, but worth a warning (newer patch, unparseable version)
+    Warn(String),
+    /// Different ABI; the plugin must not be registered
+    Incompatible(String),
+}
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    Some((parts.next()??, parts.next().unwrap_or(Some(0))?, parts.next().unwrap_or(Some(0))?))
+}
+
+/// Compare a plugin's core version against the running core version
+///
+/// Follows semver: before 1.0 the minor version is the breaking component.
+/// Plugins built against a newer minor (or a newer patch, with a warning)
+/// may rely on shapes this core does not have.
+pub fn core_compatibility(plugin_version: &str, core_version: &str) -> CoreCompatibility {
+    let (Some(plugin), Some(core)) = (parse_version(plugin_version), parse_version(core_version))
+    else {
+        return CoreCompatibility::Warn(format!(
+            "cannot compare core version '{}' with '{}'",
+            plugin_version, core_version
+        ));
+    };
+    let (p_major, p_minor, p_patch) = plugin;
+    let (c_major, c_minor, c_patch) = core;
+    if p_major != c_major {
+        return CoreCompatibility::Incompatible(format!(
+            "built against core {} but running {} (major version differs)",
+            plugin_version, core_version
+        ));
+    }
+    if (c_major == 0 && p_minor != c_minor) || p_minor > c_minor {
+        return CoreCompatibility::Incompatible(format!(
+            "built against core {} but running {} (minor version differs)",
+            plugin_version, core_version
+        ));
+    }
+    if p_minor == c_minor && p_patch > c_patch {
+        return CoreCompatibility::Warn(format!(
+            "built against newer core {} than running {}",
+            plugin_version, core_version
+        ));
+    }
+    CoreCompatibility::Compatible
+}
+
+/// Check a plugin's core version before registration
+///
+/// Logs a warning for soft mismatches and refuses incompatible plugins.
+pub fn check_plugin_core_version(plugin: &dyn Plugin) -> Result<()> {
+    match core_compatibility(plugin.core_version(), CORE_VERSION) {
+        CoreCompatibility::Compatible => Ok(()),
+        CoreCompatibility::Warn(reason) => {
+            warn!(plugin = %plugin.name(), "Plugin core version mismatch: {}", reason);
+            Ok(())
+        }
+        CoreCompatibility::Incompatible(reason) => Err(ZoeyError::plugin(format!(
+            "Refusing incompatible plugin '{}': {}",
+            plugin.name(),
+            reason
+        ))),
+    }
+}
 
 /// Validate a plugin's structure
 ///
@@ -251,6 +324,8 @@ mod tests {
 
     #[async_trait]
     impl Plugin for MockPlugin {
+        crate::plugin_core_version!();
+
         fn name(&self) -> &str {
             &self.name
         }
@@ -284,6 +359,58 @@ mod tests {
         assert!(validate_plugin(&plugin).is_err());
     }
 
+    #[test]
+    fn test_core_compatibility() {
+        use CoreCompatibility::*;
+        assert_eq!(core_compatibility("0.1.1", "0.1.1"), Compatible);
+        assert_eq!(core_compatibility("0.1.0", "0.1.1"), Compatible);
+        assert!(matches!(core_compatibility("0.1.2", "0.1.1"), Warn(_)));
+        assert!(matches!(core_compatibility("0.2.0", "0.1.1"), Incompatible(_)));
+        assert!(matches!(core_compatibility("0.0.9", "0.1.1"), Incompatible(_)));
+        assert_eq!(core_compatibility("1.2.0", "1.4.0-beta.1"), Compatible);
+        assert!(matches!(core_compatibility("1.5.0", "1.4.0"), Incompatible(_)));
+        assert!(matches!(core_compatibility("2.0.0", "1.4.0"), Incompatible(_)));
+        assert!(matches!(core_compatibility("dev", "0.1.1"), Warn(_)));
+    }
+
+    struct OldCorePlugin;
+
+    #[async_trait]
+    impl Plugin for OldCorePlugin {
+        fn name(&self) -> &str {
+            "old-core"
+        }
+
+        fn description(&self) -> &str {
+            "Built against an ancient core"
+        }
+
+        fn core_version(&self) -> &str {
+            "0.0.1"
+        }
+    }
+
+    struct MacroPlugin;
+
+    #[async_trait]
+    impl Plugin for MacroPlugin {
+        crate::plugin_core_version!();
+
+        fn name(&self) -> &str {
+            "macro"
+        }
+
+        fn description(&self) -> &str {
+            "Uses the version macro"
+        }
+    }
+
+    #[test]
+    fn test_check_plugin_core_version() {
+        assert!(check_plugin_core_version(&MacroPlugin).is_ok());
+        assert!(check_plugin_core_version(&OldCorePlugin).is_err());
+    }
+
     #[test]
     fn test_resolve_dependencies() {
         let plugin_a: Arc<dyn Plugin> = Arc::new(MockPlugin {
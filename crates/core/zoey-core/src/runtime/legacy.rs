@@ -26,6 +26,8 @@
 //! 17. `observability` - Metrics and monitoring
 //! 18. `zoey_os` - Framework integration
 //! 19. `logger` - Logging span
+//! 20. `snapshot_contributors` - Warm-restart snapshot contributors
+//! 21. `pending_snapshot_sections` - Restored sections awaiting a contributor
 //!
 //! ## Poisoned Lock Recovery
 //!
@@ -533,6 +535,9 @@ pub struct AgentRuntime {
     /// Database adapter
     pub(crate) adapter: Arc<RwLock<Option<Arc<dyn IDatabaseAdapter + Send + Sync>>>>,
 
+    /// Vector search used instead of the adapter's own, if configured
+    pub(crate) vector_search: Arc<RwLock<Option<Arc<dyn IVectorSearch>>>>,
+
     /// Registered actions
     pub(crate) actions: Arc<RwLock<Vec<Arc<dyn Action>>>>,
 
@@ -602,6 +607,12 @@ pub struct AgentRuntime {
 
     /// Training data collector for RLHF and model fine-tuning
     training_collector: Option<Arc<crate::training::TrainingCollector>>,
+
+    /// Registered snapshot contributors for warm restarts
+    pub(crate) snapshot_contributors: Arc<RwLock<Vec<Arc<dyn super::SnapshotContributor>>>>,
+
+    /// Restored snapshot sections awaiting their contributor
+    pub(crate) pending_snapshot_sections: Arc<RwLock<HashMap<String, serde_json::Value>>>,
 }
 
 /// Runtime options for constructing an AgentRuntime.
@@ -659,6 +670,10 @@ pub struct RuntimeOpts {
     /// For production use, provide SqliteAdapter or PostgresAdapter.
     pub adapter: Option<Arc<dyn IDatabaseAdapter + Send + Sync>>,
 
+    /// Vector search backend replacing the adapter's own similarity search.
+    /// If None, memory search goes through the adapter.
+    pub vector_search: Option<Arc<dyn IVectorSearch>>,
+
     /// Additional configuration settings as key-value pairs.
     /// These can be accessed via runtime.get_setting().
     pub settings: Option<HashMap<String, serde_json::Value>>,
@@ -705,6 +720,12 @@ impl RuntimeOpts {
         self
     }
 
+    /// Set the vector search backend.
+    pub fn with_vector_search(mut self, vector_search: Arc<dyn IVectorSearch>) -> Self {
+        self.vector_search = Some(vector_search);
+        self
+    }
+
     /// Set the plugins to register.
     pub fn with_plugins(mut self, plugins: Vec<Arc<dyn Plugin>>) -> Self {
         self.plugins = plugins;
@@ -810,6 +831,7 @@ impl AgentRuntime {
             agent_id,
             character,
             adapter: Arc::new(RwLock::new(opts.adapter)),
+            vector_search: Arc::new(RwLock::new(opts.vector_search)),
             actions: Arc::new(RwLock::new(Vec::new())),
             evaluators: Arc::new(RwLock::new(Vec::new())),
             providers: Arc::new(RwLock::new(Vec::new())),
@@ -834,6 +856,8 @@ impl AgentRuntime {
             lock_recovery_strategy,
             lock_poison_metrics,
             training_collector,
+            snapshot_contributors: Arc::new(RwLock::new(Vec::new())),
+            pending_snapshot_sections: Arc::new(RwLock::new(HashMap::new())),
         };
 
         let runtime_arc = Arc::new(RwLock::new(runtime));
@@ -872,6 +896,9 @@ impl AgentRuntime {
         info!("Registering plugin: {}", plugin.name());
         debug!("plugin_register:start name={}", plugin.name());
 
+        // Refuse plugins built against an incompatible core before touching any registry
+        crate::plugin::check_plugin_core_version(plugin.as_ref())?;
+
         // Register actions
         for action in plugin.actions() {
             self.actions.write_or_recover().push(action);
@@ -1050,6 +1077,15 @@ impl AgentRuntime {
             warn!("No database adapter configured - running without persistence");
         }
 
+        // Fail fast when the vector index was built with another embedding model
+        if let Some(vector_search) = self.get_vector_search() {
+            vector_search.verify_collection("memories").await?;
+            info!(
+                "✓ Vector search collection matches embedding dimension {}",
+                vector_search.embedding_dimension()
+            );
+        }
+
         // Initialize services
         let service_map = self.services.read_or_recover();
         if !service_map.is_empty() {
@@ -1125,9 +1161,28 @@ impl AgentRuntime {
             };
             let raw = (provider.handler)(mh_params).await?;
             if let Ok(vec) = serde_json::from_str::<Vec<f32>>(&raw) {
+                let vector_search = self.get_vector_search();
+                // Refuse before persisting anything the vector index could never match
+                if let Some(ref vector_search) = vector_search {
+                    let expected = vector_search.embedding_dimension();
+                    if vec.len() != expected {
+                        return Err(crate::ZoeyError::dimension_mismatch(
+                            "memories",
+                            vector_search
+                                .embedding_model()
+                                .unwrap_or_else(|| "the configured model".to_string()),
+                            expected,
+                            provider.name.clone(),
+                            vec.len(),
+                        ));
+                    }
+                }
                 let mut updated = memory.clone();
                 updated.embedding = Some(vec);
                 let _ = adapter.update_memory(&updated).await?;
+                if let Some(vector_search) = vector_search {
+                    vector_search.upsert_memory("memories", &updated).await?;
+                }
             }
             Ok(())
         } else {
@@ -1422,6 +1477,16 @@ impl AgentRuntime {
         self.adapter.read_or_recover().clone()
     }
 
+    /// Get the configured vector search backend
+    pub fn get_vector_search(&self) -> Option<Arc<dyn IVectorSearch>> {
+        self.vector_search.read_or_recover().clone()
+    }
+
+    /// Replace the vector search backend
+    pub fn set_vector_search(&self, vector_search: Option<Arc<dyn IVectorSearch>>) {
+        *self.vector_search.write_or_recover() = vector_search;
+    }
+
     /// Get model providers
     pub fn get_models(&self) -> HashMap<String, Vec<ModelProvider>> {
         crate::plugin_system::registry::get_models(self)
@@ -2,10 +2,12 @@ mod events;
 mod executor;
 pub mod legacy;
 mod lifecycle;
+mod snapshot;
 mod state;
 
 pub use events::*;
 pub use executor::*;
 pub use legacy::*;
 pub use lifecycle::LockHealthStatus;
+pub use snapshot::*;
 pub use state::*;
@@ -0,0 +1,331 @@
+//! Runtime state snapshots for warm restarts
+//!
+//! A [`StateSnapshot`] captures the ephemeral, in-memory state that would
+//! otherwise be lost when the process restarts. Adapters opt in by registering
+//! a [`SnapshotContributor`] that serializes their own maps into a named
+//! section of the snapshot.
+//!
+//! ## What is captured
+//!
+//! - Runtime settings under the `ui:` prefix (UI toggles and per-room
+//!   `ui:lastThought:*` context written while the agent was running)
+//! - One JSON section per registered contributor (e.g. Discord voice
+//!   conversation windows)
+//!
+//! ## What is intentionally NOT restored
+//!
+//! - The composed state cache: it is keyed by message ID and rebuilt cheaply
+//! - Action results and the current run ID: they belong to in-flight runs
+//! - Adapter dedup keys and voice-channel membership: they live in the
+//!   adapters' [`StateStore`](crate::StateStore), which may itself be persistent
+//! - Rate limiter windows: a restart is allowed to reset them
+//! - Non-`ui:` settings: they come from the character file and environment,
+//!   which may have changed between restarts (and may contain secrets)
+//! - Live connections (voice calls, websockets, child processes)
+//!
+//! Sections that arrive before their contributor registers are kept as pending
+//! and applied on registration, so snapshots can be restored before adapters start.
+
+use super::AgentRuntime;
+use crate::runtime::legacy::LockRecovery;
+use crate::{Result, ZoeyError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Current snapshot format version
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Setting prefix captured in snapshots
+const SNAPSHOT_SETTINGS_PREFIX: &str = "ui:";
+
+/// Serializable snapshot of ephemeral runtime state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Snapshot format version
+    pub version: u32,
+    /// Agent the snapshot was taken from
+    pub agent_id: Uuid,
+    /// Unix timestamp (seconds) when the snapshot was taken
+    pub created_at: i64,
+    /// Captured `ui:` settings
+    #[serde(default)]
+    pub settings: HashMap<String, serde_json::Value>,
+    /// Contributor sections keyed by contributor key
+    #[serde(default)]
+    pub sections: HashMap<String, serde_json::Value>,
+}
+
+impl StateSnapshot {
+    /// Create an empty snapshot for an agent
+    pub fn new(agent_id: Uuid) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            agent_id,
+            created_at: chrono::Utc::now().timestamp(),
+            settings: HashMap::new(),
+            sections: HashMap::new(),
+        }
+    }
+
+    /// Write the snapshot to disk as JSON
+    pub async fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        if let Some(dir) = path.as_ref().parent().filter(|d| !d.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(path.as_ref(), json).await?;
+        info!("State snapshot saved to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Load a snapshot from disk, returning `None` if the file does not exist
+    pub async fn load_from_file(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let raw = match tokio::fs::read_to_string(path.as_ref()).await {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot: Self = serde_json::from_str(&raw)?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(ZoeyError::validation(format!(
+                "Snapshot version {} is newer than supported version {}",
+                snapshot.version, SNAPSHOT_VERSION
+            )));
+        }
+        Ok(Some(snapshot))
+    }
+}
+
+/// Component that contributes a section to runtime snapshots
+///
+/// Implemented by adapters that keep in-memory state worth preserving across
+/// a warm restart. Only clearly-serializable state should be captured.
+pub trait SnapshotContributor: Send + Sync {
+    /// Unique section key (e.g. "discord")
+    fn key(&self) -> &str;
+
+    /// Serialize current state, or `None` if there is nothing to save
+    fn snapshot(&self) -> Option<serde_json::Value>;
+
+    /// Restore state from a previously captured section
+    fn restore(&self, section: serde_json::Value) -> Result<()>;
+}
+
+impl AgentRuntime {
+    /// Register a snapshot contributor
+    ///
+    /// If a restored snapshot already holds a section for this contributor,
+    /// it is applied immediately.
+    pub fn register_snapshot_contributor(&self, contributor: Arc<dyn SnapshotContributor>) {
+        let pending = self
+            .pending_snapshot_sections
+            .write_or_recover()
+            .remove(contributor.key());
+        if let Some(section) = pending {
+            match contributor.restore(section) {
+                Ok(()) => info!(key = %contributor.key(), "Restored pending snapshot section"),
+                Err(e) => warn!(key = %contributor.key(), error = %e, "Failed to restore snapshot section"),
+            }
+        }
+        let mut contributors = self.snapshot_contributors.write_or_recover();
+        contributors.retain(|c| c.key() != contributor.key());
+        contributors.push(contributor);
+    }
+
+    /// Capture a snapshot of ephemeral runtime state
+    pub fn snapshot(&self) -> StateSnapshot {
+        let mut snapshot = StateSnapshot::new(self.agent_id);
+
+        for (key, value) in self.settings.read_or_recover().iter() {
+            if key.starts_with(SNAPSHOT_SETTINGS_PREFIX) {
+                snapshot.settings.insert(key.clone(), value.clone());
+            }
+        }
+
+        let contributors = self.snapshot_contributors.read_or_recover().clone();
+        for contributor in contributors {
+            if let Some(section) = contributor.snapshot() {
+                snapshot.sections.insert(contributor.key().to_string(), section);
+            }
+        }
+
+        debug!(
+            settings = snapshot.settings.len(),
+            sections = snapshot.sections.len(),
+            "Captured runtime snapshot"
+        );
+        snapshot
+    }
+
+    /// Restore ephemeral runtime state from a snapshot
+    ///
+    /// Sections without a registered contributor are kept pending until one
+    /// registers. Snapshots taken by a different agent are rejected.
+    pub fn restore(&self, snapshot: StateSnapshot) -> Result<()> {
+        if snapshot.agent_id != self.agent_id {
+            return Err(ZoeyError::validation(format!(
+                "Snapshot belongs to agent {} but runtime is {}",
+                snapshot.agent_id, self.agent_id
+            )));
+        }
+
+        {
+            let mut settings = self.settings.write_or_recover();
+            for (key, value) in snapshot.settings {
+                if key.starts_with(SNAPSHOT_SETTINGS_PREFIX) {
+                    settings.insert(key, value);
+                }
+            }
+        }
+
+        let contributors = self.snapshot_contributors.read_or_recover().clone();
+        let mut pending = self.pending_snapshot_sections.write_or_recover();
+        for (key, section) in snapshot.sections {
+            match contributors.iter().find(|c| c.key() == key) {
+                Some(contributor) => {
+                    if let Err(e) = contributor.restore(section) {
+                        warn!(key = %key, error = %e, "Failed to restore snapshot section");
+                    }
+                }
+                None => {
+                    pending.insert(key, section);
+                }
+            }
+        }
+
+        info!(
+            pending_sections = pending.len(),
+            "Runtime state restored from snapshot"
+        );
+        Ok(())
+    }
+}
+
+/// [`StatePersister`](crate::infrastructure::StatePersister) that writes runtime
+/// snapshots to a file on shutdown and restores them on start
+pub struct SnapshotFilePersister {
+    path: std::path::PathBuf,
+    runtime: Arc<std::sync::RwLock<AgentRuntime>>,
+}
+
+impl SnapshotFilePersister {
+    /// Create a new snapshot persister for a runtime
+    pub fn new(path: impl Into<std::path::PathBuf>, runtime: Arc<std::sync::RwLock<AgentRuntime>>) -> Self {
+        Self {
+            path: path.into(),
+            runtime,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::infrastructure::StatePersister for SnapshotFilePersister {
+    async fn persist_state(&self) -> Result<()> {
+        let snapshot = self.runtime.read_or_recover().snapshot();
+        snapshot.save_to_file(&self.path).await
+    }
+
+    async fn restore_state(&self) -> Result<()> {
+        match StateSnapshot::load_from_file(&self.path).await? {
+            Some(snapshot) => self.runtime.read_or_recover().restore(snapshot),
+            None => {
+                debug!("No state snapshot found at {:?}", self.path);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Character;
+    use crate::RuntimeOpts;
+    use std::sync::RwLock;
+
+    struct CounterContributor {
+        value: RwLock<u64>,
+    }
+
+    impl SnapshotContributor for CounterContributor {
+        fn key(&self) -> &str {
+            "counter"
+        }
+
+        fn snapshot(&self) -> Option<serde_json::Value> {
+            Some(serde_json::json!(*self.value.read().unwrap()))
+        }
+
+        fn restore(&self, section: serde_json::Value) -> Result<()> {
+            *self.value.write().unwrap() = section.as_u64().unwrap_or(0);
+            Ok(())
+        }
+    }
+
+    async fn test_runtime() -> Arc<std::sync::RwLock<AgentRuntime>> {
+        AgentRuntime::new(RuntimeOpts {
+            character: Some(Character {
+                name: "SnapshotAgent".to_string(),
+                ..Default::default()
+            }),
+            test_mode: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let source = test_runtime().await;
+        {
+            let mut rt = source.write().unwrap();
+            rt.set_setting("ui:lastThought:room:1", serde_json::json!("thinking"), false);
+            rt.set_setting("DISCORD_TOKEN", serde_json::json!("secret"), false);
+            rt.register_snapshot_contributor(Arc::new(CounterContributor {
+                value: RwLock::new(7),
+            }));
+        }
+        let snapshot = source.read().unwrap().snapshot();
+        assert!(snapshot.settings.contains_key("ui:lastThought:room:1"));
+        assert!(!snapshot.settings.contains_key("DISCORD_TOKEN"));
+
+        // Restore before the contributor registers: section stays pending
+        let target = test_runtime().await;
+        let rt = target.read().unwrap();
+        rt.restore(snapshot).unwrap();
+        assert_eq!(
+            rt.get_setting("ui:lastThought:room:1"),
+            Some(serde_json::json!("thinking"))
+        );
+        let contributor = Arc::new(CounterContributor {
+            value: RwLock::new(0),
+        });
+        rt.register_snapshot_contributor(contributor.clone());
+        assert_eq!(*contributor.value.read().unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_other_agent() {
+        let rt = test_runtime().await;
+        let snapshot = StateSnapshot::new(Uuid::new_v4());
+        assert!(rt.read().unwrap().restore(snapshot).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        assert!(StateSnapshot::load_from_file(&path).await.unwrap().is_none());
+
+        let snapshot = StateSnapshot::new(Uuid::new_v4());
+        snapshot.save_to_file(&path).await.unwrap();
+        let loaded = StateSnapshot::load_from_file(&path).await.unwrap().unwrap();
+        assert_eq!(loaded.agent_id, snapshot.agent_id);
+        assert_eq!(loaded.version, SNAPSHOT_VERSION);
+    }
+}
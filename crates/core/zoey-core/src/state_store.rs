@@ -0,0 +1,219 @@
+//! Namespaced key-value state shared by adapters
+//!
+//! Adapters keep small pieces of ephemeral state: message dedup keys,
+//! voice-channel membership and the like. Kept in process-local maps, that
+//! state cannot be shared when several processes serve the same bot, so two
+//! instances would both answer a message or disagree about who is in which
+//! voice channel. [`StateStore`] is the interface those maps are written
+//! through instead.
+//!
+//! - [`MemoryStateStore`] keeps everything in the process (the default)
+//! - `MongoStateStore` in `zoey-storage-mongo` shares state across processes
+//!
+//! Keys live in a namespace (e.g. `"telegram:dedup"`) so adapters cannot
+//! collide, and every write may carry a TTL after which the entry reads as
+//! absent.
+
+use crate::runtime::legacy::LockRecovery;
+use crate::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Namespaced key-value store with optional per-entry TTL
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    /// Value stored under `key`, if present and not expired
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>>;
+
+    /// Store `value`, replacing any previous entry; `None` never expires
+    async fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<()>;
+
+    /// Store `value` only if `key` is absent or expired, returning whether it was stored
+    ///
+    /// Implementations shared between processes must make this atomic: it is
+    /// what keeps two instances from handling the same message.
+    async fn set_if_absent(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<bool>;
+
+    /// Remove `key`, returning whether a live entry was removed
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool>;
+
+    /// All live entries in `namespace`
+    async fn list(&self, namespace: &str) -> Result<Vec<(String, Value)>>;
+}
+
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn new(value: Value, ttl: Option<Duration>) -> Self {
+        Self {
+            value,
+            expires_at: ttl.map(|ttl| Instant::now() + ttl),
+        }
+    }
+
+    fn is_live(&self, now: Instant) -> bool {
+        match self.expires_at {
+            Some(at) => now < at,
+            None => true,
+        }
+    }
+}
+
+/// Process-local [`StateStore`]
+///
+/// Expired entries read as absent and are dropped the next time their
+/// namespace is written.
+#[derive(Default)]
+pub struct MemoryStateStore {
+    namespaces: RwLock<HashMap<String, HashMap<String, Entry>>>,
+}
+
+impl MemoryStateStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` on `namespace` after dropping its expired entries
+    fn with_namespace<T>(
+        &self,
+        namespace: &str,
+        f: impl FnOnce(&mut HashMap<String, Entry>) -> T,
+    ) -> T {
+        let mut namespaces = self.namespaces.write_or_recover();
+        let entries = namespaces.entry(namespace.to_string()).or_default();
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.is_live(now));
+        let out = f(entries);
+        if entries.is_empty() {
+            namespaces.remove(namespace);
+        }
+        out
+    }
+}
+
+#[async_trait]
+impl StateStore for MemoryStateStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        let now = Instant::now();
+        Ok(self
+            .namespaces
+            .read_or_recover()
+            .get(namespace)
+            .and_then(|entries| entries.get(key))
+            .filter(|entry| entry.is_live(now))
+            .map(|entry| entry.value.clone()))
+    }
+
+    async fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<()> {
+        self.with_namespace(namespace, |entries| {
+            entries.insert(key.to_string(), Entry::new(value, ttl));
+        });
+        Ok(())
+    }
+
+    async fn set_if_absent(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Value,
+        ttl: Option<Duration>,
+    ) -> Result<bool> {
+        Ok(self.with_namespace(namespace, |entries| {
+            if entries.contains_key(key) {
+                return false;
+            }
+            entries.insert(key.to_string(), Entry::new(value, ttl));
+            true
+        }))
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool> {
+        Ok(self.with_namespace(namespace, |entries| entries.remove(key).is_some()))
+    }
+
+    async fn list(&self, namespace: &str) -> Result<Vec<(String, Value)>> {
+        let now = Instant::now();
+        Ok(self
+            .namespaces
+            .read_or_recover()
+            .get(namespace)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|(_, entry)| entry.is_live(now))
+                    .map(|(key, entry)| (key.clone(), entry.value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_namespaced_get_set_delete() {
+        let store = MemoryStateStore::new();
+        store.set("a", "k", json!(1), None).await.unwrap();
+        store.set("b", "k", json!(2), None).await.unwrap();
+
+        assert_eq!(store.get("a", "k").await.unwrap(), Some(json!(1)));
+        assert_eq!(store.get("b", "k").await.unwrap(), Some(json!(2)));
+        assert!(store.delete("a", "k").await.unwrap());
+        assert!(!store.delete("a", "k").await.unwrap());
+        assert_eq!(store.get("a", "k").await.unwrap(), None);
+        assert_eq!(
+            store.list("b").await.unwrap(),
+            vec![("k".to_string(), json!(2))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_if_absent_respects_ttl() {
+        let store = MemoryStateStore::new();
+        let ttl = Some(Duration::from_millis(20));
+        assert!(store
+            .set_if_absent("dedup", "m1", json!(true), ttl)
+            .await
+            .unwrap());
+        assert!(!store
+            .set_if_absent("dedup", "m1", json!(true), ttl)
+            .await
+            .unwrap());
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(store.get("dedup", "m1").await.unwrap(), None);
+        assert!(store.list("dedup").await.unwrap().is_empty());
+        assert!(store
+            .set_if_absent("dedup", "m1", json!(true), ttl)
+            .await
+            .unwrap());
+    }
+}
@@ -49,6 +49,29 @@ impl std::str::FromStr for StorageType {
     }
 }
 
+/// Vector search backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorSearchType {
+    /// The storage adapter's own vector search (default)
+    #[default]
+    Adapter,
+    /// In-process brute-force cosine search, no database required
+    Memory,
+}
+
+impl std::str::FromStr for VectorSearchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "adapter" | "database" => Ok(VectorSearchType::Adapter),
+            "memory" | "in-memory" | "inmemory" => Ok(VectorSearchType::Memory),
+            _ => Err(format!("Unknown vector search type: {}", s)),
+        }
+    }
+}
+
 /// Storage configuration for the agent
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -76,6 +99,22 @@ pub struct StorageConfig {
     /// Embedding dimension for vector search
     #[serde(skip_serializing_if = "Option::is_none")]
     pub embedding_dimension: Option<usize>,
+
+    /// Embedding model name, recorded with each vector collection to catch model switches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+
+    /// Vector search backend (adapter, memory)
+    #[serde(default)]
+    pub vector_search: VectorSearchType,
+
+    /// File the in-memory vector search is snapshotted to (not persisted when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_snapshot: Option<String>,
+
+    /// Create and check the vector search index at startup (MongoDB)
+    #[serde(default)]
+    pub ensure_vector_indexes: bool,
 }
 
 /// Character definition for an agent
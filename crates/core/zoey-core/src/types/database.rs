@@ -1,8 +1,8 @@
 //! Database adapter types
 
 use super::{
-    Agent, Component, Entity, Memory, MemoryQuery, Participant, Relationship, Room,
-    SearchMemoriesParams, Task, World, UUID,
+    Agent, Component, Entity, Memory, MemoryPage, MemoryQuery, Pagination, Participant,
+    Relationship, Room, SearchMemoriesParams, Task, World, UUID,
 };
 use crate::observability::types::LLMCostRecord;
 use crate::Result;
@@ -238,6 +238,36 @@ pub trait IDatabaseAdapter: Send + Sync {
     /// Count memories
     async fn count_memories(&self, params: MemoryQuery) -> Result<usize>;
 
+    /// Get one page of a room's memories, newest first
+    ///
+    /// The default loads the whole room and pages in memory; adapters should
+    /// override it with a keyset query on `(created_at, id)`.
+    async fn get_memories_page(
+        &self,
+        room_id: UUID,
+        table_name: &str,
+        page: Pagination,
+    ) -> Result<MemoryPage> {
+        let memories = self
+            .get_memories(MemoryQuery {
+                room_id: Some(room_id),
+                table_name: table_name.to_string(),
+                ..Default::default()
+            })
+            .await?;
+        Ok(MemoryPage::paginate(memories, &page))
+    }
+
+    /// Count a room's memories in `table_name`
+    async fn count_room_memories(&self, room_id: UUID, table_name: &str) -> Result<usize> {
+        self.count_memories(MemoryQuery {
+            room_id: Some(room_id),
+            table_name: table_name.to_string(),
+            ..Default::default()
+        })
+        .await
+    }
+
     // World/Room operations
     /// Get world
     async fn get_world(&self, world_id: UUID) -> Result<Option<World>>;
@@ -257,6 +287,14 @@ pub trait IDatabaseAdapter: Send + Sync {
     /// Get rooms for agent
     async fn get_rooms_for_agent(&self, agent_id: UUID) -> Result<Vec<Room>>;
 
+    /// Delete a room and its participants, returning whether it existed
+    ///
+    /// Memories are not removed; callers purge them first. The default keeps
+    /// the room and returns `false` for adapters that cannot delete rooms.
+    async fn delete_room(&self, _room_id: UUID) -> Result<bool> {
+        Ok(false)
+    }
+
     // Participant operations
     /// Add participant to room
     async fn add_participant(&self, entity_id: UUID, room_id: UUID) -> Result<bool>;
@@ -306,6 +344,58 @@ pub trait IDatabaseAdapter: Send + Sync {
     ) -> Result<AgentRunSummaryResult>;
 }
 
+/// Similarity search over memory embeddings
+///
+/// Implemented by `MongoVectorSearch` (aggregation pipeline) and
+/// `InMemoryVectorSearch` (brute force, no database), selected through
+/// `StorageConfig::vector_search`.
+#[async_trait]
+pub trait IVectorSearch: Send + Sync {
+    /// Dimension every stored and queried embedding must have
+    fn embedding_dimension(&self) -> usize;
+
+    /// Embedding model the backend was configured for, when known
+    fn embedding_model(&self) -> Option<String> {
+        None
+    }
+
+    /// Check `collection` was built with this backend's model and dimension
+    ///
+    /// Backends that record the model and dimension per collection return
+    /// [`ZoeyError::DimensionMismatch`](crate::ZoeyError::DimensionMismatch)
+    /// here, so a model switch fails at startup instead of returning empty
+    /// search results.
+    async fn verify_collection(&self, _collection: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Memories most similar to `params.embedding`, best first
+    async fn search_by_embedding(&self, params: SearchMemoriesParams) -> Result<Vec<Memory>>;
+
+    /// Insert or replace a memory, including its embedding
+    async fn upsert_memory(&self, collection: &str, memory: &Memory) -> Result<()>;
+
+    /// Set the embedding of a stored memory
+    async fn add_embedding(&self, collection: &str, memory_id: UUID, embedding: Vec<f32>)
+        -> Result<()>;
+
+    /// Set embeddings of several stored memories
+    async fn batch_add_embeddings(
+        &self,
+        collection: &str,
+        embeddings: Vec<(UUID, Vec<f32>)>,
+    ) -> Result<()>;
+
+    /// Memories most similar to a stored memory, excluding itself
+    async fn get_similar_memories(
+        &self,
+        collection: &str,
+        memory_id: UUID,
+        count: usize,
+        threshold: Option<f32>,
+    ) -> Result<Vec<Memory>>;
+}
+
 /// Plugin migration info
 #[derive(Debug, Clone)]
 pub struct PluginMigration {
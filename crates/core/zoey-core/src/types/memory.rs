@@ -124,6 +124,108 @@ pub struct SearchMemoriesParams {
     pub threshold: Option<f32>,
 }
 
+/// Position in a memory history, newest first
+///
+/// Orders by `created_at` then ID so memories sharing a timestamp are neither
+/// skipped nor repeated between pages. The string form (`<created_at>_<id>`)
+/// is what HTTP clients pass back as `before`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryCursor {
+    /// Creation timestamp of the last memory on the previous page
+    pub created_at: i64,
+    /// ID of the last memory on the previous page
+    pub id: UUID,
+}
+
+impl MemoryCursor {
+    /// Cursor positioned at `memory`
+    pub fn of(memory: &Memory) -> Self {
+        Self {
+            created_at: memory.created_at,
+            id: memory.id,
+        }
+    }
+
+    /// Parse the `<created_at>_<id>` string form
+    pub fn parse(s: &str) -> Option<Self> {
+        let (created_at, id) = s.split_once('_')?;
+        Some(Self {
+            created_at: created_at.parse().ok()?,
+            id: id.parse().ok()?,
+        })
+    }
+
+    /// Whether `memory` comes after this cursor in newest-first order
+    pub fn is_before(&self, memory: &Memory) -> bool {
+        (memory.created_at, memory.id) < (self.created_at, self.id)
+    }
+}
+
+impl std::fmt::Display for MemoryCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.created_at, self.id)
+    }
+}
+
+/// Page request for a memory history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    /// Only return memories older than this cursor; `None` starts at the newest
+    pub before: Option<MemoryCursor>,
+
+    /// Maximum number of memories in the page
+    pub limit: usize,
+}
+
+impl Pagination {
+    /// First page of `limit` memories
+    pub fn first(limit: usize) -> Self {
+        Self {
+            before: None,
+            limit,
+        }
+    }
+}
+
+/// One page of a memory history, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryPage {
+    /// Memories ordered by `created_at` descending
+    pub memories: Vec<Memory>,
+
+    /// Cursor for the next (older) page, `None` on the last page
+    pub next_cursor: Option<MemoryCursor>,
+}
+
+impl MemoryPage {
+    /// Build a page from `memories` already filtered by the cursor and sorted
+    /// newest first, holding up to `limit + 1` entries to detect a next page
+    pub fn from_overfetched(mut memories: Vec<Memory>, limit: usize) -> Self {
+        let next_cursor = if memories.len() > limit {
+            memories.truncate(limit);
+            memories.last().map(MemoryCursor::of)
+        } else {
+            None
+        };
+        Self {
+            memories,
+            next_cursor,
+        }
+    }
+
+    /// Page through an unordered in-memory list
+    pub fn paginate(mut memories: Vec<Memory>, page: &Pagination) -> Self {
+        if let Some(before) = &page.before {
+            memories.retain(|m| before.is_before(m));
+        }
+        memories.sort_by(|a, b| (b.created_at, b.id).cmp(&(a.created_at, a.id)));
+        memories.truncate(page.limit.saturating_add(1));
+        Self::from_overfetched(memories, page.limit)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,4 +262,59 @@ mod tests {
         assert_eq!(query.table_name, "memories");
         assert!(query.entity_id.is_none());
     }
+
+    fn memory_at(created_at: i64, id: u128) -> Memory {
+        Memory {
+            id: Uuid::from_u128(id),
+            entity_id: Uuid::nil(),
+            agent_id: Uuid::nil(),
+            room_id: Uuid::nil(),
+            content: Content::default(),
+            embedding: None,
+            metadata: None,
+            created_at,
+            unique: None,
+            similarity: None,
+        }
+    }
+
+    #[test]
+    fn test_memory_pagination() {
+        // Two memories share timestamp 20 and must not be split across pages incorrectly
+        let memories = vec![
+            memory_at(10, 1),
+            memory_at(30, 2),
+            memory_at(20, 3),
+            memory_at(20, 4),
+            memory_at(40, 5),
+        ];
+
+        let first = MemoryPage::paginate(memories.clone(), &Pagination::first(2));
+        let ids: Vec<_> = first.memories.iter().map(|m| m.id.as_u128()).collect();
+        assert_eq!(ids, vec![5, 2]);
+        let cursor = first.next_cursor.unwrap();
+
+        let second = MemoryPage::paginate(
+            memories.clone(),
+            &Pagination { before: Some(cursor), limit: 2 },
+        );
+        let ids: Vec<_> = second.memories.iter().map(|m| m.id.as_u128()).collect();
+        assert_eq!(ids, vec![4, 3]);
+
+        let third = MemoryPage::paginate(
+            memories,
+            &Pagination { before: second.next_cursor, limit: 2 },
+        );
+        let ids: Vec<_> = third.memories.iter().map(|m| m.id.as_u128()).collect();
+        assert_eq!(ids, vec![1]);
+        assert!(third.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_memory_cursor_round_trip() {
+        let cursor = MemoryCursor::of(&memory_at(1_700_000_000_000, 42));
+        assert_eq!(MemoryCursor::parse(&cursor.to_string()), Some(cursor));
+        assert!(MemoryCursor::parse("not-a-cursor").is_none());
+        assert!(MemoryCursor::parse("12_not-a-uuid").is_none());
+    }
 }
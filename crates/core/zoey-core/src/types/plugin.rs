@@ -51,6 +51,30 @@ pub enum RouteType {
 /// Route handler function type (type-erased for flexibility)
 pub type RouteHandler = Arc<dyn std::any::Any + Send + Sync>;
 
+/// Version of zoey-core this crate was compiled as
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Implement [`Plugin::core_version`] with the core version the plugin is compiled against
+///
+/// [`CORE_VERSION`] is a constant, so its value is copied into the plugin
+/// crate when that crate is compiled; a plugin built against an older
+/// zoey-core keeps reporting that version when loaded by a newer one.
+///
+/// ```ignore
+/// impl Plugin for MyPlugin {
+///     zoey_core::plugin_core_version!();
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! plugin_core_version {
+    () => {
+        fn core_version(&self) -> &str {
+            $crate::types::CORE_VERSION
+        }
+    };
+}
+
 /// Plugin trait
 #[async_trait]
 pub trait Plugin: Send + Sync {
@@ -60,6 +84,13 @@ pub trait Plugin: Send + Sync {
     /// Plugin description
     fn description(&self) -> &str;
 
+    /// Core version this plugin was built against, checked at registration
+    ///
+    /// Implement it with [`plugin_core_version!`](crate::plugin_core_version)
+    /// rather than a literal. There is no default, so every plugin states
+    /// the version it was built against.
+    fn core_version(&self) -> &str;
+
     /// Plugin dependencies (other plugin names)
     fn dependencies(&self) -> Vec<String> {
         vec![]
@@ -232,6 +263,8 @@ mod tests {
 
     #[async_trait]
     impl Plugin for MockPlugin {
+        crate::plugin_core_version!();
+
         fn name(&self) -> &str {
             "mock-plugin"
         }
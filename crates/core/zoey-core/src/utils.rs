@@ -1,17 +1,32 @@
 //! Utility functions and helpers
 
+pub mod context_overflow;
 pub mod delayed_reassessment;
 pub mod logger;
+pub mod response_template;
+pub mod response_text;
 pub mod rhythm;
 pub mod search;
 pub mod uuid;
+pub mod webapp_auth;
 
 // Re-export commonly used utilities
+pub use self::context_overflow::{
+    context_turn_limit, is_context_retry, ContextOverflowPolicy, CONTEXT_OVERFLOW_MESSAGE,
+};
 pub use self::delayed_reassessment::DelayedReassessment;
 pub use self::logger::Logger;
+pub use self::response_template::{
+    render_response_template, ResponseTemplateVars, DEFAULT_RESPONSE_TEMPLATE,
+};
+pub use self::response_text::{extract_response, extract_streaming_text, ExtractedResponse};
 pub use self::rhythm::ConversationRhythm;
 pub use self::search::BM25;
 pub use self::uuid::{create_unique_uuid, string_to_uuid};
+pub use self::webapp_auth::{
+    sign_webapp_token, validate_init_data, verify_webapp_token, webapp_link_key, NonceCache,
+    TelegramInitData, WebAppClaims,
+};
 
 // Re-export from dynamic_prompts for convenience
 pub use crate::dynamic_prompts::{compose_random_user, upgrade_double_to_triple};
@@ -0,0 +1,213 @@
+//! Context-length error detection and retry metadata
+//!
+//! Adapters talking to the agent API over `/chat/stream` can hit requests
+//! whose assembled prompt exceeds the model's context window. This module is
+//! the shared place for recognising those errors and building the single
+//! retry request, so every platform adapter reacts the same way:
+//!
+//! - [`ContextOverflowPolicy::is_context_error`] matches HTTP 413 responses and
+//!   error payloads against configurable patterns.
+//! - [`ContextOverflowPolicy::retry_metadata`] adds `context_budget_hint` and
+//!   `retry_reason` to the request metadata, and only ever allows one retry.
+//! - The backend uses [`is_context_retry`] to avoid persisting the user's
+//!   message a second time and [`context_turn_limit`] to shrink the history.
+
+use std::collections::HashMap;
+
+/// Metadata key carrying the reduced context budget (tokens) for a retry
+pub const CONTEXT_BUDGET_HINT_KEY: &str = "context_budget_hint";
+
+/// Metadata key marking a request as a retry
+pub const RETRY_REASON_KEY: &str = "retry_reason";
+
+/// `retry_reason` value used for context-length retries
+pub const RETRY_REASON_CONTEXT_LENGTH: &str = "context_length";
+
+/// Error fragments that indicate the prompt exceeded the context window
+pub const DEFAULT_CONTEXT_ERROR_PATTERNS: [&str; 6] = [
+    "context_length",
+    "context length",
+    "context window",
+    "maximum context",
+    "too many tokens",
+    "prompt is too long",
+];
+
+/// Context budget (tokens) requested on retry when the request had no hint
+pub const DEFAULT_RETRY_CONTEXT_BUDGET: u32 = 2048;
+
+/// HTTP status the backend uses for oversized payloads
+const PAYLOAD_TOO_LARGE: u16 = 413;
+
+/// Number of retries allowed after a context-length error
+const MAX_CONTEXT_RETRIES: u32 = 1;
+
+/// Reply shown when the retry with reduced context also fails
+pub const CONTEXT_OVERFLOW_MESSAGE: &str = "Sorry, this conversation has grown too long for me to \
+process, even after trimming older context. Send /reset to start a fresh conversation and try again.";
+
+/// How context-length errors are detected and retried
+#[derive(Debug, Clone)]
+pub struct ContextOverflowPolicy {
+    /// Lowercased error fragments that identify a context-length error
+    patterns: Vec<String>,
+    /// Budget requested on retry when the original request carried no hint
+    retry_budget: u32,
+}
+
+impl Default for ContextOverflowPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONTEXT_ERROR_PATTERNS.iter().map(|p| p.to_string()))
+    }
+}
+
+impl ContextOverflowPolicy {
+    /// Create a policy matching the given error patterns (case-insensitive)
+    pub fn new(patterns: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            retry_budget: DEFAULT_RETRY_CONTEXT_BUDGET,
+        }
+    }
+
+    /// Set the budget requested on retry when the request had no hint
+    pub fn with_retry_budget(mut self, retry_budget: u32) -> Self {
+        self.retry_budget = retry_budget.max(1);
+        self
+    }
+
+    /// Whether an error message matches one of the configured patterns
+    pub fn matches_error(&self, error: &str) -> bool {
+        let error = error.to_lowercase();
+        self.patterns.iter().any(|p| error.contains(p.as_str()))
+    }
+
+    /// Whether a backend response indicates a context-length error
+    ///
+    /// `status` is the HTTP status of the initial response (if known) and
+    /// `body` the error text or SSE error payload.
+    pub fn is_context_error(&self, status: Option<u16>, body: &str) -> bool {
+        status == Some(PAYLOAD_TOO_LARGE) || self.matches_error(body)
+    }
+
+    /// Whether an SSE payload is an `{"error": ...}` event for a context-length error
+    pub fn is_context_error_payload(&self, payload: &serde_json::Value) -> bool {
+        match payload.get("error") {
+            Some(serde_json::Value::String(e)) => self.matches_error(e),
+            Some(other) => self.matches_error(&other.to_string()),
+            None => false,
+        }
+    }
+
+    /// Metadata for the retry of attempt `attempt` (0-based), or `None` once the retry is used up
+    ///
+    /// The reduced budget halves any existing `context_budget_hint`, otherwise
+    /// it uses the configured retry budget.
+    pub fn retry_metadata(
+        &self,
+        base: &serde_json::Value,
+        attempt: u32,
+    ) -> Option<serde_json::Value> {
+        if attempt >= MAX_CONTEXT_RETRIES {
+            return None;
+        }
+        let mut metadata = match base {
+            serde_json::Value::Object(map) => map.clone(),
+            _ => serde_json::Map::new(),
+        };
+        let budget = metadata
+            .get(CONTEXT_BUDGET_HINT_KEY)
+            .and_then(|v| v.as_u64())
+            .map(|b| (b / 2).max(1) as u32)
+            .unwrap_or(self.retry_budget);
+        metadata.insert(CONTEXT_BUDGET_HINT_KEY.to_string(), serde_json::json!(budget));
+        metadata.insert(
+            RETRY_REASON_KEY.to_string(),
+            serde_json::json!(RETRY_REASON_CONTEXT_LENGTH),
+        );
+        Some(serde_json::Value::Object(metadata))
+    }
+}
+
+/// Whether request metadata marks a context-length retry
+pub fn is_context_retry(metadata: &HashMap<String, serde_json::Value>) -> bool {
+    metadata
+        .get(RETRY_REASON_KEY)
+        .and_then(|v| v.as_str())
+        .map(|r| r == RETRY_REASON_CONTEXT_LENGTH)
+        .unwrap_or(false)
+}
+
+/// Number of recent turns to include in the prompt, reduced on a context retry
+pub fn context_turn_limit(metadata: &HashMap<String, serde_json::Value>, default: usize) -> usize {
+    if is_context_retry(metadata) {
+        (default / 2).max(1)
+    } else {
+        default
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pattern_detection() {
+        let policy = ContextOverflowPolicy::default();
+        assert!(policy.is_context_error(None, "Error: context_length_exceeded"));
+        assert!(policy.is_context_error(None, "Request has Too Many Tokens"));
+        assert!(policy.is_context_error(Some(413), "Message too large"));
+        assert!(!policy.is_context_error(Some(500), "Server at capacity, please retry"));
+
+        assert!(policy.is_context_error_payload(&json!({"error": "maximum context length is 4096"})));
+        assert!(!policy.is_context_error_payload(&json!({"text": "context_length", "final": false})));
+
+        let custom = ContextOverflowPolicy::new(vec!["num_ctx".to_string(), "  ".to_string()]);
+        assert!(custom.matches_error("exceeds NUM_CTX"));
+        assert!(!custom.matches_error("too many tokens"));
+    }
+
+    #[test]
+    fn test_single_retry() {
+        let policy = ContextOverflowPolicy::default();
+        let base = json!({"tier": "free"});
+        assert!(policy.retry_metadata(&base, 0).is_some());
+        assert!(policy.retry_metadata(&base, 1).is_none());
+        assert!(policy.retry_metadata(&base, 2).is_none());
+    }
+
+    #[test]
+    fn test_final_message_suggests_reset() {
+        assert!(CONTEXT_OVERFLOW_MESSAGE.contains("/reset"));
+        assert!(!CONTEXT_OVERFLOW_MESSAGE.to_lowercase().contains("error"));
+    }
+
+    #[test]
+    fn test_retry_metadata_contents() {
+        let policy = ContextOverflowPolicy::default().with_retry_budget(1000);
+        let retry = policy
+            .retry_metadata(&json!({"tier": "paid", "response_budget": 2048}), 0)
+            .unwrap();
+        assert_eq!(retry["tier"], "paid");
+        assert_eq!(retry["response_budget"], 2048);
+        assert_eq!(retry[CONTEXT_BUDGET_HINT_KEY], 1000);
+        assert_eq!(retry[RETRY_REASON_KEY], RETRY_REASON_CONTEXT_LENGTH);
+
+        // An existing hint is halved instead
+        let retry = policy
+            .retry_metadata(&json!({CONTEXT_BUDGET_HINT_KEY: 6000}), 0)
+            .unwrap();
+        assert_eq!(retry[CONTEXT_BUDGET_HINT_KEY], 3000);
+
+        let metadata: HashMap<String, serde_json::Value> =
+            serde_json::from_value(retry).unwrap();
+        assert!(is_context_retry(&metadata));
+        assert_eq!(context_turn_limit(&metadata, 5), 2);
+        assert_eq!(context_turn_limit(&HashMap::new(), 5), 5);
+    }
+}
@@ -2,7 +2,9 @@
 
 use once_cell::sync::OnceCell;
 use serde::Serialize;
+use std::collections::VecDeque;
 use std::io::Write;
+use std::sync::Mutex;
 use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
@@ -17,14 +19,74 @@ pub struct LogEvent {
     pub time: String,
 }
 
-static LOG_TX: OnceCell<broadcast::Sender<LogEvent>> = OnceCell::new();
+/// Most recent events kept for subscribers asking for a backlog
+pub const LOG_BACKLOG_CAPACITY: usize = 1000;
+
+/// Live log events plus a bounded ring of the most recent ones
+pub struct LogHub {
+    tx: broadcast::Sender<LogEvent>,
+    recent: Mutex<VecDeque<LogEvent>>,
+    capacity: usize,
+}
+
+impl LogHub {
+    /// Hub keeping up to `capacity` recent events
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _rx) = broadcast::channel(1024);
+        Self {
+            tx,
+            recent: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Record an event and send it to live subscribers
+    pub fn publish(&self, ev: LogEvent) {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if self.capacity > 0 {
+            if recent.len() == self.capacity {
+                recent.pop_front();
+            }
+            recent.push_back(ev.clone());
+        }
+        // Sent under the lock so a subscriber sees each event once
+        let _ = self.tx.send(ev);
+    }
+
+    /// Subscribe to live events
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Subscribe to live events, with up to `tail` of the most recent ones (oldest first)
+    ///
+    /// Nothing published is missed or repeated between the backlog and the receiver.
+    pub fn subscribe_with_backlog(
+        &self,
+        tail: usize,
+    ) -> (Vec<LogEvent>, broadcast::Receiver<LogEvent>) {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        let rx = self.tx.subscribe();
+        let skip = recent.len().saturating_sub(tail);
+        (recent.iter().skip(skip).cloned().collect(), rx)
+    }
+}
+
+static LOG_HUB: OnceCell<LogHub> = OnceCell::new();
 
 pub fn subscribe_logs() -> Option<broadcast::Receiver<LogEvent>> {
-    LOG_TX.get().map(|tx| tx.subscribe())
+    LOG_HUB.get().map(LogHub::subscribe)
+}
+
+/// [`subscribe_logs`] with up to `tail` recent events to replay first (oldest first)
+pub fn subscribe_logs_with_backlog(
+    tail: usize,
+) -> Option<(Vec<LogEvent>, broadcast::Receiver<LogEvent>)> {
+    LOG_HUB.get().map(|hub| hub.subscribe_with_backlog(tail))
 }
 
 struct BroadcastLayer {
-    tx: broadcast::Sender<LogEvent>,
+    hub: &'static LogHub,
 }
 
 impl<S> Layer<S> for BroadcastLayer
@@ -59,7 +121,7 @@ where
             line: meta.line(),
             time: chrono::Utc::now().to_rfc3339(),
         };
-        let _ = self.tx.send(ev);
+        self.hub.publish(ev);
     }
 }
 
@@ -109,18 +171,13 @@ pub fn init_logging() {
     let env_filter =
         tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| level.into());
 
-    let tx = LOG_TX
-        .get_or_init(|| {
-            let (tx, _rx) = broadcast::channel(1024);
-            tx
-        })
-        .clone();
+    let hub = LOG_HUB.get_or_init(|| LogHub::new(LOG_BACKLOG_CAPACITY));
 
     tracing_subscriber::registry()
         .with(env_filter)
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .with(tracing_subscriber::fmt::layer().with_writer(LogBufferWriter::default()))
-        .with(BroadcastLayer { tx })
+        .with(BroadcastLayer { hub })
         .init();
 }
 
@@ -157,6 +214,33 @@ mod tests {
         assert_eq!(logger.namespace, "test");
     }
 
+    fn event(message: &str) -> LogEvent {
+        LogEvent {
+            level: "INFO".into(),
+            target: "test".into(),
+            message: message.into(),
+            file: None,
+            line: None,
+            time: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_hub_keeps_bounded_backlog_without_gaps() {
+        let hub = LogHub::new(3);
+        for i in 0..5 {
+            hub.publish(event(&i.to_string()));
+        }
+        let (backlog, mut rx) = hub.subscribe_with_backlog(10);
+        let messages: Vec<_> = backlog.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["2", "3", "4"]);
+        assert_eq!(hub.subscribe_with_backlog(2).0[0].message, "3");
+
+        hub.publish(event("5"));
+        assert_eq!(rx.try_recv().unwrap().message, "5");
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn test_logger_methods() {
         let logger = Logger::new("test");
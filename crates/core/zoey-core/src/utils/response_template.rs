@@ -0,0 +1,91 @@
+//! Per-adapter wrapping of the final reply
+//!
+//! Adapters accept an optional `response_template` such as
+//! `"**{room}** — {text}"` or `"{text}\n\n_Reply via /ask_"`. Supported
+//! placeholders:
+//!
+//! - `{text}`: the extracted reply text
+//! - `{room}`: the room name (or ID when the adapter has no name)
+//! - `{character}`: the character answering
+//!
+//! Placeholders are substituted in a single pass, so braces inside the reply
+//! itself are never expanded. Unknown `{...}` sequences are kept verbatim.
+
+/// Template used when none is configured
+pub const DEFAULT_RESPONSE_TEMPLATE: &str = "{text}";
+
+/// Values available to a response template
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseTemplateVars<'a> {
+    pub text: &'a str,
+    pub room: &'a str,
+    pub character: &'a str,
+}
+
+/// Render `template` (or [`DEFAULT_RESPONSE_TEMPLATE`]) with `vars`
+///
+/// An empty reply stays empty so adapters never send a bare prefix/suffix.
+pub fn render_response_template(
+    template: Option<&str>,
+    vars: ResponseTemplateVars<'_>,
+) -> String {
+    if vars.text.is_empty() {
+        return String::new();
+    }
+    let template = template.unwrap_or(DEFAULT_RESPONSE_TEMPLATE);
+    let mut out = String::with_capacity(template.len() + vars.text.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let placeholder = [
+            ("{text}", vars.text),
+            ("{room}", vars.room),
+            ("{character}", vars.character),
+        ]
+        .into_iter()
+        .find(|(name, _)| tail.starts_with(name));
+        match placeholder {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &tail[name.len()..];
+            }
+            None => {
+                out.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARS: ResponseTemplateVars<'static> = ResponseTemplateVars {
+        text: "Hello {room}",
+        room: "case-42",
+        character: "Zoey",
+    };
+
+    #[test]
+    fn test_default_template_is_bare_text() {
+        assert_eq!(render_response_template(None, VARS), "Hello {room}");
+        assert_eq!(render_response_template(Some("{text}"), VARS), "Hello {room}");
+    }
+
+    #[test]
+    fn test_placeholders_substituted_once() {
+        let out = render_response_template(Some("[{room}] {character}: {text}\n{unknown} {"), VARS);
+        // `{room}` inside the reply is left alone
+        assert_eq!(out, "[case-42] Zoey: Hello {room}\n{unknown} {");
+    }
+
+    #[test]
+    fn test_empty_text_renders_nothing() {
+        let vars = ResponseTemplateVars { text: "", ..VARS };
+        assert_eq!(render_response_template(Some("Prefix {text}"), vars), "");
+    }
+}
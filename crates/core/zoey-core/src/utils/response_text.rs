@@ -0,0 +1,242 @@
+//! Reply and thought extraction from LLM output
+//!
+//! Models are prompted for `<response><thought/><actions/><text/></response>`
+//! XML but also answer with `<reply>` blocks, fenced XML, `REPLY:` /
+//! `Thought:` markers or plain text. Adapters use these helpers instead of
+//! their own string slicing so every platform shows the same reply:
+//!
+//! - [`extract_response`] for a fully assembled response
+//! - [`extract_streaming_text`] for a partial response while chunks arrive
+//!
+//! Expected behaviour for each format is pinned by the shared fixtures in
+//! `tests/fixtures/response_formats.json`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static FENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)```[ \t]*(xml|reply|thought)?[ \t]*\n?(.*?)```").unwrap());
+static TEXT_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<text>(.*?)</text>").unwrap());
+static REPLY_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<reply>(.*?)</reply>").unwrap());
+static THOUGHT_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<thought>(.*?)</thought>").unwrap());
+static ACTIONS_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<actions>.*?</actions>").unwrap());
+static REPLY_MARKER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?mi)^[ \t]*REPLY[ \t]*(?:[:\-]|$)").unwrap());
+static THOUGHT_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?mi)^[ \t]*(?:thoughts?|cot|chain[- ]?of[- ]?thought|reasoning)[ \t]*(?:[:\-]|$)")
+        .unwrap()
+});
+static KNOWN_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)</?(?:response|thought|actions|providers|text|reply)[ \t]*/?>").unwrap()
+});
+static CLOSING_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"</([A-Za-z_][\w-]*)[ \t]*>").unwrap());
+static TRAILING_PARTIAL_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^<>]*$").unwrap());
+
+/// Names of the tags the response template uses; anything else is reply text
+/// (`Vec<String>`, `a<b>c`, HTML in a code block) and is left alone
+const KNOWN_TAG_NAMES: [&str; 6] = [
+    "response",
+    "thought",
+    "actions",
+    "providers",
+    "text",
+    "reply",
+];
+
+/// Structural tags that never carry reply text
+const STRUCTURAL_MARKERS: [&str; 6] = [
+    "<response>",
+    "<thought>",
+    "<actions>",
+    "</response>",
+    "</thought>",
+    "</actions>",
+];
+
+/// Reply text and optional reasoning extracted from an LLM response
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractedResponse {
+    /// Text to show the user
+    pub text: String,
+    /// Model reasoning, if the response included any
+    pub thought: Option<String>,
+}
+
+/// Extract the reply and thought from a fully assembled response
+///
+/// Precedence: `<reply>`, then the first complete `<text>` block, then an
+/// unterminated `<text>`, then a `REPLY:` marker, then the response with
+/// thought/actions blocks and remaining structural tags removed. Tags the
+/// template doesn't use are kept verbatim.
+pub fn extract_response(raw: &str) -> ExtractedResponse {
+    let body = fenced_body(raw).unwrap_or(raw);
+    let thought = first_capture(&THOUGHT_TAG, body)
+        .or_else(|| marker_section(body, &THOUGHT_MARKER, &REPLY_MARKER))
+        .filter(|t| !t.is_empty());
+
+    let text = first_capture(&REPLY_TAG, body)
+        .or_else(|| first_capture(&TEXT_TAG, body))
+        .or_else(|| unterminated_text(body))
+        .or_else(|| marker_section(body, &REPLY_MARKER, &THOUGHT_MARKER))
+        .unwrap_or_else(|| {
+            let without_meta = THOUGHT_TAG.replace_all(body, "");
+            let without_meta = ACTIONS_TAG.replace_all(&without_meta, "");
+            strip_known_tags(&without_meta)
+        });
+
+    ExtractedResponse { text, thought }
+}
+
+/// Extract the reply text from a partially streamed response
+///
+/// Returns an empty string while only structure (thought, actions) has
+/// arrived so callers never flash raw XML at the user.
+pub fn extract_streaming_text(partial: &str) -> String {
+    if let Some(start) = partial.find("<text>") {
+        let after_tag = &partial[start + "<text>".len()..];
+        return match after_tag.find("</text>") {
+            Some(end) => after_tag[..end].trim().to_string(),
+            None => trim_partial_tag(after_tag),
+        };
+    }
+    if partial.contains("<text/>") || partial.contains("<text />") {
+        return String::new();
+    }
+    // <text> arrived in an earlier chunk and this is the tail of the block
+    if let Some(end) = partial.find("</text>") {
+        return partial[..end].trim().to_string();
+    }
+    let trimmed = partial.trim();
+    if STRUCTURAL_MARKERS.iter().any(|m| trimmed.contains(m)) {
+        return String::new();
+    }
+    trim_partial_tag(trimmed)
+}
+
+/// Content of the first fenced block, if it holds a tagged response
+fn fenced_body(raw: &str) -> Option<&str> {
+    let caps = FENCE.captures(raw)?;
+    let inner = caps.get(2)?.as_str();
+    let tagged = caps.get(1).is_some()
+        || ["<text>", "<reply>", "<response>"]
+            .iter()
+            .any(|t| inner.contains(t));
+    tagged.then_some(inner)
+}
+
+fn first_capture(re: &Regex, body: &str) -> Option<String> {
+    re.captures(body)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().trim().to_string())
+}
+
+/// Text after an opening `<text>` that was never closed
+///
+/// The response was cut off or the model misspelled the closing tag, so a
+/// trailing half tag and closing tags with no matching opener are dropped too.
+fn unterminated_text(body: &str) -> Option<String> {
+    let start = body.find("<text>")?;
+    let text = strip_known_tags(&body[start + "<text>".len()..]);
+    let text = CLOSING_TAG.replace_all(&text, |caps: &regex::Captures| {
+        let opener = format!("<{}", &caps[1]);
+        if text.contains(&opener) {
+            caps[0].to_string()
+        } else {
+            String::new()
+        }
+    });
+    Some(trim_partial_tag(&text))
+}
+
+/// Text following a `start` marker up to the next `stop` marker or the end
+fn marker_section(body: &str, start: &Regex, stop: &Regex) -> Option<String> {
+    let begin = start.find(body)?.end();
+    let rest = &body[begin..];
+    let end = stop.find(rest).map(|m| m.start()).unwrap_or(rest.len());
+    Some(strip_known_tags(&rest[..end]))
+}
+
+fn strip_known_tags(s: &str) -> String {
+    KNOWN_TAG.replace_all(s, "").trim().to_string()
+}
+
+/// Drop a structural tag cut off mid-stream (e.g. a trailing `</te`)
+///
+/// Only a trailing `<…` that could still become one of [`KNOWN_TAG_NAMES`]
+/// is removed, so `x <` or `Vec<Str` in the reply survive.
+fn trim_partial_tag(s: &str) -> String {
+    let trimmed = match TRAILING_PARTIAL_TAG.find(s) {
+        Some(m) if is_known_tag_prefix(m.as_str()) => &s[..m.start()],
+        _ => s,
+    };
+    trimmed.trim().to_string()
+}
+
+fn is_known_tag_prefix(partial: &str) -> bool {
+    let name = partial.trim_start_matches('<').trim_start_matches('/');
+    let name = name.to_ascii_lowercase();
+    KNOWN_TAG_NAMES.iter().any(|known| {
+        known.starts_with(name.as_str())
+            || name
+                .strip_prefix(known)
+                .is_some_and(|rest| rest.trim_start().is_empty() || rest.trim() == "/")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Fixture {
+        name: String,
+        /// "final" or "streaming"
+        kind: String,
+        input: String,
+        text: String,
+        #[serde(default)]
+        thought: Option<String>,
+    }
+
+    fn fixtures() -> Vec<Fixture> {
+        serde_json::from_str(include_str!("../../tests/fixtures/response_formats.json"))
+            .expect("valid response format fixtures")
+    }
+
+    #[test]
+    fn test_response_format_fixtures() {
+        let fixtures = fixtures();
+        assert!(fixtures.len() >= 10);
+        for f in fixtures {
+            match f.kind.as_str() {
+                "final" => {
+                    let extracted = extract_response(&f.input);
+                    assert_eq!(extracted.text, f.text, "text for fixture {}", f.name);
+                    assert_eq!(extracted.thought, f.thought, "thought for fixture {}", f.name);
+                }
+                "streaming" => {
+                    assert_eq!(
+                        extract_streaming_text(&f.input),
+                        f.text,
+                        "streaming text for fixture {}",
+                        f.name
+                    );
+                }
+                other => panic!("unknown fixture kind {} in {}", other, f.name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_streaming_never_regresses_to_xml() {
+        // Feeding a response chunk by chunk must never surface structural tags
+        let full = "<response><thought>plan</thought><actions>REPLY</actions><text>Hi there</text></response>";
+        for end in 1..=full.len() {
+            let shown = extract_streaming_text(&full[..end]);
+            assert!(!shown.contains('<'), "prefix {:?} showed {:?}", &full[..end], shown);
+        }
+    }
+}
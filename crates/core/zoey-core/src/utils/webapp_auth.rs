@@ -0,0 +1,255 @@
+//! Signed links from chat adapters into the web UI
+//!
+//! The Telegram adapter can offer an "Open full workspace" button that opens
+//! the web UI inside Telegram. The button URL carries a short-lived token that
+//! binds the web session to the chat's room and the user's entity:
+//!
+//! - [`sign_webapp_token`] / [`verify_webapp_token`] create and check the
+//!   `base64url(claims).base64url(hmac)` token
+//! - [`validate_init_data`] checks the `initData` Telegram passes to the
+//!   embedded page, following Telegram's WebApp validation algorithm
+//! - [`NonceCache`] rejects a token that has already been exchanged
+//!
+//! Both sides derive the signing key from the bot token with
+//! [`webapp_link_key`], so no extra secret has to be shared.
+
+use crate::{Result, ZoeyError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Lifetime of a link token in seconds
+pub const WEBAPP_LINK_TTL_SECS: i64 = 600;
+
+/// Oldest `auth_date` accepted in Telegram `initData`, in seconds
+pub const INIT_DATA_MAX_AGE_SECS: i64 = 86_400;
+
+/// Room and entity a web session is bound to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebAppClaims {
+    #[serde(rename = "room")]
+    pub room_id: Uuid,
+    #[serde(rename = "entity")]
+    pub entity_id: Uuid,
+    /// Unix timestamp after which the token is rejected
+    #[serde(rename = "exp")]
+    pub expires_at: i64,
+    /// Random value used to reject replays
+    pub nonce: String,
+}
+
+impl WebAppClaims {
+    /// Claims valid for `ttl_secs` from `now`, with a fresh nonce
+    pub fn new(room_id: Uuid, entity_id: Uuid, ttl_secs: i64, now: i64) -> Self {
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        Self {
+            room_id,
+            entity_id,
+            expires_at: now + ttl_secs,
+            nonce: hex::encode(nonce),
+        }
+    }
+}
+
+/// Signing key for link tokens, derived from the bot token
+pub fn webapp_link_key(bot_token: &str) -> Vec<u8> {
+    hmac_sha256(b"ZoeyWebAppLink", bot_token.as_bytes())
+}
+
+/// Sign `claims` into a URL-safe token
+pub fn sign_webapp_token(key: &[u8], claims: &WebAppClaims) -> String {
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).unwrap_or_default());
+    let signature = URL_SAFE_NO_PAD.encode(hmac_sha256(key, payload.as_bytes()));
+    format!("{}.{}", payload, signature)
+}
+
+/// Check a token's signature and expiry and return its claims
+///
+/// Replay protection is the caller's job (see [`NonceCache`]).
+pub fn verify_webapp_token(key: &[u8], token: &str, now: i64) -> Result<WebAppClaims> {
+    let (payload, signature) = token
+        .split_once('.')
+        .ok_or_else(|| ZoeyError::auth("Malformed workspace token"))?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| ZoeyError::auth("Malformed workspace token"))?;
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| ZoeyError::auth("Invalid workspace token signature"))?;
+
+    let claims: WebAppClaims = URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| ZoeyError::auth("Malformed workspace token"))?;
+    if now >= claims.expires_at {
+        return Err(ZoeyError::auth("Workspace token expired"));
+    }
+    Ok(claims)
+}
+
+/// Verified fields from Telegram WebApp `initData`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelegramInitData {
+    pub user_id: u64,
+    pub auth_date: i64,
+}
+
+/// Validate Telegram WebApp `initData` against the bot token
+///
+/// Per Telegram's algorithm the secret key is `HMAC_SHA256("WebAppData", bot_token)`
+/// and `hash` must equal the hex HMAC of the remaining fields, sorted by key
+/// and joined as `key=value` lines.
+pub fn validate_init_data(
+    bot_token: &str,
+    init_data: &str,
+    now: i64,
+    max_age_secs: i64,
+) -> Result<TelegramInitData> {
+    let mut fields: Vec<(String, String)> = url::form_urlencoded::parse(init_data.as_bytes())
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let hash = fields
+        .iter()
+        .position(|(k, _)| k == "hash")
+        .map(|i| fields.remove(i).1)
+        .ok_or_else(|| ZoeyError::auth("initData has no hash"))?;
+    let hash = hex::decode(hash).map_err(|_| ZoeyError::auth("initData hash is not hex"))?;
+
+    fields.sort_by(|a, b| a.0.cmp(&b.0));
+    let data_check_string = fields
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let secret = hmac_sha256(b"WebAppData", bot_token.as_bytes());
+    let mut mac = HmacSha256::new_from_slice(&secret).expect("HMAC accepts any key length");
+    mac.update(data_check_string.as_bytes());
+    mac.verify_slice(&hash)
+        .map_err(|_| ZoeyError::auth("Invalid initData signature"))?;
+
+    let field = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+    let auth_date = field("auth_date")
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| ZoeyError::auth("initData has no auth_date"))?;
+    if now - auth_date > max_age_secs {
+        return Err(ZoeyError::auth("initData expired"));
+    }
+    let user_id = field("user")
+        .and_then(|v| serde_json::from_str::<serde_json::Value>(v).ok())
+        .and_then(|u| u.get("id").and_then(|id| id.as_u64()))
+        .ok_or_else(|| ZoeyError::auth("initData has no user"))?;
+    Ok(TelegramInitData { user_id, auth_date })
+}
+
+/// Nonces of exchanged tokens, kept until the token would have expired
+#[derive(Default)]
+pub struct NonceCache {
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl NonceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `nonce`; returns `false` if it was already used
+    pub fn check_and_insert(&self, nonce: &str, expires_at: i64, now: i64) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, exp| *exp > now);
+        if seen.contains_key(nonce) {
+            return false;
+        }
+        seen.insert(nonce.to_string(), expires_at);
+        true
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT_TOKEN: &str = "123456:TEST-fixture-token";
+    /// Signed with `BOT_TOKEN` using Telegram's algorithm
+    const INIT_DATA: &str = "auth_date=1700000000&query_id=AAHdF6IQAAAAAN0XohDhrOrc&user=%7B%22id%22%3A279058397%2C%22first_name%22%3A%22Ada%22%2C%22username%22%3A%22ada_l%22%7D&hash=f7546867f273c92ad34c4296996848557fb229dfd6f8ce62f35839113aa0ac70";
+    const AUTH_DATE: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_token_round_trip_and_expiry() {
+        let key = webapp_link_key(BOT_TOKEN);
+        let claims = WebAppClaims::new(Uuid::new_v4(), Uuid::new_v4(), 60, 1_000);
+        let token = sign_webapp_token(&key, &claims);
+
+        assert_eq!(verify_webapp_token(&key, &token, 1_059).unwrap(), claims);
+        assert!(verify_webapp_token(&key, &token, 1_060).is_err());
+        assert!(verify_webapp_token(&webapp_link_key("other"), &token, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_tampered_token_rejected() {
+        let key = webapp_link_key(BOT_TOKEN);
+        let claims = WebAppClaims::new(Uuid::new_v4(), Uuid::new_v4(), 60, 0);
+        let token = sign_webapp_token(&key, &claims);
+        let (_, signature) = token.split_once('.').unwrap();
+
+        let forged = WebAppClaims {
+            entity_id: Uuid::new_v4(),
+            ..claims
+        };
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        let forged_token = format!("{}.{}", forged_payload, signature);
+        assert!(verify_webapp_token(&key, &forged_token, 0).is_err());
+        assert!(verify_webapp_token(&key, "not-a-token", 0).is_err());
+    }
+
+    #[test]
+    fn test_init_data_fixture_validates() {
+        let data = validate_init_data(BOT_TOKEN, INIT_DATA, AUTH_DATE + 5, INIT_DATA_MAX_AGE_SECS)
+            .unwrap();
+        assert_eq!(data.user_id, 279_058_397);
+        assert_eq!(data.auth_date, AUTH_DATE);
+    }
+
+    #[test]
+    fn test_init_data_rejects_tampering_and_age() {
+        let now = AUTH_DATE + 5;
+        assert!(validate_init_data("654321:other", INIT_DATA, now, INIT_DATA_MAX_AGE_SECS).is_err());
+        let tampered = INIT_DATA.replace("279058397", "279058398");
+        assert!(validate_init_data(BOT_TOKEN, &tampered, now, INIT_DATA_MAX_AGE_SECS).is_err());
+        let unsigned = INIT_DATA.split("&hash=").next().unwrap();
+        assert!(validate_init_data(BOT_TOKEN, unsigned, now, INIT_DATA_MAX_AGE_SECS).is_err());
+        assert!(validate_init_data(
+            BOT_TOKEN,
+            INIT_DATA,
+            AUTH_DATE + INIT_DATA_MAX_AGE_SECS + 1,
+            INIT_DATA_MAX_AGE_SECS
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_nonce_replay_rejected() {
+        let cache = NonceCache::new();
+        assert!(cache.check_and_insert("abc", 100, 0));
+        assert!(!cache.check_and_insert("abc", 100, 50));
+        assert!(cache.check_and_insert("def", 100, 50));
+        // Entries are dropped once the token could no longer verify anyway
+        assert!(cache.check_and_insert("abc", 300, 100));
+    }
+}
@@ -248,6 +248,8 @@ impl Default for WorkflowPlugin {
 
 #[async_trait]
 impl Plugin for WorkflowPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "workflow"
     }
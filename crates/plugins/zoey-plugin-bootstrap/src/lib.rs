@@ -156,6 +156,8 @@ impl Default for BootstrapPlugin {
 
 #[async_trait]
 impl Plugin for BootstrapPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "bootstrap"
     }
@@ -200,6 +200,8 @@ impl Default for HardwarePlugin {
 
 #[async_trait]
 impl Plugin for HardwarePlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "hardware"
     }
@@ -144,6 +144,8 @@ impl Default for KnowledgePlugin {
 
 #[async_trait]
 impl Plugin for KnowledgePlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "knowledge"
     }
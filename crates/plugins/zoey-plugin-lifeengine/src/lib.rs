@@ -272,6 +272,8 @@ impl Default for LifeEnginePlugin {
 
 #[async_trait]
 impl Plugin for LifeEnginePlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "lifeengine"
     }
@@ -134,6 +134,8 @@ impl Default for MemoryManagerPlugin {
 
 #[async_trait]
 impl Plugin for MemoryManagerPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "memory-manager"
     }
@@ -34,9 +34,9 @@ impl Provider for ContextMemoriesProvider {
             .ok_or_else(|| zoey_core::ZoeyError::other("Runtime unavailable"))?;
 
         if let Some(rt) = rt_ref.try_upgrade() {
-            let (adapter_opt, agent_id) = {
+            let (adapter_opt, vector_search, agent_id) = {
                 let r = rt.read().unwrap();
-                (r.get_adapter(), r.agent_id)
+                (r.get_adapter(), r.get_vector_search(), r.agent_id)
             };
 
             let mut texts: Vec<String> = Vec::new();
@@ -66,7 +66,12 @@ impl Provider for ContextMemoriesProvider {
                         unique: Some(true),
                         threshold: Some(0.30),
                     };
-                    if let Ok(cands) = adapter.search_memories_by_embedding(search).await {
+                    // A configured vector search backend replaces the adapter's search
+                    let cands = match &vector_search {
+                        Some(vs) => vs.search_by_embedding(search).await,
+                        None => adapter.search_memories_by_embedding(search).await,
+                    };
+                    if let Ok(cands) = cands {
                         for m in cands {
                             texts.push(format!("* {}", m.content.text));
                         }
@@ -92,8 +92,11 @@ impl TieredMemoryService {
                                 unique: Some(true),
                                 threshold: Some(0.25),
                             };
-                            if let Ok(mut sem) = adapter.search_memories_by_embedding(search).await
-                            {
+                            let sem = match r.get_vector_search() {
+                                Some(vs) => vs.search_by_embedding(search).await,
+                                None => adapter.search_memories_by_embedding(search).await,
+                            };
+                            if let Ok(mut sem) = sem {
                                 selected.append(&mut sem);
                             }
                         }
@@ -123,6 +123,8 @@ pub struct ModerationPlugin;
 
 #[async_trait]
 impl Plugin for ModerationPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "moderation"
     }
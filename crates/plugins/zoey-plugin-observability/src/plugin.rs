@@ -156,6 +156,8 @@ impl Default for ExplainabilityPlugin {
 
 #[async_trait]
 impl Plugin for ExplainabilityPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "explainability"
     }
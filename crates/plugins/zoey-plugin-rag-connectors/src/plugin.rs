@@ -127,6 +127,8 @@ pub struct RagConnectorsPlugin;
 
 #[async_trait]
 impl Plugin for RagConnectorsPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "rag-connectors"
     }
@@ -116,6 +116,8 @@ pub struct SchedulerPlugin;
 
 #[async_trait]
 impl Plugin for SchedulerPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "scheduler"
     }
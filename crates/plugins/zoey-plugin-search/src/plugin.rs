@@ -133,6 +133,8 @@ struct CacheEntry {
 
 #[async_trait]
 impl Plugin for SearchPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "search"
     }
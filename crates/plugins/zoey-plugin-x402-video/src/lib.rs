@@ -411,6 +411,8 @@ impl Default for X402VideoPlugin {
 
 #[async_trait]
 impl Plugin for X402VideoPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "x402-video"
     }
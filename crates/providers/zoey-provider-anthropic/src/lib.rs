@@ -265,6 +265,8 @@ impl zoey_core::types::Provider for AnthropicPlugin {
 
 #[async_trait]
 impl Plugin for AnthropicPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "anthropic"
     }
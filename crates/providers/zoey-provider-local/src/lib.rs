@@ -717,6 +717,8 @@ impl Default for LocalLLMPlugin {
 
 #[async_trait]
 impl Plugin for LocalLLMPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "local-llm"
     }
@@ -121,6 +121,8 @@ impl Default for OpenAIPlugin {
 
 #[async_trait]
 impl Plugin for OpenAIPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "openai"
     }
@@ -263,6 +263,8 @@ impl zoey_core::types::Provider for RedpillPlugin {
 
 #[async_trait]
 impl Plugin for RedpillPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "redpill"
     }
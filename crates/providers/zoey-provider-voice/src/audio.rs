@@ -0,0 +1,351 @@
+//! Audio conversion utilities
+//!
+//! STT engines each expect a specific input layout (Whisper: 16kHz mono,
+//! Moshi/Unmute: 24kHz mono), while adapters deliver whatever their platform
+//! produces (Discord: 48kHz stereo PCM). [`normalize`] converts incoming
+//! [`AudioData`] to an engine's [`AudioSpec`] so call sites never resample
+//! by hand.
+//!
+//! Only uncompressed input (16-bit PCM and WAV) can be converted here.
+//! Compressed formats must be decoded by the caller first; they are rejected
+//! with [`VoiceError::InvalidInput`] instead of being transcribed as noise.
+
+use crate::types::{AudioData, AudioFormat, AudioSpec, VoiceError};
+use bytes::Bytes;
+use tracing::debug;
+use zoey_core::Result;
+
+/// Interpret little-endian 16-bit PCM bytes as samples
+pub fn pcm16_to_samples(data: &[u8]) -> Vec<i16> {
+    data.chunks_exact(2)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect()
+}
+
+/// Encode samples as little-endian 16-bit PCM bytes
+pub fn samples_to_pcm16(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+/// Downmix interleaved samples to mono by averaging each frame
+pub fn downmix_to_mono(samples: &[i16], channels: u16) -> Vec<i16> {
+    let channels = channels.max(1) as usize;
+    if channels == 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels)
+        .map(|frame| {
+            let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+            (sum / channels as i32) as i16
+        })
+        .collect()
+}
+
+/// Duplicate mono samples across `channels` interleaved channels
+pub fn upmix_mono(samples: &[i16], channels: u16) -> Vec<i16> {
+    let channels = channels.max(1) as usize;
+    samples
+        .iter()
+        .flat_map(|&s| std::iter::repeat(s).take(channels))
+        .collect()
+}
+
+/// Convert interleaved samples between channel counts
+///
+/// Supports N -> N, N -> mono and mono -> N. Other layouts (e.g. 6 -> 2)
+/// have no obvious mapping and are rejected.
+pub fn convert_channels(samples: &[i16], from: u16, to: u16) -> Result<Vec<i16>> {
+    match (from, to) {
+        (f, t) if f == t => Ok(samples.to_vec()),
+        (f, 1) => Ok(downmix_to_mono(samples, f)),
+        (1, t) => Ok(upmix_mono(samples, t)),
+        (f, t) => Err(VoiceError::InvalidInput(format!(
+            "cannot convert {} channels to {} channels",
+            f, t
+        ))
+        .into()),
+    }
+}
+
+/// Resample interleaved samples with linear interpolation
+pub fn resample_linear(samples: &[i16], channels: u16, from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+    let ratio = from_rate as f64 / to_rate as f64;
+    let new_frames = (frames as f64 / ratio) as usize;
+    let mut out = Vec::with_capacity(new_frames * channels);
+
+    for i in 0..new_frames {
+        let src = i as f64 * ratio;
+        let idx = src as usize;
+        let frac = src - idx as f64;
+        for ch in 0..channels {
+            let a = samples[idx * channels + ch] as f64;
+            let sample = if idx + 1 < frames {
+                let b = samples[(idx + 1) * channels + ch] as f64;
+                a * (1.0 - frac) + b * frac
+            } else {
+                a
+            };
+            out.push(sample.round() as i16);
+        }
+    }
+    out
+}
+
+/// Decoded interleaved PCM audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcmAudio {
+    /// Interleaved 16-bit samples
+    pub samples: Vec<i16>,
+    /// Sample rate (Hz)
+    pub sample_rate: u32,
+    /// Channel count
+    pub channels: u16,
+}
+
+/// Decode a WAV file with 16-bit integer or 32-bit float samples
+pub fn decode_wav(data: &[u8]) -> Result<PcmAudio> {
+    let invalid = |msg: &str| VoiceError::InvalidInput(format!("invalid WAV data: {}", msg));
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("missing RIFF/WAVE header").into());
+    }
+
+    let mut pos = 12;
+    let mut fmt: Option<(u16, u16, u32, u16)> = None;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let body_start = pos + 8;
+        let body_end = (body_start + size).min(data.len());
+        let body = &data[body_start..body_end];
+
+        if id == b"fmt " {
+            if body.len() < 16 {
+                return Err(invalid("fmt chunk too short").into());
+            }
+            fmt = Some((
+                u16::from_le_bytes([body[0], body[1]]),
+                u16::from_le_bytes([body[2], body[3]]),
+                u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+                u16::from_le_bytes([body[14], body[15]]),
+            ));
+        } else if id == b"data" {
+            let (tag, channels, sample_rate, bits) = fmt.ok_or_else(|| invalid("data before fmt chunk"))?;
+            let samples = match (tag, bits) {
+                (1, 16) => pcm16_to_samples(body),
+                (3, 32) => body
+                    .chunks_exact(4)
+                    .map(|c| {
+                        let v = f32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+                        (v.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+                    })
+                    .collect(),
+                _ => {
+                    return Err(VoiceError::InvalidInput(format!(
+                        "unsupported WAV encoding (format tag {}, {} bits)",
+                        tag, bits
+                    ))
+                    .into())
+                }
+            };
+            return Ok(PcmAudio {
+                samples,
+                sample_rate,
+                channels: channels.max(1),
+            });
+        }
+        // Chunks are word-aligned
+        pos = body_start + size + (size & 1);
+    }
+    Err(invalid("no data chunk").into())
+}
+
+/// Length of the canonical 16-bit PCM WAV header written by [`wav_header`]
+pub const WAV_HEADER_LEN: usize = 44;
+
+/// Canonical 44-byte header for 16-bit PCM with `data_len` bytes of samples
+///
+/// `u32::MAX` as `data_len` marks a stream of unknown length; the RIFF size
+/// saturates to the same value.
+pub fn wav_header(sample_rate: u32, channels: u16, data_len: u32) -> Vec<u8> {
+    let block_align = channels * 2;
+    let mut out = Vec::with_capacity(WAV_HEADER_LEN);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out
+}
+
+/// Encode 16-bit samples as a WAV file
+pub fn encode_wav(samples: &[i16], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut out = Vec::with_capacity(WAV_HEADER_LEN + data_len as usize);
+    out.extend_from_slice(&wav_header(sample_rate, channels, data_len));
+    out.extend_from_slice(&samples_to_pcm16(samples));
+    out
+}
+
+/// Convert audio to the target spec
+///
+/// Returns the input unchanged when it already matches. Fails with
+/// [`VoiceError::InvalidInput`] when the source is compressed or the
+/// target format is not PCM/WAV.
+pub fn normalize(audio: &AudioData, target: &AudioSpec) -> Result<AudioData> {
+    let source = audio.spec();
+    if source == *target {
+        return Ok(audio.clone());
+    }
+    if !matches!(target.format, AudioFormat::Pcm | AudioFormat::Wav) {
+        return Err(VoiceError::InvalidInput(format!(
+            "cannot convert audio to {} (only PCM and WAV targets are supported)",
+            target.format.as_str()
+        ))
+        .into());
+    }
+
+    let (samples, sample_rate, channels) = match audio.format {
+        AudioFormat::Pcm => {
+            if audio.sample_rate == 0 || audio.channels == 0 {
+                return Err(VoiceError::InvalidInput(format!(
+                    "PCM input has invalid spec {}",
+                    source
+                ))
+                .into());
+            }
+            (pcm16_to_samples(&audio.data), audio.sample_rate, audio.channels)
+        }
+        AudioFormat::Wav => {
+            let wav = decode_wav(&audio.data)?;
+            (wav.samples, wav.sample_rate, wav.channels)
+        }
+        other => {
+            return Err(VoiceError::InvalidInput(format!(
+                "cannot convert {} input to {}: compressed audio must be decoded before transcription",
+                other.as_str(),
+                target
+            ))
+            .into())
+        }
+    };
+
+    let samples = convert_channels(&samples, channels, target.channels)?;
+    let samples = resample_linear(&samples, target.channels, sample_rate, target.sample_rate);
+    let frames = samples.len() as u64 / target.channels.max(1) as u64;
+    let data = match target.format {
+        AudioFormat::Wav => encode_wav(&samples, target.sample_rate, target.channels),
+        _ => samples_to_pcm16(&samples),
+    };
+
+    debug!(from = %source, to = %target, frames = frames, "Converted STT input audio");
+
+    Ok(AudioData {
+        data: Bytes::from(data),
+        format: target.format,
+        sample_rate: target.sample_rate,
+        channels: target.channels,
+        duration_ms: Some(frames * 1000 / target.sample_rate.max(1) as u64),
+        character_count: audio.character_count,
+        engine: audio.engine.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(samples: &[i16], sample_rate: u32, channels: u16) -> AudioData {
+        AudioData::new(Bytes::from(samples_to_pcm16(samples)), AudioFormat::Pcm, sample_rate)
+            .with_channels(channels)
+    }
+
+    #[test]
+    fn test_downmix_averages_channels() {
+        assert_eq!(downmix_to_mono(&[100, 300, -200, 200], 2), vec![200, 0]);
+        assert_eq!(downmix_to_mono(&[i16::MAX, i16::MAX], 2), vec![i16::MAX]);
+        assert_eq!(downmix_to_mono(&[1, 2, 3], 1), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_resample_changes_length() {
+        let samples: Vec<i16> = (0..480).map(|i| i as i16).collect();
+        assert_eq!(resample_linear(&samples, 1, 48000, 16000).len(), 160);
+        assert_eq!(resample_linear(&samples, 1, 16000, 24000).len(), 720);
+        assert_eq!(resample_linear(&samples, 2, 48000, 24000).len(), 240);
+    }
+
+    #[test]
+    fn test_normalize_discord_stereo_to_whisper() {
+        // 48kHz stereo, 10ms of a constant tone per channel
+        let frames: Vec<i16> = (0..480).flat_map(|_| [1000i16, 3000]).collect();
+        let out = normalize(&pcm(&frames, 48000, 2), &AudioSpec::pcm_mono(16000)).unwrap();
+        assert_eq!(out.spec(), AudioSpec::pcm_mono(16000));
+        let samples = pcm16_to_samples(&out.data);
+        assert_eq!(samples.len(), 160);
+        assert!(samples.iter().all(|&s| s == 2000));
+        assert_eq!(out.duration_ms, Some(10));
+    }
+
+    #[test]
+    fn test_normalize_upsamples_for_moshi() {
+        let out = normalize(&pcm(&[0; 160], 16000, 1), &AudioSpec::pcm_mono(24000)).unwrap();
+        assert_eq!(out.sample_rate, 24000);
+        assert_eq!(pcm16_to_samples(&out.data).len(), 240);
+    }
+
+    #[test]
+    fn test_normalize_decodes_wav() {
+        let wav = encode_wav(&[500; 960], 48000, 2);
+        let audio = AudioData::new(Bytes::from(wav), AudioFormat::Wav, 48000);
+        let out = normalize(&audio, &AudioSpec::pcm_mono(16000)).unwrap();
+        assert_eq!(out.format, AudioFormat::Pcm);
+        assert_eq!(pcm16_to_samples(&out.data).len(), 160);
+    }
+
+    #[test]
+    fn test_normalize_to_wav_target() {
+        let out = normalize(
+            &pcm(&[7; 320], 16000, 1),
+            &AudioSpec::new(16000, 1, AudioFormat::Wav),
+        )
+        .unwrap();
+        let decoded = decode_wav(&out.data).unwrap();
+        assert_eq!(decoded.samples, vec![7; 320]);
+        assert_eq!(decoded.sample_rate, 16000);
+    }
+
+    #[test]
+    fn test_normalize_matching_spec_is_passthrough() {
+        let audio = pcm(&[1, 2, 3], 16000, 1);
+        let out = normalize(&audio, &AudioSpec::pcm_mono(16000)).unwrap();
+        assert_eq!(out.data, audio.data);
+    }
+
+    #[test]
+    fn test_normalize_rejects_compressed_input() {
+        let audio = AudioData::new(Bytes::from_static(b"ID3..."), AudioFormat::Mp3, 44100);
+        let err = normalize(&audio, &AudioSpec::pcm_mono(16000)).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("Invalid input"), "{}", msg);
+        assert!(msg.contains("mp3"), "{}", msg);
+    }
+
+    #[test]
+    fn test_convert_channels_rejects_unmappable_layout() {
+        assert!(convert_channels(&[0; 12], 6, 2).is_err());
+    }
+}
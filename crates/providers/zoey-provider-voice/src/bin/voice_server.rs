@@ -354,7 +354,12 @@ async fn main() -> anyhow::Result<()> {
         SttEngine::Vosk { model: Arc::new(vosk_model) }
     } else {
         // Use Whisper (default)
-        info!(model = %args.whisper_model, "Using Whisper STT (model will download on first use)");
+        let offline = zoey_provider_voice::TranscriptionConfig::default().with_env_overrides();
+        match (&offline.model_path, offline.never_download) {
+            (Some(path), _) => info!(model = %path.display(), "Using Whisper STT (pre-placed model, downloads disabled)"),
+            (None, true) => info!(model = %args.whisper_model, "Using Whisper STT (cached model only, downloads disabled)"),
+            (None, false) => info!(model = %args.whisper_model, "Using Whisper STT (model will download on first use)"),
+        }
         SttEngine::Whisper { model_size: args.whisper_model.clone() }
     };
     
@@ -716,8 +721,19 @@ async fn transcribe_whisper(samples: &[i16], model_size: &str) -> anyhow::Result
     }).await?
 }
 
-// Download Whisper model if needed
+// Download Whisper model if needed (honors WHISPER_MODEL_PATH / WHISPER_NEVER_DOWNLOAD)
 fn get_whisper_model_path(model_size: &str) -> anyhow::Result<PathBuf> {
+    let offline = zoey_provider_voice::TranscriptionConfig::default().with_env_overrides();
+    if let Some(model_path) = offline.model_path {
+        if !model_path.exists() {
+            return Err(anyhow::anyhow!(
+                "Whisper model not found at {} (WHISPER_MODEL_PATH); downloads are disabled",
+                model_path.display()
+            ));
+        }
+        return Ok(model_path);
+    }
+
     let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
     let cache_dir = PathBuf::from(format!("{}/.cache/whisper", home));
     std::fs::create_dir_all(&cache_dir)?;
@@ -725,6 +741,12 @@ fn get_whisper_model_path(model_size: &str) -> anyhow::Result<PathBuf> {
     let model_file = format!("ggml-{}.bin", model_size);
     let model_path = cache_dir.join(&model_file);
     
+    if !model_path.exists() && offline.never_download {
+        return Err(anyhow::anyhow!(
+            "Whisper model not found at {} and WHISPER_NEVER_DOWNLOAD is set",
+            model_path.display()
+        ));
+    }
     if !model_path.exists() {
         info!(model = model_size, "Downloading Whisper model (one-time)...");
         
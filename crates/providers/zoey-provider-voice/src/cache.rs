@@ -0,0 +1,233 @@
+//! Cache of synthesized audio for repeated phrases
+//!
+//! Adapters speak the same short lines over and over (greetings, error
+//! messages, "join a voice channel first"), and each one costs a TTS request.
+//! [`AudioCache`] keeps recent results keyed by a hash of the normalized text
+//! and everything in the config that changes the audio (engine, voice, speed,
+//! format, sample rate), evicting the least recently used entry once either
+//! the entry count or the total size is over its limit.
+//!
+//! [`VoicePlugin::synthesize`](crate::VoicePlugin::synthesize) consults it
+//! unless streaming is enabled; changing the voice, speed, effects, language
+//! voices or length limit clears it.
+
+use crate::types::{AudioData, VoiceConfig};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// Default number of cached utterances
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 128;
+
+/// Default total size of cached audio (16 MB)
+pub const DEFAULT_CACHE_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Limits of an [`AudioCache`]; a zero limit disables caching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioCacheConfig {
+    /// Most utterances kept
+    pub max_entries: usize,
+    /// Most audio bytes kept; larger utterances are never cached
+    pub max_bytes: usize,
+}
+
+impl Default for AudioCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            max_bytes: DEFAULT_CACHE_MAX_BYTES,
+        }
+    }
+}
+
+/// How well the cache is doing, for adapters to log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Syntheses answered from the cache
+    pub hits: u64,
+    /// Syntheses that went to the engine
+    pub misses: u64,
+    /// Audio bytes currently cached
+    pub bytes: usize,
+    /// Utterances currently cached
+    pub entries: usize,
+}
+
+struct Entry {
+    audio: AudioData,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<u64, Entry>,
+    /// Incremented on every access; orders entries by recency
+    clock: u64,
+    stats: CacheStats,
+}
+
+/// LRU cache of synthesized audio bounded by entry count and total bytes
+pub struct AudioCache {
+    config: AudioCacheConfig,
+    inner: Mutex<Inner>,
+}
+
+impl Default for AudioCache {
+    fn default() -> Self {
+        Self::new(AudioCacheConfig::default())
+    }
+}
+
+impl AudioCache {
+    /// Empty cache with the given limits
+    pub fn new(config: AudioCacheConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Limits of this cache
+    pub fn config(&self) -> AudioCacheConfig {
+        self.config
+    }
+
+    /// Cache key for `text` spoken with `config`
+    ///
+    /// Whitespace is collapsed and trimmed, so reflowed text still hits.
+    pub fn key(text: &str, config: &VoiceConfig) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for word in text.split_whitespace() {
+            word.hash(&mut hasher);
+        }
+        config.engine_type.as_str().hash(&mut hasher);
+        config.voice.id.hash(&mut hasher);
+        config.speed.to_bits().hash(&mut hasher);
+        config.output_format.as_str().hash(&mut hasher);
+        config.sample_rate.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Cached audio for `key`, counting a hit or a miss
+    pub fn get(&self, key: u64) -> Option<AudioData> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.clock += 1;
+        let now = inner.clock;
+        let audio = inner.entries.get_mut(&key).map(|entry| {
+            entry.last_used = now;
+            entry.audio.clone()
+        });
+        match audio {
+            Some(_) => inner.stats.hits += 1,
+            None => inner.stats.misses += 1,
+        }
+        audio
+    }
+
+    /// Keep `audio` under `key`, evicting the least recently used entries to make room
+    pub fn insert(&self, key: u64, audio: &AudioData) {
+        let size = audio.data.len();
+        if self.config.max_entries == 0 || size > self.config.max_bytes {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.clock += 1;
+        let entry = Entry {
+            audio: audio.clone(),
+            last_used: inner.clock,
+        };
+        if let Some(old) = inner.entries.insert(key, entry) {
+            inner.stats.bytes -= old.audio.data.len();
+        }
+        inner.stats.bytes += size;
+        while inner.entries.len() > self.config.max_entries
+            || inner.stats.bytes > self.config.max_bytes
+        {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            if let Some(evicted) = inner.entries.remove(&oldest) {
+                inner.stats.bytes -= evicted.audio.data.len();
+            }
+        }
+        inner.stats.entries = inner.entries.len();
+    }
+
+    /// Drop every entry; hit and miss counts are kept
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.entries.clear();
+        inner.stats.bytes = 0;
+        inner.stats.entries = 0;
+    }
+
+    /// Hits, misses and current size
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AudioFormat;
+    use bytes::Bytes;
+
+    fn audio(len: usize) -> AudioData {
+        AudioData::new(Bytes::from(vec![0u8; len]), AudioFormat::Pcm, 16000)
+    }
+
+    #[test]
+    fn test_key_normalizes_text_and_tracks_config() {
+        let config = VoiceConfig::default();
+        let key = AudioCache::key("Join a voice channel first.", &config);
+        assert_eq!(
+            AudioCache::key("  Join a voice\nchannel   first. ", &config),
+            key
+        );
+        assert_ne!(AudioCache::key("Join a voice channel first!", &config), key);
+
+        let faster = VoiceConfig {
+            speed: 1.5,
+            ..config.clone()
+        };
+        assert_ne!(AudioCache::key("Join a voice channel first.", &faster), key);
+        let wav = VoiceConfig {
+            output_format: AudioFormat::Wav,
+            ..config
+        };
+        assert_ne!(AudioCache::key("Join a voice channel first.", &wav), key);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_within_limits() {
+        let cache = AudioCache::new(AudioCacheConfig {
+            max_entries: 2,
+            max_bytes: 100,
+        });
+        cache.insert(1, &audio(10));
+        cache.insert(2, &audio(10));
+        assert!(cache.get(1).is_some());
+        cache.insert(3, &audio(10));
+        assert!(cache.get(2).is_none(), "least recently used is evicted");
+        assert!(cache.get(1).is_some());
+
+        cache.insert(4, &audio(95));
+        assert_eq!(cache.stats().entries, 1, "byte limit evicts the rest");
+        cache.insert(5, &audio(101));
+        assert!(cache.get(5).is_none(), "oversized audio is not cached");
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+        assert_eq!(stats.bytes, 95);
+        cache.clear();
+        assert_eq!(cache.stats().bytes, 0);
+        assert_eq!(cache.stats().hits, 2);
+    }
+}
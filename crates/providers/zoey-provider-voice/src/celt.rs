@@ -0,0 +1,1421 @@
+//! Pure-Rust CELT encoder producing Opus packets
+//!
+//! A port of the parts of libopus' CELT encoder that [`transcode`](crate::transcode)
+//! needs: mono 48kHz audio in 20ms frames at a constant bitrate, every packet
+//! a CELT-only Opus frame (TOC config 31). The bitstream decisions follow
+//! libopus so any Opus decoder plays the result, but the analysis is kept
+//! simple: no pitch pre-filter, no transient detection (long blocks only),
+//! normal spreading and a flat bit allocation trim.
+//!
+//! Float formulas and table values match libopus' float build (`celt/*.c`);
+//! names of the helpers follow their C counterparts so the two can be read
+//! side by side.
+
+use std::f32::consts::PI;
+
+/// Samples in each 20ms frame at 48kHz
+pub(crate) const FRAME_SAMPLES: usize = 960;
+
+/// Samples of the MDCT window overlap, which is also the decoder's delay
+pub(crate) const OVERLAP: usize = 120;
+
+/// Bytes in each packet, TOC included (48 kbit/s)
+const PACKET_BYTES: usize = 120;
+
+/// TOC byte: config 31 (CELT-only fullband 20ms), mono, one frame
+const TOC: u8 = 31 << 3;
+
+/// log2 of the number of short MDCTs in a frame
+const LM: i32 = 3;
+
+/// Bands coded (all of them at 48kHz)
+const NB_BANDS: usize = 21;
+
+/// Resolution of the bit allocation, in fractional bits
+const BITRES: i32 = 3;
+
+/// Spreading decision used for every frame (`SPREAD_NORMAL`)
+const SPREAD_NORMAL: usize = 2;
+
+/// Bit allocation trim used for every frame (neutral)
+const ALLOC_TRIM: i32 = 5;
+
+const PREEMPHASIS: f32 = 0.850_006_1;
+const MAX_FINE_BITS: i32 = 8;
+const FINE_OFFSET: i32 = 21;
+const QTHETA_OFFSET: i32 = 4;
+const ALLOC_STEPS: i32 = 6;
+const LOG_MAX_PSEUDO: i32 = 6;
+
+/// Band edges in units of 8 MDCT bins (`eband5ms`)
+const EBANDS: [i32; NB_BANDS + 1] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 12, 14, 16, 20, 24, 28, 34, 40, 48, 60, 78, 100,
+];
+
+/// Mean band energies in log2 units (`eMeans`)
+const E_MEANS: [f32; NB_BANDS] = [
+    6.4375, 6.25, 5.75, 5.3125, 5.0625, 4.8125, 4.5, 4.375, 4.875, 4.6875, 4.5625, 4.4375, 4.875,
+    4.625, 4.3125, 4.5, 4.375, 4.625, 4.75, 4.4375, 3.75,
+];
+
+/// Inter-frame energy prediction for 20ms frames (`pred_coef[3]`, `beta_coef[3]`)
+const PRED_COEF: f32 = 16384.0 / 32768.0;
+const BETA_COEF: f32 = 6554.0 / 32768.0;
+const BETA_INTRA: f32 = 4915.0 / 32768.0;
+
+/// Laplace parameters of the coarse energy for 20ms frames (`e_prob_model[3]`)
+const E_PROB_INTER: [u8; 42] = [
+    42, 121, 96, 66, 108, 43, 111, 40, 117, 44, 123, 32, 120, 36, 119, 33, 127, 33, 134, 34, 139,
+    21, 147, 23, 152, 20, 158, 25, 154, 26, 166, 21, 173, 16, 184, 13, 184, 10, 150, 13, 139, 15,
+];
+const E_PROB_INTRA: [u8; 42] = [
+    22, 178, 63, 114, 74, 82, 84, 83, 92, 82, 103, 62, 96, 72, 96, 67, 101, 73, 107, 72, 113, 55,
+    118, 52, 125, 52, 118, 52, 117, 55, 135, 49, 137, 39, 157, 32, 145, 29, 97, 33, 77, 40,
+];
+
+const SMALL_ENERGY_ICDF: [u8; 3] = [2, 1, 0];
+const SPREAD_ICDF: [u8; 4] = [25, 23, 2, 0];
+const TRIM_ICDF: [u8; 11] = [126, 124, 119, 109, 87, 41, 19, 9, 4, 2, 0];
+
+/// Per-band allocation vectors in 1/32 bit per sample (`band_allocation`)
+const BAND_ALLOCATION: [[u8; NB_BANDS]; 11] = [
+    [0; NB_BANDS],
+    [
+        90, 80, 75, 69, 63, 56, 49, 40, 34, 29, 20, 18, 10, 0, 0, 0, 0, 0, 0, 0, 0,
+    ],
+    [
+        110, 100, 90, 84, 78, 71, 65, 58, 51, 45, 39, 32, 26, 20, 12, 0, 0, 0, 0, 0, 0,
+    ],
+    [
+        118, 110, 103, 93, 86, 80, 75, 70, 65, 59, 53, 47, 40, 31, 23, 15, 4, 0, 0, 0, 0,
+    ],
+    [
+        126, 119, 112, 104, 95, 89, 83, 78, 72, 66, 60, 54, 47, 39, 32, 25, 17, 12, 1, 0, 0,
+    ],
+    [
+        134, 127, 120, 114, 103, 97, 91, 85, 78, 72, 66, 60, 54, 47, 41, 35, 29, 23, 16, 10, 1,
+    ],
+    [
+        144, 137, 130, 124, 113, 107, 101, 95, 88, 82, 76, 70, 64, 57, 51, 45, 39, 33, 26, 15, 1,
+    ],
+    [
+        152, 145, 138, 132, 123, 117, 111, 105, 98, 92, 86, 80, 74, 67, 61, 55, 49, 43, 36, 20, 1,
+    ],
+    [
+        162, 155, 148, 142, 133, 127, 121, 115, 108, 102, 96, 90, 84, 77, 71, 65, 59, 53, 46, 30, 1,
+    ],
+    [
+        172, 165, 158, 152, 143, 137, 131, 125, 118, 112, 106, 100, 94, 87, 81, 75, 69, 63, 56, 45,
+        20,
+    ],
+    [
+        200, 200, 200, 200, 200, 200, 200, 200, 198, 193, 188, 183, 178, 173, 168, 163, 158, 153,
+        148, 129, 104,
+    ],
+];
+
+/// log2 of the band widths in 1/8 bits (`logN400`)
+const LOG_N: [i32; NB_BANDS] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 8, 8, 8, 8, 16, 16, 16, 21, 21, 24, 29, 34, 36,
+];
+
+/// Offsets into [`CACHE_BITS`] per LM+1 and band (`cache_index50`)
+const CACHE_INDEX: [i16; 105] = [
+    -1, -1, -1, -1, -1, -1, -1, -1, 0, 0, 0, 0, 41, 41, 41, 82, 82, 123, 164, 200, 222, 0, 0, 0, 0,
+    0, 0, 0, 0, 41, 41, 41, 41, 123, 123, 123, 164, 164, 240, 266, 283, 295, 41, 41, 41, 41, 41,
+    41, 41, 41, 123, 123, 123, 123, 240, 240, 240, 266, 266, 305, 318, 328, 336, 123, 123, 123,
+    123, 123, 123, 123, 123, 240, 240, 240, 240, 305, 305, 305, 318, 318, 343, 351, 358, 364, 240,
+    240, 240, 240, 240, 240, 240, 240, 305, 305, 305, 305, 343, 343, 343, 351, 351, 370, 376, 382,
+    387,
+];
+
+/// Bits needed for each pulse count, in 1/8 bits minus one (`cache_bits50`)
+const CACHE_BITS: [u8; 392] = [
+    40, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 40, 15, 23, 28, 31, 34, 36, 38, 39, 41, 42, 43, 44, 45, 46, 47,
+    47, 49, 50, 51, 52, 53, 54, 55, 55, 57, 58, 59, 60, 61, 62, 63, 63, 65, 66, 67, 68, 69, 70, 71,
+    71, 40, 20, 33, 41, 48, 53, 57, 61, 64, 66, 69, 71, 73, 75, 76, 78, 80, 82, 85, 87, 89, 91, 92,
+    94, 96, 98, 101, 103, 105, 107, 108, 110, 112, 114, 117, 119, 121, 123, 124, 126, 128, 40, 23,
+    39, 51, 60, 67, 73, 79, 83, 87, 91, 94, 97, 100, 102, 105, 107, 111, 115, 118, 121, 124, 126,
+    129, 131, 135, 139, 142, 145, 148, 150, 153, 155, 159, 163, 166, 169, 172, 174, 177, 179, 35,
+    28, 49, 65, 78, 89, 99, 107, 114, 120, 126, 132, 136, 141, 145, 149, 153, 159, 165, 171, 176,
+    180, 185, 189, 192, 199, 205, 211, 216, 220, 225, 229, 232, 239, 245, 251, 21, 33, 58, 79, 97,
+    112, 125, 137, 148, 157, 166, 174, 182, 189, 195, 201, 207, 217, 227, 235, 243, 251, 17, 35,
+    63, 86, 106, 123, 139, 152, 165, 177, 187, 197, 206, 214, 222, 230, 237, 250, 25, 31, 55, 75,
+    91, 105, 117, 128, 138, 146, 154, 161, 168, 174, 180, 185, 190, 200, 208, 215, 222, 229, 235,
+    240, 245, 255, 16, 36, 65, 89, 110, 128, 144, 159, 173, 185, 196, 207, 217, 226, 234, 242, 250,
+    11, 41, 74, 103, 128, 151, 172, 191, 209, 225, 241, 255, 9, 43, 79, 110, 138, 163, 186, 207,
+    227, 246, 12, 39, 71, 99, 123, 144, 164, 182, 198, 214, 228, 241, 253, 9, 44, 81, 113, 142,
+    168, 192, 214, 235, 255, 7, 49, 90, 127, 160, 191, 220, 247, 6, 51, 95, 134, 170, 203, 234, 7,
+    47, 87, 123, 155, 184, 212, 237, 6, 52, 97, 137, 174, 208, 240, 5, 57, 106, 151, 192, 231, 5,
+    59, 111, 158, 202, 243, 5, 55, 103, 147, 187, 224, 5, 60, 113, 161, 206, 248, 4, 65, 122, 175,
+    224, 4, 67, 127, 182, 234,
+];
+
+/// Maximum bits per band for mono 20ms frames (`cache_caps50`, row 2*LM)
+const CACHE_CAPS: [u8; NB_BANDS] = [
+    193, 193, 193, 193, 193, 193, 193, 193, 193, 193, 193, 193, 194, 194, 194, 184, 184, 173, 139,
+    65, 39,
+];
+
+/// Encodes mono 48kHz audio as CELT-only Opus packets, one per 20ms frame
+pub(crate) struct CeltEncoder {
+    mdct: Mdct,
+    preemph_mem: f32,
+    in_mem: [f32; OVERLAP],
+    old_band_e: [f32; NB_BANDS],
+    last_coded_bands: i32,
+    first_frame: bool,
+}
+
+impl CeltEncoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            mdct: Mdct::new(),
+            preemph_mem: 0.0,
+            in_mem: [0.0; OVERLAP],
+            old_band_e: [0.0; NB_BANDS],
+            last_coded_bands: 0,
+            first_frame: true,
+        }
+    }
+
+    /// Encode one frame of samples in [-1, 1] into an Opus packet
+    pub(crate) fn encode(&mut self, pcm: &[f32; FRAME_SAMPLES]) -> Vec<u8> {
+        let nb_bytes = PACKET_BYTES - 1;
+        let total_bits = nb_bytes as i32 * 8;
+        let mut enc = RangeEncoder::new(nb_bytes);
+
+        // Pre-emphasis, keeping the previous frame's tail for the MDCT overlap
+        let mut input = [0.0f32; FRAME_SAMPLES + OVERLAP];
+        input[..OVERLAP].copy_from_slice(&self.in_mem);
+        for (x, &sample) in input[OVERLAP..].iter_mut().zip(pcm) {
+            let scaled = sample * 32768.0;
+            *x = scaled - self.preemph_mem;
+            self.preemph_mem = PREEMPHASIS * scaled;
+        }
+        self.in_mem.copy_from_slice(&input[FRAME_SAMPLES..]);
+
+        let silence = pcm.iter().all(|&s| s.abs() <= 1.0 / (1 << 24) as f32);
+        enc.bit_logp(silence as u32, 15);
+        if silence {
+            // The decoder reads nothing further and resets its energies
+            self.old_band_e = [-28.0; NB_BANDS];
+            return enc.finish();
+        }
+        // No pitch pre-filter, long blocks only
+        enc.bit_logp(0, 1);
+        enc.bit_logp(0, 3);
+
+        let mut freq = [0.0f32; FRAME_SAMPLES];
+        self.mdct.forward(&input, &mut freq);
+        let mut band_e = [0.0f32; NB_BANDS];
+        let mut band_log_e = [0.0f32; NB_BANDS];
+        let mut x = [0.0f32; FRAME_SAMPLES];
+        for i in 0..NB_BANDS {
+            let band = band_range(i);
+            let sum: f32 = freq[band.clone()].iter().map(|v| v * v).sum();
+            band_e[i] = (1e-27 + sum).sqrt();
+            let g = 1.0 / (1e-27 + band_e[i]);
+            for j in band {
+                x[j] = freq[j] * g;
+            }
+            band_log_e[i] = band_e[i].log2() - E_MEANS[i];
+        }
+
+        let mut error = [0.0f32; NB_BANDS];
+        let intra = std::mem::take(&mut self.first_frame);
+        self.quant_coarse_energy(
+            &band_log_e,
+            total_bits,
+            nb_bytes as i32,
+            intra,
+            &mut error,
+            &mut enc,
+        );
+
+        // tf_encode with every band at the default resolution; tf_select
+        // makes no difference then, so its reserved bit stays unused
+        let mut logp = 4;
+        let budget = total_bits - (enc.tell() + logp < total_bits) as i32;
+        for _ in 0..NB_BANDS {
+            if enc.tell() + logp <= budget {
+                enc.bit_logp(0, logp as u32);
+            }
+            logp = 5;
+        }
+
+        if enc.tell() + 4 <= total_bits {
+            enc.icdf(SPREAD_NORMAL, &SPREAD_ICDF, 5);
+        }
+
+        // No dynamic allocation boosts: one "no boost" flag per band while it fits
+        let cap: [i32; NB_BANDS] = std::array::from_fn(|i| {
+            let n = (EBANDS[i + 1] - EBANDS[i]) << LM;
+            ((CACHE_CAPS[i] as i32 + 64) * n) >> 2
+        });
+        let total_frac = total_bits << BITRES;
+        let mut tell = enc.tell_frac();
+        for &c in &cap {
+            if tell + (6 << BITRES) < total_frac && c > 0 {
+                enc.bit_logp(0, 6);
+                tell = enc.tell_frac();
+            }
+        }
+
+        if tell + (6 << BITRES) <= total_frac {
+            enc.icdf(ALLOC_TRIM as usize, &TRIM_ICDF, 7);
+        }
+
+        let bits = total_frac - enc.tell_frac() - 1;
+        let alloc = compute_allocation(&cap, bits, self.last_coded_bands, &mut enc);
+        self.last_coded_bands = if self.last_coded_bands != 0 {
+            (self.last_coded_bands + 1).min((self.last_coded_bands - 1).max(alloc.coded_bands))
+        } else {
+            alloc.coded_bands
+        };
+
+        self.quant_fine_energy(&mut error, &alloc.fine_quant, &mut enc);
+        quant_all_bands(&mut x, &alloc, total_frac, &mut enc);
+        let bits_left = total_bits - enc.tell();
+        self.quant_energy_finalise(&mut error, &alloc, bits_left, &mut enc);
+
+        enc.finish()
+    }
+
+    fn quant_coarse_energy(
+        &mut self,
+        band_log_e: &[f32; NB_BANDS],
+        budget: i32,
+        nb_bytes: i32,
+        intra: bool,
+        error: &mut [f32; NB_BANDS],
+        enc: &mut RangeEncoder,
+    ) {
+        let max_decay = 16.0f32.min(0.125 * nb_bytes as f32);
+        let intra = intra && enc.tell() + 3 <= budget;
+        if enc.tell() + 3 <= budget {
+            enc.bit_logp(intra as u32, 3);
+        }
+        let (coef, beta, prob_model) = if intra {
+            (0.0, BETA_INTRA, &E_PROB_INTRA)
+        } else {
+            (PRED_COEF, BETA_COEF, &E_PROB_INTER)
+        };
+        let mut prev = 0.0f32;
+        for i in 0..NB_BANDS {
+            let x = band_log_e[i];
+            let old_e = self.old_band_e[i].max(-9.0);
+            let f = x - coef * old_e - prev;
+            let mut qi = (0.5 + f).floor() as i32;
+            let decay_bound = self.old_band_e[i].max(-28.0) - max_decay;
+            // Keep the energy from dropping too fast (e.g. in one-bin bands)
+            if qi < 0 && x < decay_bound {
+                qi += (decay_bound - x) as i32;
+                qi = qi.min(0);
+            }
+            let tell = enc.tell();
+            let bits_left = budget - tell - 3 * (NB_BANDS - i) as i32;
+            if i != 0 && bits_left < 30 {
+                if bits_left < 24 {
+                    qi = qi.min(1);
+                }
+                if bits_left < 16 {
+                    qi = qi.max(-1);
+                }
+            }
+            if budget - tell >= 15 {
+                let pi = 2 * i.min(20);
+                enc.laplace(
+                    &mut qi,
+                    (prob_model[pi] as u32) << 7,
+                    (prob_model[pi + 1] as u32) << 6,
+                );
+            } else if budget - tell >= 2 {
+                qi = qi.clamp(-1, 1);
+                enc.icdf(
+                    ((2 * qi) ^ -((qi < 0) as i32)) as usize,
+                    &SMALL_ENERGY_ICDF,
+                    2,
+                );
+            } else if budget - tell >= 1 {
+                qi = qi.min(0);
+                enc.bit_logp(-qi as u32, 1);
+            } else {
+                qi = -1;
+            }
+            error[i] = f - qi as f32;
+            let q = qi as f32;
+            self.old_band_e[i] = coef * old_e + prev + q;
+            prev += q - beta * q;
+        }
+    }
+
+    fn quant_fine_energy(
+        &mut self,
+        error: &mut [f32; NB_BANDS],
+        fine_quant: &[i32; NB_BANDS],
+        enc: &mut RangeEncoder,
+    ) {
+        for i in 0..NB_BANDS {
+            if fine_quant[i] <= 0 {
+                continue;
+            }
+            let frac = 1 << fine_quant[i];
+            let q2 = (((error[i] + 0.5) * frac as f32).floor() as i32).clamp(0, frac - 1);
+            enc.bits(q2 as u32, fine_quant[i] as u32);
+            let offset = (q2 as f32 + 0.5) * (1 << (14 - fine_quant[i])) as f32 / 16384.0 - 0.5;
+            self.old_band_e[i] += offset;
+            error[i] -= offset;
+        }
+    }
+
+    fn quant_energy_finalise(
+        &mut self,
+        error: &mut [f32; NB_BANDS],
+        alloc: &Allocation,
+        mut bits_left: i32,
+        enc: &mut RangeEncoder,
+    ) {
+        for prio in 0..2 {
+            for (i, err) in error.iter_mut().enumerate() {
+                if bits_left < 1 {
+                    break;
+                }
+                if alloc.fine_quant[i] >= MAX_FINE_BITS || alloc.fine_priority[i] != prio {
+                    continue;
+                }
+                let q2 = (*err >= 0.0) as i32;
+                enc.bits(q2 as u32, 1);
+                let offset =
+                    (q2 as f32 - 0.5) * (1 << (14 - alloc.fine_quant[i] - 1)) as f32 / 16384.0;
+                self.old_band_e[i] += offset;
+                *err -= offset;
+                bits_left -= 1;
+            }
+        }
+    }
+}
+
+/// MDCT bins of band `i`
+fn band_range(i: usize) -> std::ops::Range<usize> {
+    (EBANDS[i] << LM) as usize..(EBANDS[i + 1] << LM) as usize
+}
+
+/// Result of [`compute_allocation`]
+struct Allocation {
+    coded_bands: i32,
+    balance: i32,
+    pulses: [i32; NB_BANDS],
+    fine_quant: [i32; NB_BANDS],
+    fine_priority: [i32; NB_BANDS],
+}
+
+/// Split `total` eighth-bits between the bands (`clt_compute_allocation`, mono)
+fn compute_allocation(
+    cap: &[i32; NB_BANDS],
+    total: i32,
+    prev: i32,
+    enc: &mut RangeEncoder,
+) -> Allocation {
+    let end = NB_BANDS;
+    let mut total = total.max(0);
+    let skip_rsv = if total >= 1 << BITRES { 1 << BITRES } else { 0 };
+    total -= skip_rsv;
+
+    let mut thresh = [0i32; NB_BANDS];
+    let mut trim_offset = [0i32; NB_BANDS];
+    for j in 0..end {
+        let width = EBANDS[j + 1] - EBANDS[j];
+        thresh[j] = (1 << BITRES).max(((3 * width) << LM << BITRES) >> 4);
+        trim_offset[j] =
+            (width * (ALLOC_TRIM - 5 - LM) * (end - j - 1) as i32 * (1 << (LM + BITRES))) >> 6;
+        if width << LM == 1 {
+            trim_offset[j] -= 1 << BITRES;
+        }
+    }
+
+    let trimmed = |bits: i32, j: usize| {
+        if bits > 0 {
+            (bits + trim_offset[j]).max(0)
+        } else {
+            bits
+        }
+    };
+    let band_bits = |vector: usize, j: usize| {
+        trimmed(
+            ((EBANDS[j + 1] - EBANDS[j]) * (BAND_ALLOCATION[vector][j] as i32)) << LM >> 2,
+            j,
+        )
+    };
+
+    let mut lo = 1i32;
+    let mut hi = BAND_ALLOCATION.len() as i32 - 1;
+    while lo <= hi {
+        let mid = (lo + hi) >> 1;
+        let mut psum = 0;
+        let mut done = false;
+        for j in (0..end).rev() {
+            let bits = band_bits(mid as usize, j);
+            if bits >= thresh[j] || done {
+                done = true;
+                psum += bits.min(cap[j]);
+            } else if bits >= 1 << BITRES {
+                psum += 1 << BITRES;
+            }
+        }
+        if psum > total {
+            hi = mid - 1;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    hi = lo;
+    lo -= 1;
+
+    let mut bits1 = [0i32; NB_BANDS];
+    let mut bits2 = [0i32; NB_BANDS];
+    for j in 0..end {
+        let b1 = band_bits(lo as usize, j);
+        let b2 = if hi as usize >= BAND_ALLOCATION.len() {
+            trimmed(cap[j], j)
+        } else {
+            band_bits(hi as usize, j)
+        };
+        bits1[j] = b1;
+        bits2[j] = (b2 - b1).max(0);
+    }
+
+    interp_bits2pulses(&bits1, &bits2, &thresh, cap, total, skip_rsv, prev, enc)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn interp_bits2pulses(
+    bits1: &[i32; NB_BANDS],
+    bits2: &[i32; NB_BANDS],
+    thresh: &[i32; NB_BANDS],
+    cap: &[i32; NB_BANDS],
+    mut total: i32,
+    skip_rsv: i32,
+    prev: i32,
+    enc: &mut RangeEncoder,
+) -> Allocation {
+    let start = 0usize;
+    let end = NB_BANDS;
+    let skip_start = 0i32;
+    let signal_bandwidth = end as i32 - 1;
+    let alloc_floor = 1 << BITRES;
+    let log_m = LM << BITRES;
+
+    let mut lo = 0;
+    let mut hi = 1 << ALLOC_STEPS;
+    for _ in 0..ALLOC_STEPS {
+        let mid = (lo + hi) >> 1;
+        let mut psum = 0;
+        let mut done = false;
+        for j in (start..end).rev() {
+            let tmp = bits1[j] + ((mid * bits2[j]) >> ALLOC_STEPS);
+            if tmp >= thresh[j] || done {
+                done = true;
+                psum += tmp.min(cap[j]);
+            } else if tmp >= alloc_floor {
+                psum += alloc_floor;
+            }
+        }
+        if psum > total {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    let mut bits = [0i32; NB_BANDS];
+    let mut psum = 0;
+    let mut done = false;
+    for j in (start..end).rev() {
+        let mut tmp = bits1[j] + ((lo * bits2[j]) >> ALLOC_STEPS);
+        if tmp < thresh[j] && !done {
+            tmp = if tmp >= alloc_floor { alloc_floor } else { 0 };
+        } else {
+            done = true;
+        }
+        tmp = tmp.min(cap[j]);
+        bits[j] = tmp;
+        psum += tmp;
+    }
+
+    let width = |from: usize, to: usize| EBANDS[to] - EBANDS[from];
+    let mut coded_bands = end;
+    loop {
+        let j = coded_bands - 1;
+        // Never skip the first band
+        if j as i32 <= skip_start {
+            total += skip_rsv;
+            break;
+        }
+        let mut left = total - psum;
+        let percoeff = left / width(start, coded_bands);
+        left -= width(start, coded_bands) * percoeff;
+        let rem = (left - width(start, j)).max(0);
+        let band_width = width(j, coded_bands);
+        let mut band_bits = bits[j] + percoeff * band_width + rem;
+        if band_bits >= thresh[j].max(alloc_floor + (1 << BITRES)) {
+            let depth_threshold = if coded_bands > 17 {
+                if (j as i32) < prev {
+                    7
+                } else {
+                    9
+                }
+            } else {
+                0
+            };
+            if coded_bands <= start + 2
+                || (band_bits > ((depth_threshold * band_width) << LM << BITRES) >> 4
+                    && j as i32 <= signal_bandwidth)
+            {
+                enc.bit_logp(1, 1);
+                break;
+            }
+            enc.bit_logp(0, 1);
+            psum += 1 << BITRES;
+            band_bits -= 1 << BITRES;
+        }
+        psum -= bits[j];
+        if band_bits >= alloc_floor {
+            psum += alloc_floor;
+            bits[j] = alloc_floor;
+        } else {
+            bits[j] = 0;
+        }
+        coded_bands -= 1;
+    }
+
+    let mut left = total - psum;
+    let percoeff = left / width(start, coded_bands);
+    left -= width(start, coded_bands) * percoeff;
+    for (j, b) in bits.iter_mut().enumerate().take(coded_bands).skip(start) {
+        *b += percoeff * width(j, j + 1);
+    }
+    for (j, b) in bits.iter_mut().enumerate().take(coded_bands).skip(start) {
+        let tmp = left.min(width(j, j + 1));
+        *b += tmp;
+        left -= tmp;
+    }
+
+    let mut fine_quant = [0i32; NB_BANDS];
+    let mut fine_priority = [0i32; NB_BANDS];
+    let mut balance = 0;
+    for j in start..coded_bands {
+        let n = width(j, j + 1) << LM;
+        let bit = bits[j] + balance;
+        // Every band has at least 8 bins at 20ms
+        let mut excess = (bit - cap[j]).max(0);
+        bits[j] = bit - excess;
+        let den = n;
+        let nclogn = den * (LOG_N[j] + log_m);
+        let mut offset = (nclogn >> 1) - den * FINE_OFFSET;
+        if bits[j] + offset < (den * 2) << BITRES {
+            offset += nclogn >> 2;
+        } else if bits[j] + offset < (den * 3) << BITRES {
+            offset += nclogn >> 3;
+        }
+        let mut ebits = (bits[j] + offset + (den << (BITRES - 1))).max(0);
+        ebits = (ebits / den) >> BITRES;
+        if ebits > bits[j] >> BITRES {
+            ebits = bits[j] >> BITRES;
+        }
+        ebits = ebits.min(MAX_FINE_BITS);
+        fine_priority[j] = (ebits * (den << BITRES) >= bits[j] + offset) as i32;
+        bits[j] -= ebits << BITRES;
+
+        // Fine energy can't use quant_all_bands' rebalancing, so do it here
+        if excess > 0 {
+            let extra_fine = (excess >> BITRES).min(MAX_FINE_BITS - ebits);
+            ebits += extra_fine;
+            let extra_bits = extra_fine << BITRES;
+            fine_priority[j] = (extra_bits >= excess - balance) as i32;
+            excess -= extra_bits;
+        }
+        fine_quant[j] = ebits;
+        balance = excess;
+    }
+    for j in coded_bands..end {
+        fine_quant[j] = bits[j] >> BITRES;
+        bits[j] = 0;
+        fine_priority[j] = (fine_quant[j] < 1) as i32;
+    }
+
+    Allocation {
+        coded_bands: coded_bands as i32,
+        balance,
+        pulses: bits,
+        fine_quant,
+        fine_priority,
+    }
+}
+
+/// Pulse cache of band `band` at `lm` (a sub-band after `3 - lm` splits)
+fn pulse_cache(band: usize, lm: i32) -> &'static [u8] {
+    let index = CACHE_INDEX[(lm + 1) as usize * NB_BANDS + band];
+    &CACHE_BITS[index as usize..]
+}
+
+fn get_pulses(i: i32) -> i32 {
+    if i < 8 {
+        i
+    } else {
+        (8 + (i & 7)) << ((i >> 3) - 1)
+    }
+}
+
+fn bits2pulses(band: usize, lm: i32, bits: i32) -> i32 {
+    let cache = pulse_cache(band, lm);
+    let mut lo = 0;
+    let mut hi = cache[0] as i32;
+    let bits = bits - 1;
+    for _ in 0..LOG_MAX_PSEUDO {
+        let mid = (lo + hi + 1) >> 1;
+        if cache[mid as usize] as i32 >= bits {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    let below = if lo == 0 {
+        -1
+    } else {
+        cache[lo as usize] as i32
+    };
+    if bits - below <= cache[hi as usize] as i32 - bits {
+        lo
+    } else {
+        hi
+    }
+}
+
+fn pulses2bits(band: usize, lm: i32, pulses: i32) -> i32 {
+    if pulses == 0 {
+        0
+    } else {
+        pulse_cache(band, lm)[pulses as usize] as i32 + 1
+    }
+}
+
+/// State shared by the bands of one frame (`band_ctx`)
+struct BandCtx<'a> {
+    enc: &'a mut RangeEncoder,
+    band: usize,
+    remaining_bits: i32,
+}
+
+/// Quantise the normalised spectrum `x` band by band (`quant_all_bands`, mono)
+fn quant_all_bands(
+    x: &mut [f32; FRAME_SAMPLES],
+    alloc: &Allocation,
+    total_bits: i32,
+    enc: &mut RangeEncoder,
+) {
+    let mut balance = alloc.balance;
+    let mut ctx = BandCtx {
+        enc,
+        band: 0,
+        remaining_bits: 0,
+    };
+    for i in 0..NB_BANDS {
+        let tell = ctx.enc.tell_frac();
+        if i != 0 {
+            balance -= tell;
+        }
+        ctx.band = i;
+        ctx.remaining_bits = total_bits - tell - 1;
+        let b = if (i as i32) < alloc.coded_bands {
+            let curr_balance = balance / 3.min(alloc.coded_bands - i as i32);
+            0.max(16383.min((ctx.remaining_bits + 1).min(alloc.pulses[i] + curr_balance)))
+        } else {
+            0
+        };
+        let band = band_range(i);
+        let n = band.len();
+        ctx.quant_partition(&mut x[band], n, b, LM);
+        balance += alloc.pulses[i] + tell;
+    }
+}
+
+impl BandCtx<'_> {
+    /// Code one (sub-)band, splitting it in two while it has too many bits (`quant_partition`)
+    fn quant_partition(&mut self, x: &mut [f32], n: usize, mut b: i32, lm: i32) {
+        let cache = pulse_cache(self.band, lm);
+        if lm != -1 && b > cache[cache[0] as usize] as i32 + 12 && n > 2 {
+            let n = n >> 1;
+            let lm = lm - 1;
+            let (x, y) = x.split_at_mut(n);
+            let split = self.compute_theta(x, y, n, &mut b, lm);
+            let mut mbits = 0.max(b.min((b - split.delta) / 2));
+            let mut sbits = b - mbits;
+            self.remaining_bits -= split.qalloc;
+
+            let rebalance = self.remaining_bits;
+            if mbits >= sbits {
+                self.quant_partition(x, n, mbits, lm);
+                let rebalance = mbits - (rebalance - self.remaining_bits);
+                if rebalance > 3 << BITRES && split.itheta != 0 {
+                    sbits += rebalance - (3 << BITRES);
+                }
+                self.quant_partition(y, n, sbits, lm);
+            } else {
+                self.quant_partition(y, n, sbits, lm);
+                let rebalance = sbits - (rebalance - self.remaining_bits);
+                if rebalance > 3 << BITRES && split.itheta != 16384 {
+                    mbits += rebalance - (3 << BITRES);
+                }
+                self.quant_partition(x, n, mbits, lm);
+            }
+        } else {
+            let mut q = bits2pulses(self.band, lm, b);
+            let mut curr_bits = pulses2bits(self.band, lm, q);
+            self.remaining_bits -= curr_bits;
+            // Never bust the budget
+            while self.remaining_bits < 0 && q > 0 {
+                self.remaining_bits += curr_bits;
+                q -= 1;
+                curr_bits = pulses2bits(self.band, lm, q);
+                self.remaining_bits -= curr_bits;
+            }
+            if q != 0 {
+                alg_quant(x, get_pulses(q), self.enc);
+            }
+        }
+    }
+
+    /// Code the energy split between the halves `x` and `y` (`compute_theta`, mono)
+    fn compute_theta(&mut self, x: &[f32], y: &[f32], n: usize, b: &mut i32, lm: i32) -> Split {
+        let pulse_cap = LOG_N[self.band] + lm * (1 << BITRES);
+        let offset = (pulse_cap >> 1) - QTHETA_OFFSET;
+        let qn = compute_qn(n as i32, *b, offset, pulse_cap);
+        let tell = self.enc.tell_frac();
+        let mut itheta = 0;
+        if qn != 1 {
+            itheta = (stereo_itheta(x, y) * qn + 8192) >> 14;
+            // Triangular pdf
+            let ft = ((qn >> 1) + 1) * ((qn >> 1) + 1);
+            let (fs, fl) = if itheta <= qn >> 1 {
+                (itheta + 1, (itheta * (itheta + 1)) >> 1)
+            } else {
+                (
+                    qn + 1 - itheta,
+                    ft - (((qn + 1 - itheta) * (qn + 2 - itheta)) >> 1),
+                )
+            };
+            self.enc.encode(fl as u32, (fl + fs) as u32, ft as u32);
+            itheta = itheta * 16384 / qn;
+        }
+        let qalloc = self.enc.tell_frac() - tell;
+        *b -= qalloc;
+        let delta = match itheta {
+            0 => -16384,
+            16384 => 16384,
+            _ => {
+                let imid = bitexact_cos(itheta as i16);
+                let iside = bitexact_cos((16384 - itheta) as i16);
+                frac_mul16(
+                    ((n as i32) - 1) << 7,
+                    bitexact_log2tan(iside as i32, imid as i32),
+                )
+            }
+        };
+        Split {
+            delta,
+            itheta,
+            qalloc,
+        }
+    }
+}
+
+/// Outcome of [`BandCtx::compute_theta`]
+struct Split {
+    delta: i32,
+    itheta: i32,
+    qalloc: i32,
+}
+
+fn compute_qn(n: i32, b: i32, offset: i32, pulse_cap: i32) -> i32 {
+    const EXP2_TABLE8: [i32; 8] = [16384, 17866, 19483, 21247, 23170, 25267, 27554, 30048];
+    let n2 = 2 * n - 1;
+    let mut qb = (b + n2 * offset) / n2;
+    qb = qb.min(b - pulse_cap - (4 << BITRES));
+    qb = qb.min(8 << BITRES);
+    if qb < (1 << BITRES >> 1) {
+        1
+    } else {
+        let qn = EXP2_TABLE8[(qb & 0x7) as usize] >> (14 - (qb >> BITRES));
+        (qn + 1) >> 1 << 1
+    }
+}
+
+/// Angle between the energies of `x` and `y`, 0..=16384 for 0..=pi/2
+fn stereo_itheta(x: &[f32], y: &[f32]) -> i32 {
+    let e_mid = 1e-15 + x.iter().map(|v| v * v).sum::<f32>();
+    let e_side = 1e-15 + y.iter().map(|v| v * v).sum::<f32>();
+    (0.5 + 16384.0 * std::f32::consts::FRAC_2_PI * e_side.sqrt().atan2(e_mid.sqrt())).floor() as i32
+}
+
+fn frac_mul16(a: i32, b: i32) -> i32 {
+    (16384 + (a as i16 as i32) * (b as i16 as i32)) >> 15
+}
+
+/// cos() approximation that is bit-exact on every platform
+fn bitexact_cos(x: i16) -> i16 {
+    let tmp = (4096 + x as i32 * x as i32) >> 13;
+    let x2 = tmp;
+    let x2 = (32767 - x2) + frac_mul16(x2, -7651 + frac_mul16(x2, 8277 + frac_mul16(-626, x2)));
+    (1 + x2) as i16
+}
+
+fn bitexact_log2tan(isin: i32, icos: i32) -> i32 {
+    let lc = ilog(icos as u32) as i32;
+    let ls = ilog(isin as u32) as i32;
+    let icos = icos << (15 - lc);
+    let isin = isin << (15 - ls);
+    (ls - lc) * (1 << 11) + frac_mul16(isin, frac_mul16(isin, -2597) + 7932)
+        - frac_mul16(icos, frac_mul16(icos, -2597) + 7932)
+}
+
+/// Pick and code `k` pulses approximating `x` (`alg_quant` without resynthesis)
+fn alg_quant(x: &mut [f32], k: i32, enc: &mut RangeEncoder) {
+    exp_rotation(x, k);
+    let iy = pvq_search(x, k);
+    encode_pulses(&iy, k, enc);
+}
+
+/// Spread the energy of a sparse band over its bins (`exp_rotation`, forward)
+fn exp_rotation(x: &mut [f32], k: i32) {
+    const SPREAD_FACTOR: i32 = 10;
+    let len = x.len() as i32;
+    if 2 * k >= len {
+        return;
+    }
+    let gain = len as f32 / (len + SPREAD_FACTOR * k) as f32;
+    let theta = 0.5 * gain * gain;
+    let c = (0.5 * PI * theta).cos();
+    let s = (0.5 * PI * (1.0 - theta)).cos();
+    let mut stride2 = 0;
+    if len >= 8 {
+        stride2 = 1;
+        while stride2 * stride2 + stride2 < len {
+            stride2 += 1;
+        }
+    }
+    exp_rotation1(x, 1, c, -s);
+    if stride2 != 0 {
+        exp_rotation1(x, stride2 as usize, s, -c);
+    }
+}
+
+fn exp_rotation1(x: &mut [f32], stride: usize, c: f32, s: f32) {
+    let len = x.len();
+    if len <= stride {
+        return;
+    }
+    for i in 0..len - stride {
+        let (x1, x2) = (x[i], x[i + stride]);
+        x[i + stride] = c * x2 + s * x1;
+        x[i] = c * x1 - s * x2;
+    }
+    if len < 2 * stride + 1 {
+        return;
+    }
+    for i in (0..len - 2 * stride).rev() {
+        let (x1, x2) = (x[i], x[i + stride]);
+        x[i + stride] = c * x2 + s * x1;
+        x[i] = c * x1 - s * x2;
+    }
+}
+
+/// Signed pulse vector with `k` pulses closest in direction to `x` (`op_pvq_search`)
+fn pvq_search(x: &mut [f32], k: i32) -> Vec<i32> {
+    let n = x.len();
+    let signs: Vec<bool> = x.iter().map(|&v| v < 0.0).collect();
+    for v in x.iter_mut() {
+        *v = v.abs();
+    }
+    let mut iy = vec![0i32; n];
+    let mut y = vec![0.0f32; n];
+    let mut xy = 0.0f32;
+    let mut yy = 0.0f32;
+    let mut pulses_left = k;
+
+    // Pre-search by projecting on the pyramid
+    if k > (n >> 1) as i32 {
+        let mut sum: f32 = x.iter().sum();
+        if !(sum > 1e-15 && sum < 64.0) {
+            x.fill(0.0);
+            x[0] = 1.0;
+            sum = 1.0;
+        }
+        let rcp = (k as f32 + 0.8) / sum;
+        for j in 0..n {
+            iy[j] = (rcp * x[j]).floor() as i32;
+            y[j] = iy[j] as f32;
+            yy += y[j] * y[j];
+            xy += x[j] * y[j];
+            y[j] *= 2.0;
+            pulses_left -= iy[j];
+        }
+    }
+
+    if pulses_left > n as i32 + 3 {
+        let tmp = pulses_left as f32;
+        yy += tmp * tmp + tmp * y[0];
+        iy[0] += pulses_left;
+        pulses_left = 0;
+    }
+
+    for _ in 0..pulses_left {
+        yy += 1.0;
+        let mut best_id = 0;
+        let mut best_num = (xy + x[0]) * (xy + x[0]);
+        let mut best_den = yy + y[0];
+        for j in 1..n {
+            let rxy = xy + x[j];
+            let ryy = yy + y[j];
+            let num = rxy * rxy;
+            if best_den * num > ryy * best_num {
+                best_den = ryy;
+                best_num = num;
+                best_id = j;
+            }
+        }
+        xy += x[best_id];
+        yy += y[best_id];
+        y[best_id] += 2.0;
+        iy[best_id] += 1;
+    }
+
+    for (v, &negative) in iy.iter_mut().zip(&signs) {
+        if negative {
+            *v = -*v;
+        }
+    }
+    iy
+}
+
+/// Code the index of pulse vector `y` among those with `k` pulses (`encode_pulses`)
+fn encode_pulses(y: &[i32], k: i32, enc: &mut RangeEncoder) {
+    let n = y.len();
+    let k = k as usize;
+    // u[i] holds U(n, i), the row of the pyramid vector count recurrence
+    let mut u = vec![0u32; k + 2];
+    for (i, v) in u.iter_mut().enumerate().skip(1) {
+        *v = ((i as u32) << 1) - 1;
+    }
+    let mut j = n - 1;
+    let mut index = (y[j] < 0) as u32;
+    let mut kk = y[j].unsigned_abs() as usize;
+    j -= 1;
+    index = index.wrapping_add(u[kk]);
+    kk += y[j].unsigned_abs() as usize;
+    if y[j] < 0 {
+        index = index.wrapping_add(u[kk + 1]);
+    }
+    while j > 0 {
+        j -= 1;
+        unext(&mut u, k + 2, 0);
+        index = index.wrapping_add(u[kk]);
+        kk += y[j].unsigned_abs() as usize;
+        if y[j] < 0 {
+            index = index.wrapping_add(u[kk + 1]);
+        }
+    }
+    let count = u[kk].wrapping_add(u[kk + 1]);
+    enc.uint(index, count);
+}
+
+/// Next row of the recurrence u[i][j] = u[i-1][j] + u[i][j-1] + u[i-1][j-1]
+fn unext(u: &mut [u32], len: usize, mut u0: u32) {
+    for j in 1..len {
+        let u1 = u[j].wrapping_add(u[j - 1]).wrapping_add(u0);
+        u[j - 1] = u0;
+        u0 = u1;
+    }
+    u[len - 1] = u0;
+}
+
+/// Bits needed to hold `x` (`EC_ILOG`)
+fn ilog(x: u32) -> u32 {
+    32 - x.leading_zeros()
+}
+
+/// Range encoder with raw bits packed from the end of the buffer (`ec_enc`)
+struct RangeEncoder {
+    buf: Vec<u8>,
+    offs: usize,
+    end_offs: usize,
+    end_window: u32,
+    nend_bits: i32,
+    nbits_total: i32,
+    rng: u32,
+    val: u32,
+    rem: i32,
+    ext: u32,
+}
+
+const EC_SYM_BITS: u32 = 8;
+const EC_CODE_BITS: i32 = 32;
+const EC_SYM_MAX: u32 = (1 << EC_SYM_BITS) - 1;
+const EC_CODE_SHIFT: u32 = EC_CODE_BITS as u32 - EC_SYM_BITS - 1;
+const EC_CODE_TOP: u32 = 1 << (EC_CODE_BITS - 1);
+const EC_CODE_BOT: u32 = EC_CODE_TOP >> EC_SYM_BITS;
+const EC_UINT_BITS: u32 = 8;
+
+impl RangeEncoder {
+    fn new(size: usize) -> Self {
+        Self {
+            buf: vec![0; size],
+            offs: 0,
+            end_offs: 0,
+            end_window: 0,
+            nend_bits: 0,
+            nbits_total: EC_CODE_BITS + 1,
+            rng: EC_CODE_TOP,
+            val: 0,
+            rem: -1,
+            ext: 0,
+        }
+    }
+
+    /// Bits used so far, rounded up
+    fn tell(&self) -> i32 {
+        self.nbits_total - ilog(self.rng) as i32
+    }
+
+    /// Bits used so far in 1/8 bits, rounded up
+    fn tell_frac(&self) -> i32 {
+        let nbits = self.nbits_total << BITRES;
+        let mut l = ilog(self.rng) as i32;
+        let mut r = self.rng >> (l - 16);
+        for _ in 0..BITRES {
+            r = (r * r) >> 15;
+            let b = (r >> 16) as i32;
+            l = l << 1 | b;
+            r >>= b;
+        }
+        nbits - l
+    }
+
+    fn write_byte(&mut self, value: u32) {
+        // The allocation never lets the coder outgrow the packet
+        if self.offs + self.end_offs < self.buf.len() {
+            self.buf[self.offs] = value as u8;
+            self.offs += 1;
+        }
+    }
+
+    fn write_byte_at_end(&mut self, value: u32) {
+        if self.offs + self.end_offs < self.buf.len() {
+            self.end_offs += 1;
+            let at = self.buf.len() - self.end_offs;
+            self.buf[at] = value as u8;
+        }
+    }
+
+    fn carry_out(&mut self, c: u32) {
+        if c != EC_SYM_MAX {
+            let carry = c >> EC_SYM_BITS;
+            if self.rem >= 0 {
+                self.write_byte(self.rem as u32 + carry);
+            }
+            if self.ext > 0 {
+                let sym = (EC_SYM_MAX + carry) & EC_SYM_MAX;
+                while self.ext > 0 {
+                    self.write_byte(sym);
+                    self.ext -= 1;
+                }
+            }
+            self.rem = (c & EC_SYM_MAX) as i32;
+        } else {
+            self.ext += 1;
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.rng <= EC_CODE_BOT {
+            self.carry_out(self.val >> EC_CODE_SHIFT);
+            self.val = (self.val << EC_SYM_BITS) & (EC_CODE_TOP - 1);
+            self.rng <<= EC_SYM_BITS;
+            self.nbits_total += EC_SYM_BITS as i32;
+        }
+    }
+
+    fn encode(&mut self, fl: u32, fh: u32, ft: u32) {
+        let r = self.rng / ft;
+        if fl > 0 {
+            self.val += self.rng - r * (ft - fl);
+            self.rng = r * (fh - fl);
+        } else {
+            self.rng -= r * (ft - fh);
+        }
+        self.normalize();
+    }
+
+    fn encode_bin(&mut self, fl: u32, fh: u32, bits: u32) {
+        let r = self.rng >> bits;
+        if fl > 0 {
+            self.val += self.rng - r * ((1 << bits) - fl);
+            self.rng = r * (fh - fl);
+        } else {
+            self.rng -= r * ((1 << bits) - fh);
+        }
+        self.normalize();
+    }
+
+    fn bit_logp(&mut self, val: u32, logp: u32) {
+        let s = self.rng >> logp;
+        let r = self.rng - s;
+        if val != 0 {
+            self.val += r;
+            self.rng = s;
+        } else {
+            self.rng = r;
+        }
+        self.normalize();
+    }
+
+    fn icdf(&mut self, s: usize, icdf: &[u8], ftb: u32) {
+        let r = self.rng >> ftb;
+        if s > 0 {
+            self.val += self.rng - r * icdf[s - 1] as u32;
+            self.rng = r * (icdf[s - 1] - icdf[s]) as u32;
+        } else {
+            self.rng -= r * icdf[s] as u32;
+        }
+        self.normalize();
+    }
+
+    /// Uniformly distributed `fl` in `0..ft`
+    fn uint(&mut self, fl: u32, ft: u32) {
+        let ft = ft - 1;
+        let mut ftb = ilog(ft);
+        if ftb > EC_UINT_BITS {
+            ftb -= EC_UINT_BITS;
+            let top = (ft >> ftb) + 1;
+            let high = fl >> ftb;
+            self.encode(high, high + 1, top);
+            self.bits(fl & ((1 << ftb) - 1), ftb);
+        } else {
+            self.encode(fl, fl + 1, ft + 1);
+        }
+    }
+
+    /// Raw bits, stored from the end of the packet
+    fn bits(&mut self, fl: u32, bits: u32) {
+        let mut window = self.end_window;
+        let mut used = self.nend_bits;
+        if used + bits as i32 > 32 {
+            while used >= EC_SYM_BITS as i32 {
+                self.write_byte_at_end(window & EC_SYM_MAX);
+                window >>= EC_SYM_BITS;
+                used -= EC_SYM_BITS as i32;
+            }
+        }
+        window |= fl << used;
+        used += bits as i32;
+        self.end_window = window;
+        self.nend_bits = used;
+        self.nbits_total += bits as i32;
+    }
+
+    /// Laplace-distributed energy delta (`ec_laplace_encode`); clamps `value` when needed
+    fn laplace(&mut self, value: &mut i32, mut fs: u32, decay: u32) {
+        const MINP: u32 = 1;
+        const NMIN: u32 = 16;
+        let mut fl = 0u32;
+        let mut val = *value;
+        if val != 0 {
+            let s = -((val < 0) as i32);
+            val = (val + s) ^ s;
+            fl = fs;
+            fs = ((32768 - MINP * (2 * NMIN) - fs) * (16384 - decay)) >> 15;
+            let mut i = 1;
+            while fs > 0 && i < val {
+                fs *= 2;
+                fl += fs + 2 * MINP;
+                fs = (fs * decay) >> 15;
+                i += 1;
+            }
+            if fs == 0 {
+                let ndi_max = ((32768 - fl + MINP - 1) as i32 - s) >> 1;
+                let di = (val - i).min(ndi_max - 1);
+                fl += ((2 * di + 1 + s) as u32) * MINP;
+                fs = MINP.min(32768 - fl);
+                *value = (i + di + s) ^ s;
+            } else {
+                fs += MINP;
+                if s == 0 {
+                    fl += fs;
+                }
+            }
+        }
+        self.encode_bin(fl, fl + fs, 15);
+    }
+
+    /// Flush the coder and return the packet with its TOC byte (`ec_enc_done`)
+    fn finish(mut self) -> Vec<u8> {
+        let mut l = EC_CODE_BITS - ilog(self.rng) as i32;
+        let mut msk = (EC_CODE_TOP - 1) >> l;
+        let mut end = self.val.wrapping_add(msk) & !msk;
+        if (end | msk) >= self.val.wrapping_add(self.rng) {
+            l += 1;
+            msk >>= 1;
+            end = self.val.wrapping_add(msk) & !msk;
+        }
+        while l > 0 {
+            self.carry_out(end >> EC_CODE_SHIFT);
+            end = (end << EC_SYM_BITS) & (EC_CODE_TOP - 1);
+            l -= EC_SYM_BITS as i32;
+        }
+        if self.rem >= 0 || self.ext > 0 {
+            self.carry_out(0);
+        }
+        let mut window = self.end_window;
+        let mut used = self.nend_bits;
+        while used >= EC_SYM_BITS as i32 {
+            self.write_byte_at_end(window & EC_SYM_MAX);
+            window >>= EC_SYM_BITS;
+            used -= EC_SYM_BITS as i32;
+        }
+        let size = self.buf.len();
+        self.buf[self.offs..size - self.end_offs].fill(0);
+        if used > 0 && self.end_offs < size {
+            self.buf[size - self.end_offs - 1] |= window as u8;
+        }
+        let mut packet = Vec::with_capacity(size + 1);
+        packet.push(TOC);
+        packet.extend_from_slice(&self.buf);
+        packet
+    }
+}
+
+/// Forward MDCT of a frame with its low-overlap window (`clt_mdct_forward`)
+struct Mdct {
+    window: [f32; OVERLAP],
+    trig: Vec<f32>,
+    twiddles: Vec<(f32, f32)>,
+}
+
+/// MDCT size: two frames' worth of samples
+const MDCT_N: usize = 2 * FRAME_SAMPLES;
+
+impl Mdct {
+    fn new() -> Self {
+        let window = std::array::from_fn(|i| {
+            let s = (0.5 * std::f64::consts::PI * (i as f64 + 0.5) / OVERLAP as f64).sin();
+            (0.5 * std::f64::consts::PI * s * s).sin() as f32
+        });
+        let trig = (0..MDCT_N / 2)
+            .map(|i| (2.0 * std::f64::consts::PI * (i as f64 + 0.125) / MDCT_N as f64).cos() as f32)
+            .collect();
+        let n4 = MDCT_N / 4;
+        let twiddles = (0..n4)
+            .map(|i| {
+                let phase = -2.0 * std::f64::consts::PI * i as f64 / n4 as f64;
+                (phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        Self {
+            window,
+            trig,
+            twiddles,
+        }
+    }
+
+    /// MDCT of `input` (one frame plus the previous overlap) into `out`
+    fn forward(&self, input: &[f32; FRAME_SAMPLES + OVERLAP], out: &mut [f32; FRAME_SAMPLES]) {
+        const N2: usize = MDCT_N / 2;
+        const N4: usize = MDCT_N / 4;
+        const EDGE: usize = (OVERLAP + 3) >> 2;
+        let w = &self.window;
+        let mut f = [0.0f32; N2];
+
+        // Window, shuffle and fold the four quarter blocks
+        let mut xp1 = OVERLAP >> 1;
+        let mut xp2 = N2 - 1 + (OVERLAP >> 1);
+        let mut wp1 = OVERLAP >> 1;
+        let mut wp2 = (OVERLAP >> 1) - 1;
+        for i in 0..EDGE {
+            f[2 * i] = w[wp2] * input[xp1 + N2] + w[wp1] * input[xp2];
+            f[2 * i + 1] = w[wp1] * input[xp1] - w[wp2] * input[xp2 - N2];
+            xp1 += 2;
+            xp2 -= 2;
+            wp1 += 2;
+            wp2 = wp2.wrapping_sub(2);
+        }
+        wp1 = 0;
+        wp2 = OVERLAP - 1;
+        for i in EDGE..N4 - EDGE {
+            f[2 * i] = input[xp2];
+            f[2 * i + 1] = input[xp1];
+            xp1 += 2;
+            xp2 -= 2;
+        }
+        for i in N4 - EDGE..N4 {
+            f[2 * i] = -w[wp1] * input[xp1 - N2] + w[wp2] * input[xp2];
+            f[2 * i + 1] = w[wp2] * input[xp1] + w[wp1] * input[xp2 + N2];
+            xp1 += 2;
+            xp2 = xp2.wrapping_sub(2);
+            wp1 += 2;
+            wp2 = wp2.wrapping_sub(2);
+        }
+
+        // Pre-rotation
+        let scale = 1.0 / N4 as f32;
+        let rotated: Vec<(f32, f32)> = (0..N4)
+            .map(|i| {
+                let (t0, t1) = (self.trig[i], self.trig[N4 + i]);
+                let (re, im) = (f[2 * i], f[2 * i + 1]);
+                ((re * t0 - im * t1) * scale, (im * t0 + re * t1) * scale)
+            })
+            .collect();
+
+        let spectrum = fft(&rotated, &self.twiddles, 1);
+
+        // Post-rotation
+        for (i, &(re, im)) in spectrum.iter().enumerate() {
+            let (t0, t1) = (self.trig[i], self.trig[N4 + i]);
+            out[2 * i] = im * t1 - re * t0;
+            out[N2 - 1 - 2 * i] = re * t1 + im * t0;
+        }
+    }
+}
+
+/// Forward DFT by radix-2 decimation in time down to odd lengths
+///
+/// `twiddles` holds e^(-2πik/N) for the full length N; `step` is N over the
+/// length of `x`.
+fn fft(x: &[(f32, f32)], twiddles: &[(f32, f32)], step: usize) -> Vec<(f32, f32)> {
+    let n = x.len();
+    if !n.is_multiple_of(2) {
+        return (0..n)
+            .map(|k| {
+                x.iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (j, &(xr, xi))| {
+                        let (tr, ti) = twiddles[(j * k % n) * step];
+                        (re + xr * tr - xi * ti, im + xr * ti + xi * tr)
+                    })
+            })
+            .collect();
+    }
+    let even: Vec<_> = x.iter().step_by(2).copied().collect();
+    let odd: Vec<_> = x.iter().skip(1).step_by(2).copied().collect();
+    let even = fft(&even, twiddles, step * 2);
+    let odd = fft(&odd, twiddles, step * 2);
+    let mut out = vec![(0.0, 0.0); n];
+    for k in 0..n / 2 {
+        let (tr, ti) = twiddles[k * step];
+        let (or, oi) = odd[k];
+        let t = (or * tr - oi * ti, or * ti + oi * tr);
+        out[k] = (even[k].0 + t.0, even[k].1 + t.1);
+        out[k + n / 2] = (even[k].0 - t.0, even[k].1 - t.1);
+    }
+    out
+}
@@ -0,0 +1,527 @@
+//! Post-synthesis audio effects
+//!
+//! Characters can give their voice a signature sound (a radio filter, a
+//! touch of reverb) with a chain of effects applied to TTS output. Effects
+//! run on 16-bit PCM, so output is decoded with the [`audio`](crate::audio)
+//! utilities first; compressed engine output cannot be processed and is
+//! passed through unchanged.
+//!
+//! The chain is configured per character under `voice.effects`, applied in
+//! order:
+//!
+//! ```json
+//! { "voice": { "effects": [
+//!     { "type": "high_pass", "cutoff_hz": 300 },
+//!     { "type": "low_pass", "cutoff_hz": 3000 },
+//!     { "type": "gain", "db": 3 },
+//!     "reverb"
+//! ] } }
+//! ```
+//!
+//! A bare name uses the effect's defaults where it has them.
+
+use crate::audio::{decode_wav, encode_wav, pcm16_to_samples, resample_linear, samples_to_pcm16};
+use crate::types::{AudioData, AudioFormat, VoiceError};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use tracing::warn;
+use zoey_core::Result;
+
+/// An effect applied to mono 16-bit PCM
+pub trait AudioEffect: Send + Sync {
+    /// Process `pcm` in place; the length may change (e.g. speed)
+    fn process(&self, pcm: &mut Vec<i16>, sample_rate: u32);
+}
+
+/// Configuration of one built-in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EffectConfig {
+    /// Amplify or attenuate by `db` decibels
+    Gain {
+        /// Gain in decibels (negative attenuates)
+        db: f32,
+    },
+    /// One-pole low-pass filter
+    LowPass {
+        /// Cutoff frequency (Hz)
+        cutoff_hz: f32,
+    },
+    /// One-pole high-pass filter
+    HighPass {
+        /// Cutoff frequency (Hz)
+        cutoff_hz: f32,
+    },
+    /// Play faster or slower by resampling; pitch changes with speed
+    Speed {
+        /// Playback rate (0.25 to 4.0, above 1.0 is faster)
+        factor: f32,
+    },
+    /// Feedback comb-filter reverb
+    Reverb {
+        /// Delay between echoes (ms)
+        #[serde(default = "default_reverb_delay_ms")]
+        delay_ms: f32,
+        /// Echo feedback (0.0 to 0.95)
+        #[serde(default = "default_reverb_decay")]
+        decay: f32,
+        /// Wet signal proportion (0.0 to 1.0)
+        #[serde(default = "default_reverb_mix")]
+        mix: f32,
+    },
+    /// Bit-crush and sample-and-hold for a robotic voice
+    Robot {
+        /// Bits of resolution kept (1 to 16)
+        #[serde(default = "default_robot_bits")]
+        bits: u8,
+        /// Samples each held value lasts
+        #[serde(default = "default_robot_hold")]
+        hold: usize,
+    },
+}
+
+fn default_reverb_delay_ms() -> f32 {
+    60.0
+}
+
+fn default_reverb_decay() -> f32 {
+    0.4
+}
+
+fn default_reverb_mix() -> f32 {
+    0.3
+}
+
+fn default_robot_bits() -> u8 {
+    6
+}
+
+fn default_robot_hold() -> usize {
+    4
+}
+
+impl EffectConfig {
+    /// Parse an effect list, skipping invalid entries with a warning
+    ///
+    /// Entries are objects tagged with `type`, or a bare effect name.
+    pub fn parse_list(entries: &[serde_json::Value]) -> Vec<EffectConfig> {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let tagged = match entry {
+                    serde_json::Value::String(name) => serde_json::json!({ "type": name }),
+                    other => other.clone(),
+                };
+                match serde_json::from_value(tagged) {
+                    Ok(effect) => Some(effect),
+                    Err(e) => {
+                        warn!(entry = %entry, error = %e, "Ignoring invalid voice effect");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Effects configured under `voice.effects` in character settings
+    pub fn from_character_settings(settings: &serde_json::Value) -> Vec<EffectConfig> {
+        settings
+            .get("voice")
+            .and_then(|v| v.get("effects"))
+            .and_then(|v| v.as_array())
+            .map(|entries| Self::parse_list(entries))
+            .unwrap_or_default()
+    }
+
+    /// Instantiate the effect
+    pub fn build(&self) -> Box<dyn AudioEffect> {
+        match *self {
+            Self::Gain { db } => Box::new(Gain { db }),
+            Self::LowPass { cutoff_hz } => Box::new(LowPass { cutoff_hz }),
+            Self::HighPass { cutoff_hz } => Box::new(HighPass { cutoff_hz }),
+            Self::Speed { factor } => Box::new(Speed { factor }),
+            Self::Reverb {
+                delay_ms,
+                decay,
+                mix,
+            } => Box::new(Reverb {
+                delay_ms,
+                decay,
+                mix,
+            }),
+            Self::Robot { bits, hold } => Box::new(Robot { bits, hold }),
+        }
+    }
+}
+
+fn to_sample(v: f32) -> i16 {
+    v.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// Amplify or attenuate by a fixed number of decibels
+pub struct Gain {
+    /// Gain in decibels
+    pub db: f32,
+}
+
+impl AudioEffect for Gain {
+    fn process(&self, pcm: &mut Vec<i16>, _sample_rate: u32) {
+        let factor = 10f32.powf(self.db / 20.0);
+        for s in pcm.iter_mut() {
+            *s = to_sample(*s as f32 * factor);
+        }
+    }
+}
+
+/// Smoothing factor terms of a one-pole filter: (RC, dt)
+fn rc_dt(cutoff_hz: f32, sample_rate: u32) -> (f32, f32) {
+    let rc = 1.0 / (2.0 * PI * cutoff_hz.max(1.0));
+    (rc, 1.0 / sample_rate.max(1) as f32)
+}
+
+/// One-pole IIR low-pass filter
+pub struct LowPass {
+    /// Cutoff frequency (Hz)
+    pub cutoff_hz: f32,
+}
+
+impl AudioEffect for LowPass {
+    fn process(&self, pcm: &mut Vec<i16>, sample_rate: u32) {
+        let (rc, dt) = rc_dt(self.cutoff_hz, sample_rate);
+        let alpha = dt / (rc + dt);
+        let mut y = 0.0f32;
+        for s in pcm.iter_mut() {
+            y += alpha * (*s as f32 - y);
+            *s = to_sample(y);
+        }
+    }
+}
+
+/// One-pole IIR high-pass filter
+pub struct HighPass {
+    /// Cutoff frequency (Hz)
+    pub cutoff_hz: f32,
+}
+
+impl AudioEffect for HighPass {
+    fn process(&self, pcm: &mut Vec<i16>, sample_rate: u32) {
+        let (rc, dt) = rc_dt(self.cutoff_hz, sample_rate);
+        let alpha = rc / (rc + dt);
+        let (mut prev_x, mut y) = (0.0f32, 0.0f32);
+        for s in pcm.iter_mut() {
+            let x = *s as f32;
+            y = alpha * (y + x - prev_x);
+            prev_x = x;
+            *s = to_sample(y);
+        }
+    }
+}
+
+/// Speed change by resampling, without pitch correction
+pub struct Speed {
+    /// Playback rate (above 1.0 is faster)
+    pub factor: f32,
+}
+
+impl AudioEffect for Speed {
+    fn process(&self, pcm: &mut Vec<i16>, sample_rate: u32) {
+        let factor = self.factor.clamp(0.25, 4.0);
+        if (factor - 1.0).abs() < f32::EPSILON {
+            return;
+        }
+        // Treat the audio as recorded at a rate `factor` times higher
+        let from_rate = (sample_rate as f32 * factor).round() as u32;
+        *pcm = resample_linear(pcm, 1, from_rate, sample_rate);
+    }
+}
+
+/// Feedback comb-filter reverb
+pub struct Reverb {
+    /// Delay between echoes (ms)
+    pub delay_ms: f32,
+    /// Echo feedback (0.0 to 0.95)
+    pub decay: f32,
+    /// Wet signal proportion (0.0 to 1.0)
+    pub mix: f32,
+}
+
+impl AudioEffect for Reverb {
+    fn process(&self, pcm: &mut Vec<i16>, sample_rate: u32) {
+        let delay = (self.delay_ms.max(1.0) * sample_rate as f32 / 1000.0) as usize;
+        if delay == 0 || delay >= pcm.len() {
+            return;
+        }
+        let decay = self.decay.clamp(0.0, 0.95);
+        let mix = self.mix.clamp(0.0, 1.0);
+        let mut wet = vec![0.0f32; pcm.len()];
+        for i in 0..pcm.len() {
+            let feedback = if i >= delay { wet[i - delay] } else { 0.0 };
+            wet[i] = pcm[i] as f32 + decay * feedback;
+        }
+        for (s, w) in pcm.iter_mut().zip(wet) {
+            *s = to_sample((1.0 - mix) * *s as f32 + mix * w);
+        }
+    }
+}
+
+/// Bit-crush with sample-and-hold
+pub struct Robot {
+    /// Bits of resolution kept (1 to 16)
+    pub bits: u8,
+    /// Samples each held value lasts
+    pub hold: usize,
+}
+
+impl AudioEffect for Robot {
+    fn process(&self, pcm: &mut Vec<i16>, _sample_rate: u32) {
+        let step = 1i32 << (16 - self.bits.clamp(1, 16) as i32);
+        let hold = self.hold.max(1);
+        let mut held = 0i16;
+        for (i, s) in pcm.iter_mut().enumerate() {
+            if i % hold == 0 {
+                held = ((*s as i32).div_euclid(step) * step) as i16;
+            }
+            *s = held;
+        }
+    }
+}
+
+/// Effects applied in order to synthesized audio
+#[derive(Default)]
+pub struct EffectChain {
+    effects: Vec<Box<dyn AudioEffect>>,
+}
+
+impl EffectChain {
+    /// Build the chain for `configs`
+    pub fn new(configs: &[EffectConfig]) -> Self {
+        Self {
+            effects: configs.iter().map(EffectConfig::build).collect(),
+        }
+    }
+
+    /// Whether no effects are configured
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Run every effect on interleaved samples, one channel at a time
+    pub fn process(&self, samples: &mut Vec<i16>, channels: u16, sample_rate: u32) {
+        let channels = channels.max(1) as usize;
+        if channels == 1 {
+            for effect in &self.effects {
+                effect.process(samples, sample_rate);
+            }
+            return;
+        }
+
+        let mut split: Vec<Vec<i16>> = (0..channels)
+            .map(|ch| samples.iter().skip(ch).step_by(channels).copied().collect())
+            .collect();
+        for channel in &mut split {
+            for effect in &self.effects {
+                effect.process(channel, sample_rate);
+            }
+        }
+        let frames = split.iter().map(Vec::len).min().unwrap_or(0);
+        *samples = (0..frames)
+            .flat_map(|i| split.iter().map(move |channel| channel[i]))
+            .collect();
+    }
+
+    /// Apply the chain to PCM or WAV audio, keeping its format
+    ///
+    /// The duration is recomputed since speed effects change the length.
+    /// Fails with [`VoiceError::InvalidInput`] for compressed audio.
+    pub fn apply(&self, audio: &AudioData) -> Result<AudioData> {
+        let (mut samples, sample_rate, channels) = match audio.format {
+            AudioFormat::Pcm => (
+                pcm16_to_samples(&audio.data),
+                audio.sample_rate,
+                audio.channels.max(1),
+            ),
+            AudioFormat::Wav => {
+                let wav = decode_wav(&audio.data)?;
+                (wav.samples, wav.sample_rate, wav.channels)
+            }
+            other => {
+                return Err(VoiceError::InvalidInput(format!(
+                    "cannot apply voice effects to {} audio: only PCM and WAV can be processed",
+                    other.as_str()
+                ))
+                .into())
+            }
+        };
+
+        self.process(&mut samples, channels, sample_rate);
+
+        let frames = samples.len() as u64 / channels as u64;
+        let data = match audio.format {
+            AudioFormat::Wav => encode_wav(&samples, sample_rate, channels),
+            _ => samples_to_pcm16(&samples),
+        };
+        Ok(AudioData {
+            data: Bytes::from(data),
+            format: audio.format,
+            sample_rate,
+            channels,
+            duration_ms: Some(frames * 1000 / sample_rate.max(1) as u64),
+            character_count: audio.character_count,
+            engine: audio.engine.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn sine(freq: f32, amplitude: f32, len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| (amplitude * (2.0 * PI * freq * i as f32 / RATE as f32).sin()) as i16)
+            .collect()
+    }
+
+    /// Peak amplitude after the filter has settled
+    fn peak(samples: &[i16]) -> i32 {
+        samples[samples.len() / 2..]
+            .iter()
+            .map(|s| (*s as i32).abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn processed(effect: &dyn AudioEffect, mut samples: Vec<i16>) -> Vec<i16> {
+        effect.process(&mut samples, RATE);
+        samples
+    }
+
+    #[test]
+    fn test_gain() {
+        let out = processed(&Gain { db: 6.0 }, vec![1000, -1000, 30000]);
+        assert!((out[0] - 1995).abs() <= 1, "{:?}", out);
+        assert!((out[1] + 1995).abs() <= 1, "{:?}", out);
+        // Clipped rather than wrapped
+        assert_eq!(out[2], i16::MAX);
+    }
+
+    #[test]
+    fn test_filters_attenuate_the_right_band() {
+        let (low, high) = (sine(100.0, 10000.0, 4000), sine(4000.0, 10000.0, 4000));
+
+        let lp = LowPass { cutoff_hz: 500.0 };
+        let (low_out, high_out) = (processed(&lp, low.clone()), processed(&lp, high.clone()));
+        assert!(
+            peak(&high_out) * 2 < peak(&low_out),
+            "{} vs {}",
+            peak(&high_out),
+            peak(&low_out)
+        );
+
+        let hp = HighPass { cutoff_hz: 1000.0 };
+        let (low_out, high_out) = (processed(&hp, low), processed(&hp, high));
+        assert!(
+            peak(&low_out) * 2 < peak(&high_out),
+            "{} vs {}",
+            peak(&low_out),
+            peak(&high_out)
+        );
+    }
+
+    #[test]
+    fn test_speed_changes_length() {
+        assert_eq!(processed(&Speed { factor: 2.0 }, vec![0; 1600]).len(), 800);
+        assert_eq!(processed(&Speed { factor: 0.5 }, vec![0; 1600]).len(), 3200);
+        assert_eq!(processed(&Speed { factor: 1.0 }, vec![0; 1600]).len(), 1600);
+    }
+
+    #[test]
+    fn test_reverb_echoes_an_impulse() {
+        let mut impulse = vec![0i16; 4000];
+        impulse[0] = 10000;
+        let out = processed(
+            &Reverb {
+                delay_ms: 50.0,
+                decay: 0.5,
+                mix: 1.0,
+            },
+            impulse,
+        );
+        // Echoes every 800 samples, each weaker than the last
+        assert_eq!(out[0], 10000);
+        assert_eq!(out[800], 5000);
+        assert_eq!(out[1600], 2500);
+        assert_eq!(out[400], 0);
+    }
+
+    #[test]
+    fn test_robot_quantizes_and_holds() {
+        let out = processed(&Robot { bits: 4, hold: 2 }, vec![5000, 9000, -5000, 100]);
+        // 4 bits leaves steps of 4096
+        assert_eq!(out, vec![4096, 4096, -8192, -8192]);
+    }
+
+    #[test]
+    fn test_chain_runs_in_order() {
+        let audio = |samples: &[i16]| {
+            AudioData::new(
+                Bytes::from(samples_to_pcm16(samples)),
+                AudioFormat::Pcm,
+                RATE,
+            )
+        };
+        let gain_then_crush = EffectChain::new(&[
+            EffectConfig::Gain { db: 20.0 },
+            EffectConfig::Robot { bits: 4, hold: 1 },
+        ]);
+        let crush_then_gain = EffectChain::new(&[
+            EffectConfig::Robot { bits: 4, hold: 1 },
+            EffectConfig::Gain { db: 20.0 },
+        ]);
+        let input = audio(&[1000; 4]);
+        let a = pcm16_to_samples(&gain_then_crush.apply(&input).unwrap().data);
+        let b = pcm16_to_samples(&crush_then_gain.apply(&input).unwrap().data);
+        assert_eq!(a, vec![8192; 4]);
+        assert_eq!(b, vec![0; 4]);
+    }
+
+    #[test]
+    fn test_speed_updates_duration() {
+        let chain = EffectChain::new(&[EffectConfig::Speed { factor: 2.0 }]);
+        let wav = encode_wav(&[100; RATE as usize * 2], RATE, 2);
+        let audio = AudioData::new(Bytes::from(wav), AudioFormat::Wav, RATE).with_channels(2);
+
+        let out = chain.apply(&audio).unwrap();
+        assert_eq!(out.format, AudioFormat::Wav);
+        assert_eq!(out.duration_ms, Some(500));
+        assert_eq!(decode_wav(&out.data).unwrap().samples.len(), RATE as usize);
+
+        let mp3 = AudioData::new(Bytes::from_static(b"ID3"), AudioFormat::Mp3, RATE);
+        assert!(chain.apply(&mp3).is_err());
+    }
+
+    #[test]
+    fn test_parse_from_character_settings() {
+        let settings = serde_json::json!({ "voice": { "effects": [
+            { "type": "low_pass", "cutoff_hz": 3000 },
+            "reverb",
+            { "type": "gain" },
+            "flanger"
+        ] } });
+        assert_eq!(
+            EffectConfig::from_character_settings(&settings),
+            vec![
+                EffectConfig::LowPass { cutoff_hz: 3000.0 },
+                EffectConfig::Reverb {
+                    delay_ms: 60.0,
+                    decay: 0.4,
+                    mix: 0.3
+                },
+            ]
+        );
+        assert!(EffectConfig::from_character_settings(&serde_json::json!({})).is_empty());
+    }
+}
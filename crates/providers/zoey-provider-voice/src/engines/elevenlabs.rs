@@ -300,8 +300,10 @@ impl VoiceEngine for ElevenLabsVoiceEngine {
             data: bytes,
             format: config.output_format,
             sample_rate: 44100, // ElevenLabs default
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -329,6 +331,10 @@ impl VoiceEngine for ElevenLabsVoiceEngine {
         let style = config.style;
         let text = text.to_string();
         let output_format = Self::map_format(config.output_format).to_string();
+        let (format, sample_rate) = match config.output_format {
+            AudioFormat::Pcm => (AudioFormat::Pcm, 24_000),
+            _ => (AudioFormat::Mp3, 44_100),
+        };
 
         let (tx, rx) = create_audio_stream(32);
 
@@ -383,6 +389,8 @@ impl VoiceEngine for ElevenLabsVoiceEngine {
                                     index: chunk_index,
                                     is_final: false,
                                     timestamp_ms: None,
+                                    format: Some(format),
+                                    sample_rate: Some(sample_rate),
                                 };
                                 if tx.send(Ok(audio_chunk)).await.is_err() {
                                     break; // Receiver dropped
@@ -404,6 +412,8 @@ impl VoiceEngine for ElevenLabsVoiceEngine {
                         index: chunk_index,
                         is_final: true,
                         timestamp_ms: None,
+                        format: Some(format),
+                        sample_rate: Some(sample_rate),
                     };
                     let _ = tx.send(Ok(final_chunk)).await;
                 }
@@ -455,6 +465,20 @@ impl VoiceEngine for ElevenLabsVoiceEngine {
         self.get_api_key().is_ok()
     }
 
+    /// List the voices instead of synthesizing, which is billed per character
+    async fn is_healthy(&self) -> bool {
+        let Ok(api_key) = self.get_api_key() else {
+            return false;
+        };
+        let probe = Self::client()
+            .get(format!("{}/voices", ELEVENLABS_API_BASE))
+            .header("xi-api-key", &api_key)
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await;
+        matches!(probe, Ok(response) if response.status().is_success())
+    }
+
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![AudioFormat::Mp3, AudioFormat::Pcm]
     }
@@ -462,6 +486,10 @@ impl VoiceEngine for ElevenLabsVoiceEngine {
     fn max_text_length(&self) -> usize {
         5000 // ElevenLabs limit
     }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -360,8 +360,10 @@ impl VoiceEngine for LocalVoiceEngine {
             data: bytes,
             format: config.output_format,
             sample_rate: config.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -380,6 +382,8 @@ impl VoiceEngine for LocalVoiceEngine {
                             index: 0,
                             is_final: false,
                             timestamp_ms: None,
+                            format: Some(audio.format),
+                            sample_rate: Some(audio.sample_rate),
                         }))
                         .await;
 
@@ -389,6 +393,8 @@ impl VoiceEngine for LocalVoiceEngine {
                             index: 1,
                             is_final: true,
                             timestamp_ms: None,
+                            format: Some(audio.format),
+                            sample_rate: Some(audio.sample_rate),
                         }))
                         .await;
                 });
@@ -437,6 +443,8 @@ impl VoiceEngine for LocalVoiceEngine {
                                     index: chunk_index,
                                     is_final: false,
                                     timestamp_ms: None,
+                                    format: None,
+                                    sample_rate: None,
                                 };
                                 if tx.send(Ok(audio_chunk)).await.is_err() {
                                     break;
@@ -457,6 +465,8 @@ impl VoiceEngine for LocalVoiceEngine {
                         index: chunk_index,
                         is_final: true,
                         timestamp_ms: None,
+                        format: None,
+                        sample_rate: None,
                     };
                     let _ = tx.send(Ok(final_chunk)).await;
                 }
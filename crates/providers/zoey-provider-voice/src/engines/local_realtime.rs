@@ -207,8 +207,10 @@ impl LocalRealtimePipeline {
             data: Bytes::from(pcm_bytes),
             format: AudioFormat::Pcm,
             sample_rate: 16000,
+            channels: 1,
             duration_ms: Some((audio.len() as u64 * 1000) / 16000),
             character_count: 0,
+            engine: None,
         };
         
         let config = TranscriptionConfig {
@@ -1055,6 +1055,8 @@ impl SpeechEngine for MoshiEngine {
             duration_ms,
             segments: Vec::new(),
             processing_time_ms: Some(processing_time_ms),
+            input_spec: None,
+            converted_spec: None,
         })
     }
 
@@ -1148,6 +1150,10 @@ impl SpeechEngine for MoshiEngine {
         }
     }
 
+    fn expected_input(&self) -> AudioSpec {
+        AudioSpec::pcm_mono(self.config.sample_rate)
+    }
+
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![AudioFormat::Pcm, AudioFormat::Wav]
     }
@@ -1180,40 +1186,24 @@ impl MoshiStream {
         self.client.send_audio(pcm)
     }
 
-    /// Send raw PCM bytes (16-bit, will be converted to f32)
+    /// Send raw PCM bytes (16-bit mono at `sample_rate`)
     pub fn send_pcm_bytes(&self, data: &[u8], sample_rate: u32) -> Result<()> {
-        // Convert to f32
-        let samples: Vec<f32> = data
-            .chunks(2)
-            .map(|chunk| {
-                let sample = i16::from_le_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]);
-                sample as f32 / 32768.0
-            })
-            .collect();
-
-        // Resample if needed
-        if sample_rate != 24000 {
-            let ratio = 24000.0 / sample_rate as f64;
-            let output_len = (samples.len() as f64 * ratio) as usize;
-            let mut resampled = Vec::with_capacity(output_len);
-
-            for i in 0..output_len {
-                let src_idx = i as f64 / ratio;
-                let idx = src_idx.floor() as usize;
-                let frac = src_idx.fract() as f32;
-
-                if idx + 1 < samples.len() {
-                    let sample = samples[idx] * (1.0 - frac) + samples[idx + 1] * frac;
-                    resampled.push(sample);
-                } else if idx < samples.len() {
-                    resampled.push(samples[idx]);
-                }
-            }
+        self.send_audio_data(&AudioData::new(
+            Bytes::copy_from_slice(data),
+            AudioFormat::Pcm,
+            sample_rate,
+        ))
+    }
 
-            self.client.send_audio(resampled)
-        } else {
-            self.client.send_audio(samples)
-        }
+    /// Send audio of any convertible spec, normalizing it to the session's input
+    pub fn send_audio_data(&self, audio: &AudioData) -> Result<()> {
+        let expected = AudioSpec::pcm_mono(self.client.config.sample_rate);
+        let normalized = crate::audio::normalize(audio, &expected)?;
+        let samples = crate::audio::pcm16_to_samples(&normalized.data)
+            .into_iter()
+            .map(|s| s as f32 / 32768.0)
+            .collect();
+        self.client.send_audio(samples)
     }
 
     /// Get next transcription event
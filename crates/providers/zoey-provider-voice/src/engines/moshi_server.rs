@@ -825,8 +825,10 @@ impl MoshiServer {
             data: Bytes::from(resampled),
             format: AudioFormat::Pcm,
             sample_rate: 16000,
+            channels: 1,
             duration_ms: Some((samples.len() as u64 * 1000) / MOSHI_SAMPLE_RATE as u64),
             character_count: 0,
+            engine: None,
         };
 
         let engine = WhisperEngine::new(model);
@@ -214,8 +214,10 @@ impl VoiceEngine for OpenAIVoiceEngine {
             data: bytes,
             format: config.output_format,
             sample_rate: config.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -249,6 +251,7 @@ impl VoiceEngine for OpenAIVoiceEngine {
             },
         };
 
+        let (format, sample_rate) = (config.output_format, config.sample_rate);
         let (tx, rx) = create_audio_stream(32);
 
         // Spawn async task to stream audio
@@ -282,6 +285,8 @@ impl VoiceEngine for OpenAIVoiceEngine {
                                     index: chunk_index,
                                     is_final: false,
                                     timestamp_ms: None,
+                                    format: Some(format),
+                                    sample_rate: Some(sample_rate),
                                 };
                                 if tx.send(Ok(audio_chunk)).await.is_err() {
                                     break; // Receiver dropped
@@ -303,6 +308,8 @@ impl VoiceEngine for OpenAIVoiceEngine {
                         index: chunk_index,
                         is_final: true,
                         timestamp_ms: None,
+                        format: Some(format),
+                        sample_rate: Some(sample_rate),
                     };
                     let _ = tx.send(Ok(final_chunk)).await;
                 }
@@ -325,6 +332,20 @@ impl VoiceEngine for OpenAIVoiceEngine {
         self.get_api_key().is_ok()
     }
 
+    /// Look up the model instead of synthesizing, which is billed per character
+    async fn is_healthy(&self) -> bool {
+        let Ok(api_key) = self.get_api_key() else {
+            return false;
+        };
+        let probe = Self::client()
+            .get(format!("{}/models/{}", OPENAI_API_BASE, self.model.as_str()))
+            .header("Authorization", format!("Bearer {}", api_key))
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await;
+        matches!(probe, Ok(response) if response.status().is_success())
+    }
+
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![
             AudioFormat::Mp3,
@@ -339,6 +360,10 @@ impl VoiceEngine for OpenAIVoiceEngine {
     fn max_text_length(&self) -> usize {
         4096
     }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -38,6 +38,7 @@
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -169,7 +170,7 @@ pub struct PiperEngine {
     voice: PiperVoice,
     /// Speaking rate (0.5 to 2.0, default 1.0)
     rate: f32,
-    /// Use streaming synthesis
+    /// Stream the HTTP response in `synthesize_stream` instead of slicing a finished clip
     streaming: bool,
 }
 
@@ -185,7 +186,7 @@ impl PiperEngine {
             endpoint: endpoint.trim_end_matches('/').to_string(),
             voice: PiperVoice::default(),
             rate: 1.0,
-            streaming: false,
+            streaming: true,
         }
     }
 
@@ -201,7 +202,11 @@ impl PiperEngine {
         self
     }
 
-    /// Enable streaming synthesis
+    /// Enable streaming synthesis (on by default)
+    ///
+    /// When enabled, [`VoiceEngine::synthesize_stream`] passes on the
+    /// server's chunked PCM response as it arrives. Disable it for servers
+    /// that buffer the whole clip anyway.
     pub fn with_streaming(mut self, enabled: bool) -> Self {
         self.streaming = enabled;
         self
@@ -259,11 +264,10 @@ impl PiperEngine {
         Ok(samples)
     }
 
-    /// Synthesize text to raw audio bytes (16-bit PCM)
-    pub async fn synthesize_raw(&self, text: &str) -> Result<Vec<u8>, VoiceError> {
-        // Try different API formats that Piper servers use
-
-        // Format 1: POST with JSON body (piper-http style)
+    /// POST to the JSON API (piper-http style), `None` unless the server accepts it
+    ///
+    /// The raw PCM body is written as it is synthesized.
+    async fn request_raw(&self, text: &str) -> Option<reqwest::Response> {
         let result = self
             .client
             .post(&format!("{}/api/tts", self.endpoint))
@@ -277,14 +281,20 @@ impl PiperEngine {
             .send()
             .await;
 
-        if let Ok(resp) = result {
-            if resp.status().is_success() {
-                return Ok(resp
-                    .bytes()
-                    .await
-                    .map_err(|e| VoiceError::NetworkError(e.to_string()))?
-                    .to_vec());
-            }
+        result.ok().filter(|resp| resp.status().is_success())
+    }
+
+    /// Synthesize text to raw audio bytes (16-bit PCM)
+    pub async fn synthesize_raw(&self, text: &str) -> Result<Vec<u8>, VoiceError> {
+        // Try different API formats that Piper servers use
+
+        // Format 1: POST with JSON body (piper-http style)
+        if let Some(resp) = self.request_raw(text).await {
+            return Ok(resp
+                .bytes()
+                .await
+                .map_err(|e| VoiceError::NetworkError(e.to_string()))?
+                .to_vec());
         }
 
         // Format 2: GET with query params (simpler servers)
@@ -408,8 +418,10 @@ impl VoiceEngine for PiperEngine {
             data: Bytes::from(pcm),
             format: AudioFormat::Pcm,
             sample_rate: self.voice.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -418,9 +430,59 @@ impl VoiceEngine for PiperEngine {
         text: &str,
         config: &VoiceConfig,
     ) -> zoey_core::Result<AudioStream> {
-        // Piper doesn't support true streaming, so synthesize and emit chunks
         let (tx, rx) = create_audio_stream(32);
 
+        // The JSON API answers with chunked PCM; pass it on as it arrives
+        if self.streaming {
+            if let Some(response) = self.request_raw(text).await {
+                let sample_rate = self.voice.sample_rate;
+                tokio::spawn(async move {
+                    let mut stream = response.bytes_stream();
+                    let mut chunk_index = 0;
+
+                    while let Some(chunk_result) = stream.next().await {
+                        match chunk_result {
+                            Ok(chunk) if chunk.is_empty() => {}
+                            Ok(chunk) => {
+                                let audio_chunk = AudioChunk {
+                                    data: chunk,
+                                    index: chunk_index,
+                                    is_final: false,
+                                    timestamp_ms: None,
+                                    format: Some(AudioFormat::Pcm),
+                                    sample_rate: Some(sample_rate),
+                                };
+                                if tx.send(Ok(audio_chunk)).await.is_err() {
+                                    return; // Receiver dropped
+                                }
+                                chunk_index += 1;
+                            }
+                            Err(e) => {
+                                let _ = tx
+                                    .send(Err(VoiceError::NetworkError(e.to_string()).into()))
+                                    .await;
+                                return;
+                            }
+                        }
+                    }
+
+                    // Send final chunk marker
+                    let final_chunk = AudioChunk {
+                        data: Bytes::new(),
+                        index: chunk_index,
+                        is_final: true,
+                        timestamp_ms: None,
+                        format: Some(AudioFormat::Pcm),
+                        sample_rate: Some(sample_rate),
+                    };
+                    let _ = tx.send(Ok(final_chunk)).await;
+                });
+                return Ok(rx);
+            }
+            debug!("Piper JSON API unavailable, synthesizing the whole clip");
+        }
+
+        // Other server styles answer with the whole clip; emit it in chunks
         let audio = self.synthesize(text, config).await;
 
         tokio::spawn(async move {
@@ -438,6 +500,8 @@ impl VoiceEngine for PiperEngine {
                                 index: i,
                                 is_final: i == total - 1,
                                 timestamp_ms: None,
+                                format: Some(data.format),
+                                sample_rate: Some(data.sample_rate),
                             }))
                             .await;
                     }
@@ -485,6 +549,13 @@ impl VoiceEngine for PiperEngine {
         self.health_check().await
     }
 
+    /// Probe the server's endpoints instead of synthesizing
+    async fn is_healthy(&self) -> bool {
+        tokio::time::timeout(HEALTH_CHECK_TIMEOUT, self.health_check())
+            .await
+            .unwrap_or(false)
+    }
+
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![AudioFormat::Pcm, AudioFormat::Wav]
     }
@@ -492,6 +563,10 @@ impl VoiceEngine for PiperEngine {
     fn max_text_length(&self) -> usize {
         10000 // Piper handles long text well
     }
+
+    fn supports_streaming(&self) -> bool {
+        self.streaming
+    }
 }
 
 // ============================================================================
@@ -752,8 +827,10 @@ impl VoiceEngine for LocalPiperEngine {
             data: Bytes::from(pcm),
             format: AudioFormat::Pcm,
             sample_rate: self.voice.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -779,6 +856,8 @@ impl VoiceEngine for LocalPiperEngine {
                                 index: i,
                                 is_final: i == total - 1,
                                 timestamp_ms: None,
+                                format: Some(data.format),
+                                sample_rate: Some(data.sample_rate),
                             }))
                             .await;
                     }
@@ -522,8 +522,10 @@ impl VoiceEngine for PocketTTSEngine {
             data: Bytes::from(data),
             format,
             sample_rate: self.voice.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -553,6 +555,8 @@ impl VoiceEngine for PocketTTSEngine {
                                 index: i,
                                 is_final: i == total - 1,
                                 timestamp_ms: None,
+                                format: Some(data.format),
+                                sample_rate: Some(data.sample_rate),
                             }))
                             .await;
                     }
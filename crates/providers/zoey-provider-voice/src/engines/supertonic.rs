@@ -423,8 +423,10 @@ impl VoiceEngine for SupertonicEngine {
             data: Bytes::from(pcm),
             format: AudioFormat::Pcm,
             sample_rate: self.voice.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -452,6 +454,8 @@ impl VoiceEngine for SupertonicEngine {
                                 index: i,
                                 is_final: i == total - 1,
                                 timestamp_ms: None,
+                                format: Some(data.format),
+                                sample_rate: Some(data.sample_rate),
                             }))
                             .await;
                     }
@@ -780,8 +784,10 @@ impl VoiceEngine for LocalSupertonicEngine {
             data: Bytes::from(pcm),
             format: AudioFormat::Pcm,
             sample_rate: self.config.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -807,6 +813,8 @@ impl VoiceEngine for LocalSupertonicEngine {
                                 index: i,
                                 is_final: i == total - 1,
                                 timestamp_ms: None,
+                                format: Some(data.format),
+                                sample_rate: Some(data.sample_rate),
                             }))
                             .await;
                     }
@@ -561,6 +561,8 @@ impl SpeechEngine for UnmuteEngine {
             duration_ms: audio.duration_ms,
             segments: Vec::new(),
             processing_time_ms: None,
+            input_spec: None,
+            converted_spec: None,
         })
     }
 
@@ -598,6 +600,11 @@ impl SpeechEngine for UnmuteEngine {
         self.health_check().await
     }
 
+    fn expected_input(&self) -> AudioSpec {
+        // Realtime API convention: 24kHz mono PCM16
+        AudioSpec::pcm_mono(24000)
+    }
+
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![
             AudioFormat::Pcm,
@@ -632,8 +639,10 @@ impl VoiceEngine for UnmuteEngine {
             data: Bytes::from(audio_bytes),
             format: config.output_format,
             sample_rate: config.sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: text.len(),
+            engine: None,
         })
     }
 
@@ -724,6 +733,8 @@ impl VoiceEngine for UnmuteEngine {
                                             index: chunk_index,
                                             is_final: false,
                                             timestamp_ms: Some(latency_ms),
+                                            format: Some(voice_config.output_format),
+                                            sample_rate: Some(voice_config.sample_rate),
                                         })).await {
                                             // Receiver dropped, stop streaming
                                             break;
@@ -738,6 +749,8 @@ impl VoiceEngine for UnmuteEngine {
                                         index: chunk_index,
                                         is_final: true,
                                         timestamp_ms: Some(start_time.elapsed().as_millis() as u64),
+                                        format: Some(voice_config.output_format),
+                                        sample_rate: Some(voice_config.sample_rate),
                                     })).await;
                                     break;
                                 }
@@ -800,6 +813,10 @@ impl VoiceEngine for UnmuteEngine {
     fn max_text_length(&self) -> usize {
         8192
     }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
 }
 
 // Stub implementation when unmute feature is disabled
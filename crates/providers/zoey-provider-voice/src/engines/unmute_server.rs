@@ -714,8 +714,10 @@ impl UnmuteServer {
             data: Bytes::copy_from_slice(audio_bytes),
             format: AudioFormat::Pcm,
             sample_rate: 16000,
+            channels: 1,
             duration_ms: Some((audio_bytes.len() as u64 * 1000) / (16000 * 2)),
             character_count: 0,
+            engine: None,
         };
         
         let config = TranscriptionConfig::default();
@@ -256,6 +256,8 @@ impl VoskEngine {
             duration_ms: Some(elapsed.as_millis() as u64),
             segments: Vec::new(),
             processing_time_ms: Some(elapsed.as_millis() as u64),
+            input_spec: None,
+            converted_spec: None,
         })
     }
 
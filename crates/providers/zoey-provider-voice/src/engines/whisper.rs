@@ -3,7 +3,10 @@
 //! Speech-to-text using whisper.cpp via the whisper-rs crate.
 //! Supports automatic model downloading and various model sizes.
 //!
-//! Models are downloaded from HuggingFace on first use and cached locally.
+//! Models are downloaded from HuggingFace on first use and cached locally,
+//! unless `TranscriptionConfig::model_path` or `never_download` is set (also
+//! via `WHISPER_MODEL_PATH` / `WHISPER_NEVER_DOWNLOAD`), in which case a
+//! missing model is an error instead of a network fetch.
 
 #[cfg(feature = "whisper")]
 use async_trait::async_trait;
@@ -48,6 +51,8 @@ pub struct WhisperEngine {
     model_size: WhisperModel,
     /// Path to model file
     model_path: PathBuf,
+    /// Whether a missing model may be downloaded
+    allow_download: bool,
     /// Whether the model is loaded
     loaded: Arc<RwLock<bool>>,
 }
@@ -55,17 +60,33 @@ pub struct WhisperEngine {
 #[cfg(feature = "whisper")]
 impl WhisperEngine {
     /// Create a new Whisper engine with the specified model size
-    /// Model will be downloaded on first use if not present
+    /// Model will be downloaded on first use if not present, unless the
+    /// environment pins an offline model (see [`TranscriptionConfig::with_env_overrides`])
     pub fn new(model_size: WhisperModel) -> Self {
-        let cache_dir = default_cache_dir();
-        let model_path = cache_dir.join(model_size.ggml_filename());
+        Self::from_config(
+            &TranscriptionConfig {
+                whisper_model: model_size,
+                ..Default::default()
+            }
+            .with_env_overrides(),
+        )
+    }
 
-        Self {
+    /// Create from a transcription config, honoring `model_path` and `never_download`
+    pub fn from_config(config: &TranscriptionConfig) -> Self {
+        let model_path = config
+            .model_path
+            .clone()
+            .unwrap_or_else(|| default_cache_dir().join(config.whisper_model.ggml_filename()));
+        let engine = Self {
             ctx: Arc::new(RwLock::new(None)),
-            model_size,
+            model_size: config.whisper_model,
             model_path,
+            allow_download: config.allows_download(),
             loaded: Arc::new(RwLock::new(false)),
-        }
+        };
+        info!("Whisper STT model source: {}", engine.model_source());
+        engine
     }
 
     /// Create with a specific model file path
@@ -74,10 +95,30 @@ impl WhisperEngine {
             ctx: Arc::new(RwLock::new(None)),
             model_size,
             model_path,
+            allow_download: true,
             loaded: Arc::new(RwLock::new(false)),
         }
     }
 
+    /// Never download the model; a missing file becomes an error
+    pub fn without_download(mut self) -> Self {
+        self.allow_download = false;
+        self
+    }
+
+    /// Human-readable description of where the model comes from
+    pub fn model_source(&self) -> String {
+        if self.allow_download {
+            format!(
+                "{} (downloaded from {} if missing)",
+                self.model_path.display(),
+                HF_MODEL_BASE
+            )
+        } else {
+            format!("{} (local only, downloads disabled)", self.model_path.display())
+        }
+    }
+
     /// Get the model file path
     pub fn model_path(&self) -> &PathBuf {
         &self.model_path
@@ -99,6 +140,16 @@ impl WhisperEngine {
             debug!("Whisper model already exists at {:?}", self.model_path);
             return Ok(());
         }
+        if !self.allow_download {
+            return Err(VoiceError::ModelError(format!(
+                "Whisper {} model not found at {} and downloads are disabled; \
+                 place {} there or point WHISPER_MODEL_PATH at an existing model",
+                self.model_size.as_str(),
+                self.model_path.display(),
+                self.model_size.ggml_filename()
+            ))
+            .into());
+        }
 
         info!(
             "Downloading Whisper {} model (~{}MB)...",
@@ -412,6 +463,8 @@ impl WhisperEngine {
             duration_ms: Some(audio_duration),
             segments: result.1,
             processing_time_ms: Some(processing_time),
+            input_spec: None,
+            converted_spec: None,
         })
     }
 }
@@ -503,6 +556,10 @@ impl SpeechEngine for WhisperEngine {
         *self.loaded.read().await
     }
 
+    fn expected_input(&self) -> AudioSpec {
+        AudioSpec::pcm_mono(16000)
+    }
+
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![AudioFormat::Wav, AudioFormat::Pcm]
     }
@@ -555,6 +612,35 @@ mod tests {
         assert!("invalid".parse::<WhisperModel>().is_err());
     }
 
+    #[tokio::test]
+    async fn test_missing_model_without_download_errors() {
+        let config = TranscriptionConfig {
+            whisper_model: WhisperModel::Tiny,
+            model_path: Some(std::env::temp_dir().join("zoey-missing-ggml-tiny.bin")),
+            ..Default::default()
+        };
+        assert!(!config.allows_download());
+        let engine = WhisperEngine::from_config(&config);
+        assert!(engine.model_source().contains("downloads disabled"));
+
+        let err = engine.ensure_model().await.unwrap_err().to_string();
+        assert!(err.contains("downloads are disabled"), "{}", err);
+        assert!(err.contains("zoey-missing-ggml-tiny.bin"), "{}", err);
+    }
+
+    #[test]
+    fn test_never_download_keeps_cache_path() {
+        let config = TranscriptionConfig {
+            whisper_model: WhisperModel::Base,
+            never_download: true,
+            ..Default::default()
+        };
+        let engine = WhisperEngine::from_config(&config);
+        assert!(engine.model_path().ends_with("ggml-base.bin"));
+        assert!(engine.model_source().contains("downloads disabled"));
+        assert!(TranscriptionConfig::default().allows_download());
+    }
+
     #[test]
     fn test_resample() {
         // Test upsampling from 8kHz to 16kHz
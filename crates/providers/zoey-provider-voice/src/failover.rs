@@ -0,0 +1,667 @@
+//! TTS failover chain with warm standby engines
+//!
+//! [`FailoverEngine`] serves requests from its primary engine and moves down
+//! an ordered chain of fallbacks when a request fails. A fallback that has not
+//! synthesized anything since the process started pays its cold start (model
+//! load, TLS handshake, container spin-up) on exactly the request that is
+//! already late, so the chain keeps its standbys warm with a tiny canary
+//! synthesis:
+//!
+//! - [`FailoverEngine::warm_up`] speaks [`CanaryConfig::text`] on every
+//!   fallback and discards the audio, recording readiness and latency
+//! - [`FailoverEngine::start_canaries`] repeats that every
+//!   [`CanaryConfig::interval`] until [`FailoverEngine::shutdown`]
+//! - fallbacks whose last canary (or real request) succeeded are tried
+//!   before cold ones; the primary goes first
+//!
+//! An engine that fails a request, a canary or a [`VoiceEngine::is_healthy`]
+//! check cools down for [`FailoverEngine::with_cooldown`] (by default
+//! [`DEFAULT_FAILOVER_COOLDOWN`]): until then it is tried only after every
+//! other engine, primary included, so a dead Piper server costs one failed
+//! request rather than one per utterance. The canary task health-checks the
+//! primary on each round; [`FailoverEngine::check_health`] checks the whole
+//! chain on demand.
+//!
+//! Each engine gets at most one canary per interval, however often warm-up is
+//! called, and engines added with `expensive: true` (per-character billing),
+//! or a primary marked with [`FailoverEngine::with_expensive_primary`], are
+//! never canaried or health-checked. [`FailoverEngine::status`] reports
+//! each engine's canary state for health output, and synthesized audio names
+//! the engine that spoke in [`AudioData::engine`].
+
+use crate::types::*;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use zoey_core::Result;
+
+/// Text synthesized by the canary
+pub const DEFAULT_CANARY_TEXT: &str = "ok";
+
+/// Time between canary rounds by default
+pub const DEFAULT_CANARY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Time a failed engine is tried last by default
+pub const DEFAULT_FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Canary warm-up settings
+#[derive(Debug, Clone)]
+pub struct CanaryConfig {
+    /// Text synthesized and discarded
+    pub text: String,
+    /// Minimum time between canaries on one engine
+    pub interval: Duration,
+    /// Voice settings used for the canary
+    pub voice: VoiceConfig,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            text: DEFAULT_CANARY_TEXT.to_string(),
+            interval: DEFAULT_CANARY_INTERVAL,
+            voice: VoiceConfig::default(),
+        }
+    }
+}
+
+/// Canary state of one engine in the chain
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryStatus {
+    /// Engine name
+    pub engine: String,
+    /// Whether this is the primary engine
+    pub primary: bool,
+    /// Never canaried
+    pub expensive: bool,
+    /// Last canary or request succeeded
+    pub warm: bool,
+    /// Latency of the last canary (ms)
+    pub latency_ms: Option<u64>,
+    /// Error of the last failed canary or request
+    pub error: Option<String>,
+    /// When the last canary started
+    #[serde(skip)]
+    pub checked_at: Option<Instant>,
+    /// Tried after the other engines until then, following a failure
+    #[serde(skip)]
+    pub cooling_until: Option<Instant>,
+}
+
+impl CanaryStatus {
+    fn new(engine: &str, primary: bool, expensive: bool) -> Self {
+        Self {
+            engine: engine.to_string(),
+            primary,
+            expensive,
+            warm: false,
+            latency_ms: None,
+            error: None,
+            checked_at: None,
+            cooling_until: None,
+        }
+    }
+}
+
+struct ChainEntry {
+    engine: Box<dyn VoiceEngine>,
+    expensive: bool,
+}
+
+struct Chain {
+    entries: Vec<ChainEntry>,
+    status: Mutex<Vec<CanaryStatus>>,
+    config: CanaryConfig,
+    cooldown: Duration,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Chain {
+    fn record(&self, index: usize, outcome: std::result::Result<(), String>) {
+        let mut status = self.status.lock().unwrap();
+        status[index].cooling_until = outcome.is_err().then(|| Instant::now() + self.cooldown);
+        status[index].warm = outcome.is_ok();
+        status[index].error = outcome.err();
+    }
+
+    /// Reserve a canary slot for `index` unless one ran within the interval
+    fn claim(&self, index: usize, now: Instant) -> bool {
+        let mut status = self.status.lock().unwrap();
+        let entry = &mut status[index];
+        if entry.expensive
+            || entry
+                .checked_at
+                .is_some_and(|at| now.duration_since(at) < self.config.interval)
+        {
+            return false;
+        }
+        entry.checked_at = Some(now);
+        true
+    }
+
+    async fn warm_up(&self) -> usize {
+        let mut ran = 0;
+        for index in 1..self.entries.len() {
+            let started = Instant::now();
+            if !self.claim(index, started) {
+                continue;
+            }
+            ran += 1;
+            let engine = &self.entries[index].engine;
+            let result = engine
+                .synthesize(&self.config.text, &self.config.voice)
+                .await;
+            let latency = started.elapsed();
+            match &result {
+                Ok(_) => tracing::debug!(engine = engine.name(), ?latency, "Canary synthesis ok"),
+                Err(e) => {
+                    tracing::warn!(engine = engine.name(), error = %e, "Canary synthesis failed")
+                }
+            }
+            self.status.lock().unwrap()[index].latency_ms = Some(latency.as_millis() as u64);
+            self.record(index, result.map(|_| ()).map_err(|e| e.to_string()));
+        }
+        ran
+    }
+
+    /// Health-check the engines at `indices`, cooling down those that fail
+    ///
+    /// Returns how many were not flagged down; expensive engines are skipped
+    /// and count as up.
+    async fn check_health(&self, indices: impl IntoIterator<Item = usize>) -> usize {
+        let mut up = 0;
+        for index in indices {
+            let entry = &self.entries[index];
+            if entry.expensive || entry.engine.is_healthy().await {
+                up += 1;
+                continue;
+            }
+            tracing::warn!(
+                engine = entry.engine.name(),
+                "TTS engine failed its health check"
+            );
+            self.record(index, Err("health check failed".to_string()));
+        }
+        up
+    }
+
+    /// Primary first, then warm fallbacks, then cold ones, each in chain
+    /// order; engines cooling down after a failure go last, in the same order
+    fn order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let status = self.status.lock().unwrap();
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by_key(|&i| {
+            let cooling = status[i].cooling_until.is_some_and(|until| now < until);
+            (cooling, i > 0 && !status[i].warm)
+        });
+        order
+    }
+}
+
+/// TTS engine that fails over along a chain of engines
+///
+/// Cloning is cheap; clones share the chain, its canary state and its
+/// canary task.
+#[derive(Clone)]
+pub struct FailoverEngine {
+    chain: Arc<Chain>,
+}
+
+impl FailoverEngine {
+    /// Chain starting at `primary`, with fallbacks added by [`Self::with_fallback`]
+    pub fn new(primary: Box<dyn VoiceEngine>, config: CanaryConfig) -> Self {
+        let status = vec![CanaryStatus::new(primary.name(), true, false)];
+        Self {
+            chain: Arc::new(Chain {
+                entries: vec![ChainEntry {
+                    engine: primary,
+                    expensive: false,
+                }],
+                status: Mutex::new(status),
+                config,
+                cooldown: DEFAULT_FAILOVER_COOLDOWN,
+                task: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Append a fallback; `expensive` engines are never canaried
+    ///
+    /// # Panics
+    /// When called after the engine has been cloned or its canaries started.
+    pub fn with_fallback(mut self, engine: Box<dyn VoiceEngine>, expensive: bool) -> Self {
+        let chain =
+            Arc::get_mut(&mut self.chain).expect("fallbacks are added before the chain is shared");
+        chain
+            .status
+            .get_mut()
+            .unwrap()
+            .push(CanaryStatus::new(engine.name(), false, expensive));
+        chain.entries.push(ChainEntry { engine, expensive });
+        self
+    }
+
+    /// Mark the primary as expensive, so it is never health-checked
+    ///
+    /// # Panics
+    /// When called after the engine has been cloned or its canaries started.
+    pub fn with_expensive_primary(mut self, expensive: bool) -> Self {
+        let chain = Arc::get_mut(&mut self.chain)
+            .expect("the primary is marked before the chain is shared");
+        chain.status.get_mut().unwrap()[0].expensive = expensive;
+        chain.entries[0].expensive = expensive;
+        self
+    }
+
+    /// Set how long an engine is tried last after it fails
+    ///
+    /// # Panics
+    /// When called after the engine has been cloned or its canaries started.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        Arc::get_mut(&mut self.chain)
+            .expect("the cooldown is set before the chain is shared")
+            .cooldown = cooldown;
+        self
+    }
+
+    /// Health-check every engine that is not expensive
+    ///
+    /// Engines that fail cool down. Returns how many engines were not
+    /// flagged down, counting the unchecked expensive ones.
+    pub async fn check_health(&self) -> usize {
+        self.chain.check_health(0..self.chain.entries.len()).await
+    }
+
+    /// Canary every fallback that has none within the interval
+    ///
+    /// Returns how many canaries ran.
+    pub async fn warm_up(&self) -> usize {
+        self.chain.warm_up().await
+    }
+
+    /// Health-check the primary and warm up, now and then every
+    /// [`CanaryConfig::interval`]
+    ///
+    /// Replaces a previously started canary task. The task stops on
+    /// [`Self::shutdown`] or once every handle to the chain is dropped.
+    pub fn start_canaries(&self) {
+        let chain = Arc::downgrade(&self.chain);
+        let interval = self.chain.config.interval;
+        let handle = tokio::spawn(run_canaries(chain, interval));
+        if let Some(previous) = self.chain.task.lock().unwrap().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Stop the canary task; a canary in flight is cancelled
+    pub fn shutdown(&self) {
+        if let Some(task) = self.chain.task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// Whether the canary task is running
+    pub fn canaries_running(&self) -> bool {
+        self.chain
+            .task
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+    }
+
+    /// Canary state of each engine, in chain order
+    pub fn status(&self) -> Vec<CanaryStatus> {
+        self.chain.status.lock().unwrap().clone()
+    }
+
+    /// Engine names in the order the next request will try them
+    pub fn failover_order(&self) -> Vec<String> {
+        self.chain
+            .order()
+            .into_iter()
+            .map(|i| self.chain.entries[i].engine.name().to_string())
+            .collect()
+    }
+}
+
+async fn run_canaries(chain: Weak<Chain>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let Some(chain) = chain.upgrade() else {
+            return;
+        };
+        chain.check_health([0]).await;
+        chain.warm_up().await;
+    }
+}
+
+#[async_trait]
+impl VoiceEngine for FailoverEngine {
+    fn name(&self) -> &str {
+        self.chain.entries[0].engine.name()
+    }
+
+    async fn synthesize(&self, text: &str, config: &VoiceConfig) -> Result<AudioData> {
+        let mut last_error = None;
+        for index in self.chain.order() {
+            let engine = &self.chain.entries[index].engine;
+            match engine.synthesize(text, config).await {
+                Ok(mut audio) => {
+                    self.chain.record(index, Ok(()));
+                    audio
+                        .engine
+                        .get_or_insert_with(|| engine.name().to_string());
+                    return Ok(audio);
+                }
+                Err(e) => {
+                    tracing::warn!(engine = engine.name(), error = %e, "TTS engine failed, trying next");
+                    self.chain.record(index, Err(e.to_string()));
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("chain has a primary engine"))
+    }
+
+    async fn synthesize_stream(&self, text: &str, config: &VoiceConfig) -> Result<AudioStream> {
+        let mut last_error = None;
+        for index in self.chain.order() {
+            let engine = &self.chain.entries[index].engine;
+            match engine.synthesize_stream(text, config).await {
+                Ok(stream) => {
+                    self.chain.record(index, Ok(()));
+                    return Ok(stream);
+                }
+                Err(e) => {
+                    tracing::warn!(engine = engine.name(), error = %e, "TTS engine failed, trying next");
+                    self.chain.record(index, Err(e.to_string()));
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("chain has a primary engine"))
+    }
+
+    async fn available_voices(&self) -> Result<Vec<Voice>> {
+        self.chain.entries[0].engine.available_voices().await
+    }
+
+    async fn is_ready(&self) -> bool {
+        for entry in &self.chain.entries {
+            if entry.engine.is_ready().await {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Healthy while any engine in the chain is
+    async fn is_healthy(&self) -> bool {
+        self.check_health().await > 0
+    }
+
+    fn supported_formats(&self) -> Vec<AudioFormat> {
+        self.chain.entries[0].engine.supported_formats()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.chain.entries[0].engine.supports_streaming()
+    }
+
+    /// The smallest limit in the chain, so any engine can take the text
+    fn max_text_length(&self) -> usize {
+        self.chain
+            .entries
+            .iter()
+            .map(|entry| entry.engine.max_text_length())
+            .min()
+            .unwrap_or(4096)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDeterministicEngine;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Mock engine counting calls, optionally failing or hanging
+    struct Standby {
+        name: &'static str,
+        calls: Arc<AtomicUsize>,
+        fail: Arc<AtomicBool>,
+        hang: bool,
+    }
+
+    impl Standby {
+        fn new(name: &'static str) -> (Self, Arc<AtomicUsize>, Arc<AtomicBool>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let fail = Arc::new(AtomicBool::new(false));
+            let engine = Self {
+                name,
+                calls: Arc::clone(&calls),
+                fail: Arc::clone(&fail),
+                hang: false,
+            };
+            (engine, calls, fail)
+        }
+    }
+
+    #[async_trait]
+    impl VoiceEngine for Standby {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn synthesize(&self, text: &str, config: &VoiceConfig) -> Result<AudioData> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(VoiceError::NotReady(self.name.to_string()).into());
+            }
+            MockDeterministicEngine::new(16000)
+                .synthesize(text, config)
+                .await
+        }
+
+        async fn synthesize_stream(
+            &self,
+            _text: &str,
+            _config: &VoiceConfig,
+        ) -> Result<AudioStream> {
+            Err(VoiceError::Other("not streaming".to_string()).into())
+        }
+
+        async fn available_voices(&self) -> Result<Vec<Voice>> {
+            Ok(Vec::new())
+        }
+
+        async fn is_ready(&self) -> bool {
+            !self.fail.load(Ordering::SeqCst)
+        }
+    }
+
+    fn config(interval: Duration) -> CanaryConfig {
+        CanaryConfig {
+            interval,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_once_per_interval_and_skips_expensive() {
+        let (primary, primary_calls, _) = Standby::new("primary");
+        let (piper, piper_calls, _) = Standby::new("piper");
+        let (eleven, eleven_calls, _) = Standby::new("elevenlabs");
+        let chain = FailoverEngine::new(Box::new(primary), config(Duration::from_secs(60)))
+            .with_fallback(Box::new(piper), false)
+            .with_fallback(Box::new(eleven), true);
+
+        assert_eq!(chain.warm_up().await, 1);
+        assert_eq!(chain.warm_up().await, 0);
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(eleven_calls.load(Ordering::SeqCst), 0);
+
+        let status = chain.status();
+        assert!(status[1].warm);
+        assert!(status[1].latency_ms.is_some());
+        assert!(status[2].expensive && !status[2].warm);
+        assert!(status[2].checked_at.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_canaries_repeat_each_interval() {
+        let (piper, piper_calls, _) = Standby::new("piper");
+        let chain = FailoverEngine::new(
+            Box::new(MockDeterministicEngine::new(16000)),
+            config(Duration::from_secs(60)),
+        )
+        .with_fallback(Box::new(piper), false);
+        chain.start_canaries();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 1);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 2);
+        chain.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_failover_prefers_warm_fallback() {
+        let (primary, _, primary_fail) = Standby::new("primary");
+        let (cold, cold_calls, cold_fail) = Standby::new("cold");
+        let (warm, warm_calls, _) = Standby::new("warm");
+        let chain = FailoverEngine::new(Box::new(primary), config(Duration::from_secs(60)))
+            .with_fallback(Box::new(cold), false)
+            .with_fallback(Box::new(warm), false);
+
+        cold_fail.store(true, Ordering::SeqCst);
+        chain.warm_up().await;
+        cold_fail.store(false, Ordering::SeqCst);
+        assert_eq!(chain.failover_order(), ["primary", "warm", "cold"]);
+
+        primary_fail.store(true, Ordering::SeqCst);
+        let audio = chain
+            .synthesize("hi", &VoiceConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(audio.character_count, 2);
+        assert_eq!(warm_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cold_calls.load(Ordering::SeqCst), 1);
+        assert!(!chain.status()[0].warm);
+        assert!(chain.status()[0].error.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_engine_cools_down_before_retry() {
+        let (piper, piper_calls, piper_fail) = Standby::new("piper");
+        let (openai, openai_calls, _) = Standby::new("openai");
+        let chain = FailoverEngine::new(Box::new(piper), config(Duration::from_secs(300)))
+            .with_fallback(Box::new(openai), true)
+            .with_cooldown(Duration::from_secs(30));
+        let voice = VoiceConfig::default();
+
+        piper_fail.store(true, Ordering::SeqCst);
+        let audio = chain.synthesize("hi", &voice).await.unwrap();
+        assert_eq!(audio.engine.as_deref(), Some("openai"));
+        assert_eq!(chain.failover_order(), ["openai", "piper"]);
+
+        // Back up, but still cooling down: not tried
+        piper_fail.store(false, Ordering::SeqCst);
+        tokio::time::advance(Duration::from_secs(29)).await;
+        let audio = chain.synthesize("hi", &voice).await.unwrap();
+        assert_eq!(audio.engine.as_deref(), Some("openai"));
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(openai_calls.load(Ordering::SeqCst), 2);
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(chain.failover_order(), ["piper", "openai"]);
+        let audio = chain.synthesize("hi", &voice).await.unwrap();
+        assert_eq!(audio.engine.as_deref(), Some("piper"));
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 2);
+        assert!(chain.status()[0].cooling_until.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_health_check_moves_primary_down_and_all_cooling_still_tried() {
+        let (piper, _, piper_fail) = Standby::new("piper");
+        let (local, _, local_fail) = Standby::new("local");
+        let (openai, openai_calls, openai_fail) = Standby::new("openai");
+        let chain = FailoverEngine::new(Box::new(piper), config(Duration::from_secs(300)))
+            .with_fallback(Box::new(local), false)
+            .with_fallback(Box::new(openai), true);
+
+        piper_fail.store(true, Ordering::SeqCst);
+        assert_eq!(chain.check_health().await, 2);
+        assert!(chain.is_healthy().await);
+        assert_eq!(openai_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(chain.failover_order(), ["local", "openai", "piper"]);
+        assert_eq!(
+            chain.status()[0].error.as_deref(),
+            Some("health check failed")
+        );
+
+        // Every engine failing: all are tried, the primary first among them
+        local_fail.store(true, Ordering::SeqCst);
+        openai_fail.store(true, Ordering::SeqCst);
+        assert!(chain
+            .synthesize("hi", &VoiceConfig::default())
+            .await
+            .is_err());
+        assert_eq!(chain.failover_order(), ["piper", "local", "openai"]);
+        piper_fail.store(false, Ordering::SeqCst);
+        let audio = chain
+            .synthesize("hi", &VoiceConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(audio.engine.as_deref(), Some("piper"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expensive_primary_is_not_health_checked() {
+        let (openai, openai_calls, _) = Standby::new("openai");
+        let (piper, piper_calls, _) = Standby::new("piper");
+        let chain = FailoverEngine::new(Box::new(openai), config(Duration::from_secs(60)))
+            .with_expensive_primary(true)
+            .with_fallback(Box::new(piper), false);
+
+        assert_eq!(chain.check_health().await, 2);
+        chain.start_canaries();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        chain.shutdown();
+        assert_eq!(openai_calls.load(Ordering::SeqCst), 0);
+        // The default health check synthesizes once, then the canary
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 2);
+        assert!(chain.status()[0].expensive);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_cancels_canary_in_flight() {
+        let (mut piper, piper_calls, _) = Standby::new("piper");
+        piper.hang = true;
+        let chain = FailoverEngine::new(
+            Box::new(MockDeterministicEngine::new(16000)),
+            config(Duration::from_secs(60)),
+        )
+        .with_fallback(Box::new(piper), false);
+        chain.start_canaries();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 1);
+        assert!(chain.canaries_running());
+
+        chain.shutdown();
+        tokio::task::yield_now().await;
+        assert!(!chain.canaries_running());
+        tokio::time::sleep(Duration::from_secs(300)).await;
+        assert_eq!(piper_calls.load(Ordering::SeqCst), 1);
+    }
+}
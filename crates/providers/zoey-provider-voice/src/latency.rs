@@ -0,0 +1,479 @@
+//! End-to-end latency tracing for voice conversation turns
+//!
+//! A voice turn crosses three components: the adapter's capture and STT,
+//! the agent API's LLM stream and the TTS engine. A [`VoiceTurnTrace`] is
+//! started when speech capture ends and collects a timestamp at each
+//! [`TurnMark`]; [`VoiceTurnTrace::report`] turns those into stage durations.
+//!
+//! [`LatencyTracker`] owns in-flight traces so components only pass a
+//! [`TurnId`] around, logs one structured line per finished turn, optionally
+//! posts the report to a telemetry endpoint and keeps the last N reports for
+//! [`LatencyTracker::latency_summary`].
+//!
+//! Stages a turn never reaches (no TTS, a failed LLM call) are left empty
+//! rather than failing the report.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Reports kept for [`LatencyTracker::latency_summary`] by default
+pub const DEFAULT_LATENCY_HISTORY: usize = 100;
+
+/// In-flight traces older than this are dropped as abandoned
+const ABANDONED_TRACE_AGE: Duration = Duration::from_secs(300);
+
+/// Identifier passed between components instead of the trace itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct TurnId(u64);
+
+impl TurnId {
+    /// Numeric value, e.g. for request metadata
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for TurnId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Point in a turn after speech capture ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnMark {
+    /// Audio handed to the STT engine
+    SttStart,
+    /// Transcript returned
+    SttEnd,
+    /// Chat request sent to the agent
+    LlmStart,
+    /// First streamed response token received
+    LlmFirstToken,
+    /// Response complete
+    LlmEnd,
+    /// Response text handed to the TTS engine
+    TtsStart,
+    /// First synthesized audio received
+    TtsFirstByte,
+    /// Audio playback started
+    PlaybackStart,
+}
+
+/// Measured stage of a turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnStage {
+    /// Capture end to STT start (queueing, wake-word gating)
+    CaptureToStt,
+    /// STT processing
+    Stt,
+    /// Chat request to first streamed token
+    LlmFirstToken,
+    /// Chat request to complete response
+    LlmTotal,
+    /// TTS request to first audio
+    TtsFirstByte,
+    /// Capture end to playback start (what the user perceives)
+    PlaybackStart,
+}
+
+impl TurnStage {
+    /// Every stage, in turn order
+    pub const ALL: [TurnStage; 6] = [
+        TurnStage::CaptureToStt,
+        TurnStage::Stt,
+        TurnStage::LlmFirstToken,
+        TurnStage::LlmTotal,
+        TurnStage::TtsFirstByte,
+        TurnStage::PlaybackStart,
+    ];
+
+    /// Stage name used in logs and reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::CaptureToStt => "capture_to_stt",
+            Self::Stt => "stt",
+            Self::LlmFirstToken => "llm_first_token",
+            Self::LlmTotal => "llm_total",
+            Self::TtsFirstByte => "tts_first_byte",
+            Self::PlaybackStart => "playback_start",
+        }
+    }
+
+    /// Marks bounding the stage; `None` as start means capture end
+    fn bounds(self) -> (Option<TurnMark>, TurnMark) {
+        match self {
+            Self::CaptureToStt => (None, TurnMark::SttStart),
+            Self::Stt => (Some(TurnMark::SttStart), TurnMark::SttEnd),
+            Self::LlmFirstToken => (Some(TurnMark::LlmStart), TurnMark::LlmFirstToken),
+            Self::LlmTotal => (Some(TurnMark::LlmStart), TurnMark::LlmEnd),
+            Self::TtsFirstByte => (Some(TurnMark::TtsStart), TurnMark::TtsFirstByte),
+            Self::PlaybackStart => (None, TurnMark::PlaybackStart),
+        }
+    }
+}
+
+/// Timestamps of one voice turn
+///
+/// All timestamps are taken by the caller, so tests can drive the trace with
+/// instants offset from a fixed base instead of the wall clock.
+#[derive(Debug, Clone)]
+pub struct VoiceTurnTrace {
+    id: TurnId,
+    capture_end: Instant,
+    marks: HashMap<TurnMark, Instant>,
+}
+
+impl VoiceTurnTrace {
+    /// Start a trace at the moment speech capture ended
+    pub fn new(id: TurnId, capture_end: Instant) -> Self {
+        Self {
+            id,
+            capture_end,
+            marks: HashMap::new(),
+        }
+    }
+
+    /// Turn identifier
+    pub fn id(&self) -> TurnId {
+        self.id
+    }
+
+    /// Record `mark` at `at`; the first timestamp for a mark wins
+    pub fn mark_at(&mut self, mark: TurnMark, at: Instant) {
+        self.marks.entry(mark).or_insert(at);
+    }
+
+    /// Duration of `stage`, `None` when either bound was never marked
+    pub fn stage(&self, stage: TurnStage) -> Option<Duration> {
+        let (start, end) = stage.bounds();
+        let start = match start {
+            Some(mark) => *self.marks.get(&mark)?,
+            None => self.capture_end,
+        };
+        Some(self.marks.get(&end)?.saturating_duration_since(start))
+    }
+
+    /// Stage durations measured so far
+    pub fn report(&self) -> TurnReport {
+        TurnReport {
+            turn_id: self.id,
+            stages: TurnStage::ALL
+                .iter()
+                .filter_map(|&s| self.stage(s).map(|d| (s, d.as_millis() as u64)))
+                .collect(),
+        }
+    }
+}
+
+/// Stage durations of a finished turn, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurnReport {
+    /// Turn identifier
+    pub turn_id: TurnId,
+    /// Measured stages in turn order; unreached stages are absent
+    pub stages: Vec<(TurnStage, u64)>,
+}
+
+impl TurnReport {
+    /// Milliseconds spent in `stage`, if measured
+    pub fn get(&self, stage: TurnStage) -> Option<u64> {
+        self.stages.iter().find(|(s, _)| *s == stage).map(|(_, ms)| *ms)
+    }
+
+    /// Whether the turn reached playback
+    pub fn is_complete(&self) -> bool {
+        self.get(TurnStage::PlaybackStart).is_some()
+    }
+
+    /// Report as a JSON object keyed by stage name (`<stage>_ms`)
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut obj = serde_json::Map::new();
+        obj.insert("turnId".to_string(), self.turn_id.get().into());
+        obj.insert("complete".to_string(), self.is_complete().into());
+        for (stage, ms) in &self.stages {
+            obj.insert(format!("{}_ms", stage.as_str()), (*ms).into());
+        }
+        serde_json::Value::Object(obj)
+    }
+}
+
+/// Percentiles of one stage over recent turns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageSummary {
+    /// Stage summarized
+    pub stage: TurnStage,
+    /// Turns that measured this stage
+    pub count: usize,
+    /// Median, milliseconds
+    pub p50_ms: u64,
+    /// 90th percentile, milliseconds
+    pub p90_ms: u64,
+    /// 99th percentile, milliseconds
+    pub p99_ms: u64,
+}
+
+/// Latency percentiles over the last N turns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencySummary {
+    /// Turns in the window
+    pub turns: usize,
+    /// Turns that reached playback
+    pub complete_turns: usize,
+    /// Per-stage percentiles, stages without samples omitted
+    pub stages: Vec<StageSummary>,
+}
+
+/// Nearest-rank percentile of ascending `sorted` values
+fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Summarize `reports` per stage
+pub fn summarize<'a>(reports: impl IntoIterator<Item = &'a TurnReport>) -> LatencySummary {
+    let mut turns = 0;
+    let mut complete_turns = 0;
+    let mut samples: HashMap<TurnStage, Vec<u64>> = HashMap::new();
+    for report in reports {
+        turns += 1;
+        if report.is_complete() {
+            complete_turns += 1;
+        }
+        for (stage, ms) in &report.stages {
+            samples.entry(*stage).or_default().push(*ms);
+        }
+    }
+    let stages = TurnStage::ALL
+        .iter()
+        .filter_map(|stage| {
+            let mut values = samples.remove(stage)?;
+            values.sort_unstable();
+            Some(StageSummary {
+                stage: *stage,
+                count: values.len(),
+                p50_ms: percentile(&values, 50.0),
+                p90_ms: percentile(&values, 90.0),
+                p99_ms: percentile(&values, 99.0),
+            })
+        })
+        .collect();
+    LatencySummary {
+        turns,
+        complete_turns,
+        stages,
+    }
+}
+
+#[derive(Default)]
+struct TrackerState {
+    in_flight: HashMap<TurnId, VoiceTurnTrace>,
+    history: VecDeque<TurnReport>,
+}
+
+/// Owner of in-flight voice turn traces and recent reports
+pub struct LatencyTracker {
+    next_id: AtomicU64,
+    history_len: usize,
+    telemetry_endpoint: Option<String>,
+    client: reqwest::Client,
+    state: Mutex<TrackerState>,
+}
+
+impl LatencyTracker {
+    /// Tracker keeping the last `history_len` reports
+    pub fn new(history_len: usize) -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            history_len: history_len.max(1),
+            telemetry_endpoint: None,
+            client: reqwest::Client::new(),
+            state: Mutex::new(TrackerState::default()),
+        }
+    }
+
+    /// Also POST each finished report as JSON to `endpoint`
+    pub fn with_telemetry_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.telemetry_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Start a turn whose speech capture ended now
+    pub fn begin(&self) -> TurnId {
+        self.begin_at(Instant::now())
+    }
+
+    /// Start a turn whose speech capture ended at `capture_end`
+    pub fn begin_at(&self, capture_end: Instant) -> TurnId {
+        let id = TurnId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut state = self.state.lock().unwrap();
+        state
+            .in_flight
+            .retain(|_, t| capture_end.saturating_duration_since(t.capture_end) < ABANDONED_TRACE_AGE);
+        state.in_flight.insert(id, VoiceTurnTrace::new(id, capture_end));
+        id
+    }
+
+    /// Record `mark` for turn `id` now
+    pub fn mark(&self, id: TurnId, mark: TurnMark) {
+        self.mark_at(id, mark, Instant::now());
+    }
+
+    /// Record `mark` for turn `id` at `at`; unknown turns are ignored
+    pub fn mark_at(&self, id: TurnId, mark: TurnMark, at: Instant) {
+        if let Some(trace) = self.state.lock().unwrap().in_flight.get_mut(&id) {
+            trace.mark_at(mark, at);
+        }
+    }
+
+    /// Drop turn `id` without reporting (e.g. the utterance was not addressed to the bot)
+    pub fn discard(&self, id: TurnId) {
+        self.state.lock().unwrap().in_flight.remove(&id);
+    }
+
+    /// Finish turn `id`: log, record and post its report
+    pub fn finish(&self, id: TurnId) -> Option<TurnReport> {
+        let report = {
+            let mut state = self.state.lock().unwrap();
+            let report = state.in_flight.remove(&id)?.report();
+            if state.history.len() == self.history_len {
+                state.history.pop_front();
+            }
+            state.history.push_back(report.clone());
+            report
+        };
+
+        let ms = |stage| report.get(stage).map(|v| v as i64).unwrap_or(-1);
+        info!(
+            turn_id = %report.turn_id,
+            capture_to_stt_ms = ms(TurnStage::CaptureToStt),
+            stt_ms = ms(TurnStage::Stt),
+            llm_first_token_ms = ms(TurnStage::LlmFirstToken),
+            llm_total_ms = ms(TurnStage::LlmTotal),
+            tts_first_byte_ms = ms(TurnStage::TtsFirstByte),
+            playback_start_ms = ms(TurnStage::PlaybackStart),
+            complete = report.is_complete(),
+            "Voice turn latency"
+        );
+
+        if let Some(endpoint) = &self.telemetry_endpoint {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                let request = self.client.post(endpoint).json(&report.to_json());
+                handle.spawn(async move {
+                    if let Err(e) = request.send().await {
+                        debug!(error = %e, "Failed to post voice turn latency");
+                    }
+                });
+            }
+        }
+        Some(report)
+    }
+
+    /// Percentiles over the retained reports
+    pub fn latency_summary(&self) -> LatencySummary {
+        summarize(&self.state.lock().unwrap().history)
+    }
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_HISTORY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    #[test]
+    fn test_stage_arithmetic() {
+        let t0 = Instant::now();
+        let mut trace = VoiceTurnTrace::new(TurnId(1), t0);
+        trace.mark_at(TurnMark::SttStart, t0 + ms(20));
+        trace.mark_at(TurnMark::SttEnd, t0 + ms(320));
+        trace.mark_at(TurnMark::LlmStart, t0 + ms(330));
+        trace.mark_at(TurnMark::LlmFirstToken, t0 + ms(730));
+        trace.mark_at(TurnMark::LlmEnd, t0 + ms(1530));
+        trace.mark_at(TurnMark::TtsStart, t0 + ms(1540));
+        trace.mark_at(TurnMark::TtsFirstByte, t0 + ms(1690));
+        trace.mark_at(TurnMark::PlaybackStart, t0 + ms(1900));
+        // Later duplicate marks are ignored
+        trace.mark_at(TurnMark::SttStart, t0 + ms(999));
+
+        let report = trace.report();
+        assert!(report.is_complete());
+        assert_eq!(report.get(TurnStage::CaptureToStt), Some(20));
+        assert_eq!(report.get(TurnStage::Stt), Some(300));
+        assert_eq!(report.get(TurnStage::LlmFirstToken), Some(400));
+        assert_eq!(report.get(TurnStage::LlmTotal), Some(1200));
+        assert_eq!(report.get(TurnStage::TtsFirstByte), Some(150));
+        assert_eq!(report.get(TurnStage::PlaybackStart), Some(1900));
+    }
+
+    #[test]
+    fn test_missing_stage_yields_partial_report() {
+        let tracker = LatencyTracker::new(10);
+        let t0 = Instant::now();
+        let id = tracker.begin_at(t0);
+        tracker.mark_at(id, TurnMark::SttStart, t0 + ms(10));
+        tracker.mark_at(id, TurnMark::SttEnd, t0 + ms(110));
+        tracker.mark_at(id, TurnMark::LlmStart, t0 + ms(120));
+        tracker.mark_at(id, TurnMark::LlmFirstToken, t0 + ms(220));
+        tracker.mark_at(id, TurnMark::LlmEnd, t0 + ms(520));
+
+        // TTS disabled: no TTS or playback marks
+        let report = tracker.finish(id).unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.get(TurnStage::LlmTotal), Some(400));
+        assert_eq!(report.get(TurnStage::TtsFirstByte), None);
+        assert_eq!(report.stages.len(), 4);
+        let json = report.to_json();
+        assert_eq!(json["stt_ms"], 100);
+        assert!(json.get("tts_first_byte_ms").is_none());
+
+        // Finished and discarded turns are gone
+        assert!(tracker.finish(id).is_none());
+        let other = tracker.begin_at(t0);
+        tracker.discard(other);
+        assert!(tracker.finish(other).is_none());
+        assert_eq!(tracker.latency_summary().turns, 1);
+    }
+
+    #[test]
+    fn test_latency_summary_percentiles() {
+        let tracker = LatencyTracker::new(10);
+        let t0 = Instant::now();
+        // 12 turns with 100..=1200ms to playback; only the last 10 are kept
+        for i in 1..=12u64 {
+            let id = tracker.begin_at(t0);
+            tracker.mark_at(id, TurnMark::PlaybackStart, t0 + ms(i * 100));
+            tracker.finish(id);
+        }
+        let summary = tracker.latency_summary();
+        assert_eq!(summary.turns, 10);
+        assert_eq!(summary.complete_turns, 10);
+        assert_eq!(summary.stages.len(), 1);
+        let playback = &summary.stages[0];
+        assert_eq!(playback.stage, TurnStage::PlaybackStart);
+        assert_eq!(playback.count, 10);
+        assert_eq!(playback.p50_ms, 700);
+        assert_eq!(playback.p90_ms, 1100);
+        assert_eq!(playback.p99_ms, 1200);
+
+        assert_eq!(percentile(&[], 50.0), 0);
+        assert_eq!(percentile(&[42], 99.0), 42);
+    }
+}
@@ -0,0 +1,325 @@
+//! Guard against synthesizing runaway text
+//!
+//! A prompt-injected or runaway reply of tens of thousands of characters
+//! would otherwise be sent to a paid TTS API and come back as a
+//! forty-minute recording. Text longer than `VoiceConfig::max_tts_chars` is
+//! handled by `VoiceConfig::length_policy` before any engine sees it:
+//!
+//! - [`LengthPolicy::Reject`] fails with [`VoiceError::TextTooLong`]
+//! - [`LengthPolicy::TruncateWithNotice`] cuts at the last sentence end that
+//!   fits and appends [`TRUNCATION_NOTICE`], so listeners know text is missing
+//! - [`LengthPolicy::SummarizeCallback`] asks a caller-provided callback
+//!   (e.g. an LLM) for a shorter text, truncating instead when it fails,
+//!   times out or returns nothing usable
+//!
+//! Lengths are counted in characters. Whatever was applied is logged as a
+//! warning and reported as [`LengthLimited`] on the synthesis result.
+
+use crate::types::{VoiceConfig, VoiceError};
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default maximum characters sent to a TTS engine per request
+pub const DEFAULT_MAX_TTS_CHARS: usize = 5000;
+
+/// Spoken after truncated text
+pub const TRUNCATION_NOTICE: &str = "… message truncated.";
+
+/// Default time the summarize callback gets before falling back to truncation
+pub const DEFAULT_SUMMARIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Callback shortening `text` to at most `max_chars` characters, `None` on failure
+pub type SummarizeFn =
+    Arc<dyn Fn(String, usize) -> BoxFuture<'static, Option<String>> + Send + Sync>;
+
+/// What to do with text longer than `VoiceConfig::max_tts_chars`
+#[derive(Clone, Default)]
+pub enum LengthPolicy {
+    /// Fail with [`VoiceError::TextTooLong`]
+    #[default]
+    Reject,
+    /// Cut at a sentence boundary and append [`TRUNCATION_NOTICE`]
+    TruncateWithNotice,
+    /// Speak a shortened text from `summarize`, truncating if it fails or
+    /// takes longer than `timeout`
+    SummarizeCallback {
+        /// Produces the shortened text
+        summarize: SummarizeFn,
+        /// Time allowed for `summarize`
+        timeout: Duration,
+    },
+}
+
+impl LengthPolicy {
+    /// [`LengthPolicy::SummarizeCallback`] with [`DEFAULT_SUMMARIZE_TIMEOUT`]
+    pub fn summarize<F, Fut>(summarize: F) -> Self
+    where
+        F: Fn(String, usize) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<String>> + Send + 'static,
+    {
+        Self::SummarizeCallback {
+            summarize: Arc::new(move |text, max_chars| Box::pin(summarize(text, max_chars))),
+            timeout: DEFAULT_SUMMARIZE_TIMEOUT,
+        }
+    }
+}
+
+impl fmt::Debug for LengthPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reject => f.write_str("Reject"),
+            Self::TruncateWithNotice => f.write_str("TruncateWithNotice"),
+            Self::SummarizeCallback { timeout, .. } => f
+                .debug_struct("SummarizeCallback")
+                .field("timeout", timeout)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// How over-long text was shortened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAction {
+    /// Cut with [`TRUNCATION_NOTICE`]
+    Truncated,
+    /// Replaced by the summarize callback's text
+    Summarized,
+    /// The summarize callback failed or timed out; truncated instead
+    SummaryFallback,
+}
+
+/// Report of text shortened before synthesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LengthLimited {
+    /// What was done
+    pub action: LimitAction,
+    /// Characters in the text as given
+    pub original_chars: usize,
+    /// Characters actually synthesized
+    pub spoken_chars: usize,
+}
+
+/// `text` cut to at most `max_chars` characters, notice included
+///
+/// Prefers the last sentence end (`.`, `!`, `?`, `…` and their full-width
+/// forms) that fits, then the last whitespace, then a plain character cut.
+pub fn truncate_with_notice(text: &str, max_chars: usize) -> String {
+    let notice_chars = TRUNCATION_NOTICE.chars().count();
+    let budget = max_chars.saturating_sub(notice_chars + 1);
+    if budget == 0 {
+        return TRUNCATION_NOTICE.chars().take(max_chars).collect();
+    }
+    // Byte offset just past the `budget`th character
+    let limit = text
+        .char_indices()
+        .nth(budget)
+        .map_or(text.len(), |(i, _)| i);
+    let head = &text[..limit];
+    let next_is_space = |end: usize| text[end..].chars().next().is_none_or(char::is_whitespace);
+    let sentence_end = head
+        .char_indices()
+        .rev()
+        .find(|&(i, c)| {
+            matches!(c, '.' | '!' | '?' | '…' | '。' | '！' | '？')
+                && next_is_space(i + c.len_utf8())
+        })
+        .map(|(i, c)| i + c.len_utf8());
+    let word_end = || {
+        head.char_indices()
+            .rev()
+            .find(|&(_, c)| c.is_whitespace())
+            .map(|(i, _)| i)
+    };
+    let cut = sentence_end
+        .or_else(word_end)
+        .filter(|&end| !head[..end].trim().is_empty())
+        .unwrap_or(limit);
+    format!("{} {}", head[..cut].trim_end(), TRUNCATION_NOTICE)
+}
+
+/// Apply `config.length_policy` to text longer than `config.max_tts_chars`
+///
+/// Text within the limit is returned borrowed and unreported.
+pub async fn enforce<'a>(
+    text: &'a str,
+    config: &VoiceConfig,
+) -> Result<(Cow<'a, str>, Option<LengthLimited>), VoiceError> {
+    let max = config.max_tts_chars;
+    let original_chars = text.chars().count();
+    if original_chars <= max {
+        return Ok((Cow::Borrowed(text), None));
+    }
+    let (spoken, action) = match &config.length_policy {
+        LengthPolicy::Reject => {
+            tracing::warn!(chars = original_chars, max, "Rejected over-long TTS text");
+            return Err(VoiceError::TextTooLong {
+                length: original_chars,
+                max,
+            });
+        }
+        LengthPolicy::TruncateWithNotice => {
+            (truncate_with_notice(text, max), LimitAction::Truncated)
+        }
+        LengthPolicy::SummarizeCallback { summarize, timeout } => {
+            match tokio::time::timeout(*timeout, summarize(text.to_string(), max)).await {
+                Ok(Some(summary)) if !summary.trim().is_empty() => {
+                    let summary = summary.trim();
+                    if summary.chars().count() <= max {
+                        (summary.to_string(), LimitAction::Summarized)
+                    } else {
+                        (truncate_with_notice(summary, max), LimitAction::Summarized)
+                    }
+                }
+                Ok(_) => (
+                    truncate_with_notice(text, max),
+                    LimitAction::SummaryFallback,
+                ),
+                Err(_) => {
+                    tracing::warn!(timeout = ?timeout, "TTS summarize callback timed out");
+                    (
+                        truncate_with_notice(text, max),
+                        LimitAction::SummaryFallback,
+                    )
+                }
+            }
+        }
+    };
+    let report = LengthLimited {
+        action,
+        original_chars,
+        spoken_chars: spoken.chars().count(),
+    };
+    tracing::warn!(
+        action = ?report.action,
+        original_chars,
+        spoken_chars = report.spoken_chars,
+        max,
+        "Shortened over-long TTS text"
+    );
+    Ok((Cow::Owned(spoken), Some(report)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_tts_chars: usize, length_policy: LengthPolicy) -> VoiceConfig {
+        VoiceConfig {
+            max_tts_chars,
+            length_policy,
+            ..Default::default()
+        }
+    }
+
+    const TEXT: &str = "First sentence here. Second one follows! And a third that is long.";
+
+    #[tokio::test]
+    async fn test_text_under_the_limit_is_untouched() {
+        for policy in [
+            LengthPolicy::Reject,
+            LengthPolicy::TruncateWithNotice,
+            LengthPolicy::summarize(|_, _| async { panic!("not called") }),
+        ] {
+            let (text, report) = enforce(TEXT, &config(TEXT.len(), policy)).await.unwrap();
+            assert!(matches!(text, Cow::Borrowed(TEXT)));
+            assert_eq!(report, None);
+        }
+        assert_eq!(VoiceConfig::default().max_tts_chars, DEFAULT_MAX_TTS_CHARS);
+    }
+
+    #[tokio::test]
+    async fn test_reject_is_typed() {
+        let err = enforce(TEXT, &config(10, LengthPolicy::Reject))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            VoiceError::TextTooLong {
+                length: 66,
+                max: 10
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_truncate_cuts_at_sentence_end() {
+        let (text, report) = enforce(TEXT, &config(62, LengthPolicy::TruncateWithNotice))
+            .await
+            .unwrap();
+        assert_eq!(
+            text,
+            "First sentence here. Second one follows! … message truncated."
+        );
+        assert_eq!(
+            report,
+            Some(LengthLimited {
+                action: LimitAction::Truncated,
+                original_chars: 66,
+                spoken_chars: 61,
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncation_respects_multibyte_characters() {
+        let text = "Привет, как дела? Всё хорошо. Ещё один длинный кусок текста без конца";
+        let cut = truncate_with_notice(text, 50);
+        assert!(cut.chars().count() <= 50, "{}", cut);
+        assert_eq!(cut, "Привет, как дела? Всё хорошо. … message truncated.");
+
+        // No sentence end: last whole word; no spaces at all: character cut
+        let cut = truncate_with_notice("日本語のテキスト と もっと 長い テキスト", 31);
+        assert_eq!(cut, "日本語のテキスト … message truncated.");
+        let cut = truncate_with_notice(&"é".repeat(100), 30);
+        assert_eq!(cut, format!("{} {}", "é".repeat(9), TRUNCATION_NOTICE));
+        // A full-width sentence end counts
+        let cut = truncate_with_notice("今日は晴れ。 明日は雨になるでしょう、たぶん", 32);
+        assert_eq!(cut, "今日は晴れ。 … message truncated.");
+    }
+
+    #[tokio::test]
+    async fn test_summarize_callback() {
+        let policy = LengthPolicy::summarize(|text: String, max| async move {
+            assert!(text.len() > max);
+            Some("Three sentences about length.".to_string())
+        });
+        let (text, report) = enforce(TEXT, &config(40, policy)).await.unwrap();
+        assert_eq!(text, "Three sentences about length.");
+        assert_eq!(report.unwrap().action, LimitAction::Summarized);
+
+        // A failing callback falls back to truncation
+        let failing = LengthPolicy::summarize(|_, _| async { None });
+        let (text, report) = enforce(TEXT, &config(45, failing)).await.unwrap();
+        assert_eq!(text, "First sentence here. … message truncated.");
+        assert_eq!(report.unwrap().action, LimitAction::SummaryFallback);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_timeout_falls_back_to_truncation() {
+        let policy = LengthPolicy::SummarizeCallback {
+            summarize: Arc::new(|_, _| {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Some("too late".to_string())
+                })
+            }),
+            timeout: Duration::from_millis(20),
+        };
+        let (text, report) = enforce(TEXT, &config(45, policy)).await.unwrap();
+        assert_eq!(text, "First sentence here. … message truncated.");
+        assert_eq!(
+            report.unwrap(),
+            LengthLimited {
+                action: LimitAction::SummaryFallback,
+                original_chars: 66,
+                spoken_chars: 41,
+            }
+        );
+    }
+}
@@ -12,20 +12,95 @@
 //! - Whisper (default) - CPU/GPU via whisper.cpp, auto-downloads models
 //! - Unmute (premium) - GPU-accelerated real-time streaming
 //!
+//! STT input is normalized to each engine's [`SpeechEngine::expected_input`]
+//! (channels, sample rate, PCM/WAV) before transcription; see [`audio`].
+//!
+//! A wake-word stage ([`wakeword`], ONNX models with the `wakeword` feature)
+//! can gate STT so only speech following the character's name is transcribed.
+//!
+//! [`speaker`] enrolls users' voices and verifies who is speaking (ONNX
+//! speaker-embedding model with the `speaker-id` feature), so privileged
+//! voice commands can be limited to recognized speakers.
+//!
+//! [`latency`] traces a voice turn's STT, LLM and TTS stages end to end.
+//!
+//! Synthesized speech can be given a signature sound with a per-character
+//! chain of [`effects`] (filters, reverb, speed, robot).
+//!
+//! Mixed-language replies can switch voice per language segment; see
+//! [`multilingual`].
+//!
+//! Text over `VoiceConfig::max_tts_chars` is rejected, truncated or
+//! summarized before synthesis; see [`length_limit`].
+//!
+//! Replies longer than the engine accepts are synthesized in sentence-aligned
+//! chunks and joined into one clip; see [`long_form`].
+//!
+//! [`failover`] chains TTS engines and keeps the standby ones warm with
+//! periodic canary syntheses, so a failover does not hit a cold start.
+//!
+//! Repeated phrases are served from an LRU [`cache`] of synthesized audio
+//! instead of hitting the engine again.
+//!
+//! A character picks its engine and voice in its settings; see [`profile`].
+//!
+//! [`AudioData::transcode`] converts PCM/WAV output to Ogg/Opus for Telegram
+//! voice notes with a built-in CELT encoder; see [`transcode`].
+//!
+//! [`transition`] detects when a session's replies switch TTS engine so the
+//! change can be announced.
+//!
+//! [`VoicePlugin::synthesize_stream_chunks`] streams audio chunks from any
+//! engine, and [`streaming::playback`] plays them as they arrive; see
+//! [`streaming`].
+//!
+//! [`testing`] provides a deterministic mock engine and golden-file audio
+//! comparison for regression tests.
+//!
 //! Default voice: Female (shimmer for OpenAI, Rachel for ElevenLabs)
 
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+pub mod audio;
+pub mod cache;
+mod celt;
+pub mod effects;
 mod engines;
+pub mod failover;
+pub mod latency;
+pub mod length_limit;
+pub mod long_form;
+pub mod multilingual;
+pub mod profile;
+pub mod sink;
+pub mod speaker;
+pub mod streaming;
+pub mod testing;
+pub mod transcode;
+pub mod transition;
 mod types;
+pub mod wakeword;
 
+pub use cache::{AudioCache, AudioCacheConfig, CacheStats};
+pub use effects::{AudioEffect, EffectChain, EffectConfig};
 pub use engines::*;
+pub use failover::{CanaryConfig, CanaryStatus, FailoverEngine};
+pub use latency::{LatencySummary, LatencyTracker, TurnId, TurnMark, TurnReport, VoiceTurnTrace};
+pub use length_limit::{LengthLimited, LengthPolicy, LimitAction};
+pub use long_form::{LongAudio, LongSynthesisProgress, LongSynthesisSummary, WavStreamWriter};
+pub use multilingual::{LanguageSegment, MultilingualAudio, SegmentDecision};
+pub use profile::{EngineSpec, ProfileEngine, VoiceProfile};
+pub use sink::SinkFormat;
+pub use speaker::{SpeakerRegistry, VerificationResult};
+pub use streaming::{PcmReader, PlaybackLength, StreamPlayback};
+pub use transition::{EngineTransitions, TransitionNotice};
 pub use types::*;
 
 use async_trait::async_trait;
 use zoey_core::types::*;
 use zoey_core::Result;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Once;
@@ -115,8 +190,16 @@ pub struct VoicePlugin {
     stt_engine: Option<Arc<RwLock<Box<dyn SpeechEngine>>>>,
     /// TTS Configuration
     tts_config: VoiceConfig,
+    /// Effects built from `tts_config.effects`
+    effects: Arc<EffectChain>,
     /// STT Configuration
     stt_config: TranscriptionConfig,
+    /// Enrolled voices for speaker verification (optional)
+    speakers: Option<Arc<SpeakerRegistry>>,
+    /// Failover chain behind `tts_engine`, when built with one
+    failover: Option<FailoverEngine>,
+    /// Recently synthesized utterances
+    cache: AudioCache,
 }
 
 impl VoicePlugin {
@@ -126,8 +209,67 @@ impl VoicePlugin {
             tts_engine: Arc::new(RwLock::new(engine)),
             #[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
             stt_engine: None,
+            effects: Arc::new(EffectChain::new(&config.effects)),
             tts_config: config,
             stt_config: TranscriptionConfig::default(),
+            speakers: None,
+            failover: None,
+            cache: AudioCache::default(),
+        }
+    }
+
+    /// Create a voice plugin that fails over along `engines`, in order
+    ///
+    /// The first engine is the primary, e.g. `vec![piper, openai]`. Every
+    /// fallback is canaried; build the chain with [`FailoverEngine::with_fallback`]
+    /// and [`FailoverEngine::with_expensive_primary`] and use
+    /// [`Self::with_failover_chain`] to keep per-character billed engines out
+    /// of the canaries and health checks. [`AudioData::engine`] names the engine
+    /// that spoke.
+    ///
+    /// # Panics
+    /// When `engines` is empty.
+    pub fn with_failover(engines: Vec<Box<dyn VoiceEngine>>, config: VoiceConfig) -> Self {
+        let mut engines = engines.into_iter();
+        let primary = engines.next().expect("a failover chain needs at least one engine");
+        let chain = engines.fold(
+            FailoverEngine::new(primary, CanaryConfig::default()),
+            |chain, engine| chain.with_fallback(engine, false),
+        );
+        Self::with_failover_chain(chain, config)
+    }
+
+    /// Create a voice plugin serving TTS from a failover chain
+    ///
+    /// Starts the chain's canary warm-up and health checks (requires a Tokio
+    /// runtime); stop them with [`Self::shutdown`].
+    pub fn with_failover_chain(chain: FailoverEngine, config: VoiceConfig) -> Self {
+        chain.start_canaries();
+        let mut plugin = Self::new(Box::new(chain.clone()), config);
+        plugin.failover = Some(chain);
+        plugin
+    }
+
+    /// Create from the `voice` section of a character's settings
+    ///
+    /// See [`profile`] for the settings read; `voice.effects` is applied as
+    /// well. A profile with a `fallback_engine` builds a failover chain, which
+    /// requires a Tokio runtime.
+    pub fn from_character_settings(settings: &serde_json::Value) -> Result<Self> {
+        let profile = VoiceProfile::from_settings(settings)?;
+        let mut config = VoiceConfig {
+            effects: EffectConfig::from_character_settings(settings),
+            ..Default::default()
+        };
+        profile.apply(&mut config);
+        Ok(Self::from_profile(&profile, config))
+    }
+
+    fn from_profile(profile: &VoiceProfile, config: VoiceConfig) -> Self {
+        if profile.fallback.is_some() {
+            Self::with_failover(profile.engines(), config)
+        } else {
+            Self::new(profile.primary.build(), config)
         }
     }
 
@@ -142,8 +284,12 @@ impl VoicePlugin {
         Self {
             tts_engine: Arc::new(RwLock::new(tts_engine)),
             stt_engine: Some(Arc::new(RwLock::new(stt_engine))),
+            effects: Arc::new(EffectChain::new(&tts_config.effects)),
             tts_config,
             stt_config,
+            speakers: None,
+            failover: None,
+            cache: AudioCache::default(),
         }
     }
 
@@ -251,6 +397,7 @@ impl VoicePlugin {
         
         Self {
             tts_engine: Arc::new(RwLock::new(Box::new(tts_engine))),
+            effects: Arc::default(),
             stt_engine: Some(Arc::new(RwLock::new(Box::new(stt_engine)))),
             tts_config: VoiceConfig {
                 engine_type: VoiceEngineType::Local,
@@ -280,6 +427,7 @@ impl VoicePlugin {
         
         Self {
             tts_engine: Arc::new(RwLock::new(Box::new(tts_engine))),
+            effects: Arc::default(),
             stt_engine: Some(Arc::new(RwLock::new(Box::new(stt_engine)))),
             tts_config: VoiceConfig {
                 engine_type: VoiceEngineType::OpenAI,
@@ -462,6 +610,7 @@ impl VoicePlugin {
         
         Self {
             tts_engine: engine_arc,
+            effects: Arc::default(),
             stt_engine: Some(stt_engine),
             tts_config: VoiceConfig {
                 engine_type: VoiceEngineType::Local, // Unmute acts as local
@@ -558,6 +707,7 @@ impl VoicePlugin {
         
         Self {
             tts_engine: Arc::new(RwLock::new(Box::new(tts_engine))),
+            effects: Arc::default(),
             #[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
             stt_engine: Some(Arc::new(RwLock::new(Box::new(stt_engine)))),
             tts_config: VoiceConfig {
@@ -599,6 +749,7 @@ impl VoicePlugin {
         
         Self {
             tts_engine: Arc::new(RwLock::new(Box::new(tts_engine))),
+            effects: Arc::default(),
             #[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
             stt_engine: Some(Arc::new(RwLock::new(Box::new(stt_engine)))),
             tts_config: VoiceConfig {
@@ -629,6 +780,18 @@ impl VoicePlugin {
         self.stt_config.whisper_model = model;
     }
 
+    /// Add Whisper STT with a full transcription config, e.g. a pre-placed
+    /// `model_path` or `never_download` for air-gapped deployments
+    #[cfg(feature = "whisper")]
+    pub fn add_whisper_stt_config(&mut self, config: TranscriptionConfig) {
+        let stt_engine = engines::whisper::WhisperEngine::from_config(&config);
+        self.stt_engine = Some(Arc::new(RwLock::new(Box::new(stt_engine))));
+        self.stt_config = TranscriptionConfig {
+            engine_type: SpeechEngineType::Whisper,
+            ..config
+        };
+    }
+
     /// Add Unmute STT to an existing plugin
     #[cfg(feature = "unmute")]
     pub fn add_unmute_stt(&mut self, endpoint: &str) {
@@ -665,25 +828,272 @@ impl VoicePlugin {
     // =========================================================================
 
     /// Synthesize text to speech
+    ///
+    /// Unless streaming is enabled, repeated text is answered from the
+    /// [`cache`] without calling the engine.
     pub async fn synthesize(&self, text: &str) -> Result<AudioData> {
+        if self.tts_config.streaming {
+            return Ok(self.synthesize_multilingual(text).await?.audio);
+        }
+        let key = AudioCache::key(text, &self.tts_config);
+        if let Some(audio) = self.cache.get(key) {
+            return Ok(audio);
+        }
+        let audio = self.synthesize_multilingual(text).await?.audio;
+        self.cache.insert(key, &audio);
+        Ok(audio)
+    }
+
+    /// Synthesize text to speech, converted to `format` when one is given
+    ///
+    /// Lets callers ask for a format regardless of the engine's configured
+    /// one, e.g. Opus for a Telegram voice note from an engine producing WAV.
+    /// `None` is [`Self::synthesize`]; see [`AudioData::transcode`] for the
+    /// conversions available.
+    pub async fn synthesize_as(&self, text: &str, format: Option<AudioFormat>) -> Result<AudioData> {
+        let audio = self.synthesize(text).await?;
+        match format {
+            Some(format) => audio.transcode(format),
+            None => Ok(audio),
+        }
+    }
+
+    /// Synthesize text to speech, reporting the voice used per language segment
+    ///
+    /// With `language_voices` configured, mixed-language text is spoken
+    /// segment by segment in each language's voice; text in one language is
+    /// still a single request. Without them this is [`Self::synthesize`].
+    pub async fn synthesize_multilingual(&self, text: &str) -> Result<MultilingualAudio> {
+        let (text, length_limited) = length_limit::enforce(text, &self.tts_config).await?;
         let engine = self.tts_engine.read().await;
-        engine.synthesize(text, &self.tts_config).await
+        let segments = multilingual::plan(&self.tts_config, &text, &engine.supported_formats());
+        if segments.len() > 1 {
+            let mut result =
+                multilingual::synthesize_segments(engine.as_ref(), &segments, &self.tts_config).await?;
+            drop(engine);
+            tracing::debug!(segments = ?result.segments, "Synthesized language segments");
+            result.audio = apply_effects(&self.effects, result.audio)?;
+            result.length_limited = length_limited;
+            return Ok(result);
+        }
+        let config = multilingual::single_config(&self.tts_config, segments.first());
+        let audio = synthesize_with_effects(engine.as_ref(), &text, &config, &self.effects).await?;
+        Ok(MultilingualAudio {
+            segments: multilingual::single_decision(&self.tts_config, segments.first(), &audio),
+            audio,
+            length_limited,
+        })
+    }
+
+    /// Synthesize text to speech in the format `sink` requires
+    ///
+    /// The engine is asked for a format the sink accepts when it supports
+    /// one; otherwise its output is converted with [`sink::transcode`].
+    pub async fn synthesize_for(&self, text: &str, sink: SinkFormat) -> Result<AudioData> {
+        let (text, _) = length_limit::enforce(text, &self.tts_config).await?;
+        let engine = self.tts_engine.read().await;
+        let segments = multilingual::plan(&self.tts_config, &text, &engine.supported_formats());
+        if segments.len() > 1 {
+            let result =
+                multilingual::synthesize_segments(engine.as_ref(), &segments, &self.tts_config).await?;
+            drop(engine);
+            let audio = apply_effects(&self.effects, result.audio)?;
+            return sink::transcode(&audio, sink);
+        }
+        let mut config = multilingual::single_config(&self.tts_config, segments.first()).into_owned();
+        let mut supported = engine.supported_formats();
+        if !self.effects.is_empty() {
+            // Effects need PCM; transcode converts it for the sink afterwards
+            supported.retain(|f| matches!(f, AudioFormat::Pcm | AudioFormat::Wav));
+        }
+        if let Some(format) = sink.engine_format(&supported) {
+            config.output_format = format;
+        }
+        let audio = synthesize_attributed(engine.as_ref(), &text, &config).await?;
+        drop(engine);
+        let audio = apply_effects(&self.effects, audio)?;
+        let out = sink::transcode(&audio, sink)?;
+        tracing::debug!(sink = %sink, from = %audio.spec(), to = %out.spec(), "Synthesized for sink");
+        Ok(out)
     }
 
     /// Synthesize text to speech with streaming (low latency)
     pub async fn synthesize_stream(&self, text: &str) -> Result<AudioStream> {
+        let (text, _) = length_limit::enforce(text, &self.tts_config).await?;
+        let engine = self.tts_engine.read().await;
+        engine.synthesize_stream(&text, &self.tts_config).await
+    }
+
+    /// Synthesize text as a stream of audio chunks, whatever the engine
+    ///
+    /// When [`Self::supports_streaming`], chunks come from the engine while
+    /// it synthesizes, as PCM if it offers that. Otherwise the text is
+    /// [`Self::synthesize_long`]ed and the clip [`streaming::slice`]d into
+    /// `chunk_ms` chunks. Either way time to first chunk counts from this
+    /// call.
+    pub async fn synthesize_stream_chunks(&self, text: &str, chunk_ms: u32) -> Result<AudioStream> {
+        let started = std::time::Instant::now();
+        let engine = self.tts_engine.read().await;
+        if self.streams_with(engine.as_ref()) && text.chars().count() <= engine.max_text_length() {
+            let (text, _) = length_limit::enforce(text, &self.tts_config).await?;
+            let config = stream_config(engine.as_ref(), &self.tts_config);
+            let stream = engine.synthesize_stream(&text, &config).await?;
+            return Ok(stream.with_start(started));
+        }
+        drop(engine);
+        let audio = self.synthesize_long(text).await?.audio;
+        Ok(streaming::slice(&audio, chunk_ms).with_start(started))
+    }
+
+    /// Whether [`Self::synthesize_stream_chunks`] yields audio before synthesis is done
+    ///
+    /// Needs streaming enabled, an engine that streams natively and no voice
+    /// effects, which work on whole clips.
+    pub async fn supports_streaming(&self) -> bool {
+        self.streams_with(self.tts_engine.read().await.as_ref())
+    }
+
+    fn streams_with(&self, engine: &dyn VoiceEngine) -> bool {
+        self.tts_config.streaming && self.effects.is_empty() && engine.supports_streaming()
+    }
+
+    /// Synthesize text of any length as one clip
+    ///
+    /// Text within the engine's [`VoiceEngine::max_text_length`] is
+    /// [`Self::synthesize`]d as usual. Longer text is split at sentence ends
+    /// into chunks the engine accepts and the audio is joined; see
+    /// [`long_form`] for how each format is concatenated.
+    pub async fn synthesize_long(&self, text: &str) -> Result<LongAudio> {
+        let max_chars = self.tts_engine.read().await.max_text_length();
+        if text.chars().count() <= max_chars {
+            let audio = self.synthesize(text).await?;
+            return Ok(LongAudio {
+                audio,
+                chunks: 1,
+                byte_concatenated: false,
+                length_limited: None,
+            });
+        }
+        let (text, length_limited) = length_limit::enforce(text, &self.tts_config).await?;
+        let engine = self.tts_engine.read().await;
+        let config = effects_config(engine.as_ref(), &self.tts_config, &self.effects);
+        let mut long = long_form::synthesize_concatenated(engine.as_ref(), &config, &text).await?;
+        drop(engine);
+        tracing::debug!(
+            chunks = long.chunks,
+            byte_concatenated = long.byte_concatenated,
+            "Synthesized long text"
+        );
+        long.audio = apply_effects(&self.effects, long.audio)?;
+        long.length_limited = length_limited;
+        Ok(long)
+    }
+
+    /// Synthesize long text as WAV streamed to `writer`
+    ///
+    /// Text is synthesized chunk by chunk so peak memory stays around one
+    /// chunk of audio. The header keeps streaming (unknown-length) sizes;
+    /// use [`Self::synthesize_long_to_file`] for a patched header.
+    pub async fn synthesize_long_to_writer<W>(&self, text: &str, writer: W) -> Result<LongSynthesisSummary>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        self.synthesize_long_to_writer_with_progress(text, writer, |_| {}).await
+    }
+
+    /// [`Self::synthesize_long_to_writer`] calling `on_progress` after each chunk
+    pub async fn synthesize_long_to_writer_with_progress<W, F>(
+        &self,
+        text: &str,
+        writer: W,
+        on_progress: F,
+    ) -> Result<LongSynthesisSummary>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+        F: FnMut(LongSynthesisProgress),
+    {
+        let (text, length_limited) = length_limit::enforce(text, &self.tts_config).await?;
         let engine = self.tts_engine.read().await;
-        engine.synthesize_stream(text, &self.tts_config).await
+        let (wav, mut summary) =
+            long_form::synthesize_to_wav_stream(engine.as_ref(), &self.tts_config, &text, writer, on_progress)
+                .await?;
+        wav.finish().await?;
+        summary.length_limited = length_limited;
+        Ok(summary)
+    }
+
+    /// Synthesize long text into a WAV file with correct header sizes
+    pub async fn synthesize_long_to_file(
+        &self,
+        text: &str,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<LongSynthesisSummary> {
+        let (text, length_limited) = length_limit::enforce(text, &self.tts_config).await?;
+        let file = tokio::fs::File::create(path.as_ref()).await.map_err(|e| {
+            VoiceError::AudioError(format!("Failed to create {}: {}", path.as_ref().display(), e))
+        })?;
+        let engine = self.tts_engine.read().await;
+        let (wav, mut summary) =
+            long_form::synthesize_to_wav_stream(engine.as_ref(), &self.tts_config, &text, file, |_| {})
+                .await?;
+        wav.finish_patched().await?;
+        summary.length_limited = length_limited;
+        Ok(summary)
     }
 
     /// Set the voice
     pub fn set_voice(&mut self, voice: Voice) {
         self.tts_config.voice = voice;
+        self.cache.clear();
     }
 
     /// Set the speaking speed (0.25 to 4.0, default 1.0)
     pub fn set_speed(&mut self, speed: f32) {
         self.tts_config.speed = speed.clamp(0.25, 4.0);
+        self.cache.clear();
+    }
+
+    /// Replace the effects applied to synthesized audio
+    pub fn set_effects(&mut self, effects: Vec<EffectConfig>) {
+        self.effects = Arc::new(EffectChain::new(&effects));
+        self.tts_config.effects = effects;
+        self.cache.clear();
+    }
+
+    /// Replace the voices used for other languages, keyed by language code
+    pub fn set_language_voices(&mut self, voices: HashMap<String, Voice>) {
+        self.tts_config.language_voices = voices;
+        self.cache.clear();
+    }
+
+    /// Cap the characters synthesized per request and choose what happens above it
+    pub fn set_length_limit(&mut self, max_chars: usize, policy: LengthPolicy) {
+        self.tts_config.max_tts_chars = max_chars;
+        self.tts_config.length_policy = policy;
+        self.cache.clear();
+    }
+
+    /// Switch to the voice in another character's settings
+    ///
+    /// Replaces the TTS engine (and failover chain), voice and effects while
+    /// keeping STT, speaker verification, language voices and length limits,
+    /// e.g. when the active character changes. Invalid settings leave the
+    /// plugin unchanged. Model handlers taken from [`Plugin::models`] before
+    /// the switch keep the previous engine and voice.
+    pub fn reconfigure(&mut self, settings: &serde_json::Value) -> Result<()> {
+        let profile = VoiceProfile::from_settings(settings)?;
+        let mut config = self.tts_config.clone();
+        profile.apply(&mut config);
+        config.effects = EffectConfig::from_character_settings(settings);
+
+        let replacement = Self::from_profile(&profile, config);
+        self.shutdown();
+        self.tts_engine = replacement.tts_engine;
+        self.failover = replacement.failover;
+        self.effects = replacement.effects;
+        self.tts_config = replacement.tts_config;
+        self.cache.clear();
+        Ok(())
     }
 
     /// Enable/disable streaming mode
@@ -691,6 +1101,16 @@ impl VoicePlugin {
         self.tts_config.streaming = enabled;
     }
 
+    /// Replace the audio cache with an empty one with new limits
+    pub fn set_cache_limits(&mut self, config: AudioCacheConfig) {
+        self.cache = AudioCache::new(config);
+    }
+
+    /// Hits, misses and size of the audio cache
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
     /// Get the current TTS engine type
     pub fn engine_type(&self) -> VoiceEngineType {
         self.tts_config.engine_type
@@ -707,6 +1127,48 @@ impl VoicePlugin {
         &self.tts_config
     }
 
+    /// Canary state of each engine in the failover chain (empty without one)
+    pub fn engine_status(&self) -> Vec<CanaryStatus> {
+        self.failover.as_ref().map(FailoverEngine::status).unwrap_or_default()
+    }
+
+    /// Stop background work such as failover canaries
+    pub fn shutdown(&self) {
+        if let Some(chain) = &self.failover {
+            chain.shutdown();
+        }
+    }
+
+    // =========================================================================
+    // Speaker Verification
+    // =========================================================================
+
+    /// Use `registry` for speaker enrollment and verification
+    pub fn set_speaker_registry(&mut self, registry: Arc<SpeakerRegistry>) {
+        self.speakers = Some(registry);
+    }
+
+    /// Speaker registry, if one was set
+    pub fn speaker_registry(&self) -> Option<&Arc<SpeakerRegistry>> {
+        self.speakers.as_ref()
+    }
+
+    /// Enroll a user's voice from a few utterances
+    pub async fn enroll_speaker(&self, user_id: u64, samples: Vec<AudioData>) -> Result<()> {
+        self.require_speakers()?.enroll(user_id, samples).await
+    }
+
+    /// Check whether `audio` was spoken by the enrolled `user_id`
+    pub async fn verify_speaker(&self, user_id: u64, audio: &AudioData) -> Result<VerificationResult> {
+        self.require_speakers()?.verify(user_id, audio).await
+    }
+
+    fn require_speakers(&self) -> Result<&Arc<SpeakerRegistry>> {
+        self.speakers
+            .as_ref()
+            .ok_or_else(|| VoiceError::NotReady("no speaker registry configured".to_string()).into())
+    }
+
     // =========================================================================
     // STT Methods
     // =========================================================================
@@ -731,7 +1193,7 @@ impl VoicePlugin {
         })?;
         
         let engine_guard = engine.read().await;
-        engine_guard.transcribe(audio, &self.stt_config).await
+        transcribe_normalized(engine_guard.as_ref(), audio, &self.stt_config).await
     }
 
     /// Transcribe audio to text (stub when no STT features)
@@ -750,7 +1212,8 @@ impl VoicePlugin {
         })?;
         
         let engine_guard = engine.read().await;
-        engine_guard.transcribe_stream(audio, &self.stt_config).await
+        let normalized = audio::normalize(audio, &engine_guard.expected_input())?;
+        engine_guard.transcribe_stream(&normalized, &self.stt_config).await
     }
 
     /// Transcribe audio to text with streaming (stub when no STT features)
@@ -769,8 +1232,10 @@ impl VoicePlugin {
             data: Bytes::copy_from_slice(pcm_data),
             format: AudioFormat::Pcm,
             sample_rate,
+            channels: 1,
             duration_ms: Some((pcm_data.len() as u64 * 1000) / (sample_rate as u64 * 2)),
             character_count: 0,
+            engine: None,
         };
         self.transcribe(&audio).await
     }
@@ -821,6 +1286,107 @@ impl VoicePlugin {
     }
 }
 
+/// Convert audio to the engine's expected input, then transcribe it
+///
+/// Records the original and converted specs on the result.
+#[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
+async fn transcribe_normalized(
+    engine: &dyn SpeechEngine,
+    audio: &AudioData,
+    config: &TranscriptionConfig,
+) -> Result<TranscriptionResult> {
+    let expected = engine.expected_input();
+    let normalized = audio::normalize(audio, &expected)?;
+    let mut result = engine.transcribe(&normalized, config).await?;
+    result.input_spec = Some(audio.spec());
+    result.converted_spec = Some(expected);
+    Ok(result)
+}
+
+/// Synthesize with `config`, then run the effects chain
+///
+/// An empty chain leaves synthesis untouched. Otherwise a compressed output
+/// format is swapped for WAV or PCM when the engine supports one, since
+/// effects run on PCM.
+async fn synthesize_with_effects(
+    engine: &dyn VoiceEngine,
+    text: &str,
+    config: &VoiceConfig,
+    effects: &EffectChain,
+) -> Result<AudioData> {
+    if effects.is_empty() {
+        return synthesize_attributed(engine, text, config).await;
+    }
+    let config = effects_config(engine, config, effects);
+    let audio = synthesize_attributed(engine, text, &config).await?;
+    apply_effects(effects, audio)
+}
+
+/// Synthesize with `engine`, naming it as the producer unless it named one
+///
+/// A [`FailoverEngine`] names the fallback that actually spoke.
+async fn synthesize_attributed(
+    engine: &dyn VoiceEngine,
+    text: &str,
+    config: &VoiceConfig,
+) -> Result<AudioData> {
+    let mut audio = engine.synthesize(text, config).await?;
+    audio.engine.get_or_insert_with(|| engine.name().to_string());
+    Ok(audio)
+}
+
+/// `config` asking for WAV or PCM when effects need to process the output
+fn effects_config<'a>(
+    engine: &dyn VoiceEngine,
+    config: &'a VoiceConfig,
+    effects: &EffectChain,
+) -> Cow<'a, VoiceConfig> {
+    if effects.is_empty() || matches!(config.output_format, AudioFormat::Pcm | AudioFormat::Wav) {
+        return Cow::Borrowed(config);
+    }
+    let supported = engine.supported_formats();
+    match [AudioFormat::Wav, AudioFormat::Pcm]
+        .into_iter()
+        .find(|f| supported.contains(f))
+    {
+        Some(format) => Cow::Owned(VoiceConfig {
+            output_format: format,
+            ..config.clone()
+        }),
+        None => Cow::Borrowed(config),
+    }
+}
+
+/// `config` asking for PCM (or WAV) when the engine offers it, so streamed chunks play as they arrive
+fn stream_config<'a>(engine: &dyn VoiceEngine, config: &'a VoiceConfig) -> Cow<'a, VoiceConfig> {
+    if matches!(config.output_format, AudioFormat::Pcm | AudioFormat::Wav) {
+        return Cow::Borrowed(config);
+    }
+    let supported = engine.supported_formats();
+    match [AudioFormat::Pcm, AudioFormat::Wav]
+        .into_iter()
+        .find(|f| supported.contains(f))
+    {
+        Some(format) => Cow::Owned(VoiceConfig {
+            output_format: format,
+            ..config.clone()
+        }),
+        None => Cow::Borrowed(config),
+    }
+}
+
+/// Run the effects chain on uncompressed audio; compressed audio is returned unprocessed
+fn apply_effects(effects: &EffectChain, audio: AudioData) -> Result<AudioData> {
+    if effects.is_empty() {
+        return Ok(audio);
+    }
+    if !matches!(audio.format, AudioFormat::Pcm | AudioFormat::Wav) {
+        tracing::warn!(format = audio.format.as_str(), "Engine output is compressed, skipping voice effects");
+        return Ok(audio);
+    }
+    effects.apply(&audio)
+}
+
 impl Default for VoicePlugin {
     fn default() -> Self {
         Self::with_openai(None)
@@ -829,6 +1395,8 @@ impl Default for VoicePlugin {
 
 #[async_trait]
 impl Plugin for VoicePlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "voice"
     }
@@ -862,6 +1430,12 @@ impl Plugin for VoicePlugin {
                     source: "default".to_string(),
                     change: "set via set_streaming(bool)".to_string(),
                 },
+                SettingRow {
+                    name: "VOICE_EFFECTS".to_string(),
+                    value: format!("{}", self.tts_config.effects.len()),
+                    source: "default".to_string(),
+                    change: "set via voice.effects".to_string(),
+                },
                 SettingRow {
                     name: "STT_ENGINE".to_string(),
                     value: self.stt_config.engine_type.as_str().to_string(),
@@ -893,17 +1467,21 @@ impl Plugin for VoicePlugin {
         // Register TTS model handler
         let tts_engine = Arc::clone(&self.tts_engine);
         let tts_config = self.tts_config.clone();
+        let effects = Arc::clone(&self.effects);
 
         let tts_handler: ModelHandler = Arc::new(move |params: ModelHandlerParams| {
             let engine = Arc::clone(&tts_engine);
             let config = tts_config.clone();
+            let effects = Arc::clone(&effects);
 
             Box::pin(async move {
-                let text = params.params.prompt;
+                let prompt = params.params.prompt;
+                let (text, length_limited) = length_limit::enforce(&prompt, &config).await?;
 
                 // Use tokio RwLock which is Send-safe across await points
                 let engine_guard = engine.read().await;
-                let audio = engine_guard.synthesize(&text, &config).await?;
+                let audio =
+                    synthesize_with_effects(engine_guard.as_ref(), &text, &config, &effects).await?;
 
                 // Return audio as base64-encoded string
                 let encoded =
@@ -914,6 +1492,7 @@ impl Plugin for VoicePlugin {
                     "format": audio.format.as_str(),
                     "sample_rate": audio.sample_rate,
                     "duration_ms": audio.duration_ms,
+                    "length_limited": length_limited,
                 })
                 .to_string())
             })
@@ -948,12 +1527,15 @@ impl Plugin for VoicePlugin {
                         data: Bytes::from(audio_bytes),
                         format: AudioFormat::Pcm,
                         sample_rate,
+                        channels: 1,
                         duration_ms: None,
                         character_count: 0,
+                        engine: None,
                     };
 
                     let engine_guard = engine.read().await;
-                    let result = engine_guard.transcribe(&audio, &config).await?;
+                    let result =
+                        transcribe_normalized(engine_guard.as_ref(), &audio, &config).await?;
 
                     Ok(serde_json::json!({
                         "text": result.text,
@@ -993,6 +1575,10 @@ impl Provider for VoicePlugin {
             caps.push("TRANSCRIBE".to_string());
             caps.push("SPEECH_RECOGNITION".to_string());
         }
+
+        if self.failover.is_some() {
+            caps.push("TTS_FAILOVER".to_string());
+        }
         
         Some(caps)
     }
@@ -1024,6 +1610,12 @@ impl Provider for VoicePlugin {
             String::new()
         };
         
+        let mut data = HashMap::new();
+        let status = self.engine_status();
+        if !status.is_empty() {
+            data.insert("tts_engines".to_string(), serde_json::json!(status));
+        }
+
         Ok(ProviderResult {
             text: Some(format!(
                 "Voice provider: {} TTS with {} voice{}",
@@ -1031,6 +1623,7 @@ impl Provider for VoicePlugin {
                 self.tts_config.voice.name,
                 stt_info
             )),
+            data: (!data.is_empty()).then_some(data),
             ..Default::default()
         })
     }
@@ -1096,6 +1689,148 @@ mod tests {
         assert!(!plugin.has_stt());
     }
 
+    /// Mock engine counting syntheses
+    struct CountingEngine(Arc<std::sync::atomic::AtomicUsize>);
+
+    #[async_trait]
+    impl VoiceEngine for CountingEngine {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn synthesize(&self, text: &str, config: &VoiceConfig) -> Result<AudioData> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            testing::MockDeterministicEngine::new(16000)
+                .synthesize(text, config)
+                .await
+        }
+
+        async fn synthesize_stream(&self, _text: &str, _config: &VoiceConfig) -> Result<AudioStream> {
+            Err(VoiceError::Other("not streaming".to_string()).into())
+        }
+
+        async fn available_voices(&self) -> Result<Vec<Voice>> {
+            Ok(Vec::new())
+        }
+
+        async fn is_ready(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_synthesis_skips_engine() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let config = VoiceConfig {
+            streaming: false,
+            ..Default::default()
+        };
+        let mut plugin = VoicePlugin::new(Box::new(CountingEngine(calls.clone())), config);
+        let hint = "Join a voice channel first.";
+
+        let first = plugin.synthesize(hint).await.unwrap();
+        let second = plugin.synthesize(hint).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.data, second.data);
+        let stats = plugin.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.bytes, first.data.len());
+
+        // A new speed invalidates what was cached
+        plugin.set_speed(1.25);
+        plugin.synthesize(hint).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Streaming bypasses the cache
+        plugin.set_streaming(true);
+        plugin.synthesize(hint).await.unwrap();
+        plugin.synthesize(hint).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_stream_chunks_slice_engines_without_streaming() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let config = VoiceConfig {
+            output_format: AudioFormat::Pcm,
+            streaming: false,
+            ..Default::default()
+        };
+        let plugin = VoicePlugin::new(Box::new(CountingEngine(calls)), config);
+        assert!(!plugin.supports_streaming().await);
+        let text = "Streaming or not";
+
+        let mut stream = plugin.synthesize_stream_chunks(text, 50).await.unwrap();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.recv().await {
+            chunks.push(chunk.unwrap());
+        }
+        assert!(stream.time_to_first_chunk().is_some());
+        // 16 characters of 20ms in 50ms chunks
+        assert_eq!(chunks.len(), 7);
+        assert!(chunks.last().unwrap().is_final);
+        assert!(chunks
+            .iter()
+            .all(|c| c.format == Some(AudioFormat::Pcm) && c.sample_rate == Some(16_000)));
+        let joined: Vec<u8> = chunks.iter().flat_map(|c| c.data.to_vec()).collect();
+        assert_eq!(joined, plugin.synthesize(text).await.unwrap().data);
+    }
+
+    #[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
+    struct SpecEchoEngine;
+
+    #[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
+    #[async_trait]
+    impl SpeechEngine for SpecEchoEngine {
+        fn name(&self) -> &str {
+            "spec-echo"
+        }
+
+        async fn transcribe(
+            &self,
+            audio: &AudioData,
+            _config: &TranscriptionConfig,
+        ) -> Result<TranscriptionResult> {
+            Ok(TranscriptionResult::new(audio.spec().to_string()))
+        }
+
+        async fn transcribe_stream(
+            &self,
+            _audio: &AudioData,
+            _config: &TranscriptionConfig,
+        ) -> Result<TranscriptionStream> {
+            Err(VoiceError::Other("not streaming".to_string()).into())
+        }
+
+        async fn is_ready(&self) -> bool {
+            true
+        }
+
+        fn expected_input(&self) -> AudioSpec {
+            AudioSpec::pcm_mono(24000)
+        }
+    }
+
+    #[cfg(any(feature = "whisper", feature = "unmute", feature = "moshi"))]
+    #[tokio::test]
+    async fn test_transcribe_normalizes_input() {
+        let stereo = AudioData::new(Bytes::from(vec![0u8; 48000 * 4 / 100]), AudioFormat::Pcm, 48000)
+            .with_channels(2);
+        let result = transcribe_normalized(&SpecEchoEngine, &stereo, &TranscriptionConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(result.text, "24000Hz/1ch/pcm");
+        assert_eq!(result.input_spec, Some(AudioSpec::new(48000, 2, AudioFormat::Pcm)));
+        assert_eq!(result.converted_spec, Some(AudioSpec::pcm_mono(24000)));
+
+        let mp3 = AudioData::new(Bytes::from_static(b"ID3"), AudioFormat::Mp3, 44100);
+        assert!(transcribe_normalized(&SpecEchoEngine, &mp3, &TranscriptionConfig::default())
+            .await
+            .is_err());
+    }
+
     #[cfg(feature = "whisper")]
     #[test]
     fn test_with_whisper() {
@@ -0,0 +1,610 @@
+//! Long-form synthesis streamed to a WAV writer or concatenated in memory
+//!
+//! A multi-minute narration assembled as one `Bytes` blob can take hundreds
+//! of MB. Instead, [`split_long_text`] cuts the text into sentence-sized
+//! chunks, each chunk is synthesized as WAV/PCM and its samples are appended
+//! to a [`WavStreamWriter`], so only about one chunk of audio is held in
+//! memory at a time.
+//!
+//! The header is written before the total length is known:
+//!
+//! - seekable writers (files, cursors) get the real RIFF/data sizes patched
+//!   in by [`WavStreamWriter::finish_patched`]
+//! - other writers keep the streaming convention of `0xFFFFFFFF` sizes, which
+//!   decoders treat as "read until end of stream"
+//!
+//! Replies that only need to stay under the engine's input limit use
+//! [`synthesize_concatenated`] instead: chunks of up to
+//! [`VoiceEngine::max_text_length`] are synthesized a few at a time and
+//! joined into one [`AudioData`]. WAV/PCM chunks are decoded and their
+//! samples joined under a single header; compressed chunks (MP3, Opus, ...)
+//! are byte-concatenated, which [`LongAudio::byte_concatenated`] reports.
+
+use crate::audio::{
+    convert_channels, decode_wav, encode_wav, pcm16_to_samples, resample_linear,
+    samples_to_pcm16, wav_header, PcmAudio, WAV_HEADER_LEN,
+};
+use crate::length_limit::LengthLimited;
+use crate::types::*;
+use bytes::Bytes;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use std::io::SeekFrom;
+use tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use zoey_core::Result;
+
+/// Default maximum characters per synthesized chunk
+pub const DEFAULT_LONG_CHUNK_CHARS: usize = 600;
+
+/// Chunks synthesized at once by [`synthesize_concatenated`]
+pub const LONG_CONCURRENCY: usize = 3;
+
+/// Data size written while the final length is unknown
+const STREAMING_SIZE: u32 = u32::MAX;
+
+/// Progress after each synthesized chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LongSynthesisProgress {
+    /// Chunks written so far (1-based)
+    pub chunk: usize,
+    /// Total chunks in the text
+    pub total_chunks: usize,
+    /// PCM bytes written so far, excluding the header
+    pub data_bytes: u64,
+}
+
+/// Result of a long-form synthesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LongSynthesisSummary {
+    pub chunks: usize,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// PCM bytes written, excluding the header
+    pub data_bytes: u64,
+    pub duration_ms: u64,
+    /// Set when the text was over `max_tts_chars` and shortened
+    pub length_limited: Option<LengthLimited>,
+}
+
+/// Long text synthesized chunk by chunk and joined into one clip
+#[derive(Debug, Clone)]
+pub struct LongAudio {
+    /// The whole reply
+    pub audio: AudioData,
+    /// Engine requests made
+    pub chunks: usize,
+    /// Compressed chunks were joined byte for byte rather than re-encoded
+    ///
+    /// Most MP3 and Ogg/Opus decoders play such a file through, but its
+    /// container headers describe only the first chunk.
+    pub byte_concatenated: bool,
+    /// Set when the text was over `max_tts_chars` and shortened
+    pub length_limited: Option<LengthLimited>,
+}
+
+/// Split text into chunks of at most `max_chars`, preferring sentence ends
+///
+/// Sentences longer than `max_chars` are split at word boundaries, and words
+/// longer than that are cut.
+pub fn split_long_text(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    let mut push = |current: &mut String, piece: &str| {
+        let piece = piece.trim();
+        if piece.is_empty() {
+            return;
+        }
+        if !current.is_empty() && current.chars().count() + 1 + piece.chars().count() > max_chars {
+            chunks.push(std::mem::take(current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(piece);
+    };
+
+    for sentence in split_sentences(text) {
+        if sentence.chars().count() <= max_chars {
+            push(&mut current, sentence);
+            continue;
+        }
+        for word in sentence.split_whitespace() {
+            let chars: Vec<char> = word.chars().collect();
+            for part in chars.chunks(max_chars) {
+                push(&mut current, &part.iter().collect::<String>());
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Sentences including their terminating punctuation
+pub(crate) fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_break = matches!(c, '.' | '!' | '?' | '\n')
+            && chars.peek().map_or(true, |(_, next)| next.is_whitespace());
+        if at_break {
+            let end = i + c.len_utf8();
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
+}
+
+/// Writes 16-bit PCM WAV incrementally
+pub struct WavStreamWriter<W> {
+    writer: W,
+    sample_rate: u32,
+    channels: u16,
+    data_bytes: u64,
+}
+
+impl<W: AsyncWrite + Unpin> WavStreamWriter<W> {
+    /// Write a streaming header (unknown length) and return the writer
+    pub async fn new(mut writer: W, sample_rate: u32, channels: u16) -> Result<Self> {
+        writer
+            .write_all(&wav_header(sample_rate, channels, STREAMING_SIZE))
+            .await
+            .map_err(io_error)?;
+        Ok(Self {
+            writer,
+            sample_rate,
+            channels,
+            data_bytes: 0,
+        })
+    }
+
+    /// Append interleaved samples
+    pub async fn write_samples(&mut self, samples: &[i16]) -> Result<()> {
+        let bytes = samples_to_pcm16(samples);
+        self.writer.write_all(&bytes).await.map_err(io_error)?;
+        self.data_bytes += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// PCM bytes written so far
+    pub fn data_bytes(&self) -> u64 {
+        self.data_bytes
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Flush and return the writer, leaving the streaming sizes in the header
+    pub async fn finish(mut self) -> Result<W> {
+        self.writer.flush().await.map_err(io_error)?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> WavStreamWriter<W> {
+    /// Patch the RIFF and data sizes with the actual length, then flush
+    ///
+    /// Streams longer than a WAV header can describe (~4 GiB) keep the
+    /// streaming sizes.
+    pub async fn finish_patched(mut self) -> Result<W> {
+        if let Ok(data_len) = u32::try_from(self.data_bytes) {
+            if data_len < STREAMING_SIZE - 36 {
+                let header = wav_header(self.sample_rate, self.channels, data_len);
+                let end = WAV_HEADER_LEN as u64 + self.data_bytes;
+                self.writer.seek(SeekFrom::Start(0)).await.map_err(io_error)?;
+                self.writer.write_all(&header).await.map_err(io_error)?;
+                self.writer.seek(SeekFrom::Start(end)).await.map_err(io_error)?;
+            }
+        }
+        self.finish().await
+    }
+}
+
+/// Synthesize `text` chunk by chunk into a [`WavStreamWriter`]
+///
+/// The output format follows the first chunk; later chunks are converted to
+/// match. Engines must produce WAV or PCM (the config asks for WAV).
+pub(crate) async fn synthesize_to_wav_stream<W, F>(
+    engine: &dyn VoiceEngine,
+    config: &VoiceConfig,
+    text: &str,
+    writer: W,
+    mut on_progress: F,
+) -> Result<(WavStreamWriter<W>, LongSynthesisSummary)>
+where
+    W: AsyncWrite + Unpin,
+    F: FnMut(LongSynthesisProgress),
+{
+    let chunks = split_long_text(text, engine.max_text_length().min(DEFAULT_LONG_CHUNK_CHARS));
+    if chunks.is_empty() {
+        return Err(VoiceError::InvalidInput("nothing to synthesize".to_string()).into());
+    }
+    let config = VoiceConfig {
+        output_format: AudioFormat::Wav,
+        ..config.clone()
+    };
+
+    let mut writer = Some(writer);
+    let mut wav: Option<WavStreamWriter<W>> = None;
+    for (i, chunk) in chunks.iter().enumerate() {
+        let pcm = decode_chunk(&engine.synthesize(chunk, &config).await?)?;
+        if let Some(w) = writer.take() {
+            wav = Some(WavStreamWriter::new(w, pcm.sample_rate, pcm.channels).await?);
+        }
+        let out = wav.as_mut().expect("header is written with the first chunk");
+        let samples = convert_channels(&pcm.samples, pcm.channels, out.channels)?;
+        let samples = resample_linear(&samples, out.channels, pcm.sample_rate, out.sample_rate);
+        drop(pcm);
+        out.write_samples(&samples).await?;
+        on_progress(LongSynthesisProgress {
+            chunk: i + 1,
+            total_chunks: chunks.len(),
+            data_bytes: out.data_bytes,
+        });
+    }
+
+    let wav = wav.expect("at least one chunk was written");
+    let frame_bytes = wav.channels as u64 * 2;
+    let summary = LongSynthesisSummary {
+        chunks: chunks.len(),
+        sample_rate: wav.sample_rate,
+        channels: wav.channels,
+        data_bytes: wav.data_bytes,
+        duration_ms: wav.data_bytes / frame_bytes * 1000 / wav.sample_rate.max(1) as u64,
+        length_limited: None,
+    };
+    Ok((wav, summary))
+}
+
+/// Synthesize `text` in chunks the engine accepts and join the audio
+///
+/// Up to [`LONG_CONCURRENCY`] chunks are in flight at once; the audio keeps
+/// the text's order. Uncompressed chunks are converted to the first chunk's
+/// sample rate and channels.
+pub(crate) async fn synthesize_concatenated(
+    engine: &dyn VoiceEngine,
+    config: &VoiceConfig,
+    text: &str,
+) -> Result<LongAudio> {
+    let chunks = split_long_text(text, engine.max_text_length());
+    if chunks.is_empty() {
+        return Err(VoiceError::InvalidInput("nothing to synthesize".to_string()).into());
+    }
+    let parts: Vec<AudioData> = stream::iter(chunks.iter().map(|chunk| engine.synthesize(chunk, config)))
+        .buffered(LONG_CONCURRENCY)
+        .try_collect()
+        .await?;
+    let audio = concatenate(&parts)?;
+    Ok(LongAudio {
+        byte_concatenated: !is_uncompressed(audio.format) && parts.len() > 1,
+        audio,
+        chunks: chunks.len(),
+        length_limited: None,
+    })
+}
+
+fn is_uncompressed(format: AudioFormat) -> bool {
+    matches!(format, AudioFormat::Wav | AudioFormat::Pcm)
+}
+
+/// Join chunks into one clip in the first chunk's format
+fn concatenate(parts: &[AudioData]) -> Result<AudioData> {
+    let first = parts
+        .first()
+        .ok_or_else(|| zoey_core::ZoeyError::from(VoiceError::InvalidInput("nothing to synthesize".to_string())))?;
+    if parts.len() == 1 {
+        return Ok(first.clone());
+    }
+    let character_count = parts.iter().map(|p| p.character_count).sum();
+
+    if parts.iter().all(|p| is_uncompressed(p.format)) {
+        let mut out: Option<PcmAudio> = None;
+        for part in parts {
+            let pcm = decode_chunk(part)?;
+            let out = out.get_or_insert_with(|| PcmAudio {
+                samples: Vec::new(),
+                sample_rate: pcm.sample_rate,
+                channels: pcm.channels,
+            });
+            let samples = convert_channels(&pcm.samples, pcm.channels, out.channels)?;
+            out.samples
+                .extend(resample_linear(&samples, out.channels, pcm.sample_rate, out.sample_rate));
+        }
+        let out = out.expect("at least one chunk was decoded");
+        let data = match first.format {
+            AudioFormat::Wav => encode_wav(&out.samples, out.sample_rate, out.channels),
+            _ => samples_to_pcm16(&out.samples),
+        };
+        let frames = (out.samples.len() / out.channels.max(1) as usize) as u64;
+        let mut audio = AudioData::new(Bytes::from(data), first.format, out.sample_rate).with_channels(out.channels);
+        audio.duration_ms = Some(frames * 1000 / out.sample_rate.max(1) as u64);
+        audio.character_count = character_count;
+        audio.engine = first.engine.clone();
+        return Ok(audio);
+    }
+
+    if let Some(other) = parts.iter().find(|p| p.format != first.format) {
+        return Err(VoiceError::AudioError(format!(
+            "cannot join {} and {} chunks",
+            first.format.as_str(),
+            other.format.as_str()
+        ))
+        .into());
+    }
+    let mut data = Vec::with_capacity(parts.iter().map(|p| p.data.len()).sum());
+    for part in parts {
+        data.extend_from_slice(&part.data);
+    }
+    let mut audio = AudioData::new(Bytes::from(data), first.format, first.sample_rate).with_channels(first.channels);
+    audio.duration_ms = parts.iter().map(|p| p.duration_ms).sum();
+    audio.character_count = character_count;
+    audio.engine = first.engine.clone();
+    Ok(audio)
+}
+
+pub(crate) fn decode_chunk(audio: &AudioData) -> Result<PcmAudio> {
+    match audio.format {
+        AudioFormat::Wav => decode_wav(&audio.data),
+        AudioFormat::Pcm => Ok(PcmAudio {
+            samples: pcm16_to_samples(&audio.data),
+            sample_rate: audio.sample_rate,
+            channels: audio.channels.max(1),
+        }),
+        other => Err(VoiceError::InvalidInput(format!(
+            "long-form WAV assembly needs WAV or PCM from the engine, got {}",
+            other.as_str()
+        ))
+        .into()),
+    }
+}
+
+fn io_error(e: std::io::Error) -> zoey_core::ZoeyError {
+    VoiceError::AudioError(format!("Failed to write WAV stream: {}", e)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VoicePlugin;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::io::Cursor;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Samples per synthesized chunk in the fake engine
+    const CHUNK_SAMPLES: usize = 4_000;
+
+    /// Returns a fixed-length tone per call as WAV
+    struct ToneEngine;
+
+    #[async_trait]
+    impl VoiceEngine for ToneEngine {
+        fn name(&self) -> &str {
+            "tone"
+        }
+
+        async fn synthesize(&self, text: &str, config: &VoiceConfig) -> Result<AudioData> {
+            assert_eq!(config.output_format, AudioFormat::Wav);
+            let samples: Vec<i16> = (0..CHUNK_SAMPLES).map(|i| (i % 100) as i16).collect();
+            let mut audio =
+                AudioData::new(Bytes::from(crate::audio::encode_wav(&samples, 16_000, 1)), AudioFormat::Wav, 16_000);
+            audio.character_count = text.len();
+            Ok(audio)
+        }
+
+        async fn synthesize_stream(&self, _text: &str, _config: &VoiceConfig) -> Result<AudioStream> {
+            Err(VoiceError::Other("not streaming".to_string()).into())
+        }
+
+        async fn available_voices(&self) -> Result<Vec<Voice>> {
+            Ok(Vec::new())
+        }
+
+        async fn is_ready(&self) -> bool {
+            true
+        }
+
+        fn max_text_length(&self) -> usize {
+            40
+        }
+    }
+
+    /// Non-seekable writer recording total bytes and the largest single write
+    #[derive(Default)]
+    struct CountingWriter {
+        total: usize,
+        largest_write: usize,
+    }
+
+    impl AsyncWrite for CountingWriter {
+        fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+            self.total += buf.len();
+            self.largest_write = self.largest_write.max(buf.len());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Returns the text as fake MP3 frames of 100 ms per call
+    struct Mp3Engine;
+
+    #[async_trait]
+    impl VoiceEngine for Mp3Engine {
+        fn name(&self) -> &str {
+            "mp3"
+        }
+
+        async fn synthesize(&self, text: &str, _config: &VoiceConfig) -> Result<AudioData> {
+            let mut audio = AudioData::new(Bytes::from(format!("[{}]", text)), AudioFormat::Mp3, 24_000);
+            audio.duration_ms = Some(100);
+            audio.character_count = text.len();
+            Ok(audio)
+        }
+
+        async fn synthesize_stream(&self, _text: &str, _config: &VoiceConfig) -> Result<AudioStream> {
+            Err(VoiceError::Other("not streaming".to_string()).into())
+        }
+
+        async fn available_voices(&self) -> Result<Vec<Voice>> {
+            Ok(Vec::new())
+        }
+
+        async fn is_ready(&self) -> bool {
+            true
+        }
+
+        fn max_text_length(&self) -> usize {
+            40
+        }
+    }
+
+    fn header_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    #[test]
+    fn test_split_long_text() {
+        let text = "First sentence here. Second one! A third, rather longer sentence that needs splitting?";
+        let chunks = split_long_text(text, 30);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 30), "{:?}", chunks);
+        assert_eq!(chunks[0], "First sentence here.");
+        assert_eq!(chunks.join(" "), text);
+        assert!(split_long_text("   ", 30).is_empty());
+        assert_eq!(split_long_text("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[tokio::test]
+    async fn test_patched_header_matches_data_length() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut wav = WavStreamWriter::new(&mut cursor, 22_050, 2).await.unwrap();
+        wav.write_samples(&[1, -1, 2, -2]).await.unwrap();
+        wav.write_samples(&[3, -3]).await.unwrap();
+        wav.finish_patched().await.unwrap();
+
+        let bytes = cursor.into_inner();
+        assert_eq!(bytes.len(), WAV_HEADER_LEN + 12);
+        assert_eq!(header_u32(&bytes, 4) as usize, bytes.len() - 8);
+        assert_eq!(header_u32(&bytes, 40), 12);
+        let decoded = decode_wav(&bytes).unwrap();
+        assert_eq!(decoded.samples, vec![1, -1, 2, -2, 3, -3]);
+        assert_eq!((decoded.sample_rate, decoded.channels), (22_050, 2));
+    }
+
+    #[tokio::test]
+    async fn test_non_seekable_keeps_streaming_sizes() {
+        let mut out = Vec::new();
+        let mut wav = WavStreamWriter::new(&mut out, 16_000, 1).await.unwrap();
+        wav.write_samples(&[5, 6, 7]).await.unwrap();
+        wav.finish().await.unwrap();
+
+        assert_eq!(header_u32(&out, 4), u32::MAX);
+        assert_eq!(header_u32(&out, 40), u32::MAX);
+        assert_eq!(decode_wav(&out).unwrap().samples, vec![5, 6, 7]);
+    }
+
+    #[tokio::test]
+    async fn test_long_synthesis_buffers_at_most_one_chunk() {
+        let plugin = VoicePlugin::new(Box::new(ToneEngine), VoiceConfig::default());
+        let text = "This is sentence number one. ".repeat(20);
+        let expected_chunks = split_long_text(&text, 40).len();
+        assert!(expected_chunks > 5);
+
+        let mut progress = Vec::new();
+        let mut writer = CountingWriter::default();
+        let summary = plugin
+            .synthesize_long_to_writer_with_progress(&text, &mut writer, |p| progress.push(p))
+            .await
+            .unwrap();
+
+        let chunk_bytes = CHUNK_SAMPLES * 2;
+        assert_eq!(summary.chunks, expected_chunks);
+        assert_eq!(summary.data_bytes as usize, expected_chunks * chunk_bytes);
+        assert_eq!(writer.total, WAV_HEADER_LEN + expected_chunks * chunk_bytes);
+        assert!(writer.largest_write <= chunk_bytes, "largest write {}", writer.largest_write);
+        assert_eq!(progress.len(), expected_chunks);
+        assert_eq!(progress.last().unwrap().chunk, expected_chunks);
+        assert_eq!(progress.last().unwrap().data_bytes, summary.data_bytes);
+    }
+
+    #[tokio::test]
+    async fn test_long_synthesis_to_file_patches_header() {
+        let plugin = VoicePlugin::new(Box::new(ToneEngine), VoiceConfig::default());
+        let path = std::env::temp_dir().join(format!("zoey-long-{}.wav", std::process::id()));
+        let summary = plugin
+            .synthesize_long_to_file("One. Two. Three.", &path)
+            .await
+            .unwrap();
+        let bytes = tokio::fs::read(&path).await.unwrap();
+        let _ = tokio::fs::remove_file(&path).await;
+
+        assert_eq!(header_u32(&bytes, 40) as u64, summary.data_bytes);
+        assert_eq!(decode_wav(&bytes).unwrap().samples.len(), summary.chunks * CHUNK_SAMPLES);
+    }
+
+    #[tokio::test]
+    async fn test_long_wav_is_joined_under_one_header() {
+        let engine = crate::testing::MockDeterministicEngine::new(16_000).with_max_text_length(40);
+        let config = VoiceConfig {
+            output_format: AudioFormat::Wav,
+            ..VoiceConfig::default()
+        };
+        let plugin = VoicePlugin::new(Box::new(engine.clone()), config);
+        let text = "The first sentence is here. And a second one follows it! Is there a third?";
+        let chunks = split_long_text(text, 40);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.ends_with(['.', '!', '?'])), "{:?}", chunks);
+
+        let long = plugin.synthesize_long(text).await.unwrap();
+        assert_eq!(long.chunks, chunks.len());
+        assert!(!long.byte_concatenated);
+        assert_eq!(long.audio.format, AudioFormat::Wav);
+        assert_eq!(long.audio.data.windows(4).filter(|w| w == b"RIFF").count(), 1);
+
+        let expected: Vec<i16> = chunks.iter().flat_map(|c| engine.render(c)).collect();
+        assert_eq!(decode_wav(&long.audio.data).unwrap().samples, expected);
+        assert_eq!(long.audio.duration_ms, Some(expected.len() as u64 * 1000 / 16_000));
+        assert_eq!(long.audio.character_count, chunks.iter().map(|c| c.chars().count()).sum::<usize>());
+    }
+
+    #[tokio::test]
+    async fn test_long_compressed_audio_is_flagged_as_byte_concatenated() {
+        let config = VoiceConfig {
+            output_format: AudioFormat::Mp3,
+            ..VoiceConfig::default()
+        };
+        let plugin = VoicePlugin::new(Box::new(Mp3Engine), config);
+
+        let short = plugin.synthesize_long("Hello there.").await.unwrap();
+        assert_eq!((short.chunks, short.byte_concatenated), (1, false));
+
+        let text = "This is sentence number one. ".repeat(4);
+        let chunks = split_long_text(&text, 40);
+        let long = plugin.synthesize_long(&text).await.unwrap();
+        assert!(long.byte_concatenated);
+        assert_eq!(long.chunks, chunks.len());
+        assert_eq!(long.audio.format, AudioFormat::Mp3);
+        assert_eq!(long.audio.duration_ms, Some(100 * chunks.len() as u64));
+        let expected: String = chunks.iter().map(|c| format!("[{}]", c)).collect();
+        assert_eq!(&long.audio.data[..], expected.as_bytes());
+    }
+}
@@ -0,0 +1,623 @@
+//! Per-language voices for mixed-language replies
+//!
+//! Replies that switch language mid-answer ("Sure, I can help. Te lo explico
+//! en español.") come out mangled when one English voice reads the Spanish
+//! half. With `VoiceConfig::language_voices` set, text is split by
+//! [`segment_by_language`]:
+//!
+//! - each sentence's language is detected with whatlang, restricted to the
+//!   configured languages plus the default voice's language
+//! - consecutive sentences in the same language form a segment
+//! - a segment shorter than [`DEFAULT_MIN_SEGMENT_CHARS`] (a borrowed
+//!   "¡Claro!") or in no recognizable language joins its neighbor, the
+//!   preceding one when there is one, so voices do not flip on single words
+//!
+//! Each segment is spoken with [`voice_for`] its language (the default voice
+//! when none is configured) and the PCM is joined in order with
+//! [`LANGUAGE_PAUSE_MS`] of silence at each boundary. Text that stays in one
+//! language is synthesized in a single request as before, only with that
+//! language's voice. Keys of `language_voices` are ISO 639-1 or 639-3 codes,
+//! optionally with a region (`es`, `spa`, `es-MX`).
+
+use crate::audio::{convert_channels, encode_wav, resample_linear, PcmAudio};
+use crate::length_limit::LengthLimited;
+use crate::long_form::{decode_chunk, split_sentences};
+use crate::types::*;
+use bytes::Bytes;
+use serde::Serialize;
+use std::borrow::Cow;
+use whatlang::{Detector, Lang};
+use zoey_core::Result;
+
+/// Segments shorter than this many characters take a neighbor's language
+pub const DEFAULT_MIN_SEGMENT_CHARS: usize = 16;
+
+/// Silence inserted where the language changes
+pub const LANGUAGE_PAUSE_MS: u32 = 120;
+
+/// Detections below this confidence count as no language
+const MIN_CONFIDENCE: f64 = 0.05;
+
+/// ISO 639-1 codes of the languages whatlang detects
+const ISO_639_1: &[(&str, Lang)] = &[
+    ("af", Lang::Afr),
+    ("ak", Lang::Aka),
+    ("am", Lang::Amh),
+    ("ar", Lang::Ara),
+    ("az", Lang::Aze),
+    ("be", Lang::Bel),
+    ("bg", Lang::Bul),
+    ("bn", Lang::Ben),
+    ("ca", Lang::Cat),
+    ("cs", Lang::Ces),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("eo", Lang::Epo),
+    ("es", Lang::Spa),
+    ("et", Lang::Est),
+    ("fa", Lang::Pes),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("gu", Lang::Guj),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hr", Lang::Hrv),
+    ("hu", Lang::Hun),
+    ("hy", Lang::Hye),
+    ("id", Lang::Ind),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("jv", Lang::Jav),
+    ("ka", Lang::Kat),
+    ("km", Lang::Khm),
+    ("kn", Lang::Kan),
+    ("ko", Lang::Kor),
+    ("la", Lang::Lat),
+    ("lt", Lang::Lit),
+    ("lv", Lang::Lav),
+    ("mk", Lang::Mkd),
+    ("ml", Lang::Mal),
+    ("mr", Lang::Mar),
+    ("my", Lang::Mya),
+    ("nb", Lang::Nob),
+    ("ne", Lang::Nep),
+    ("nl", Lang::Nld),
+    ("no", Lang::Nob),
+    ("or", Lang::Ori),
+    ("pa", Lang::Pan),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("si", Lang::Sin),
+    ("sk", Lang::Slk),
+    ("sl", Lang::Slv),
+    ("sn", Lang::Sna),
+    ("sr", Lang::Srp),
+    ("sv", Lang::Swe),
+    ("ta", Lang::Tam),
+    ("te", Lang::Tel),
+    ("th", Lang::Tha),
+    ("tk", Lang::Tuk),
+    ("tl", Lang::Tgl),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("ur", Lang::Urd),
+    ("uz", Lang::Uzb),
+    ("vi", Lang::Vie),
+    ("yi", Lang::Yid),
+    ("zh", Lang::Cmn),
+    ("zu", Lang::Zul),
+];
+
+/// A run of text spoken in one language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageSegment {
+    /// ISO 639-1 code (639-3 when there is none), `None` when undetected
+    pub language: Option<&'static str>,
+    /// Sentences of the segment, joined by single spaces
+    pub text: String,
+}
+
+/// How one segment was spoken, for debugging voice switches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SegmentDecision {
+    /// Detected language, as in [`LanguageSegment::language`]
+    pub language: Option<&'static str>,
+    /// Voice that spoke the segment
+    pub voice_id: String,
+    /// No voice is configured for the language; the default voice spoke it
+    pub fallback: bool,
+    /// Characters of text in the segment
+    pub chars: usize,
+    /// Length of the segment's audio, excluding pauses
+    pub duration_ms: Option<u64>,
+}
+
+/// Synthesized audio with the per-segment voice decisions
+#[derive(Debug, Clone)]
+pub struct MultilingualAudio {
+    /// The whole reply
+    pub audio: AudioData,
+    /// Empty when `language_voices` is not configured
+    pub segments: Vec<SegmentDecision>,
+    /// Set when the text was over `max_tts_chars` and shortened
+    pub length_limited: Option<LengthLimited>,
+}
+
+/// Language of an ISO 639-1 or 639-3 code, ignoring any region suffix
+pub fn parse_language(code: &str) -> Option<Lang> {
+    let base = code
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    ISO_639_1
+        .iter()
+        .find(|(iso, _)| *iso == base)
+        .map(|(_, lang)| *lang)
+        .or_else(|| Lang::from_code(base))
+}
+
+fn language_tag(lang: Lang) -> &'static str {
+    ISO_639_1
+        .iter()
+        .find(|(_, l)| *l == lang)
+        .map_or_else(|| lang.code(), |(iso, _)| *iso)
+}
+
+/// Voice for `language`, and whether it fell back to the default voice
+pub fn voice_for<'a>(config: &'a VoiceConfig, language: Option<&str>) -> (&'a Voice, bool) {
+    let lang = language.and_then(parse_language);
+    config
+        .language_voices
+        .iter()
+        .find(|(code, _)| lang.is_some() && parse_language(code) == lang)
+        .map_or((&config.voice, true), |(_, voice)| (voice, false))
+}
+
+/// Split `text` into same-language segments, detecting among `languages`
+///
+/// Segments under `min_chars` characters, or whose language was not
+/// detected, are merged into the preceding segment (the following one at the
+/// start of the text).
+pub fn segment_by_language(
+    text: &str,
+    languages: &[Lang],
+    min_chars: usize,
+) -> Vec<LanguageSegment> {
+    let detector = Detector::with_allowlist(languages.to_vec());
+    let mut segments: Vec<(Option<Lang>, String)> = Vec::new();
+    for sentence in split_sentences(text) {
+        let sentence = sentence.trim();
+        if sentence.is_empty() {
+            continue;
+        }
+        let lang = detector
+            .detect(sentence)
+            .filter(|info| info.confidence() >= MIN_CONFIDENCE)
+            .map(|info| info.lang());
+        push_text(&mut segments, lang, sentence);
+    }
+
+    while segments.len() > 1 {
+        let Some(i) = segments
+            .iter()
+            .position(|(lang, text)| lang.is_none() || text.chars().count() < min_chars)
+        else {
+            break;
+        };
+        let (_, text) = segments.remove(i);
+        if i > 0 {
+            segments[i - 1].1.push(' ');
+            segments[i - 1].1.push_str(&text);
+        } else {
+            segments[0].1.insert(0, ' ');
+            segments[0].1.insert_str(0, &text);
+        }
+        // Neighbors on both sides of the removed segment may now share a language
+        let mut merged: Vec<(Option<Lang>, String)> = Vec::with_capacity(segments.len());
+        for (lang, text) in segments {
+            push_text(&mut merged, lang, &text);
+        }
+        segments = merged;
+    }
+
+    segments
+        .into_iter()
+        .map(|(lang, text)| LanguageSegment {
+            language: lang.map(language_tag),
+            text,
+        })
+        .collect()
+}
+
+fn push_text(segments: &mut Vec<(Option<Lang>, String)>, lang: Option<Lang>, text: &str) {
+    match segments.last_mut() {
+        Some((last, current)) if *last == lang => {
+            current.push(' ');
+            current.push_str(text);
+        }
+        _ => segments.push((lang, text.to_string())),
+    }
+}
+
+/// Languages worth telling apart: the configured ones and the default voice's
+fn candidate_languages(config: &VoiceConfig) -> Vec<Lang> {
+    let mut languages: Vec<Lang> = std::iter::once(config.voice.language.as_str())
+        .chain(config.language_voices.keys().map(String::as_str))
+        .filter_map(parse_language)
+        .collect();
+    languages.sort_by_key(|lang| lang.code());
+    languages.dedup();
+    languages
+}
+
+/// Segments to synthesize separately, or none for a single request
+///
+/// Returns one segment for text in a single language, so its voice can be
+/// picked, and nothing when no language voices are configured or the engine
+/// cannot produce PCM to join segments with.
+pub(crate) fn plan(
+    config: &VoiceConfig,
+    text: &str,
+    formats: &[AudioFormat],
+) -> Vec<LanguageSegment> {
+    if config.language_voices.is_empty() {
+        return Vec::new();
+    }
+    let languages = candidate_languages(config);
+    if languages.len() < 2 {
+        return match languages.first() {
+            Some(lang) => vec![LanguageSegment {
+                language: Some(language_tag(*lang)),
+                text: text.to_string(),
+            }],
+            None => Vec::new(),
+        };
+    }
+    let segments = segment_by_language(text, &languages, DEFAULT_MIN_SEGMENT_CHARS);
+    if segments.len() > 1
+        && !formats
+            .iter()
+            .any(|f| matches!(f, AudioFormat::Wav | AudioFormat::Pcm))
+    {
+        tracing::warn!(
+            segments = segments.len(),
+            "Engine has no WAV/PCM output to join language segments, using the default voice"
+        );
+        return Vec::new();
+    }
+    segments
+}
+
+/// `config` with the voice for a lone `segment`'s language
+pub(crate) fn single_config<'a>(
+    config: &'a VoiceConfig,
+    segment: Option<&LanguageSegment>,
+) -> Cow<'a, VoiceConfig> {
+    match segment.map(|s| voice_for(config, s.language)) {
+        Some((voice, false)) => Cow::Owned(VoiceConfig {
+            voice: voice.clone(),
+            ..config.clone()
+        }),
+        _ => Cow::Borrowed(config),
+    }
+}
+
+/// Decision recorded for text spoken in one request
+pub(crate) fn single_decision(
+    config: &VoiceConfig,
+    segment: Option<&LanguageSegment>,
+    audio: &AudioData,
+) -> Vec<SegmentDecision> {
+    match segment {
+        Some(segment) => {
+            let (voice, fallback) = voice_for(config, segment.language);
+            vec![SegmentDecision {
+                language: segment.language,
+                voice_id: voice.id.clone(),
+                fallback,
+                chars: segment.text.chars().count(),
+                duration_ms: audio.duration_ms,
+            }]
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Synthesize each segment with its voice and join them as WAV
+///
+/// The output follows the first segment's sample rate and channels; later
+/// segments are converted to match.
+pub(crate) async fn synthesize_segments(
+    engine: &dyn VoiceEngine,
+    segments: &[LanguageSegment],
+    config: &VoiceConfig,
+) -> Result<MultilingualAudio> {
+    let output_format = if engine.supported_formats().contains(&AudioFormat::Wav) {
+        AudioFormat::Wav
+    } else {
+        AudioFormat::Pcm
+    };
+    let mut out: Option<PcmAudio> = None;
+    let mut produced_by = None;
+    let mut decisions = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let (voice, fallback) = voice_for(config, segment.language);
+        let segment_config = VoiceConfig {
+            voice: voice.clone(),
+            output_format,
+            ..config.clone()
+        };
+        let segment_audio = engine.synthesize(&segment.text, &segment_config).await?;
+        if produced_by.is_none() {
+            produced_by = segment_audio.engine.clone();
+        }
+        let pcm = decode_chunk(&segment_audio)?;
+        let out = match out.as_mut() {
+            Some(out) => {
+                let pause = (out.sample_rate as u64 * LANGUAGE_PAUSE_MS as u64 / 1000) as usize
+                    * out.channels as usize;
+                out.samples.extend(std::iter::repeat_n(0, pause));
+                out
+            }
+            None => out.insert(PcmAudio {
+                samples: Vec::new(),
+                sample_rate: pcm.sample_rate,
+                channels: pcm.channels,
+            }),
+        };
+        let samples = convert_channels(&pcm.samples, pcm.channels, out.channels)?;
+        let samples = resample_linear(&samples, out.channels, pcm.sample_rate, out.sample_rate);
+        decisions.push(SegmentDecision {
+            language: segment.language,
+            voice_id: voice.id.clone(),
+            fallback,
+            chars: segment.text.chars().count(),
+            duration_ms: Some(frames_ms(samples.len(), out)),
+        });
+        out.samples.extend(samples);
+    }
+
+    let out = out.ok_or_else(|| {
+        zoey_core::ZoeyError::from(VoiceError::InvalidInput(
+            "nothing to synthesize".to_string(),
+        ))
+    })?;
+    let mut audio = AudioData::new(
+        Bytes::from(encode_wav(&out.samples, out.sample_rate, out.channels)),
+        AudioFormat::Wav,
+        out.sample_rate,
+    )
+    .with_channels(out.channels);
+    audio.duration_ms = Some(frames_ms(out.samples.len(), &out));
+    audio.character_count = segments.iter().map(|s| s.text.chars().count()).sum();
+    audio.engine = produced_by.or_else(|| Some(engine.name().to_string()));
+    Ok(MultilingualAudio {
+        audio,
+        segments: decisions,
+        length_limited: None,
+    })
+}
+
+fn frames_ms(samples: usize, spec: &PcmAudio) -> u64 {
+    let frames = (samples / spec.channels.max(1) as usize) as u64;
+    frames * 1000 / spec.sample_rate.max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockDeterministicEngine;
+    use crate::VoicePlugin;
+    use std::sync::{Arc, Mutex};
+
+    const EN_ES: &[Lang] = &[Lang::Eng, Lang::Spa];
+
+    fn spanish_voice() -> Voice {
+        Voice::custom(
+            "es-voice".to_string(),
+            "Lucía".to_string(),
+            VoiceGender::Female,
+            "es-ES".to_string(),
+        )
+    }
+
+    fn bilingual_config() -> VoiceConfig {
+        VoiceConfig {
+            output_format: AudioFormat::Wav,
+            language_voices: [("es".to_string(), spanish_voice())].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Text and voice ID of each request
+    type Calls = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Mock engine recording each request's text and voice
+    struct RecordingEngine {
+        inner: MockDeterministicEngine,
+        calls: Calls,
+    }
+
+    #[async_trait::async_trait]
+    impl VoiceEngine for RecordingEngine {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn synthesize(&self, text: &str, config: &VoiceConfig) -> Result<AudioData> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((text.to_string(), config.voice.id.clone()));
+            self.inner.synthesize(text, config).await
+        }
+
+        async fn synthesize_stream(&self, text: &str, config: &VoiceConfig) -> Result<AudioStream> {
+            self.inner.synthesize_stream(text, config).await
+        }
+
+        async fn available_voices(&self) -> Result<Vec<Voice>> {
+            self.inner.available_voices().await
+        }
+
+        async fn is_ready(&self) -> bool {
+            true
+        }
+
+        fn supported_formats(&self) -> Vec<AudioFormat> {
+            self.inner.supported_formats()
+        }
+    }
+
+    fn recording_plugin(config: VoiceConfig) -> (VoicePlugin, Calls) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let engine = RecordingEngine {
+            inner: MockDeterministicEngine::new(16_000),
+            calls: calls.clone(),
+        };
+        (VoicePlugin::new(Box::new(engine), config), calls)
+    }
+
+    #[test]
+    fn test_segments_group_consecutive_sentences() {
+        let text = "Sure, I can help you with that. The meeting is tomorrow at noon. \
+                    Te lo explico en español para que quede claro. Nos vemos mañana en la oficina.";
+        let segments = segment_by_language(text, EN_ES, DEFAULT_MIN_SEGMENT_CHARS);
+        assert_eq!(
+            segments,
+            vec![
+                LanguageSegment {
+                    language: Some("en"),
+                    text: "Sure, I can help you with that. The meeting is tomorrow at noon."
+                        .to_string(),
+                },
+                LanguageSegment {
+                    language: Some("es"),
+                    text: "Te lo explico en español para que quede claro. Nos vemos mañana en la oficina."
+                        .to_string(),
+                },
+            ]
+        );
+        assert!(segment_by_language("  ", EN_ES, DEFAULT_MIN_SEGMENT_CHARS).is_empty());
+    }
+
+    #[test]
+    fn test_short_segments_inherit_neighbor_language() {
+        // A borrowed "¡Claro!" stays in the English voice
+        let text = "The meeting is tomorrow at noon. ¡Claro! Let me check the calendar for you.";
+        let segments = segment_by_language(text, EN_ES, DEFAULT_MIN_SEGMENT_CHARS);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].language, Some("en"));
+        assert_eq!(segments[0].text, text);
+
+        // At the start there is no preceding segment, so the next one absorbs it
+        let text = "Okay. Nos vemos mañana en la oficina.";
+        let segments = segment_by_language(text, EN_ES, DEFAULT_MIN_SEGMENT_CHARS);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].language, Some("es"));
+        assert_eq!(segments[0].text, text);
+
+        // Without a threshold the short sentence keeps its own segment
+        let segments = segment_by_language("The meeting is tomorrow at noon. ¡Claro!", EN_ES, 0);
+        assert_eq!(segments.len(), 2);
+    }
+
+    #[test]
+    fn test_voice_selection_falls_back_to_default() {
+        let config = VoiceConfig {
+            language_voices: [("spa".to_string(), spanish_voice())].into_iter().collect(),
+            ..Default::default()
+        };
+        let chosen = |language| {
+            let (voice, fallback) = voice_for(&config, language);
+            (voice.id.as_str(), fallback)
+        };
+        assert_eq!(chosen(Some("es")), ("es-voice", false));
+        assert_eq!(chosen(Some("es-MX")), ("es-voice", false));
+        assert_eq!(chosen(Some("fr")), ("shimmer", true));
+        assert_eq!(chosen(None), ("shimmer", true));
+        assert_eq!(parse_language("EN-us"), Some(Lang::Eng));
+        assert_eq!(parse_language("klingon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_mixed_text_switches_voices_in_order() {
+        let (plugin, calls) = recording_plugin(bilingual_config());
+        let text = "Sure, I can help you with that. Te lo explico en español para que quede claro.";
+        let result = plugin.synthesize_multilingual(text).await.unwrap();
+
+        let calls = calls.lock().unwrap().clone();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    "Sure, I can help you with that.".to_string(),
+                    "shimmer".to_string()
+                ),
+                (
+                    "Te lo explico en español para que quede claro.".to_string(),
+                    "es-voice".to_string()
+                ),
+            ]
+        );
+        let decisions = &result.segments;
+        assert_eq!(decisions.len(), 2);
+        assert_eq!(
+            (decisions[0].language, decisions[0].fallback),
+            (Some("en"), true)
+        );
+        assert_eq!(
+            (decisions[1].language, decisions[1].voice_id.as_str()),
+            (Some("es"), "es-voice")
+        );
+
+        // Segments in order with a pause between them
+        let engine = MockDeterministicEngine::new(16_000);
+        let mut expected = engine.render("Sure, I can help you with that.");
+        expected.extend(std::iter::repeat_n(
+            0,
+            16_000 * LANGUAGE_PAUSE_MS as usize / 1000,
+        ));
+        expected.extend(engine.render("Te lo explico en español para que quede claro."));
+        let decoded = crate::audio::decode_wav(&result.audio.data).unwrap();
+        assert_eq!(decoded.samples, expected);
+        assert_eq!(result.audio.character_count, text.chars().count() - 1);
+    }
+
+    #[tokio::test]
+    async fn test_monolingual_text_takes_single_request() {
+        let (plugin, calls) = recording_plugin(bilingual_config());
+        let text = "Sure, I can help you with that. The meeting is tomorrow at noon.";
+        let spoken = plugin.synthesize(text).await.unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![(text.to_string(), "shimmer".to_string())]
+        );
+
+        // Identical to a plugin without language voices
+        let (plain, plain_calls) = recording_plugin(VoiceConfig {
+            output_format: AudioFormat::Wav,
+            ..Default::default()
+        });
+        let unchanged = plain.synthesize_multilingual(text).await.unwrap();
+        assert_eq!(spoken.data, unchanged.audio.data);
+        assert_eq!(plain_calls.lock().unwrap().len(), 1);
+        assert!(unchanged.segments.is_empty());
+
+        // All-Spanish text is one request in the Spanish voice
+        let (plugin, calls) = recording_plugin(bilingual_config());
+        let result = plugin
+            .synthesize_multilingual("Nos vemos mañana en la oficina.")
+            .await
+            .unwrap();
+        assert_eq!(calls.lock().unwrap()[0].1, "es-voice");
+        assert_eq!(result.segments.len(), 1);
+        assert!(!result.segments[0].fallback);
+    }
+}
@@ -0,0 +1,580 @@
+//! Per-character voice profiles
+//!
+//! A character chooses its voice in its settings rather than in code. The
+//! profile lives under `voice`, next to the keys the Discord adapter reads:
+//!
+//! ```json
+//! {
+//!   "voice": {
+//!     "engine": "piper",
+//!     "endpoint": "http://localhost:5500",
+//!     "voice_id": "en_US-amy-medium",
+//!     "voice_name": "Amy",
+//!     "speed": 1.1,
+//!     "output_format": "wav",
+//!     "fallback_engine": "openai"
+//!   }
+//! }
+//! ```
+//!
+//! `engine` is one of `openai` (the default), `elevenlabs`, `piper` or
+//! `local`; the last two need an `endpoint` (`local_endpoint` is accepted as
+//! well). `voice_id`/`voice_name` default to the engine's default voice,
+//! `speed` (0.25 to 4.0) to 1.0 and `output_format` to `mp3`. `model`,
+//! `sample_rate`, `stability` and `similarity_boost` are optional, and a
+//! `fallback_engine` (with `fallback_endpoint` when it needs one) puts the
+//! primary engine in a [`FailoverEngine`](crate::FailoverEngine) chain.
+//! Numbers may be given as strings, as character files often store them.
+//!
+//! [`VoiceProfile::from_settings`] rejects a profile with an error naming
+//! the offending field (`voice.speed: ...`).
+//! [`VoicePlugin::from_character_settings`](crate::VoicePlugin::from_character_settings)
+//! builds a plugin from a profile and
+//! [`VoicePlugin::reconfigure`](crate::VoicePlugin::reconfigure) switches a
+//! running plugin to another character's voice.
+
+use crate::engines::{ElevenLabsVoiceEngine, LocalVoiceEngine, OpenAIVoiceEngine, PiperEngine};
+use crate::types::{AudioFormat, Voice, VoiceConfig, VoiceEngine, VoiceEngineType, VoiceGender};
+use serde_json::{Map, Value};
+use zoey_core::{Result, ZoeyError};
+
+/// TTS engine a profile can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileEngine {
+    /// OpenAI TTS
+    OpenAI,
+    /// ElevenLabs
+    ElevenLabs,
+    /// Piper server
+    Piper,
+    /// Local HTTP TTS server
+    Local,
+}
+
+impl ProfileEngine {
+    /// Name used in settings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OpenAI => "openai",
+            Self::ElevenLabs => "elevenlabs",
+            Self::Piper => "piper",
+            Self::Local => "local",
+        }
+    }
+
+    /// Engine type reported in [`VoiceConfig::engine_type`]
+    pub fn engine_type(&self) -> VoiceEngineType {
+        match self {
+            Self::OpenAI => VoiceEngineType::OpenAI,
+            Self::ElevenLabs => VoiceEngineType::ElevenLabs,
+            Self::Piper | Self::Local => VoiceEngineType::Local,
+        }
+    }
+
+    /// Whether the engine is a server that needs an endpoint
+    pub fn needs_endpoint(&self) -> bool {
+        matches!(self, Self::Piper | Self::Local)
+    }
+
+    /// Voice used when the profile names none
+    pub fn default_voice(&self) -> Voice {
+        match self {
+            Self::ElevenLabs => Voice::elevenlabs_rachel(),
+            Self::Piper => Voice::custom(
+                "en_US-lessac-medium".to_string(),
+                "Lessac".to_string(),
+                VoiceGender::Female,
+                "en-US".to_string(),
+            ),
+            Self::OpenAI | Self::Local => Voice::default_female(),
+        }
+    }
+
+    /// Sample rate used when the profile names none
+    pub fn default_sample_rate(&self) -> u32 {
+        match self {
+            Self::Piper => 22050,
+            _ => VoiceConfig::default().sample_rate,
+        }
+    }
+
+    fn parse(field: &str, name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAI),
+            "elevenlabs" => Ok(Self::ElevenLabs),
+            "piper" => Ok(Self::Piper),
+            "local" => Ok(Self::Local),
+            _ => Err(invalid(
+                field,
+                format!(
+                    "unknown engine '{}' (expected openai, elevenlabs, piper or local)",
+                    name
+                ),
+            )),
+        }
+    }
+}
+
+/// An engine and, for servers, where it listens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineSpec {
+    /// Which engine
+    pub engine: ProfileEngine,
+    /// Server URL, required by Piper and local servers
+    pub endpoint: Option<String>,
+}
+
+impl EngineSpec {
+    /// Instantiate the engine; API keys come from the environment
+    pub fn build(&self) -> Box<dyn VoiceEngine> {
+        let endpoint = self.endpoint.clone().unwrap_or_default();
+        match self.engine {
+            ProfileEngine::OpenAI => Box::new(OpenAIVoiceEngine::new(None)),
+            ProfileEngine::ElevenLabs => Box::new(ElevenLabsVoiceEngine::new(None)),
+            ProfileEngine::Piper => Box::new(PiperEngine::new(&endpoint)),
+            ProfileEngine::Local => Box::new(LocalVoiceEngine::new(endpoint)),
+        }
+    }
+}
+
+/// A character's voice, read from its `voice` settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceProfile {
+    /// Engine that speaks
+    pub primary: EngineSpec,
+    /// Engine taking over when the primary fails
+    pub fallback: Option<EngineSpec>,
+    /// Engine-specific voice identifier
+    pub voice_id: String,
+    /// Human-readable voice name
+    pub voice_name: String,
+    /// Speaking speed, 0.25 to 4.0
+    pub speed: f32,
+    /// Output audio format
+    pub output_format: AudioFormat,
+    /// Engine-specific model
+    pub model: Option<String>,
+    /// Output sample rate (Hz)
+    pub sample_rate: u32,
+    /// ElevenLabs stability, 0.0 to 1.0
+    pub stability: Option<f32>,
+    /// ElevenLabs similarity boost, 0.0 to 1.0
+    pub similarity_boost: Option<f32>,
+}
+
+impl Default for VoiceProfile {
+    fn default() -> Self {
+        let engine = ProfileEngine::OpenAI;
+        let voice = engine.default_voice();
+        Self {
+            primary: EngineSpec {
+                engine,
+                endpoint: None,
+            },
+            fallback: None,
+            voice_id: voice.id,
+            voice_name: voice.name,
+            speed: 1.0,
+            output_format: AudioFormat::Mp3,
+            model: None,
+            sample_rate: engine.default_sample_rate(),
+            stability: None,
+            similarity_boost: None,
+        }
+    }
+}
+
+impl VoiceProfile {
+    /// Read the profile under `voice` in character settings
+    ///
+    /// Settings without a `voice` section give the default profile (OpenAI,
+    /// shimmer).
+    pub fn from_settings(settings: &Value) -> Result<Self> {
+        let voice = match settings.get("voice") {
+            None | Some(Value::Null) => return Ok(Self::default()),
+            Some(Value::Object(voice)) => voice,
+            Some(_) => return Err(ZoeyError::validation("voice: expected an object")),
+        };
+
+        let engine = match string_field(voice, "engine")? {
+            Some(name) => ProfileEngine::parse("engine", &name)?,
+            None => ProfileEngine::OpenAI,
+        };
+        let endpoint = match string_field(voice, "endpoint")? {
+            Some(endpoint) => Some(endpoint),
+            None => string_field(voice, "local_endpoint")?,
+        };
+        let primary = engine_spec(engine, endpoint, "endpoint")?;
+
+        let fallback = match string_field(voice, "fallback_engine")? {
+            Some(name) => {
+                let engine = ProfileEngine::parse("fallback_engine", &name)?;
+                let endpoint = string_field(voice, "fallback_endpoint")?;
+                Some(engine_spec(engine, endpoint, "fallback_endpoint")?)
+            }
+            None => None,
+        };
+
+        let default_voice = engine.default_voice();
+        let (voice_id, voice_name) = match string_field(voice, "voice_id")? {
+            Some(id) => {
+                let name = string_field(voice, "voice_name")?.unwrap_or_else(|| id.clone());
+                (id, name)
+            }
+            None => (
+                default_voice.id,
+                string_field(voice, "voice_name")?.unwrap_or(default_voice.name),
+            ),
+        };
+
+        let speed = number_field(voice, "speed")?.unwrap_or(1.0);
+        if !(0.25..=4.0).contains(&speed) {
+            return Err(invalid(
+                "speed",
+                format!("{} is outside 0.25 to 4.0", speed),
+            ));
+        }
+
+        let output_format = match string_field(voice, "output_format")? {
+            Some(format) => parse_format(&format)?,
+            None => AudioFormat::Mp3,
+        };
+
+        let sample_rate = match number_field(voice, "sample_rate")? {
+            Some(rate) if rate >= 1.0 && rate.fract() == 0.0 && rate <= u32::MAX as f64 => {
+                rate as u32
+            }
+            Some(rate) => {
+                return Err(invalid(
+                    "sample_rate",
+                    format!("{} is not a positive whole number of Hz", rate),
+                ))
+            }
+            None => engine.default_sample_rate(),
+        };
+
+        Ok(Self {
+            primary,
+            fallback,
+            voice_id,
+            voice_name,
+            speed: speed as f32,
+            output_format,
+            model: string_field(voice, "model")?,
+            sample_rate,
+            stability: unit_field(voice, "stability")?,
+            similarity_boost: unit_field(voice, "similarity_boost")?,
+        })
+    }
+
+    /// Character settings that read back as this profile
+    pub fn to_settings(&self) -> Value {
+        let mut voice = Map::new();
+        voice.insert("engine".into(), self.primary.engine.as_str().into());
+        if let Some(endpoint) = &self.primary.endpoint {
+            voice.insert("endpoint".into(), endpoint.as_str().into());
+        }
+        voice.insert("voice_id".into(), self.voice_id.as_str().into());
+        voice.insert("voice_name".into(), self.voice_name.as_str().into());
+        voice.insert("speed".into(), self.speed.into());
+        voice.insert("output_format".into(), self.output_format.as_str().into());
+        voice.insert("sample_rate".into(), self.sample_rate.into());
+        if let Some(model) = &self.model {
+            voice.insert("model".into(), model.as_str().into());
+        }
+        if let Some(stability) = self.stability {
+            voice.insert("stability".into(), stability.into());
+        }
+        if let Some(similarity_boost) = self.similarity_boost {
+            voice.insert("similarity_boost".into(), similarity_boost.into());
+        }
+        if let Some(fallback) = &self.fallback {
+            voice.insert("fallback_engine".into(), fallback.engine.as_str().into());
+            if let Some(endpoint) = &fallback.endpoint {
+                voice.insert("fallback_endpoint".into(), endpoint.as_str().into());
+            }
+        }
+        serde_json::json!({ "voice": voice })
+    }
+
+    /// The voice, keeping the default voice's gender and language
+    pub fn voice(&self) -> Voice {
+        let default_voice = self.primary.engine.default_voice();
+        Voice {
+            id: self.voice_id.clone(),
+            name: self.voice_name.clone(),
+            ..default_voice
+        }
+    }
+
+    /// Write the profile's engine and voice settings into `config`
+    ///
+    /// Effects, language voices and length limits are left alone.
+    pub fn apply(&self, config: &mut VoiceConfig) {
+        config.engine_type = self.primary.engine.engine_type();
+        config.voice = self.voice();
+        config.speed = self.speed;
+        config.output_format = self.output_format;
+        config.model = self.model.clone();
+        config.endpoint = self.primary.endpoint.clone();
+        config.sample_rate = self.sample_rate;
+        config.stability = self.stability;
+        config.similarity_boost = self.similarity_boost;
+    }
+
+    /// Engines to synthesize with: the primary, then the fallback if any
+    pub fn engines(&self) -> Vec<Box<dyn VoiceEngine>> {
+        std::iter::once(&self.primary)
+            .chain(&self.fallback)
+            .map(EngineSpec::build)
+            .collect()
+    }
+}
+
+fn invalid(field: &str, reason: impl std::fmt::Display) -> ZoeyError {
+    ZoeyError::validation(format!("voice.{}: {}", field, reason))
+}
+
+fn engine_spec(
+    engine: ProfileEngine,
+    endpoint: Option<String>,
+    endpoint_field: &str,
+) -> Result<EngineSpec> {
+    match &endpoint {
+        None if engine.needs_endpoint() => Err(invalid(
+            endpoint_field,
+            format!("required by the {} engine", engine.as_str()),
+        )),
+        Some(url) if !url.starts_with("http://") && !url.starts_with("https://") => Err(invalid(
+            endpoint_field,
+            format!("'{}' is not an http(s) URL", url),
+        )),
+        _ => Ok(EngineSpec { engine, endpoint }),
+    }
+}
+
+/// A string field, `None` when missing or blank
+fn string_field(voice: &Map<String, Value>, field: &str) -> Result<Option<String>> {
+    match voice.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) if s.trim().is_empty() => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.trim().to_string())),
+        Some(other) => Err(invalid(field, format!("expected a string, got {}", other))),
+    }
+}
+
+/// A number field, also accepting numeric strings
+fn number_field(voice: &Map<String, Value>, field: &str) -> Result<Option<f64>> {
+    match voice.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => Ok(n.as_f64()),
+        Some(Value::String(s)) if s.trim().is_empty() => Ok(None),
+        Some(Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(Some)
+            .ok_or_else(|| invalid(field, format!("'{}' is not a number", s))),
+        Some(other) => Err(invalid(field, format!("expected a number, got {}", other))),
+    }
+}
+
+/// A number field between 0.0 and 1.0
+fn unit_field(voice: &Map<String, Value>, field: &str) -> Result<Option<f32>> {
+    match number_field(voice, field)? {
+        Some(n) if !(0.0..=1.0).contains(&n) => {
+            Err(invalid(field, format!("{} is outside 0.0 to 1.0", n)))
+        }
+        n => Ok(n.map(|n| n as f32)),
+    }
+}
+
+fn parse_format(name: &str) -> Result<AudioFormat> {
+    [
+        AudioFormat::Mp3,
+        AudioFormat::Opus,
+        AudioFormat::Aac,
+        AudioFormat::Flac,
+        AudioFormat::Wav,
+        AudioFormat::Pcm,
+    ]
+    .into_iter()
+    .find(|format| format.as_str().eq_ignore_ascii_case(name))
+    .ok_or_else(|| {
+        invalid(
+            "output_format",
+            format!(
+                "unknown format '{}' (expected mp3, opus, aac, flac, wav or pcm)",
+                name
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VoicePlugin;
+    use serde_json::json;
+
+    async fn engine_name(plugin: &VoicePlugin) -> String {
+        plugin.tts_engine.read().await.name().to_string()
+    }
+
+    /// Settings → profile → settings → profile gives the same profile
+    fn round_trip(settings: Value) -> VoiceProfile {
+        let profile = VoiceProfile::from_settings(&settings).unwrap();
+        let reread = VoiceProfile::from_settings(&profile.to_settings()).unwrap();
+        assert_eq!(reread, profile);
+        profile
+    }
+
+    #[tokio::test]
+    async fn test_openai_profile_round_trip() {
+        let settings = json!({
+            "voice": {
+                "engine": "openai",
+                "model": "tts-1-hd",
+                "voice_id": "nova",
+                "voice_name": "Nova",
+                "speed": "1.25",
+                "output_format": "opus"
+            }
+        });
+        let profile = round_trip(settings.clone());
+        assert_eq!(profile.primary.engine, ProfileEngine::OpenAI);
+        assert_eq!(profile.speed, 1.25);
+        assert_eq!(profile.sample_rate, 24000);
+
+        let plugin = VoicePlugin::from_character_settings(&settings).unwrap();
+        let config = plugin.tts_config();
+        assert_eq!(config.engine_type, VoiceEngineType::OpenAI);
+        assert_eq!(config.voice.id, "nova");
+        assert_eq!(config.voice.name, "Nova");
+        assert_eq!(config.output_format, AudioFormat::Opus);
+        assert_eq!(config.model.as_deref(), Some("tts-1-hd"));
+        assert_eq!(engine_name(&plugin).await, "openai");
+        assert!(plugin.engine_status().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_piper_profile_with_fallback_round_trip() {
+        let settings = json!({
+            "voice": {
+                "engine": "piper",
+                "local_endpoint": "http://localhost:5500",
+                "voice_id": "en_US-amy-medium",
+                "voice_name": "Amy",
+                "speed": 0.9,
+                "output_format": "WAV",
+                "fallback_engine": "openai",
+                "effects": ["robot"]
+            }
+        });
+        let profile = round_trip(settings.clone());
+        assert_eq!(
+            profile.primary,
+            EngineSpec {
+                engine: ProfileEngine::Piper,
+                endpoint: Some("http://localhost:5500".to_string()),
+            }
+        );
+        assert_eq!(profile.sample_rate, 22050);
+        assert_eq!(profile.output_format, AudioFormat::Wav);
+
+        let plugin = VoicePlugin::from_character_settings(&settings).unwrap();
+        assert_eq!(plugin.engine_type(), VoiceEngineType::Local);
+        assert_eq!(plugin.tts_config().voice.name, "Amy");
+        assert_eq!(plugin.tts_config().effects.len(), 1);
+        assert_eq!(engine_name(&plugin).await, "piper");
+        assert_eq!(plugin.engine_status().len(), 2);
+        plugin.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_elevenlabs_profile_round_trip() {
+        let settings = json!({
+            "voice": {
+                "engine": "ElevenLabs",
+                "voice_id": "AZnzlk1XvdvUeBnXmlld",
+                "stability": 0.4,
+                "similarity_boost": "0.8"
+            }
+        });
+        let profile = round_trip(settings.clone());
+        assert_eq!(profile.primary.engine, ProfileEngine::ElevenLabs);
+        assert_eq!(profile.voice_name, "AZnzlk1XvdvUeBnXmlld");
+        assert_eq!(profile.stability, Some(0.4));
+        assert_eq!(profile.similarity_boost, Some(0.8));
+
+        let plugin = VoicePlugin::from_character_settings(&settings).unwrap();
+        assert_eq!(plugin.engine_type(), VoiceEngineType::ElevenLabs);
+        assert_eq!(engine_name(&plugin).await, "elevenlabs");
+
+        // No voice section: the default OpenAI voice
+        let profile = round_trip(json!({ "name": "zoey" }));
+        assert_eq!(profile, VoiceProfile::default());
+        assert_eq!(profile.voice_id, "shimmer");
+    }
+
+    #[test]
+    fn test_errors_name_the_field() {
+        let error = |voice: Value| {
+            VoiceProfile::from_settings(&json!({ "voice": voice }))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error(json!({ "engine": "espeak" })).contains("voice.engine"));
+        assert!(error(json!({ "engine": "piper" })).contains("voice.endpoint"));
+        assert!(
+            error(json!({ "engine": "local", "endpoint": "localhost:5000" }))
+                .contains("voice.endpoint")
+        );
+        assert!(error(json!({ "speed": 9 })).contains("voice.speed"));
+        assert!(error(json!({ "speed": "fast" })).contains("voice.speed"));
+        assert!(error(json!({ "voice_id": 7 })).contains("voice.voice_id"));
+        assert!(error(json!({ "output_format": "ogg" })).contains("voice.output_format"));
+        assert!(error(json!({ "sample_rate": 22050.5 })).contains("voice.sample_rate"));
+        assert!(error(json!({ "stability": 2 })).contains("voice.stability"));
+        assert!(error(json!({ "fallback_engine": "piper" })).contains("voice.fallback_endpoint"));
+        assert!(VoiceProfile::from_settings(&json!({ "voice": "shimmer" })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_switches_engine_and_voice() {
+        let mut plugin = VoicePlugin::from_character_settings(&json!({
+            "voice": { "engine": "openai", "voice_id": "onyx" }
+        }))
+        .unwrap();
+        plugin.set_length_limit(500, Default::default());
+
+        plugin
+            .reconfigure(&json!({
+                "voice": {
+                    "engine": "piper",
+                    "endpoint": "http://localhost:5500",
+                    "fallback_engine": "elevenlabs"
+                }
+            }))
+            .unwrap();
+        assert_eq!(engine_name(&plugin).await, "piper");
+        assert_eq!(plugin.tts_config().voice.id, "en_US-lessac-medium");
+        assert_eq!(plugin.tts_config().sample_rate, 22050);
+        assert_eq!(plugin.tts_config().max_tts_chars, 500);
+        assert_eq!(plugin.engine_status().len(), 2);
+
+        // Invalid settings leave the plugin as it was
+        assert!(plugin
+            .reconfigure(&json!({ "voice": { "engine": "elevenlabs", "speed": 0 } }))
+            .is_err());
+        assert_eq!(engine_name(&plugin).await, "piper");
+
+        plugin
+            .reconfigure(&json!({ "voice": { "engine": "elevenlabs" } }))
+            .unwrap();
+        assert_eq!(engine_name(&plugin).await, "elevenlabs");
+        assert_eq!(plugin.tts_config().voice.name, "Rachel");
+        assert!(plugin.engine_status().is_empty());
+    }
+}
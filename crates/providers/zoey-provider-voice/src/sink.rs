@@ -0,0 +1,185 @@
+//! Per-platform audio output requirements
+//!
+//! Every place synthesized speech ends up (a Discord voice call, a Telegram
+//! voice note, the web UI player) accepts a different set of formats.
+//! [`SinkFormat`] names those requirements in one place and
+//! [`VoicePlugin::synthesize_for`](crate::VoicePlugin::synthesize_for) asks
+//! the engine for a format the sink accepts, converting afterwards with
+//! [`transcode`] when the engine cannot produce one.
+//!
+//! Conversion is limited to uncompressed input (PCM/WAV), the same as
+//! [`audio::normalize`](crate::audio::normalize); compressed audio the sink
+//! does not accept is rejected rather than passed through. Voice notes are
+//! encoded to Ogg/Opus with [`AudioData::transcode`].
+
+use crate::audio::normalize;
+use crate::transcode::OPUS_SAMPLE_RATE;
+use crate::types::{AudioData, AudioFormat, AudioSpec, VoiceError};
+use zoey_core::Result;
+
+/// Where synthesized audio is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkFormat {
+    /// Discord voice call: 48kHz stereo 16-bit PCM
+    DiscordVoice,
+    /// Telegram voice note: Opus in an Ogg container
+    TelegramVoice,
+    /// Telegram audio file: MP3, or WAV when the engine has no MP3
+    TelegramAudio,
+    /// Web UI player: MP3 or WAV
+    Web,
+}
+
+impl SinkFormat {
+    /// Sink name for logs and errors
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DiscordVoice => "discord-voice",
+            Self::TelegramVoice => "telegram-voice",
+            Self::TelegramAudio => "telegram-audio",
+            Self::Web => "web",
+        }
+    }
+
+    /// Formats the sink plays as-is, most preferred first
+    pub fn accepted_formats(self) -> &'static [AudioFormat] {
+        match self {
+            Self::DiscordVoice => &[AudioFormat::Pcm],
+            Self::TelegramVoice => &[AudioFormat::Opus],
+            Self::TelegramAudio | Self::Web => &[AudioFormat::Mp3, AudioFormat::Wav],
+        }
+    }
+
+    /// Sample rate and channel count the sink requires, if it dictates one
+    pub fn required_layout(self) -> Option<(u32, u16)> {
+        match self {
+            Self::DiscordVoice => Some((48_000, 2)),
+            _ => None,
+        }
+    }
+
+    /// Whether `audio` can be handed to the sink unchanged
+    pub fn accepts(self, audio: &AudioData) -> bool {
+        let layout_ok = match self.required_layout() {
+            Some((rate, channels)) => audio.sample_rate == rate && audio.channels == channels,
+            None => true,
+        };
+        layout_ok && self.accepted_formats().contains(&audio.format)
+    }
+
+    /// Format to request from an engine supporting `supported`
+    ///
+    /// Prefers a format the sink accepts, then uncompressed output that
+    /// [`transcode`] can convert. `None` leaves the engine's configured format.
+    pub fn engine_format(self, supported: &[AudioFormat]) -> Option<AudioFormat> {
+        self.accepted_formats()
+            .iter()
+            .chain(&[AudioFormat::Wav, AudioFormat::Pcm])
+            .find(|f| supported.contains(f))
+            .copied()
+    }
+
+    /// Spec uncompressed audio is converted to
+    fn conversion_target(self, audio: &AudioData) -> AudioSpec {
+        match self {
+            Self::DiscordVoice => AudioSpec::new(48_000, 2, AudioFormat::Pcm),
+            Self::TelegramVoice => AudioSpec::new(OPUS_SAMPLE_RATE, 1, AudioFormat::Opus),
+            Self::TelegramAudio | Self::Web => {
+                AudioSpec::new(audio.sample_rate, audio.channels.max(1), AudioFormat::Wav)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SinkFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Convert `audio` to what `sink` requires
+///
+/// Returns the input unchanged when the sink accepts it. Fails with
+/// [`VoiceError::InvalidInput`] when the audio is compressed in a format the
+/// sink does not take.
+pub fn transcode(audio: &AudioData, sink: SinkFormat) -> Result<AudioData> {
+    if sink.accepts(audio) {
+        return Ok(audio.clone());
+    }
+    if !matches!(audio.format, AudioFormat::Pcm | AudioFormat::Wav) {
+        return Err(VoiceError::InvalidInput(format!(
+            "{} audio cannot be converted for {} (accepts {})",
+            audio.format.as_str(),
+            sink,
+            sink.accepted_formats()
+                .iter()
+                .map(|f| f.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .into());
+    }
+    let target = sink.conversion_target(audio);
+    if target.format == AudioFormat::Opus {
+        audio.transcode(AudioFormat::Opus)
+    } else {
+        normalize(audio, &target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{decode_wav, samples_to_pcm16};
+    use bytes::Bytes;
+
+    fn pcm(samples: &[i16], sample_rate: u32, channels: u16) -> AudioData {
+        AudioData::new(Bytes::from(samples_to_pcm16(samples)), AudioFormat::Pcm, sample_rate)
+            .with_channels(channels)
+    }
+
+    #[test]
+    fn test_accepted_audio_passes_through() {
+        let mp3 = AudioData::new(Bytes::from_static(b"ID3"), AudioFormat::Mp3, 44100);
+        assert_eq!(transcode(&mp3, SinkFormat::Web).unwrap().data, mp3.data);
+        let opus = AudioData::new(Bytes::from_static(b"OggS"), AudioFormat::Opus, 48000);
+        assert_eq!(transcode(&opus, SinkFormat::TelegramVoice).unwrap().data, opus.data);
+    }
+
+    #[test]
+    fn test_pcm_converted_for_discord() {
+        let out = transcode(&pcm(&[500; 240], 24000, 1), SinkFormat::DiscordVoice).unwrap();
+        assert_eq!(out.spec(), AudioSpec::new(48000, 2, AudioFormat::Pcm));
+        assert!(SinkFormat::DiscordVoice.accepts(&out));
+    }
+
+    #[test]
+    fn test_pcm_wrapped_as_wav_for_web() {
+        let out = transcode(&pcm(&[7; 160], 16000, 1), SinkFormat::Web).unwrap();
+        assert_eq!(out.format, AudioFormat::Wav);
+        let wav = decode_wav(&out.data).unwrap();
+        assert_eq!((wav.sample_rate, wav.channels, wav.samples.len()), (16000, 1, 160));
+    }
+
+    #[test]
+    fn test_unconvertible_audio_rejected() {
+        let mp3 = AudioData::new(Bytes::from_static(b"ID3"), AudioFormat::Mp3, 44100);
+        assert!(transcode(&mp3, SinkFormat::TelegramVoice).is_err());
+        assert!(transcode(&mp3, SinkFormat::DiscordVoice).is_err());
+        assert_eq!(
+            transcode(&pcm(&[0; 10], 24000, 1), SinkFormat::TelegramVoice).unwrap().format,
+            AudioFormat::Opus
+        );
+    }
+
+    #[test]
+    fn test_engine_format_preference() {
+        let openai = [AudioFormat::Mp3, AudioFormat::Opus, AudioFormat::Wav, AudioFormat::Pcm];
+        assert_eq!(SinkFormat::TelegramVoice.engine_format(&openai), Some(AudioFormat::Opus));
+        assert_eq!(SinkFormat::Web.engine_format(&openai), Some(AudioFormat::Mp3));
+        assert_eq!(SinkFormat::DiscordVoice.engine_format(&openai), Some(AudioFormat::Pcm));
+        // WAV-only engine (e.g. Piper) is asked for WAV and converted afterwards
+        assert_eq!(SinkFormat::DiscordVoice.engine_format(&[AudioFormat::Wav]), Some(AudioFormat::Wav));
+        assert_eq!(SinkFormat::TelegramVoice.engine_format(&[AudioFormat::Mp3]), None);
+    }
+}
@@ -0,0 +1,561 @@
+//! Speaker enrollment and verification
+//!
+//! Anyone in a voice channel can speak a command, so some commands should
+//! only be accepted from a recognized voice. A [`SpeakerRegistry`] keeps one
+//! voice embedding per enrolled user (the normalized average of the
+//! embeddings of a few enrollment utterances) and scores new audio against it
+//! by cosine similarity; scores at or above the threshold are accepted.
+//!
+//! With the `speaker-id` feature embeddings come from an ONNX speaker model
+//! run via `ort`, taking 16kHz mono waveform samples in `[-1, 1]` with shape
+//! `[1, samples]` and returning one embedding vector (e.g. an ECAPA-TDNN
+//! export). Any [`SpeakerEmbeddingModel`] can be plugged in instead, which is
+//! how the registry is tested.
+//!
+//! Enrollments persist through an [`EnrollmentStore`]: a directory of JSON
+//! files ([`DirectoryEnrollmentStore`]) or entity components in the database
+//! ([`AdapterEnrollmentStore`]).
+
+use crate::audio::{normalize, pcm16_to_samples};
+use crate::types::{AudioData, AudioSpec, VoiceError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use zoey_core::types::Component;
+use zoey_core::{IDatabaseAdapter, Result};
+
+/// Sample rate speaker models operate on (Hz)
+pub const SPEAKER_SAMPLE_RATE: u32 = 16000;
+
+/// Default similarity (cosine, -1.0-1.0) at or above which a speaker is accepted
+pub const DEFAULT_VERIFY_THRESHOLD: f32 = 0.7;
+
+/// Default number of utterances recorded for an enrollment
+pub const DEFAULT_ENROLL_UTTERANCES: usize = 3;
+
+/// Component type used to persist enrollments
+const SPEAKER_COMPONENT_TYPE: &str = "voice_speaker_embedding";
+
+/// Speaker verification settings
+#[derive(Debug, Clone)]
+pub struct SpeakerConfig {
+    /// ONNX speaker-embedding model
+    pub model_path: PathBuf,
+    /// Similarity at or above which a speaker is accepted
+    pub threshold: f32,
+    /// Directory enrollments are kept in
+    pub enrollment_dir: PathBuf,
+}
+
+impl Default for SpeakerConfig {
+    fn default() -> Self {
+        Self {
+            model_path: PathBuf::from(".zoey/voice/speaker/speaker_embedding.onnx"),
+            threshold: DEFAULT_VERIFY_THRESHOLD,
+            enrollment_dir: PathBuf::from(".zoey/voice/speakers"),
+        }
+    }
+}
+
+/// Outcome of checking audio against an enrolled voice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerificationResult {
+    /// Cosine similarity to the enrolled voice (0.0 when not enrolled)
+    pub score: f32,
+    /// Whether `score` reached the threshold
+    pub accepted: bool,
+}
+
+/// Inference backend turning speech into a voice embedding
+pub trait SpeakerEmbeddingModel: Send {
+    /// Embed 16kHz mono samples in `[-1, 1]`
+    fn embed(&mut self, samples: &[f32]) -> Result<Vec<f32>>;
+}
+
+/// `v` scaled to unit length (unchanged when all zeros)
+fn unit(v: &[f32]) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return v.to_vec();
+    }
+    v.iter().map(|x| x / norm).collect()
+}
+
+/// Cosine similarity of two embeddings (0.0 on a length mismatch or zero vector)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// Unit-length mean of unit-length embeddings, so each utterance weighs the same
+pub fn average_embedding(embeddings: &[Vec<f32>]) -> Result<Vec<f32>> {
+    let first = embeddings
+        .first()
+        .ok_or_else(|| VoiceError::InvalidInput("no enrollment samples".to_string()))?;
+    let mut sum = vec![0.0; first.len()];
+    for embedding in embeddings {
+        if embedding.len() != sum.len() {
+            return Err(VoiceError::ModelError(format!(
+                "speaker embeddings differ in size ({} vs {})",
+                embedding.len(),
+                sum.len()
+            ))
+            .into());
+        }
+        for (total, v) in sum.iter_mut().zip(unit(embedding)) {
+            *total += v;
+        }
+    }
+    Ok(unit(&sum))
+}
+
+/// Storage backend for enrolled voice embeddings
+#[async_trait]
+pub trait EnrollmentStore: Send + Sync {
+    /// Embedding enrolled for a user, `None` when not enrolled
+    async fn load(&self, user_id: u64) -> Result<Option<Vec<f32>>>;
+
+    /// Persist a user's embedding, replacing any earlier one
+    async fn save(&self, user_id: u64, embedding: &[f32]) -> Result<()>;
+
+    /// Forget a user's embedding
+    async fn remove(&self, user_id: u64) -> Result<()>;
+}
+
+/// In-memory enrollment store (enrollments are lost on restart)
+#[derive(Default)]
+pub struct MemoryEnrollmentStore {
+    embeddings: RwLock<HashMap<u64, Vec<f32>>>,
+}
+
+#[async_trait]
+impl EnrollmentStore for MemoryEnrollmentStore {
+    async fn load(&self, user_id: u64) -> Result<Option<Vec<f32>>> {
+        Ok(self.embeddings.read().unwrap().get(&user_id).cloned())
+    }
+
+    async fn save(&self, user_id: u64, embedding: &[f32]) -> Result<()> {
+        self.embeddings
+            .write()
+            .unwrap()
+            .insert(user_id, embedding.to_vec());
+        Ok(())
+    }
+
+    async fn remove(&self, user_id: u64) -> Result<()> {
+        self.embeddings.write().unwrap().remove(&user_id);
+        Ok(())
+    }
+}
+
+/// Enrollment store keeping `<user_id>.json` files in a directory
+pub struct DirectoryEnrollmentStore {
+    dir: PathBuf,
+}
+
+impl DirectoryEnrollmentStore {
+    /// Store enrollments in `dir`, created on first save
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, user_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", user_id))
+    }
+}
+
+fn io_error(path: &std::path::Path, e: impl std::fmt::Display) -> zoey_core::ZoeyError {
+    VoiceError::Other(format!("speaker enrollment {}: {}", path.display(), e)).into()
+}
+
+#[async_trait]
+impl EnrollmentStore for DirectoryEnrollmentStore {
+    async fn load(&self, user_id: u64) -> Result<Option<Vec<f32>>> {
+        let path = self.path(user_id);
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(&path, e)),
+        };
+        let value: serde_json::Value =
+            serde_json::from_slice(&bytes).map_err(|e| io_error(&path, e))?;
+        let embedding =
+            serde_json::from_value(value["embedding"].clone()).map_err(|e| io_error(&path, e))?;
+        Ok(Some(embedding))
+    }
+
+    async fn save(&self, user_id: u64, embedding: &[f32]) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| io_error(&self.dir, e))?;
+        let path = self.path(user_id);
+        let json = serde_json::json!({ "user_id": user_id, "embedding": embedding });
+        tokio::fs::write(&path, json.to_string())
+            .await
+            .map_err(|e| io_error(&path, e))
+    }
+
+    async fn remove(&self, user_id: u64) -> Result<()> {
+        let path = self.path(user_id);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+}
+
+/// Enrollment store backed by the runtime's database adapter (entity components)
+pub struct AdapterEnrollmentStore {
+    adapter: Arc<dyn IDatabaseAdapter + Send + Sync>,
+    /// Platform the user IDs belong to (e.g. "discord")
+    platform: String,
+}
+
+impl AdapterEnrollmentStore {
+    /// Store enrollments for `platform` user IDs through `adapter`
+    pub fn new(adapter: Arc<dyn IDatabaseAdapter + Send + Sync>, platform: &str) -> Self {
+        Self {
+            adapter,
+            platform: platform.to_string(),
+        }
+    }
+
+    fn entity_id(&self, user_id: u64) -> zoey_core::Uuid {
+        zoey_core::string_to_uuid(&format!("{}-user-{}", self.platform, user_id))
+    }
+
+    fn world_id(&self) -> zoey_core::Uuid {
+        zoey_core::string_to_uuid(&format!("{}-speakers", self.platform))
+    }
+
+    async fn find(&self, user_id: u64) -> Result<Option<Component>> {
+        self.adapter
+            .get_component(
+                self.entity_id(user_id),
+                SPEAKER_COMPONENT_TYPE,
+                Some(self.world_id()),
+                None,
+            )
+            .await
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[async_trait]
+impl EnrollmentStore for AdapterEnrollmentStore {
+    async fn load(&self, user_id: u64) -> Result<Option<Vec<f32>>> {
+        Ok(self
+            .find(user_id)
+            .await?
+            .and_then(|c| serde_json::from_value(c.data["embedding"].clone()).ok()))
+    }
+
+    async fn save(&self, user_id: u64, embedding: &[f32]) -> Result<()> {
+        let data = serde_json::json!({ "embedding": embedding });
+        let now = unix_now();
+        match self.find(user_id).await? {
+            Some(mut existing) => {
+                existing.data = data;
+                existing.updated_at = Some(now);
+                self.adapter.update_component(&existing).await
+            }
+            None => {
+                let component = Component {
+                    id: zoey_core::Uuid::new_v4(),
+                    entity_id: self.entity_id(user_id),
+                    world_id: self.world_id(),
+                    source_entity_id: None,
+                    component_type: SPEAKER_COMPONENT_TYPE.to_string(),
+                    data,
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                };
+                self.adapter.create_component(&component).await.map(|_| ())
+            }
+        }
+    }
+
+    async fn remove(&self, user_id: u64) -> Result<()> {
+        match self.find(user_id).await? {
+            Some(existing) => self.adapter.delete_component(existing.id).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Enrolled voices and the model that embeds speech
+pub struct SpeakerRegistry {
+    model: Mutex<Box<dyn SpeakerEmbeddingModel>>,
+    store: Arc<dyn EnrollmentStore>,
+    threshold: f32,
+    /// Embeddings loaded from the store (`None`: known not enrolled)
+    cache: RwLock<HashMap<u64, Option<Vec<f32>>>>,
+}
+
+impl SpeakerRegistry {
+    /// Load the ONNX model from `config.model_path`
+    #[cfg(feature = "speaker-id")]
+    pub fn load(config: &SpeakerConfig, store: Arc<dyn EnrollmentStore>) -> Result<Self> {
+        let model = onnx::OnnxSpeakerModel::load(&config.model_path)?;
+        Ok(Self::with_model(Box::new(model), store, config.threshold))
+    }
+
+    /// Build a registry around any embedding backend
+    pub fn with_model(
+        model: Box<dyn SpeakerEmbeddingModel>,
+        store: Arc<dyn EnrollmentStore>,
+        threshold: f32,
+    ) -> Self {
+        Self {
+            model: Mutex::new(model),
+            store,
+            threshold: threshold.clamp(-1.0, 1.0),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Acceptance threshold
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Embed one utterance (any PCM/WAV layout)
+    pub fn embed(&self, audio: &AudioData) -> Result<Vec<f32>> {
+        let mono = normalize(audio, &AudioSpec::pcm_mono(SPEAKER_SAMPLE_RATE))?;
+        let samples: Vec<f32> = pcm16_to_samples(&mono.data)
+            .into_iter()
+            .map(|s| s as f32 / 32768.0)
+            .collect();
+        if samples.is_empty() {
+            return Err(VoiceError::InvalidInput("empty speaker sample".to_string()).into());
+        }
+        self.model.lock().unwrap().embed(&samples)
+    }
+
+    /// Enroll `user_id` with the averaged embedding of `samples`, replacing
+    /// any earlier enrollment
+    pub async fn enroll(&self, user_id: u64, samples: Vec<AudioData>) -> Result<()> {
+        let embeddings = samples
+            .iter()
+            .map(|audio| self.embed(audio))
+            .collect::<Result<Vec<_>>>()?;
+        let embedding = average_embedding(&embeddings)?;
+        self.store.save(user_id, &embedding).await?;
+        self.cache.write().unwrap().insert(user_id, Some(embedding));
+        tracing::info!(user_id = %user_id, samples = samples.len(), "Speaker enrolled");
+        Ok(())
+    }
+
+    /// Score `audio` against the voice enrolled for `user_id`
+    ///
+    /// Users without an enrollment are never accepted.
+    pub async fn verify(&self, user_id: u64, audio: &AudioData) -> Result<VerificationResult> {
+        let Some(enrolled) = self.enrollment(user_id).await? else {
+            return Ok(VerificationResult {
+                score: 0.0,
+                accepted: false,
+            });
+        };
+        let score = cosine_similarity(&enrolled, &self.embed(audio)?);
+        Ok(VerificationResult {
+            score,
+            accepted: score >= self.threshold,
+        })
+    }
+
+    /// Whether `user_id` has an enrolled voice
+    pub async fn is_enrolled(&self, user_id: u64) -> Result<bool> {
+        Ok(self.enrollment(user_id).await?.is_some())
+    }
+
+    /// Forget the voice enrolled for `user_id`
+    pub async fn remove(&self, user_id: u64) -> Result<()> {
+        self.store.remove(user_id).await?;
+        self.cache.write().unwrap().insert(user_id, None);
+        Ok(())
+    }
+
+    async fn enrollment(&self, user_id: u64) -> Result<Option<Vec<f32>>> {
+        if let Some(cached) = self.cache.read().unwrap().get(&user_id) {
+            return Ok(cached.clone());
+        }
+        let loaded = self.store.load(user_id).await?;
+        self.cache.write().unwrap().insert(user_id, loaded.clone());
+        Ok(loaded)
+    }
+}
+
+#[cfg(feature = "speaker-id")]
+mod onnx {
+    //! Speaker-embedding inference: waveform -> embedding
+
+    use super::SpeakerEmbeddingModel;
+    use crate::types::VoiceError;
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::path::Path;
+    use zoey_core::Result;
+
+    fn model_error(e: impl std::fmt::Display) -> zoey_core::ZoeyError {
+        VoiceError::ModelError(e.to_string()).into()
+    }
+
+    pub(super) struct OnnxSpeakerModel {
+        session: Session,
+    }
+
+    impl OnnxSpeakerModel {
+        pub(super) fn load(path: &Path) -> Result<Self> {
+            if !path.exists() {
+                return Err(model_error(format!(
+                    "speaker-embedding model not found at {}",
+                    path.display()
+                )));
+            }
+            let session = Session::builder()
+                .map_err(model_error)?
+                .commit_from_file(path)
+                .map_err(|e| model_error(format!("{}: {}", path.display(), e)))?;
+            Ok(Self { session })
+        }
+    }
+
+    impl SpeakerEmbeddingModel for OnnxSpeakerModel {
+        fn embed(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+            let input = Tensor::from_array((vec![1, samples.len()], samples.to_vec()))
+                .map_err(model_error)?;
+            let outputs = self.session.run(ort::inputs![input]).map_err(model_error)?;
+            let (_, values) = outputs[0]
+                .try_extract_tensor::<f32>()
+                .map_err(model_error)?;
+            Ok(values.to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AudioFormat;
+    use bytes::Bytes;
+
+    /// Embeds audio by its first sample, so fixtures pick their embedding
+    struct FixtureModel {
+        embeddings: HashMap<i16, Vec<f32>>,
+    }
+
+    impl SpeakerEmbeddingModel for FixtureModel {
+        fn embed(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+            let key = (samples[0] * 32768.0).round() as i16;
+            Ok(self.embeddings[&key].clone())
+        }
+    }
+
+    /// 16kHz mono clip whose samples are all `key`
+    fn clip(key: i16) -> AudioData {
+        let samples = vec![key; 1600];
+        AudioData::new(
+            Bytes::from(crate::audio::samples_to_pcm16(&samples)),
+            AudioFormat::Pcm,
+            SPEAKER_SAMPLE_RATE,
+        )
+    }
+
+    fn registry(store: Arc<dyn EnrollmentStore>) -> SpeakerRegistry {
+        let embeddings = HashMap::from([
+            (1, vec![1.0, 0.0, 0.0]),
+            (2, vec![0.0, 2.0, 0.0]),
+            (3, vec![1.0, 1.0, 0.0]),
+            (4, vec![0.0, 0.0, 1.0]),
+            (5, vec![0.9, 1.1, 0.1]),
+        ]);
+        SpeakerRegistry::with_model(Box::new(FixtureModel { embeddings }), store, 0.8)
+    }
+
+    #[test]
+    fn test_average_is_normalized_mean() {
+        let avg = average_embedding(&[vec![2.0, 0.0], vec![0.0, 5.0]]).unwrap();
+        let half = 1.0 / 2f32.sqrt();
+        assert!((avg[0] - half).abs() < 1e-6 && (avg[1] - half).abs() < 1e-6);
+
+        assert!(average_embedding(&[]).is_err());
+        assert!(average_embedding(&[vec![1.0, 0.0], vec![1.0]]).is_err());
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0]), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_threshold_decisions() {
+        let registry = registry(Arc::new(MemoryEnrollmentStore::default()));
+        // Not enrolled: never accepted
+        let result = registry.verify(7, &clip(1)).await.unwrap();
+        assert_eq!(
+            result,
+            VerificationResult {
+                score: 0.0,
+                accepted: false
+            }
+        );
+
+        registry.enroll(7, vec![clip(1), clip(2)]).await.unwrap();
+        assert!(registry.is_enrolled(7).await.unwrap());
+
+        // Same direction as the averaged enrollment
+        let same = registry.verify(7, &clip(3)).await.unwrap();
+        assert!((same.score - 1.0).abs() < 1e-6 && same.accepted);
+        // Close enough
+        let close = registry.verify(7, &clip(5)).await.unwrap();
+        assert!(close.accepted, "{:?}", close);
+        // One enrollment sample alone scores cos 45° ≈ 0.707, under 0.8
+        let partial = registry.verify(7, &clip(1)).await.unwrap();
+        assert!(
+            (partial.score - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-3 && !partial.accepted
+        );
+        // A different voice
+        let other = registry.verify(7, &clip(4)).await.unwrap();
+        assert!(other.score.abs() < 1e-6 && !other.accepted);
+
+        // Compressed audio cannot be embedded
+        let mp3 = AudioData::new(Bytes::from_static(b"ID3"), AudioFormat::Mp3, 44100);
+        assert!(registry.verify(7, &mp3).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directory_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("zoey-speakers-{}", std::process::id()));
+        let store = Arc::new(DirectoryEnrollmentStore::new(&dir));
+        registry(store.clone())
+            .enroll(42, vec![clip(1), clip(2)])
+            .await
+            .unwrap();
+        assert!(dir.join("42.json").exists());
+
+        // A fresh registry (e.g. after a restart) reads it back
+        let reloaded = registry(Arc::new(DirectoryEnrollmentStore::new(&dir)));
+        assert!(reloaded.verify(42, &clip(3)).await.unwrap().accepted);
+        assert!(!reloaded.is_enrolled(43).await.unwrap());
+
+        reloaded.remove(42).await.unwrap();
+        assert!(!reloaded.is_enrolled(42).await.unwrap());
+        assert_eq!(store.load(42).await.unwrap(), None);
+        // Removing twice is fine
+        store.remove(42).await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
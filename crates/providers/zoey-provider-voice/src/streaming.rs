@@ -0,0 +1,536 @@
+//! Chunked TTS streams and playback as they arrive
+//!
+//! [`VoicePlugin::synthesize_stream_chunks`](crate::VoicePlugin::synthesize_stream_chunks)
+//! gives every engine the same [`AudioStream`]: engines that stream natively
+//! pass audio on while it is synthesized, the others are [`slice`]d once the
+//! clip is done. Each chunk names its format and sample rate; raw PCM is
+//! 16-bit mono, WAV carries its layout in the header at the start of the
+//! stream.
+//!
+//! [`playback`] converts a PCM or WAV stream into the interleaved `f32`
+//! samples songbird's `RawAdapter` reads, ready as soon as the first chunk is
+//! in, so a voice channel can start playing before synthesis has finished.
+
+use crate::audio::{decode_wav, samples_to_pcm16, wav_header};
+use crate::types::{
+    create_audio_stream, AudioChunk, AudioData, AudioFormat, AudioStream, VoiceError,
+};
+use bytes::Bytes;
+use std::io::{self, Read};
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::warn;
+use zoey_core::Result;
+
+/// Chunk length used when a caller has no preference (ms)
+pub const DEFAULT_CHUNK_MS: u32 = 200;
+
+/// Split finished audio into a stream of `chunk_ms` chunks
+///
+/// PCM and WAV are cut at frame boundaries; WAV, and PCM with more than one
+/// channel, goes out as WAV with the header in front of the first chunk.
+/// Encoded formats can't be cut at arbitrary bytes and become one chunk.
+pub fn slice(audio: &AudioData, chunk_ms: u32) -> AudioStream {
+    let pcm = match audio.format {
+        AudioFormat::Pcm => Some((audio.sample_rate, audio.channels.max(1), audio.data.clone())),
+        AudioFormat::Wav => decode_wav(&audio.data).ok().map(|pcm| {
+            let data = Bytes::from(samples_to_pcm16(&pcm.samples));
+            (pcm.sample_rate, pcm.channels, data)
+        }),
+        _ => None,
+    };
+    let Some((sample_rate, channels, data)) = pcm else {
+        let (tx, stream) = create_audio_stream(1);
+        let _ = tx.try_send(Ok(AudioChunk {
+            data: audio.data.clone(),
+            index: 0,
+            is_final: true,
+            timestamp_ms: Some(0),
+            format: Some(audio.format),
+            sample_rate: Some(audio.sample_rate),
+        }));
+        return stream;
+    };
+
+    let raw = audio.format == AudioFormat::Pcm && channels == 1;
+    let format = if raw {
+        AudioFormat::Pcm
+    } else {
+        AudioFormat::Wav
+    };
+    let frame_bytes = channels as usize * 2;
+    let frames = (sample_rate as usize * chunk_ms.max(1) as usize / 1000).max(1);
+    let chunk_bytes = frames * frame_bytes;
+    let mut pieces: Vec<Bytes> = (0..data.len())
+        .step_by(chunk_bytes)
+        .map(|start| data.slice(start..(start + chunk_bytes).min(data.len())))
+        .collect();
+    if pieces.is_empty() {
+        pieces.push(Bytes::new());
+    }
+    if !raw {
+        let header = wav_header(sample_rate, channels, data.len() as u32);
+        pieces[0] = [header.as_slice(), &pieces[0][..]].concat().into();
+    }
+
+    let total = pieces.len();
+    let (tx, stream) = create_audio_stream(total);
+    for (index, data) in pieces.into_iter().enumerate() {
+        let _ = tx.try_send(Ok(AudioChunk {
+            data,
+            index,
+            is_final: index + 1 == total,
+            timestamp_ms: Some(index as u64 * chunk_ms as u64),
+            format: Some(format),
+            sample_rate: Some(sample_rate),
+        }));
+    }
+    stream
+}
+
+/// Audio of a stream as songbird reads it, available from the first chunk
+pub struct StreamPlayback {
+    /// Interleaved little-endian `f32` samples, what songbird's `RawAdapter` takes
+    pub reader: PcmReader,
+    /// Sample rate (Hz)
+    pub sample_rate: u32,
+    /// Channel count
+    pub channels: u16,
+    /// Time from the start of the stream to its first chunk with audio
+    pub time_to_first_chunk: Duration,
+    /// Length of the whole audio, known once the stream has ended
+    pub length: PlaybackLength,
+}
+
+/// Length of a [`StreamPlayback`]'s audio, reported when its stream ends
+#[derive(Debug)]
+pub struct PlaybackLength(oneshot::Receiver<Duration>);
+
+impl PlaybackLength {
+    /// Wait until the stream has ended and return the audio's length
+    pub async fn wait(self) -> Duration {
+        self.0.await.unwrap_or_default()
+    }
+}
+
+/// Blocking reader of the samples [`playback`] converts
+///
+/// Reads wait for the next chunk and return 0 once the stream is over.
+#[derive(Debug)]
+pub struct PcmReader {
+    /// Locked only so the reader is `Sync`, as symphonia's `MediaSource` requires
+    rx: Mutex<mpsc::Receiver<Vec<u8>>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for PcmReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        while self.pos == self.buf.len() {
+            let rx = self.rx.get_mut().unwrap_or_else(PoisonError::into_inner);
+            match rx.recv() {
+                Ok(buf) => {
+                    self.buf = buf;
+                    self.pos = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Start converting `stream` for playback once its first audio is in
+///
+/// Fails when the stream errors or ends before any audio, or when its
+/// chunks are not PCM or WAV, so callers can fall back to playing the whole
+/// clip. An error later on ends the audio early and is logged.
+pub async fn playback(mut stream: AudioStream) -> Result<StreamPlayback> {
+    let no_audio = || VoiceError::AudioError("TTS stream ended before any audio".to_string());
+    let mut decoder = F32Decoder::default();
+    let first = loop {
+        let chunk = stream.recv().await.ok_or_else(no_audio)??;
+        let samples = decoder.push(&chunk)?;
+        if !samples.is_empty() {
+            break samples;
+        }
+        if chunk.is_final {
+            return Err(no_audio().into());
+        }
+    };
+    let layout = decoder
+        .layout
+        .expect("layout is known once samples are decoded");
+    let time_to_first_chunk = stream.time_to_first_chunk().unwrap_or_default();
+
+    let (tx, rx) = mpsc::channel();
+    let (length_tx, length_rx) = oneshot::channel();
+    let mut total = first.len();
+    let _ = tx.send(first);
+    tokio::spawn(async move {
+        while let Some(chunk) = stream.recv().await {
+            let samples = chunk.and_then(|chunk| {
+                let samples = decoder.push(&chunk)?;
+                Ok((samples, chunk.is_final))
+            });
+            match samples {
+                Ok((samples, is_final)) => {
+                    total += samples.len();
+                    if (!samples.is_empty() && tx.send(samples).is_err()) || is_final {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    warn!(error = %e, "TTS stream failed during playback, ending audio early");
+                    break;
+                }
+            }
+        }
+        drop(tx);
+        let frames = (total / (4 * layout.channels as usize)) as u64;
+        let _ = length_tx.send(Duration::from_nanos(
+            frames * 1_000_000_000 / layout.sample_rate.max(1) as u64,
+        ));
+    });
+
+    Ok(StreamPlayback {
+        reader: PcmReader {
+            rx: Mutex::new(rx),
+            buf: Vec::new(),
+            pos: 0,
+        },
+        sample_rate: layout.sample_rate,
+        channels: layout.channels,
+        time_to_first_chunk,
+        length: PlaybackLength(length_rx),
+    })
+}
+
+/// Sample rate and channels of the PCM in a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PcmLayout {
+    sample_rate: u32,
+    channels: u16,
+}
+
+/// Converts 16-bit chunks to `f32` bytes, whatever their boundaries
+#[derive(Debug, Default)]
+struct F32Decoder {
+    layout: Option<PcmLayout>,
+    /// WAV bytes received before the `data` chunk
+    header: Vec<u8>,
+    /// Half of a sample cut off at the end of the previous chunk
+    carry: Option<u8>,
+}
+
+impl F32Decoder {
+    /// Samples of `chunk` as `f32` bytes, empty while the WAV header is incomplete
+    fn push(&mut self, chunk: &AudioChunk) -> Result<Vec<u8>> {
+        if chunk.data.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.layout.is_some() {
+            return Ok(self.convert(&chunk.data));
+        }
+        match chunk.format {
+            Some(AudioFormat::Pcm) => {
+                let sample_rate = chunk
+                    .sample_rate
+                    .ok_or_else(|| unplayable("PCM chunks without a sample rate".to_string()))?;
+                self.layout = Some(PcmLayout {
+                    sample_rate,
+                    channels: 1,
+                });
+                Ok(self.convert(&chunk.data))
+            }
+            Some(AudioFormat::Wav) => {
+                self.header.extend_from_slice(&chunk.data);
+                let Some((layout, start)) = wav_data_start(&self.header)? else {
+                    return Ok(Vec::new());
+                };
+                self.layout = Some(layout);
+                let header = std::mem::take(&mut self.header);
+                Ok(self.convert(&header[start..]))
+            }
+            Some(format) => Err(unplayable(format!("{} chunks", format.as_str()))),
+            None => Err(unplayable("chunks of unknown format".to_string())),
+        }
+    }
+
+    fn convert(&mut self, mut data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity((data.len() / 2 + 1) * 4);
+        if let Some(low) = self.carry.take() {
+            match data.split_first() {
+                Some((&high, rest)) => {
+                    push_f32(&mut out, [low, high]);
+                    data = rest;
+                }
+                None => {
+                    self.carry = Some(low);
+                    return out;
+                }
+            }
+        }
+        let mut samples = data.chunks_exact(2);
+        for sample in &mut samples {
+            push_f32(&mut out, [sample[0], sample[1]]);
+        }
+        self.carry = samples.remainder().first().copied();
+        out
+    }
+}
+
+fn push_f32(out: &mut Vec<u8>, sample: [u8; 2]) {
+    let value = i16::from_le_bytes(sample) as f32 / 32768.0;
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn unplayable(what: String) -> zoey_core::ZoeyError {
+    VoiceError::InvalidInput(format!("{} can't be played as they arrive", what)).into()
+}
+
+/// Layout and offset of the samples in the start of a WAV stream
+///
+/// `None` while `buf` does not reach the `data` chunk yet. Sizes are not
+/// checked, streamed WAV usually has placeholders there.
+fn wav_data_start(buf: &[u8]) -> Result<Option<(PcmLayout, usize)>> {
+    let invalid = |msg: &str| VoiceError::InvalidInput(format!("invalid WAV stream: {}", msg));
+    if buf.len() < 12 {
+        return Ok(None);
+    }
+    if &buf[0..4] != b"RIFF" || &buf[8..12] != b"WAVE" {
+        return Err(invalid("missing RIFF/WAVE header").into());
+    }
+    let mut layout = None;
+    let mut pos = 12;
+    while pos + 8 <= buf.len() {
+        let id = &buf[pos..pos + 4];
+        let size =
+            u32::from_le_bytes([buf[pos + 4], buf[pos + 5], buf[pos + 6], buf[pos + 7]]) as usize;
+        let body = pos + 8;
+        if id == b"data" {
+            let layout = layout.ok_or_else(|| invalid("data before fmt chunk"))?;
+            return Ok(Some((layout, body)));
+        }
+        if id == b"fmt " {
+            if buf.len() < body + 16 {
+                return Ok(None);
+            }
+            let field = |at: usize| u16::from_le_bytes([buf[body + at], buf[body + at + 1]]);
+            let (tag, channels, bits) = (field(0), field(2), field(14));
+            if (tag, bits) != (1, 16) {
+                return Err(VoiceError::InvalidInput(format!(
+                    "unsupported WAV stream encoding (format tag {}, {} bits)",
+                    tag, bits
+                ))
+                .into());
+            }
+            let sample_rate =
+                u32::from_le_bytes([buf[body + 4], buf[body + 5], buf[body + 6], buf[body + 7]]);
+            layout = Some(PcmLayout {
+                sample_rate,
+                channels: channels.max(1),
+            });
+        }
+        // Chunks are word-aligned
+        pos = body + size + (size & 1);
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::encode_wav;
+    use crate::testing::MockDeterministicEngine;
+    use crate::types::{VoiceConfig, VoiceEngine};
+    use futures_util::StreamExt;
+    use std::time::Instant;
+
+    async fn collect(stream: AudioStream) -> Vec<AudioChunk> {
+        stream.map(|chunk| chunk.unwrap()).collect().await
+    }
+
+    fn chunk(data: &[u8], format: AudioFormat, index: usize) -> AudioChunk {
+        AudioChunk {
+            data: Bytes::copy_from_slice(data),
+            index,
+            is_final: false,
+            timestamp_ms: None,
+            format: Some(format),
+            sample_rate: Some(16_000),
+        }
+    }
+
+    fn as_f32(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_slice_pcm_at_chunk_ms() {
+        // 250ms of mono 16kHz PCM in 100ms chunks
+        let samples: Vec<i16> = (0..4000).map(|i| i as i16).collect();
+        let audio = AudioData::new(
+            Bytes::from(samples_to_pcm16(&samples)),
+            AudioFormat::Pcm,
+            16_000,
+        );
+        let chunks = collect(slice(&audio, 100)).await;
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].data.len(), 3200);
+        assert_eq!(chunks[2].data.len(), 1600);
+        assert_eq!(chunks.iter().filter(|c| c.is_final).count(), 1);
+        assert!(chunks[2].is_final);
+        assert_eq!(chunks[1].timestamp_ms, Some(100));
+        assert!(chunks
+            .iter()
+            .all(|c| c.format == Some(AudioFormat::Pcm) && c.sample_rate == Some(16_000)));
+        let joined: Vec<u8> = chunks.iter().flat_map(|c| c.data.to_vec()).collect();
+        assert_eq!(joined, audio.data);
+    }
+
+    #[tokio::test]
+    async fn test_slice_wav_and_encoded() {
+        let samples: Vec<i16> = (0..960).map(|i| (i * 7) as i16).collect();
+        let wav = AudioData::new(
+            Bytes::from(encode_wav(&samples, 24_000, 2)),
+            AudioFormat::Wav,
+            24_000,
+        );
+        let chunks = collect(slice(&wav, 10)).await;
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.format == Some(AudioFormat::Wav)));
+        let joined: Vec<u8> = chunks.iter().flat_map(|c| c.data.to_vec()).collect();
+        assert_eq!(decode_wav(&joined).unwrap().samples, samples);
+
+        let mp3 = AudioData::new(
+            Bytes::from_static(b"ID3 not really mp3"),
+            AudioFormat::Mp3,
+            44_100,
+        );
+        let chunks = collect(slice(&mp3, 10)).await;
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].is_final);
+        assert_eq!(chunks[0].data, mp3.data);
+        assert_eq!(chunks[0].format, Some(AudioFormat::Mp3));
+    }
+
+    #[test]
+    fn test_decoder_handles_split_samples_and_headers() {
+        let samples = [0i16, 16384, -32768, 1];
+        let pcm = samples_to_pcm16(&samples);
+        let expected = vec![0.0, 0.5, -1.0, 1.0 / 32768.0];
+
+        // Chunk boundaries in the middle of a sample
+        let mut decoder = F32Decoder::default();
+        let mut out = Vec::new();
+        for (i, part) in [&pcm[..3], &pcm[3..4], &pcm[4..]].into_iter().enumerate() {
+            out.extend(decoder.push(&chunk(part, AudioFormat::Pcm, i)).unwrap());
+        }
+        assert_eq!(as_f32(&out), expected);
+
+        // WAV header spread over several chunks
+        let wav = encode_wav(&samples, 22_050, 1);
+        let mut decoder = F32Decoder::default();
+        assert!(decoder
+            .push(&chunk(&wav[..10], AudioFormat::Wav, 0))
+            .unwrap()
+            .is_empty());
+        assert!(decoder
+            .push(&chunk(&wav[10..30], AudioFormat::Wav, 1))
+            .unwrap()
+            .is_empty());
+        let mut out = decoder
+            .push(&chunk(&wav[30..47], AudioFormat::Wav, 2))
+            .unwrap();
+        out.extend(
+            decoder
+                .push(&chunk(&wav[47..], AudioFormat::Wav, 3))
+                .unwrap(),
+        );
+        assert_eq!(as_f32(&out), expected);
+        assert_eq!(
+            decoder.layout,
+            Some(PcmLayout {
+                sample_rate: 22_050,
+                channels: 1
+            })
+        );
+
+        let mut decoder = F32Decoder::default();
+        assert!(decoder
+            .push(&chunk(b"mp3 frame", AudioFormat::Mp3, 0))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_playback_starts_from_first_chunk() {
+        let engine = MockDeterministicEngine::new(16_000);
+        let text = "hello there";
+        let config = VoiceConfig::default();
+        let expected: Vec<f32> = engine
+            .render(text)
+            .iter()
+            .map(|&s| s as f32 / 32768.0)
+            .collect();
+
+        let stream = engine.synthesize_stream(text, &config).await.unwrap();
+        let played = playback(stream).await.unwrap();
+        assert_eq!((played.sample_rate, played.channels), (16_000, 1));
+        let mut reader = played.reader;
+        let bytes = tokio::task::spawn_blocking(move || {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).unwrap();
+            bytes
+        })
+        .await
+        .unwrap();
+        assert_eq!(as_f32(&bytes), expected);
+        let length = played.length.wait().await;
+        assert_eq!(
+            length.as_millis() as u32,
+            text.len() as u32 * crate::testing::MOCK_CHAR_MS
+        );
+
+        let mp3 = AudioData::new(Bytes::from_static(b"not pcm"), AudioFormat::Mp3, 44_100);
+        assert!(playback(slice(&mp3, 100)).await.is_err());
+        let (tx, empty) = create_audio_stream(1);
+        drop(tx);
+        assert!(playback(empty).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_time_to_first_chunk() {
+        let (tx, stream) = create_audio_stream(4);
+        let mut stream = stream.with_start(Instant::now() - Duration::from_millis(50));
+        assert_eq!(stream.time_to_first_chunk(), None);
+
+        // An empty marker is not audio
+        let mut marker = chunk(&[], AudioFormat::Pcm, 0);
+        tx.send(Ok(marker.clone())).await.unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(stream.time_to_first_chunk(), None);
+
+        tx.send(Ok(chunk(&[1, 2], AudioFormat::Pcm, 1)))
+            .await
+            .unwrap();
+        marker.is_final = true;
+        tx.send(Ok(marker)).await.unwrap();
+        stream.recv().await.unwrap().unwrap();
+        let first = stream.time_to_first_chunk().unwrap();
+        assert!(first >= Duration::from_millis(50));
+        stream.recv().await.unwrap().unwrap();
+        assert_eq!(stream.time_to_first_chunk(), Some(first));
+    }
+}
@@ -0,0 +1,555 @@
+//! Deterministic audio and golden-file comparison for tests
+//!
+//! Refactors of the audio pipeline tend to break it subtly (sample-rate math,
+//! WAV header offsets) in ways that only show up as garbled playback. This
+//! module pins the output of each transform against checked-in WAV files:
+//!
+//! - [`MockDeterministicEngine`] synthesizes the same PCM for the same text
+//!   every time (one short tone per character), so anything downstream of it
+//!   is byte-stable without a TTS server
+//! - [`compare_audio`] compares samples against a golden recording within an
+//!   RMS tolerance and reports where and how they differ
+//! - [`assert_matches_golden`] loads a golden WAV and panics with that
+//!   report; with `ZOEY_UPDATE_GOLDENS=1` it rewrites the file instead
+//!
+//! The crate's own goldens live in `tests/fixtures/golden/` and cover the
+//! long-form concatenation, resampling, [`normalize`](crate::audio::normalize),
+//! [`effects`](crate::effects) and [`sink`](crate::sink) conversion paths.
+
+use crate::audio::{decode_wav, encode_wav, samples_to_pcm16, PcmAudio};
+use crate::types::*;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::f64::consts::PI;
+use std::fmt;
+use std::path::Path;
+use zoey_core::Result;
+
+/// Environment variable that makes [`assert_matches_golden`] rewrite goldens
+pub const UPDATE_GOLDENS_ENV: &str = "ZOEY_UPDATE_GOLDENS";
+
+/// Length of the tone synthesized for each character (ms)
+pub const MOCK_CHAR_MS: u32 = 20;
+
+/// Peak amplitude of the mock tones
+const MOCK_AMPLITUDE: f64 = 12_000.0;
+
+/// TTS engine producing reproducible tones instead of speech
+///
+/// Each character becomes a [`MOCK_CHAR_MS`] tone whose frequency depends on
+/// the character; whitespace is silence. Further channels repeat the first
+/// at lower volume, so downmixing does not cancel out.
+#[derive(Debug, Clone)]
+pub struct MockDeterministicEngine {
+    sample_rate: u32,
+    channels: u16,
+    max_text_length: usize,
+}
+
+impl MockDeterministicEngine {
+    /// Mono engine at `sample_rate`
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            channels: 1,
+            max_text_length: 4096,
+        }
+    }
+
+    /// Produce `channels` interleaved channels
+    pub fn with_channels(mut self, channels: u16) -> Self {
+        self.channels = channels.max(1);
+        self
+    }
+
+    /// Report a smaller text limit, e.g. to force long-form chunking
+    pub fn with_max_text_length(mut self, max_text_length: usize) -> Self {
+        self.max_text_length = max_text_length;
+        self
+    }
+
+    /// Tone frequency for a character, `None` for silence
+    fn frequency(c: char) -> Option<f64> {
+        if c.is_whitespace() {
+            None
+        } else {
+            Some(220.0 + (c as u32 % 48) as f64 * 20.0)
+        }
+    }
+
+    /// Interleaved samples for `text`
+    pub fn render(&self, text: &str) -> Vec<i16> {
+        let per_char = (self.sample_rate * MOCK_CHAR_MS / 1000) as usize;
+        let channels = self.channels as usize;
+        let mut out = Vec::with_capacity(text.chars().count() * per_char * channels);
+        for c in text.chars() {
+            let frequency = Self::frequency(c);
+            for i in 0..per_char {
+                let value = frequency
+                    .map(|f| (2.0 * PI * f * i as f64 / self.sample_rate as f64).sin())
+                    .unwrap_or(0.0);
+                for ch in 0..channels {
+                    let gain = 1.0 - 0.25 * ch as f64;
+                    out.push((value * MOCK_AMPLITUDE * gain).round() as i16);
+                }
+            }
+        }
+        out
+    }
+
+    /// `text` rendered as [`PcmAudio`]
+    pub fn render_pcm(&self, text: &str) -> PcmAudio {
+        PcmAudio {
+            samples: self.render(text),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+}
+
+#[async_trait]
+impl VoiceEngine for MockDeterministicEngine {
+    fn name(&self) -> &str {
+        "mock-deterministic"
+    }
+
+    /// WAV unless PCM is requested
+    async fn synthesize(&self, text: &str, config: &VoiceConfig) -> Result<AudioData> {
+        let samples = self.render(text);
+        let (data, format) = match config.output_format {
+            AudioFormat::Pcm => (samples_to_pcm16(&samples), AudioFormat::Pcm),
+            _ => (
+                encode_wav(&samples, self.sample_rate, self.channels),
+                AudioFormat::Wav,
+            ),
+        };
+        let frames = (samples.len() / self.channels as usize) as u64;
+        let mut audio = AudioData::new(Bytes::from(data), format, self.sample_rate)
+            .with_channels(self.channels);
+        audio.duration_ms = Some(frames * 1000 / self.sample_rate.max(1) as u64);
+        audio.character_count = text.chars().count();
+        Ok(audio)
+    }
+
+    /// One PCM chunk per character
+    async fn synthesize_stream(&self, text: &str, _config: &VoiceConfig) -> Result<AudioStream> {
+        let chars: Vec<char> = text.chars().collect();
+        let (tx, rx) = create_audio_stream(chars.len().max(1));
+        let per_char_ms = MOCK_CHAR_MS as u64;
+        for (index, c) in chars.iter().enumerate() {
+            let chunk = AudioChunk {
+                data: Bytes::from(samples_to_pcm16(&self.render(&c.to_string()))),
+                index,
+                is_final: index + 1 == chars.len(),
+                timestamp_ms: Some(index as u64 * per_char_ms),
+                format: Some(AudioFormat::Pcm),
+                sample_rate: Some(self.sample_rate),
+            };
+            let _ = tx.try_send(Ok(chunk));
+        }
+        Ok(rx)
+    }
+
+    async fn available_voices(&self) -> Result<Vec<Voice>> {
+        Ok(vec![Voice::custom(
+            "mock".to_string(),
+            "Mock".to_string(),
+            VoiceGender::Neutral,
+            "en".to_string(),
+        )])
+    }
+
+    async fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn supported_formats(&self) -> Vec<AudioFormat> {
+        vec![AudioFormat::Wav, AudioFormat::Pcm]
+    }
+
+    fn max_text_length(&self) -> usize {
+        self.max_text_length
+    }
+}
+
+/// How audio differs from its golden recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioDiff {
+    /// Sample rate, channels and frame count of the actual audio
+    pub actual: (u32, u16, usize),
+    /// Sample rate, channels and frame count of the golden audio
+    pub golden: (u32, u16, usize),
+    /// First differing sample: index, actual value, golden value
+    pub first_divergent: Option<(usize, i16, i16)>,
+    /// RMS of the sample differences over the common length, as a
+    /// fraction of full scale
+    pub rms_difference: f64,
+    /// Tolerance the comparison was made with
+    pub tolerance: f64,
+}
+
+impl AudioDiff {
+    fn layout_matches(&self) -> bool {
+        self.actual == self.golden
+    }
+}
+
+impl fmt::Display for AudioDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |(rate, channels, frames): (u32, u16, usize)| {
+            format!(
+                "{}Hz/{}ch, {} frames ({:.3}s)",
+                rate,
+                channels,
+                frames,
+                frames as f64 / rate.max(1) as f64
+            )
+        };
+        write!(f, "audio differs from golden")?;
+        if !self.layout_matches() {
+            write!(
+                f,
+                "; actual {} vs golden {}",
+                describe(self.actual),
+                describe(self.golden)
+            )?;
+        }
+        if let Some((index, actual, golden)) = self.first_divergent {
+            let channels = self.actual.1.max(1) as usize;
+            write!(
+                f,
+                "; first divergent sample {} (frame {}, channel {}): {} vs {}",
+                index,
+                index / channels,
+                index % channels,
+                actual,
+                golden
+            )?;
+        }
+        write!(
+            f,
+            "; RMS difference {:.6} (tolerance {:.6})",
+            self.rms_difference, self.tolerance
+        )
+    }
+}
+
+impl std::error::Error for AudioDiff {}
+
+/// Compare `actual` with `golden` sample by sample
+///
+/// Matches when sample rate, channel count and length agree and the RMS of
+/// the differences, as a fraction of full scale, is at most `tolerance`
+/// (`0.0` demands identical samples).
+pub fn compare_audio(
+    actual: &PcmAudio,
+    golden: &PcmAudio,
+    tolerance: f64,
+) -> std::result::Result<(), AudioDiff> {
+    let frames = |pcm: &PcmAudio| pcm.samples.len() / pcm.channels.max(1) as usize;
+    let common = actual.samples.len().min(golden.samples.len());
+    let mut first_divergent = None;
+    let mut sum_sq = 0.0f64;
+    for (i, (&a, &g)) in actual.samples.iter().zip(&golden.samples).enumerate() {
+        if a != g && first_divergent.is_none() {
+            first_divergent = Some((i, a, g));
+        }
+        let d = (a as f64 - g as f64) / i16::MAX as f64;
+        sum_sq += d * d;
+    }
+    let rms_difference = if common == 0 {
+        0.0
+    } else {
+        (sum_sq / common as f64).sqrt()
+    };
+    let diff = AudioDiff {
+        actual: (actual.sample_rate, actual.channels, frames(actual)),
+        golden: (golden.sample_rate, golden.channels, frames(golden)),
+        first_divergent,
+        rms_difference,
+        tolerance,
+    };
+    if diff.layout_matches()
+        && actual.samples.len() == golden.samples.len()
+        && rms_difference <= tolerance
+    {
+        Ok(())
+    } else {
+        Err(diff)
+    }
+}
+
+/// Whether goldens should be rewritten rather than checked
+pub fn updating_goldens() -> bool {
+    std::env::var(UPDATE_GOLDENS_ENV)
+        .map(|v| matches!(v.trim(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Check `actual` against `{dir}/{name}.wav`, panicking with an [`AudioDiff`] report
+///
+/// With [`UPDATE_GOLDENS_ENV`] set, the golden is (re)written instead.
+pub fn assert_matches_golden(dir: &Path, name: &str, actual: &PcmAudio, tolerance: f64) {
+    let path = dir.join(format!("{}.wav", name));
+    if updating_goldens() {
+        std::fs::create_dir_all(dir).expect("create golden directory");
+        let wav = encode_wav(&actual.samples, actual.sample_rate, actual.channels);
+        std::fs::write(&path, wav).expect("write golden");
+        return;
+    }
+    let bytes = std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "missing golden {} ({}); run with {}=1 to create it",
+            path.display(),
+            e,
+            UPDATE_GOLDENS_ENV
+        )
+    });
+    let golden = decode_wav(&bytes)
+        .unwrap_or_else(|e| panic!("unreadable golden {}: {}", path.display(), e));
+    if let Err(diff) = compare_audio(actual, &golden, tolerance) {
+        panic!(
+            "{}: {}\nrun with {}=1 if the change is intended",
+            path.display(),
+            diff,
+            UPDATE_GOLDENS_ENV
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{normalize, resample_linear};
+    use crate::effects::{EffectChain, EffectConfig};
+    use crate::long_form::synthesize_to_wav_stream;
+    use crate::sink::{transcode, SinkFormat};
+    use crate::VoicePlugin;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    /// Text rendered for every golden
+    const CORPUS: &str = "Hi, Zoey here.";
+
+    /// Long enough to be split into several long-form chunks
+    const LONG_CORPUS: &str = "One fish. Two fish! Red fish? Blue fish.";
+
+    /// Tolerance for the goldens: far below audible, above float rounding
+    const GOLDEN_TOLERANCE: f64 = 1e-4;
+
+    fn golden_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden")
+    }
+
+    fn wav_config() -> VoiceConfig {
+        VoiceConfig {
+            output_format: AudioFormat::Wav,
+            ..Default::default()
+        }
+    }
+
+    fn pcm_of(audio: &AudioData) -> PcmAudio {
+        match audio.format {
+            AudioFormat::Wav => decode_wav(&audio.data).unwrap(),
+            _ => PcmAudio {
+                samples: crate::audio::pcm16_to_samples(&audio.data),
+                sample_rate: audio.sample_rate,
+                channels: audio.channels,
+            },
+        }
+    }
+
+    fn effect_chain() -> Vec<EffectConfig> {
+        vec![
+            EffectConfig::HighPass { cutoff_hz: 300.0 },
+            EffectConfig::LowPass { cutoff_hz: 3000.0 },
+            EffectConfig::Gain { db: 3.0 },
+            EffectConfig::Reverb {
+                delay_ms: 30.0,
+                decay: 0.4,
+                mix: 0.3,
+            },
+            EffectConfig::Robot { bits: 8, hold: 2 },
+            EffectConfig::Speed { factor: 1.25 },
+        ]
+    }
+
+    #[test]
+    fn test_engine_is_deterministic() {
+        let engine = MockDeterministicEngine::new(16_000).with_channels(2);
+        assert_eq!(engine.render(CORPUS), engine.render(CORPUS));
+        let frames = CORPUS.chars().count() * 16_000 * MOCK_CHAR_MS as usize / 1000;
+        assert_eq!(engine.render(CORPUS).len(), frames * 2);
+        assert!(engine.render(" ").iter().all(|&s| s == 0));
+        assert_ne!(engine.render("a"), engine.render("b"));
+
+        assert_matches_golden(
+            &golden_dir(),
+            "engine_16k_mono",
+            &MockDeterministicEngine::new(16_000).render_pcm(CORPUS),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn test_compare_audio_reports_differences() {
+        let golden = MockDeterministicEngine::new(8_000).render_pcm(CORPUS);
+        assert!(compare_audio(&golden, &golden, 0.0).is_ok());
+
+        let mut nudged = golden.clone();
+        nudged.samples[10] += 1;
+        let diff = compare_audio(&nudged, &golden, 0.0).unwrap_err();
+        assert_eq!(
+            diff.first_divergent,
+            Some((10, golden.samples[10] + 1, golden.samples[10]))
+        );
+        assert!(compare_audio(&nudged, &golden, 1e-4).is_ok());
+
+        let mut short = golden.clone();
+        short.samples.truncate(golden.samples.len() - 80);
+        let diff = compare_audio(&short, &golden, 1.0).unwrap_err();
+        assert_eq!(diff.first_divergent, None);
+        let report = diff.to_string();
+        let expected = format!(
+            "actual 8000Hz/1ch, {} frames (0.270s) vs golden 8000Hz/1ch, {} frames (0.280s)",
+            short.samples.len(),
+            golden.samples.len()
+        );
+        assert!(report.contains(&expected), "{}", report);
+
+        let resampled = PcmAudio {
+            sample_rate: 16_000,
+            ..golden.clone()
+        };
+        assert!(compare_audio(&resampled, &golden, 1.0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_matches_synthesis() {
+        let engine = MockDeterministicEngine::new(16_000);
+        let mut stream = engine
+            .synthesize_stream(CORPUS, &wav_config())
+            .await
+            .unwrap();
+        let mut streamed = Vec::new();
+        while let Some(chunk) = stream.recv().await {
+            let chunk = chunk.unwrap();
+            streamed.extend(crate::audio::pcm16_to_samples(&chunk.data));
+            if chunk.is_final {
+                break;
+            }
+        }
+        assert_eq!(streamed, engine.render(CORPUS));
+    }
+
+    #[tokio::test]
+    async fn test_golden_long_form_concatenation() {
+        // Chunks are joined into one WAV with a patched header
+        let engine = MockDeterministicEngine::new(22_050).with_max_text_length(12);
+        let (wav, summary) = synthesize_to_wav_stream(
+            &engine,
+            &wav_config(),
+            LONG_CORPUS,
+            Cursor::new(Vec::new()),
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert!(summary.chunks > 2, "{} chunks", summary.chunks);
+        let bytes = wav.finish_patched().await.unwrap().into_inner();
+        let joined = decode_wav(&bytes).unwrap();
+        assert_matches_golden(&golden_dir(), "long_form_22k", &joined, GOLDEN_TOLERANCE);
+    }
+
+    #[test]
+    fn test_golden_resampling() {
+        let mono = MockDeterministicEngine::new(22_050).render_pcm(CORPUS);
+        let down = PcmAudio {
+            samples: resample_linear(&mono.samples, 1, 22_050, 16_000),
+            sample_rate: 16_000,
+            channels: 1,
+        };
+        assert_matches_golden(
+            &golden_dir(),
+            "resample_22k_to_16k",
+            &down,
+            GOLDEN_TOLERANCE,
+        );
+
+        let stereo = MockDeterministicEngine::new(24_000)
+            .with_channels(2)
+            .render_pcm(CORPUS);
+        let up = PcmAudio {
+            samples: resample_linear(&stereo.samples, 2, 24_000, 48_000),
+            sample_rate: 48_000,
+            channels: 2,
+        };
+        assert_matches_golden(
+            &golden_dir(),
+            "resample_24k_to_48k_stereo",
+            &up,
+            GOLDEN_TOLERANCE,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_golden_normalization() {
+        // Discord-style 48kHz stereo PCM prepared for Whisper
+        let engine = MockDeterministicEngine::new(48_000).with_channels(2);
+        let pcm = VoiceConfig {
+            output_format: AudioFormat::Pcm,
+            ..Default::default()
+        };
+        let input = engine.synthesize(CORPUS, &pcm).await.unwrap();
+        let normalized = normalize(&input, &AudioSpec::pcm_mono(16_000)).unwrap();
+        assert_eq!(normalized.spec(), AudioSpec::pcm_mono(16_000));
+        assert_matches_golden(
+            &golden_dir(),
+            "normalize_48k_stereo_to_16k_mono",
+            &pcm_of(&normalized),
+            GOLDEN_TOLERANCE,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_golden_effects() {
+        let engine = MockDeterministicEngine::new(24_000);
+        let raw = engine.synthesize(CORPUS, &wav_config()).await.unwrap();
+        let processed = EffectChain::new(&effect_chain()).apply(&raw).unwrap();
+        assert_matches_golden(
+            &golden_dir(),
+            "effects_chain_24k",
+            &pcm_of(&processed),
+            GOLDEN_TOLERANCE,
+        );
+
+        // The plugin's synthesis path applies the same chain
+        let config = VoiceConfig {
+            effects: effect_chain(),
+            ..wav_config()
+        };
+        let plugin = VoicePlugin::new(Box::new(engine), config);
+        let synthesized = plugin.synthesize(CORPUS).await.unwrap();
+        assert_matches_golden(
+            &golden_dir(),
+            "effects_chain_24k",
+            &pcm_of(&synthesized),
+            GOLDEN_TOLERANCE,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_golden_sink_preprocessing() {
+        // 22.05kHz mono WAV prepared for a Discord voice call
+        let engine = MockDeterministicEngine::new(22_050);
+        let wav = engine.synthesize(CORPUS, &wav_config()).await.unwrap();
+        let discord = transcode(&wav, SinkFormat::DiscordVoice).unwrap();
+        assert_eq!(discord.spec(), AudioSpec::new(48_000, 2, AudioFormat::Pcm));
+        assert_matches_golden(
+            &golden_dir(),
+            "sink_discord_voice",
+            &pcm_of(&discord),
+            GOLDEN_TOLERANCE,
+        );
+    }
+}
@@ -0,0 +1,382 @@
+//! Converting synthesized audio between formats
+//!
+//! [`AudioData::transcode`] turns engine output into another format: PCM and
+//! WAV into each other, and either of them into Opus in an Ogg container,
+//! the only format Telegram shows as a round voice note. Opus packets come
+//! from the pure-Rust CELT encoder in [`crate::celt`]; the Ogg pages and the
+//! `OpusHead`/`OpusTags` headers are written here.
+//!
+//! Opus always runs at 48kHz, so audio is resampled first, and voice notes
+//! are mono, so stereo input is downmixed. The source sample rate is kept as
+//! `OpusHead`'s input sample rate, and `duration_ms` is recomputed from the
+//! encoded samples.
+
+use crate::audio::{normalize, pcm16_to_samples};
+use crate::celt::{CeltEncoder, FRAME_SAMPLES, OVERLAP};
+use crate::types::{AudioData, AudioFormat, AudioSpec, VoiceError};
+use bytes::Bytes;
+use zoey_core::Result;
+
+/// Sample rate Opus encodes and decodes at
+pub const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// Samples the decoder drops at the start (the CELT window overlap)
+pub const OPUS_PRE_SKIP: u16 = OVERLAP as u16;
+
+/// Bitstream serial number of the Ogg streams written
+const OGG_SERIAL: u32 = 0x5a4f_4559;
+
+/// Ogg page header flags
+const PAGE_BOS: u8 = 0x02;
+const PAGE_EOS: u8 = 0x04;
+
+/// Lacing values a page holds at most
+const MAX_PAGE_SEGMENTS: usize = 255;
+
+impl AudioData {
+    /// Convert to `to`, keeping the sample rate and channels where the format allows
+    ///
+    /// Only uncompressed input (PCM/WAV) converts; PCM, WAV and Opus can be
+    /// produced. Opus output is Ogg-encapsulated 48kHz mono audio. Fails with
+    /// [`VoiceError::InvalidInput`] for other conversions.
+    pub fn transcode(&self, to: AudioFormat) -> Result<AudioData> {
+        if self.format == to {
+            return Ok(self.clone());
+        }
+        let mut out = match to {
+            AudioFormat::Pcm | AudioFormat::Wav => {
+                normalize(self, &AudioSpec::new(self.sample_rate, self.channels, to))?
+            }
+            AudioFormat::Opus => self.to_ogg_opus()?,
+            other => {
+                return Err(VoiceError::InvalidInput(format!(
+                    "cannot transcode {} to {} (only PCM, WAV and Opus are produced)",
+                    self.format.as_str(),
+                    other.as_str()
+                ))
+                .into())
+            }
+        };
+        out.character_count = self.character_count;
+        out.engine = self.engine.clone();
+        Ok(out)
+    }
+
+    fn to_ogg_opus(&self) -> Result<AudioData> {
+        let pcm = normalize(self, &AudioSpec::new(OPUS_SAMPLE_RATE, 1, AudioFormat::Pcm))?;
+        let samples = pcm16_to_samples(&pcm.data);
+        let frames = samples.len() as u64;
+        let packets = encode_opus(&samples);
+        let data = write_ogg_opus(&packets, 1, self.sample_rate, frames);
+        Ok(AudioData {
+            data: Bytes::from(data),
+            format: AudioFormat::Opus,
+            sample_rate: OPUS_SAMPLE_RATE,
+            channels: 1,
+            duration_ms: Some(frames * 1000 / OPUS_SAMPLE_RATE as u64),
+            character_count: 0,
+            engine: None,
+        })
+    }
+}
+
+/// Encode mono 48kHz samples as 20ms Opus packets
+///
+/// The last packet is padded with silence, and one more is added when needed
+/// so the samples held back by the window overlap are flushed out.
+fn encode_opus(samples: &[i16]) -> Vec<Vec<u8>> {
+    let mut encoder = CeltEncoder::new();
+    let needed = samples.len() + OPUS_PRE_SKIP as usize;
+    let mut frame = [0.0f32; FRAME_SAMPLES];
+    let mut packets = Vec::with_capacity(needed / FRAME_SAMPLES + 1);
+    for offset in (0..needed).step_by(FRAME_SAMPLES) {
+        let chunk = samples.get(offset..).unwrap_or_default();
+        frame.fill(0.0);
+        for (out, &sample) in frame.iter_mut().zip(chunk) {
+            *out = sample as f32 / 32768.0;
+        }
+        packets.push(encoder.encode(&frame));
+    }
+    packets
+}
+
+/// Ogg stream of `packets` (20ms each) holding `frames` samples per channel
+fn write_ogg_opus(packets: &[Vec<u8>], channels: u8, input_rate: u32, frames: u64) -> Vec<u8> {
+    let mut ogg = OggWriter::default();
+    ogg.page(&[&opus_head(channels, input_rate)], 0, PAGE_BOS);
+    ogg.page(&[&opus_tags()], 0, 0);
+
+    // The final granule position trims the padding off the last packet
+    let end = OPUS_PRE_SKIP as u64 + frames;
+    let mut page: Vec<&[u8]> = Vec::new();
+    let mut segments = 0;
+    let mut granule = 0u64;
+    for packet in packets {
+        let lacing = packet.len() / 255 + 1;
+        if segments + lacing > MAX_PAGE_SEGMENTS {
+            ogg.page(&page, granule, 0);
+            page.clear();
+            segments = 0;
+        }
+        page.push(packet);
+        segments += lacing;
+        granule = (granule + FRAME_SAMPLES as u64).min(end);
+    }
+    ogg.page(&page, end, PAGE_EOS);
+    ogg.out
+}
+
+/// `OpusHead` identification header (RFC 7845 §5.1), channel mapping family 0
+fn opus_head(channels: u8, input_rate: u32) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1);
+    head.push(channels);
+    head.extend_from_slice(&OPUS_PRE_SKIP.to_le_bytes());
+    head.extend_from_slice(&input_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    head
+}
+
+/// `OpusTags` comment header (RFC 7845 §5.2) without comments
+fn opus_tags() -> Vec<u8> {
+    let vendor = b"zoey-voice";
+    let mut tags = Vec::with_capacity(16 + vendor.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
+
+/// Writes the pages of one logical Ogg bitstream
+#[derive(Default)]
+struct OggWriter {
+    sequence: u32,
+    out: Vec<u8>,
+}
+
+impl OggWriter {
+    /// Append a page holding whole `packets`, ending at `granule`
+    fn page(&mut self, packets: &[&[u8]], granule: u64, flags: u8) {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.resize(lacing.len() + packet.len() / 255, 255);
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let start = self.out.len();
+        self.out.extend_from_slice(b"OggS");
+        self.out.push(0);
+        self.out.push(flags);
+        self.out.extend_from_slice(&granule.to_le_bytes());
+        self.out.extend_from_slice(&OGG_SERIAL.to_le_bytes());
+        self.out.extend_from_slice(&self.sequence.to_le_bytes());
+        self.out.extend_from_slice(&[0; 4]);
+        self.out.push(lacing.len() as u8);
+        self.out.extend_from_slice(&lacing);
+        for packet in packets {
+            self.out.extend_from_slice(packet);
+        }
+        let crc = ogg_crc(&self.out[start..]);
+        self.out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+        self.sequence += 1;
+    }
+}
+
+/// CRC-32 of an Ogg page (polynomial 0x04c11db7, unreflected, no final xor)
+fn ogg_crc(data: &[u8]) -> u32 {
+    data.iter().fold(0, |crc, &byte| {
+        (crc << 8) ^ OGG_CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    })
+}
+
+static OGG_CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{decode_wav, encode_wav, samples_to_pcm16};
+
+    /// One Ogg page as read back
+    struct Page {
+        flags: u8,
+        granule: u64,
+        sequence: u32,
+        packets: Vec<Vec<u8>>,
+    }
+
+    /// Split an Ogg stream into pages, checking each page's CRC
+    fn read_pages(mut data: &[u8]) -> Vec<Page> {
+        let mut pages = Vec::new();
+        while !data.is_empty() {
+            assert_eq!(&data[..4], b"OggS");
+            let segments = data[26] as usize;
+            let lacing = &data[27..27 + segments];
+            let body_len: usize = lacing.iter().map(|&l| l as usize).sum();
+            let len = 27 + segments + body_len;
+
+            let mut page = data[..len].to_vec();
+            let crc = u32::from_le_bytes(page[22..26].try_into().unwrap());
+            page[22..26].fill(0);
+            assert_eq!(ogg_crc(&page), crc, "page CRC");
+
+            let mut packets = Vec::new();
+            let mut body = &data[27 + segments..len];
+            let mut packet = Vec::new();
+            for &l in lacing {
+                packet.extend_from_slice(&body[..l as usize]);
+                body = &body[l as usize..];
+                if l < 255 {
+                    packets.push(std::mem::take(&mut packet));
+                }
+            }
+            pages.push(Page {
+                flags: data[5],
+                granule: u64::from_le_bytes(data[6..14].try_into().unwrap()),
+                sequence: u32::from_le_bytes(data[18..22].try_into().unwrap()),
+                packets,
+            });
+            data = &data[len..];
+        }
+        pages
+    }
+
+    /// Channels, pre-skip and input sample rate from an `OpusHead` packet
+    fn read_opus_head(packet: &[u8]) -> (u8, u16, u32) {
+        assert_eq!(&packet[..8], b"OpusHead");
+        assert_eq!(packet[8], 1, "version");
+        (
+            packet[9],
+            u16::from_le_bytes([packet[10], packet[11]]),
+            u32::from_le_bytes(packet[12..16].try_into().unwrap()),
+        )
+    }
+
+    /// Playback length from the last page's granule position
+    fn ogg_duration_ms(pages: &[Page]) -> u64 {
+        let (_, pre_skip, _) = read_opus_head(&pages[0].packets[0]);
+        (pages.last().unwrap().granule - pre_skip as u64) * 1000 / OPUS_SAMPLE_RATE as u64
+    }
+
+    #[test]
+    fn test_ogg_headers_pages_and_granules() {
+        // 300 packets need more than one page of lacing values
+        let packets: Vec<Vec<u8>> = (0..300).map(|i| vec![0xf8, i as u8, 1]).collect();
+        let frames = 300 * FRAME_SAMPLES as u64 - 500;
+        let pages = read_pages(&write_ogg_opus(&packets, 1, 22_050, frames));
+
+        assert_eq!(pages[0].flags, PAGE_BOS);
+        assert_eq!(
+            read_opus_head(&pages[0].packets[0]),
+            (1, OPUS_PRE_SKIP, 22_050)
+        );
+        assert_eq!(&pages[1].packets[0][..8], b"OpusTags");
+        let audio = &pages[2..];
+        assert!(audio.len() >= 2);
+        assert_eq!(audio.last().unwrap().flags, PAGE_EOS);
+        assert_eq!(audio.iter().map(|p| p.packets.len()).sum::<usize>(), 300);
+        let read: Vec<Vec<u8>> = audio.iter().flat_map(|p| p.packets.clone()).collect();
+        assert_eq!(read, packets);
+        for (i, page) in pages.iter().enumerate() {
+            assert_eq!(page.sequence, i as u32);
+        }
+        assert!(audio.windows(2).all(|w| w[0].granule <= w[1].granule));
+        assert_eq!(ogg_duration_ms(&pages), frames * 1000 / 48_000);
+    }
+
+    #[test]
+    fn test_lacing_of_long_packets() {
+        let packets = vec![vec![1u8; 600], vec![2u8; 510], Vec::new()];
+        let pages = read_pages(&write_ogg_opus(&packets, 2, 48_000, 960));
+        assert_eq!(pages[2].packets, packets);
+    }
+
+    #[test]
+    fn test_pcm_and_wav_transcode() {
+        let samples: Vec<i16> = (0..1600).map(|i| (i % 100) as i16).collect();
+        let mut pcm = AudioData::new(
+            Bytes::from(samples_to_pcm16(&samples)),
+            AudioFormat::Pcm,
+            16_000,
+        );
+        pcm.character_count = 12;
+
+        let wav = pcm.transcode(AudioFormat::Wav).unwrap();
+        assert_eq!(wav.spec(), AudioSpec::new(16_000, 1, AudioFormat::Wav));
+        assert_eq!(decode_wav(&wav.data).unwrap().samples, samples);
+        assert_eq!((wav.duration_ms, wav.character_count), (Some(100), 12));
+
+        let back = wav.transcode(AudioFormat::Pcm).unwrap();
+        assert_eq!(back.data, pcm.data);
+
+        assert!(pcm.transcode(AudioFormat::Mp3).is_err());
+        let mp3 = AudioData::new(Bytes::from_static(b"ID3"), AudioFormat::Mp3, 44_100);
+        assert!(mp3.transcode(AudioFormat::Opus).is_err());
+    }
+
+    #[test]
+    fn test_wav_encoded_as_ogg_opus() {
+        // 1.5s of a 440Hz tone at Piper's 22.05kHz
+        let samples: Vec<i16> = (0..33_075)
+            .map(|i| ((i as f32 * 440.0 * std::f32::consts::TAU / 22_050.0).sin() * 8000.0) as i16)
+            .collect();
+        let wav = AudioData::new(
+            Bytes::from(encode_wav(&samples, 22_050, 1)),
+            AudioFormat::Wav,
+            22_050,
+        );
+
+        let opus = wav.transcode(AudioFormat::Opus).unwrap();
+        assert_eq!(opus.spec(), AudioSpec::new(48_000, 1, AudioFormat::Opus));
+
+        let pages = read_pages(&opus.data);
+        assert_eq!(
+            read_opus_head(&pages[0].packets[0]),
+            (1, OPUS_PRE_SKIP, 22_050)
+        );
+        let duration = ogg_duration_ms(&pages);
+        assert!(duration.abs_diff(1500) <= 20, "duration {}ms", duration);
+        assert!(opus.duration_ms.unwrap().abs_diff(1500) <= 20);
+        // Far smaller than the PCM it came from
+        assert!(opus.data.len() < wav.data.len() / 4);
+        // Every audio packet is a 20ms CELT frame
+        assert!(pages[2..]
+            .iter()
+            .flat_map(|p| &p.packets)
+            .all(|packet| packet[0] == 0xF8));
+    }
+
+    #[test]
+    fn test_stereo_downmixed_for_opus() {
+        let pcm = AudioData::new(
+            Bytes::from(samples_to_pcm16(&[1000, -1000].repeat(4800))),
+            AudioFormat::Pcm,
+            48_000,
+        )
+        .with_channels(2);
+        let opus = pcm.transcode(AudioFormat::Opus).unwrap();
+        assert_eq!(opus.channels, 1);
+        let pages = read_pages(&opus.data);
+        assert_eq!(read_opus_head(&pages[0].packets[0]).0, 1);
+        assert_eq!(ogg_duration_ms(&pages), 100);
+    }
+}
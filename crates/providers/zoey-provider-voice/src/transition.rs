@@ -0,0 +1,184 @@
+//! Notices when a session's TTS engine changes
+//!
+//! If consecutive requests in one logical session (a Discord guild, a
+//! Telegram chat) are served by different engines, for example because a
+//! failover moved from ElevenLabs to Piper, the voice changes abruptly and
+//! users assume something broke. [`EngineTransitions`] remembers which engine
+//! served each session's last request so the caller can announce the change:
+//! speak [`TransitionNotice::spoken`] before the reply, or post a text note
+//! when [`EngineTransitions::observe`] reports a change.
+//!
+//! No change is reported on a session's first request, once the session has
+//! been idle longer than the tracker's TTL (usually
+//! [`TransitionNotice::session_ttl`]), or when the caller marks the request as
+//! an explicit voice switch (the user asked for it).
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Spoken by default before the first reply from a different engine
+pub const DEFAULT_TRANSITION_NOTICE: &str = "Switching to my backup voice for a moment.";
+
+/// How long a session's last engine is remembered by default
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Engine-change announcement settings
+#[derive(Debug, Clone)]
+pub struct TransitionNotice {
+    /// Prepended to the text when the engine changed; `None` leaves the
+    /// announcement to the adapter
+    pub spoken: Option<String>,
+    /// Idle time after which a session starts over
+    pub session_ttl: Duration,
+}
+
+impl Default for TransitionNotice {
+    fn default() -> Self {
+        Self {
+            spoken: Some(DEFAULT_TRANSITION_NOTICE.to_string()),
+            session_ttl: DEFAULT_SESSION_TTL,
+        }
+    }
+}
+
+impl TransitionNotice {
+    /// `text` with the spoken notice prepended when `engine_changed`
+    pub fn apply<'a>(&self, text: &'a str, engine_changed: bool) -> Cow<'a, str> {
+        match self.spoken.as_deref().filter(|_| engine_changed) {
+            Some(notice) if !notice.trim().is_empty() => {
+                Cow::Owned(format!("{} {}", notice.trim(), text))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Last engine per session, forgotten after a TTL
+pub struct EngineTransitions {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl EngineTransitions {
+    /// Remember each session's engine for `ttl` after its last request
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record that `engine` served `session_key`, returning whether the
+    /// engine changed since the session's previous request
+    ///
+    /// `explicit_switch` marks a change the user asked for, which is recorded
+    /// but never reported.
+    pub fn observe(&self, session_key: &str, engine: &str, explicit_switch: bool) -> bool {
+        self.observe_at(session_key, engine, explicit_switch, Instant::now())
+    }
+
+    fn observe_at(
+        &self,
+        session_key: &str,
+        engine: &str,
+        explicit_switch: bool,
+        now: Instant,
+    ) -> bool {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, (_, at)| now.saturating_duration_since(*at) < self.ttl);
+        let previous = sessions.insert(session_key.to_string(), (engine.to_string(), now));
+        match previous {
+            Some((last, _)) => last != engine && !explicit_switch,
+            None => false,
+        }
+    }
+
+    /// Forget a session, e.g. when the bot leaves a voice channel
+    pub fn reset(&self, session_key: &str) {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(session_key);
+    }
+}
+
+impl From<&TransitionNotice> for EngineTransitions {
+    fn from(notice: &TransitionNotice) -> Self {
+        Self::new(notice.session_ttl)
+    }
+}
+
+impl Default for EngineTransitions {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_reported_between_engines() {
+        let transitions = EngineTransitions::default();
+        let t0 = Instant::now();
+        let engines = ["elevenlabs", "elevenlabs", "piper", "piper", "elevenlabs"];
+        let changed: Vec<bool> = engines
+            .iter()
+            .enumerate()
+            .map(|(i, engine)| {
+                transitions.observe_at("guild-1", engine, false, t0 + Duration::from_secs(i as u64))
+            })
+            .collect();
+        assert_eq!(changed, [false, false, true, false, true]);
+
+        // Sessions are tracked independently; a new one starts quiet
+        assert!(!transitions.observe_at("chat-2", "piper", false, t0 + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_ttl_expiry_resets_session() {
+        let transitions = EngineTransitions::new(Duration::from_secs(60));
+        let t0 = Instant::now();
+        assert!(!transitions.observe_at("guild-1", "elevenlabs", false, t0));
+        assert!(!transitions.observe_at("guild-1", "piper", false, t0 + Duration::from_secs(61)));
+        assert!(transitions.observe_at(
+            "guild-1",
+            "elevenlabs",
+            false,
+            t0 + Duration::from_secs(90)
+        ));
+
+        transitions.reset("guild-1");
+        assert!(!transitions.observe_at("guild-1", "piper", false, t0 + Duration::from_secs(91)));
+    }
+
+    #[test]
+    fn test_explicit_switch_is_not_announced() {
+        let transitions = EngineTransitions::default();
+        let t0 = Instant::now();
+        assert!(!transitions.observe_at("guild-1", "elevenlabs", false, t0));
+        assert!(!transitions.observe_at("guild-1", "piper", true, t0 + Duration::from_secs(1)));
+        assert!(!transitions.observe_at("guild-1", "piper", false, t0 + Duration::from_secs(2)));
+        assert!(transitions.observe_at(
+            "guild-1",
+            "elevenlabs",
+            false,
+            t0 + Duration::from_secs(3)
+        ));
+
+        let notice = TransitionNotice::default();
+        assert_eq!(notice.apply("Hi", false), "Hi");
+        assert_eq!(
+            notice.apply("Hi", true),
+            format!("{} Hi", DEFAULT_TRANSITION_NOTICE)
+        );
+        let silent = TransitionNotice {
+            spoken: None,
+            ..Default::default()
+        };
+        assert_eq!(silent.apply("Hi", true), "Hi");
+    }
+}
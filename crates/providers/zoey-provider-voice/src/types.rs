@@ -1,9 +1,16 @@
 //! Core types for the voice provider
 
+use crate::effects::EffectConfig;
+use crate::length_limit::{LengthPolicy, DEFAULT_MAX_TTS_CHARS};
 use async_trait::async_trait;
 use bytes::Bytes;
 use zoey_core::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 /// Voice engine type enum
@@ -262,6 +269,45 @@ impl Default for AudioFormat {
     }
 }
 
+/// Sample layout of a piece of audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioSpec {
+    /// Sample rate (Hz)
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: u16,
+    /// Container/encoding
+    pub format: AudioFormat,
+}
+
+impl AudioSpec {
+    /// Create a new audio spec
+    pub fn new(sample_rate: u32, channels: u16, format: AudioFormat) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            format,
+        }
+    }
+
+    /// 16-bit mono PCM at the given sample rate
+    pub fn pcm_mono(sample_rate: u32) -> Self {
+        Self::new(sample_rate, 1, AudioFormat::Pcm)
+    }
+}
+
+impl std::fmt::Display for AudioSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}Hz/{}ch/{}",
+            self.sample_rate,
+            self.channels,
+            self.format.as_str()
+        )
+    }
+}
+
 /// Voice synthesis configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VoiceConfig {
@@ -287,6 +333,23 @@ pub struct VoiceConfig {
     pub endpoint: Option<String>,
     /// Sample rate (Hz)
     pub sample_rate: u32,
+    /// Effects applied in order to synthesized audio
+    #[serde(default)]
+    pub effects: Vec<EffectConfig>,
+    /// Voices for other languages, keyed by language code (`es`, `es-MX`,
+    /// `spa`); mixed-language replies switch voice per segment
+    #[serde(default)]
+    pub language_voices: HashMap<String, Voice>,
+    /// Longest text, in characters, synthesized per request
+    #[serde(default = "default_max_tts_chars")]
+    pub max_tts_chars: usize,
+    /// What happens to text over `max_tts_chars`; see [`crate::length_limit`]
+    #[serde(skip)]
+    pub length_policy: LengthPolicy,
+}
+
+fn default_max_tts_chars() -> usize {
+    DEFAULT_MAX_TTS_CHARS
 }
 
 impl Default for VoiceConfig {
@@ -303,6 +366,10 @@ impl Default for VoiceConfig {
             style: None,
             endpoint: None,
             sample_rate: 24000,
+            effects: Vec::new(),
+            language_voices: HashMap::new(),
+            max_tts_chars: DEFAULT_MAX_TTS_CHARS,
+            length_policy: LengthPolicy::default(),
         }
     }
 }
@@ -316,10 +383,16 @@ pub struct AudioData {
     pub format: AudioFormat,
     /// Sample rate (Hz)
     pub sample_rate: u32,
+    /// Number of interleaved channels (PCM)
+    pub channels: u16,
     /// Duration in milliseconds
     pub duration_ms: Option<u64>,
     /// Character count of input text
     pub character_count: usize,
+    /// Name of the engine that synthesized this audio, when known
+    ///
+    /// Set by [`crate::FailoverEngine`] and the plugin; engines leave it `None`.
+    pub engine: Option<String>,
 }
 
 impl AudioData {
@@ -329,11 +402,24 @@ impl AudioData {
             data,
             format,
             sample_rate,
+            channels: 1,
             duration_ms: None,
             character_count: 0,
+            engine: None,
         }
     }
 
+    /// Set the channel count
+    pub fn with_channels(mut self, channels: u16) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Sample layout of this audio
+    pub fn spec(&self) -> AudioSpec {
+        AudioSpec::new(self.sample_rate, self.channels, self.format)
+    }
+
     /// Get size in bytes
     pub fn size(&self) -> usize {
         self.data.len()
@@ -356,19 +442,91 @@ pub struct AudioChunk {
     pub is_final: bool,
     /// Timestamp offset in milliseconds
     pub timestamp_ms: Option<u64>,
+    /// Format of `data`, when the engine reports it
+    pub format: Option<AudioFormat>,
+    /// Sample rate of `data` (Hz), when the engine reports it
+    pub sample_rate: Option<u32>,
+}
+
+/// Audio chunks of one synthesis, in order, as the engine produces them
+///
+/// Read with [`Self::recv`] or as a [`futures_util::Stream`]. The stream
+/// notes when the first chunk with audio arrives, see
+/// [`Self::time_to_first_chunk`].
+#[derive(Debug)]
+pub struct AudioStream {
+    rx: mpsc::Receiver<Result<AudioChunk>>,
+    started: Instant,
+    time_to_first_chunk: Option<Duration>,
+}
+
+impl AudioStream {
+    /// Wrap a chunk channel; time to first chunk counts from now
+    pub fn new(rx: mpsc::Receiver<Result<AudioChunk>>) -> Self {
+        Self {
+            rx,
+            started: Instant::now(),
+            time_to_first_chunk: None,
+        }
+    }
+
+    /// Count time to first chunk from `started` instead, e.g. the request
+    pub fn with_start(mut self, started: Instant) -> Self {
+        self.started = started;
+        self
+    }
+
+    /// Receive the next chunk, `None` once the engine is done
+    pub async fn recv(&mut self) -> Option<Result<AudioChunk>> {
+        let item = self.rx.recv().await;
+        self.observe(&item);
+        item
+    }
+
+    /// Time from the start of the stream to its first chunk with audio
+    ///
+    /// `None` until that chunk has been received.
+    pub fn time_to_first_chunk(&self) -> Option<Duration> {
+        self.time_to_first_chunk
+    }
+
+    fn observe(&mut self, item: &Option<Result<AudioChunk>>) {
+        if self.time_to_first_chunk.is_some() {
+            return;
+        }
+        if let Some(Ok(chunk)) = item {
+            if !chunk.data.is_empty() {
+                self.time_to_first_chunk = Some(self.started.elapsed());
+            }
+        }
+    }
 }
 
-/// Audio stream receiver type
-pub type AudioStream = mpsc::Receiver<Result<AudioChunk>>;
+impl futures_util::Stream for AudioStream {
+    type Item = Result<AudioChunk>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(self.rx.poll_recv(cx));
+        self.observe(&item);
+        Poll::Ready(item)
+    }
+}
 
 /// Audio stream sender type
 pub type AudioStreamSender = mpsc::Sender<Result<AudioChunk>>;
 
 /// Create an audio stream channel
 pub fn create_audio_stream(buffer_size: usize) -> (AudioStreamSender, AudioStream) {
-    mpsc::channel(buffer_size)
+    let (tx, rx) = mpsc::channel(buffer_size);
+    (tx, AudioStream::new(rx))
 }
 
+/// Text synthesized by the default [`VoiceEngine::is_healthy`]
+pub const HEALTH_CHECK_TEXT: &str = "ok";
+
+/// Time a health check may take before the engine counts as down
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Voice engine trait - implemented by each TTS backend
 #[async_trait]
 pub trait VoiceEngine: Send + Sync {
@@ -387,6 +545,23 @@ pub trait VoiceEngine: Send + Sync {
     /// Check if engine is ready
     async fn is_ready(&self) -> bool;
 
+    /// Check that the engine can synthesize right now
+    ///
+    /// Unlike [`Self::is_ready`], this talks to the backend: by default it
+    /// synthesizes [`HEALTH_CHECK_TEXT`] and discards the audio, failing after
+    /// [`HEALTH_CHECK_TIMEOUT`]. Engines billed per character or with a
+    /// cheaper probe should override it.
+    async fn is_healthy(&self) -> bool {
+        matches!(
+            tokio::time::timeout(
+                HEALTH_CHECK_TIMEOUT,
+                self.synthesize(HEALTH_CHECK_TEXT, &VoiceConfig::default()),
+            )
+            .await,
+            Ok(Ok(_))
+        )
+    }
+
     /// Get supported audio formats
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![AudioFormat::Mp3]
@@ -396,6 +571,12 @@ pub trait VoiceEngine: Send + Sync {
     fn max_text_length(&self) -> usize {
         4096
     }
+
+    /// Whether [`Self::synthesize_stream`] yields audio while it is still
+    /// being synthesized, rather than slicing up a finished clip
+    fn supports_streaming(&self) -> bool {
+        false
+    }
 }
 
 // ============================================================================
@@ -562,6 +743,12 @@ pub struct TranscriptionResult {
     pub segments: Vec<TranscriptionSegment>,
     /// Processing time in milliseconds
     pub processing_time_ms: Option<u64>,
+    /// Spec of the audio as received, before normalization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_spec: Option<AudioSpec>,
+    /// Spec the audio was converted to for the engine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converted_spec: Option<AudioSpec>,
 }
 
 impl TranscriptionResult {
@@ -575,6 +762,8 @@ impl TranscriptionResult {
             duration_ms: None,
             segments: Vec::new(),
             processing_time_ms: None,
+            input_spec: None,
+            converted_spec: None,
         }
     }
 
@@ -623,6 +812,33 @@ pub struct TranscriptionConfig {
     pub beam_size: u32,
     /// Suppress non-speech tokens
     pub suppress_non_speech: bool,
+    /// Pre-placed Whisper model file; when set the model is never downloaded
+    #[serde(default)]
+    pub model_path: Option<PathBuf>,
+    /// Never fetch models over the network (air-gapped deployments)
+    #[serde(default)]
+    pub never_download: bool,
+}
+
+impl TranscriptionConfig {
+    /// Apply `WHISPER_MODEL_PATH` and `WHISPER_NEVER_DOWNLOAD` from the environment
+    ///
+    /// Lets operators pin an offline model for every Whisper engine without
+    /// touching each adapter's configuration.
+    pub fn with_env_overrides(mut self) -> Self {
+        if let Some(path) = std::env::var_os("WHISPER_MODEL_PATH").filter(|p| !p.is_empty()) {
+            self.model_path = Some(PathBuf::from(path));
+        }
+        if let Ok(v) = std::env::var("WHISPER_NEVER_DOWNLOAD") {
+            self.never_download = matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on");
+        }
+        self
+    }
+
+    /// Whether a missing model may be downloaded
+    pub fn allows_download(&self) -> bool {
+        self.model_path.is_none() && !self.never_download
+    }
 }
 
 impl Default for TranscriptionConfig {
@@ -639,6 +855,8 @@ impl Default for TranscriptionConfig {
             temperature: 0.0,
             beam_size: 5,
             suppress_non_speech: true,
+            model_path: None,
+            never_download: false,
         }
     }
 }
@@ -682,6 +900,12 @@ pub trait SpeechEngine: Send + Sync {
     /// Check if engine is ready (model loaded, service available)
     async fn is_ready(&self) -> bool;
 
+    /// Input layout the engine expects; audio is converted to it before
+    /// `transcribe` is called
+    fn expected_input(&self) -> AudioSpec {
+        AudioSpec::pcm_mono(16000)
+    }
+
     /// Get supported input audio formats
     fn supported_formats(&self) -> Vec<AudioFormat> {
         vec![AudioFormat::Wav, AudioFormat::Mp3, AudioFormat::Pcm]
@@ -738,6 +962,10 @@ pub enum VoiceError {
     #[error("Unsupported audio format: {0}")]
     UnsupportedFormat(String),
 
+    /// Input audio that cannot be converted to what the engine expects
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
     // STT-specific errors
     /// Audio duration too long
     #[error("Audio duration exceeds maximum: {duration_secs}s > {max_secs}s")]
@@ -775,6 +1003,9 @@ pub enum VoiceError {
 
 impl From<VoiceError> for zoey_core::ZoeyError {
     fn from(err: VoiceError) -> Self {
-        zoey_core::ZoeyError::other(err.to_string())
+        match err {
+            VoiceError::TextTooLong { .. } => zoey_core::ZoeyError::Validation(err.to_string()),
+            err => zoey_core::ZoeyError::other(err.to_string()),
+        }
     }
 }
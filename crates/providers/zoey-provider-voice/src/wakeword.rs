@@ -0,0 +1,423 @@
+//! Wake-word detection ahead of speech-to-text
+//!
+//! Running full STT on every utterance just to find the character's name is
+//! expensive and puts unrelated conversation into transcripts. A
+//! [`WakeWordDetector`] scores 80ms frames of 16kHz mono audio with a small
+//! model and a [`TranscriptionSession`] only lets audio through to STT for a
+//! window after a detection (or while a conversation is active).
+//!
+//! With the `wakeword` feature the detector runs openWakeWord ONNX models via
+//! `ort`. The model directory must contain the shared feature models
+//! (`melspectrogram.onnx`, `embedding_model.onnx`) next to the wake-word
+//! model itself (e.g. `zoey.onnx`). Any [`WakeWordModel`] can be plugged in
+//! instead, which is how the gating logic is tested.
+
+use crate::types::VoiceError;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::warn;
+use zoey_core::Result;
+
+/// Sample rate wake-word models operate on (Hz)
+pub const WAKEWORD_SAMPLE_RATE: u32 = 16000;
+
+/// Samples per detector frame (80ms at 16kHz)
+pub const WAKEWORD_FRAME_SAMPLES: usize = 1280;
+
+/// Shared openWakeWord feature models expected next to the wake-word model
+pub const WAKEWORD_FEATURE_MODELS: [&str; 2] = ["melspectrogram.onnx", "embedding_model.onnx"];
+
+/// Minimum gap between two reported detections (ms)
+const REFRACTORY_MS: u64 = 1000;
+
+/// Wake-word detection settings
+#[derive(Debug, Clone)]
+pub struct WakeWordConfig {
+    /// Directory holding the ONNX models
+    pub model_dir: PathBuf,
+    /// Wake-word model name (file stem, e.g. "zoey" for `zoey.onnx`)
+    pub model: String,
+    /// Score (0.0-1.0) at or above which a frame counts as a detection
+    pub threshold: f32,
+    /// How long STT stays active after a detection
+    pub window: Duration,
+}
+
+impl Default for WakeWordConfig {
+    fn default() -> Self {
+        Self {
+            model_dir: PathBuf::from(".zoey/voice/wakeword"),
+            model: "zoey".to_string(),
+            threshold: 0.5,
+            window: Duration::from_secs(10),
+        }
+    }
+}
+
+impl WakeWordConfig {
+    /// Path of the wake-word model file
+    pub fn model_path(&self) -> PathBuf {
+        self.model_dir.join(format!("{}.onnx", self.model))
+    }
+}
+
+/// Check that the wake-word model and its feature models exist
+///
+/// The error lists every expected filename so a missing download is easy to fix.
+pub fn check_model_files(model_path: &Path) -> Result<()> {
+    let dir = model_path.parent().unwrap_or_else(|| Path::new("."));
+    let mut expected: Vec<PathBuf> = WAKEWORD_FEATURE_MODELS.iter().map(|f| dir.join(f)).collect();
+    expected.push(model_path.to_path_buf());
+    let missing: Vec<String> = expected
+        .iter()
+        .filter(|p| !p.exists())
+        .map(|p| p.file_name().unwrap_or_default().to_string_lossy().into_owned())
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<String> = expected
+        .iter()
+        .map(|p| p.file_name().unwrap_or_default().to_string_lossy().into_owned())
+        .collect();
+    Err(VoiceError::ModelError(format!(
+        "wake-word models missing in {}: expected {} (missing: {}). \
+         Download the openWakeWord feature models and place your wake-word model alongside them.",
+        dir.display(),
+        names.join(", "),
+        missing.join(", ")
+    ))
+    .into())
+}
+
+/// A wake-word detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// Model score for the detecting frame
+    pub score: f32,
+    /// Position of the end of the detecting frame in the processed stream (ms)
+    pub offset_ms: u64,
+}
+
+/// Inference backend scoring one frame of audio
+pub trait WakeWordModel: Send {
+    /// Score a frame of [`WAKEWORD_FRAME_SAMPLES`] 16kHz mono samples (0.0-1.0)
+    fn predict(&mut self, frame: &[i16]) -> Result<f32>;
+
+    /// Clear any streaming state
+    fn reset(&mut self) {}
+}
+
+/// Streaming wake-word detector
+pub struct WakeWordDetector {
+    model: Box<dyn WakeWordModel>,
+    threshold: f32,
+    pending: Vec<i16>,
+    samples_seen: u64,
+    last_detection_ms: Option<u64>,
+}
+
+impl WakeWordDetector {
+    /// Load an openWakeWord model; feature models are read from the same directory
+    #[cfg(feature = "wakeword")]
+    pub fn new(model_path: impl AsRef<Path>, threshold: f32) -> Result<Self> {
+        let model = onnx::OnnxWakeWordModel::load(model_path.as_ref())?;
+        Ok(Self::with_model(Box::new(model), threshold))
+    }
+
+    /// Build a detector around any inference backend
+    pub fn with_model(model: Box<dyn WakeWordModel>, threshold: f32) -> Self {
+        Self {
+            model,
+            threshold: threshold.clamp(0.0, 1.0),
+            pending: Vec::with_capacity(WAKEWORD_FRAME_SAMPLES),
+            samples_seen: 0,
+            last_detection_ms: None,
+        }
+    }
+
+    /// Detection threshold
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Feed 16kHz mono audio, returning the first detection it contains
+    ///
+    /// Input of any length is accepted; it is scored in
+    /// [`WAKEWORD_FRAME_SAMPLES`] chunks and any remainder is kept for the
+    /// next call.
+    pub fn process(&mut self, frame: &[i16]) -> Option<Detection> {
+        self.pending.extend_from_slice(frame);
+        let mut detection = None;
+        while self.pending.len() >= WAKEWORD_FRAME_SAMPLES {
+            let chunk: Vec<i16> = self.pending.drain(..WAKEWORD_FRAME_SAMPLES).collect();
+            self.samples_seen += chunk.len() as u64;
+            let score = match self.model.predict(&chunk) {
+                Ok(score) => score,
+                Err(e) => {
+                    warn!(error = %e, "Wake-word inference failed");
+                    continue;
+                }
+            };
+            let offset_ms = self.samples_seen * 1000 / WAKEWORD_SAMPLE_RATE as u64;
+            let cooling_down = self
+                .last_detection_ms
+                .map(|last| offset_ms.saturating_sub(last) < REFRACTORY_MS)
+                .unwrap_or(false);
+            if score >= self.threshold && !cooling_down && detection.is_none() {
+                self.last_detection_ms = Some(offset_ms);
+                detection = Some(Detection { score, offset_ms });
+            }
+        }
+        detection
+    }
+
+    /// Drop buffered audio and model state
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.model.reset();
+    }
+}
+
+/// Gates STT for one speaker behind wake-word detections
+pub struct TranscriptionSession {
+    detector: WakeWordDetector,
+    window: Duration,
+    open_until: Option<Instant>,
+}
+
+impl TranscriptionSession {
+    /// Create a session keeping STT open for `window` after each detection
+    pub fn new(detector: WakeWordDetector, window: Duration) -> Self {
+        Self {
+            detector,
+            window,
+            open_until: None,
+        }
+    }
+
+    /// Feed 16kHz mono audio; a detection opens the transcription window
+    pub fn push_audio(&mut self, samples: &[i16]) -> Option<Detection> {
+        let detection = self.detector.process(samples);
+        if detection.is_some() {
+            self.keep_open();
+        }
+        detection
+    }
+
+    /// Extend the window from now, e.g. while a conversation is active
+    pub fn keep_open(&mut self) {
+        self.open_until = Some(Instant::now() + self.window);
+    }
+
+    /// Whether buffered speech should be sent to full STT
+    pub fn should_transcribe(&self) -> bool {
+        self.is_open_at(Instant::now())
+    }
+
+    fn is_open_at(&self, now: Instant) -> bool {
+        self.open_until.map(|until| now < until).unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "wakeword")]
+mod onnx {
+    //! openWakeWord inference: raw audio -> mel frames -> embeddings -> score
+
+    use super::{check_model_files, WakeWordModel, WAKEWORD_FEATURE_MODELS, WAKEWORD_FRAME_SAMPLES};
+    use crate::types::VoiceError;
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::collections::VecDeque;
+    use std::path::Path;
+    use zoey_core::Result;
+
+    /// Extra samples of context the mel model needs before each frame
+    const MEL_CONTEXT_SAMPLES: usize = 480;
+    /// Mel bins per frame
+    const MEL_BINS: usize = 32;
+    /// Mel frames produced per detector frame (10ms hop)
+    const MEL_FRAMES_PER_CHUNK: usize = WAKEWORD_FRAME_SAMPLES / 160;
+    /// Mel frames per embedding window
+    const EMBEDDING_WINDOW: usize = 76;
+    /// Embedding dimension
+    const EMBEDDING_DIM: usize = 96;
+    /// Embeddings scored by the wake-word model
+    const FEATURE_WINDOW: usize = 16;
+
+    fn model_error(e: impl std::fmt::Display) -> zoey_core::ZoeyError {
+        VoiceError::ModelError(e.to_string()).into()
+    }
+
+    fn load_session(path: &Path) -> Result<Session> {
+        Session::builder()
+            .map_err(model_error)?
+            .commit_from_file(path)
+            .map_err(|e| model_error(format!("{}: {}", path.display(), e)))
+    }
+
+    pub(super) struct OnnxWakeWordModel {
+        melspectrogram: Session,
+        embedding: Session,
+        wakeword: Session,
+        raw: VecDeque<i16>,
+        mel: VecDeque<[f32; MEL_BINS]>,
+        features: VecDeque<[f32; EMBEDDING_DIM]>,
+    }
+
+    impl OnnxWakeWordModel {
+        pub(super) fn load(model_path: &Path) -> Result<Self> {
+            check_model_files(model_path)?;
+            let dir = model_path.parent().unwrap_or_else(|| Path::new("."));
+            Ok(Self {
+                melspectrogram: load_session(&dir.join(WAKEWORD_FEATURE_MODELS[0]))?,
+                embedding: load_session(&dir.join(WAKEWORD_FEATURE_MODELS[1]))?,
+                wakeword: load_session(model_path)?,
+                raw: VecDeque::new(),
+                mel: VecDeque::new(),
+                features: VecDeque::new(),
+            })
+        }
+
+        fn run(session: &mut Session, shape: Vec<usize>, data: Vec<f32>) -> Result<Vec<f32>> {
+            let input = Tensor::from_array((shape, data)).map_err(model_error)?;
+            let outputs = session.run(ort::inputs![input]).map_err(model_error)?;
+            let (_, values) = outputs[0]
+                .try_extract_tensor::<f32>()
+                .map_err(model_error)?;
+            Ok(values.to_vec())
+        }
+    }
+
+    impl WakeWordModel for OnnxWakeWordModel {
+        fn predict(&mut self, frame: &[i16]) -> Result<f32> {
+            self.raw.extend(frame.iter().copied());
+            while self.raw.len() > WAKEWORD_FRAME_SAMPLES + MEL_CONTEXT_SAMPLES {
+                self.raw.pop_front();
+            }
+            let samples: Vec<f32> = self.raw.iter().map(|&s| s as f32).collect();
+            let mel = Self::run(&mut self.melspectrogram, vec![1, samples.len()], samples)?;
+            let frames: Vec<[f32; MEL_BINS]> = mel
+                .chunks_exact(MEL_BINS)
+                .map(|bins| {
+                    let mut frame = [0.0; MEL_BINS];
+                    for (out, v) in frame.iter_mut().zip(bins) {
+                        // Same scaling openWakeWord applies to the mel output
+                        *out = v / 10.0 + 2.0;
+                    }
+                    frame
+                })
+                .collect();
+            let skip = frames.len().saturating_sub(MEL_FRAMES_PER_CHUNK);
+            self.mel.extend(frames.into_iter().skip(skip));
+            while self.mel.len() > EMBEDDING_WINDOW {
+                self.mel.pop_front();
+            }
+            if self.mel.len() < EMBEDDING_WINDOW {
+                return Ok(0.0);
+            }
+
+            let window: Vec<f32> = self.mel.iter().flatten().copied().collect();
+            let embedding = Self::run(&mut self.embedding, vec![1, EMBEDDING_WINDOW, MEL_BINS, 1], window)?;
+            let mut feature = [0.0; EMBEDDING_DIM];
+            for (out, v) in feature.iter_mut().zip(embedding) {
+                *out = v;
+            }
+            self.features.push_back(feature);
+            while self.features.len() > FEATURE_WINDOW {
+                self.features.pop_front();
+            }
+            if self.features.len() < FEATURE_WINDOW {
+                return Ok(0.0);
+            }
+
+            let features: Vec<f32> = self.features.iter().flatten().copied().collect();
+            let scores = Self::run(&mut self.wakeword, vec![1, FEATURE_WINDOW, EMBEDDING_DIM], features)?;
+            Ok(scores.first().copied().unwrap_or(0.0))
+        }
+
+        fn reset(&mut self) {
+            self.raw.clear();
+            self.mel.clear();
+            self.features.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Returns queued scores frame by frame, then 0.0
+    struct ScriptedModel {
+        scores: VecDeque<f32>,
+    }
+
+    impl WakeWordModel for ScriptedModel {
+        fn predict(&mut self, frame: &[i16]) -> Result<f32> {
+            assert_eq!(frame.len(), WAKEWORD_FRAME_SAMPLES);
+            Ok(self.scores.pop_front().unwrap_or(0.0))
+        }
+    }
+
+    fn detector(scores: &[f32], threshold: f32) -> WakeWordDetector {
+        WakeWordDetector::with_model(
+            Box::new(ScriptedModel {
+                scores: scores.iter().copied().collect(),
+            }),
+            threshold,
+        )
+    }
+
+    fn frames(n: usize) -> Vec<i16> {
+        vec![0; WAKEWORD_FRAME_SAMPLES * n]
+    }
+
+    #[test]
+    fn test_threshold() {
+        let mut d = detector(&[0.2, 0.49, 0.5, 0.9], 0.5);
+        assert_eq!(d.process(&frames(2)), None);
+        let hit = d.process(&frames(1)).unwrap();
+        assert_eq!(hit.score, 0.5);
+        assert_eq!(hit.offset_ms, 240);
+        // The next frame is within the refractory period
+        assert_eq!(d.process(&frames(1)), None);
+    }
+
+    #[test]
+    fn test_partial_frames_are_buffered() {
+        let mut d = detector(&[0.8], 0.5);
+        assert_eq!(d.process(&vec![0; WAKEWORD_FRAME_SAMPLES / 2]), None);
+        let hit = d.process(&vec![0; WAKEWORD_FRAME_SAMPLES / 2]).unwrap();
+        assert_eq!(hit.offset_ms, 80);
+    }
+
+    #[test]
+    fn test_gating_window() {
+        let mut session = TranscriptionSession::new(detector(&[0.1, 0.95], 0.5), Duration::from_secs(5));
+        assert!(!session.should_transcribe());
+        assert!(session.push_audio(&frames(1)).is_none());
+        assert!(!session.should_transcribe());
+        assert!(session.push_audio(&frames(1)).is_some());
+        assert!(session.should_transcribe());
+
+        let now = Instant::now();
+        assert!(session.is_open_at(now + Duration::from_secs(4)));
+        assert!(!session.is_open_at(now + Duration::from_secs(6)));
+
+        // An active conversation keeps the window open without a new detection
+        let mut idle = TranscriptionSession::new(detector(&[], 0.5), Duration::from_secs(5));
+        idle.keep_open();
+        assert!(idle.should_transcribe());
+    }
+
+    #[test]
+    fn test_missing_model_files_listed() {
+        let dir = std::env::temp_dir().join("zoey-wakeword-missing");
+        let err = check_model_files(&dir.join("zoey.onnx")).unwrap_err().to_string();
+        assert!(err.contains("melspectrogram.onnx"));
+        assert!(err.contains("embedding_model.onnx"));
+        assert!(err.contains("zoey.onnx"));
+    }
+}
@@ -0,0 +1,334 @@
+//! Bulk memory inserts
+//!
+//! Knowledge ingestion writes hundreds of chunk memories per document.
+//! [`MongoAdapter::create_memories_bulk`](crate::MongoAdapter::create_memories_bulk)
+//! sends them with `insert_many`, [`MAX_BULK_BATCH`] documents per round trip,
+//! and returns a [`BulkWriteReport`] with the outcome of every memory so the
+//! caller can retry only the ones that failed.
+//!
+//! In unordered mode every document is attempted and duplicate `_id`s (a
+//! chunk re-ingested with the same deterministic ID) are reported as
+//! [`BulkItemStatus::Skipped`]. In ordered mode the server stops at the first
+//! error; the memories after it are [`BulkItemStatus::NotAttempted`].
+
+use async_trait::async_trait;
+use mongodb::{
+    bson::Document,
+    error::{Error as MongoError, ErrorKind},
+    Collection,
+};
+use zoey_core::{types::UUID, Result, ZoeyError};
+
+use crate::state_store::DUPLICATE_KEY;
+
+/// Most documents sent in one `insert_many` call
+pub const MAX_BULK_BATCH: usize = 500;
+
+/// What happened to one memory of a bulk insert
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkItemStatus {
+    /// Written
+    Inserted,
+    /// A memory with the same ID already exists
+    Skipped,
+    /// Rejected by the server
+    Failed(String),
+    /// Not sent because an earlier memory failed in ordered mode
+    NotAttempted,
+}
+
+/// Outcome of one memory, by its position in the request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkItemResult {
+    /// Index of the memory in the request
+    pub index: usize,
+    /// ID of the memory
+    pub id: UUID,
+    /// What happened to it
+    pub status: BulkItemStatus,
+}
+
+/// Per-memory outcome of a bulk insert
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BulkWriteReport {
+    /// One entry per requested memory, in request order
+    pub items: Vec<BulkItemResult>,
+    /// `insert_many` calls made
+    pub round_trips: usize,
+}
+
+impl BulkWriteReport {
+    fn count(&self, matches: impl Fn(&BulkItemStatus) -> bool) -> usize {
+        self.items.iter().filter(|i| matches(&i.status)).count()
+    }
+
+    /// Memories written
+    pub fn inserted(&self) -> usize {
+        self.count(|s| *s == BulkItemStatus::Inserted)
+    }
+
+    /// Memories that already existed
+    pub fn skipped(&self) -> usize {
+        self.count(|s| *s == BulkItemStatus::Skipped)
+    }
+
+    /// Memories rejected or never sent
+    pub fn failed(&self) -> usize {
+        self.count(|s| matches!(s, BulkItemStatus::Failed(_) | BulkItemStatus::NotAttempted))
+    }
+
+    /// Indices of the memories worth sending again
+    pub fn retry_indices(&self) -> Vec<usize> {
+        self.items
+            .iter()
+            .filter(|i| {
+                matches!(
+                    i.status,
+                    BulkItemStatus::Failed(_) | BulkItemStatus::NotAttempted
+                )
+            })
+            .map(|i| i.index)
+            .collect()
+    }
+
+    /// Whether every memory is stored (inserted or already present)
+    pub fn is_complete(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// A write error for one document of an `insert_many` call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DocumentError {
+    /// Index within the call's documents
+    pub index: usize,
+    pub code: i32,
+    pub message: String,
+}
+
+/// Where bulk-inserted memory documents go (a collection, or a mock in tests)
+#[async_trait]
+pub(crate) trait MemoryDocuments: Send + Sync {
+    /// Insert documents in one round trip, returning the per-document errors
+    ///
+    /// `Err` means the call failed as a whole (e.g. the connection dropped).
+    async fn insert_many(
+        &self,
+        documents: Vec<Document>,
+        ordered: bool,
+    ) -> Result<Vec<DocumentError>>;
+}
+
+#[async_trait]
+impl MemoryDocuments for Collection<Document> {
+    async fn insert_many(
+        &self,
+        documents: Vec<Document>,
+        ordered: bool,
+    ) -> Result<Vec<DocumentError>> {
+        match Collection::insert_many(self, documents)
+            .ordered(ordered)
+            .await
+        {
+            Ok(_) => Ok(Vec::new()),
+            Err(e) => document_errors(e),
+        }
+    }
+}
+
+/// Per-document errors of a failed `insert_many`, or the error itself if it
+/// did not come from individual documents
+fn document_errors(error: MongoError) -> Result<Vec<DocumentError>> {
+    match *error.kind {
+        ErrorKind::InsertMany(ref failure) if failure.write_concern_error.is_none() => Ok(failure
+            .write_errors
+            .iter()
+            .flatten()
+            .map(|e| DocumentError {
+                index: e.index,
+                code: e.code,
+                message: e.message.clone(),
+            })
+            .collect()),
+        _ => Err(ZoeyError::database(format!(
+            "Failed to bulk insert memories: {}",
+            error
+        ))),
+    }
+}
+
+/// Insert `documents` (memory `ids` in the same order) in batches of [`MAX_BULK_BATCH`]
+pub(crate) async fn insert_memories(
+    target: &dyn MemoryDocuments,
+    ids: Vec<UUID>,
+    mut documents: Vec<Document>,
+    ordered: bool,
+) -> Result<BulkWriteReport> {
+    let mut report = BulkWriteReport {
+        items: ids
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| BulkItemResult {
+                index,
+                id,
+                status: BulkItemStatus::NotAttempted,
+            })
+            .collect(),
+        round_trips: 0,
+    };
+    let mut offset = 0;
+    while !documents.is_empty() {
+        let rest = documents.split_off(documents.len().min(MAX_BULK_BATCH));
+        let sent = documents.len();
+        let errors = target.insert_many(documents, ordered).await?;
+        report.round_trips += 1;
+
+        // In ordered mode nothing after the first error was written
+        let attempted = match errors.iter().map(|e| e.index).min() {
+            Some(first) if ordered => first + 1,
+            _ => sent,
+        };
+        for item in &mut report.items[offset..offset + attempted] {
+            item.status = BulkItemStatus::Inserted;
+        }
+        for error in errors {
+            let Some(item) = report.items.get_mut(offset + error.index) else {
+                continue;
+            };
+            item.status = if error.code == DUPLICATE_KEY {
+                BulkItemStatus::Skipped
+            } else {
+                BulkItemStatus::Failed(error.message)
+            };
+        }
+        if attempted < sent {
+            break;
+        }
+        offset += sent;
+        documents = rest;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Counts commands and rejects documents whose `_id` is already stored
+    #[derive(Default)]
+    struct CountingCollection {
+        commands: AtomicUsize,
+        ids: Mutex<HashSet<String>>,
+    }
+
+    impl CountingCollection {
+        /// One round trip per memory, as `create_memory` does
+        async fn insert_one(&self, document: Document) -> Result<()> {
+            self.commands.fetch_add(1, Ordering::SeqCst);
+            let id = document.get_str("_id").unwrap().to_string();
+            if !self.ids.lock().unwrap().insert(id) {
+                return Err(ZoeyError::database("duplicate key"));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl MemoryDocuments for CountingCollection {
+        async fn insert_many(
+            &self,
+            documents: Vec<Document>,
+            ordered: bool,
+        ) -> Result<Vec<DocumentError>> {
+            self.commands.fetch_add(1, Ordering::SeqCst);
+            let mut ids = self.ids.lock().unwrap();
+            let mut errors = Vec::new();
+            for (index, document) in documents.iter().enumerate() {
+                if !ids.insert(document.get_str("_id").unwrap().to_string()) {
+                    errors.push(DocumentError {
+                        index,
+                        code: DUPLICATE_KEY,
+                        message: "E11000 duplicate key error".into(),
+                    });
+                    if ordered {
+                        break;
+                    }
+                }
+            }
+            Ok(errors)
+        }
+    }
+
+    fn chunks(count: usize) -> (Vec<UUID>, Vec<Document>) {
+        let ids: Vec<UUID> = (0..count).map(|_| uuid::Uuid::new_v4()).collect();
+        let documents = ids
+            .iter()
+            .map(|id| doc! { "_id": id.to_string() })
+            .collect();
+        (ids, documents)
+    }
+
+    #[tokio::test]
+    async fn test_bulk_insert_round_trips_against_single_inserts() {
+        let (ids, documents) = chunks(300);
+
+        let single = CountingCollection::default();
+        for document in documents.clone() {
+            single.insert_one(document).await.unwrap();
+        }
+        assert_eq!(single.commands.load(Ordering::SeqCst), 300);
+
+        let bulk = CountingCollection::default();
+        let report = insert_memories(&bulk, ids, documents, false).await.unwrap();
+        assert_eq!(bulk.commands.load(Ordering::SeqCst), 1);
+        assert_eq!(report.round_trips, 1);
+        assert_eq!(report.inserted(), 300);
+        assert!(report.is_complete());
+
+        let (ids, documents) = chunks(MAX_BULK_BATCH * 2 + 1);
+        let report = insert_memories(&bulk, ids, documents, true).await.unwrap();
+        assert_eq!(report.round_trips, 3);
+        assert_eq!(report.inserted(), MAX_BULK_BATCH * 2 + 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_are_skipped_unordered_and_stop_ordered_batches() {
+        let target = CountingCollection::default();
+        let (ids, documents) = chunks(6);
+        insert_memories(&target, vec![ids[2]], vec![documents[2].clone()], true)
+            .await
+            .unwrap();
+
+        let report = insert_memories(&target, ids.clone(), documents.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!((report.inserted(), report.skipped()), (5, 1));
+        assert_eq!(report.items[2].status, BulkItemStatus::Skipped);
+        assert_eq!(report.items[2].id, ids[2]);
+        assert!(report.is_complete());
+
+        // Re-ingesting in ordered mode stops at the first existing memory
+        let (mut new_ids, mut new_documents) = chunks(3);
+        new_ids.insert(1, ids[0]);
+        new_documents.insert(1, documents[0].clone());
+        let report = insert_memories(&target, new_ids, new_documents, true)
+            .await
+            .unwrap();
+        let statuses: Vec<_> = report.items.iter().map(|i| i.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                BulkItemStatus::Inserted,
+                BulkItemStatus::Skipped,
+                BulkItemStatus::NotAttempted,
+                BulkItemStatus::NotAttempted,
+            ]
+        );
+        assert_eq!(report.retry_indices(), vec![2, 3]);
+        assert!(!report.is_complete());
+    }
+}
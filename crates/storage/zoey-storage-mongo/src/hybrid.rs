@@ -0,0 +1,473 @@
+//! Hybrid keyword + vector search
+//!
+//! Vector search misses exact matches for case numbers, citations and names.
+//! [`MongoVectorSearch::hybrid_search`](crate::MongoVectorSearch::hybrid_search)
+//! runs a keyword query and a vector query in parallel and merges the two
+//! ranked lists with reciprocal rank fusion (RRF):
+//!
+//! ```text
+//! score(d) = vector_weight / (rrf_k + vector_rank(d)) + keyword_weight / (rrf_k + keyword_rank(d))
+//! ```
+//!
+//! Ranks start at 1 and a leg that did not return `d` contributes nothing.
+//! A memory found by both legs appears once, with both contributions summed;
+//! within a leg only its best rank counts. Equal fused scores are ordered by
+//! vector rank, then keyword rank, then memory ID, so results are stable.
+//!
+//! The keyword leg uses Atlas Search (`$search`). Deployments without it fall
+//! back to a case-insensitive regex `$match` on `content.text`, scored by the
+//! number of query terms a memory contains.
+//!
+//! Each returned memory keeps its vector similarity in `similarity` (`None`
+//! when only the keyword leg found it) and carries the fusion details under
+//! the [`HYBRID_METADATA_KEY`] metadata key.
+
+use mongodb::bson::{doc, Bson, Document};
+use std::collections::HashMap;
+use zoey_core::{
+    types::{Memory, MemoryMetadata},
+    Result, ZoeyError,
+};
+
+use crate::vector_search::VectorFilter;
+
+/// RRF constant used when none is configured
+pub const DEFAULT_RRF_K: f64 = 60.0;
+
+/// Atlas Search index queried when none is configured (Atlas's default name)
+pub const DEFAULT_SEARCH_INDEX: &str = "default";
+
+/// Metadata key holding a result's fusion details
+pub const HYBRID_METADATA_KEY: &str = "hybrid";
+
+/// Candidates fetched from each leg per requested result
+const CANDIDATES_PER_RESULT: usize = 3;
+
+/// Most query terms matched by the regex fallback
+const MAX_KEYWORD_TERMS: usize = 16;
+
+/// Options of a hybrid search
+#[derive(Debug, Clone)]
+pub struct HybridSearchOptions {
+    rrf_k: f64,
+    vector_weight: f64,
+    keyword_weight: f64,
+    filter: VectorFilter,
+    search_index: String,
+}
+
+impl Default for HybridSearchOptions {
+    fn default() -> Self {
+        Self {
+            rrf_k: DEFAULT_RRF_K,
+            vector_weight: 1.0,
+            keyword_weight: 1.0,
+            filter: VectorFilter::default(),
+            search_index: DEFAULT_SEARCH_INDEX.to_string(),
+        }
+    }
+}
+
+impl HybridSearchOptions {
+    /// Equal weights, [`DEFAULT_RRF_K`] and no filter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// RRF constant; larger values flatten the gap between top and lower ranks
+    pub fn rrf_k(mut self, rrf_k: f64) -> Self {
+        self.rrf_k = rrf_k;
+        self
+    }
+
+    /// Weight of the vector ranking
+    pub fn vector_weight(mut self, weight: f64) -> Self {
+        self.vector_weight = weight;
+        self
+    }
+
+    /// Weight of the keyword ranking
+    pub fn keyword_weight(mut self, weight: f64) -> Self {
+        self.keyword_weight = weight;
+        self
+    }
+
+    /// Restrict both legs to memories matching `filter`
+    pub fn filter(mut self, filter: VectorFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Atlas Search index used by the keyword leg
+    pub fn search_index(mut self, name: impl Into<String>) -> Self {
+        self.search_index = name.into();
+        self
+    }
+
+    pub(crate) fn filter_ref(&self) -> &VectorFilter {
+        &self.filter
+    }
+
+    /// Reject a non-positive RRF constant and negative weights
+    pub(crate) fn validate(&self) -> Result<()> {
+        if !(self.rrf_k.is_finite() && self.rrf_k > 0.0) {
+            return Err(ZoeyError::validation(format!(
+                "RRF constant must be positive, got {}",
+                self.rrf_k
+            )));
+        }
+        for (name, weight) in [
+            ("vector", self.vector_weight),
+            ("keyword", self.keyword_weight),
+        ] {
+            if !(weight.is_finite() && weight >= 0.0) {
+                return Err(ZoeyError::validation(format!(
+                    "{} weight must be non-negative, got {}",
+                    name, weight
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Results fetched from each leg for `k` fused results
+pub(crate) fn candidates(k: usize) -> usize {
+    k.saturating_mul(CANDIDATES_PER_RESULT).max(k)
+}
+
+/// Merge the vector results and the keyword results (with their scores),
+/// each best first, into the `k` best memories by RRF score
+pub(crate) fn fuse(
+    vector: Vec<Memory>,
+    keyword: Vec<(Memory, f64)>,
+    options: &HybridSearchOptions,
+    k: usize,
+) -> Vec<Memory> {
+    struct Fused {
+        memory: Memory,
+        score: f64,
+        vector_rank: Option<usize>,
+        keyword_rank: Option<usize>,
+        keyword_score: Option<f64>,
+    }
+
+    let mut fused: Vec<Fused> = Vec::new();
+    let mut positions: HashMap<uuid::Uuid, usize> = HashMap::new();
+
+    for (index, memory) in vector.into_iter().enumerate() {
+        if positions.contains_key(&memory.id) {
+            continue;
+        }
+        let rank = index + 1;
+        positions.insert(memory.id, fused.len());
+        fused.push(Fused {
+            memory,
+            score: options.vector_weight / (options.rrf_k + rank as f64),
+            vector_rank: Some(rank),
+            keyword_rank: None,
+            keyword_score: None,
+        });
+    }
+    for (index, (memory, keyword_score)) in keyword.into_iter().enumerate() {
+        let rank = index + 1;
+        let contribution = options.keyword_weight / (options.rrf_k + rank as f64);
+        match positions.get(&memory.id) {
+            Some(&position) => {
+                let entry = &mut fused[position];
+                if entry.keyword_rank.is_none() {
+                    entry.score += contribution;
+                    entry.keyword_rank = Some(rank);
+                    entry.keyword_score = Some(keyword_score);
+                }
+            }
+            None => {
+                positions.insert(memory.id, fused.len());
+                fused.push(Fused {
+                    // Only the vector leg measures similarity
+                    memory: Memory {
+                        similarity: None,
+                        ..memory
+                    },
+                    score: contribution,
+                    vector_rank: None,
+                    keyword_rank: Some(rank),
+                    keyword_score: Some(keyword_score),
+                });
+            }
+        }
+    }
+
+    let by_rank = |rank: Option<usize>| rank.unwrap_or(usize::MAX);
+    fused.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| by_rank(a.vector_rank).cmp(&by_rank(b.vector_rank)))
+            .then_with(|| by_rank(a.keyword_rank).cmp(&by_rank(b.keyword_rank)))
+            .then_with(|| a.memory.id.cmp(&b.memory.id))
+    });
+    fused.truncate(k);
+
+    fused
+        .into_iter()
+        .map(|f| {
+            let mut memory = f.memory;
+            let details = serde_json::json!({
+                "rrf_score": f.score,
+                "vector_similarity": memory.similarity,
+                "vector_rank": f.vector_rank,
+                "keyword_score": f.keyword_score,
+                "keyword_rank": f.keyword_rank,
+            });
+            memory
+                .metadata
+                .get_or_insert_with(|| MemoryMetadata {
+                    memory_type: None,
+                    entity_name: None,
+                    data: HashMap::new(),
+                })
+                .data
+                .insert(HYBRID_METADATA_KEY.to_string(), details);
+            memory
+        })
+        .collect()
+}
+
+/// Fields returned by the keyword leg, with the score in `keyword_score`
+fn keyword_projection(score: Bson) -> Document {
+    doc! {
+        "$project": {
+            "_id": 1,
+            "entity_id": 1,
+            "agent_id": 1,
+            "room_id": 1,
+            "content": 1,
+            "metadata": 1,
+            "created_at": 1,
+            "unique_flag": 1,
+            "keyword_score": score,
+        }
+    }
+}
+
+/// Atlas Search pipeline for the keyword leg
+pub(crate) fn search_pipeline(
+    query: &str,
+    options: &HybridSearchOptions,
+    limit: usize,
+) -> Result<Vec<Document>> {
+    let mut pipeline = vec![doc! {
+        "$search": {
+            "index": &options.search_index,
+            "text": { "query": query, "path": "content.text" },
+        }
+    }];
+    let filter = options.filter.to_match()?;
+    if !filter.is_empty() {
+        pipeline.push(doc! { "$match": filter });
+    }
+    pipeline.push(doc! { "$limit": limit as i64 });
+    pipeline.push(keyword_projection(doc! { "$meta": "searchScore" }.into()));
+    Ok(pipeline)
+}
+
+/// Lowercased, deduplicated query terms with surrounding punctuation removed
+///
+/// Punctuation inside a term is kept, so "2:21-cv-01234" stays one term.
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split_whitespace() {
+        let term = word
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase();
+        if !term.is_empty() && !terms.contains(&term) {
+            terms.push(term);
+        }
+        if terms.len() == MAX_KEYWORD_TERMS {
+            break;
+        }
+    }
+    terms
+}
+
+/// Regex pipeline for the keyword leg when Atlas Search is unavailable,
+/// or `None` when the query has no terms
+///
+/// A memory's score is the number of query terms its text contains.
+pub(crate) fn regex_pipeline(
+    query: &str,
+    options: &HybridSearchOptions,
+    limit: usize,
+) -> Result<Option<Vec<Document>>> {
+    let patterns: Vec<String> = query_terms(query)
+        .iter()
+        .map(|t| regex::escape(t))
+        .collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut filter = options.filter.to_match()?;
+    filter.insert(
+        "content.text",
+        doc! { "$regex": patterns.join("|"), "$options": "i" },
+    );
+    let matched_terms: Vec<Bson> = patterns
+        .iter()
+        .map(|p| {
+            Bson::Document(doc! { "$cond": [
+                { "$regexMatch": { "input": "$content.text", "regex": p, "options": "i" } },
+                1,
+                0,
+            ] })
+        })
+        .collect();
+    Ok(Some(vec![
+        doc! { "$match": filter },
+        doc! { "$addFields": { "keyword_score": { "$add": matched_terms } } },
+        doc! { "$sort": { "keyword_score": -1, "created_at": -1 } },
+        doc! { "$limit": limit as i64 },
+        keyword_projection("$keyword_score".into()),
+    ]))
+}
+
+/// Whether a `$search` failure means Atlas Search is not available here
+pub(crate) fn search_unavailable(message: &str) -> bool {
+    [
+        "Unrecognized pipeline stage name",
+        "only allowed on MongoDB Atlas",
+        "only valid on Atlas",
+        "$search is not allowed",
+        "SearchNotEnabled",
+        "Search index not found",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zoey_core::types::Content;
+
+    fn memory(id: u128, similarity: Option<f32>) -> Memory {
+        Memory {
+            id: uuid::Uuid::from_u128(id),
+            entity_id: uuid::Uuid::nil(),
+            agent_id: uuid::Uuid::nil(),
+            room_id: uuid::Uuid::nil(),
+            content: Content::default(),
+            embedding: None,
+            metadata: None,
+            created_at: 0,
+            unique: None,
+            similarity,
+        }
+    }
+
+    fn hybrid(memory: &Memory, key: &str) -> serde_json::Value {
+        memory.metadata.as_ref().unwrap().data[HYBRID_METADATA_KEY][key].clone()
+    }
+
+    #[test]
+    fn test_rrf_fusion_merges_duplicates_and_orders_ties() {
+        // Vector ranks 1..=3 for memories 1, 2, 3; keyword ranks 1..=3 for 4, 2, 5
+        let vector = vec![
+            memory(1, Some(0.91)),
+            memory(2, Some(0.85)),
+            memory(3, Some(0.80)),
+        ];
+        let keyword = vec![
+            (memory(4, None), 12.5),
+            (memory(2, Some(0.2)), 7.0),
+            (memory(5, None), 3.0),
+        ];
+        let options = HybridSearchOptions::new().rrf_k(10.0);
+        let results = fuse(vector, keyword, &options, 10);
+
+        let ids: Vec<u128> = results.iter().map(|m| m.id.as_u128()).collect();
+        // 2: 1/12 + 1/12; 1 and 4 tie at 1/11 (vector rank wins); then 3 and 5 tie at 1/13
+        assert_eq!(ids, vec![2, 1, 4, 3, 5]);
+        let fused_two = hybrid(&results[0], "rrf_score").as_f64().unwrap();
+        assert!((fused_two - 2.0 / 12.0).abs() < 1e-12);
+        assert_eq!(hybrid(&results[0], "vector_rank"), 2);
+        assert_eq!(hybrid(&results[0], "keyword_rank"), 2);
+        assert_eq!(hybrid(&results[0], "keyword_score"), 7.0);
+        assert_eq!(results[0].similarity, Some(0.85));
+
+        // Keyword-only results carry no vector similarity
+        assert_eq!(results[2].similarity, None);
+        assert_eq!(hybrid(&results[2], "vector_rank"), serde_json::Value::Null);
+        assert_eq!(hybrid(&results[2], "keyword_score"), 12.5);
+
+        // Weights shift the balance; k truncates
+        let options = HybridSearchOptions::new()
+            .rrf_k(10.0)
+            .vector_weight(0.0)
+            .keyword_weight(2.0);
+        let results = fuse(
+            vec![memory(1, Some(0.9))],
+            vec![(memory(4, None), 1.0), (memory(4, None), 0.5)],
+            &options,
+            1,
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id.as_u128(), 4);
+        let fused_four = hybrid(&results[0], "rrf_score").as_f64().unwrap();
+        assert!((fused_four - 2.0 / 11.0).abs() < 1e-12);
+
+        assert!(HybridSearchOptions::new().rrf_k(0.0).validate().is_err());
+        assert!(HybridSearchOptions::new()
+            .keyword_weight(-1.0)
+            .validate()
+            .is_err());
+        assert!(HybridSearchOptions::new().validate().is_ok());
+    }
+
+    #[test]
+    fn test_keyword_pipelines() {
+        assert_eq!(
+            query_terms("Smith v. Jones, case 2:21-cv-01234 smith"),
+            vec!["smith", "v", "jones", "case", "2:21-cv-01234"]
+        );
+        assert!(regex_pipeline("  ?! ", &HybridSearchOptions::new(), 5)
+            .unwrap()
+            .is_none());
+
+        let room = uuid::Uuid::new_v4();
+        let options = HybridSearchOptions::new().filter(VectorFilter::new().room(room));
+        let pipeline = regex_pipeline("§ 1983 (a)", &options, 5).unwrap().unwrap();
+        assert_eq!(
+            pipeline[0],
+            doc! { "$match": {
+                "room_id": room.to_string(),
+                "content.text": { "$regex": "1983|a", "$options": "i" },
+            } }
+        );
+        let escaped = regex_pipeline("U.S.C.", &options, 5).unwrap().unwrap();
+        assert_eq!(
+            escaped[0]
+                .get_document("$match")
+                .unwrap()
+                .get_document("content.text")
+                .unwrap()
+                .get_str("$regex")
+                .unwrap(),
+            r"u\.s\.c"
+        );
+
+        let search = search_pipeline("1983", &options.clone().search_index("legal"), 5).unwrap();
+        assert_eq!(
+            search[0],
+            doc! { "$search": { "index": "legal", "text": { "query": "1983", "path": "content.text" } } }
+        );
+        assert_eq!(
+            search[1],
+            doc! { "$match": { "room_id": room.to_string() } }
+        );
+
+        assert!(search_unavailable(
+            "Unrecognized pipeline stage name: '$search'"
+        ));
+        assert!(!search_unavailable("connection reset by peer"));
+    }
+}
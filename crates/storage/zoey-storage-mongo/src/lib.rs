@@ -8,9 +8,21 @@
 // Re-exports
 pub use zoey_core;
 
+pub mod bulk;
+pub mod hybrid;
+pub mod metrics;
 pub mod mongo;
+pub mod purge;
+pub mod state_store;
+pub mod vector_index;
 pub mod vector_search;
 
 // Re-export adapters
+pub use bulk::{BulkItemResult, BulkItemStatus, BulkWriteReport};
+pub use hybrid::HybridSearchOptions;
+pub use metrics::{MongoMetricsSink, PoolStats, QueryOp, TracingMetricsSink};
 pub use mongo::MongoAdapter;
-pub use vector_search::MongoVectorSearch;
+pub use purge::{CollectionPurge, PurgeStatus, RoomPurgeReport, Straggler};
+pub use state_store::MongoStateStore;
+pub use vector_index::{VectorIndexOutcome, VectorIndexSpec, VectorSimilarity};
+pub use vector_search::{AtlasVectorIndex, MongoVectorSearch, VectorFilter};
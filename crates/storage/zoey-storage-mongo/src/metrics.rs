@@ -0,0 +1,279 @@
+//! Query timing and connection pool metrics
+//!
+//! A [`MongoMetricsSink`] set with
+//! [`MongoAdapter::with_metrics`](crate::MongoAdapter::with_metrics) is told
+//! about every query the adapter runs: one event per adapter method, with
+//! the operation, the collection, how long it took and how many documents it
+//! returned or wrote. [`TracingMetricsSink`] logs the slow ones. Without a
+//! sink the adapter skips the timing entirely.
+//!
+//! [`MongoAdapter::pool_stats`](crate::MongoAdapter::pool_stats) reports the
+//! driver's connection pool from its CMAP events (connections in use and idle,
+//! and how long checkouts waited), for health checks.
+
+use mongodb::event::{cmap::CmapEvent, EventHandler};
+use std::sync::{
+    atomic::{AtomicI64, AtomicU64, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tracing::{trace, warn};
+use zoey_core::types::{AgentRunSummaryResult, MemoryPage, UUID};
+
+use crate::bulk::BulkWriteReport;
+use crate::purge::RoomPurgeReport;
+
+/// Queries taking at least this long are logged by [`TracingMetricsSink::default`]
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Kind of query an adapter method ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryOp {
+    /// `find` / `find_one`
+    Find,
+    /// `insert_one` / `insert_many`
+    Insert,
+    /// `update_one` / upserts
+    Update,
+    /// `delete_one` / `delete_many`
+    Delete,
+    /// `count_documents`
+    Count,
+    /// Aggregation pipeline
+    Aggregate,
+}
+
+impl QueryOp {
+    /// Lowercase name for logs and metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Find => "find",
+            Self::Insert => "insert",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Count => "count",
+            Self::Aggregate => "aggregate",
+        }
+    }
+}
+
+/// Receives one event per query the adapter runs
+///
+/// Called inline after the query completes, so implementations should only
+/// record and return.
+pub trait MongoMetricsSink: Send + Sync {
+    /// `op` on `collection` took `duration` and returned or wrote
+    /// `result_count` documents (0 when it failed)
+    fn on_query(&self, op: QueryOp, collection: &str, duration: Duration, result_count: usize);
+}
+
+/// Logs queries at `trace`, and those over the slow threshold at `warn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracingMetricsSink {
+    slow_threshold: Duration,
+}
+
+impl Default for TracingMetricsSink {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOW_QUERY_THRESHOLD)
+    }
+}
+
+impl TracingMetricsSink {
+    /// Warn about queries taking at least `slow_threshold`
+    pub fn new(slow_threshold: Duration) -> Self {
+        Self { slow_threshold }
+    }
+
+    /// Whether a query taking `duration` is logged as slow
+    pub fn is_slow(&self, duration: Duration) -> bool {
+        duration >= self.slow_threshold
+    }
+}
+
+impl MongoMetricsSink for TracingMetricsSink {
+    fn on_query(&self, op: QueryOp, collection: &str, duration: Duration, result_count: usize) {
+        if self.is_slow(duration) {
+            warn!(
+                op = op.as_str(),
+                collection,
+                duration_ms = duration.as_millis() as u64,
+                result_count,
+                "Slow MongoDB query"
+            );
+        } else {
+            trace!(
+                op = op.as_str(),
+                collection,
+                duration_ms = duration.as_millis() as u64,
+                result_count,
+                "MongoDB query"
+            );
+        }
+    }
+}
+
+/// Documents an adapter method returned or wrote, for [`MongoMetricsSink::on_query`]
+pub(crate) trait ResultCount {
+    fn result_count(&self) -> usize;
+}
+
+impl<T> ResultCount for Vec<T> {
+    fn result_count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> ResultCount for Option<T> {
+    fn result_count(&self) -> usize {
+        usize::from(self.is_some())
+    }
+}
+
+/// Whether the document was found or written
+impl ResultCount for bool {
+    fn result_count(&self) -> usize {
+        usize::from(*self)
+    }
+}
+
+/// A count query's result
+impl ResultCount for usize {
+    fn result_count(&self) -> usize {
+        *self
+    }
+}
+
+/// The ID of the document created
+impl ResultCount for UUID {
+    fn result_count(&self) -> usize {
+        1
+    }
+}
+
+/// A write of one document that succeeded
+impl ResultCount for () {
+    fn result_count(&self) -> usize {
+        1
+    }
+}
+
+impl ResultCount for MemoryPage {
+    fn result_count(&self) -> usize {
+        self.memories.len()
+    }
+}
+
+impl ResultCount for AgentRunSummaryResult {
+    fn result_count(&self) -> usize {
+        self.runs.len()
+    }
+}
+
+impl ResultCount for BulkWriteReport {
+    fn result_count(&self) -> usize {
+        self.inserted()
+    }
+}
+
+impl ResultCount for RoomPurgeReport {
+    fn result_count(&self) -> usize {
+        self.deleted() as usize
+    }
+}
+
+/// Snapshot of the driver's connection pools, summed over servers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Connections checked out by running operations
+    pub in_use: usize,
+    /// Open connections waiting in the pool
+    pub idle: usize,
+    /// Checkouts so far, successful or not
+    pub checkouts: u64,
+    /// Mean time a checkout waited for a connection
+    pub avg_checkout_wait: Duration,
+    /// Longest time a checkout waited for a connection
+    pub max_checkout_wait: Duration,
+}
+
+/// Tracks the connection pools through the driver's CMAP events
+#[derive(Debug, Default)]
+pub(crate) struct PoolMonitor {
+    open: AtomicI64,
+    in_use: AtomicI64,
+    checkouts: AtomicU64,
+    wait_nanos: AtomicU64,
+    max_wait_nanos: AtomicU64,
+}
+
+impl PoolMonitor {
+    /// CMAP event handler feeding `monitor`, for `ClientOptions::cmap_event_handler`
+    pub(crate) fn handler(monitor: Arc<Self>) -> EventHandler<CmapEvent> {
+        EventHandler::callback(move |event| monitor.observe(&event))
+    }
+
+    pub(crate) fn observe(&self, event: &CmapEvent) {
+        match event {
+            CmapEvent::ConnectionCreated(_) => {
+                self.open.fetch_add(1, Ordering::Relaxed);
+            }
+            CmapEvent::ConnectionClosed(_) => {
+                self.open.fetch_sub(1, Ordering::Relaxed);
+            }
+            CmapEvent::ConnectionCheckedOut(checked_out) => {
+                self.in_use.fetch_add(1, Ordering::Relaxed);
+                self.record_wait(checked_out.duration);
+            }
+            CmapEvent::ConnectionCheckoutFailed(failed) => self.record_wait(failed.duration),
+            CmapEvent::ConnectionCheckedIn(_) => {
+                self.in_use.fetch_sub(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    fn record_wait(&self, wait: Duration) {
+        let nanos = wait.as_nanos().min(u64::MAX as u128) as u64;
+        self.checkouts.fetch_add(1, Ordering::Relaxed);
+        self.wait_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_wait_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        let open = self.open.load(Ordering::Relaxed).max(0) as usize;
+        let in_use = self.in_use.load(Ordering::Relaxed).max(0) as usize;
+        let checkouts = self.checkouts.load(Ordering::Relaxed);
+        let wait_nanos = self.wait_nanos.load(Ordering::Relaxed);
+        PoolStats {
+            in_use,
+            idle: open.saturating_sub(in_use),
+            checkouts,
+            avg_checkout_wait: Duration::from_nanos(wait_nanos.checked_div(checkouts).unwrap_or(0)),
+            max_checkout_wait: Duration::from_nanos(self.max_wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_threshold() {
+        let sink = TracingMetricsSink::new(Duration::from_millis(250));
+        assert!(!sink.is_slow(Duration::from_millis(249)));
+        assert!(sink.is_slow(Duration::from_millis(250)));
+        assert!(TracingMetricsSink::default().is_slow(DEFAULT_SLOW_QUERY_THRESHOLD));
+    }
+
+    #[test]
+    fn test_result_counts() {
+        assert_eq!(vec![1, 2, 3].result_count(), 3);
+        assert_eq!(Some("agent").result_count(), 1);
+        assert_eq!(None::<u8>.result_count(), 0);
+        assert_eq!(false.result_count(), 0);
+        assert_eq!(42usize.result_count(), 42);
+        assert_eq!(().result_count(), 1);
+    }
+}
@@ -1,24 +1,80 @@
 //! MongoDB database adapter
 //!
 //! Implements core database operations for MongoDB with Atlas Search support for vector search.
+//!
+//! Memories may carry an expiry ([`MongoAdapter::create_memory_with_ttl`]).
+//! A partial TTL index on `expires_at` lets the server delete them once
+//! expired, and memory reads skip expired documents the server has not
+//! reaped yet (the TTL monitor only runs about once a minute).
+//!
+//! Query timings go to an optional [`MongoMetricsSink`]
+//! ([`MongoAdapter::with_metrics`]); see [`crate::metrics`].
+//!
+//! [`MongoAdapter::initialize`] also creates and checks the Atlas Vector
+//! Search index when its config sets `ensureVectorIndexes`; see
+//! [`crate::vector_index`].
 
 use async_trait::async_trait;
 use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, oid::ObjectId, to_bson, Bson, Document},
+    bson::{doc, oid::ObjectId, to_bson, Bson, DateTime, Document},
     options::{ClientOptions, FindOptions, IndexOptions, UpdateOptions},
     Client, Collection, Database, IndexModel,
 };
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 use zoey_core::observability::types::LLMCostRecord;
 use zoey_core::{types::*, Result, ZoeyError};
 
+use crate::bulk::{self, BulkWriteReport};
+use crate::metrics::{MongoMetricsSink, PoolMonitor, PoolStats, QueryOp, ResultCount};
+use crate::purge::{self, InTransaction, PurgeStatus, RoomPurgeReport, Straggler};
+use crate::vector_index::VectorIndexSpec;
+use crate::vector_search::{MongoVectorSearch, METADATA_COLLECTION};
+
+/// Name of the TTL index on `memories.expires_at`
+pub const MEMORY_TTL_INDEX: &str = "memories_expires_at_ttl";
+
+/// When a memory written now with `ttl` expires
+fn memory_expiry(now: DateTime, ttl: Duration) -> DateTime {
+    let ttl_ms = ttl.as_millis().min(i64::MAX as u128) as i64;
+    DateTime::from_millis(now.timestamp_millis().saturating_add(ttl_ms))
+}
+
+/// Matches memories without an expiry or expiring after `now`
+///
+/// Unlike an `$or`, this can be merged into filters that already use one.
+fn memory_not_expired(now: DateTime) -> Document {
+    doc! { "$not": { "$lte": now } }
+}
+
+/// Add the not-expired condition to a memory filter
+fn live_memories(mut filter: Document) -> Document {
+    filter.insert("expires_at", memory_not_expired(DateTime::now()));
+    filter
+}
+
+/// Options [`MongoAdapter::initialize`] reads from its config
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct InitOptions {
+    /// Create and check the vector index at startup
+    ensure_vector_indexes: bool,
+    embedding_dimension: Option<usize>,
+    embedding_model: Option<String>,
+    vector_index: VectorIndexSpec,
+}
+
 /// MongoDB database adapter
 pub struct MongoAdapter {
     db: Database,
     client: Client,
     embedding_dimension: std::sync::RwLock<usize>,
+    metrics: Option<Arc<dyn MongoMetricsSink>>,
+    pool: Arc<PoolMonitor>,
 }
 
 impl MongoAdapter {
@@ -30,25 +86,65 @@ impl MongoAdapter {
             .await
             .map_err(|e| ZoeyError::database(format!("Failed to parse MongoDB URI: {}", e)))?;
 
-        let client = Client::with_options(client_options)
-            .map_err(|e| ZoeyError::database(format!("Failed to create MongoDB client: {}", e)))?;
-
-        let db = client.database(database_name);
+        let adapter = Self::with_client_options(client_options, database_name)?;
 
         // Ping the database to verify connection
-        db.run_command(doc! { "ping": 1 })
+        adapter
+            .db
+            .run_command(doc! { "ping": 1 })
             .await
             .map_err(|e| ZoeyError::database(format!("Failed to connect to MongoDB: {}", e)))?;
 
         info!("Successfully connected to MongoDB");
 
+        Ok(adapter)
+    }
+
+    /// Create the adapter without contacting the server
+    fn with_client_options(mut client_options: ClientOptions, database_name: &str) -> Result<Self> {
+        let pool = Arc::new(PoolMonitor::default());
+        client_options.cmap_event_handler = Some(PoolMonitor::handler(pool.clone()));
+
+        let client = Client::with_options(client_options)
+            .map_err(|e| ZoeyError::database(format!("Failed to create MongoDB client: {}", e)))?;
+
         Ok(Self {
-            db,
+            db: client.database(database_name),
             client,
             embedding_dimension: std::sync::RwLock::new(1536), // Default OpenAI embedding dimension
+            metrics: None,
+            pool,
         })
     }
 
+    /// Report every query to `sink`
+    pub fn with_metrics(mut self, sink: Arc<dyn MongoMetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Connections in use and idle, and how long checkouts have waited
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    /// Run `query`, reporting it to the metrics sink if there is one
+    async fn observed<T: ResultCount>(
+        &self,
+        op: QueryOp,
+        collection: &str,
+        query: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let Some(sink) = &self.metrics else {
+            return query.await;
+        };
+        let started = Instant::now();
+        let result = query.await;
+        let count = result.as_ref().map_or(0, ResultCount::result_count);
+        sink.on_query(op, collection, started.elapsed(), count);
+        result
+    }
+
     /// Get the database instance
     pub fn database(&self) -> &Database {
         &self.db
@@ -164,11 +260,30 @@ impl MongoAdapter {
             IndexModel::builder()
                 .keys(doc! { "agent_id": 1, "room_id": 1, "created_at": -1 })
                 .build(),
+            IndexModel::builder()
+                .keys(doc! { "room_id": 1, "created_at": -1, "_id": -1 })
+                .build(),
             IndexModel::builder()
                 .keys(doc! { "agent_id": 1, "unique_flag": 1 })
                 .build(),
         ];
         collection.create_indexes(indexes).await.ok();
+
+        // Named so a restart finds the same index and creating it is a no-op
+        let ttl_index = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(MEMORY_TTL_INDEX.to_string())
+                    .expire_after(Duration::from_secs(0))
+                    .partial_filter_expression(doc! { "expires_at": { "$exists": true } })
+                    .build(),
+            )
+            .build();
+        if let Err(e) = collection.create_index(ttl_index).await {
+            // Reads still skip expired memories; they just stay on disk
+            warn!(error = %e, "Failed to create memory TTL index");
+        }
         Ok(())
     }
 
@@ -285,6 +400,230 @@ impl MongoAdapter {
         Ok(())
     }
 
+    /// Store a memory that expires `ttl` from now
+    ///
+    /// The memory disappears from reads as soon as it expires and is deleted
+    /// by MongoDB's TTL monitor shortly after.
+    pub async fn create_memory_with_ttl(&self, memory: &Memory, ttl: Duration) -> Result<UUID> {
+        self.observed(QueryOp::Insert, "memories", async {
+            let mut doc = Self::memory_to_doc(memory);
+            doc.insert("expires_at", memory_expiry(DateTime::now(), ttl));
+            self.collection::<Document>("memories")
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create memory: {}", e)))?;
+            Ok(memory.id)
+        })
+        .await
+    }
+
+    /// Make a memory expire `ttl` from now, or never with `None`
+    ///
+    /// Returns whether a live memory was found; an expired one can't be revived.
+    pub async fn set_memory_ttl(&self, memory_id: UUID, ttl: Option<Duration>) -> Result<bool> {
+        self.observed(QueryOp::Update, "memories", async {
+            let update = match ttl {
+                Some(ttl) => doc! { "$set": { "expires_at": memory_expiry(DateTime::now(), ttl) } },
+                None => doc! { "$unset": { "expires_at": "" } },
+            };
+            let result = self
+                .collection::<Document>("memories")
+                .update_one(live_memories(doc! { "_id": memory_id.to_string() }), update)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to update memory TTL: {}", e)))?;
+            Ok(result.matched_count > 0)
+        })
+        .await
+    }
+
+    /// Insert many memories with `insert_many`, reporting each one's outcome
+    ///
+    /// Unordered inserts attempt every memory and report duplicate IDs as
+    /// skipped; ordered inserts stop at the first error. Embeddings are
+    /// stored as by [`create_memory`](IDatabaseAdapter::create_memory), so
+    /// the chunks are searchable through `MongoVectorSearch` right away.
+    pub async fn create_memories_bulk(
+        &self,
+        memories: Vec<Memory>,
+        ordered: bool,
+    ) -> Result<BulkWriteReport> {
+        self.observed(QueryOp::Insert, "memories", async {
+            let ids = memories.iter().map(|m| m.id).collect();
+            let documents = memories.iter().map(Self::memory_to_doc).collect();
+            let report = bulk::insert_memories(
+                &self.collection::<Document>("memories"),
+                ids,
+                documents,
+                ordered,
+            )
+            .await?;
+            debug!(
+                inserted = report.inserted(),
+                skipped = report.skipped(),
+                failed = report.failed(),
+                round_trips = report.round_trips,
+                "Bulk memory insert"
+            );
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Delete a room, its participant links and, with `purge_memories`, its
+    /// memories and vector documents
+    ///
+    /// On a replica set or `mongos` the deletes run in one transaction and a
+    /// failure rolls all of them back and returns the error. A standalone
+    /// server purges the collections in order and stops at the first
+    /// failure; the report says which collections were purged.
+    pub async fn delete_room_cascade(
+        &self,
+        room_id: UUID,
+        purge_memories: bool,
+    ) -> Result<RoomPurgeReport> {
+        self.observed(QueryOp::Delete, "rooms", async {
+            let vector_collections = self.vector_collections().await?;
+            let targets = purge::purge_targets(room_id, purge_memories, &vector_collections);
+
+            if !self.supports_transactions().await? {
+                let report = purge::purge_room(&mut self.db.clone(), room_id, targets, false).await;
+                if !report.is_complete() {
+                    warn!(room_id = %room_id, deleted = report.deleted(), "Room purge incomplete");
+                }
+                return Ok(report);
+            }
+
+            let mut session = self.client.start_session().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to start room purge session: {}", e))
+            })?;
+            session.start_transaction().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to start room purge transaction: {}", e))
+            })?;
+            let mut documents = InTransaction {
+                db: &self.db,
+                session: &mut session,
+            };
+            let report = purge::purge_room(&mut documents, room_id, targets, true).await;
+            let failure = report.collections.iter().find_map(|c| match &c.status {
+                PurgeStatus::Failed(reason) => Some(reason.clone()),
+                _ => None,
+            });
+            if let Some(reason) = failure {
+                if let Err(e) = session.abort_transaction().await {
+                    warn!(room_id = %room_id, error = %e, "Failed to abort room purge transaction");
+                }
+                return Err(ZoeyError::database(format!(
+                    "Room purge rolled back: {}",
+                    reason
+                )));
+            }
+            session
+                .commit_transaction()
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to commit room purge: {}", e)))?;
+            debug!(room_id = %room_id, deleted = report.deleted(), "Room purged");
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Documents of `room_id` left in any collection a purge deletes from
+    ///
+    /// Empty once the room, its participant links, memories and vector
+    /// documents are all gone.
+    pub async fn verify_room_purged(&self, room_id: UUID) -> Result<Vec<Straggler>> {
+        self.observed(QueryOp::Count, "rooms", async {
+            let vector_collections = self.vector_collections().await?;
+            let mut stragglers = Vec::new();
+            for target in purge::purge_targets(room_id, true, &vector_collections) {
+                let count = self
+                    .collection::<Document>(&target.collection)
+                    .count_documents(target.filter)
+                    .await
+                    .map_err(|e| {
+                        ZoeyError::database(format!(
+                            "Failed to count '{}' documents: {}",
+                            target.collection, e
+                        ))
+                    })?;
+                if count > 0 {
+                    stragglers.push(Straggler {
+                        collection: target.collection,
+                        count,
+                    });
+                }
+            }
+            Ok(stragglers)
+        })
+        .await
+    }
+
+    /// Whether the server is a replica set member or `mongos`
+    async fn supports_transactions(&self) -> Result<bool> {
+        let hello = self
+            .db
+            .run_command(doc! { "hello": 1 })
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to query server topology: {}", e)))?;
+        Ok(purge::supports_transactions(&hello))
+    }
+
+    /// Collections `MongoVectorSearch` has stored documents in
+    async fn vector_collections(&self) -> Result<Vec<String>> {
+        let names = self
+            .collection::<Document>(METADATA_COLLECTION)
+            .distinct("_id", doc! {})
+            .await
+            .map_err(|e| {
+                ZoeyError::database(format!("Failed to list vector collections: {}", e))
+            })?;
+        Ok(names
+            .into_iter()
+            .filter_map(|name| name.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Entities with the given IDs, in no particular order
+    async fn find_entities_by_ids(&self, entity_ids: Vec<UUID>) -> Result<Vec<Entity>> {
+        if entity_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let collection = self.collection::<Document>("entities");
+        let ids: Vec<String> = entity_ids.iter().map(|id| id.to_string()).collect();
+        let filter = doc! { "_id": { "$in": ids } };
+
+        let mut cursor = collection
+            .find(filter)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to get entities: {}", e)))?;
+
+        let mut entities = Vec::new();
+        while let Some(doc) = cursor.try_next().await.map_err(|e| {
+            ZoeyError::database(format!("Failed to iterate entities: {}", e))
+        })? {
+            let entity = self.doc_to_entity(&doc)?;
+            entities.push(entity);
+        }
+
+        Ok(entities)
+    }
+
+    /// Memory fields as stored, without an expiry
+    fn memory_to_doc(memory: &Memory) -> Document {
+        doc! {
+            "_id": memory.id.to_string(),
+            "entity_id": memory.entity_id.to_string(),
+            "agent_id": memory.agent_id.to_string(),
+            "room_id": memory.room_id.to_string(),
+            "content": to_bson(&memory.content).unwrap_or(Bson::Document(doc! {})),
+            "embedding": memory.embedding.as_ref().map(|e| to_bson(e).unwrap_or(Bson::Null)),
+            "metadata": memory.metadata.as_ref().map(|m| to_bson(m).unwrap_or(Bson::Document(doc! {}))),
+            "created_at": memory.created_at,
+            "unique_flag": memory.unique.unwrap_or(false),
+        }
+    }
+
     /// Convert UUID to BSON string
     fn uuid_to_bson(id: uuid::Uuid) -> Bson {
         Bson::String(id.to_string())
@@ -306,8 +645,26 @@ impl IDatabaseAdapter for MongoAdapter {
         &self.db
     }
 
-    async fn initialize(&mut self, _config: Option<serde_json::Value>) -> Result<()> {
-        self.init_schema().await
+    async fn initialize(&mut self, config: Option<serde_json::Value>) -> Result<()> {
+        let options: InitOptions = match config {
+            Some(config) => serde_json::from_value(config)
+                .map_err(|e| ZoeyError::config(format!("Invalid MongoDB adapter config: {}", e)))?,
+            None => InitOptions::default(),
+        };
+        if let Some(dimension) = options.embedding_dimension {
+            *self.embedding_dimension.write().unwrap() = dimension;
+        }
+        self.init_schema().await?;
+
+        if options.ensure_vector_indexes {
+            let dimension = *self.embedding_dimension.read().unwrap();
+            let mut search = MongoVectorSearch::new(self.db.clone(), dimension);
+            if let Some(model) = options.embedding_model {
+                search = search.with_embedding_model(model);
+            }
+            search.ensure_indexes(options.vector_index).await?;
+        }
+        Ok(())
     }
 
     async fn is_ready(&self) -> Result<bool> {
@@ -367,16 +724,49 @@ impl IDatabaseAdapter for MongoAdapter {
 
     // Agent operations
     async fn get_agent(&self, agent_id: UUID) -> Result<Option<Agent>> {
-        let collection = self.collection::<Document>("agents");
-        let filter = doc! { "_id": agent_id.to_string() };
+        self.observed(QueryOp::Find, "agents", async {
+            let collection = self.collection::<Document>("agents");
+            let filter = doc! { "_id": agent_id.to_string() };
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get agent: {}", e)))?;
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get agent: {}", e)))?;
+
+            match result {
+                Some(doc) => {
+                    let agent = Agent {
+                        id: Self::bson_to_uuid(doc.get("_id").unwrap_or(&Bson::Null))?,
+                        name: doc.get_str("name").unwrap_or("").to_string(),
+                        character: mongodb::bson::from_bson(
+                            doc.get("character").cloned().unwrap_or(Bson::Null),
+                        )
+                        .unwrap_or(serde_json::Value::Null),
+                        created_at: doc.get_i64("created_at").ok(),
+                        updated_at: doc.get_i64("updated_at").ok(),
+                    };
+                    Ok(Some(agent))
+                }
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    async fn get_agents(&self) -> Result<Vec<Agent>> {
+        self.observed(QueryOp::Find, "agents", async {
+            let collection = self.collection::<Document>("agents");
+            let mut cursor = collection
+                .find(doc! {})
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get agents: {}", e)))?;
 
-        match result {
-            Some(doc) => {
+            let mut agents = Vec::new();
+            while let Some(doc) = cursor
+                .try_next()
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to iterate agents: {}", e)))?
+            {
                 let agent = Agent {
                     id: Self::bson_to_uuid(doc.get("_id").unwrap_or(&Bson::Null))?,
                     name: doc.get_str("name").unwrap_or("").to_string(),
@@ -387,90 +777,72 @@ impl IDatabaseAdapter for MongoAdapter {
                     created_at: doc.get_i64("created_at").ok(),
                     updated_at: doc.get_i64("updated_at").ok(),
                 };
-                Ok(Some(agent))
+                agents.push(agent);
             }
-            None => Ok(None),
-        }
-    }
-
-    async fn get_agents(&self) -> Result<Vec<Agent>> {
-        let collection = self.collection::<Document>("agents");
-        let mut cursor = collection
-            .find(doc! {})
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get agents: {}", e)))?;
-
-        let mut agents = Vec::new();
-        while let Some(doc) = cursor
-            .try_next()
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to iterate agents: {}", e)))?
-        {
-            let agent = Agent {
-                id: Self::bson_to_uuid(doc.get("_id").unwrap_or(&Bson::Null))?,
-                name: doc.get_str("name").unwrap_or("").to_string(),
-                character: mongodb::bson::from_bson(
-                    doc.get("character").cloned().unwrap_or(Bson::Null),
-                )
-                .unwrap_or(serde_json::Value::Null),
-                created_at: doc.get_i64("created_at").ok(),
-                updated_at: doc.get_i64("updated_at").ok(),
-            };
-            agents.push(agent);
-        }
 
-        Ok(agents)
+            Ok(agents)
+        })
+        .await
     }
 
     async fn create_agent(&self, agent: &Agent) -> Result<bool> {
-        let collection = self.collection::<Document>("agents");
+        self.observed(QueryOp::Insert, "agents", async {
+            let collection = self.collection::<Document>("agents");
 
-        let doc = doc! {
-            "_id": agent.id.to_string(),
-            "name": &agent.name,
-            "character": to_bson(&agent.character).unwrap_or(Bson::Null),
-            "created_at": agent.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
-            "updated_at": agent.updated_at,
-        };
+            let doc = doc! {
+                "_id": agent.id.to_string(),
+                "name": &agent.name,
+                "character": to_bson(&agent.character).unwrap_or(Bson::Null),
+                "created_at": agent.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+                "updated_at": agent.updated_at,
+            };
 
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create agent: {}", e)))?;
+            collection
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create agent: {}", e)))?;
 
-        Ok(true)
+            Ok(true)
+        })
+        .await
     }
 
     async fn update_agent(&self, agent_id: UUID, agent: &Agent) -> Result<bool> {
-        let collection = self.collection::<Document>("agents");
-
-        let filter = doc! { "_id": agent_id.to_string() };
-        let update = doc! {
-            "$set": {
-                "name": &agent.name,
-                "character": to_bson(&agent.character).unwrap_or(Bson::Null),
-                "updated_at": chrono::Utc::now().timestamp(),
-            }
-        };
+        self.observed(QueryOp::Update, "agents", async {
+            let collection = self.collection::<Document>("agents");
+
+            let filter = doc! { "_id": agent_id.to_string() };
+            let update = doc! {
+                "$set": {
+                    "name": &agent.name,
+                    "character": to_bson(&agent.character).unwrap_or(Bson::Null),
+                    "updated_at": chrono::Utc::now().timestamp(),
+                }
+            };
 
-        let result = collection
-            .update_one(filter, update)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to update agent: {}", e)))?;
+            let result = collection
+                .update_one(filter, update)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to update agent: {}", e)))?;
 
-        Ok(result.modified_count > 0)
+            Ok(result.modified_count > 0)
+        })
+        .await
     }
 
     async fn delete_agent(&self, agent_id: UUID) -> Result<bool> {
-        let collection = self.collection::<Document>("agents");
-        let filter = doc! { "_id": agent_id.to_string() };
+        self.observed(QueryOp::Delete, "agents", async {
+            let collection = self.collection::<Document>("agents");
+            let filter = doc! { "_id": agent_id.to_string() };
 
-        let result = collection
-            .delete_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to delete agent: {}", e)))?;
+            let result = collection
+                .delete_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to delete agent: {}", e)))?;
 
-        Ok(result.deleted_count > 0)
+            Ok(result.deleted_count > 0)
+        })
+        .await
     }
 
     async fn ensure_embedding_dimension(&self, dimension: usize) -> Result<()> {
@@ -479,28 +851,8 @@ impl IDatabaseAdapter for MongoAdapter {
     }
 
     async fn get_entities_by_ids(&self, entity_ids: Vec<UUID>) -> Result<Vec<Entity>> {
-        if entity_ids.is_empty() {
-            return Ok(vec![]);
-        }
-
-        let collection = self.collection::<Document>("entities");
-        let ids: Vec<String> = entity_ids.iter().map(|id| id.to_string()).collect();
-        let filter = doc! { "_id": { "$in": ids } };
-
-        let mut cursor = collection
-            .find(filter)
+        self.observed(QueryOp::Find, "entities", self.find_entities_by_ids(entity_ids))
             .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get entities: {}", e)))?;
-
-        let mut entities = Vec::new();
-        while let Some(doc) = cursor.try_next().await.map_err(|e| {
-            ZoeyError::database(format!("Failed to iterate entities: {}", e))
-        })? {
-            let entity = self.doc_to_entity(&doc)?;
-            entities.push(entity);
-        }
-
-        Ok(entities)
     }
 
     async fn get_entities_for_room(
@@ -508,90 +860,102 @@ impl IDatabaseAdapter for MongoAdapter {
         room_id: UUID,
         _include_components: bool,
     ) -> Result<Vec<Entity>> {
-        let participants_collection = self.collection::<Document>("participants");
-        let filter = doc! { "room_id": room_id.to_string() };
-
-        let mut cursor = participants_collection
-            .find(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get participants: {}", e)))?;
+        self.observed(QueryOp::Find, "participants", async {
+            let participants_collection = self.collection::<Document>("participants");
+            let filter = doc! { "room_id": room_id.to_string() };
 
-        let mut entity_ids = Vec::new();
-        while let Some(doc) = cursor.try_next().await.map_err(|e| {
-            ZoeyError::database(format!("Failed to iterate participants: {}", e))
-        })? {
-            if let Ok(entity_id) = Self::bson_to_uuid(doc.get("entity_id").unwrap_or(&Bson::Null)) {
-                entity_ids.push(entity_id);
+            let mut cursor = participants_collection
+                .find(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get participants: {}", e)))?;
+
+            let mut entity_ids = Vec::new();
+            while let Some(doc) = cursor.try_next().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to iterate participants: {}", e))
+            })? {
+                if let Ok(entity_id) = Self::bson_to_uuid(doc.get("entity_id").unwrap_or(&Bson::Null)) {
+                    entity_ids.push(entity_id);
+                }
             }
-        }
 
-        self.get_entities_by_ids(entity_ids).await
+            self.find_entities_by_ids(entity_ids).await
+        })
+        .await
     }
 
     async fn create_entities(&self, entities: Vec<Entity>) -> Result<bool> {
-        let collection = self.collection::<Document>("entities");
-
-        for entity in entities {
-            let doc = doc! {
-                "_id": entity.id.to_string(),
-                "agent_id": entity.agent_id.to_string(),
-                "name": &entity.name,
-                "username": &entity.username,
-                "email": &entity.email,
-                "avatar_url": &entity.avatar_url,
-                "metadata": to_bson(&entity.metadata).unwrap_or(Bson::Document(doc! {})),
-                "created_at": entity.created_at,
-            };
+        self.observed(QueryOp::Update, "entities", async {
+            let collection = self.collection::<Document>("entities");
+
+            for entity in entities {
+                let doc = doc! {
+                    "_id": entity.id.to_string(),
+                    "agent_id": entity.agent_id.to_string(),
+                    "name": &entity.name,
+                    "username": &entity.username,
+                    "email": &entity.email,
+                    "avatar_url": &entity.avatar_url,
+                    "metadata": to_bson(&entity.metadata).unwrap_or(Bson::Document(doc! {})),
+                    "created_at": entity.created_at,
+                };
 
-            let options = UpdateOptions::builder().upsert(true).build();
-            collection
-                .update_one(
-                    doc! { "_id": entity.id.to_string() },
-                    doc! { "$set": doc },
-                )
-                .with_options(options)
-                .await
-                .map_err(|e| ZoeyError::database(format!("Failed to create entity: {}", e)))?;
-        }
+                let options = UpdateOptions::builder().upsert(true).build();
+                collection
+                    .update_one(
+                        doc! { "_id": entity.id.to_string() },
+                        doc! { "$set": doc },
+                    )
+                    .with_options(options)
+                    .await
+                    .map_err(|e| ZoeyError::database(format!("Failed to create entity: {}", e)))?;
+            }
 
-        Ok(true)
+            Ok(true)
+        })
+        .await
     }
 
     async fn update_entity(&self, entity: &Entity) -> Result<()> {
-        let collection = self.collection::<Document>("entities");
-
-        let filter = doc! { "_id": entity.id.to_string() };
-        let update = doc! {
-            "$set": {
-                "name": &entity.name,
-                "username": &entity.username,
-                "email": &entity.email,
-                "avatar_url": &entity.avatar_url,
-                "metadata": to_bson(&entity.metadata).unwrap_or(Bson::Document(doc! {})),
-            }
-        };
+        self.observed(QueryOp::Update, "entities", async {
+            let collection = self.collection::<Document>("entities");
+
+            let filter = doc! { "_id": entity.id.to_string() };
+            let update = doc! {
+                "$set": {
+                    "name": &entity.name,
+                    "username": &entity.username,
+                    "email": &entity.email,
+                    "avatar_url": &entity.avatar_url,
+                    "metadata": to_bson(&entity.metadata).unwrap_or(Bson::Document(doc! {})),
+                }
+            };
 
-        collection
-            .update_one(filter, update)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to update entity: {}", e)))?;
+            collection
+                .update_one(filter, update)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to update entity: {}", e)))?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn get_entity_by_id(&self, entity_id: UUID) -> Result<Option<Entity>> {
-        let collection = self.collection::<Document>("entities");
-        let filter = doc! { "_id": entity_id.to_string() };
+        self.observed(QueryOp::Find, "entities", async {
+            let collection = self.collection::<Document>("entities");
+            let filter = doc! { "_id": entity_id.to_string() };
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get entity: {}", e)))?;
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get entity: {}", e)))?;
 
-        match result {
-            Some(doc) => Ok(Some(self.doc_to_entity(&doc)?)),
-            None => Ok(None),
-        }
+            match result {
+                Some(doc) => Ok(Some(self.doc_to_entity(&doc)?)),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
     async fn get_component(
@@ -601,29 +965,32 @@ impl IDatabaseAdapter for MongoAdapter {
         world_id: Option<UUID>,
         source_entity_id: Option<UUID>,
     ) -> Result<Option<Component>> {
-        let collection = self.collection::<Document>("components");
+        self.observed(QueryOp::Find, "components", async {
+            let collection = self.collection::<Document>("components");
 
-        let mut filter = doc! {
-            "entity_id": entity_id.to_string(),
-            "type": component_type,
-        };
+            let mut filter = doc! {
+                "entity_id": entity_id.to_string(),
+                "type": component_type,
+            };
 
-        if let Some(wid) = world_id {
-            filter.insert("world_id", wid.to_string());
-        }
-        if let Some(seid) = source_entity_id {
-            filter.insert("source_entity_id", seid.to_string());
-        }
+            if let Some(wid) = world_id {
+                filter.insert("world_id", wid.to_string());
+            }
+            if let Some(seid) = source_entity_id {
+                filter.insert("source_entity_id", seid.to_string());
+            }
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get component: {}", e)))?;
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get component: {}", e)))?;
 
-        match result {
-            Some(doc) => Ok(Some(self.doc_to_component(&doc)?)),
-            None => Ok(None),
-        }
+            match result {
+                Some(doc) => Ok(Some(self.doc_to_component(&doc)?)),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
     async fn get_components(
@@ -632,150 +999,157 @@ impl IDatabaseAdapter for MongoAdapter {
         world_id: Option<UUID>,
         source_entity_id: Option<UUID>,
     ) -> Result<Vec<Component>> {
-        let collection = self.collection::<Document>("components");
+        self.observed(QueryOp::Find, "components", async {
+            let collection = self.collection::<Document>("components");
 
-        let mut filter = doc! { "entity_id": entity_id.to_string() };
+            let mut filter = doc! { "entity_id": entity_id.to_string() };
 
-        if let Some(wid) = world_id {
-            filter.insert("world_id", wid.to_string());
-        }
-        if let Some(seid) = source_entity_id {
-            filter.insert("source_entity_id", seid.to_string());
-        }
+            if let Some(wid) = world_id {
+                filter.insert("world_id", wid.to_string());
+            }
+            if let Some(seid) = source_entity_id {
+                filter.insert("source_entity_id", seid.to_string());
+            }
 
-        let mut cursor = collection
-            .find(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get components: {}", e)))?;
+            let mut cursor = collection
+                .find(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get components: {}", e)))?;
 
-        let mut components = Vec::new();
-        while let Some(doc) = cursor.try_next().await.map_err(|e| {
-            ZoeyError::database(format!("Failed to iterate components: {}", e))
-        })? {
-            components.push(self.doc_to_component(&doc)?);
-        }
+            let mut components = Vec::new();
+            while let Some(doc) = cursor.try_next().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to iterate components: {}", e))
+            })? {
+                components.push(self.doc_to_component(&doc)?);
+            }
 
-        Ok(components)
+            Ok(components)
+        })
+        .await
     }
 
     async fn create_component(&self, component: &Component) -> Result<bool> {
-        let collection = self.collection::<Document>("components");
+        self.observed(QueryOp::Insert, "components", async {
+            let collection = self.collection::<Document>("components");
 
-        let doc = doc! {
-            "_id": component.id.to_string(),
-            "entity_id": component.entity_id.to_string(),
-            "world_id": component.world_id.to_string(),
-            "source_entity_id": component.source_entity_id.map(|id| id.to_string()),
-            "type": &component.component_type,
-            "data": to_bson(&component.data).unwrap_or(Bson::Document(doc! {})),
-            "created_at": component.created_at,
-            "updated_at": component.updated_at,
-        };
+            let doc = doc! {
+                "_id": component.id.to_string(),
+                "entity_id": component.entity_id.to_string(),
+                "world_id": component.world_id.to_string(),
+                "source_entity_id": component.source_entity_id.map(|id| id.to_string()),
+                "type": &component.component_type,
+                "data": to_bson(&component.data).unwrap_or(Bson::Document(doc! {})),
+                "created_at": component.created_at,
+                "updated_at": component.updated_at,
+            };
 
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create component: {}", e)))?;
+            collection
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create component: {}", e)))?;
 
-        Ok(true)
+            Ok(true)
+        })
+        .await
     }
 
     async fn update_component(&self, component: &Component) -> Result<()> {
-        let collection = self.collection::<Document>("components");
-
-        let filter = doc! { "_id": component.id.to_string() };
-        let update = doc! {
-            "$set": {
-                "data": to_bson(&component.data).unwrap_or(Bson::Document(doc! {})),
-                "updated_at": chrono::Utc::now().timestamp(),
-            }
-        };
+        self.observed(QueryOp::Update, "components", async {
+            let collection = self.collection::<Document>("components");
+
+            let filter = doc! { "_id": component.id.to_string() };
+            let update = doc! {
+                "$set": {
+                    "data": to_bson(&component.data).unwrap_or(Bson::Document(doc! {})),
+                    "updated_at": chrono::Utc::now().timestamp(),
+                }
+            };
 
-        collection
-            .update_one(filter, update)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to update component: {}", e)))?;
+            collection
+                .update_one(filter, update)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to update component: {}", e)))?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn delete_component(&self, component_id: UUID) -> Result<()> {
-        let collection = self.collection::<Document>("components");
-        let filter = doc! { "_id": component_id.to_string() };
+        self.observed(QueryOp::Delete, "components", async {
+            let collection = self.collection::<Document>("components");
+            let filter = doc! { "_id": component_id.to_string() };
 
-        collection
-            .delete_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to delete component: {}", e)))?;
+            collection
+                .delete_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to delete component: {}", e)))?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn get_memories(&self, params: MemoryQuery) -> Result<Vec<Memory>> {
-        let collection = self.collection::<Document>("memories");
+        self.observed(QueryOp::Find, "memories", async {
+            let collection = self.collection::<Document>("memories");
 
-        let mut filter = doc! {};
+            let mut filter = doc! {};
 
-        if let Some(agent_id) = params.agent_id {
-            filter.insert("agent_id", agent_id.to_string());
-        }
-        if let Some(room_id) = params.room_id {
-            filter.insert("room_id", room_id.to_string());
-        }
-        if let Some(entity_id) = params.entity_id {
-            filter.insert("entity_id", entity_id.to_string());
-        }
-        if let Some(unique) = params.unique {
-            filter.insert("unique_flag", unique);
-        }
+            if let Some(agent_id) = params.agent_id {
+                filter.insert("agent_id", agent_id.to_string());
+            }
+            if let Some(room_id) = params.room_id {
+                filter.insert("room_id", room_id.to_string());
+            }
+            if let Some(entity_id) = params.entity_id {
+                filter.insert("entity_id", entity_id.to_string());
+            }
+            if let Some(unique) = params.unique {
+                filter.insert("unique_flag", unique);
+            }
 
-        let mut options = FindOptions::builder()
-            .sort(doc! { "created_at": -1 })
-            .build();
+            let mut options = FindOptions::builder()
+                .sort(doc! { "created_at": -1 })
+                .build();
 
-        if let Some(count) = params.count {
-            options.limit = Some(count as i64);
-        }
+            if let Some(count) = params.count {
+                options.limit = Some(count as i64);
+            }
 
-        let mut cursor = collection
-            .find(filter)
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get memories: {}", e)))?;
+            let mut cursor = collection
+                .find(live_memories(filter))
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get memories: {}", e)))?;
 
-        let mut memories = Vec::new();
-        while let Some(doc) = cursor.try_next().await.map_err(|e| {
-            ZoeyError::database(format!("Failed to iterate memories: {}", e))
-        })? {
-            memories.push(self.doc_to_memory(&doc)?);
-        }
+            let mut memories = Vec::new();
+            while let Some(doc) = cursor.try_next().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to iterate memories: {}", e))
+            })? {
+                memories.push(self.doc_to_memory(&doc)?);
+            }
 
-        Ok(memories)
+            Ok(memories)
+        })
+        .await
     }
 
     async fn create_memory(&self, memory: &Memory, _table_name: &str) -> Result<UUID> {
-        let collection = self.collection::<Document>("memories");
+        self.observed(QueryOp::Insert, "memories", async {
+            let collection = self.collection::<Document>("memories");
 
-        let doc = doc! {
-            "_id": memory.id.to_string(),
-            "entity_id": memory.entity_id.to_string(),
-            "agent_id": memory.agent_id.to_string(),
-            "room_id": memory.room_id.to_string(),
-            "content": to_bson(&memory.content).unwrap_or(Bson::Document(doc! {})),
-            "embedding": memory.embedding.as_ref().map(|e| to_bson(e).unwrap_or(Bson::Null)),
-            "metadata": memory.metadata.as_ref().map(|m| to_bson(m).unwrap_or(Bson::Document(doc! {}))),
-            "created_at": memory.created_at,
-            "unique_flag": memory.unique.unwrap_or(false),
-        };
-
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create memory: {}", e)))?;
+            collection
+                .insert_one(Self::memory_to_doc(memory))
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create memory: {}", e)))?;
 
-        Ok(memory.id)
+            Ok(memory.id)
+        })
+        .await
     }
 
+    // Reported by `get_memories`
     async fn search_memories_by_embedding(
         &self,
         params: SearchMemoriesParams,
@@ -801,334 +1175,445 @@ impl IDatabaseAdapter for MongoAdapter {
     }
 
     async fn get_cached_embeddings(&self, params: MemoryQuery) -> Result<Vec<Memory>> {
-        let collection = self.collection::<Document>("memories");
+        self.observed(QueryOp::Find, "memories", async {
+            let collection = self.collection::<Document>("memories");
 
-        let mut filter = doc! {
-            "embedding": { "$exists": true, "$ne": null }
-        };
+            let mut filter = doc! {
+                "embedding": { "$exists": true, "$ne": null }
+            };
 
-        if let Some(agent_id) = params.agent_id {
-            filter.insert("agent_id", agent_id.to_string());
-        }
-        if let Some(room_id) = params.room_id {
-            filter.insert("room_id", room_id.to_string());
-        }
-        if let Some(entity_id) = params.entity_id {
-            filter.insert("entity_id", entity_id.to_string());
-        }
+            if let Some(agent_id) = params.agent_id {
+                filter.insert("agent_id", agent_id.to_string());
+            }
+            if let Some(room_id) = params.room_id {
+                filter.insert("room_id", room_id.to_string());
+            }
+            if let Some(entity_id) = params.entity_id {
+                filter.insert("entity_id", entity_id.to_string());
+            }
 
-        let mut options = FindOptions::builder()
-            .sort(doc! { "created_at": -1 })
-            .build();
+            let mut options = FindOptions::builder()
+                .sort(doc! { "created_at": -1 })
+                .build();
 
-        if let Some(count) = params.count {
-            options.limit = Some(count as i64);
-        }
+            if let Some(count) = params.count {
+                options.limit = Some(count as i64);
+            }
 
-        let mut cursor = collection
-            .find(filter)
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get cached embeddings: {}", e)))?;
+            let mut cursor = collection
+                .find(live_memories(filter))
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get cached embeddings: {}", e)))?;
 
-        let mut memories = Vec::new();
-        while let Some(doc) = cursor.try_next().await.map_err(|e| {
-            ZoeyError::database(format!("Failed to iterate memories: {}", e))
-        })? {
-            memories.push(self.doc_to_memory(&doc)?);
-        }
+            let mut memories = Vec::new();
+            while let Some(doc) = cursor.try_next().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to iterate memories: {}", e))
+            })? {
+                memories.push(self.doc_to_memory(&doc)?);
+            }
 
-        Ok(memories)
+            Ok(memories)
+        })
+        .await
     }
 
     async fn update_memory(&self, memory: &Memory) -> Result<bool> {
-        let collection = self.collection::<Document>("memories");
-
-        let filter = doc! { "_id": memory.id.to_string() };
-        let update = doc! {
-            "$set": {
-                "content": to_bson(&memory.content).unwrap_or(Bson::Document(doc! {})),
-                "embedding": memory.embedding.as_ref().map(|e| to_bson(e).unwrap_or(Bson::Null)),
-                "metadata": memory.metadata.as_ref().map(|m| to_bson(m).unwrap_or(Bson::Document(doc! {}))),
-            }
-        };
+        self.observed(QueryOp::Update, "memories", async {
+            let collection = self.collection::<Document>("memories");
+
+            let filter = doc! { "_id": memory.id.to_string() };
+            let update = doc! {
+                "$set": {
+                    "content": to_bson(&memory.content).unwrap_or(Bson::Document(doc! {})),
+                    "embedding": memory.embedding.as_ref().map(|e| to_bson(e).unwrap_or(Bson::Null)),
+                    "metadata": memory.metadata.as_ref().map(|m| to_bson(m).unwrap_or(Bson::Document(doc! {}))),
+                }
+            };
 
-        let result = collection
-            .update_one(filter, update)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to update memory: {}", e)))?;
+            let result = collection
+                .update_one(filter, update)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to update memory: {}", e)))?;
 
-        Ok(result.modified_count > 0)
+            Ok(result.modified_count > 0)
+        })
+        .await
     }
 
     async fn remove_memory(&self, memory_id: UUID, _table_name: &str) -> Result<bool> {
-        let collection = self.collection::<Document>("memories");
-        let filter = doc! { "_id": memory_id.to_string() };
+        self.observed(QueryOp::Delete, "memories", async {
+            let collection = self.collection::<Document>("memories");
+            let filter = doc! { "_id": memory_id.to_string() };
 
-        let result = collection
-            .delete_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to delete memory: {}", e)))?;
+            let result = collection
+                .delete_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to delete memory: {}", e)))?;
 
-        Ok(result.deleted_count > 0)
+            Ok(result.deleted_count > 0)
+        })
+        .await
     }
 
     async fn remove_all_memories(&self, agent_id: UUID, _table_name: &str) -> Result<bool> {
-        let collection = self.collection::<Document>("memories");
-        let filter = doc! { "agent_id": agent_id.to_string() };
+        self.observed(QueryOp::Delete, "memories", async {
+            let collection = self.collection::<Document>("memories");
+            let filter = doc! { "agent_id": agent_id.to_string() };
 
-        let result = collection
-            .delete_many(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to delete memories: {}", e)))?;
+            let result = collection
+                .delete_many(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to delete memories: {}", e)))?;
 
-        Ok(result.deleted_count > 0)
+            Ok(result.deleted_count > 0)
+        })
+        .await
     }
 
     async fn count_memories(&self, params: MemoryQuery) -> Result<usize> {
-        let collection = self.collection::<Document>("memories");
+        self.observed(QueryOp::Count, "memories", async {
+            let collection = self.collection::<Document>("memories");
 
-        let mut filter = doc! {};
+            let mut filter = doc! {};
 
-        if let Some(agent_id) = params.agent_id {
-            filter.insert("agent_id", agent_id.to_string());
-        }
-        if let Some(room_id) = params.room_id {
-            filter.insert("room_id", room_id.to_string());
-        }
-        if let Some(entity_id) = params.entity_id {
-            filter.insert("entity_id", entity_id.to_string());
-        }
-        if let Some(unique) = params.unique {
-            filter.insert("unique_flag", unique);
-        }
+            if let Some(agent_id) = params.agent_id {
+                filter.insert("agent_id", agent_id.to_string());
+            }
+            if let Some(room_id) = params.room_id {
+                filter.insert("room_id", room_id.to_string());
+            }
+            if let Some(entity_id) = params.entity_id {
+                filter.insert("entity_id", entity_id.to_string());
+            }
+            if let Some(unique) = params.unique {
+                filter.insert("unique_flag", unique);
+            }
 
-        let count = collection
-            .count_documents(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to count memories: {}", e)))?;
+            let count = collection
+                .count_documents(live_memories(filter))
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to count memories: {}", e)))?;
+
+            Ok(count as usize)
+        })
+        .await
+    }
+
+    async fn get_memories_page(
+        &self,
+        room_id: UUID,
+        _table_name: &str,
+        page: Pagination,
+    ) -> Result<MemoryPage> {
+        self.observed(QueryOp::Find, "memories", async {
+            let collection = self.collection::<Document>("memories");
+
+            let mut filter = doc! { "room_id": room_id.to_string() };
+            if let Some(before) = page.before {
+                // Hyphenated UUID strings sort like the UUIDs themselves
+                filter.insert(
+                    "$or",
+                    vec![
+                        doc! { "created_at": { "$lt": before.created_at } },
+                        doc! {
+                            "created_at": before.created_at,
+                            "_id": { "$lt": before.id.to_string() },
+                        },
+                    ],
+                );
+            }
+
+            // One extra row tells whether another page follows
+            let options = FindOptions::builder()
+                .sort(doc! { "created_at": -1, "_id": -1 })
+                .limit(page.limit.saturating_add(1) as i64)
+                .build();
+
+            let mut cursor = collection
+                .find(live_memories(filter))
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get memory page: {}", e)))?;
+
+            let mut memories = Vec::new();
+            while let Some(doc) = cursor.try_next().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to iterate memories: {}", e))
+            })? {
+                memories.push(self.doc_to_memory(&doc)?);
+            }
 
-        Ok(count as usize)
+            Ok(MemoryPage::from_overfetched(memories, page.limit))
+        })
+        .await
     }
 
     async fn get_world(&self, world_id: UUID) -> Result<Option<World>> {
-        let collection = self.collection::<Document>("worlds");
-        let filter = doc! { "_id": world_id.to_string() };
+        self.observed(QueryOp::Find, "worlds", async {
+            let collection = self.collection::<Document>("worlds");
+            let filter = doc! { "_id": world_id.to_string() };
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get world: {}", e)))?;
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get world: {}", e)))?;
 
-        match result {
-            Some(doc) => Ok(Some(self.doc_to_world(&doc)?)),
-            None => Ok(None),
-        }
+            match result {
+                Some(doc) => Ok(Some(self.doc_to_world(&doc)?)),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
     async fn ensure_world(&self, world: &World) -> Result<()> {
-        let collection = self.collection::<Document>("worlds");
+        self.observed(QueryOp::Update, "worlds", async {
+            let collection = self.collection::<Document>("worlds");
 
-        let doc = doc! {
-            "_id": world.id.to_string(),
-            "name": &world.name,
-            "agent_id": world.agent_id.to_string(),
-            "server_id": &world.server_id,
-            "metadata": to_bson(&world.metadata).unwrap_or(Bson::Document(doc! {})),
-            "created_at": world.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
-        };
+            let doc = doc! {
+                "_id": world.id.to_string(),
+                "name": &world.name,
+                "agent_id": world.agent_id.to_string(),
+                "server_id": &world.server_id,
+                "metadata": to_bson(&world.metadata).unwrap_or(Bson::Document(doc! {})),
+                "created_at": world.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            };
 
-        let options = UpdateOptions::builder().upsert(true).build();
-        collection
-            .update_one(doc! { "_id": world.id.to_string() }, doc! { "$set": doc })
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to ensure world: {}", e)))?;
+            let options = UpdateOptions::builder().upsert(true).build();
+            collection
+                .update_one(doc! { "_id": world.id.to_string() }, doc! { "$set": doc })
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to ensure world: {}", e)))?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn get_room(&self, room_id: UUID) -> Result<Option<Room>> {
-        let collection = self.collection::<Document>("rooms");
-        let filter = doc! { "_id": room_id.to_string() };
+        self.observed(QueryOp::Find, "rooms", async {
+            let collection = self.collection::<Document>("rooms");
+            let filter = doc! { "_id": room_id.to_string() };
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get room: {}", e)))?;
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get room: {}", e)))?;
 
-        match result {
-            Some(doc) => Ok(Some(self.doc_to_room(&doc)?)),
-            None => Ok(None),
-        }
+            match result {
+                Some(doc) => Ok(Some(self.doc_to_room(&doc)?)),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
     async fn create_room(&self, room: &Room) -> Result<UUID> {
-        let collection = self.collection::<Document>("rooms");
+        self.observed(QueryOp::Insert, "rooms", async {
+            let collection = self.collection::<Document>("rooms");
 
-        let channel_type_str = serde_json::to_string(&room.channel_type)
-            .unwrap_or_default()
-            .trim_matches('"')
-            .to_string();
-
-        let doc = doc! {
-            "_id": room.id.to_string(),
-            "agent_id": room.agent_id.map(|id| id.to_string()),
-            "name": &room.name,
-            "source": &room.source,
-            "type": channel_type_str,
-            "channel_id": &room.channel_id,
-            "server_id": &room.server_id,
-            "world_id": room.world_id.to_string(),
-            "metadata": to_bson(&room.metadata).unwrap_or(Bson::Document(doc! {})),
-            "created_at": room.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
-        };
+            let channel_type_str = serde_json::to_string(&room.channel_type)
+                .unwrap_or_default()
+                .trim_matches('"')
+                .to_string();
 
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create room: {}", e)))?;
+            let doc = doc! {
+                "_id": room.id.to_string(),
+                "agent_id": room.agent_id.map(|id| id.to_string()),
+                "name": &room.name,
+                "source": &room.source,
+                "type": channel_type_str,
+                "channel_id": &room.channel_id,
+                "server_id": &room.server_id,
+                "world_id": room.world_id.to_string(),
+                "metadata": to_bson(&room.metadata).unwrap_or(Bson::Document(doc! {})),
+                "created_at": room.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            };
 
-        Ok(room.id)
+            collection
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create room: {}", e)))?;
+
+            Ok(room.id)
+        })
+        .await
     }
 
     async fn get_rooms(&self, world_id: UUID) -> Result<Vec<Room>> {
-        let collection = self.collection::<Document>("rooms");
-        let filter = doc! { "world_id": world_id.to_string() };
+        self.observed(QueryOp::Find, "rooms", async {
+            let collection = self.collection::<Document>("rooms");
+            let filter = doc! { "world_id": world_id.to_string() };
 
-        let mut cursor = collection
-            .find(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get rooms: {}", e)))?;
+            let mut cursor = collection
+                .find(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get rooms: {}", e)))?;
 
-        let mut rooms = Vec::new();
-        while let Some(doc) = cursor
-            .try_next()
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to iterate rooms: {}", e)))?
-        {
-            rooms.push(self.doc_to_room(&doc)?);
-        }
+            let mut rooms = Vec::new();
+            while let Some(doc) = cursor
+                .try_next()
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to iterate rooms: {}", e)))?
+            {
+                rooms.push(self.doc_to_room(&doc)?);
+            }
 
-        Ok(rooms)
+            Ok(rooms)
+        })
+        .await
     }
 
     async fn get_rooms_for_agent(&self, agent_id: UUID) -> Result<Vec<Room>> {
-        let collection = self.collection::<Document>("rooms");
-        let filter = doc! { "agent_id": agent_id.to_string() };
+        self.observed(QueryOp::Find, "rooms", async {
+            let collection = self.collection::<Document>("rooms");
+            let filter = doc! { "agent_id": agent_id.to_string() };
 
-        let mut cursor = collection
-            .find(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get rooms: {}", e)))?;
+            let mut cursor = collection
+                .find(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get rooms: {}", e)))?;
 
-        let mut rooms = Vec::new();
-        while let Some(doc) = cursor
-            .try_next()
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to iterate rooms: {}", e)))?
-        {
-            rooms.push(self.doc_to_room(&doc)?);
-        }
+            let mut rooms = Vec::new();
+            while let Some(doc) = cursor
+                .try_next()
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to iterate rooms: {}", e)))?
+            {
+                rooms.push(self.doc_to_room(&doc)?);
+            }
 
-        Ok(rooms)
+            Ok(rooms)
+        })
+        .await
     }
 
     async fn add_participant(&self, entity_id: UUID, room_id: UUID) -> Result<bool> {
-        let collection = self.collection::<Document>("participants");
+        self.observed(QueryOp::Update, "participants", async {
+            let collection = self.collection::<Document>("participants");
 
-        let doc = doc! {
-            "entity_id": entity_id.to_string(),
-            "room_id": room_id.to_string(),
-            "joined_at": chrono::Utc::now().timestamp(),
-            "metadata": {},
-        };
+            let doc = doc! {
+                "entity_id": entity_id.to_string(),
+                "room_id": room_id.to_string(),
+                "joined_at": chrono::Utc::now().timestamp(),
+                "metadata": {},
+            };
 
-        let options = UpdateOptions::builder().upsert(true).build();
-        let filter = doc! {
-            "entity_id": entity_id.to_string(),
-            "room_id": room_id.to_string(),
-        };
+            let options = UpdateOptions::builder().upsert(true).build();
+            let filter = doc! {
+                "entity_id": entity_id.to_string(),
+                "room_id": room_id.to_string(),
+            };
 
-        collection
-            .update_one(filter, doc! { "$set": doc })
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to add participant: {}", e)))?;
+            collection
+                .update_one(filter, doc! { "$set": doc })
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to add participant: {}", e)))?;
+
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn delete_room(&self, room_id: UUID) -> Result<bool> {
+        self.observed(QueryOp::Delete, "rooms", async {
+            self.collection::<Document>("participants")
+                .delete_many(doc! { "room_id": room_id.to_string() })
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to remove participants: {}", e)))?;
+
+            let result = self
+                .collection::<Document>("rooms")
+                .delete_one(doc! { "_id": room_id.to_string() })
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to delete room: {}", e)))?;
 
-        Ok(true)
+            Ok(result.deleted_count > 0)
+        })
+        .await
     }
 
     async fn remove_participant(&self, entity_id: UUID, room_id: UUID) -> Result<bool> {
-        let collection = self.collection::<Document>("participants");
-        let filter = doc! {
-            "entity_id": entity_id.to_string(),
-            "room_id": room_id.to_string(),
-        };
+        self.observed(QueryOp::Delete, "participants", async {
+            let collection = self.collection::<Document>("participants");
+            let filter = doc! {
+                "entity_id": entity_id.to_string(),
+                "room_id": room_id.to_string(),
+            };
 
-        let result = collection
-            .delete_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to remove participant: {}", e)))?;
+            let result = collection
+                .delete_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to remove participant: {}", e)))?;
 
-        Ok(result.deleted_count > 0)
+            Ok(result.deleted_count > 0)
+        })
+        .await
     }
 
     async fn get_participants(&self, room_id: UUID) -> Result<Vec<Participant>> {
-        let collection = self.collection::<Document>("participants");
-        let filter = doc! { "room_id": room_id.to_string() };
-
-        let mut cursor = collection
-            .find(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get participants: {}", e)))?;
+        self.observed(QueryOp::Find, "participants", async {
+            let collection = self.collection::<Document>("participants");
+            let filter = doc! { "room_id": room_id.to_string() };
 
-        let mut participants = Vec::new();
-        while let Some(doc) = cursor.try_next().await.map_err(|e| {
-            ZoeyError::database(format!("Failed to iterate participants: {}", e))
-        })? {
-            let participant = Participant {
-                entity_id: Self::bson_to_uuid(doc.get("entity_id").unwrap_or(&Bson::Null))?,
-                room_id: Self::bson_to_uuid(doc.get("room_id").unwrap_or(&Bson::Null))?,
-                joined_at: doc.get_i64("joined_at").ok(),
-                metadata: mongodb::bson::from_bson(
-                    doc.get("metadata").cloned().unwrap_or(Bson::Document(doc! {})),
-                )
-                .unwrap_or_default(),
-            };
-            participants.push(participant);
-        }
+            let mut cursor = collection
+                .find(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get participants: {}", e)))?;
+
+            let mut participants = Vec::new();
+            while let Some(doc) = cursor.try_next().await.map_err(|e| {
+                ZoeyError::database(format!("Failed to iterate participants: {}", e))
+            })? {
+                let participant = Participant {
+                    entity_id: Self::bson_to_uuid(doc.get("entity_id").unwrap_or(&Bson::Null))?,
+                    room_id: Self::bson_to_uuid(doc.get("room_id").unwrap_or(&Bson::Null))?,
+                    joined_at: doc.get_i64("joined_at").ok(),
+                    metadata: mongodb::bson::from_bson(
+                        doc.get("metadata").cloned().unwrap_or(Bson::Document(doc! {})),
+                    )
+                    .unwrap_or_default(),
+                };
+                participants.push(participant);
+            }
 
-        Ok(participants)
+            Ok(participants)
+        })
+        .await
     }
 
     async fn create_relationship(&self, relationship: &Relationship) -> Result<bool> {
-        let collection = self.collection::<Document>("relationships");
+        self.observed(QueryOp::Update, "relationships", async {
+            let collection = self.collection::<Document>("relationships");
 
-        let doc = doc! {
-            "_id": uuid::Uuid::new_v4().to_string(),
-            "entity_id_a": relationship.entity_id_a.to_string(),
-            "entity_id_b": relationship.entity_id_b.to_string(),
-            "type": &relationship.relationship_type,
-            "agent_id": relationship.agent_id.to_string(),
-            "metadata": to_bson(&relationship.metadata).unwrap_or(Bson::Document(doc! {})),
-            "created_at": relationship.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
-        };
+            let doc = doc! {
+                "_id": uuid::Uuid::new_v4().to_string(),
+                "entity_id_a": relationship.entity_id_a.to_string(),
+                "entity_id_b": relationship.entity_id_b.to_string(),
+                "type": &relationship.relationship_type,
+                "agent_id": relationship.agent_id.to_string(),
+                "metadata": to_bson(&relationship.metadata).unwrap_or(Bson::Document(doc! {})),
+                "created_at": relationship.created_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            };
 
-        let options = UpdateOptions::builder().upsert(true).build();
-        let filter = doc! {
-            "entity_id_a": relationship.entity_id_a.to_string(),
-            "entity_id_b": relationship.entity_id_b.to_string(),
-            "type": &relationship.relationship_type,
-        };
+            let options = UpdateOptions::builder().upsert(true).build();
+            let filter = doc! {
+                "entity_id_a": relationship.entity_id_a.to_string(),
+                "entity_id_b": relationship.entity_id_b.to_string(),
+                "type": &relationship.relationship_type,
+            };
 
-        collection
-            .update_one(filter, doc! { "$set": doc })
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create relationship: {}", e)))?;
+            collection
+                .update_one(filter, doc! { "$set": doc })
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create relationship: {}", e)))?;
 
-        Ok(true)
+            Ok(true)
+        })
+        .await
     }
 
     async fn get_relationship(
@@ -1136,91 +1621,61 @@ impl IDatabaseAdapter for MongoAdapter {
         entity_id_a: UUID,
         entity_id_b: UUID,
     ) -> Result<Option<Relationship>> {
-        let collection = self.collection::<Document>("relationships");
-        let filter = doc! {
-            "$or": [
-                { "entity_id_a": entity_id_a.to_string(), "entity_id_b": entity_id_b.to_string() },
-                { "entity_id_a": entity_id_b.to_string(), "entity_id_b": entity_id_a.to_string() },
-            ]
-        };
+        self.observed(QueryOp::Find, "relationships", async {
+            let collection = self.collection::<Document>("relationships");
+            let filter = doc! {
+                "$or": [
+                    { "entity_id_a": entity_id_a.to_string(), "entity_id_b": entity_id_b.to_string() },
+                    { "entity_id_a": entity_id_b.to_string(), "entity_id_b": entity_id_a.to_string() },
+                ]
+            };
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get relationship: {}", e)))?;
-
-        match result {
-            Some(doc) => {
-                let relationship = Relationship {
-                    entity_id_a: Self::bson_to_uuid(
-                        doc.get("entity_id_a").unwrap_or(&Bson::Null),
-                    )?,
-                    entity_id_b: Self::bson_to_uuid(
-                        doc.get("entity_id_b").unwrap_or(&Bson::Null),
-                    )?,
-                    relationship_type: doc.get_str("type").unwrap_or("").to_string(),
-                    agent_id: Self::bson_to_uuid(doc.get("agent_id").unwrap_or(&Bson::Null))?,
-                    metadata: mongodb::bson::from_bson(
-                        doc.get("metadata").cloned().unwrap_or(Bson::Document(doc! {})),
-                    )
-                    .unwrap_or_default(),
-                    created_at: doc.get_i64("created_at").ok(),
-                };
-                Ok(Some(relationship))
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get relationship: {}", e)))?;
+
+            match result {
+                Some(doc) => {
+                    let relationship = Relationship {
+                        entity_id_a: Self::bson_to_uuid(
+                            doc.get("entity_id_a").unwrap_or(&Bson::Null),
+                        )?,
+                        entity_id_b: Self::bson_to_uuid(
+                            doc.get("entity_id_b").unwrap_or(&Bson::Null),
+                        )?,
+                        relationship_type: doc.get_str("type").unwrap_or("").to_string(),
+                        agent_id: Self::bson_to_uuid(doc.get("agent_id").unwrap_or(&Bson::Null))?,
+                        metadata: mongodb::bson::from_bson(
+                            doc.get("metadata").cloned().unwrap_or(Bson::Document(doc! {})),
+                        )
+                        .unwrap_or_default(),
+                        created_at: doc.get_i64("created_at").ok(),
+                    };
+                    Ok(Some(relationship))
+                }
+                None => Ok(None),
             }
-            None => Ok(None),
-        }
+        })
+        .await
     }
 
     async fn create_task(&self, task: &Task) -> Result<UUID> {
-        let collection = self.collection::<Document>("tasks");
-
-        let status_str = match task.status {
-            TaskStatus::Pending => "PENDING",
-            TaskStatus::Running => "RUNNING",
-            TaskStatus::Completed => "COMPLETED",
-            TaskStatus::Failed => "FAILED",
-            TaskStatus::Cancelled => "CANCELLED",
-        };
-
-        let doc = doc! {
-            "_id": task.id.to_string(),
-            "agent_id": task.agent_id.to_string(),
-            "task_type": &task.task_type,
-            "data": to_bson(&task.data).unwrap_or(Bson::Document(doc! {})),
-            "status": status_str,
-            "priority": task.priority,
-            "scheduled_at": task.scheduled_at,
-            "executed_at": task.executed_at,
-            "retry_count": task.retry_count,
-            "max_retries": task.max_retries,
-            "error": &task.error,
-            "created_at": task.created_at,
-            "updated_at": task.updated_at,
-        };
-
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create task: {}", e)))?;
-
-        Ok(task.id)
-    }
-
-    async fn update_task(&self, task: &Task) -> Result<bool> {
-        let collection = self.collection::<Document>("tasks");
-
-        let status_str = match task.status {
-            TaskStatus::Pending => "PENDING",
-            TaskStatus::Running => "RUNNING",
-            TaskStatus::Completed => "COMPLETED",
-            TaskStatus::Failed => "FAILED",
-            TaskStatus::Cancelled => "CANCELLED",
-        };
+        self.observed(QueryOp::Insert, "tasks", async {
+            let collection = self.collection::<Document>("tasks");
+
+            let status_str = match task.status {
+                TaskStatus::Pending => "PENDING",
+                TaskStatus::Running => "RUNNING",
+                TaskStatus::Completed => "COMPLETED",
+                TaskStatus::Failed => "FAILED",
+                TaskStatus::Cancelled => "CANCELLED",
+            };
 
-        let filter = doc! { "_id": task.id.to_string() };
-        let update = doc! {
-            "$set": {
+            let doc = doc! {
+                "_id": task.id.to_string(),
+                "agent_id": task.agent_id.to_string(),
+                "task_type": &task.task_type,
                 "data": to_bson(&task.data).unwrap_or(Bson::Document(doc! {})),
                 "status": status_str,
                 "priority": task.priority,
@@ -1229,246 +1684,303 @@ impl IDatabaseAdapter for MongoAdapter {
                 "retry_count": task.retry_count,
                 "max_retries": task.max_retries,
                 "error": &task.error,
-                "updated_at": chrono::Utc::now().timestamp(),
-            }
-        };
+                "created_at": task.created_at,
+                "updated_at": task.updated_at,
+            };
 
-        let result = collection
-            .update_one(filter, update)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to update task: {}", e)))?;
+            collection
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create task: {}", e)))?;
 
-        Ok(result.modified_count > 0)
+            Ok(task.id)
+        })
+        .await
+    }
+
+    async fn update_task(&self, task: &Task) -> Result<bool> {
+        self.observed(QueryOp::Update, "tasks", async {
+            let collection = self.collection::<Document>("tasks");
+
+            let status_str = match task.status {
+                TaskStatus::Pending => "PENDING",
+                TaskStatus::Running => "RUNNING",
+                TaskStatus::Completed => "COMPLETED",
+                TaskStatus::Failed => "FAILED",
+                TaskStatus::Cancelled => "CANCELLED",
+            };
+
+            let filter = doc! { "_id": task.id.to_string() };
+            let update = doc! {
+                "$set": {
+                    "data": to_bson(&task.data).unwrap_or(Bson::Document(doc! {})),
+                    "status": status_str,
+                    "priority": task.priority,
+                    "scheduled_at": task.scheduled_at,
+                    "executed_at": task.executed_at,
+                    "retry_count": task.retry_count,
+                    "max_retries": task.max_retries,
+                    "error": &task.error,
+                    "updated_at": chrono::Utc::now().timestamp(),
+                }
+            };
+
+            let result = collection
+                .update_one(filter, update)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to update task: {}", e)))?;
+
+            Ok(result.modified_count > 0)
+        })
+        .await
     }
 
     async fn get_task(&self, task_id: UUID) -> Result<Option<Task>> {
-        let collection = self.collection::<Document>("tasks");
-        let filter = doc! { "_id": task_id.to_string() };
+        self.observed(QueryOp::Find, "tasks", async {
+            let collection = self.collection::<Document>("tasks");
+            let filter = doc! { "_id": task_id.to_string() };
 
-        let result = collection
-            .find_one(filter)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get task: {}", e)))?;
+            let result = collection
+                .find_one(filter)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get task: {}", e)))?;
 
-        match result {
-            Some(doc) => Ok(Some(self.doc_to_task(&doc)?)),
-            None => Ok(None),
-        }
+            match result {
+                Some(doc) => Ok(Some(self.doc_to_task(&doc)?)),
+                None => Ok(None),
+            }
+        })
+        .await
     }
 
     async fn get_pending_tasks(&self, agent_id: UUID) -> Result<Vec<Task>> {
-        let collection = self.collection::<Document>("tasks");
-        let filter = doc! {
-            "agent_id": agent_id.to_string(),
-            "status": "PENDING",
-        };
+        self.observed(QueryOp::Find, "tasks", async {
+            let collection = self.collection::<Document>("tasks");
+            let filter = doc! {
+                "agent_id": agent_id.to_string(),
+                "status": "PENDING",
+            };
 
-        let options = FindOptions::builder()
-            .sort(doc! { "scheduled_at": 1, "created_at": 1 })
-            .build();
+            let options = FindOptions::builder()
+                .sort(doc! { "scheduled_at": 1, "created_at": 1 })
+                .build();
 
-        let mut cursor = collection
-            .find(filter)
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get pending tasks: {}", e)))?;
+            let mut cursor = collection
+                .find(filter)
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get pending tasks: {}", e)))?;
 
-        let mut tasks = Vec::new();
-        while let Some(doc) = cursor
-            .try_next()
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to iterate tasks: {}", e)))?
-        {
-            tasks.push(self.doc_to_task(&doc)?);
-        }
+            let mut tasks = Vec::new();
+            while let Some(doc) = cursor
+                .try_next()
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to iterate tasks: {}", e)))?
+            {
+                tasks.push(self.doc_to_task(&doc)?);
+            }
 
-        Ok(tasks)
+            Ok(tasks)
+        })
+        .await
     }
 
     async fn log(&self, log: &Log) -> Result<()> {
-        let collection = self.collection::<Document>("logs");
+        self.observed(QueryOp::Insert, "logs", async {
+            let collection = self.collection::<Document>("logs");
 
-        let id = log.id.unwrap_or_else(uuid::Uuid::new_v4);
+            let id = log.id.unwrap_or_else(uuid::Uuid::new_v4);
 
-        let doc = doc! {
-            "_id": id.to_string(),
-            "entity_id": log.entity_id.to_string(),
-            "room_id": log.room_id.map(|id| id.to_string()),
-            "body": to_bson(&log.body).unwrap_or(Bson::Document(doc! {})),
-            "type": &log.log_type,
-            "created_at": log.created_at,
-        };
+            let doc = doc! {
+                "_id": id.to_string(),
+                "entity_id": log.entity_id.to_string(),
+                "room_id": log.room_id.map(|id| id.to_string()),
+                "body": to_bson(&log.body).unwrap_or(Bson::Document(doc! {})),
+                "type": &log.log_type,
+                "created_at": log.created_at,
+            };
 
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to create log: {}", e)))?;
+            collection
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to create log: {}", e)))?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn get_logs(&self, params: LogQuery) -> Result<Vec<Log>> {
-        let collection = self.collection::<Document>("logs");
+        self.observed(QueryOp::Find, "logs", async {
+            let collection = self.collection::<Document>("logs");
 
-        let mut filter = doc! {};
+            let mut filter = doc! {};
 
-        if let Some(entity_id) = params.entity_id {
-            filter.insert("entity_id", entity_id.to_string());
-        }
-        if let Some(room_id) = params.room_id {
-            filter.insert("room_id", room_id.to_string());
-        }
-        if let Some(log_type) = params.log_type {
-            filter.insert("type", log_type);
-        }
+            if let Some(entity_id) = params.entity_id {
+                filter.insert("entity_id", entity_id.to_string());
+            }
+            if let Some(room_id) = params.room_id {
+                filter.insert("room_id", room_id.to_string());
+            }
+            if let Some(log_type) = params.log_type {
+                filter.insert("type", log_type);
+            }
 
-        let mut options = FindOptions::builder()
-            .sort(doc! { "created_at": -1 })
-            .build();
+            let mut options = FindOptions::builder()
+                .sort(doc! { "created_at": -1 })
+                .build();
 
-        if let Some(limit) = params.limit {
-            options.limit = Some(limit as i64);
-        }
-        if let Some(offset) = params.offset {
-            options.skip = Some(offset as u64);
-        }
+            if let Some(limit) = params.limit {
+                options.limit = Some(limit as i64);
+            }
+            if let Some(offset) = params.offset {
+                options.skip = Some(offset as u64);
+            }
 
-        let mut cursor = collection
-            .find(filter)
-            .with_options(options)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to get logs: {}", e)))?;
+            let mut cursor = collection
+                .find(filter)
+                .with_options(options)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to get logs: {}", e)))?;
 
-        let mut logs = Vec::new();
-        while let Some(doc) = cursor
-            .try_next()
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to iterate logs: {}", e)))?
-        {
-            let log = Log {
-                id: Some(Self::bson_to_uuid(doc.get("_id").unwrap_or(&Bson::Null))?),
-                entity_id: Self::bson_to_uuid(doc.get("entity_id").unwrap_or(&Bson::Null))?,
-                room_id: doc
-                    .get("room_id")
-                    .and_then(|b| Self::bson_to_uuid(b).ok()),
-                body: mongodb::bson::from_bson(
-                    doc.get("body").cloned().unwrap_or(Bson::Document(doc! {})),
-                )
-                .unwrap_or(serde_json::Value::Null),
-                log_type: doc.get_str("type").unwrap_or("").to_string(),
-                created_at: doc.get_i64("created_at").unwrap_or(0),
-            };
-            logs.push(log);
-        }
+            let mut logs = Vec::new();
+            while let Some(doc) = cursor
+                .try_next()
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to iterate logs: {}", e)))?
+            {
+                let log = Log {
+                    id: Some(Self::bson_to_uuid(doc.get("_id").unwrap_or(&Bson::Null))?),
+                    entity_id: Self::bson_to_uuid(doc.get("entity_id").unwrap_or(&Bson::Null))?,
+                    room_id: doc
+                        .get("room_id")
+                        .and_then(|b| Self::bson_to_uuid(b).ok()),
+                    body: mongodb::bson::from_bson(
+                        doc.get("body").cloned().unwrap_or(Bson::Document(doc! {})),
+                    )
+                    .unwrap_or(serde_json::Value::Null),
+                    log_type: doc.get_str("type").unwrap_or("").to_string(),
+                    created_at: doc.get_i64("created_at").unwrap_or(0),
+                };
+                logs.push(log);
+            }
 
-        Ok(logs)
+            Ok(logs)
+        })
+        .await
     }
 
     async fn get_agent_run_summaries(
         &self,
         params: RunSummaryQuery,
     ) -> Result<AgentRunSummaryResult> {
-        let collection = self.collection::<Document>("llm_costs");
+        self.observed(QueryOp::Find, "llm_costs", async {
+            let collection = self.collection::<Document>("llm_costs");
 
-        let mut filter = doc! {};
+            let mut filter = doc! {};
 
-        if let Some(agent_id) = params.agent_id {
-            filter.insert("agent_id", agent_id.to_string());
-        }
-        if let Some(status) = params.status {
-            filter.insert("success", matches!(status, RunStatus::Completed));
-        }
+            if let Some(agent_id) = params.agent_id {
+                filter.insert("agent_id", agent_id.to_string());
+            }
+            if let Some(status) = params.status {
+                filter.insert("success", matches!(status, RunStatus::Completed));
+            }
 
-        let total = collection
-            .count_documents(filter.clone())
-            .await
-            .unwrap_or(0);
+            let total = collection
+                .count_documents(filter.clone())
+                .await
+                .unwrap_or(0);
 
-        let mut options = FindOptions::builder()
-            .sort(doc! { "timestamp": -1 })
-            .build();
+            let mut options = FindOptions::builder()
+                .sort(doc! { "timestamp": -1 })
+                .build();
 
-        if let Some(limit) = params.limit {
-            options.limit = Some(limit as i64);
-        }
-        if let Some(offset) = params.offset {
-            options.skip = Some(offset as u64);
-        }
+            if let Some(limit) = params.limit {
+                options.limit = Some(limit as i64);
+            }
+            if let Some(offset) = params.offset {
+                options.skip = Some(offset as u64);
+            }
 
-        let mut cursor = collection.find(filter).with_options(options).await
-            .map_err(|e| ZoeyError::database(format!("Failed to query run summaries: {}", e)))?;
+            let mut cursor = collection.find(filter).with_options(options).await
+                .map_err(|e| ZoeyError::database(format!("Failed to query run summaries: {}", e)))?;
 
-        let mut runs = Vec::new();
-        while let Some(doc) = cursor.try_next().await.ok().flatten() {
-            let id_str = doc.get_str("_id").unwrap_or("");
-            let success = doc.get_bool("success").unwrap_or(false);
-            let timestamp = doc.get_i64("timestamp").unwrap_or(0);
+            let mut runs = Vec::new();
+            while let Some(doc) = cursor.try_next().await.ok().flatten() {
+                let id_str = doc.get_str("_id").unwrap_or("");
+                let success = doc.get_bool("success").unwrap_or(false);
+                let timestamp = doc.get_i64("timestamp").unwrap_or(0);
 
-            let status = if success {
-                RunStatus::Completed
-            } else {
-                RunStatus::Error
-            };
+                let status = if success {
+                    RunStatus::Completed
+                } else {
+                    RunStatus::Error
+                };
 
-            runs.push(AgentRunSummary {
-                run_id: id_str.to_string(),
-                status,
-                started_at: Some(timestamp),
-                ended_at: Some(timestamp),
-                duration_ms: None,
-                message_id: None,
-                room_id: None,
-                entity_id: None,
-                metadata: None,
-                counts: None,
-            });
-        }
+                runs.push(AgentRunSummary {
+                    run_id: id_str.to_string(),
+                    status,
+                    started_at: Some(timestamp),
+                    ended_at: Some(timestamp),
+                    duration_ms: None,
+                    message_id: None,
+                    room_id: None,
+                    entity_id: None,
+                    metadata: None,
+                    counts: None,
+                });
+            }
 
-        Ok(AgentRunSummaryResult {
-            runs,
-            total: total as usize,
-            has_more: params
-                .limit
-                .map(|l| (l as u64) + (params.offset.unwrap_or(0) as u64) < total)
-                .unwrap_or(false),
+            Ok(AgentRunSummaryResult {
+                runs,
+                total: total as usize,
+                has_more: params
+                    .limit
+                    .map(|l| (l as u64) + (params.offset.unwrap_or(0) as u64) < total)
+                    .unwrap_or(false),
+            })
         })
+        .await
     }
 
     async fn persist_llm_cost(&self, record: LLMCostRecord) -> Result<()> {
-        let collection = self.collection::<Document>("llm_costs");
+        self.observed(QueryOp::Insert, "llm_costs", async {
+            let collection = self.collection::<Document>("llm_costs");
 
-        let doc = doc! {
-            "_id": record.id.to_string(),
-            "timestamp": record.timestamp.timestamp(),
-            "agent_id": record.agent_id.to_string(),
-            "user_id": record.user_id,
-            "conversation_id": record.conversation_id.map(|id| id.to_string()),
-            "action_name": record.action_name,
-            "evaluator_name": record.evaluator_name,
-            "provider": record.provider,
-            "model": record.model,
-            "temperature": record.temperature as f64,
-            "prompt_tokens": record.prompt_tokens as i64,
-            "completion_tokens": record.completion_tokens as i64,
-            "total_tokens": record.total_tokens as i64,
-            "cached_tokens": record.cached_tokens.map(|t| t as i64),
-            "input_cost_usd": record.input_cost_usd as f64,
-            "output_cost_usd": record.output_cost_usd as f64,
-            "total_cost_usd": record.total_cost_usd as f64,
-            "latency_ms": record.latency_ms as i64,
-            "ttft_ms": record.ttft_ms.map(|t| t as i64),
-            "success": record.success,
-            "error": record.error,
-            "prompt_hash": record.prompt_hash,
-            "prompt_preview": record.prompt_preview,
-        };
+            let doc = doc! {
+                "_id": record.id.to_string(),
+                "timestamp": record.timestamp.timestamp(),
+                "agent_id": record.agent_id.to_string(),
+                "user_id": record.user_id,
+                "conversation_id": record.conversation_id.map(|id| id.to_string()),
+                "action_name": record.action_name,
+                "evaluator_name": record.evaluator_name,
+                "provider": record.provider,
+                "model": record.model,
+                "temperature": record.temperature as f64,
+                "prompt_tokens": record.prompt_tokens as i64,
+                "completion_tokens": record.completion_tokens as i64,
+                "total_tokens": record.total_tokens as i64,
+                "cached_tokens": record.cached_tokens.map(|t| t as i64),
+                "input_cost_usd": record.input_cost_usd as f64,
+                "output_cost_usd": record.output_cost_usd as f64,
+                "total_cost_usd": record.total_cost_usd as f64,
+                "latency_ms": record.latency_ms as i64,
+                "ttft_ms": record.ttft_ms.map(|t| t as i64),
+                "success": record.success,
+                "error": record.error,
+                "prompt_hash": record.prompt_hash,
+                "prompt_preview": record.prompt_preview,
+            };
 
-        collection
-            .insert_one(doc)
-            .await
-            .map_err(|e| ZoeyError::database(format!("Failed to persist LLM cost: {}", e)))?;
+            collection
+                .insert_one(doc)
+                .await
+                .map_err(|e| ZoeyError::database(format!("Failed to persist LLM cost: {}", e)))?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 }
 
@@ -1614,9 +2126,311 @@ impl MongoAdapter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
 
     #[test]
     fn test_mongo_adapter_creation() {
         assert!(true);
     }
+
+    #[test]
+    fn test_init_options() {
+        let options: InitOptions = serde_json::from_value(serde_json::json!({
+            "ensureVectorIndexes": true,
+            "embeddingDimension": 768,
+            "vectorIndex": { "name": "docs_vector" },
+        }))
+        .unwrap();
+        assert!(options.ensure_vector_indexes);
+        assert_eq!(options.embedding_dimension, Some(768));
+        assert_eq!(options.vector_index.name, "docs_vector");
+        assert_eq!(options.vector_index.collection, "memories");
+
+        let defaults: InitOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(!defaults.ensure_vector_indexes);
+        assert_eq!(defaults.vector_index, VectorIndexSpec::default());
+    }
+
+    #[test]
+    fn test_memory_expiry_filter() {
+        let now = DateTime::from_millis(1_700_000_000_000);
+        assert_eq!(
+            memory_expiry(now, Duration::from_secs(90)).timestamp_millis(),
+            1_700_000_090_000
+        );
+        assert_eq!(memory_expiry(now, Duration::MAX).timestamp_millis(), i64::MAX);
+
+        // Expired means `expires_at <= now`; missing and null never match `$lte`
+        assert_eq!(
+            memory_not_expired(now),
+            doc! { "$not": { "$lte": DateTime::from_millis(1_700_000_000_000) } }
+        );
+
+        // Merges with filters that already use `$or` (memory pages)
+        let page = doc! { "room_id": "r", "$or": [ { "created_at": { "$lt": 5 } } ] };
+        let filter = live_memories(page);
+        assert!(filter.contains_key("$or"));
+        assert!(filter.get_document("expires_at").unwrap().contains_key("$not"));
+    }
+
+    #[derive(Default)]
+    struct CountingSink {
+        events: Mutex<Vec<(QueryOp, String)>>,
+    }
+
+    impl MongoMetricsSink for CountingSink {
+        fn on_query(&self, op: QueryOp, collection: &str, _: Duration, _: usize) {
+            self.events.lock().unwrap().push((op, collection.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_query_emits_one_event() {
+        // Nothing listens on port 1, so every query fails once server selection gives up
+        let options = ClientOptions::parse("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=20")
+            .await
+            .unwrap();
+        let sink = Arc::new(CountingSink::default());
+        let adapter = MongoAdapter::with_client_options(options, "zoey_metrics")
+            .unwrap()
+            .with_metrics(sink.clone());
+
+        let id = uuid::Uuid::new_v4();
+        let agent = Agent {
+            id,
+            name: "Zoey".to_string(),
+            character: serde_json::json!({}),
+            created_at: None,
+            updated_at: None,
+        };
+        let entity = Entity {
+            id,
+            agent_id: id,
+            name: None,
+            username: None,
+            email: None,
+            avatar_url: None,
+            metadata: Metadata::new(),
+            created_at: None,
+        };
+        let component = Component {
+            id,
+            entity_id: id,
+            world_id: id,
+            source_entity_id: None,
+            component_type: "profile".to_string(),
+            data: serde_json::json!({}),
+            created_at: None,
+            updated_at: None,
+        };
+        let memory = Memory {
+            id,
+            entity_id: id,
+            agent_id: id,
+            room_id: id,
+            content: Content::default(),
+            embedding: None,
+            metadata: None,
+            created_at: 0,
+            unique: None,
+            similarity: None,
+        };
+        let world = World {
+            id,
+            name: "world".to_string(),
+            agent_id: id,
+            server_id: None,
+            metadata: Metadata::new(),
+            created_at: None,
+        };
+        let room = Room {
+            id,
+            agent_id: None,
+            name: "room".to_string(),
+            source: "test".to_string(),
+            channel_type: ChannelType::Dm,
+            channel_id: None,
+            server_id: None,
+            world_id: id,
+            metadata: Metadata::new(),
+            created_at: None,
+        };
+        let relationship = Relationship {
+            entity_id_a: id,
+            entity_id_b: id,
+            relationship_type: "knows".to_string(),
+            agent_id: id,
+            metadata: Metadata::new(),
+            created_at: None,
+        };
+        let task = Task {
+            id,
+            agent_id: id,
+            task_type: "test".to_string(),
+            data: serde_json::json!({}),
+            status: TaskStatus::Pending,
+            priority: 0,
+            scheduled_at: None,
+            executed_at: None,
+            retry_count: 0,
+            max_retries: 3,
+            error: None,
+            created_at: 0,
+            updated_at: None,
+        };
+        let log = Log {
+            id: None,
+            entity_id: id,
+            room_id: None,
+            body: serde_json::json!({}),
+            log_type: "test".to_string(),
+            created_at: 0,
+        };
+        let cost = LLMCostRecord {
+            id,
+            timestamp: chrono::Utc::now(),
+            agent_id: id,
+            user_id: None,
+            conversation_id: None,
+            action_name: None,
+            evaluator_name: None,
+            provider: "local".to_string(),
+            model: "test".to_string(),
+            temperature: 0.0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            cached_tokens: None,
+            input_cost_usd: 0.0,
+            output_cost_usd: 0.0,
+            total_cost_usd: 0.0,
+            latency_ms: 0,
+            ttft_ms: None,
+            success: true,
+            error: None,
+            prompt_hash: None,
+            prompt_preview: None,
+        };
+        let search = SearchMemoriesParams {
+            table_name: "memories".to_string(),
+            agent_id: None,
+            room_id: None,
+            world_id: None,
+            entity_id: None,
+            embedding: vec![0.0; 4],
+            count: 5,
+            unique: None,
+            threshold: None,
+        };
+        let page = Pagination {
+            before: None,
+            limit: 10,
+        };
+
+        let _ = adapter.create_memory_with_ttl(&memory, Duration::from_secs(60)).await;
+        let _ = adapter.set_memory_ttl(id, None).await;
+        let _ = adapter.create_memories_bulk(vec![memory.clone()], false).await;
+        let _ = adapter.delete_room_cascade(id, true).await;
+        let _ = adapter.verify_room_purged(id).await;
+        let _ = adapter.get_agent(id).await;
+        let _ = adapter.get_agents().await;
+        let _ = adapter.create_agent(&agent).await;
+        let _ = adapter.update_agent(id, &agent).await;
+        let _ = adapter.delete_agent(id).await;
+        let _ = adapter.get_entities_by_ids(vec![id]).await;
+        let _ = adapter.get_entities_for_room(id, false).await;
+        let _ = adapter.create_entities(vec![entity.clone()]).await;
+        let _ = adapter.update_entity(&entity).await;
+        let _ = adapter.get_entity_by_id(id).await;
+        let _ = adapter.get_component(id, "profile", None, None).await;
+        let _ = adapter.get_components(id, None, None).await;
+        let _ = adapter.create_component(&component).await;
+        let _ = adapter.update_component(&component).await;
+        let _ = adapter.delete_component(id).await;
+        let _ = adapter.get_memories(MemoryQuery::default()).await;
+        let _ = adapter.create_memory(&memory, "memories").await;
+        let _ = adapter.search_memories_by_embedding(search).await;
+        let _ = adapter.get_cached_embeddings(MemoryQuery::default()).await;
+        let _ = adapter.update_memory(&memory).await;
+        let _ = adapter.remove_memory(id, "memories").await;
+        let _ = adapter.remove_all_memories(id, "memories").await;
+        let _ = adapter.count_memories(MemoryQuery::default()).await;
+        let _ = adapter.get_memories_page(id, "memories", page).await;
+        let _ = adapter.get_world(id).await;
+        let _ = adapter.ensure_world(&world).await;
+        let _ = adapter.get_room(id).await;
+        let _ = adapter.create_room(&room).await;
+        let _ = adapter.get_rooms(id).await;
+        let _ = adapter.get_rooms_for_agent(id).await;
+        let _ = adapter.add_participant(id, id).await;
+        let _ = adapter.delete_room(id).await;
+        let _ = adapter.remove_participant(id, id).await;
+        let _ = adapter.get_participants(id).await;
+        let _ = adapter.create_relationship(&relationship).await;
+        let _ = adapter.get_relationship(id, id).await;
+        let _ = adapter.create_task(&task).await;
+        let _ = adapter.update_task(&task).await;
+        let _ = adapter.get_task(id).await;
+        let _ = adapter.get_pending_tasks(id).await;
+        let _ = adapter.log(&log).await;
+        let _ = adapter.get_logs(LogQuery::default()).await;
+        let _ = adapter.get_agent_run_summaries(RunSummaryQuery::default()).await;
+        let _ = adapter.persist_llm_cost(cost).await;
+
+        use QueryOp::*;
+        let expected = [
+            (Insert, "memories"),
+            (Update, "memories"),
+            (Insert, "memories"),
+            (Delete, "rooms"),
+            (Count, "rooms"),
+            (Find, "agents"),
+            (Find, "agents"),
+            (Insert, "agents"),
+            (Update, "agents"),
+            (Delete, "agents"),
+            (Find, "entities"),
+            (Find, "participants"),
+            (Update, "entities"),
+            (Update, "entities"),
+            (Find, "entities"),
+            (Find, "components"),
+            (Find, "components"),
+            (Insert, "components"),
+            (Update, "components"),
+            (Delete, "components"),
+            (Find, "memories"),
+            (Insert, "memories"),
+            (Find, "memories"),
+            (Find, "memories"),
+            (Update, "memories"),
+            (Delete, "memories"),
+            (Delete, "memories"),
+            (Count, "memories"),
+            (Find, "memories"),
+            (Find, "worlds"),
+            (Update, "worlds"),
+            (Find, "rooms"),
+            (Insert, "rooms"),
+            (Find, "rooms"),
+            (Find, "rooms"),
+            (Update, "participants"),
+            (Delete, "rooms"),
+            (Delete, "participants"),
+            (Find, "participants"),
+            (Update, "relationships"),
+            (Find, "relationships"),
+            (Insert, "tasks"),
+            (Update, "tasks"),
+            (Find, "tasks"),
+            (Find, "tasks"),
+            (Insert, "logs"),
+            (Find, "logs"),
+            (Find, "llm_costs"),
+            (Insert, "llm_costs"),
+        ]
+        .map(|(op, collection)| (op, collection.to_string()));
+        assert_eq!(*sink.events.lock().unwrap(), expected);
+        assert_eq!(adapter.pool_stats().in_use, 0);
+    }
 }
@@ -0,0 +1,308 @@
+//! Room deletion that leaves nothing behind
+//!
+//! [`MongoAdapter::delete_room_cascade`](crate::MongoAdapter::delete_room_cascade)
+//! deletes a room with its participant links and, optionally, its memories
+//! and the documents of every vector collection (those recorded in
+//! `vector_collections`). Connected to a replica set or `mongos`, all
+//! deletes run in one multi-document transaction that is aborted on the
+//! first failure, so either everything goes or nothing does. A standalone
+//! server has no transactions; there the collections are purged one by one
+//! in [`purge_targets`] order, stopping at the first failure. The room
+//! document goes last, so an interrupted purge leaves the room in place to
+//! be deleted again.
+//!
+//! Either way a [`RoomPurgeReport`] lists what was deleted from each
+//! collection, and [`MongoAdapter::verify_room_purged`](crate::MongoAdapter::verify_room_purged)
+//! counts whatever is still there.
+
+use async_trait::async_trait;
+use mongodb::{
+    bson::{doc, Document},
+    ClientSession, Database,
+};
+use zoey_core::{types::UUID, Result, ZoeyError};
+
+/// Collection and filter of the documents belonging to a room
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PurgeTarget {
+    pub collection: String,
+    pub filter: Document,
+}
+
+/// What happened to one collection of a room purge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PurgeStatus {
+    /// Deleted this many documents
+    Purged(u64),
+    /// The delete failed
+    Failed(String),
+    /// Not reached because an earlier collection failed
+    NotAttempted,
+}
+
+/// Outcome of one collection, in deletion order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionPurge {
+    /// Name of the collection
+    pub collection: String,
+    /// What happened to the room's documents in it
+    pub status: PurgeStatus,
+}
+
+/// Per-collection outcome of a room deletion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomPurgeReport {
+    /// The room deleted
+    pub room_id: UUID,
+    /// Whether the deletes ran in one transaction
+    pub transactional: bool,
+    /// One entry per collection, in deletion order
+    pub collections: Vec<CollectionPurge>,
+}
+
+impl RoomPurgeReport {
+    /// Documents deleted across all collections
+    pub fn deleted(&self) -> u64 {
+        self.collections
+            .iter()
+            .map(|c| match c.status {
+                PurgeStatus::Purged(count) => count,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Whether every collection was purged
+    pub fn is_complete(&self) -> bool {
+        self.collections
+            .iter()
+            .all(|c| matches!(c.status, PurgeStatus::Purged(_)))
+    }
+}
+
+/// Documents of a room still present after a purge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Straggler {
+    /// Name of the collection
+    pub collection: String,
+    /// Documents of the room left in it
+    pub count: u64,
+}
+
+/// Where a room's documents are deleted (a database, a transaction, or a mock in tests)
+#[async_trait]
+pub(crate) trait RoomDocuments: Send {
+    /// Delete the documents matching `filter`, returning how many went
+    async fn delete_many(&mut self, collection: &str, filter: Document) -> Result<u64>;
+}
+
+#[async_trait]
+impl RoomDocuments for Database {
+    async fn delete_many(&mut self, collection: &str, filter: Document) -> Result<u64> {
+        self.collection::<Document>(collection)
+            .delete_many(filter)
+            .await
+            .map(|result| result.deleted_count)
+            .map_err(|e| ZoeyError::database(format!("Failed to purge '{}': {}", collection, e)))
+    }
+}
+
+/// Deletes inside a session's open transaction
+pub(crate) struct InTransaction<'a> {
+    pub db: &'a Database,
+    pub session: &'a mut ClientSession,
+}
+
+#[async_trait]
+impl RoomDocuments for InTransaction<'_> {
+    async fn delete_many(&mut self, collection: &str, filter: Document) -> Result<u64> {
+        self.db
+            .collection::<Document>(collection)
+            .delete_many(filter)
+            .session(&mut *self.session)
+            .await
+            .map(|result| result.deleted_count)
+            .map_err(|e| ZoeyError::database(format!("Failed to purge '{}': {}", collection, e)))
+    }
+}
+
+/// Whether a `hello` reply comes from a server that supports transactions
+///
+/// Replica set members report a `setName` and `mongos` routers the
+/// `isdbgrid` message; a standalone server reports neither.
+pub(crate) fn supports_transactions(hello: &Document) -> bool {
+    hello.contains_key("setName") || hello.get_str("msg").is_ok_and(|msg| msg == "isdbgrid")
+}
+
+/// Collections holding a room's documents, in deletion order
+///
+/// Memories come first and the room document last, so a purge stopped
+/// midway never leaves memories behind a room that is already gone.
+pub(crate) fn purge_targets(
+    room_id: UUID,
+    purge_memories: bool,
+    vector_collections: &[String],
+) -> Vec<PurgeTarget> {
+    let room = room_id.to_string();
+    let mut targets = Vec::new();
+    if purge_memories {
+        targets.push(PurgeTarget {
+            collection: "memories".to_string(),
+            filter: doc! { "room_id": &room },
+        });
+        for collection in vector_collections {
+            if targets.iter().all(|t| t.collection != *collection) {
+                targets.push(PurgeTarget {
+                    collection: collection.clone(),
+                    filter: doc! { "room_id": &room },
+                });
+            }
+        }
+    }
+    targets.push(PurgeTarget {
+        collection: "participants".to_string(),
+        filter: doc! { "room_id": &room },
+    });
+    targets.push(PurgeTarget {
+        collection: "rooms".to_string(),
+        filter: doc! { "_id": &room },
+    });
+    targets
+}
+
+/// Delete every target in order, stopping at the first failure
+pub(crate) async fn purge_room(
+    documents: &mut dyn RoomDocuments,
+    room_id: UUID,
+    targets: Vec<PurgeTarget>,
+    transactional: bool,
+) -> RoomPurgeReport {
+    let mut report = RoomPurgeReport {
+        room_id,
+        transactional,
+        collections: Vec::with_capacity(targets.len()),
+    };
+    let mut failed = false;
+    for target in targets {
+        let status = if failed {
+            PurgeStatus::NotAttempted
+        } else {
+            match documents
+                .delete_many(&target.collection, target.filter)
+                .await
+            {
+                Ok(count) => PurgeStatus::Purged(count),
+                Err(e) => {
+                    failed = true;
+                    PurgeStatus::Failed(e.to_string())
+                }
+            }
+        };
+        report.collections.push(CollectionPurge {
+            collection: target.collection,
+            status,
+        });
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Documents per collection, with collections that refuse deletes
+    #[derive(Default)]
+    struct FakeDatabase {
+        counts: HashMap<String, u64>,
+        broken: Vec<String>,
+        deleted: Vec<String>,
+    }
+
+    #[async_trait]
+    impl RoomDocuments for FakeDatabase {
+        async fn delete_many(&mut self, collection: &str, _filter: Document) -> Result<u64> {
+            if self.broken.iter().any(|b| b == collection) {
+                return Err(ZoeyError::database(format!(
+                    "{} is unavailable",
+                    collection
+                )));
+            }
+            self.deleted.push(collection.to_string());
+            Ok(self.counts.remove(collection).unwrap_or(0))
+        }
+    }
+
+    fn room() -> UUID {
+        uuid::Uuid::from_u128(7)
+    }
+
+    #[test]
+    fn test_targets_put_memories_first_and_the_room_last() {
+        let vectors = vec!["knowledge".to_string(), "memories".to_string()];
+        let targets = purge_targets(room(), true, &vectors);
+        let names: Vec<&str> = targets.iter().map(|t| t.collection.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["memories", "knowledge", "participants", "rooms"]
+        );
+        assert_eq!(targets[0].filter, doc! { "room_id": room().to_string() });
+        assert_eq!(targets[3].filter, doc! { "_id": room().to_string() });
+
+        let targets = purge_targets(room(), false, &vectors);
+        let names: Vec<&str> = targets.iter().map(|t| t.collection.as_str()).collect();
+        assert_eq!(names, vec!["participants", "rooms"]);
+    }
+
+    #[test]
+    fn test_transaction_support_from_hello() {
+        assert!(supports_transactions(
+            &doc! { "isWritablePrimary": true, "setName": "rs0" }
+        ));
+        assert!(supports_transactions(&doc! { "msg": "isdbgrid" }));
+        assert!(!supports_transactions(&doc! { "isWritablePrimary": true }));
+    }
+
+    #[tokio::test]
+    async fn test_purge_reports_counts_and_stops_at_the_first_failure() {
+        let targets = || purge_targets(room(), true, &["knowledge".to_string()]);
+        let mut db = FakeDatabase {
+            counts: HashMap::from([
+                ("memories".to_string(), 12),
+                ("knowledge".to_string(), 4),
+                ("participants".to_string(), 2),
+                ("rooms".to_string(), 1),
+            ]),
+            ..Default::default()
+        };
+        let report = purge_room(&mut db, room(), targets(), false).await;
+        assert!(report.is_complete());
+        assert_eq!(report.deleted(), 19);
+        assert_eq!(report.collections[1].status, PurgeStatus::Purged(4));
+
+        let mut db = FakeDatabase {
+            counts: HashMap::from([("memories".to_string(), 3)]),
+            broken: vec!["knowledge".to_string()],
+            ..Default::default()
+        };
+        let report = purge_room(&mut db, room(), targets(), false).await;
+        assert!(!report.is_complete());
+        assert_eq!(report.deleted(), 3);
+        // The room is kept so the purge can be run again
+        assert_eq!(db.deleted, vec!["memories"]);
+        let statuses: Vec<_> = report
+            .collections
+            .iter()
+            .map(|c| c.status.clone())
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                PurgeStatus::Purged(3),
+                PurgeStatus::Failed("Database error: knowledge is unavailable".to_string()),
+                PurgeStatus::NotAttempted,
+                PurgeStatus::NotAttempted,
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! MongoDB-backed adapter state
+//!
+//! [`MongoStateStore`] implements [`StateStore`] on a single collection so
+//! several adapter processes can share dedup keys and voice state. Each entry
+//! is one document keyed by `{ns, key}`; the compound `_id` is what makes
+//! `set_if_absent` atomic, since a second insert of a live key fails with a
+//! duplicate-key error. Expiry is enforced on read, and a TTL index on
+//! `expires_at` lets the server reap expired documents in the background.
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, from_bson, to_bson, Bson, DateTime, Document},
+    error::{ErrorKind, WriteFailure},
+    options::IndexOptions,
+    Collection, Database, IndexModel,
+};
+use std::time::Duration;
+use zoey_core::{Result, StateStore, ZoeyError};
+
+/// Default collection holding adapter state
+pub const STATE_COLLECTION: &str = "adapter_state";
+
+/// MongoDB error code for duplicate keys
+pub(crate) const DUPLICATE_KEY: i32 = 11000;
+
+/// Shared [`StateStore`] on a MongoDB collection
+pub struct MongoStateStore {
+    collection: Collection<Document>,
+}
+
+impl MongoStateStore {
+    /// Store state in [`STATE_COLLECTION`]
+    pub fn new(db: &Database) -> Self {
+        Self::with_collection(db, STATE_COLLECTION)
+    }
+
+    /// Store state in a custom collection
+    pub fn with_collection(db: &Database, name: &str) -> Self {
+        Self {
+            collection: db.collection(name),
+        }
+    }
+
+    /// Create the TTL and namespace indexes
+    ///
+    /// Not required for correctness; without them expired documents stay on
+    /// disk and `list` scans the collection.
+    pub async fn ensure_indexes(&self) -> Result<()> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "expires_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(Duration::from_secs(0))
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder().keys(doc! { "_id.ns": 1 }).build(),
+        ];
+        self.collection
+            .create_indexes(indexes)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to create state indexes: {}", e)))?;
+        Ok(())
+    }
+}
+
+fn entry_id(namespace: &str, key: &str) -> Document {
+    doc! { "ns": namespace, "key": key }
+}
+
+/// Matches entries that have not expired
+fn live(now: DateTime) -> Document {
+    doc! { "$or": [ { "expires_at": Bson::Null }, { "expires_at": { "$gt": now } } ] }
+}
+
+fn entry_doc(
+    namespace: &str,
+    key: &str,
+    value: &serde_json::Value,
+    ttl: Option<Duration>,
+) -> Result<Document> {
+    let value = to_bson(value)
+        .map_err(|e| ZoeyError::database(format!("Failed to encode state value: {}", e)))?;
+    let mut entry = doc! { "_id": entry_id(namespace, key), "value": value };
+    if let Some(ttl) = ttl {
+        let ttl_ms = ttl.as_millis().min(i64::MAX as u128) as i64;
+        let expires_at =
+            DateTime::from_millis(DateTime::now().timestamp_millis().saturating_add(ttl_ms));
+        entry.insert("expires_at", expires_at);
+    }
+    Ok(entry)
+}
+
+fn entry_value(entry: &Document) -> Result<serde_json::Value> {
+    let value = entry.get("value").cloned().unwrap_or(Bson::Null);
+    from_bson(value)
+        .map_err(|e| ZoeyError::database(format!("Failed to decode state value: {}", e)))
+}
+
+fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Write(WriteFailure::WriteError(ref e)) if e.code == DUPLICATE_KEY
+    )
+}
+
+#[async_trait]
+impl StateStore for MongoStateStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<serde_json::Value>> {
+        let mut filter = live(DateTime::now());
+        filter.insert("_id", entry_id(namespace, key));
+        let entry = self
+            .collection
+            .find_one(filter)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to read state: {}", e)))?;
+        entry.as_ref().map(entry_value).transpose()
+    }
+
+    async fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: serde_json::Value,
+        ttl: Option<Duration>,
+    ) -> Result<()> {
+        self.collection
+            .replace_one(
+                doc! { "_id": entry_id(namespace, key) },
+                entry_doc(namespace, key, &value, ttl)?,
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to write state: {}", e)))?;
+        Ok(())
+    }
+
+    async fn set_if_absent(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: serde_json::Value,
+        ttl: Option<Duration>,
+    ) -> Result<bool> {
+        // Only an expired entry matches; a live one makes the upsert collide
+        // with it on `_id`
+        let filter = doc! {
+            "_id": entry_id(namespace, key),
+            "expires_at": { "$lte": DateTime::now() },
+        };
+        match self
+            .collection
+            .replace_one(filter, entry_doc(namespace, key, &value, ttl)?)
+            .upsert(true)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(ZoeyError::database(format!("Failed to write state: {}", e))),
+        }
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool> {
+        let mut filter = live(DateTime::now());
+        filter.insert("_id", entry_id(namespace, key));
+        let result = self
+            .collection
+            .delete_one(filter)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to delete state: {}", e)))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn list(&self, namespace: &str) -> Result<Vec<(String, serde_json::Value)>> {
+        let mut filter = live(DateTime::now());
+        filter.insert("_id.ns", namespace);
+        let entries: Vec<Document> = self
+            .collection
+            .find(filter)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to list state: {}", e)))?
+            .try_collect()
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to list state: {}", e)))?;
+        entries
+            .iter()
+            .map(|entry| {
+                let key = entry
+                    .get_document("_id")
+                    .and_then(|id| id.get_str("key"))
+                    .map_err(|e| ZoeyError::database(format!("Malformed state entry: {}", e)))?;
+                Ok((key.to_string(), entry_value(entry)?))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entry_doc_round_trip() {
+        let value = json!({ "channel": 1234567890123_u64, "tags": ["a"] });
+        let entry =
+            entry_doc("discord:voice", "42", &value, Some(Duration::from_secs(60))).unwrap();
+
+        assert_eq!(
+            entry.get_document("_id").unwrap(),
+            &entry_id("discord:voice", "42")
+        );
+        assert!(entry.get_datetime("expires_at").unwrap() > &DateTime::now());
+        assert_eq!(entry_value(&entry).unwrap(), value);
+
+        let forever = entry_doc("discord:voice", "42", &value, None).unwrap();
+        assert!(!forever.contains_key("expires_at"));
+    }
+}
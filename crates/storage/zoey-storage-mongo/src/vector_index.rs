@@ -0,0 +1,353 @@
+//! Atlas Vector Search index management
+//!
+//! [`MongoVectorSearch::ensure_indexes`](crate::MongoVectorSearch::ensure_indexes)
+//! makes sure the index a [`VectorIndexSpec`] describes is there before the
+//! first query, so a fresh Atlas cluster doesn't fail at query time with
+//! "index not found". A missing index is created with the
+//! `createSearchIndexes` command and polled until Atlas reports it `READY`.
+//! An existing index must have been built for the configured embedding
+//! dimension; otherwise the call fails with [`ZoeyError::DimensionMismatch`].
+//!
+//! Deployments without Atlas Search get the regular indexes the local
+//! aggregation pipeline filters on instead, and similarity is computed
+//! without approximate nearest neighbour (ANN) search.
+//!
+//! [`MongoAdapter::initialize`](crate::MongoAdapter) runs this at startup when
+//! its config sets `ensureVectorIndexes`.
+
+use mongodb::bson::{doc, Bson, Document};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zoey_core::{Result, ZoeyError};
+
+use crate::vector_search::AtlasVectorIndex;
+
+/// Index name used when the spec names none
+pub const DEFAULT_VECTOR_INDEX_NAME: &str = "memories_vector";
+
+/// How long [`VectorIndexSpec::default`] waits for an index to become queryable
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Interval between index status polls
+pub(crate) const READY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Field holding the embeddings
+const EMBEDDING_PATH: &str = "embedding";
+
+/// Similarity function of a vector index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VectorSimilarity {
+    /// Cosine similarity, matching the local pipeline
+    #[default]
+    Cosine,
+    /// Euclidean distance
+    Euclidean,
+    /// Dot product, for normalized embeddings
+    DotProduct,
+}
+
+impl VectorSimilarity {
+    /// Name Atlas uses in index definitions
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cosine => "cosine",
+            Self::Euclidean => "euclidean",
+            Self::DotProduct => "dotProduct",
+        }
+    }
+}
+
+/// Vector index a collection should have
+///
+/// Deserializes from camelCase JSON (`collection`, `name`, `similarity`,
+/// `filterFields`, `readyTimeoutSecs`); missing keys take the defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VectorIndexSpec {
+    /// Collection holding the embeddings
+    pub collection: String,
+    /// Search index name
+    pub name: String,
+    /// Similarity function
+    pub similarity: VectorSimilarity,
+    /// Paths declared as filter fields, usable in `$vectorSearch` filters
+    pub filter_fields: Vec<String>,
+    /// Seconds to wait for the index to become queryable
+    pub ready_timeout_secs: u64,
+}
+
+impl Default for VectorIndexSpec {
+    fn default() -> Self {
+        Self {
+            collection: "memories".to_string(),
+            name: DEFAULT_VECTOR_INDEX_NAME.to_string(),
+            similarity: VectorSimilarity::default(),
+            filter_fields: ["agent_id", "room_id", "entity_id"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            ready_timeout_secs: DEFAULT_READY_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+impl VectorIndexSpec {
+    /// Index `name` on `collection`, with the default similarity and filter fields
+    pub fn new(collection: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            collection: collection.into(),
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Use another similarity function
+    pub fn similarity(mut self, similarity: VectorSimilarity) -> Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// Declare these paths as filter fields instead of the defaults
+    pub fn filter_fields(mut self, fields: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter_fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Wait at most `timeout` for the index to become queryable
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout_secs = timeout.as_secs();
+        self
+    }
+
+    /// Time to wait for the index to become queryable
+    pub fn ready_timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.ready_timeout_secs)
+    }
+
+    /// The index for [`MongoVectorSearch::with_atlas_index`](crate::MongoVectorSearch::with_atlas_index)
+    pub fn atlas_index(&self) -> AtlasVectorIndex {
+        AtlasVectorIndex::new(self.name.clone(), self.filter_fields.clone())
+    }
+
+    /// Index definition for embeddings of `dimension`
+    pub(crate) fn definition(&self, dimension: usize) -> Document {
+        let mut fields = vec![Bson::Document(doc! {
+            "type": "vector",
+            "path": EMBEDDING_PATH,
+            "numDimensions": dimension as i64,
+            "similarity": self.similarity.as_str(),
+        })];
+        fields.extend(
+            self.filter_fields
+                .iter()
+                .map(|path| Bson::Document(doc! { "type": "filter", "path": path })),
+        );
+        doc! { "fields": fields }
+    }
+
+    /// `createSearchIndexes` command creating the index
+    pub(crate) fn create_command(&self, dimension: usize) -> Document {
+        doc! {
+            "createSearchIndexes": &self.collection,
+            "indexes": [{
+                "name": &self.name,
+                "type": "vectorSearch",
+                "definition": self.definition(dimension),
+            }],
+        }
+    }
+}
+
+/// What [`MongoVectorSearch::ensure_indexes`](crate::MongoVectorSearch::ensure_indexes) did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorIndexOutcome {
+    /// The Atlas index was created and is ready
+    Created,
+    /// The Atlas index already existed with the right dimension and is ready
+    Existing,
+    /// No Atlas Search here; the local pipeline's indexes were created instead
+    LocalOnly,
+}
+
+/// Build state reported by `$listSearchIndexes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BuildState {
+    Ready,
+    Building,
+    Failed(String),
+}
+
+/// Build state of a `$listSearchIndexes` entry
+pub(crate) fn build_state(index: &Document) -> BuildState {
+    match index.get_str("status").unwrap_or_default() {
+        "READY" => BuildState::Ready,
+        "FAILED" => BuildState::Failed(
+            index
+                .get_str("message")
+                .unwrap_or("no reason given")
+                .to_string(),
+        ),
+        // A rebuilding index keeps serving queries from its previous version
+        _ if index.get_bool("queryable").unwrap_or(false) => BuildState::Ready,
+        _ => BuildState::Building,
+    }
+}
+
+/// Dimension of the embedding field in a `$listSearchIndexes` entry
+pub(crate) fn index_dimension(index: &Document) -> Option<usize> {
+    let fields = index
+        .get_document("latestDefinition")
+        .or_else(|_| index.get_document("definition"))
+        .ok()?
+        .get_array("fields")
+        .ok()?;
+    fields
+        .iter()
+        .filter_map(Bson::as_document)
+        .find(|field| {
+            field.get_str("type") == Ok("vector") && field.get_str("path") == Ok(EMBEDDING_PATH)
+        })
+        .and_then(|field| match field.get("numDimensions") {
+            Some(Bson::Int32(d)) => Some(*d as usize),
+            Some(Bson::Int64(d)) => Some(*d as usize),
+            Some(Bson::Double(d)) => Some(*d as usize),
+            _ => None,
+        })
+}
+
+/// Check an existing index was built for `dimension`-dimension embeddings of `model`
+///
+/// A mismatch is [`ZoeyError::DimensionMismatch`] with the index's dimension
+/// as `stored_dimension` and the configured one as `dimension`.
+pub(crate) fn check_index_dimension(
+    spec: &VectorIndexSpec,
+    index: &Document,
+    model: &str,
+    dimension: usize,
+) -> Result<()> {
+    match index_dimension(index) {
+        Some(found) if found == dimension => Ok(()),
+        Some(found) => Err(ZoeyError::dimension_mismatch(
+            &spec.collection,
+            format!("index {}", spec.name),
+            found,
+            model,
+            dimension,
+        )),
+        None => Err(ZoeyError::config(format!(
+            "Search index '{}' on '{}' has no vector field on '{}'; drop it or configure another index name",
+            spec.name, spec.collection, EMBEDDING_PATH
+        ))),
+    }
+}
+
+/// Whether `createSearchIndexes` failed because the index was created meanwhile
+pub(crate) fn already_exists(message: &str) -> bool {
+    message.contains("already exists") || message.contains("Duplicate Index")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(status: &str, dimension: i32) -> Document {
+        doc! {
+            "name": "memories_vector",
+            "type": "vectorSearch",
+            "status": status,
+            "queryable": status == "READY",
+            "latestDefinition": { "fields": [
+                { "type": "filter", "path": "room_id" },
+                { "type": "vector", "path": "embedding", "numDimensions": dimension, "similarity": "cosine" },
+            ] },
+        }
+    }
+
+    #[test]
+    fn test_create_command() {
+        let spec = VectorIndexSpec::new("memories", "memories_vector")
+            .similarity(VectorSimilarity::DotProduct)
+            .filter_fields(["room_id", "metadata.case_id"]);
+        assert_eq!(
+            spec.create_command(1536),
+            doc! {
+                "createSearchIndexes": "memories",
+                "indexes": [{
+                    "name": "memories_vector",
+                    "type": "vectorSearch",
+                    "definition": { "fields": [
+                        { "type": "vector", "path": "embedding", "numDimensions": 1536_i64, "similarity": "dotProduct" },
+                        { "type": "filter", "path": "room_id" },
+                        { "type": "filter", "path": "metadata.case_id" },
+                    ] },
+                }],
+            }
+        );
+        let atlas = spec.atlas_index();
+        assert_eq!(atlas.name, "memories_vector");
+        assert_eq!(atlas.filter_fields, ["room_id", "metadata.case_id"]);
+    }
+
+    #[test]
+    fn test_spec_from_config() {
+        let spec: VectorIndexSpec = serde_json::from_value(serde_json::json!({
+            "name": "docs_vector",
+            "similarity": "euclidean",
+            "readyTimeoutSecs": 30,
+        }))
+        .unwrap();
+        assert_eq!(spec.collection, "memories");
+        assert_eq!(spec.name, "docs_vector");
+        assert_eq!(spec.similarity, VectorSimilarity::Euclidean);
+        assert_eq!(spec.filter_fields, VectorIndexSpec::default().filter_fields);
+        assert_eq!(spec.ready_timeout_duration(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_build_state() {
+        assert_eq!(build_state(&listed("READY", 3)), BuildState::Ready);
+        assert_eq!(build_state(&listed("PENDING", 3)), BuildState::Building);
+        assert_eq!(build_state(&listed("BUILDING", 3)), BuildState::Building);
+        let rebuilding = doc! { "status": "BUILDING", "queryable": true };
+        assert_eq!(build_state(&rebuilding), BuildState::Ready);
+        let failed = doc! { "status": "FAILED", "message": "bad definition" };
+        assert_eq!(
+            build_state(&failed),
+            BuildState::Failed("bad definition".to_string())
+        );
+    }
+
+    #[test]
+    fn test_index_dimension_checked() {
+        let spec = VectorIndexSpec::default();
+        assert_eq!(index_dimension(&listed("READY", 1536)), Some(1536));
+        assert!(check_index_dimension(&spec, &listed("READY", 1536), "ada-002", 1536).is_ok());
+
+        match check_index_dimension(&spec, &listed("READY", 768), "ada-002", 1536) {
+            Err(ZoeyError::DimensionMismatch {
+                stored_dimension,
+                dimension,
+                ..
+            }) => {
+                assert_eq!(stored_dimension, 768);
+                assert_eq!(dimension, 1536);
+            }
+            other => panic!("expected a dimension mismatch, got {:?}", other),
+        }
+
+        let keyword_only = doc! { "latestDefinition": { "mappings": { "dynamic": true } } };
+        assert!(matches!(
+            check_index_dimension(&spec, &keyword_only, "ada-002", 1536),
+            Err(ZoeyError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_already_exists() {
+        assert!(already_exists(
+            "Command failed: Error code 68 (IndexAlreadyExists): Index memories_vector already exists"
+        ));
+        assert!(!already_exists("connection reset by peer"));
+    }
+}
@@ -3,18 +3,171 @@
 //! This module implements vector similarity search using MongoDB's aggregation
 //! pipeline with manual cosine similarity calculation. Works with any MongoDB
 //! instance (local or hosted) without requiring Atlas Search.
+//!
+//! The embedding model and dimension each collection was built with are
+//! recorded in the `vector_collections` collection on first use and checked
+//! before upserts and queries, so switching embedding models fails with
+//! [`ZoeyError::DimensionMismatch`] instead of silently matching nothing.
+//!
+//! [`MongoVectorSearch::search_with_filter`] restricts a query to a room,
+//! entity or metadata values on the server. With an [`AtlasVectorIndex`]
+//! configured the filter goes into the `$vectorSearch` stage; otherwise it
+//! joins the `$match` stage of the local pipeline.
+//!
+//! [`MongoVectorSearch::hybrid_search`] adds a keyword query to the vector
+//! query and fuses the two rankings; see [`crate::hybrid`].
+//!
+//! [`MongoVectorSearch::ensure_indexes`] creates and checks the Atlas index
+//! before first use; see [`crate::vector_index`].
 
+use async_trait::async_trait;
 use mongodb::{
-    bson::{doc, Bson, Document},
+    bson::{doc, to_bson, Bson, Document},
     Collection, Database, IndexModel,
 };
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 use tracing::{info, warn};
 use zoey_core::{types::*, Result, ZoeyError};
 
+use crate::hybrid::{self, HybridSearchOptions};
+use crate::vector_index::{self, BuildState, VectorIndexOutcome, VectorIndexSpec};
+
+/// Collection holding one metadata document per vector collection
+pub const METADATA_COLLECTION: &str = "vector_collections";
+
+/// Model name recorded when none is configured
+const UNSPECIFIED_MODEL: &str = "unspecified";
+
+/// Candidates considered by `$vectorSearch` per requested result
+const NUM_CANDIDATES_PER_RESULT: usize = 10;
+
+/// Atlas Vector Search index used for filtered queries
+#[derive(Debug, Clone)]
+pub struct AtlasVectorIndex {
+    /// Search index name
+    pub name: String,
+    /// Paths declared with `"type": "filter"` in the index definition
+    pub filter_fields: Vec<String>,
+}
+
+impl AtlasVectorIndex {
+    /// Describe an index and the filter paths it defines
+    pub fn new(
+        name: impl Into<String>,
+        filter_fields: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            filter_fields: filter_fields.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Equality filter applied on the server before similarity ranking
+///
+/// Metadata keys address fields under `metadata`, so `.metadata("case_id", "42")`
+/// matches documents whose `metadata.case_id` is `"42"`. Nested keys use dots.
+#[derive(Debug, Clone, Default)]
+pub struct VectorFilter {
+    room_id: Option<uuid::Uuid>,
+    entity_id: Option<uuid::Uuid>,
+    metadata: Vec<(String, Bson)>,
+}
+
+impl VectorFilter {
+    /// Create an empty filter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match memories in this room
+    pub fn room(mut self, room_id: uuid::Uuid) -> Self {
+        self.room_id = Some(room_id);
+        self
+    }
+
+    /// Only match memories from this entity
+    pub fn entity(mut self, entity_id: uuid::Uuid) -> Self {
+        self.entity_id = Some(entity_id);
+        self
+    }
+
+    /// Only match memories whose metadata has this value under `key`
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Whether the filter matches every document
+    pub fn is_empty(&self) -> bool {
+        self.room_id.is_none() && self.entity_id.is_none() && self.metadata.is_empty()
+    }
+
+    /// Document paths and the values they must equal
+    fn conditions(&self) -> Result<Vec<(String, Bson)>> {
+        let mut conditions = Vec::new();
+        if let Some(room_id) = self.room_id {
+            conditions.push(("room_id".to_string(), Bson::String(room_id.to_string())));
+        }
+        if let Some(entity_id) = self.entity_id {
+            conditions.push(("entity_id".to_string(), Bson::String(entity_id.to_string())));
+        }
+        for (key, value) in &self.metadata {
+            if key.split('.').any(|part| part.is_empty() || part.starts_with('$')) {
+                return Err(ZoeyError::validation(format!(
+                    "Invalid metadata filter key '{}'",
+                    key
+                )));
+            }
+            conditions.push((format!("metadata.{}", key), value.clone()));
+        }
+        Ok(conditions)
+    }
+
+    /// Conditions for a `$match` stage
+    pub fn to_match(&self) -> Result<Document> {
+        let mut filter = Document::new();
+        for (path, value) in self.conditions()? {
+            filter.insert(path, value);
+        }
+        Ok(filter)
+    }
+
+    /// `filter` clause for `$vectorSearch`, or `None` when the filter is empty
+    ///
+    /// A path the index does not declare as a filter field is rejected before
+    /// the query is sent.
+    pub fn to_vector_search_filter(&self, index: &AtlasVectorIndex) -> Result<Option<Document>> {
+        let mut clauses = Vec::new();
+        for (path, value) in self.conditions()? {
+            if !index.filter_fields.contains(&path) {
+                return Err(ZoeyError::config(format!(
+                    "Vector index '{}' does not define filter field '{}'; add it to the index definition as {{ \"type\": \"filter\", \"path\": \"{}\" }}",
+                    index.name, path, path
+                )));
+            }
+            clauses.push(Bson::Document(doc! { path: { "$eq": value } }));
+        }
+        Ok(match clauses.len() {
+            0 => None,
+            1 => clauses.pop().and_then(|c| c.as_document().cloned()),
+            _ => Some(doc! { "$and": clauses }),
+        })
+    }
+}
+
 /// MongoDB Vector Search operations using local aggregation-based similarity
 pub struct MongoVectorSearch {
     db: Database,
     embedding_dimension: usize,
+    embedding_model: Option<String>,
+    atlas_index: Option<AtlasVectorIndex>,
+    /// Collections already checked against their metadata document
+    verified: RwLock<HashSet<String>>,
+    /// Set once `$search` failed for lack of Atlas Search; keyword queries use regex after that
+    atlas_search_unavailable: AtomicBool,
 }
 
 impl MongoVectorSearch {
@@ -23,14 +176,121 @@ impl MongoVectorSearch {
         Self {
             db,
             embedding_dimension,
+            embedding_model: None,
+            atlas_index: None,
+            verified: RwLock::new(HashSet::new()),
+            atlas_search_unavailable: AtomicBool::new(false),
         }
     }
 
+    /// Name the embedding model recorded with each collection
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
+
+    /// Run filtered searches through an Atlas Vector Search index
+    pub fn with_atlas_index(mut self, index: AtlasVectorIndex) -> Self {
+        self.atlas_index = Some(index);
+        self
+    }
+
     /// Get the configured embedding dimension
     pub fn embedding_dimension(&self) -> usize {
         self.embedding_dimension
     }
 
+    fn model_name(&self) -> &str {
+        self.embedding_model.as_deref().unwrap_or(UNSPECIFIED_MODEL)
+    }
+
+    /// Check a collection against its recorded model and dimension, recording them if absent
+    ///
+    /// Collections that predate the metadata are checked against one of their
+    /// stored embeddings. Each collection is only checked once per process.
+    pub async fn ensure_collection_metadata(&self, collection_name: &str) -> Result<()> {
+        if self
+            .verified
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(collection_name)
+        {
+            return Ok(());
+        }
+
+        let metadata: Collection<Document> = self.db.collection(METADATA_COLLECTION);
+        let recorded = metadata
+            .find_one(doc! { "_id": collection_name })
+            .await
+            .map_err(|e| {
+                ZoeyError::database(format!("Failed to read vector collection metadata: {}", e))
+            })?;
+
+        match recorded {
+            Some(recorded) => check_recorded(
+                collection_name,
+                &recorded,
+                self.embedding_model.as_deref(),
+                self.embedding_dimension,
+            )?,
+            None => {
+                let collection: Collection<Document> = self.db.collection(collection_name);
+                let sample = collection
+                    .find_one(doc! { "embedding": { "$exists": true, "$ne": null } })
+                    .await
+                    .map_err(|e| {
+                        ZoeyError::database(format!("Failed to sample stored embeddings: {}", e))
+                    })?;
+                let stored_dimension = sample
+                    .as_ref()
+                    .and_then(|d| d.get_array("embedding").ok())
+                    .map(|a| a.len());
+                if let Some(stored_dimension) = stored_dimension {
+                    if stored_dimension != self.embedding_dimension {
+                        return Err(ZoeyError::dimension_mismatch(
+                            collection_name,
+                            UNSPECIFIED_MODEL,
+                            stored_dimension,
+                            self.model_name(),
+                            self.embedding_dimension,
+                        ));
+                    }
+                }
+
+                // $setOnInsert keeps whatever another process recorded first
+                metadata
+                    .update_one(
+                        doc! { "_id": collection_name },
+                        doc! { "$setOnInsert": {
+                            "model": self.model_name(),
+                            "dimension": self.embedding_dimension as i64,
+                            "created_at": chrono::Utc::now().timestamp(),
+                        } },
+                    )
+                    .upsert(true)
+                    .await
+                    .map_err(|e| {
+                        ZoeyError::database(format!(
+                            "Failed to record vector collection metadata: {}",
+                            e
+                        ))
+                    })?;
+                info!(
+                    "Recorded embedding model '{}' ({} dimensions) for collection '{}'",
+                    self.model_name(),
+                    self.embedding_dimension,
+                    collection_name
+                );
+            }
+        }
+
+        self.verified
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(collection_name.to_string());
+        Ok(())
+    }
+
     /// Create indexes to optimize vector search queries
     ///
     /// Creates compound indexes on common filter fields to speed up the
@@ -68,6 +328,118 @@ impl MongoVectorSearch {
         Ok(())
     }
 
+    /// Make sure the Atlas Vector Search index in `spec` exists and is ready
+    ///
+    /// A missing index is created for the configured embedding dimension and
+    /// polled until queryable, failing with [`ZoeyError::Timeout`] after
+    /// [`VectorIndexSpec::ready_timeout`]. An existing index built for another
+    /// dimension fails with [`ZoeyError::DimensionMismatch`]. Without Atlas
+    /// Search, the indexes from [`Self::create_vector_index`] are created instead.
+    pub async fn ensure_indexes(&self, spec: VectorIndexSpec) -> Result<VectorIndexOutcome> {
+        // $listSearchIndexes fails on a collection that doesn't exist yet
+        self.db.create_collection(&spec.collection).await.ok();
+
+        let existing = match self.find_search_index(&spec).await {
+            Ok(existing) => existing,
+            Err(e) if hybrid::search_unavailable(&e.to_string()) => {
+                warn!(
+                    "Atlas Search is unavailable ({}); creating regular indexes on '{}', vector queries run without ANN",
+                    e, spec.collection
+                );
+                self.create_vector_index(&spec.collection).await?;
+                return Ok(VectorIndexOutcome::LocalOnly);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let outcome = match existing {
+            Some(index) => {
+                vector_index::check_index_dimension(
+                    &spec,
+                    &index,
+                    self.model_name(),
+                    self.embedding_dimension,
+                )?;
+                VectorIndexOutcome::Existing
+            }
+            None => {
+                let command = spec.create_command(self.embedding_dimension);
+                match self.db.run_command(command).await {
+                    Ok(_) => info!(
+                        "Creating vector search index '{}' on '{}' ({} dimensions, {})",
+                        spec.name,
+                        spec.collection,
+                        self.embedding_dimension,
+                        spec.similarity.as_str()
+                    ),
+                    // Another process created it between the lookup and here
+                    Err(e) if vector_index::already_exists(&e.to_string()) => {}
+                    Err(e) => {
+                        return Err(ZoeyError::database(format!(
+                            "Failed to create vector search index '{}': {}",
+                            spec.name, e
+                        )))
+                    }
+                }
+                VectorIndexOutcome::Created
+            }
+        };
+
+        let index = self.wait_until_ready(&spec).await?;
+        vector_index::check_index_dimension(
+            &spec,
+            &index,
+            self.model_name(),
+            self.embedding_dimension,
+        )?;
+        info!(
+            "Vector search index '{}' on '{}' is ready",
+            spec.name, spec.collection
+        );
+        Ok(outcome)
+    }
+
+    /// The `$listSearchIndexes` entry for the index in `spec`, if it exists
+    async fn find_search_index(&self, spec: &VectorIndexSpec) -> Result<Option<Document>> {
+        use futures::TryStreamExt;
+
+        let collection: Collection<Document> = self.db.collection(&spec.collection);
+        let mut cursor = collection
+            .aggregate([doc! { "$listSearchIndexes": { "name": &spec.name } }])
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to list search indexes: {}", e)))?;
+        cursor
+            .try_next()
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to list search indexes: {}", e)))
+    }
+
+    /// Poll the index in `spec` until it is queryable
+    async fn wait_until_ready(&self, spec: &VectorIndexSpec) -> Result<Document> {
+        let deadline = tokio::time::Instant::now() + spec.ready_timeout_duration();
+        loop {
+            if let Some(index) = self.find_search_index(spec).await? {
+                match vector_index::build_state(&index) {
+                    BuildState::Ready => return Ok(index),
+                    BuildState::Failed(reason) => {
+                        return Err(ZoeyError::database(format!(
+                            "Vector search index '{}' on '{}' failed to build: {}",
+                            spec.name, spec.collection, reason
+                        )))
+                    }
+                    BuildState::Building => {}
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(ZoeyError::timeout(format!(
+                    "Vector search index '{}' on '{}' not ready after {}s",
+                    spec.name, spec.collection, spec.ready_timeout_secs
+                )));
+            }
+            tokio::time::sleep(vector_index::READY_POLL_INTERVAL).await;
+        }
+    }
+
     /// Search memories by embedding similarity using cosine similarity
     ///
     /// Uses MongoDB aggregation pipeline to compute cosine similarity:
@@ -86,6 +458,7 @@ impl MongoVectorSearch {
                 self.embedding_dimension,
             ));
         }
+        self.ensure_collection_metadata(&params.table_name).await?;
 
         // Convert query embedding to BSON array
         let query_embedding: Vec<Bson> = params
@@ -262,6 +635,7 @@ impl MongoVectorSearch {
                 self.embedding_dimension,
             ));
         }
+        self.ensure_collection_metadata(collection_name).await?;
 
         let collection: Collection<Document> = self.db.collection(collection_name);
 
@@ -279,12 +653,52 @@ impl MongoVectorSearch {
         Ok(())
     }
 
+    /// Insert or replace a memory document, including its embedding
+    pub async fn upsert_memory(&self, collection_name: &str, memory: &Memory) -> Result<()> {
+        if let Some(ref embedding) = memory.embedding {
+            if embedding.len() != self.embedding_dimension {
+                return Err(ZoeyError::vector_search(
+                    "Embedding dimension mismatch",
+                    embedding.len(),
+                    self.embedding_dimension,
+                ));
+            }
+        }
+        self.ensure_collection_metadata(collection_name).await?;
+
+        let collection: Collection<Document> = self.db.collection(collection_name);
+
+        // Same layout as `MongoAdapter::create_memory`
+        let document = doc! {
+            "_id": memory.id.to_string(),
+            "entity_id": memory.entity_id.to_string(),
+            "agent_id": memory.agent_id.to_string(),
+            "room_id": memory.room_id.to_string(),
+            "content": to_bson(&memory.content).unwrap_or(Bson::Document(doc! {})),
+            "embedding": memory.embedding.as_ref().map(|e| {
+                Bson::Array(e.iter().map(|&v| Bson::Double(v as f64)).collect())
+            }),
+            "metadata": memory.metadata.as_ref().map(|m| to_bson(m).unwrap_or(Bson::Document(doc! {}))),
+            "created_at": memory.created_at,
+            "unique_flag": memory.unique.unwrap_or(false),
+        };
+
+        collection
+            .replace_one(doc! { "_id": memory.id.to_string() }, document)
+            .upsert(true)
+            .await
+            .map_err(|e| ZoeyError::database(format!("Failed to upsert memory: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Batch add embeddings to multiple documents
     pub async fn batch_add_embeddings(
         &self,
         collection_name: &str,
         embeddings: Vec<(uuid::Uuid, Vec<f32>)>,
     ) -> Result<()> {
+        self.ensure_collection_metadata(collection_name).await?;
         let collection: Collection<Document> = self.db.collection(collection_name);
 
         // Process in batches for efficiency
@@ -322,6 +736,7 @@ impl MongoVectorSearch {
         count: usize,
         threshold: Option<f32>,
     ) -> Result<Vec<Memory>> {
+        self.ensure_collection_metadata(collection_name).await?;
         let collection: Collection<Document> = self.db.collection(collection_name);
 
         // First, get the source memory's embedding
@@ -477,6 +892,14 @@ impl MongoVectorSearch {
         threshold: Option<f32>,
         filters: Option<Document>,
     ) -> Result<Vec<Memory>> {
+        if embedding.len() != self.embedding_dimension {
+            return Err(ZoeyError::vector_search(
+                "Embedding dimension mismatch",
+                embedding.len(),
+                self.embedding_dimension,
+            ));
+        }
+        self.ensure_collection_metadata(collection_name).await?;
         let collection: Collection<Document> = self.db.collection(collection_name);
 
         // Convert query embedding to BSON array
@@ -603,6 +1026,333 @@ impl MongoVectorSearch {
 
         Ok(memories)
     }
+
+    /// Search the `k` most similar memories that match `filter`
+    ///
+    /// The filter is applied by the server before ranking, so memories from
+    /// other rooms or cases never compete for the `k` slots.
+    pub async fn search_with_filter(
+        &self,
+        collection_name: &str,
+        embedding: &[f32],
+        k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<Memory>> {
+        let Some(index) = &self.atlas_index else {
+            return self
+                .search_with_precomputed(
+                    collection_name,
+                    embedding,
+                    self.query_magnitude(embedding),
+                    k,
+                    None,
+                    Some(filter.to_match()?),
+                )
+                .await;
+        };
+
+        if embedding.len() != self.embedding_dimension {
+            return Err(ZoeyError::vector_search(
+                "Embedding dimension mismatch",
+                embedding.len(),
+                self.embedding_dimension,
+            ));
+        }
+        let vector_filter = filter.to_vector_search_filter(index)?;
+        self.ensure_collection_metadata(collection_name).await?;
+        let collection: Collection<Document> = self.db.collection(collection_name);
+
+        let query_embedding: Vec<Bson> = embedding.iter().map(|&v| Bson::Double(v as f64)).collect();
+        let mut vector_search = doc! {
+            "index": &index.name,
+            "path": "embedding",
+            "queryVector": query_embedding,
+            "numCandidates": (k * NUM_CANDIDATES_PER_RESULT) as i64,
+            "limit": k as i64,
+        };
+        if let Some(vector_filter) = vector_filter {
+            vector_search.insert("filter", vector_filter);
+        }
+
+        let pipeline = vec![
+            doc! { "$vectorSearch": vector_search },
+            doc! {
+                "$project": {
+                    "_id": 1,
+                    "entity_id": 1,
+                    "agent_id": 1,
+                    "room_id": 1,
+                    "content": 1,
+                    "metadata": 1,
+                    "created_at": 1,
+                    "unique_flag": 1,
+                    "similarity": { "$meta": "vectorSearchScore" }
+                }
+            },
+        ];
+
+        let mut cursor = collection
+            .aggregate(pipeline)
+            .await
+            .map_err(|e| atlas_search_error(&index.name, &e.to_string()))?;
+
+        let mut memories = Vec::new();
+        use futures::TryStreamExt;
+
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| atlas_search_error(&index.name, &e.to_string()))?
+        {
+            let memory = Memory {
+                id: parse_uuid_from_doc(&doc, "_id")?,
+                entity_id: parse_uuid_from_doc(&doc, "entity_id")?,
+                agent_id: parse_uuid_from_doc(&doc, "agent_id")?,
+                room_id: parse_uuid_from_doc(&doc, "room_id")?,
+                content: mongodb::bson::from_bson(
+                    doc.get("content")
+                        .cloned()
+                        .unwrap_or(mongodb::bson::Bson::Document(doc! {})),
+                )
+                .unwrap_or_default(),
+                embedding: None,
+                metadata: doc
+                    .get("metadata")
+                    .and_then(|b| mongodb::bson::from_bson(b.clone()).ok()),
+                created_at: doc.get_i64("created_at").unwrap_or(0),
+                unique: doc.get_bool("unique_flag").ok(),
+                similarity: doc.get_f64("similarity").ok().map(|s| s as f32),
+            };
+            memories.push(memory);
+        }
+
+        info!(
+            "Found {} memories via Atlas vector search in '{}'",
+            memories.len(),
+            collection_name
+        );
+
+        Ok(memories)
+    }
+
+    /// Search the `k` best memories by keyword and vector rank combined
+    ///
+    /// The keyword and vector queries run in parallel and their rankings are
+    /// merged with reciprocal rank fusion; a memory found by both appears
+    /// once. See [`crate::hybrid`] for the scoring and tie-breaking.
+    pub async fn hybrid_search(
+        &self,
+        collection_name: &str,
+        query_text: &str,
+        embedding: &[f32],
+        k: usize,
+        options: &HybridSearchOptions,
+    ) -> Result<Vec<Memory>> {
+        options.validate()?;
+        let candidates = hybrid::candidates(k);
+        let (vector, keyword) = futures::join!(
+            self.search_with_filter(collection_name, embedding, candidates, options.filter_ref()),
+            self.keyword_search(collection_name, query_text, candidates, options),
+        );
+        let (vector, keyword) = (vector?, keyword?);
+
+        let (vector_count, keyword_count) = (vector.len(), keyword.len());
+        let memories = hybrid::fuse(vector, keyword, options, k);
+        info!(
+            "Found {} memories via hybrid search in '{}' ({} vector, {} keyword candidates)",
+            memories.len(),
+            collection_name,
+            vector_count,
+            keyword_count
+        );
+        Ok(memories)
+    }
+
+    /// Keyword leg of a hybrid search: memories with their keyword scores, best first
+    async fn keyword_search(
+        &self,
+        collection_name: &str,
+        query_text: &str,
+        limit: usize,
+        options: &HybridSearchOptions,
+    ) -> Result<Vec<(Memory, f64)>> {
+        if query_text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let collection: Collection<Document> = self.db.collection(collection_name);
+
+        if !self.atlas_search_unavailable.load(Ordering::Relaxed) {
+            let pipeline = hybrid::search_pipeline(query_text, options, limit)?;
+            match collect_keyword_hits(&collection, pipeline).await {
+                Ok(hits) => return Ok(hits),
+                Err(e) if hybrid::search_unavailable(&e.to_string()) => {
+                    if !self.atlas_search_unavailable.swap(true, Ordering::Relaxed) {
+                        warn!(
+                            "Atlas Search is unavailable ({}); hybrid keyword queries fall back to regex matching",
+                            e
+                        );
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        match hybrid::regex_pipeline(query_text, options, limit)? {
+            Some(pipeline) => collect_keyword_hits(&collection, pipeline).await,
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Run a keyword pipeline, reading each memory with its `keyword_score`
+async fn collect_keyword_hits(
+    collection: &Collection<Document>,
+    pipeline: Vec<Document>,
+) -> Result<Vec<(Memory, f64)>> {
+    use futures::TryStreamExt;
+
+    let mut cursor = collection
+        .aggregate(pipeline)
+        .await
+        .map_err(|e| ZoeyError::database(format!("Keyword search failed: {}", e)))?;
+    let mut hits = Vec::new();
+    while let Some(doc) = cursor.try_next().await.map_err(|e| {
+        ZoeyError::database(format!("Failed to iterate keyword results: {}", e))
+    })? {
+        let score = match doc.get("keyword_score") {
+            Some(Bson::Double(score)) => *score,
+            Some(Bson::Int32(score)) => *score as f64,
+            Some(Bson::Int64(score)) => *score as f64,
+            _ => 0.0,
+        };
+        let memory = Memory {
+            id: parse_uuid_from_doc(&doc, "_id")?,
+            entity_id: parse_uuid_from_doc(&doc, "entity_id")?,
+            agent_id: parse_uuid_from_doc(&doc, "agent_id")?,
+            room_id: parse_uuid_from_doc(&doc, "room_id")?,
+            content: mongodb::bson::from_bson(
+                doc.get("content")
+                    .cloned()
+                    .unwrap_or(mongodb::bson::Bson::Document(doc! {})),
+            )
+            .unwrap_or_default(),
+            embedding: None,
+            metadata: doc
+                .get("metadata")
+                .and_then(|b| mongodb::bson::from_bson(b.clone()).ok()),
+            created_at: doc.get_i64("created_at").unwrap_or(0),
+            unique: doc.get_bool("unique_flag").ok(),
+            similarity: None,
+        };
+        hits.push((memory, score));
+    }
+    Ok(hits)
+}
+
+#[async_trait]
+impl IVectorSearch for MongoVectorSearch {
+    fn embedding_dimension(&self) -> usize {
+        self.embedding_dimension
+    }
+
+    fn embedding_model(&self) -> Option<String> {
+        self.embedding_model.clone()
+    }
+
+    async fn verify_collection(&self, collection: &str) -> Result<()> {
+        self.ensure_collection_metadata(collection).await
+    }
+
+    async fn search_by_embedding(&self, params: SearchMemoriesParams) -> Result<Vec<Memory>> {
+        MongoVectorSearch::search_by_embedding(self, params).await
+    }
+
+    async fn upsert_memory(&self, collection: &str, memory: &Memory) -> Result<()> {
+        MongoVectorSearch::upsert_memory(self, collection, memory).await
+    }
+
+    async fn add_embedding(
+        &self,
+        collection: &str,
+        memory_id: uuid::Uuid,
+        embedding: Vec<f32>,
+    ) -> Result<()> {
+        MongoVectorSearch::add_embedding(self, collection, memory_id, embedding).await
+    }
+
+    async fn batch_add_embeddings(
+        &self,
+        collection: &str,
+        embeddings: Vec<(uuid::Uuid, Vec<f32>)>,
+    ) -> Result<()> {
+        MongoVectorSearch::batch_add_embeddings(self, collection, embeddings).await
+    }
+
+    async fn get_similar_memories(
+        &self,
+        collection: &str,
+        memory_id: uuid::Uuid,
+        count: usize,
+        threshold: Option<f32>,
+    ) -> Result<Vec<Memory>> {
+        MongoVectorSearch::get_similar_memories(self, collection, memory_id, count, threshold).await
+    }
+}
+
+/// Compare a collection's metadata document with the model and dimension in use
+///
+/// A different dimension is an error. A different model with the same
+/// dimension only warns, since providers serve some models under several names.
+fn check_recorded(
+    collection: &str,
+    recorded: &Document,
+    model: Option<&str>,
+    dimension: usize,
+) -> Result<()> {
+    let stored_model = recorded.get_str("model").unwrap_or(UNSPECIFIED_MODEL);
+    let stored_dimension = match recorded.get("dimension") {
+        Some(Bson::Int64(d)) => *d as usize,
+        Some(Bson::Int32(d)) => *d as usize,
+        _ => {
+            return Err(ZoeyError::database(format!(
+                "Vector collection metadata for '{}' has no dimension",
+                collection
+            )))
+        }
+    };
+    if stored_dimension != dimension {
+        return Err(ZoeyError::dimension_mismatch(
+            collection,
+            stored_model,
+            stored_dimension,
+            model.unwrap_or(UNSPECIFIED_MODEL),
+            dimension,
+        ));
+    }
+    if let Some(model) = model {
+        if stored_model != UNSPECIFIED_MODEL && stored_model != model {
+            warn!(
+                "Collection '{}' was embedded with '{}' but '{}' is configured",
+                collection, stored_model, model
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Map a `$vectorSearch` failure, calling out filter paths missing from the index
+///
+/// `filter_fields` can drift from the deployed index definition; Atlas then
+/// rejects the query with "Path '...' needs to be indexed as filter".
+fn atlas_search_error(index: &str, message: &str) -> ZoeyError {
+    if message.contains("needs to be indexed") {
+        ZoeyError::config(format!(
+            "Vector index '{}' does not define a field used in the filter: {}",
+            index, message
+        ))
+    } else {
+        ZoeyError::database(format!("Vector search failed: {}", message))
+    }
 }
 
 /// Helper function to parse UUID from BSON document
@@ -633,6 +1383,83 @@ mod tests {
         assert!((magnitude - 5.0).abs() < 0.0001);
     }
 
+    #[test]
+    fn test_check_recorded_dimension() {
+        let recorded = doc! {
+            "_id": "memories",
+            "model": "text-embedding-3-small",
+            "dimension": 1536_i64,
+        };
+        assert!(
+            check_recorded("memories", &recorded, Some("text-embedding-3-small"), 1536).is_ok()
+        );
+        // Same dimension under another model name only warns
+        assert!(check_recorded("memories", &recorded, Some("ada-002"), 1536).is_ok());
+
+        match check_recorded("memories", &recorded, Some("nomic-embed-text"), 768) {
+            Err(ZoeyError::DimensionMismatch {
+                stored_model,
+                stored_dimension,
+                model,
+                dimension,
+                ..
+            }) => {
+                assert_eq!(stored_model, "text-embedding-3-small");
+                assert_eq!(stored_dimension, 1536);
+                assert_eq!(model, "nomic-embed-text");
+                assert_eq!(dimension, 768);
+            }
+            other => panic!("expected a dimension mismatch, got {:?}", other),
+        }
+
+        let unnamed = doc! { "_id": "memories", "dimension": 768_i32 };
+        assert!(check_recorded("memories", &unnamed, None, 768).is_ok());
+        assert!(check_recorded("memories", &doc! { "_id": "memories" }, None, 768).is_err());
+    }
+
+    #[test]
+    fn test_vector_filter_translation() {
+        let room = uuid::Uuid::new_v4();
+        let filter = VectorFilter::new().room(room).metadata("case_id", "42");
+
+        assert_eq!(
+            filter.to_match().unwrap(),
+            doc! { "room_id": room.to_string(), "metadata.case_id": "42" }
+        );
+
+        let index = AtlasVectorIndex::new("memories_vector", ["room_id", "metadata.case_id"]);
+        assert_eq!(
+            filter.to_vector_search_filter(&index).unwrap(),
+            Some(doc! { "$and": [
+                { "room_id": { "$eq": room.to_string() } },
+                { "metadata.case_id": { "$eq": "42" } },
+            ] })
+        );
+        assert_eq!(
+            VectorFilter::new()
+                .room(room)
+                .to_vector_search_filter(&index)
+                .unwrap(),
+            Some(doc! { "room_id": { "$eq": room.to_string() } })
+        );
+        assert_eq!(VectorFilter::new().to_vector_search_filter(&index).unwrap(), None);
+
+        // Unindexed paths fail instead of returning unfiltered results
+        let entity_filter = VectorFilter::new().entity(uuid::Uuid::new_v4());
+        match entity_filter.to_vector_search_filter(&index) {
+            Err(ZoeyError::Config(msg)) => assert!(msg.contains("'entity_id'")),
+            other => panic!("expected a config error, got {:?}", other),
+        }
+        assert!(matches!(
+            atlas_search_error("memories_vector", "Path 'entity_id' needs to be indexed as filter"),
+            ZoeyError::Config(_)
+        ));
+
+        for key in ["$where", "case.$gt", "", "case..id"] {
+            assert!(VectorFilter::new().metadata(key, 1).to_match().is_err());
+        }
+    }
+
     #[test]
     fn test_cosine_similarity_logic() {
         // Vectors: [1, 0] and [1, 0] should have similarity 1.0
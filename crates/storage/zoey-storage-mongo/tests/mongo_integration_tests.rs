@@ -6,7 +6,7 @@
 //! Run with: cargo test -p zoey-storage-mongo --test mongo_integration_tests -- --ignored
 
 use zoey_core::types::*;
-use zoey_storage_mongo::MongoAdapter;
+use zoey_storage_mongo::{AtlasVectorIndex, MongoAdapter, MongoVectorSearch, VectorFilter};
 
 async fn setup_adapter() -> Option<MongoAdapter> {
     let mongodb_url = std::env::var("MONGODB_URL").ok()?;
@@ -148,6 +148,231 @@ async fn test_memory_crud() {
     assert!(deleted);
 }
 
+#[tokio::test]
+#[ignore = "Requires MongoDB instance"]
+async fn test_memory_pagination() {
+    let Some(adapter) = setup_adapter().await else {
+        eprintln!("Skipping test - MongoDB not available");
+        return;
+    };
+
+    let agent_id = uuid::Uuid::new_v4();
+    let room_id = uuid::Uuid::new_v4();
+
+    // Five memories, two sharing a timestamp
+    for (i, created_at) in [100, 200, 200, 300, 400].into_iter().enumerate() {
+        let memory = Memory {
+            id: uuid::Uuid::new_v4(),
+            entity_id: agent_id,
+            agent_id,
+            room_id,
+            content: Content {
+                text: format!("message {}", i),
+                ..Default::default()
+            },
+            embedding: None,
+            metadata: None,
+            created_at,
+            unique: Some(false),
+            similarity: None,
+        };
+        adapter.create_memory(&memory, "messages").await.unwrap();
+    }
+
+    let mut seen = Vec::new();
+    let mut page = Pagination::first(2);
+    loop {
+        let result = adapter.get_memories_page(room_id, "messages", page).await.unwrap();
+        assert!(result.memories.len() <= 2);
+        seen.extend(result.memories.iter().map(|m| (m.created_at, m.id)));
+        match result.next_cursor {
+            Some(cursor) => page.before = Some(cursor),
+            None => break,
+        }
+    }
+
+    assert_eq!(seen.len(), 5);
+    assert!(seen.windows(2).all(|w| w[0] > w[1]));
+    assert_eq!(adapter.count_room_memories(room_id, "messages").await.unwrap(), 5);
+}
+
+#[tokio::test]
+#[ignore = "Requires MongoDB instance"]
+async fn test_memory_ttl() {
+    let Some(mut adapter) = setup_adapter().await else {
+        eprintln!("Skipping test - MongoDB not available");
+        return;
+    };
+
+    let agent_id = uuid::Uuid::new_v4();
+    let room_id = uuid::Uuid::new_v4();
+    let memory = |text: &str| Memory {
+        id: uuid::Uuid::new_v4(),
+        entity_id: agent_id,
+        agent_id,
+        room_id,
+        content: Content {
+            text: text.to_string(),
+            ..Default::default()
+        },
+        embedding: None,
+        metadata: None,
+        created_at: chrono::Utc::now().timestamp(),
+        unique: Some(false),
+        similarity: None,
+    };
+    let query = || MemoryQuery {
+        agent_id: Some(agent_id),
+        room_id: None,
+        entity_id: None,
+        world_id: None,
+        unique: None,
+        count: Some(10),
+        offset: None,
+        table_name: "memories".to_string(),
+        start: None,
+        end: None,
+    };
+    let texts = |memories: Vec<Memory>| {
+        let mut texts: Vec<String> = memories.into_iter().map(|m| m.content.text).collect();
+        texts.sort();
+        texts
+    };
+
+    let kept = memory("kept");
+    let typing = memory("typing");
+    let hint = memory("hint");
+    adapter.create_memory(&kept, "memories").await.unwrap();
+    adapter
+        .create_memory_with_ttl(&typing, std::time::Duration::ZERO)
+        .await
+        .unwrap();
+    adapter
+        .create_memory_with_ttl(&hint, std::time::Duration::from_secs(3600))
+        .await
+        .unwrap();
+
+    // Expired at once, excluded well before the TTL monitor runs
+    assert_eq!(texts(adapter.get_memories(query()).await.unwrap()), ["hint", "kept"]);
+    assert_eq!(adapter.count_memories(query()).await.unwrap(), 2);
+    let page = adapter
+        .get_memories_page(room_id, "memories", Pagination::first(10))
+        .await
+        .unwrap();
+    assert_eq!(page.memories.len(), 2);
+    let search = SearchMemoriesParams {
+        table_name: "memories".to_string(),
+        agent_id: Some(agent_id),
+        room_id: None,
+        world_id: None,
+        entity_id: None,
+        embedding: vec![0.0; 3],
+        count: 10,
+        unique: None,
+        threshold: None,
+    };
+    assert_eq!(texts(adapter.search_memories_by_embedding(search).await.unwrap()), ["hint", "kept"]);
+
+    // The TTL can be cleared, shortened or extended; expired memories stay gone
+    assert!(adapter.set_memory_ttl(hint.id, None).await.unwrap());
+    assert!(adapter
+        .set_memory_ttl(kept.id, Some(std::time::Duration::ZERO))
+        .await
+        .unwrap());
+    assert!(!adapter
+        .set_memory_ttl(typing.id, Some(std::time::Duration::from_secs(3600)))
+        .await
+        .unwrap());
+    assert_eq!(texts(adapter.get_memories(query()).await.unwrap()), ["hint"]);
+
+    // Index creation is idempotent across restarts
+    adapter.initialize(None).await.unwrap();
+    let indexes = adapter
+        .database()
+        .collection::<mongodb::bson::Document>("memories")
+        .list_index_names()
+        .await
+        .unwrap();
+    let ttl_indexes = indexes
+        .iter()
+        .filter(|name| name.as_str() == zoey_storage_mongo::mongo::MEMORY_TTL_INDEX)
+        .count();
+    assert_eq!(ttl_indexes, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires MongoDB instance"]
+async fn test_vector_search_with_filter() {
+    let Some(adapter) = setup_adapter().await else {
+        eprintln!("Skipping test - MongoDB not available");
+        return;
+    };
+
+    let searcher = MongoVectorSearch::new(adapter.database().clone(), 3);
+    let agent_id = uuid::Uuid::new_v4();
+    let case_room = uuid::Uuid::new_v4();
+    let other_room = uuid::Uuid::new_v4();
+    let memory = |room_id, case: &str, embedding: Vec<f32>| Memory {
+        id: uuid::Uuid::new_v4(),
+        entity_id: agent_id,
+        agent_id,
+        room_id,
+        content: Content {
+            text: case.to_string(),
+            ..Default::default()
+        },
+        embedding: Some(embedding),
+        metadata: Some(serde_json::from_value(serde_json::json!({ "case_id": case })).unwrap()),
+        created_at: chrono::Utc::now().timestamp(),
+        unique: Some(false),
+        similarity: None,
+    };
+
+    // Identical embeddings in the other room tie with the case room on score
+    for m in [
+        memory(case_room, "a", vec![1.0, 0.0, 0.0]),
+        memory(case_room, "b", vec![0.9, 0.1, 0.0]),
+        memory(other_room, "a", vec![1.0, 0.0, 0.0]),
+        memory(other_room, "c", vec![1.0, 0.0, 0.0]),
+    ] {
+        searcher.upsert_memory("memories", &m).await.unwrap();
+    }
+
+    let query = [1.0, 0.0, 0.0];
+    let results = searcher
+        .search_with_filter("memories", &query, 10, &VectorFilter::new().room(case_room))
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|m| m.room_id == case_room));
+
+    let results = searcher
+        .search_with_filter(
+            "memories",
+            &query,
+            10,
+            &VectorFilter::new().room(case_room).metadata("case_id", "a"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].content.text, "a");
+
+    // Atlas filters on fields the index lacks are rejected before querying
+    let atlas = MongoVectorSearch::new(adapter.database().clone(), 3)
+        .with_atlas_index(AtlasVectorIndex::new("memories_vector", ["room_id"]));
+    let err = atlas
+        .search_with_filter(
+            "memories",
+            &query,
+            10,
+            &VectorFilter::new().metadata("case_id", "a"),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("metadata.case_id"));
+}
+
 #[tokio::test]
 #[ignore = "Requires MongoDB instance"]
 async fn test_entity_operations() {
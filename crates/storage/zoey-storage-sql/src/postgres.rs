@@ -1869,6 +1869,15 @@ impl IDatabaseAdapter for PostgresAdapter {
         Ok(result.rows_affected() > 0)
     }
 
+    async fn delete_room(&self, room_id: UUID) -> Result<bool> {
+        // Participants cascade with the room
+        let result = sqlx::query("DELETE FROM rooms WHERE id = $1")
+            .bind(room_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     async fn remove_participant(&self, entity_id: UUID, room_id: UUID) -> Result<bool> {
         let result = sqlx::query("DELETE FROM participants WHERE entity_id = $1 AND room_id = $2")
             .bind(entity_id)
@@ -1456,6 +1456,16 @@ impl IDatabaseAdapter for SqliteAdapter {
         Ok(result.rows_affected() > 0)
     }
 
+    async fn delete_room(&self, room_id: UUID) -> Result<bool> {
+        // Participants cascade with the room
+        let result = sqlx::query("DELETE FROM rooms WHERE id = ?")
+            .bind(room_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     async fn remove_participant(&self, entity_id: UUID, room_id: UUID) -> Result<bool> {
         let result = sqlx::query("DELETE FROM participants WHERE entity_id = ? AND room_id = ?")
             .bind(entity_id.to_string())
@@ -968,6 +968,11 @@ impl IDatabaseAdapter for SupabaseAdapter {
         Ok(true)
     }
 
+    async fn delete_room(&self, room_id: UUID) -> Result<bool> {
+        // Participants cascade with the room
+        self.delete("rooms", &format!("id=eq.{}", room_id)).await
+    }
+
     async fn remove_participant(&self, entity_id: UUID, room_id: UUID) -> Result<bool> {
         let filter = format!("entity_id=eq.{}&room_id=eq.{}", entity_id, room_id);
         self.delete("participants", &filter).await
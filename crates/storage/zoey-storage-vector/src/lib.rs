@@ -19,7 +19,9 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::{info, warn};
 
+mod memory_search;
 mod store;
+pub use memory_search::InMemoryVectorSearch;
 pub use store::{LocalVectorStore, VectorStoreConfig};
 
 /// Local Vector Database Plugin
@@ -104,6 +106,8 @@ impl LocalVectorPlugin {
 
 #[async_trait]
 impl Plugin for LocalVectorPlugin {
+    zoey_core::plugin_core_version!();
+
     fn name(&self) -> &str {
         "local-vector"
     }
@@ -0,0 +1,439 @@
+//! In-memory vector search backend
+//!
+//! A brute-force cosine-similarity search over memories kept in process, for
+//! small deployments and development that should not need MongoDB. Every
+//! query scans the collection, which is fine for tens of thousands of
+//! memories but not beyond.
+//!
+//! With a snapshot path the collections are loaded from it on startup and
+//! rewritten after every change, so the index survives restarts.
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+use zoey_core::{
+    types::{IVectorSearch, Memory, SearchMemoriesParams, UUID},
+    Result, ZoeyError,
+};
+
+type Collections = HashMap<String, HashMap<UUID, Memory>>;
+
+/// On-disk snapshot layout
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    dimension: usize,
+    collections: HashMap<String, Vec<Memory>>,
+}
+
+/// [`IVectorSearch`] over memories held in process
+pub struct InMemoryVectorSearch {
+    dimension: usize,
+    snapshot: Option<PathBuf>,
+    collections: RwLock<Collections>,
+}
+
+impl InMemoryVectorSearch {
+    /// Create an empty, non-persistent index
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            snapshot: None,
+            collections: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Create an index persisted to `path`, loading it if the file exists
+    ///
+    /// A snapshot written with a different embedding dimension is rejected
+    /// rather than silently mixing incompatible vectors.
+    pub fn with_snapshot(dimension: usize, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut collections = HashMap::new();
+
+        if path.exists() {
+            let data = std::fs::read(&path)
+                .map_err(|e| ZoeyError::other(format!("Failed to read vector snapshot: {}", e)))?;
+            let snapshot: Snapshot = serde_json::from_slice(&data)
+                .map_err(|e| ZoeyError::other(format!("Failed to parse vector snapshot: {}", e)))?;
+            if snapshot.dimension != dimension {
+                return Err(ZoeyError::vector_search(
+                    format!(
+                        "Vector snapshot {} has a different dimension",
+                        path.display()
+                    ),
+                    snapshot.dimension,
+                    dimension,
+                ));
+            }
+            for (name, memories) in snapshot.collections {
+                collections.insert(name, memories.into_iter().map(|m| (m.id, m)).collect());
+            }
+            info!("Loaded vector snapshot from {}", path.display());
+        }
+
+        Ok(Self {
+            dimension,
+            snapshot: Some(path),
+            collections: RwLock::new(collections),
+        })
+    }
+
+    /// Write the snapshot, if one is configured
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.snapshot else {
+            return Ok(());
+        };
+
+        let snapshot = Snapshot {
+            dimension: self.dimension,
+            collections: self
+                .collections
+                .read()
+                .iter()
+                .map(|(name, memories)| (name.clone(), memories.values().cloned().collect()))
+                .collect(),
+        };
+        let data = serde_json::to_vec(&snapshot)
+            .map_err(|e| ZoeyError::other(format!("Failed to serialize vector snapshot: {}", e)))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ZoeyError::other(format!("Failed to create snapshot directory: {}", e))
+            })?;
+        }
+        // Write beside the target and rename so a crash never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| ZoeyError::other(format!("Failed to write vector snapshot: {}", e)))?;
+
+        debug!("Saved vector snapshot to {}", path.display());
+        Ok(())
+    }
+
+    /// Number of memories across all collections
+    pub fn len(&self) -> usize {
+        self.collections.read().values().map(HashMap::len).sum()
+    }
+
+    /// Whether no memories are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn check_dimension(&self, collection: &str, embedding: &[f32]) -> Result<()> {
+        if embedding.len() != self.dimension {
+            return Err(ZoeyError::vector_search(
+                format!(
+                    "Embedding dimension mismatch for collection '{}'",
+                    collection
+                ),
+                embedding.len(),
+                self.dimension,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Save after a change, logging instead of failing the write
+    fn persist(&self) {
+        if let Err(e) = self.save() {
+            warn!("Vector snapshot not saved: {}", e);
+        }
+    }
+
+    /// Best `count` of `memories` at or above `threshold`, most similar first
+    fn rank<'a>(
+        memories: impl Iterator<Item = &'a Memory>,
+        query: &[f32],
+        count: usize,
+        threshold: f32,
+    ) -> Vec<Memory> {
+        let mut scored: Vec<(f32, &Memory)> = memories
+            .filter_map(|m| {
+                let similarity = cosine_similarity(query, m.embedding.as_deref()?);
+                (similarity >= threshold).then_some((similarity, m))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(count);
+
+        scored
+            .into_iter()
+            .map(|(similarity, m)| Memory {
+                // Don't return embeddings, matching the MongoDB backend
+                embedding: None,
+                similarity: Some(similarity),
+                ..m.clone()
+            })
+            .collect()
+    }
+}
+
+/// Cosine similarity, 0.0 when either vector has no magnitude
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let mag_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let mag_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if mag_a == 0.0 || mag_b == 0.0 {
+        0.0
+    } else {
+        dot / (mag_a * mag_b)
+    }
+}
+
+#[async_trait]
+impl IVectorSearch for InMemoryVectorSearch {
+    fn embedding_dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn search_by_embedding(&self, params: SearchMemoriesParams) -> Result<Vec<Memory>> {
+        self.check_dimension(&params.table_name, &params.embedding)?;
+
+        let collections = self.collections.read();
+        let Some(memories) = collections.get(&params.table_name) else {
+            return Ok(Vec::new());
+        };
+        let candidates = memories.values().filter(|m| {
+            params.agent_id.map_or(true, |id| m.agent_id == id)
+                && params.room_id.map_or(true, |id| m.room_id == id)
+                && params.entity_id.map_or(true, |id| m.entity_id == id)
+                && params
+                    .unique
+                    .map_or(true, |unique| m.unique.unwrap_or(false) == unique)
+        });
+        let results = Self::rank(
+            candidates,
+            &params.embedding,
+            params.count,
+            params.threshold.unwrap_or(0.0),
+        );
+
+        debug!(
+            "Found {} memories via in-memory vector search in '{}'",
+            results.len(),
+            params.table_name
+        );
+        Ok(results)
+    }
+
+    async fn upsert_memory(&self, collection: &str, memory: &Memory) -> Result<()> {
+        if let Some(embedding) = &memory.embedding {
+            self.check_dimension(collection, embedding)?;
+        }
+        self.collections
+            .write()
+            .entry(collection.to_string())
+            .or_default()
+            .insert(memory.id, memory.clone());
+        self.persist();
+        Ok(())
+    }
+
+    async fn add_embedding(
+        &self,
+        collection: &str,
+        memory_id: UUID,
+        embedding: Vec<f32>,
+    ) -> Result<()> {
+        self.check_dimension(collection, &embedding)?;
+        {
+            let mut collections = self.collections.write();
+            let memory = collections
+                .get_mut(collection)
+                .and_then(|memories| memories.get_mut(&memory_id))
+                .ok_or_else(|| {
+                    ZoeyError::not_found(format!(
+                        "Memory {} not found in collection '{}'",
+                        memory_id, collection
+                    ))
+                })?;
+            memory.embedding = Some(embedding);
+        }
+        self.persist();
+        Ok(())
+    }
+
+    async fn batch_add_embeddings(
+        &self,
+        collection: &str,
+        embeddings: Vec<(UUID, Vec<f32>)>,
+    ) -> Result<()> {
+        {
+            let mut collections = self.collections.write();
+            let Some(memories) = collections.get_mut(collection) else {
+                return Ok(());
+            };
+            for (memory_id, embedding) in embeddings {
+                if embedding.len() != self.dimension {
+                    warn!(
+                        "Skipping embedding for {} due to dimension mismatch: {} vs {}",
+                        memory_id,
+                        embedding.len(),
+                        self.dimension
+                    );
+                    continue;
+                }
+                if let Some(memory) = memories.get_mut(&memory_id) {
+                    memory.embedding = Some(embedding);
+                }
+            }
+        }
+        self.persist();
+        Ok(())
+    }
+
+    async fn get_similar_memories(
+        &self,
+        collection: &str,
+        memory_id: UUID,
+        count: usize,
+        threshold: Option<f32>,
+    ) -> Result<Vec<Memory>> {
+        let collections = self.collections.read();
+        let memories = collections
+            .get(collection)
+            .ok_or_else(|| ZoeyError::not_found("Source memory not found"))?;
+        let source = memories
+            .get(&memory_id)
+            .ok_or_else(|| ZoeyError::not_found("Source memory not found"))?;
+        let query = source
+            .embedding
+            .as_deref()
+            .ok_or_else(|| ZoeyError::database("Source memory has no embedding"))?;
+
+        Ok(Self::rank(
+            memories.values().filter(|m| m.id != memory_id),
+            query,
+            count,
+            threshold.unwrap_or(0.0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    use zoey_core::types::Content;
+
+    fn memory(agent_id: UUID, room_id: UUID, text: &str, embedding: Vec<f32>) -> Memory {
+        Memory {
+            id: Uuid::new_v4(),
+            entity_id: Uuid::new_v4(),
+            agent_id,
+            room_id,
+            content: Content {
+                text: text.to_string(),
+                ..Default::default()
+            },
+            embedding: Some(embedding),
+            metadata: None,
+            created_at: 0,
+            unique: None,
+            similarity: None,
+        }
+    }
+
+    fn query(embedding: Vec<f32>, count: usize) -> SearchMemoriesParams {
+        SearchMemoriesParams {
+            table_name: "memories".to_string(),
+            agent_id: None,
+            room_id: None,
+            world_id: None,
+            entity_id: None,
+            embedding,
+            count,
+            unique: None,
+            threshold: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_and_filters() {
+        let index = InMemoryVectorSearch::new(3);
+        let (agent, room, other_room) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for m in [
+            memory(agent, room, "close", vec![1.0, 0.1, 0.0]),
+            memory(agent, room, "exact", vec![1.0, 0.0, 0.0]),
+            memory(agent, room, "opposite", vec![-1.0, 0.0, 0.0]),
+            memory(agent, other_room, "elsewhere", vec![1.0, 0.0, 0.0]),
+        ] {
+            index.upsert_memory("memories", &m).await.unwrap();
+        }
+
+        let mut params = query(vec![1.0, 0.0, 0.0], 10);
+        params.room_id = Some(room);
+        let results = index.search_by_embedding(params.clone()).await.unwrap();
+        let texts: Vec<_> = results.iter().map(|m| m.content.text.as_str()).collect();
+        // The opposite vector is below the default threshold of 0.0
+        assert_eq!(texts, ["exact", "close"]);
+        assert!(results[0].embedding.is_none());
+        assert!((results[0].similarity.unwrap() - 1.0).abs() < 1e-6);
+
+        params.count = 1;
+        assert_eq!(index.search_by_embedding(params).await.unwrap().len(), 1);
+
+        let err = index
+            .search_by_embedding(query(vec![1.0, 0.0], 10))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ZoeyError::VectorSearch { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_similar_excludes_source() {
+        let index = InMemoryVectorSearch::new(2);
+        let (agent, room) = (Uuid::new_v4(), Uuid::new_v4());
+        let source = memory(agent, room, "source", vec![1.0, 0.0]);
+        let mut later = memory(agent, room, "later", vec![0.0, 0.0]);
+        later.embedding = None;
+        index.upsert_memory("memories", &source).await.unwrap();
+        index.upsert_memory("memories", &later).await.unwrap();
+
+        // Embeddings generated after the memory was stored
+        index
+            .add_embedding("memories", later.id, vec![0.9, 0.1])
+            .await
+            .unwrap();
+        assert!(index
+            .add_embedding("memories", Uuid::new_v4(), vec![1.0, 0.0])
+            .await
+            .is_err());
+
+        let similar = index
+            .get_similar_memories("memories", source.id, 5, Some(0.5))
+            .await
+            .unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].id, later.id);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.json");
+        let m = memory(Uuid::new_v4(), Uuid::new_v4(), "kept", vec![0.0, 1.0]);
+
+        let index = InMemoryVectorSearch::with_snapshot(2, &path).unwrap();
+        index.upsert_memory("memories", &m).await.unwrap();
+        drop(index);
+
+        let reloaded = InMemoryVectorSearch::with_snapshot(2, &path).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        let results = reloaded
+            .search_by_embedding(query(vec![0.0, 1.0], 5))
+            .await
+            .unwrap();
+        assert_eq!(results[0].id, m.id);
+
+        assert!(InMemoryVectorSearch::with_snapshot(3, &path).is_err());
+    }
+}
@@ -2,7 +2,7 @@ use zoey_core::observability::{set_global_cost_tracker, CostTracker, Observabili
 use zoey_core::IDatabaseAdapter;
 use zoey_core::{
     agent_api::{AgentApiConfig, AgentApiServer},
-    types::{InitializeOptions, StorageType},
+    types::{InitializeOptions, StorageConfig, StorageType, VectorSearchType},
     AgentRuntime, RuntimeOpts,
 };
 use zoey_ext_workflow::WorkflowPlugin;
@@ -15,9 +15,9 @@ use zoey_provider_anthropic::AnthropicPlugin;
 use zoey_provider_local::LocalLLMPlugin;
 use zoey_provider_openai::OpenAIPlugin;
 use zoey_storage_sql::{PostgresAdapter, SqliteAdapter};
-use zoey_storage_mongo::MongoAdapter;
+use zoey_storage_mongo::{MongoAdapter, MongoStateStore};
 use zoey_storage_supabase::SupabaseAdapter;
-use zoey_storage_vector::LocalVectorPlugin;
+use zoey_storage_vector::{InMemoryVectorSearch, LocalVectorPlugin};
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -28,13 +28,36 @@ use dotenvy::dotenv;
 use zoey_adaptor_discord::{start_discord, DiscordConfig};
 use zoey_adaptor_telegram::{start_telegram, TelegramConfig};
 use zoey_adaptor_terminal::{TerminalAdaptor, TerminalConfig};
-use zoey_adaptor_web::{SimpleUiConfig, SimpleUiServer};
+use zoey_adaptor_web::SimpleUiServer;
 use zoey_core::observability::{start_rest_api, RestApiConfig};
+use zoey_core::infrastructure::StatePersister;
+use zoey_core::SnapshotFilePersister;
 use zoey_core::types::agent::Character;
 use zoey_core::utils::logger::init_logging;
 use serde_json::json;
 use serenity::model::gateway::GatewayIntents;
 
+/// Adapter state store: MongoDB when `ADAPTER_STATE_MONGODB_URL` is set, else in-process
+async fn adapter_state_store() -> Arc<dyn zoey_core::StateStore> {
+    if let Ok(url) = std::env::var("ADAPTER_STATE_MONGODB_URL") {
+        let db_name = std::env::var("ADAPTER_STATE_MONGODB_DATABASE")
+            .or_else(|_| std::env::var("MONGODB_DATABASE"))
+            .unwrap_or_else(|_| "zoey".to_string());
+        match MongoAdapter::new(&url, &db_name).await {
+            Ok(mongo) => {
+                let store = MongoStateStore::new(mongo.database());
+                if let Err(e) = store.ensure_indexes().await {
+                    tracing::warn!("Failed to create adapter state indexes: {}", e);
+                }
+                tracing::info!("Adapter state shared through MongoDB: {}", db_name);
+                return Arc::new(store);
+            }
+            Err(e) => tracing::error!("Adapter state store unavailable, keeping state in process: {}", e),
+        }
+    }
+    Arc::new(zoey_core::MemoryStateStore::new())
+}
+
 fn env_bool(key: &str) -> Option<bool> {
     std::env::var(key)
         .ok()
@@ -96,6 +119,9 @@ fn main() -> zoey_core::Result<()> {
         .with_character(character.clone())
         .with_plugins(plugins);
     if let Some(adapter) = adapter_opt { opts = opts.with_adapter(adapter); }
+    if let Some(vector_search) = build_vector_search(&character.storage) {
+        opts = opts.with_vector_search(vector_search);
+    }
 
     let runtime = AgentRuntime::new(opts).await?;
 
@@ -157,6 +183,20 @@ fn main() -> zoey_core::Result<()> {
         }
     }
 
+    // Warm restart: restore ephemeral runtime state saved on the previous shutdown.
+    // Adapter sections are applied when each adapter registers its contributor.
+    let snapshot_persister = if env_bool("ZOEY_STATE_SNAPSHOT_ENABLED").unwrap_or(true) {
+        let path = std::env::var("ZOEY_STATE_SNAPSHOT_PATH")
+            .unwrap_or_else(|_| "./data/state_snapshot.json".to_string());
+        let persister = SnapshotFilePersister::new(path, runtime.clone());
+        if let Err(e) = persister.restore_state().await {
+            eprintln!("[runner] Failed to restore state snapshot: {}", e);
+        }
+        Some(persister)
+    } else {
+        None
+    };
+
     // Initialize runtime (migrations, adapter checks) and enable Observability REST
     {
         let mut rt = runtime.write().unwrap();
@@ -328,15 +368,61 @@ fn main() -> zoey_core::Result<()> {
     let streaming_enabled = { let rt = runtime.read().unwrap(); rt.get_setting("ui:streaming").and_then(|v| v.as_bool()).unwrap_or(true) };
     // For internal proxy, always use 127.0.0.1 to reach the local API server
     let api_base = format!("http://127.0.0.1:{}/agent", agent_port_chosen);
-    let ui = SimpleUiServer::new(SimpleUiConfig {
-        enabled: true,
-        host: ui_host.clone(),
-        port: ui_port,
-        agent_api_url: api_base,
-        use_streaming: streaming_enabled,
-        token: None,
-        logs_enabled,
-    }, runtime.clone());
+    // Dedup keys and voice state, shared between processes when ADAPTER_STATE_MONGODB_URL is set
+    let state_store = adapter_state_store().await;
+    // IDENTITY_LINKING lets chat users pair their accounts with the web UI (/link)
+    let identity_links = env_bool("IDENTITY_LINKING").unwrap_or(false)
+        .then(|| Arc::new(zoey_core::IdentityLinks::new(state_store.clone())));
+    let mut ui = SimpleUiServer::builder()
+        .host(ui_host.clone())
+        .port(ui_port)
+        .backend(api_base)
+        .streaming(streaming_enabled)
+        .logs(logs_enabled)
+        .locale(std::env::var("UI_LOCALE").unwrap_or_else(|_| "en".to_string()))
+        .max_streams_per_ip(std::env::var("UI_MAX_STREAMS_PER_IP").ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(zoey_adaptor_web::DEFAULT_MAX_STREAMS_PER_IP))
+        .allowed_origins(std::env::var("UI_ALLOWED_ORIGINS").ok()
+            .map(|s| s.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect::<Vec<_>>())
+            .unwrap_or_default())
+        .model_controls(env_bool("UI_MODEL_CONTROLS").unwrap_or(false))
+        .content_security_policy(env_bool("UI_CSP").unwrap_or(true))
+        .proxy_timeout(std::env::var("UI_PROXY_TIMEOUT_SECS").ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(zoey_adaptor_web::DEFAULT_PROXY_TIMEOUT))
+        .proxy_stream_idle_timeout(std::env::var("UI_STREAM_IDLE_TIMEOUT_SECS").ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(zoey_adaptor_web::DEFAULT_STREAM_IDLE_TIMEOUT))
+        .proxy_max_upload_bytes(std::env::var("UI_PROXY_MAX_UPLOAD_MB").ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .map(|mb| mb * 1024 * 1024)
+            .unwrap_or(zoey_adaptor_web::DEFAULT_PROXY_MAX_UPLOAD_BYTES))
+        .ws_idle_timeout(std::env::var("UI_WS_IDLE_TIMEOUT_SECS").ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(zoey_adaptor_web::DEFAULT_WS_IDLE_TIMEOUT));
+    if let Some(token) = std::env::var("UI_ADMIN_TOKEN").ok().filter(|t| !t.is_empty()) {
+        ui = ui.admin_token(token);
+    }
+    // The Telegram workspace button signs its links with the bot token
+    if let Some(token) = std::env::var("TELEGRAM_WEBAPP_URL").ok().filter(|s| !s.is_empty())
+        .and_then(|_| std::env::var("TELEGRAM_BOT_TOKEN").ok())
+        .filter(|t| !t.is_empty())
+    {
+        ui = ui.telegram_bot_token(token);
+    }
+    if let Some(links) = identity_links.clone() {
+        ui = ui.identity_links(links);
+    }
+    // Cases survive restarts when the agent has a database (e.g. MongoDB)
+    let case_adapter = { let rt = runtime.read().unwrap(); rt.get_adapter() };
+    if let Some(adapter) = case_adapter {
+        ui = ui.case_store(Arc::new(zoey_adaptor_web::AdapterCaseStore::new(adapter)));
+    }
+    let ui = ui.build(runtime.clone());
     ui.start().await?;
 
     println!("Agent API: http://{}:{}/agent\nSimple UI: http://{}:{}/", agent_host, agent_port_chosen, ui_host, ui_port);
@@ -448,6 +534,79 @@ fn main() -> zoey_core::Result<()> {
                     allowed_channels,
                     allowed_users,
                     voice: voice_config,
+                    // DISCORD_CHANNEL_CHARACTERS="123=Zoey Support,456=characters/zoey-casual.xml"
+                    channel_characters: std::env::var("DISCORD_CHANNEL_CHARACTERS").ok()
+                        .map(|s| s.split(',')
+                            .filter_map(|pair| pair.split_once('='))
+                            .filter_map(|(cid, name)| Some((cid.trim().parse::<u64>().ok()?, name.trim().to_string())))
+                            .filter(|(_, name)| !name.is_empty())
+                            .collect())
+                        .unwrap_or_default(),
+                    // DISCORD_FALLBACK_CHANNELS="<guild id>=<channel id>,..." answers for read-only channels
+                    fallback_channels: std::env::var("DISCORD_FALLBACK_CHANNELS").ok()
+                        .map(|s| s.split(',')
+                            .filter_map(|pair| pair.split_once('='))
+                            .filter_map(|(gid, cid)| Some((gid.trim().parse::<u64>().ok()?, cid.trim().parse::<u64>().ok()?)))
+                            .collect())
+                        .unwrap_or_default(),
+                    admin_users: parse_list("DISCORD_ADMIN_USERS").unwrap_or_default(),
+                    typing_refresh_interval: std::env::var("DISCORD_TYPING_REFRESH_MS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(std::time::Duration::from_millis)
+                        .unwrap_or(zoey_adaptor_discord::typing::DEFAULT_TYPING_REFRESH_INTERVAL),
+                    typing_max_duration: std::env::var("DISCORD_TYPING_MAX_SECS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(std::time::Duration::from_secs)
+                        .unwrap_or(zoey_adaptor_discord::typing::DEFAULT_TYPING_MAX_DURATION),
+                    listen_auto_off: std::env::var("DISCORD_LISTEN_AUTO_OFF_MINS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(|m| std::time::Duration::from_secs(m * 60))
+                        .unwrap_or(zoey_adaptor_discord::listen::DEFAULT_LISTEN_AUTO_OFF),
+                    // 0 disables the 🎙️ reaction on the voice-join message
+                    push_to_talk_window: std::env::var("DISCORD_PUSH_TO_TALK_SECS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(std::time::Duration::from_secs)
+                        .unwrap_or(zoey_adaptor_discord::push_to_talk::DEFAULT_PUSH_TO_TALK_WINDOW),
+                    url_ingestion: std::env::var("DISCORD_URL_INGESTION").ok()
+                        .and_then(|s| zoey_adaptor_discord::UrlIngestion::parse(&s))
+                        .unwrap_or_default(),
+                    url_ask_timeout: std::env::var("DISCORD_URL_ASK_TIMEOUT_SECS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(std::time::Duration::from_secs)
+                        .unwrap_or(zoey_adaptor_discord::links::DEFAULT_URL_ASK_TIMEOUT),
+                    choice_ttl: std::env::var("DISCORD_CHOICE_TTL_SECS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(std::time::Duration::from_secs)
+                        .unwrap_or(zoey_adaptor_discord::choices::DEFAULT_CHOICE_TTL),
+                    choices_open_to_all: env_bool("DISCORD_CHOICES_OPEN_TO_ALL").unwrap_or(false),
+                    // e.g. DISCORD_RESPONSE_TEMPLATE="**{room}**\n{text}"
+                    response_template: std::env::var("DISCORD_RESPONSE_TEMPLATE").ok().filter(|s| !s.is_empty()),
+                    memory_batch: zoey_adaptor_discord::BatcherConfig {
+                        flush_interval: std::env::var("DISCORD_MEMORY_FLUSH_SECS").ok()
+                            .and_then(|s| s.parse::<u64>().ok())
+                            .map(std::time::Duration::from_secs)
+                            .unwrap_or(zoey_adaptor_discord::batcher::DEFAULT_FLUSH_INTERVAL),
+                        max_batch: std::env::var("DISCORD_MEMORY_BATCH_SIZE").ok()
+                            .and_then(|s| s.parse::<usize>().ok())
+                            .unwrap_or(zoey_adaptor_discord::batcher::DEFAULT_MAX_BATCH),
+                        max_queue: std::env::var("DISCORD_MEMORY_QUEUE_MAX").ok()
+                            .and_then(|s| s.parse::<usize>().ok())
+                            .unwrap_or(zoey_adaptor_discord::batcher::DEFAULT_MAX_QUEUE),
+                    },
+                    cache: zoey_adaptor_discord::CacheTuning {
+                        cache_users: env_bool("DISCORD_CACHE_USERS").unwrap_or(false),
+                        max_messages: std::env::var("DISCORD_CACHE_MAX_MESSAGES").ok()
+                            .and_then(|s| s.parse::<usize>().ok())
+                            .unwrap_or(zoey_adaptor_discord::cache::DEFAULT_MAX_MESSAGES),
+                        cache_members: env_bool("DISCORD_CACHE_MEMBERS").unwrap_or(false),
+                    },
+                    placeholder: std::env::var("DISCORD_PLACEHOLDER")
+                        .unwrap_or_else(|_| zoey_adaptor_discord::DEFAULT_PLACEHOLDER.to_string()),
+                    continuity_idle: std::env::var("DISCORD_CONTINUITY_IDLE_HOURS").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(|h| std::time::Duration::from_secs(h * 60 * 60))
+                        .unwrap_or(zoey_adaptor_discord::continuity::DEFAULT_CONTINUITY_IDLE),
+                    state_store: state_store.clone(),
                 };
                 println!("[runner] Starting Discord adapter...");
                 let _ = start_discord(runtime.clone(), config).await;
@@ -514,8 +673,137 @@ fn main() -> zoey_core::Result<()> {
                     token,
                     allowed_chats,
                     allowed_users,
+                    // TELEGRAM_UNKNOWN_USER_POLICY=queue asks admins to approve users outside
+                    // TELEGRAM_ALLOWED_USERS ("notify" only tells them; "reject" drops silently)
+                    unknown_user_policy: std::env::var("TELEGRAM_UNKNOWN_USER_POLICY").ok()
+                        .and_then(|s| zoey_adaptor_telegram::UnknownUserPolicy::parse(&s))
+                        .unwrap_or_default(),
+                    access_requests: {
+                        let defaults = zoey_adaptor_telegram::AccessRequestConfig::default();
+                        zoey_adaptor_telegram::AccessRequestConfig {
+                            // Falls back to messaging each of TELEGRAM_ADMIN_USERS
+                            admin_chat: std::env::var("TELEGRAM_ACCESS_ADMIN_CHAT").ok()
+                                .and_then(|s| s.trim().parse::<i64>().ok()),
+                            rejection_message: std::env::var("TELEGRAM_ACCESS_REJECTION_MESSAGE").ok()
+                                .filter(|s| !s.trim().is_empty())
+                                .unwrap_or(defaults.rejection_message),
+                            ..defaults
+                        }
+                    },
                     bot_username,
                     voice: voice_config,
+                    tiers: parse_u64_list("TELEGRAM_PAID_USERS")
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|id| (id, zoey_adaptor_telegram::Tier::paid()))
+                        .collect(),
+                    quota_reset_hour_utc: std::env::var("TELEGRAM_QUOTA_RESET_HOUR_UTC").ok().and_then(|s| s.parse::<u32>().ok()).unwrap_or(0),
+                    admin_users: parse_u64_list("TELEGRAM_ADMIN_USERS").unwrap_or_default(),
+                    context_overflow: {
+                        let patterns = std::env::var("TELEGRAM_CONTEXT_ERROR_PATTERNS").ok()
+                            .map(|s| s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect::<Vec<_>>())
+                            .filter(|v| !v.is_empty());
+                        let policy = match patterns {
+                            Some(p) => zoey_core::ContextOverflowPolicy::new(p),
+                            None => zoey_core::ContextOverflowPolicy::default(),
+                        };
+                        match std::env::var("TELEGRAM_CONTEXT_RETRY_BUDGET").ok().and_then(|s| s.parse::<u32>().ok()) {
+                            Some(budget) => policy.with_retry_budget(budget),
+                            None => policy,
+                        }
+                    },
+                    webapp_url: std::env::var("TELEGRAM_WEBAPP_URL").ok().filter(|s| !s.is_empty()),
+                    response_template: std::env::var("TELEGRAM_RESPONSE_TEMPLATE").ok().filter(|s| !s.is_empty()),
+                    // TELEGRAM_NEAR_MISS_EMOJI enables "seen" reactions (e.g. 👀)
+                    near_miss_ack: std::env::var("TELEGRAM_NEAR_MISS_EMOJI").ok().filter(|s| !s.is_empty())
+                        .map(|emoji| zoey_adaptor_telegram::NearMissConfig { emoji, ..Default::default() }),
+                    followups: env_bool("TELEGRAM_FOLLOWUPS").unwrap_or(false).then(|| {
+                        zoey_adaptor_telegram::FollowupConfig {
+                            ttl: std::env::var("TELEGRAM_FOLLOWUP_TTL_SECS").ok()
+                                .and_then(|s| s.parse::<u64>().ok())
+                                .map(std::time::Duration::from_secs)
+                                .unwrap_or(zoey_adaptor_telegram::followups::DEFAULT_FOLLOWUP_TTL),
+                            ..Default::default()
+                        }
+                    }),
+                    // TELEGRAM_ACTIONS=false hides the buttons for actions suggested in replies
+                    actions: env_bool("TELEGRAM_ACTIONS").unwrap_or(true)
+                        .then(zoey_adaptor_telegram::ActionConfig::default),
+                    // TELEGRAM_ONBOARDING welcomes groups the bot is added to
+                    onboarding: env_bool("TELEGRAM_ONBOARDING").unwrap_or(false).then(|| {
+                        zoey_adaptor_telegram::OnboardingConfig {
+                            repeat_after: std::env::var("TELEGRAM_ONBOARDING_REPEAT_DAYS").ok()
+                                .and_then(|s| s.parse::<u64>().ok())
+                                .map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60))
+                                .unwrap_or(zoey_adaptor_telegram::onboarding::DEFAULT_ONBOARDING_REPEAT),
+                        }
+                    }),
+                    // TELEGRAM_ABUSE_GUARD mutes users flooding the bot for a while
+                    abuse_guard: env_bool("TELEGRAM_ABUSE_GUARD").unwrap_or(false).then(|| {
+                        zoey_adaptor_telegram::AbuseGuardConfig {
+                            max_messages: std::env::var("TELEGRAM_ABUSE_MAX_MESSAGES_PER_MINUTE").ok()
+                                .and_then(|s| s.parse::<usize>().ok())
+                                .unwrap_or(zoey_adaptor_telegram::AbuseGuardConfig::default().max_messages),
+                            ..Default::default()
+                        }
+                    }),
+                    // TELEGRAM_DIGEST lets admins batch group questions with /digest
+                    digest: env_bool("TELEGRAM_DIGEST").unwrap_or(false).then(|| {
+                        let defaults = zoey_adaptor_telegram::DigestConfig::default();
+                        zoey_adaptor_telegram::DigestConfig {
+                            interval: std::env::var("TELEGRAM_DIGEST_INTERVAL_MINS").ok()
+                                .and_then(|s| s.parse::<u64>().ok())
+                                .map(|m| std::time::Duration::from_secs(m * 60))
+                                .unwrap_or(defaults.interval),
+                            max_pending: std::env::var("TELEGRAM_DIGEST_MAX_PENDING").ok()
+                                .and_then(|s| s.parse::<usize>().ok())
+                                .unwrap_or(defaults.max_pending),
+                            // e.g. TELEGRAM_DIGEST_BYPASS="\burgent\b,\basap\b"
+                            bypass_patterns: std::env::var("TELEGRAM_DIGEST_BYPASS").ok()
+                                .map(|s| s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+                                .unwrap_or(defaults.bypass_patterns),
+                            ..defaults
+                        }
+                    }),
+                    // Unset: stream, falling back to task polling when the backend has no /chat/stream
+                    streaming: env_bool("TELEGRAM_STREAMING"),
+                    polling: zoey_adaptor_telegram::PollConfig {
+                        deadline: std::env::var("TELEGRAM_POLL_DEADLINE_SECS").ok()
+                            .and_then(|s| s.parse::<u64>().ok())
+                            .map(std::time::Duration::from_secs)
+                            .unwrap_or(zoey_adaptor_telegram::PollConfig::default().deadline),
+                        ..Default::default()
+                    },
+                    state_store: state_store.clone(),
+                    identity_links: identity_links.clone(),
+                    // TELEGRAM_TELEMETRY=jsonl (TELEGRAM_TELEMETRY_PATH) or http (TELEGRAM_TELEMETRY_ENDPOINT)
+                    telemetry: zoey_core::TelemetryConfig {
+                        sink: match std::env::var("TELEGRAM_TELEMETRY").unwrap_or_default().as_str() {
+                            "jsonl" => zoey_core::TelemetrySinkConfig::Jsonl {
+                                path: std::env::var("TELEGRAM_TELEMETRY_PATH")
+                                    .unwrap_or_else(|_| "data/telemetry/telegram.jsonl".to_string())
+                                    .into(),
+                                max_bytes: zoey_core::adapter_telemetry::DEFAULT_JSONL_MAX_BYTES,
+                                keep: zoey_core::adapter_telemetry::DEFAULT_JSONL_KEEP,
+                            },
+                            "http" => zoey_core::TelemetrySinkConfig::Http {
+                                endpoint: std::env::var("TELEGRAM_TELEMETRY_ENDPOINT").unwrap_or_default(),
+                                batch_size: zoey_core::adapter_telemetry::DEFAULT_HTTP_BATCH_SIZE,
+                            },
+                            _ => zoey_core::TelemetrySinkConfig::Tracing,
+                        },
+                        salt: std::env::var("TELEGRAM_TELEMETRY_SALT").unwrap_or_default(),
+                        ..Default::default()
+                    },
+                    // TELEGRAM_MARKDOWN renders the model's markdown as MarkdownV2
+                    parse_mode: env_bool("TELEGRAM_MARKDOWN")
+                        .unwrap_or(false)
+                        .then_some(zoey_adaptor_telegram::ParseMode::MarkdownV2),
+                    max_attachment_bytes: std::env::var("TELEGRAM_MAX_ATTACHMENT_MB").ok()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .map(|mb| mb * 1024 * 1024)
+                        .unwrap_or(zoey_adaptor_telegram::DEFAULT_MAX_ATTACHMENT_BYTES),
+                    ..Default::default()
                 };
                 let _ = start_telegram(runtime.clone(), config).await;
             }
@@ -545,12 +833,43 @@ fn main() -> zoey_core::Result<()> {
             if let Some(ref mut s) = term { s.recv().await; }
         } => {},
     }
+    if let Some(persister) = snapshot_persister {
+        if let Err(e) = persister.persist_state().await {
+            eprintln!("[runner] Failed to persist state snapshot: {}", e);
+        }
+    }
     zoey_adaptor_telegram::shutdown_telegram();
     api.stop().await?;
     Ok(())
     })
 }
 
+/// In-memory vector search when the character selects it, None to use the adapter's search
+fn build_vector_search(storage: &StorageConfig) -> Option<Arc<dyn zoey_core::IVectorSearch>> {
+    if storage.vector_search != VectorSearchType::Memory {
+        return None;
+    }
+    let dimension = storage.embedding_dimension.unwrap_or(1536);
+    let snapshot = storage.vector_snapshot.clone()
+        .or_else(|| std::env::var("VECTOR_SNAPSHOT_PATH").ok());
+    match snapshot {
+        Some(path) => match InMemoryVectorSearch::with_snapshot(dimension, &path) {
+            Ok(index) => {
+                tracing::info!("Using in-memory vector search ({} memories) snapshotted to {}", index.len(), path);
+                Some(Arc::new(index))
+            }
+            Err(e) => {
+                tracing::error!("Failed to load vector snapshot {}: {}", path, e);
+                None
+            }
+        },
+        None => {
+            tracing::info!("Using in-memory vector search without a snapshot");
+            Some(Arc::new(InMemoryVectorSearch::new(dimension)))
+        }
+    }
+}
+
 async fn build_plugins_and_adapter(
     character: &Character,
 ) -> (
@@ -727,7 +1046,11 @@ async fn build_plugins_and_adapter(
                         match url {
                             Some(url) => {
                                 match MongoAdapter::new(&url, &db_name).await {
-                                    Ok(mut mongo) => match mongo.initialize(None).await {
+                                    Ok(mut mongo) => match mongo.initialize(Some(json!({
+                                        "ensureVectorIndexes": storage_config.ensure_vector_indexes,
+                                        "embeddingDimension": storage_config.embedding_dimension,
+                                        "embeddingModel": storage_config.embedding_model,
+                                    }))).await {
                                         Ok(_) => {
                                             tracing::info!("Initialized MongoDB storage: {}", db_name);
                                             Some(Arc::new(mongo) as Arc<dyn zoey_core::IDatabaseAdapter + Send + Sync>)